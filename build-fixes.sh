@@ -197,272 +197,7 @@ button:focus-visible {
 }
 EOF
 
-# 3. Rust Backend Module Stubs erstellen
-echo "🦀 Erstelle Rust Backend Module..."
-
-mkdir -p src-tauri/src
-
-cat > src-tauri/src/screen_capture.rs << 'EOF'
-// src-tauri/src/screen_capture.rs - Screen Capture Module
-
-use serde::{Deserialize, Serialize};
-use tauri::Window;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ScreenCaptureConfig {
-    pub fps: u32,
-    pub quality: u8,
-    pub codec: String,
-    pub hardware_acceleration: String,
-    pub capture_cursor: bool,
-    pub capture_audio: bool,
-    pub monitor_index: usize,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct MonitorInfo {
-    pub index: usize,
-    pub name: String,
-    pub width: u32,
-    pub height: u32,
-    pub x_offset: i32,
-    pub y_offset: i32,
-    pub refresh_rate: Option<u32>,
-    pub primary: bool,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum DisplayServer {
-    X11,
-    Wayland,
-    Unknown,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum VideoCodec {
-    H264,
-    VP8,
-    VP9,
-    AV1,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum HardwareAcceleration {
-    None,
-    VAAPI,
-    NVENC,
-    QuickSync,
-}
-
-pub struct ScreenCaptureManager {
-    config: Option<ScreenCaptureConfig>,
-    is_capturing: bool,
-}
-
-impl ScreenCaptureManager {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        Ok(Self {
-            config: None,
-            is_capturing: false,
-        })
-    }
-
-    pub fn get_monitors(&self) -> Vec<MonitorInfo> {
-        // Stub implementation - würde echte Monitor-Erkennung implementieren
-        vec![
-            MonitorInfo {
-                index: 0,
-                name: "Primary Monitor".to_string(),
-                width: 1920,
-                height: 1080,
-                x_offset: 0,
-                y_offset: 0,
-                refresh_rate: Some(60),
-                primary: true,
-            }
-        ]
-    }
-
-    pub fn update_config(&mut self, config: ScreenCaptureConfig) -> Result<(), Box<dyn std::error::Error>> {
-        self.config = Some(config);
-        Ok(())
-    }
-
-    pub fn start_capture(&mut self, _window: Window) -> Result<(), Box<dyn std::error::Error>> {
-        if self.config.is_none() {
-            return Err("No configuration set".into());
-        }
-        
-        self.is_capturing = true;
-        println!("Screen capture started (stub implementation)");
-        Ok(())
-    }
-
-    pub fn stop_capture(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.is_capturing = false;
-        println!("Screen capture stopped");
-        Ok(())
-    }
-}
-EOF
-
-cat > src-tauri/src/input_forwarding.rs << 'EOF'
-// src-tauri/src/input_forwarding.rs - Input Forwarding Module
-
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct InputEvent {
-    pub event_type: InputEventType,
-    pub x: Option<i32>,
-    pub y: Option<i32>,
-    pub button: Option<MouseButton>,
-    pub key_code: Option<u32>,
-    pub modifiers: Option<Vec<String>>,
-    pub is_pressed: Option<bool>,
-    pub delta_x: Option<f64>,
-    pub delta_y: Option<f64>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum InputEventType {
-    MouseMove,
-    MouseButton,
-    MouseScroll,
-    KeyPress,
-    KeyRelease,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum MouseButton {
-    Left,
-    Middle,
-    Right,
-    Back,
-    Forward,
-    ScrollUp,
-    ScrollDown,
-}
-
-pub mod forwarder_trait {
-    use super::*;
-
-    pub trait ImprovedInputForwarder: Send + Sync {
-        fn forward_event(&self, event: &InputEvent) -> Result<(), Box<dyn std::error::Error>>;
-        fn set_enabled(&self, enabled: bool);
-        fn configure_monitors(&mut self, monitors: Vec<types::MonitorConfiguration>) -> Result<(), error::InputForwardingError>;
-    }
-}
-
-pub mod factory {
-    use super::*;
-    use super::forwarder_trait::ImprovedInputForwarder;
-
-    #[derive(Debug, Clone)]
-    pub enum DisplayServer {
-        X11,
-        Wayland,
-        Unknown,
-    }
-
-    pub fn detect_display_server() -> DisplayServer {
-        // Stub implementation
-        if std::env::var("WAYLAND_DISPLAY").is_ok() {
-            DisplayServer::Wayland
-        } else if std::env::var("DISPLAY").is_ok() {
-            DisplayServer::X11
-        } else {
-            DisplayServer::Unknown
-        }
-    }
-
-    pub fn create_improved_input_forwarder(
-        _display_server: Option<DisplayServer>
-    ) -> Result<Box<dyn ImprovedInputForwarder>, error::InputForwardingError> {
-        Ok(Box::new(StubInputForwarder::new()))
-    }
-
-    struct StubInputForwarder {
-        enabled: bool,
-    }
-
-    impl StubInputForwarder {
-        fn new() -> Self {
-            Self { enabled: false }
-        }
-    }
-
-    impl ImprovedInputForwarder for StubInputForwarder {
-        fn forward_event(&self, event: &InputEvent) -> Result<(), Box<dyn std::error::Error>> {
-            if self.enabled {
-                println!("Input event: {:?}", event);
-            }
-            Ok(())
-        }
-
-        fn set_enabled(&self, _enabled: bool) {
-            // Stub implementation
-        }
-
-        fn configure_monitors(&mut self, _monitors: Vec<types::MonitorConfiguration>) -> Result<(), error::InputForwardingError> {
-            Ok(())
-        }
-    }
-}
-
-pub mod types {
-    use serde::{Deserialize, Serialize};
-
-    #[derive(Debug, Clone)]
-    pub enum DisplayServer {
-        X11,
-        Wayland,
-        Unknown,
-    }
-
-    #[derive(Debug, Clone, Serialize, Deserialize)]
-    pub struct InputForwardingConfig {
-        pub enable_multi_monitor: bool,
-        pub monitors: Vec<MonitorConfiguration>,
-    }
-
-    #[derive(Debug, Clone, Serialize, Deserialize)]
-    pub struct MonitorConfiguration {
-        pub index: usize,
-        pub x_offset: i32,
-        pub y_offset: i32,
-        pub width: i32,
-        pub height: i32,
-        pub scale_factor: f64,
-        pub is_primary: bool,
-    }
-}
-
-pub mod error {
-    use std::fmt;
-
-    #[derive(Debug)]
-    pub enum InputForwardingError {
-        InitializationFailed(String),
-        ConfigurationError(String),
-        SendError(String),
-    }
-
-    impl fmt::Display for InputForwardingError {
-        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            match self {
-                InputForwardingError::InitializationFailed(msg) => write!(f, "Initialization failed: {}", msg),
-                InputForwardingError::ConfigurationError(msg) => write!(f, "Configuration error: {}", msg),
-                InputForwardingError::SendError(msg) => write!(f, "Send error: {}", msg),
-            }
-        }
-    }
-
-    impl std::error::Error for InputForwardingError {}
-}
-EOF
-
-# 4. Icons erstellen (einfache SVG-basierte PNGs)
+# 3. Icons erstellen (einfache SVG-basierte PNGs)
 echo "🎨 Erstelle Icons..."
 
 mkdir -p icons docs/static/img
@@ -507,7 +242,7 @@ else
     touch docs/static/img/logo.png
 fi
 
-# 5. Desktop-Datei erstellen
+# 4. Desktop-Datei erstellen
 mkdir -p packaging
 
 cat > packaging/smoldesk.desktop << 'EOF'
@@ -526,7 +261,7 @@ StartupNotify=true
 StartupWMClass=SmolDesk
 EOF
 
-# 6. Fehlende Verzeichnisse erstellen
+# 5. Fehlende Verzeichnisse erstellen
 mkdir -p dist
 mkdir -p target/release/bundle
 
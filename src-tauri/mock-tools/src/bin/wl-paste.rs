@@ -0,0 +1,3 @@
+fn main() {
+    smoldesk_mock_tools::run("wl-paste")
+}
@@ -0,0 +1,90 @@
+//! Fake external tools for SmolDesk's tests.
+//!
+//! Every binary of this crate stands in for the tool it is named after. It
+//! records its invocation and replays canned output from the directory in
+//! `SMOLDESK_MOCK_DIR`:
+//!
+//! - `invocations.log` gets one line per call: the tool name and its
+//!   arguments, separated by tabs
+//! - `<tool>.stdout` and `<tool>.stderr` are copied to stdout and stderr
+//! - `<tool>.exit` holds the exit code, 0 if missing
+//! - `<tool>.sleep_ms` keeps the process alive after its output, like a
+//!   streaming ffmpeg, until the time is up or it is killed
+//! - if `<tool>.read_stdin` exists, stdin is read to the end and appended to
+//!   `<tool>.stdin`
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+/// Directory with the canned output and the invocation log
+pub const MOCK_DIR_VAR: &str = "SMOLDESK_MOCK_DIR";
+
+/// Name of the invocation log inside the mock directory
+pub const INVOCATION_LOG: &str = "invocations.log";
+
+/// Behave like `tool` and exit
+pub fn run(tool: &str) -> ! {
+    let dir = match env::var_os(MOCK_DIR_VAR) {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            eprintln!("{}: {} is not set, refusing to run outside the test harness", tool, MOCK_DIR_VAR);
+            process::exit(127);
+        }
+    };
+
+    if let Err(e) = record_invocation(&dir, tool) {
+        eprintln!("{}: failed to record invocation: {}", tool, e);
+        process::exit(127);
+    }
+
+    if dir.join(format!("{}.read_stdin", tool)).exists() {
+        let mut input = Vec::new();
+        let _ = io::stdin().read_to_end(&mut input);
+        let _ = append(&dir.join(format!("{}.stdin", tool)), &input);
+    }
+
+    if let Ok(data) = fs::read(dir.join(format!("{}.stdout", tool))) {
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(&data);
+        let _ = stdout.flush();
+    }
+    if let Ok(data) = fs::read(dir.join(format!("{}.stderr", tool))) {
+        let _ = io::stderr().write_all(&data);
+    }
+
+    if let Some(ms) = read_number(&dir.join(format!("{}.sleep_ms", tool))) {
+        thread::sleep(Duration::from_millis(ms));
+    }
+
+    let code = read_number(&dir.join(format!("{}.exit", tool))).unwrap_or(0);
+    process::exit(code as i32)
+}
+
+fn record_invocation(dir: &Path, tool: &str) -> io::Result<()> {
+    let mut line = tool.to_string();
+    for arg in env::args().skip(1) {
+        line.push('\t');
+        line.push_str(&arg);
+    }
+    line.push('\n');
+
+    // A single append keeps lines of concurrent invocations intact
+    append(&dir.join(INVOCATION_LOG), line.as_bytes())
+}
+
+fn append(path: &Path, data: &[u8]) -> io::Result<()> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(data)
+}
+
+fn read_number(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
@@ -0,0 +1,492 @@
+// src-tauri/src/accessibility.rs - Text of accessible objects on the host's screen
+//
+// Screen reader users get nothing from a video stream. Applications publish
+// what they draw on the AT-SPI2 accessibility bus as a tree of objects with a
+// role, a name, text and screen extents, so a viewer can ask for the text in a
+// region of a monitor or in the focused widget instead of running OCR.
+//
+// An application that stops answering must not hang the command, so every
+// query runs under a timeout and walks a bounded part of the tree.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::screen_capture::types::MonitorInfo;
+
+/// Time a query may take before it fails with `Timeout`
+pub const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Objects returned for one region at most, in reading order
+pub const MAX_OBJECTS: usize = 500;
+
+/// Characters of text returned per object at most
+pub const MAX_TEXT_CHARS: usize = 10_000;
+
+/// Rectangle in screen coordinates spanning all monitors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ScreenRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ScreenRect {
+    /// Converts a rectangle relative to `monitor` into screen coordinates.
+    /// None if it is empty or reaches past the monitor.
+    pub fn on_monitor(monitor: &MonitorInfo, x: u32, y: u32, width: u32, height: u32) -> Option<Self> {
+        let fits = width > 0
+            && height > 0
+            && x.checked_add(width).is_some_and(|right| right <= monitor.width)
+            && y.checked_add(height).is_some_and(|bottom| bottom <= monitor.height);
+        if !fits {
+            return None;
+        }
+
+        Some(ScreenRect {
+            x: monitor.x_offset + x as i32,
+            y: monitor.y_offset + y as i32,
+            width,
+            height,
+        })
+    }
+
+    pub fn intersects(&self, other: &ScreenRect) -> bool {
+        let right = |rect: &ScreenRect| rect.x as i64 + rect.width as i64;
+        let bottom = |rect: &ScreenRect| rect.y as i64 + rect.height as i64;
+        (self.x as i64) < right(other)
+            && (other.x as i64) < right(self)
+            && (self.y as i64) < bottom(other)
+            && (other.y as i64) < bottom(self)
+    }
+}
+
+/// One accessible object, e.g. a label, a button or a text field
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessibleText {
+    /// AT-SPI role name, e.g. "label", "push button" or "text"
+    pub role: String,
+    pub name: String,
+
+    /// Text content, or the current value of sliders and spin buttons
+    pub text: Option<String>,
+    pub bounds: ScreenRect,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccessibilityError {
+    /// No accessibility bus, e.g. AT-SPI is not running or disabled
+    BusUnavailable(String),
+    /// An application did not answer in time
+    Timeout(Duration),
+    QueryFailed(String),
+}
+
+impl fmt::Display for AccessibilityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccessibilityError::BusUnavailable(msg) => write!(f, "Accessibility bus unavailable: {}", msg),
+            AccessibilityError::Timeout(timeout) => write!(f, "Accessibility query timed out after {} ms", timeout.as_millis()),
+            AccessibilityError::QueryFailed(msg) => write!(f, "Accessibility query failed: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for AccessibilityError {}
+
+pub type TreeFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, AccessibilityError>> + Send + 'a>>;
+
+/// Source of accessible objects; the system one is AT-SPI2, tests use a fake
+pub trait AccessibilityTree: Send + Sync {
+    /// Showing objects with a name or text whose extents intersect `region`
+    fn objects_in(&self, region: ScreenRect) -> TreeFuture<'_, Vec<AccessibleText>>;
+
+    /// The object with keyboard focus, if any
+    fn focused(&self) -> TreeFuture<'_, Option<AccessibleText>>;
+}
+
+/// Text in `region`, top to bottom and left to right
+pub async fn text_at_region(
+    tree: &dyn AccessibilityTree,
+    region: ScreenRect,
+    timeout: Duration,
+) -> Result<Vec<AccessibleText>, AccessibilityError> {
+    let mut objects = tokio::time::timeout(timeout, tree.objects_in(region))
+        .await
+        .map_err(|_| AccessibilityError::Timeout(timeout))??;
+
+    objects.sort_by_key(|object| (object.bounds.y, object.bounds.x));
+    objects.truncate(MAX_OBJECTS);
+    objects.iter_mut().for_each(truncate_text);
+    Ok(objects)
+}
+
+/// Text or value of the focused widget
+pub async fn focused_text(
+    tree: &dyn AccessibilityTree,
+    timeout: Duration,
+) -> Result<Option<AccessibleText>, AccessibilityError> {
+    let mut focused = tokio::time::timeout(timeout, tree.focused())
+        .await
+        .map_err(|_| AccessibilityError::Timeout(timeout))??;
+
+    if let Some(object) = &mut focused {
+        truncate_text(object);
+    }
+    Ok(focused)
+}
+
+fn truncate_text(object: &mut AccessibleText) {
+    if let Some(text) = &mut object.text {
+        if let Some((index, _)) = text.char_indices().nth(MAX_TEXT_CHARS) {
+            text.truncate(index);
+        }
+    }
+}
+
+/// The host's accessibility bus
+pub fn system_tree() -> Box<dyn AccessibilityTree> {
+    #[cfg(feature = "accessibility")]
+    {
+        Box::new(atspi_tree::AtspiTree::new())
+    }
+
+    #[cfg(not(feature = "accessibility"))]
+    {
+        Box::new(UnsupportedTree)
+    }
+}
+
+#[cfg(not(feature = "accessibility"))]
+struct UnsupportedTree;
+
+#[cfg(not(feature = "accessibility"))]
+impl AccessibilityTree for UnsupportedTree {
+    fn objects_in(&self, _region: ScreenRect) -> TreeFuture<'_, Vec<AccessibleText>> {
+        Box::pin(async { Err(unsupported()) })
+    }
+
+    fn focused(&self) -> TreeFuture<'_, Option<AccessibleText>> {
+        Box::pin(async { Err(unsupported()) })
+    }
+}
+
+#[cfg(not(feature = "accessibility"))]
+fn unsupported() -> AccessibilityError {
+    AccessibilityError::BusUnavailable("SmolDesk was built without the accessibility feature".to_string())
+}
+
+#[cfg(feature = "accessibility")]
+mod atspi_tree {
+    use atspi::connection::AccessibilityConnection;
+    use atspi::proxy::accessible::AccessibleProxy;
+    use atspi::proxy::component::ComponentProxy;
+    use atspi::proxy::text::TextProxy;
+    use atspi::proxy::value::ValueProxy;
+    use atspi::{CoordType, Interface, State};
+    use tokio::sync::Mutex;
+    use zbus::CacheProperties;
+
+    use super::*;
+    use crate::logging::TARGET_SCREEN_CAPTURE;
+
+    const REGISTRY_BUS_NAME: &str = "org.a11y.atspi.Registry";
+    const ROOT_PATH: &str = "/org/a11y/atspi/accessible/root";
+
+    /// Objects visited per query at most; large documents expose thousands
+    const MAX_VISITED: usize = 5000;
+
+    /// Deeper objects are not visited
+    const MAX_DEPTH: usize = 64;
+
+    /// Queries the AT-SPI2 registry. The connection is opened on the first
+    /// query and again after it broke, e.g. when the bus was restarted.
+    pub struct AtspiTree {
+        connection: Mutex<Option<AccessibilityConnection>>,
+    }
+
+    /// One object of the tree with what was read about it
+    struct Node {
+        object: AccessibleText,
+        showing: bool,
+        focused: bool,
+        children: Vec<(String, zbus::zvariant::OwnedObjectPath)>,
+    }
+
+    impl AtspiTree {
+        pub fn new() -> Self {
+            AtspiTree { connection: Mutex::new(None) }
+        }
+
+        async fn connection(&self) -> Result<zbus::Connection, AccessibilityError> {
+            let mut connection = self.connection.lock().await;
+            if connection.is_none() {
+                let opened = AccessibilityConnection::open().await
+                    .map_err(|e| AccessibilityError::BusUnavailable(e.to_string()))?;
+                *connection = Some(opened);
+            }
+            Ok(connection.as_ref().unwrap().connection().clone())
+        }
+
+        /// Walks the tree from the registry root depth first; `visit` decides
+        /// whether the children of an object are visited
+        async fn walk<F>(&self, mut visit: F) -> Result<(), AccessibilityError>
+        where
+            F: FnMut(&Node) -> Walk + Send,
+        {
+            let connection = self.connection().await?;
+            let root_path = zbus::zvariant::OwnedObjectPath::try_from(ROOT_PATH).unwrap();
+            let root = proxy(&connection, REGISTRY_BUS_NAME, root_path).await;
+            let root = match root {
+                Ok(root) => root,
+                Err(e) => {
+                    // The bus is gone; reconnect on the next query
+                    *self.connection.lock().await = None;
+                    return Err(AccessibilityError::BusUnavailable(e.to_string()));
+                },
+            };
+
+            let mut pending = vec![(root, 0)];
+            let mut visited = 0;
+            while let Some((proxy_ref, depth)) = pending.pop() {
+                visited += 1;
+                if visited > MAX_VISITED {
+                    log::debug!(target: TARGET_SCREEN_CAPTURE, "Accessibility walk stopped after {} objects", MAX_VISITED);
+                    break;
+                }
+
+                // An application that broke its object away is skipped, not fatal
+                let node = match read_node(&connection, proxy_ref).await {
+                    Ok(node) => node,
+                    Err(e) if depth == 0 => return Err(AccessibilityError::QueryFailed(e.to_string())),
+                    Err(_) => continue,
+                };
+                match visit(&node) {
+                    Walk::Stop => break,
+                    Walk::Skip => continue,
+                    Walk::Descend if depth >= MAX_DEPTH => continue,
+                    Walk::Descend => {},
+                }
+
+                // Reversed so that the first child is visited first
+                for (bus_name, path) in node.children.into_iter().rev() {
+                    if let Ok(child) = proxy(&connection, &bus_name, path).await {
+                        pending.push((child, depth + 1));
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Walk {
+        Descend,
+        Skip,
+        Stop,
+    }
+
+    impl AccessibilityTree for AtspiTree {
+        fn objects_in(&self, region: ScreenRect) -> TreeFuture<'_, Vec<AccessibleText>> {
+            Box::pin(async move {
+                let mut objects = Vec::new();
+                self.walk(|node| {
+                    // Applications have no extents of their own
+                    let is_application = node.object.role == "application";
+                    if !node.showing && !is_application {
+                        return Walk::Skip;
+                    }
+                    if !is_application && !node.object.bounds.intersects(&region) {
+                        return Walk::Skip;
+                    }
+                    let has_text = node.object.text.as_deref().is_some_and(|text| !text.trim().is_empty());
+                    let is_labelled_leaf = node.children.is_empty() && !node.object.name.trim().is_empty();
+                    if !is_application && (has_text || is_labelled_leaf) {
+                        objects.push(node.object.clone());
+                    }
+                    Walk::Descend
+                }).await?;
+                Ok(objects)
+            })
+        }
+
+        fn focused(&self) -> TreeFuture<'_, Option<AccessibleText>> {
+            Box::pin(async move {
+                let mut focused = None;
+                self.walk(|node| {
+                    if node.focused {
+                        focused = Some(node.object.clone());
+                        return Walk::Stop;
+                    }
+                    if node.showing || node.object.role == "application" {
+                        Walk::Descend
+                    } else {
+                        Walk::Skip
+                    }
+                }).await?;
+                Ok(focused)
+            })
+        }
+    }
+
+    async fn proxy(
+        connection: &zbus::Connection,
+        bus_name: &str,
+        path: zbus::zvariant::OwnedObjectPath,
+    ) -> zbus::Result<AccessibleProxy<'static>> {
+        AccessibleProxy::builder(connection)
+            .destination(bus_name.to_string())?
+            .path(path)?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+    }
+
+    async fn read_node(connection: &zbus::Connection, proxy: AccessibleProxy<'static>) -> zbus::Result<Node> {
+        let role = proxy.get_role_name().await?;
+        let name = proxy.name().await.unwrap_or_default();
+        let states = proxy.get_state().await?;
+        let interfaces = proxy.get_interfaces().await?;
+        let children = proxy.get_children().await?
+            .into_iter()
+            .map(|child| (child.name.to_string(), child.path))
+            .collect();
+
+        let destination = proxy.inner().destination().to_owned();
+        let path = proxy.inner().path().to_owned();
+
+        let mut bounds = ScreenRect { x: 0, y: 0, width: 0, height: 0 };
+        if interfaces.contains(Interface::Component) {
+            let component = ComponentProxy::builder(connection)
+                .destination(destination.clone())?
+                .path(path.clone())?
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await?;
+            let (x, y, width, height) = component.get_extents(CoordType::Screen).await?;
+            bounds = ScreenRect { x, y, width: width.max(0) as u32, height: height.max(0) as u32 };
+        }
+
+        let mut text = None;
+        if interfaces.contains(Interface::Text) {
+            let text_proxy = TextProxy::builder(connection)
+                .destination(destination.clone())?
+                .path(path.clone())?
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await?;
+            let count = text_proxy.character_count().await?;
+            let end = count.min(MAX_TEXT_CHARS as i32);
+            text = Some(text_proxy.get_text(0, end).await?);
+        } else if interfaces.contains(Interface::Value) {
+            let value_proxy = ValueProxy::builder(connection)
+                .destination(destination)?
+                .path(path)?
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await?;
+            text = Some(value_proxy.current_value().await?.to_string());
+        }
+
+        Ok(Node {
+            object: AccessibleText { role, name, text, bounds },
+            showing: states.contains(State::Showing),
+            focused: states.contains(State::Focused),
+            children,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::monitor;
+
+    struct FakeTree {
+        objects: Vec<AccessibleText>,
+        delay: Duration,
+    }
+
+    impl AccessibilityTree for FakeTree {
+        fn objects_in(&self, region: ScreenRect) -> TreeFuture<'_, Vec<AccessibleText>> {
+            Box::pin(async move {
+                tokio::time::sleep(self.delay).await;
+                Ok(self.objects.iter().filter(|object| object.bounds.intersects(&region)).cloned().collect())
+            })
+        }
+
+        fn focused(&self) -> TreeFuture<'_, Option<AccessibleText>> {
+            Box::pin(async move {
+                tokio::time::sleep(self.delay).await;
+                Ok(self.objects.last().cloned())
+            })
+        }
+    }
+
+    fn object(role: &str, text: &str, x: i32, y: i32) -> AccessibleText {
+        AccessibleText {
+            role: role.to_string(),
+            name: String::new(),
+            text: Some(text.to_string()),
+            bounds: ScreenRect { x, y, width: 100, height: 20 },
+        }
+    }
+
+    #[test]
+    fn test_region_is_relative_to_the_monitor() {
+        let region = ScreenRect::on_monitor(&monitor(), 100, 50, 200, 100).unwrap();
+        assert_eq!(region, ScreenRect { x: 2660, y: 50, width: 200, height: 100 });
+
+        assert_eq!(ScreenRect::on_monitor(&monitor(), 1900, 0, 100, 100), None);
+        assert_eq!(ScreenRect::on_monitor(&monitor(), 0, 0, 0, 100), None);
+        assert_eq!(ScreenRect::on_monitor(&monitor(), u32::MAX, 0, 10, 10), None);
+
+        // Touching edges do not intersect
+        let left = ScreenRect { x: 0, y: 0, width: 10, height: 10 };
+        assert!(!left.intersects(&ScreenRect { x: 10, y: 0, width: 10, height: 10 }));
+        assert!(left.intersects(&ScreenRect { x: 9, y: 9, width: 10, height: 10 }));
+    }
+
+    #[tokio::test]
+    async fn test_text_at_region_in_reading_order() {
+        let tree = FakeTree {
+            objects: vec![
+                object("push button", "Cancel", 2800, 400),
+                object("label", "Save changes?", 2700, 100),
+                object("push button", "Save", 2700, 400),
+                object("label", "On the other monitor", 100, 100),
+                object("text", &"x".repeat(MAX_TEXT_CHARS + 10), 2700, 200),
+            ],
+            delay: Duration::ZERO,
+        };
+
+        let region = ScreenRect::on_monitor(&monitor(), 0, 0, 1920, 1080).unwrap();
+        let objects = text_at_region(&tree, region, QUERY_TIMEOUT).await.unwrap();
+        let texts: Vec<_> = objects.iter().map(|object| object.text.as_deref().unwrap().len()).collect();
+        assert_eq!(objects[0].text.as_deref(), Some("Save changes?"));
+        assert_eq!(texts[1], MAX_TEXT_CHARS);
+        assert_eq!(objects[2].text.as_deref(), Some("Save"));
+        assert_eq!(objects[3].text.as_deref(), Some("Cancel"));
+        assert_eq!(objects.len(), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_unresponsive_application_times_out() {
+        let tree = FakeTree {
+            objects: vec![object("text", "secret", 0, 0)],
+            delay: Duration::from_secs(30),
+        };
+
+        let region = ScreenRect { x: 0, y: 0, width: 10, height: 10 };
+        assert_eq!(
+            text_at_region(&tree, region, QUERY_TIMEOUT).await,
+            Err(AccessibilityError::Timeout(QUERY_TIMEOUT))
+        );
+        assert_eq!(focused_text(&tree, QUERY_TIMEOUT).await, Err(AccessibilityError::Timeout(QUERY_TIMEOUT)));
+    }
+}
@@ -0,0 +1,551 @@
+// src-tauri/src/capabilities.rs - Probes for external tools and system capabilities
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use serde::{Deserialize, Serialize};
+
+use crate::command_runner::{CommandRunner, SystemCommandRunner};
+use crate::input_forwarding::factory::detect_display_server;
+use crate::input_forwarding::portal::PortalInputForwarder;
+use crate::input_forwarding::types::DisplayServer;
+use crate::input_forwarding::utils::check_tool_exists;
+use crate::safe_mode::SafeModeSource;
+use crate::screen_capture::types::DisplayServer as CaptureDisplayServer;
+use crate::screen_capture::virtual_display::{detect_backend, VirtualDisplayBackend};
+use crate::screen_capture::wayland::detect_wayland_compositor;
+use crate::tool_locator::{self, ToolPathProblem};
+
+const UINPUT_DEVICE: &str = "/dev/uinput";
+
+/// Backend features whose availability depends on external tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Feature {
+    ScreenCapture,
+    InputForwarding,
+    Clipboard,
+    VirtualDisplay,
+}
+
+/// How usable a feature is on this system
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CapabilityStatus {
+    Available,
+    Degraded,
+    Unavailable,
+}
+
+/// Result of probing a single external tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolStatus {
+    pub name: String,
+    pub installed: bool,
+    pub version: Option<String>,
+    /// Outcome of a functional smoke test, if one exists for this tool
+    pub smoke_test_passed: Option<bool>,
+    pub smoke_test_output: Option<String>,
+}
+
+/// Availability of one feature with a human-readable explanation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeatureStatus {
+    pub feature: Feature,
+    pub status: CapabilityStatus,
+    pub message: String,
+    pub remediation: Option<String>,
+}
+
+/// Structured report of everything SmolDesk depends on at runtime
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapabilityReport {
+    pub display_server: String,
+    pub compositor: Option<String>,
+    pub tools: Vec<ToolStatus>,
+    pub uinput_accessible: bool,
+    pub ydotoold_running: bool,
+    #[serde(default)]
+    pub remote_desktop_portal: bool,
+    pub pipewire_running: bool,
+    /// Paths from the `tools` settings that cannot be used
+    #[serde(default)]
+    pub tool_paths: Vec<ToolPathProblem>,
+    pub features: Vec<FeatureStatus>,
+    pub generated_at: String,
+    /// Set while the app runs in safe mode, with what switched it on
+    #[serde(default)]
+    pub safe_mode: Option<SafeModeSource>,
+}
+
+impl CapabilityReport {
+    /// Status of a single feature
+    pub fn feature(&self, feature: Feature) -> Option<&FeatureStatus> {
+        self.features.iter().find(|status| status.feature == feature)
+    }
+
+    /// Probe result of a single tool
+    pub fn tool(&self, name: &str) -> Option<&ToolStatus> {
+        self.tools.iter().find(|tool| tool.name == name)
+    }
+
+    /// Why the configured path of `tool` cannot be used, if it is configured
+    pub fn tool_path_problem(&self, tool: &str) -> Option<&ToolPathProblem> {
+        self.tool_paths.iter().find(|problem| problem.tool == tool)
+    }
+
+    /// Status of a feature that is not fully usable, with its remediation hint.
+    /// Returns `None` if the feature is available.
+    pub fn diagnose(&self, feature: Feature) -> Option<&FeatureStatus> {
+        self.feature(feature)
+            .filter(|status| status.status != CapabilityStatus::Available)
+    }
+
+    /// Marks input and clipboard as switched off by safe mode, whatever the
+    /// system would support
+    pub fn with_safe_mode(mut self, source: Option<SafeModeSource>) -> Self {
+        self.safe_mode = source;
+        if source.is_some() {
+            for status in &mut self.features {
+                if matches!(status.feature, Feature::InputForwarding | Feature::Clipboard) {
+                    *status = unavailable(status.feature, "Disabled by safe mode", "Restart SmolDesk without safe mode");
+                }
+            }
+        }
+        self
+    }
+}
+
+/// Probes all external dependencies. Spawns several short-lived processes,
+/// so this should not be called on hot paths.
+pub fn check_system_capabilities() -> CapabilityReport {
+    let display_server = detect_display_server();
+
+    let compositor = match display_server {
+        DisplayServer::Wayland => Some(format!("{:?}", detect_wayland_compositor())),
+        _ => None,
+    };
+
+    let tools = vec![
+        probe_ffmpeg(&display_server),
+        probe_tool("xdotool", &["version"]),
+        probe_tool("ydotool", &["--help"]),
+        probe_tool("xclip", &["-version"]),
+        probe_tool("wl-copy", &["--version"]),
+        probe_tool("wl-paste", &["--version"]),
+        probe_tool("pipewire", &["--version"]),
+    ];
+
+    let uinput_accessible = check_uinput_access(Path::new(UINPUT_DEVICE));
+    let ydotoold_running = ydotoold_socket_candidates().iter().any(|path| is_socket(path));
+    let pipewire_running = pipewire_socket().map(|path| is_socket(&path)).unwrap_or(false);
+    let remote_desktop_portal = display_server == DisplayServer::Wayland && PortalInputForwarder::is_available();
+    let tool_paths = tool_locator::current().validate();
+
+    let mut report = CapabilityReport {
+        display_server: format!("{:?}", display_server),
+        compositor,
+        tools,
+        uinput_accessible,
+        ydotoold_running,
+        remote_desktop_portal,
+        pipewire_running,
+        tool_paths,
+        features: Vec::new(),
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        safe_mode: None,
+    };
+
+    report.features = vec![
+        screen_capture_status(&report, &display_server),
+        input_forwarding_status(&report, &display_server),
+        clipboard_status(&report, &display_server),
+        virtual_display_status(&display_server),
+    ];
+
+    report
+}
+
+fn screen_capture_status(report: &CapabilityReport, display_server: &DisplayServer) -> FeatureStatus {
+    let feature = Feature::ScreenCapture;
+    let ffmpeg = report.tool("ffmpeg");
+
+    if let Some(status) = misconfigured(report, feature, &["ffmpeg"]) {
+        return status;
+    }
+    if !ffmpeg.map(|tool| tool.installed).unwrap_or(false) {
+        return unavailable(feature, "ffmpeg is not installed", "Install ffmpeg from your distribution's packages");
+    }
+
+    match display_server {
+        DisplayServer::X11 => {
+            if ffmpeg.and_then(|tool| tool.smoke_test_passed) == Some(false) {
+                return unavailable(
+                    feature,
+                    "ffmpeg is installed but x11grab capture failed",
+                    "Make sure ffmpeg was built with x11grab support and DISPLAY points to a running X server",
+                );
+            }
+            available(feature, "Screen capture via ffmpeg x11grab")
+        },
+        DisplayServer::Wayland => {
+            if !report.pipewire_running {
+                return unavailable(
+                    feature,
+                    "PipeWire is not running",
+                    "Start the PipeWire service (systemctl --user start pipewire) and xdg-desktop-portal",
+                );
+            }
+            available(feature, "Screen capture via PipeWire")
+        },
+        DisplayServer::Unknown => unavailable(
+            feature,
+            "No X11 or Wayland session detected",
+            "Run SmolDesk inside a graphical session with DISPLAY or WAYLAND_DISPLAY set",
+        ),
+    }
+}
+
+fn input_forwarding_status(report: &CapabilityReport, display_server: &DisplayServer) -> FeatureStatus {
+    let feature = Feature::InputForwarding;
+    let installed = |name: &str| report.tool(name).map(|tool| tool.installed).unwrap_or(false);
+
+    match display_server {
+        DisplayServer::X11 => {
+            if let Some(status) = misconfigured(report, feature, &["xdotool"]) {
+                return status;
+            }
+            if !installed("xdotool") {
+                return unavailable(feature, "xdotool is not installed", "Install the xdotool package");
+            }
+            available(feature, "Input forwarding via xdotool")
+        },
+        DisplayServer::Wayland => {
+            // The portal injects through the compositor and needs neither ydotoold nor uinput
+            if report.remote_desktop_portal {
+                return available(feature, "Input forwarding via the RemoteDesktop portal");
+            }
+            if let Some(status) = misconfigured(report, feature, &["ydotool"]) {
+                return status;
+            }
+            if !installed("ydotool") {
+                return unavailable(feature, "ydotool is not installed", "Install the ydotool package");
+            }
+            if !report.ydotoold_running {
+                return unavailable(
+                    feature,
+                    "ydotool installed but ydotoold not running",
+                    "Start the daemon with 'systemctl --user start ydotool' or run ydotoold",
+                );
+            }
+            if !report.uinput_accessible {
+                return degraded(
+                    feature,
+                    "ydotoold is running but /dev/uinput is not accessible to this user",
+                    "Add your user to the input group or install a udev rule granting access to /dev/uinput",
+                );
+            }
+            available(feature, "Input forwarding via ydotool")
+        },
+        DisplayServer::Unknown => unavailable(
+            feature,
+            "No X11 or Wayland session detected",
+            "Run SmolDesk inside a graphical session with DISPLAY or WAYLAND_DISPLAY set",
+        ),
+    }
+}
+
+fn clipboard_status(report: &CapabilityReport, display_server: &DisplayServer) -> FeatureStatus {
+    let feature = Feature::Clipboard;
+    let installed = |name: &str| report.tool(name).map(|tool| tool.installed).unwrap_or(false);
+
+    match display_server {
+        DisplayServer::X11 => {
+            if let Some(status) = misconfigured(report, feature, &["xclip"]) {
+                return status;
+            }
+            if !installed("xclip") {
+                return unavailable(feature, "xclip is not installed", "Install the xclip package");
+            }
+            available(feature, "Clipboard via xclip")
+        },
+        DisplayServer::Wayland => {
+            if let Some(status) = misconfigured(report, feature, &["wl-copy", "wl-paste"]) {
+                return status;
+            }
+            match (installed("wl-copy"), installed("wl-paste")) {
+                (true, true) => available(feature, "Clipboard via wl-clipboard"),
+                (false, false) => unavailable(feature, "wl-clipboard is not installed", "Install the wl-clipboard package"),
+                _ => degraded(
+                    feature,
+                    "wl-clipboard is only partially installed",
+                    "Reinstall the wl-clipboard package so both wl-copy and wl-paste are available",
+                ),
+            }
+        },
+        DisplayServer::Unknown => unavailable(
+            feature,
+            "No X11 or Wayland session detected",
+            "Run SmolDesk inside a graphical session with DISPLAY or WAYLAND_DISPLAY set",
+        ),
+    }
+}
+
+fn virtual_display_status(display_server: &DisplayServer) -> FeatureStatus {
+    let feature = Feature::VirtualDisplay;
+    let capture_display_server = match display_server {
+        DisplayServer::X11 => CaptureDisplayServer::X11,
+        DisplayServer::Wayland => CaptureDisplayServer::Wayland,
+        DisplayServer::Unknown => CaptureDisplayServer::Unknown,
+    };
+
+    match detect_backend(&capture_display_server) {
+        Ok(VirtualDisplayBackend::XrandrVirtualOutput) => available(feature, "Virtual displays via xrandr VIRTUAL outputs"),
+        Ok(VirtualDisplayBackend::WlrootsHeadless) => available(feature, "Virtual displays via wlroots headless outputs"),
+        Err(e) => unavailable(
+            feature,
+            &e.to_string(),
+            "Use an X server with VIRTUAL outputs or a wlroots compositor such as Sway",
+        ),
+    }
+}
+
+/// A configured path for one of `tools` that cannot be used makes the feature
+/// unavailable; falling back to PATH would hide the misconfiguration
+fn misconfigured(report: &CapabilityReport, feature: Feature, tools: &[&str]) -> Option<FeatureStatus> {
+    let problem = tools.iter().find_map(|tool| report.tool_path_problem(tool))?;
+    Some(unavailable(
+        feature,
+        &format!("Configured {} {} cannot be used: {}", problem.setting, problem.path.display(), problem.reason),
+        &format!("Fix or remove tools.{} in the settings", problem.setting),
+    ))
+}
+
+fn available(feature: Feature, message: &str) -> FeatureStatus {
+    FeatureStatus {
+        feature,
+        status: CapabilityStatus::Available,
+        message: message.to_string(),
+        remediation: None,
+    }
+}
+
+fn degraded(feature: Feature, message: &str, remediation: &str) -> FeatureStatus {
+    FeatureStatus {
+        feature,
+        status: CapabilityStatus::Degraded,
+        message: message.to_string(),
+        remediation: Some(remediation.to_string()),
+    }
+}
+
+fn unavailable(feature: Feature, message: &str, remediation: &str) -> FeatureStatus {
+    FeatureStatus {
+        feature,
+        status: CapabilityStatus::Unavailable,
+        message: message.to_string(),
+        remediation: Some(remediation.to_string()),
+    }
+}
+
+/// Checks whether a tool exists and reads the first line of its version output
+fn probe_tool(name: &str, version_args: &[&str]) -> ToolStatus {
+    let installed = check_tool_exists(name);
+    let version = if installed { read_version(name, version_args) } else { None };
+
+    ToolStatus {
+        name: name.to_string(),
+        installed,
+        version,
+        smoke_test_passed: None,
+        smoke_test_output: None,
+    }
+}
+
+/// ffmpeg additionally gets a zero-length x11grab capture on X11
+fn probe_ffmpeg(display_server: &DisplayServer) -> ToolStatus {
+    let mut status = probe_tool("ffmpeg", &["-version"]);
+    if !status.installed || *display_server != DisplayServer::X11 {
+        return status;
+    }
+
+    let display = std::env::var("DISPLAY").unwrap_or_else(|_| ":0".to_string());
+    let output = SystemCommandRunner.command("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-f", "x11grab", "-t", "0", "-i"])
+        .arg(&display)
+        .args(["-f", "null", "-"])
+        .stdin(Stdio::null())
+        .output();
+
+    match output {
+        Ok(output) => {
+            status.smoke_test_passed = Some(output.status.success());
+            let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+            if !stderr.is_empty() {
+                status.smoke_test_output = Some(stderr);
+            }
+        },
+        Err(e) => {
+            status.smoke_test_passed = Some(false);
+            status.smoke_test_output = Some(e.to_string());
+        },
+    }
+
+    status
+}
+
+fn read_version(name: &str, args: &[&str]) -> Option<String> {
+    let output = SystemCommandRunner.command(name)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+
+    // Some tools (xclip) print their version to stderr
+    first_non_empty_line(&output.stdout)
+        .or_else(|| first_non_empty_line(&output.stderr))
+}
+
+fn first_non_empty_line(bytes: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(bytes)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+fn check_uinput_access(path: &Path) -> bool {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .is_ok()
+}
+
+/// Locations ydotoold listens on, in the order ydotool itself checks them
+fn ydotoold_socket_candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+
+    if let Ok(socket) = std::env::var("YDOTOOL_SOCKET") {
+        candidates.push(PathBuf::from(socket));
+    }
+    if let Ok(runtime_dir) = std::env::var("XDG_RUNTIME_DIR") {
+        candidates.push(PathBuf::from(runtime_dir).join(".ydotool_socket"));
+    }
+    candidates.push(PathBuf::from("/tmp/.ydotool_socket"));
+
+    candidates
+}
+
+fn pipewire_socket() -> Option<PathBuf> {
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR").ok()?;
+    let name = std::env::var("PIPEWIRE_REMOTE").unwrap_or_else(|_| "pipewire-0".to_string());
+    Some(PathBuf::from(runtime_dir).join(name))
+}
+
+#[cfg(unix)]
+fn is_socket(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    std::fs::metadata(path)
+        .map(|metadata| metadata.file_type().is_socket())
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_socket(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(name: &str, installed: bool) -> ToolStatus {
+        ToolStatus {
+            name: name.to_string(),
+            installed,
+            version: None,
+            smoke_test_passed: None,
+            smoke_test_output: None,
+        }
+    }
+
+    fn report(tools: Vec<ToolStatus>, ydotoold_running: bool, uinput_accessible: bool) -> CapabilityReport {
+        CapabilityReport {
+            display_server: "Wayland".to_string(),
+            compositor: None,
+            tools,
+            uinput_accessible,
+            ydotoold_running,
+            remote_desktop_portal: false,
+            pipewire_running: true,
+            tool_paths: Vec::new(),
+            features: Vec::new(),
+            generated_at: String::new(),
+            safe_mode: None,
+        }
+    }
+
+    #[test]
+    fn test_ydotool_without_daemon_is_reported() {
+        let mut report = report(vec![tool("ydotool", true)], false, true);
+        report.features = vec![input_forwarding_status(&report, &DisplayServer::Wayland)];
+
+        let diagnosis = report.diagnose(Feature::InputForwarding).unwrap();
+        assert!(diagnosis.message.starts_with("ydotool installed but ydotoold not running"));
+    }
+
+    #[test]
+    fn test_remote_desktop_portal_replaces_ydotool() {
+        let mut report = report(Vec::new(), false, false);
+        report.remote_desktop_portal = true;
+        report.features = vec![input_forwarding_status(&report, &DisplayServer::Wayland)];
+
+        assert_eq!(report.feature(Feature::InputForwarding).unwrap().status, CapabilityStatus::Available);
+    }
+
+    #[test]
+    fn test_available_feature_has_no_explanation() {
+        let mut report = report(vec![tool("wl-copy", true), tool("wl-paste", true)], true, true);
+        report.features = vec![clipboard_status(&report, &DisplayServer::Wayland)];
+
+        assert_eq!(report.feature(Feature::Clipboard).unwrap().status, CapabilityStatus::Available);
+        assert!(report.diagnose(Feature::Clipboard).is_none());
+    }
+
+    #[test]
+    fn test_safe_mode_overrides_remote_control_features() {
+        let mut report = report(vec![tool("wl-copy", true), tool("wl-paste", true)], true, true);
+        report.features = vec![
+            screen_capture_status(&report, &DisplayServer::Wayland),
+            clipboard_status(&report, &DisplayServer::Wayland),
+        ];
+        let screen_capture = report.feature(Feature::ScreenCapture).unwrap().clone();
+
+        let report = report.with_safe_mode(Some(SafeModeSource::Environment));
+        assert_eq!(report.diagnose(Feature::Clipboard).unwrap().message, "Disabled by safe mode");
+        assert_eq!(report.feature(Feature::ScreenCapture).unwrap().message, screen_capture.message);
+        assert_eq!(serde_json::to_value(&report).unwrap()["safe_mode"], "environment");
+    }
+
+    #[test]
+    fn test_misconfigured_tool_path_is_diagnosed() {
+        let mut report = report(vec![tool("wl-copy", true), tool("wl-paste", true)], true, true);
+        report.tool_paths = vec![ToolPathProblem {
+            tool: "wl-paste".to_string(),
+            setting: "wl_clipboard_path".to_string(),
+            path: PathBuf::from("/app/bin/wl-paste"),
+            reason: "not executable".to_string(),
+        }];
+        report.features = vec![clipboard_status(&report, &DisplayServer::Wayland)];
+
+        let diagnosis = report.diagnose(Feature::Clipboard).unwrap();
+        assert_eq!(diagnosis.status, CapabilityStatus::Unavailable);
+        assert_eq!(diagnosis.message, "Configured wl_clipboard_path /app/bin/wl-paste cannot be used: not executable");
+        assert_eq!(diagnosis.remediation.as_deref(), Some("Fix or remove tools.wl_clipboard_path in the settings"));
+    }
+
+    #[test]
+    fn test_first_non_empty_line() {
+        assert_eq!(first_non_empty_line(b"\n  xclip version 0.13\nmore"), Some("xclip version 0.13".to_string()));
+        assert_eq!(first_non_empty_line(b"  \n"), None);
+    }
+}
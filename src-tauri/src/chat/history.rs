@@ -0,0 +1,136 @@
+// src-tauri/src/chat/history.rs - Chat history on disk, one file per peer fingerprint
+//
+// Signaling IDs change with every connection, the device fingerprint does
+// not, so a peer that connects again finds its earlier conversation. Each
+// file is an append-only log with one JSON message per line; a status change
+// appends the message again, and loading keeps the last record of each ID.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::chat::types::ChatMessage;
+
+/// Directory below the app data directory
+const HISTORY_DIR: &str = "chat_history";
+
+/// Messages kept per peer; older ones are dropped when the file is compacted
+pub const MAX_PERSISTED_MESSAGES: usize = 1000;
+
+pub struct ChatHistoryStore {
+    dir: PathBuf,
+}
+
+impl ChatHistoryStore {
+    /// Opens the history directory below `data_dir`, creating it if needed
+    pub fn open(data_dir: &Path) -> io::Result<Self> {
+        let dir = data_dir.join(HISTORY_DIR);
+        fs::create_dir_all(&dir)?;
+        Ok(ChatHistoryStore { dir })
+    }
+
+    /// Records a new message or a new status of a known one
+    pub fn append(&self, fingerprint: &str, message: &ChatMessage) -> io::Result<()> {
+        let line = serde_json::to_string(message)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(self.path_for(fingerprint))?;
+        writeln!(file, "{}", line)
+    }
+
+    /// The last `limit` messages exchanged with the peer, oldest first.
+    /// Compacts the file once it holds far more records than messages kept.
+    pub fn load(&self, fingerprint: &str, limit: usize) -> io::Result<Vec<ChatMessage>> {
+        let path = self.path_for(fingerprint);
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut messages: Vec<ChatMessage> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        let mut records = 0;
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            // A line cut off by a crash costs one record, not the history
+            let Ok(message) = serde_json::from_str::<ChatMessage>(&line) else {
+                continue;
+            };
+            records += 1;
+            match positions.get(&message.id) {
+                Some(&index) => messages[index] = message,
+                None => {
+                    positions.insert(message.id.clone(), messages.len());
+                    messages.push(message);
+                },
+            }
+        }
+
+        let kept = messages.len().min(MAX_PERSISTED_MESSAGES);
+        messages.drain(..messages.len() - kept);
+        if records > 2 * kept.max(MAX_PERSISTED_MESSAGES / 2) {
+            self.rewrite(&path, &messages)?;
+        }
+
+        messages.drain(..messages.len().saturating_sub(limit));
+        Ok(messages)
+    }
+
+    fn rewrite(&self, path: &Path, messages: &[ChatMessage]) -> io::Result<()> {
+        let temp = path.with_extension("jsonl.tmp");
+        let mut file = File::create(&temp)?;
+        for message in messages {
+            writeln!(file, "{}", serde_json::to_string(message)?)?;
+        }
+        file.sync_all()?;
+        fs::rename(temp, path)
+    }
+
+    /// Fingerprints contain characters that do not belong in file names
+    fn path_for(&self, fingerprint: &str) -> PathBuf {
+        let digest = Sha256::digest(fingerprint.as_bytes());
+        let name: String = digest.iter().take(16).map(|byte| format!("{:02x}", byte)).collect();
+        self.dir.join(format!("{}.jsonl", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    use crate::chat::types::{ChatDirection, DeliveryStatus};
+
+    fn message(id: &str, status: DeliveryStatus) -> ChatMessage {
+        ChatMessage {
+            id: id.to_string(),
+            peer_id: "peer-1".to_string(),
+            direction: ChatDirection::Outgoing,
+            text: format!("text {}", id),
+            sent_at: Utc::now(),
+            status,
+        }
+    }
+
+    #[test]
+    fn test_status_updates_replace_earlier_records() {
+        let dir = std::env::temp_dir().join(format!("smoldesk-chat-{}", uuid::Uuid::new_v4()));
+        let store = ChatHistoryStore::open(&dir).unwrap();
+
+        store.append("SHA256:ab:cd", &message("a", DeliveryStatus::Sent)).unwrap();
+        store.append("SHA256:ab:cd", &message("b", DeliveryStatus::Sent)).unwrap();
+        store.append("SHA256:ab:cd", &message("a", DeliveryStatus::Delivered)).unwrap();
+        store.append("SHA256:other", &message("c", DeliveryStatus::Sent)).unwrap();
+
+        let loaded = store.load("SHA256:ab:cd", 10).unwrap();
+        let summary: Vec<(&str, DeliveryStatus)> = loaded.iter().map(|m| (m.id.as_str(), m.status)).collect();
+        assert_eq!(summary, vec![("a", DeliveryStatus::Delivered), ("b", DeliveryStatus::Sent)]);
+
+        assert_eq!(store.load("SHA256:ab:cd", 1).unwrap()[0].id, "b");
+        assert!(store.load("SHA256:unknown", 10).unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
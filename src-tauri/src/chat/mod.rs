@@ -0,0 +1,603 @@
+// src-tauri/src/chat/mod.rs - Text chat with connected peers
+//
+// Support sessions need a way to talk that does not depend on the frontend:
+// the conversation with each peer lives here for as long as the peer is
+// connected, so a reloaded window gets it back from `get_chat_history`. With
+// `persist_history` it is also kept on disk per device fingerprint. Every
+// frame to a peer, whether message, delivery receipt or typing notice, goes
+// through a `ChatTransport`, which seals it with the peer's clipboard sync key.
+
+pub mod history;
+pub mod types;
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::chat::history::ChatHistoryStore;
+use crate::chat::types::{
+    ChatConfig, ChatDirection, ChatEvent, ChatFrame, ChatMessage, ChatReceipt, ChatStatusChange, ChatText,
+    ChatTyping, DeliveryStatus, TypingNotice, MAX_MESSAGE_BYTES,
+};
+use crate::logging::TARGET_CHAT;
+use crate::protocol::{ProtocolError, PROTOCOL_VERSION};
+
+/// Messages kept in memory per connected peer
+pub const MAX_SESSION_MESSAGES: usize = 1000;
+
+/// Messages returned by `history` without a limit
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// Typing notices are sent and reported at most this often per peer
+pub const TYPING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Longest message ID accepted from a peer; ours are UUIDs
+const MAX_ID_LEN: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatError {
+    EmptyMessage,
+    /// Size of the text in bytes
+    MessageTooLarge(usize),
+    /// No transport, or no key negotiated with the peer yet
+    NotConnected(String),
+    Protocol(ProtocolError),
+}
+
+impl fmt::Display for ChatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChatError::EmptyMessage => write!(f, "Chat message is empty"),
+            ChatError::MessageTooLarge(size) => write!(f, "Chat message has {} bytes, at most {} are allowed", size, MAX_MESSAGE_BYTES),
+            ChatError::NotConnected(msg) => write!(f, "Chat not connected: {}", msg),
+            ChatError::Protocol(e) => write!(f, "Invalid chat frame: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ChatError {}
+
+impl From<ProtocolError> for ChatError {
+    fn from(error: ProtocolError) -> Self {
+        ChatError::Protocol(error)
+    }
+}
+
+/// Carries frames to a peer
+pub trait ChatTransport: Send + Sync {
+    /// Seals `frame` for `peer_id` and hands it to the connection
+    fn send(&self, peer_id: &str, frame: &ChatFrame) -> Result<(), ChatError>;
+}
+
+/// Receives the events for the UI
+pub type ChatListener = Box<dyn Fn(&ChatEvent) + Send + Sync>;
+
+/// Fingerprint of a peer's verified device identity
+pub type FingerprintSource = Box<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+#[derive(Default)]
+struct Conversation {
+    messages: VecDeque<ChatMessage>,
+    /// Set once the peer's identity is known and its disk history attached
+    fingerprint: Option<String>,
+    typing_sent: Option<(Instant, bool)>,
+    typing_received: Option<(Instant, bool)>,
+}
+
+impl Conversation {
+    fn contains(&self, id: &str) -> bool {
+        self.messages.iter().any(|message| message.id == id)
+    }
+
+    fn push(&mut self, message: ChatMessage) {
+        self.messages.push_back(message);
+        self.trim();
+    }
+
+    /// Appends an incoming message, but before incoming messages the peer
+    /// sent later that overtook it. Own messages keep their place, as the
+    /// peer wrote in reply to what it had seen.
+    fn insert_incoming(&mut self, message: ChatMessage) {
+        let mut index = self.messages.len();
+        while index > 0 {
+            let previous = &self.messages[index - 1];
+            if previous.direction != ChatDirection::Incoming || previous.sent_at <= message.sent_at {
+                break;
+            }
+            index -= 1;
+        }
+        self.messages.insert(index, message);
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.messages.len() > MAX_SESSION_MESSAGES {
+            self.messages.pop_front();
+        }
+    }
+}
+
+/// Conversations with the connected peers
+pub struct ChatManager {
+    config: Mutex<ChatConfig>,
+    conversations: Mutex<HashMap<String, Conversation>>,
+    store: Option<ChatHistoryStore>,
+    transport: Option<Arc<dyn ChatTransport>>,
+    listener: Option<ChatListener>,
+    fingerprints: Option<FingerprintSource>,
+}
+
+impl ChatManager {
+    pub fn new(config: ChatConfig) -> Self {
+        ChatManager {
+            config: Mutex::new(config),
+            conversations: Mutex::new(HashMap::new()),
+            store: None,
+            transport: None,
+            listener: None,
+            fingerprints: None,
+        }
+    }
+
+    pub fn set_config(&self, config: ChatConfig) {
+        *self.config.lock().unwrap() = config;
+    }
+
+    /// Sets the connection frames to peers go through
+    pub fn set_transport(&mut self, transport: Arc<dyn ChatTransport>) {
+        self.transport = Some(transport);
+    }
+
+    pub fn set_listener(&mut self, listener: ChatListener) {
+        self.listener = Some(listener);
+    }
+
+    /// Where to learn a peer's fingerprint, which keys its disk history
+    pub fn set_fingerprint_source(&mut self, source: FingerprintSource) {
+        self.fingerprints = Some(source);
+    }
+
+    /// Keeps the disk history below `data_dir`; only written with `persist_history`
+    pub fn set_history_dir(&mut self, data_dir: &Path) -> std::io::Result<()> {
+        self.store = Some(ChatHistoryStore::open(data_dir)?);
+        Ok(())
+    }
+
+    /// Sends a message; it comes back as `Sent`, or `Delivered` if the
+    /// receipt was faster. Nothing is recorded if it cannot be sent.
+    pub fn send_message(&self, peer_id: &str, text: &str) -> Result<ChatMessage, ChatError> {
+        validate_text(text)?;
+        let transport = self.transport.as_ref()
+            .ok_or_else(|| ChatError::NotConnected("no transport to peers".to_string()))?;
+
+        let message = ChatMessage {
+            id: Uuid::new_v4().to_string(),
+            peer_id: peer_id.to_string(),
+            direction: ChatDirection::Outgoing,
+            text: text.to_string(),
+            sent_at: Utc::now(),
+            status: DeliveryStatus::Pending,
+        };
+        // Recorded first, so a receipt finds the message
+        self.with_conversation(peer_id, |conversation| conversation.push(message.clone()));
+
+        let frame = ChatFrame::Message(ChatText {
+            protocol_version: PROTOCOL_VERSION,
+            id: message.id.clone(),
+            text: message.text.clone(),
+        });
+        if let Err(e) = transport.send(peer_id, &frame) {
+            self.with_conversation(peer_id, |conversation| conversation.messages.retain(|m| m.id != message.id));
+            return Err(e);
+        }
+
+        Ok(self.update_status(peer_id, &message.id, DeliveryStatus::Sent).unwrap_or(message))
+    }
+
+    /// Handles a frame the peer sent at `sent_at`, already opened from its envelope
+    pub fn receive(&self, peer_id: &str, payload: &str, sent_at: DateTime<Utc>) -> Result<(), ChatError> {
+        match ChatFrame::decode(payload)? {
+            ChatFrame::Message(text) => self.receive_message(peer_id, text, sent_at),
+            ChatFrame::Delivered(receipt) => {
+                if self.update_status(peer_id, &receipt.id, DeliveryStatus::Delivered).is_none() {
+                    log::debug!(target: TARGET_CHAT, "Receipt from {} for unknown message {}", peer_id, receipt.id);
+                }
+                Ok(())
+            },
+            ChatFrame::Typing(notice) => {
+                self.receive_typing(peer_id, notice.typing, Instant::now());
+                Ok(())
+            },
+        }
+    }
+
+    /// Tells the peer that the user started or stopped typing. Returns false
+    /// if the notice was held back by the rate limit.
+    pub fn send_typing(&self, peer_id: &str, typing: bool) -> Result<bool, ChatError> {
+        let transport = self.transport.as_ref()
+            .ok_or_else(|| ChatError::NotConnected("no transport to peers".to_string()))?;
+
+        let now = Instant::now();
+        let allowed = self.with_conversation(peer_id, |conversation| {
+            let allowed = typing_allowed(conversation.typing_sent, typing, now);
+            if allowed {
+                conversation.typing_sent = Some((now, typing));
+            }
+            allowed
+        });
+        if !allowed {
+            return Ok(false);
+        }
+
+        transport.send(peer_id, &ChatFrame::Typing(TypingNotice { typing }))?;
+        Ok(true)
+    }
+
+    /// The last `limit` messages with the peer, oldest first
+    pub fn history(&self, peer_id: &str, limit: Option<usize>) -> Vec<ChatMessage> {
+        let limit = limit.unwrap_or(DEFAULT_HISTORY_LIMIT);
+        self.with_conversation(peer_id, |conversation| {
+            let skip = conversation.messages.len().saturating_sub(limit);
+            conversation.messages.iter().skip(skip).cloned().collect()
+        })
+    }
+
+    /// Drops the conversation of a disconnected peer; the disk history stays
+    pub fn end_session(&self, peer_id: &str) {
+        self.conversations.lock().unwrap().remove(peer_id);
+    }
+
+    fn receive_message(&self, peer_id: &str, text: ChatText, sent_at: DateTime<Utc>) -> Result<(), ChatError> {
+        validate_text(&text.text)?;
+        if text.id.is_empty() || text.id.len() > MAX_ID_LEN {
+            return Err(ProtocolError::Malformed(format!("message ID must have 1 to {} bytes", MAX_ID_LEN)).into());
+        }
+
+        let message = ChatMessage {
+            id: text.id,
+            peer_id: peer_id.to_string(),
+            direction: ChatDirection::Incoming,
+            text: text.text,
+            sent_at,
+            status: DeliveryStatus::Delivered,
+        };
+        let is_new = self.with_conversation(peer_id, |conversation| {
+            if conversation.contains(&message.id) {
+                return false;
+            }
+            self.persist(conversation, &message);
+            conversation.insert_incoming(message.clone());
+            true
+        });
+
+        // A repeated message gets its receipt again, the first one may have been lost
+        if let Some(transport) = &self.transport {
+            if let Err(e) = transport.send(peer_id, &ChatFrame::Delivered(ChatReceipt { id: message.id.clone() })) {
+                log::warn!(target: TARGET_CHAT, "Failed to confirm chat message {} to {}: {}", message.id, peer_id, e);
+            }
+        }
+        if is_new {
+            self.emit(ChatEvent::Message(message));
+        }
+        Ok(())
+    }
+
+    fn receive_typing(&self, peer_id: &str, typing: bool, now: Instant) {
+        let allowed = self.with_conversation(peer_id, |conversation| {
+            let allowed = typing_allowed(conversation.typing_received, typing, now);
+            if allowed {
+                conversation.typing_received = Some((now, typing));
+            }
+            allowed
+        });
+        if allowed {
+            self.emit(ChatEvent::Typing(ChatTyping { peer_id: peer_id.to_string(), typing }));
+        }
+    }
+
+    /// Moves an outgoing message on to `next` and reports the change. Returns
+    /// the message as it is afterwards, None if there is no such message.
+    fn update_status(&self, peer_id: &str, id: &str, next: DeliveryStatus) -> Option<ChatMessage> {
+        let (message, changed) = self.with_conversation(peer_id, |conversation| {
+            let index = conversation.messages.iter()
+                .position(|message| message.id == id && message.direction == ChatDirection::Outgoing)?;
+            let status = conversation.messages[index].status.advance(next);
+            if let Some(status) = status {
+                conversation.messages[index].status = status;
+                let message = conversation.messages[index].clone();
+                self.persist(conversation, &message);
+            }
+            Some((conversation.messages[index].clone(), status.is_some()))
+        })?;
+
+        if changed {
+            self.emit(ChatEvent::Status(ChatStatusChange {
+                peer_id: peer_id.to_string(),
+                id: id.to_string(),
+                status: message.status,
+            }));
+        }
+        Some(message)
+    }
+
+    /// Runs `f` on the peer's conversation, which is created on first use
+    fn with_conversation<R>(&self, peer_id: &str, f: impl FnOnce(&mut Conversation) -> R) -> R {
+        let mut conversations = self.conversations.lock().unwrap();
+        let conversation = conversations.entry(peer_id.to_string()).or_default();
+        self.attach_history(peer_id, conversation);
+        f(conversation)
+    }
+
+    /// Once the peer's fingerprint is known, puts the earlier messages with
+    /// that device in front of the conversation and writes the new ones out
+    fn attach_history(&self, peer_id: &str, conversation: &mut Conversation) {
+        if conversation.fingerprint.is_some() || !self.config.lock().unwrap().persist_history {
+            return;
+        }
+        let (Some(store), Some(fingerprints)) = (&self.store, &self.fingerprints) else {
+            return;
+        };
+        let Some(fingerprint) = fingerprints(peer_id) else {
+            return;
+        };
+
+        let earlier = match store.load(&fingerprint, MAX_SESSION_MESSAGES) {
+            Ok(earlier) => earlier,
+            Err(e) => {
+                log::error!(target: TARGET_CHAT, "Failed to load chat history of {}: {}", peer_id, e);
+                Vec::new()
+            },
+        };
+        for message in conversation.messages.iter().filter(|m| m.status != DeliveryStatus::Pending) {
+            if let Err(e) = store.append(&fingerprint, message) {
+                log::error!(target: TARGET_CHAT, "Failed to write chat history of {}: {}", peer_id, e);
+            }
+        }
+
+        // The peer ID belongs to the connection, the history to the device
+        let current = std::mem::take(&mut conversation.messages);
+        let mut messages: VecDeque<ChatMessage> = earlier.into_iter()
+            .filter(|message| !current.iter().any(|m| m.id == message.id))
+            .map(|message| ChatMessage { peer_id: peer_id.to_string(), ..message })
+            .collect();
+        messages.extend(current);
+        conversation.messages = messages;
+        conversation.trim();
+        conversation.fingerprint = Some(fingerprint);
+    }
+
+    fn persist(&self, conversation: &Conversation, message: &ChatMessage) {
+        let (Some(store), Some(fingerprint)) = (&self.store, &conversation.fingerprint) else {
+            return;
+        };
+        if !self.config.lock().unwrap().persist_history {
+            return;
+        }
+        if let Err(e) = store.append(fingerprint, message) {
+            log::error!(target: TARGET_CHAT, "Failed to write chat history of {}: {}", message.peer_id, e);
+        }
+    }
+
+    fn emit(&self, event: ChatEvent) {
+        if let Some(listener) = &self.listener {
+            listener(&event);
+        }
+    }
+}
+
+fn validate_text(text: &str) -> Result<(), ChatError> {
+    if text.trim().is_empty() {
+        return Err(ChatError::EmptyMessage);
+    }
+    if text.len() > MAX_MESSAGE_BYTES {
+        return Err(ChatError::MessageTooLarge(text.len()));
+    }
+    Ok(())
+}
+
+/// Whether a typing notice passes the rate limit: a start at most once per
+/// `TYPING_INTERVAL` after the last notice, a stop only after a start
+fn typing_allowed(last: Option<(Instant, bool)>, typing: bool, now: Instant) -> bool {
+    match last {
+        None => typing,
+        Some((at, _)) if typing => now.duration_since(at) >= TYPING_INTERVAL,
+        Some((_, was_typing)) => was_typing,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records the frames instead of sealing them; fails while `offline` is set
+    #[derive(Default)]
+    struct RecordingTransport {
+        frames: Mutex<Vec<(String, ChatFrame)>>,
+        offline: std::sync::atomic::AtomicBool,
+    }
+
+    impl ChatTransport for RecordingTransport {
+        fn send(&self, peer_id: &str, frame: &ChatFrame) -> Result<(), ChatError> {
+            if self.offline.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(ChatError::NotConnected(format!("no key for {}", peer_id)));
+            }
+            self.frames.lock().unwrap().push((peer_id.to_string(), frame.clone()));
+            Ok(())
+        }
+    }
+
+    impl RecordingTransport {
+        fn take(&self) -> Vec<(String, ChatFrame)> {
+            std::mem::take(&mut *self.frames.lock().unwrap())
+        }
+    }
+
+    fn manager(config: ChatConfig) -> (ChatManager, Arc<RecordingTransport>, Arc<Mutex<Vec<ChatEvent>>>) {
+        let transport = Arc::new(RecordingTransport::default());
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let mut manager = ChatManager::new(config);
+        manager.set_transport(transport.clone());
+        let recorded = events.clone();
+        manager.set_listener(Box::new(move |event: &ChatEvent| recorded.lock().unwrap().push(event.clone())));
+        (manager, transport, events)
+    }
+
+    /// Hands the frames `from` sent to `to`, as the peer `sender` would see them
+    fn deliver(from: &RecordingTransport, to: &ChatManager, sender: &str) {
+        for (_, frame) in from.take() {
+            let payload = serde_json::to_string(&frame).unwrap();
+            to.receive(sender, &payload, Utc::now()).unwrap();
+        }
+    }
+
+    fn incoming(id: &str, text: &str) -> String {
+        serde_json::to_string(&ChatFrame::Message(ChatText {
+            protocol_version: PROTOCOL_VERSION,
+            id: id.to_string(),
+            text: text.to_string(),
+        })).unwrap()
+    }
+
+    #[test]
+    fn test_messages_are_confirmed_once_delivered() {
+        let (host, host_transport, host_events) = manager(ChatConfig::default());
+        let (viewer, viewer_transport, viewer_events) = manager(ChatConfig::default());
+
+        let sent = host.send_message("viewer", "Can you see my screen?").unwrap();
+        assert_eq!(sent.status, DeliveryStatus::Sent);
+
+        deliver(&host_transport, &viewer, "host");
+        let received = viewer.history("host", None);
+        assert_eq!(received.len(), 1);
+        assert_eq!((received[0].direction, received[0].status), (ChatDirection::Incoming, DeliveryStatus::Delivered));
+        assert!(matches!(&viewer_events.lock().unwrap()[0], ChatEvent::Message(message) if message.id == sent.id));
+
+        // The receipt advances the message on the host, a repeated one changes nothing
+        let receipt = viewer_transport.take();
+        assert!(matches!(&receipt[..], [(peer, ChatFrame::Delivered(ChatReceipt { id }))] if peer == "host" && *id == sent.id));
+        for (_, frame) in receipt.iter().chain(receipt.iter()) {
+            host.receive("viewer", &serde_json::to_string(frame).unwrap(), Utc::now()).unwrap();
+        }
+        assert_eq!(host.history("viewer", None)[0].status, DeliveryStatus::Delivered);
+        let statuses: Vec<DeliveryStatus> = host_events.lock().unwrap().iter()
+            .filter_map(|event| match event {
+                ChatEvent::Status(change) => Some(change.status),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(statuses, vec![DeliveryStatus::Sent, DeliveryStatus::Delivered]);
+    }
+
+    #[test]
+    fn test_repeated_messages_are_confirmed_but_kept_once() {
+        let (viewer, viewer_transport, viewer_events) = manager(ChatConfig::default());
+
+        viewer.receive("host", &incoming("m1", "hello"), Utc::now()).unwrap();
+        viewer.receive("host", &incoming("m1", "hello"), Utc::now()).unwrap();
+
+        assert_eq!(viewer.history("host", None).len(), 1);
+        assert_eq!(viewer_events.lock().unwrap().len(), 1);
+        assert_eq!(viewer_transport.take().len(), 2);
+    }
+
+    #[test]
+    fn test_unsendable_messages_are_not_recorded() {
+        let (host, host_transport, _) = manager(ChatConfig::default());
+        host_transport.offline.store(true, std::sync::atomic::Ordering::SeqCst);
+
+        assert!(matches!(host.send_message("viewer", "hello"), Err(ChatError::NotConnected(_))));
+        assert!(host.history("viewer", None).is_empty());
+    }
+
+    #[test]
+    fn test_message_size_is_capped() {
+        let (host, _, _) = manager(ChatConfig::default());
+
+        assert!(host.send_message("viewer", &"a".repeat(MAX_MESSAGE_BYTES)).is_ok());
+        // Bytes count, not characters
+        let too_large = "ä".repeat(MAX_MESSAGE_BYTES / 2 + 1);
+        assert_eq!(host.send_message("viewer", &too_large), Err(ChatError::MessageTooLarge(MAX_MESSAGE_BYTES + 2)));
+        assert_eq!(host.send_message("viewer", "  \n"), Err(ChatError::EmptyMessage));
+
+        let result = host.receive("viewer", &incoming("big", &too_large), Utc::now());
+        assert!(matches!(result, Err(ChatError::MessageTooLarge(_))));
+        assert_eq!(host.history("viewer", None).len(), 1);
+    }
+
+    #[test]
+    fn test_overtaken_messages_are_put_back_in_send_order() {
+        let (host, _, _) = manager(ChatConfig::default());
+        let start = Utc::now();
+        let at = |secs: i64| start + chrono::Duration::seconds(secs);
+
+        host.receive("viewer", &incoming("first", "1"), at(1)).unwrap();
+        host.send_message("viewer", "reply").unwrap();
+        host.receive("viewer", &incoming("third", "3"), at(3)).unwrap();
+        // Sent before "third" but arrived after it
+        host.receive("viewer", &incoming("second", "2"), at(2)).unwrap();
+        // Sent before the reply was written, yet never moves ahead of it
+        host.receive("viewer", &incoming("zeroth", "0"), at(0)).unwrap();
+
+        let texts: Vec<String> = host.history("viewer", None).into_iter().map(|message| message.text).collect();
+        assert_eq!(texts, vec!["1", "reply", "0", "2", "3"]);
+        assert_eq!(host.history("viewer", Some(2)).len(), 2);
+        assert_eq!(host.history("viewer", Some(2))[0].text, "2");
+    }
+
+    #[test]
+    fn test_typing_notices_are_rate_limited() {
+        let start = Instant::now();
+        let after = |millis: u64| start + Duration::from_millis(millis);
+
+        assert!(!typing_allowed(None, false, start));
+        assert!(typing_allowed(None, true, start));
+        assert!(!typing_allowed(Some((start, true)), true, after(500)));
+        assert!(typing_allowed(Some((start, true)), true, after(1000)));
+        assert!(typing_allowed(Some((start, true)), false, after(100)));
+        assert!(!typing_allowed(Some((start, false)), false, after(2000)));
+        assert!(!typing_allowed(Some((start, false)), true, after(100)));
+        assert!(typing_allowed(Some((start, false)), true, after(1000)));
+
+        let (host, host_transport, _) = manager(ChatConfig::default());
+        assert_eq!(host.send_typing("viewer", true), Ok(true));
+        assert_eq!(host.send_typing("viewer", true), Ok(false));
+        assert_eq!(host.send_typing("viewer", false), Ok(true));
+        assert_eq!(host_transport.take().len(), 2);
+    }
+
+    #[test]
+    fn test_history_follows_the_device_across_connections() {
+        let data_dir = std::env::temp_dir().join(format!("smoldesk-chat-{}", Uuid::new_v4()));
+        let (mut host, host_transport, _) = manager(ChatConfig { persist_history: true });
+        host.set_history_dir(&data_dir).unwrap();
+        host.set_fingerprint_source(Box::new(|peer_id: &str| {
+            peer_id.starts_with("laptop").then(|| "SHA256:laptop".to_string())
+        }));
+
+        let sent = host.send_message("laptop-1", "before").unwrap();
+        host.receive("laptop-1", &serde_json::to_string(&ChatFrame::Delivered(ChatReceipt { id: sent.id })).unwrap(), Utc::now()).unwrap();
+        host.send_message("phone-1", "not persisted without a fingerprint").unwrap();
+        host.end_session("laptop-1");
+        host.end_session("phone-1");
+        host_transport.take();
+
+        // Same device, new connection
+        host.receive("laptop-2", &incoming("m2", "after"), Utc::now()).unwrap();
+        let history = host.history("laptop-2", None);
+        let summary: Vec<(&str, &str, DeliveryStatus)> = history.iter()
+            .map(|message| (message.peer_id.as_str(), message.text.as_str(), message.status))
+            .collect();
+        assert_eq!(summary, vec![
+            ("laptop-2", "before", DeliveryStatus::Delivered),
+            ("laptop-2", "after", DeliveryStatus::Delivered),
+        ]);
+        assert!(host.history("phone-2", None).is_empty());
+
+        std::fs::remove_dir_all(&data_dir).unwrap();
+    }
+}
@@ -0,0 +1,188 @@
+// src-tauri/src/chat/types.rs - Chat messages, their delivery state and the wire frames
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::protocol::{self, ProtocolError, WireSubsystem};
+
+/// Largest message text in bytes (UTF-8)
+pub const MAX_MESSAGE_BYTES: usize = 4096;
+
+/// Settings section `chat`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatConfig {
+    /// Keep messages on disk per peer fingerprint, so a peer that connects
+    /// again finds the earlier conversation. Off: messages live as long as
+    /// the connection.
+    pub persist_history: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChatDirection {
+    Outgoing,
+    Incoming,
+}
+
+/// How far an outgoing message got; incoming messages are always `Delivered`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeliveryStatus {
+    /// Recorded, not yet handed to the connection
+    Pending,
+    /// Sealed and handed to the connection
+    Sent,
+    /// The peer confirmed receipt
+    Delivered,
+}
+
+impl DeliveryStatus {
+    /// Status after `next`, or None if the message cannot get there from
+    /// here. A receipt may overtake the local send confirmation, so a pending
+    /// message can become delivered directly; nothing ever goes back.
+    pub fn advance(self, next: DeliveryStatus) -> Option<DeliveryStatus> {
+        match (self, next) {
+            (DeliveryStatus::Pending, DeliveryStatus::Sent)
+            | (DeliveryStatus::Pending, DeliveryStatus::Delivered)
+            | (DeliveryStatus::Sent, DeliveryStatus::Delivered) => Some(next),
+            _ => None,
+        }
+    }
+}
+
+/// A message of the conversation with one peer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub id: String,
+    pub peer_id: String,
+    pub direction: ChatDirection,
+    pub text: String,
+    /// On the sender's clock; for incoming messages authenticated with the envelope
+    pub sent_at: DateTime<Utc>,
+    pub status: DeliveryStatus,
+}
+
+/// Emitted as `chat_status` when an outgoing message advances
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChatStatusChange {
+    pub peer_id: String,
+    pub id: String,
+    pub status: DeliveryStatus,
+}
+
+/// Emitted as `chat_typing`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChatTyping {
+    pub peer_id: String,
+    pub typing: bool,
+}
+
+/// Events for the UI
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ChatEvent {
+    Message(ChatMessage),
+    Status(ChatStatusChange),
+    Typing(ChatTyping),
+}
+
+impl ChatEvent {
+    /// Name of the Tauri event the frontend listens to
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            ChatEvent::Message(_) => "chat_message",
+            ChatEvent::Status(_) => "chat_status",
+            ChatEvent::Typing(_) => "chat_typing",
+        }
+    }
+}
+
+/// Message text on the wire
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatText {
+    pub protocol_version: u32,
+    pub id: String,
+    pub text: String,
+}
+
+/// Confirms receipt of a message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatReceipt {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypingNotice {
+    pub typing: bool,
+}
+
+/// Everything the chat sends to a peer, sealed as a whole
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "payload", rename_all = "camelCase")]
+pub enum ChatFrame {
+    Message(ChatText),
+    Delivered(ChatReceipt),
+    Typing(TypingNotice),
+}
+
+impl ChatFrame {
+    /// Decodes a frame of the peer; message texts are checked for their
+    /// protocol version before their fields
+    pub fn decode(json: &str) -> Result<Self, ProtocolError> {
+        let value: Value = serde_json::from_str(json).map_err(|e| ProtocolError::Malformed(e.to_string()))?;
+        if value.get("type").and_then(Value::as_str) == Some("message") {
+            let payload = value.get("payload").unwrap_or(&Value::Null);
+            protocol::negotiate(WireSubsystem::Chat, protocol::version_of(payload))?;
+        }
+        serde_json::from_value(value).map_err(|e| ProtocolError::Malformed(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::protocol::PROTOCOL_VERSION;
+
+    #[test]
+    fn test_frame_wire_snapshot() {
+        let frame = ChatFrame::Message(ChatText {
+            protocol_version: PROTOCOL_VERSION,
+            id: "m1".to_string(),
+            text: "hello".to_string(),
+        });
+        let value = serde_json::to_value(&frame).unwrap();
+        assert_eq!(value, json!({
+            "type": "message",
+            "payload": { "protocolVersion": PROTOCOL_VERSION, "id": "m1", "text": "hello" },
+        }));
+        assert_eq!(ChatFrame::decode(&value.to_string()).unwrap(), frame);
+
+        let receipt = ChatFrame::Delivered(ChatReceipt { id: "m1".to_string() });
+        assert_eq!(serde_json::to_value(&receipt).unwrap(), json!({ "type": "delivered", "payload": { "id": "m1" } }));
+
+        // A message without a version is rejected before its fields
+        assert!(matches!(
+            ChatFrame::decode(r#"{"type":"message","payload":{"id":"m1"}}"#),
+            Err(ProtocolError::VersionMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_delivery_status_only_moves_forward() {
+        use DeliveryStatus::*;
+
+        assert_eq!(Pending.advance(Sent), Some(Sent));
+        assert_eq!(Pending.advance(Delivered), Some(Delivered));
+        assert_eq!(Sent.advance(Delivered), Some(Delivered));
+
+        assert_eq!(Sent.advance(Sent), None);
+        assert_eq!(Delivered.advance(Sent), None);
+        assert_eq!(Delivered.advance(Delivered), None);
+        assert_eq!(Sent.advance(Pending), None);
+    }
+}
@@ -0,0 +1,179 @@
+// src-tauri/src/cli.rs - Subcommands that script a running instance
+//
+// `smoldesk` without a subcommand starts the app, `smoldesk --safe-mode` starts
+// it without any remote control (see `safe_mode`). With one, the binary sends a
+// single request over the control socket (see `control_socket`), prints the
+// result as JSON on stdout and exits: 0 on success, 1 if the app refused the
+// command (the `CommandError` goes to stderr), 3 if no instance is reachable.
+
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use serde_json::{json, Value};
+
+use crate::command_error::{CommandError, ErrorKind};
+use crate::control_socket::{self, ControlCommand};
+
+/// Exit code when the command reached the app but failed there
+const EXIT_COMMAND_FAILED: i32 = 1;
+
+/// Exit code when no running instance answered; clap itself uses 2 for usage errors
+const EXIT_NOT_RUNNING: i32 = 3;
+
+#[derive(Debug, Parser)]
+#[command(name = "smoldesk", version, about = "WebRTC remote desktop for Linux")]
+struct Cli {
+    /// Start without input forwarding, clipboard sync and file transfer
+    #[arg(long)]
+    safe_mode: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// What `main` does after parsing the command line
+#[derive(Debug)]
+pub enum Invocation {
+    Start { safe_mode: bool },
+    /// A subcommand ran; the process exits with this code
+    Exit(i32),
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Send a file to a connected peer
+    SendFile {
+        path: PathBuf,
+        #[arg(long)]
+        peer: String,
+    },
+    /// Change the clipboard of the running instance
+    Clipboard {
+        #[command(subcommand)]
+        action: ClipboardAction,
+    },
+    /// Print connection and capture state as JSON
+    Status,
+    /// Inspect file transfers
+    Transfers {
+        #[command(subcommand)]
+        action: TransfersAction,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ClipboardAction {
+    /// Set the clipboard text; it syncs to peers like a local copy
+    Set {
+        #[arg(long)]
+        text: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum TransfersAction {
+    /// List queued and running transfers
+    List,
+}
+
+/// Runs a subcommand against the running instance and returns its exit code.
+/// Without a subcommand the caller starts the app.
+pub fn run() -> Invocation {
+    let cli = Cli::parse();
+    match cli.command {
+        Some(command) => Invocation::Exit(execute(command)),
+        None => Invocation::Start { safe_mode: cli.safe_mode },
+    }
+}
+
+fn execute(command: Command) -> i32 {
+    let command = match to_control_command(command) {
+        Ok(command) => command,
+        Err(error) => {
+            print_error(&json!(error));
+            return EXIT_COMMAND_FAILED;
+        }
+    };
+
+    let response = match control_socket::send(&control_socket::default_dir(), command) {
+        Ok(response) => response,
+        Err(e) => {
+            print_error(&json!(CommandError::new(
+                ErrorKind::ControlNotRunning,
+                format!("No running SmolDesk instance answered: {}", e),
+            )));
+            return EXIT_NOT_RUNNING;
+        }
+    };
+
+    if response["ok"] == json!(true) {
+        println!("{}", serde_json::to_string_pretty(&response["result"]).unwrap_or_default());
+        0
+    } else {
+        print_error(&response["error"]);
+        EXIT_COMMAND_FAILED
+    }
+}
+
+fn to_control_command(command: Command) -> Result<ControlCommand, CommandError> {
+    Ok(match command {
+        // The app runs in another working directory
+        Command::SendFile { path, peer } => {
+            let path = path.canonicalize().map_err(|e| CommandError::new(
+                ErrorKind::TransferFileNotFound,
+                format!("{}: {}", path.display(), e),
+            ))?;
+            ControlCommand::SendFile { path, peer_id: peer }
+        },
+        Command::Clipboard { action: ClipboardAction::Set { text } } => ControlCommand::SetClipboardText { text },
+        Command::Status => ControlCommand::Status,
+        Command::Transfers { action: TransfersAction::List } => ControlCommand::ListTransfers,
+    })
+}
+
+fn print_error(error: &Value) {
+    eprintln!("{}", serde_json::to_string_pretty(error).unwrap_or_default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Option<ControlCommand> {
+        let command = Cli::try_parse_from(args).unwrap().command?;
+        Some(to_control_command(command).unwrap())
+    }
+
+    #[test]
+    fn test_subcommands_map_to_control_commands() {
+        assert_eq!(parse(&["smoldesk"]), None);
+        assert_eq!(parse(&["smoldesk", "status"]), Some(ControlCommand::Status));
+        assert_eq!(parse(&["smoldesk", "transfers", "list"]), Some(ControlCommand::ListTransfers));
+        assert_eq!(
+            parse(&["smoldesk", "clipboard", "set", "--text", "hello"]),
+            Some(ControlCommand::SetClipboardText { text: "hello".to_string() })
+        );
+
+        let file = std::env::temp_dir().join(format!("smoldesk-cli-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&file, b"data").unwrap();
+        assert_eq!(
+            parse(&["smoldesk", "send-file", file.to_str().unwrap(), "--peer", "viewer"]),
+            Some(ControlCommand::SendFile { path: file.canonicalize().unwrap(), peer_id: "viewer".to_string() })
+        );
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn test_safe_mode_flag() {
+        assert!(Cli::try_parse_from(["smoldesk", "--safe-mode"]).unwrap().safe_mode);
+        assert!(!Cli::try_parse_from(["smoldesk"]).unwrap().safe_mode);
+    }
+
+    #[test]
+    fn test_missing_file_and_peer_are_rejected_before_connecting() {
+        assert!(Cli::try_parse_from(["smoldesk", "send-file", "/tmp/x"]).is_err());
+
+        let command = Cli::try_parse_from(["smoldesk", "send-file", "/nonexistent/smoldesk", "--peer", "viewer"])
+            .unwrap().command.unwrap();
+        assert_eq!(to_control_command(command).unwrap_err().kind, ErrorKind::TransferFileNotFound);
+    }
+}
@@ -0,0 +1,428 @@
+// src-tauri/src/clipboard/delta.rs - Große Texte als Änderung gegenüber dem zuletzt gesendeten Eintrag
+//
+// Wer nacheinander Fassungen desselben großen Dokuments kopiert, würde jedes
+// Mal den ganzen Text übertragen. Ist ein Text über `delta_sync_min_size` dem
+// zuletzt an denselben Peer gesendeten ähnlich genug, geht stattdessen ein
+// `TextDelta`-Eintrag hinaus: die ID des Basiseintrags und eine Liste von
+// Kopier- und Einfügeoperationen. Der Empfänger setzt den Text aus seinem
+// zuletzt von diesem Peer empfangenen Eintrag zusammen und prüft ihn per
+// SHA-256; passt etwas nicht, fordert er den Eintrag vollständig an.
+//
+// Der Vergleich läuft zeilenweise in einem eigenen Thread und wird nach
+// `DIFF_TIMEOUT` abgebrochen, damit auch ungünstige Eingaben den
+// Überwachungs-Thread nicht aufhalten; dann wird vollständig gesendet.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::clipboard::error::ClipboardError;
+use crate::clipboard::types::{ClipboardContentType, ClipboardEntry, ClipboardMetadata};
+use crate::logging::TARGET_CLIPBOARD;
+
+/// Längste Wartezeit des Senders auf den Vergleich
+const DIFF_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Gleiche Zeilen, die je Inhalt als Kopierquelle infrage kommen; begrenzt
+/// den Aufwand bei Texten aus lauter gleichen Zeilen
+const MAX_CANDIDATES: usize = 16;
+
+/// Alle so viele Zeilen prüft der Vergleich, ob er abgebrochen wurde
+const CANCEL_CHECK_INTERVAL: usize = 1024;
+
+/// Eine Operation beim Zusammensetzen des neuen Texts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+pub enum DeltaOp {
+    /// Die Bytes `start..start + len` des Basistexts übernehmen
+    Copy { start: usize, len: usize },
+
+    /// Neuen Text einfügen
+    Insert { text: String },
+}
+
+/// Inhalt eines `TextDelta`-Eintrags (als JSON in `ClipboardEntry::data`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextDelta {
+    /// Eintrag, aus dem kopiert wird
+    pub base_id: String,
+
+    /// Art des Ergebnisses (`Text` oder `Html`)
+    pub content_type: ClipboardContentType,
+
+    pub ops: Vec<DeltaOp>,
+
+    /// SHA-256 des zusammengesetzten Texts (hex)
+    pub sha256: String,
+}
+
+impl TextDelta {
+    /// Liest das Delta aus einem `TextDelta`-Eintrag
+    pub fn from_entry(entry: &ClipboardEntry) -> Result<Self, ClipboardError> {
+        if entry.content_type != ClipboardContentType::TextDelta {
+            return Err(ClipboardError::InvalidFormat(format!("Entry {} is not a text delta", entry.id)));
+        }
+        serde_json::from_str(&entry.data)
+            .map_err(|e| ClipboardError::SerializationError(e.to_string()))
+    }
+
+    /// Setzt den Text aus `base` zusammen; höchstens `max_len` Bytes
+    pub fn apply(&self, base: &str, max_len: usize) -> Result<String, ClipboardError> {
+        let mut text = String::new();
+        for op in &self.ops {
+            let part = match op {
+                DeltaOp::Copy { start, len } => start.checked_add(*len)
+                    .and_then(|end| base.get(*start..end))
+                    .ok_or_else(|| ClipboardError::DeltaMismatch(format!("Copy of {}+{} is outside the base text", start, len)))?,
+                DeltaOp::Insert { text } => text.as_str(),
+            };
+            if text.len() + part.len() > max_len {
+                return Err(ClipboardError::ContentTooLarge(text.len() + part.len(), max_len));
+            }
+            text.push_str(part);
+        }
+
+        if sha256_hex(&text) != self.sha256 {
+            return Err(ClipboardError::DeltaMismatch(format!("Text rebuilt from {} has the wrong hash", self.base_id)));
+        }
+        Ok(text)
+    }
+}
+
+/// Letzte Texteinträge je Peer, zwischen denen Deltas gebildet werden.
+/// `None` steht für Einträge, die ohne Peer-ID an alle gehen bzw. kommen.
+pub struct DeltaSync {
+    /// Mindestgröße und -ähnlichkeit aus der Konfiguration
+    settings: Mutex<(usize, f32)>,
+
+    /// Zuletzt an einen Peer gesendeter Texteintrag
+    sent: Mutex<HashMap<Option<String>, ClipboardEntry>>,
+
+    /// Zuletzt von einem Peer empfangener Texteintrag
+    received: Mutex<HashMap<Option<String>, ClipboardEntry>>,
+}
+
+impl DeltaSync {
+    pub fn new(min_size: usize, similarity: f32) -> Self {
+        DeltaSync {
+            settings: Mutex::new((min_size, similarity)),
+            sent: Mutex::new(HashMap::new()),
+            received: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn configure(&self, min_size: usize, similarity: f32) {
+        *self.settings.lock().unwrap() = (min_size, similarity);
+    }
+
+    /// Kodiert einen lokalen Eintrag für einen Peer, als Delta zum zuletzt an
+    /// ihn gesendeten Text, wenn sich das lohnt, sonst vollständig
+    pub fn encode_for(&self, peer_id: Option<&str>, entry: &ClipboardEntry) -> Result<String, ClipboardError> {
+        let key = peer_id.map(str::to_string);
+        if !is_text(entry) {
+            return super::encode_sync_entry(entry);
+        }
+
+        let base = self.sent.lock().unwrap().insert(key, entry.clone());
+        let delta = base.and_then(|base| self.delta(&base, entry));
+        match delta {
+            Some(delta) => super::encode_sync_entry(&delta_entry(entry, &delta)?),
+            None => super::encode_sync_entry(entry),
+        }
+    }
+
+    /// Zuletzt an einen Peer gesendeter Eintrag mit dieser ID, für eine
+    /// vollständige Neusendung
+    pub fn sent_entry(&self, peer_id: Option<&str>, entry_id: &str) -> Option<ClipboardEntry> {
+        self.sent.lock().unwrap()
+            .get(&peer_id.map(str::to_string))
+            .filter(|entry| entry.id == entry_id)
+            .cloned()
+    }
+    
+    /// Vermerkt einen vollständig gesendeten Eintrag als Basis für die
+    /// nächsten Deltas an den Peer
+    pub fn record_sent(&self, peer_id: Option<&str>, entry: &ClipboardEntry) {
+        if is_text(entry) {
+            self.sent.lock().unwrap().insert(peer_id.map(str::to_string), entry.clone());
+        }
+    }
+
+    /// Setzt einen empfangenen `TextDelta`-Eintrag zusammen; andere Einträge
+    /// kommen unverändert zurück. Texte werden als Basis für die nächsten
+    /// Deltas des Peers vermerkt. `DeltaMismatch`: der Peer muss den Eintrag
+    /// vollständig senden.
+    pub fn resolve(&self, peer_id: Option<&str>, entry: ClipboardEntry, max_len: usize) -> Result<ClipboardEntry, ClipboardError> {
+        let key = peer_id.map(str::to_string);
+        let entry = if entry.content_type == ClipboardContentType::TextDelta {
+            let delta = TextDelta::from_entry(&entry)?;
+            let base = self.received.lock().unwrap().get(&key)
+                .filter(|base| base.id == delta.base_id)
+                .map(|base| base.data.clone())
+                .ok_or_else(|| ClipboardError::DeltaMismatch(format!("Base entry {} is unknown", delta.base_id)))?;
+
+            let text = delta.apply(&base, max_len)?;
+            ClipboardEntry {
+                content_type: delta.content_type,
+                metadata: ClipboardMetadata { size: text.len(), ..entry.metadata },
+                data: text,
+                ..entry
+            }
+        } else {
+            entry
+        };
+
+        if is_text(&entry) {
+            self.received.lock().unwrap().insert(key, entry.clone());
+        }
+        Ok(entry)
+    }
+
+    /// Delta von `base` zu `entry`, wenn es die Einstellungen erfüllt
+    fn delta(&self, base: &ClipboardEntry, entry: &ClipboardEntry) -> Option<TextDelta> {
+        let (min_size, similarity) = *self.settings.lock().unwrap();
+        if similarity <= 0.0 || entry.data.len() < min_size || base.content_type != entry.content_type {
+            return None;
+        }
+
+        let ops = match diff_with_timeout(base.data.clone(), entry.data.clone(), DIFF_TIMEOUT) {
+            Some(ops) => ops,
+            None => {
+                log::debug!(target: TARGET_CLIPBOARD, "Diff of clipboard entry {} timed out, sending it in full", entry.id);
+                return None;
+            },
+        };
+
+        let copied: usize = ops.iter()
+            .map(|op| match op {
+                DeltaOp::Copy { len, .. } => *len,
+                DeltaOp::Insert { .. } => 0,
+            })
+            .sum();
+        if (copied as f64) < similarity as f64 * entry.data.len() as f64 {
+            return None;
+        }
+
+        Some(TextDelta {
+            base_id: base.id.clone(),
+            content_type: entry.content_type.clone(),
+            ops,
+            sha256: sha256_hex(&entry.data),
+        })
+    }
+}
+
+/// Nur reine Texte und HTML werden als Delta übertragen; Vorschauen zu großer
+/// Inhalte werden ohnehin nicht synchronisiert
+fn is_text(entry: &ClipboardEntry) -> bool {
+    matches!(entry.content_type, ClipboardContentType::Text | ClipboardContentType::Html) && !entry.metadata.truncated
+}
+
+/// Der `TextDelta`-Eintrag, der statt `entry` gesendet wird
+fn delta_entry(entry: &ClipboardEntry, delta: &TextDelta) -> Result<ClipboardEntry, ClipboardError> {
+    let data = serde_json::to_string(delta)
+        .map_err(|e| ClipboardError::SerializationError(e.to_string()))?;
+    Ok(ClipboardEntry {
+        content_type: ClipboardContentType::TextDelta,
+        data,
+        ..entry.clone()
+    })
+}
+
+fn sha256_hex(text: &str) -> String {
+    Sha256::digest(text.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Vergleicht in einem eigenen Thread; None, wenn das länger als `timeout` dauert
+fn diff_with_timeout(base: String, new: String, timeout: Duration) -> Option<Vec<DeltaOp>> {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel();
+
+    let worker_cancelled = cancelled.clone();
+    thread::spawn(move || {
+        let _ = sender.send(diff(&base, &new, &worker_cancelled));
+    });
+
+    let ops = receiver.recv_timeout(timeout).ok().flatten();
+    // Ein abgehängter Vergleich hört beim nächsten Prüfpunkt auf
+    cancelled.store(true, Ordering::SeqCst);
+    ops
+}
+
+/// Zeilenweiser Vergleich: Jede Zeile des neuen Texts wird, wenn möglich, aus
+/// dem Basistext kopiert (bevorzugt aus der Zeile nach der zuletzt kopierten),
+/// sonst eingefügt. Aufeinanderfolgende Kopien werden zusammengefasst.
+fn diff(base: &str, new: &str, cancelled: &AtomicBool) -> Option<Vec<DeltaOp>> {
+    let base_lines: Vec<(usize, &str)> = lines(base).collect();
+    let mut candidates: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (number, (_, line)) in base_lines.iter().enumerate() {
+        let numbers = candidates.entry(*line).or_default();
+        if numbers.len() < MAX_CANDIDATES {
+            numbers.push(number);
+        }
+    }
+
+    let mut ops = Vec::new();
+    let mut inserted = String::new();
+    let mut next_line = 0;
+    for (count, (_, line)) in lines(new).enumerate() {
+        if count % CANCEL_CHECK_INTERVAL == 0 && cancelled.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        let matched = if base_lines.get(next_line).is_some_and(|(_, base_line)| *base_line == line) {
+            Some(next_line)
+        } else {
+            candidates.get(line).and_then(|numbers| {
+                numbers.iter().find(|number| **number >= next_line).or_else(|| numbers.first()).copied()
+            })
+        };
+
+        match matched {
+            Some(number) => {
+                if !inserted.is_empty() {
+                    ops.push(DeltaOp::Insert { text: std::mem::take(&mut inserted) });
+                }
+                let start = base_lines[number].0;
+                match ops.last_mut() {
+                    Some(DeltaOp::Copy { start: previous, len }) if *previous + *len == start => *len += line.len(),
+                    _ => ops.push(DeltaOp::Copy { start, len: line.len() }),
+                }
+                next_line = number + 1;
+            },
+            None => inserted.push_str(line),
+        }
+    }
+    if !inserted.is_empty() {
+        ops.push(DeltaOp::Insert { text: inserted });
+    }
+
+    Some(ops)
+}
+
+/// Zeilen samt Zeilenende und ihrem Byte-Offset
+fn lines(text: &str) -> impl Iterator<Item = (usize, &str)> + '_ {
+    text.split_inclusive('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len();
+        Some((start, line))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::decode_sync_entry;
+
+    fn text_entry(id: &str, data: &str) -> ClipboardEntry {
+        ClipboardEntry {
+            id: id.to_string(),
+            content_type: ClipboardContentType::Text,
+            data: data.to_string(),
+            metadata: ClipboardMetadata {
+                size: data.len(),
+                mime_type: "text/plain".to_string(),
+                source: "local".to_string(),
+                truncated: false,
+                origin: None,
+            },
+            timestamp: chrono::Utc::now(),
+            pinned: false,
+        }
+    }
+
+    fn document(lines: usize, edited: Option<usize>) -> String {
+        (0..lines)
+            .map(|i| if Some(i) == edited { format!("Zeile {} wurde geändert\n", i) } else { format!("Zeile {} des Dokuments\n", i) })
+            .collect()
+    }
+
+    #[test]
+    fn test_small_edit_is_sent_as_delta_and_rebuilt() {
+        let sender = DeltaSync::new(1024, 0.8);
+        let receiver = DeltaSync::new(1024, 0.8);
+        let first = text_entry("v1", &document(2000, None));
+        let second = text_entry("v2", &document(2000, Some(1000)));
+
+        // Die erste Fassung geht vollständig hinaus
+        let payload = sender.encode_for(Some("peer-1"), &first).unwrap();
+        let received = receiver.resolve(Some("host"), decode_sync_entry(&payload).unwrap(), usize::MAX).unwrap();
+        assert_eq!(received.data, first.data);
+
+        let payload = sender.encode_for(Some("peer-1"), &second).unwrap();
+        assert!(payload.len() < second.data.len() / 10, "delta is {} bytes", payload.len());
+        let wire = decode_sync_entry(&payload).unwrap();
+        assert_eq!(wire.content_type, ClipboardContentType::TextDelta);
+        assert_eq!(TextDelta::from_entry(&wire).unwrap().base_id, "v1");
+
+        let rebuilt = receiver.resolve(Some("host"), wire, usize::MAX).unwrap();
+        assert_eq!(rebuilt.id, "v2");
+        assert_eq!(rebuilt.content_type, ClipboardContentType::Text);
+        assert_eq!(rebuilt.data, second.data);
+        assert_eq!(rebuilt.metadata.size, second.data.len());
+
+        // Ein anderer Peer hat die erste Fassung nie bekommen
+        let payload = sender.encode_for(Some("peer-2"), &second).unwrap();
+        assert_eq!(decode_sync_entry(&payload).unwrap().content_type, ClipboardContentType::Text);
+    }
+
+    #[test]
+    fn test_dissimilar_small_or_disabled_entries_are_sent_in_full() {
+        let sender = DeltaSync::new(1024, 0.8);
+        sender.encode_for(None, &text_entry("a", &document(2000, None))).unwrap();
+        let unrelated: String = (0..2000).map(|i| format!("ganz anderer Inhalt {}\n", i)).collect();
+        let payload = sender.encode_for(None, &text_entry("b", &unrelated)).unwrap();
+        assert_eq!(decode_sync_entry(&payload).unwrap().content_type, ClipboardContentType::Text);
+
+        let small = DeltaSync::new(1024, 0.8);
+        small.encode_for(None, &text_entry("a", "kurz\n")).unwrap();
+        let payload = small.encode_for(None, &text_entry("b", "kurz\ner\n")).unwrap();
+        assert_eq!(decode_sync_entry(&payload).unwrap().content_type, ClipboardContentType::Text);
+
+        let disabled = DeltaSync::new(0, 0.0);
+        disabled.encode_for(None, &text_entry("a", &document(2000, None))).unwrap();
+        let payload = disabled.encode_for(None, &text_entry("b", &document(2000, Some(5)))).unwrap();
+        assert_eq!(decode_sync_entry(&payload).unwrap().content_type, ClipboardContentType::Text);
+    }
+
+    #[test]
+    fn test_mismatch_requires_full_entry() {
+        let delta = TextDelta {
+            base_id: "v1".to_string(),
+            content_type: ClipboardContentType::Text,
+            ops: vec![DeltaOp::Copy { start: 0, len: 5 }, DeltaOp::Insert { text: " Welt".to_string() }],
+            sha256: sha256_hex("Hallo Welt"),
+        };
+        assert_eq!(delta.apply("Hallo du", usize::MAX).unwrap(), "Hallo Welt");
+
+        // Anderer Basistext, Kopie außerhalb des Texts, zu großes Ergebnis
+        assert!(matches!(delta.apply("Hello you", usize::MAX), Err(ClipboardError::DeltaMismatch(_))));
+        assert!(matches!(delta.apply("Hal", usize::MAX), Err(ClipboardError::DeltaMismatch(_))));
+        assert!(matches!(delta.apply("Hallo du", 8), Err(ClipboardError::ContentTooLarge(..))));
+
+        // Ohne den Basiseintrag muss der Peer vollständig senden
+        let receiver = DeltaSync::new(1024, 0.8);
+        let wire = delta_entry(&text_entry("v2", "Hallo Welt"), &delta).unwrap();
+        assert!(matches!(receiver.resolve(Some("host"), wire, usize::MAX), Err(ClipboardError::DeltaMismatch(_))));
+    }
+
+    #[test]
+    fn test_diff_is_cancellable() {
+        let base = document(50_000, None);
+        let new = document(50_000, Some(7));
+
+        let cancelled = AtomicBool::new(true);
+        assert_eq!(diff(&base, &new, &cancelled), None);
+        assert_eq!(diff_with_timeout(base.clone(), new.clone(), Duration::ZERO), None);
+
+        // Gleiche Zeilen werden aus der Zeile nach der letzten Kopie genommen
+        let ops = diff("x\na\ny\na\n", "y\na\nz\n", &AtomicBool::new(false)).unwrap();
+        assert_eq!(ops, vec![DeltaOp::Copy { start: 4, len: 4 }, DeltaOp::Insert { text: "z\n".to_string() }]);
+    }
+}
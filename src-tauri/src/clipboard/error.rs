@@ -3,6 +3,8 @@
 use std::error::Error;
 use std::fmt;
 
+use crate::protocol::{ProtocolError, VersionMismatch};
+
 /// Fehlertypen für Zwischenablage-Operationen
 #[derive(Debug)]
 pub enum ClipboardError {
@@ -50,6 +52,22 @@ pub enum ClipboardError {
     
     /// Konfigurationsfehler
     ConfigError(String),
+    
+    /// Gegenstelle spricht eine nicht unterstützte Protokollversion
+    VersionMismatch(VersionMismatch),
+    
+    /// Verschlüsselter Eintrag ließ sich nicht authentifizieren (manipuliert,
+    /// wiederholt oder mit fremdem Schlüssel versiegelt)
+    AuthenticationFailed(String),
+    
+    /// Eintrag ist älter als das Frischefenster
+    StaleEntry { age_secs: i64, max_secs: u64 },
+    
+    /// Kein Schlüsselbund für den Verlaufsschlüssel; der Verlauf bleibt im Speicher
+    KeyringUnavailable(String),
+    
+    /// Ein Text-Delta passt nicht zum Basiseintrag; der Peer muss vollständig senden
+    DeltaMismatch(String),
 }
 
 impl fmt::Display for ClipboardError {
@@ -72,6 +90,13 @@ impl fmt::Display for ClipboardError {
             },
             ClipboardError::ContentBlocked(reason) => write!(f, "Content blocked: {}", reason),
             ClipboardError::ConfigError(msg) => write!(f, "Configuration error: {}", msg),
+            ClipboardError::VersionMismatch(mismatch) => mismatch.fmt(f),
+            ClipboardError::AuthenticationFailed(msg) => write!(f, "Authentication failed: {}", msg),
+            ClipboardError::StaleEntry { age_secs, max_secs } => {
+                write!(f, "Stale entry: sent {} s ago (max: {} s)", age_secs, max_secs)
+            },
+            ClipboardError::KeyringUnavailable(msg) => write!(f, "Keyring unavailable: {}", msg),
+            ClipboardError::DeltaMismatch(msg) => write!(f, "Delta mismatch: {}", msg),
         }
     }
 }
@@ -97,6 +122,15 @@ impl From<base64::DecodeError> for ClipboardError {
     }
 }
 
+impl From<ProtocolError> for ClipboardError {
+    fn from(error: ProtocolError) -> Self {
+        match error {
+            ProtocolError::VersionMismatch(mismatch) => ClipboardError::VersionMismatch(mismatch),
+            ProtocolError::Malformed(msg) => ClipboardError::SerializationError(msg),
+        }
+    }
+}
+
 /// Hilfsfunktionen für Fehlerbehandlung
 pub fn clipboard_unavailable_error(context: &str) -> ClipboardError {
     ClipboardError::ClipboardUnavailable(context.to_string())
@@ -0,0 +1,145 @@
+// src-tauri/src/clipboard/filter.rs - Inhaltsfilter für Verlauf und Synchronisation
+
+use regex::Regex;
+
+use crate::clipboard::error::ClipboardError;
+use crate::clipboard::types::ClipboardConfig;
+
+/// Zielformat, mit dem Passwort-Manager sensible Inhalte kennzeichnen
+pub const PASSWORD_MANAGER_HINT: &str = "x-kde-passwordManagerHint";
+
+/// Kompilierte Form der Filterregeln aus der ClipboardConfig
+#[derive(Debug, Clone)]
+pub struct ClipboardFilter {
+    /// Kompilierte Ignore-Muster
+    ignore_patterns: Vec<Regex>,
+
+    /// Ausgeschlossene MIME-Typen (kleingeschrieben)
+    excluded_mime_types: Vec<String>,
+
+    /// Maximale Größe eines Eintrags in Bytes
+    max_entry_size: usize,
+}
+
+impl ClipboardFilter {
+    /// Erstellt den Filter aus der Konfiguration; ungültige Regex-Muster sind ein Fehler
+    pub fn from_config(config: &ClipboardConfig) -> Result<Self, ClipboardError> {
+        let ignore_patterns = config.ignore_patterns.iter()
+            .map(|pattern| {
+                Regex::new(pattern).map_err(|e| ClipboardError::ConfigError(
+                    format!("Invalid ignore pattern '{}': {}", pattern, e)
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(ClipboardFilter {
+            ignore_patterns,
+            excluded_mime_types: config.excluded_mime_types.iter()
+                .map(|mime| mime.to_lowercase())
+                .collect(),
+            max_entry_size: config.max_entry_size,
+        })
+    }
+
+    /// Prüft einen Inhalt samt der angebotenen Formate.
+    ///
+    /// Gibt `ContentBlocked` bzw. `ContentTooLarge` zurück, wenn der Inhalt
+    /// nicht in den Verlauf aufgenommen oder synchronisiert werden darf.
+    pub fn check(&self, content: &str, available_formats: &[String]) -> Result<(), ClipboardError> {
+        if content.len() > self.max_entry_size {
+            return Err(ClipboardError::ContentTooLarge(content.len(), self.max_entry_size));
+        }
+
+        if let Some(format) = available_formats.iter()
+            .find(|format| self.excluded_mime_types.contains(&format.to_lowercase()))
+        {
+            return Err(ClipboardError::ContentBlocked(format!("excluded format {}", format)));
+        }
+
+        if let Some(pattern) = self.ignore_patterns.iter().find(|re| re.is_match(content)) {
+            return Err(ClipboardError::ContentBlocked(format!("matches ignore pattern {}", pattern.as_str())));
+        }
+
+        Ok(())
+    }
+
+    /// Kurzform von `check`, wenn nur das Ergebnis interessiert
+    pub fn allows(&self, content: &str, available_formats: &[String]) -> bool {
+        self.check(content, available_formats).is_ok()
+    }
+}
+
+impl Default for ClipboardFilter {
+    fn default() -> Self {
+        ClipboardFilter::from_config(&ClipboardConfig::default())
+            .expect("default clipboard config is valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formats(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_default_filter_blocks_password_manager_hint() {
+        let filter = ClipboardFilter::default();
+
+        assert!(filter.allows("hello", &formats(&["text/plain"])));
+        assert!(!filter.allows("hunter2", &formats(&["text/plain", PASSWORD_MANAGER_HINT])));
+    }
+
+    #[test]
+    fn test_ignore_patterns() {
+        let config = ClipboardConfig {
+            ignore_patterns: vec![r"^\d{4}-\d{4}-\d{4}-\d{4}$".to_string()],
+            ..ClipboardConfig::default()
+        };
+        let filter = ClipboardFilter::from_config(&config).unwrap();
+
+        assert!(!filter.allows("1234-5678-9012-3456", &formats(&["text/plain"])));
+        assert!(filter.allows("not a card number", &formats(&["text/plain"])));
+    }
+
+    #[test]
+    fn test_max_entry_size() {
+        let config = ClipboardConfig {
+            max_entry_size: 8,
+            ..ClipboardConfig::default()
+        };
+        let filter = ClipboardFilter::from_config(&config).unwrap();
+
+        assert!(filter.allows("12345678", &[]));
+        assert!(matches!(
+            filter.check("123456789", &[]),
+            Err(ClipboardError::ContentTooLarge(9, 8))
+        ));
+    }
+
+    #[test]
+    fn test_excluded_mime_types_are_case_insensitive() {
+        let config = ClipboardConfig {
+            excluded_mime_types: vec!["Application/X-Secret".to_string()],
+            ..ClipboardConfig::default()
+        };
+        let filter = ClipboardFilter::from_config(&config).unwrap();
+
+        assert!(!filter.allows("data", &formats(&["application/x-secret"])));
+    }
+
+    #[test]
+    fn test_invalid_pattern_is_config_error() {
+        let config = ClipboardConfig {
+            ignore_patterns: vec!["(unclosed".to_string()],
+            ..ClipboardConfig::default()
+        };
+
+        assert!(matches!(
+            ClipboardFilter::from_config(&config),
+            Err(ClipboardError::ConfigError(_))
+        ));
+    }
+}
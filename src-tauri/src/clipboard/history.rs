@@ -0,0 +1,223 @@
+// src-tauri/src/clipboard/history.rs - Verlauf mit Zugriff per ID, Anheften und Suche
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::clipboard::types::{ClipboardContentType, ClipboardEntry};
+
+/// Zwischenablage-Verlauf in Einfügereihenfolge mit Nachschlagen per ID
+#[derive(Debug, Default)]
+pub struct ClipboardHistory {
+    /// Einträge nach ID
+    entries: HashMap<String, ClipboardEntry>,
+
+    /// IDs in Einfügereihenfolge (älteste zuerst)
+    order: VecDeque<String>,
+}
+
+impl ClipboardHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Baut den Verlauf aus einer gespeicherten Liste auf (älteste zuerst)
+    pub fn from_entries(entries: Vec<ClipboardEntry>) -> Self {
+        let mut history = Self::new();
+        for entry in entries {
+            history.push(entry);
+        }
+        history
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.entries.contains_key(id)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ClipboardEntry> {
+        self.entries.get(id)
+    }
+
+    /// Hängt einen Eintrag an; false, wenn die ID bereits vorhanden ist
+    pub fn push(&mut self, entry: ClipboardEntry) -> bool {
+        if self.entries.contains_key(&entry.id) {
+            return false;
+        }
+        self.order.push_back(entry.id.clone());
+        self.entries.insert(entry.id.clone(), entry);
+        true
+    }
+
+    /// Einträge in Einfügereihenfolge (älteste zuerst)
+    pub fn iter(&self) -> impl Iterator<Item = &ClipboardEntry> + '_ {
+        self.order.iter().filter_map(|id| self.entries.get(id))
+    }
+
+    /// Kopie aller Einträge (älteste zuerst), z.B. für die Persistenz
+    pub fn to_vec(&self) -> Vec<ClipboardEntry> {
+        self.iter().cloned().collect()
+    }
+
+    /// Heftet einen Eintrag an oder löst ihn; false, wenn die ID unbekannt ist
+    pub fn set_pinned(&mut self, id: &str, pinned: bool) -> bool {
+        match self.entries.get_mut(id) {
+            Some(entry) => {
+                entry.pinned = pinned;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Behält nur Einträge, für die `keep` true liefert
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&ClipboardEntry) -> bool
+    {
+        self.entries.retain(|_, entry| keep(entry));
+        let entries = &self.entries;
+        self.order.retain(|id| entries.contains_key(id));
+    }
+
+    /// Kürzt auf die maximale Größe; entfernt die ältesten nicht angehefteten
+    /// Einträge. Angeheftete Einträge bleiben auch über der Grenze erhalten.
+    pub fn trim(&mut self, max_size: usize) {
+        let mut excess = self.len().saturating_sub(max_size);
+        if excess == 0 {
+            return;
+        }
+
+        let entries = &mut self.entries;
+        self.order.retain(|id| {
+            if excess > 0 && !entries[id].pinned {
+                entries.remove(id);
+                excess -= 1;
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// Entfernt alle nicht angehefteten Einträge
+    pub fn clear_unpinned(&mut self) {
+        self.retain(|entry| entry.pinned);
+    }
+
+    /// Sucht ohne Beachtung der Groß-/Kleinschreibung in Texteinträgen und in
+    /// den Dateinamen von Dateieinträgen; Ergebnisse neueste zuerst
+    pub fn search(&self, query: &str, content_type: Option<&ClipboardContentType>) -> Vec<ClipboardEntry> {
+        let needle = query.to_lowercase();
+
+        self.order.iter()
+            .rev()
+            .filter_map(|id| self.entries.get(id))
+            .filter(|entry| content_type.map_or(true, |kind| &entry.content_type == kind))
+            .filter(|entry| matches_query(entry, &needle))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Prüft einen Eintrag gegen eine bereits kleingeschriebene Suche
+fn matches_query(entry: &ClipboardEntry, needle: &str) -> bool {
+    match entry.content_type {
+        ClipboardContentType::Text | ClipboardContentType::Html => {
+            entry.data.to_lowercase().contains(needle)
+        },
+        ClipboardContentType::Files => {
+            file_names(&entry.data).any(|name| name.to_lowercase().contains(needle))
+        },
+        // Bilder haben keinen durchsuchbaren Inhalt, Deltas nur Operationen
+        ClipboardContentType::Image | ClipboardContentType::ImageReference | ClipboardContentType::TextDelta => false,
+    }
+}
+
+/// Dateinamen aus einer text/uri-list (eine URI pro Zeile, # leitet Kommentare ein)
+fn file_names(uri_list: &str) -> impl Iterator<Item = String> + '_ {
+    uri_list.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let path = line.strip_prefix("file://").unwrap_or(line);
+            let name = path.trim_end_matches('/').rsplit('/').next()?;
+            let decoded = urlencoding::decode(name).map(|name| name.into_owned()).unwrap_or_else(|_| name.to_string());
+            Some(decoded)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::types::ClipboardMetadata;
+
+    fn entry(id: &str, content_type: ClipboardContentType, data: &str) -> ClipboardEntry {
+        ClipboardEntry {
+            id: id.to_string(),
+            content_type,
+            data: data.to_string(),
+            metadata: ClipboardMetadata {
+                size: data.len(),
+                mime_type: "text/plain".to_string(),
+                source: "local".to_string(),
+                truncated: false,
+                origin: None,
+            },
+            timestamp: chrono::Utc::now(),
+            pinned: false,
+        }
+    }
+
+    fn ids(entries: &[ClipboardEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_pinned_entries_survive_trim_and_clear() {
+        let mut history = ClipboardHistory::new();
+        for i in 0..4 {
+            history.push(entry(&format!("e{}", i), ClipboardContentType::Text, "text"));
+        }
+        assert!(!history.push(entry("e1", ClipboardContentType::Text, "duplicate")));
+        assert!(history.set_pinned("e0", true));
+        assert!(!history.set_pinned("missing", true));
+
+        history.trim(2);
+        assert_eq!(ids(&history.to_vec()), vec!["e0", "e3"]);
+
+        // Nur angeheftete Einträge übrig: die Grenze darf überschritten bleiben
+        history.set_pinned("e3", true);
+        history.push(entry("e4", ClipboardContentType::Text, "text"));
+        history.trim(1);
+        assert_eq!(ids(&history.to_vec()), vec!["e0", "e3"]);
+
+        history.clear_unpinned();
+        assert_eq!(history.len(), 2);
+        history.set_pinned("e3", false);
+        history.clear_unpinned();
+        assert_eq!(ids(&history.to_vec()), vec!["e0"]);
+        assert!(history.get("e0").unwrap().pinned);
+    }
+
+    #[test]
+    fn test_search_text_and_file_names() {
+        let mut history = ClipboardHistory::new();
+        history.push(entry("t1", ClipboardContentType::Text, "Meeting notes for Monday"));
+        history.push(entry("f1", ClipboardContentType::Files, "file:///home/user/Monday%20Report.pdf\nfile:///home/user/docs/"));
+        history.push(entry("i1", ClipboardContentType::Image, "bW9uZGF5"));
+        history.push(entry("t2", ClipboardContentType::Text, "MONDAY standup"));
+
+        assert_eq!(ids(&history.search("monday", None)), vec!["t2", "f1", "t1"]);
+        assert_eq!(ids(&history.search("report.PDF", None)), vec!["f1"]);
+        assert_eq!(ids(&history.search("docs", Some(&ClipboardContentType::Files))), vec!["f1"]);
+        // Verzeichnisnamen im Pfad sind keine Dateinamen
+        assert!(history.search("user", None).is_empty());
+        assert_eq!(ids(&history.search("monday", Some(&ClipboardContentType::Text))), vec!["t2", "t1"]);
+    }
+}
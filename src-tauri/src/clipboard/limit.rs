@@ -0,0 +1,167 @@
+// src-tauri/src/clipboard/limit.rs - Größenbegrenztes Lesen der Zwischenablage
+
+use std::io::{self, Read};
+use std::process::{Command, Stdio};
+
+use crate::clipboard::error::ClipboardError;
+
+/// Länge der Vorschau, die von zu großen Inhalten im Verlauf bleibt
+pub const TRUNCATED_PREVIEW_SIZE: usize = 1024;
+
+/// Ergebnis eines größenbegrenzten Lesevorgangs
+#[derive(Debug, Clone, PartialEq)]
+pub struct LimitedText {
+    /// Der gelesene Text; bei zu großen Inhalten nur der Anfang
+    pub text: String,
+
+    /// Tatsächliche Größe des Inhalts in Bytes
+    pub size: usize,
+
+    /// Ob `text` nur eine Vorschau ist
+    pub truncated: bool,
+}
+
+impl LimitedText {
+    /// Begrenzt einen bereits vollständig gelesenen Text
+    pub fn from_text(text: String, max_size: usize) -> Self {
+        let size = text.len();
+        if size <= max_size {
+            return LimitedText { text, size, truncated: false };
+        }
+
+        let mut end = preview_size(max_size);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        LimitedText { text: text[..end].to_string(), size, truncated: true }
+    }
+
+    /// Baut das Ergebnis aus den von `read_limited` behaltenen Bytes
+    fn from_bytes(bytes: Vec<u8>, size: usize) -> Self {
+        if bytes.len() == size {
+            return LimitedText { text: String::from_utf8_lossy(&bytes).to_string(), size, truncated: false };
+        }
+
+        // Die Vorschau kann mitten in einem Zeichen enden
+        let valid = match std::str::from_utf8(&bytes) {
+            Ok(text) => text.len(),
+            Err(e) => e.valid_up_to(),
+        };
+
+        LimitedText { text: String::from_utf8_lossy(&bytes[..valid]).to_string(), size, truncated: true }
+    }
+}
+
+fn preview_size(max_size: usize) -> usize {
+    TRUNCATED_PREVIEW_SIZE.min(max_size)
+}
+
+/// Liest bis zum Ende, behält aber höchstens `max_size` Bytes. Ist der Inhalt
+/// größer, bleibt nur die Vorschau, der Rest wird nur noch gezählt.
+/// Liefert die behaltenen Bytes und die Gesamtgröße.
+pub fn read_limited<R: Read>(mut reader: R, max_size: usize) -> io::Result<(Vec<u8>, usize)> {
+    let mut kept = Vec::new();
+    let mut total = 0;
+    let mut chunk = [0u8; 64 * 1024];
+
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+
+        let keep = if total + read <= max_size {
+            read
+        } else {
+            preview_size(max_size).saturating_sub(kept.len()).min(read)
+        };
+        kept.extend_from_slice(&chunk[..keep]);
+        total += read;
+
+        if total > max_size {
+            kept.truncate(preview_size(max_size));
+        }
+    }
+
+    Ok((kept, total))
+}
+
+/// Startet ein Lese-Tool (xclip, wl-paste, ...) und liest seine Ausgabe mit
+/// `read_limited`. Schlägt es fehl, entscheidet `is_empty` anhand von stderr,
+/// ob die Zwischenablage nur leer ist.
+pub fn read_command_limited(
+    mut cmd: Command,
+    tool: &str,
+    max_size: usize,
+    is_empty: impl Fn(&str) -> bool,
+) -> Result<LimitedText, ClipboardError> {
+    let mut child = cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| ClipboardError::IoError(format!("Failed to execute {}: {}", tool, e)))?;
+
+    let read = match child.stdout.take() {
+        Some(stdout) => read_limited(stdout, max_size),
+        None => Ok((Vec::new(), 0)),
+    };
+
+    let output = child.wait_with_output()
+        .map_err(|e| ClipboardError::IoError(format!("Failed to wait for {}: {}", tool, e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if is_empty(&stderr) {
+            return Err(ClipboardError::EmptyClipboard);
+        }
+        return Err(ClipboardError::IoError(format!("{} failed: {}", tool, stderr)));
+    }
+
+    let (bytes, size) = read
+        .map_err(|e| ClipboardError::IoError(format!("Failed to read {} output: {}", tool, e)))?;
+
+    if size == 0 {
+        return Err(ClipboardError::EmptyClipboard);
+    }
+
+    Ok(LimitedText::from_bytes(bytes, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_limited_keeps_only_a_preview() {
+        let small = vec![b'a'; 100];
+        assert_eq!(read_limited(&small[..], 100).unwrap(), (small.clone(), 100));
+
+        // Mehrere Blöcke, die Grenze liegt mitten im zweiten
+        let large = vec![b'b'; 200 * 1024];
+        let (kept, size) = read_limited(&large[..], 100 * 1024).unwrap();
+        assert_eq!((kept.len(), size), (TRUNCATED_PREVIEW_SIZE, 200 * 1024));
+
+        // Die Vorschau ist nie größer als die Grenze selbst
+        let (kept, size) = read_limited(&small[..], 10).unwrap();
+        assert_eq!((kept.len(), size), (10, 100));
+    }
+
+    #[test]
+    fn test_preview_ends_on_char_boundary() {
+        // "ä" belegt zwei Bytes, die Vorschau darf es nicht zerschneiden
+        let text = "ä".repeat(1000);
+        let limited = LimitedText::from_text(text.clone(), 1001);
+        assert!(limited.truncated);
+        assert_eq!((limited.text.len(), limited.size), (1000, 2000));
+
+        let (kept, size) = read_limited(text.as_bytes(), 1001).unwrap();
+        assert_eq!(LimitedText::from_bytes(kept, size), limited);
+
+        let whole = LimitedText::from_text(text.clone(), 2000);
+        assert!(!whole.truncated);
+        assert_eq!(whole.text, text);
+    }
+}
@@ -1,5 +1,9 @@
 // src-tauri/src/clipboard/mod.rs - Zwischenablage-Synchronisation System
 
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::mpsc::RecvTimeoutError;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
@@ -10,9 +14,29 @@ pub mod types;
 pub mod x11_clipboard;
 pub mod wayland_clipboard;
 pub mod error;
+pub mod filter;
+pub mod persistence;
+pub mod history;
+pub mod sync_policy;
+pub mod limit;
+pub mod offload;
+pub mod delta;
+pub mod shared;
+pub mod poll;
 
 use types::*;
 use error::ClipboardError;
+use filter::ClipboardFilter;
+use persistence::HistoryStore;
+use history::ClipboardHistory;
+use offload::ImageOffload;
+use delta::DeltaSync;
+use shared::SharedClipboard;
+use poll::{ClipboardPoller, ClipboardStats, MonitorMode};
+use sync_policy::{ClipboardSyncPolicy, ClipboardSyncStatus, RemoteSyncOutcome, SyncState};
+use crate::command_runner::CommandRunner;
+use crate::logging::TARGET_CLIPBOARD;
+use crate::protocol::{self, WireSubsystem, PROTOCOL_VERSION};
 
 /// Zwischenablage-Manager für SmolDesk
 pub struct ClipboardManager {
@@ -20,10 +44,16 @@ pub struct ClipboardManager {
     clipboard_impl: Box<dyn ClipboardProvider>,
     
     /// Lokaler Zwischenablage-Verlauf
-    history: Arc<Mutex<Vec<ClipboardEntry>>>,
+    history: Arc<Mutex<ClipboardHistory>>,
     
-    /// Maximale Anzahl von Einträgen im Verlauf
-    max_history_size: usize,
+    /// Konfiguration (Verlaufsgröße, Filter, Persistenz)
+    config: Arc<Mutex<ClipboardConfig>>,
+    
+    /// Kompilierte Filterregeln
+    filter: Arc<Mutex<ClipboardFilter>>,
+    
+    /// Verschlüsselter Verlaufsspeicher (nur wenn ein Datenverzeichnis gesetzt ist)
+    store: Arc<Mutex<Option<HistoryStore>>>,
     
     /// Callback-Funktionen für Änderungen
     change_callbacks: Arc<Mutex<Vec<Box<dyn Fn(&ClipboardEntry) + Send + Sync>>>>,
@@ -34,13 +64,32 @@ pub struct ClipboardManager {
     /// Überwachung aktiv
     monitoring: Arc<Mutex<bool>>,
     
-    /// Letzter bekannter Zwischenablage-Inhalt (für Änderungserkennung)
-    last_content: Arc<Mutex<Option<String>>>,
+    /// Hash des letzten bekannten Inhalts (für Änderungserkennung); nur der
+    /// Hash, damit große Inhalte nicht doppelt im Speicher liegen
+    last_content: Arc<Mutex<Option<u64>>>,
+    
+    /// Richtung, Konfliktauflösung und zuletzt übernommener Peer-Eintrag
+    sync_state: Arc<Mutex<SyncState>>,
+    
+    /// Große Bilder, die per Dateiübertragung gesendet oder empfangen werden
+    image_offload: Arc<ImageOffload>,
+    
+    /// Zuletzt je Peer gesendete und empfangene Texte für Deltas
+    delta_sync: Arc<DeltaSync>,
+    
+    /// Gemeinsame Zwischenablage je Verbindung, getrennt von der des Systems
+    shared_clipboard: Arc<SharedClipboard>,
+    
+    /// Abfrageintervall, wenn es keine native Änderungsbenachrichtigung gibt
+    poller: Arc<ClipboardPoller>,
 }
 
 impl ClipboardManager {
     /// Erstellt einen neuen ClipboardManager
-    pub fn new(display_server: crate::screen_capture::types::DisplayServer) -> Result<Self, ClipboardError> {
+    pub fn new(
+        display_server: crate::screen_capture::types::DisplayServer,
+        config: ClipboardConfig,
+    ) -> Result<Self, ClipboardError> {
         let clipboard_impl: Box<dyn ClipboardProvider> = match display_server {
             crate::screen_capture::types::DisplayServer::X11 => {
                 Box::new(x11_clipboard::X11ClipboardProvider::new()?)
@@ -53,17 +102,153 @@ impl ClipboardManager {
             }
         };
         
+        Self::with_provider(clipboard_impl, config)
+    }
+    
+    /// Erstellt einen ClipboardManager für eine gegebene Implementierung
+    fn with_provider(clipboard_impl: Box<dyn ClipboardProvider>, config: ClipboardConfig) -> Result<Self, ClipboardError> {
+        let filter = ClipboardFilter::from_config(&config)?;
+        let image_offload = Arc::new(ImageOffload::new(config.image_offload_threshold));
+        let delta_sync = Arc::new(DeltaSync::new(config.delta_sync_min_size, config.delta_sync_similarity));
+        let shared_clipboard = Arc::new(SharedClipboard::new(
+            config.connection_mode,
+            config.shared_clipboard_max_entries,
+            config.shared_clipboard_max_entry_size,
+        ));
+        let poller = Arc::new(ClipboardPoller::new(config.max_poll_interval_ms));
+        
         Ok(ClipboardManager {
             clipboard_impl,
-            history: Arc::new(Mutex::new(Vec::new())),
-            max_history_size: 50, // Maximal 50 Einträge im Verlauf
+            history: Arc::new(Mutex::new(ClipboardHistory::new())),
+            config: Arc::new(Mutex::new(config)),
+            filter: Arc::new(Mutex::new(filter)),
+            store: Arc::new(Mutex::new(None)),
             change_callbacks: Arc::new(Mutex::new(Vec::new())),
             monitor_thread: None,
             monitoring: Arc::new(Mutex::new(false)),
             last_content: Arc::new(Mutex::new(None)),
+            sync_state: Arc::new(Mutex::new(SyncState::default())),
+            image_offload,
+            delta_sync,
+            shared_clipboard,
+            poller,
+        })
+    }
+    
+    /// Setzt das Datenverzeichnis für die Persistenz. Schlüssel und
+    /// gespeicherter Verlauf werden in einem Hintergrund-Thread geladen und vor
+    /// die seit dem Start kopierten Einträge gestellt; bis dahin bleibt der
+    /// Verlauf nur im Speicher. `on_loaded` erhält die Zahl übersprungener
+    /// Datensätze oder, bei aktivierter Persistenz, den Fehler (z.B.
+    /// `KeyringUnavailable`; der Verlauf wird dann nie geschrieben).
+    pub fn set_storage_dir<F>(&self, data_dir: &Path, runner: Arc<dyn CommandRunner>, on_loaded: F) -> thread::JoinHandle<()>
+    where
+        F: FnOnce(Result<usize, ClipboardError>) + Send + 'static
+    {
+        let data_dir = data_dir.to_path_buf();
+        let history = self.history.clone();
+        let config = self.config.clone();
+        let store_slot = self.store.clone();
+        
+        thread::spawn(move || {
+            let persist = config.lock().unwrap().persist_to_disk;
+            let store = match HistoryStore::open(&data_dir, runner.as_ref()) {
+                Ok(store) => store,
+                Err(e) if persist => return on_loaded(Err(e)),
+                Err(e) => {
+                    log::warn!(target: TARGET_CLIPBOARD, "Clipboard history store unavailable: {}", e);
+                    return;
+                },
+            };
+            
+            if !persist {
+                *store_slot.lock().unwrap() = Some(store);
+                return on_loaded(Ok(0));
+            }
+            
+            let loaded = match store.load() {
+                Ok(loaded) => loaded,
+                Err(e) => return on_loaded(Err(e)),
+            };
+            
+            // Verlauf unter der Sperre zusammenführen und den Speicher
+            // einsetzen, damit keine Änderung dazwischen verloren geht
+            let mut history = history.lock().unwrap();
+            let mut merged = ClipboardHistory::from_entries(loaded.entries);
+            for entry in history.to_vec() {
+                merged.push(entry);
+            }
+            merged.trim(config.lock().unwrap().max_history_size);
+            *history = merged;
+            
+            let result = store.save(&history.to_vec());
+            *store_slot.lock().unwrap() = Some(store);
+            drop(history);
+            
+            on_loaded(result.map(|_| loaded.skipped));
         })
     }
     
+    /// Ändert die Konfiguration zur Laufzeit
+    pub fn update_config(&mut self, config: ClipboardConfig) -> Result<(), ClipboardError> {
+        let filter = ClipboardFilter::from_config(&config)?;
+        
+        // Eine andere Selection braucht einen neuen Watcher
+        let selection_changed = self.config.lock().unwrap().selection != config.selection;
+        if selection_changed {
+            self.clipboard_impl.set_selection(config.selection)?;
+        }
+        
+        {
+            let mut history = self.history.lock().unwrap();
+            // Bestehende Einträge, die nun gefiltert würden, entfernen
+            history.retain(|entry| entry.content_type != ClipboardContentType::Text || filter.allows(&entry.data, &[]));
+            history.trim(config.max_history_size);
+        }
+        
+        let persist = config.persist_to_disk;
+        self.image_offload.set_threshold(config.image_offload_threshold);
+        self.delta_sync.configure(config.delta_sync_min_size, config.delta_sync_similarity);
+        self.shared_clipboard.configure(config.connection_mode, config.shared_clipboard_max_entries, config.shared_clipboard_max_entry_size);
+        self.poller.set_max_interval(config.max_poll_interval_ms);
+        *self.filter.lock().unwrap() = filter;
+        *self.config.lock().unwrap() = config;
+        
+        // Persistenz an- oder abschalten
+        if let Some(store) = &*self.store.lock().unwrap() {
+            if persist {
+                store.save(&self.history.lock().unwrap().to_vec())?;
+            } else {
+                store.clear()?;
+            }
+        }
+        
+        if selection_changed && self.is_monitoring() {
+            self.stop_monitoring();
+            *self.last_content.lock().unwrap() = None;
+            self.start_monitoring()?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Holt die aktuelle Konfiguration
+    pub fn get_config(&self) -> ClipboardConfig {
+        self.config.lock().unwrap().clone()
+    }
+    
+    /// Ersetzt den Abfrageplan durch einen, den auch andere Module kennen
+    /// (z.B. die Eingabeweiterleitung); vor `start_monitoring` aufrufen
+    pub fn set_poller(&mut self, poller: Arc<ClipboardPoller>) {
+        poller.set_max_interval(self.config.lock().unwrap().max_poll_interval_ms);
+        self.poller = poller;
+    }
+    
+    /// Art der Überwachung und aktuelles Abfrageintervall
+    pub fn stats(&self) -> ClipboardStats {
+        self.poller.stats()
+    }
+    
     /// Startet die Überwachung der Zwischenablage
     pub fn start_monitoring(&mut self) -> Result<(), ClipboardError> {
         // Prüfen, ob bereits überwacht wird
@@ -85,45 +270,86 @@ impl ClipboardManager {
         let history = self.history.clone();
         let callbacks = self.change_callbacks.clone();
         let last_content = self.last_content.clone();
-        let max_history = self.max_history_size;
+        let config = self.config.clone();
+        let filter = self.filter.clone();
+        let store = self.store.clone();
+        let sync_state = self.sync_state.clone();
+        let poller = self.poller.clone();
         
         // Clone der Implementierung für den Thread
         let mut clipboard_impl = self.clipboard_impl.create_clone();
         
         self.monitor_thread = Some(thread::spawn(move || {
-            let mut poll_interval = Duration::from_millis(500); // Standard: alle 500ms prüfen
+            // Native Änderungsbenachrichtigungen bevorzugen, sonst Polling
+            let mut subscription = clipboard_impl.subscribe().ok();
+            poller.set_mode(if subscription.is_some() { MonitorMode::Watching } else { MonitorMode::Polling });
+            poller.activity();
             
             while *monitoring_flag.lock().unwrap() {
-                // Versuche aktuelle Zwischenablage zu lesen
-                match clipboard_impl.get_text() {
-                    Ok(current_content) => {
+                // Auf eine Änderung warten; Timeout, um das Stopp-Flag zu prüfen
+                let watch_result = subscription.as_ref()
+                    .map(|sub| sub.changes.recv_timeout(Duration::from_millis(500)));
+                
+                match watch_result {
+                    Some(Ok(())) | None => {},
+                    Some(Err(RecvTimeoutError::Timeout)) => continue,
+                    Some(Err(RecvTimeoutError::Disconnected)) => {
+                        log::warn!(target: TARGET_CLIPBOARD, "Clipboard watcher stopped, falling back to polling");
+                        subscription = None;
+                        poller.set_mode(MonitorMode::Polling);
+                    }
+                }
+                
+                // Versuche aktuelle Zwischenablage zu lesen (HTML und Bilder vor Text)
+                let read_config = config.lock().unwrap().clone();
+                match read_local_content(&mut *clipboard_impl, &read_config) {
+                    Ok(current) => {
                         let mut should_notify = false;
                         let mut new_entry: Option<ClipboardEntry> = None;
                         
                         // Prüfen, ob sich der Inhalt geändert hat
                         {
+                            let current_hash = current.hash();
                             let mut last = last_content.lock().unwrap();
-                            if let Some(ref last_text) = *last {
-                                if last_text != &current_content {
+                            if let Some(last_hash) = *last {
+                                if last_hash != current_hash {
                                     should_notify = true;
                                 }
-                            } else if !current_content.is_empty() {
+                            } else if !current.data.is_empty() {
                                 should_notify = true;
                             }
                             
                             if should_notify {
-                                *last = Some(current_content.clone());
+                                *last = Some(current_hash);
+                                // Auch Echos und gefilterte Inhalte: schnell kopiert wird oft mehrmals
+                                poller.activity();
                                 
+                                // Von einem Peer gesetzte Inhalte nicht erneut melden, auch
+                                // wenn eine Anwendung sie erneut in die Zwischenablage legt
+                                if sync_state.lock().unwrap().is_echo(current_hash, Instant::now()) {
+                                    should_notify = false;
+                                }
+                            }
+                            
+                            // Gefilterte Inhalte (z.B. Passwörter) nie speichern oder weitergeben
+                            if should_notify {
+                                should_notify = filter.lock().unwrap().allows(&current.data, &current.formats);
+                            }
+                            
+                            if should_notify {
                                 new_entry = Some(ClipboardEntry {
                                     id: uuid::Uuid::new_v4().to_string(),
-                                    content_type: ClipboardContentType::Text,
-                                    data: current_content.clone(),
+                                    content_type: current.content_type,
                                     metadata: ClipboardMetadata {
-                                        size: current_content.len(),
-                                        mime_type: "text/plain".to_string(),
+                                        size: current.size,
+                                        mime_type: current.mime_type,
                                         source: "local".to_string(),
+                                        truncated: current.truncated,
+                                        origin: None,
                                     },
+                                    data: current.data,
                                     timestamp: chrono::Utc::now(),
+                                    pinned: false,
                                 });
                             }
                         }
@@ -135,32 +361,48 @@ impl ClipboardManager {
                                 hist.push(entry.clone());
                                 
                                 // Verlauf begrenzen
-                                if hist.len() > max_history {
-                                    hist.remove(0);
+                                let config_guard = config.lock().unwrap();
+                                hist.trim(config_guard.max_history_size);
+                                
+                                if config_guard.persist_to_disk {
+                                    if let Some(store) = &*store.lock().unwrap() {
+                                        if let Err(e) = store.save(&hist.to_vec()) {
+                                            log::error!(target: TARGET_CLIPBOARD, "Failed to persist clipboard history: {}", e);
+                                        }
+                                    }
                                 }
                             }
                             
-                            // Callbacks benachrichtigen
-                            let callbacks_guard = callbacks.lock().unwrap();
-                            for callback in callbacks_guard.iter() {
-                                callback(&entry);
+                            // Zu große Inhalte bleiben als Vorschau lokal
+                            if entry.metadata.truncated {
+                                log::info!(target: TARGET_CLIPBOARD, "Clipboard content of {} bytes exceeds max_sync_size, keeping a preview in history only", entry.metadata.size);
+                            } else if sync_state.lock().unwrap().local_change(&entry) {
+                                // Callbacks benachrichtigen, sofern die Richtung ausgehende Inhalte erlaubt
+                                let callbacks_guard = callbacks.lock().unwrap();
+                                for callback in callbacks_guard.iter() {
+                                    callback(&entry);
+                                }
+                            } else {
+                                log::debug!(target: TARGET_CLIPBOARD, "Outgoing clipboard sync disabled, keeping change local");
                             }
                         }
-                        
-                        // Normale Polling-Rate
-                        poll_interval = Duration::from_millis(500);
-                    },
-                    Err(ClipboardError::EmptyClipboard) => {
-                        // Zwischenablage ist leer, das ist normal
-                        poll_interval = Duration::from_millis(1000); // Weniger häufig prüfen
                     },
-                    Err(_) => {
-                        // Fehler beim Lesen, etwas langsamer versuchen
-                        poll_interval = Duration::from_millis(2000);
-                    }
+                    // Leere Zwischenablage ist normal
+                    Err(ClipboardError::EmptyClipboard) => {},
+                    // Lesefehler werden wie ausbleibende Änderungen immer seltener wiederholt
+                    Err(e) => log::trace!(target: TARGET_CLIPBOARD, "Failed to read clipboard: {}", e),
                 }
                 
-                thread::sleep(poll_interval);
+                if subscription.is_none() {
+                    poller.wait();
+                }
+            }
+            
+            poller.set_mode(MonitorMode::Stopped);
+            
+            // Watcher (z.B. wl-paste-Kindprozess) beenden
+            if let Some(sub) = subscription {
+                sub.stop();
             }
         }));
         
@@ -174,6 +416,7 @@ impl ClipboardManager {
             let mut monitoring = self.monitoring.lock().unwrap();
             *monitoring = false;
         }
+        self.poller.interrupt();
         
         // Thread beenden
         if let Some(handle) = self.monitor_thread.take() {
@@ -181,9 +424,22 @@ impl ClipboardManager {
         }
     }
     
-    /// Holt den aktuellen Text aus der Zwischenablage
+    /// Prüft, ob die Überwachung aktiv ist
+    pub fn is_monitoring(&self) -> bool {
+        *self.monitoring.lock().unwrap()
+    }
+    
+    /// Holt den aktuellen Text aus der Zwischenablage; Texte über
+    /// `max_sync_size` liefern `ContentTooLarge`
     pub fn get_text(&mut self) -> Result<String, ClipboardError> {
-        self.clipboard_impl.get_text()
+        let max_sync_size = self.config.lock().unwrap().max_sync_size;
+        let content = self.clipboard_impl.get_text_limited("text/plain", max_sync_size)?;
+        
+        if content.truncated {
+            return Err(ClipboardError::ContentTooLarge(content.size, max_sync_size));
+        }
+        
+        Ok(content.text)
     }
     
     /// Setzt Text in die Zwischenablage
@@ -193,12 +449,81 @@ impl ClipboardManager {
         // Lokalen Cache aktualisieren
         {
             let mut last = self.last_content.lock().unwrap();
-            *last = Some(text.to_string());
+            *last = Some(content_hash(text));
         }
         
         Ok(())
     }
     
+    /// Setzt Text, der von einem Peer stammt, ohne ihn als lokale Änderung zu melden
+    pub fn set_remote_text(&mut self, text: &str) -> Result<(), ClipboardError> {
+        let entry = ClipboardEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            content_type: ClipboardContentType::Text,
+            data: text.to_string(),
+            metadata: ClipboardMetadata {
+                size: text.len(),
+                mime_type: "text/plain".to_string(),
+                source: "remote".to_string(),
+                truncated: false,
+                origin: None,
+            },
+            timestamp: chrono::Utc::now(),
+            pinned: false,
+        };
+        
+        {
+            let mut sync_state = self.sync_state.lock().unwrap();
+            if !sync_state.policy().direction.allows_incoming() {
+                return Err(ClipboardError::ContentBlocked("incoming clipboard sync is disabled".to_string()));
+            }
+            sync_state.remote_applied(&entry, content_hash(text), Instant::now());
+        }
+        
+        self.set_text(text)
+    }
+    
+    /// Ändert Richtung und Toleranz der Synchronisation zur Laufzeit
+    pub fn set_sync_policy(&self, policy: ClipboardSyncPolicy) {
+        log::info!(target: TARGET_CLIPBOARD, "Clipboard sync direction set to {:?}", policy.direction);
+        self.sync_state.lock().unwrap().set_policy(policy);
+    }
+    
+    /// Richtlinie und zuletzt übernommener Peer-Eintrag
+    pub fn get_sync_status(&self) -> ClipboardSyncStatus {
+        self.sync_state.lock().unwrap().status()
+    }
+    
+    /// Koordination mit der Dateiübertragung für große Bilder; wird außerhalb
+    /// des Manager-Locks zusammen mit dem FileTransferManager verwendet
+    pub fn image_offload(&self) -> Arc<ImageOffload> {
+        self.image_offload.clone()
+    }
+    
+    /// Delta-Kodierung großer Texte; wird wie `image_offload` außerhalb des
+    /// Manager-Locks im Änderungs-Callback verwendet
+    pub fn delta_sync(&self) -> Arc<DeltaSync> {
+        self.delta_sync.clone()
+    }
+    
+    /// Gemeinsame Zwischenablage je Verbindung; wird wie `delta_sync` im
+    /// Änderungs-Callback gebraucht, um Peers mit `SharedOnly` auszulassen
+    pub fn shared_clipboard(&self) -> Arc<SharedClipboard> {
+        self.shared_clipboard.clone()
+    }
+    
+    /// Holt HTML aus der Zwischenablage
+    pub fn get_html(&mut self) -> Result<String, ClipboardError> {
+        self.clipboard_impl.get_html()
+    }
+    
+    /// Setzt HTML in die Zwischenablage
+    pub fn set_html(&mut self, html: &str) -> Result<(), ClipboardError> {
+        self.clipboard_impl.set_html(html)?;
+        *self.last_content.lock().unwrap() = Some(content_hash(html));
+        Ok(())
+    }
+    
     /// Holt Bilddaten aus der Zwischenablage
     pub fn get_image(&mut self) -> Result<Vec<u8>, ClipboardError> {
         self.clipboard_impl.get_image()
@@ -209,16 +534,64 @@ impl ClipboardManager {
         self.clipboard_impl.set_image(image_data, format)
     }
     
-    /// Holt den Zwischenablage-Verlauf
+    /// Holt den Zwischenablage-Verlauf (älteste Einträge zuerst)
     pub fn get_history(&self) -> Vec<ClipboardEntry> {
-        let history = self.history.lock().unwrap();
-        history.clone()
+        self.history.lock().unwrap().to_vec()
     }
     
-    /// Löscht den Zwischenablage-Verlauf
+    /// Löscht den Zwischenablage-Verlauf bis auf angeheftete Einträge
     pub fn clear_history(&self) {
         let mut history = self.history.lock().unwrap();
-        history.clear();
+        history.clear_unpinned();
+        
+        if history.is_empty() {
+            if let Some(store) = &*self.store.lock().unwrap() {
+                let _ = store.clear();
+            }
+        } else {
+            self.persist(&history);
+        }
+    }
+    
+    /// Löscht den Verlauf im Speicher und überschreibt die gespeicherte Datei.
+    /// Angeheftete Einträge bleiben nur mit `keep_pinned` erhalten.
+    pub fn wipe_history(&self, keep_pinned: bool) -> Result<(), ClipboardError> {
+        let mut history = self.history.lock().unwrap();
+        if keep_pinned {
+            history.clear_unpinned();
+        } else {
+            *history = ClipboardHistory::new();
+        }
+        
+        if let Some(store) = &*self.store.lock().unwrap() {
+            let keep = if self.config.lock().unwrap().persist_to_disk { history.to_vec() } else { Vec::new() };
+            store.wipe(&keep)?;
+        }
+        Ok(())
+    }
+    
+    /// Heftet einen Verlaufseintrag an, damit er Kürzen und Leeren übersteht
+    pub fn pin_entry(&self, entry_id: &str) -> Result<(), ClipboardError> {
+        self.set_pinned(entry_id, true)
+    }
+    
+    /// Löst einen angehefteten Verlaufseintrag
+    pub fn unpin_entry(&self, entry_id: &str) -> Result<(), ClipboardError> {
+        self.set_pinned(entry_id, false)
+    }
+    
+    /// Durchsucht den Verlauf (Text ohne Groß-/Kleinschreibung, bei Dateien die
+    /// Dateinamen), optional nur einen Inhaltstyp; neueste Einträge zuerst
+    pub fn search_history(&self, query: &str, content_type: Option<ClipboardContentType>) -> Vec<ClipboardEntry> {
+        self.history.lock().unwrap().search(query, content_type.as_ref())
+    }
+    
+    /// Legt einen älteren Verlaufseintrag wieder in die Zwischenablage
+    pub fn select_history_entry(&mut self, entry_id: &str) -> Result<(), ClipboardError> {
+        let entry = self.history.lock().unwrap().get(entry_id).cloned()
+            .ok_or_else(|| ClipboardError::EntryNotFound(entry_id.to_string()))?;
+        
+        self.apply_entry(&entry)
     }
     
     /// Fügt einen Callback für Änderungen hinzu
@@ -234,7 +607,7 @@ impl ClipboardManager {
     pub fn export_entry(&self, entry_id: &str) -> Result<String, ClipboardError> {
         let history = self.history.lock().unwrap();
         
-        if let Some(entry) = history.iter().find(|e| e.id == entry_id) {
+        if let Some(entry) = history.get(entry_id) {
             serde_json::to_string(entry)
                 .map_err(|e| ClipboardError::SerializationError(e.to_string()))
         } else {
@@ -251,76 +624,339 @@ impl ClipboardManager {
         history.push(entry);
         
         // Verlauf begrenzen
-        if history.len() > self.max_history_size {
-            history.remove(0);
-        }
+        history.trim(self.config.lock().unwrap().max_history_size);
+        self.persist(&history);
         
         Ok(())
     }
     
-    /// Synchronisiert mit einem entfernten Zwischenablage-Eintrag
-    pub fn sync_remote_entry(&mut self, entry: ClipboardEntry) -> Result<(), ClipboardError> {
+    /// Synchronisiert mit einem entfernten Zwischenablage-Eintrag.
+    ///
+    /// Bei gleichzeitigen Änderungen gewinnt der spätere Eintrag (siehe
+    /// `sync_policy`); verworfene Einträge sind kein Fehler.
+    ///
+    /// Für `ImageReference`-Einträge liefert dies `Pending`: Das Bild wird erst
+    /// nach seiner Übertragung (siehe `offload`) mit derselben ID übernommen.
+    ///
+    /// `peer_id` ist der sendende Peer; er wird als Ursprung vermerkt, wenn der
+    /// Eintrag noch keinen hat. Mit `relay_remote_entries` geht ein direkt vom
+    /// Ursprung empfangener Eintrag an die Änderungs-Callbacks weiter.
+    pub fn sync_remote_entry(&mut self, peer_id: Option<&str>, entry: ClipboardEntry) -> Result<RemoteSyncOutcome, ClipboardError> {
+        let entry = received_from(peer_id, entry);
+        let outcome = self.sync_state.lock().unwrap().check_remote(&entry);
+        if outcome != RemoteSyncOutcome::Applied {
+            log::debug!(target: TARGET_CLIPBOARD, "Remote clipboard entry {} not applied: {:?}", entry.id, outcome);
+            return Ok(outcome);
+        }
+        
+        if entry.content_type == ClipboardContentType::ImageReference {
+            return Ok(RemoteSyncOutcome::Pending);
+        }
+        
+        self.check_sync_size(&entry)?;
+        if entry.content_type != ClipboardContentType::Image {
+            self.filter.lock().unwrap().check(&entry.data, &[entry.metadata.mime_type.clone()])?;
+        }
+        
+        // Vor dem Setzen vermerken, damit der Monitor das Echo erkennt
+        if entry.content_type != ClipboardContentType::Files {
+            self.sync_state.lock().unwrap().remote_applied(&entry, content_hash(&entry.data), Instant::now());
+        }
+        
         // Lokale Zwischenablage aktualisieren
+        self.apply_entry(&entry)?;
+        
+        // Zum Verlauf hinzufügen (Duplikate werden ignoriert)
+        {
+            let mut history = self.history.lock().unwrap();
+            if history.push(entry.clone()) {
+                // Verlauf begrenzen
+                history.trim(self.config.lock().unwrap().max_history_size);
+                self.persist(&history);
+            }
+        }
+        
+        let relay = self.config.lock().unwrap().relay_remote_entries
+            && self.sync_state.lock().unwrap().should_relay(&entry);
+        if relay {
+            for callback in self.change_callbacks.lock().unwrap().iter() {
+                callback(&entry);
+            }
+        }
+        
+        Ok(RemoteSyncOutcome::Applied)
+    }
+    
+    /// Wie `sync_remote_entry` für einen entschlüsselten Eintrag, dessen
+    /// authentifizierte Sendezeit höchstens `sync_freshness_secs` zurückliegt
+    pub fn sync_sealed_entry(&mut self, peer_id: Option<&str>, entry: ClipboardEntry, sent_at: chrono::DateTime<chrono::Utc>) -> Result<RemoteSyncOutcome, ClipboardError> {
+        let max_secs = self.config.lock().unwrap().sync_freshness_secs;
+        let age_secs = (chrono::Utc::now() - sent_at).num_seconds();
+        if max_secs > 0 && age_secs > max_secs as i64 {
+            return Err(ClipboardError::StaleEntry { age_secs, max_secs });
+        }
+    
+        self.sync_remote_entry(peer_id, entry)
+    }
+    
+    /// Setzt einen `TextDelta`-Eintrag eines Peers aus dessen zuletzt
+    /// empfangenem Text zusammen; andere Einträge bleiben unverändert.
+    /// `DeltaMismatch`: der Peer muss den Eintrag vollständig senden.
+    pub fn resolve_remote_entry(&self, peer_id: Option<&str>, entry: ClipboardEntry) -> Result<ClipboardEntry, ClipboardError> {
+        let max_sync_size = self.config.lock().unwrap().max_sync_size;
+        self.delta_sync.resolve(peer_id, entry, max_sync_size)
+    }
+    
+    /// Ein zuvor an einen Peer gesendeter Eintrag, vollständig kodiert; für
+    /// Peers, denen ein Delta nicht zum Basiseintrag passte
+    pub fn create_full_sync_entry(&self, peer_id: Option<&str>, entry_id: &str) -> Result<String, ClipboardError> {
+        let entry = match self.delta_sync.sent_entry(peer_id, entry_id) {
+            Some(entry) => entry,
+            None => self.history.lock().unwrap().get(entry_id).cloned()
+                .ok_or_else(|| ClipboardError::EntryNotFound(entry_id.to_string()))?,
+        };
+        
+        let payload = self.create_sync_entry(&entry)?;
+        self.delta_sync.record_sent(peer_id, &entry);
+        Ok(payload)
+    }
+    
+    /// Erstellt eine kompakte Repräsentation für die Netzwerkübertragung
+    pub fn create_sync_entry(&self, entry: &ClipboardEntry) -> Result<String, ClipboardError> {
+        // Zu große und gefilterte Inhalte dürfen nie an Peers gehen
+        self.check_sync_size(entry)?;
+        if !matches!(entry.content_type, ClipboardContentType::Image | ClipboardContentType::ImageReference) {
+            self.filter.lock().unwrap().check(&entry.data, &[entry.metadata.mime_type.clone()])?;
+        }
+        
+        encode_sync_entry(entry)
+    }
+}
+
+impl ClipboardManager {
+    /// Setzt einen Eintrag in die lokale Zwischenablage, ohne ihn als lokale Änderung zu melden
+    fn apply_entry(&mut self, entry: &ClipboardEntry) -> Result<(), ClipboardError> {
+        // Von zu großen Inhalten ist nur die Vorschau bekannt
+        if entry.metadata.truncated {
+            return Err(ClipboardError::ContentTooLarge(entry.metadata.size, self.config.lock().unwrap().max_sync_size));
+        }
+        
         match entry.content_type {
             ClipboardContentType::Text => {
-                self.set_text(&entry.data)?;
+                self.set_text(&entry.data)
             },
             ClipboardContentType::Image => {
                 let image_data = general_purpose::STANDARD.decode(&entry.data)
                     .map_err(|e| ClipboardError::DecodingError(e.to_string()))?;
-                self.set_image(&image_data, &entry.metadata.mime_type)?;
+                let format = entry.metadata.mime_type.trim_start_matches("image/");
+                self.set_image(&image_data, format)?;
+                *self.last_content.lock().unwrap() = Some(content_hash(&entry.data));
+                Ok(())
             },
             ClipboardContentType::Html => {
-                // HTML als Text behandeln für jetzt
-                self.set_text(&entry.data)?;
+                self.set_html(&entry.data)
             },
             ClipboardContentType::Files => {
                 // Dateien können nicht direkt in die Zwischenablage gesetzt werden
-                return Err(ClipboardError::UnsupportedOperation("Cannot set files to clipboard".to_string()));
+                Err(ClipboardError::UnsupportedOperation("Cannot set files to clipboard".to_string()))
+            },
+            ClipboardContentType::ImageReference => {
+                // Das Bild selbst kommt erst mit der Dateiübertragung
+                Err(ClipboardError::UnsupportedOperation("Image reference has no image data yet".to_string()))
+            },
+            ClipboardContentType::TextDelta => {
+                // Wird vor der Übernahme mit `resolve_remote_entry` aufgelöst
+                Err(ClipboardError::UnsupportedOperation("Text delta was not resolved".to_string()))
             }
         }
+    }
+    
+    /// Prüft einen Eintrag gegen `max_sync_size`; Bilder sind davon ausgenommen
+    fn check_sync_size(&self, entry: &ClipboardEntry) -> Result<(), ClipboardError> {
+        let max_sync_size = self.config.lock().unwrap().max_sync_size;
         
-        // Zum Verlauf hinzufügen
-        {
-            let mut history = self.history.lock().unwrap();
-            
-            // Prüfen, ob bereits vorhanden (Duplikate vermeiden)
-            if !history.iter().any(|e| e.id == entry.id) {
-                history.push(entry);
-                
-                // Verlauf begrenzen
-                if history.len() > self.max_history_size {
-                    history.remove(0);
-                }
-            }
+        if entry.metadata.truncated {
+            return Err(ClipboardError::ContentTooLarge(entry.metadata.size, max_sync_size));
+        }
+        if entry.content_type != ClipboardContentType::Image && entry.data.len() > max_sync_size {
+            return Err(ClipboardError::ContentTooLarge(entry.data.len(), max_sync_size));
         }
         
         Ok(())
     }
     
-    /// Erstellt eine kompakte Repräsentation für die Netzwerkübertragung
-    pub fn create_sync_entry(&self, entry: &ClipboardEntry) -> Result<String, ClipboardError> {
-        // Für große Daten Base64-Kodierung verwenden
-        let sync_entry = SyncClipboardEntry {
-            id: entry.id.clone(),
-            content_type: entry.content_type.clone(),
-            data: match entry.content_type {
-                ClipboardContentType::Image => {
-                    // Bilddaten sind bereits Base64-kodiert
-                    entry.data.clone()
-                },
-                _ => {
-                    // Text-Daten Base64-kodieren für sichere Übertragung
-                    general_purpose::STANDARD.encode(&entry.data)
-                }
+    /// Heftet einen Eintrag an oder löst ihn und speichert den Verlauf
+    fn set_pinned(&self, entry_id: &str, pinned: bool) -> Result<(), ClipboardError> {
+        let mut history = self.history.lock().unwrap();
+        if !history.set_pinned(entry_id, pinned) {
+            return Err(ClipboardError::EntryNotFound(entry_id.to_string()));
+        }
+        
+        self.persist(&history);
+        Ok(())
+    }
+    
+    /// Speichert den Verlauf, falls Persistenz aktiviert ist
+    fn persist(&self, history: &ClipboardHistory) {
+        if !self.config.lock().unwrap().persist_to_disk {
+            return;
+        }
+        
+        if let Some(store) = &*self.store.lock().unwrap() {
+            if let Err(e) = store.save(&history.to_vec()) {
+                log::error!(target: TARGET_CLIPBOARD, "Failed to persist clipboard history: {}", e);
+            }
+        }
+    }
+}
+
+/// Kodiert einen Eintrag für die Netzwerkübertragung (ohne Filterprüfung)
+pub fn encode_sync_entry(entry: &ClipboardEntry) -> Result<String, ClipboardError> {
+    // Für große Daten Base64-Kodierung verwenden
+    let sync_entry = SyncClipboardEntry {
+        protocol_version: PROTOCOL_VERSION,
+        id: entry.id.clone(),
+        content_type: entry.content_type.clone(),
+        data: match entry.content_type {
+            ClipboardContentType::Image => {
+                // Bilddaten sind bereits Base64-kodiert
+                entry.data.clone()
             },
-            metadata: entry.metadata.clone(),
-            timestamp: entry.timestamp,
-        };
+            _ => {
+                // Text-Daten Base64-kodieren für sichere Übertragung
+                general_purpose::STANDARD.encode(&entry.data)
+            }
+        },
+        metadata: entry.metadata.clone(),
+        timestamp: entry.timestamp,
+    };
+    
+    serde_json::to_string(&sync_entry)
+        .map_err(|e| ClipboardError::SerializationError(e.to_string()))
+}
+
+/// Dekodiert einen Eintrag aus dem Netzwerkformat von `encode_sync_entry`
+pub fn decode_sync_entry(payload: &str) -> Result<ClipboardEntry, ClipboardError> {
+    let (sync_entry, _): (SyncClipboardEntry, u32) =
+        protocol::decode_versioned(WireSubsystem::ClipboardSync, payload)?;
+    
+    let data = match sync_entry.content_type {
+        // Bilddaten bleiben Base64-kodiert
+        ClipboardContentType::Image => sync_entry.data,
+        _ => {
+            let bytes = general_purpose::STANDARD.decode(&sync_entry.data)
+                .map_err(|e| ClipboardError::DecodingError(e.to_string()))?;
+            String::from_utf8(bytes)
+                .map_err(|e| ClipboardError::DecodingError(e.to_string()))?
+        }
+    };
+    
+    Ok(ClipboardEntry {
+        id: sync_entry.id,
+        content_type: sync_entry.content_type,
+        data,
+        metadata: ClipboardMetadata {
+            source: "remote".to_string(),
+            ..sync_entry.metadata
+        },
+        timestamp: sync_entry.timestamp,
+        pinned: false,
+    })
+}
+
+/// Vermerkt den sendenden Peer als Ursprung, falls der Eintrag keinen hat,
+/// und zählt die Übertragung
+fn received_from(peer_id: Option<&str>, mut entry: ClipboardEntry) -> ClipboardEntry {
+    let mut origin = entry.metadata.origin.take().unwrap_or_else(|| ClipboardOrigin {
+        peer_id: peer_id.unwrap_or("remote").to_string(),
+        hops: 0,
+    });
+    origin.hops = origin.hops.saturating_add(1);
+    entry.metadata.origin = Some(origin);
+    entry
+}
+
+/// Ein gelesener Zwischenablage-Inhalt samt angebotener Formate
+struct LocalContent {
+    content_type: ClipboardContentType,
+    /// Der Inhalt; über `max_sync_size` nur eine Vorschau
+    data: String,
+    /// Größe des ganzen Inhalts in Bytes
+    size: usize,
+    truncated: bool,
+    mime_type: String,
+    formats: Vec<String>,
+}
+
+impl LocalContent {
+    /// Hash für die Änderungserkennung; bei Vorschauen zählt auch die Größe
+    fn hash(&self) -> u64 {
+        if !self.truncated {
+            return content_hash(&self.data);
+        }
         
-        serde_json::to_string(&sync_entry)
-            .map_err(|e| ClipboardError::SerializationError(e.to_string()))
+        let mut hasher = DefaultHasher::new();
+        self.data.hash(&mut hasher);
+        self.size.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Liest den reichhaltigsten erlaubten Inhalt: HTML, dann Bilder (nur wenn
+/// kein Text angeboten wird), dann Text. Die Formate werden zuerst abgefragt,
+/// Text und HTML nur bis `max_sync_size` gelesen.
+fn read_local_content(
+    provider: &mut dyn ClipboardProvider,
+    config: &ClipboardConfig,
+) -> Result<LocalContent, ClipboardError> {
+    let formats = provider.get_available_formats();
+    let offers = |mime: &str| formats.iter().any(|format| format.eq_ignore_ascii_case(mime));
+    
+    if config.sync_html && offers("text/html") {
+        if let Ok(html) = provider.get_text_limited("text/html", config.max_sync_size) {
+            if !html.text.is_empty() {
+                return Ok(LocalContent {
+                    content_type: ClipboardContentType::Html,
+                    data: html.text,
+                    size: html.size,
+                    truncated: html.truncated,
+                    mime_type: "text/html".to_string(),
+                    formats,
+                });
+            }
+        }
     }
+    
+    let offers_text = ["text/plain", "UTF8_STRING", "STRING"].iter().any(|mime| offers(mime));
+    if config.sync_images && offers("image/png") && !offers_text {
+        let image = provider.get_image()?;
+        let data = general_purpose::STANDARD.encode(&image);
+        return Ok(LocalContent {
+            content_type: ClipboardContentType::Image,
+            size: data.len(),
+            data,
+            truncated: false,
+            mime_type: "image/png".to_string(),
+            formats,
+        });
+    }
+    
+    let text = provider.get_text_limited("text/plain", config.max_sync_size)?;
+    Ok(LocalContent {
+        content_type: ClipboardContentType::Text,
+        data: text.text,
+        size: text.size,
+        truncated: text.truncated,
+        mime_type: "text/plain".to_string(),
+        formats,
+    })
+}
+
+/// Hash eines Inhalts für die Echo-Erkennung
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
 }
 
 impl Drop for ClipboardManager {
@@ -331,10 +967,560 @@ impl Drop for ClipboardManager {
 
 // Vereinfachte Sync-Struktur für Netzwerkübertragung
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct SyncClipboardEntry {
+    /// Protokollversion des Senders, 0 bei Gegenstellen ohne Versionierung
+    #[serde(default)]
+    pub protocol_version: u32,
     pub id: String,
     pub content_type: ClipboardContentType,
     pub data: String, // Immer Base64-kodiert für Sync
     pub metadata: ClipboardMetadata,
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    
+    /// Provider mit festen Inhalten für die Formatauswahl
+    struct FixedProvider {
+        formats: Vec<String>,
+        text: String,
+    }
+    
+    impl ClipboardProvider for FixedProvider {
+        fn get_text(&mut self) -> Result<String, ClipboardError> {
+            Ok(self.text.clone())
+        }
+        
+        fn set_text(&mut self, _text: &str) -> Result<(), ClipboardError> {
+            Ok(())
+        }
+        
+        fn get_image(&mut self) -> Result<Vec<u8>, ClipboardError> {
+            Ok(vec![0x89, b'P', b'N', b'G'])
+        }
+        
+        fn set_image(&mut self, _image_data: &[u8], _format: &str) -> Result<(), ClipboardError> {
+            Ok(())
+        }
+        
+        fn get_html(&mut self) -> Result<String, ClipboardError> {
+            Ok("<b>rich</b>".to_string())
+        }
+        
+        fn is_available(&self) -> bool {
+            true
+        }
+        
+        fn create_clone(&self) -> Box<dyn ClipboardProvider> {
+            Box::new(FixedProvider { formats: self.formats.clone(), text: self.text.clone() })
+        }
+        
+        fn get_available_formats(&self) -> Vec<String> {
+            self.formats.clone()
+        }
+    }
+    
+    fn provider(formats: &[&str]) -> FixedProvider {
+        FixedProvider { formats: formats.iter().map(|f| f.to_string()).collect(), text: "plain".to_string() }
+    }
+    
+    #[test]
+    fn test_prefers_html_when_offered() {
+        let mut clipboard = provider(&["TARGETS", "text/html", "UTF8_STRING"]);
+        let content = read_local_content(&mut clipboard, &ClipboardConfig::default()).unwrap();
+        assert_eq!(content.content_type, ClipboardContentType::Html);
+        assert_eq!(content.data, "<b>rich</b>");
+        
+        let config = ClipboardConfig { sync_html: false, ..ClipboardConfig::default() };
+        let content = read_local_content(&mut clipboard, &config).unwrap();
+        assert_eq!(content.content_type, ClipboardContentType::Text);
+    }
+    
+    #[test]
+    fn test_images_only_without_text() {
+        let mut image_only = provider(&["TARGETS", "image/png"]);
+        let content = read_local_content(&mut image_only, &ClipboardConfig::default()).unwrap();
+        assert_eq!(content.content_type, ClipboardContentType::Image);
+        assert_eq!(content.data, general_purpose::STANDARD.encode([0x89, b'P', b'N', b'G']));
+        
+        // Ein angebotener Text-Fallback (z.B. Dateiname) hat Vorrang
+        let mut with_text = provider(&["image/png", "text/plain"]);
+        let content = read_local_content(&mut with_text, &ClipboardConfig::default()).unwrap();
+        assert_eq!(content.content_type, ClipboardContentType::Text);
+    }
+    
+    #[test]
+    fn test_large_content_is_kept_as_preview_and_not_synced() {
+        let config = ClipboardConfig { max_sync_size: 64, ..ClipboardConfig::default() };
+        let large = FixedProvider { formats: vec!["text/plain".to_string()], text: "x".repeat(100) };
+        
+        let content = read_local_content(&mut *large.create_clone(), &config).unwrap();
+        assert!(content.truncated);
+        assert_eq!((content.data.len(), content.size), (64, 100));
+        
+        let mut manager = ClipboardManager::with_provider(Box::new(large), config).unwrap();
+        assert!(matches!(manager.get_text(), Err(ClipboardError::ContentTooLarge(100, 64))));
+        
+        let sent = Arc::new(AtomicUsize::new(0));
+        {
+            let sent = sent.clone();
+            manager.add_change_callback(move |_| {
+                sent.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        
+        manager.start_monitoring().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while manager.get_history().is_empty() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        manager.stop_monitoring();
+        
+        // Im Verlauf als Vorschau mit der echten Größe, aber nie an Peers
+        let history = manager.get_history();
+        assert_eq!(history.len(), 1);
+        assert!(history[0].metadata.truncated);
+        assert_eq!((history[0].data.len(), history[0].metadata.size), (64, 100));
+        assert_eq!(sent.load(Ordering::SeqCst), 0);
+        assert!(matches!(manager.create_sync_entry(&history[0]), Err(ClipboardError::ContentTooLarge(100, 64))));
+        assert!(matches!(manager.select_history_entry(&history[0].id), Err(ClipboardError::ContentTooLarge(100, 64))));
+    }
+    
+    #[test]
+    fn test_shared_clipboard_uses_the_sync_format_and_stays_out_of_history() {
+        let config = ClipboardConfig { connection_mode: ClipboardConnectionMode::Both, ..ClipboardConfig::default() };
+        let mut manager = ClipboardManager::with_provider(Box::new(provider(&["text/plain"])), config).unwrap();
+        let shared = manager.shared_clipboard();
+        
+        let entry = shared.set("viewer", "snippet", "Grüße").unwrap();
+        let payload = encode_sync_entry(&entry).unwrap();
+        
+        let viewer = SharedClipboard::new(ClipboardConnectionMode::Both, 4, 1024);
+        assert!(viewer.apply_remote("host", decode_sync_entry(&payload).unwrap()).unwrap());
+        assert_eq!(viewer.get("host", "snippet").unwrap().data, "Grüße");
+        assert!(manager.get_history().is_empty());
+        
+        // Die Konfiguration setzt die Vorgabe für Verbindungen ohne eigene Einstellung
+        manager.update_config(ClipboardConfig { connection_mode: ClipboardConnectionMode::SharedOnly, ..ClipboardConfig::default() }).unwrap();
+        assert_eq!(shared.mode_for(Some("viewer")), ClipboardConnectionMode::SharedOnly);
+    }
+    
+    #[test]
+    fn test_sync_entry_roundtrip() {
+        let entry = ClipboardEntry {
+            id: "entry-1".to_string(),
+            content_type: ClipboardContentType::Text,
+            data: "Grüße".to_string(),
+            metadata: ClipboardMetadata {
+                size: 6,
+                mime_type: "text/plain".to_string(),
+                source: "local".to_string(),
+                truncated: false,
+                origin: None,
+            },
+            timestamp: chrono::Utc::now(),
+            pinned: false,
+        };
+        
+        let decoded = decode_sync_entry(&encode_sync_entry(&entry).unwrap()).unwrap();
+        assert_eq!(decoded.id, entry.id);
+        assert_eq!(decoded.data, entry.data);
+        assert_eq!(decoded.metadata.source, "remote");
+        assert!(decode_sync_entry("{").is_err());
+    }
+
+    #[test]
+    fn test_sync_entry_wire_snapshot() {
+        let entry = ClipboardEntry {
+            id: "entry-1".to_string(),
+            content_type: ClipboardContentType::Text,
+            data: "hi".to_string(),
+            metadata: ClipboardMetadata {
+                size: 2,
+                mime_type: "text/plain".to_string(),
+                source: "local".to_string(),
+                truncated: false,
+                origin: None,
+            },
+            timestamp: "2024-01-01T00:00:00Z".parse().unwrap(),
+            pinned: false,
+        };
+
+        let payload: serde_json::Value = serde_json::from_str(&encode_sync_entry(&entry).unwrap()).unwrap();
+        assert_eq!(payload, serde_json::json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "id": "entry-1",
+            "contentType": "Text",
+            "data": "aGk=",
+            "metadata": { "size": 2, "mimeType": "text/plain", "source": "local", "truncated": false },
+            "timestamp": "2024-01-01T00:00:00Z",
+        }));
+        assert_eq!(serde_json::to_value(&entry).unwrap(), serde_json::json!({
+            "id": "entry-1",
+            "contentType": "Text",
+            "data": "hi",
+            "metadata": { "size": 2, "mimeType": "text/plain", "source": "local", "truncated": false },
+            "timestamp": "2024-01-01T00:00:00Z",
+            "pinned": false,
+        }));
+
+        // Entries from senders without versioning are rejected, not misread
+        let legacy = r#"{"id":"x","content_type":"Text","data":"aGk=","metadata":{"size":2,"mime_type":"text/plain","source":"local"},"timestamp":"2024-01-01T00:00:00Z"}"#;
+        assert!(matches!(decode_sync_entry(legacy), Err(ClipboardError::VersionMismatch(_))));
+    }
+
+    #[test]
+    fn test_remote_entries_follow_sync_policy() {
+        use sync_policy::SyncDirection;
+        
+        let mut manager = ClipboardManager::with_provider(Box::new(provider(&["text/plain"])), ClipboardConfig::default()).unwrap();
+        let remote = |id: &str| ClipboardEntry {
+            id: id.to_string(),
+            content_type: ClipboardContentType::Text,
+            data: "from peer".to_string(),
+            metadata: ClipboardMetadata {
+                size: 9,
+                mime_type: "text/plain".to_string(),
+                source: "remote".to_string(),
+                truncated: false,
+                origin: None,
+            },
+            timestamp: chrono::Utc::now(),
+            pinned: false,
+        };
+        
+        manager.set_sync_policy(ClipboardSyncPolicy { direction: SyncDirection::HostToClient, ..ClipboardSyncPolicy::default() });
+        assert_eq!(manager.sync_remote_entry(None, remote("r1")).unwrap(), RemoteSyncOutcome::DirectionBlocked);
+        assert!(manager.set_remote_text("blocked").is_err());
+        assert!(manager.get_sync_status().last_applied_remote_id.is_none());
+        
+        manager.set_sync_policy(ClipboardSyncPolicy::default());
+        assert_eq!(manager.sync_remote_entry(None, remote("r2")).unwrap(), RemoteSyncOutcome::Applied);
+        assert_eq!(manager.sync_remote_entry(None, remote("r2")).unwrap(), RemoteSyncOutcome::Duplicate);
+        assert_eq!(manager.get_sync_status().last_applied_remote_id.as_deref(), Some("r2"));
+        assert_eq!(manager.get_history().len(), 1);
+    }
+    
+    #[test]
+    fn test_sealed_entries_outside_freshness_window_are_rejected() {
+        let mut manager = ClipboardManager::with_provider(Box::new(provider(&["text/plain"])), ClipboardConfig::default()).unwrap();
+        let remote = |id: &str| ClipboardEntry {
+            id: id.to_string(),
+            content_type: ClipboardContentType::Text,
+            data: "from peer".to_string(),
+            metadata: ClipboardMetadata {
+                size: 9,
+                mime_type: "text/plain".to_string(),
+                source: "remote".to_string(),
+                truncated: false,
+                origin: None,
+            },
+            timestamp: chrono::Utc::now(),
+            pinned: false,
+        };
+        
+        let stale = chrono::Utc::now() - chrono::Duration::seconds(120);
+        assert!(matches!(
+            manager.sync_sealed_entry(None, remote("old"), stale),
+            Err(ClipboardError::StaleEntry { max_secs: 30, .. })
+        ));
+        assert!(manager.get_history().is_empty());
+        assert_eq!(manager.sync_sealed_entry(None, remote("new"), chrono::Utc::now()).unwrap(), RemoteSyncOutcome::Applied);
+        
+        // 0 schaltet die Prüfung ab
+        manager.update_config(ClipboardConfig { sync_freshness_secs: 0, ..ClipboardConfig::default() }).unwrap();
+        assert_eq!(manager.sync_sealed_entry(None, remote("late"), stale).unwrap(), RemoteSyncOutcome::Applied);
+    }
+    
+    /// Zwischenablage im Speicher, die sich alle Kopien teilen
+    #[derive(Clone, Default)]
+    struct MemoryProvider {
+        content: Arc<Mutex<String>>,
+    }
+    
+    impl ClipboardProvider for MemoryProvider {
+        fn get_text(&mut self) -> Result<String, ClipboardError> {
+            let content = self.content.lock().unwrap().clone();
+            if content.is_empty() {
+                return Err(ClipboardError::EmptyClipboard);
+            }
+            Ok(content)
+        }
+        
+        fn set_text(&mut self, text: &str) -> Result<(), ClipboardError> {
+            *self.content.lock().unwrap() = text.to_string();
+            Ok(())
+        }
+        
+        fn get_image(&mut self) -> Result<Vec<u8>, ClipboardError> {
+            Err(ClipboardError::EmptyClipboard)
+        }
+        
+        fn set_image(&mut self, _image_data: &[u8], _format: &str) -> Result<(), ClipboardError> {
+            Ok(())
+        }
+        
+        fn is_available(&self) -> bool {
+            true
+        }
+        
+        fn create_clone(&self) -> Box<dyn ClipboardProvider> {
+            Box::new(self.clone())
+        }
+    }
+    
+    /// Ein Peer mit überwachter Zwischenablage; gesendete Einträge werden
+    /// gesammelt und vom Test an die anderen Peers zugestellt
+    struct SimulatedPeer {
+        id: &'static str,
+        manager: ClipboardManager,
+        clipboard: MemoryProvider,
+        sent: Arc<Mutex<Vec<String>>>,
+    }
+    
+    impl SimulatedPeer {
+        fn new(id: &'static str, config: ClipboardConfig) -> Self {
+            let clipboard = MemoryProvider::default();
+            let mut manager = ClipboardManager::with_provider(Box::new(clipboard.clone()), config).unwrap();
+            let sent = Arc::new(Mutex::new(Vec::new()));
+            {
+                let sent = sent.clone();
+                manager.add_change_callback(move |entry| {
+                    sent.lock().unwrap().push(encode_sync_entry(entry).unwrap());
+                });
+            }
+            manager.start_monitoring().unwrap();
+            SimulatedPeer { id, manager, clipboard, sent }
+        }
+        
+        /// Eine Anwendung setzt die Zwischenablage
+        fn copy(&self, text: &str) {
+            *self.clipboard.content.lock().unwrap() = text.to_string();
+        }
+        
+        fn take_sent(&self) -> Vec<String> {
+            std::mem::take(&mut *self.sent.lock().unwrap())
+        }
+        
+        /// Wartet, bis der Monitor eine Änderung gesendet hat
+        fn wait_for_sent(&self) -> Vec<String> {
+            let deadline = Instant::now() + Duration::from_secs(3);
+            while self.sent.lock().unwrap().is_empty() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
+            }
+            self.take_sent()
+        }
+        
+        fn receive(&mut self, from: &str, payload: &str) -> RemoteSyncOutcome {
+            self.manager.sync_remote_entry(Some(from), decode_sync_entry(payload).unwrap()).unwrap()
+        }
+    }
+    
+    /// Länger als zwei Abfragen des Monitors
+    const MONITOR_SETTLE: Duration = Duration::from_millis(1500);
+    
+    #[test]
+    fn test_three_peers_do_not_bounce_applied_entries() {
+        let mut peers = ["a", "b", "c"].map(|id| SimulatedPeer::new(id, ClipboardConfig::default()));
+        for peer in &peers {
+            // Der spätere Eintrag gewinnt immer, unabhängig von den IDs
+            peer.manager.set_sync_policy(ClipboardSyncPolicy { conflict_tolerance_ms: 0, ..ClipboardSyncPolicy::default() });
+        }
+        
+        // A und C kopieren kurz nacheinander, jeder sendet an beide anderen
+        for (sender, text) in [(0, "from a"), (2, "from c")] {
+            peers[sender].copy(text);
+            let sent = peers[sender].wait_for_sent();
+            assert_eq!(sent.len(), 1);
+            let from = peers[sender].id;
+            for receiver in (0..3).filter(|&receiver| receiver != sender) {
+                assert_eq!(peers[receiver].receive(from, &sent[0]), RemoteSyncOutcome::Applied);
+            }
+        }
+        
+        // Bei B kommt das Setzen des ersten Eintrags verspätet an; das ist kein
+        // neuer lokaler Inhalt, obwohl inzwischen der von C übernommen wurde
+        peers[1].copy("from a");
+        thread::sleep(MONITOR_SETTLE);
+        for peer in &peers {
+            assert!(peer.take_sent().is_empty(), "{} re-broadcast an applied entry", peer.id);
+        }
+        
+        let origins: Vec<_> = peers[1].manager.get_history().into_iter()
+            .map(|entry| entry.metadata.origin.unwrap())
+            .collect();
+        assert_eq!(origins, vec![
+            ClipboardOrigin { peer_id: "a".to_string(), hops: 1 },
+            ClipboardOrigin { peer_id: "c".to_string(), hops: 1 },
+        ]);
+    }
+    
+    #[test]
+    fn test_relayed_entries_are_forwarded_once() {
+        // B verbindet A und C, alle geben übernommene Einträge weiter
+        let config = ClipboardConfig { relay_remote_entries: true, ..ClipboardConfig::default() };
+        let [mut a, mut b, mut c] = ["a", "b", "c"].map(|id| SimulatedPeer::new(id, config.clone()));
+        
+        a.copy("from a");
+        let sent = a.wait_for_sent();
+        assert_eq!(b.receive("a", &sent[0]), RemoteSyncOutcome::Applied);
+        
+        // B gibt den Eintrag mit Ursprung A weiter
+        let relayed = b.take_sent();
+        assert_eq!(relayed.len(), 1);
+        assert_eq!(a.receive("b", &relayed[0]), RemoteSyncOutcome::Duplicate);
+        assert_eq!(c.receive("b", &relayed[0]), RemoteSyncOutcome::Applied);
+        assert_eq!(c.manager.get_history()[0].metadata.origin, Some(ClipboardOrigin { peer_id: "a".to_string(), hops: 2 }));
+        
+        // C gibt ihn nicht noch einmal weiter, und kein Monitor meldet ihn erneut
+        thread::sleep(MONITOR_SETTLE);
+        for peer in [&a, &b, &c] {
+            assert!(peer.take_sent().is_empty(), "{} sent the entry again", peer.id);
+        }
+        assert_eq!(*c.clipboard.content.lock().unwrap(), "from a");
+    }
+    
+    fn import_text(manager: &ClipboardManager, id: &str) {
+        manager.import_entry(&format!(
+            r#"{{"id":"{}","content_type":"Text","data":"text {}","metadata":{{"size":6,"mime_type":"text/plain","source":"local"}},"timestamp":"2024-01-01T00:00:00Z"}}"#,
+            id, id
+        )).unwrap();
+    }
+    
+    #[test]
+    fn test_history_loads_in_background_and_wipes() {
+        let env = crate::test_env::TestEnv::new();
+        env.set_stdout("secret-tool", "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=");
+        let dir = std::env::temp_dir().join(format!("smoldesk-clipboard-{}", uuid::Uuid::new_v4()));
+        let config = ClipboardConfig { persist_to_disk: true, ..ClipboardConfig::default() };
+        
+        let manager = ClipboardManager::with_provider(Box::new(provider(&["text/plain"])), config.clone()).unwrap();
+        manager.set_storage_dir(&dir, env.runner(), |result| assert_eq!(result.unwrap(), 0)).join().unwrap();
+        import_text(&manager, "a");
+        manager.pin_entry("a").unwrap();
+        
+        // Einträge vor dem Laden kommen hinter den gespeicherten Verlauf
+        let manager = ClipboardManager::with_provider(Box::new(provider(&["text/plain"])), config).unwrap();
+        import_text(&manager, "b");
+        manager.set_storage_dir(&dir, env.runner(), |result| assert_eq!(result.unwrap(), 0)).join().unwrap();
+        let ids: Vec<String> = manager.get_history().into_iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+        
+        manager.wipe_history(true).unwrap();
+        assert_eq!(manager.get_history().len(), 1);
+        manager.wipe_history(false).unwrap();
+        assert!(manager.get_history().is_empty());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn test_history_stays_in_memory_without_keyring() {
+        let env = crate::test_env::TestEnv::new();
+        env.set_exit_code("secret-tool", 1);
+        env.set_stderr("secret-tool", "The name org.freedesktop.secrets was not provided");
+        env.set_exit_code("keyctl", 1);
+        let dir = std::env::temp_dir().join(format!("smoldesk-clipboard-{}", uuid::Uuid::new_v4()));
+        let config = ClipboardConfig { persist_to_disk: true, ..ClipboardConfig::default() };
+        
+        let manager = ClipboardManager::with_provider(Box::new(provider(&["text/plain"])), config).unwrap();
+        manager.set_storage_dir(&dir, env.runner(), |result| {
+            assert!(matches!(result, Err(ClipboardError::KeyringUnavailable(_))));
+        }).join().unwrap();
+        
+        import_text(&manager, "a");
+        assert_eq!(manager.get_history().len(), 1);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[test]
+    fn test_pinned_entries_survive_clear_and_can_be_reselected() {
+        let mut manager = ClipboardManager::with_provider(Box::new(provider(&["text/plain"])), ClipboardConfig::default()).unwrap();
+        for id in ["a", "b"] {
+            manager.import_entry(&format!(
+                r#"{{"id":"{}","content_type":"Text","data":"text {}","metadata":{{"size":6,"mime_type":"text/plain","source":"local"}},"timestamp":"2024-01-01T00:00:00Z"}}"#,
+                id, id
+            )).unwrap();
+        }
+        
+        manager.pin_entry("a").unwrap();
+        assert!(manager.pin_entry("missing").is_err());
+        manager.clear_history();
+        
+        let history = manager.get_history();
+        assert_eq!(history.len(), 1);
+        assert!(history[0].pinned);
+        
+        manager.select_history_entry("a").unwrap();
+        assert_eq!(*manager.last_content.lock().unwrap(), Some(content_hash("text a")));
+        assert!(manager.select_history_entry("b").is_err());
+        
+        manager.unpin_entry("a").unwrap();
+        manager.clear_history();
+        assert!(manager.get_history().is_empty());
+    }
+    
+    /// Provider, der zählt, wie viele seiner Kopien noch existieren
+    struct TrackedProvider {
+        live: Arc<AtomicUsize>,
+    }
+    
+    impl TrackedProvider {
+        fn new(live: &Arc<AtomicUsize>) -> Self {
+            live.fetch_add(1, Ordering::SeqCst);
+            TrackedProvider { live: live.clone() }
+        }
+    }
+    
+    impl Drop for TrackedProvider {
+        fn drop(&mut self) {
+            self.live.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+    
+    impl ClipboardProvider for TrackedProvider {
+        fn get_text(&mut self) -> Result<String, ClipboardError> {
+            Ok("plain".to_string())
+        }
+        
+        fn set_text(&mut self, _text: &str) -> Result<(), ClipboardError> {
+            Ok(())
+        }
+        
+        fn get_image(&mut self) -> Result<Vec<u8>, ClipboardError> {
+            Err(ClipboardError::EmptyClipboard)
+        }
+        
+        fn set_image(&mut self, _image_data: &[u8], _format: &str) -> Result<(), ClipboardError> {
+            Ok(())
+        }
+        
+        fn is_available(&self) -> bool {
+            true
+        }
+        
+        fn create_clone(&self) -> Box<dyn ClipboardProvider> {
+            Box::new(TrackedProvider::new(&self.live))
+        }
+    }
+    
+    #[test]
+    fn test_drop_joins_monitor_thread() {
+        let live = Arc::new(AtomicUsize::new(0));
+        let mut manager = ClipboardManager::with_provider(Box::new(TrackedProvider::new(&live)), ClipboardConfig::default()).unwrap();
+        
+        // Der Überwachungs-Thread hält eine eigene Kopie des Providers
+        manager.start_monitoring().unwrap();
+        assert_eq!(live.load(Ordering::SeqCst), 2);
+        
+        drop(manager);
+        assert_eq!(live.load(Ordering::SeqCst), 0);
+    }
+}
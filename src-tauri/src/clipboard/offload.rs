@@ -0,0 +1,513 @@
+// src-tauri/src/clipboard/offload.rs - Große Bilder per Dateiübertragung statt inline synchronisieren
+//
+// Bilder über `image_offload_threshold` gehen nicht als Base64 in der
+// Sync-Nachricht an den Peer. Stattdessen startet der Sender eine
+// Dateiübertragung und schickt einen `ImageReference`-Eintrag mit der
+// Transfer-ID. Der Empfänger nimmt genau diese Übertragung in ein temporäres
+// Verzeichnis an und setzt das Bild nach Abschluss in seine Zwischenablage.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::clipboard::error::ClipboardError;
+use crate::clipboard::types::{ClipboardContentType, ClipboardEntry, ClipboardMetadata};
+use crate::file_transfer::error::FileTransferError;
+use crate::file_transfer::scheduler::TransferPriority;
+use crate::file_transfer::types::TransferEvent;
+use crate::file_transfer::FileTransferManager;
+use crate::logging::TARGET_CLIPBOARD;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Inhalt eines `ImageReference`-Eintrags (als JSON in `ClipboardEntry::data`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageReference {
+    /// Übertragung, die das Bild liefert
+    #[serde(alias = "transfer_id")]
+    pub transfer_id: String,
+
+    /// Abmessungen aus dem PNG-Header, für Vorschau und Platzhalter
+    pub width: u32,
+    pub height: u32,
+
+    /// Größe der PNG-Datei in Bytes
+    pub size: u64,
+}
+
+impl ImageReference {
+    /// Liest die Referenz aus einem `ImageReference`-Eintrag
+    pub fn from_entry(entry: &ClipboardEntry) -> Result<Self, ClipboardError> {
+        if entry.content_type != ClipboardContentType::ImageReference {
+            return Err(ClipboardError::InvalidFormat(format!("Entry {} is not an image reference", entry.id)));
+        }
+        serde_json::from_str(&entry.data)
+            .map_err(|e| ClipboardError::SerializationError(e.to_string()))
+    }
+}
+
+/// Breite und Höhe aus dem IHDR-Chunk einer PNG-Datei
+pub fn png_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    if data.len() < 24 || data[..8] != PNG_SIGNATURE || &data[12..16] != b"IHDR" {
+        return None;
+    }
+
+    let width = u32::from_be_bytes(data[16..20].try_into().ok()?);
+    let height = u32::from_be_bytes(data[20..24].try_into().ok()?);
+    Some((width, height))
+}
+
+/// Angekündigtes Bild eines Peers, dessen Übertragung noch läuft
+struct PendingImage {
+    /// Der `ImageReference`-Eintrag; ID und Zeitstempel gehen auf das Bild über
+    entry: ClipboardEntry,
+    reference: ImageReference,
+    peer_id: String,
+    destination: PathBuf,
+    accepted: bool,
+}
+
+/// Koordiniert Zwischenablage und Dateiübertragung für ausgelagerte Bilder
+pub struct ImageOffload {
+    /// Bilder darüber (Base64-Größe in Bytes) werden ausgelagert; 0 = nie
+    threshold: Mutex<usize>,
+
+    /// Temporäre Dateien ausgehender und eingehender Bilder
+    staging_dir: PathBuf,
+
+    /// Ausgehende Bilder: Transfer-ID -> temporäre Quelldatei
+    outgoing: Mutex<HashMap<String, PathBuf>>,
+
+    /// Angekündigte Bilder nach Transfer-ID
+    incoming: Mutex<HashMap<String, PendingImage>>,
+}
+
+impl ImageOffload {
+    pub fn new(threshold: usize) -> Self {
+        Self::with_staging_dir(threshold, std::env::temp_dir().join("smoldesk-clipboard"))
+    }
+
+    pub fn with_staging_dir(threshold: usize, staging_dir: PathBuf) -> Self {
+        ImageOffload {
+            threshold: Mutex::new(threshold),
+            staging_dir,
+            outgoing: Mutex::new(HashMap::new()),
+            incoming: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn set_threshold(&self, threshold: usize) {
+        *self.threshold.lock().unwrap() = threshold;
+    }
+
+    /// Soll ein lokaler Eintrag per Dateiübertragung statt inline gesendet werden?
+    pub fn should_offload(&self, entry: &ClipboardEntry) -> bool {
+        let threshold = *self.threshold.lock().unwrap();
+        entry.content_type == ClipboardContentType::Image && threshold > 0 && entry.data.len() > threshold
+    }
+
+    /// Gehört die Übertragung zu einem ausgelagerten Bild?
+    pub fn owns(&self, transfer_id: &str) -> bool {
+        self.outgoing.lock().unwrap().contains_key(transfer_id)
+            || self.incoming.lock().unwrap().contains_key(transfer_id)
+    }
+
+    /// Startet die Übertragung eines Bildes an einen Peer und liefert den
+    /// `ImageReference`-Eintrag, der statt des Bildes synchronisiert wird
+    pub async fn offer(
+        &self,
+        file_transfer: &FileTransferManager,
+        entry: &ClipboardEntry,
+        peer_id: &str
+    ) -> Result<ClipboardEntry, ClipboardError> {
+        let image_data = general_purpose::STANDARD.decode(&entry.data)
+            .map_err(|e| ClipboardError::DecodingError(e.to_string()))?;
+        let (width, height) = png_dimensions(&image_data)
+            .ok_or_else(|| ClipboardError::InvalidFormat("Clipboard image is not a PNG".to_string()))?;
+
+        let source = self.staged_path()?;
+        fs::write(&source, &image_data)
+            .map_err(|e| ClipboardError::IoError(e.to_string()))?;
+
+        // Bilder sollen vor großen Dateien ankommen, die Nutzer wartet auf das Einfügen
+        let transfer_id = match file_transfer.start_upload(&source, peer_id, None, TransferPriority::High).await {
+            Ok(transfer_id) => transfer_id,
+            Err(e) => {
+                remove_staged(&source);
+                return Err(transfer_error(e));
+            }
+        };
+        self.outgoing.lock().unwrap().insert(transfer_id.clone(), source);
+
+        let reference = ImageReference {
+            transfer_id,
+            width,
+            height,
+            size: image_data.len() as u64,
+        };
+        log::debug!(target: TARGET_CLIPBOARD, "Offloading {}x{} clipboard image to {} as transfer {}",
+            width, height, peer_id, reference.transfer_id);
+
+        Ok(ClipboardEntry {
+            id: entry.id.clone(),
+            content_type: ClipboardContentType::ImageReference,
+            data: serde_json::to_string(&reference)
+                .map_err(|e| ClipboardError::SerializationError(e.to_string()))?,
+            metadata: entry.metadata.clone(),
+            timestamp: entry.timestamp,
+            pinned: false,
+        })
+    }
+
+    /// Merkt sich ein angekündigtes Bild und nimmt dessen Übertragung an. Trifft
+    /// die Transfer-Anfrage erst danach ein, wird sie in `handle_transfer_event`
+    /// angenommen.
+    pub async fn expect(
+        &self,
+        file_transfer: &FileTransferManager,
+        peer_id: &str,
+        entry: ClipboardEntry
+    ) -> Result<(), ClipboardError> {
+        let reference = ImageReference::from_entry(&entry)?;
+        let transfer_id = reference.transfer_id.clone();
+        if self.owns(&transfer_id) {
+            return Ok(());
+        }
+
+        let destination = self.staged_path()?;
+        self.incoming.lock().unwrap().insert(transfer_id.clone(), PendingImage {
+            entry,
+            reference,
+            peer_id: peer_id.to_string(),
+            destination,
+            accepted: false,
+        });
+
+        if file_transfer.get_transfer_info(&transfer_id).is_some() {
+            if let Err(e) = self.accept(file_transfer, &transfer_id).await {
+                self.discard_incoming(&transfer_id);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Verarbeitet Events der Dateiübertragung. Liefert den fertigen
+    /// Bild-Eintrag, sobald ein angekündigtes Bild vollständig empfangen wurde.
+    pub async fn handle_transfer_event(
+        &self,
+        file_transfer: &FileTransferManager,
+        event: &TransferEvent
+    ) -> Option<ClipboardEntry> {
+        match event {
+            TransferEvent::TransferRequested { transfer_id, .. } => {
+                let waiting = self.incoming.lock().unwrap()
+                    .get(transfer_id)
+                    .map_or(false, |pending| !pending.accepted);
+                if waiting {
+                    if let Err(e) = self.accept(file_transfer, transfer_id).await {
+                        log::warn!(target: TARGET_CLIPBOARD, "Failed to accept clipboard image transfer {}: {}", transfer_id, e);
+                        self.discard_incoming(transfer_id);
+                    }
+                }
+                None
+            },
+            TransferEvent::TransferCompleted { transfer_id } => {
+                if let Some(source) = self.outgoing.lock().unwrap().remove(transfer_id) {
+                    remove_staged(&source);
+                    return None;
+                }
+
+                let pending = self.incoming.lock().unwrap().remove(transfer_id)?;
+                let image = read_received_image(&pending);
+                remove_staged(&pending.destination);
+                match image {
+                    Ok(entry) => Some(entry),
+                    Err(e) => {
+                        log::warn!(target: TARGET_CLIPBOARD, "Discarding clipboard image from transfer {}: {}", transfer_id, e);
+                        None
+                    }
+                }
+            },
+            TransferEvent::TransferRejected { transfer_id, .. }
+            | TransferEvent::TransferCancelled { transfer_id }
+            | TransferEvent::TransferFailed { transfer_id, .. } => {
+                if let Some(source) = self.outgoing.lock().unwrap().remove(transfer_id) {
+                    remove_staged(&source);
+                }
+                self.discard_incoming(transfer_id);
+                None
+            },
+            _ => None,
+        }
+    }
+
+    /// Nimmt die Übertragung eines angekündigten Bildes an, wenn Peer und Größe passen
+    async fn accept(&self, file_transfer: &FileTransferManager, transfer_id: &str) -> Result<(), ClipboardError> {
+        let info = file_transfer.get_transfer_info(transfer_id)
+            .ok_or_else(|| ClipboardError::EntryNotFound(transfer_id.to_string()))?;
+
+        let destination = {
+            let mut incoming = self.incoming.lock().unwrap();
+            let pending = incoming.get_mut(transfer_id)
+                .ok_or_else(|| ClipboardError::EntryNotFound(transfer_id.to_string()))?;
+
+            // Eine Referenz darf nur die Übertragung desselben Peers annehmen
+            if info.peer_id != pending.peer_id || info.file_metadata.size != pending.reference.size {
+                return Err(ClipboardError::PermissionDenied(format!(
+                    "Transfer {} does not match the announced clipboard image", transfer_id
+                )));
+            }
+            pending.accepted = true;
+            pending.destination.clone()
+        };
+
+        file_transfer.accept_transfer(transfer_id, &destination).await
+            .map_err(transfer_error)
+    }
+
+    fn discard_incoming(&self, transfer_id: &str) {
+        if let Some(pending) = self.incoming.lock().unwrap().remove(transfer_id) {
+            remove_staged(&pending.destination);
+        }
+    }
+
+    /// Neuer Pfad im Staging-Verzeichnis
+    fn staged_path(&self) -> Result<PathBuf, ClipboardError> {
+        fs::create_dir_all(&self.staging_dir)
+            .map_err(|e| ClipboardError::IoError(e.to_string()))?;
+        Ok(self.staging_dir.join(format!("{}.png", Uuid::new_v4())))
+    }
+}
+
+/// Baut aus einer empfangenen Datei den Bild-Eintrag des Peers
+fn read_received_image(pending: &PendingImage) -> Result<ClipboardEntry, ClipboardError> {
+    let image_data = fs::read(&pending.destination)
+        .map_err(|e| ClipboardError::IoError(e.to_string()))?;
+    if png_dimensions(&image_data).is_none() {
+        return Err(ClipboardError::InvalidFormat("Received clipboard image is not a PNG".to_string()));
+    }
+
+    let data = general_purpose::STANDARD.encode(&image_data);
+    Ok(ClipboardEntry {
+        id: pending.entry.id.clone(),
+        content_type: ClipboardContentType::Image,
+        metadata: ClipboardMetadata {
+            size: data.len(),
+            mime_type: "image/png".to_string(),
+            source: "remote".to_string(),
+            truncated: false,
+            origin: pending.entry.metadata.origin.clone(),
+        },
+        data,
+        timestamp: pending.entry.timestamp,
+        pinned: false,
+    })
+}
+
+fn remove_staged(path: &Path) {
+    if let Err(e) = fs::remove_file(path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            log::warn!(target: TARGET_CLIPBOARD, "Failed to remove staged clipboard image {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn transfer_error(error: FileTransferError) -> ClipboardError {
+    ClipboardError::NetworkError(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use crate::clipboard::sync_policy::RemoteSyncOutcome;
+    use crate::clipboard::types::{ClipboardConfig, ClipboardProvider};
+    use crate::clipboard::{decode_sync_entry, encode_sync_entry, ClipboardManager};
+    use crate::file_transfer::types::{TransferConfig, TransferMessage};
+
+    /// Provider, der eingefügte Bilder festhält
+    #[derive(Clone, Default)]
+    struct ImageProvider {
+        pasted: Arc<Mutex<Option<Vec<u8>>>>,
+    }
+
+    impl ClipboardProvider for ImageProvider {
+        fn get_text(&mut self) -> Result<String, ClipboardError> {
+            Err(ClipboardError::EmptyClipboard)
+        }
+
+        fn set_text(&mut self, _text: &str) -> Result<(), ClipboardError> {
+            Ok(())
+        }
+
+        fn get_image(&mut self) -> Result<Vec<u8>, ClipboardError> {
+            Err(ClipboardError::EmptyClipboard)
+        }
+
+        fn set_image(&mut self, image_data: &[u8], _format: &str) -> Result<(), ClipboardError> {
+            *self.pasted.lock().unwrap() = Some(image_data.to_vec());
+            Ok(())
+        }
+
+        fn is_available(&self) -> bool {
+            true
+        }
+
+        fn create_clone(&self) -> Box<dyn ClipboardProvider> {
+            Box::new(self.clone())
+        }
+    }
+
+    /// Eine Seite der Dateiübertragung mit ausgehenden Nachrichten und Events
+    struct Peer {
+        manager: FileTransferManager,
+        outgoing: mpsc::UnboundedReceiver<(String, TransferMessage)>,
+        events: mpsc::UnboundedReceiver<TransferEvent>,
+    }
+
+    fn peer() -> Peer {
+        let mut manager = FileTransferManager::new(TransferConfig::default()).unwrap();
+        let (message_sender, outgoing) = mpsc::unbounded_channel();
+        let (event_sender, events) = mpsc::unbounded_channel();
+        manager.set_message_sender(message_sender);
+        manager.set_event_sender(event_sender);
+        Peer { manager, outgoing, events }
+    }
+
+    /// Bildschirmfoto-ähnliches PNG, das sich schlecht komprimieren lässt
+    fn screenshot_png(width: u32, height: u32) -> Vec<u8> {
+        let pixels = image::RgbImage::from_fn(width, height, |x, y| {
+            let noise = x.wrapping_mul(7919) ^ y.wrapping_mul(104_729) ^ x.wrapping_mul(y);
+            image::Rgb([noise as u8, (noise >> 8) as u8, (x + y) as u8])
+        });
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::ImageRgb8(pixels).write_to(&mut png, image::ImageOutputFormat::Png).unwrap();
+        png.into_inner()
+    }
+
+    fn image_entry(png: &[u8]) -> ClipboardEntry {
+        let data = general_purpose::STANDARD.encode(png);
+        ClipboardEntry {
+            id: "screenshot".to_string(),
+            content_type: ClipboardContentType::Image,
+            metadata: ClipboardMetadata {
+                size: data.len(),
+                mime_type: "image/png".to_string(),
+                source: "local".to_string(),
+                truncated: false,
+                origin: None,
+            },
+            data,
+            timestamp: chrono::Utc::now(),
+            pinned: false,
+        }
+    }
+
+    fn staged_images(dir: &Path) -> usize {
+        fs::read_dir(dir).map_or(0, |entries| {
+            entries.filter_map(Result::ok)
+                .filter(|entry| entry.path().extension().map_or(false, |ext| ext == "png"))
+                .count()
+        })
+    }
+
+    #[test]
+    fn test_offload_threshold() {
+        let png = screenshot_png(32, 16);
+        assert_eq!(png_dimensions(&png), Some((32, 16)));
+        assert_eq!(png_dimensions(b"GIF89a"), None);
+
+        let entry = image_entry(&png);
+        assert!(ImageOffload::new(64).should_offload(&entry));
+        assert!(!ImageOffload::new(entry.data.len()).should_offload(&entry));
+        assert!(!ImageOffload::new(0).should_offload(&entry));
+
+        let text = ClipboardEntry { content_type: ClipboardContentType::Text, ..entry };
+        assert!(!ImageOffload::new(64).should_offload(&text));
+    }
+
+    #[tokio::test]
+    async fn test_screenshot_is_pasted_after_transfer() {
+        let dir = std::env::temp_dir().join(format!("smoldesk-offload-{}", Uuid::new_v4()));
+        let host_offload = ImageOffload::with_staging_dir(1024, dir.join("host"));
+        let viewer_offload = ImageOffload::with_staging_dir(1024, dir.join("viewer"));
+        let mut host_transfer = peer();
+        let mut viewer_transfer = peer();
+
+        let viewer_clipboard = ImageProvider::default();
+        let pasted = viewer_clipboard.pasted.clone();
+        let mut viewer = ClipboardManager::with_provider(Box::new(viewer_clipboard), ClipboardConfig::default()).unwrap();
+
+        // Host: statt des Bildes geht nur die Referenz über den Sync-Kanal
+        let png = screenshot_png(256, 192);
+        let entry = image_entry(&png);
+        assert!(host_offload.should_offload(&entry));
+        let reference = host_offload.offer(&host_transfer.manager, &entry, "viewer").await.unwrap();
+        let payload = encode_sync_entry(&reference).unwrap();
+        assert!(payload.len() < 1024);
+        let (_, request) = host_transfer.outgoing.try_recv().unwrap();
+
+        // Viewer: die Referenz trifft vor der Transfer-Anfrage ein
+        let reference = decode_sync_entry(&payload).unwrap();
+        let announced = ImageReference::from_entry(&reference).unwrap();
+        assert_eq!((announced.width, announced.height, announced.size), (256, 192, png.len() as u64));
+        assert_eq!(viewer.sync_remote_entry(Some("host"), reference.clone()).unwrap(), RemoteSyncOutcome::Pending);
+        viewer_offload.expect(&viewer_transfer.manager, "host", reference).await.unwrap();
+
+        viewer_transfer.manager.handle_transfer_message("host", request).await.unwrap();
+        let requested = viewer_transfer.events.try_recv().unwrap();
+        assert!(viewer_offload.handle_transfer_event(&viewer_transfer.manager, &requested).await.is_none());
+        let (_, accept) = viewer_transfer.outgoing.try_recv().unwrap();
+
+        // Chunks und Bestätigungen weiterreichen, bis das Bild fertig ist
+        let (host_manager, host_outgoing) = (&host_transfer.manager, &mut host_transfer.outgoing);
+        let (viewer_manager, viewer_outgoing, viewer_events) =
+            (&viewer_transfer.manager, &mut viewer_transfer.outgoing, &mut viewer_transfer.events);
+        let upload = host_manager.handle_transfer_message("viewer", accept);
+        let relay = async {
+            loop {
+                tokio::select! {
+                    Some((_, message)) = host_outgoing.recv() => {
+                        viewer_manager.handle_transfer_message("host", message).await.unwrap();
+                    },
+                    Some((_, message)) = viewer_outgoing.recv() => {
+                        host_manager.handle_transfer_message("viewer", message).await.unwrap();
+                    },
+                    Some(event) = viewer_events.recv() => {
+                        if let Some(image) = viewer_offload.handle_transfer_event(viewer_manager, &event).await {
+                            return image;
+                        }
+                    },
+                }
+            }
+        };
+        let (uploaded, image) = tokio::time::timeout(Duration::from_secs(10), async { tokio::join!(upload, relay) })
+            .await
+            .expect("image transfer did not finish");
+        uploaded.unwrap();
+
+        assert_eq!(image.id, "screenshot");
+        assert_eq!(viewer.sync_remote_entry(Some("host"), image).unwrap(), RemoteSyncOutcome::Applied);
+        assert_eq!(pasted.lock().unwrap().as_deref(), Some(png.as_slice()));
+        assert_eq!(viewer.get_history().len(), 1);
+
+        // Temporäre Dateien beider Seiten sind aufgeräumt
+        while let Ok(event) = host_transfer.events.try_recv() {
+            host_offload.handle_transfer_event(&host_transfer.manager, &event).await;
+        }
+        assert!(!host_offload.owns(&announced.transfer_id));
+        assert!(!viewer_offload.owns(&announced.transfer_id));
+        assert_eq!(staged_images(&dir.join("host")), 0);
+        assert_eq!(staged_images(&dir.join("viewer")), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
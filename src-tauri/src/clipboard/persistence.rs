@@ -0,0 +1,527 @@
+// src-tauri/src/clipboard/persistence.rs - Verschlüsselte Speicherung des Zwischenablage-Verlaufs
+//
+// Der Verlauf liegt als Protokoll im App-Datenverzeichnis, an das nur
+// angehängt wird: Jede Änderung wird als eigener Datensatz mit eigener Nonce
+// (ChaCha20-Poly1305) geschrieben. Der Schlüssel liegt im Schlüsselbund des
+// Systems (siehe `crate::keyring`), nie neben der Datei. Beschädigte
+// Datensätze werden beim Laden übersprungen, die übrigen bleiben erhalten.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::clipboard::error::ClipboardError;
+use crate::clipboard::types::ClipboardEntry;
+use crate::command_runner::CommandRunner;
+use crate::keyring::{self, KEY_LEN};
+
+const HISTORY_FILE: &str = "clipboard_history.log";
+
+/// Frühere Ablage: ganzer Verlauf in einem Block, mit demselben Schlüssel
+const LEGACY_HISTORY_FILE: &str = "clipboard_history.bin";
+
+/// Name und Beschriftung des Schlüssels im Schlüsselbund
+const KEY_NAME: &str = "clipboard-history";
+const KEY_LABEL: &str = "SmolDesk clipboard history";
+
+/// Beginn jedes Datensatzes; nach einem beschädigten Datensatz wird bis zur
+/// nächsten Markierung weitergesucht
+const RECORD_MAGIC: &[u8; 4] = b"SDH1";
+const NONCE_LEN: usize = 12;
+
+/// Datensatzkopf: Markierung, Länge des Chiffrats (u32 BE), Nonce
+const HEADER_LEN: usize = RECORD_MAGIC.len() + 4 + NONCE_LEN;
+
+/// Zusätzliche authentifizierte Daten; binden Datensätze an ihren Zweck
+const RECORD_AAD: &[u8] = b"smoldesk-clipboard-history-v1";
+
+/// Ab so vielen Datensätzen pro Eintrag wird die Datei neu geschrieben
+const COMPACT_FACTOR: usize = 4;
+
+/// Datensatz im Protokoll; spätere Datensätze überschreiben frühere
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "camelCase")]
+enum HistoryRecord {
+    /// Neuer oder geänderter Eintrag (z.B. angeheftet)
+    Put { entry: ClipboardEntry },
+
+    /// Entfernter Eintrag
+    Remove { id: String },
+}
+
+/// Ergebnis von `HistoryStore::load`
+#[derive(Debug)]
+pub struct LoadedHistory {
+    /// Einträge in Einfügereihenfolge (älteste zuerst)
+    pub entries: Vec<ClipboardEntry>,
+
+    /// Anzahl übersprungener, nicht lesbarer Datensätze
+    pub skipped: usize,
+}
+
+/// Stand der Datei, gegen den `save` die Änderungen ermittelt
+#[derive(Default)]
+struct LogState {
+    /// Einträge der Datei in Einfügereihenfolge mit Hash ihres Inhalts;
+    /// None, solange die Datei nicht gelesen wurde
+    written: Option<Vec<(String, u64)>>,
+
+    /// Anzahl der Datensätze in der Datei
+    records: usize,
+}
+
+/// Speichert den Verlauf verschlüsselt im App-Datenverzeichnis
+pub struct HistoryStore {
+    /// Pfad der Protokolldatei
+    history_path: PathBuf,
+
+    /// Symmetrischer Schlüssel aus dem Schlüsselbund
+    cipher: ChaCha20Poly1305,
+
+    /// Stand der Datei
+    state: Mutex<LogState>,
+}
+
+impl HistoryStore {
+    /// Öffnet den Speicher im angegebenen Verzeichnis. Holt den Schlüssel aus
+    /// dem Schlüsselbund oder legt ihn dort an; ohne Schlüsselbund schlägt dies
+    /// mit `KeyringUnavailable` fehl, statt auf eine Schlüsseldatei auszuweichen.
+    pub fn open(data_dir: &Path, runner: &dyn CommandRunner) -> Result<Self, ClipboardError> {
+        fs::create_dir_all(data_dir)?;
+
+        let key = keyring::load_or_create_key(runner, KEY_NAME, KEY_LABEL)
+            .map_err(ClipboardError::KeyringUnavailable)?;
+
+        let store = Self::with_key(data_dir, &key);
+        store.migrate_legacy(data_dir)?;
+        Ok(store)
+    }
+
+    /// Speicher mit gegebenem Schlüssel (ohne Schlüsselbund)
+    fn with_key(data_dir: &Path, key: &[u8; KEY_LEN]) -> Self {
+        HistoryStore {
+            history_path: data_dir.join(HISTORY_FILE),
+            cipher: ChaCha20Poly1305::new(Key::from_slice(key)),
+            state: Mutex::new(LogState::default()),
+        }
+    }
+
+    /// Lädt den gespeicherten Verlauf; fehlt die Datei, ist der Verlauf leer.
+    /// Nicht lesbare Datensätze werden übersprungen und beim nächsten
+    /// Neuschreiben der Datei verworfen.
+    pub fn load(&self) -> Result<LoadedHistory, ClipboardError> {
+        let mut state = self.state.lock().unwrap();
+        self.load_locked(&mut state)
+    }
+
+    fn load_locked(&self, state: &mut LogState) -> Result<LoadedHistory, ClipboardError> {
+        let raw = match fs::read(&self.history_path) {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let (records, skipped) = self.decode_records(&raw);
+        let mut entries: Vec<ClipboardEntry> = Vec::new();
+        for record in &records {
+            match record {
+                HistoryRecord::Put { entry } => {
+                    match entries.iter_mut().find(|existing| existing.id == entry.id) {
+                        Some(existing) => *existing = entry.clone(),
+                        None => entries.push(entry.clone()),
+                    }
+                },
+                HistoryRecord::Remove { id } => entries.retain(|existing| &existing.id != id),
+            }
+        }
+
+        state.written = Some(entries.iter().map(|entry| (entry.id.clone(), digest(entry))).collect());
+        // Übersprungene Datensätze zählen mit, damit die Datei bald verdichtet wird
+        state.records = records.len() + skipped;
+
+        Ok(LoadedHistory { entries, skipped })
+    }
+
+    /// Schreibt die Änderungen gegenüber dem Stand der Datei als neue
+    /// Datensätze. Hat sich die Reihenfolge geändert oder ist die Datei zu
+    /// lang geworden, wird sie neu geschrieben.
+    pub fn save(&self, entries: &[ClipboardEntry]) -> Result<(), ClipboardError> {
+        let mut state = self.state.lock().unwrap();
+        if state.written.is_none() {
+            self.load_locked(&mut state)?;
+        }
+        let written = state.written.as_deref().unwrap_or_default();
+
+        let current: Vec<(String, u64)> = entries.iter().map(|entry| (entry.id.clone(), digest(entry))).collect();
+        let kept: Vec<&(String, u64)> = written.iter()
+            .filter(|(id, _)| current.iter().any(|(current_id, _)| current_id == id))
+            .collect();
+
+        // Neue Einträge kommen nur hinten hinzu; alles andere braucht eine neue Datei
+        let appendable = current.len() >= kept.len()
+            && current.iter().zip(&kept).all(|((id, _), (kept_id, _))| id == kept_id)
+            && current[kept.len()..].iter().all(|(id, _)| !written.iter().any(|(written_id, _)| written_id == id));
+
+        let mut records = Vec::new();
+        if appendable {
+            for (id, _) in written.iter().filter(|(id, _)| !current.iter().any(|(current_id, _)| current_id == id)) {
+                records.push(HistoryRecord::Remove { id: id.clone() });
+            }
+            for (entry, (_, hash)) in entries.iter().zip(&current) {
+                if !written.contains(&(entry.id.clone(), *hash)) {
+                    records.push(HistoryRecord::Put { entry: entry.clone() });
+                }
+            }
+        }
+
+        if !appendable || state.records + records.len() > COMPACT_FACTOR * entries.len().max(8) {
+            self.rewrite(entries)?;
+            state.records = entries.len();
+        } else if !records.is_empty() {
+            self.append(&records)?;
+            state.records += records.len();
+        }
+
+        state.written = Some(current);
+        Ok(())
+    }
+
+    /// Überschreibt die Datei mit Nullen, kürzt sie und entfernt sie. Nur die
+    /// Einträge in `keep` werden danach neu geschrieben.
+    pub fn wipe(&self, keep: &[ClipboardEntry]) -> Result<(), ClipboardError> {
+        let mut state = self.state.lock().unwrap();
+        wipe_file(&self.history_path)?;
+
+        if !keep.is_empty() {
+            self.rewrite(keep)?;
+        }
+        state.written = Some(keep.iter().map(|entry| (entry.id.clone(), digest(entry))).collect());
+        state.records = keep.len();
+        Ok(())
+    }
+
+    /// Entfernt den gespeicherten Verlauf vollständig
+    pub fn clear(&self) -> Result<(), ClipboardError> {
+        self.wipe(&[])
+    }
+
+    /// Hängt Datensätze an die Datei an
+    fn append(&self, records: &[HistoryRecord]) -> Result<(), ClipboardError> {
+        let mut encoded = Vec::new();
+        for record in records {
+            self.encode_record(record, &mut encoded)?;
+        }
+
+        let mut file = open_private_file(&self.history_path, false)?;
+        file.seek(SeekFrom::End(0))?;
+        file.write_all(&encoded)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    /// Schreibt die Datei atomar neu (temporäre Datei + rename)
+    fn rewrite(&self, entries: &[ClipboardEntry]) -> Result<(), ClipboardError> {
+        let mut encoded = Vec::new();
+        for entry in entries {
+            self.encode_record(&HistoryRecord::Put { entry: entry.clone() }, &mut encoded)?;
+        }
+
+        let temp_path = self.history_path.with_extension("tmp");
+        {
+            let mut file = open_private_file(&temp_path, true)?;
+            file.write_all(&encoded)?;
+            file.sync_all()?;
+        }
+        fs::rename(&temp_path, &self.history_path)?;
+        Ok(())
+    }
+
+    fn encode_record(&self, record: &HistoryRecord, out: &mut Vec<u8>) -> Result<(), ClipboardError> {
+        let plaintext = serde_json::to_vec(record)?;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let ciphertext = self.cipher.encrypt(Nonce::from_slice(&nonce), Payload { msg: &plaintext, aad: RECORD_AAD })
+            .map_err(|_| ClipboardError::SerializationError("Failed to encrypt clipboard history".to_string()))?;
+        let len = u32::try_from(ciphertext.len())
+            .map_err(|_| ClipboardError::ContentTooLarge(ciphertext.len(), u32::MAX as usize))?;
+
+        out.extend_from_slice(RECORD_MAGIC);
+        out.extend_from_slice(&len.to_be_bytes());
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&ciphertext);
+        Ok(())
+    }
+
+    /// Liest alle lesbaren Datensätze und zählt die übersprungenen
+    fn decode_records(&self, raw: &[u8]) -> (Vec<HistoryRecord>, usize) {
+        let mut records = Vec::new();
+        let mut skipped = 0;
+        let mut pos = 0;
+
+        while let Some(offset) = find_magic(&raw[pos..]) {
+            let start = pos + offset;
+            match self.decode_record(&raw[start..]) {
+                Some((record, len)) => {
+                    records.push(record);
+                    pos = start + len;
+                },
+                None => {
+                    skipped += 1;
+                    pos = start + 1;
+                },
+            }
+        }
+
+        (records, skipped)
+    }
+
+    /// Ein Datensatz am Anfang von `raw` und seine Länge in Bytes
+    fn decode_record(&self, raw: &[u8]) -> Option<(HistoryRecord, usize)> {
+        let header = raw.get(..HEADER_LEN)?;
+        let len = u32::from_be_bytes(header[4..8].try_into().ok()?) as usize;
+        let nonce = &header[8..HEADER_LEN];
+        let ciphertext = raw.get(HEADER_LEN..HEADER_LEN.checked_add(len)?)?;
+
+        let plaintext = self.cipher.decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: RECORD_AAD }).ok()?;
+        let record = serde_json::from_slice(&plaintext).ok()?;
+        Some((record, HEADER_LEN + len))
+    }
+
+    /// Übernimmt einen Verlauf aus der früheren Ablage und löscht diese
+    fn migrate_legacy(&self, data_dir: &Path) -> Result<(), ClipboardError> {
+        let history_path = data_dir.join(LEGACY_HISTORY_FILE);
+        if !history_path.exists() {
+            return Ok(());
+        }
+
+        if !self.history_path.exists() {
+            if let Some(entries) = self.read_legacy(&history_path) {
+                self.rewrite(&entries)?;
+            }
+        }
+
+        wipe_file(&history_path)?;
+        Ok(())
+    }
+
+    /// Liest die frühere Verlaufsdatei; None, wenn sie unlesbar ist
+    fn read_legacy(&self, history_path: &Path) -> Option<Vec<ClipboardEntry>> {
+        let raw = fs::read(history_path).ok().filter(|raw| raw.len() >= NONCE_LEN)?;
+
+        let (nonce, ciphertext) = raw.split_at(NONCE_LEN);
+        let plaintext = self.cipher.decrypt(Nonce::from_slice(nonce), ciphertext).ok()?;
+        serde_json::from_slice(&plaintext).ok()
+    }
+}
+
+/// Position der nächsten Datensatzmarkierung
+fn find_magic(raw: &[u8]) -> Option<usize> {
+    raw.windows(RECORD_MAGIC.len()).position(|window| window == RECORD_MAGIC)
+}
+
+/// Hash eines Eintrags, um geänderte Einträge (z.B. angeheftet) zu erkennen
+fn digest(entry: &ClipboardEntry) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_vec(entry).unwrap_or_default().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Überschreibt eine Datei mit Nullen, kürzt sie auf 0 Bytes und entfernt sie
+fn wipe_file(path: &Path) -> Result<(), ClipboardError> {
+    let mut file = match fs::OpenOptions::new().write(true).open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let len = file.metadata()?.len();
+    let zeros = vec![0u8; 64 * 1024];
+    let mut remaining = len;
+    while remaining > 0 {
+        let chunk = remaining.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..chunk])?;
+        remaining -= chunk as u64;
+    }
+    file.sync_all()?;
+    file.set_len(0)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// Öffnet oder erstellt eine Datei, die nur für den aktuellen Benutzer lesbar ist
+fn open_private_file(path: &Path, truncate: bool) -> Result<fs::File, ClipboardError> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(truncate);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    Ok(options.open(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::types::{ClipboardContentType, ClipboardMetadata};
+    use crate::test_env::TestEnv;
+
+    fn entry(id: &str, data: &str) -> ClipboardEntry {
+        ClipboardEntry {
+            id: id.to_string(),
+            content_type: ClipboardContentType::Text,
+            data: data.to_string(),
+            metadata: ClipboardMetadata {
+                size: data.len(),
+                mime_type: "text/plain".to_string(),
+                source: "local".to_string(),
+                truncated: false,
+                origin: None,
+            },
+            timestamp: chrono::Utc::now(),
+            pinned: false,
+        }
+    }
+
+    fn ids(entries: &[ClipboardEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.id.as_str()).collect()
+    }
+
+    fn temp_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("smoldesk-history-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_changes_are_appended_and_replayed() {
+        let dir = temp_dir();
+        let store = HistoryStore::with_key(&dir, &[1u8; KEY_LEN]);
+
+        let mut entries = vec![entry("a", "secret one"), entry("b", "two")];
+        store.save(&entries).unwrap();
+        let size = fs::metadata(dir.join(HISTORY_FILE)).unwrap().len();
+
+        entries.push(entry("c", "three"));
+        entries[0].pinned = true;
+        entries.remove(1);
+        store.save(&entries).unwrap();
+        assert!(fs::metadata(dir.join(HISTORY_FILE)).unwrap().len() > size, "changes are appended");
+
+        let raw = fs::read(dir.join(HISTORY_FILE)).unwrap();
+        assert!(!raw.windows(b"secret".len()).any(|window| window == b"secret"), "nothing on disk is plaintext");
+
+        let loaded = HistoryStore::with_key(&dir, &[1u8; KEY_LEN]).load().unwrap();
+        assert_eq!(ids(&loaded.entries), vec!["a", "c"]);
+        assert!(loaded.entries[0].pinned);
+        assert_eq!(loaded.skipped, 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_corrupt_records_are_skipped() {
+        let dir = temp_dir();
+        let store = HistoryStore::with_key(&dir, &[1u8; KEY_LEN]);
+        let entries = vec![entry("a", "one"), entry("b", "two"), entry("c", "three")];
+        store.save(&entries[..1]).unwrap();
+        let first_len = fs::metadata(dir.join(HISTORY_FILE)).unwrap().len() as usize;
+        store.save(&entries[..2]).unwrap();
+        store.save(&entries).unwrap();
+
+        // Ein Bit im zweiten Datensatz kippen und einen halb geschriebenen anhängen
+        let mut raw = fs::read(dir.join(HISTORY_FILE)).unwrap();
+        raw[first_len + HEADER_LEN + 2] ^= 0x01;
+        let torn = raw[..HEADER_LEN + 3].to_vec();
+        raw.extend_from_slice(&torn);
+        fs::write(dir.join(HISTORY_FILE), &raw).unwrap();
+
+        let store = HistoryStore::with_key(&dir, &[1u8; KEY_LEN]);
+        let loaded = store.load().unwrap();
+        assert_eq!(ids(&loaded.entries), vec!["a", "c"]);
+        assert_eq!(loaded.skipped, 2);
+
+        // Neue Datensätze hinter dem beschädigten Ende bleiben lesbar
+        let mut entries = loaded.entries;
+        entries.push(entry("d", "four"));
+        store.save(&entries).unwrap();
+        assert_eq!(ids(&HistoryStore::with_key(&dir, &[1u8; KEY_LEN]).load().unwrap().entries), vec!["a", "c", "d"]);
+
+        // Mit einem anderen Schlüssel ist nichts lesbar, aber das Laden scheitert nicht
+        let loaded = HistoryStore::with_key(&dir, &[2u8; KEY_LEN]).load().unwrap();
+        assert!(loaded.entries.is_empty());
+        assert!(loaded.skipped > 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_wipe_keeps_only_given_entries() {
+        let dir = temp_dir();
+        let store = HistoryStore::with_key(&dir, &[1u8; KEY_LEN]);
+        let mut pinned = entry("a", "pinned");
+        pinned.pinned = true;
+        store.save(&[pinned.clone(), entry("b", "two")]).unwrap();
+
+        store.wipe(&[pinned]).unwrap();
+        assert_eq!(ids(&store.load().unwrap().entries), vec!["a"]);
+
+        store.clear().unwrap();
+        assert!(!dir.join(HISTORY_FILE).exists());
+        assert!(store.load().unwrap().entries.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_open_requires_keyring() {
+        let env = TestEnv::new();
+        env.set_exit_code("secret-tool", 1);
+        env.set_stderr("secret-tool", "Cannot autolaunch D-Bus without X11 $DISPLAY");
+        env.set_exit_code("keyctl", 1);
+
+        let dir = temp_dir();
+        let result = HistoryStore::open(&dir, &*env.runner());
+        assert!(matches!(result, Err(ClipboardError::KeyringUnavailable(_))));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0, "no key file is written instead");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_legacy_history_is_migrated_and_removed() {
+        let dir = temp_dir();
+        // Derselbe Schlüssel, den der Schlüsselbund unten liefert
+        let keyring_key = [1u8; KEY_LEN];
+        let plaintext = serde_json::to_vec(&vec![entry("old", "legacy")]).unwrap();
+        let nonce = [0u8; NONCE_LEN];
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(&keyring_key))
+            .encrypt(Nonce::from_slice(&nonce), plaintext.as_ref())
+            .unwrap();
+        fs::write(dir.join(LEGACY_HISTORY_FILE), [nonce.as_slice(), &ciphertext].concat()).unwrap();
+
+        let env = TestEnv::new();
+        env.set_stdout("secret-tool", "AQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQE=");
+        let store = HistoryStore::open(&dir, &*env.runner()).unwrap();
+
+        assert_eq!(ids(&store.load().unwrap().entries), vec!["old"]);
+        assert!(!dir.join(LEGACY_HISTORY_FILE).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,226 @@
+// src-tauri/src/clipboard/poll.rs - Adaptives Abfrageintervall für Zwischenablagen ohne Watcher
+//
+// Ohne native Änderungsbenachrichtigung wird die Zwischenablage abgefragt.
+// Ein festes Intervall ist direkt nach dem Kopieren zu langsam (ein Einfügen
+// beim Peer wartet darauf) und in langen Pausen unnötig häufig. Nach einer
+// erkannten Änderung oder einer weitergeleiteten Eingabe wird deshalb
+// `FAST_WINDOW` lang alle `FAST_INTERVAL` abgefragt; danach verdoppelt sich
+// das Intervall mit jeder Abfrage bis `max_poll_interval_ms`.
+
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+/// Intervall direkt nach einer Änderung oder Eingabe
+pub const FAST_INTERVAL: Duration = Duration::from_millis(100);
+
+/// So lange nach der letzten Änderung oder Eingabe bleibt es beim schnellen Intervall
+pub const FAST_WINDOW: Duration = Duration::from_secs(5);
+
+/// Erlaubter Bereich für `max_poll_interval_ms`
+pub const MIN_MAX_POLL_INTERVAL_MS: u64 = 2000;
+pub const MAX_MAX_POLL_INTERVAL_MS: u64 = 5000;
+
+fn clamp_max_interval(max_interval_ms: u64) -> Duration {
+    Duration::from_millis(max_interval_ms.clamp(MIN_MAX_POLL_INTERVAL_MS, MAX_MAX_POLL_INTERVAL_MS))
+}
+
+/// Abfrageplan; die Zeit wird übergeben, damit der Verlauf ohne Warten testbar ist
+#[derive(Debug, Clone)]
+pub struct PollSchedule {
+    max_interval: Duration,
+    last_activity: Option<Instant>,
+    interval: Duration,
+}
+
+impl PollSchedule {
+    /// Beginnt ruhig, bis zur ersten Änderung oder Eingabe
+    pub fn new(max_interval_ms: u64) -> Self {
+        let max_interval = clamp_max_interval(max_interval_ms);
+        PollSchedule { max_interval, last_activity: None, interval: max_interval }
+    }
+
+    pub fn set_max_interval(&mut self, max_interval_ms: u64) {
+        self.max_interval = clamp_max_interval(max_interval_ms);
+        self.interval = self.interval.min(self.max_interval);
+    }
+
+    pub fn max_interval(&self) -> Duration {
+        self.max_interval
+    }
+
+    /// Zuletzt bestimmtes Intervall
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Änderung erkannt oder Eingabe weitergeleitet: wieder schnell abfragen
+    pub fn activity(&mut self, now: Instant) {
+        self.last_activity = Some(now);
+        self.interval = FAST_INTERVAL;
+    }
+
+    /// Wartezeit bis zur nächsten Abfrage; außerhalb von `FAST_WINDOW`
+    /// doppelt so lang wie die vorige
+    pub fn next_interval(&mut self, now: Instant) -> Duration {
+        let fast = self.last_activity.is_some_and(|at| now.saturating_duration_since(at) < FAST_WINDOW);
+        if !fast {
+            self.interval = (self.interval * 2).min(self.max_interval);
+        }
+        self.interval
+    }
+}
+
+/// Wie der Überwachungs-Thread Änderungen erkennt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum MonitorMode {
+    /// Überwachung aus
+    Stopped,
+    /// Native Änderungsbenachrichtigung, kein Abfragen
+    Watching,
+    /// Abfragen nach `PollSchedule`
+    Polling,
+}
+
+/// Antwort von `get_clipboard_stats`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClipboardStats {
+    pub monitor: MonitorMode,
+    /// Aktuelles Abfrageintervall; nur beim Abfragen
+    pub poll_interval_ms: Option<u64>,
+    pub max_poll_interval_ms: u64,
+}
+
+struct PollerState {
+    schedule: PollSchedule,
+    mode: MonitorMode,
+    /// Wartenden Thread sofort weiterlaufen lassen
+    woken: bool,
+}
+
+/// Abfrageplan, den sich der Überwachungs-Thread mit dem Rest der App teilt.
+/// Eingaben melden sich über `input_activity`, auch wenn der
+/// ClipboardManager gerade neu erstellt wird.
+pub struct ClipboardPoller {
+    state: Mutex<PollerState>,
+    wakeup: Condvar,
+}
+
+impl ClipboardPoller {
+    pub fn new(max_interval_ms: u64) -> Self {
+        ClipboardPoller {
+            state: Mutex::new(PollerState {
+                schedule: PollSchedule::new(max_interval_ms),
+                mode: MonitorMode::Stopped,
+                woken: false,
+            }),
+            wakeup: Condvar::new(),
+        }
+    }
+
+    pub fn set_max_interval(&self, max_interval_ms: u64) {
+        self.state.lock().unwrap().schedule.set_max_interval(max_interval_ms);
+    }
+
+    /// Eine Eingabe wurde an das System weitergeleitet, z.B. ein Strg+V des
+    /// Peers; ein wartender Thread fragt sofort ab
+    pub fn input_activity(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.schedule.activity(Instant::now());
+        state.woken = true;
+        self.wakeup.notify_all();
+    }
+
+    /// Der Überwachungs-Thread hat eine Änderung erkannt oder gerade begonnen
+    pub(crate) fn activity(&self) {
+        self.state.lock().unwrap().schedule.activity(Instant::now());
+    }
+
+    pub(crate) fn set_mode(&self, mode: MonitorMode) {
+        self.state.lock().unwrap().mode = mode;
+    }
+
+    /// Wartet bis zur nächsten Abfrage, eine Eingabe oder `interrupt`
+    pub(crate) fn wait(&self) {
+        let mut state = self.state.lock().unwrap();
+        let interval = state.schedule.next_interval(Instant::now());
+        let (mut state, _) = self.wakeup
+            .wait_timeout_while(state, interval, |state| !state.woken)
+            .unwrap();
+        state.woken = false;
+    }
+
+    /// Beendet ein laufendes `wait` sofort, etwa beim Stoppen der Überwachung
+    pub(crate) fn interrupt(&self) {
+        self.state.lock().unwrap().woken = true;
+        self.wakeup.notify_all();
+    }
+
+    pub fn stats(&self) -> ClipboardStats {
+        let state = self.state.lock().unwrap();
+        ClipboardStats {
+            monitor: state.mode,
+            poll_interval_ms: (state.mode == MonitorMode::Polling).then(|| state.schedule.interval().as_millis() as u64),
+            max_poll_interval_ms: state.schedule.max_interval().as_millis() as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_schedule_stays_fast_then_decays_to_max() {
+        let start = Instant::now();
+        let mut schedule = PollSchedule::new(2000);
+        assert_eq!(schedule.next_interval(start), ms(2000));
+
+        schedule.activity(start);
+        let mut now = start;
+        while now < start + FAST_WINDOW {
+            assert_eq!(schedule.next_interval(now), FAST_INTERVAL);
+            now += FAST_INTERVAL;
+        }
+
+        let decay: Vec<Duration> = (0..6)
+            .map(|_| {
+                let interval = schedule.next_interval(now);
+                now += interval;
+                interval
+            })
+            .collect();
+        assert_eq!(decay, vec![ms(200), ms(400), ms(800), ms(1600), ms(2000), ms(2000)]);
+
+        // Eine Eingabe mitten im ruhigen Abschnitt macht sofort wieder schnell
+        schedule.activity(now);
+        assert_eq!(schedule.next_interval(now + ms(4900)), FAST_INTERVAL);
+        assert_eq!(schedule.next_interval(now + FAST_WINDOW), ms(200));
+    }
+
+    #[test]
+    fn test_max_interval_is_clamped() {
+        let mut schedule = PollSchedule::new(60_000);
+        assert_eq!(schedule.max_interval(), ms(5000));
+
+        schedule.set_max_interval(500);
+        assert_eq!(schedule.max_interval(), ms(2000));
+        assert_eq!(schedule.interval(), ms(2000));
+
+        let poller = ClipboardPoller::new(3000);
+        assert_eq!(poller.stats(), ClipboardStats {
+            monitor: MonitorMode::Stopped,
+            poll_interval_ms: None,
+            max_poll_interval_ms: 3000,
+        });
+
+        poller.set_mode(MonitorMode::Polling);
+        poller.input_activity();
+        assert_eq!(poller.stats().poll_interval_ms, Some(100));
+    }
+}
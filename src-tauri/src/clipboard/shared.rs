@@ -0,0 +1,273 @@
+// src-tauri/src/clipboard/shared.rs - Gemeinsame Zwischenablage je Verbindung
+//
+// Wer nicht möchte, dass eine Fernsitzung die echte Zwischenablage berührt,
+// kann Textschnipsel über einen eigenen Kanal austauschen: einen
+// Schlüssel-Wert-Speicher je Peer. Einträge gehen im selben Format wie die
+// Synchronisation (`encode_sync_entry`) hinaus, der Schlüssel steht in der ID
+// des Eintrags. Der ClipboardProvider wird dabei nie aufgerufen, und nichts
+// davon landet im Verlauf. Trennt der Peer die Verbindung, werden seine
+// Einträge verworfen; die Einstellung der Verbindung (`ClipboardConnectionMode`)
+// bleibt für die nächste Verbindung erhalten.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::clipboard::error::ClipboardError;
+use crate::clipboard::types::{ClipboardConnectionMode, ClipboardContentType, ClipboardEntry, ClipboardMetadata};
+
+/// Längster erlaubter Schlüssel in Bytes
+pub const MAX_KEY_LEN: usize = 128;
+
+/// Höchstzahl und -größe der Einträge je Peer
+#[derive(Debug, Clone, Copy)]
+struct SharedLimits {
+    max_entries: usize,
+    max_entry_size: usize,
+}
+
+/// Gemeinsame Zwischenablagen aller Peers
+pub struct SharedClipboard {
+    limits: Mutex<SharedLimits>,
+
+    /// Einstellung für Peers ohne eigene
+    default_mode: Mutex<ClipboardConnectionMode>,
+
+    /// Eigene Einstellung je Peer
+    modes: Mutex<HashMap<String, ClipboardConnectionMode>>,
+
+    /// Einträge je Peer, nach Schlüssel sortiert
+    entries: Mutex<HashMap<String, BTreeMap<String, ClipboardEntry>>>,
+}
+
+impl SharedClipboard {
+    pub fn new(default_mode: ClipboardConnectionMode, max_entries: usize, max_entry_size: usize) -> Self {
+        SharedClipboard {
+            limits: Mutex::new(SharedLimits { max_entries, max_entry_size }),
+            default_mode: Mutex::new(default_mode),
+            modes: Mutex::new(HashMap::new()),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Übernimmt geänderte Einstellungen; die Grenzen gelten für neue Einträge
+    pub fn configure(&self, default_mode: ClipboardConnectionMode, max_entries: usize, max_entry_size: usize) {
+        *self.limits.lock().unwrap() = SharedLimits { max_entries, max_entry_size };
+        *self.default_mode.lock().unwrap() = default_mode;
+    }
+
+    /// Einstellung eines Peers; `None` steht für Einträge ohne Peer-ID, die
+    /// an alle gehen
+    pub fn mode_for(&self, peer_id: Option<&str>) -> ClipboardConnectionMode {
+        peer_id
+            .and_then(|peer_id| self.modes.lock().unwrap().get(peer_id).copied())
+            .unwrap_or_else(|| *self.default_mode.lock().unwrap())
+    }
+
+    /// Setzt die Einstellung eines Peers; ohne Peer-ID die Vorgabe für alle
+    /// ohne eigene Einstellung
+    pub fn set_mode(&self, peer_id: Option<&str>, mode: ClipboardConnectionMode) {
+        match peer_id {
+            Some(peer_id) => {
+                self.modes.lock().unwrap().insert(peer_id.to_string(), mode);
+            },
+            None => *self.default_mode.lock().unwrap() = mode,
+        }
+    }
+
+    /// Darf die Systemzwischenablage an alle diese Peers gehen? Ein Eintrag
+    /// ohne Peer-ID erreicht jeden, also hält ihn ein einziger Peer mit
+    /// `SharedOnly` für alle zurück.
+    pub fn allows_system_for_all(&self, peer_ids: &[String]) -> bool {
+        self.mode_for(None).allows_system()
+            && peer_ids.iter().all(|peer_id| self.mode_for(Some(peer_id)).allows_system())
+    }
+
+    /// Legt einen lokalen Text unter `key` ab und liefert den Eintrag zum
+    /// Senden an den Peer
+    pub fn set(&self, peer_id: &str, key: &str, text: &str) -> Result<ClipboardEntry, ClipboardError> {
+        let entry = ClipboardEntry {
+            id: key.to_string(),
+            content_type: ClipboardContentType::Text,
+            data: text.to_string(),
+            metadata: ClipboardMetadata {
+                size: text.len(),
+                mime_type: "text/plain".to_string(),
+                source: "local".to_string(),
+                truncated: false,
+                origin: None,
+            },
+            timestamp: chrono::Utc::now(),
+            pinned: false,
+        };
+
+        self.insert(peer_id, entry.clone())?;
+        Ok(entry)
+    }
+
+    /// Übernimmt einen Eintrag des Peers (ID = Schlüssel). Ein älterer
+    /// Eintrag als der vorhandene wird verworfen; liefert dann false.
+    pub fn apply_remote(&self, peer_id: &str, entry: ClipboardEntry) -> Result<bool, ClipboardError> {
+        if !matches!(entry.content_type, ClipboardContentType::Text | ClipboardContentType::Html) {
+            return Err(ClipboardError::InvalidFormat(format!("Shared clipboard only holds text, not {:?}", entry.content_type)));
+        }
+
+        let current = self.get(peer_id, &entry.id);
+        if current.is_some_and(|current| entry.timestamp < current.timestamp) {
+            return Ok(false);
+        }
+
+        let entry = ClipboardEntry {
+            metadata: ClipboardMetadata { source: "remote".to_string(), ..entry.metadata },
+            pinned: false,
+            ..entry
+        };
+        self.insert(peer_id, entry)?;
+        Ok(true)
+    }
+
+    pub fn get(&self, peer_id: &str, key: &str) -> Option<ClipboardEntry> {
+        self.entries.lock().unwrap()
+            .get(peer_id)
+            .and_then(|entries| entries.get(key))
+            .cloned()
+    }
+
+    /// Alle Einträge eines Peers, nach Schlüssel sortiert
+    pub fn list(&self, peer_id: &str) -> Vec<ClipboardEntry> {
+        self.entries.lock().unwrap()
+            .get(peer_id)
+            .map(|entries| entries.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Verwirft die Einträge eines getrennten Peers; true, wenn er welche hatte
+    pub fn remove_peer(&self, peer_id: &str) -> bool {
+        self.entries.lock().unwrap()
+            .remove(peer_id)
+            .is_some_and(|entries| !entries.is_empty())
+    }
+
+    /// Prüft Einstellung, Schlüssel und Grenzen und legt den Eintrag ab
+    fn insert(&self, peer_id: &str, entry: ClipboardEntry) -> Result<(), ClipboardError> {
+        if !self.mode_for(Some(peer_id)).allows_shared() {
+            return Err(ClipboardError::ContentBlocked(format!("shared clipboard is disabled for peer {}", peer_id)));
+        }
+        check_key(&entry.id)?;
+
+        let limits = *self.limits.lock().unwrap();
+        if entry.metadata.truncated || entry.data.len() > limits.max_entry_size {
+            return Err(ClipboardError::ContentTooLarge(entry.metadata.size.max(entry.data.len()), limits.max_entry_size));
+        }
+
+        let mut entries = self.entries.lock().unwrap();
+        let peer_entries = entries.entry(peer_id.to_string()).or_default();
+        if !peer_entries.contains_key(&entry.id) && peer_entries.len() >= limits.max_entries {
+            return Err(ClipboardError::ContentBlocked(format!("shared clipboard is full ({} entries)", limits.max_entries)));
+        }
+        peer_entries.insert(entry.id.clone(), entry);
+        Ok(())
+    }
+}
+
+/// Schlüssel sind nicht leer, höchstens `MAX_KEY_LEN` Bytes lang und ohne
+/// Steuerzeichen
+fn check_key(key: &str) -> Result<(), ClipboardError> {
+    if key.is_empty() || key.len() > MAX_KEY_LEN || key.chars().any(char::is_control) {
+        return Err(ClipboardError::InvalidFormat(format!("Invalid shared clipboard key: {:?}", key)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shared() -> SharedClipboard {
+        SharedClipboard::new(ClipboardConnectionMode::Both, 2, 16)
+    }
+
+    #[test]
+    fn test_entries_are_scoped_to_the_peer() {
+        let shared = shared();
+        shared.set("alice", "snippet", "hello").unwrap();
+
+        assert_eq!(shared.get("alice", "snippet").unwrap().data, "hello");
+        assert!(shared.get("bob", "snippet").is_none());
+        assert!(shared.list("bob").is_empty());
+    }
+
+    #[test]
+    fn test_limits_count_and_size() {
+        let shared = shared();
+        shared.set("alice", "b", "1").unwrap();
+        shared.set("alice", "a", "2").unwrap();
+
+        // Überschreiben geht auch bei voller Ablage
+        shared.set("alice", "a", "3").unwrap();
+        assert!(matches!(shared.set("alice", "c", "4"), Err(ClipboardError::ContentBlocked(_))));
+        assert!(matches!(shared.set("alice", "a", &"x".repeat(17)), Err(ClipboardError::ContentTooLarge(17, 16))));
+        assert!(matches!(shared.set("alice", "", "5"), Err(ClipboardError::InvalidFormat(_))));
+
+        let keys: Vec<_> = shared.list("alice").into_iter().map(|entry| (entry.id, entry.data)).collect();
+        assert_eq!(keys, vec![("a".to_string(), "3".to_string()), ("b".to_string(), "1".to_string())]);
+    }
+
+    #[test]
+    fn test_disconnect_clears_entries_but_keeps_the_mode() {
+        let shared = shared();
+        shared.set_mode(Some("alice"), ClipboardConnectionMode::SharedOnly);
+        shared.set("alice", "snippet", "hello").unwrap();
+        shared.set("bob", "snippet", "hi").unwrap();
+
+        assert!(shared.remove_peer("alice"));
+        assert!(shared.list("alice").is_empty());
+        assert!(shared.get("alice", "snippet").is_none());
+        assert_eq!(shared.list("bob").len(), 1);
+
+        // Ein zweites Trennen hat nichts mehr zu verwerfen
+        assert!(!shared.remove_peer("alice"));
+
+        // Nach dem erneuten Verbinden gilt die Einstellung weiter
+        assert_eq!(shared.mode_for(Some("alice")), ClipboardConnectionMode::SharedOnly);
+        shared.set("alice", "snippet", "again").unwrap();
+        assert_eq!(shared.get("alice", "snippet").unwrap().data, "again");
+    }
+
+    #[test]
+    fn test_remote_entries_keep_the_newest() {
+        let shared = shared();
+        let local = shared.set("alice", "snippet", "mine").unwrap();
+
+        let older = ClipboardEntry {
+            data: "old".to_string(),
+            timestamp: local.timestamp - chrono::Duration::seconds(1),
+            ..local.clone()
+        };
+        assert!(!shared.apply_remote("alice", older).unwrap());
+
+        let newer = ClipboardEntry {
+            data: "theirs".to_string(),
+            timestamp: local.timestamp + chrono::Duration::seconds(1),
+            ..local
+        };
+        assert!(shared.apply_remote("alice", newer).unwrap());
+        let entry = shared.get("alice", "snippet").unwrap();
+        assert_eq!((entry.data.as_str(), entry.metadata.source.as_str()), ("theirs", "remote"));
+    }
+
+    #[test]
+    fn test_mode_gates_the_shared_and_system_clipboard() {
+        let shared = SharedClipboard::new(ClipboardConnectionMode::SystemClipboard, 2, 16);
+        assert!(matches!(shared.set("alice", "snippet", "hello"), Err(ClipboardError::ContentBlocked(_))));
+
+        shared.set_mode(Some("alice"), ClipboardConnectionMode::SharedOnly);
+        shared.set("alice", "snippet", "hello").unwrap();
+
+        let peers = vec!["alice".to_string(), "bob".to_string()];
+        assert!(!shared.allows_system_for_all(&peers));
+        assert!(shared.allows_system_for_all(&peers[1..]));
+
+        shared.set_mode(None, ClipboardConnectionMode::SharedOnly);
+        assert!(!shared.allows_system_for_all(&[]));
+    }
+}
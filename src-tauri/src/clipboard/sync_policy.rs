@@ -0,0 +1,383 @@
+// src-tauri/src/clipboard/sync_policy.rs - Richtung und Konfliktauflösung der Synchronisation
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::clipboard::types::ClipboardEntry;
+
+/// So lange gilt ein übernommener Peer-Inhalt, den der Monitor wieder liest,
+/// als Echo. Danach ist derselbe Inhalt eine neue lokale Kopie.
+pub const ECHO_WINDOW: Duration = Duration::from_secs(10);
+
+/// Höchstzahl gemerkter Peer-Einträge für die Echo-Erkennung
+pub const ECHO_CAPACITY: usize = 32;
+
+/// Einträge mit mehr Übertragungen werden nicht weitergegeben
+pub const MAX_RELAY_HOPS: u32 = 1;
+
+/// In welche Richtung Inhalte synchronisiert werden (Host = dieser Rechner)
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum SyncDirection {
+    /// Nur lokale Änderungen an den Client senden
+    HostToClient,
+
+    /// Nur Inhalte des Clients übernehmen
+    ClientToHost,
+
+    /// In beide Richtungen
+    #[default]
+    Bidirectional,
+
+    /// Keine Synchronisation
+    Disabled,
+}
+
+impl SyncDirection {
+    /// Dürfen lokale Änderungen an den Peer gehen?
+    pub fn allows_outgoing(&self) -> bool {
+        matches!(self, SyncDirection::HostToClient | SyncDirection::Bidirectional)
+    }
+
+    /// Dürfen Inhalte des Peers übernommen werden?
+    pub fn allows_incoming(&self) -> bool {
+        matches!(self, SyncDirection::ClientToHost | SyncDirection::Bidirectional)
+    }
+}
+
+/// Laufzeit-Einstellungen der Synchronisation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardSyncPolicy {
+    /// Erlaubte Richtung
+    pub direction: SyncDirection,
+
+    /// Zeitstempel, die näher beieinander liegen, gelten als gleichzeitig
+    /// (Uhrenabweichung zwischen den Peers)
+    pub conflict_tolerance_ms: u64,
+}
+
+impl Default for ClipboardSyncPolicy {
+    fn default() -> Self {
+        ClipboardSyncPolicy {
+            direction: SyncDirection::Bidirectional,
+            conflict_tolerance_ms: 500,
+        }
+    }
+}
+
+/// Zustand der Synchronisation für die Oberfläche
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardSyncStatus {
+    /// Aktive Richtlinie
+    pub policy: ClipboardSyncPolicy,
+
+    /// ID des zuletzt übernommenen Peer-Eintrags
+    pub last_applied_remote_id: Option<String>,
+
+    /// Zeitstempel des zuletzt übernommenen Peer-Eintrags
+    pub last_applied_remote_at: Option<DateTime<Utc>>,
+}
+
+/// Was mit einem Eintrag eines Peers geschehen ist
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RemoteSyncOutcome {
+    /// In die lokale Zwischenablage übernommen
+    Applied,
+
+    /// Die Richtung erlaubt keine eingehenden Inhalte
+    DirectionBlocked,
+
+    /// Der lokale Inhalt ist neuer und gewinnt
+    LostConflict,
+
+    /// Bereits übernommen
+    Duplicate,
+
+    /// Angenommen; das Bild folgt per Dateiübertragung
+    Pending,
+
+    /// Text-Delta ohne passenden Basiseintrag; der Peer soll den Eintrag mit
+    /// `resend_clipboard_entry` vollständig senden
+    NeedsFullEntry,
+}
+
+/// Identität und Zeitpunkt eines Eintrags
+#[derive(Debug, Clone, PartialEq)]
+struct SyncMark {
+    id: String,
+    timestamp: DateTime<Utc>,
+}
+
+impl SyncMark {
+    fn of(entry: &ClipboardEntry) -> Self {
+        SyncMark {
+            id: entry.id.clone(),
+            timestamp: entry.timestamp,
+        }
+    }
+}
+
+/// Ein übernommener Peer-Inhalt
+#[derive(Debug, Clone)]
+struct AppliedRemote {
+    mark: SyncMark,
+    hash: u64,
+    applied_at: Instant,
+}
+
+/// Entscheidet über eingehende und ausgehende Einträge
+#[derive(Debug, Default)]
+pub struct SyncState {
+    policy: ClipboardSyncPolicy,
+
+    /// Eintrag, der gerade in der Zwischenablage liegt (lokal oder vom Peer)
+    current: Option<SyncMark>,
+
+    /// Zuletzt übernommene Peer-Einträge, höchstens `ECHO_CAPACITY` und
+    /// nicht älter als `ECHO_WINDOW` (neueste zuletzt)
+    recent_remote: VecDeque<AppliedRemote>,
+
+    /// Zuletzt übernommener Peer-Eintrag, auch nach Ablauf des Fensters
+    last_remote: Option<SyncMark>,
+}
+
+impl SyncState {
+    pub fn policy(&self) -> &ClipboardSyncPolicy {
+        &self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: ClipboardSyncPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn status(&self) -> ClipboardSyncStatus {
+        ClipboardSyncStatus {
+            policy: self.policy.clone(),
+            last_applied_remote_id: self.last_remote.as_ref().map(|mark| mark.id.clone()),
+            last_applied_remote_at: self.last_remote.as_ref().map(|mark| mark.timestamp),
+        }
+    }
+
+    /// Ist ein lokal gelesener Inhalt nur das Echo eines kürzlich
+    /// übernommenen Peer-Eintrags?
+    pub fn is_echo(&self, hash: u64, now: Instant) -> bool {
+        self.recent_remote.iter()
+            .any(|remote| remote.hash == hash && now.saturating_duration_since(remote.applied_at) < ECHO_WINDOW)
+    }
+
+    /// Darf ein übernommener Peer-Eintrag an die übrigen Peers weitergehen?
+    pub fn should_relay(&self, entry: &ClipboardEntry) -> bool {
+        self.policy.direction.allows_outgoing()
+            && entry.metadata.origin.as_ref().map_or(false, |origin| origin.hops <= MAX_RELAY_HOPS)
+    }
+
+    /// Merkt sich eine lokale Änderung; true, wenn sie an den Peer gehen darf
+    pub fn local_change(&mut self, entry: &ClipboardEntry) -> bool {
+        self.current = Some(SyncMark::of(entry));
+        self.policy.direction.allows_outgoing()
+    }
+
+    /// Prüft einen Peer-Eintrag, ohne ihn zu übernehmen
+    pub fn check_remote(&self, entry: &ClipboardEntry) -> RemoteSyncOutcome {
+        if !self.policy.direction.allows_incoming() {
+            return RemoteSyncOutcome::DirectionBlocked;
+        }
+
+        // Bereits übernommen oder der eigene Eintrag, den ein Peer weitergibt
+        let incoming = SyncMark::of(entry);
+        let known = self.recent_remote.iter().map(|remote| &remote.mark)
+            .chain(&self.last_remote)
+            .chain(&self.current)
+            .any(|mark| mark.id == incoming.id);
+        if known {
+            return RemoteSyncOutcome::Duplicate;
+        }
+
+        match &self.current {
+            Some(current) if !remote_wins(current, &incoming, self.policy.conflict_tolerance_ms) => {
+                RemoteSyncOutcome::LostConflict
+            },
+            _ => RemoteSyncOutcome::Applied,
+        }
+    }
+
+    /// Vermerkt einen übernommenen Peer-Eintrag
+    pub fn remote_applied(&mut self, entry: &ClipboardEntry, hash: u64, now: Instant) {
+        let mark = SyncMark::of(entry);
+        self.current = Some(mark.clone());
+        self.last_remote = Some(mark.clone());
+
+        self.recent_remote.retain(|remote| now.saturating_duration_since(remote.applied_at) < ECHO_WINDOW);
+        if self.recent_remote.len() >= ECHO_CAPACITY {
+            self.recent_remote.pop_front();
+        }
+        self.recent_remote.push_back(AppliedRemote { mark, hash, applied_at: now });
+    }
+}
+
+/// Last-Writer-Wins: der spätere Zeitstempel gewinnt. Liegen beide innerhalb
+/// der Toleranz, entscheidet die größere ID, damit beide Peers unabhängig
+/// voneinander zum selben Ergebnis kommen.
+fn remote_wins(current: &SyncMark, incoming: &SyncMark, tolerance_ms: u64) -> bool {
+    let difference = incoming.timestamp.signed_duration_since(current.timestamp).num_milliseconds();
+
+    if difference.unsigned_abs() <= tolerance_ms {
+        incoming.id > current.id
+    } else {
+        difference > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clipboard::types::{ClipboardContentType, ClipboardMetadata};
+    use chrono::Duration;
+
+    fn entry(id: &str, data: &str, timestamp: DateTime<Utc>) -> ClipboardEntry {
+        ClipboardEntry {
+            id: id.to_string(),
+            content_type: ClipboardContentType::Text,
+            data: data.to_string(),
+            metadata: ClipboardMetadata {
+                size: data.len(),
+                mime_type: "text/plain".to_string(),
+                source: "local".to_string(),
+                truncated: false,
+                origin: None,
+            },
+            timestamp,
+            pinned: false,
+        }
+    }
+
+    /// Ein Peer: übernimmt den Eintrag des anderen, wenn er gewinnt
+    fn deliver(receiver: &mut SyncState, entry: &ClipboardEntry) -> RemoteSyncOutcome {
+        let outcome = receiver.check_remote(entry);
+        if outcome == RemoteSyncOutcome::Applied {
+            receiver.remote_applied(entry, entry.data.len() as u64, Instant::now());
+        }
+        outcome
+    }
+
+    #[test]
+    fn test_near_simultaneous_copies_converge() {
+        let now = Utc::now();
+        let mut host = SyncState::default();
+        let mut client = SyncState::default();
+
+        // Beide kopieren innerhalb von 100 ms, beide Einträge kreuzen sich
+        let host_entry = entry("a-host", "host text", now);
+        let client_entry = entry("b-client", "client text", now + Duration::milliseconds(100));
+        assert!(host.local_change(&host_entry));
+        assert!(client.local_change(&client_entry));
+
+        let at_host = deliver(&mut host, &client_entry);
+        let at_client = deliver(&mut client, &host_entry);
+
+        // Genau ein Eintrag gewinnt, auf beiden Seiten derselbe
+        assert_eq!(at_host, RemoteSyncOutcome::Applied);
+        assert_eq!(at_client, RemoteSyncOutcome::LostConflict);
+        assert_eq!(host.status().last_applied_remote_id.as_deref(), Some("b-client"));
+    }
+
+    #[test]
+    fn test_tie_break_ignores_clock_skew() {
+        let now = Utc::now();
+        let mut host = SyncState::default();
+        let mut client = SyncState::default();
+
+        // Die Uhr des Clients geht vor: sein Eintrag wirkt neuer, die ID entscheidet trotzdem
+        let host_entry = entry("z-host", "host text", now + Duration::milliseconds(10));
+        let client_entry = entry("a-client", "client text", now + Duration::milliseconds(400));
+        host.local_change(&host_entry);
+        client.local_change(&client_entry);
+
+        assert_eq!(deliver(&mut host, &client_entry), RemoteSyncOutcome::LostConflict);
+        assert_eq!(deliver(&mut client, &host_entry), RemoteSyncOutcome::Applied);
+    }
+
+    #[test]
+    fn test_later_writer_wins_outside_tolerance() {
+        let now = Utc::now();
+        let mut host = SyncState::default();
+
+        host.local_change(&entry("z-local", "local", now));
+        let stale = entry("a-remote", "stale", now - Duration::seconds(2));
+        assert_eq!(host.check_remote(&stale), RemoteSyncOutcome::LostConflict);
+
+        let newer = entry("a-remote-2", "newer", now + Duration::seconds(2));
+        assert_eq!(deliver(&mut host, &newer), RemoteSyncOutcome::Applied);
+        assert_eq!(deliver(&mut host, &newer), RemoteSyncOutcome::Duplicate);
+    }
+
+    #[test]
+    fn test_applied_remote_content_is_not_rebroadcast() {
+        let now = Instant::now();
+        let mut host = SyncState::default();
+        let remote = entry("remote", "from peer", Utc::now());
+        host.remote_applied(&remote, 42, now);
+
+        // Der Monitor liest den eben gesetzten Inhalt (auch mehrfach) wieder
+        assert!(host.is_echo(42, now));
+        assert!(host.is_echo(42, now + std::time::Duration::from_millis(500)));
+        assert!(!host.is_echo(7, now));
+
+        // Nach dem Fenster ist derselbe Inhalt eine neue Kopie
+        assert!(!host.is_echo(42, now + ECHO_WINDOW));
+    }
+
+    #[test]
+    fn test_recent_remote_entries_are_bounded() {
+        let now = Instant::now();
+        let mut host = SyncState::default();
+
+        // Schnell nacheinander übernommene Einträge gelten alle als Echo
+        for i in 0..=ECHO_CAPACITY as u64 {
+            host.remote_applied(&entry(&format!("remote-{}", i), "text", Utc::now()), i, now);
+        }
+        assert!(host.is_echo(1, now) && host.is_echo(ECHO_CAPACITY as u64, now));
+        assert!(!host.is_echo(0, now), "the oldest entry is forgotten");
+        assert_eq!(host.check_remote(&entry("remote-1", "text", Utc::now())), RemoteSyncOutcome::Duplicate);
+
+        // Abgelaufene Einträge werden beim nächsten Übernehmen verworfen
+        host.remote_applied(&entry("late", "text", Utc::now()), 99, now + ECHO_WINDOW);
+        assert_eq!(host.recent_remote.len(), 1);
+        assert_eq!(host.status().last_applied_remote_id.as_deref(), Some("late"));
+    }
+
+    #[test]
+    fn test_only_direct_entries_are_relayed() {
+        use crate::clipboard::types::ClipboardOrigin;
+
+        let host = SyncState::default();
+        let with_hops = |hops: u32| {
+            let mut relayed = entry("relayed", "text", Utc::now());
+            relayed.metadata.origin = Some(ClipboardOrigin { peer_id: "a".to_string(), hops });
+            relayed
+        };
+
+        assert!(host.should_relay(&with_hops(1)));
+        assert!(!host.should_relay(&with_hops(2)));
+        assert!(!host.should_relay(&entry("local", "text", Utc::now())));
+    }
+
+    #[test]
+    fn test_direction_limits_both_ways() {
+        let mut host = SyncState::default();
+        let remote = entry("remote", "from peer", Utc::now());
+
+        host.set_policy(ClipboardSyncPolicy { direction: SyncDirection::HostToClient, ..ClipboardSyncPolicy::default() });
+        assert_eq!(host.check_remote(&remote), RemoteSyncOutcome::DirectionBlocked);
+        assert!(host.local_change(&entry("local", "mine", Utc::now())));
+
+        host.set_policy(ClipboardSyncPolicy { direction: SyncDirection::ClientToHost, ..ClipboardSyncPolicy::default() });
+        assert!(!host.local_change(&entry("local-2", "mine", Utc::now())));
+
+        host.set_policy(ClipboardSyncPolicy { direction: SyncDirection::Disabled, ..ClipboardSyncPolicy::default() });
+        assert_eq!(host.check_remote(&remote), RemoteSyncOutcome::DirectionBlocked);
+        assert!(!host.local_change(&entry("local-3", "mine", Utc::now())));
+    }
+}
@@ -1,8 +1,12 @@
 // src-tauri/src/clipboard/types.rs - Typen für die Zwischenablage-Synchronisation
 
+use std::sync::mpsc::Receiver;
+
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
+use crate::clipboard::limit::LimitedText;
+
 /// Typ des Zwischenablage-Inhalts
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum ClipboardContentType {
@@ -10,28 +14,57 @@ pub enum ClipboardContentType {
     Image,
     Html,
     Files,
+    /// Bild, das per Dateiübertragung nachgeliefert wird (siehe `offload`)
+    ImageReference,
+    /// Text als Änderung gegenüber einem früheren Eintrag (siehe `delta`);
+    /// kommt nur über die Leitung und landet nie im Verlauf
+    TextDelta,
 }
 
 /// Metadaten für Zwischenablage-Einträge
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ClipboardMetadata {
     /// Größe der Daten in Bytes
     pub size: usize,
     
-    /// MIME-Typ des Inhalts
+    /// MIME-Typ des Inhalts (`mime_type` in Verläufen älterer Versionen)
+    #[serde(alias = "mime_type")]
     pub mime_type: String,
     
     /// Quelle des Eintrags (local, remote, etc.)
     pub source: String,
+    
+    /// Nur eine Vorschau gespeichert, `size` ist die Größe des ganzen Inhalts
+    #[serde(default)]
+    pub truncated: bool,
+    
+    /// Herkunft von Einträgen, die von einem Peer kamen; fehlt bei lokal
+    /// kopierten Inhalten und bei Gegenstellen älterer Versionen
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<ClipboardOrigin>,
+}
+
+/// Woher ein Eintrag ursprünglich stammt und wie oft er weitergegeben wurde
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipboardOrigin {
+    /// Peer, auf dem der Inhalt kopiert wurde
+    pub peer_id: String,
+    
+    /// Anzahl der Übertragungen zwischen Peers (1 = direkt vom Ursprung)
+    pub hops: u32,
 }
 
 /// Ein Eintrag in der Zwischenablage
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ClipboardEntry {
     /// Eindeutige ID des Eintrags
     pub id: String,
     
-    /// Art des Inhalts
+    /// Art des Inhalts (`content_type` in Verläufen älterer Versionen)
+    #[serde(alias = "content_type")]
     pub content_type: ClipboardContentType,
     
     /// Die eigentlichen Daten (Text oder Base64-kodiert für Binärdaten)
@@ -42,6 +75,10 @@ pub struct ClipboardEntry {
     
     /// Zeitstempel der Erstellung
     pub timestamp: DateTime<Utc>,
+    
+    /// Angeheftet: bleibt beim Kürzen und Leeren des Verlaufs erhalten
+    #[serde(default)]
+    pub pinned: bool,
 }
 
 /// Trait für plattformspezifische Zwischenablage-Implementierungen
@@ -70,6 +107,16 @@ pub trait ClipboardProvider: Send + Sync {
         self.set_text(html)
     }
     
+    /// Holt Text (`text/plain`) oder HTML (`text/html`), behält aber höchstens
+    /// `max_size` Bytes; von größeren Inhalten bleibt nur eine Vorschau.
+    ///
+    /// Provider, die die Ausgabe ihres Tools streamen können, überschreiben
+    /// dies, damit große Inhalte nie vollständig im Speicher landen.
+    fn get_text_limited(&mut self, mime_type: &str, max_size: usize) -> Result<LimitedText, crate::clipboard::error::ClipboardError> {
+        let text = if mime_type == "text/html" { self.get_html()? } else { self.get_text()? };
+        Ok(LimitedText::from_text(text, max_size))
+    }
+    
     /// Holt Dateilisten aus der Zwischenablage
     fn get_files(&mut self) -> Result<Vec<String>, crate::clipboard::error::ClipboardError> {
         Err(crate::clipboard::error::ClipboardError::UnsupportedOperation("File clipboard not supported".to_string()))
@@ -85,6 +132,241 @@ pub trait ClipboardProvider: Send + Sync {
     fn get_available_formats(&self) -> Vec<String> {
         vec!["text/plain".to_string()]
     }
+    
+    /// Wählt die X-Selection, die gelesen, gesetzt und beobachtet wird
+    fn set_selection(&mut self, selection: ClipboardSelection) -> Result<(), crate::clipboard::error::ClipboardError> {
+        match selection {
+            ClipboardSelection::Clipboard => Ok(()),
+            ClipboardSelection::Primary => Err(crate::clipboard::error::ClipboardError::UnsupportedOperation(
+                "Primary selection not supported".to_string()
+            )),
+        }
+    }
+    
+    /// Abonniert Änderungsbenachrichtigungen der Zwischenablage.
+    ///
+    /// Provider ohne native Benachrichtigungen liefern einen Fehler; der
+    /// ClipboardManager fällt dann auf Polling zurück.
+    fn subscribe(&mut self) -> Result<ClipboardSubscription, crate::clipboard::error::ClipboardError> {
+        Err(crate::clipboard::error::ClipboardError::UnsupportedOperation(
+            "Clipboard change notifications not supported".to_string()
+        ))
+    }
+}
+
+/// Aktives Abonnement auf Änderungen der Zwischenablage
+pub struct ClipboardSubscription {
+    /// Liefert ein Signal pro Änderung; getrennt, wenn der Watcher aufgibt
+    pub changes: Receiver<()>,
+    
+    /// Beendet den Watcher (z.B. den Kindprozess)
+    stop: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl ClipboardSubscription {
+    /// Erstellt ein Abonnement mit einer Funktion zum Beenden des Watchers
+    pub fn new<F>(changes: Receiver<()>, stop: F) -> Self
+    where
+        F: FnOnce() + Send + 'static
+    {
+        ClipboardSubscription {
+            changes,
+            stop: Some(Box::new(stop)),
+        }
+    }
+    
+    /// Beendet das Abonnement
+    pub fn stop(mut self) {
+        if let Some(stop) = self.stop.take() {
+            stop();
+        }
+    }
+}
+
+impl Drop for ClipboardSubscription {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            stop();
+        }
+    }
+}
+
+/// Welche Selection synchronisiert wird
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum ClipboardSelection {
+    /// Strg+C / Strg+V
+    #[default]
+    Clipboard,
+    
+    /// Markierter Text, eingefügt per Mittelklick
+    Primary,
+}
+
+/// Welche Zwischenablage eine Verbindung verwenden darf
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum ClipboardConnectionMode {
+    /// Nur die Systemzwischenablage wird synchronisiert
+    #[default]
+    SystemClipboard,
+    
+    /// Nur die gemeinsame Zwischenablage (siehe `shared`); die
+    /// Systemzwischenablage bleibt unberührt
+    SharedOnly,
+    
+    /// Beides
+    Both,
+}
+
+impl ClipboardConnectionMode {
+    /// Darf die Systemzwischenablage mit dem Peer synchronisiert werden?
+    pub fn allows_system(&self) -> bool {
+        !matches!(self, ClipboardConnectionMode::SharedOnly)
+    }
+    
+    /// Darf die gemeinsame Zwischenablage verwendet werden?
+    pub fn allows_shared(&self) -> bool {
+        !matches!(self, ClipboardConnectionMode::SystemClipboard)
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_sync_size() -> usize {
+    1024 * 1024 // 1 MB
+}
+
+fn default_image_offload_threshold() -> usize {
+    512 * 1024 // 512 KB Base64
+}
+
+fn default_sync_freshness_secs() -> u64 {
+    30
+}
+
+fn default_delta_sync_min_size() -> usize {
+    64 * 1024 // 64 KB
+}
+
+fn default_shared_clipboard_max_entries() -> usize {
+    32
+}
+
+fn default_shared_clipboard_max_entry_size() -> usize {
+    64 * 1024 // 64 KB
+}
+
+fn default_max_poll_interval_ms() -> u64 {
+    crate::clipboard::poll::MIN_MAX_POLL_INTERVAL_MS
+}
+
+/// Konfiguration des ClipboardManagers (Verlauf, Filter, Persistenz)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardConfig {
+    /// Maximale Anzahl von Einträgen im Verlauf
+    pub max_history_size: usize,
+    
+    /// Maximale Größe eines einzelnen Eintrags in Bytes
+    pub max_entry_size: usize,
+    
+    /// Größere Inhalte werden nur als Vorschau im Verlauf vermerkt und nicht synchronisiert
+    #[serde(default = "default_max_sync_size")]
+    pub max_sync_size: usize,
+    
+    /// Regex-Muster; passende Inhalte werden weder gespeichert noch synchronisiert
+    pub ignore_patterns: Vec<String>,
+    
+    /// MIME-Typen, bei deren Vorhandensein der Inhalt ignoriert wird
+    pub excluded_mime_types: Vec<String>,
+    
+    /// Verlauf verschlüsselt im App-Datenverzeichnis speichern
+    pub persist_to_disk: bool,
+    
+    /// Beobachtete Selection (CLIPBOARD oder PRIMARY)
+    #[serde(default)]
+    pub selection: ClipboardSelection,
+    
+    /// HTML statt reinem Text übernehmen, wenn angeboten
+    #[serde(default = "default_true")]
+    pub sync_html: bool,
+    
+    /// Bilder (image/png) übernehmen
+    #[serde(default = "default_true")]
+    pub sync_images: bool,
+    
+    /// Größere Bilder (Base64-Größe in Bytes) gehen per Dateiübertragung statt
+    /// inline an den Peer; 0 = immer inline
+    #[serde(default = "default_image_offload_threshold")]
+    pub image_offload_threshold: usize,
+    
+    /// Verschlüsselte Einträge, deren Sendezeit länger zurückliegt, werden
+    /// abgelehnt (Schutz vor Wiedereinspielung); 0 = keine Prüfung
+    #[serde(default = "default_sync_freshness_secs")]
+    pub sync_freshness_secs: u64,
+    
+    /// Texte ab dieser Größe (Bytes) gehen als Delta zum zuletzt an den Peer
+    /// gesendeten Text, wenn sie ihm ähnlich genug sind
+    #[serde(default = "default_delta_sync_min_size")]
+    pub delta_sync_min_size: usize,
+    
+    /// Anteil (0..1) des neuen Texts, der aus dem alten kopiert werden kann,
+    /// damit ein Delta gesendet wird; 0 = aus (Gegenstellen vor Version mit
+    /// `TextDelta` können Deltas nicht lesen)
+    #[serde(default)]
+    pub delta_sync_similarity: f32,
+    
+    /// Zwischenablage für Verbindungen ohne eigene Einstellung
+    /// (`set_clipboard_connection_mode`)
+    #[serde(default)]
+    pub connection_mode: ClipboardConnectionMode,
+    
+    /// Höchstzahl der Einträge der gemeinsamen Zwischenablage je Verbindung
+    #[serde(default = "default_shared_clipboard_max_entries")]
+    pub shared_clipboard_max_entries: usize,
+    
+    /// Maximale Größe eines Eintrags der gemeinsamen Zwischenablage in Bytes
+    #[serde(default = "default_shared_clipboard_max_entry_size")]
+    pub shared_clipboard_max_entry_size: usize,
+    
+    /// Von einem Peer übernommene Einträge an die übrigen Peers weitergeben
+    /// (z.B. zwischen zwei Viewern eines Hosts); weitergegebene Einträge
+    /// werden nicht noch einmal weitergegeben
+    #[serde(default)]
+    pub relay_remote_entries: bool,
+    
+    /// Längstes Abfrageintervall in ms, wenn es keine native
+    /// Änderungsbenachrichtigung gibt (2000 bis 5000, siehe `poll`)
+    #[serde(default = "default_max_poll_interval_ms")]
+    pub max_poll_interval_ms: u64,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        ClipboardConfig {
+            max_history_size: 50,
+            max_entry_size: 1024 * 1024, // 1 MB
+            max_sync_size: default_max_sync_size(),
+            ignore_patterns: vec![],
+            excluded_mime_types: vec![
+                // Von KeePassXC, KWallet u.a. gesetzt, um Passwörter zu markieren
+                crate::clipboard::filter::PASSWORD_MANAGER_HINT.to_string(),
+            ],
+            persist_to_disk: false,
+            selection: ClipboardSelection::Clipboard,
+            sync_html: true,
+            sync_images: true,
+            image_offload_threshold: default_image_offload_threshold(),
+            sync_freshness_secs: default_sync_freshness_secs(),
+            delta_sync_min_size: default_delta_sync_min_size(),
+            delta_sync_similarity: 0.0,
+            connection_mode: ClipboardConnectionMode::SystemClipboard,
+            shared_clipboard_max_entries: default_shared_clipboard_max_entries(),
+            shared_clipboard_max_entry_size: default_shared_clipboard_max_entry_size(),
+            relay_remote_entries: false,
+            max_poll_interval_ms: default_max_poll_interval_ms(),
+        }
+    }
 }
 
 /// Konfiguration für die Zwischenablage-Synchronisation
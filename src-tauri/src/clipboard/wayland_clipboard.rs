@@ -1,8 +1,23 @@
 // src-tauri/src/clipboard/wayland_clipboard.rs - Wayland-spezifische Zwischenablage-Implementierung
 
-use std::process::Command;
-use crate::clipboard::types::ClipboardProvider;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use crate::clipboard::types::{ClipboardProvider, ClipboardSubscription};
+use crate::command_runner::{CommandRunner, SystemCommandRunner};
 use crate::clipboard::error::ClipboardError;
+use crate::clipboard::limit::{self, LimitedText};
+use crate::logging::TARGET_CLIPBOARD;
+
+/// Wartezeit vor dem Neustart eines beendeten wl-paste-Watchers
+const WATCH_RESPAWN_DELAY: Duration = Duration::from_secs(1);
+
+/// Maximale Anzahl aufeinanderfolgender fehlgeschlagener Neustarts
+const WATCH_MAX_RESPAWN_FAILURES: u32 = 5;
 
 /// Wayland-spezifische Zwischenablage-Implementierung
 pub struct WaylandClipboardProvider {
@@ -12,14 +27,22 @@ pub struct WaylandClipboardProvider {
     /// Ob wl-copy und wl-paste verfügbar sind
     has_wl_copy: bool,
     has_wl_paste: bool,
+    
+    /// Erzeugt die Aufrufe von wl-copy und wl-paste
+    runner: Arc<dyn CommandRunner>,
 }
 
 impl WaylandClipboardProvider {
     /// Erstellt einen neuen WaylandClipboardProvider
     pub fn new() -> Result<Self, ClipboardError> {
+        Self::with_runner(Arc::new(SystemCommandRunner))
+    }
+    
+    /// Erstellt einen WaylandClipboardProvider, dessen Tool-Aufrufe über `runner` laufen
+    pub fn with_runner(runner: Arc<dyn CommandRunner>) -> Result<Self, ClipboardError> {
         // Prüfen, ob wl-clipboard-Tools verfügbar sind
-        let has_wl_copy = Self::check_tool_available("wl-copy");
-        let has_wl_paste = Self::check_tool_available("wl-paste");
+        let has_wl_copy = Self::check_tool_available(runner.as_ref(), "wl-copy");
+        let has_wl_paste = Self::check_tool_available(runner.as_ref(), "wl-paste");
         let has_wl_clipboard = has_wl_copy && has_wl_paste;
         
         if !has_wl_clipboard {
@@ -32,12 +55,13 @@ impl WaylandClipboardProvider {
             has_wl_clipboard,
             has_wl_copy,
             has_wl_paste,
+            runner,
         })
     }
     
     /// Prüft, ob ein Tool verfügbar ist
-    fn check_tool_available(tool: &str) -> bool {
-        Command::new("which")
+    fn check_tool_available(runner: &dyn CommandRunner, tool: &str) -> bool {
+        runner.command("which")
             .arg(tool)
             .output()
             .map(|output| output.status.success())
@@ -50,7 +74,7 @@ impl WaylandClipboardProvider {
             return Err(ClipboardError::UnsupportedOperation("wl-paste not available".to_string()));
         }
         
-        let mut cmd = Command::new("wl-paste");
+        let mut cmd = self.runner.command("wl-paste");
         cmd.args(args);
         
         let output = cmd.output()
@@ -73,7 +97,7 @@ impl WaylandClipboardProvider {
             return Err(ClipboardError::UnsupportedOperation("wl-copy not available".to_string()));
         }
         
-        let mut cmd = Command::new("wl-copy");
+        let mut cmd = self.runner.command("wl-copy");
         cmd.args(args);
         
         if let Some(input_data) = input {
@@ -115,6 +139,90 @@ impl WaylandClipboardProvider {
         Ok(())
     }
     
+    /// Startet `wl-paste --watch`, das bei jeder Änderung eine Zeile ausgibt.
+    ///
+    /// Der Inhalt selbst wird verworfen (`cat > /dev/null`), damit die
+    /// Ausgabe eindeutig in Änderungen zerlegt werden kann; gelesen wird
+    /// anschließend wie gewohnt über `get_text`.
+    fn spawn_watch_process(runner: &dyn CommandRunner) -> Result<Child, ClipboardError> {
+        runner.command("wl-paste")
+            .args(&["--watch", "sh", "-c", "cat > /dev/null; echo"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| ClipboardError::IoError(format!("Failed to spawn wl-paste --watch: {}", e)))
+    }
+    
+    /// Liest Änderungen aus dem Watcher und startet ihn neu, wenn er endet
+    /// (z.B. nach einem Neustart des Compositors)
+    fn run_watch_loop(
+        runner: Arc<dyn CommandRunner>,
+        first_child: Child,
+        current_child: Arc<Mutex<Option<Child>>>,
+        stopped: Arc<AtomicBool>,
+        sender: mpsc::Sender<()>,
+    ) {
+        let mut next_child = Some(first_child);
+        let mut failures = 0;
+        
+        while !stopped.load(Ordering::SeqCst) {
+            let mut child = match next_child.take() {
+                Some(child) => child,
+                None => match Self::spawn_watch_process(runner.as_ref()) {
+                    Ok(child) => child,
+                    Err(e) => {
+                        failures += 1;
+                        if failures >= WATCH_MAX_RESPAWN_FAILURES {
+                            // Sender wird verworfen, der Manager fällt auf Polling zurück
+                            log::error!(target: TARGET_CLIPBOARD, "Giving up on wl-paste --watch: {}", e);
+                            return;
+                        }
+                        thread::sleep(WATCH_RESPAWN_DELAY);
+                        continue;
+                    }
+                },
+            };
+            
+            let stdout = child.stdout.take();
+            *current_child.lock().unwrap() = Some(child);
+            
+            // stop() kann zwischen Spawn und Registrierung aufgerufen worden sein
+            if stopped.load(Ordering::SeqCst) {
+                break;
+            }
+            
+            if let Some(stdout) = stdout {
+                for line in BufReader::new(stdout).lines() {
+                    if line.is_err() {
+                        break;
+                    }
+                    failures = 0;
+                    if sender.send(()).is_err() {
+                        // Empfänger existiert nicht mehr
+                        stopped.store(true, Ordering::SeqCst);
+                        break;
+                    }
+                }
+            }
+            
+            // Watcher ist beendet; Prozess einsammeln und ggf. neu starten
+            if let Some(mut child) = current_child.lock().unwrap().take() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            
+            if !stopped.load(Ordering::SeqCst) {
+                thread::sleep(WATCH_RESPAWN_DELAY);
+            }
+        }
+        
+        if let Some(mut child) = current_child.lock().unwrap().take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+    
     /// Holt verfügbare MIME-Typen aus der Wayland-Zwischenablage
     fn get_available_mime_types(&self) -> Result<Vec<String>, ClipboardError> {
         let output = self.run_wl_paste(&["-l"])?;
@@ -148,7 +256,7 @@ impl ClipboardProvider for WaylandClipboardProvider {
     
     fn get_image(&mut self) -> Result<Vec<u8>, ClipboardError> {
         // Versuche PNG-Format zu holen
-        let png_result = Command::new("wl-paste")
+        let png_result = self.runner.command("wl-paste")
             .args(&["-t", "image/png"])
             .output();
         
@@ -159,7 +267,7 @@ impl ClipboardProvider for WaylandClipboardProvider {
         }
         
         // Fallback auf JPEG
-        let jpeg_result = Command::new("wl-paste")
+        let jpeg_result = self.runner.command("wl-paste")
             .args(&["-t", "image/jpeg"])
             .output();
         
@@ -170,7 +278,7 @@ impl ClipboardProvider for WaylandClipboardProvider {
         }
         
         // Fallback auf GIF
-        let gif_result = Command::new("wl-paste")
+        let gif_result = self.runner.command("wl-paste")
             .args(&["-t", "image/gif"])
             .output();
         
@@ -206,14 +314,14 @@ impl ClipboardProvider for WaylandClipboardProvider {
             .map_err(|e| ClipboardError::IoError(format!("Failed to write temp file: {}", e)))?;
         
         // Verwende wl-copy mit Datei-Input
-        let output = Command::new("wl-copy")
+        let output = self.runner.command("wl-copy")
             .args(&["-t", mime_type])
             .arg("<")
             .arg(&temp_file)
             .output();
         
         // Alternative: Verwende cat mit pipe zu wl-copy
-        let output = Command::new("sh")
+        let output = self.runner.command("sh")
             .arg("-c")
             .arg(&format!("cat '{}' | wl-copy -t '{}'", temp_file, mime_type))
             .output()
@@ -254,6 +362,23 @@ impl ClipboardProvider for WaylandClipboardProvider {
         Ok(())
     }
     
+    fn get_text_limited(&mut self, mime_type: &str, max_size: usize) -> Result<LimitedText, ClipboardError> {
+        if !self.has_wl_paste {
+            return Err(ClipboardError::UnsupportedOperation("wl-paste not available".to_string()));
+        }
+        
+        let mut cmd = self.runner.command("wl-paste");
+        if mime_type == "text/plain" {
+            cmd.arg("-n");
+        } else {
+            cmd.args(&["-t", mime_type]);
+        }
+        
+        limit::read_command_limited(cmd, "wl-paste", max_size, |stderr| {
+            stderr.contains("No selection") || stderr.contains("nothing to paste")
+        })
+    }
+    
     fn get_files(&mut self) -> Result<Vec<String>, ClipboardError> {
         // Versuche URI-Liste zu holen
         let output = self.run_wl_paste(&["-t", "text/uri-list"])?;
@@ -289,12 +414,43 @@ impl ClipboardProvider for WaylandClipboardProvider {
             has_wl_clipboard: self.has_wl_clipboard,
             has_wl_copy: self.has_wl_copy,
             has_wl_paste: self.has_wl_paste,
+            runner: self.runner.clone(),
         })
     }
     
     fn get_available_formats(&self) -> Vec<String> {
         self.get_available_mime_types().unwrap_or_else(|_| vec!["text/plain".to_string()])
     }
+    
+    fn subscribe(&mut self) -> Result<ClipboardSubscription, ClipboardError> {
+        if !self.has_wl_paste {
+            return Err(ClipboardError::UnsupportedOperation("wl-paste not available".to_string()));
+        }
+        
+        // Der erste Start muss gelingen, sonst bleibt der Manager beim Polling
+        let first_child = Self::spawn_watch_process(self.runner.as_ref())?;
+        
+        let (sender, receiver) = mpsc::channel();
+        let current_child = Arc::new(Mutex::new(None));
+        let stopped = Arc::new(AtomicBool::new(false));
+        
+        {
+            let runner = self.runner.clone();
+            let current_child = current_child.clone();
+            let stopped = stopped.clone();
+            thread::spawn(move || {
+                Self::run_watch_loop(runner, first_child, current_child, stopped, sender);
+            });
+        }
+        
+        Ok(ClipboardSubscription::new(receiver, move || {
+            stopped.store(true, Ordering::SeqCst);
+            // Kindprozess beenden, damit der Lese-Thread EOF sieht
+            if let Some(child) = current_child.lock().unwrap().as_mut() {
+                let _ = child.kill();
+            }
+        }))
+    }
 }
 
 /// Einfache HTML-zu-Text-Konvertierung
@@ -316,3 +472,72 @@ fn html_to_text(html: &str) -> String {
         .replace("&#x60;", "`")
         .replace("&#x3D;", "=")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::{call, TestEnv};
+
+    fn provider(env: &TestEnv) -> WaylandClipboardProvider {
+        WaylandClipboardProvider::with_runner(env.runner()).unwrap()
+    }
+
+    #[test]
+    fn test_text_round_trip() {
+        let env = TestEnv::new();
+        let mut provider = provider(&env);
+
+        env.set_stdout("wl-paste", "Grüße aus der Ferne");
+        assert_eq!(provider.get_text().unwrap(), "Grüße aus der Ferne");
+
+        env.record_stdin("wl-copy");
+        provider.set_text("lokaler Text").unwrap();
+        assert_eq!(env.stdin("wl-copy"), b"lokaler Text");
+
+        assert_eq!(env.invocations(), vec![call("wl-paste -n"), call("wl-copy")]);
+    }
+
+    #[test]
+    fn test_empty_selection() {
+        let env = TestEnv::new();
+        let mut provider = provider(&env);
+
+        // wl-paste meldet eine leere Zwischenablage über den Exit-Code
+        env.set_exit_code("wl-paste", 1);
+        env.set_stderr("wl-paste", "No selection");
+        assert!(matches!(provider.get_text(), Err(ClipboardError::EmptyClipboard)));
+
+        env.set_stderr("wl-paste", "Failed to connect to a Wayland server");
+        assert!(matches!(provider.get_text(), Err(ClipboardError::IoError(_))));
+    }
+
+    #[test]
+    fn test_large_text_is_streamed_into_a_preview() {
+        let env = TestEnv::new();
+        let mut provider = provider(&env);
+
+        env.set_stdout("wl-paste", "x".repeat(300 * 1024));
+        let content = provider.get_text_limited("text/plain", 100 * 1024).unwrap();
+        assert!(content.truncated);
+        assert_eq!((content.text.len(), content.size), (limit::TRUNCATED_PREVIEW_SIZE, 300 * 1024));
+
+        env.set_stdout("wl-paste", "<b>klein</b>");
+        let content = provider.get_text_limited("text/html", 100 * 1024).unwrap();
+        assert_eq!((content.text.as_str(), content.truncated), ("<b>klein</b>", false));
+
+        assert_eq!(env.take_invocations(), vec![call("wl-paste -n"), call("wl-paste -t text/html")]);
+
+        env.set_stdout("wl-paste", "");
+        assert!(matches!(provider.get_text_limited("text/plain", 1024), Err(ClipboardError::EmptyClipboard)));
+    }
+
+    #[test]
+    fn test_files_from_uri_list() {
+        let env = TestEnv::new();
+        let mut provider = provider(&env);
+
+        env.set_stdout("wl-paste", "# Kommentar\nfile:///home/user/Bericht%202024.pdf\nfile:///tmp/a.txt\n");
+        assert_eq!(provider.get_files().unwrap(), vec!["/home/user/Bericht 2024.pdf", "/tmp/a.txt"]);
+        assert_eq!(env.take_invocations(), vec![call("wl-paste -t text/uri-list")]);
+    }
+}
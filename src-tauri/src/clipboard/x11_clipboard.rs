@@ -1,9 +1,10 @@
 // src-tauri/src/clipboard/x11_clipboard.rs - X11-spezifische Zwischenablage-Implementierung
 
-use std::process::Command;
-use crate::clipboard::types::ClipboardProvider;
+use crate::command_runner::{CommandRunner, SystemCommandRunner};
+use crate::clipboard::types::{ClipboardProvider, ClipboardSelection};
 use crate::clipboard::error::ClipboardError;
-use base64::{Engine as _, engine::general_purpose};
+use crate::clipboard::limit::{self, LimitedText};
+use crate::tool_locator;
 
 /// X11-spezifische Zwischenablage-Implementierung
 pub struct X11ClipboardProvider {
@@ -15,6 +16,9 @@ pub struct X11ClipboardProvider {
     
     /// Bevorzugtes Tool (xclip oder xsel)
     preferred_tool: X11ClipboardTool,
+    
+    /// Verwendete Selection (CLIPBOARD oder PRIMARY)
+    selection: ClipboardSelection,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -50,13 +54,56 @@ impl X11ClipboardProvider {
             has_xclip,
             has_xsel,
             preferred_tool,
+            selection: ClipboardSelection::Clipboard,
         })
     }
     
+    /// Selection-Name für xclip
+    fn xclip_selection(&self) -> &'static str {
+        match self.selection {
+            ClipboardSelection::Clipboard => "clipboard",
+            ClipboardSelection::Primary => "primary",
+        }
+    }
+    
+    /// Selection-Schalter für xsel
+    fn xsel_selection(&self) -> &'static str {
+        match self.selection {
+            ClipboardSelection::Clipboard => "-b",
+            ClipboardSelection::Primary => "-p",
+        }
+    }
+    
+    /// Liest ein Ziel unverändert als Bytes (Bilder sind kein UTF-8)
+    fn read_xclip_target(&self, target: &str) -> Result<Vec<u8>, ClipboardError> {
+        if !self.has_xclip {
+            return Err(ClipboardError::UnsupportedOperation("xclip not available".to_string()));
+        }
+        
+        let output = SystemCommandRunner.command("xclip")
+            .args(&["-selection", self.xclip_selection(), "-t", target, "-o"])
+            .output()
+            .map_err(|e| ClipboardError::IoError(format!("Failed to execute xclip: {}", e)))?;
+        
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if stderr.contains("selection is empty") || stderr.contains("target") {
+                return Err(ClipboardError::EmptyClipboard);
+            }
+            return Err(ClipboardError::IoError(format!("xclip failed: {}", stderr)));
+        }
+        
+        if output.stdout.is_empty() {
+            return Err(ClipboardError::EmptyClipboard);
+        }
+        
+        Ok(output.stdout)
+    }
+    
     /// Prüft, ob ein Tool verfügbar ist
     fn check_tool_available(tool: &str) -> bool {
-        Command::new("which")
-            .arg(tool)
+        SystemCommandRunner.command("which")
+            .arg(tool_locator::resolve(tool))
             .output()
             .map(|output| output.status.success())
             .unwrap_or(false)
@@ -68,7 +115,7 @@ impl X11ClipboardProvider {
             return Err(ClipboardError::UnsupportedOperation("xclip not available".to_string()));
         }
         
-        let mut cmd = Command::new("xclip");
+        let mut cmd = SystemCommandRunner.command("xclip");
         cmd.args(args);
         
         if let Some(input_data) = input {
@@ -120,7 +167,7 @@ impl X11ClipboardProvider {
             return Err(ClipboardError::UnsupportedOperation("xsel not available".to_string()));
         }
         
-        let mut cmd = Command::new("xsel");
+        let mut cmd = SystemCommandRunner.command("xsel");
         cmd.args(args);
         
         if let Some(input_data) = input {
@@ -166,7 +213,7 @@ impl X11ClipboardProvider {
     fn get_available_mime_types(&self) -> Result<Vec<String>, ClipboardError> {
         match self.preferred_tool {
             X11ClipboardTool::XClip => {
-                let output = self.run_xclip_command(&["-selection", "clipboard", "-t", "TARGETS", "-o"], None)?;
+                let output = self.run_xclip_command(&["-selection", self.xclip_selection(), "-t", "TARGETS", "-o"], None)?;
                 
                 Ok(output.lines()
                     .map(|line| line.trim().to_string())
@@ -188,7 +235,7 @@ impl ClipboardProvider for X11ClipboardProvider {
     fn get_text(&mut self) -> Result<String, ClipboardError> {
         match self.preferred_tool {
             X11ClipboardTool::XClip => {
-                let output = self.run_xclip_command(&["-selection", "clipboard", "-o"], None)?;
+                let output = self.run_xclip_command(&["-selection", self.xclip_selection(), "-o"], None)?;
                 if output.is_empty() {
                     Err(ClipboardError::EmptyClipboard)
                 } else {
@@ -196,7 +243,7 @@ impl ClipboardProvider for X11ClipboardProvider {
                 }
             },
             X11ClipboardTool::XSel => {
-                let output = self.run_xsel_command(&["-b", "-o"], None)?;
+                let output = self.run_xsel_command(&[self.xsel_selection(), "-o"], None)?;
                 if output.is_empty() {
                     Err(ClipboardError::EmptyClipboard)
                 } else {
@@ -216,11 +263,11 @@ impl ClipboardProvider for X11ClipboardProvider {
         
         match self.preferred_tool {
             X11ClipboardTool::XClip => {
-                self.run_xclip_command(&["-selection", "clipboard", "-i"], Some(text))?;
+                self.run_xclip_command(&["-selection", self.xclip_selection(), "-i"], Some(text))?;
                 Ok(())
             },
             X11ClipboardTool::XSel => {
-                self.run_xsel_command(&["-b", "-i"], Some(text))?;
+                self.run_xsel_command(&[self.xsel_selection(), "-i"], Some(text))?;
                 Ok(())
             },
             X11ClipboardTool::None => {
@@ -232,22 +279,10 @@ impl ClipboardProvider for X11ClipboardProvider {
     fn get_image(&mut self) -> Result<Vec<u8>, ClipboardError> {
         match self.preferred_tool {
             X11ClipboardTool::XClip => {
-                // Versuche PNG-Format zu holen
-                let png_result = self.run_xclip_command(&["-selection", "clipboard", "-t", "image/png", "-o"], None);
-                
-                if let Ok(output) = png_result {
-                    if !output.is_empty() {
-                        // Ausgabe ist bereits Binärdaten, direkt zurückgeben
-                        return Ok(output.into_bytes());
-                    }
-                }
-                
-                // Fallback auf JPEG
-                let jpeg_result = self.run_xclip_command(&["-selection", "clipboard", "-t", "image/jpeg", "-o"], None);
-                
-                if let Ok(output) = jpeg_result {
-                    if !output.is_empty() {
-                        return Ok(output.into_bytes());
+                // PNG bevorzugen, sonst JPEG
+                for target in ["image/png", "image/jpeg"] {
+                    if let Ok(data) = self.read_xclip_target(target) {
+                        return Ok(data);
                     }
                 }
                 
@@ -287,8 +322,8 @@ impl ClipboardProvider for X11ClipboardProvider {
                     .map_err(|e| ClipboardError::IoError(format!("Failed to write temp file: {}", e)))?;
                 
                 // Verwende xclip mit Datei-Input
-                let output = Command::new("xclip")
-                    .args(&["-selection", "clipboard", "-t", mime_type, "-i", &temp_file])
+                let output = SystemCommandRunner.command("xclip")
+                    .args(&["-selection", self.xclip_selection(), "-t", mime_type, "-i", &temp_file])
                     .output()
                     .map_err(|e| ClipboardError::IoError(format!("Failed to execute xclip: {}", e)))?;
                 
@@ -315,7 +350,7 @@ impl ClipboardProvider for X11ClipboardProvider {
     fn get_html(&mut self) -> Result<String, ClipboardError> {
         match self.preferred_tool {
             X11ClipboardTool::XClip => {
-                let output = self.run_xclip_command(&["-selection", "clipboard", "-t", "text/html", "-o"], None)?;
+                let output = self.run_xclip_command(&["-selection", self.xclip_selection(), "-t", "text/html", "-o"], None)?;
                 if output.is_empty() {
                     // Fallback auf Text
                     self.get_text()
@@ -336,12 +371,9 @@ impl ClipboardProvider for X11ClipboardProvider {
     fn set_html(&mut self, html: &str) -> Result<(), ClipboardError> {
         match self.preferred_tool {
             X11ClipboardTool::XClip => {
-                // Setze sowohl HTML als auch Text
-                self.run_xclip_command(&["-selection", "clipboard", "-t", "text/html", "-i"], Some(html))?;
-                
-                // Zusätzlich als plain text setzen für Kompatibilität
-                let text_content = html_to_text(html);
-                self.run_xclip_command(&["-selection", "clipboard", "-t", "text/plain", "-i"], Some(&text_content))?;
+                // xclip bietet nur ein Ziel pro Aufruf an, ein zweiter Aufruf würde
+                // das HTML wieder verdrängen. HTML-fähige Anwendungen bevorzugen text/html.
+                self.run_xclip_command(&["-selection", self.xclip_selection(), "-t", "text/html", "-i"], Some(html))?;
                 
                 Ok(())
             },
@@ -356,11 +388,39 @@ impl ClipboardProvider for X11ClipboardProvider {
         }
     }
     
+    fn get_text_limited(&mut self, mime_type: &str, max_size: usize) -> Result<LimitedText, ClipboardError> {
+        match self.preferred_tool {
+            X11ClipboardTool::XClip => {
+                let mut cmd = SystemCommandRunner.command("xclip");
+                cmd.args(&["-selection", self.xclip_selection()]);
+                // Ohne Ziel liefert xclip den Text wie get_text
+                if mime_type != "text/plain" {
+                    cmd.args(&["-t", mime_type]);
+                }
+                cmd.arg("-o");
+                
+                limit::read_command_limited(cmd, "xclip", max_size, |stderr| {
+                    stderr.contains("selection is empty") || stderr.contains("target")
+                })
+            },
+            X11ClipboardTool::XSel => {
+                // xsel kennt nur Text; HTML fällt wie bei get_html auf Text zurück
+                let mut cmd = SystemCommandRunner.command("xsel");
+                cmd.args(&[self.xsel_selection(), "-o"]);
+                
+                limit::read_command_limited(cmd, "xsel", max_size, |_| true)
+            },
+            X11ClipboardTool::None => {
+                Err(ClipboardError::ClipboardUnavailable("No clipboard tool available".to_string()))
+            }
+        }
+    }
+    
     fn get_files(&mut self) -> Result<Vec<String>, ClipboardError> {
         match self.preferred_tool {
             X11ClipboardTool::XClip => {
                 // Versuche URI-Liste zu holen
-                let output = self.run_xclip_command(&["-selection", "clipboard", "-t", "text/uri-list", "-o"], None)?;
+                let output = self.run_xclip_command(&["-selection", self.xclip_selection(), "-t", "text/uri-list", "-o"], None)?;
                 
                 if output.is_empty() {
                     return Err(ClipboardError::EmptyClipboard);
@@ -398,12 +458,108 @@ impl ClipboardProvider for X11ClipboardProvider {
             has_xclip: self.has_xclip,
             has_xsel: self.has_xsel,
             preferred_tool: self.preferred_tool,
+            selection: self.selection,
         })
     }
     
     fn get_available_formats(&self) -> Vec<String> {
         self.get_available_mime_types().unwrap_or_else(|_| vec!["text/plain".to_string()])
     }
+    
+    fn set_selection(&mut self, selection: ClipboardSelection) -> Result<(), ClipboardError> {
+        self.selection = selection;
+        Ok(())
+    }
+    
+    #[cfg(feature = "x11-support")]
+    fn subscribe(&mut self) -> Result<crate::clipboard::types::ClipboardSubscription, ClipboardError> {
+        xfixes_watch::subscribe(self.selection)
+    }
+}
+
+/// Benachrichtigungen über Besitzerwechsel der Selection via XFIXES,
+/// statt xclip alle 500ms zu starten
+#[cfg(feature = "x11-support")]
+mod xfixes_watch {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+    use std::time::Duration;
+    
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xfixes::{ConnectionExt as _, SelectionEventMask};
+    use x11rb::protocol::xproto::ConnectionExt as _;
+    use x11rb::protocol::Event;
+    
+    use crate::clipboard::error::ClipboardError;
+    use crate::clipboard::types::{ClipboardSelection, ClipboardSubscription};
+    use crate::logging::TARGET_CLIPBOARD;
+    
+    /// Wie oft der Watcher das Stopp-Flag prüft, wenn keine Events anliegen
+    const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+    
+    pub fn subscribe(selection: ClipboardSelection) -> Result<ClipboardSubscription, ClipboardError> {
+        let (conn, screen_num) = x11rb::connect(None)
+            .map_err(|e| ClipboardError::ClipboardUnavailable(format!("Failed to connect to X server: {}", e)))?;
+        let root = conn.setup().roots[screen_num].root;
+        
+        conn.xfixes_query_version(5, 0)
+            .map_err(to_clipboard_error)?
+            .reply()
+            .map_err(|e| ClipboardError::UnsupportedOperation(format!("XFIXES not available: {}", e)))?;
+        
+        let atom_name: &[u8] = match selection {
+            ClipboardSelection::Clipboard => b"CLIPBOARD",
+            ClipboardSelection::Primary => b"PRIMARY",
+        };
+        let atom = conn.intern_atom(false, atom_name)
+            .map_err(to_clipboard_error)?
+            .reply()
+            .map_err(to_clipboard_error)?
+            .atom;
+        
+        let mask = SelectionEventMask::SET_SELECTION_OWNER
+            | SelectionEventMask::SELECTION_WINDOW_DESTROY
+            | SelectionEventMask::SELECTION_CLIENT_CLOSE;
+        conn.xfixes_select_selection_input(root, atom, mask)
+            .map_err(to_clipboard_error)?;
+        conn.flush().map_err(to_clipboard_error)?;
+        
+        let (sender, receiver) = mpsc::channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+        
+        {
+            let stopped = stopped.clone();
+            thread::spawn(move || {
+                while !stopped.load(Ordering::SeqCst) {
+                    match conn.poll_for_event() {
+                        Ok(Some(Event::XfixesSelectionNotify(event))) if event.selection == atom => {
+                            if sender.send(()).is_err() {
+                                return;
+                            }
+                        },
+                        Ok(Some(_)) => {},
+                        Ok(None) => thread::sleep(EVENT_POLL_INTERVAL),
+                        Err(e) => {
+                            // Sender wird verworfen, der Manager fällt auf Polling zurück
+                            log::error!(target: TARGET_CLIPBOARD, "Lost X connection of clipboard watcher: {}", e);
+                            return;
+                        }
+                    }
+                }
+            });
+        }
+        
+        log::debug!(target: TARGET_CLIPBOARD, "Watching {:?} selection via XFIXES", selection);
+        
+        Ok(ClipboardSubscription::new(receiver, move || {
+            stopped.store(true, Ordering::SeqCst);
+        }))
+    }
+    
+    fn to_clipboard_error<E: std::fmt::Display>(e: E) -> ClipboardError {
+        ClipboardError::IoError(format!("X11 request failed: {}", e))
+    }
 }
 
 /// Einfache HTML-zu-Text-Konvertierung
@@ -0,0 +1,560 @@
+// src-tauri/src/command_error.rs - Typed errors returned by Tauri commands
+//
+// Every command fails with a `CommandError`, which reaches the frontend as
+// `{ kind, message, remediation }`. The frontend branches on `kind`; `message`
+// is for display and may change between releases.
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::accessibility::AccessibilityError;
+use crate::capabilities::FeatureStatus;
+use crate::chat::ChatError;
+use crate::clipboard::error::ClipboardError;
+use crate::config_bundle::BundleError;
+use crate::connection_security::SecurityError;
+use crate::file_transfer::error::FileTransferError;
+use crate::ice::IceError;
+use crate::input_forwarding::error::InputForwardingError;
+use crate::logging::LoggingError;
+use crate::protocol::{ProtocolError, VersionMismatch};
+use crate::safe_mode::SafeModeError;
+use crate::screen_capture::error::ScreenCaptureError;
+use crate::settings::SettingsError;
+use crate::signaling::error::SignalingError;
+
+/// Stable error kinds. The serialized strings are part of the IPC interface:
+/// new kinds may be added, existing ones are never renamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ErrorKind {
+    #[serde(rename = "capture/not-initialized")]
+    CaptureNotInitialized,
+    #[serde(rename = "capture/invalid-monitor")]
+    CaptureInvalidMonitor,
+    #[serde(rename = "capture/invalid-region")]
+    CaptureInvalidRegion,
+    #[serde(rename = "capture/invalid-config")]
+    CaptureInvalidConfig,
+    #[serde(rename = "capture/too-many-captures")]
+    CaptureTooManyCaptures,
+    /// The monitor is being captured and the request was not forced
+    #[serde(rename = "capture/monitor-busy")]
+    CaptureMonitorBusy,
+    #[serde(rename = "capture/consent-denied")]
+    CaptureConsentDenied,
+    /// The codec or hardware acceleration cannot be used on this system
+    #[serde(rename = "capture/encoder-unavailable")]
+    CaptureEncoderUnavailable,
+    #[serde(rename = "capture/permission-denied")]
+    CapturePermissionDenied,
+    /// X11, PipeWire or the desktop portal failed
+    #[serde(rename = "capture/display-server")]
+    CaptureDisplayServer,
+    #[serde(rename = "capture/virtual-display")]
+    CaptureVirtualDisplay,
+    /// No built-in or saved capture profile has the requested name
+    #[serde(rename = "capture/unknown-profile")]
+    CaptureUnknownProfile,
+    #[serde(rename = "capture/failed")]
+    CaptureFailed,
+
+    #[serde(rename = "input/not-initialized")]
+    InputNotInitialized,
+    #[serde(rename = "input/permission-denied")]
+    InputPermissionDenied,
+    #[serde(rename = "input/unsupported-event")]
+    InputUnsupportedEvent,
+    #[serde(rename = "input/invalid-config")]
+    InputInvalidConfig,
+    #[serde(rename = "input/macro")]
+    InputMacro,
+    #[serde(rename = "input/hotkey")]
+    InputHotkey,
+    #[serde(rename = "input/failed")]
+    InputFailed,
+
+    #[serde(rename = "clipboard/not-initialized")]
+    ClipboardNotInitialized,
+    #[serde(rename = "clipboard/unavailable")]
+    ClipboardUnavailable,
+    #[serde(rename = "clipboard/empty")]
+    ClipboardEmpty,
+    #[serde(rename = "clipboard/invalid-format")]
+    ClipboardInvalidFormat,
+    #[serde(rename = "clipboard/entry-not-found")]
+    ClipboardEntryNotFound,
+    #[serde(rename = "clipboard/too-large")]
+    ClipboardTooLarge,
+    #[serde(rename = "clipboard/blocked")]
+    ClipboardBlocked,
+    #[serde(rename = "clipboard/permission-denied")]
+    ClipboardPermissionDenied,
+    #[serde(rename = "clipboard/invalid-config")]
+    ClipboardInvalidConfig,
+    #[serde(rename = "clipboard/authentication-failed")]
+    ClipboardAuthenticationFailed,
+    #[serde(rename = "clipboard/stale")]
+    ClipboardStale,
+    #[serde(rename = "clipboard/keyring-unavailable")]
+    ClipboardKeyringUnavailable,
+    #[serde(rename = "clipboard/failed")]
+    ClipboardFailed,
+
+    #[serde(rename = "transfer/not-initialized")]
+    TransferNotInitialized,
+    #[serde(rename = "transfer/file-not-found")]
+    TransferFileNotFound,
+    #[serde(rename = "transfer/file-too-large")]
+    TransferFileTooLarge,
+    #[serde(rename = "transfer/not-found")]
+    TransferNotFound,
+    #[serde(rename = "transfer/invalid-operation")]
+    TransferInvalidOperation,
+    /// Hash, size or decryption of the received data did not match
+    #[serde(rename = "transfer/integrity")]
+    TransferIntegrity,
+    #[serde(rename = "transfer/failed")]
+    TransferFailed,
+
+    #[serde(rename = "security/not-initialized")]
+    SecurityNotInitialized,
+    #[serde(rename = "security/authentication-failed")]
+    SecurityAuthenticationFailed,
+    /// Invalid, expired, revoked or replayed token
+    #[serde(rename = "security/token-invalid")]
+    SecurityTokenInvalid,
+    #[serde(rename = "security/permission-denied")]
+    SecurityPermissionDenied,
+    #[serde(rename = "security/too-many-attempts")]
+    SecurityTooManyAttempts,
+    /// Known device name presented a different identity key
+    #[serde(rename = "security/identity-mismatch")]
+    SecurityIdentityMismatch,
+    #[serde(rename = "security/invalid-config")]
+    SecurityInvalidConfig,
+    #[serde(rename = "security/failed")]
+    SecurityFailed,
+
+    #[serde(rename = "signaling/invalid-url")]
+    SignalingInvalidUrl,
+    #[serde(rename = "signaling/not-connected")]
+    SignalingNotConnected,
+    #[serde(rename = "signaling/not-in-room")]
+    SignalingNotInRoom,
+
+    #[serde(rename = "chat/empty")]
+    ChatEmpty,
+    /// The text exceeds 4 KB
+    #[serde(rename = "chat/too-large")]
+    ChatTooLarge,
+    /// No clipboard sync key has been negotiated with the peer yet
+    #[serde(rename = "chat/not-connected")]
+    ChatNotConnected,
+    #[serde(rename = "chat/invalid-message")]
+    ChatInvalidMessage,
+
+    #[serde(rename = "ice/invalid-url")]
+    IceInvalidUrl,
+    /// A TURN server without username or credential
+    #[serde(rename = "ice/missing-credential")]
+    IceMissingCredential,
+    /// Neither the Secret Service nor the kernel keyring can hold TURN credentials
+    #[serde(rename = "ice/keyring-unavailable")]
+    IceKeyringUnavailable,
+    /// The reachability test supports only UDP servers
+    #[serde(rename = "ice/unsupported")]
+    IceUnsupported,
+
+    /// Input, clipboard and file transfer are off until the app restarts without safe mode
+    #[serde(rename = "safe-mode/disabled")]
+    SafeModeDisabled,
+
+    /// The frontend or peer speaks an unsupported protocol version
+    #[serde(rename = "protocol/version-mismatch")]
+    ProtocolVersionMismatch,
+
+    #[serde(rename = "peer/rate-limited")]
+    PeerRateLimited,
+    #[serde(rename = "peer/suspended")]
+    PeerSuspended,
+
+    #[serde(rename = "settings/invalid")]
+    SettingsInvalid,
+    #[serde(rename = "settings/failed")]
+    SettingsFailed,
+    /// A configuration bundle's passphrase is missing, too short or wrong
+    #[serde(rename = "settings/passphrase")]
+    SettingsPassphrase,
+    /// A capture or transfer is running, so the configuration cannot be replaced
+    #[serde(rename = "settings/busy")]
+    SettingsBusy,
+
+    #[serde(rename = "logging/not-initialized")]
+    LoggingNotInitialized,
+    #[serde(rename = "logging/invalid-level")]
+    LoggingInvalidLevel,
+    #[serde(rename = "logging/failed")]
+    LoggingFailed,
+
+    #[serde(rename = "metrics/bind-failed")]
+    MetricsBindFailed,
+    /// A control socket request with a missing or wrong token
+    #[serde(rename = "control/unauthorized")]
+    ControlUnauthorized,
+    #[serde(rename = "control/invalid-request")]
+    ControlInvalidRequest,
+    /// Reported by the command line when no instance answers on the control socket
+    #[serde(rename = "control/not-running")]
+    ControlNotRunning,
+    #[serde(rename = "host/wake-failed")]
+    HostWakeFailed,
+    /// Neither wpctl nor pactl could change the volume
+    #[serde(rename = "host/volume-failed")]
+    HostVolumeFailed,
+    #[serde(rename = "notification/failed")]
+    NotificationFailed,
+
+    /// AT-SPI is not running, disabled, or the build lacks the accessibility feature
+    #[serde(rename = "accessibility/unavailable")]
+    AccessibilityUnavailable,
+    /// An application did not answer the accessibility query in time
+    #[serde(rename = "accessibility/timeout")]
+    AccessibilityTimeout,
+    #[serde(rename = "accessibility/failed")]
+    AccessibilityFailed,
+
+    /// Failures of Tauri itself, e.g. an event that could not be emitted
+    #[serde(rename = "internal")]
+    Internal,
+}
+
+/// Error returned by every Tauri command
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// What the user can do about it, e.g. a package to install
+    pub remediation: Option<String>,
+}
+
+impl CommandError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        CommandError { kind, message: message.into(), remediation: None }
+    }
+
+    /// A manager that is missing, diagnosed by the capability probe if possible
+    pub fn not_initialized(kind: ErrorKind, fallback: &str, status: Option<&FeatureStatus>) -> Self {
+        match status {
+            Some(status) => CommandError {
+                kind,
+                message: status.message.clone(),
+                remediation: status.remediation.clone(),
+            },
+            None => CommandError::new(kind, fallback),
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.remediation {
+            Some(remediation) => write!(f, "{} ({})", self.message, remediation),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<ScreenCaptureError> for CommandError {
+    fn from(error: ScreenCaptureError) -> Self {
+        let kind = match &error {
+            ScreenCaptureError::InvalidMonitor(_) => ErrorKind::CaptureInvalidMonitor,
+            ScreenCaptureError::InvalidRegion(_) => ErrorKind::CaptureInvalidRegion,
+            ScreenCaptureError::InvalidConfig(_) => ErrorKind::CaptureInvalidConfig,
+            ScreenCaptureError::TooManyCaptures(_) => ErrorKind::CaptureTooManyCaptures,
+            ScreenCaptureError::MonitorBusy(_) => ErrorKind::CaptureMonitorBusy,
+            ScreenCaptureError::ConsentDenied(_) => ErrorKind::CaptureConsentDenied,
+            ScreenCaptureError::EncoderDeviceNotFound(_)
+            | ScreenCaptureError::CudaUnavailable(_)
+            | ScreenCaptureError::UnsupportedOption(_)
+            | ScreenCaptureError::UnsupportedEncoder(_)
+            | ScreenCaptureError::HardwareAccelerationError(_) => ErrorKind::CaptureEncoderUnavailable,
+            ScreenCaptureError::DevicePermissionDenied(_) => ErrorKind::CapturePermissionDenied,
+            ScreenCaptureError::DisplayServerError(_)
+            | ScreenCaptureError::PipeWireError(_)
+            | ScreenCaptureError::PortalError(_) => ErrorKind::CaptureDisplayServer,
+            ScreenCaptureError::VirtualDisplayError(_) => ErrorKind::CaptureVirtualDisplay,
+            ScreenCaptureError::InitializationFailed(_)
+            | ScreenCaptureError::CaptureError(_)
+            | ScreenCaptureError::EncodingError(_)
+            | ScreenCaptureError::StreamBufferError(_)
+            | ScreenCaptureError::FFmpegError(_) => ErrorKind::CaptureFailed,
+        };
+        CommandError::new(kind, error.to_string())
+    }
+}
+
+impl From<InputForwardingError> for CommandError {
+    fn from(error: InputForwardingError) -> Self {
+        let kind = match &error {
+            InputForwardingError::PermissionDenied(_) => ErrorKind::InputPermissionDenied,
+            InputForwardingError::UnsupportedEvent(_) => ErrorKind::InputUnsupportedEvent,
+            InputForwardingError::MonitorConfigError(_) => ErrorKind::InputInvalidConfig,
+            InputForwardingError::MacroError(_) => ErrorKind::InputMacro,
+            InputForwardingError::HotkeyError(_) => ErrorKind::InputHotkey,
+            InputForwardingError::InitializationFailed(_)
+            | InputForwardingError::SendEventFailed(_) => ErrorKind::InputFailed,
+        };
+        CommandError::new(kind, error.to_string())
+    }
+}
+
+impl From<ClipboardError> for CommandError {
+    fn from(error: ClipboardError) -> Self {
+        let kind = match &error {
+            ClipboardError::UnsupportedPlatform(_)
+            | ClipboardError::ClipboardUnavailable(_)
+            | ClipboardError::Timeout(_) => ErrorKind::ClipboardUnavailable,
+            ClipboardError::EmptyClipboard => ErrorKind::ClipboardEmpty,
+            ClipboardError::InvalidFormat(_)
+            | ClipboardError::SerializationError(_)
+            | ClipboardError::DecodingError(_)
+            | ClipboardError::DeltaMismatch(_) => ErrorKind::ClipboardInvalidFormat,
+            ClipboardError::EntryNotFound(_) => ErrorKind::ClipboardEntryNotFound,
+            ClipboardError::ContentTooLarge(..) => ErrorKind::ClipboardTooLarge,
+            ClipboardError::ContentBlocked(_) => ErrorKind::ClipboardBlocked,
+            ClipboardError::PermissionDenied(_) => ErrorKind::ClipboardPermissionDenied,
+            ClipboardError::ConfigError(_) => ErrorKind::ClipboardInvalidConfig,
+            ClipboardError::VersionMismatch(_) => ErrorKind::ProtocolVersionMismatch,
+            ClipboardError::AuthenticationFailed(_) => ErrorKind::ClipboardAuthenticationFailed,
+            ClipboardError::StaleEntry { .. } => ErrorKind::ClipboardStale,
+            ClipboardError::KeyringUnavailable(_) => ErrorKind::ClipboardKeyringUnavailable,
+            ClipboardError::NetworkError(_)
+            | ClipboardError::UnsupportedOperation(_)
+            | ClipboardError::IoError(_) => ErrorKind::ClipboardFailed,
+        };
+        CommandError::new(kind, error.to_string())
+    }
+}
+
+impl From<FileTransferError> for CommandError {
+    fn from(error: FileTransferError) -> Self {
+        let kind = match &error {
+            FileTransferError::FileNotFound(_) => ErrorKind::TransferFileNotFound,
+            FileTransferError::FileTooLarge(..) => ErrorKind::TransferFileTooLarge,
+            FileTransferError::TransferNotFound(_) => ErrorKind::TransferNotFound,
+            FileTransferError::InvalidFileType(_)
+            | FileTransferError::InvalidOperation(_) => ErrorKind::TransferInvalidOperation,
+            FileTransferError::HashMismatch { .. }
+            | FileTransferError::SizeMismatch { .. }
+            | FileTransferError::DecryptionFailed { .. }
+            | FileTransferError::ReceiptInvalid(_) => ErrorKind::TransferIntegrity,
+            FileTransferError::IoError(_)
+            | FileTransferError::ChunkError(_)
+            | FileTransferError::KeyExchangeFailed(_)
+            | FileTransferError::NetworkError(_) => ErrorKind::TransferFailed,
+            FileTransferError::VersionMismatch(_) => ErrorKind::ProtocolVersionMismatch,
+        };
+        CommandError::new(kind, error.to_string())
+    }
+}
+
+impl From<SecurityError> for CommandError {
+    fn from(error: SecurityError) -> Self {
+        let kind = match &error {
+            SecurityError::AuthenticationFailed(_) => ErrorKind::SecurityAuthenticationFailed,
+            SecurityError::TokenInvalid(_)
+            | SecurityError::TokenExpired(_)
+            | SecurityError::TokenRevoked(_)
+            | SecurityError::TokenReplayed(_) => ErrorKind::SecurityTokenInvalid,
+            SecurityError::PermissionDenied(_) => ErrorKind::SecurityPermissionDenied,
+            SecurityError::TooManyAttempts(_) => ErrorKind::SecurityTooManyAttempts,
+            SecurityError::IdentityMismatch(_) => ErrorKind::SecurityIdentityMismatch,
+            SecurityError::ConfigurationError(_)
+            | SecurityError::ValidationError(_) => ErrorKind::SecurityInvalidConfig,
+            SecurityError::EncryptionError(_)
+            | SecurityError::DecryptionError(_)
+            | SecurityError::StorageError(_) => ErrorKind::SecurityFailed,
+        };
+        CommandError::new(kind, error.to_string())
+    }
+}
+
+impl From<SignalingError> for CommandError {
+    fn from(error: SignalingError) -> Self {
+        let kind = match &error {
+            SignalingError::InvalidUrl(_) => ErrorKind::SignalingInvalidUrl,
+            SignalingError::NotConnected | SignalingError::ChannelClosed => ErrorKind::SignalingNotConnected,
+            SignalingError::NotInRoom => ErrorKind::SignalingNotInRoom,
+        };
+        CommandError::new(kind, error.to_string())
+    }
+}
+
+impl From<ChatError> for CommandError {
+    fn from(error: ChatError) -> Self {
+        let kind = match &error {
+            ChatError::EmptyMessage => ErrorKind::ChatEmpty,
+            ChatError::MessageTooLarge(_) => ErrorKind::ChatTooLarge,
+            ChatError::NotConnected(_) => ErrorKind::ChatNotConnected,
+            ChatError::Protocol(ProtocolError::VersionMismatch(_)) => ErrorKind::ProtocolVersionMismatch,
+            ChatError::Protocol(ProtocolError::Malformed(_)) => ErrorKind::ChatInvalidMessage,
+        };
+        CommandError::new(kind, error.to_string())
+    }
+}
+
+impl From<SettingsError> for CommandError {
+    fn from(error: SettingsError) -> Self {
+        let kind = match &error {
+            SettingsError::Parse(_)
+            | SettingsError::InvalidUpdate(_)
+            | SettingsError::UnsupportedVersion(_) => ErrorKind::SettingsInvalid,
+            SettingsError::Io(_) => ErrorKind::SettingsFailed,
+        };
+        CommandError::new(kind, error.to_string())
+    }
+}
+
+impl From<BundleError> for CommandError {
+    fn from(error: BundleError) -> Self {
+        match error {
+            BundleError::Settings(error) => error.into(),
+            BundleError::Invalid(_) | BundleError::UnsupportedVersion(_) => {
+                CommandError::new(ErrorKind::SettingsInvalid, error.to_string())
+            },
+            BundleError::PassphraseRequired | BundleError::WeakPassphrase | BundleError::WrongPassphrase => {
+                CommandError::new(ErrorKind::SettingsPassphrase, error.to_string())
+            },
+            BundleError::Io(_) => CommandError::new(ErrorKind::SettingsFailed, error.to_string()),
+        }
+    }
+}
+
+impl From<LoggingError> for CommandError {
+    fn from(error: LoggingError) -> Self {
+        let kind = match &error {
+            LoggingError::NotInitialized => ErrorKind::LoggingNotInitialized,
+            LoggingError::InvalidLevel(_) | LoggingError::UnknownTarget(_) => ErrorKind::LoggingInvalidLevel,
+            LoggingError::AlreadyInitialized
+            | LoggingError::IoError(_)
+            | LoggingError::InitializationFailed(_) => ErrorKind::LoggingFailed,
+        };
+        CommandError::new(kind, error.to_string())
+    }
+}
+
+impl From<IceError> for CommandError {
+    fn from(error: IceError) -> Self {
+        let kind = match &error {
+            IceError::InvalidUrl { .. } => ErrorKind::IceInvalidUrl,
+            IceError::MissingCredential(_) => ErrorKind::IceMissingCredential,
+            IceError::KeyringUnavailable(_) => ErrorKind::IceKeyringUnavailable,
+            IceError::Unsupported(_) => ErrorKind::IceUnsupported,
+        };
+        CommandError::new(kind, error.to_string())
+    }
+}
+
+impl From<AccessibilityError> for CommandError {
+    fn from(error: AccessibilityError) -> Self {
+        let kind = match &error {
+            AccessibilityError::BusUnavailable(_) => ErrorKind::AccessibilityUnavailable,
+            AccessibilityError::Timeout(_) => ErrorKind::AccessibilityTimeout,
+            AccessibilityError::QueryFailed(_) => ErrorKind::AccessibilityFailed,
+        };
+        CommandError::new(kind, error.to_string())
+    }
+}
+
+impl From<SafeModeError> for CommandError {
+    fn from(error: SafeModeError) -> Self {
+        CommandError {
+            kind: ErrorKind::SafeModeDisabled,
+            message: error.to_string(),
+            remediation: Some("Restart SmolDesk without safe mode".to_string()),
+        }
+    }
+}
+
+impl From<VersionMismatch> for CommandError {
+    fn from(error: VersionMismatch) -> Self {
+        CommandError::new(ErrorKind::ProtocolVersionMismatch, error.to_string())
+    }
+}
+
+impl From<tauri::Error> for CommandError {
+    fn from(error: tauri::Error) -> Self {
+        CommandError::new(ErrorKind::Internal, error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capabilities::{CapabilityStatus, Feature};
+
+    #[test]
+    fn test_serialized_shape() {
+        let error = CommandError::new(ErrorKind::CaptureNotInitialized, "Screen capture manager not initialized");
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({
+                "kind": "capture/not-initialized",
+                "message": "Screen capture manager not initialized",
+                "remediation": null,
+            })
+        );
+
+        let error = CommandError {
+            remediation: Some("Add the user to the input group".to_string()),
+            ..InputForwardingError::PermissionDenied("/dev/uinput".to_string()).into()
+        };
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["kind"], "input/permission-denied");
+        assert_eq!(json["remediation"], "Add the user to the input group");
+        assert_eq!(json.as_object().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_module_errors_map_to_kinds() {
+        let kind = |error: CommandError| serde_json::to_value(error.kind).unwrap();
+
+        assert_eq!(kind(ScreenCaptureError::InvalidMonitor("3".to_string()).into()), "capture/invalid-monitor");
+        assert_eq!(kind(ScreenCaptureError::ConsentDenied("cancelled".to_string()).into()), "capture/consent-denied");
+        assert_eq!(kind(ClipboardError::EmptyClipboard.into()), "clipboard/empty");
+        assert_eq!(kind(ClipboardError::StaleEntry { age_secs: 60, max_secs: 30 }.into()), "clipboard/stale");
+        assert_eq!(kind(FileTransferError::FileTooLarge(2, 1).into()), "transfer/file-too-large");
+        assert_eq!(kind(SecurityError::TokenExpired("jwt".to_string()).into()), "security/token-invalid");
+        assert_eq!(kind(SignalingError::NotInRoom.into()), "signaling/not-in-room");
+        assert_eq!(kind(ChatError::MessageTooLarge(5000).into()), "chat/too-large");
+        assert_eq!(kind(IceError::MissingCredential("turn:turn.example.org".to_string()).into()), "ice/missing-credential");
+        let safe_mode = SafeModeError { control: crate::safe_mode::RemoteControl::Input, source: crate::safe_mode::SafeModeSource::Settings };
+        assert_eq!(kind(safe_mode.into()), "safe-mode/disabled");
+        assert_eq!(kind(LoggingError::InvalidLevel("loud".to_string()).into()), "logging/invalid-level");
+        assert_eq!(kind(BundleError::WrongPassphrase.into()), "settings/passphrase");
+        let mismatch = crate::protocol::negotiate(crate::protocol::WireSubsystem::ClipboardSync, 0).unwrap_err();
+        assert_eq!(kind(ClipboardError::VersionMismatch(mismatch.clone()).into()), "protocol/version-mismatch");
+        assert_eq!(kind(mismatch.into()), "protocol/version-mismatch");
+    }
+
+    #[test]
+    fn test_not_initialized_uses_capability_diagnosis() {
+        let status = FeatureStatus {
+            feature: Feature::Clipboard,
+            status: CapabilityStatus::Unavailable,
+            message: "wl-copy not found".to_string(),
+            remediation: Some("Install wl-clipboard".to_string()),
+        };
+
+        let error = CommandError::not_initialized(ErrorKind::ClipboardNotInitialized, "Clipboard manager not initialized", Some(&status));
+        assert_eq!(error.kind, ErrorKind::ClipboardNotInitialized);
+        assert_eq!(error.message, "wl-copy not found");
+        assert_eq!(error.to_string(), "wl-copy not found (Install wl-clipboard)");
+
+        let error = CommandError::not_initialized(ErrorKind::ClipboardNotInitialized, "Clipboard manager not initialized", None);
+        assert_eq!(error.remediation, None);
+    }
+}
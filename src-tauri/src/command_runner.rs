@@ -0,0 +1,27 @@
+// src-tauri/src/command_runner.rs - Construction of external tool invocations
+//
+// Input injection and capture shell out to xdotool, ydotool and ffmpeg. The
+// forwarders and capturers build those commands through a CommandRunner, so
+// tests can run them against the recording fakes in `mock-tools`.
+
+use std::process::Command;
+
+use crate::tool_locator;
+
+/// Creates the `Command` for an external tool
+pub trait CommandRunner: Send + Sync {
+    /// Command for `program`; arguments, pipes and spawning are left to the caller
+    fn command(&self, program: &str) -> Command;
+}
+
+/// Runs the tools configured in the `tools` settings or found in the user's
+/// PATH, with a restricted environment (see `tool_locator`)
+pub struct SystemCommandRunner;
+
+impl CommandRunner for SystemCommandRunner {
+    fn command(&self, program: &str) -> Command {
+        let mut cmd = Command::new(tool_locator::resolve(program));
+        tool_locator::restrict_env(&mut cmd);
+        cmd
+    }
+}
@@ -0,0 +1,517 @@
+// src-tauri/src/config_bundle.rs - Export and import of the whole configuration
+//
+// A bundle is a zip archive that sets up another installation the same way:
+//
+//   manifest.json  bundle format, settings version, whether secrets are included
+//   settings.toml  AppSettings with capture profiles, shortcut policy and trusted peers
+//   secrets.json   only on request: identity key, password hashes and TURN
+//                  credentials, sealed with ChaCha20-Poly1305 under a key
+//                  derived from a passphrase (Argon2id)
+//
+// Without secrets the password hashes are left out of settings.toml and the
+// importing installation keeps its own passwords and identity.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use argon2::Argon2;
+use base64::{Engine as _, engine::general_purpose};
+use chacha20poly1305::{aead::{Aead, KeyInit}, ChaCha20Poly1305, Key, Nonce};
+use chrono::{DateTime, Utc};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::connection_security::identity::DeviceIdentity;
+use crate::settings::{self, AppSettings, SettingsError, SETTINGS_VERSION};
+
+/// Current bundle layout; newer bundles are rejected
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+const MANIFEST_ENTRY: &str = "manifest.json";
+const SETTINGS_ENTRY: &str = "settings.toml";
+const SECRETS_ENTRY: &str = "secrets.json";
+
+/// Entries are small; anything larger is not a bundle written by SmolDesk
+const MAX_ENTRY_SIZE: u64 = 4 * 1024 * 1024;
+
+const KDF_NAME: &str = "argon2id";
+
+/// Settings that never appear in a diff in plain text
+const SECRET_PATHS: [&str; 2] = ["security.password_hash", "security.room_password_hash"];
+
+#[derive(Debug)]
+pub enum BundleError {
+    Io(io::Error),
+    /// Not a configuration bundle, or a damaged one
+    Invalid(String),
+    /// The bundle was written by a newer version of SmolDesk
+    UnsupportedVersion(u32),
+    Settings(SettingsError),
+    /// Secrets are to be written or read but no passphrase was given
+    PassphraseRequired,
+    WeakPassphrase,
+    /// The secrets cannot be decrypted with the given passphrase
+    WrongPassphrase,
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::Io(e) => write!(f, "Configuration bundle I/O error: {}", e),
+            BundleError::Invalid(msg) => write!(f, "Invalid configuration bundle: {}", msg),
+            BundleError::UnsupportedVersion(version) => write!(
+                f, "Configuration bundle format {} is newer than the supported format {}", version, BUNDLE_FORMAT_VERSION
+            ),
+            BundleError::Settings(e) => write!(f, "{}", e),
+            BundleError::PassphraseRequired => write!(f, "The bundle's secrets need a passphrase"),
+            BundleError::WeakPassphrase => write!(f, "The passphrase must have at least {} characters", MIN_PASSPHRASE_LEN),
+            BundleError::WrongPassphrase => write!(f, "Wrong passphrase for the bundle's secrets"),
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+impl From<io::Error> for BundleError {
+    fn from(e: io::Error) -> Self {
+        BundleError::Io(e)
+    }
+}
+
+impl From<SettingsError> for BundleError {
+    fn from(e: SettingsError) -> Self {
+        BundleError::Settings(e)
+    }
+}
+
+impl From<zip::result::ZipError> for BundleError {
+    fn from(e: zip::result::ZipError) -> Self {
+        match e {
+            zip::result::ZipError::Io(e) => BundleError::Io(e),
+            e => BundleError::Invalid(e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    /// Settings schema version of settings.toml
+    pub settings_version: u32,
+    pub app_version: String,
+    pub created_at: DateTime<Utc>,
+    pub includes_secrets: bool,
+}
+
+/// What goes into secrets.json, and the passphrase that seals it
+pub struct SecretsExport<'a> {
+    pub passphrase: &'a str,
+    pub identity: Option<&'a DeviceIdentity>,
+    /// TURN credentials from the keyring, by ICE server ID
+    pub ice_credentials: BTreeMap<String, String>,
+}
+
+/// Plain content of secrets.json
+#[derive(Serialize, Deserialize)]
+struct BundleSecrets {
+    /// Ed25519 private key, Base64
+    identity_key: Option<String>,
+    password_hash: Option<String>,
+    room_password_hash: Option<String>,
+    #[serde(default)]
+    ice_credentials: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SealedSecrets {
+    kdf: String,
+    salt: String,       // Base64, 16 bytes
+    nonce: String,      // Base64, 12 bytes
+    ciphertext: String, // Base64
+}
+
+/// A bundle read from disk
+pub struct ImportedBundle {
+    pub manifest: BundleManifest,
+    /// Migrated to the current settings version; holds the password hashes
+    /// only if the bundle includes secrets
+    pub settings: AppSettings,
+    pub identity: Option<DeviceIdentity>,
+    /// TURN credentials by ICE server ID, for the keyring
+    pub ice_credentials: BTreeMap<String, String>,
+}
+
+impl ImportedBundle {
+    /// The settings to apply on top of `current`. Without secrets in the
+    /// bundle the local passwords stay; tool paths always stay, they belong
+    /// to the machine the bundle was exported on.
+    pub fn settings_for(&self, current: &AppSettings) -> AppSettings {
+        let mut settings = self.settings.clone();
+        settings.tools = current.tools.clone();
+        if !self.manifest.includes_secrets {
+            settings.security.password_hash = current.security.password_hash.clone();
+            settings.security.room_password_hash = current.security.room_password_hash.clone();
+        }
+        settings
+    }
+}
+
+/// One setting that differs, as a dotted path into the settings
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SettingChange {
+    pub path: String,
+    /// Null if the setting did not exist before
+    pub old: Value,
+    pub new: Value,
+}
+
+/// Sent to the frontend after an import
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigImportReport {
+    pub manifest: BundleManifest,
+    pub changes: Vec<SettingChange>,
+    /// Whether the bundle replaced this installation's identity key
+    pub identity_replaced: bool,
+}
+
+/// Writes `settings` as a bundle. Secrets are only written together with a
+/// passphrase; otherwise the password hashes are left out.
+pub fn write_bundle(path: &Path, settings: &AppSettings, secrets: Option<SecretsExport<'_>>) -> Result<BundleManifest, BundleError> {
+    let mut settings = settings.clone();
+    settings.version = SETTINGS_VERSION;
+
+    let sealed = match &secrets {
+        Some(export) => {
+            if export.passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+                return Err(BundleError::WeakPassphrase);
+            }
+            let secrets = BundleSecrets {
+                identity_key: export.identity.map(|identity| general_purpose::STANDARD.encode(identity.secret_key())),
+                password_hash: settings.security.password_hash.clone(),
+                room_password_hash: settings.security.room_password_hash.clone(),
+                ice_credentials: export.ice_credentials.clone(),
+            };
+            Some(seal(&secrets, export.passphrase)?)
+        },
+        None => None,
+    };
+    // The hashes travel in secrets.json or not at all
+    settings.security.password_hash = None;
+    settings.security.room_password_hash = None;
+
+    let manifest = BundleManifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        settings_version: SETTINGS_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: Utc::now(),
+        includes_secrets: sealed.is_some(),
+    };
+
+    let manifest_json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| BundleError::Invalid(e.to_string()))?;
+    let settings_toml = toml::to_string_pretty(&settings)
+        .map_err(|e| BundleError::Settings(SettingsError::Parse(e.to_string())))?;
+
+    // Written next to the target first, so a failed export leaves no half bundle
+    let temp_path = path.with_extension("tmp");
+    {
+        let mut zip = zip::ZipWriter::new(settings::create_private_file(&temp_path)?);
+        let options = zip::write::FileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+
+        zip.start_file(MANIFEST_ENTRY, options)?;
+        zip.write_all(&manifest_json)?;
+        zip.start_file(SETTINGS_ENTRY, options)?;
+        zip.write_all(settings_toml.as_bytes())?;
+        if let Some(sealed) = &sealed {
+            zip.start_file(SECRETS_ENTRY, options)?;
+            let sealed_json = serde_json::to_vec_pretty(sealed)
+                .map_err(|e| BundleError::Invalid(e.to_string()))?;
+            zip.write_all(&sealed_json)?;
+        }
+        zip.finish()?.sync_all()?;
+    }
+    fs::rename(&temp_path, path)?;
+
+    Ok(manifest)
+}
+
+/// Reads and validates a bundle. A passphrase is only needed if it includes secrets.
+pub fn read_bundle(path: &Path, passphrase: Option<&str>) -> Result<ImportedBundle, BundleError> {
+    let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+
+    let manifest: BundleManifest = serde_json::from_slice(&read_entry(&mut archive, MANIFEST_ENTRY)?)
+        .map_err(|e| BundleError::Invalid(format!("{}: {}", MANIFEST_ENTRY, e)))?;
+    if manifest.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(BundleError::UnsupportedVersion(manifest.format_version));
+    }
+
+    // Older settings are migrated like a settings.toml from disk
+    let content = String::from_utf8(read_entry(&mut archive, SETTINGS_ENTRY)?)
+        .map_err(|e| BundleError::Invalid(format!("{}: {}", SETTINGS_ENTRY, e)))?;
+    let mut settings = settings::parse(&content)?;
+    settings.security.password_hash = None;
+    settings.security.room_password_hash = None;
+
+    let mut identity = None;
+    let mut ice_credentials = BTreeMap::new();
+    if manifest.includes_secrets {
+        let passphrase = passphrase.ok_or(BundleError::PassphraseRequired)?;
+        let sealed: SealedSecrets = serde_json::from_slice(&read_entry(&mut archive, SECRETS_ENTRY)?)
+            .map_err(|e| BundleError::Invalid(format!("{}: {}", SECRETS_ENTRY, e)))?;
+        let secrets = open(&sealed, passphrase)?;
+
+        if let Some(key) = &secrets.identity_key {
+            let key_bytes: [u8; 32] = general_purpose::STANDARD.decode(key).ok()
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| BundleError::Invalid("identity key has the wrong format".to_string()))?;
+            identity = Some(DeviceIdentity::from_secret_key(&key_bytes));
+        }
+        settings.security.password_hash = secrets.password_hash;
+        settings.security.room_password_hash = secrets.room_password_hash;
+        ice_credentials = secrets.ice_credentials;
+    }
+
+    Ok(ImportedBundle { manifest, settings, identity, ice_credentials })
+}
+
+fn read_entry(archive: &mut zip::ZipArchive<File>, name: &str) -> Result<Vec<u8>, BundleError> {
+    let entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(zip::result::ZipError::FileNotFound) => return Err(BundleError::Invalid(format!("{} is missing", name))),
+        Err(e) => return Err(e.into()),
+    };
+    if entry.size() > MAX_ENTRY_SIZE {
+        return Err(BundleError::Invalid(format!("{} is too large", name)));
+    }
+
+    let mut data = Vec::new();
+    entry.take(MAX_ENTRY_SIZE).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn seal(secrets: &BundleSecrets, passphrase: &str) -> Result<SealedSecrets, BundleError> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+
+    let plaintext = serde_json::to_vec(secrets)
+        .map_err(|e| BundleError::Invalid(e.to_string()))?;
+    let ciphertext = passphrase_cipher(passphrase, &salt)?
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|e| BundleError::Invalid(format!("Cannot encrypt secrets: {}", e)))?;
+
+    Ok(SealedSecrets {
+        kdf: KDF_NAME.to_string(),
+        salt: general_purpose::STANDARD.encode(salt),
+        nonce: general_purpose::STANDARD.encode(nonce),
+        ciphertext: general_purpose::STANDARD.encode(ciphertext),
+    })
+}
+
+fn open(sealed: &SealedSecrets, passphrase: &str) -> Result<BundleSecrets, BundleError> {
+    if sealed.kdf != KDF_NAME {
+        return Err(BundleError::Invalid(format!("unknown key derivation {}", sealed.kdf)));
+    }
+    let decode = |value: &str| general_purpose::STANDARD.decode(value)
+        .map_err(|e| BundleError::Invalid(format!("{}: {}", SECRETS_ENTRY, e)));
+    let nonce = decode(&sealed.nonce)?;
+    if nonce.len() != 12 {
+        return Err(BundleError::Invalid(format!("{}: nonce has the wrong length", SECRETS_ENTRY)));
+    }
+
+    let plaintext = passphrase_cipher(passphrase, &decode(&sealed.salt)?)?
+        .decrypt(Nonce::from_slice(&nonce), decode(&sealed.ciphertext)?.as_slice())
+        .map_err(|_| BundleError::WrongPassphrase)?;
+    serde_json::from_slice(&plaintext)
+        .map_err(|e| BundleError::Invalid(format!("{}: {}", SECRETS_ENTRY, e)))
+}
+
+fn passphrase_cipher(passphrase: &str, salt: &[u8]) -> Result<ChaCha20Poly1305, BundleError> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| BundleError::Invalid(format!("Cannot derive key: {}", e)))?;
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+/// Settings that differ between `old` and `new`. Objects are compared field
+/// by field, lists as a whole; password hashes are masked.
+pub fn diff_settings(old: &AppSettings, new: &AppSettings) -> Vec<SettingChange> {
+    let mut old_values = BTreeMap::new();
+    let mut new_values = BTreeMap::new();
+    flatten("", serde_json::to_value(old).unwrap_or(Value::Null), &mut old_values);
+    flatten("", serde_json::to_value(new).unwrap_or(Value::Null), &mut new_values);
+
+    let mut paths: Vec<&String> = old_values.keys().chain(new_values.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    paths.into_iter()
+        .filter(|path| old_values.get(*path) != new_values.get(*path))
+        .map(|path| {
+            let value = |values: &BTreeMap<String, Value>| match values.get(path) {
+                Some(Value::Null) | None => Value::Null,
+                Some(_) if SECRET_PATHS.contains(&path.as_str()) => Value::String("<hidden>".to_string()),
+                Some(value) => value.clone(),
+            };
+            SettingChange { path: path.clone(), old: value(&old_values), new: value(&new_values) }
+        })
+        .collect()
+}
+
+fn flatten(prefix: &str, value: Value, out: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(fields) if !fields.is_empty() => {
+            for (key, value) in fields {
+                let path = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+                flatten(&path, value, out);
+            }
+        },
+        value => {
+            out.insert(prefix.to_string(), value);
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection_security::identity::TrustedPeer;
+    use crate::input_forwarding::shortcuts::{ShortcutAction, ShortcutRule};
+
+    fn temp_path() -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("smoldesk-bundle-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("smoldesk-config.zip")
+    }
+
+    fn configured_settings() -> AppSettings {
+        let mut settings = AppSettings::default();
+        settings.capture.fps = 60;
+        settings.capture_profiles.insert("support".to_string(), settings.capture.clone());
+        settings.input.shortcut_policy.rules.push(ShortcutRule {
+            combo: "super+l".to_string(),
+            action: ShortcutAction::Ignore,
+        });
+        settings.security.trusted_peers.push(TrustedPeer {
+            fingerprint: "SHA256:abc".to_string(),
+            device_name: "helpdesk-1".to_string(),
+            nickname: Some("Helpdesk".to_string()),
+            access_rights: Vec::new(),
+            trusted_at: 1_700_000_000,
+        });
+        settings.security.password_hash = Some("salt:hash".to_string());
+        settings.security.room_password_hash = Some("$argon2id$v=19$m=19456,t=2,p=1$c2FsdA$aGFzaA".to_string());
+        settings.tools.ffmpeg_path = Some("/opt/ffmpeg/bin/ffmpeg".into());
+        settings
+    }
+
+    #[test]
+    fn test_round_trip_without_secrets() {
+        let path = temp_path();
+        let settings = configured_settings();
+
+        let manifest = write_bundle(&path, &settings, None).unwrap();
+        assert!(!manifest.includes_secrets);
+
+        let bundle = read_bundle(&path, None).unwrap();
+        assert_eq!(bundle.manifest, manifest);
+        assert!(bundle.identity.is_none());
+        assert_eq!(bundle.settings.security.password_hash, None);
+        assert_eq!(bundle.settings.security.room_password_hash, None);
+
+        // The target keeps its own passwords and tool paths, everything else comes from the bundle
+        let mut local = AppSettings::default();
+        local.security.password_hash = Some("local:hash".to_string());
+        let imported = bundle.settings_for(&local);
+        assert_eq!(imported.security.password_hash.as_deref(), Some("local:hash"));
+        assert_eq!(imported.tools, local.tools);
+        assert_eq!(imported.capture_profiles, settings.capture_profiles);
+        assert_eq!(imported.input.shortcut_policy, settings.input.shortcut_policy);
+        assert_eq!(imported.security.trusted_peers, settings.security.trusted_peers);
+
+        // No hash may leak into the archive
+        let content = fs::read(&path).unwrap();
+        assert!(!content.windows(9).any(|window| window == b"salt:hash"));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_round_trip_with_secrets() {
+        let path = temp_path();
+        let settings = configured_settings();
+        let identity = DeviceIdentity::generate();
+
+        let ice_credentials = BTreeMap::from([("0123456789abcdef".to_string(), "s3cret".to_string())]);
+        let export = SecretsExport { passphrase: "correct horse", identity: Some(&identity), ice_credentials: ice_credentials.clone() };
+        assert!(write_bundle(&path, &settings, Some(export)).unwrap().includes_secrets);
+
+        assert!(matches!(read_bundle(&path, None), Err(BundleError::PassphraseRequired)));
+        assert!(matches!(read_bundle(&path, Some("wrong horse")), Err(BundleError::WrongPassphrase)));
+
+        let bundle = read_bundle(&path, Some("correct horse")).unwrap();
+        assert_eq!(bundle.identity.unwrap().fingerprint(), identity.fingerprint());
+        assert_eq!(bundle.ice_credentials, ice_credentials);
+        let imported = bundle.settings_for(&AppSettings::default());
+        assert_eq!(imported.security.password_hash, settings.security.password_hash);
+        assert_eq!(imported.security.room_password_hash, settings.security.room_password_hash);
+
+        let short = SecretsExport { passphrase: "short", identity: None, ice_credentials: BTreeMap::new() };
+        assert!(matches!(write_bundle(&path, &settings, Some(short)), Err(BundleError::WeakPassphrase)));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_newer_bundles_are_rejected() {
+        let path = temp_path();
+        {
+            let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+            let manifest = BundleManifest {
+                format_version: BUNDLE_FORMAT_VERSION + 1,
+                settings_version: SETTINGS_VERSION,
+                app_version: "99.0.0".to_string(),
+                created_at: Utc::now(),
+                includes_secrets: false,
+            };
+            zip.start_file(MANIFEST_ENTRY, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(&serde_json::to_vec(&manifest).unwrap()).unwrap();
+            zip.finish().unwrap();
+        }
+
+        assert!(matches!(read_bundle(&path, None), Err(BundleError::UnsupportedVersion(2))));
+
+        // Neither is a file that is no bundle at all
+        fs::write(&path, b"not a zip").unwrap();
+        assert!(matches!(read_bundle(&path, None), Err(BundleError::Invalid(_))));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_diff_lists_changed_settings_and_masks_secrets() {
+        let old = AppSettings::default();
+        let new = configured_settings();
+
+        let changes = diff_settings(&old, &new);
+        let fps = changes.iter().find(|change| change.path == "capture.fps").unwrap();
+        assert_eq!(fps.new, serde_json::json!(60));
+        assert!(changes.iter().any(|change| change.path == "capture_profiles.support.fps"));
+        assert!(changes.iter().any(|change| change.path == "security.trusted_peers"));
+
+        let password = changes.iter().find(|change| change.path == "security.password_hash").unwrap();
+        assert_eq!(password.old, Value::Null);
+        assert_eq!(password.new, serde_json::json!("<hidden>"));
+
+        assert!(diff_settings(&new, &new).is_empty());
+    }
+}
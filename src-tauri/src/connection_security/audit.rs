@@ -0,0 +1,556 @@
+// src-tauri/src/connection_security/audit.rs - Manipulationssicheres Protokoll sicherheitsrelevanter Aktionen
+
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::connection_security::SecurityError;
+use crate::constant_time::constant_time_eq;
+use crate::logging::TARGET_SECURITY;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const LOG_FILE: &str = "audit_log.jsonl";
+const HEAD_FILE: &str = "audit_log.head";
+const KEY_FILE: &str = "audit.key";
+
+// Vorgänger-Hash des ersten Eintrags
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+// Protokollierte Ereignisse
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum AuditEventKind {
+    PeerConnected,
+    PeerDisconnected,
+    PermissionGranted,
+    InputEnabled,
+    InputDisabled,
+    InputPreview,
+    FileSent,
+    FileReceived,
+    ClipboardSent,
+    ClipboardReceived,
+    RecordingStarted,
+    PeerSuspended,
+    PeerVerified,
+    PeerIdentityMismatch,
+    PeerTrusted,
+    PeerUntrusted,
+    AccessibilityTextRead,
+}
+
+// Ein Ereignis, wie es andere Module melden
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub kind: AuditEventKind,
+    pub peer_id: Option<String>,
+    pub detail: Option<String>,       // z.B. Dateiname oder gewährtes Recht
+    pub size_bytes: Option<u64>,
+    pub content: Option<String>,      // Zwischenablage-Inhalt, nur bei ausdrücklicher Zustimmung gespeichert
+}
+
+impl AuditEvent {
+    pub fn new(kind: AuditEventKind, peer_id: Option<&str>) -> Self {
+        AuditEvent {
+            kind,
+            peer_id: peer_id.map(str::to_string),
+            detail: None,
+            size_bytes: None,
+            content: None,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn with_size(mut self, size_bytes: u64) -> Self {
+        self.size_bytes = Some(size_bytes);
+        self
+    }
+
+    pub fn with_content(mut self, content: impl Into<String>) -> Self {
+        self.content = Some(content.into());
+        self
+    }
+}
+
+// Schnittstelle, über die Module Ereignisse melden, ohne das Protokoll zu kennen
+pub trait AuditSink: Send + Sync {
+    fn record(&self, event: AuditEvent);
+}
+
+// Einstellungen des Protokolls
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditConfig {
+    pub log_clipboard_content: bool, // Standard: nur Ereignistyp und Größe
+}
+
+// Ein verketteter Protokolleintrag
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub sequence: u64,
+    pub timestamp: DateTime<Utc>,
+    pub event: AuditEvent,
+    pub prev_hash: String,
+    pub hash: String, // HMAC-SHA256 über Vorgänger-Hash und Eintrag (hex)
+}
+
+// Signierter Inhalt eines Eintrags (alles außer dem Hash selbst)
+#[derive(Serialize)]
+struct EntryBody<'a> {
+    sequence: u64,
+    timestamp: &'a DateTime<Utc>,
+    event: &'a AuditEvent,
+    prev_hash: &'a str,
+}
+
+// Zuletzt geschriebener Eintrag, getrennt gespeichert, damit ein abgeschnittenes Ende auffällt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AuditHead {
+    entries: u64,
+    hash: String,
+    mac: String,
+}
+
+// Exportformate
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum AuditExportFormat {
+    Json,
+    Csv,
+}
+
+// Ergebnis der Prüfung
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditVerification {
+    pub valid: bool,
+    pub entries: usize,
+    pub first_invalid_sequence: Option<u64>, // Erster Eintrag, an dem die Kette bricht
+    pub reason: Option<String>,
+}
+
+struct ChainState {
+    next_sequence: u64,
+    last_hash: String,
+    memory: Vec<AuditEntry>, // Nur ohne Datenverzeichnis
+}
+
+// Protokoll mit HMAC-Verkettung; ohne Datenverzeichnis nur im Speicher
+pub struct AuditLog {
+    key: [u8; 32],
+    dir: Option<PathBuf>,
+    config: Mutex<AuditConfig>,
+    state: Mutex<ChainState>,
+}
+
+impl AuditLog {
+    // Protokoll nur im Speicher (z.B. wenn kein App-Datenverzeichnis existiert)
+    pub fn in_memory(config: AuditConfig) -> Self {
+        let mut key = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut key);
+
+        AuditLog {
+            key,
+            dir: None,
+            config: Mutex::new(config),
+            state: Mutex::new(ChainState {
+                next_sequence: 0,
+                last_hash: GENESIS_HASH.to_string(),
+                memory: Vec::new(),
+            }),
+        }
+    }
+
+    // Öffnet das Protokoll im Datenverzeichnis und setzt die Kette fort
+    pub fn open(data_dir: &Path, config: AuditConfig) -> Result<Self, SecurityError> {
+        fs::create_dir_all(data_dir)?;
+        let key = load_or_create_key(&data_dir.join(KEY_FILE))?;
+
+        let entries = read_entries(&data_dir.join(LOG_FILE))?;
+        let (next_sequence, last_hash) = match entries.last() {
+            Some(last) => (last.sequence + 1, last.hash.clone()),
+            None => (0, GENESIS_HASH.to_string()),
+        };
+
+        Ok(AuditLog {
+            key,
+            dir: Some(data_dir.to_path_buf()),
+            config: Mutex::new(config),
+            state: Mutex::new(ChainState {
+                next_sequence,
+                last_hash,
+                memory: Vec::new(),
+            }),
+        })
+    }
+
+    pub fn config(&self) -> AuditConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    pub fn set_config(&self, config: AuditConfig) {
+        *self.config.lock().unwrap() = config;
+    }
+
+    // Hängt ein Ereignis an die Kette an
+    pub fn append(&self, mut event: AuditEvent) -> Result<AuditEntry, SecurityError> {
+        if !self.config.lock().unwrap().log_clipboard_content {
+            event.content = None;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let timestamp = Utc::now();
+        let hash = self.entry_hash(state.next_sequence, &timestamp, &event, &state.last_hash)?;
+        let entry = AuditEntry {
+            sequence: state.next_sequence,
+            timestamp,
+            event,
+            prev_hash: state.last_hash.clone(),
+            hash,
+        };
+
+        match &self.dir {
+            Some(dir) => {
+                let mut line = serde_json::to_vec(&entry)
+                    .map_err(|e| SecurityError::StorageError(e.to_string()))?;
+                line.push(b'\n');
+
+                let mut file = fs::OpenOptions::new().create(true).append(true).open(dir.join(LOG_FILE))?;
+                file.write_all(&line)?;
+                file.sync_data()?;
+
+                self.write_head(dir, entry.sequence + 1, &entry.hash)?;
+            },
+            None => state.memory.push(entry.clone()),
+        }
+
+        state.next_sequence += 1;
+        state.last_hash = entry.hash.clone();
+        Ok(entry)
+    }
+
+    // Alle Einträge in Reihenfolge
+    pub fn entries(&self) -> Result<Vec<AuditEntry>, SecurityError> {
+        match &self.dir {
+            Some(dir) => read_entries(&dir.join(LOG_FILE)),
+            None => Ok(self.state.lock().unwrap().memory.clone()),
+        }
+    }
+
+    // Prüft die Kette: Reihenfolge, Vorgänger-Hashes, HMACs und das Ende
+    pub fn verify(&self) -> Result<AuditVerification, SecurityError> {
+        // Keine Einträge während der Prüfung anhängen
+        let state = self.state.lock().unwrap();
+        let entries = match &self.dir {
+            Some(dir) => read_entries(&dir.join(LOG_FILE))?,
+            None => state.memory.clone(),
+        };
+
+        let invalid = |sequence: u64, reason: String| AuditVerification {
+            valid: false,
+            entries: entries.len(),
+            first_invalid_sequence: Some(sequence),
+            reason: Some(reason),
+        };
+
+        let mut prev_hash = GENESIS_HASH.to_string();
+        for (index, entry) in entries.iter().enumerate() {
+            if entry.sequence != index as u64 {
+                return Ok(invalid(index as u64, format!("Eintrag {} fehlt oder ist verschoben", index)));
+            }
+            if entry.prev_hash != prev_hash {
+                return Ok(invalid(entry.sequence, "Vorgänger-Hash passt nicht".to_string()));
+            }
+            let expected = self.entry_hash(entry.sequence, &entry.timestamp, &entry.event, &entry.prev_hash)?;
+            if !constant_time_eq(expected.as_bytes(), entry.hash.as_bytes()) {
+                return Ok(invalid(entry.sequence, "HMAC ungültig, Eintrag wurde verändert".to_string()));
+            }
+            prev_hash = entry.hash.clone();
+        }
+
+        // Abgeschnittenes Ende erkennen, auch wenn Datei und Kopf gemeinsam gelöscht wurden
+        if (entries.len() as u64) < state.next_sequence {
+            return Ok(invalid(
+                entries.len() as u64,
+                format!("{} Einträge erwartet, {} vorhanden", state.next_sequence, entries.len()),
+            ));
+        }
+        if let Some(dir) = &self.dir {
+            let head = self.read_head(dir)?;
+            let expected_entries = head.as_ref().map_or(0, |head| head.entries);
+            if expected_entries != entries.len() as u64 || head.map_or(false, |head| head.hash != prev_hash) {
+                return Ok(invalid(
+                    entries.len() as u64,
+                    format!("{} Einträge erwartet, {} vorhanden", expected_entries, entries.len()),
+                ));
+            }
+        }
+
+        Ok(AuditVerification {
+            valid: true,
+            entries: entries.len(),
+            first_invalid_sequence: None,
+            reason: None,
+        })
+    }
+
+    // Exportiert alle Einträge als JSON-Array oder CSV
+    pub fn export(&self, format: AuditExportFormat) -> Result<String, SecurityError> {
+        let entries = self.entries()?;
+
+        match format {
+            AuditExportFormat::Json => serde_json::to_string_pretty(&entries)
+                .map_err(|e| SecurityError::StorageError(e.to_string())),
+            AuditExportFormat::Csv => {
+                let mut csv = String::from("sequence,timestamp,event,peer_id,detail,size_bytes,content,prev_hash,hash\n");
+                for entry in &entries {
+                    let fields = [
+                        entry.sequence.to_string(),
+                        entry.timestamp.to_rfc3339(),
+                        format!("{:?}", entry.event.kind),
+                        entry.event.peer_id.clone().unwrap_or_default(),
+                        entry.event.detail.clone().unwrap_or_default(),
+                        entry.event.size_bytes.map(|size| size.to_string()).unwrap_or_default(),
+                        entry.event.content.clone().unwrap_or_default(),
+                        entry.prev_hash.clone(),
+                        entry.hash.clone(),
+                    ];
+                    let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+                    csv.push_str(&row.join(","));
+                    csv.push('\n');
+                }
+                Ok(csv)
+            },
+        }
+    }
+
+    fn entry_hash(
+        &self,
+        sequence: u64,
+        timestamp: &DateTime<Utc>,
+        event: &AuditEvent,
+        prev_hash: &str,
+    ) -> Result<String, SecurityError> {
+        let body = serde_json::to_vec(&EntryBody { sequence, timestamp, event, prev_hash })
+            .map_err(|e| SecurityError::StorageError(e.to_string()))?;
+
+        let mut mac = self.mac()?;
+        mac.update(prev_hash.as_bytes());
+        mac.update(&body);
+        Ok(hex(&mac.finalize().into_bytes()))
+    }
+
+    fn head_mac(&self, entries: u64, hash: &str) -> Result<String, SecurityError> {
+        let mut mac = self.mac()?;
+        mac.update(format!("head:{}:{}", entries, hash).as_bytes());
+        Ok(hex(&mac.finalize().into_bytes()))
+    }
+
+    fn mac(&self) -> Result<HmacSha256, SecurityError> {
+        HmacSha256::new_from_slice(&self.key)
+            .map_err(|e| SecurityError::EncryptionError(format!("HMAC-Initialisierungsfehler: {}", e)))
+    }
+
+    // Schreibt den Kopf atomar (temporäre Datei + rename)
+    fn write_head(&self, dir: &Path, entries: u64, hash: &str) -> Result<(), SecurityError> {
+        let head = AuditHead {
+            entries,
+            hash: hash.to_string(),
+            mac: self.head_mac(entries, hash)?,
+        };
+        let data = serde_json::to_vec(&head)
+            .map_err(|e| SecurityError::StorageError(e.to_string()))?;
+
+        let temp_path = dir.join(HEAD_FILE).with_extension("tmp");
+        fs::write(&temp_path, data)?;
+        fs::rename(&temp_path, dir.join(HEAD_FILE))?;
+        Ok(())
+    }
+
+    // Liest den Kopf; ein gefälschter Kopf zählt als fehlend
+    fn read_head(&self, dir: &Path) -> Result<Option<AuditHead>, SecurityError> {
+        let data = match fs::read(dir.join(HEAD_FILE)) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let head: AuditHead = match serde_json::from_slice(&data) {
+            Ok(head) => head,
+            Err(_) => return Ok(None),
+        };
+        let expected = self.head_mac(head.entries, &head.hash)?;
+        Ok(if constant_time_eq(expected.as_bytes(), head.mac.as_bytes()) { Some(head) } else { None })
+    }
+}
+
+impl AuditSink for AuditLog {
+    fn record(&self, event: AuditEvent) {
+        let kind = event.kind;
+        if let Err(e) = self.append(event) {
+            log::error!(target: TARGET_SECURITY, "Failed to write audit entry {:?}: {}", kind, e);
+        }
+    }
+}
+
+// Liest das Protokoll; eine unlesbare Zeile bricht die Kette und wird bei der Prüfung sichtbar
+fn read_entries(path: &Path) -> Result<Vec<AuditEntry>, SecurityError> {
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => log::warn!(target: TARGET_SECURITY, "Unreadable audit log line: {}", e),
+        }
+    }
+    Ok(entries)
+}
+
+// Lädt den HMAC-Schlüssel oder erzeugt einen neuen (nur für den Benutzer lesbar)
+fn load_or_create_key(path: &Path) -> Result<[u8; 32], SecurityError> {
+    if let Ok(existing) = fs::read(path) {
+        if existing.len() == 32 {
+            let mut key = [0u8; 32];
+            key.copy_from_slice(&existing);
+            return Ok(key);
+        }
+    }
+
+    let mut key = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut key);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    file.write_all(&key)?;
+    file.sync_all()?;
+
+    Ok(key)
+}
+
+// CSV-Feld nach RFC 4180 quotieren
+fn csv_field(value: &str) -> String {
+    if value.contains(|c| matches!(c, ',' | '"' | '\n' | '\r')) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("smoldesk-audit-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn write_events(log: &AuditLog) {
+        log.record(AuditEvent::new(AuditEventKind::PeerConnected, Some("peer-1")));
+        log.record(AuditEvent::new(AuditEventKind::FileReceived, Some("peer-1")).with_detail("report, final.pdf").with_size(2048));
+        log.record(AuditEvent::new(AuditEventKind::PeerDisconnected, Some("peer-1")));
+    }
+
+    #[test]
+    fn test_chain_survives_reopen() {
+        let dir = temp_dir("reopen");
+        write_events(&AuditLog::open(&dir, AuditConfig::default()).unwrap());
+
+        let log = AuditLog::open(&dir, AuditConfig::default()).unwrap();
+        log.record(AuditEvent::new(AuditEventKind::InputEnabled, Some("peer-2")));
+
+        let verification = log.verify().unwrap();
+        assert!(verification.valid, "{:?}", verification.reason);
+        assert_eq!(verification.entries, 4);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_detects_modification_and_truncation() {
+        let dir = temp_dir("tamper");
+        let log = AuditLog::open(&dir, AuditConfig::default()).unwrap();
+        write_events(&log);
+        let path = dir.join(LOG_FILE);
+        let original = fs::read_to_string(&path).unwrap();
+
+        // Geänderte Dateigröße im mittleren Eintrag
+        fs::write(&path, original.replace("\"size_bytes\":2048", "\"size_bytes\":4096")).unwrap();
+        let verification = log.verify().unwrap();
+        assert!(!verification.valid);
+        assert_eq!(verification.first_invalid_sequence, Some(1));
+
+        // Letzten Eintrag abschneiden
+        let truncated: String = original.lines().take(2).map(|line| format!("{}\n", line)).collect();
+        fs::write(&path, truncated).unwrap();
+        let verification = log.verify().unwrap();
+        assert!(!verification.valid);
+        assert_eq!(verification.first_invalid_sequence, Some(2));
+
+        // Ersten Eintrag entfernen
+        let without_first: String = original.lines().skip(1).map(|line| format!("{}\n", line)).collect();
+        fs::write(&path, without_first).unwrap();
+        assert_eq!(log.verify().unwrap().first_invalid_sequence, Some(0));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_clipboard_content_is_opt_in() {
+        let log = AuditLog::in_memory(AuditConfig::default());
+        log.record(AuditEvent::new(AuditEventKind::ClipboardSent, None).with_size(6).with_content("secret"));
+
+        log.set_config(AuditConfig { log_clipboard_content: true });
+        log.record(AuditEvent::new(AuditEventKind::ClipboardSent, None).with_size(6).with_content("public"));
+
+        let entries = log.entries().unwrap();
+        assert_eq!(entries[0].event.content, None);
+        assert_eq!(entries[0].event.size_bytes, Some(6));
+        assert_eq!(entries[1].event.content.as_deref(), Some("public"));
+        assert!(log.verify().unwrap().valid);
+    }
+
+    #[test]
+    fn test_csv_export_quotes_fields() {
+        let log = AuditLog::in_memory(AuditConfig::default());
+        write_events(&log);
+
+        let csv = log.export(AuditExportFormat::Csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[2].starts_with("1,"));
+        assert!(lines[2].contains(",FileReceived,peer-1,\"report, final.pdf\",2048,,"));
+
+        let json: Vec<AuditEntry> = serde_json::from_str(&log.export(AuditExportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json.len(), 3);
+    }
+}
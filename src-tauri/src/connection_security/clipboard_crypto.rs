@@ -0,0 +1,340 @@
+// src-tauri/src/connection_security/clipboard_crypto.rs - Ende-zu-Ende-Verschlüsselung synchronisierter Zwischenablage-Einträge
+//
+// Pro Verbindung tauschen beide Seiten ephemere X25519-Schlüssel aus. Aus dem
+// gemeinsamen Geheimnis werden per HKDF zwei Schlüssel abgeleitet, einer je
+// Richtung, damit ein Peer einen eigenen Umschlag nicht zurückgespiegelt
+// bekommen kann. Ein neuer Austausch (z. B. nach einem Reconnect) ersetzt den
+// alten Schlüssel; Umschläge der vorherigen Verbindung sind danach ungültig.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+
+use base64::{Engine as _, engine::general_purpose};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use crate::connection_security::SecurityError;
+
+const NONCE_LEN: usize = 12;
+const KEY_INFO: &[u8] = b"smoldesk clipboard v1";
+
+/// Nonces, die je Peer zur Replay-Erkennung gemerkt werden; ältere Umschläge
+/// fängt das Frischefenster der Zwischenablage ab
+pub const MAX_SEEN_NONCES: usize = 4096;
+
+/// Verschlüsselter Zwischenablage-Eintrag auf dem Weg zum Peer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SealedSyncEntry {
+    /// Kennung des Verbindungsschlüssels; wechselt bei jedem Schlüsselaustausch
+    pub key_id: String,
+
+    /// Sendezeitpunkt in Unix-Millisekunden, als AAD authentifiziert
+    pub sent_at_ms: i64,
+
+    /// Base64
+    pub nonce: String,
+
+    /// Base64; ChaCha20-Poly1305 über den serialisierten Sync-Eintrag
+    pub ciphertext: String,
+}
+
+/// Schlüssel einer Verbindung, je Richtung einer
+struct PeerKey {
+    key_id: String,
+    outgoing: ChaCha20Poly1305,
+    incoming: ChaCha20Poly1305,
+    seen_nonces: HashSet<[u8; NONCE_LEN]>,
+    nonce_order: VecDeque<[u8; NONCE_LEN]>,
+}
+
+impl PeerKey {
+    /// Leitet beide Richtungsschlüssel aus dem ECDH-Geheimnis ab
+    fn derive(shared_secret: &[u8], own_public: &PublicKey, peer_public: &PublicKey) -> Self {
+        // Beide Seiten bilden dasselbe Salt, unabhängig davon, wer zuerst gesendet hat
+        let (first, second) = if own_public.as_bytes() <= peer_public.as_bytes() {
+            (own_public.as_bytes(), peer_public.as_bytes())
+        } else {
+            (peer_public.as_bytes(), own_public.as_bytes())
+        };
+        let mut salt = Vec::with_capacity(64);
+        salt.extend_from_slice(first);
+        salt.extend_from_slice(second);
+
+        let hkdf = Hkdf::<Sha256>::new(Some(&salt), shared_secret);
+        let direction_key = |sender: &PublicKey| {
+            let mut info = KEY_INFO.to_vec();
+            info.extend_from_slice(sender.as_bytes());
+            let mut key = [0u8; 32];
+            hkdf.expand(&info, &mut key)
+                .expect("32 bytes is a valid HKDF-SHA256 output length");
+            ChaCha20Poly1305::new(Key::from_slice(&key))
+        };
+
+        PeerKey {
+            key_id: hex_prefix(&Sha256::digest(&salt)),
+            outgoing: direction_key(own_public),
+            incoming: direction_key(peer_public),
+            seen_nonces: HashSet::new(),
+            nonce_order: VecDeque::new(),
+        }
+    }
+
+    fn aad(key_id: &str, sent_at_ms: i64) -> Vec<u8> {
+        let mut aad = KEY_INFO.to_vec();
+        aad.extend_from_slice(key_id.as_bytes());
+        aad.extend_from_slice(&sent_at_ms.to_be_bytes());
+        aad
+    }
+
+    /// Merkt sich eine Nonce; false, wenn sie schon einmal angenommen wurde
+    fn remember_nonce(&mut self, nonce: [u8; NONCE_LEN]) -> bool {
+        if !self.seen_nonces.insert(nonce) {
+            return false;
+        }
+        self.nonce_order.push_back(nonce);
+        if self.nonce_order.len() > MAX_SEEN_NONCES {
+            if let Some(oldest) = self.nonce_order.pop_front() {
+                self.seen_nonces.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Schlüssel der Zwischenablage-Synchronisation je Peer
+#[derive(Default)]
+pub struct ClipboardKeys {
+    /// Eigene ephemere Schlüssel, die noch auf den Peer-Schlüssel warten
+    pending: Mutex<HashMap<String, (EphemeralSecret, PublicKey)>>,
+
+    /// Abgeleitete Schlüssel je Peer
+    keys: Mutex<HashMap<String, PeerKey>>,
+}
+
+impl ClipboardKeys {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Beginnt einen Austausch mit dem Peer und liefert den eigenen öffentlichen
+    /// Schlüssel (Base64). Ein bisheriger Schlüssel des Peers wird verworfen.
+    pub fn begin_exchange(&self, peer_id: &str) -> String {
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&secret);
+
+        self.keys.lock().unwrap().remove(peer_id);
+        self.pending.lock().unwrap().insert(peer_id.to_string(), (secret, public));
+        general_purpose::STANDARD.encode(public.as_bytes())
+    }
+
+    /// Schließt den Austausch mit dem öffentlichen Schlüssel des Peers ab und
+    /// liefert die Kennung des neuen Schlüssels
+    pub fn complete_exchange(&self, peer_id: &str, peer_public_key: &str) -> Result<String, SecurityError> {
+        let peer_public = decode_public_key(peer_public_key)?;
+        let (secret, own_public) = self.pending.lock().unwrap().remove(peer_id)
+            .ok_or_else(|| SecurityError::ConfigurationError(
+                format!("Kein laufender Schlüsselaustausch mit Peer {}", peer_id)
+            ))?;
+        let shared = secret.diffie_hellman(&peer_public);
+
+        let key = PeerKey::derive(shared.as_bytes(), &own_public, &peer_public);
+        let key_id = key.key_id.clone();
+        self.keys.lock().unwrap().insert(peer_id.to_string(), key);
+        Ok(key_id)
+    }
+
+    /// Vergisst Schlüssel und laufenden Austausch eines Peers
+    pub fn forget(&self, peer_id: &str) {
+        self.pending.lock().unwrap().remove(peer_id);
+        self.keys.lock().unwrap().remove(peer_id);
+    }
+
+    pub fn has_key(&self, peer_id: &str) -> bool {
+        self.keys.lock().unwrap().contains_key(peer_id)
+    }
+
+    /// Peers mit ausgehandeltem Schlüssel
+    pub fn peers(&self) -> Vec<String> {
+        self.keys.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Verschlüsselt einen serialisierten Sync-Eintrag für den Peer
+    pub fn seal(&self, peer_id: &str, payload: &str, sent_at_ms: i64) -> Result<SealedSyncEntry, SecurityError> {
+        let keys = self.keys.lock().unwrap();
+        let key = keys.get(peer_id).ok_or_else(|| no_key(peer_id))?;
+
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let aad = PeerKey::aad(&key.key_id, sent_at_ms);
+        let ciphertext = key.outgoing
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: payload.as_bytes(), aad: &aad })
+            .map_err(|_| SecurityError::EncryptionError("Zwischenablage-Eintrag nicht verschlüsselbar".to_string()))?;
+
+        Ok(SealedSyncEntry {
+            key_id: key.key_id.clone(),
+            sent_at_ms,
+            nonce: general_purpose::STANDARD.encode(nonce),
+            ciphertext: general_purpose::STANDARD.encode(ciphertext),
+        })
+    }
+
+    /// Authentifiziert und entschlüsselt einen Umschlag des Peers. Jeder Umschlag
+    /// wird nur einmal angenommen.
+    pub fn open(&self, peer_id: &str, sealed: &SealedSyncEntry) -> Result<String, SecurityError> {
+        let mut keys = self.keys.lock().unwrap();
+        let key = keys.get_mut(peer_id).ok_or_else(|| no_key(peer_id))?;
+        if sealed.key_id != key.key_id {
+            return Err(SecurityError::DecryptionError(
+                format!("Umschlag gehört zu einem anderen Schlüssel als dem von Peer {}", peer_id)
+            ));
+        }
+
+        let nonce: [u8; NONCE_LEN] = general_purpose::STANDARD.decode(&sealed.nonce).ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| SecurityError::DecryptionError("Ungültige Nonce".to_string()))?;
+        let ciphertext = general_purpose::STANDARD.decode(&sealed.ciphertext)
+            .map_err(|e| SecurityError::DecryptionError(format!("Ungültiger Ciphertext: {}", e)))?;
+
+        let aad = PeerKey::aad(&key.key_id, sealed.sent_at_ms);
+        let plaintext = key.incoming
+            .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: &aad })
+            .map_err(|_| SecurityError::DecryptionError("Authentifizierung des Zwischenablage-Eintrags fehlgeschlagen".to_string()))?;
+
+        // Erst nach erfolgreicher Authentifizierung vermerken, sonst könnte ein
+        // Angreifer mit gefälschten Umschlägen echte Nonces vorab sperren
+        if !key.remember_nonce(nonce) {
+            return Err(SecurityError::DecryptionError("Zwischenablage-Eintrag wurde bereits empfangen".to_string()));
+        }
+
+        String::from_utf8(plaintext)
+            .map_err(|e| SecurityError::DecryptionError(format!("Eintrag ist kein UTF-8: {}", e)))
+    }
+}
+
+fn no_key(peer_id: &str) -> SecurityError {
+    SecurityError::ConfigurationError(format!("Kein Zwischenablage-Schlüssel für Peer {}", peer_id))
+}
+
+/// Dekodiert einen Base64-kodierten X25519-Schlüssel
+fn decode_public_key(encoded: &str) -> Result<PublicKey, SecurityError> {
+    let bytes = general_purpose::STANDARD.decode(encoded)
+        .map_err(|e| SecurityError::ValidationError(format!("Ungültige Schlüsselkodierung: {}", e)))?;
+    let bytes: [u8; 32] = bytes.try_into()
+        .map_err(|_| SecurityError::ValidationError("Öffentlicher Schlüssel muss 32 Bytes lang sein".to_string()))?;
+    Ok(PublicKey::from(bytes))
+}
+
+fn hex_prefix(digest: &[u8]) -> String {
+    digest.iter().take(8).map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Zwei Seiten mit abgeschlossenem Austausch: (host, viewer)
+    fn paired(host_sees: &str, viewer_sees: &str) -> (ClipboardKeys, ClipboardKeys) {
+        let host = ClipboardKeys::new();
+        let viewer = ClipboardKeys::new();
+        let host_public = host.begin_exchange(host_sees);
+        let viewer_public = viewer.begin_exchange(viewer_sees);
+        let host_key = host.complete_exchange(host_sees, &viewer_public).unwrap();
+        let viewer_key = viewer.complete_exchange(viewer_sees, &host_public).unwrap();
+        assert_eq!(host_key, viewer_key);
+        (host, viewer)
+    }
+
+    #[test]
+    fn test_round_trip_in_both_directions() {
+        let (host, viewer) = paired("viewer", "host");
+
+        let sealed = host.seal("viewer", "{\"id\":\"a\"}", 1000).unwrap();
+        assert_eq!(viewer.open("host", &sealed).unwrap(), "{\"id\":\"a\"}");
+
+        let sealed = viewer.seal("host", "reply", 2000).unwrap();
+        assert_eq!(host.open("viewer", &sealed).unwrap(), "reply");
+    }
+
+    #[test]
+    fn test_tampered_envelopes_are_rejected() {
+        let (host, viewer) = paired("viewer", "host");
+        let sealed = host.seal("viewer", "secret", 1000).unwrap();
+
+        let mut ciphertext = general_purpose::STANDARD.decode(&sealed.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        let tampered = SealedSyncEntry { ciphertext: general_purpose::STANDARD.encode(ciphertext), ..sealed.clone() };
+        assert!(matches!(viewer.open("host", &tampered), Err(SecurityError::DecryptionError(_))));
+
+        // Der Zeitstempel ist Teil der AAD und kann nicht aufgefrischt werden
+        let backdated = SealedSyncEntry { sent_at_ms: 5000, ..sealed.clone() };
+        assert!(matches!(viewer.open("host", &backdated), Err(SecurityError::DecryptionError(_))));
+
+        // Fehlversuche sperren den echten Umschlag nicht
+        assert_eq!(viewer.open("host", &sealed).unwrap(), "secret");
+    }
+
+    #[test]
+    fn test_replayed_and_reflected_envelopes_are_rejected() {
+        let (host, viewer) = paired("viewer", "host");
+        let sealed = host.seal("viewer", "once", 1000).unwrap();
+
+        assert!(viewer.open("host", &sealed).is_ok());
+        assert!(matches!(viewer.open("host", &sealed), Err(SecurityError::DecryptionError(_))));
+
+        // Der eigene Umschlag wird nicht als Eintrag des Peers angenommen
+        let own = host.seal("viewer", "mirror", 1000).unwrap();
+        assert!(host.open("viewer", &own).is_err());
+    }
+
+    #[test]
+    fn test_keys_are_isolated_between_peers() {
+        let host = ClipboardKeys::new();
+        let alice = ClipboardKeys::new();
+        let bob = ClipboardKeys::new();
+        let host_for_alice = host.begin_exchange("alice");
+        let host_for_bob = host.begin_exchange("bob");
+        let alice_public = alice.begin_exchange("host");
+        let bob_public = bob.begin_exchange("host");
+        host.complete_exchange("alice", &alice_public).unwrap();
+        host.complete_exchange("bob", &bob_public).unwrap();
+        alice.complete_exchange("host", &host_for_alice).unwrap();
+        bob.complete_exchange("host", &host_for_bob).unwrap();
+
+        let for_alice = host.seal("alice", "for alice", 1000).unwrap();
+        assert!(bob.open("host", &for_alice).is_err());
+        assert_eq!(alice.open("host", &for_alice).unwrap(), "for alice");
+
+        // Bob kann sich gegenüber dem Host nicht als Alice ausgeben
+        let from_bob = bob.seal("host", "from bob", 1000).unwrap();
+        assert!(host.open("alice", &from_bob).is_err());
+        assert_eq!(host.open("bob", &from_bob).unwrap(), "from bob");
+    }
+
+    #[test]
+    fn test_reconnect_rotates_the_key() {
+        let (host, viewer) = paired("viewer", "host");
+        let old = host.seal("viewer", "before", 1000).unwrap();
+
+        // Neue Verbindung: beide Seiten tauschen erneut Schlüssel aus
+        let host_public = host.begin_exchange("viewer");
+        assert!(!host.has_key("viewer"));
+        let viewer_public = viewer.begin_exchange("host");
+        host.complete_exchange("viewer", &viewer_public).unwrap();
+        viewer.complete_exchange("host", &host_public).unwrap();
+
+        assert!(viewer.open("host", &old).is_err());
+        let new = host.seal("viewer", "after", 2000).unwrap();
+        assert_ne!(new.key_id, old.key_id);
+        assert_eq!(viewer.open("host", &new).unwrap(), "after");
+
+        host.forget("viewer");
+        assert!(host.seal("viewer", "gone", 3000).is_err());
+    }
+}
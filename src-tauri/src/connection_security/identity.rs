@@ -0,0 +1,322 @@
+// src-tauri/src/connection_security/identity.rs - Dauerhafte Geräteidentität und Fingerprint-Prüfung
+//
+// Jede Installation besitzt ein Ed25519-Schlüsselpaar. Der private Schlüssel liegt
+// verschlüsselt im Datenverzeichnis; der Schlüssel dafür wird aus der Machine-ID
+// abgeleitet, die Datei ist also nur auf diesem Rechner lesbar. Ein Peer weist
+// seine Identität nach, indem er eine Einmal-Nonce des Gegenübers signiert.
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use base64::{Engine as _, engine::general_purpose};
+use chacha20poly1305::{aead::{Aead, KeyInit}, ChaCha20Poly1305, Key, Nonce};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use hkdf::Hkdf;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::connection_security::{AccessRight, SecurityError};
+
+pub const IDENTITY_FILE: &str = "identity.key";
+
+// Gültigkeit einer offenen Identitätsprüfung in Sekunden
+pub const CHALLENGE_TTL_SECS: u64 = 60;
+
+// Quellen der Machine-ID, aus der der Dateischlüssel abgeleitet wird
+const MACHINE_ID_PATHS: [&str; 2] = ["/etc/machine-id", "/var/lib/dbus/machine-id"];
+
+// Trennt Identitätssignaturen von allen anderen Verwendungen des Schlüssels
+const CHALLENGE_CONTEXT: &str = "smoldesk-identity-v1";
+
+const STORAGE_VERSION: u32 = 1;
+
+// Verschlüsselter privater Schlüssel auf der Platte
+#[derive(Serialize, Deserialize)]
+struct StoredIdentity {
+    version: u32,
+    nonce: String,      // Base64, 12 Bytes
+    ciphertext: String, // Base64, ChaCha20-Poly1305 über den 32-Byte-Schlüssel
+}
+
+// Eigene Identität dieser Installation
+pub struct DeviceIdentity {
+    signing_key: SigningKey,
+}
+
+impl DeviceIdentity {
+    pub fn generate() -> Self {
+        DeviceIdentity {
+            signing_key: SigningKey::generate(&mut OsRng),
+        }
+    }
+
+    // Identität aus dem Datenverzeichnis laden oder beim ersten Start erzeugen
+    pub fn load_or_create(data_dir: &Path) -> Result<Self, SecurityError> {
+        Self::load_or_create_with_secret(&data_dir.join(IDENTITY_FILE), &machine_secret()?)
+    }
+
+    // Identität aus einem exportierten privaten Schlüssel (z.B. aus einem Konfigurationspaket)
+    pub fn from_secret_key(key_bytes: &[u8; 32]) -> Self {
+        DeviceIdentity {
+            signing_key: SigningKey::from_bytes(key_bytes),
+        }
+    }
+
+    // Privater Schlüssel im Klartext; nur für verschlüsselte Exporte
+    pub fn secret_key(&self) -> [u8; 32] {
+        self.signing_key.to_bytes()
+    }
+
+    // Ersetzt die gespeicherte Identität im Datenverzeichnis
+    pub fn store(&self, data_dir: &Path) -> Result<(), SecurityError> {
+        self.save(&data_dir.join(IDENTITY_FILE), &machine_secret()?)
+    }
+
+    fn load_or_create_with_secret(path: &Path, secret: &[u8]) -> Result<Self, SecurityError> {
+        if path.exists() {
+            return Self::load(path, secret);
+        }
+
+        let identity = Self::generate();
+        identity.save(path, secret)?;
+        log::info!(target: crate::logging::TARGET_SECURITY, "Created device identity {}", identity.fingerprint());
+        Ok(identity)
+    }
+
+    fn load(path: &Path, secret: &[u8]) -> Result<Self, SecurityError> {
+        let stored: StoredIdentity = serde_json::from_slice(&fs::read(path)?)
+            .map_err(|e| SecurityError::StorageError(format!("Ungültige Identitätsdatei: {}", e)))?;
+        if stored.version != STORAGE_VERSION {
+            return Err(SecurityError::StorageError(format!("Unbekannte Version der Identitätsdatei: {}", stored.version)));
+        }
+
+        let decode = |value: &str| general_purpose::STANDARD.decode(value)
+            .map_err(|e| SecurityError::StorageError(format!("Ungültige Identitätsdatei: {}", e)));
+        let nonce = decode(&stored.nonce)?;
+        if nonce.len() != 12 {
+            return Err(SecurityError::StorageError("Ungültige Identitätsdatei: Nonce hat die falsche Länge".to_string()));
+        }
+
+        // Scheitert auch, wenn die Datei von einem anderen Rechner stammt
+        let key_bytes = file_cipher(secret)
+            .decrypt(Nonce::from_slice(&nonce), decode(&stored.ciphertext)?.as_slice())
+            .map_err(|_| SecurityError::DecryptionError("Identitätsdatei lässt sich auf diesem Rechner nicht entschlüsseln".to_string()))?;
+        let key_bytes: [u8; 32] = key_bytes.try_into()
+            .map_err(|_| SecurityError::StorageError("Ungültige Identitätsdatei: Schlüssel hat die falsche Länge".to_string()))?;
+
+        Ok(DeviceIdentity {
+            signing_key: SigningKey::from_bytes(&key_bytes),
+        })
+    }
+
+    // Verschlüsselt und atomar speichern, nur für den Benutzer lesbar
+    fn save(&self, path: &Path, secret: &[u8]) -> Result<(), SecurityError> {
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = file_cipher(secret)
+            .encrypt(Nonce::from_slice(&nonce), self.signing_key.to_bytes().as_slice())
+            .map_err(|e| SecurityError::EncryptionError(format!("Identität konnte nicht verschlüsselt werden: {}", e)))?;
+
+        let stored = StoredIdentity {
+            version: STORAGE_VERSION,
+            nonce: general_purpose::STANDARD.encode(nonce),
+            ciphertext: general_purpose::STANDARD.encode(ciphertext),
+        };
+        let json = serde_json::to_vec_pretty(&stored)
+            .map_err(|e| SecurityError::StorageError(e.to_string()))?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temp_path = path.with_extension("key.tmp");
+        {
+            let mut options = fs::OpenOptions::new();
+            options.write(true).create(true).truncate(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                options.mode(0o600);
+            }
+            let mut file = options.open(&temp_path)?;
+            file.write_all(&json)?;
+            file.sync_all()?;
+        }
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    // Öffentlicher Schlüssel (Base64)
+    pub fn public_key(&self) -> String {
+        general_purpose::STANDARD.encode(self.signing_key.verifying_key().as_bytes())
+    }
+
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.signing_key.verifying_key())
+    }
+
+    // Nonce eines Peers zusammen mit dem eigenen Gerätenamen signieren
+    pub fn sign_challenge(&self, nonce: &str, device_name: &str) -> IdentityProof {
+        IdentityProof {
+            public_key: self.public_key(),
+            device_name: device_name.to_string(),
+            signature: self.sign(&challenge_message(nonce, device_name)),
+        }
+    }
+
+    // Signatur (Base64) über eine Nachricht; die Nachricht muss ihren Zweck selbst nennen
+    pub fn sign(&self, message: &[u8]) -> String {
+        general_purpose::STANDARD.encode(self.signing_key.sign(message).to_bytes())
+    }
+}
+
+// Signatur eines öffentlichen Schlüssels (beides Base64) prüfen; liefert dessen Fingerprint
+pub fn verify_signature(public_key: &str, message: &[u8], signature: &str) -> Result<String, SecurityError> {
+    let invalid = |msg: &str| SecurityError::AuthenticationFailed(format!("Ungültige Signatur: {}", msg));
+
+    let key_bytes: [u8; 32] = general_purpose::STANDARD.decode(public_key)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| invalid("öffentlicher Schlüssel"))?;
+    let public_key = VerifyingKey::from_bytes(&key_bytes)
+        .map_err(|_| invalid("öffentlicher Schlüssel"))?;
+
+    let signature_bytes: [u8; 64] = general_purpose::STANDARD.decode(signature)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| invalid("Signatur-Kodierung"))?;
+
+    public_key.verify(message, &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| invalid("Signatur passt nicht"))?;
+
+    Ok(fingerprint(&public_key))
+}
+
+// Fingerprint eines öffentlichen Schlüssels im Stil von OpenSSH ("SHA256:...")
+pub fn fingerprint(public_key: &VerifyingKey) -> String {
+    format!("SHA256:{}", general_purpose::STANDARD_NO_PAD.encode(Sha256::digest(public_key.as_bytes())))
+}
+
+// Offene Identitätsprüfung, die an den Peer geht
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityChallenge {
+    pub nonce: String,
+    pub expires_at: u64,
+}
+
+// Antwort eines Peers auf eine Identitätsprüfung
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityProof {
+    pub public_key: String,  // Ed25519, Base64
+    pub device_name: String, // Vom Peer gewählter Name, mitsigniert
+    pub signature: String,   // Base64
+}
+
+impl IdentityProof {
+    // Signatur über Nonce und Gerätenamen prüfen; liefert den Fingerprint des Peers
+    pub fn verify(&self, nonce: &str) -> Result<String, SecurityError> {
+        verify_signature(&self.public_key, &challenge_message(nonce, &self.device_name), &self.signature)
+    }
+}
+
+// Vertrauenswürdiger Peer, dauerhaft in den Einstellungen gespeichert
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrustedPeer {
+    pub fingerprint: String,
+    pub device_name: String,          // Name beim Vertrauen; ein anderer Schlüssel unter diesem Namen wird abgelehnt
+    #[serde(default)]
+    pub nickname: Option<String>,
+    #[serde(default)]
+    pub access_rights: Vec<AccessRight>, // Rechte nach erfolgreicher Prüfung (leer = Standardrechte)
+    pub trusted_at: u64,
+}
+
+// Ergebnis einer erfolgreichen Identitätsprüfung
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerifiedPeer {
+    pub peer_id: String,
+    pub fingerprint: String,
+    pub device_name: String,
+    pub trusted: Option<TrustedPeer>,
+}
+
+// Name dieses Rechners für Identitätsnachweise
+pub fn device_name() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| fs::read_to_string("/etc/hostname"))
+        .map(|name| name.trim().to_string())
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "SmolDesk".to_string())
+}
+
+fn challenge_message(nonce: &str, device_name: &str) -> Vec<u8> {
+    format!("{}\n{}\n{}", CHALLENGE_CONTEXT, nonce, device_name).into_bytes()
+}
+
+fn file_cipher(secret: &[u8]) -> ChaCha20Poly1305 {
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(None, secret)
+        .expand(b"smoldesk-identity-file", &mut key)
+        .expect("32 bytes are a valid HKDF-SHA256 output length");
+    ChaCha20Poly1305::new(Key::from_slice(&key))
+}
+
+fn machine_secret() -> Result<Vec<u8>, SecurityError> {
+    MACHINE_ID_PATHS.iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .find(|id| !id.is_empty())
+        .map(String::into_bytes)
+        .ok_or_else(|| SecurityError::StorageError("Keine Machine-ID gefunden (/etc/machine-id)".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_verification() {
+        let identity = DeviceIdentity::generate();
+        let proof = identity.sign_challenge("nonce-1", "laptop");
+        assert_eq!(proof.verify("nonce-1").unwrap(), identity.fingerprint());
+        assert!(identity.fingerprint().starts_with("SHA256:"));
+
+        // Andere Nonce, anderer Name oder fremder Schlüssel fallen durch
+        assert!(matches!(proof.verify("nonce-2"), Err(SecurityError::AuthenticationFailed(_))));
+        let renamed = IdentityProof { device_name: "desktop".to_string(), ..proof.clone() };
+        assert!(renamed.verify("nonce-1").is_err());
+        let impostor = IdentityProof { public_key: DeviceIdentity::generate().public_key(), ..proof.clone() };
+        assert!(impostor.verify("nonce-1").is_err());
+        let garbage = IdentityProof { signature: "kein-base64".to_string(), ..proof };
+        assert!(garbage.verify("nonce-1").is_err());
+    }
+
+    #[test]
+    fn test_identity_is_stored_encrypted() {
+        let dir = std::env::temp_dir().join(format!("smoldesk-identity-{}", uuid::Uuid::new_v4()));
+        let path = dir.join(IDENTITY_FILE);
+
+        let created = DeviceIdentity::load_or_create_with_secret(&path, b"machine-a").unwrap();
+        let loaded = DeviceIdentity::load_or_create_with_secret(&path, b"machine-a").unwrap();
+        assert_eq!(created.fingerprint(), loaded.fingerprint());
+
+        // Der private Schlüssel steht nicht im Klartext in der Datei
+        let content = fs::read(&path).unwrap();
+        let secret = created.signing_key.to_bytes();
+        assert!(!content.windows(secret.len()).any(|window| window == secret));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        // Auf einem anderen Rechner lässt sie sich nicht öffnen
+        assert!(matches!(
+            DeviceIdentity::load_or_create_with_secret(&path, b"machine-b"),
+            Err(SecurityError::DecryptionError(_))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,1507 @@
+// src-tauri/src/connection_security/mod.rs
+
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Digest};
+use rand::{thread_rng, Rng};
+use rand::distributions::Alphanumeric;
+use base64::{Engine as _, engine::general_purpose};
+use jsonwebtoken::{encode, decode, Header, Algorithm, Validation, EncodingKey, DecodingKey};
+use std::time::{SystemTime, UNIX_EPOCH, Duration};
+use argon2::{Argon2, PasswordHasher, PasswordVerifier};
+use argon2::password_hash::{PasswordHash, SaltString, rand_core::OsRng};
+
+use crate::logging::TARGET_SECURITY;
+
+pub mod audit;
+pub mod clipboard_crypto;
+pub mod identity;
+pub mod rate_limit;
+
+use audit::{AuditEvent, AuditEventKind, AuditSink};
+use clipboard_crypto::{ClipboardKeys, SealedSyncEntry};
+use identity::{DeviceIdentity, IdentityChallenge, IdentityProof, TrustedPeer, VerifiedPeer, CHALLENGE_TTL_SECS};
+use rate_limit::{RateLimitConfig, RateLimitDecision, RateLimitedCommand, RateLimiter};
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Typ-Aliase für bessere Lesbarkeit
+pub type SessionId = String;
+pub type Token = String;
+pub type UserId = String;
+
+// Ratengrenzen-Schlüssel für Befehle, die keine Peer-ID mitschicken
+pub const UNIDENTIFIED_PEER: &str = "remote";
+
+// Sicherheitsfehler
+#[derive(Debug)]
+pub enum SecurityError {
+    AuthenticationFailed(String),
+    TokenInvalid(String),
+    TokenExpired(String),
+    PermissionDenied(String),
+    EncryptionError(String),
+    DecryptionError(String),
+    ConfigurationError(String),
+    ValidationError(String),
+    TokenRevoked(String),
+    TokenReplayed(String),
+    TooManyAttempts(u64), // Sekunden bis zur Entsperrung
+    StorageError(String),
+    IdentityMismatch(String), // Bekannter Gerätename mit fremdem Schlüssel
+}
+
+impl fmt::Display for SecurityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecurityError::AuthenticationFailed(msg) => write!(f, "Authentifizierung fehlgeschlagen: {}", msg),
+            SecurityError::TokenInvalid(msg) => write!(f, "Ungültiges Token: {}", msg),
+            SecurityError::TokenExpired(msg) => write!(f, "Token abgelaufen: {}", msg),
+            SecurityError::PermissionDenied(msg) => write!(f, "Zugriff verweigert: {}", msg),
+            SecurityError::EncryptionError(msg) => write!(f, "Verschlüsselungsfehler: {}", msg),
+            SecurityError::DecryptionError(msg) => write!(f, "Entschlüsselungsfehler: {}", msg),
+            SecurityError::ConfigurationError(msg) => write!(f, "Konfigurationsfehler: {}", msg),
+            SecurityError::ValidationError(msg) => write!(f, "Validierungsfehler: {}", msg),
+            SecurityError::TokenRevoked(msg) => write!(f, "Token widerrufen: {}", msg),
+            SecurityError::TokenReplayed(msg) => write!(f, "Token bereits verwendet: {}", msg),
+            SecurityError::TooManyAttempts(secs) => write!(f, "Zu viele Fehlversuche, gesperrt für {} Sekunden", secs),
+            SecurityError::StorageError(msg) => write!(f, "Speicherfehler: {}", msg),
+            SecurityError::IdentityMismatch(msg) => write!(f, "Identität geändert: {}", msg),
+        }
+    }
+}
+
+impl Error for SecurityError {}
+
+impl From<std::io::Error> for SecurityError {
+    fn from(error: std::io::Error) -> Self {
+        SecurityError::StorageError(error.to_string())
+    }
+}
+
+// Verbindungsmodi
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ConnectionMode {
+    Public,      // Öffentliche Verbindung, jeder kann beitreten
+    Protected,   // Geschützt mit Passwort
+    Authenticated, // Nur authentifizierte Benutzer
+    Private,     // Nur für bestimmte Benutzer
+}
+
+// Zugriffsrechte
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum AccessRight {
+    ViewOnly,    // Nur Ansicht
+    ControlInput, // Eingabesteuerung
+    FileTransfer, // Dateiübertragung
+    AudioAccess,  // Audiozugriff
+    FullAccess,   // Vollzugriff
+    AccessibilityText, // Bildschirmtext über den Barrierefreiheits-Bus, wird nie per Rolle vergeben
+    SystemActions, // Störende Sonderbefehle wie Bildschirmsperre ohne Rückfrage, wird nie per Rolle vergeben
+}
+
+// Benutzerrollen
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum UserRole {
+    Guest,      // Gast
+    Member,     // Mitglied
+    Moderator,  // Moderator
+    Admin,      // Administrator
+    Owner,      // Eigentümer
+}
+
+// Benutzer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct User {
+    pub id: UserId,
+    pub username: String,
+    pub role: UserRole,
+    pub access_rights: Vec<AccessRight>,
+}
+
+// Session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: SessionId,
+    pub user_id: UserId,
+    pub created_at: u64,
+    pub expires_at: u64,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+// JWT-Claims
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: UserId,              // Benutzer-ID
+    pub session_id: SessionId,    // Sitzungs-ID
+    pub role: UserRole,           // Rolle
+    pub access_rights: Vec<AccessRight>, // Zugriffsrechte
+    pub exp: u64,                 // Ablaufzeit
+    pub iat: u64,                 // Ausstellungszeit
+    pub iss: String,              // Aussteller
+}
+
+// Angaben zum Peer, für den ein Zugangstoken ausgestellt wird
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerInfo {
+    pub peer_id: String,
+    pub display_name: Option<String>,
+    pub access_rights: Vec<AccessRight>,
+}
+
+// Inhalt eines Zugangstokens für den Signalisierungs-Handshake
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessTokenClaims {
+    pub token_id: String,         // Einmal-Nonce (Replay-Schutz)
+    pub peer: PeerInfo,           // Peer, für den das Token gilt
+    pub key_id: u32,              // ID des Signaturschlüssels
+    pub issued_at: u64,           // Ausstellungszeit
+    pub expires_at: u64,          // Ablaufzeit
+}
+
+// Schlüssel zum Signieren von Zugangstokens
+struct SigningKey {
+    id: u32,
+    key: Vec<u8>,
+}
+
+// Aktueller und (während der Übergangszeit) vorheriger Signaturschlüssel
+struct TokenKeys {
+    current: SigningKey,
+    previous: Option<(SigningKey, u64)>, // (Schlüssel, gültig bis)
+}
+
+// Verbindungskonfiguration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionSecurityConfig {
+    pub mode: ConnectionMode,
+    pub password_hash: Option<String>,
+    pub allowed_users: Option<Vec<UserId>>,
+    pub default_access_rights: Vec<AccessRight>,
+    pub session_timeout_minutes: u64,
+    pub use_encryption: bool,
+    pub max_failed_attempts: u32,
+    pub room_password_hash: Option<String>, // Argon2id-Hash im PHC-Format, nie Klartext
+    pub lockout_minutes: u64,
+    #[serde(default)]
+    pub allow_custom_commands: bool, // Eigene xdotool/ydotool-Befehle des Peers, standardmäßig aus
+    #[serde(default)]
+    pub rate_limits: RateLimitConfig, // Grenzen für Eingaben und Zwischenablage je Peer
+    #[serde(default)]
+    pub trusted_peers: Vec<TrustedPeer>, // Peers, deren Fingerprint bestätigt wurde
+}
+
+impl Default for ConnectionSecurityConfig {
+    fn default() -> Self {
+        ConnectionSecurityConfig {
+            mode: ConnectionMode::Protected,
+            password_hash: None,
+            allowed_users: None,
+            default_access_rights: vec![AccessRight::ViewOnly],
+            session_timeout_minutes: 60,
+            use_encryption: true,
+            max_failed_attempts: 5,
+            room_password_hash: None,
+            lockout_minutes: 15,
+            allow_custom_commands: false,
+            rate_limits: RateLimitConfig::default(),
+            trusted_peers: Vec::new(),
+        }
+    }
+}
+
+// Verbindungssicherheitsmanager
+pub struct ConnectionSecurityManager {
+    config: Arc<Mutex<ConnectionSecurityConfig>>,
+    secret_key: String,
+    active_sessions: Arc<Mutex<Vec<Session>>>,
+    failed_attempts: Arc<Mutex<std::collections::HashMap<String, (u32, u64)>>>, // IP -> (Anzahl, Zeitstempel)
+    token_keys: Arc<Mutex<TokenKeys>>,
+    used_token_ids: Arc<Mutex<std::collections::HashMap<String, u64>>>, // Token-ID -> Ablaufzeit
+    revoked_token_ids: Arc<Mutex<std::collections::HashMap<String, u64>>>, // Token-ID -> Ablaufzeit
+    room_password_attempts: Arc<Mutex<std::collections::HashMap<String, (u32, u64)>>>, // Peer -> (Fehlversuche, gesperrt bis)
+    peer_access_rights: Arc<Mutex<std::collections::HashMap<String, Vec<AccessRight>>>>, // Peer -> Rechte (leer = ausstehend)
+    rate_limiter: Arc<Mutex<RateLimiter>>,
+    audit_sink: Option<Arc<dyn AuditSink>>,
+    identity: Option<DeviceIdentity>,
+    identity_challenges: Arc<Mutex<std::collections::HashMap<String, (String, u64)>>>, // Peer -> (Nonce, gültig bis)
+    peer_identities: Arc<Mutex<std::collections::HashMap<String, (String, String)>>>, // Peer -> (Fingerprint, Gerätename)
+    clipboard_keys: Arc<ClipboardKeys>, // Peer -> Schlüssel der Zwischenablage-Synchronisation
+}
+
+impl ConnectionSecurityManager {
+    pub fn new(secret_key: &str, config: ConnectionSecurityConfig) -> Self {
+        // Stellen Sie sicher, dass der Secret-Key stark genug ist
+        let mut actual_key = secret_key.to_string();
+        if actual_key.len() < 32 {
+            // Generiere einen starken Schlüssel, wenn der bereitgestellte zu schwach ist
+            let random_suffix: String = thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(32 - actual_key.len())
+                .map(char::from)
+                .collect();
+                
+            actual_key = format!("{}{}", actual_key, random_suffix);
+        }
+        
+        // Erster Token-Schlüssel wird vom Secret abgeleitet, spätere sind zufällig
+        let initial_token_key = SigningKey {
+            id: 0,
+            key: Sha256::digest(format!("access-token:{}", actual_key).as_bytes()).to_vec(),
+        };
+        
+        ConnectionSecurityManager {
+            secret_key: actual_key,
+            active_sessions: Arc::new(Mutex::new(Vec::new())),
+            failed_attempts: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            token_keys: Arc::new(Mutex::new(TokenKeys {
+                current: initial_token_key,
+                previous: None,
+            })),
+            used_token_ids: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            revoked_token_ids: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            room_password_attempts: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            peer_access_rights: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            rate_limiter: Arc::new(Mutex::new(RateLimiter::new(config.rate_limits.clone()))),
+            config: Arc::new(Mutex::new(config)),
+            audit_sink: None,
+            identity: None,
+            identity_challenges: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            peer_identities: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            clipboard_keys: Arc::new(ClipboardKeys::new()),
+        }
+    }
+    
+    // Ziel für Sperrungen von Peers im Audit-Protokoll
+    pub fn set_audit_sink(&mut self, sink: Arc<dyn AuditSink>) {
+        self.audit_sink = Some(sink);
+    }
+    
+    // Dauerhafte Identität dieser Installation
+    pub fn set_identity(&mut self, identity: DeviceIdentity) {
+        self.identity = Some(identity);
+    }
+    
+    pub fn identity(&self) -> Option<&DeviceIdentity> {
+        self.identity.as_ref()
+    }
+    
+    // Zugangscode generieren
+    pub fn generate_access_code() -> String {
+        let code: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect();
+        
+        code
+    }
+    
+    // Passwort hashen
+    pub fn hash_password(password: &str, salt: Option<&str>) -> String {
+        let salt_value = salt.unwrap_or_else(|| {
+            // Generiere zufälligen Salt, wenn keiner bereitgestellt wurde
+            &thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(16)
+                .map(char::from)
+                .collect::<String>()
+        });
+        
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{}{}", salt_value, password));
+        let result = hasher.finalize();
+        
+        format!("{}${}", salt_value, general_purpose::STANDARD.encode(result))
+    }
+    
+    // Passwort verifizieren
+    pub fn verify_password(&self, password: &str, hash: &str) -> bool {
+        let parts: Vec<&str> = hash.split('$').collect();
+        if parts.len() != 2 {
+            return false;
+        }
+        
+        let salt = parts[0];
+        let stored_hash = parts[1];
+        
+        let calculated_hash = Self::hash_password(password, Some(salt));
+        let calculated_parts: Vec<&str> = calculated_hash.split('$').collect();
+        
+        if calculated_parts.len() != 2 {
+            return false;
+        }
+        
+        calculated_parts[1] == stored_hash
+    }
+    
+    // JWT-Token generieren
+    pub fn generate_token(&self, user: &User, ip_address: Option<&str>, user_agent: Option<&str>) -> Result<(Token, Session), SecurityError> {
+        let config = self.config.lock().unwrap();
+        
+        // Prüfen, ob der Benutzer zugelassen ist
+        if let ConnectionMode::Private = config.mode {
+            if let Some(allowed_users) = &config.allowed_users {
+                if !allowed_users.contains(&user.id) {
+                    return Err(SecurityError::PermissionDenied(
+                        "Benutzer nicht für diese Verbindung zugelassen".to_string()
+                    ));
+                }
+            }
+        }
+        
+        // Neue Sitzung erstellen
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| SecurityError::ConfigurationError(format!("Systemzeit-Fehler: {}", e)))?
+            .as_secs();
+        
+        let expires_at = now + (config.session_timeout_minutes * 60);
+        
+        let session_id = format!("session_{}", general_purpose::STANDARD.encode(thread_rng().gen::<[u8; 16]>()));
+        
+        let session = Session {
+            id: session_id.clone(),
+            user_id: user.id.clone(),
+            created_at: now,
+            expires_at,
+            ip_address: ip_address.map(String::from),
+            user_agent: user_agent.map(String::from),
+        };
+        
+        // JWT-Claims erstellen
+        let claims = Claims {
+            sub: user.id.clone(),
+            session_id: session.id.clone(),
+            role: user.role.clone(),
+            access_rights: user.access_rights.clone(),
+            exp: expires_at,
+            iat: now,
+            iss: "SmolDesk".to_string(),
+        };
+        
+        // Token generieren
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(self.secret_key.as_ref())
+        ).map_err(|e| SecurityError::EncryptionError(format!("Token-Erstellung fehlgeschlagen: {}", e)))?;
+        
+        // Sitzung speichern
+        let mut sessions = self.active_sessions.lock().unwrap();
+        sessions.push(session.clone());
+        
+        Ok((token, session))
+    }
+    
+    // JWT-Token validieren
+    pub fn validate_token(&self, token: &str) -> Result<Claims, SecurityError> {
+        // Token dekodieren
+        let validation = Validation::new(Algorithm::HS256);
+        
+        let token_data = decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(self.secret_key.as_ref()),
+            &validation
+        ).map_err(|e| {
+            match e.kind() {
+                jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
+                    SecurityError::TokenExpired("Token ist abgelaufen".to_string())
+                },
+                _ => SecurityError::TokenInvalid(format!("Ungültiges Token: {}", e))
+            }
+        })?;
+        
+        let claims = token_data.claims;
+        
+        // Überprüfen, ob die Sitzung noch aktiv ist
+        let sessions = self.active_sessions.lock().unwrap();
+        let session_exists = sessions.iter().any(|s| s.id == claims.session_id && s.user_id == claims.sub);
+        
+        if !session_exists {
+            return Err(SecurityError::TokenInvalid("Sitzung nicht gefunden oder ungültig".to_string()));
+        }
+        
+        Ok(claims)
+    }
+    
+    // Verbindung authentifizieren
+    pub fn authenticate_connection(&self, mode: ConnectionMode, credentials: Option<&str>, user: Option<&User>, ip_address: Option<&str>) -> Result<bool, SecurityError> {
+        let config = self.config.lock().unwrap();
+        
+        // Überprüfen, ob zu viele fehlgeschlagene Versuche vorliegen
+        if let Some(ip) = ip_address {
+            let mut failed_attempts = self.failed_attempts.lock().unwrap();
+            if let Some((attempts, timestamp)) = failed_attempts.get(ip) {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_err(|e| SecurityError::ConfigurationError(format!("Systemzeit-Fehler: {}", e)))?
+                    .as_secs();
+                
+                // Wenn die letzte Anfrage innerhalb der letzten 15 Minuten war und die maximale Anzahl überschritten wurde
+                if now - timestamp < 15 * 60 && *attempts >= config.max_failed_attempts {
+                    return Err(SecurityError::AuthenticationFailed(
+                        "Zu viele fehlgeschlagene Versuche. Bitte versuchen Sie es später erneut.".to_string()
+                    ));
+                }
+            }
+        }
+        
+        // Authentifizierung je nach Modus
+        match mode {
+            ConnectionMode::Public => {
+                // Öffentliche Verbindung, keine Authentifizierung erforderlich
+                Ok(true)
+            },
+            ConnectionMode::Protected => {
+                // Geschützte Verbindung mit Passwort
+                if let Some(password) = credentials {
+                    if let Some(hash) = &config.password_hash {
+                        let verified = self.verify_password(password, hash);
+                        
+                        if !verified && ip_address.is_some() {
+                            self.record_failed_attempt(ip_address.unwrap())?;
+                        }
+                        
+                        Ok(verified)
+                    } else {
+                        Err(SecurityError::ConfigurationError("Kein Passwort-Hash konfiguriert".to_string()))
+                    }
+                } else {
+                    if ip_address.is_some() {
+                        self.record_failed_attempt(ip_address.unwrap())?;
+                    }
+                    
+                    Err(SecurityError::AuthenticationFailed("Passwort erforderlich".to_string()))
+                }
+            },
+            ConnectionMode::Authenticated => {
+                // Nur authentifizierte Benutzer
+                if let Some(user_data) = user {
+                    // Hier könnte eine erweiterte Benutzerauthentifizierung stattfinden
+                    Ok(!user_data.id.is_empty())
+                } else {
+                    if ip_address.is_some() {
+                        self.record_failed_attempt(ip_address.unwrap())?;
+                    }
+                    
+                    Err(SecurityError::AuthenticationFailed("Benutzerauthentifizierung erforderlich".to_string()))
+                }
+            },
+            ConnectionMode::Private => {
+                // Nur für bestimmte Benutzer
+                if let Some(user_data) = user {
+                    if let Some(allowed_users) = &config.allowed_users {
+                        let allowed = allowed_users.contains(&user_data.id);
+                        
+                        if !allowed && ip_address.is_some() {
+                            self.record_failed_attempt(ip_address.unwrap())?;
+                        }
+                        
+                        Ok(allowed)
+                    } else {
+                        Err(SecurityError::ConfigurationError("Keine zugelassenen Benutzer konfiguriert".to_string()))
+                    }
+                } else {
+                    if ip_address.is_some() {
+                        self.record_failed_attempt(ip_address.unwrap())?;
+                    }
+                    
+                    Err(SecurityError::AuthenticationFailed("Benutzerauthentifizierung erforderlich".to_string()))
+                }
+            }
+        }
+    }
+    
+    // Fehlgeschlagenen Versuch protokollieren
+    fn record_failed_attempt(&self, ip_address: &str) -> Result<(), SecurityError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| SecurityError::ConfigurationError(format!("Systemzeit-Fehler: {}", e)))?
+            .as_secs();
+        
+        let mut failed_attempts = self.failed_attempts.lock().unwrap();
+        
+        let entry = failed_attempts.entry(ip_address.to_string()).or_insert((0, now));
+        entry.0 += 1;
+        entry.1 = now;
+        
+        Ok(())
+    }
+    
+    // Sitzung beenden
+    pub fn terminate_session(&self, session_id: &str) -> Result<(), SecurityError> {
+        let mut sessions = self.active_sessions.lock().unwrap();
+        let initial_len = sessions.len();
+        
+        sessions.retain(|s| s.id != session_id);
+        
+        if sessions.len() == initial_len {
+            return Err(SecurityError::ValidationError("Sitzung nicht gefunden".to_string()));
+        }
+        
+        Ok(())
+    }
+    
+    // Alle Sitzungen eines Benutzers beenden
+    pub fn terminate_user_sessions(&self, user_id: &str) -> Result<usize, SecurityError> {
+        let mut sessions = self.active_sessions.lock().unwrap();
+        let initial_len = sessions.len();
+        
+        sessions.retain(|s| s.user_id != user_id);
+        
+        let terminated_count = initial_len - sessions.len();
+        
+        Ok(terminated_count)
+    }
+    
+    // Abgelaufene Sitzungen bereinigen
+    pub fn cleanup_expired_sessions(&self) -> Result<usize, SecurityError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| SecurityError::ConfigurationError(format!("Systemzeit-Fehler: {}", e)))?
+            .as_secs();
+        
+        let mut sessions = self.active_sessions.lock().unwrap();
+        let initial_len = sessions.len();
+        
+        sessions.retain(|s| s.expires_at > now);
+        
+        let removed_count = initial_len - sessions.len();
+        
+        Ok(removed_count)
+    }
+    
+    // Zugriffsrechte überprüfen
+    pub fn check_access_rights(&self, claims: &Claims, required_rights: &[AccessRight]) -> bool {
+        for right in required_rights {
+            if !claims.access_rights.contains(right) {
+                return false;
+            }
+        }
+        
+        true
+    }
+    
+    // Minimale Zugriffsrechte basierend auf der Rolle zuweisen
+    pub fn assign_default_rights_by_role(role: &UserRole) -> Vec<AccessRight> {
+        match role {
+            UserRole::Guest => {
+                vec![AccessRight::ViewOnly]
+            },
+            UserRole::Member => {
+                vec![AccessRight::ViewOnly, AccessRight::ControlInput]
+            },
+            UserRole::Moderator => {
+                vec![AccessRight::ViewOnly, AccessRight::ControlInput, AccessRight::AudioAccess]
+            },
+            UserRole::Admin | UserRole::Owner => {
+                vec![AccessRight::ViewOnly, AccessRight::ControlInput, AccessRight::FileTransfer, AccessRight::AudioAccess, AccessRight::FullAccess]
+            }
+        }
+    }
+    
+    // Konfiguration aktualisieren
+    // Aktuelle Konfiguration samt gesetzter Passwort-Hashes
+    pub fn get_config(&self) -> ConnectionSecurityConfig {
+        self.config.lock().unwrap().clone()
+    }
+    
+    pub fn update_config(&self, config: ConnectionSecurityConfig) {
+        self.rate_limiter.lock().unwrap().set_config(config.rate_limits.clone());
+        let mut current_config = self.config.lock().unwrap();
+        *current_config = config;
+    }
+    
+    // Befehl eines Peers gegen die Ratengrenzen prüfen; Sperrungen landen im Audit-Protokoll.
+    // Befehle ohne Peer-ID teilen sich einen gemeinsamen Zähler, statt ungebremst durchzugehen.
+    pub fn check_rate_limit(&self, peer_id: Option<&str>, command: RateLimitedCommand) -> RateLimitDecision {
+        let peer_id = peer_id.unwrap_or(UNIDENTIFIED_PEER);
+        let decision = self.rate_limiter.lock().unwrap().check(peer_id, command, std::time::Instant::now());
+        
+        if let RateLimitDecision::Suspended(Some(report)) = &decision {
+            let detail = format!("{:?} rate exceeded {}x, suspended for {} s",
+                command, self.config.lock().unwrap().rate_limits.suspend_factor, report.suspended_secs.unwrap_or(0));
+            log::warn!(target: TARGET_SECURITY, "Peer {} suspended: {}", peer_id, detail);
+            
+            if let Some(sink) = &self.audit_sink {
+                sink.record(AuditEvent::new(AuditEventKind::PeerSuspended, Some(peer_id)).with_detail(detail));
+            }
+        }
+        
+        decision
+    }
+    
+    // Sicherheitsrelevante Nachrichten signieren (HMAC-SHA256)
+    pub fn sign_message(&self, message: &str) -> Result<String, SecurityError> {
+        let mut mac = HmacSha256::new_from_slice(self.secret_key.as_bytes())
+            .map_err(|e| SecurityError::EncryptionError(format!("HMAC-Initialisierungsfehler: {}", e)))?;
+        
+        mac.update(message.as_bytes());
+        
+        let result = mac.finalize();
+        let signature = general_purpose::STANDARD.encode(result.into_bytes());
+        
+        Ok(signature)
+    }
+    
+    // Signatur verifizieren
+    pub fn verify_signature(&self, message: &str, signature: &str) -> Result<bool, SecurityError> {
+        let signature_bytes = general_purpose::STANDARD.decode(signature)
+            .map_err(|e| SecurityError::ValidationError(format!("Ungültige Signatur-Kodierung: {}", e)))?;
+        
+        let mut mac = HmacSha256::new_from_slice(self.secret_key.as_bytes())
+            .map_err(|e| SecurityError::EncryptionError(format!("HMAC-Initialisierungsfehler: {}", e)))?;
+        
+        mac.update(message.as_bytes());
+        
+        mac.verify_slice(&signature_bytes)
+            .map(|_| true)
+            .map_err(|_| SecurityError::ValidationError("Signaturverifizierung fehlgeschlagen".to_string()))
+    }
+    
+    // Passwort für geschützte Verbindung setzen
+    pub fn set_connection_password(&self, password: &str) -> Result<(), SecurityError> {
+        let password_hash = Self::hash_password(password, None);
+        
+        let mut config = self.config.lock().unwrap();
+        config.password_hash = Some(password_hash);
+        config.mode = ConnectionMode::Protected;
+        
+        Ok(())
+    }
+    
+    // Erlaubte Benutzer für private Verbindung festlegen
+    pub fn set_allowed_users(&self, user_ids: Vec<UserId>) -> Result<(), SecurityError> {
+        if user_ids.is_empty() {
+            return Err(SecurityError::ConfigurationError("Liste der erlaubten Benutzer darf nicht leer sein".to_string()));
+        }
+        
+        let mut config = self.config.lock().unwrap();
+        config.allowed_users = Some(user_ids);
+        config.mode = ConnectionMode::Private;
+        
+        Ok(())
+    }
+    
+    // Aktive Sitzungen abrufen
+    pub fn get_active_sessions(&self) -> Vec<Session> {
+        let sessions = self.active_sessions.lock().unwrap();
+        sessions.clone()
+    }
+    
+    // Sitzung anhand der ID finden
+    pub fn find_session(&self, session_id: &str) -> Option<Session> {
+        let sessions = self.active_sessions.lock().unwrap();
+        sessions.iter().find(|s| s.id == session_id).cloned()
+    }
+    
+    // Aktuellen Verbindungsmodus abrufen
+    pub fn get_connection_mode(&self) -> ConnectionMode {
+        let config = self.config.lock().unwrap();
+        config.mode.clone()
+    }
+    
+    // Überprüfen, ob Verschlüsselung aktiviert ist
+    pub fn is_encryption_enabled(&self) -> bool {
+        let config = self.config.lock().unwrap();
+        config.use_encryption
+    }
+    
+    // Eigene Befehle (SpecialCommand::Custom) erlauben oder sperren
+    pub fn set_custom_commands_allowed(&self, allowed: bool) {
+        let mut config = self.config.lock().unwrap();
+        config.allow_custom_commands = allowed;
+    }
+    
+    // Überprüfen, ob eigene Befehle erlaubt sind
+    pub fn custom_commands_allowed(&self) -> bool {
+        let config = self.config.lock().unwrap();
+        config.allow_custom_commands
+    }
+    
+    // Zugangstoken für den Signalisierungs-Handshake erzeugen (Format: payload.signatur)
+    pub fn generate_access_token(&self, peer_info: PeerInfo, ttl: Duration) -> Result<Token, SecurityError> {
+        let now = Self::unix_now()?;
+        
+        let token_id = general_purpose::URL_SAFE_NO_PAD.encode(thread_rng().gen::<[u8; 16]>());
+        let keys = self.token_keys.lock().unwrap();
+        
+        let claims = AccessTokenClaims {
+            token_id,
+            peer: peer_info,
+            key_id: keys.current.id,
+            issued_at: now,
+            expires_at: now + ttl.as_secs(),
+        };
+        
+        let payload = serde_json::to_vec(&claims)
+            .map_err(|e| SecurityError::EncryptionError(format!("Token-Serialisierung fehlgeschlagen: {}", e)))?;
+        let payload = general_purpose::URL_SAFE_NO_PAD.encode(payload);
+        
+        let signature = Self::sign_token_payload(&keys.current.key, &payload)?;
+        
+        Ok(format!("{}.{}", payload, signature))
+    }
+    
+    // Zugangstoken prüfen: Signatur, Ablauf, Widerruf und Einmalverwendung
+    pub fn validate_access_token(&self, token: &str) -> Result<AccessTokenClaims, SecurityError> {
+        let now = Self::unix_now()?;
+        let claims = self.verify_access_token(token, now)?;
+        
+        if now >= claims.expires_at {
+            return Err(SecurityError::TokenExpired("Zugangstoken ist abgelaufen".to_string()));
+        }
+        
+        {
+            let mut revoked = self.revoked_token_ids.lock().unwrap();
+            revoked.retain(|_, expires_at| *expires_at > now);
+            
+            if revoked.contains_key(&claims.token_id) {
+                return Err(SecurityError::TokenRevoked(claims.token_id.clone()));
+            }
+        }
+        
+        // Replay-Cache: jede Token-ID wird nur einmal akzeptiert
+        {
+            let mut used = self.used_token_ids.lock().unwrap();
+            used.retain(|_, expires_at| *expires_at > now);
+            
+            if used.contains_key(&claims.token_id) {
+                return Err(SecurityError::TokenReplayed(claims.token_id.clone()));
+            }
+            used.insert(claims.token_id.clone(), claims.expires_at);
+        }
+        
+        Ok(claims)
+    }
+    
+    // Zugangstoken widerrufen (bis zu seinem Ablauf gesperrt)
+    pub fn revoke_token(&self, token: &str) -> Result<(), SecurityError> {
+        let now = Self::unix_now()?;
+        let claims = self.verify_access_token(token, now)?;
+        
+        let mut revoked = self.revoked_token_ids.lock().unwrap();
+        revoked.insert(claims.token_id, claims.expires_at);
+        
+        Ok(())
+    }
+    
+    // Signaturschlüssel für Zugangstokens rotieren; der alte bleibt für die Übergangszeit gültig
+    pub fn rotate_token_key(&self, grace_period: Duration) -> Result<(), SecurityError> {
+        let now = Self::unix_now()?;
+        
+        let mut keys = self.token_keys.lock().unwrap();
+        let new_key = SigningKey {
+            id: keys.current.id.wrapping_add(1),
+            key: thread_rng().gen::<[u8; 32]>().to_vec(),
+        };
+        
+        let old_key = std::mem::replace(&mut keys.current, new_key);
+        keys.previous = Some((old_key, now + grace_period.as_secs()));
+        
+        Ok(())
+    }
+    
+    // Raumpasswort setzen (nur der Argon2id-Hash wird gespeichert)
+    pub fn set_room_password(&self, password: &str) -> Result<(), SecurityError> {
+        if password.is_empty() {
+            return Err(SecurityError::ValidationError("Passwort darf nicht leer sein".to_string()));
+        }
+        
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| SecurityError::EncryptionError(format!("Passwort-Hashing fehlgeschlagen: {}", e)))?
+            .to_string();
+        
+        let mut config = self.config.lock().unwrap();
+        config.room_password_hash = Some(hash);
+        
+        // Neues Passwort setzt bestehende Sperren zurück
+        self.room_password_attempts.lock().unwrap().clear();
+        
+        Ok(())
+    }
+    
+    // Raumpasswort entfernen
+    pub fn clear_room_password(&self) {
+        let mut config = self.config.lock().unwrap();
+        config.room_password_hash = None;
+        
+        self.room_password_attempts.lock().unwrap().clear();
+    }
+    
+    // Prüfen, ob ein Raumpasswort gesetzt ist
+    pub fn has_room_password(&self) -> bool {
+        self.config.lock().unwrap().room_password_hash.is_some()
+    }
+    
+    // Raumpasswort eines Peers prüfen; bei Erfolg erhält der Peer die Standardrechte
+    pub fn verify_room_password(&self, peer_id: &str, attempt: &str) -> Result<bool, SecurityError> {
+        let now = Self::unix_now()?;
+        
+        let (hash, max_attempts, lockout_secs, default_rights) = {
+            let config = self.config.lock().unwrap();
+            let hash = config.room_password_hash.clone()
+                .ok_or_else(|| SecurityError::ConfigurationError("Kein Raumpasswort gesetzt".to_string()))?;
+            (hash, config.max_failed_attempts, config.lockout_minutes * 60, config.default_access_rights.clone())
+        };
+        
+        // Gesperrte Peers gar nicht erst prüfen
+        {
+            let attempts = self.room_password_attempts.lock().unwrap();
+            if let Some((_, locked_until)) = attempts.get(peer_id) {
+                if *locked_until > now {
+                    return Err(SecurityError::TooManyAttempts(locked_until - now));
+                }
+            }
+        }
+        
+        let parsed_hash = PasswordHash::new(&hash)
+            .map_err(|e| SecurityError::ConfigurationError(format!("Ungültiger Passwort-Hash: {}", e)))?;
+        
+        // verify_password vergleicht in konstanter Zeit
+        let verified = Argon2::default()
+            .verify_password(attempt.as_bytes(), &parsed_hash)
+            .is_ok();
+        
+        let mut attempts = self.room_password_attempts.lock().unwrap();
+        
+        if verified {
+            attempts.remove(peer_id);
+            drop(attempts);
+            
+            // Peer von "ausstehend" auf die Standardrechte anheben
+            let mut peer_rights = self.peer_access_rights.lock().unwrap();
+            let rights = peer_rights.entry(peer_id.to_string()).or_default();
+            for right in default_rights {
+                if !rights.contains(&right) {
+                    rights.push(right);
+                }
+            }
+            
+            return Ok(true);
+        }
+        
+        let entry = attempts.entry(peer_id.to_string()).or_insert((0, 0));
+        entry.0 += 1;
+        
+        if entry.0 >= max_attempts {
+            entry.0 = 0;
+            entry.1 = now + lockout_secs;
+            return Err(SecurityError::TooManyAttempts(lockout_secs));
+        }
+        
+        Ok(false)
+    }
+    
+    // Anzahl der Fehlversuche eines Peers seit der letzten Sperre
+    pub fn failed_room_password_attempts(&self, peer_id: &str) -> u32 {
+        self.room_password_attempts.lock().unwrap()
+            .get(peer_id)
+            .map(|(count, _)| *count)
+            .unwrap_or(0)
+    }
+    
+    // Zugriffsrechte eines Peers abrufen (leer = ausstehend)
+    pub fn get_peer_access_rights(&self, peer_id: &str) -> Vec<AccessRight> {
+        self.peer_access_rights.lock().unwrap()
+            .get(peer_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+    
+    // Fingerprint der eigenen Identität, zum Abgleich über einen zweiten Kanal
+    pub fn get_identity_fingerprint(&self) -> Result<String, SecurityError> {
+        Ok(self.own_identity()?.fingerprint())
+    }
+    
+    // Einmal-Nonce erzeugen, die der Peer mit seinem Identitätsschlüssel signieren muss
+    pub fn create_identity_challenge(&self, peer_id: &str) -> Result<IdentityChallenge, SecurityError> {
+        let now = Self::unix_now()?;
+        let nonce = general_purpose::URL_SAFE_NO_PAD.encode(thread_rng().gen::<[u8; 32]>());
+        let expires_at = now + CHALLENGE_TTL_SECS;
+        
+        let mut challenges = self.identity_challenges.lock().unwrap();
+        challenges.retain(|_, (_, valid_until)| *valid_until > now);
+        challenges.insert(peer_id.to_string(), (nonce.clone(), expires_at));
+        
+        Ok(IdentityChallenge { nonce, expires_at })
+    }
+    
+    // Identitätsprüfung eines Peers mit der eigenen Identität beantworten
+    pub fn answer_identity_challenge(&self, nonce: &str, device_name: &str) -> Result<IdentityProof, SecurityError> {
+        Ok(self.own_identity()?.sign_challenge(nonce, device_name))
+    }
+    
+    // Nachweis eines Peers prüfen. Vertrauenswürdige Peers erhalten ihre
+    // gespeicherten Rechte; ein bekannter Gerätename mit anderem Fingerprint
+    // wird abgelehnt.
+    pub fn verify_identity_proof(&self, peer_id: &str, proof: &IdentityProof) -> Result<VerifiedPeer, SecurityError> {
+        let now = Self::unix_now()?;
+        
+        // Jede Nonce gilt nur für einen Versuch
+        let nonce = match self.identity_challenges.lock().unwrap().remove(peer_id) {
+            Some((nonce, expires_at)) if expires_at > now => nonce,
+            Some(_) => return Err(SecurityError::AuthenticationFailed("Identitätsprüfung ist abgelaufen".to_string())),
+            None => return Err(SecurityError::AuthenticationFailed("Keine offene Identitätsprüfung für diesen Peer".to_string())),
+        };
+        let fingerprint = proof.verify(&nonce)?;
+        
+        let (trusted, default_rights) = {
+            let config = self.config.lock().unwrap();
+            if let Some(known) = config.trusted_peers.iter()
+                .find(|peer| peer.device_name == proof.device_name && peer.fingerprint != fingerprint)
+            {
+                let detail = format!("{} meldet sich mit {} statt {}", proof.device_name, fingerprint, known.fingerprint);
+                log::warn!(target: TARGET_SECURITY, "Peer {} presented a changed identity: {}", peer_id, detail);
+                if let Some(sink) = &self.audit_sink {
+                    sink.record(AuditEvent::new(AuditEventKind::PeerIdentityMismatch, Some(peer_id)).with_detail(detail.clone()));
+                }
+                return Err(SecurityError::IdentityMismatch(detail));
+            }
+            
+            let trusted = config.trusted_peers.iter().find(|peer| peer.fingerprint == fingerprint).cloned();
+            (trusted, config.default_access_rights.clone())
+        };
+        
+        self.peer_identities.lock().unwrap()
+            .insert(peer_id.to_string(), (fingerprint.clone(), proof.device_name.clone()));
+        
+        // Bekannte Peers müssen kein Raumpasswort mehr eingeben
+        if let Some(trusted) = &trusted {
+            let granted = if trusted.access_rights.is_empty() { default_rights } else { trusted.access_rights.clone() };
+            let mut peer_rights = self.peer_access_rights.lock().unwrap();
+            let rights = peer_rights.entry(peer_id.to_string()).or_default();
+            for right in granted {
+                if !rights.contains(&right) {
+                    rights.push(right);
+                }
+            }
+        }
+        
+        if let Some(sink) = &self.audit_sink {
+            let detail = match &trusted {
+                Some(trusted) => format!("{} (vertraut: {})", fingerprint, trusted.nickname.as_deref().unwrap_or(&trusted.device_name)),
+                None => fingerprint.clone(),
+            };
+            sink.record(AuditEvent::new(AuditEventKind::PeerVerified, Some(peer_id)).with_detail(detail));
+        }
+        
+        Ok(VerifiedPeer {
+            peer_id: peer_id.to_string(),
+            fingerprint,
+            device_name: proof.device_name.clone(),
+            trusted,
+        })
+    }
+    
+    // Geprüfter Fingerprint eines verbundenen Peers
+    pub fn get_peer_fingerprint(&self, peer_id: &str) -> Option<String> {
+        self.peer_identities.lock().unwrap()
+            .get(peer_id)
+            .map(|(fingerprint, _)| fingerprint.clone())
+    }
+    
+    // Eintrag eines verbundenen Peers in der Vertrauensliste; Regeln hängen
+    // so am Fingerprint statt an der wechselnden Signalisierungs-ID
+    pub fn trusted_peer_for(&self, peer_id: &str) -> Option<TrustedPeer> {
+        let fingerprint = self.get_peer_fingerprint(peer_id)?;
+        self.config.lock().unwrap().trusted_peers.iter()
+            .find(|peer| peer.fingerprint == fingerprint)
+            .cloned()
+    }
+    
+    pub fn trusted_peers(&self) -> Vec<TrustedPeer> {
+        self.config.lock().unwrap().trusted_peers.clone()
+    }
+    
+    // Geprüften Peer in die Vertrauensliste aufnehmen; ersetzt frühere Einträge
+    // mit demselben Fingerprint oder Gerätenamen (z.B. nach einer Neuinstallation)
+    pub fn trust_peer(&self, peer_id: &str, nickname: Option<String>, access_rights: Vec<AccessRight>) -> Result<TrustedPeer, SecurityError> {
+        let (fingerprint, device_name) = self.peer_identities.lock().unwrap()
+            .get(peer_id)
+            .cloned()
+            .ok_or_else(|| SecurityError::ValidationError(format!("Identität von Peer {} wurde nicht geprüft", peer_id)))?;
+        
+        let trusted = TrustedPeer {
+            fingerprint,
+            device_name,
+            nickname,
+            access_rights,
+            trusted_at: Self::unix_now()?,
+        };
+        
+        let mut config = self.config.lock().unwrap();
+        config.trusted_peers.retain(|peer| peer.fingerprint != trusted.fingerprint && peer.device_name != trusted.device_name);
+        config.trusted_peers.push(trusted.clone());
+        
+        Ok(trusted)
+    }
+    
+    // Peer aus der Vertrauensliste entfernen; verbundene Peers mit diesem
+    // Fingerprint verlieren ihre Rechte
+    pub fn revoke_peer(&self, fingerprint: &str) -> Result<(), SecurityError> {
+        {
+            let mut config = self.config.lock().unwrap();
+            let count = config.trusted_peers.len();
+            config.trusted_peers.retain(|peer| peer.fingerprint != fingerprint);
+            if config.trusted_peers.len() == count {
+                return Err(SecurityError::ValidationError(format!("Kein vertrauenswürdiger Peer mit Fingerprint {}", fingerprint)));
+            }
+        }
+        
+        let peer_ids: Vec<String> = self.peer_identities.lock().unwrap().iter()
+            .filter(|(_, (peer_fingerprint, _))| peer_fingerprint == fingerprint)
+            .map(|(peer_id, _)| peer_id.clone())
+            .collect();
+        let mut peer_rights = self.peer_access_rights.lock().unwrap();
+        for peer_id in peer_ids {
+            peer_rights.remove(&peer_id);
+        }
+        
+        Ok(())
+    }
+    
+    // Schlüsselaustausch für die Zwischenablage beginnen; liefert den eigenen
+    // öffentlichen Schlüssel. Ein neuer Austausch (Reconnect) ersetzt den alten Schlüssel.
+    pub fn begin_clipboard_key_exchange(&self, peer_id: &str) -> String {
+        self.clipboard_keys.begin_exchange(peer_id)
+    }
+    
+    // Schlüsselaustausch mit dem öffentlichen Schlüssel des Peers abschließen
+    pub fn complete_clipboard_key_exchange(&self, peer_id: &str, peer_public_key: &str) -> Result<(), SecurityError> {
+        let key_id = self.clipboard_keys.complete_exchange(peer_id, peer_public_key)?;
+        log::info!(target: TARGET_SECURITY, "Clipboard key {} established with peer {}", key_id, peer_id);
+        Ok(())
+    }
+    
+    // Schlüssel eines getrennten Peers verwerfen
+    pub fn forget_clipboard_key(&self, peer_id: &str) {
+        self.clipboard_keys.forget(peer_id);
+    }
+    
+    pub fn has_clipboard_key(&self, peer_id: &str) -> bool {
+        self.clipboard_keys.has_key(peer_id)
+    }
+    
+    // Peers, an die Zwischenablage-Einträge nur verschlüsselt gehen
+    pub fn clipboard_key_peers(&self) -> Vec<String> {
+        self.clipboard_keys.peers()
+    }
+    
+    // Serialisierten Sync-Eintrag für einen Peer versiegeln
+    pub fn encrypt_sync_entry(&self, peer_id: &str, payload: &str) -> Result<SealedSyncEntry, SecurityError> {
+        self.clipboard_keys.seal(peer_id, payload, chrono::Utc::now().timestamp_millis())
+    }
+    
+    // Umschlag eines Peers öffnen; liefert den Sync-Eintrag und seine authentifizierte Sendezeit
+    pub fn decrypt_sync_entry(&self, peer_id: &str, sealed: &SealedSyncEntry) -> Result<(String, chrono::DateTime<chrono::Utc>), SecurityError> {
+        let payload = self.clipboard_keys.open(peer_id, sealed)?;
+        let sent_at = chrono::DateTime::from_timestamp_millis(sealed.sent_at_ms)
+            .ok_or_else(|| SecurityError::ValidationError("Ungültiger Sendezeitpunkt".to_string()))?;
+        Ok((payload, sent_at))
+    }
+    
+    fn own_identity(&self) -> Result<&DeviceIdentity, SecurityError> {
+        self.identity.as_ref()
+            .ok_or_else(|| SecurityError::ConfigurationError("Keine Geräteidentität geladen".to_string()))
+    }
+    
+    // Signatur eines Zugangstokens prüfen und erst danach die Claims dekodieren
+    fn verify_access_token(&self, token: &str, now: u64) -> Result<AccessTokenClaims, SecurityError> {
+        let (payload, signature) = token.split_once('.')
+            .ok_or_else(|| SecurityError::TokenInvalid("Ungültiges Token-Format".to_string()))?;
+        
+        let signature_bytes = general_purpose::URL_SAFE_NO_PAD.decode(signature)
+            .map_err(|e| SecurityError::TokenInvalid(format!("Ungültige Signatur-Kodierung: {}", e)))?;
+        
+        // Aktueller Schlüssel zuerst, der vorherige nur während seiner Übergangszeit
+        let key_id = {
+            let keys = self.token_keys.lock().unwrap();
+            let previous = keys.previous.as_ref()
+                .filter(|(_, valid_until)| now < *valid_until)
+                .map(|(previous, _)| previous);
+            let mut signed_with = None;
+            for key in std::iter::once(&keys.current).chain(previous) {
+                if Self::token_signature_matches(&key.key, payload, &signature_bytes)? {
+                    signed_with = Some(key.id);
+                    break;
+                }
+            }
+            signed_with.ok_or_else(|| SecurityError::TokenInvalid("Signaturverifizierung fehlgeschlagen".to_string()))?
+        };
+        
+        let claims: AccessTokenClaims = general_purpose::URL_SAFE_NO_PAD.decode(payload)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| SecurityError::TokenInvalid("Ungültiger Token-Inhalt".to_string()))?;
+        
+        if claims.key_id != key_id {
+            return Err(SecurityError::TokenInvalid("Key-ID passt nicht zum Signaturschlüssel".to_string()));
+        }
+        
+        Ok(claims)
+    }
+    
+    fn token_signature_matches(key: &[u8], payload: &str, signature: &[u8]) -> Result<bool, SecurityError> {
+        let mut mac = HmacSha256::new_from_slice(key)
+            .map_err(|e| SecurityError::EncryptionError(format!("HMAC-Initialisierungsfehler: {}", e)))?;
+        mac.update(payload.as_bytes());
+        Ok(mac.verify_slice(signature).is_ok())
+    }
+    
+    // Token-Payload mit HMAC-SHA256 signieren
+    fn sign_token_payload(key: &[u8], payload: &str) -> Result<String, SecurityError> {
+        let mut mac = HmacSha256::new_from_slice(key)
+            .map_err(|e| SecurityError::EncryptionError(format!("HMAC-Initialisierungsfehler: {}", e)))?;
+        mac.update(payload.as_bytes());
+        
+        Ok(general_purpose::URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes()))
+    }
+    
+    // Aktuelle Unix-Zeit in Sekunden
+    fn unix_now() -> Result<u64, SecurityError> {
+        Ok(SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| SecurityError::ConfigurationError(format!("Systemzeit-Fehler: {}", e)))?
+            .as_secs())
+    }
+}
+
+// OAuth2 PKCE-Authentifizierung
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthConfig {
+    pub client_id: String,
+    pub auth_url: String,
+    pub token_url: String,
+    pub redirect_uri: String,
+    pub scope: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PKCEParams {
+    pub code_verifier: String,
+    pub code_challenge: String,
+    pub state: String,
+}
+
+pub struct OAuth2Manager {
+    config: OAuthConfig,
+}
+
+impl OAuth2Manager {
+    pub fn new(config: OAuthConfig) -> Self {
+        OAuth2Manager {
+            config,
+        }
+    }
+    
+    // PKCE-Parameter generieren
+    pub fn generate_pkce_params(&self) -> Result<PKCEParams, SecurityError> {
+        // Code-Verifier generieren (min. 43 Zeichen, max. 128 Zeichen)
+        let code_verifier: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(64)
+            .map(char::from)
+            .collect();
+        
+        // Code-Challenge aus Code-Verifier erstellen (SHA256)
+        let mut hasher = Sha256::new();
+        hasher.update(code_verifier.as_bytes());
+        let challenge_bytes = hasher.finalize();
+        let code_challenge = general_purpose::URL_SAFE_NO_PAD.encode(challenge_bytes);
+        
+        // State generieren (CSRF-Schutz)
+        let state: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        
+        Ok(PKCEParams {
+            code_verifier,
+            code_challenge,
+            state,
+        })
+    }
+    
+    // Autorisierungs-URL erstellen
+    pub fn build_authorization_url(&self, pkce_params: &PKCEParams) -> String {
+        format!(
+            "{}?response_type=code&client_id={}&redirect_uri={}&scope={}&code_challenge={}&code_challenge_method=S256&state={}",
+            self.config.auth_url,
+            self.config.client_id,
+            urlencoding::encode(&self.config.redirect_uri),
+            urlencoding::encode(&self.config.scope),
+            pkce_params.code_challenge,
+            pkce_params.state
+        )
+    }
+    
+    // Token-Request erstellen
+    pub fn build_token_request(&self, authorization_code: &str, code_verifier: &str) -> String {
+        format!(
+            "grant_type=authorization_code&client_id={}&code={}&redirect_uri={}&code_verifier={}",
+            self.config.client_id,
+            authorization_code,
+            urlencoding::encode(&self.config.redirect_uri),
+            code_verifier
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager() -> ConnectionSecurityManager {
+        ConnectionSecurityManager::new("test-secret-key-with-enough-length!", ConnectionSecurityConfig::default())
+    }
+
+    fn peer() -> PeerInfo {
+        PeerInfo {
+            peer_id: "peer-1".to_string(),
+            display_name: Some("Viewer".to_string()),
+            access_rights: vec![AccessRight::ViewOnly],
+        }
+    }
+
+    #[test]
+    fn test_rate_limit_suspension_is_audited() {
+        use audit::{AuditConfig, AuditLog};
+
+        let log = Arc::new(AuditLog::in_memory(AuditConfig::default()));
+        let mut manager = manager();
+        manager.set_audit_sink(log.clone());
+
+        let decisions: Vec<RateLimitDecision> = (0..51)
+            .map(|_| manager.check_rate_limit(Some("peer-1"), RateLimitedCommand::Clipboard))
+            .collect();
+        assert!(decisions[..5].iter().all(RateLimitDecision::is_allowed));
+        assert!(matches!(decisions[50], RateLimitDecision::Suspended(Some(_))));
+
+        let entries = log.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].event.kind, AuditEventKind::PeerSuspended);
+        assert_eq!(entries[0].event.peer_id.as_deref(), Some("peer-1"));
+    }
+
+    #[test]
+    fn test_rate_limits_follow_config_updates() {
+        let manager = manager();
+        manager.update_config(ConnectionSecurityConfig {
+            rate_limits: RateLimitConfig { enabled: false, ..Default::default() },
+            ..Default::default()
+        });
+        assert!((0..100).all(|_| manager.check_rate_limit(Some("peer-1"), RateLimitedCommand::Clipboard).is_allowed()));
+    }
+
+    #[test]
+    fn test_commands_without_peer_id_share_one_limit() {
+        let manager = manager();
+        let input = RateLimitConfig::default().input_events_per_sec as usize;
+
+        assert!((0..input).all(|_| manager.check_rate_limit(None, RateLimitedCommand::Input).is_allowed()));
+        assert!(!manager.check_rate_limit(None, RateLimitedCommand::Input).is_allowed());
+        assert!(!manager.check_rate_limit(Some(UNIDENTIFIED_PEER), RateLimitedCommand::Input).is_allowed());
+        assert!(manager.check_rate_limit(Some("peer-1"), RateLimitedCommand::Input).is_allowed());
+        assert!(manager.check_rate_limit(None, RateLimitedCommand::Clipboard).is_allowed());
+    }
+
+    #[test]
+    fn test_custom_commands_off_by_default() {
+        let manager = manager();
+        assert!(!manager.custom_commands_allowed());
+
+        manager.set_custom_commands_allowed(true);
+        assert!(manager.custom_commands_allowed());
+    }
+
+    #[test]
+    fn test_access_token_roundtrip() {
+        let manager = manager();
+        let token = manager.generate_access_token(peer(), Duration::from_secs(60)).unwrap();
+
+        let claims = manager.validate_access_token(&token).unwrap();
+        assert_eq!(claims.peer.peer_id, "peer-1");
+    }
+
+    #[test]
+    fn test_expired_access_token() {
+        let manager = manager();
+        let token = manager.generate_access_token(peer(), Duration::from_secs(0)).unwrap();
+
+        assert!(matches!(manager.validate_access_token(&token), Err(SecurityError::TokenExpired(_))));
+    }
+
+    #[test]
+    fn test_tampered_access_token() {
+        let manager = manager();
+        let token = manager.generate_access_token(peer(), Duration::from_secs(60)).unwrap();
+
+        // Payload eines anderen Tokens mit fremder Signatur kombinieren
+        let other = manager.generate_access_token(peer(), Duration::from_secs(3600)).unwrap();
+        let (payload, _) = other.split_once('.').unwrap();
+        let (_, signature) = token.split_once('.').unwrap();
+        let forged = format!("{}.{}", payload, signature);
+
+        assert!(matches!(manager.validate_access_token(&forged), Err(SecurityError::TokenInvalid(_))));
+
+        // Token eines anderen Managers (anderer Schlüssel)
+        let foreign = ConnectionSecurityManager::new("another-secret-key-with-enough-length", ConnectionSecurityConfig::default())
+            .generate_access_token(peer(), Duration::from_secs(60))
+            .unwrap();
+        assert!(matches!(manager.validate_access_token(&foreign), Err(SecurityError::TokenInvalid(_))));
+    }
+
+    #[test]
+    fn test_unsigned_payload_is_rejected_before_decoding() {
+        let manager = manager();
+        let token = manager.generate_access_token(peer(), Duration::from_secs(60)).unwrap();
+        let (_, signature) = token.split_once('.').unwrap();
+
+        let garbage = format!("{}.{}", general_purpose::URL_SAFE_NO_PAD.encode(b"{not json"), signature);
+        match manager.validate_access_token(&garbage) {
+            Err(SecurityError::TokenInvalid(msg)) => assert_eq!(msg, "Signaturverifizierung fehlgeschlagen"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_replayed_access_token() {
+        let manager = manager();
+        let token = manager.generate_access_token(peer(), Duration::from_secs(60)).unwrap();
+
+        manager.validate_access_token(&token).unwrap();
+        assert!(matches!(manager.validate_access_token(&token), Err(SecurityError::TokenReplayed(_))));
+    }
+
+    #[test]
+    fn test_revoked_access_token() {
+        let manager = manager();
+        let token = manager.generate_access_token(peer(), Duration::from_secs(60)).unwrap();
+
+        manager.revoke_token(&token).unwrap();
+        assert!(matches!(manager.validate_access_token(&token), Err(SecurityError::TokenRevoked(_))));
+    }
+
+    #[test]
+    fn test_room_password_grants_view_rights() {
+        let manager = manager();
+        manager.set_room_password("correct horse").unwrap();
+
+        assert!(manager.get_peer_access_rights("peer-1").is_empty());
+        assert!(!manager.verify_room_password("peer-1", "wrong").unwrap());
+        assert!(manager.verify_room_password("peer-1", "correct horse").unwrap());
+        assert_eq!(manager.get_peer_access_rights("peer-1"), vec![AccessRight::ViewOnly]);
+
+        // Nur der Hash wird gespeichert
+        let hash = manager.config.lock().unwrap().room_password_hash.clone().unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(!hash.contains("correct horse"));
+    }
+
+    #[test]
+    fn test_room_password_lockout() {
+        let manager = manager();
+        manager.set_room_password("secret").unwrap();
+
+        for _ in 0..4 {
+            assert!(!manager.verify_room_password("attacker", "guess").unwrap());
+        }
+        assert!(matches!(manager.verify_room_password("attacker", "guess"), Err(SecurityError::TooManyAttempts(_))));
+
+        // Auch das richtige Passwort wird während der Sperre abgelehnt
+        assert!(matches!(manager.verify_room_password("attacker", "secret"), Err(SecurityError::TooManyAttempts(_))));
+
+        // Andere Peers sind nicht betroffen
+        assert!(manager.verify_room_password("peer-1", "secret").unwrap());
+    }
+
+    #[test]
+    fn test_key_rotation_grace_period() {
+        let manager = manager();
+        let old_token = manager.generate_access_token(peer(), Duration::from_secs(60)).unwrap();
+        let expired_key_token = manager.generate_access_token(peer(), Duration::from_secs(60)).unwrap();
+
+        manager.rotate_token_key(Duration::from_secs(60)).unwrap();
+        assert!(manager.validate_access_token(&old_token).is_ok());
+
+        // Ohne Übergangszeit ist der alte Schlüssel sofort ungültig
+        manager.rotate_token_key(Duration::from_secs(0)).unwrap();
+        assert!(matches!(manager.validate_access_token(&expired_key_token), Err(SecurityError::TokenInvalid(_))));
+
+        let new_token = manager.generate_access_token(peer(), Duration::from_secs(60)).unwrap();
+        assert!(manager.validate_access_token(&new_token).is_ok());
+    }
+
+    #[test]
+    fn test_trusted_peer_identity_flow() {
+        let host = manager();
+        let mut viewer = manager();
+        viewer.set_identity(DeviceIdentity::generate());
+
+        // Unbekannte Peers sind geprüft, erhalten aber keine Rechte
+        let challenge = host.create_identity_challenge("peer-1").unwrap();
+        let proof = viewer.answer_identity_challenge(&challenge.nonce, "laptop").unwrap();
+        let verified = host.verify_identity_proof("peer-1", &proof).unwrap();
+        assert_eq!(verified.fingerprint, viewer.get_identity_fingerprint().unwrap());
+        assert!(verified.trusted.is_none());
+        assert!(host.get_peer_access_rights("peer-1").is_empty());
+
+        let trusted = host.trust_peer("peer-1", Some("Büro".to_string()), Vec::new()).unwrap();
+        assert_eq!(host.trusted_peer_for("peer-1"), Some(trusted));
+
+        // Beim nächsten Verbindungsaufbau unter neuer ID genügt der Schlüssel
+        let challenge = host.create_identity_challenge("peer-2").unwrap();
+        let proof = viewer.answer_identity_challenge(&challenge.nonce, "laptop").unwrap();
+        assert!(host.verify_identity_proof("peer-2", &proof).unwrap().trusted.is_some());
+        assert_eq!(host.get_peer_access_rights("peer-2"), ConnectionSecurityConfig::default().default_access_rights);
+
+        host.revoke_peer(&verified.fingerprint).unwrap();
+        assert!(host.get_peer_access_rights("peer-2").is_empty());
+        assert!(host.trusted_peers().is_empty());
+    }
+
+    #[test]
+    fn test_changed_identity_is_rejected() {
+        let host = manager();
+        let mut viewer = manager();
+        viewer.set_identity(DeviceIdentity::generate());
+
+        let challenge = host.create_identity_challenge("peer-1").unwrap();
+        let proof = viewer.answer_identity_challenge(&challenge.nonce, "laptop").unwrap();
+        host.verify_identity_proof("peer-1", &proof).unwrap();
+        host.trust_peer("peer-1", None, Vec::new()).unwrap();
+
+        // Gleicher Gerätename, aber neuer Schlüssel
+        let mut impostor = manager();
+        impostor.set_identity(DeviceIdentity::generate());
+        let challenge = host.create_identity_challenge("peer-2").unwrap();
+        let proof = impostor.answer_identity_challenge(&challenge.nonce, "laptop").unwrap();
+        assert!(matches!(host.verify_identity_proof("peer-2", &proof), Err(SecurityError::IdentityMismatch(_))));
+        assert!(host.get_peer_access_rights("peer-2").is_empty());
+    }
+
+    #[test]
+    fn test_identity_challenge_is_single_use() {
+        let host = manager();
+        let mut viewer = manager();
+        viewer.set_identity(DeviceIdentity::generate());
+
+        let challenge = host.create_identity_challenge("peer-1").unwrap();
+        let proof = viewer.answer_identity_challenge(&challenge.nonce, "laptop").unwrap();
+        host.verify_identity_proof("peer-1", &proof).unwrap();
+        assert!(matches!(host.verify_identity_proof("peer-1", &proof), Err(SecurityError::AuthenticationFailed(_))));
+
+        // Signaturen über eine fremde Nonce werden abgelehnt
+        host.create_identity_challenge("peer-1").unwrap();
+        assert!(matches!(host.verify_identity_proof("peer-1", &proof), Err(SecurityError::AuthenticationFailed(_))));
+    }
+}
@@ -0,0 +1,304 @@
+// src-tauri/src/connection_security/rate_limit.rs - Ratenbegrenzung für Befehle von Peers
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+// Verworfene Befehle werden je Peer und Befehl höchstens in diesem Abstand gemeldet
+pub const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+// Zeitfenster, in dem eine grobe Überschreitung zur Sperre führt
+const SUSPEND_WINDOW: Duration = Duration::from_secs(1);
+
+// Befehle, die ein Peer auslösen kann und die auf dem Host Prozesse starten
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitedCommand {
+    Input,
+    Clipboard,
+}
+
+// Grenzwerte je Peer; 0 hebt die Grenze für einen Befehl auf
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub input_events_per_sec: u32,
+    pub clipboard_writes_per_sec: u32,
+    pub suspend_factor: u32,   // Vielfaches der Grenze, ab dem der Peer gesperrt wird (0 = nie)
+    pub suspend_seconds: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            enabled: true,
+            input_events_per_sec: 500,
+            clipboard_writes_per_sec: 5,
+            suspend_factor: 10,
+            suspend_seconds: 60,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    fn rate(&self, command: RateLimitedCommand) -> u32 {
+        match command {
+            RateLimitedCommand::Input => self.input_events_per_sec,
+            RateLimitedCommand::Clipboard => self.clipboard_writes_per_sec,
+        }
+    }
+}
+
+// Meldung über verworfene Befehle, für das Event `rate_limited`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RateLimitReport {
+    pub peer_id: String,
+    pub command: RateLimitedCommand,
+    pub dropped: u64,                  // Seit der letzten Meldung verworfen
+    pub suspended_secs: Option<u64>,   // Gesetzt, wenn der Peer gerade gesperrt wurde
+}
+
+// Ergebnis der Prüfung eines Befehls
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateLimitDecision {
+    Allowed,
+    Dropped(Option<RateLimitReport>),   // Meldung nur, wenn eine fällig ist
+    Suspended(Option<RateLimitReport>), // Meldung nur beim Übergang in die Sperre
+}
+
+impl RateLimitDecision {
+    pub fn is_allowed(&self) -> bool {
+        *self == RateLimitDecision::Allowed
+    }
+
+    pub fn report(&self) -> Option<&RateLimitReport> {
+        match self {
+            RateLimitDecision::Allowed => None,
+            RateLimitDecision::Dropped(report) | RateLimitDecision::Suspended(report) => report.as_ref(),
+        }
+    }
+}
+
+// Token-Bucket, der eine Sekunde an Befehlen als Burst erlaubt
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn full(rate: f64, now: Instant) -> Self {
+        TokenBucket { tokens: rate, last_refill: now }
+    }
+
+    fn try_take(&mut self, rate: f64, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(rate);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// Zustand eines Befehls für einen Peer
+#[derive(Debug)]
+struct CommandState {
+    bucket: TokenBucket,
+    window_start: Instant,
+    window_attempts: u64,
+    unreported: u64,
+    last_report: Option<Instant>,
+}
+
+#[derive(Debug, Default)]
+struct PeerState {
+    commands: HashMap<RateLimitedCommand, CommandState>,
+    suspended_until: Option<Instant>,
+}
+
+// Begrenzt Befehle je Peer und sperrt Peers, die die Grenze grob überschreiten
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    peers: HashMap<String, PeerState>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        RateLimiter { config, peers: HashMap::new() }
+    }
+
+    // Neue Grenzen gelten ab dem nächsten Befehl; laufende Sperren bleiben bestehen
+    pub fn set_config(&mut self, config: RateLimitConfig) {
+        self.config = config;
+    }
+
+    // Prüft einen Befehl eines Peers zum Zeitpunkt `now`
+    pub fn check(&mut self, peer_id: &str, command: RateLimitedCommand, now: Instant) -> RateLimitDecision {
+        let rate = self.config.rate(command) as f64;
+        if !self.config.enabled || rate == 0.0 {
+            return RateLimitDecision::Allowed;
+        }
+
+        let peer = self.peers.entry(peer_id.to_string()).or_default();
+
+        // Nach Ablauf der Sperre beginnt der Peer mit vollen Buckets
+        if let Some(until) = peer.suspended_until {
+            if now < until {
+                return RateLimitDecision::Suspended(None);
+            }
+            peer.suspended_until = None;
+            peer.commands.clear();
+        }
+
+        let state = peer.commands.entry(command).or_insert_with(|| CommandState {
+            bucket: TokenBucket::full(rate, now),
+            window_start: now,
+            window_attempts: 0,
+            unreported: 0,
+            last_report: None,
+        });
+
+        if now.saturating_duration_since(state.window_start) >= SUSPEND_WINDOW {
+            state.window_start = now;
+            state.window_attempts = 0;
+        }
+        state.window_attempts += 1;
+
+        let suspend_factor = self.config.suspend_factor as f64;
+        if suspend_factor > 0.0 && state.window_attempts as f64 > rate * suspend_factor {
+            let report = RateLimitReport {
+                peer_id: peer_id.to_string(),
+                command,
+                dropped: state.unreported + 1,
+                suspended_secs: Some(self.config.suspend_seconds),
+            };
+            peer.suspended_until = Some(now + Duration::from_secs(self.config.suspend_seconds));
+            return RateLimitDecision::Suspended(Some(report));
+        }
+
+        if state.bucket.try_take(rate, now) {
+            return RateLimitDecision::Allowed;
+        }
+
+        state.unreported += 1;
+        let report_due = match state.last_report {
+            Some(last) => now.saturating_duration_since(last) >= REPORT_INTERVAL,
+            None => true,
+        };
+        if !report_due {
+            return RateLimitDecision::Dropped(None);
+        }
+
+        state.last_report = Some(now);
+        let dropped = std::mem::take(&mut state.unreported);
+        RateLimitDecision::Dropped(Some(RateLimitReport {
+            peer_id: peer_id.to_string(),
+            command,
+            dropped,
+            suspended_secs: None,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimitConfig::default())
+    }
+
+    fn at(start: Instant, millis: u64) -> Instant {
+        start + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_burst_up_to_one_second_is_allowed() {
+        let mut limiter = limiter();
+        let start = Instant::now();
+
+        for _ in 0..5 {
+            assert!(limiter.check("peer", RateLimitedCommand::Clipboard, start).is_allowed());
+        }
+        let decision = limiter.check("peer", RateLimitedCommand::Clipboard, start);
+        assert_eq!(decision.report().map(|report| report.dropped), Some(1));
+
+        // Andere Peers und Befehle haben eigene Buckets
+        assert!(limiter.check("other", RateLimitedCommand::Clipboard, start).is_allowed());
+        assert!(limiter.check("peer", RateLimitedCommand::Input, start).is_allowed());
+    }
+
+    #[test]
+    fn test_sustained_rate_at_limit_is_allowed() {
+        let mut limiter = limiter();
+        let start = Instant::now();
+
+        // 500 Eingaben pro Sekunde über zehn Sekunden
+        for tick in 0..5000 {
+            assert!(limiter.check("peer", RateLimitedCommand::Input, at(start, tick * 2)).is_allowed(), "tick {}", tick);
+        }
+    }
+
+    #[test]
+    fn test_sustained_overload_is_dropped_and_reported_once_per_second() {
+        let mut limiter = limiter();
+        let start = Instant::now();
+
+        // Doppelte Rate: nach dem Burst wird etwa jede zweite Eingabe verworfen
+        let mut allowed = 0;
+        let mut reports = Vec::new();
+        for tick in 0..10_000 {
+            match limiter.check("peer", RateLimitedCommand::Input, at(start, tick)) {
+                RateLimitDecision::Allowed => allowed += 1,
+                RateLimitDecision::Dropped(report) => reports.extend(report),
+                RateLimitDecision::Suspended(_) => panic!("suspended at tick {}", tick),
+            }
+        }
+
+        assert!((5000..=5600).contains(&allowed), "allowed {}", allowed);
+        assert!((8..=10).contains(&reports.len()), "reports {}", reports.len());
+        let dropped: u64 = reports.iter().map(|report| report.dropped).sum();
+        assert!(dropped <= 10_000 - allowed);
+    }
+
+    #[test]
+    fn test_tenfold_overload_suspends_peer() {
+        let mut limiter = limiter();
+        let start = Instant::now();
+
+        // 51 Schreibvorgänge in derselben Sekunde überschreiten 10 x 5
+        let mut suspension = None;
+        for write in 0..51 {
+            if let RateLimitDecision::Suspended(report) = limiter.check("peer", RateLimitedCommand::Clipboard, start) {
+                suspension = Some((write, report));
+                break;
+            }
+        }
+        let (write, report) = suspension.unwrap();
+        assert_eq!(write, 50);
+        assert_eq!(report.unwrap().suspended_secs, Some(60));
+
+        // Gesperrt für alle Befehle, ohne erneute Meldung
+        assert_eq!(limiter.check("peer", RateLimitedCommand::Input, at(start, 30_000)), RateLimitDecision::Suspended(None));
+
+        // Nach Ablauf der Sperre wieder mit vollem Burst
+        assert!(limiter.check("peer", RateLimitedCommand::Clipboard, at(start, 60_000)).is_allowed());
+    }
+
+    #[test]
+    fn test_disabled_or_unlimited_allows_everything() {
+        let mut limiter = RateLimiter::new(RateLimitConfig { enabled: false, ..Default::default() });
+        let start = Instant::now();
+        assert!((0..1000).all(|_| limiter.check("peer", RateLimitedCommand::Clipboard, start).is_allowed()));
+
+        limiter.set_config(RateLimitConfig { clipboard_writes_per_sec: 0, ..Default::default() });
+        assert!((0..1000).all(|_| limiter.check("peer", RateLimitedCommand::Clipboard, start).is_allowed()));
+    }
+}
@@ -0,0 +1,23 @@
+// src-tauri/src/constant_time.rs - Comparing secrets without a timing side channel
+//
+// Tokens, MACs and hash chains are compared here, so that an attacker timing
+// the answers cannot learn how much of a guess was right.
+
+/// Compares secrets without revealing through timing where they differ.
+/// Only the length is not hidden.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(constant_time_eq(b"", b""));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret!"));
+    }
+}
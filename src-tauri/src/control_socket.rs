@@ -0,0 +1,370 @@
+// src-tauri/src/control_socket.rs - Local control socket for scripting a running instance
+//
+// The `smoldesk` subcommands (see `cli`) reach the running app through a Unix
+// socket in the user's runtime directory. A connection carries one JSON request
+// line and gets one JSON response line back. Only the user running the app gets
+// in: the directory is private to them, the server checks the credentials of
+// every connecting process, and each request must carry the token the server
+// writes next to the socket on startup. Both files are removed on shutdown.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use nix::sys::socket::{getsockopt, sockopt::PeerCredentials};
+use nix::unistd::getuid;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::command_error::{CommandError, ErrorKind as CommandErrorKind};
+use crate::constant_time::constant_time_eq;
+use crate::protocol::{self, ProtocolError, WireSubsystem, PROTOCOL_VERSION};
+
+/// Socket file inside the control directory
+pub const SOCKET_FILE: &str = "control.sock";
+
+/// Token file inside the control directory, readable only by its owner
+pub const TOKEN_FILE: &str = "control.token";
+
+/// How long the accept loop sleeps when no client is waiting
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A stuck client must not block the next one; sending a file hashes it first
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Upper bound for a request line; clipboard text is the largest payload
+const MAX_REQUEST_LEN: u64 = 4 * 1024 * 1024;
+
+const TOKEN_LEN: usize = 32;
+
+/// What a script asks the running instance to do
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum ControlCommand {
+    /// `path` must be absolute; the app does not share the caller's working directory
+    SendFile { path: PathBuf, peer_id: String },
+    SetClipboardText { text: String },
+    Status,
+    ListTransfers,
+}
+
+/// One request line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ControlRequest {
+    #[serde(default)]
+    pub protocol_version: u32,
+    pub token: String,
+    pub command: ControlCommand,
+}
+
+/// One response line: `result` on success, otherwise `error` as `{ kind, message, remediation }`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ControlResponse {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<CommandError>,
+}
+
+impl From<Result<Value, CommandError>> for ControlResponse {
+    fn from(result: Result<Value, CommandError>) -> Self {
+        match result {
+            Ok(result) => ControlResponse { ok: true, result: Some(result), error: None },
+            Err(error) => ControlResponse { ok: false, result: None, error: Some(error) },
+        }
+    }
+}
+
+/// Runs a command against the managers of the app
+pub type ControlHandler = Arc<dyn Fn(ControlCommand) -> Result<Value, CommandError> + Send + Sync>;
+
+/// `$XDG_RUNTIME_DIR/smoldesk`, or a per-user directory in the temp dir
+pub fn default_dir() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime_dir) if !runtime_dir.is_empty() => PathBuf::from(runtime_dir).join("smoldesk"),
+        _ => std::env::temp_dir().join(format!("smoldesk-{}", getuid())),
+    }
+}
+
+/// Unix socket listener dispatching requests to a `ControlHandler`
+pub struct ControlServer {
+    dir: PathBuf,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ControlServer {
+    /// Creates the private control directory, writes a fresh token and starts the
+    /// accept thread. Fails if another instance is already listening.
+    pub fn start(dir: &Path, handler: ControlHandler) -> io::Result<Self> {
+        prepare_dir(dir)?;
+
+        let socket_path = dir.join(SOCKET_FILE);
+        if socket_path.exists() {
+            if UnixStream::connect(&socket_path).is_ok() {
+                return Err(io::Error::new(ErrorKind::AddrInUse, "another instance is listening on the control socket"));
+            }
+            // Left behind by a crashed instance
+            fs::remove_file(&socket_path)?;
+        }
+
+        let token: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(TOKEN_LEN)
+            .map(char::from)
+            .collect();
+        let token_path = dir.join(TOKEN_FILE);
+        let _ = fs::remove_file(&token_path);
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&token_path)?
+            .write_all(token.as_bytes())?;
+
+        let listener = UnixListener::bind(&socket_path)?;
+        fs::set_permissions(&socket_path, fs::Permissions::from_mode(0o600))?;
+        listener.set_nonblocking(true)?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = running.clone();
+        let thread = thread::spawn(move || Self::accept_loop(listener, thread_running, token, handler));
+
+        log::info!("Control socket listening on {}", socket_path.display());
+
+        Ok(ControlServer {
+            dir: dir.to_path_buf(),
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn socket_path(&self) -> PathBuf {
+        self.dir.join(SOCKET_FILE)
+    }
+
+    /// Stops accepting requests, waits for the accept thread and removes socket and token
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+            let _ = fs::remove_file(self.dir.join(SOCKET_FILE));
+            let _ = fs::remove_file(self.dir.join(TOKEN_FILE));
+        }
+    }
+
+    fn accept_loop(listener: UnixListener, running: Arc<AtomicBool>, token: String, handler: ControlHandler) {
+        while running.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(e) = Self::serve(stream, &token, &handler) {
+                        log::debug!("Control request failed: {}", e);
+                    }
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL_INTERVAL),
+                Err(e) => {
+                    log::warn!("Control socket accept failed: {}", e);
+                    thread::sleep(ACCEPT_POLL_INTERVAL);
+                },
+            }
+        }
+    }
+
+    fn serve(mut stream: UnixStream, token: &str, handler: &ControlHandler) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+        stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+
+        // The directory permissions already keep others out; this also covers
+        // a directory someone loosened by hand
+        let credentials = getsockopt(&stream, PeerCredentials).map_err(io::Error::from)?;
+        if credentials.uid() != getuid().as_raw() {
+            log::warn!("Refused control connection from uid {}", credentials.uid());
+            return Ok(());
+        }
+
+        let mut line = String::new();
+        BufReader::new((&stream).take(MAX_REQUEST_LEN)).read_line(&mut line)?;
+
+        let response = ControlResponse::from(match Self::authorize(&line, token) {
+            Ok(command) => handler(command),
+            Err(e) => Err(e),
+        });
+        serde_json::to_writer(&mut stream, &response)?;
+        stream.write_all(b"\n")?;
+        stream.flush()
+    }
+
+    /// Decodes a request line and checks its token
+    fn authorize(line: &str, token: &str) -> Result<ControlCommand, CommandError> {
+        let (request, _): (ControlRequest, u32) = protocol::decode_versioned(WireSubsystem::ControlSocket, line)
+            .map_err(|e| match e {
+                ProtocolError::VersionMismatch(mismatch) => CommandError::from(mismatch),
+                ProtocolError::Malformed(msg) => CommandError::new(CommandErrorKind::ControlInvalidRequest, msg),
+            })?;
+
+        if !constant_time_eq(request.token.as_bytes(), token.as_bytes()) {
+            return Err(CommandError::new(CommandErrorKind::ControlUnauthorized, "Invalid control token"));
+        }
+        Ok(request.command)
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Sends one command to the instance listening in `dir` and returns its raw response
+pub fn send(dir: &Path, command: ControlCommand) -> io::Result<Value> {
+    let token = fs::read_to_string(dir.join(TOKEN_FILE))?;
+    let request = ControlRequest {
+        protocol_version: PROTOCOL_VERSION,
+        token: token.trim().to_string(),
+        command,
+    };
+
+    let mut stream = UnixStream::connect(dir.join(SOCKET_FILE))?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    serde_json::to_writer(&mut stream, &request)?;
+    stream.write_all(b"\n")?;
+    stream.flush()?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    serde_json::from_str(&line).map_err(io::Error::from)
+}
+
+/// Creates the control directory with mode 0700 and refuses one owned by someone else
+fn prepare_dir(dir: &Path) -> io::Result<()> {
+    if !dir.exists() {
+        fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    }
+
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.uid() != getuid().as_raw() {
+        return Err(io::Error::new(
+            ErrorKind::PermissionDenied,
+            format!("{} is not a directory owned by the current user", dir.display()),
+        ));
+    }
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn test_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("smoldesk-control-{}", uuid::Uuid::new_v4()))
+    }
+
+    fn echo_handler() -> ControlHandler {
+        Arc::new(|command| match command {
+            ControlCommand::Status => Ok(json!({ "peers": 0 })),
+            ControlCommand::SetClipboardText { text } => Ok(json!(text)),
+            _ => Err(CommandError::new(CommandErrorKind::TransferNotInitialized, "File transfer manager not initialized")),
+        })
+    }
+
+    fn raw_request(dir: &Path, line: &str) -> Value {
+        let mut stream = UnixStream::connect(dir.join(SOCKET_FILE)).unwrap();
+        stream.write_all(line.as_bytes()).unwrap();
+        stream.write_all(b"\n").unwrap();
+        let mut response = String::new();
+        BufReader::new(stream).read_line(&mut response).unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn test_request_wire_format() {
+        let request = ControlRequest {
+            protocol_version: PROTOCOL_VERSION,
+            token: "t".to_string(),
+            command: ControlCommand::SendFile { path: PathBuf::from("/tmp/a.txt"), peer_id: "viewer".to_string() },
+        };
+        assert_eq!(serde_json::to_value(&request).unwrap(), json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "token": "t",
+            "command": { "type": "sendFile", "path": "/tmp/a.txt", "peerId": "viewer" },
+        }));
+    }
+
+    #[test]
+    fn test_commands_are_dispatched_with_the_token() {
+        let dir = test_dir();
+        let mut server = ControlServer::start(&dir, echo_handler()).unwrap();
+
+        assert_eq!(send(&dir, ControlCommand::Status).unwrap(), json!({ "ok": true, "result": { "peers": 0 } }));
+        assert_eq!(
+            send(&dir, ControlCommand::SetClipboardText { text: "hi".to_string() }).unwrap()["result"],
+            json!("hi")
+        );
+
+        let failed = send(&dir, ControlCommand::ListTransfers).unwrap();
+        assert_eq!(failed["ok"], json!(false));
+        assert_eq!(failed["error"]["kind"], json!("transfer/not-initialized"));
+
+        server.stop();
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_bad_requests_are_refused() {
+        let dir = test_dir();
+        let server = ControlServer::start(&dir, echo_handler()).unwrap();
+
+        let wrong_token = raw_request(&dir, &format!(
+            r#"{{"protocolVersion":{},"token":"guess","command":{{"type":"status"}}}}"#, PROTOCOL_VERSION
+        ));
+        assert_eq!(wrong_token["error"]["kind"], json!("control/unauthorized"));
+
+        let unversioned = raw_request(&dir, r#"{"token":"guess","command":{"type":"status"}}"#);
+        assert_eq!(unversioned["error"]["kind"], json!("protocol/version-mismatch"));
+
+        let garbage = raw_request(&dir, "status please");
+        assert_eq!(garbage["error"]["kind"], json!("control/invalid-request"));
+
+        drop(server);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_files_are_private_and_removed_on_stop() {
+        let dir = test_dir();
+        let mut server = ControlServer::start(&dir, echo_handler()).unwrap();
+
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&dir), 0o700);
+        assert_eq!(mode(&dir.join(TOKEN_FILE)), 0o600);
+        assert_eq!(mode(&server.socket_path()), 0o600);
+
+        // A second instance must not take over the socket
+        assert_eq!(ControlServer::start(&dir, echo_handler()).err().map(|e| e.kind()), Some(ErrorKind::AddrInUse));
+
+        server.stop();
+        assert!(!dir.join(SOCKET_FILE).exists());
+        assert!(!dir.join(TOKEN_FILE).exists());
+        assert!(send(&dir, ControlCommand::Status).is_err());
+
+        // A socket left behind by a crash is replaced
+        UnixListener::bind(dir.join(SOCKET_FILE)).unwrap();
+        let mut server = ControlServer::start(&dir, echo_handler()).unwrap();
+        assert!(send(&dir, ControlCommand::Status).is_ok());
+        server.stop();
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
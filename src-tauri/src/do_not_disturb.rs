@@ -0,0 +1,497 @@
+// src-tauri/src/do_not_disturb.rs - Do-not-disturb on the host while the screen is shared
+//
+// Desktop notifications pop up on the captured screen and everyone watching
+// can read them. While a capture runs, the desktop's own do-not-disturb mode
+// is switched on and the state from before is restored afterwards. The
+// pre-sharing state is written to disk first, so a crash mid-session does not
+// leave the host in do-not-disturb for good. A change the user makes during
+// the session wins: it is detected by polling and left alone at the end.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::command_runner::CommandRunner;
+use crate::logging::TARGET_SCREEN_CAPTURE;
+
+const STATE_FILE: &str = "do_not_disturb.json";
+
+/// mako has no built-in mode; the user's config defines what it hides
+const MAKO_MODE: &str = "do-not-disturb";
+
+/// `Until` in plasmanotifyrc; Plasma treats a date this far out as "until turned off"
+const PLASMA_DND_UNTIL: &str = "2099-12-31T23:59:59";
+
+/// Notification daemon whose do-not-disturb mode is switched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DndBackend {
+    /// `show-banners` of org.gnome.desktop.notifications, set with gsettings
+    Gnome,
+    /// The `DoNotDisturb` group of plasmanotifyrc. Inhibit() over qdbus would
+    /// end with the qdbus process, so the setting itself is written.
+    Plasma,
+    /// The `do-not-disturb` mode of makoctl
+    Mako,
+    /// Paused notifications of dunstctl
+    Dunst,
+}
+
+/// Whether sharing switches on do-not-disturb
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DndConfig {
+    pub enabled: bool,
+}
+
+/// Returned by `get_dnd_integration`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DndStatus {
+    pub enabled: bool,
+    /// Daemon found on this desktop, None if none is supported
+    pub backend: Option<DndBackend>,
+    /// Do-not-disturb was switched on by SmolDesk and is still held
+    pub active: bool,
+}
+
+/// Why do-not-disturb changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DndChangeReason {
+    SharingStarted,
+    SharingStopped,
+    /// The user switched it during the session; it is not touched again
+    ChangedByUser,
+    /// State left behind by a session that did not end cleanly
+    Recovered,
+}
+
+/// Sent with `dnd_changed`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DndChange {
+    pub backend: DndBackend,
+    /// Do-not-disturb is on now
+    pub active: bool,
+    pub reason: DndChangeReason,
+}
+
+/// What the desktop looked like before sharing, persisted for crash recovery
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct DndSession {
+    backend: DndBackend,
+    /// Do-not-disturb was already on before sharing
+    previous: bool,
+    /// State last set or seen; a different reading means the user changed it
+    expected: bool,
+    user_changed: bool,
+}
+
+/// Switches do-not-disturb with the sharing state
+pub struct DndController {
+    runner: Arc<dyn CommandRunner>,
+    /// XDG_CURRENT_DESKTOP, lowercase
+    desktop: String,
+    config: DndConfig,
+    state_path: Option<PathBuf>,
+    session: Option<DndSession>,
+    /// Set once per sharing period, so an unsupported desktop is not probed every poll
+    attempted: bool,
+}
+
+impl DndController {
+    pub fn new(runner: Arc<dyn CommandRunner>, config: DndConfig) -> Self {
+        let desktop = std::env::var("XDG_CURRENT_DESKTOP")
+            .or_else(|_| std::env::var("XDG_SESSION_DESKTOP"))
+            .unwrap_or_default();
+        Self::with_desktop(runner, config, &desktop)
+    }
+
+    pub fn with_desktop(runner: Arc<dyn CommandRunner>, config: DndConfig, desktop: &str) -> Self {
+        DndController {
+            runner,
+            desktop: desktop.to_lowercase(),
+            config,
+            state_path: None,
+            session: None,
+            attempted: false,
+        }
+    }
+
+    /// Restores what a crashed session left behind and persists later sessions in `data_dir`
+    pub fn set_state_dir(&mut self, data_dir: &Path) -> Result<Option<DndChange>, String> {
+        fs::create_dir_all(data_dir)
+            .map_err(|e| format!("Failed to create {}: {}", data_dir.display(), e))?;
+        let path = data_dir.join(STATE_FILE);
+
+        let leftover = fs::read(&path)
+            .ok()
+            .and_then(|json| serde_json::from_slice::<DndSession>(&json).ok());
+        self.state_path = Some(path);
+
+        let change = leftover.and_then(|session| {
+            log::warn!(target: TARGET_SCREEN_CAPTURE, "Restoring do-not-disturb left over from a previous session");
+            self.restore(session, DndChangeReason::Recovered)
+        });
+        self.save();
+        Ok(change)
+    }
+
+    /// Disabling the integration mid-session restores the previous state at once
+    pub fn set_config(&mut self, config: DndConfig) -> Option<DndChange> {
+        self.config = config;
+        if self.config.enabled {
+            return None;
+        }
+        let session = self.session.take()?;
+        let change = self.restore(session, DndChangeReason::SharingStopped);
+        self.save();
+        change
+    }
+
+    pub fn status(&self) -> DndStatus {
+        DndStatus {
+            enabled: self.config.enabled,
+            backend: self.session.as_ref().map(|session| session.backend).or_else(|| self.detect_backend()),
+            active: self.session.as_ref().map(|session| !session.previous && !session.user_changed).unwrap_or(false),
+        }
+    }
+
+    /// Called whenever sharing may have started or stopped, and periodically
+    /// while it runs to notice changes made by the user
+    pub fn update(&mut self, sharing: bool) -> Option<DndChange> {
+        if !sharing {
+            self.attempted = false;
+            let session = self.session.take()?;
+            let change = self.restore(session, DndChangeReason::SharingStopped);
+            self.save();
+            return change;
+        }
+
+        if self.session.is_some() {
+            return self.reconcile();
+        }
+        if !self.config.enabled || self.attempted {
+            return None;
+        }
+        self.attempted = true;
+        self.engage()
+    }
+
+    fn engage(&mut self) -> Option<DndChange> {
+        let Some(backend) = self.detect_backend() else {
+            log::info!(target: TARGET_SCREEN_CAPTURE, "No supported notification daemon found, do-not-disturb stays unchanged");
+            return None;
+        };
+        let previous = match query(&*self.runner, backend) {
+            Ok(active) => active,
+            Err(e) => {
+                log::warn!(target: TARGET_SCREEN_CAPTURE, "Failed to read do-not-disturb state: {}", e);
+                return None;
+            },
+        };
+
+        // Persisted before switching, so a crash right after can still restore
+        self.session = Some(DndSession { backend, previous, expected: true, user_changed: false });
+        self.save();
+        if previous {
+            return None;
+        }
+
+        match set(&*self.runner, backend, true) {
+            Ok(()) => {
+                log::info!(target: TARGET_SCREEN_CAPTURE, "Enabled do-not-disturb ({:?}) while sharing", backend);
+                Some(DndChange { backend, active: true, reason: DndChangeReason::SharingStarted })
+            },
+            Err(e) => {
+                log::warn!(target: TARGET_SCREEN_CAPTURE, "Failed to enable do-not-disturb: {}", e);
+                self.session = None;
+                self.save();
+                None
+            },
+        }
+    }
+
+    fn reconcile(&mut self) -> Option<DndChange> {
+        let session = self.session.as_mut()?;
+        let current = query(&*self.runner, session.backend).ok()?;
+        if current == session.expected {
+            return None;
+        }
+
+        log::info!(target: TARGET_SCREEN_CAPTURE, "Do-not-disturb was turned {} by the user during sharing", if current { "on" } else { "off" });
+        session.expected = current;
+        session.user_changed = true;
+        let change = DndChange { backend: session.backend, active: current, reason: DndChangeReason::ChangedByUser };
+        self.save();
+        Some(change)
+    }
+
+    /// Puts back the pre-sharing state unless the user took over
+    fn restore(&self, session: DndSession, reason: DndChangeReason) -> Option<DndChange> {
+        if session.previous || session.user_changed {
+            return None;
+        }
+        // Already off, e.g. changed between two polls; nothing to undo
+        if query(&*self.runner, session.backend) == Ok(false) {
+            return None;
+        }
+
+        match set(&*self.runner, session.backend, false) {
+            Ok(()) => {
+                log::info!(target: TARGET_SCREEN_CAPTURE, "Restored do-not-disturb ({:?}) after sharing", session.backend);
+                Some(DndChange { backend: session.backend, active: false, reason })
+            },
+            Err(e) => {
+                log::error!(target: TARGET_SCREEN_CAPTURE, "Failed to restore do-not-disturb: {}", e);
+                None
+            },
+        }
+    }
+
+    /// The desktop's own daemon first, then the standalone daemons that answer
+    fn detect_backend(&self) -> Option<DndBackend> {
+        let mut candidates = Vec::new();
+        if ["gnome", "unity", "pantheon", "budgie"].iter().any(|name| self.desktop.contains(name)) {
+            candidates.push(DndBackend::Gnome);
+        } else if self.desktop.contains("kde") || self.desktop.contains("plasma") {
+            candidates.push(DndBackend::Plasma);
+        }
+        candidates.extend([DndBackend::Mako, DndBackend::Dunst]);
+
+        candidates.into_iter().find(|backend| query(&*self.runner, *backend).is_ok())
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.state_path else {
+            return;
+        };
+        let result = match &self.session {
+            Some(session) => serde_json::to_vec(session)
+                .map_err(|e| e.to_string())
+                .and_then(|json| fs::write(path, json).map_err(|e| e.to_string())),
+            None => match fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+                _ => Ok(()),
+            },
+        };
+        if let Err(e) = result {
+            log::error!(target: TARGET_SCREEN_CAPTURE, "Failed to save do-not-disturb state: {}", e);
+        }
+    }
+}
+
+/// Whether do-not-disturb is on; Err if the daemon or its tool is missing
+fn query(runner: &dyn CommandRunner, backend: DndBackend) -> Result<bool, String> {
+    match backend {
+        DndBackend::Gnome => {
+            let output = run(runner, "gsettings", &["get", "org.gnome.desktop.notifications", "show-banners"])?;
+            parse_bool(&output).map(|banners| !banners)
+        },
+        DndBackend::Plasma => {
+            let output = run_plasma(runner, "kreadconfig", &["--file", "plasmanotifyrc", "--group", "DoNotDisturb", "--key", "Until"])?;
+            Ok(plasma_dnd_active(&output, chrono::Local::now().naive_local()))
+        },
+        DndBackend::Mako => {
+            let output = run(runner, "makoctl", &["mode"])?;
+            Ok(output.lines().any(|mode| mode.trim() == MAKO_MODE))
+        },
+        DndBackend::Dunst => parse_bool(&run(runner, "dunstctl", &["is-paused"])?),
+    }
+}
+
+fn set(runner: &dyn CommandRunner, backend: DndBackend, active: bool) -> Result<(), String> {
+    match backend {
+        DndBackend::Gnome => {
+            let banners = if active { "false" } else { "true" };
+            run(runner, "gsettings", &["set", "org.gnome.desktop.notifications", "show-banners", banners]).map(drop)
+        },
+        DndBackend::Plasma => {
+            // --notify makes plasmashell pick up the change at once
+            let mut args = vec!["--notify", "--file", "plasmanotifyrc", "--group", "DoNotDisturb", "--key", "Until"];
+            if active {
+                args.push(PLASMA_DND_UNTIL);
+            } else {
+                args.push("--delete");
+            }
+            run_plasma(runner, "kwriteconfig", &args).map(drop)
+        },
+        DndBackend::Mako => {
+            let flag = if active { "-a" } else { "-r" };
+            run(runner, "makoctl", &["mode", flag, MAKO_MODE]).map(drop)
+        },
+        DndBackend::Dunst => {
+            run(runner, "dunstctl", &["set-paused", if active { "true" } else { "false" }]).map(drop)
+        },
+    }
+}
+
+fn parse_bool(output: &str) -> Result<bool, String> {
+    match output.trim() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        other => Err(format!("Unexpected output: {}", other)),
+    }
+}
+
+/// `Until` holds the end of do-not-disturb as "2024-05-01T18:00:00"; empty when off
+fn plasma_dnd_active(until: &str, now: chrono::NaiveDateTime) -> bool {
+    chrono::NaiveDateTime::parse_from_str(until.trim(), "%Y-%m-%dT%H:%M:%S")
+        .map(|until| until > now)
+        .unwrap_or(false)
+}
+
+/// Plasma 6 ships kreadconfig6/kwriteconfig6, Plasma 5 the same tools with a 5
+fn run_plasma(runner: &dyn CommandRunner, tool: &str, args: &[&str]) -> Result<String, String> {
+    let plasma6 = format!("{}6", tool);
+    match runner.command(&plasma6).args(args).output() {
+        Ok(output) => check_output(&plasma6, output),
+        Err(_) => run(runner, &format!("{}5", tool), args),
+    }
+}
+
+fn run(runner: &dyn CommandRunner, program: &str, args: &[&str]) -> Result<String, String> {
+    let output = runner.command(program).args(args).output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    check_output(program, output)
+}
+
+fn check_output(program: &str, output: Output) -> Result<String, String> {
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(format!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::{call, TestEnv};
+
+    fn controller(env: &TestEnv, desktop: &str) -> DndController {
+        DndController::with_desktop(env.runner(), DndConfig { enabled: true }, desktop)
+    }
+
+    #[test]
+    fn test_detect_backend() {
+        let env = TestEnv::new();
+        assert_eq!(controller(&env, "ubuntu:GNOME").detect_backend(), Some(DndBackend::Gnome));
+        assert_eq!(controller(&env, "sway").detect_backend(), Some(DndBackend::Mako));
+
+        // makoctl fails without a running mako
+        env.set_exit_code("makoctl", 1);
+        assert_eq!(controller(&env, "sway").detect_backend(), Some(DndBackend::Dunst));
+        env.set_exit_code("dunstctl", 1);
+        assert_eq!(controller(&env, "sway").detect_backend(), None);
+    }
+
+    #[test]
+    fn test_parse_state() {
+        let now = chrono::NaiveDateTime::parse_from_str("2024-05-01T12:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
+        assert!(plasma_dnd_active("2024-05-01T18:00:00\n", now));
+        assert!(!plasma_dnd_active("2024-05-01T08:00:00\n", now));
+        assert!(!plasma_dnd_active("\n", now));
+
+        assert_eq!(parse_bool("true\n"), Ok(true));
+        assert!(parse_bool("No such schema").is_err());
+    }
+
+    #[test]
+    fn test_sharing_toggles_and_restores() {
+        let env = TestEnv::new();
+        env.set_stdout("gsettings", "true\n");
+        let mut dnd = controller(&env, "GNOME");
+
+        let change = dnd.update(true).unwrap();
+        assert_eq!(change, DndChange { backend: DndBackend::Gnome, active: true, reason: DndChangeReason::SharingStarted });
+        assert_eq!(env.take_invocations(), vec![
+            call("gsettings get org.gnome.desktop.notifications show-banners"),
+            call("gsettings get org.gnome.desktop.notifications show-banners"),
+            call("gsettings set org.gnome.desktop.notifications show-banners false"),
+        ]);
+        assert!(dnd.status().active);
+
+        // The daemon now reports do-not-disturb as set
+        env.set_stdout("gsettings", "false\n");
+        assert_eq!(dnd.update(true), None);
+        env.take_invocations();
+
+        let change = dnd.update(false).unwrap();
+        assert_eq!(change.reason, DndChangeReason::SharingStopped);
+        assert_eq!(env.take_invocations(), vec![
+            call("gsettings get org.gnome.desktop.notifications show-banners"),
+            call("gsettings set org.gnome.desktop.notifications show-banners true"),
+        ]);
+    }
+
+    #[test]
+    fn test_state_before_sharing_is_kept() {
+        let env = TestEnv::new();
+        env.set_exit_code("makoctl", 1);
+        env.set_stdout("dunstctl", "true\n");
+        let mut dnd = controller(&env, "i3");
+
+        // Already paused: nothing to switch on, nothing to undo
+        assert_eq!(dnd.update(true), None);
+        assert_eq!(dnd.update(false), None);
+        assert!(!env.take_invocations().iter().any(|invocation| invocation[1] == "set-paused"));
+    }
+
+    #[test]
+    fn test_user_change_wins() {
+        let env = TestEnv::new();
+        env.set_stdout("makoctl", "default\n");
+        let mut dnd = controller(&env, "sway");
+        dnd.update(true).unwrap();
+
+        // The user leaves do-not-disturb during the session
+        env.set_stdout("makoctl", "default\n");
+        let change = dnd.update(true).unwrap();
+        assert_eq!(change, DndChange { backend: DndBackend::Mako, active: false, reason: DndChangeReason::ChangedByUser });
+
+        // ... and turns it back on; it stays on after sharing
+        env.set_stdout("makoctl", "default\ndo-not-disturb\n");
+        dnd.update(true);
+        env.take_invocations();
+        assert_eq!(dnd.update(false), None);
+        assert!(env.take_invocations().is_empty());
+    }
+
+    #[test]
+    fn test_crashed_session_is_restored() {
+        let env = TestEnv::new();
+        let dir = std::env::temp_dir().join(format!("smoldesk-dnd-{}", uuid::Uuid::new_v4()));
+        env.set_exit_code("makoctl", 1);
+        env.set_stdout("dunstctl", "false\n");
+
+        let mut dnd = controller(&env, "");
+        dnd.set_state_dir(&dir).unwrap();
+        dnd.update(true).unwrap();
+        assert!(dir.join(STATE_FILE).exists());
+        drop(dnd);
+
+        env.set_stdout("dunstctl", "true\n");
+        env.take_invocations();
+        let mut dnd = controller(&env, "");
+        let change = dnd.set_state_dir(&dir).unwrap().unwrap();
+        assert_eq!(change.reason, DndChangeReason::Recovered);
+        assert_eq!(env.take_invocations().last(), Some(&call("dunstctl set-paused false")));
+        assert!(!dir.join(STATE_FILE).exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_plasma_uses_kconfig_tools() {
+        let env = TestEnv::new();
+        env.set_stdout("kreadconfig6", "\n");
+        let mut dnd = controller(&env, "KDE");
+
+        dnd.update(true).unwrap();
+        assert_eq!(env.take_invocations().last(), Some(&call(&format!(
+            "kwriteconfig6 --notify --file plasmanotifyrc --group DoNotDisturb --key Until {}", PLASMA_DND_UNTIL
+        ))));
+    }
+}
@@ -0,0 +1,325 @@
+// src-tauri/src/file_transfer/bandwidth.rs - Bandbreitenbegrenzung für Dateiübertragungen
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+/// Untergrenze im Auto-Modus, damit Übertragungen nie ganz stehen bleiben
+const AUTO_MIN_BYTES_PER_SEC: u64 = 32 * 1024;
+
+/// Automatische Begrenzung anhand der aktuellen Video-Bitrate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoBandwidthConfig {
+    /// Geschätzte Gesamtkapazität der Verbindung in Bytes/Sekunde
+    pub link_capacity_bytes_per_sec: u64,
+
+    /// Zusätzlicher Puffer für den Videostream in Prozent seiner Bitrate
+    pub headroom_percent: u32,
+}
+
+/// Token-Bucket mit Schuldenmodell: eine Reservierung darf den Bucket ins
+/// Minus ziehen, der Aufrufer wartet dann die zurückgegebene Zeit ab.
+#[derive(Debug)]
+pub struct TokenBucket {
+    /// Rate in Bytes/Sekunde
+    rate: u64,
+
+    /// Maximale Burst-Größe in Bytes
+    capacity: f64,
+
+    /// Aktueller Füllstand (kann negativ sein)
+    tokens: f64,
+
+    /// Zeitpunkt der letzten Auffüllung
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Erstellt einen Bucket, der eine Sekunde Burst erlaubt
+    pub fn new(rate: u64, now: Instant) -> Self {
+        TokenBucket {
+            rate,
+            capacity: rate as f64,
+            tokens: rate as f64,
+            last_refill: now,
+        }
+    }
+
+    /// Ändert die Rate, ohne angesammelte Schulden zu verlieren
+    pub fn set_rate(&mut self, rate: u64, now: Instant) {
+        self.refill(now);
+        self.rate = rate;
+        self.capacity = rate as f64;
+        self.tokens = self.tokens.min(self.capacity);
+    }
+
+    /// Aktuelle Rate in Bytes/Sekunde
+    pub fn rate(&self) -> u64 {
+        self.rate
+    }
+
+    /// Reserviert `bytes` und liefert die Wartezeit bis zur Freigabe
+    pub fn reserve(&mut self, bytes: u64, now: Instant) -> Duration {
+        self.refill(now);
+        self.tokens -= bytes as f64;
+
+        if self.tokens >= 0.0 || self.rate == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate as f64)
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate as f64).min(self.capacity);
+        self.last_refill = now;
+    }
+}
+
+/// Quelle für die aktuelle Video-Bitrate in Bits/Sekunde
+pub type VideoBitrateSource = Box<dyn Fn() -> u64 + Send + Sync>;
+
+/// Liefert die aktuelle Zeit; in Tests eine gesteuerte Uhr
+type Clock = Box<dyn Fn() -> Instant + Send + Sync>;
+
+/// Globale und übertragungsbezogene Bandbreitenbegrenzung
+pub struct BandwidthLimiter {
+    /// Globaler Bucket (None = unbegrenzt)
+    global: Mutex<Option<TokenBucket>>,
+
+    /// Buckets für einzelne Übertragungen
+    per_transfer: Mutex<HashMap<String, TokenBucket>>,
+
+    /// Auto-Modus
+    auto: Mutex<Option<AutoBandwidthConfig>>,
+
+    /// Liefert die Video-Bitrate für den Auto-Modus
+    video_bitrate: Mutex<Option<VideoBitrateSource>>,
+
+    clock: Clock,
+}
+
+impl BandwidthLimiter {
+    /// Erstellt einen Limiter mit optionaler globaler Grenze
+    pub fn new(global_limit: Option<u64>, auto: Option<AutoBandwidthConfig>) -> Self {
+        Self::with_clock(global_limit, auto, Box::new(Instant::now))
+    }
+
+    fn with_clock(global_limit: Option<u64>, auto: Option<AutoBandwidthConfig>, clock: Clock) -> Self {
+        let now = clock();
+        BandwidthLimiter {
+            global: Mutex::new(global_limit.map(|rate| TokenBucket::new(rate, now))),
+            per_transfer: Mutex::new(HashMap::new()),
+            auto: Mutex::new(auto),
+            video_bitrate: Mutex::new(None),
+            clock,
+        }
+    }
+
+    /// Setzt die globale Grenze (None = unbegrenzt); beendet den Auto-Modus
+    pub fn set_global_limit(&self, limit: Option<u64>) {
+        *self.auto.lock().unwrap() = None;
+        self.apply_global_rate(limit);
+    }
+
+    /// Aktiviert den Auto-Modus
+    pub fn set_auto(&self, auto: AutoBandwidthConfig) {
+        *self.auto.lock().unwrap() = Some(auto);
+    }
+
+    /// Setzt die Quelle der Video-Bitrate für den Auto-Modus
+    pub fn set_video_bitrate_source(&self, source: VideoBitrateSource) {
+        *self.video_bitrate.lock().unwrap() = Some(source);
+    }
+
+    /// Setzt oder entfernt die Grenze einer einzelnen Übertragung
+    pub fn set_transfer_limit(&self, transfer_id: &str, limit: Option<u64>) {
+        let now = (self.clock)();
+        let mut per_transfer = self.per_transfer.lock().unwrap();
+        match limit {
+            Some(rate) => {
+                per_transfer.entry(transfer_id.to_string())
+                    .and_modify(|bucket| bucket.set_rate(rate, now))
+                    .or_insert_with(|| TokenBucket::new(rate, now));
+            },
+            None => {
+                per_transfer.remove(transfer_id);
+            }
+        }
+    }
+
+    /// Entfernt den Zustand einer beendeten Übertragung
+    pub fn remove_transfer(&self, transfer_id: &str) {
+        self.per_transfer.lock().unwrap().remove(transfer_id);
+    }
+
+    /// Aktuelle globale Grenze in Bytes/Sekunde
+    pub fn global_limit(&self) -> Option<u64> {
+        self.global.lock().unwrap().as_ref().map(|bucket| bucket.rate())
+    }
+
+    /// Wartet, bis `bytes` für die Übertragung gesendet werden dürfen
+    pub async fn acquire(&self, transfer_id: &str, bytes: u64) {
+        let wait = self.reserve(transfer_id, bytes);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Reserviert `bytes` in beiden Buckets und liefert die längere Wartezeit
+    fn reserve(&self, transfer_id: &str, bytes: u64) -> Duration {
+        self.update_auto_limit();
+        let now = (self.clock)();
+
+        let global_wait = self.global.lock().unwrap().as_mut()
+            .map(|bucket| bucket.reserve(bytes, now))
+            .unwrap_or(Duration::ZERO);
+
+        let transfer_wait = self.per_transfer.lock().unwrap().get_mut(transfer_id)
+            .map(|bucket| bucket.reserve(bytes, now))
+            .unwrap_or(Duration::ZERO);
+
+        global_wait.max(transfer_wait)
+    }
+
+    /// Leitet die globale Grenze im Auto-Modus aus der Video-Bitrate ab
+    fn update_auto_limit(&self) {
+        let auto = match self.auto.lock().unwrap().clone() {
+            Some(auto) => auto,
+            None => return,
+        };
+
+        let video_bits = self.video_bitrate.lock().unwrap().as_ref()
+            .map(|source| source())
+            .unwrap_or(0);
+
+        let video_bytes = video_bits / 8;
+        let reserved = video_bytes + video_bytes * auto.headroom_percent as u64 / 100;
+        let limit = auto.link_capacity_bytes_per_sec
+            .saturating_sub(reserved)
+            .max(AUTO_MIN_BYTES_PER_SEC);
+
+        self.apply_global_rate(Some(limit));
+    }
+
+    fn apply_global_rate(&self, limit: Option<u64>) {
+        let now = (self.clock)();
+        let mut global = self.global.lock().unwrap();
+        match (global.as_mut(), limit) {
+            (Some(bucket), Some(rate)) => bucket.set_rate(rate, now),
+            (None, Some(rate)) => *global = Some(TokenBucket::new(rate, now)),
+            (_, None) => *global = None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    /// Uhr, die nur weiterläuft, wenn der Test sie stellt
+    #[derive(Clone)]
+    struct TestClock {
+        start: Instant,
+        elapsed_ms: Arc<AtomicU64>,
+    }
+
+    impl TestClock {
+        fn new() -> Self {
+            TestClock { start: Instant::now(), elapsed_ms: Arc::new(AtomicU64::new(0)) }
+        }
+
+        fn advance(&self, millis: u64) {
+            self.elapsed_ms.fetch_add(millis, Ordering::SeqCst);
+        }
+
+        fn source(&self) -> Clock {
+            let clock = self.clone();
+            Box::new(move || clock.start + Duration::from_millis(clock.elapsed_ms.load(Ordering::SeqCst)))
+        }
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_bucket_refills_up_to_capacity() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(1000, start);
+
+        // Eine Sekunde Burst, danach wird gewartet
+        assert_eq!(bucket.reserve(1000, start), Duration::ZERO);
+        assert_eq!(bucket.reserve(500, start), ms(500));
+        // Die Schuld ist nach der Wartezeit abgetragen
+        assert_eq!(bucket.reserve(0, start + ms(500)), Duration::ZERO);
+
+        // Lange Pausen sammeln nicht mehr als eine Sekunde an
+        let later = start + Duration::from_secs(10);
+        assert_eq!(bucket.reserve(1000, later), Duration::ZERO);
+        assert_eq!(bucket.reserve(100, later), ms(100));
+    }
+
+    #[test]
+    fn test_limits_change_at_runtime() {
+        let clock = TestClock::new();
+        let limiter = BandwidthLimiter::with_clock(Some(1000), None, clock.source());
+
+        assert_eq!(limiter.reserve("t1", 1000), Duration::ZERO);
+        assert_eq!(limiter.reserve("t1", 1000), ms(1000));
+
+        // Eine höhere Rate trägt die bestehende Schuld schneller ab
+        limiter.set_global_limit(Some(2000));
+        assert_eq!(limiter.global_limit(), Some(2000));
+        assert_eq!(limiter.reserve("t1", 1000), ms(1000));
+        clock.advance(1000);
+        assert_eq!(limiter.reserve("t1", 0), Duration::ZERO);
+
+        // Die engere Grenze einer Übertragung gilt nur für sie
+        clock.advance(1000);
+        limiter.set_transfer_limit("t1", Some(100));
+        assert_eq!(limiter.reserve("t1", 300), ms(2000));
+        assert_eq!(limiter.reserve("t2", 300), Duration::ZERO);
+
+        limiter.set_transfer_limit("t1", None);
+        limiter.set_global_limit(None);
+        assert_eq!(limiter.global_limit(), None);
+        assert_eq!(limiter.reserve("t1", 1_000_000), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_auto_mode_backs_off_and_recovers() {
+        let clock = TestClock::new();
+        let auto = AutoBandwidthConfig { link_capacity_bytes_per_sec: 1_000_000, headroom_percent: 25 };
+        let limiter = BandwidthLimiter::with_clock(None, Some(auto), clock.source());
+        let video_bits = Arc::new(AtomicU64::new(4_000_000));
+        {
+            let video_bits = video_bits.clone();
+            limiter.set_video_bitrate_source(Box::new(move || video_bits.load(Ordering::SeqCst)));
+        }
+
+        // 500 KB/s Video plus 25 % Puffer bleiben frei
+        limiter.reserve("t1", 0);
+        assert_eq!(limiter.global_limit(), Some(375_000));
+
+        // Braucht das Video die ganze Verbindung, bleibt die Untergrenze
+        video_bits.store(8_000_000, Ordering::SeqCst);
+        limiter.reserve("t1", 0);
+        assert_eq!(limiter.global_limit(), Some(AUTO_MIN_BYTES_PER_SEC));
+
+        video_bits.store(4_000_000, Ordering::SeqCst);
+        clock.advance(1000);
+        limiter.reserve("t1", 0);
+        assert_eq!(limiter.global_limit(), Some(375_000));
+
+        // Eine feste Grenze beendet den Auto-Modus
+        limiter.set_global_limit(Some(1000));
+        video_bits.store(0, Ordering::SeqCst);
+        limiter.reserve("t1", 0);
+        assert_eq!(limiter.global_limit(), Some(1000));
+    }
+}
@@ -0,0 +1,372 @@
+// src-tauri/src/file_transfer/chunk_manager.rs - Lesen und Schreiben von Datei-Chunks
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::thread;
+use sha2::{Sha256, Digest};
+
+use crate::file_transfer::chunk_sizing::total_chunks;
+use crate::file_transfer::error::FileTransferError;
+use crate::file_transfer::security::TransferCipher;
+
+/// Obergrenze für parallele Hash-Worker beim Upload
+pub const MAX_HASH_WORKERS: usize = 4;
+
+/// Verwaltet das chunkweise Lesen und Schreiben von Dateien.
+///
+/// Die Chunk-Größe wird je Übertragung ausgehandelt und daher bei jedem
+/// Aufruf übergeben; der letzte Chunk einer Datei darf kürzer sein.
+#[derive(Debug, Default)]
+pub struct ChunkManager;
+
+/// Ergebnis eines geschriebenen Chunks
+#[derive(Debug, Clone, PartialEq)]
+pub struct WrittenChunk {
+    /// Geschriebene (Klartext-)Bytes
+    pub len: usize,
+
+    /// SHA-256 der geschriebenen Bytes
+    pub hash: String,
+}
+
+impl ChunkManager {
+    /// Erstellt einen neuen ChunkManager
+    pub fn new() -> Self {
+        ChunkManager
+    }
+
+    /// Legt die Zieldatei in ihrer endgültigen Größe an.
+    ///
+    /// Die Blöcke werden per fallocate am Stück reserviert, damit in beliebiger
+    /// Reihenfolge eintreffende Chunks die Datei nicht fragmentieren und ein
+    /// volles Dateisystem schon beim Annehmen auffällt. Dateisysteme ohne
+    /// fallocate bekommen eine Sparse-Datei (ftruncate).
+    pub fn preallocate(&self, path: &Path, size: u64) -> Result<(), FileTransferError> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .open(path)?;
+
+        if size == 0 {
+            return Ok(());
+        }
+
+        if let Err(errno) = nix::fcntl::posix_fallocate(std::os::unix::io::AsRawFd::as_raw_fd(&file), 0, size as i64) {
+            if errno == nix::errno::Errno::ENOSPC {
+                return Err(FileTransferError::IoError(
+                    format!("Not enough space for {} bytes at {}", size, path.display())
+                ));
+            }
+            log::debug!(target: crate::logging::TARGET_TRANSFER, "fallocate unavailable for {}: {}, using a sparse file", path.display(), errno);
+        }
+
+        file.set_len(size)?;
+        Ok(())
+    }
+
+    /// Liest einen Chunk aus einer Datei
+    pub async fn read_chunk(
+        &self,
+        path: &Path,
+        chunk_index: usize,
+        chunk_size: usize
+    ) -> Result<Vec<u8>, FileTransferError> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(chunk_index as u64 * chunk_size as u64))?;
+
+        let mut buffer = Vec::with_capacity(chunk_size);
+        file.take(chunk_size as u64).read_to_end(&mut buffer)?;
+
+        if buffer.is_empty() {
+            return Err(FileTransferError::ChunkError(
+                format!("Chunk {} is beyond the end of {}", chunk_index, path.display())
+            ));
+        }
+
+        Ok(buffer)
+    }
+
+    /// Schreibt einen Chunk an seine Position in der Zieldatei.
+    ///
+    /// Bei verschlüsselten Übertragungen wird der Chunk vorher entschlüsselt
+    /// und authentifiziert; ein Fehler dabei schreibt nichts auf die Platte.
+    /// Der zurückgegebene Hash ist selbst berechnet und ersetzt beim Abschluss
+    /// das erneute Lesen der Datei. Chunks über `chunk_size` würden den
+    /// nächsten überschreiben und werden abgewiesen.
+    pub async fn write_chunk(
+        &self,
+        path: &Path,
+        chunk_index: usize,
+        chunk_size: usize,
+        data: &[u8],
+        expected_hash: Option<&str>,
+        cipher: Option<&TransferCipher>
+    ) -> Result<WrittenChunk, FileTransferError> {
+        let decrypted;
+        let plaintext = match cipher {
+            Some(cipher) => {
+                decrypted = cipher.decrypt_chunk(chunk_index, data)?;
+                &decrypted[..]
+            },
+            None => data,
+        };
+
+        if plaintext.len() > chunk_size {
+            return Err(FileTransferError::ChunkError(format!(
+                "Chunk {} has {} bytes, more than the chunk size of {}",
+                chunk_index, plaintext.len(), chunk_size
+            )));
+        }
+
+        let hash = Self::calculate_chunk_hash(plaintext);
+        if let Some(expected) = expected_hash {
+            if hash != expected {
+                return Err(FileTransferError::HashMismatch {
+                    expected: expected.to_string(),
+                    actual: hash,
+                });
+            }
+        }
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .open(path)?;
+
+        file.seek(SeekFrom::Start(chunk_index as u64 * chunk_size as u64))?;
+        file.write_all(plaintext)?;
+
+        Ok(WrittenChunk {
+            len: plaintext.len(),
+            hash,
+        })
+    }
+
+    /// Berechnet die Hashes aller Chunks einer Datei mit bis zu `workers`
+    /// Threads; jeder Thread liest mit eigenem Dateihandle jeden n-ten Chunk
+    pub fn hash_chunks(&self, path: &Path, chunk_size: usize, workers: usize) -> Result<Vec<String>, FileTransferError> {
+        let size = std::fs::metadata(path)?.len();
+        let total_chunks = total_chunks(size, chunk_size);
+        let workers = workers.clamp(1, total_chunks.max(1));
+
+        let results: Vec<Result<Vec<(usize, String)>, FileTransferError>> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|worker| scope.spawn(move || self.hash_strided(path, chunk_size, worker, workers, total_chunks)))
+                .collect();
+
+            handles.into_iter()
+                .map(|handle| handle.join().unwrap_or_else(|_| Err(FileTransferError::ChunkError(
+                    "Hash worker panicked".to_string()
+                ))))
+                .collect()
+        });
+
+        let mut hashes = vec![String::new(); total_chunks];
+        for result in results {
+            for (index, hash) in result? {
+                hashes[index] = hash;
+            }
+        }
+        Ok(hashes)
+    }
+
+    /// Hasht die Chunks `first`, `first + step`, ...
+    fn hash_strided(
+        &self,
+        path: &Path,
+        chunk_size: usize,
+        first: usize,
+        step: usize,
+        total_chunks: usize
+    ) -> Result<Vec<(usize, String)>, FileTransferError> {
+        let mut file = File::open(path)?;
+        let mut buffer = Vec::with_capacity(chunk_size);
+        let mut hashes = Vec::with_capacity(total_chunks / step + 1);
+
+        for index in (first..total_chunks).step_by(step) {
+            file.seek(SeekFrom::Start(index as u64 * chunk_size as u64))?;
+            buffer.clear();
+            (&mut file).take(chunk_size as u64).read_to_end(&mut buffer)?;
+            hashes.push((index, Self::calculate_chunk_hash(&buffer)));
+        }
+
+        Ok(hashes)
+    }
+
+    /// Berechnet den SHA-256-Hash eines Chunks
+    pub fn calculate_chunk_hash(data: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(data))
+    }
+
+    /// Datei-Hash aus der geordneten Liste der Chunk-Hashes: SHA-256 über die
+    /// aneinandergehängten Hex-Hashes (feste Länge, daher eindeutig)
+    pub fn combine_chunk_hashes(hashes: &[String]) -> String {
+        let mut hasher = Sha256::new();
+        for hash in hashes {
+            hasher.update(hash.as_bytes());
+        }
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::Instant;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("smoldesk-chunks-{}-{}", name, std::process::id()))
+    }
+
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_preallocate_sets_final_size() {
+        let path = temp_path("prealloc");
+        let manager = ChunkManager::new();
+
+        manager.preallocate(&path, 100).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 100);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_out_of_order_writes_match_combined_hash() {
+        let source = temp_path("source");
+        let dest = temp_path("dest");
+        let data = pattern(100);
+        std::fs::write(&source, &data).unwrap();
+
+        let manager = ChunkManager::new();
+        let sent = manager.hash_chunks(&source, 16, 3).unwrap();
+        assert_eq!(sent.len(), 7);
+
+        manager.preallocate(&dest, data.len() as u64).unwrap();
+        let mut received = vec![String::new(); sent.len()];
+        for index in (0..sent.len()).rev() {
+            let chunk = &data[index * 16..((index + 1) * 16).min(data.len())];
+            let written = manager.write_chunk(&dest, index, 16, chunk, Some(&sent[index]), None).await.unwrap();
+            received[index] = written.hash;
+        }
+
+        assert_eq!(std::fs::read(&dest).unwrap(), data);
+        assert_eq!(ChunkManager::combine_chunk_hashes(&received), ChunkManager::combine_chunk_hashes(&sent));
+        // Ein erneutes Lesen ergibt dieselbe Liste
+        assert_eq!(manager.hash_chunks(&dest, 16, 1).unwrap(), sent);
+
+        std::fs::remove_file(&source).unwrap();
+        std::fs::remove_file(&dest).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_negotiated_size_with_short_final_chunk() {
+        let source = temp_path("negotiated-source");
+        let dest = temp_path("negotiated-dest");
+        let data = pattern(100);
+        std::fs::write(&source, &data).unwrap();
+
+        let manager = ChunkManager::new();
+        manager.preallocate(&dest, data.len() as u64).unwrap();
+        for index in 0..total_chunks(data.len() as u64, 24) {
+            let chunk = manager.read_chunk(&source, index, 24).await.unwrap();
+            assert_eq!(chunk.len(), if index == 4 { 4 } else { 24 });
+            manager.write_chunk(&dest, index, 24, &chunk, None, None).await.unwrap();
+        }
+        assert_eq!(std::fs::read(&dest).unwrap(), data);
+
+        // Ein Chunk über der ausgehandelten Größe würde den nächsten überschreiben
+        let result = manager.write_chunk(&dest, 0, 16, &data[..24], None, None).await;
+        assert!(matches!(result, Err(FileTransferError::ChunkError(_))));
+
+        std::fs::remove_file(&source).unwrap();
+        std::fs::remove_file(&dest).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_chunk_is_rejected() {
+        let dest = temp_path("corrupt");
+        let manager = ChunkManager::new();
+        let expected = ChunkManager::calculate_chunk_hash(b"original");
+
+        let result = manager.write_chunk(&dest, 0, 16, b"tampered", Some(&expected), None).await;
+        assert!(matches!(result, Err(FileTransferError::HashMismatch { .. })));
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn test_parallel_hashing_matches_sequential() {
+        let path = temp_path("parallel");
+        std::fs::write(&path, pattern(10_000)).unwrap();
+        let manager = ChunkManager::new();
+
+        let sequential = manager.hash_chunks(&path, 1024, 1).unwrap();
+        assert_eq!(manager.hash_chunks(&path, 1024, MAX_HASH_WORKERS).unwrap(), sequential);
+        assert_ne!(ChunkManager::combine_chunk_hashes(&sequential), ChunkManager::combine_chunk_hashes(&sequential[1..]));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Vergleicht die Abschlussprüfung per Neu-Lesen mit der Kombination der
+    /// Chunk-Hashes, dazu sequentielles und paralleles Hashen beim Upload:
+    ///
+    /// `SMOLDESK_BENCH_MB=2048 cargo test --release bench_download_verification -- --ignored --nocapture`
+    #[tokio::test]
+    #[ignore]
+    async fn bench_download_verification() {
+        let size_mb: usize = std::env::var("SMOLDESK_BENCH_MB").ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(2048);
+        let chunk_size = 64 * 1024;
+        let manager = ChunkManager::new();
+        let source = temp_path("bench-source");
+        let dest = temp_path("bench-dest");
+
+        {
+            let mut file = File::create(&source).unwrap();
+            let block = pattern(1024 * 1024);
+            for _ in 0..size_mb {
+                file.write_all(&block).unwrap();
+            }
+        }
+
+        let started = Instant::now();
+        let sequential = manager.hash_chunks(&source, chunk_size, 1).unwrap();
+        let sequential_hashing = started.elapsed();
+
+        let started = Instant::now();
+        let sent = manager.hash_chunks(&source, chunk_size, MAX_HASH_WORKERS).unwrap();
+        let parallel_hashing = started.elapsed();
+        assert_eq!(sent, sequential);
+
+        // Empfang: Chunks schreiben, Hashes nebenbei sammeln
+        let started = Instant::now();
+        manager.preallocate(&dest, (size_mb * 1024 * 1024) as u64).unwrap();
+        let mut received = Vec::with_capacity(sent.len());
+        for index in 0..sent.len() {
+            let chunk = manager.read_chunk(&source, index, chunk_size).await.unwrap();
+            received.push(manager.write_chunk(&dest, index, chunk_size, &chunk, Some(&sent[index]), None).await.unwrap().hash);
+        }
+        let receiving = started.elapsed();
+
+        let started = Instant::now();
+        assert_eq!(ChunkManager::combine_chunk_hashes(&manager.hash_chunks(&dest, chunk_size, 1).unwrap()), ChunkManager::combine_chunk_hashes(&sent));
+        let full_reread = started.elapsed();
+
+        let started = Instant::now();
+        assert_eq!(ChunkManager::combine_chunk_hashes(&received), ChunkManager::combine_chunk_hashes(&sent));
+        let combined = started.elapsed();
+
+        println!("{} MB in {} KB chunks", size_mb, chunk_size / 1024);
+        println!("upload hashing: sequential {:?}, {} workers {:?}", sequential_hashing, MAX_HASH_WORKERS, parallel_hashing);
+        println!("receive with preallocation: {:?}", receiving);
+        println!("verification: full re-read {:?}, chunk hashes {:?}", full_reread, combined);
+
+        std::fs::remove_file(&source).unwrap();
+        std::fs::remove_file(&dest).unwrap();
+    }
+}
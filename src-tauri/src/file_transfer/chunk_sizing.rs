@@ -0,0 +1,292 @@
+// src-tauri/src/file_transfer/chunk_sizing.rs - Aushandlung der Chunk-Größe und adaptive Chunk-Batches
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+use crate::file_transfer::error::FileTransferError;
+use crate::file_transfer::types::TransferConfig;
+
+/// Bestätigungen, die langsamer als dieses Vielfache der besten Latenz sind, halbieren den Batch
+const SLOW_ACK_FACTOR: u32 = 2;
+
+/// Bestätigungen bis zu diesem Vielfachen (in Achteln) der besten Latenz verdoppeln den Batch
+const FAST_ACK_EIGHTHS: u32 = 12;
+
+/// Erlaubter Bereich für Chunk-Größen in Bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkSizeBounds {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl ChunkSizeBounds {
+    /// Bereich aus der Konfiguration; die eigene Chunk-Größe liegt immer darin
+    pub fn from_config(config: &TransferConfig) -> Self {
+        let chunk_size = config.chunk_size.max(1);
+        ChunkSizeBounds {
+            min: config.min_chunk_size.clamp(1, chunk_size),
+            max: config.max_chunk_size.max(chunk_size),
+        }
+    }
+
+    /// Bereich, den beide Seiten erlauben
+    pub fn intersect(&self, other: &ChunkSizeBounds) -> Option<ChunkSizeBounds> {
+        let min = self.min.max(other.min);
+        let max = self.max.min(other.max);
+        (min <= max).then_some(ChunkSizeBounds { min, max })
+    }
+
+    /// Ob eine Chunk-Größe im Bereich liegt
+    pub fn contains(&self, size: usize) -> bool {
+        (self.min..=self.max).contains(&size)
+    }
+
+    /// Begrenzt eine Chunk-Größe auf den Bereich
+    pub fn clamp(&self, size: usize) -> usize {
+        size.clamp(self.min, self.max)
+    }
+}
+
+/// Ergebnis der Aushandlung auf Empfängerseite
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NegotiatedChunkSize {
+    /// Chunk-Größe der Übertragung
+    pub chunk_size: usize,
+
+    /// Gemeinsamer Bereich, innerhalb dessen der Sender Batches bildet
+    pub bounds: ChunkSizeBounds,
+}
+
+/// Handelt die Chunk-Größe für eine eingehende Anfrage aus.
+///
+/// Gegenstellen ohne eigenen Bereich senden mit fester Größe; die wird
+/// unverändert übernommen, da der Empfänger beliebige Größen schreiben kann.
+pub fn negotiate_chunk_size(
+    requested: usize,
+    sender: Option<ChunkSizeBounds>,
+    receiver: ChunkSizeBounds
+) -> Result<NegotiatedChunkSize, FileTransferError> {
+    if requested == 0 {
+        return Err(FileTransferError::ChunkError("Requested chunk size is zero".to_string()));
+    }
+
+    let sender = match sender {
+        Some(sender) => sender,
+        None => return Ok(NegotiatedChunkSize {
+            chunk_size: requested,
+            bounds: ChunkSizeBounds { min: requested, max: requested },
+        }),
+    };
+
+    let bounds = sender.intersect(&receiver).ok_or_else(|| FileTransferError::ChunkError(format!(
+        "No common chunk size: sender allows {}-{} bytes, receiver {}-{} bytes",
+        sender.min, sender.max, receiver.min, receiver.max
+    )))?;
+
+    Ok(NegotiatedChunkSize {
+        chunk_size: bounds.clamp(requested),
+        bounds,
+    })
+}
+
+/// Anzahl der Chunks einer Datei; der letzte Chunk darf kürzer sein
+pub fn total_chunks(file_size: u64, chunk_size: usize) -> usize {
+    file_size.div_ceil(chunk_size as u64) as usize
+}
+
+/// Passt die Zahl gleichzeitig unbestätigter Chunks an die gemessene
+/// Bestätigungslatenz an.
+///
+/// Nach jeder vollständig bestätigten Runde wird der Batch verdoppelt, solange
+/// die langsamste Bestätigung nahe an der besten bisher gemessenen liegt, und
+/// halbiert, sobald sie deutlich darüber liegt (Warteschlange im Netz). Der
+/// Batch umfasst mindestens einen Chunk und höchstens so viele, wie in die
+/// ausgehandelte Obergrenze passen.
+#[derive(Debug)]
+pub struct ChunkBatcher {
+    /// Chunks pro Batch
+    batch: usize,
+
+    /// Obergrenze für `batch`
+    max_batch: usize,
+
+    /// Beste bisher gemessene Latenz
+    baseline: Option<Duration>,
+
+    /// Langsamste Latenz der laufenden Runde
+    round_max: Duration,
+
+    /// Bestätigte Chunks der laufenden Runde
+    round_acked: usize,
+
+    /// Gesendete, noch unbestätigte Chunks
+    in_flight: HashMap<usize, Instant>,
+}
+
+impl ChunkBatcher {
+    /// Erstellt einen Batcher, der mit einem Chunk pro Batch beginnt
+    pub fn new(chunk_size: usize, max_batch_bytes: usize) -> Self {
+        ChunkBatcher {
+            batch: 1,
+            max_batch: (max_batch_bytes / chunk_size.max(1)).max(1),
+            baseline: None,
+            round_max: Duration::ZERO,
+            round_acked: 0,
+            in_flight: HashMap::new(),
+        }
+    }
+
+    /// Aktuelle Batch-Größe in Chunks
+    pub fn batch(&self) -> usize {
+        self.batch
+    }
+
+    /// Ob ein weiterer Chunk gesendet werden darf
+    pub fn has_room(&self) -> bool {
+        self.in_flight.len() < self.batch
+    }
+
+    /// Merkt einen gesendeten Chunk vor
+    pub fn sent(&mut self, chunk_index: usize) {
+        self.in_flight.insert(chunk_index, Instant::now());
+    }
+
+    /// Verbucht eine Bestätigung; unbekannte Chunks werden ignoriert
+    pub fn acked(&mut self, chunk_index: usize) {
+        if let Some(sent_at) = self.in_flight.remove(&chunk_index) {
+            self.record_latency(sent_at.elapsed());
+        }
+    }
+
+    /// Verbucht die Latenz einer Bestätigung und passt am Rundenende den Batch an
+    pub fn record_latency(&mut self, latency: Duration) {
+        let baseline = *self.baseline.get_or_insert(latency);
+        self.baseline = Some(baseline.min(latency));
+        self.round_max = self.round_max.max(latency);
+        self.round_acked += 1;
+
+        if self.round_acked < self.batch {
+            return;
+        }
+
+        let baseline = self.baseline.unwrap_or(latency);
+        if self.round_max > baseline * SLOW_ACK_FACTOR {
+            self.batch = (self.batch / 2).max(1);
+        } else if self.round_max * 8 <= baseline * FAST_ACK_EIGHTHS {
+            self.batch = (self.batch * 2).min(self.max_batch);
+        }
+
+        self.round_max = Duration::ZERO;
+        self.round_acked = 0;
+    }
+
+    /// Gibt Chunks auf, deren Bestätigung länger als `timeout` aussteht, und
+    /// halbiert den Batch; liefert, ob etwas aufgegeben wurde
+    pub fn expire(&mut self, timeout: Duration) -> bool {
+        let before = self.in_flight.len();
+        self.in_flight.retain(|_, sent_at| sent_at.elapsed() < timeout);
+
+        if self.in_flight.len() == before {
+            return false;
+        }
+
+        self.batch = (self.batch / 2).max(1);
+        self.round_max = Duration::ZERO;
+        self.round_acked = 0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KB: usize = 1024;
+
+    fn bounds(min: usize, max: usize) -> ChunkSizeBounds {
+        ChunkSizeBounds { min, max }
+    }
+
+    #[test]
+    fn test_receiver_limits_large_request() {
+        let negotiated = negotiate_chunk_size(1024 * KB, Some(bounds(16 * KB, 1024 * KB)), bounds(4 * KB, 16 * KB)).unwrap();
+        assert_eq!(negotiated.chunk_size, 16 * KB);
+        assert_eq!(negotiated.bounds, bounds(16 * KB, 16 * KB));
+    }
+
+    #[test]
+    fn test_receiver_raises_small_request() {
+        let negotiated = negotiate_chunk_size(16 * KB, Some(bounds(8 * KB, 1024 * KB)), bounds(64 * KB, 256 * KB)).unwrap();
+        assert_eq!(negotiated.chunk_size, 64 * KB);
+        assert_eq!(negotiated.bounds, bounds(64 * KB, 256 * KB));
+    }
+
+    #[test]
+    fn test_disjoint_bounds_are_rejected() {
+        let result = negotiate_chunk_size(16 * KB, Some(bounds(8 * KB, 16 * KB)), bounds(64 * KB, 256 * KB));
+        assert!(matches!(result, Err(FileTransferError::ChunkError(_))));
+    }
+
+    #[test]
+    fn test_fixed_size_sender_is_accepted_as_is() {
+        let negotiated = negotiate_chunk_size(1024 * KB, None, bounds(4 * KB, 16 * KB)).unwrap();
+        assert_eq!(negotiated.chunk_size, 1024 * KB);
+    }
+
+    #[test]
+    fn test_bounds_always_contain_configured_size() {
+        let config = TransferConfig {
+            chunk_size: 2048 * KB,
+            min_chunk_size: 4096 * KB,
+            max_chunk_size: 16 * KB,
+            ..Default::default()
+        };
+        assert_eq!(ChunkSizeBounds::from_config(&config), bounds(2048 * KB, 2048 * KB));
+    }
+
+    #[test]
+    fn test_total_chunks_counts_short_final_chunk() {
+        assert_eq!(total_chunks(0, 16), 0);
+        assert_eq!(total_chunks(16, 16), 1);
+        assert_eq!(total_chunks(17, 16), 2);
+    }
+
+    #[test]
+    fn test_batch_doubles_on_fast_acks_up_to_bound() {
+        let mut batcher = ChunkBatcher::new(16 * KB, 64 * KB);
+        for _ in 0..10 {
+            for _ in 0..batcher.batch() {
+                batcher.record_latency(Duration::from_millis(10));
+            }
+        }
+        assert_eq!(batcher.batch(), 4);
+    }
+
+    #[test]
+    fn test_batch_halves_on_slow_acks() {
+        let mut batcher = ChunkBatcher::new(16 * KB, 1024 * KB);
+        for _ in 0..3 {
+            for _ in 0..batcher.batch() {
+                batcher.record_latency(Duration::from_millis(10));
+            }
+        }
+        assert_eq!(batcher.batch(), 8);
+
+        for _ in 0..batcher.batch() {
+            batcher.record_latency(Duration::from_millis(50));
+        }
+        assert_eq!(batcher.batch(), 4);
+    }
+
+    #[test]
+    fn test_expired_chunks_free_the_window() {
+        let mut batcher = ChunkBatcher::new(16 * KB, 16 * KB);
+        batcher.sent(0);
+        assert!(!batcher.has_room());
+
+        assert!(batcher.expire(Duration::ZERO));
+        assert!(batcher.has_room());
+        assert!(!batcher.expire(Duration::ZERO));
+    }
+}
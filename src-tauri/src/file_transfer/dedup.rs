@@ -0,0 +1,408 @@
+// src-tauri/src/file_transfer/dedup.rs - Bereits vorhandene Chunks nicht erneut übertragen
+//
+// Der Sender schickt die SHA-256-Hashes seiner Chunks mit (kleine Dateien in
+// der Anfrage, große seitenweise auf Anfrage des Empfängers). Beim Annehmen
+// sucht der Empfänger jeden Hash in seinem Chunk-Cache und in der Datei, die
+// schon am Ziel liegt, kopiert Treffer in die neue Datei und meldet dem Sender
+// deren Indizes; diese Chunks werden nicht gesendet. Ein Treffer zählt nur bei
+// gleicher Länge, und die Prüfung des Datei-Hashes beim Abschluss bleibt die
+// letzte Instanz.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::file_transfer::chunk_manager::{ChunkManager, WrittenChunk, MAX_HASH_WORKERS};
+use crate::file_transfer::chunk_sizing::total_chunks;
+use crate::file_transfer::error::FileTransferError;
+use crate::logging::TARGET_TRANSFER;
+
+/// Unterverzeichnis des App-Datenverzeichnisses für den gespeicherten Chunk-Cache
+pub const CHUNK_CACHE_DIR: &str = "chunk-cache";
+
+/// Chunk-Hashes je Manifest-Nachricht; kleinere Dateien schicken sie in der Anfrage
+pub const MANIFEST_PAGE_CHUNKS: usize = 512;
+
+/// Ob `hash` wie ein SHA-256 in Hex aussieht; andere Namen kommen nicht in den Cache
+pub fn is_chunk_hash(hash: &str) -> bool {
+    hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// Länge des Chunks `index` einer Datei von `file_size` Bytes
+pub fn chunk_len(file_size: u64, chunk_size: usize, index: usize) -> usize {
+    let offset = index as u64 * chunk_size as u64;
+    file_size.saturating_sub(offset).min(chunk_size as u64) as usize
+}
+
+struct CachedChunk {
+    data: Vec<u8>,
+    last_used: u64,
+}
+
+/// Zuletzt empfangene Chunks nach ihrem Hash, begrenzt auf `max_bytes`.
+///
+/// Die Daten liegen im Speicher; mit einem Verzeichnis zusätzlich als Datei je
+/// Chunk, damit der Cache einen Neustart übersteht. Beim Laden zählt die
+/// Änderungszeit der Dateien als letzte Nutzung.
+pub struct ChunkCache {
+    max_bytes: u64,
+    bytes: u64,
+    entries: HashMap<String, CachedChunk>,
+    /// Hashes nach letzter Nutzung, die älteste zuerst
+    order: BTreeMap<u64, String>,
+    tick: u64,
+    dir: Option<PathBuf>,
+}
+
+impl ChunkCache {
+    /// Cache nur im Speicher; 0 Bytes schaltet ihn ab
+    pub fn new(max_bytes: u64) -> Self {
+        ChunkCache {
+            max_bytes,
+            bytes: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            tick: 0,
+            dir: None,
+        }
+    }
+
+    /// Cache in `data_dir/chunk-cache`; lädt die dort gespeicherten Chunks.
+    /// Dateien, deren Inhalt nicht zum Namen passt, werden gelöscht.
+    pub fn open(data_dir: &Path, max_bytes: u64) -> Result<Self, FileTransferError> {
+        let dir = data_dir.join(CHUNK_CACHE_DIR);
+        fs::create_dir_all(&dir)?;
+
+        let mut stored = Vec::new();
+        for entry in fs::read_dir(&dir)?.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let modified = entry.metadata().and_then(|metadata| metadata.modified()).ok();
+            match (is_chunk_hash(&name), modified) {
+                (true, Some(modified)) => stored.push((modified, name, path)),
+                _ => {
+                    let _ = fs::remove_file(&path);
+                },
+            }
+        }
+        stored.sort();
+
+        let mut cache = ChunkCache::new(max_bytes);
+        for (_, hash, path) in stored {
+            match fs::read(&path) {
+                Ok(data) if ChunkManager::calculate_chunk_hash(&data) == hash => cache.insert_entry(hash, data),
+                _ => {
+                    log::warn!(target: TARGET_TRANSFER, "Dropping damaged cached chunk {}", path.display());
+                    let _ = fs::remove_file(&path);
+                },
+            }
+        }
+        // Über die Grenze hinaus geladene (ältere) Chunks auch auf der Platte löschen
+        cache.dir = Some(dir);
+        cache.evict(0);
+        Ok(cache)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_bytes > 0
+    }
+
+    /// Belegte Bytes
+    pub fn size(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Daten zum Hash, sofern sie `len` Bytes lang sind; zählt als Nutzung
+    pub fn get(&mut self, hash: &str, len: usize) -> Option<Vec<u8>> {
+        let entry = self.entries.get_mut(hash).filter(|entry| entry.data.len() == len)?;
+        self.order.remove(&entry.last_used);
+        self.tick += 1;
+        entry.last_used = self.tick;
+        self.order.insert(self.tick, hash.to_string());
+
+        if let Some(dir) = &self.dir {
+            let _ = filetime::set_file_mtime(dir.join(hash), filetime::FileTime::now());
+        }
+        Some(entry.data.clone())
+    }
+
+    /// Nimmt einen Chunk auf und verdrängt dafür die am längsten ungenutzten.
+    /// Der Hash wird aus den Daten berechnet, ein Treffer ist also nie falsch.
+    pub fn insert(&mut self, data: Vec<u8>) {
+        if data.is_empty() || data.len() as u64 > self.max_bytes {
+            return;
+        }
+        let hash = ChunkManager::calculate_chunk_hash(&data);
+        if self.entries.contains_key(&hash) {
+            return;
+        }
+
+        self.evict(data.len() as u64);
+        if let Some(dir) = &self.dir {
+            if let Err(e) = fs::write(dir.join(&hash), &data) {
+                log::warn!(target: TARGET_TRANSFER, "Failed to store chunk in cache: {}", e);
+                return;
+            }
+        }
+        self.insert_entry(hash, data);
+    }
+
+    fn insert_entry(&mut self, hash: String, data: Vec<u8>) {
+        self.tick += 1;
+        self.bytes += data.len() as u64;
+        self.order.insert(self.tick, hash.clone());
+        self.entries.insert(hash, CachedChunk { data, last_used: self.tick });
+    }
+
+    /// Verdrängt, bis `incoming` weitere Bytes unter die Grenze passen
+    fn evict(&mut self, incoming: u64) {
+        let limit = self.max_bytes.saturating_sub(incoming);
+        while self.bytes > limit {
+            let Some((_, hash)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&hash) {
+                self.bytes -= entry.data.len() as u64;
+            }
+            if let Some(dir) = &self.dir {
+                let _ = fs::remove_file(dir.join(&hash));
+            }
+        }
+    }
+}
+
+/// Übernimmt die Chunks aus `manifest`, die im Cache oder in `existing` schon
+/// vorliegen, in die Datei `target` und liefert sie mit ihrem Hash.
+///
+/// `existing` (meist die Datei am Ziel) wird ganz gehasht, damit auch
+/// verschobene Chunks gefunden werden; jeder übernommene Chunk wird beim
+/// Kopieren erneut geprüft. Ist `existing` die Zieldatei selbst, zählen nur
+/// Chunks, die schon an ihrer Stelle stehen. Der Cache ist nur je Chunk
+/// gesperrt, damit parallel empfangene Chunks ihn weiter füllen können.
+pub fn reuse_chunks(
+    manifest: &[String],
+    file_size: u64,
+    chunk_size: usize,
+    target: &Path,
+    existing: Option<&Path>,
+    cache: &Mutex<ChunkCache>,
+) -> Result<Vec<(usize, WrittenChunk)>, FileTransferError> {
+    let existing = existing.filter(|path| path.is_file());
+    let in_place = existing.is_some_and(|path| path == target);
+    let (existing_file, existing_size, existing_hashes) = match existing {
+        Some(path) => (
+            Some(File::open(path)?),
+            fs::metadata(path)?.len(),
+            ChunkManager::new().hash_chunks(path, chunk_size, MAX_HASH_WORKERS)?,
+        ),
+        None => (None, 0, Vec::new()),
+    };
+    // Erste Stelle je Hash; in der Zieldatei selbst wird nichts verschoben,
+    // da ein kopierter Chunk die Quelle eines späteren überschreiben könnte
+    let mut existing_index = HashMap::new();
+    if !in_place {
+        for (position, hash) in existing_hashes.iter().enumerate().rev() {
+            existing_index.insert(hash.as_str(), position);
+        }
+    }
+
+    let target_file = OpenOptions::new().write(true).open(target)?;
+    let mut reused = Vec::new();
+    for (index, hash) in manifest.iter().enumerate().take(total_chunks(file_size, chunk_size)) {
+        let len = chunk_len(file_size, chunk_size, index);
+        let offset = index as u64 * chunk_size as u64;
+
+        if in_place && existing_hashes.get(index) == Some(hash) && chunk_len(existing_size, chunk_size, index) == len {
+            reused.push((index, WrittenChunk { len, hash: hash.clone() }));
+            continue;
+        }
+
+        let cached = cache.lock().unwrap().get(hash, len);
+        let data = match (cached, &existing_file, existing_index.get(hash.as_str())) {
+            (Some(data), _, _) => data,
+            (None, Some(file), Some(&position)) if chunk_len(existing_size, chunk_size, position) == len => {
+                let mut data = vec![0; len];
+                file.read_exact_at(&mut data, position as u64 * chunk_size as u64)?;
+                data
+            },
+            _ => continue,
+        };
+
+        // Die Datei am Ziel kann sich seit dem Hashen geändert haben
+        if ChunkManager::calculate_chunk_hash(&data) != *hash {
+            continue;
+        }
+        target_file.write_all_at(&data, offset)?;
+        reused.push((index, WrittenChunk { len, hash: hash.clone() }));
+    }
+
+    Ok(reused)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use uuid::Uuid;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("smoldesk-dedup-{}-{}", name, Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Pseudozufällige, nicht periodische Daten
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed * 2 + 1;
+        (0..len).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        }).collect()
+    }
+
+    fn hash(data: &[u8]) -> String {
+        ChunkManager::calculate_chunk_hash(data)
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let (a, b, c) = (noise(100, 1), noise(100, 2), noise(100, 3));
+        let mut cache = ChunkCache::new(250);
+        cache.insert(a.clone());
+        cache.insert(b.clone());
+
+        // a wird genutzt, also verdrängt c den Chunk b
+        assert_eq!(cache.get(&hash(&a), 100), Some(a.clone()));
+        cache.insert(c.clone());
+        assert_eq!(cache.size(), 200);
+        assert!(cache.get(&hash(&b), 100).is_none());
+        assert!(cache.get(&hash(&c), 100).is_some());
+
+        // Gleicher Hash, aber andere Länge gilt nicht als Treffer
+        assert!(cache.get(&hash(&a), 99).is_none());
+        // Größer als der ganze Cache: wird nicht aufgenommen
+        cache.insert(noise(300, 4));
+        assert_eq!(cache.size(), 200);
+        assert!(!ChunkCache::new(0).is_enabled());
+    }
+
+    #[test]
+    fn test_persisted_cache_survives_reopen() {
+        let dir = test_dir("persist");
+        let (a, b) = (noise(100, 1), noise(100, 2));
+        {
+            let mut cache = ChunkCache::open(&dir, 1000).unwrap();
+            cache.insert(a.clone());
+            cache.insert(b.clone());
+        }
+        // Eine beschädigte Datei wird beim Laden verworfen
+        fs::write(dir.join(CHUNK_CACHE_DIR).join(hash(&b)), b"damaged").unwrap();
+        fs::write(dir.join(CHUNK_CACHE_DIR).join("stray.tmp"), b"x").unwrap();
+
+        let mut cache = ChunkCache::open(&dir, 1000).unwrap();
+        assert_eq!(cache.get(&hash(&a), 100), Some(a.clone()));
+        assert!(cache.get(&hash(&b), 100).is_none());
+        assert_eq!(fs::read_dir(dir.join(CHUNK_CACHE_DIR)).unwrap().count(), 1);
+
+        // Mit kleinerer Grenze geöffnet, bleibt nur, was hineinpasst
+        cache.insert(b.clone());
+        drop(cache);
+        let cache = ChunkCache::open(&dir, 150).unwrap();
+        assert_eq!(cache.size(), 100);
+        assert_eq!(fs::read_dir(dir.join(CHUNK_CACHE_DIR)).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reuse_finds_shifted_and_cached_chunks() {
+        let dir = test_dir("reuse");
+        let chunk_size = 64;
+        let old = noise(640, 7);
+        let existing = dir.join("existing.bin");
+        fs::write(&existing, &old).unwrap();
+
+        // Neue Version: ein Chunk vorn eingefügt, einer geändert, ein kurzer Rest
+        let inserted = noise(64, 8);
+        let mut new = inserted.clone();
+        new.extend_from_slice(&old[..320]);
+        new.extend_from_slice(&noise(64, 9));
+        new.extend_from_slice(&old[384..600]);
+        let manifest: Vec<String> = new.chunks(chunk_size).map(hash).collect();
+
+        let target = dir.join("target.bin");
+        ChunkManager::new().preallocate(&target, new.len() as u64).unwrap();
+        let cache = Mutex::new(ChunkCache::new(1024));
+        cache.lock().unwrap().insert(inserted);
+        let reused = reuse_chunks(&manifest, new.len() as u64, chunk_size, &target, Some(&existing), &cache).unwrap();
+
+        // Der erste kommt aus dem Cache, die übrigen verschoben aus der alten
+        // Datei; es fehlen der geänderte und der kurze Rest, den es dort nur in
+        // voller Länge gibt
+        let indices: Vec<usize> = reused.iter().map(|(index, _)| *index).collect();
+        assert_eq!(indices, vec![0, 1, 2, 3, 4, 5, 7, 8, 9]);
+        let written = fs::read(&target).unwrap();
+        for (index, chunk) in &reused {
+            let range = index * chunk_size..index * chunk_size + chunk.len;
+            assert_eq!(&written[range.clone()], &new[range]);
+            assert_eq!(chunk.hash, manifest[*index]);
+        }
+
+        // Ist die vorhandene Datei das Ziel selbst, zählen nur Chunks an ihrer Stelle
+        let reused = reuse_chunks(&manifest, new.len() as u64, chunk_size, &existing, Some(&existing), &Mutex::new(ChunkCache::new(0))).unwrap();
+        assert!(reused.is_empty());
+        let same: Vec<String> = old.chunks(chunk_size).map(hash).collect();
+        let reused = reuse_chunks(&same, old.len() as u64, chunk_size, &existing, Some(&existing), &Mutex::new(ChunkCache::new(0))).unwrap();
+        assert_eq!(reused.len(), 10);
+        assert_eq!(fs::read(&existing).unwrap(), old);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Wie viel eine erneute Übertragung einer zu 5 % geänderten Datei spart:
+    ///
+    /// `SMOLDESK_BENCH_MB=1024 cargo test --release bench_modified_file -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_modified_file() {
+        let size_mb: usize = std::env::var("SMOLDESK_BENCH_MB").ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(1024);
+        let chunk_size = 64 * 1024;
+        let dir = test_dir("bench");
+        let old_path = dir.join("old.img");
+        let new_path = dir.join("new.img");
+        let target = dir.join("target.img");
+
+        let mut data = noise(size_mb * 1024 * 1024, 42);
+        fs::write(&old_path, &data).unwrap();
+        // 5 % der Chunks an verstreuten Stellen ändern
+        let chunks = total_chunks(data.len() as u64, chunk_size);
+        for index in (0..chunks).step_by(20) {
+            data[index * chunk_size] ^= 0xff;
+        }
+        fs::write(&new_path, &data).unwrap();
+        drop(data);
+
+        let manager = ChunkManager::new();
+        let started = Instant::now();
+        let manifest = manager.hash_chunks(&new_path, chunk_size, MAX_HASH_WORKERS).unwrap();
+        let hashing = started.elapsed();
+
+        let started = Instant::now();
+        manager.preallocate(&target, fs::metadata(&new_path).unwrap().len()).unwrap();
+        let reused = reuse_chunks(&manifest, fs::metadata(&new_path).unwrap().len(), chunk_size, &target, Some(&old_path), &Mutex::new(ChunkCache::new(0))).unwrap();
+        let reusing = started.elapsed();
+
+        let reused_bytes: usize = reused.iter().map(|(_, chunk)| chunk.len).sum();
+        let saved = reused_bytes as f64 / fs::metadata(&new_path).unwrap().len() as f64;
+        println!("{} MB in {} KB chunks, 5 % changed", size_mb, chunk_size / 1024);
+        println!("sender hashing: {:?}, receiver reuse: {:?}", hashing, reusing);
+        println!("reused {} of {} chunks, {:.1} % of the bytes not transferred", reused.len(), chunks, saved * 100.0);
+        assert!((0.94..0.96).contains(&saved));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,478 @@
+// src-tauri/src/file_transfer/drop.rs - Ablegen per Drag & Drop empfangener Dateien auf dem Host
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::error::FileTransferError;
+use super::staging::move_into_place;
+use super::types::TransferEvent;
+use crate::command_runner::{CommandRunner, SystemCommandRunner};
+use crate::input_forwarding::factory::detect_display_server;
+use crate::input_forwarding::types::DisplayServer;
+use crate::logging::TARGET_TRANSFER;
+
+/// Ablageordner relativ zum Home-Verzeichnis, wenn kein Fenster als Ziel taugt
+pub const DEFAULT_DROP_FOLDER: &str = "Desktop/SmolDesk Drops";
+
+/// Wohin eine abgelegte Datei verschoben wird
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DropTargetMode {
+    /// Arbeitsverzeichnis des Fensters unter der Ablageposition, sonst Ablageordner
+    WindowDirectory,
+
+    /// Immer in den Ablageordner
+    DropFolder,
+}
+
+/// Konfiguration für das Ablegen von Dateien
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DropConfig {
+    /// Zielauswahl
+    pub mode: DropTargetMode,
+
+    /// Ablageordner (None = ~/Desktop/SmolDesk Drops)
+    #[serde(default)]
+    pub drop_folder: Option<PathBuf>,
+}
+
+impl Default for DropConfig {
+    fn default() -> Self {
+        DropConfig {
+            mode: DropTargetMode::WindowDirectory,
+            drop_folder: None,
+        }
+    }
+}
+
+impl DropConfig {
+    /// Aufgelöster Ablageordner
+    pub fn resolved_drop_folder(&self) -> Result<PathBuf, FileTransferError> {
+        if let Some(folder) = &self.drop_folder {
+            return Ok(folder.clone());
+        }
+        home_dir()
+            .map(|home| home.join(DEFAULT_DROP_FOLDER))
+            .ok_or_else(|| FileTransferError::IoError("Home directory not found".to_string()))
+    }
+}
+
+/// Ablageposition in absoluten Host-Koordinaten
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DropPosition {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Eine laufende Ablage-Geste
+#[derive(Debug, Clone)]
+pub struct FileDrop {
+    pub drop_id: String,
+    pub transfer_id: String,
+    pub peer_id: String,
+    pub position: DropPosition,
+
+    /// Temporäres Verzeichnis, in das die Datei zunächst empfangen wird
+    pub staging_dir: PathBuf,
+
+    /// Zielpfad der Übertragung innerhalb von `staging_dir`
+    pub staged_path: PathBuf,
+}
+
+/// Events für das UI, verknüpft mit der Ablage-Geste über `drop_id`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DropEvent {
+    DropProgress {
+        drop_id: String,
+        transfer_id: String,
+        position: DropPosition,
+        bytes_transferred: u64,
+        total_bytes: u64,
+    },
+    DropCompleted {
+        drop_id: String,
+        transfer_id: String,
+        position: DropPosition,
+        path: PathBuf,
+    },
+    DropFailed {
+        drop_id: String,
+        transfer_id: String,
+        position: DropPosition,
+        error: String,
+    },
+}
+
+impl DropEvent {
+    /// Name des Tauri-Events, auf das das Frontend hört
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            DropEvent::DropProgress { .. } => "file_drop_progress",
+            DropEvent::DropCompleted { .. } => "file_drop_completed",
+            DropEvent::DropFailed { .. } => "file_drop_failed",
+        }
+    }
+}
+
+/// Empfänger für Ablage-Events
+pub type DropListener = Box<dyn Fn(&DropEvent) + Send + Sync>;
+
+/// Verwaltet Ablage-Gesten und legt fertige Dateien am Ziel ab
+pub struct DropManager {
+    config: Mutex<DropConfig>,
+
+    /// Laufende Ablagen nach Übertragungs-ID
+    pending: Mutex<HashMap<String, FileDrop>>,
+
+    /// Basisverzeichnis für Staging-Verzeichnisse
+    staging_root: PathBuf,
+
+    listener: Mutex<Option<DropListener>>,
+}
+
+impl DropManager {
+    pub fn new() -> Self {
+        Self::with_staging_root(std::env::temp_dir().join("smoldesk-drops"))
+    }
+
+    pub fn with_staging_root(staging_root: PathBuf) -> Self {
+        DropManager {
+            config: Mutex::new(DropConfig::default()),
+            pending: Mutex::new(HashMap::new()),
+            staging_root,
+            listener: Mutex::new(None),
+        }
+    }
+
+    pub fn config(&self) -> DropConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    pub fn set_config(&self, config: DropConfig) {
+        *self.config.lock().unwrap() = config;
+    }
+
+    pub fn set_listener(&self, listener: DropListener) {
+        *self.listener.lock().unwrap() = Some(listener);
+    }
+
+    /// Legt ein Staging-Verzeichnis für die Übertragung an und merkt sich die Ablage
+    pub fn stage(
+        &self,
+        peer_id: &str,
+        transfer_id: &str,
+        file_name: &str,
+        position: DropPosition,
+    ) -> Result<FileDrop, FileTransferError> {
+        // Nur den Dateinamen übernehmen, damit der Peer nicht aus dem Staging ausbricht
+        let file_name = Path::new(file_name).file_name()
+            .ok_or_else(|| FileTransferError::InvalidFileType(format!("Invalid file name: {}", file_name)))?;
+
+        let drop_id = Uuid::new_v4().to_string();
+        let staging_dir = self.staging_root.join(&drop_id);
+        fs::create_dir_all(&staging_dir)
+            .map_err(|e| FileTransferError::IoError(e.to_string()))?;
+
+        let file_drop = FileDrop {
+            drop_id,
+            transfer_id: transfer_id.to_string(),
+            peer_id: peer_id.to_string(),
+            position,
+            staged_path: staging_dir.join(file_name),
+            staging_dir,
+        };
+        self.pending.lock().unwrap().insert(transfer_id.to_string(), file_drop.clone());
+        Ok(file_drop)
+    }
+
+    /// Verwirft eine Ablage, etwa wenn die Annahme der Übertragung fehlschlug
+    pub fn discard(&self, transfer_id: &str) {
+        if let Some(file_drop) = self.pending.lock().unwrap().remove(transfer_id) {
+            let _ = fs::remove_dir_all(&file_drop.staging_dir);
+        }
+    }
+
+    /// Verarbeitet ein Übertragungs-Event; betrifft es eine Ablage, wird ein Ablage-Event gemeldet
+    pub fn handle_transfer_event(&self, event: &TransferEvent) -> Option<DropEvent> {
+        let drop_event = match event {
+            TransferEvent::TransferProgress { transfer_id, progress } => {
+                let pending = self.pending.lock().unwrap();
+                let file_drop = pending.get(transfer_id)?;
+                DropEvent::DropProgress {
+                    drop_id: file_drop.drop_id.clone(),
+                    transfer_id: transfer_id.clone(),
+                    position: file_drop.position,
+                    bytes_transferred: progress.bytes_transferred,
+                    total_bytes: progress.total_bytes,
+                }
+            },
+            TransferEvent::TransferCompleted { transfer_id } => {
+                let file_drop = self.pending.lock().unwrap().remove(transfer_id)?;
+                let result = self.deliver(&file_drop);
+                let _ = fs::remove_dir_all(&file_drop.staging_dir);
+                match result {
+                    Ok(path) => {
+                        log::info!(target: TARGET_TRANSFER, "Dropped {} at {}", transfer_id, path.display());
+                        DropEvent::DropCompleted {
+                            drop_id: file_drop.drop_id,
+                            transfer_id: transfer_id.clone(),
+                            position: file_drop.position,
+                            path,
+                        }
+                    },
+                    Err(e) => {
+                        log::error!(target: TARGET_TRANSFER, "Failed to drop {}: {}", transfer_id, e);
+                        DropEvent::DropFailed {
+                            drop_id: file_drop.drop_id,
+                            transfer_id: transfer_id.clone(),
+                            position: file_drop.position,
+                            error: e.to_string(),
+                        }
+                    },
+                }
+            },
+            TransferEvent::TransferFailed { transfer_id, error } => {
+                self.abort(transfer_id, error.clone())?
+            },
+            TransferEvent::TransferCancelled { transfer_id } => {
+                self.abort(transfer_id, "Transfer cancelled".to_string())?
+            },
+            TransferEvent::TransferRejected { transfer_id, reason } => {
+                self.abort(transfer_id, reason.clone())?
+            },
+            _ => return None,
+        };
+
+        if let Some(listener) = &*self.listener.lock().unwrap() {
+            listener(&drop_event);
+        }
+        Some(drop_event)
+    }
+
+    /// Entfernt eine abgebrochene Ablage samt Staging-Verzeichnis
+    fn abort(&self, transfer_id: &str, error: String) -> Option<DropEvent> {
+        let file_drop = self.pending.lock().unwrap().remove(transfer_id)?;
+        let _ = fs::remove_dir_all(&file_drop.staging_dir);
+        Some(DropEvent::DropFailed {
+            drop_id: file_drop.drop_id,
+            transfer_id: transfer_id.to_string(),
+            position: file_drop.position,
+            error,
+        })
+    }
+
+    /// Verschiebt die empfangene Datei an ihr Ziel
+    fn deliver(&self, file_drop: &FileDrop) -> Result<PathBuf, FileTransferError> {
+        let config = self.config();
+        let target_dir = match config.mode {
+            DropTargetMode::WindowDirectory => match window_directory_at(file_drop.position) {
+                Some(dir) => dir,
+                None => config.resolved_drop_folder()?,
+            },
+            DropTargetMode::DropFolder => config.resolved_drop_folder()?,
+        };
+        fs::create_dir_all(&target_dir)
+            .map_err(|e| FileTransferError::IoError(e.to_string()))?;
+
+        let file_name = file_drop.staged_path.file_name()
+            .ok_or_else(|| FileTransferError::InvalidFileType(file_drop.staged_path.display().to_string()))?;
+        let destination = unique_destination(&target_dir, &file_name.to_string_lossy());
+        move_into_place(&file_drop.staged_path, &destination)?;
+        Ok(destination)
+    }
+}
+
+/// Freier Zielpfad im Verzeichnis; bei Namenskonflikten "name (1).ext", "name (2).ext", ...
+pub fn unique_destination(dir: &Path, file_name: &str) -> PathBuf {
+    let candidate = dir.join(file_name);
+    if !candidate.exists() {
+        return candidate;
+    }
+
+    let path = Path::new(file_name);
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let extension = path.extension().map(|e| e.to_string_lossy().into_owned());
+    (1..)
+        .map(|n| match &extension {
+            Some(ext) => dir.join(format!("{} ({}).{}", stem, n, ext)),
+            None => dir.join(format!("{} ({})", stem, n)),
+        })
+        .find(|candidate| !candidate.exists())
+        .unwrap()
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").filter(|home| !home.is_empty()).map(PathBuf::from)
+}
+
+/// Arbeitsverzeichnis des Fensters unter der Position (nur X11)
+///
+/// Bewegt dabei den Mauszeiger auf die Ablageposition, wie es eine echte Ablage täte.
+fn window_directory_at(position: DropPosition) -> Option<PathBuf> {
+    if detect_display_server() != DisplayServer::X11 {
+        return None;
+    }
+
+    let output = SystemCommandRunner.command("xdotool")
+        .args(["mousemove", &position.x.to_string(), &position.y.to_string(), "getmouselocation", "--shell"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let window = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("WINDOW=").map(str::to_string))?;
+
+    let output = SystemCommandRunner.command("xdotool")
+        .args(["getwindowpid", &window])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let pid = String::from_utf8_lossy(&output.stdout).trim().parse::<u32>().ok()?;
+
+    let cwd = fs::read_link(format!("/proc/{}/cwd", pid)).ok()?;
+    usable_window_directory(&cwd, home_dir().as_deref())
+}
+
+/// Prüft, ob das Arbeitsverzeichnis eines Fensters als Ziel taugt
+///
+/// Aus dem Desktop gestartete Programme laufen im Home- oder Wurzelverzeichnis;
+/// das sagt nichts über das Fenster aus, daher gilt dann der Ablageordner.
+fn usable_window_directory(cwd: &Path, home: Option<&Path>) -> Option<PathBuf> {
+    if cwd == Path::new("/") || Some(cwd) == home || !cwd.is_dir() {
+        return None;
+    }
+    let writable = fs::metadata(cwd).map(|m| !m.permissions().readonly()).unwrap_or(false);
+    writable.then(|| cwd.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::types::TransferProgress;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("smoldesk-drop-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn manager(root: &Path) -> DropManager {
+        let manager = DropManager::with_staging_root(root.join("staging"));
+        manager.set_config(DropConfig {
+            mode: DropTargetMode::DropFolder,
+            drop_folder: Some(root.join("drops")),
+        });
+        manager
+    }
+
+    const POSITION: DropPosition = DropPosition { x: 100, y: 200 };
+
+    #[test]
+    fn test_unique_destination() {
+        let dir = test_dir("unique");
+        assert_eq!(unique_destination(&dir, "report.pdf"), dir.join("report.pdf"));
+
+        fs::write(dir.join("report.pdf"), b"a").unwrap();
+        fs::write(dir.join("report (1).pdf"), b"b").unwrap();
+        assert_eq!(unique_destination(&dir, "report.pdf"), dir.join("report (2).pdf"));
+
+        fs::write(dir.join("notes"), b"c").unwrap();
+        assert_eq!(unique_destination(&dir, "notes"), dir.join("notes (1)"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_stage_strips_directories() {
+        let dir = test_dir("stage");
+        let manager = manager(&dir);
+
+        let file_drop = manager.stage("peer", "t1", "../../etc/passwd", POSITION).unwrap();
+        assert_eq!(file_drop.staged_path, file_drop.staging_dir.join("passwd"));
+        assert!(file_drop.staging_dir.starts_with(dir.join("staging")));
+        assert!(manager.stage("peer", "t2", "..", POSITION).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_completed_drop_lands_in_drop_folder() {
+        let dir = test_dir("complete");
+        let manager = manager(&dir);
+
+        let file_drop = manager.stage("peer", "t1", "photo.png", POSITION).unwrap();
+        fs::write(&file_drop.staged_path, b"png").unwrap();
+
+        let progress = manager.handle_transfer_event(&TransferEvent::TransferProgress {
+            transfer_id: "t1".to_string(),
+            progress: TransferProgress {
+                bytes_transferred: 1,
+                total_bytes: 3,
+                chunks_completed: 1,
+                total_chunks: 3,
+                transfer_rate: 0.0,
+                eta_seconds: None,
+                bytes_deduplicated: 0,
+            },
+        });
+        match progress {
+            Some(DropEvent::DropProgress { drop_id, bytes_transferred, .. }) => {
+                assert_eq!(drop_id, file_drop.drop_id);
+                assert_eq!(bytes_transferred, 1);
+            },
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let completed = manager.handle_transfer_event(&TransferEvent::TransferCompleted {
+            transfer_id: "t1".to_string(),
+        });
+        match completed {
+            Some(DropEvent::DropCompleted { drop_id, path, .. }) => {
+                assert_eq!(drop_id, file_drop.drop_id);
+                assert_eq!(path, dir.join("drops").join("photo.png"));
+                assert_eq!(fs::read(&path).unwrap(), b"png");
+            },
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert!(!file_drop.staging_dir.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_cancelled_drop_cleans_up() {
+        let dir = test_dir("cancel");
+        let manager = manager(&dir);
+
+        let file_drop = manager.stage("peer", "t1", "video.mp4", POSITION).unwrap();
+        let event = manager.handle_transfer_event(&TransferEvent::TransferCancelled {
+            transfer_id: "t1".to_string(),
+        });
+        assert!(matches!(event, Some(DropEvent::DropFailed { .. })));
+        assert!(!file_drop.staging_dir.exists());
+
+        // Übertragungen ohne Ablage werden ignoriert
+        assert!(manager.handle_transfer_event(&TransferEvent::TransferCompleted {
+            transfer_id: "other".to_string(),
+        }).is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_usable_window_directory() {
+        let dir = test_dir("cwd");
+        let home = Path::new("/home/user");
+
+        assert_eq!(usable_window_directory(&dir, Some(home)), Some(dir.clone()));
+        assert_eq!(usable_window_directory(Path::new("/"), Some(home)), None);
+        assert_eq!(usable_window_directory(&dir, Some(&dir)), None);
+        assert_eq!(usable_window_directory(&dir.join("missing"), Some(home)), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
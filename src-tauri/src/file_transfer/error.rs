@@ -0,0 +1,98 @@
+// src-tauri/src/file_transfer/error.rs - Fehlerbehandlung für Dateiübertragungen
+
+use std::error::Error;
+use std::fmt;
+
+use crate::protocol::VersionMismatch;
+
+/// Fehlertypen für Dateiübertragungen
+#[derive(Debug)]
+pub enum FileTransferError {
+    /// Datei nicht gefunden
+    FileNotFound(String),
+
+    /// Ungültiger Dateityp (z.B. Verzeichnis statt Datei)
+    InvalidFileType(String),
+
+    /// I/O-Fehler
+    IoError(String),
+
+    /// Datei überschreitet die maximale Größe
+    FileTooLarge(u64, u64), // (actual_size, max_size)
+
+    /// Übertragung nicht gefunden
+    TransferNotFound(String),
+
+    /// Operation im aktuellen Zustand nicht erlaubt
+    InvalidOperation(String),
+
+    /// Hash der Datei stimmt nicht überein
+    HashMismatch {
+        expected: String,
+        actual: String,
+    },
+
+    /// Fehler beim Lesen oder Schreiben eines Chunks
+    ChunkError(String),
+
+    /// Schlüsselaustausch fehlgeschlagen oder kein Schlüssel vorhanden
+    KeyExchangeFailed(String),
+
+    /// Größe der empfangenen Datei weicht von den Metadaten ab
+    SizeMismatch {
+        expected: u64,
+        actual: u64,
+    },
+
+    /// Chunk konnte nicht entschlüsselt werden (falscher Schlüssel, manipuliert oder vertauscht)
+    DecryptionFailed {
+        transfer_id: String,
+        chunk_index: usize,
+    },
+
+    /// Netzwerkfehler
+    NetworkError(String),
+
+    /// Gegenstelle spricht eine nicht unterstützte Protokollversion
+    VersionMismatch(VersionMismatch),
+
+    /// Quittung unlesbar, verändert oder von einem anderen Schlüssel signiert
+    ReceiptInvalid(String),
+}
+
+impl fmt::Display for FileTransferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileTransferError::FileNotFound(path) => write!(f, "File not found: {}", path),
+            FileTransferError::InvalidFileType(msg) => write!(f, "Invalid file type: {}", msg),
+            FileTransferError::IoError(msg) => write!(f, "I/O error: {}", msg),
+            FileTransferError::FileTooLarge(actual, max) => {
+                write!(f, "File too large: {} bytes (max: {} bytes)", actual, max)
+            },
+            FileTransferError::TransferNotFound(id) => write!(f, "Transfer not found: {}", id),
+            FileTransferError::InvalidOperation(msg) => write!(f, "Invalid operation: {}", msg),
+            FileTransferError::HashMismatch { expected, actual } => {
+                write!(f, "Hash mismatch: expected {}, got {}", expected, actual)
+            },
+            FileTransferError::SizeMismatch { expected, actual } => {
+                write!(f, "Size mismatch: expected {} bytes, got {}", expected, actual)
+            },
+            FileTransferError::ChunkError(msg) => write!(f, "Chunk error: {}", msg),
+            FileTransferError::KeyExchangeFailed(msg) => write!(f, "Key exchange failed: {}", msg),
+            FileTransferError::DecryptionFailed { transfer_id, chunk_index } => {
+                write!(f, "Failed to decrypt chunk {} of transfer {}", chunk_index, transfer_id)
+            },
+            FileTransferError::NetworkError(msg) => write!(f, "Network error: {}", msg),
+            FileTransferError::VersionMismatch(mismatch) => mismatch.fmt(f),
+            FileTransferError::ReceiptInvalid(msg) => write!(f, "Invalid transfer receipt: {}", msg),
+        }
+    }
+}
+
+impl Error for FileTransferError {}
+
+impl From<std::io::Error> for FileTransferError {
+    fn from(error: std::io::Error) -> Self {
+        FileTransferError::IoError(error.to_string())
+    }
+}
@@ -0,0 +1,412 @@
+// src-tauri/src/file_transfer/history.rs - Verlauf abgeschlossener Übertragungen
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::file_transfer::error::FileTransferError;
+use crate::file_transfer::receipt::TransferReceipt;
+use crate::file_transfer::types::{TransferSession, TransferType};
+use crate::logging::TARGET_TRANSFER;
+
+/// Append-only-Log im App-Datenverzeichnis, ein JSON-Objekt pro Zeile
+const HISTORY_FILE: &str = "transfer_history.jsonl";
+
+/// Standardgröße einer Seite
+pub const DEFAULT_HISTORY_PAGE_SIZE: usize = 50;
+
+/// Ausgang einer Übertragung
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TransferOutcome {
+    Completed,
+    Cancelled,
+    Rejected,
+    Failed,
+}
+
+/// Ein Eintrag im Übertragungsverlauf
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferHistoryEntry {
+    pub transfer_id: String,
+    pub transfer_type: TransferType,
+    pub peer_id: String,
+    pub file_name: String,
+
+    /// Dateigröße laut Metadaten
+    pub size: u64,
+
+    /// Tatsächlich übertragene Bytes
+    pub bytes_transferred: u64,
+
+    /// Datei-Hash der Anfrage
+    pub file_hash: Option<String>,
+
+    /// Lokaler Pfad (Quelle beim Upload, Ziel beim Download)
+    pub local_path: Option<PathBuf>,
+
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub outcome: TransferOutcome,
+
+    /// Fehler- oder Ablehnungsgrund
+    pub reason: Option<String>,
+
+    /// Signierte Quittung abgeschlossener Übertragungen
+    #[serde(default)]
+    pub receipt: Option<TransferReceipt>,
+}
+
+impl TransferHistoryEntry {
+    /// Erstellt den Eintrag für eine beendete Session
+    pub fn from_session(session: &TransferSession, outcome: TransferOutcome, reason: Option<String>) -> Self {
+        let finished_at = Utc::now();
+        let elapsed = session.started_at.elapsed();
+        let local_path = match session.transfer_type {
+            TransferType::Upload => session.source_path.clone(),
+            TransferType::Download => session.destination_path.clone(),
+        };
+
+        TransferHistoryEntry {
+            transfer_id: session.id.clone(),
+            transfer_type: session.transfer_type.clone(),
+            peer_id: session.peer_id.clone(),
+            file_name: session.file_metadata.name.clone(),
+            size: session.file_metadata.size,
+            bytes_transferred: session.progress.bytes_transferred,
+            file_hash: session.file_hash.clone(),
+            local_path,
+            started_at: finished_at - Duration::from_std(elapsed).unwrap_or_else(|_| Duration::zero()),
+            finished_at,
+            duration_ms: elapsed.as_millis() as u64,
+            outcome,
+            reason,
+            receipt: None,
+        }
+    }
+}
+
+/// Filter und Seite für Abfragen des Verlaufs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransferHistoryQuery {
+    pub peer_id: Option<String>,
+    pub outcome: Option<TransferOutcome>,
+    pub transfer_type: Option<TransferType>,
+
+    /// Nur Übertragungen, die ab diesem Zeitpunkt endeten
+    pub from: Option<DateTime<Utc>>,
+
+    /// Nur Übertragungen, die vor diesem Zeitpunkt endeten
+    pub to: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub offset: usize,
+
+    /// Einträge pro Seite (None = DEFAULT_HISTORY_PAGE_SIZE)
+    pub limit: Option<usize>,
+}
+
+impl TransferHistoryQuery {
+    fn matches(&self, entry: &TransferHistoryEntry) -> bool {
+        self.peer_id.as_ref().map_or(true, |peer| &entry.peer_id == peer)
+            && self.outcome.map_or(true, |outcome| entry.outcome == outcome)
+            && self.transfer_type.as_ref().map_or(true, |kind| &entry.transfer_type == kind)
+            && self.from.map_or(true, |from| entry.finished_at >= from)
+            && self.to.map_or(true, |to| entry.finished_at < to)
+    }
+}
+
+/// Eine Seite des Verlaufs, neueste Einträge zuerst
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferHistoryPage {
+    pub entries: Vec<TransferHistoryEntry>,
+
+    /// Anzahl aller Treffer über alle Seiten
+    pub total: usize,
+    pub offset: usize,
+}
+
+/// Schreibaufträge an den Hintergrund-Thread
+enum HistoryWrite {
+    Append(Box<TransferHistoryEntry>),
+    Rewrite(Vec<TransferHistoryEntry>),
+    /// Bestätigt, sobald alle vorherigen Aufträge geschrieben sind
+    Flush(Sender<()>),
+}
+
+/// Verlauf im Speicher, auf Platte geschrieben von einem eigenen Thread,
+/// damit Übertragungen nie auf Dateisystemzugriffe warten
+pub struct TransferHistory {
+    entries: Arc<Mutex<Vec<TransferHistoryEntry>>>,
+
+    /// Kanal zum Schreib-Thread; None, solange kein Datenverzeichnis gesetzt ist
+    writer: Mutex<Option<Sender<HistoryWrite>>>,
+}
+
+impl Default for TransferHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransferHistory {
+    /// Erstellt einen Verlauf, der nur im Speicher liegt
+    pub fn new() -> Self {
+        TransferHistory {
+            entries: Arc::new(Mutex::new(Vec::new())),
+            writer: Mutex::new(None),
+        }
+    }
+
+    /// Lädt den Verlauf aus dem Datenverzeichnis und schreibt ab jetzt dorthin.
+    /// Einträge außerhalb der Aufbewahrungsdauer werden dabei verworfen.
+    pub fn open(&self, data_dir: &Path, retention_days: Option<u32>) -> Result<(), FileTransferError> {
+        fs::create_dir_all(data_dir)?;
+        let path = data_dir.join(HISTORY_FILE);
+
+        let mut loaded = load_entries(&path)?;
+        let loaded_count = loaded.len();
+        retain_recent(&mut loaded, retention_days, Utc::now());
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || write_loop(path, receiver));
+
+        // Abgelaufene Einträge auch aus der Datei entfernen
+        if loaded.len() != loaded_count {
+            let _ = sender.send(HistoryWrite::Rewrite(loaded.clone()));
+        }
+
+        {
+            let mut entries = self.entries.lock().unwrap();
+            // Bereits im Speicher erfasste Einträge anhängen und mitschreiben
+            for entry in entries.iter() {
+                let _ = sender.send(HistoryWrite::Append(Box::new(entry.clone())));
+            }
+            loaded.append(&mut entries);
+            *entries = loaded;
+        }
+
+        *self.writer.lock().unwrap() = Some(sender);
+        Ok(())
+    }
+
+    /// Nimmt einen Eintrag auf; das Schreiben geschieht im Hintergrund
+    pub fn record(&self, entry: TransferHistoryEntry) {
+        if let Some(writer) = &*self.writer.lock().unwrap() {
+            let _ = writer.send(HistoryWrite::Append(Box::new(entry.clone())));
+        }
+        self.entries.lock().unwrap().push(entry);
+    }
+
+    /// Wartet, bis alle aufgenommenen Einträge auf Platte stehen
+    pub fn flush(&self) {
+        let (done, written) = mpsc::channel();
+        let sent = match &*self.writer.lock().unwrap() {
+            Some(writer) => writer.send(HistoryWrite::Flush(done)).is_ok(),
+            None => false,
+        };
+        if sent {
+            let _ = written.recv();
+        }
+    }
+
+    /// Fragt den Verlauf gefiltert und seitenweise ab, neueste zuerst
+    pub fn query(&self, query: &TransferHistoryQuery) -> TransferHistoryPage {
+        let entries = self.entries.lock().unwrap();
+        let matching: Vec<&TransferHistoryEntry> = entries.iter()
+            .rev()
+            .filter(|entry| query.matches(entry))
+            .collect();
+
+        let limit = query.limit.unwrap_or(DEFAULT_HISTORY_PAGE_SIZE);
+        TransferHistoryPage {
+            total: matching.len(),
+            offset: query.offset,
+            entries: matching.into_iter()
+                .skip(query.offset)
+                .take(limit)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Letzter Eintrag einer Übertragung
+    pub fn find(&self, transfer_id: &str) -> Option<TransferHistoryEntry> {
+        self.entries.lock().unwrap().iter()
+            .rev()
+            .find(|entry| entry.transfer_id == transfer_id)
+            .cloned()
+    }
+
+    /// Löscht Einträge, die älter als `older_than_days` Tage sind (0 = alle).
+    /// Gibt die Anzahl der gelöschten Einträge zurück.
+    pub fn clear(&self, older_than_days: u32) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        retain_recent(&mut entries, Some(older_than_days), Utc::now());
+
+        if let Some(writer) = &*self.writer.lock().unwrap() {
+            let _ = writer.send(HistoryWrite::Rewrite(entries.clone()));
+        }
+        before - entries.len()
+    }
+}
+
+/// Behält nur Einträge, die innerhalb der letzten `retention_days` Tage endeten
+fn retain_recent(entries: &mut Vec<TransferHistoryEntry>, retention_days: Option<u32>, now: DateTime<Utc>) {
+    if let Some(days) = retention_days {
+        let cutoff = now - Duration::days(days as i64);
+        entries.retain(|entry| days > 0 && entry.finished_at >= cutoff);
+    }
+}
+
+/// Liest das Log; unlesbare Zeilen (z.B. nach einem Absturz beim Schreiben) werden übersprungen
+fn load_entries(path: &Path) -> Result<Vec<TransferHistoryEntry>, FileTransferError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => log::warn!(target: TARGET_TRANSFER, "Skipping unreadable transfer history line: {}", e),
+        }
+    }
+    Ok(entries)
+}
+
+fn write_loop(path: PathBuf, receiver: Receiver<HistoryWrite>) {
+    // Endet, sobald der TransferHistory verworfen wird
+    for write in receiver {
+        let result = match write {
+            HistoryWrite::Append(entry) => append_entry(&path, &entry),
+            HistoryWrite::Rewrite(entries) => rewrite_entries(&path, &entries),
+            HistoryWrite::Flush(done) => {
+                let _ = done.send(());
+                continue;
+            },
+        };
+        if let Err(e) = result {
+            log::error!(target: TARGET_TRANSFER, "Failed to write transfer history: {}", e);
+        }
+    }
+}
+
+fn append_entry(path: &Path, entry: &TransferHistoryEntry) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(&line)
+}
+
+/// Schreibt das Log atomar neu (temporäre Datei + rename)
+fn rewrite_entries(path: &Path, entries: &[TransferHistoryEntry]) -> std::io::Result<()> {
+    let temp_path = path.with_extension("jsonl.tmp");
+    {
+        let mut file = File::create(&temp_path)?;
+        for entry in entries {
+            let mut line = serde_json::to_vec(entry)?;
+            line.push(b'\n');
+            file.write_all(&line)?;
+        }
+        file.sync_all()?;
+    }
+    fs::rename(&temp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, peer: &str, outcome: TransferOutcome, finished_at: DateTime<Utc>) -> TransferHistoryEntry {
+        TransferHistoryEntry {
+            transfer_id: id.to_string(),
+            transfer_type: TransferType::Upload,
+            peer_id: peer.to_string(),
+            file_name: format!("{}.bin", id),
+            size: 1024,
+            bytes_transferred: 1024,
+            file_hash: None,
+            local_path: None,
+            started_at: finished_at - Duration::seconds(5),
+            finished_at,
+            duration_ms: 5000,
+            outcome,
+            reason: None,
+            receipt: None,
+        }
+    }
+
+    #[test]
+    fn test_query_filters_and_pages_newest_first() {
+        let history = TransferHistory::new();
+        let now = Utc::now();
+        for i in 0..5 {
+            history.record(entry(&format!("a{}", i), "alice", TransferOutcome::Completed, now - Duration::hours(5 - i)));
+        }
+        history.record(entry("b0", "bob", TransferOutcome::Failed, now));
+
+        let page = history.query(&TransferHistoryQuery {
+            peer_id: Some("alice".to_string()),
+            offset: 1,
+            limit: Some(2),
+            ..TransferHistoryQuery::default()
+        });
+        assert_eq!(page.total, 5);
+        let ids: Vec<&str> = page.entries.iter().map(|entry| entry.transfer_id.as_str()).collect();
+        assert_eq!(ids, vec!["a3", "a2"]);
+
+        let failed = history.query(&TransferHistoryQuery {
+            outcome: Some(TransferOutcome::Failed),
+            ..TransferHistoryQuery::default()
+        });
+        assert_eq!(failed.entries[0].peer_id, "bob");
+
+        let recent = history.query(&TransferHistoryQuery {
+            from: Some(now - Duration::minutes(90)),
+            to: Some(now),
+            ..TransferHistoryQuery::default()
+        });
+        assert_eq!(recent.total, 1);
+    }
+
+    #[test]
+    fn test_persists_and_honors_retention() {
+        let dir = std::env::temp_dir().join(format!("smoldesk-transfer-history-{}", std::process::id()));
+        let now = Utc::now();
+
+        {
+            let history = TransferHistory::new();
+            history.open(&dir, Some(30)).unwrap();
+            history.record(entry("old", "alice", TransferOutcome::Completed, now - Duration::days(40)));
+            history.record(entry("new", "alice", TransferOutcome::Cancelled, now));
+        }
+        // Der Schreib-Thread arbeitet asynchron
+        thread::sleep(std::time::Duration::from_millis(200));
+
+        let history = TransferHistory::new();
+        history.open(&dir, Some(30)).unwrap();
+        let page = history.query(&TransferHistoryQuery::default());
+        assert_eq!(page.total, 1);
+        assert_eq!(page.entries[0].transfer_id, "new");
+
+        assert_eq!(history.clear(0), 1);
+        assert_eq!(history.query(&TransferHistoryQuery::default()).total, 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,49 @@
+// src-tauri/src/file_transfer/metadata.rs - Dateiberechtigungen und Zeitstempel
+
+use std::path::Path;
+
+use crate::file_transfer::error::FileTransferError;
+use crate::file_transfer::types::FileMetadata;
+
+/// Standardberechtigungen, wenn die Plattform keine Unix-Modi kennt
+const DEFAULT_PERMISSIONS: u32 = 0o644;
+
+/// Liest die Unix-Berechtigungen einer Datei
+#[cfg(unix)]
+pub fn read_permissions(path: &Path) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::metadata(path)
+        .map(|metadata| metadata.permissions().mode() & 0o7777)
+        .unwrap_or(DEFAULT_PERMISSIONS)
+}
+
+#[cfg(not(unix))]
+pub fn read_permissions(_path: &Path) -> u32 {
+    DEFAULT_PERMISSIONS
+}
+
+/// Übernimmt Berechtigungen (nach Anwendung der Maske) und Änderungszeit
+/// aus den übertragenen Metadaten
+pub fn apply_metadata(path: &Path, metadata: &FileMetadata, permission_mask: u32) -> Result<(), FileTransferError> {
+    apply_permissions(path, metadata.permissions & permission_mask)?;
+
+    filetime::set_file_mtime(path, filetime::FileTime::from_system_time(metadata.modified))
+        .map_err(|e| FileTransferError::IoError(format!("Failed to set modification time: {}", e)))?;
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn apply_permissions(path: &Path, mode: u32) -> Result<(), FileTransferError> {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+        .map_err(|e| FileTransferError::IoError(format!("Failed to set permissions: {}", e)))
+}
+
+#[cfg(not(unix))]
+fn apply_permissions(_path: &Path, _mode: u32) -> Result<(), FileTransferError> {
+    // Keine Unix-Modi auf dieser Plattform
+    Ok(())
+}
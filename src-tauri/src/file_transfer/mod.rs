@@ -14,12 +14,54 @@ use tokio::sync::mpsc;
 pub mod error;
 pub mod types;
 pub mod chunk_manager;
+pub mod chunk_sizing;
 pub mod security;
+pub mod bandwidth;
+pub mod scheduler;
+pub mod metadata;
+pub mod history;
+pub mod drop;
+pub mod resume;
+pub mod preview;
+pub mod staging;
+pub mod dedup;
+pub mod receipt;
 
 use error::FileTransferError;
 use types::*;
-use chunk_manager::ChunkManager;
+use chunk_manager::{ChunkManager, MAX_HASH_WORKERS};
+use chunk_sizing::{negotiate_chunk_size, total_chunks, ChunkBatcher, ChunkSizeBounds};
 use security::FileTransferSecurity;
+use bandwidth::{AutoBandwidthConfig, BandwidthLimiter, VideoBitrateSource};
+use scheduler::{TransferPriority, TransferQueue};
+use drop::{DropConfig, DropListener, DropManager, DropPosition, FileDrop};
+use history::{TransferHistory, TransferHistoryEntry, TransferHistoryPage, TransferHistoryQuery, TransferOutcome};
+use resume::ResumeSidecar;
+use preview::{is_previewable, FilePreview, PreviewRateLimiter};
+use staging::{move_into_place, PurgeReport, StagingArea, StorageUsage};
+use dedup::{is_chunk_hash, reuse_chunks, ChunkCache, MANIFEST_PAGE_CHUNKS};
+use receipt::{ReceiptSigner, TransferReceipt};
+use crate::connection_security::audit::{AuditEvent, AuditEventKind, AuditSink};
+use crate::logging::TARGET_TRANSFER;
+use crate::protocol::{self, WireSubsystem, PROTOCOL_VERSION};
+
+/// Wartezeit beim Prüfen pausierter Übertragungen
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Wartezeit beim Prüfen, ob ein adaptiver Batch wieder Platz hat
+const ACK_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Unbestätigte Chunks gelten danach als verloren und verkleinern den Batch
+const ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Abstand, in dem die Wartung nach hängenden Übertragungen sucht
+const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Abstand, in dem die Wartung verwaiste Staging-Verzeichnisse sucht
+const STAGING_CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Länger wartet eine Übertragungsanfrage nicht auf ihre Vorschau
+const PREVIEW_TIMEOUT: Duration = Duration::from_secs(2);
 
 /// Hauptmanager für Dateiübertragungen
 pub struct FileTransferManager {
@@ -32,43 +74,394 @@ pub struct FileTransferManager {
     /// Sicherheitsmanager
     security: Arc<FileTransferSecurity>,
     
+    /// Bandbreitenbegrenzung für Senden und Chunk-Anfragen
+    bandwidth: Arc<BandwidthLimiter>,
+    
+    /// Adaptive Batches laufender Uploads, deren Empfänger Chunks bestätigt
+    batchers: Arc<Mutex<HashMap<String, ChunkBatcher>>>,
+    
+    /// Uploads, die auf einen freien Platz warten
+    queue: Arc<Mutex<TransferQueue>>,
+    
     /// Konfiguration
     config: TransferConfig,
     
     /// Event-Sender für UI-Updates
     event_sender: Option<mpsc::UnboundedSender<TransferEvent>>,
     
+    /// Ausgehende Protokollnachrichten (Peer-ID, Nachricht) an die Transportschicht
+    message_sender: Option<mpsc::UnboundedSender<(String, TransferMessage)>>,
+    
     /// Statistiken
     stats: Arc<Mutex<TransferStats>>,
+    
+    /// Verlauf beendeter Übertragungen
+    history: Arc<TransferHistory>,
+    
+    /// Audit-Protokoll für gesendete und empfangene Dateien
+    audit_sink: Option<Arc<dyn AuditSink>>,
+    
+    /// Signiert die Quittungen abgeschlossener Übertragungen
+    receipt_signer: Option<Arc<ReceiptSigner>>,
+    
+    /// Per Drag & Drop auf dem Host abgelegte Dateien
+    drops: Arc<DropManager>,
+    
+    /// Begrenzt nachträgliche Vorschau-Anfragen je Peer
+    preview_limiter: Arc<Mutex<PreviewRateLimiter>>,
+    
+    /// Quarantäne für Downloads bis zur Hash-Prüfung; ohne Datenverzeichnis
+    /// wird direkt ans Ziel geschrieben
+    staging: Arc<Mutex<Option<StagingArea>>>,
+    
+    /// Zuletzt empfangene Chunks, die spätere Übertragungen nicht erneut brauchen
+    chunk_cache: Arc<Mutex<ChunkCache>>,
+    
+    /// Wartungs-Task, der hängende Übertragungen erkennt; nur beim Besitzer gesetzt
+    maintenance: Option<tokio::task::JoinHandle<()>>,
+}
+
+/// Der Wartungs-Task endet mit dem Manager
+impl Drop for FileTransferManager {
+    fn drop(&mut self) {
+        if let Some(task) = self.maintenance.take() {
+            task.abort();
+        }
+    }
 }
 
 impl FileTransferManager {
     /// Erstellt einen neuen FileTransferManager
     pub fn new(config: TransferConfig) -> Result<Self, FileTransferError> {
-        let chunk_manager = Arc::new(ChunkManager::new(config.chunk_size));
+        let chunk_manager = Arc::new(ChunkManager::new());
         let security = Arc::new(FileTransferSecurity::new(config.encryption_enabled)?);
+        let bandwidth = Arc::new(BandwidthLimiter::new(
+            config.max_bandwidth_bytes_per_sec,
+            config.auto_bandwidth.clone(),
+        ));
+        let chunk_cache = Arc::new(Mutex::new(ChunkCache::new(config.chunk_cache_bytes)));
         
         Ok(FileTransferManager {
             active_transfers: Arc::new(Mutex::new(HashMap::new())),
             chunk_manager,
             security,
+            bandwidth,
+            batchers: Arc::new(Mutex::new(HashMap::new())),
+            queue: Arc::new(Mutex::new(TransferQueue::new())),
             config,
             event_sender: None,
+            message_sender: None,
             stats: Arc::new(Mutex::new(TransferStats::default())),
+            history: Arc::new(TransferHistory::new()),
+            audit_sink: None,
+            receipt_signer: None,
+            drops: Arc::new(DropManager::new()),
+            preview_limiter: Arc::new(Mutex::new(PreviewRateLimiter::default())),
+            staging: Arc::new(Mutex::new(None)),
+            chunk_cache,
+            maintenance: None,
         })
     }
     
+    /// Startet den Wartungs-Task, der hängende Übertragungen erkennt und nach
+    /// der Karenzzeit abbricht und stündlich verwaiste Staging-Verzeichnisse
+    /// entfernt. Muss im Kontext der Tokio-Runtime und nach
+    /// den `set_*_sender`-Aufrufen erfolgen, da der Task deren Stand übernimmt.
+    pub fn start_maintenance(&mut self) {
+        if let Some(task) = self.maintenance.take() {
+            task.abort();
+        }
+        
+        let worker = self.shared();
+        self.maintenance = Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(MAINTENANCE_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            let mut last_cleanup = activity_now();
+            loop {
+                interval.tick().await;
+                worker.check_stalled_transfers(activity_now()).await;
+                if last_cleanup.elapsed() >= STAGING_CLEANUP_INTERVAL {
+                    worker.cleanup_staging(SystemTime::now());
+                    last_cleanup = activity_now();
+                }
+            }
+        }));
+    }
+    
+    /// Zweiter Zugriff auf denselben Zustand, ohne eigenen Wartungs-Task
+    fn shared(&self) -> FileTransferManager {
+        FileTransferManager {
+            active_transfers: self.active_transfers.clone(),
+            chunk_manager: self.chunk_manager.clone(),
+            security: self.security.clone(),
+            bandwidth: self.bandwidth.clone(),
+            batchers: self.batchers.clone(),
+            queue: self.queue.clone(),
+            config: self.config.clone(),
+            event_sender: self.event_sender.clone(),
+            message_sender: self.message_sender.clone(),
+            stats: self.stats.clone(),
+            history: self.history.clone(),
+            audit_sink: self.audit_sink.clone(),
+            receipt_signer: self.receipt_signer.clone(),
+            drops: self.drops.clone(),
+            preview_limiter: self.preview_limiter.clone(),
+            staging: self.staging.clone(),
+            chunk_cache: self.chunk_cache.clone(),
+            maintenance: None,
+        }
+    }
+    
+    /// Markiert laufende Übertragungen ohne Aktivität seit `transfer_timeout_secs`
+    /// als hängend und bricht sie nach weiteren `stall_grace_secs` ab. Hängende
+    /// Übertragungen mit neuer Aktivität laufen normal weiter. Pausierte und
+    /// wartende Übertragungen werden nicht geprüft; Fortsetzen setzt die Uhr zurück.
+    pub async fn check_stalled_transfers(&self, now: Instant) {
+        let timeout = Duration::from_secs(self.config.transfer_timeout_secs);
+        let grace = Duration::from_secs(self.config.stall_grace_secs);
+        
+        let mut stalled = Vec::new();
+        let mut recovered = Vec::new();
+        let mut expired = Vec::new();
+        {
+            let mut transfers = self.active_transfers.lock().unwrap();
+            for session in transfers.values_mut() {
+                let idle = now.saturating_duration_since(session.last_activity);
+                match session.status {
+                    TransferStatus::Active | TransferStatus::Preparing if idle >= timeout => {
+                        session.status = TransferStatus::Stalled;
+                        stalled.push((session.id.clone(), idle.as_secs()));
+                    },
+                    TransferStatus::Stalled if idle >= timeout + grace => {
+                        expired.push(session.id.clone());
+                    },
+                    TransferStatus::Stalled if idle < timeout => {
+                        session.status = TransferStatus::Active;
+                        recovered.push(session.id.clone());
+                    },
+                    _ => {},
+                }
+            }
+        }
+        
+        for (transfer_id, idle_secs) in stalled {
+            log::warn!(target: TARGET_TRANSFER, "Transfer {} stalled, no activity for {}s", transfer_id, idle_secs);
+            self.send_event(TransferEvent::TransferStalled { transfer_id, idle_secs }).await;
+        }
+        
+        for transfer_id in recovered {
+            log::info!(target: TARGET_TRANSFER, "Stalled transfer {} is moving again", transfer_id);
+            self.send_event(TransferEvent::TransferResumed { transfer_id }).await;
+        }
+        
+        for transfer_id in expired {
+            log::warn!(target: TARGET_TRANSFER, "Cancelling transfer {} after {}s without activity",
+                transfer_id, (timeout + grace).as_secs());
+            let reason = format!("No activity for {} seconds", (timeout + grace).as_secs());
+            let _ = self.remove_transfer(&transfer_id, Some(reason), self.config.resume_from_disk).await;
+        }
+    }
+    
+    /// Lädt den Übertragungsverlauf aus dem Datenverzeichnis und speichert ihn dort
+    pub fn set_history_dir(&self, data_dir: &Path) -> Result<(), FileTransferError> {
+        self.history.open(data_dir, self.config.history_retention_days)
+    }
+    
+    /// Legt das Staging-Verzeichnis im Datenverzeichnis an und entfernt dort
+    /// Reste früherer Läufe, die älter als die Aufbewahrungsdauer sind
+    pub fn set_staging_dir(&self, data_dir: &Path) -> Result<PurgeReport, FileTransferError> {
+        *self.staging.lock().unwrap() = Some(StagingArea::open(data_dir)?);
+        Ok(self.cleanup_staging(SystemTime::now()))
+    }
+    
+    /// Lädt den Chunk-Cache aus dem Datenverzeichnis und speichert neue Chunks dort
+    pub fn set_chunk_cache_dir(&self, data_dir: &Path) -> Result<(), FileTransferError> {
+        *self.chunk_cache.lock().unwrap() = ChunkCache::open(data_dir, self.config.chunk_cache_bytes)?;
+        Ok(())
+    }
+    
+    /// Entfernt Staging-Verzeichnisse ohne laufende Übertragung, die seit
+    /// `staging_retention_hours` nicht geändert wurden
+    pub fn cleanup_staging(&self, now: SystemTime) -> PurgeReport {
+        let retention = Duration::from_secs(self.config.staging_retention_hours * 3600);
+        let active = self.transfer_ids();
+        let report = match &*self.staging.lock().unwrap() {
+            Some(staging) => staging.cleanup(&active, retention, now),
+            None => PurgeReport::default(),
+        };
+        if report.removed_transfers > 0 {
+            log::info!(target: TARGET_TRANSFER, "Removed {} orphaned staging directories ({} bytes)",
+                report.removed_transfers, report.freed_bytes);
+        }
+        report
+    }
+    
+    /// Belegter Platz im Staging-Verzeichnis und im Chunk-Cache
+    pub fn get_storage_usage(&self) -> StorageUsage {
+        let active = self.transfer_ids();
+        let mut usage = match &*self.staging.lock().unwrap() {
+            Some(staging) => staging.usage(&active),
+            None => StorageUsage::default(),
+        };
+        usage.chunk_cache_bytes = self.chunk_cache.lock().unwrap().size();
+        usage
+    }
+    
+    /// Entfernt sofort alle Staging-Verzeichnisse ohne laufende Übertragung,
+    /// auch Teildateien, die noch fortgesetzt werden könnten
+    pub fn purge_staging(&self) -> PurgeReport {
+        let active = self.transfer_ids();
+        match &*self.staging.lock().unwrap() {
+            Some(staging) => staging.purge(&active),
+            None => PurgeReport::default(),
+        }
+    }
+    
+    fn transfer_ids(&self) -> std::collections::HashSet<String> {
+        self.active_transfers.lock().unwrap().keys().cloned().collect()
+    }
+    
+    /// Entfernt das Staging-Verzeichnis einer Übertragung
+    fn discard_staging(&self, transfer_id: &str) {
+        if let Some(staging) = &*self.staging.lock().unwrap() {
+            staging.remove(transfer_id);
+        }
+    }
+    
+    /// Fragt den Übertragungsverlauf gefiltert und seitenweise ab
+    pub fn get_transfer_history(&self, query: &TransferHistoryQuery) -> TransferHistoryPage {
+        self.history.query(query)
+    }
+    
+    /// Löscht Verlaufseinträge, die älter als `older_than_days` Tage sind
+    /// (None = gemäß Aufbewahrungsdauer, 0 = alle). Gibt die Anzahl zurück.
+    pub fn clear_history(&self, older_than_days: Option<u32>) -> usize {
+        match older_than_days.or(self.config.history_retention_days) {
+            Some(days) => self.history.clear(days),
+            None => 0,
+        }
+    }
+    
     /// Setzt den Event-Sender für UI-Updates
     pub fn set_event_sender(&mut self, sender: mpsc::UnboundedSender<TransferEvent>) {
         self.event_sender = Some(sender);
     }
     
+    /// Setzt den Kanal für ausgehende Protokollnachrichten an Peers
+    pub fn set_message_sender(&mut self, sender: mpsc::UnboundedSender<(String, TransferMessage)>) {
+        self.message_sender = Some(sender);
+    }
+    
+    /// Ob Protokollnachrichten eine Transportschicht zu den Peers erreichen
+    pub fn has_transport(&self) -> bool {
+        self.message_sender.is_some()
+    }
+    
+    /// Stellt ab jetzt Quittungen für abgeschlossene Übertragungen aus
+    pub fn set_receipt_signer(&mut self, signer: ReceiptSigner) {
+        self.receipt_signer = Some(Arc::new(signer));
+    }
+    
+    /// Quittung einer abgeschlossenen Übertragung aus dem Verlauf
+    pub fn get_transfer_receipt(&self, transfer_id: &str) -> Result<TransferReceipt, FileTransferError> {
+        let entry = self.history.find(transfer_id)
+            .ok_or_else(|| FileTransferError::TransferNotFound(transfer_id.to_string()))?;
+        entry.receipt.ok_or_else(|| FileTransferError::InvalidOperation(
+            format!("Transfer {} has no receipt", transfer_id)
+        ))
+    }
+    
+    /// Meldet abgeschlossene Übertragungen an das Audit-Protokoll
+    pub fn set_audit_sink(&mut self, sink: Arc<dyn AuditSink>) {
+        self.audit_sink = Some(sink);
+    }
+    
+    /// Meldet Fortschritt und Abschluss von Ablage-Gesten
+    pub fn set_drop_listener(&self, listener: DropListener) {
+        self.drops.set_listener(listener);
+    }
+    
+    pub fn get_drop_config(&self) -> DropConfig {
+        self.drops.config()
+    }
+    
+    pub fn set_drop_config(&self, config: DropConfig) {
+        self.drops.set_config(config);
+    }
+    
+    /// Nimmt eine per Drag & Drop abgelegte Datei an
+    ///
+    /// Die Datei wird zunächst in ein Staging-Verzeichnis empfangen und nach
+    /// Abschluss an der Ablageposition (absolute Host-Koordinaten) abgelegt.
+    pub async fn handle_remote_file_drop(
+        &self,
+        peer_id: &str,
+        transfer_id: &str,
+        position: DropPosition
+    ) -> Result<FileDrop, FileTransferError> {
+        let file_name = {
+            let transfers = self.active_transfers.lock().unwrap();
+            let session = transfers.get(transfer_id)
+                .ok_or_else(|| FileTransferError::TransferNotFound(transfer_id.to_string()))?;
+            if session.peer_id != peer_id {
+                return Err(FileTransferError::InvalidOperation(
+                    format!("Transfer {} does not belong to peer {}", transfer_id, peer_id)
+                ));
+            }
+            if session.transfer_type != TransferType::Download || session.status != TransferStatus::Pending {
+                return Err(FileTransferError::InvalidOperation(
+                    format!("Transfer {} is not an incoming pending transfer", transfer_id)
+                ));
+            }
+            session.file_metadata.name.clone()
+        };
+        
+        let file_drop = self.drops.stage(peer_id, transfer_id, &file_name, position)?;
+        if let Err(e) = self.accept_transfer(transfer_id, &file_drop.staged_path).await {
+            self.drops.discard(transfer_id);
+            return Err(e);
+        }
+        
+        log::info!(target: TARGET_TRANSFER, "Receiving drop {} from {} at ({}, {})",
+            file_drop.drop_id, peer_id, position.x, position.y);
+        Ok(file_drop)
+    }
+    
+    /// Setzt die Bandbreitengrenze global (transfer_id = None) oder für eine Übertragung
+    pub fn set_bandwidth_limit(
+        &self,
+        transfer_id: Option<&str>,
+        bytes_per_sec: Option<u64>
+    ) -> Result<(), FileTransferError> {
+        match transfer_id {
+            Some(id) => {
+                if !self.active_transfers.lock().unwrap().contains_key(id) {
+                    return Err(FileTransferError::TransferNotFound(id.to_string()));
+                }
+                self.bandwidth.set_transfer_limit(id, bytes_per_sec);
+            },
+            None => self.bandwidth.set_global_limit(bytes_per_sec),
+        }
+        Ok(())
+    }
+    
+    /// Aktiviert die automatische Begrenzung anhand der Video-Bitrate
+    pub fn set_auto_bandwidth(&self, auto: AutoBandwidthConfig) {
+        self.bandwidth.set_auto(auto);
+    }
+    
+    /// Setzt die Quelle der aktuellen Video-Bitrate (Bits/Sekunde) für den Auto-Modus
+    pub fn set_video_bitrate_source(&self, source: VideoBitrateSource) {
+        self.bandwidth.set_video_bitrate_source(source);
+    }
+    
     /// Startet eine neue Datei-Upload-Session
     pub async fn start_upload(
         &self,
         file_path: &Path,
         destination_peer: &str,
-        metadata: Option<FileMetadata>
+        metadata: Option<FileMetadata>,
+        priority: TransferPriority
     ) -> Result<String, FileTransferError> {
         // Datei validieren
         if !file_path.exists() {
@@ -91,8 +484,18 @@ impl FileTransferManager {
         // Transfer-ID generieren
         let transfer_id = Uuid::new_v4().to_string();
         
-        // Datei-Hash berechnen
-        let file_hash = self.calculate_file_hash(file_path).await?;
+        // Chunk-Hashes parallel berechnen; der Datei-Hash wird daraus kombiniert.
+        // Die Vorschau für die Übertragungsanfrage entsteht gleichzeitig.
+        let chunk_size = self.config.chunk_size;
+        let mime_type = metadata.as_ref()
+            .map(|metadata| metadata.mime_type.clone())
+            .unwrap_or_else(|| self.detect_mime_type(file_path));
+        let (chunk_hashes, preview) = tokio::join!(
+            self.hash_chunks_parallel(file_path, chunk_size),
+            self.create_preview(file_path, &mime_type)
+        );
+        let chunk_hashes = chunk_hashes?;
+        let file_hash = ChunkManager::combine_chunk_hashes(&chunk_hashes);
         
         // Metadaten erstellen
         let file_metadata = metadata.unwrap_or_else(|| FileMetadata {
@@ -101,8 +504,10 @@ impl FileTransferManager {
                 .to_string_lossy()
                 .to_string(),
             size: file_size,
-            mime_type: self.detect_mime_type(file_path),
-            created: SystemTime::now(),
+            mime_type,
+            created: file_path.metadata()
+                .and_then(|m| m.created())
+                .unwrap_or_else(|_| SystemTime::now()),
             modified: file_path.metadata()
                 .and_then(|m| m.modified())
                 .unwrap_or_else(|_| SystemTime::now()),
@@ -115,23 +520,37 @@ impl FileTransferManager {
             id: transfer_id.clone(),
             transfer_type: TransferType::Upload,
             peer_id: destination_peer.to_string(),
-            status: TransferStatus::Preparing,
+            status: TransferStatus::Queued,
             file_metadata: file_metadata.clone(),
             file_hash: Some(file_hash.clone()),
             source_path: Some(file_path.to_path_buf()),
             destination_path: None,
+            staging_path: None,
             progress: TransferProgress {
                 bytes_transferred: 0,
                 total_bytes: file_size,
                 chunks_completed: 0,
-                total_chunks: ((file_size + self.config.chunk_size as u64 - 1) / self.config.chunk_size as u64) as usize,
+                total_chunks: total_chunks(file_size, chunk_size),
                 transfer_rate: 0.0,
                 eta_seconds: None,
+                bytes_deduplicated: 0,
             },
             started_at: Instant::now(),
-            last_activity: Instant::now(),
+            last_activity: activity_now(),
             retry_count: 0,
             chunks: HashMap::new(),
+            encrypted: self.config.encryption_enabled,
+            peer_public_key: None,
+            priority,
+            activated: false,
+            hash_kind: FileHashKind::ChunkList,
+            chunk_hashes: chunk_hashes.into_iter().enumerate().collect(),
+            chunk_size,
+            hash_chunk_size: chunk_size,
+            chunk_size_bounds: ChunkSizeBounds::from_config(&self.config),
+            chunk_acks: false,
+            preview,
+            manifest: Vec::new(),
         };
         
         // Session speichern
@@ -144,19 +563,28 @@ impl FileTransferManager {
         self.send_event(TransferEvent::TransferStarted {
             transfer_id: transfer_id.clone(),
             transfer_type: TransferType::Upload,
-            file_metadata: file_metadata.clone(),
+            file_metadata,
             peer_id: destination_peer.to_string(),
         }).await;
         
-        // Upload-Anfrage an Peer senden
-        self.send_transfer_request(destination_peer, TransferRequest {
-            transfer_id: transfer_id.clone(),
-            file_metadata,
-            file_hash,
-            chunk_size: self.config.chunk_size,
-            total_chunks: ((file_size + self.config.chunk_size as u64 - 1) / self.config.chunk_size as u64) as usize,
-            encryption_enabled: self.config.encryption_enabled,
-        }).await?;
+        // In die Queue einreihen; der Scheduler startet, sobald ein Platz frei ist
+        {
+            let mut queue = self.queue.lock().unwrap();
+            queue.enqueue(&transfer_id, priority);
+        }
+        self.schedule_next().await;
+        
+        // Position melden, falls die Übertragung warten muss
+        let position = {
+            let queue = self.queue.lock().unwrap();
+            queue.entries().iter().position(|entry| entry.transfer_id == transfer_id)
+        };
+        if let Some(position) = position {
+            self.send_event(TransferEvent::TransferQueued {
+                transfer_id: transfer_id.clone(),
+                position,
+            }).await;
+        }
         
         // Statistiken aktualisieren
         {
@@ -182,22 +610,63 @@ impl FileTransferManager {
             }
         }
         
+        // Chunks gehen in die Quarantäne, sofern es eine gibt; erst die geprüfte Datei kommt ans Ziel
+        let staging_path = match (&*self.staging.lock().unwrap(), destination_path.file_name()) {
+            (Some(staging), Some(file_name)) => Some(staging.prepare(transfer_id, file_name)?),
+            _ => None,
+        };
+        
+        // Datei in voller Größe anlegen, bevor der erste Chunk eintrifft
+        let file_size = self.active_transfers.lock().unwrap()
+            .get(transfer_id)
+            .map(|session| session.file_metadata.size)
+            .ok_or_else(|| FileTransferError::TransferNotFound(transfer_id.to_string()))?;
+        self.chunk_manager.preallocate(staging_path.as_deref().unwrap_or(destination_path), file_size)?;
+        
         // Session aktualisieren
-        {
+        let (peer_id, encrypted, peer_public_key, chunk_size, bounds, chunk_acks) = {
             let mut transfers = self.active_transfers.lock().unwrap();
             if let Some(session) = transfers.get_mut(transfer_id) {
                 session.destination_path = Some(destination_path.to_path_buf());
+                session.staging_path = staging_path;
                 session.status = TransferStatus::Active;
-                session.last_activity = Instant::now();
+                session.last_activity = activity_now();
+                (
+                    session.peer_id.clone(),
+                    session.encrypted,
+                    session.peer_public_key.clone(),
+                    session.chunk_size,
+                    session.chunk_size_bounds,
+                    session.chunk_acks,
+                )
             } else {
                 return Err(FileTransferError::TransferNotFound(transfer_id.to_string()));
             }
-        }
+        };
+        
+        // Schlüsselaustausch beantworten
+        let public_key = if encrypted {
+            let peer_public_key = peer_public_key.ok_or_else(|| FileTransferError::KeyExchangeFailed(
+                "Sender did not provide a public key".to_string()
+            ))?;
+            Some(self.security.respond_key_exchange(transfer_id, &peer_public_key)?)
+        } else {
+            None
+        };
+        
+        // Chunks, die im Cache oder in der Datei am Ziel schon vorliegen, muss der Sender nicht schicken
+        let existing_chunks = self.reuse_existing_chunks(transfer_id, destination_path).await;
         
-        // Akzeptanz-Nachricht senden
-        self.send_transfer_response(transfer_id, TransferResponse::Accept {
+        // Akzeptanz-Nachricht mit der ausgehandelten Chunk-Größe senden
+        self.send_transfer_response(&peer_id, TransferResponse::Accept {
+            protocol_version: PROTOCOL_VERSION,
             transfer_id: transfer_id.to_string(),
             ready: true,
+            public_key,
+            accepted_chunk_size: Some(chunk_size),
+            max_chunk_size: Some(bounds.max),
+            chunk_acks,
+            existing_chunks: existing_chunks.clone(),
         }).await?;
         
         // Event senden
@@ -205,9 +674,84 @@ impl FileTransferManager {
             transfer_id: transfer_id.to_string(),
         }).await;
         
+        if !existing_chunks.is_empty() {
+            let progress = self.active_transfers.lock().unwrap()
+                .get(transfer_id)
+                .map(|session| session.progress.clone());
+            if let Some(progress) = progress {
+                self.send_event(TransferEvent::TransferProgress {
+                    transfer_id: transfer_id.to_string(),
+                    progress: progress.clone(),
+                }).await;
+                
+                // Alles schon vorhanden: es kommt kein Chunk mehr, der den Abschluss auslöst
+                if progress.chunks_completed >= progress.total_chunks {
+                    self.complete_download(transfer_id).await?;
+                }
+            }
+        }
+        
         Ok(())
     }
     
+    /// Übernimmt die Chunks aus dem Manifest des Senders, die im Chunk-Cache
+    /// oder in der Datei am Ziel schon vorliegen, und verbucht sie als
+    /// geschrieben. Ohne vollständiges Manifest oder nach einer geänderten
+    /// Chunk-Größe wird alles übertragen; Fehler kosten nur die Ersparnis.
+    async fn reuse_existing_chunks(&self, transfer_id: &str, destination_path: &Path) -> Vec<usize> {
+        let (manifest, file_size, chunk_size, target) = {
+            let transfers = self.active_transfers.lock().unwrap();
+            let session = match transfers.get(transfer_id) {
+                Some(session) if !session.manifest.is_empty() && session.chunk_size == session.hash_chunk_size => session,
+                _ => return Vec::new(),
+            };
+            if session.manifest.iter().any(String::is_empty) {
+                log::debug!(target: TARGET_TRANSFER, "Manifest of transfer {} incomplete, sending all chunks", transfer_id);
+                return Vec::new();
+            }
+            match session.write_path() {
+                Some(path) => (session.manifest.clone(), session.file_metadata.size, session.chunk_size, path.clone()),
+                None => return Vec::new(),
+            }
+        };
+        
+        let cache = self.chunk_cache.clone();
+        let existing = destination_path.to_path_buf();
+        let reused = tokio::task::spawn_blocking(move || {
+            reuse_chunks(&manifest, file_size, chunk_size, &target, Some(&existing), &cache)
+        }).await;
+        let reused = match reused {
+            Ok(Ok(reused)) => reused,
+            Ok(Err(e)) => {
+                log::warn!(target: TARGET_TRANSFER, "Could not reuse existing chunks for transfer {}: {}", transfer_id, e);
+                return Vec::new();
+            },
+            Err(e) => {
+                log::warn!(target: TARGET_TRANSFER, "Chunk reuse for transfer {} panicked: {}", transfer_id, e);
+                return Vec::new();
+            },
+        };
+        
+        let mut transfers = self.active_transfers.lock().unwrap();
+        let session = match transfers.get_mut(transfer_id) {
+            Some(session) => session,
+            None => return Vec::new(),
+        };
+        for (index, chunk) in &reused {
+            session.chunks.insert(*index, ChunkStatus::Completed);
+            session.chunk_hashes.insert(*index, chunk.hash.clone());
+            session.progress.chunks_completed += 1;
+            session.progress.bytes_transferred += chunk.len as u64;
+            session.progress.bytes_deduplicated += chunk.len as u64;
+        }
+        if !reused.is_empty() {
+            log::info!(target: TARGET_TRANSFER, "Transfer {}: {} of {} chunks ({} bytes) already present",
+                transfer_id, reused.len(), session.progress.total_chunks, session.progress.bytes_deduplicated);
+        }
+        
+        reused.into_iter().map(|(index, _)| index).collect()
+    }
+    
     /// Lehnt eine eingehende Dateiübertragung ab
     pub async fn reject_transfer(
         &self,
@@ -215,13 +759,22 @@ impl FileTransferManager {
         reason: Option<&str>
     ) -> Result<(), FileTransferError> {
         // Session entfernen
-        {
+        let session = {
             let mut transfers = self.active_transfers.lock().unwrap();
-            transfers.remove(transfer_id);
+            transfers.remove(transfer_id)
+        };
+        
+        let peer_id = session.as_ref().map(|session| session.peer_id.clone()).unwrap_or_default();
+        if let Some(session) = session {
+            self.history.record(TransferHistoryEntry::from_session(
+                &session,
+                TransferOutcome::Rejected,
+                reason.map(str::to_string),
+            ));
         }
         
         // Ablehnungs-Nachricht senden
-        self.send_transfer_response(transfer_id, TransferResponse::Reject {
+        self.send_transfer_response(&peer_id, TransferResponse::Reject {
             transfer_id: transfer_id.to_string(),
             reason: reason.unwrap_or("Transfer rejected by user").to_string(),
         }).await?;
@@ -235,14 +788,44 @@ impl FileTransferManager {
         Ok(())
     }
     
+    /// Fordert die Vorschau einer angebotenen Datei an, falls der Sender keine
+    /// mitgeschickt hat. Das Ergebnis kommt als `TransferPreview`-Event.
+    pub async fn request_transfer_preview(&self, transfer_id: &str) -> Result<(), FileTransferError> {
+        let (peer_id, preview) = {
+            let transfers = self.active_transfers.lock().unwrap();
+            let session = transfers.get(transfer_id)
+                .ok_or_else(|| FileTransferError::TransferNotFound(transfer_id.to_string()))?;
+            if session.transfer_type != TransferType::Download || session.status != TransferStatus::Pending {
+                return Err(FileTransferError::InvalidOperation(
+                    format!("Transfer {} is not an incoming pending transfer", transfer_id)
+                ));
+            }
+            (session.peer_id.clone(), session.preview.clone())
+        };
+        
+        // Eine bereits vorhandene Vorschau nicht erneut übertragen
+        if preview.is_some() {
+            self.send_event(TransferEvent::TransferPreview {
+                transfer_id: transfer_id.to_string(),
+                preview,
+            }).await;
+            return Ok(());
+        }
+        
+        self.forward_message(&peer_id, TransferMessage::PreviewRequest(PreviewRequest {
+            transfer_id: transfer_id.to_string(),
+        }));
+        Ok(())
+    }
+    
     /// Pausiert eine aktive Übertragung
     pub async fn pause_transfer(&self, transfer_id: &str) -> Result<(), FileTransferError> {
         let mut transfers = self.active_transfers.lock().unwrap();
         if let Some(session) = transfers.get_mut(transfer_id) {
             match session.status {
-                TransferStatus::Active => {
+                TransferStatus::Active | TransferStatus::Stalled => {
                     session.status = TransferStatus::Paused;
-                    session.last_activity = Instant::now();
+                    session.last_activity = activity_now();
                     
                     // Event senden
                     drop(transfers); // Mutex freigeben vor async
@@ -250,6 +833,9 @@ impl FileTransferManager {
                         transfer_id: transfer_id.to_string(),
                     }).await;
                     
+                    // Freigewordenen Platz an die nächste wartende Übertragung geben
+                    self.schedule_next().await;
+                    
                     Ok(())
                 },
                 _ => Err(FileTransferError::InvalidOperation(
@@ -264,11 +850,36 @@ impl FileTransferManager {
     /// Setzt eine pausierte Übertragung fort
     pub async fn resume_transfer(&self, transfer_id: &str) -> Result<(), FileTransferError> {
         let mut transfers = self.active_transfers.lock().unwrap();
+        let active_count = self.count_active(&transfers);
         if let Some(session) = transfers.get_mut(transfer_id) {
             match session.status {
                 TransferStatus::Paused => {
+                    let is_download = session.transfer_type == TransferType::Download;
+                    session.last_activity = activity_now();
+                    
+                    // Uploads ohne freien Platz stellen sich vorne in ihrer Priorität an
+                    if !is_download && active_count >= self.config.max_concurrent_transfers {
+                        session.status = TransferStatus::Queued;
+                        let priority = session.priority;
+                        drop(transfers);
+                        
+                        let position = {
+                            let mut queue = self.queue.lock().unwrap();
+                            queue.enqueue_front(transfer_id, priority);
+                            queue.entries().iter()
+                                .position(|entry| entry.transfer_id == transfer_id)
+                                .unwrap_or(0)
+                        };
+                        
+                        self.send_event(TransferEvent::TransferQueued {
+                            transfer_id: transfer_id.to_string(),
+                            position,
+                        }).await;
+                        
+                        return Ok(());
+                    }
+                    
                     session.status = TransferStatus::Active;
-                    session.last_activity = Instant::now();
                     
                     // Event senden
                     drop(transfers); // Mutex freigeben vor async
@@ -276,6 +887,11 @@ impl FileTransferManager {
                         transfer_id: transfer_id.to_string(),
                     }).await;
                     
+                    // Downloads fordern fehlende Chunks gedrosselt neu an
+                    if is_download {
+                        self.request_missing_chunks(transfer_id).await?;
+                    }
+                    
                     Ok(())
                 },
                 _ => Err(FileTransferError::InvalidOperation(
@@ -289,6 +905,48 @@ impl FileTransferManager {
     
     /// Bricht eine Übertragung ab
     pub async fn cancel_transfer(&self, transfer_id: &str) -> Result<(), FileTransferError> {
+        self.remove_transfer(transfer_id, None, false).await
+    }
+    
+    /// Schließt beim Beenden der App alle Übertragungen. Teildateien von
+    /// Downloads bleiben mit ihrem Empfangsstand liegen, damit sie sich nach
+    /// dem Neustart fortsetzen lassen, und der Verlauf wird vollständig
+    /// geschrieben. Gibt die Anzahl der geschlossenen Übertragungen zurück.
+    pub async fn shutdown(&self) -> usize {
+        let mut transfer_ids: Vec<String> = self.active_transfers.lock().unwrap().keys().cloned().collect();
+        
+        // Wartende zuerst, sonst rücken sie beim Schließen der laufenden nach
+        {
+            let queue = self.queue.lock().unwrap();
+            transfer_ids.sort_by_key(|transfer_id| !queue.contains(transfer_id));
+        }
+        
+        let mut closed = 0;
+        for transfer_id in transfer_ids {
+            let reason = Some("SmolDesk was closed".to_string());
+            if self.remove_transfer(&transfer_id, reason, true).await.is_ok() {
+                closed += 1;
+            }
+        }
+        
+        self.history.flush();
+        closed
+    }
+    
+    /// Entfernt eine abgebrochene Übertragung. Mit `keep_partial` bleibt die
+    /// Teildatei eines Downloads liegen und der Empfangsstand wird daneben gespeichert.
+    async fn remove_transfer(
+        &self,
+        transfer_id: &str,
+        reason: Option<String>,
+        keep_partial: bool
+    ) -> Result<(), FileTransferError> {
+        // Wartende Übertragungen nur aus der Queue nehmen
+        let was_queued = {
+            let mut queue = self.queue.lock().unwrap();
+            queue.remove(transfer_id)
+        };
+        
         // Session entfernen
         let session = {
             let mut transfers = self.active_transfers.lock().unwrap();
@@ -296,10 +954,23 @@ impl FileTransferManager {
         };
         
         if let Some(session) = session {
-            // Unvollständige Datei löschen bei Downloads
+            self.security.remove_transfer(transfer_id);
+            self.bandwidth.remove_transfer(transfer_id);
+            self.batchers.lock().unwrap().remove(transfer_id);
+            self.history.record(TransferHistoryEntry::from_session(&session, TransferOutcome::Cancelled, reason));
+            
+            // Unvollständige Datei bei Downloads löschen oder für später festhalten
             if session.transfer_type == TransferType::Download {
-                if let Some(dest_path) = &session.destination_path {
-                    let _ = std::fs::remove_file(dest_path);
+                if let Some(partial_path) = session.write_path() {
+                    if keep_partial {
+                        match ResumeSidecar::from_session(&session).write(partial_path) {
+                            Ok(path) => log::info!(target: TARGET_TRANSFER, "Kept partial download of {} with {}", transfer_id, path.display()),
+                            Err(e) => log::error!(target: TARGET_TRANSFER, "Failed to write resume sidecar for {}: {}", transfer_id, e),
+                        }
+                    } else {
+                        let _ = std::fs::remove_file(partial_path);
+                        self.discard_staging(transfer_id);
+                    }
                 }
             }
             
@@ -308,6 +979,11 @@ impl FileTransferManager {
                 transfer_id: transfer_id.to_string(),
             }).await;
             
+            // Ein laufender Upload gibt seinen Platz frei
+            if !was_queued {
+                self.schedule_next().await;
+            }
+            
             Ok(())
         } else {
             Err(FileTransferError::TransferNotFound(transfer_id.to_string()))
@@ -333,8 +1009,24 @@ impl FileTransferManager {
             TransferMessage::ChunkRequest(request) => {
                 self.handle_chunk_request(peer_id, request).await
             },
+            TransferMessage::ChunkAck(ack) => {
+                self.handle_chunk_ack(ack);
+                Ok(())
+            },
             TransferMessage::Control(control) => {
                 self.handle_control_message(peer_id, control).await
+            },
+            TransferMessage::PreviewRequest(request) => {
+                self.handle_preview_request(peer_id, request)
+            },
+            TransferMessage::Preview(response) => {
+                self.handle_preview_response(peer_id, response).await
+            },
+            TransferMessage::ManifestRequest(request) => {
+                self.handle_manifest_request(peer_id, request)
+            },
+            TransferMessage::Manifest(manifest) => {
+                self.handle_manifest(peer_id, manifest)
             }
         }
     }
@@ -373,7 +1065,35 @@ impl FileTransferManager {
     
     /// Holt Übertragungsstatistiken
     pub fn get_stats(&self) -> TransferStats {
-        self.stats.lock().unwrap().clone()
+        let mut stats = self.stats.lock().unwrap().clone();
+        
+        stats.queued_transfers = self.queue.lock().unwrap().len();
+        stats.active_transfers = self.count_active(&self.active_transfers.lock().unwrap());
+        
+        stats
+    }
+    
+    /// Holt die aktuelle Queue in Startreihenfolge
+    pub fn get_transfer_queue(&self) -> Vec<TransferQueueEntry> {
+        let entries = self.queue.lock().unwrap().entries();
+        let transfers = self.active_transfers.lock().unwrap();
+        
+        entries.into_iter().enumerate()
+            .filter_map(|(position, entry)| {
+                transfers.get(&entry.transfer_id).map(|session| TransferQueueEntry {
+                    transfer_id: entry.transfer_id,
+                    file_name: session.file_metadata.name.clone(),
+                    peer_id: session.peer_id.clone(),
+                    priority: entry.priority,
+                    position,
+                })
+            })
+            .collect()
+    }
+    
+    /// Verschiebt eine wartende Übertragung an eine neue Position der Queue
+    pub fn reorder_transfer_queue(&self, transfer_id: &str, position: usize) -> Result<(), FileTransferError> {
+        self.queue.lock().unwrap().move_to(transfer_id, position)
     }
     
     // Private Hilfsmethoden
@@ -397,6 +1117,47 @@ impl FileTransferManager {
         Ok(format!("{:x}", hasher.finalize()))
     }
     
+    /// Berechnet die Chunk-Hashes einer Datei auf einem Blocking-Thread
+    async fn hash_chunks_parallel(&self, file_path: &Path, chunk_size: usize) -> Result<Vec<String>, FileTransferError> {
+        let chunk_manager = self.chunk_manager.clone();
+        let path = file_path.to_path_buf();
+        let workers = std::thread::available_parallelism()
+            .map(|count| count.get())
+            .unwrap_or(1)
+            .min(MAX_HASH_WORKERS);
+        
+        tokio::task::spawn_blocking(move || chunk_manager.hash_chunks(&path, chunk_size, workers))
+            .await
+            .map_err(|e| FileTransferError::IoError(e.to_string()))?
+    }
+    
+    /// Erzeugt eine Vorschau auf einem Blocking-Thread; None bei Fehlern oder
+    /// nach `PREVIEW_TIMEOUT`, damit die Übertragung nicht auf sie wartet
+    async fn create_preview(&self, file_path: &Path, mime_type: &str) -> Option<FilePreview> {
+        if !is_previewable(mime_type) {
+            return None;
+        }
+        
+        let path = file_path.to_path_buf();
+        let mime_type = mime_type.to_string();
+        let task = tokio::task::spawn_blocking(move || preview::generate_preview(&path, &mime_type));
+        match tokio::time::timeout(PREVIEW_TIMEOUT, task).await {
+            Ok(Ok(Ok(preview))) => preview,
+            Ok(Ok(Err(e))) => {
+                log::debug!(target: TARGET_TRANSFER, "No preview for {}: {}", file_path.display(), e);
+                None
+            },
+            Ok(Err(e)) => {
+                log::warn!(target: TARGET_TRANSFER, "Preview task for {} failed: {}", file_path.display(), e);
+                None
+            },
+            Err(_) => {
+                log::debug!(target: TARGET_TRANSFER, "Preview of {} took longer than {:?}, skipping it", file_path.display(), PREVIEW_TIMEOUT);
+                None
+            },
+        }
+    }
+    
     /// Erkennt den MIME-Typ einer Datei
     fn detect_mime_type(&self, file_path: &Path) -> String {
         // Vereinfachte MIME-Type-Erkennung basierend auf Dateiendung
@@ -421,40 +1182,45 @@ impl FileTransferManager {
         }.to_string()
     }
     
-    /// Holt Dateiberechtigungen (vereinfacht)
-    fn get_file_permissions(&self, _file_path: &Path) -> u32 {
-        // Vereinfachte Implementierung - in einer vollständigen Version
-        // würden hier die tatsächlichen Dateiberechtigungen ausgelesen
-        0o644
+    /// Holt Dateiberechtigungen
+    fn get_file_permissions(&self, file_path: &Path) -> u32 {
+        metadata::read_permissions(file_path)
     }
     
     /// Sendet ein Event an das UI
     async fn send_event(&self, event: TransferEvent) {
+        self.drops.handle_transfer_event(&event);
         if let Some(sender) = &self.event_sender {
             let _ = sender.send(event);
         }
     }
     
+    /// Reicht eine Nachricht an die Transportschicht weiter, sofern eine gesetzt ist
+    fn forward_message(&self, peer_id: &str, message: TransferMessage) {
+        if let Some(sender) = &self.message_sender {
+            let _ = sender.send((peer_id.to_string(), message));
+        }
+    }
+    
     /// Sendet eine Transfer-Anfrage an einen Peer
     async fn send_transfer_request(
         &self,
         peer_id: &str,
         request: TransferRequest
     ) -> Result<(), FileTransferError> {
-        // Hier würde die tatsächliche Netzwerkübertragung implementiert
-        // Für jetzt als Platzhalter
-        println!("Sending transfer request to {}: {:?}", peer_id, request);
+        log::debug!(target: TARGET_TRANSFER, "Sending transfer request to {}: {:?}", peer_id, request);
+        self.forward_message(peer_id, TransferMessage::Request(request));
         Ok(())
     }
     
     /// Sendet eine Transfer-Antwort an einen Peer
     async fn send_transfer_response(
         &self,
-        transfer_id: &str,
+        peer_id: &str,
         response: TransferResponse
     ) -> Result<(), FileTransferError> {
-        // Hier würde die tatsächliche Netzwerkübertragung implementiert
-        println!("Sending transfer response for {}: {:?}", transfer_id, response);
+        log::debug!(target: TARGET_TRANSFER, "Sending transfer response to {}: {:?}", peer_id, response);
+        self.forward_message(peer_id, TransferMessage::Response(response));
         Ok(())
     }
     
@@ -464,6 +1230,60 @@ impl FileTransferManager {
         peer_id: &str,
         request: TransferRequest
     ) -> Result<(), FileTransferError> {
+        // Gegenstellen mit unbekannter Protokollversion ablehnen, bevor etwas angelegt wird
+        if let Err(mismatch) = protocol::negotiate(WireSubsystem::FileTransfer, request.protocol_version) {
+            self.send_transfer_response(peer_id, TransferResponse::Reject {
+                transfer_id: request.transfer_id.clone(),
+                reason: mismatch.to_string(),
+            }).await?;
+            return Err(FileTransferError::VersionMismatch(mismatch));
+        }
+        
+        // Verschlüsselte Übertragungen ohne Schlüssel nicht annehmen
+        if request.encryption_enabled && request.sender_public_key.is_none() {
+            return Err(FileTransferError::KeyExchangeFailed(
+                format!("Transfer {} requests encryption without a public key", request.transfer_id)
+            ));
+        }
+        
+        // Chunk-Größe im gemeinsamen Bereich beider Seiten aushandeln
+        let negotiated = match negotiate_chunk_size(
+            request.chunk_size,
+            request.chunk_size_bounds,
+            ChunkSizeBounds::from_config(&self.config)
+        ) {
+            Ok(negotiated) => negotiated,
+            Err(e) => {
+                self.send_transfer_response(peer_id, TransferResponse::Reject {
+                    transfer_id: request.transfer_id.clone(),
+                    reason: e.to_string(),
+                }).await?;
+                return Err(e);
+            }
+        };
+        
+        // Übergroße Vorschauen verwerfen; der Empfänger kann sie nicht nachfordern
+        let preview = request.preview.filter(|preview| {
+            let within_limit = preview.within_limit();
+            if !within_limit {
+                log::warn!(target: TARGET_TRANSFER, "Dropping oversized preview of transfer {} ({} bytes)",
+                    request.transfer_id, preview.data.len());
+            }
+            within_limit
+        });
+        
+        // Chunk-Hashes für die Deduplizierung; passen sie nicht in die Anfrage,
+        // werden sie seitenweise nachgefordert, solange der Benutzer entscheidet
+        let dedup = self.config.deduplication && request.dedup && negotiated.chunk_size == request.chunk_size;
+        let manifest_chunks = total_chunks(request.file_metadata.size, request.chunk_size);
+        let (manifest, request_manifest) = if !dedup {
+            (Vec::new(), false)
+        } else if request.chunk_hashes.len() == manifest_chunks {
+            (request.chunk_hashes, false)
+        } else {
+            (vec![String::new(); manifest_chunks], true)
+        };
+        
         // Transfer-Session für Download erstellen
         let session = TransferSession {
             id: request.transfer_id.clone(),
@@ -474,18 +1294,32 @@ impl FileTransferManager {
             file_hash: Some(request.file_hash.clone()),
             source_path: None,
             destination_path: None,
+            staging_path: None,
             progress: TransferProgress {
                 bytes_transferred: 0,
                 total_bytes: request.file_metadata.size,
                 chunks_completed: 0,
-                total_chunks: request.total_chunks,
+                total_chunks: total_chunks(request.file_metadata.size, negotiated.chunk_size),
                 transfer_rate: 0.0,
                 eta_seconds: None,
+                bytes_deduplicated: 0,
             },
             started_at: Instant::now(),
-            last_activity: Instant::now(),
+            last_activity: activity_now(),
             retry_count: 0,
             chunks: HashMap::new(),
+            encrypted: request.encryption_enabled,
+            peer_public_key: request.sender_public_key.clone(),
+            priority: TransferPriority::default(),
+            activated: true,
+            hash_kind: request.hash_kind,
+            chunk_hashes: HashMap::new(),
+            chunk_size: negotiated.chunk_size,
+            hash_chunk_size: request.chunk_size,
+            chunk_size_bounds: negotiated.bounds,
+            chunk_acks: request.chunk_acks,
+            preview: preview.clone(),
+            manifest,
         };
         
         // Session speichern
@@ -499,8 +1333,15 @@ impl FileTransferManager {
             transfer_id: request.transfer_id.clone(),
             peer_id: peer_id.to_string(),
             file_metadata: request.file_metadata,
+            preview,
         }).await;
         
+        if request_manifest {
+            self.forward_message(peer_id, TransferMessage::ManifestRequest(ManifestRequest {
+                transfer_id: request.transfer_id,
+            }));
+        }
+        
         Ok(())
     }
     
@@ -511,7 +1352,41 @@ impl FileTransferManager {
         response: TransferResponse
     ) -> Result<(), FileTransferError> {
         match response {
-            TransferResponse::Accept { transfer_id, ready: _ } => {
+            TransferResponse::Accept { protocol_version, transfer_id, ready: _, public_key, accepted_chunk_size, max_chunk_size, chunk_acks, existing_chunks } => {
+                if let Err(mismatch) = protocol::negotiate(WireSubsystem::FileTransfer, protocol_version) {
+                    let e = FileTransferError::VersionMismatch(mismatch);
+                    self.fail_transfer(&transfer_id, &e).await;
+                    return Err(e);
+                }
+                
+                // Schlüsselaustausch abschließen, bevor Chunks verschickt werden
+                let encrypted = {
+                    let transfers = self.active_transfers.lock().unwrap();
+                    transfers.get(&transfer_id)
+                        .map(|session| session.encrypted)
+                        .ok_or_else(|| FileTransferError::TransferNotFound(transfer_id.clone()))?
+                };
+                
+                if encrypted {
+                    let result = match &public_key {
+                        Some(key) => self.security.complete_key_exchange(&transfer_id, key),
+                        None => Err(FileTransferError::KeyExchangeFailed(
+                            "Receiver did not provide a public key".to_string()
+                        )),
+                    };
+                    
+                    if let Err(e) = result {
+                        self.fail_transfer(&transfer_id, &e).await;
+                        return Err(e);
+                    }
+                }
+                
+                if let Err(e) = self.apply_accepted_chunk_size(&transfer_id, accepted_chunk_size, max_chunk_size, chunk_acks) {
+                    self.fail_transfer(&transfer_id, &e).await;
+                    return Err(e);
+                }
+                self.skip_existing_chunks(&transfer_id, &existing_chunks).await;
+                
                 // Upload kann beginnen
                 self.start_upload_chunks(&transfer_id).await?;
             },
@@ -527,54 +1402,179 @@ impl FileTransferManager {
         Ok(())
     }
     
-    /// Behandelt eingehende Chunk-Daten
-    async fn handle_chunk_data(
+    /// Übernimmt die vom Empfänger akzeptierte Chunk-Größe und richtet bei
+    /// bestätigten Chunks den adaptiven Batch ein
+    fn apply_accepted_chunk_size(
         &self,
-        _peer_id: &str,
-        chunk: ChunkData
+        transfer_id: &str,
+        accepted_chunk_size: Option<usize>,
+        max_chunk_size: Option<usize>,
+        chunk_acks: bool
     ) -> Result<(), FileTransferError> {
         let mut transfers = self.active_transfers.lock().unwrap();
+        let session = transfers.get_mut(transfer_id)
+            .ok_or_else(|| FileTransferError::TransferNotFound(transfer_id.to_string()))?;
         
-        if let Some(session) = transfers.get_mut(&chunk.transfer_id) {
-            // Chunk validieren und speichern
-            if let Some(dest_path) = &session.destination_path {
-                self.chunk_manager.write_chunk(
-                    dest_path,
-                    chunk.chunk_index,
-                    &chunk.data,
-                    chunk.chunk_hash.as_deref()
-                ).await?;
-                
-                // Progress aktualisieren
-                session.chunks.insert(chunk.chunk_index, ChunkStatus::Completed);
+        // Ältere Empfänger machen kein Gegenangebot und übernehmen den Vorschlag
+        if let Some(size) = accepted_chunk_size.filter(|size| *size != session.chunk_size) {
+            if !session.chunk_size_bounds.contains(size) {
+                return Err(FileTransferError::ChunkError(format!(
+                    "Receiver accepted a chunk size of {} bytes, allowed are {}-{} bytes",
+                    size, session.chunk_size_bounds.min, session.chunk_size_bounds.max
+                )));
+            }
+            
+            session.chunk_size = size;
+            session.progress.total_chunks = total_chunks(session.progress.total_bytes, size);
+            // Die vorab berechneten Hashes gelten nur für die vorgeschlagene Größe;
+            // der Empfänger prüft die Datei am Ende gegen den ursprünglichen Datei-Hash
+            session.chunk_hashes.clear();
+        }
+        
+        if let Some(max) = max_chunk_size {
+            session.chunk_size_bounds.max = session.chunk_size_bounds.max.min(max).max(session.chunk_size);
+        }
+        
+        session.chunk_acks = chunk_acks && self.config.adaptive_chunk_batching;
+        if session.chunk_acks {
+            self.batchers.lock().unwrap().insert(
+                transfer_id.to_string(),
+                ChunkBatcher::new(session.chunk_size, session.chunk_size_bounds.max),
+            );
+        }
+        
+        Ok(())
+    }
+    
+    /// Verbucht die Chunks, die der Empfänger schon hat, als gesendet. Nach
+    /// einer geänderten Chunk-Größe passen die Indizes nicht zum Manifest.
+    async fn skip_existing_chunks(&self, transfer_id: &str, existing_chunks: &[usize]) {
+        if existing_chunks.is_empty() {
+            return;
+        }
+        
+        let progress = {
+            let mut transfers = self.active_transfers.lock().unwrap();
+            let session = match transfers.get_mut(transfer_id) {
+                Some(session) if session.chunk_size == session.hash_chunk_size => session,
+                _ => return,
+            };
+            for &index in existing_chunks {
+                if index >= session.progress.total_chunks || session.chunks.get(&index) == Some(&ChunkStatus::Completed) {
+                    continue;
+                }
+                let len = dedup::chunk_len(session.progress.total_bytes, session.chunk_size, index) as u64;
+                session.chunks.insert(index, ChunkStatus::Completed);
                 session.progress.chunks_completed += 1;
-                session.progress.bytes_transferred += chunk.data.len() as u64;
-                session.last_activity = Instant::now();
-                
-                // Transfer-Rate berechnen
-                let elapsed = session.started_at.elapsed().as_secs_f64();
-                if elapsed > 0.0 {
-                    session.progress.transfer_rate = session.progress.bytes_transferred as f64 / elapsed;
-                    
-                    // ETA schätzen
-                    let remaining_bytes = session.progress.total_bytes - session.progress.bytes_transferred;
-                    if session.progress.transfer_rate > 0.0 {
-                        session.progress.eta_seconds = Some(remaining_bytes as f64 / session.progress.transfer_rate);
+                session.progress.bytes_transferred += len;
+                session.progress.bytes_deduplicated += len;
+            }
+            session.progress.clone()
+        };
+        
+        self.send_event(TransferEvent::TransferProgress {
+            transfer_id: transfer_id.to_string(),
+            progress,
+        }).await;
+    }
+    
+    /// Behandelt eingehende Chunk-Daten
+    async fn handle_chunk_data(
+        &self,
+        peer_id: &str,
+        chunk: ChunkData
+    ) -> Result<(), FileTransferError> {
+        let (dest_path, encrypted, chunk_size, chunk_acks) = {
+            let transfers = self.active_transfers.lock().unwrap();
+            match transfers.get(&chunk.transfer_id) {
+                Some(session) => {
+                    // Bereits geschriebene Chunks ignorieren (Wiederholungen)
+                    if session.chunks.get(&chunk.chunk_index) == Some(&ChunkStatus::Completed) {
+                        return Ok(());
                     }
-                }
-                
-                // Progress-Event senden
-                drop(transfers); // Mutex freigeben vor async
-                self.send_event(TransferEvent::TransferProgress {
+                    match session.write_path() {
+                        Some(path) => (path.clone(), session.encrypted, session.chunk_size, session.chunk_acks),
+                        None => return Ok(()),
+                    }
+                },
+                None => return Ok(()),
+            }
+        };
+        
+        // Unverschlüsselte Chunks in einer verschlüsselten Übertragung nicht akzeptieren
+        let cipher = if encrypted {
+            if !chunk.encrypted {
+                let error = FileTransferError::DecryptionFailed {
                     transfer_id: chunk.transfer_id.clone(),
-                    progress: session.progress.clone(),
-                }).await;
-                
-                // Prüfen, ob Transfer komplett ist
-                if session.progress.chunks_completed >= session.progress.total_chunks {
-                    self.complete_download(&chunk.transfer_id).await?;
-                }
+                    chunk_index: chunk.chunk_index,
+                };
+                self.fail_transfer(&chunk.transfer_id, &error).await;
+                return Err(error);
             }
+            Some(self.security.cipher(&chunk.transfer_id)?)
+        } else {
+            None
+        };
+        
+        // Chunk entschlüsseln, validieren und speichern
+        let written = match self.chunk_manager.write_chunk(
+            &dest_path,
+            chunk.chunk_index,
+            chunk_size,
+            &chunk.data,
+            chunk.chunk_hash.as_deref(),
+            cipher.as_ref()
+        ).await {
+            Ok(written) => written,
+            Err(e @ FileTransferError::DecryptionFailed { .. }) => {
+                // Falscher Schlüssel oder manipulierter Chunk: Übertragung abbrechen
+                self.fail_transfer(&chunk.transfer_id, &e).await;
+                return Err(e);
+            },
+            Err(e) => return Err(e),
+        };
+        
+        // Für spätere Übertragungen derselben Daten zwischenspeichern
+        if self.config.deduplication && self.chunk_cache.lock().unwrap().is_enabled() {
+            let data = self.chunk_manager.read_chunk(&dest_path, chunk.chunk_index, chunk_size).await?;
+            self.chunk_cache.lock().unwrap().insert(data);
+        }
+        
+        let progress = {
+            let mut transfers = self.active_transfers.lock().unwrap();
+            let session = match transfers.get_mut(&chunk.transfer_id) {
+                Some(session) => session,
+                None => return Ok(()),
+            };
+            
+            // Progress aktualisieren
+            session.chunks.insert(chunk.chunk_index, ChunkStatus::Completed);
+            session.chunk_hashes.insert(chunk.chunk_index, written.hash);
+            session.progress.chunks_completed += 1;
+            session.progress.bytes_transferred += written.len as u64;
+            session.last_activity = activity_now();
+            update_rate(&mut session.progress, session.started_at);
+            
+            session.progress.clone()
+        };
+        
+        // Progress-Event senden
+        self.send_event(TransferEvent::TransferProgress {
+            transfer_id: chunk.transfer_id.clone(),
+            progress: progress.clone(),
+        }).await;
+        
+        // Bestätigung für den adaptiven Batch des Senders
+        if chunk_acks {
+            self.send_chunk_ack(peer_id, ChunkAck {
+                transfer_id: chunk.transfer_id.clone(),
+                chunk_index: chunk.chunk_index,
+            }).await?;
+        }
+        
+        // Prüfen, ob Transfer komplett ist
+        if progress.chunks_completed >= progress.total_chunks {
+            self.complete_download(&chunk.transfer_id).await?;
         }
         
         Ok(())
@@ -586,28 +1586,19 @@ impl FileTransferManager {
         peer_id: &str,
         request: ChunkRequest
     ) -> Result<(), FileTransferError> {
-        let transfers = self.active_transfers.lock().unwrap();
-        
-        if let Some(session) = transfers.get(&request.transfer_id) {
-            if let Some(source_path) = &session.source_path {
-                // Chunk lesen und senden
-                let chunk_data = self.chunk_manager.read_chunk(
-                    source_path,
-                    request.chunk_index,
-                    self.config.chunk_size
-                ).await?;
-                
-                // Chunk an Peer senden
-                self.send_chunk_to_peer(peer_id, ChunkData {
-                    transfer_id: request.transfer_id,
-                    chunk_index: request.chunk_index,
-                    data: chunk_data,
-                    chunk_hash: None, // Wird vom ChunkManager berechnet
-                }).await?;
-            }
+        let known = self.active_transfers.lock().unwrap().contains_key(&request.transfer_id);
+        if !known {
+            return Ok(());
         }
         
-        Ok(())
+        self.send_chunk(peer_id, &request.transfer_id, request.chunk_index).await
+    }
+    
+    /// Verbucht die Bestätigung eines gesendeten Chunks
+    fn handle_chunk_ack(&self, ack: ChunkAck) {
+        if let Some(batcher) = self.batchers.lock().unwrap().get_mut(&ack.transfer_id) {
+            batcher.acked(ack.chunk_index);
+        }
     }
     
     /// Behandelt Kontrollnachrichten
@@ -630,49 +1621,686 @@ impl FileTransferManager {
         Ok(())
     }
     
-    /// Startet das Senden von Chunks für einen Upload
-    async fn start_upload_chunks(&self, transfer_id: &str) -> Result<(), FileTransferError> {
-        // Implementation würde hier Chunks in separaten Tasks senden
-        // Für jetzt als Platzhalter
-        println!("Starting upload chunks for transfer: {}", transfer_id);
+    /// Beantwortet eine Vorschau-Anfrage des Empfängers. Eine noch fehlende
+    /// Vorschau wird im Hintergrund erzeugt, damit der Nachrichtenfluss weiterläuft.
+    fn handle_preview_request(
+        &self,
+        peer_id: &str,
+        request: PreviewRequest
+    ) -> Result<(), FileTransferError> {
+        let (preview, source_path, mime_type) = {
+            let transfers = self.active_transfers.lock().unwrap();
+            match transfers.get(&request.transfer_id) {
+                Some(session) if session.transfer_type == TransferType::Upload && session.peer_id == peer_id => (
+                    session.preview.clone(),
+                    session.source_path.clone(),
+                    session.file_metadata.mime_type.clone(),
+                ),
+                _ => return Err(FileTransferError::TransferNotFound(request.transfer_id)),
+            }
+        };
+        
+        let transfer_id = request.transfer_id;
+        if !self.preview_limiter.lock().unwrap().check(peer_id, Instant::now()) {
+            log::warn!(target: TARGET_TRANSFER, "Too many preview requests from {}, not answering for {}", peer_id, transfer_id);
+            self.forward_message(peer_id, TransferMessage::Preview(PreviewResponse { transfer_id, preview: None }));
+            return Ok(());
+        }
+        
+        let source_path = match (preview, source_path) {
+            (Some(preview), _) => {
+                self.forward_message(peer_id, TransferMessage::Preview(PreviewResponse { transfer_id, preview: Some(preview) }));
+                return Ok(());
+            },
+            (None, Some(path)) => path,
+            (None, None) => {
+                self.forward_message(peer_id, TransferMessage::Preview(PreviewResponse { transfer_id, preview: None }));
+                return Ok(());
+            },
+        };
+        
+        let worker = self.shared();
+        let peer_id = peer_id.to_string();
+        tokio::spawn(async move {
+            let preview = worker.create_preview(&source_path, &mime_type).await;
+            if let Some(session) = worker.active_transfers.lock().unwrap().get_mut(&transfer_id) {
+                session.preview = preview.clone();
+            }
+            worker.forward_message(&peer_id, TransferMessage::Preview(PreviewResponse { transfer_id, preview }));
+        });
         Ok(())
     }
     
-    /// Schließt einen Download ab
-    async fn complete_download(&self, transfer_id: &str) -> Result<(), FileTransferError> {
-        let mut transfers = self.active_transfers.lock().unwrap();
+    /// Übernimmt eine nachgereichte Vorschau und meldet sie dem UI
+    async fn handle_preview_response(
+        &self,
+        peer_id: &str,
+        response: PreviewResponse
+    ) -> Result<(), FileTransferError> {
+        let preview = response.preview.filter(FilePreview::within_limit);
+        {
+            let mut transfers = self.active_transfers.lock().unwrap();
+            match transfers.get_mut(&response.transfer_id) {
+                Some(session) if session.transfer_type == TransferType::Download && session.peer_id == peer_id => {
+                    session.preview = preview.clone();
+                },
+                _ => return Err(FileTransferError::TransferNotFound(response.transfer_id)),
+            }
+        }
         
-        if let Some(session) = transfers.get_mut(transfer_id) {
-            // Hash-Verifizierung
-            if let Some(dest_path) = &session.destination_path {
-                if let Some(expected_hash) = &session.file_hash {
-                    let actual_hash = self.calculate_file_hash(dest_path).await?;
-                    
-                    if actual_hash != *expected_hash {
-                        return Err(FileTransferError::HashMismatch {
-                            expected: expected_hash.clone(),
-                            actual: actual_hash,
-                        });
+        self.send_event(TransferEvent::TransferPreview {
+            transfer_id: response.transfer_id,
+            preview,
+        }).await;
+        Ok(())
+    }
+    
+    /// Schickt dem Empfänger die Chunk-Hashes, die nicht in die Anfrage passten
+    fn handle_manifest_request(
+        &self,
+        peer_id: &str,
+        request: ManifestRequest
+    ) -> Result<(), FileTransferError> {
+        let hashes: Vec<String> = {
+            let transfers = self.active_transfers.lock().unwrap();
+            match transfers.get(&request.transfer_id) {
+                Some(session) if session.transfer_type == TransferType::Upload && session.peer_id == peer_id => {
+                    (0..session.progress.total_chunks)
+                        .map(|index| session.chunk_hashes.get(&index).cloned().unwrap_or_default())
+                        .collect()
+                },
+                _ => return Err(FileTransferError::TransferNotFound(request.transfer_id)),
+            }
+        };
+        
+        for (page, chunk_hashes) in hashes.chunks(MANIFEST_PAGE_CHUNKS).enumerate() {
+            self.forward_message(peer_id, TransferMessage::Manifest(ChunkManifest {
+                transfer_id: request.transfer_id.clone(),
+                first_index: page * MANIFEST_PAGE_CHUNKS,
+                chunk_hashes: chunk_hashes.to_vec(),
+            }));
+        }
+        Ok(())
+    }
+    
+    /// Trägt eine Seite der Chunk-Hashes in einen Download ein
+    fn handle_manifest(
+        &self,
+        peer_id: &str,
+        manifest: ChunkManifest
+    ) -> Result<(), FileTransferError> {
+        let mut transfers = self.active_transfers.lock().unwrap();
+        match transfers.get_mut(&manifest.transfer_id) {
+            Some(session) if session.transfer_type == TransferType::Download && session.peer_id == peer_id => {
+                // Ungültige Einträge bleiben leer; ohne vollständiges Manifest wird nichts übernommen
+                let slots = session.manifest.iter_mut().skip(manifest.first_index);
+                for (slot, hash) in slots.zip(manifest.chunk_hashes) {
+                    if is_chunk_hash(&hash) {
+                        *slot = hash;
                     }
                 }
+                Ok(())
+            },
+            _ => Err(FileTransferError::TransferNotFound(manifest.transfer_id)),
+        }
+    }
+    
+    /// Startet das Senden von Chunks für einen Upload
+    async fn start_upload_chunks(&self, transfer_id: &str) -> Result<(), FileTransferError> {
+        let (peer_id, pending, chunk_acks) = {
+            let mut transfers = self.active_transfers.lock().unwrap();
+            let session = transfers.get_mut(transfer_id)
+                .ok_or_else(|| FileTransferError::TransferNotFound(transfer_id.to_string()))?;
+            session.status = TransferStatus::Active;
+            session.started_at = Instant::now();
+            // Chunks, die der Empfänger schon hat, sind bereits abgeschlossen
+            let pending: Vec<usize> = (0..session.progress.total_chunks)
+                .filter(|index| session.chunks.get(index) != Some(&ChunkStatus::Completed))
+                .collect();
+            (session.peer_id.clone(), pending, session.chunk_acks)
+        };
+        
+        for chunk_index in pending {
+            // Pausierte Übertragungen warten, beendete abbrechen
+            loop {
+                let status = self.active_transfers.lock().unwrap()
+                    .get(transfer_id)
+                    .map(|session| session.status.clone());
+                
+                match status {
+                    // Hängend heißt nur ohne Aktivität; weitersenden, bis die Wartung abbricht
+                    Some(TransferStatus::Active) | Some(TransferStatus::Stalled) => break,
+                    Some(TransferStatus::Paused) | Some(TransferStatus::Queued) => {
+                        tokio::time::sleep(PAUSE_POLL_INTERVAL).await
+                    },
+                    _ => return Ok(()),
+                }
             }
             
-            session.status = TransferStatus::Completed;
+            if chunk_acks {
+                self.wait_for_batch_room(transfer_id).await;
+            }
+            
+            self.send_chunk(&peer_id, transfer_id, chunk_index).await?;
+        }
+        
+        self.complete_upload(transfer_id).await;
+        Ok(())
+    }
+    
+    /// Wartet, bis der adaptive Batch einen weiteren unbestätigten Chunk erlaubt
+    async fn wait_for_batch_room(&self, transfer_id: &str) {
+        loop {
+            {
+                let mut batchers = self.batchers.lock().unwrap();
+                match batchers.get_mut(transfer_id) {
+                    Some(batcher) => {
+                        batcher.expire(ACK_TIMEOUT);
+                        if batcher.has_room() {
+                            return;
+                        }
+                    },
+                    None => return,
+                }
+            }
+            
+            tokio::time::sleep(ACK_POLL_INTERVAL).await;
+        }
+    }
+    
+    /// Schließt einen Upload ab und startet die nächste wartende Übertragung
+    async fn complete_upload(&self, transfer_id: &str) {
+        let entry = {
+            let mut transfers = self.active_transfers.lock().unwrap();
+            match transfers.get_mut(transfer_id) {
+                Some(session) if matches!(session.status, TransferStatus::Active | TransferStatus::Stalled) => {
+                    session.status = TransferStatus::Completed;
+                    session.last_activity = activity_now();
+                    Some((
+                        TransferHistoryEntry::from_session(session, TransferOutcome::Completed, None),
+                        session.progress.bytes_deduplicated,
+                        session.progress.total_chunks,
+                    ))
+                },
+                _ => None,
+            }
+        };
+        
+        if let Some((mut entry, deduplicated, chunk_count)) = entry {
+            // Uploads kennen nur den Hash der Chunk-Liste
+            self.attach_receipt(&mut entry, chunk_count, None).await;
+            let size = entry.size;
+            self.security.remove_transfer(transfer_id);
+            self.bandwidth.remove_transfer(transfer_id);
+            self.batchers.lock().unwrap().remove(transfer_id);
+            self.audit_completed(AuditEventKind::FileSent, &entry);
+            self.history.record(entry);
+            
+            {
+                let mut stats = self.stats.lock().unwrap();
+                stats.uploads_completed += 1;
+                stats.total_bytes_transferred += size;
+                stats.total_bytes_deduplicated += deduplicated;
+            }
             
-            // Event senden
-            drop(transfers); // Mutex freigeben vor async
             self.send_event(TransferEvent::TransferCompleted {
                 transfer_id: transfer_id.to_string(),
             }).await;
             
-            // Statistiken aktualisieren
-            {
-                let mut stats = self.stats.lock().unwrap();
-                stats.downloads_completed += 1;
-                stats.total_bytes_transferred += session.file_metadata.size;
+            self.schedule_next().await;
+        }
+    }
+    
+    /// Zählt laufende Uploads, die einen Platz belegen; hängende bis zu ihrem Abbruch
+    fn count_active(&self, transfers: &HashMap<String, TransferSession>) -> usize {
+        transfers.values()
+            .filter(|session| session.transfer_type == TransferType::Upload)
+            .filter(|session| matches!(session.status, TransferStatus::Preparing | TransferStatus::Active | TransferStatus::Stalled))
+            .count()
+    }
+    
+    /// Startet wartende Uploads, solange Plätze frei sind
+    async fn schedule_next(&self) {
+        loop {
+            let next = {
+                let transfers = self.active_transfers.lock().unwrap();
+                if self.count_active(&transfers) >= self.config.max_concurrent_transfers {
+                    return;
+                }
+                self.queue.lock().unwrap().pop_next()
+            };
+            
+            let transfer_id = match next {
+                Some(id) => id,
+                None => return,
+            };
+            
+            if let Err(e) = self.activate_upload(&transfer_id).await {
+                // Uploads hinterlassen keine Teildatei
+                let _ = self.mark_failed(&transfer_id, &e);
+                self.send_event(TransferEvent::TransferFailed {
+                    transfer_id,
+                    error: e.to_string(),
+                }).await;
+            }
+        }
+    }
+    
+    /// Aktiviert einen Upload aus der Queue: neue Uploads senden ihre Anfrage,
+    /// zuvor pausierte laufen einfach weiter
+    async fn activate_upload(&self, transfer_id: &str) -> Result<(), FileTransferError> {
+        let request = {
+            let mut transfers = self.active_transfers.lock().unwrap();
+            let session = match transfers.get_mut(transfer_id) {
+                Some(session) => session,
+                None => return Ok(()),
+            };
+            
+            session.last_activity = activity_now();
+            
+            if session.activated {
+                session.status = TransferStatus::Active;
+                None
+            } else {
+                session.status = TransferStatus::Preparing;
+                session.activated = true;
+                // Kleine Manifeste reisen mit der Anfrage, große auf Nachfrage
+                let total_chunks = session.progress.total_chunks;
+                let chunk_hashes = if self.config.deduplication && total_chunks <= MANIFEST_PAGE_CHUNKS {
+                    (0..total_chunks)
+                        .map(|index| session.chunk_hashes.get(&index).cloned().unwrap_or_default())
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                Some((session.peer_id.clone(), TransferRequest {
+                    protocol_version: PROTOCOL_VERSION,
+                    transfer_id: transfer_id.to_string(),
+                    file_metadata: session.file_metadata.clone(),
+                    file_hash: session.file_hash.clone().unwrap_or_default(),
+                    chunk_size: session.chunk_size,
+                    total_chunks: session.progress.total_chunks,
+                    encryption_enabled: session.encrypted,
+                    sender_public_key: None,
+                    hash_kind: session.hash_kind,
+                    chunk_size_bounds: Some(session.chunk_size_bounds),
+                    chunk_acks: self.config.adaptive_chunk_batching,
+                    preview: session.preview.clone(),
+                    dedup: self.config.deduplication,
+                    chunk_hashes,
+                }))
+            }
+        };
+        
+        let (peer_id, mut request) = match request {
+            Some(request) => request,
+            None => {
+                self.send_event(TransferEvent::TransferResumed {
+                    transfer_id: transfer_id.to_string(),
+                }).await;
+                return Ok(());
+            }
+        };
+        
+        // Ephemeren Schlüssel für diese Übertragung erzeugen
+        if request.encryption_enabled {
+            request.sender_public_key = Some(self.security.begin_key_exchange(transfer_id));
+        }
+        
+        // Upload-Anfrage an Peer senden
+        self.send_transfer_request(&peer_id, request).await
+    }
+    
+    /// Liest, verschlüsselt und sendet einen Chunk unter Beachtung der Bandbreitengrenze
+    async fn send_chunk(
+        &self,
+        peer_id: &str,
+        transfer_id: &str,
+        chunk_index: usize
+    ) -> Result<(), FileTransferError> {
+        let (source_path, encrypted, known_hash, chunk_size) = {
+            let transfers = self.active_transfers.lock().unwrap();
+            match transfers.get(transfer_id) {
+                Some(session) => match &session.source_path {
+                    Some(path) => (
+                        path.clone(),
+                        session.encrypted,
+                        session.chunk_hashes.get(&chunk_index).cloned(),
+                        session.chunk_size,
+                    ),
+                    None => return Ok(()),
+                },
+                None => return Ok(()),
+            }
+        };
+        
+        // Chunk in der ausgehandelten Größe lesen; der letzte ist ggf. kürzer
+        let chunk_data = self.chunk_manager.read_chunk(
+            &source_path,
+            chunk_index,
+            chunk_size
+        ).await?;
+        let chunk_len = chunk_data.len() as u64;
+        
+        // Hash über den Klartext, damit der Empfänger nach dem Entschlüsseln prüfen kann;
+        // er stammt aus der Vorab-Berechnung, eine inzwischen geänderte Datei fällt so auf
+        let chunk_hash = known_hash.unwrap_or_else(|| ChunkManager::calculate_chunk_hash(&chunk_data));
+        
+        let data = if encrypted {
+            self.security.cipher(transfer_id)?
+                .encrypt_chunk(chunk_index, &chunk_data)?
+        } else {
+            chunk_data
+        };
+        
+        // Drosseln, bevor der Chunk die Leitung belegt
+        self.bandwidth.acquire(transfer_id, data.len() as u64).await;
+        
+        if let Some(batcher) = self.batchers.lock().unwrap().get_mut(transfer_id) {
+            batcher.sent(chunk_index);
+        }
+        
+        // Chunk an Peer senden
+        self.send_chunk_to_peer(peer_id, ChunkData {
+            transfer_id: transfer_id.to_string(),
+            chunk_index,
+            data,
+            chunk_hash: Some(chunk_hash),
+            encrypted,
+        }).await?;
+        
+        // Fortschritt des Uploads aktualisieren; die Rate spiegelt die Drosselung wider
+        let progress = {
+            let mut transfers = self.active_transfers.lock().unwrap();
+            transfers.get_mut(transfer_id).map(|session| {
+                session.chunks.insert(chunk_index, ChunkStatus::Completed);
+                session.progress.chunks_completed += 1;
+                session.progress.bytes_transferred += chunk_len;
+                session.last_activity = activity_now();
+                update_rate(&mut session.progress, session.started_at);
+                session.progress.clone()
+            })
+        };
+        
+        if let Some(progress) = progress {
+            self.send_event(TransferEvent::TransferProgress {
+                transfer_id: transfer_id.to_string(),
+                progress,
+            }).await;
+        }
+        
+        Ok(())
+    }
+    
+    /// Fordert alle noch fehlenden Chunks eines Downloads gedrosselt an
+    async fn request_missing_chunks(&self, transfer_id: &str) -> Result<(), FileTransferError> {
+        let (peer_id, missing, chunk_size) = {
+            let transfers = self.active_transfers.lock().unwrap();
+            let session = transfers.get(transfer_id)
+                .ok_or_else(|| FileTransferError::TransferNotFound(transfer_id.to_string()))?;
+            
+            let missing: Vec<usize> = (0..session.progress.total_chunks)
+                .filter(|index| session.chunks.get(index) != Some(&ChunkStatus::Completed))
+                .collect();
+            
+            (session.peer_id.clone(), missing, session.chunk_size)
+        };
+        
+        for chunk_index in missing {
+            // Abbrechen, sobald die Übertragung nicht mehr aktiv ist
+            let active = self.active_transfers.lock().unwrap()
+                .get(transfer_id)
+                .map(|session| session.status == TransferStatus::Active)
+                .unwrap_or(false);
+            if !active {
+                break;
+            }
+            
+            // Anfragen im Takt der erwarteten Chunk-Größe
+            self.bandwidth.acquire(transfer_id, chunk_size as u64).await;
+            
+            self.send_chunk_request(&peer_id, ChunkRequest {
+                transfer_id: transfer_id.to_string(),
+                chunk_index,
+            }).await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Schließt einen Download ab: Größe und Hash prüfen, die Datei aus der
+    /// Quarantäne ans Ziel verschieben, dann Berechtigungen und Änderungszeit
+    /// der Originaldatei übernehmen
+    async fn complete_download(&self, transfer_id: &str) -> Result<(), FileTransferError> {
+        let (written_path, dest_path, file_hash, file_metadata, hash_kind, hash_chunk_size, chunk_hashes) = {
+            let transfers = self.active_transfers.lock().unwrap();
+            match transfers.get(transfer_id) {
+                Some(session) => match (session.write_path(), &session.destination_path) {
+                    (Some(written_path), Some(path)) => {
+                        // Lücken in der Liste bedeuten, dass nicht alle Chunks geschrieben wurden;
+                        // bei abweichender Chunk-Größe passen die Hashes nicht zum Datei-Hash
+                        let chunk_hashes: Option<Vec<String>> = (0..session.progress.total_chunks)
+                            .map(|index| session.chunk_hashes.get(&index).cloned())
+                            .collect::<Option<Vec<String>>>()
+                            .filter(|_| session.chunk_size == session.hash_chunk_size);
+                        (
+                            written_path.clone(),
+                            path.clone(),
+                            session.file_hash.clone(),
+                            session.file_metadata.clone(),
+                            session.hash_kind,
+                            session.hash_chunk_size,
+                            chunk_hashes,
+                        )
+                    },
+                    _ => return Ok(()),
+                },
+                None => return Err(FileTransferError::TransferNotFound(transfer_id.to_string())),
+            }
+        };
+        
+        let expected = file_hash.as_deref().map(|hash| (hash, hash_kind, hash_chunk_size));
+        let verified = match self.verify_download(&written_path, expected, chunk_hashes, &file_metadata).await {
+            Ok(()) if written_path != dest_path => move_into_place(&written_path, &dest_path),
+            verified => verified,
+        };
+        // Berechtigungen (ohne setuid/setgid) und Änderungszeit übernehmen
+        if let Err(e) = verified.and_then(|()| metadata::apply_metadata(&dest_path, &file_metadata, self.config.permission_mask)) {
+            self.fail_transfer(transfer_id, &e).await;
+            return Err(e);
+        }
+        self.discard_staging(transfer_id);
+        
+        let entry = {
+            let mut transfers = self.active_transfers.lock().unwrap();
+            transfers.get_mut(transfer_id).map(|session| {
+                session.status = TransferStatus::Completed;
+                session.last_activity = activity_now();
+                (
+                    TransferHistoryEntry::from_session(session, TransferOutcome::Completed, None),
+                    session.progress.bytes_deduplicated,
+                    session.progress.total_chunks,
+                )
+            })
+        };
+        self.security.remove_transfer(transfer_id);
+        self.bandwidth.remove_transfer(transfer_id);
+        self.batchers.lock().unwrap().remove(transfer_id);
+        let deduplicated = entry.as_ref().map_or(0, |(_, deduplicated, _)| *deduplicated);
+        if let Some((mut entry, _, chunk_count)) = entry {
+            // Ein geprüfter Hash über die ganze Datei muss nicht neu berechnet werden
+            let whole_file_hash = file_hash.filter(|_| hash_kind == FileHashKind::WholeFile);
+            self.attach_receipt(&mut entry, chunk_count, whole_file_hash).await;
+            self.audit_completed(AuditEventKind::FileReceived, &entry);
+            self.history.record(entry);
+        }
+        
+        // Event senden
+        self.send_event(TransferEvent::TransferCompleted {
+            transfer_id: transfer_id.to_string(),
+        }).await;
+        
+        // Statistiken aktualisieren
+        {
+            let mut stats = self.stats.lock().unwrap();
+            stats.downloads_completed += 1;
+            stats.total_bytes_transferred += file_metadata.size;
+            stats.total_bytes_deduplicated += deduplicated;
+        }
+        
+        Ok(())
+    }
+    
+    /// Prüft Größe und Hash der empfangenen Datei
+    async fn verify_download(
+        &self,
+        dest_path: &Path,
+        expected_hash: Option<(&str, FileHashKind, usize)>,
+        chunk_hashes: Option<Vec<String>>,
+        file_metadata: &FileMetadata
+    ) -> Result<(), FileTransferError> {
+        // Größe prüfen
+        let actual_size = std::fs::metadata(dest_path)?.len();
+        if actual_size != file_metadata.size {
+            return Err(FileTransferError::SizeMismatch {
+                expected: file_metadata.size,
+                actual: actual_size,
+            });
+        }
+        
+        // Hash-Verifizierung
+        if let Some((expected_hash, hash_kind, hash_chunk_size)) = expected_hash {
+            let actual_hash = match (hash_kind, self.config.verification_mode, chunk_hashes) {
+                (FileHashKind::WholeFile, _, _) => self.calculate_file_hash(dest_path).await?,
+                (FileHashKind::ChunkList, VerificationMode::ChunkHashes, Some(chunk_hashes)) => {
+                    ChunkManager::combine_chunk_hashes(&chunk_hashes)
+                },
+                (FileHashKind::ChunkList, _, _) => {
+                    ChunkManager::combine_chunk_hashes(&self.hash_chunks_parallel(dest_path, hash_chunk_size).await?)
+                },
+            };
+            
+            if actual_hash != expected_hash {
+                return Err(FileTransferError::HashMismatch {
+                    expected: expected_hash.to_string(),
+                    actual: actual_hash,
+                });
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Markiert eine Übertragung als fehlgeschlagen und räumt auf
+    async fn fail_transfer(&self, transfer_id: &str, error: &FileTransferError) {
+        let partial_file = self.mark_failed(transfer_id, error);
+        
+        // Unvollständige Datei nicht liegen lassen
+        if let Some(path) = partial_file {
+            let _ = std::fs::remove_file(path);
+        }
+        self.discard_staging(transfer_id);
+        
+        self.send_event(TransferEvent::TransferFailed {
+            transfer_id: transfer_id.to_string(),
+            error: error.to_string(),
+        }).await;
+        
+        self.schedule_next().await;
+    }
+    
+    /// Stellt die Quittung einer abgeschlossenen Übertragung aus. Ohne
+    /// Geräteidentität bleibt der Eintrag ohne Quittung.
+    async fn attach_receipt(&self, entry: &mut TransferHistoryEntry, chunk_count: usize, whole_file_hash: Option<String>) {
+        let Some(signer) = &self.receipt_signer else {
+            return;
+        };
+        let sha256 = match (whole_file_hash, &entry.local_path) {
+            (Some(hash), _) => hash,
+            (None, Some(path)) => match self.calculate_file_hash(path).await {
+                Ok(hash) => hash,
+                Err(e) => {
+                    log::warn!(target: TARGET_TRANSFER, "No receipt for transfer {}, hashing failed: {}", entry.transfer_id, e);
+                    return;
+                },
+            },
+            (None, None) => return,
+        };
+        
+        match signer(TransferReceipt::new(entry, sha256, chunk_count)) {
+            Some(receipt) => entry.receipt = Some(receipt),
+            None => log::warn!(target: TARGET_TRANSFER, "No device identity, transfer {} completed without a receipt", entry.transfer_id),
+        }
+    }
+    
+    /// Protokolliert eine abgeschlossene Übertragung im Audit-Protokoll
+    fn audit_completed(&self, kind: AuditEventKind, entry: &TransferHistoryEntry) {
+        if let Some(sink) = &self.audit_sink {
+            sink.record(AuditEvent::new(kind, Some(&entry.peer_id))
+                .with_detail(entry.file_name.clone())
+                .with_size(entry.size));
+        }
+    }
+    
+    /// Setzt den Fehlerstatus und gibt bei Downloads den Pfad der Teildatei zurück
+    fn mark_failed(&self, transfer_id: &str, error: &FileTransferError) -> Option<PathBuf> {
+        let (partial_file, entry) = {
+            let mut transfers = self.active_transfers.lock().unwrap();
+            match transfers.get_mut(transfer_id) {
+                Some(session) => {
+                    session.status = TransferStatus::Failed(error.to_string());
+                    session.last_activity = activity_now();
+                    
+                    let entry = TransferHistoryEntry::from_session(
+                        session,
+                        TransferOutcome::Failed,
+                        Some(error.to_string()),
+                    );
+                    let partial_file = if session.transfer_type == TransferType::Download {
+                        session.write_path().cloned()
+                    } else {
+                        None
+                    };
+                    (partial_file, Some(entry))
+                },
+                None => (None, None),
             }
+        };
+        
+        self.security.remove_transfer(transfer_id);
+        self.bandwidth.remove_transfer(transfer_id);
+        self.batchers.lock().unwrap().remove(transfer_id);
+        if let Some(entry) = entry {
+            self.history.record(entry);
+        }
+        
+        {
+            let mut stats = self.stats.lock().unwrap();
+            stats.transfers_failed += 1;
         }
         
+        partial_file
+    }
+    
+    /// Sendet eine Chunk-Anfrage an einen Peer
+    async fn send_chunk_request(
+        &self,
+        peer_id: &str,
+        request: ChunkRequest
+    ) -> Result<(), FileTransferError> {
+        log::debug!(target: TARGET_TRANSFER, "Requesting chunk from {}: transfer_id={}, chunk_index={}",
+                 peer_id, request.transfer_id, request.chunk_index);
+        self.forward_message(peer_id, TransferMessage::ChunkRequest(request));
+        Ok(())
+    }
+    
+    /// Sendet eine Chunk-Bestätigung an einen Peer
+    async fn send_chunk_ack(
+        &self,
+        peer_id: &str,
+        ack: ChunkAck
+    ) -> Result<(), FileTransferError> {
+        self.forward_message(peer_id, TransferMessage::ChunkAck(ack));
         Ok(())
     }
     
@@ -682,9 +2310,632 @@ impl FileTransferManager {
         peer_id: &str,
         chunk: ChunkData
     ) -> Result<(), FileTransferError> {
-        // Hier würde die tatsächliche Netzwerkübertragung implementiert
-        println!("Sending chunk to {}: transfer_id={}, chunk_index={}, size={}", 
+        log::debug!(target: TARGET_TRANSFER, "Sending chunk to {}: transfer_id={}, chunk_index={}, size={}", 
                  peer_id, chunk.transfer_id, chunk.chunk_index, chunk.data.len());
+        self.forward_message(peer_id, TransferMessage::Chunk(chunk));
         Ok(())
     }
 }
+
+/// Zeitpunkt für `last_activity` und die Wartung. Tokios Uhr entspricht
+/// `Instant::now()`, lässt sich in Tests aber anhalten und vorspulen.
+fn activity_now() -> Instant {
+    tokio::time::Instant::now().into_std()
+}
+
+/// Berechnet Übertragungsrate und Restzeit seit Beginn der Übertragung;
+/// beim Empfänger schon vorhandene Bytes zählen nicht zur Rate
+fn update_rate(progress: &mut TransferProgress, started_at: Instant) {
+    let elapsed = started_at.elapsed().as_secs_f64();
+    if elapsed > 0.0 {
+        let sent = progress.bytes_transferred.saturating_sub(progress.bytes_deduplicated);
+        progress.transfer_rate = sent as f64 / elapsed;
+        
+        // ETA schätzen
+        let remaining_bytes = progress.total_bytes.saturating_sub(progress.bytes_transferred);
+        if progress.transfer_rate > 0.0 {
+            progress.eta_seconds = Some(remaining_bytes as f64 / progress.transfer_rate);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection_security::identity::DeviceIdentity;
+    use crate::protocol::ProtocolError;
+    
+    const KB: usize = 1024;
+    
+    /// Eine Seite der Übertragung mit ihren ausgehenden Nachrichten und Events
+    struct Peer {
+        manager: FileTransferManager,
+        outgoing: mpsc::UnboundedReceiver<(String, TransferMessage)>,
+        events: mpsc::UnboundedReceiver<TransferEvent>,
+    }
+    
+    fn peer(config: TransferConfig) -> Peer {
+        let mut manager = FileTransferManager::new(config).unwrap();
+        let (message_sender, outgoing) = mpsc::unbounded_channel();
+        let (event_sender, events) = mpsc::unbounded_channel();
+        manager.set_message_sender(message_sender);
+        manager.set_event_sender(event_sender);
+        Peer { manager, outgoing, events }
+    }
+    
+    fn chunk_config(chunk_size: usize, min_chunk_size: usize, max_chunk_size: usize) -> TransferConfig {
+        TransferConfig {
+            chunk_size,
+            min_chunk_size,
+            max_chunk_size,
+            ..Default::default()
+        }
+    }
+    
+    fn session_chunks(peer: &Peer, transfer_id: &str) -> (usize, usize) {
+        let transfers = peer.manager.active_transfers.lock().unwrap();
+        let session = &transfers[transfer_id];
+        (session.chunk_size, session.progress.total_chunks)
+    }
+    
+    /// Überträgt `data` vom Sender zum Empfänger und liefert Transfer-ID und empfangene Datei
+    async fn transfer(sender: &mut Peer, receiver: &mut Peer, data: &[u8]) -> Result<(String, Vec<u8>), FileTransferError> {
+        let dir = std::env::temp_dir().join(format!("smoldesk-transfer-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("source.bin");
+        let dest = dir.join("dest.bin");
+        std::fs::write(&source, data).unwrap();
+        
+        let result = run_transfer(sender, receiver, &source, &dest).await
+            .map(|transfer_id| (transfer_id, std::fs::read(&dest).unwrap()));
+        std::fs::remove_dir_all(&dir).unwrap();
+        result
+    }
+    
+    async fn run_transfer(sender: &mut Peer, receiver: &mut Peer, source: &Path, dest: &Path) -> Result<String, FileTransferError> {
+        let (sender_manager, sender_outgoing) = (&sender.manager, &mut sender.outgoing);
+        let (receiver_manager, receiver_outgoing) = (&receiver.manager, &mut receiver.outgoing);
+        
+        let transfer_id = sender_manager.start_upload(source, "receiver", None, TransferPriority::default()).await?;
+        let (_, request) = sender_outgoing.try_recv().unwrap();
+        receiver_manager.handle_transfer_message("sender", request).await?;
+        receiver_manager.accept_transfer(&transfer_id, dest).await?;
+        let (_, accept) = receiver_outgoing.try_recv().unwrap();
+        
+        // Der Sender schickt seine Chunks, während Chunks und Bestätigungen weitergereicht werden
+        let upload = sender_manager.handle_transfer_message("receiver", accept);
+        let relay = async {
+            loop {
+                let done = receiver_manager.get_transfer_info(&transfer_id)
+                    .map(|info| info.status != TransferStatus::Active)
+                    .unwrap_or(true);
+                if done {
+                    return Ok::<(), FileTransferError>(());
+                }
+                
+                tokio::select! {
+                    Some((_, message)) = sender_outgoing.recv() => {
+                        receiver_manager.handle_transfer_message("sender", message).await?;
+                    },
+                    Some((_, message)) = receiver_outgoing.recv() => {
+                        sender_manager.handle_transfer_message("receiver", message).await?;
+                    },
+                }
+            }
+        };
+        
+        let (uploaded, relayed) = tokio::time::timeout(Duration::from_secs(10), async { tokio::join!(upload, relay) })
+            .await
+            .expect("transfer did not finish");
+        uploaded?;
+        relayed?;
+        
+        Ok(transfer_id)
+    }
+    
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+    
+    #[tokio::test]
+    async fn test_receiver_lowers_chunk_size() {
+        // LAN-Sender gegen einen Empfänger hinter einem engen Datenkanal
+        let mut sender = peer(chunk_config(1024 * KB, 16 * KB, 4096 * KB));
+        let mut receiver = peer(chunk_config(16 * KB, 4 * KB, 16 * KB));
+        let data = pattern(100_000);
+        
+        let (transfer_id, received) = transfer(&mut sender, &mut receiver, &data).await.unwrap();
+        
+        assert_eq!(received, data);
+        assert_eq!(receiver.manager.get_transfer_info(&transfer_id).unwrap().status, TransferStatus::Completed);
+        assert_eq!(session_chunks(&receiver, &transfer_id), (16 * KB, 7));
+        assert_eq!(session_chunks(&sender, &transfer_id), (16 * KB, 7));
+    }
+    
+    #[tokio::test]
+    async fn test_receiver_raises_chunk_size_with_adaptive_batches() {
+        let mut sender = peer(TransferConfig {
+            adaptive_chunk_batching: true,
+            ..chunk_config(16 * KB, 16 * KB, 1024 * KB)
+        });
+        let mut receiver = peer(chunk_config(64 * KB, 64 * KB, 256 * KB));
+        let data = pattern(300_000);
+        
+        let (transfer_id, received) = transfer(&mut sender, &mut receiver, &data).await.unwrap();
+        
+        assert_eq!(received, data);
+        assert_eq!(receiver.manager.get_transfer_info(&transfer_id).unwrap().status, TransferStatus::Completed);
+        assert_eq!(session_chunks(&receiver, &transfer_id), (64 * KB, 5));
+        assert!(sender.manager.active_transfers.lock().unwrap()[&transfer_id].chunk_acks);
+    }
+    
+    #[tokio::test]
+    async fn test_disjoint_chunk_sizes_reject_transfer() {
+        let mut sender = peer(chunk_config(16 * KB, 4 * KB, 16 * KB));
+        let mut receiver = peer(chunk_config(64 * KB, 64 * KB, 256 * KB));
+        
+        let result = transfer(&mut sender, &mut receiver, &pattern(1000)).await;
+        assert!(matches!(result, Err(FileTransferError::ChunkError(_))));
+        
+        let (_, reject) = receiver.outgoing.try_recv().unwrap();
+        assert!(matches!(reject, TransferMessage::Response(TransferResponse::Reject { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_download_lands_after_verification() {
+        let mut sender = peer(TransferConfig::default());
+        let mut receiver = peer(TransferConfig::default());
+        let dir = std::env::temp_dir().join(format!("smoldesk-transfer-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        receiver.manager.set_staging_dir(&dir.join("data")).unwrap();
+        let source = dir.join("source.bin");
+        let dest = dir.join("downloads").join("dest.bin");
+        let data = pattern(150_000);
+        std::fs::write(&source, &data).unwrap();
+        
+        let transfer_id = run_transfer(&mut sender, &mut receiver, &source, &dest).await.unwrap();
+        
+        // Geschrieben wurde in die Quarantäne, übrig bleibt nur die geprüfte Datei am Ziel
+        let session = receiver.manager.active_transfers.lock().unwrap()[&transfer_id].clone();
+        assert!(session.staging_path.unwrap().starts_with(dir.join("data").join(staging::STAGING_DIR)));
+        assert_eq!(std::fs::read(&dest).unwrap(), data);
+        let usage = receiver.manager.get_storage_usage();
+        assert_eq!((usage.staged_transfers, usage.staging_bytes), (0, 0));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_completed_transfers_get_signed_receipts() {
+        let mut sender = peer(chunk_config(16 * KB, 16 * KB, 16 * KB));
+        let mut receiver = peer(chunk_config(16 * KB, 16 * KB, 16 * KB));
+        let identities = [DeviceIdentity::generate(), DeviceIdentity::generate()];
+        let public_keys: Vec<String> = identities.iter().map(DeviceIdentity::public_key).collect();
+        for (peer, identity) in [&mut sender, &mut receiver].into_iter().zip(identities) {
+            peer.manager.set_receipt_signer(Box::new(move |receipt: TransferReceipt| {
+                Some(receipt.sign(&identity, Some("SHA256:peer".to_string())))
+            }));
+        }
+        let data = pattern(100_000);
+        
+        let (transfer_id, _) = transfer(&mut sender, &mut receiver, &data).await.unwrap();
+        
+        let expected_hash = format!("{:x}", Sha256::digest(&data));
+        for (peer, public_key) in [&sender, &receiver].into_iter().zip(&public_keys) {
+            let receipt = peer.manager.get_transfer_receipt(&transfer_id).unwrap();
+            assert_eq!((receipt.size, receipt.sha256.as_str(), receipt.chunk_count), (data.len() as u64, expected_hash.as_str(), 7));
+            assert_eq!(receipt.peer_fingerprint.as_deref(), Some("SHA256:peer"));
+            assert!(receipt.verify(Some(public_key)).is_ok());
+        }
+        assert_eq!(sender.manager.get_transfer_receipt(&transfer_id).unwrap().transfer_type, TransferType::Upload);
+        assert!(matches!(sender.manager.get_transfer_receipt("unknown"), Err(FileTransferError::TransferNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_unversioned_request_is_rejected() {
+        let mut receiver = peer(TransferConfig { encryption_enabled: false, ..TransferConfig::default() });
+        let message = TransferMessage::decode(r#"{"type":"request","payload":{"transferId":"t1"}}"#);
+        assert!(matches!(message, Err(ProtocolError::VersionMismatch(_))));
+
+        let request = TransferRequest {
+            protocol_version: 0,
+            transfer_id: "t1".to_string(),
+            file_metadata: FileMetadata {
+                name: "old.bin".to_string(),
+                size: 10,
+                mime_type: "application/octet-stream".to_string(),
+                created: SystemTime::UNIX_EPOCH,
+                modified: SystemTime::UNIX_EPOCH,
+                permissions: 0o644,
+                attributes: HashMap::new(),
+            },
+            file_hash: "unused".to_string(),
+            chunk_size: 64 * KB,
+            total_chunks: 1,
+            encryption_enabled: false,
+            sender_public_key: None,
+            hash_kind: FileHashKind::WholeFile,
+            chunk_size_bounds: None,
+            chunk_acks: false,
+            preview: None,
+            dedup: false,
+            chunk_hashes: Vec::new(),
+        };
+        let result = receiver.manager.handle_transfer_message("sender", TransferMessage::Request(request)).await;
+        assert!(matches!(result, Err(FileTransferError::VersionMismatch(_))));
+        assert!(receiver.manager.get_transfer_info("t1").is_none());
+
+        let (_, reject) = receiver.outgoing.try_recv().unwrap();
+        assert!(matches!(reject, TransferMessage::Response(TransferResponse::Reject { .. })));
+    }
+
+    /// Empfänger mit angenommenem, unverschlüsseltem Download von 200 KB
+    async fn receiving_peer(config: TransferConfig) -> (Peer, String, PathBuf) {
+        let mut receiver = peer(TransferConfig { encryption_enabled: false, ..config });
+        let transfer_id = Uuid::new_v4().to_string();
+        let dest = std::env::temp_dir()
+            .join(format!("smoldesk-transfer-{}", Uuid::new_v4()))
+            .join("download.bin");
+        
+        let request = TransferRequest {
+            protocol_version: PROTOCOL_VERSION,
+            transfer_id: transfer_id.clone(),
+            file_metadata: FileMetadata {
+                name: "download.bin".to_string(),
+                size: 200_000,
+                mime_type: "application/octet-stream".to_string(),
+                created: SystemTime::now(),
+                modified: SystemTime::now(),
+                permissions: 0o644,
+                attributes: HashMap::new(),
+            },
+            file_hash: "unused".to_string(),
+            chunk_size: 64 * KB,
+            total_chunks: 4,
+            encryption_enabled: false,
+            sender_public_key: None,
+            hash_kind: FileHashKind::ChunkList,
+            chunk_size_bounds: None,
+            chunk_acks: false,
+            preview: None,
+            dedup: false,
+            chunk_hashes: Vec::new(),
+        };
+        receiver.manager.handle_transfer_message("sender", TransferMessage::Request(request)).await.unwrap();
+        receiver.manager.accept_transfer(&transfer_id, &dest).await.unwrap();
+        while receiver.events.try_recv().is_ok() {}
+        
+        (receiver, transfer_id, dest)
+    }
+    
+    /// Lässt den Wartungs-Task nach dem Vorspulen der Uhr laufen
+    async fn advance(duration: Duration) {
+        tokio::time::advance(duration).await;
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+    }
+    
+    fn status(peer: &Peer, transfer_id: &str) -> Option<TransferStatus> {
+        peer.manager.get_transfer_info(transfer_id).map(|info| info.status)
+    }
+    
+    fn events(peer: &mut Peer) -> Vec<TransferEvent> {
+        std::iter::from_fn(|| peer.events.try_recv().ok()).collect()
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_stalled_download_is_cancelled_after_grace_period() {
+        let config = TransferConfig { transfer_timeout_secs: 60, stall_grace_secs: 30, ..Default::default() };
+        let (mut receiver, transfer_id, dest) = receiving_peer(config).await;
+        receiver.manager.start_maintenance();
+        
+        advance(Duration::from_secs(59)).await;
+        assert_eq!(status(&receiver, &transfer_id), Some(TransferStatus::Active));
+        
+        advance(Duration::from_secs(2)).await;
+        assert_eq!(status(&receiver, &transfer_id), Some(TransferStatus::Stalled));
+        assert!(matches!(
+            events(&mut receiver).as_slice(),
+            [TransferEvent::TransferStalled { idle_secs: 60, .. }]
+        ));
+        
+        advance(Duration::from_secs(30)).await;
+        assert_eq!(status(&receiver, &transfer_id), None);
+        assert!(matches!(events(&mut receiver).as_slice(), [TransferEvent::TransferCancelled { .. }]));
+        assert!(!dest.exists());
+        assert!(!ResumeSidecar::path_for(&dest).exists());
+        
+        let history = receiver.manager.get_transfer_history(&TransferHistoryQuery::default());
+        assert_eq!(history.entries[0].outcome, TransferOutcome::Cancelled);
+        assert_eq!(history.entries[0].reason.as_deref(), Some("No activity for 90 seconds"));
+        
+        std::fs::remove_dir_all(dest.parent().unwrap()).unwrap();
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_activity_recovers_a_stalled_transfer() {
+        let (mut receiver, transfer_id, dest) = receiving_peer(TransferConfig::default()).await;
+        receiver.manager.start_maintenance();
+        
+        advance(Duration::from_secs(61)).await;
+        assert_eq!(status(&receiver, &transfer_id), Some(TransferStatus::Stalled));
+        
+        // Der Peer meldet sich mit dem nächsten Chunk zurück
+        receiver.manager.handle_transfer_message("sender", TransferMessage::Chunk(ChunkData {
+            transfer_id: transfer_id.clone(),
+            chunk_index: 0,
+            data: pattern(64 * KB),
+            chunk_hash: None,
+            encrypted: false,
+        })).await.unwrap();
+        advance(Duration::from_secs(1)).await;
+        
+        assert_eq!(status(&receiver, &transfer_id), Some(TransferStatus::Active));
+        assert!(matches!(
+            events(&mut receiver).as_slice(),
+            [TransferEvent::TransferStalled { .. }, TransferEvent::TransferProgress { .. }, TransferEvent::TransferResumed { .. }]
+        ));
+        
+        std::fs::remove_dir_all(dest.parent().unwrap()).unwrap();
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_pause_suspends_the_timeout() {
+        let (mut receiver, transfer_id, dest) = receiving_peer(TransferConfig::default()).await;
+        receiver.manager.start_maintenance();
+        
+        advance(Duration::from_secs(30)).await;
+        receiver.manager.pause_transfer(&transfer_id).await.unwrap();
+        advance(Duration::from_secs(600)).await;
+        assert_eq!(status(&receiver, &transfer_id), Some(TransferStatus::Paused));
+        
+        // Fortsetzen startet die Uhr neu
+        receiver.manager.resume_transfer(&transfer_id).await.unwrap();
+        advance(Duration::from_secs(59)).await;
+        assert_eq!(status(&receiver, &transfer_id), Some(TransferStatus::Active));
+        assert!(!events(&mut receiver).iter().any(|event| matches!(event, TransferEvent::TransferStalled { .. })));
+        
+        std::fs::remove_dir_all(dest.parent().unwrap()).unwrap();
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_stalled_download_keeps_partial_file_with_resume_from_disk() {
+        let config = TransferConfig { resume_from_disk: true, ..Default::default() };
+        let (mut receiver, transfer_id, dest) = receiving_peer(config).await;
+        receiver.manager.handle_transfer_message("sender", TransferMessage::Chunk(ChunkData {
+            transfer_id: transfer_id.clone(),
+            chunk_index: 1,
+            data: pattern(64 * KB),
+            chunk_hash: None,
+            encrypted: false,
+        })).await.unwrap();
+        
+        // Ohne laufenden Task, mit einem Zeitpunkt nach Timeout und Karenzzeit
+        receiver.manager.check_stalled_transfers(activity_now() + Duration::from_secs(61)).await;
+        assert_eq!(status(&receiver, &transfer_id), Some(TransferStatus::Stalled));
+        receiver.manager.check_stalled_transfers(activity_now() + Duration::from_secs(121)).await;
+        assert_eq!(status(&receiver, &transfer_id), None);
+        
+        assert!(dest.exists());
+        let sidecar = ResumeSidecar::load(&dest).unwrap();
+        assert_eq!(sidecar.transfer_id, transfer_id);
+        assert_eq!(sidecar.chunk_hashes.keys().copied().collect::<Vec<_>>(), vec![1]);
+        assert_eq!(sidecar.chunk_hashes[&1], ChunkManager::calculate_chunk_hash(&pattern(64 * KB)));
+        
+        std::fs::remove_dir_all(dest.parent().unwrap()).unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_shutdown_keeps_partial_downloads_resumable() {
+        let (mut receiver, transfer_id, dest) = receiving_peer(TransferConfig::default()).await;
+        receiver.manager.handle_transfer_message("sender", TransferMessage::Chunk(ChunkData {
+            transfer_id: transfer_id.clone(),
+            chunk_index: 0,
+            data: pattern(64 * KB),
+            chunk_hash: None,
+            encrypted: false,
+        })).await.unwrap();
+        events(&mut receiver);
+        
+        assert_eq!(receiver.manager.shutdown().await, 1);
+        assert!(receiver.manager.get_active_transfers().is_empty());
+        assert!(matches!(events(&mut receiver).as_slice(), [TransferEvent::TransferCancelled { .. }]));
+        
+        // Auch ohne `resume_from_disk` bleibt der Empfangsstand erhalten
+        let sidecar = ResumeSidecar::load(&dest).unwrap();
+        assert_eq!(sidecar.transfer_id, transfer_id);
+        assert_eq!(sidecar.chunk_hashes.keys().copied().collect::<Vec<_>>(), vec![0]);
+        let history = receiver.manager.get_transfer_history(&TransferHistoryQuery::default());
+        assert_eq!(history.entries[0].outcome, TransferOutcome::Cancelled);
+        assert_eq!(history.entries[0].reason.as_deref(), Some("SmolDesk was closed"));
+        
+        std::fs::remove_dir_all(dest.parent().unwrap()).unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_request_carries_text_preview() {
+        let mut sender = peer(TransferConfig::default());
+        let mut receiver = peer(TransferConfig::default());
+        let dir = std::env::temp_dir().join(format!("smoldesk-transfer-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("notes.txt");
+        std::fs::write(&source, "Einkaufsliste").unwrap();
+        
+        let transfer_id = sender.manager.start_upload(&source, "receiver", None, TransferPriority::default()).await.unwrap();
+        let (_, request) = sender.outgoing.try_recv().unwrap();
+        receiver.manager.handle_transfer_message("sender", request).await.unwrap();
+        
+        match events(&mut receiver).as_slice() {
+            [TransferEvent::TransferRequested { preview: Some(preview), .. }] => {
+                assert_eq!(preview.mime_type, "text/plain");
+                assert_eq!(preview.data, b"Einkaufsliste");
+                assert!(!preview.truncated);
+            },
+            other => panic!("unexpected events: {:?}", other),
+        }
+        
+        // Eine vorhandene Vorschau wird nicht erneut angefordert
+        receiver.manager.request_transfer_preview(&transfer_id).await.unwrap();
+        assert!(receiver.outgoing.try_recv().is_err());
+        assert!(matches!(events(&mut receiver).as_slice(), [TransferEvent::TransferPreview { preview: Some(_), .. }]));
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_preview_requests_are_answered_and_rate_limited() {
+        let mut sender = peer(TransferConfig::default());
+        let dir = std::env::temp_dir().join(format!("smoldesk-transfer-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("notes.txt");
+        std::fs::write(&source, "Einkaufsliste").unwrap();
+        
+        let transfer_id = sender.manager.start_upload(&source, "receiver", None, TransferPriority::default()).await.unwrap();
+        while sender.outgoing.try_recv().is_ok() {}
+        let request = || TransferMessage::PreviewRequest(PreviewRequest { transfer_id: transfer_id.clone() });
+        
+        for _ in 0..preview::MAX_PREVIEW_REQUESTS {
+            sender.manager.handle_transfer_message("receiver", request()).await.unwrap();
+            match sender.outgoing.try_recv().unwrap() {
+                (peer_id, TransferMessage::Preview(PreviewResponse { preview: Some(preview), .. })) => {
+                    assert_eq!(peer_id, "receiver");
+                    assert_eq!(preview.data, b"Einkaufsliste");
+                },
+                other => panic!("unexpected message: {:?}", other),
+            }
+        }
+        
+        sender.manager.handle_transfer_message("receiver", request()).await.unwrap();
+        assert!(matches!(
+            sender.outgoing.try_recv().unwrap(),
+            (_, TransferMessage::Preview(PreviewResponse { preview: None, .. }))
+        ));
+        
+        // Nur der Empfänger der Übertragung bekommt eine Vorschau
+        let result = sender.manager.handle_transfer_message("someone-else", request()).await;
+        assert!(matches!(result, Err(FileTransferError::TransferNotFound(_))));
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_late_preview_reaches_the_ui() {
+        let (mut receiver, transfer_id, dest) = receiving_peer(TransferConfig::default()).await;
+        
+        // Angenommene Übertragungen brauchen keine Vorschau mehr
+        assert!(matches!(
+            receiver.manager.request_transfer_preview(&transfer_id).await,
+            Err(FileTransferError::InvalidOperation(_))
+        ));
+        
+        let oversized = FilePreview {
+            mime_type: "text/plain".to_string(),
+            data: vec![b'a'; preview::MAX_PREVIEW_SIZE + 1],
+            truncated: true,
+        };
+        receiver.manager.handle_transfer_message("sender", TransferMessage::Preview(PreviewResponse {
+            transfer_id: transfer_id.clone(),
+            preview: Some(oversized),
+        })).await.unwrap();
+        assert!(matches!(events(&mut receiver).as_slice(), [TransferEvent::TransferPreview { preview: None, .. }]));
+        
+        std::fs::remove_dir_all(dest.parent().unwrap()).unwrap();
+    }
+    
+    fn progress(peer: &Peer, transfer_id: &str) -> TransferProgress {
+        peer.manager.get_transfer_info(transfer_id).unwrap().progress
+    }
+    
+    #[tokio::test]
+    async fn test_resend_of_modified_file_sends_only_changed_chunks() {
+        let config = TransferConfig { chunk_cache_bytes: 0, ..chunk_config(16 * KB, 16 * KB, 16 * KB) };
+        let mut sender = peer(config.clone());
+        let mut receiver = peer(config);
+        let dir = std::env::temp_dir().join(format!("smoldesk-transfer-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        receiver.manager.set_staging_dir(&dir.join("data")).unwrap();
+        let source = dir.join("source.bin");
+        let dest = dir.join("dest.bin");
+        let mut data = pattern(20 * 16 * KB);
+        std::fs::write(&source, &data).unwrap();
+        
+        let first = run_transfer(&mut sender, &mut receiver, &source, &dest).await.unwrap();
+        assert_eq!(progress(&sender, &first).bytes_deduplicated, 0);
+        
+        // Ein Chunk von 20 geändert: nur er geht erneut über die Leitung
+        data[7 * 16 * KB] ^= 0xff;
+        std::fs::write(&source, &data).unwrap();
+        let second = run_transfer(&mut sender, &mut receiver, &source, &dest).await.unwrap();
+        
+        assert_eq!(std::fs::read(&dest).unwrap(), data);
+        for peer in [&sender, &receiver] {
+            let progress = progress(peer, &second);
+            assert_eq!((progress.chunks_completed, progress.bytes_transferred), (20, data.len() as u64));
+            assert_eq!(progress.bytes_deduplicated, 19 * 16 * KB as u64);
+        }
+        assert_eq!(sender.manager.get_stats().total_bytes_deduplicated, 19 * 16 * KB as u64);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_cached_chunks_complete_a_transfer_without_sending() {
+        let mut sender = peer(chunk_config(16 * KB, 16 * KB, 16 * KB));
+        let mut receiver = peer(chunk_config(16 * KB, 16 * KB, 16 * KB));
+        let data = pattern(100_000);
+        transfer(&mut sender, &mut receiver, &data).await.unwrap();
+        assert!(receiver.manager.get_storage_usage().chunk_cache_bytes >= data.len() as u64);
+        
+        // Dieselbe Datei an ein neues Ziel kommt komplett aus dem Cache
+        let (transfer_id, received) = transfer(&mut sender, &mut receiver, &data).await.unwrap();
+        assert_eq!(received, data);
+        assert_eq!(progress(&sender, &transfer_id).bytes_deduplicated, data.len() as u64);
+        let sent_chunks = std::iter::from_fn(|| sender.outgoing.try_recv().ok())
+            .filter(|(_, message)| matches!(message, TransferMessage::Chunk(_)))
+            .count();
+        assert_eq!(sent_chunks, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_large_manifest_is_sent_in_pages() {
+        let config = TransferConfig { encryption_enabled: false, ..chunk_config(16 * KB, 16 * KB, 16 * KB) };
+        let mut sender = peer(config.clone());
+        let mut receiver = peer(config);
+        let dir = std::env::temp_dir().join(format!("smoldesk-transfer-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("source.bin");
+        let dest = dir.join("dest.bin");
+        let data = pattern(600 * 16 * KB);
+        std::fs::write(&source, &data).unwrap();
+        std::fs::write(&dest, &data).unwrap();
+        
+        // Zu viele Chunks für die Anfrage: der Empfänger fordert die Hashes an
+        let transfer_id = sender.manager.start_upload(&source, "receiver", None, TransferPriority::default()).await.unwrap();
+        let (_, request) = sender.outgoing.try_recv().unwrap();
+        assert!(matches!(&request, TransferMessage::Request(request) if request.dedup && request.chunk_hashes.is_empty()));
+        receiver.manager.handle_transfer_message("sender", request).await.unwrap();
+        let (_, manifest_request) = receiver.outgoing.try_recv().unwrap();
+        assert!(matches!(manifest_request, TransferMessage::ManifestRequest(_)));
+        
+        sender.manager.handle_transfer_message("receiver", manifest_request).await.unwrap();
+        let pages: Vec<TransferMessage> = std::iter::from_fn(|| sender.outgoing.try_recv().ok()).map(|(_, page)| page).collect();
+        assert!(matches!(pages.as_slice(), [
+            TransferMessage::Manifest(ChunkManifest { first_index: 0, .. }),
+            TransferMessage::Manifest(ChunkManifest { first_index: MANIFEST_PAGE_CHUNKS, .. }),
+        ]));
+        for page in pages {
+            receiver.manager.handle_transfer_message("sender", page).await.unwrap();
+        }
+        
+        // Die Datei liegt schon am Ziel: nichts muss gesendet werden
+        receiver.manager.accept_transfer(&transfer_id, &dest).await.unwrap();
+        let (_, accept) = receiver.outgoing.try_recv().unwrap();
+        assert!(matches!(&accept, TransferMessage::Response(TransferResponse::Accept { existing_chunks, .. }) if existing_chunks.len() == 600));
+        assert_eq!(status(&receiver, &transfer_id), Some(TransferStatus::Completed));
+        
+        sender.manager.handle_transfer_message("receiver", accept).await.unwrap();
+        assert_eq!(status(&sender, &transfer_id), Some(TransferStatus::Completed));
+        assert_eq!(std::fs::read(&dest).unwrap(), data);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
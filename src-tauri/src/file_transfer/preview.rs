@@ -0,0 +1,205 @@
+// src-tauri/src/file_transfer/preview.rs - Vorschauen eingehender Dateien vor dem Annehmen
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+use crate::file_transfer::error::FileTransferError;
+
+/// Obergrenze einer Vorschau; größere werden weder gesendet noch angenommen
+pub const MAX_PREVIEW_SIZE: usize = 64 * 1024;
+
+/// Kantenlänge, auf die Bilder für die Vorschau höchstens verkleinert werden
+pub const THUMBNAIL_SIZE: u32 = 256;
+
+/// Größere Bilder werden nicht dekodiert, um den Speicher zu schonen
+const MAX_IMAGE_SOURCE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Vorschau-Anfragen je Peer innerhalb von `PREVIEW_RATE_WINDOW`
+pub const MAX_PREVIEW_REQUESTS: usize = 10;
+const PREVIEW_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Vorschau einer Datei: Textanfang oder Miniaturbild
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FilePreview {
+    /// MIME-Typ von `data`; bei Bildern immer `image/jpeg`
+    pub mime_type: String,
+    pub data: Vec<u8>,
+
+    /// Ob die Datei mehr enthält als der Textanfang
+    pub truncated: bool,
+}
+
+impl FilePreview {
+    /// Ob die Vorschau die Größengrenze einhält
+    pub fn within_limit(&self) -> bool {
+        self.data.len() <= MAX_PREVIEW_SIZE
+    }
+}
+
+/// Ob für den MIME-Typ eine Vorschau erzeugt wird
+pub fn is_previewable(mime_type: &str) -> bool {
+    mime_type.starts_with("text/") || mime_type == "image/png" || mime_type == "image/jpeg"
+}
+
+/// Erzeugt die Vorschau einer Datei; None, wenn der Typ keine hat oder sie zu groß wäre.
+/// Liest und dekodiert synchron, gehört also auf einen Blocking-Thread.
+pub fn generate_preview(path: &Path, mime_type: &str) -> Result<Option<FilePreview>, FileTransferError> {
+    let preview = if mime_type.starts_with("text/") {
+        Some(text_preview(path, mime_type)?)
+    } else if is_previewable(mime_type) {
+        image_preview(path)?
+    } else {
+        None
+    };
+
+    Ok(preview.filter(FilePreview::within_limit))
+}
+
+/// Die ersten `MAX_PREVIEW_SIZE` Bytes, ohne ein angeschnittenes UTF-8-Zeichen am Ende
+fn text_preview(path: &Path, mime_type: &str) -> Result<FilePreview, FileTransferError> {
+    let mut file = File::open(path)
+        .map_err(|e| FileTransferError::IoError(e.to_string()))?;
+    let mut data = Vec::with_capacity(MAX_PREVIEW_SIZE);
+    file.by_ref()
+        .take(MAX_PREVIEW_SIZE as u64 + 1)
+        .read_to_end(&mut data)
+        .map_err(|e| FileTransferError::IoError(e.to_string()))?;
+
+    let truncated = data.len() > MAX_PREVIEW_SIZE;
+    data.truncate(MAX_PREVIEW_SIZE);
+    if truncated {
+        if let Err(e) = std::str::from_utf8(&data) {
+            // Nur ein unvollständiges Zeichen am Ende kürzen; andere Kodierungen bleiben unverändert
+            if e.error_len().is_none() {
+                data.truncate(e.valid_up_to());
+            }
+        }
+    }
+
+    Ok(FilePreview {
+        mime_type: mime_type.to_string(),
+        data,
+        truncated,
+    })
+}
+
+/// Miniaturbild als JPEG
+fn image_preview(path: &Path) -> Result<Option<FilePreview>, FileTransferError> {
+    let size = path.metadata()
+        .map_err(|e| FileTransferError::IoError(e.to_string()))?
+        .len();
+    if size > MAX_IMAGE_SOURCE_SIZE {
+        return Ok(None);
+    }
+
+    let image = image::io::Reader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| FileTransferError::IoError(e.to_string()))?
+        .decode()
+        .map_err(|e| FileTransferError::InvalidFileType(format!("Cannot decode image: {}", e)))?;
+
+    // JPEG kennt keinen Alphakanal
+    let thumbnail = image::DynamicImage::ImageRgb8(image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgb8());
+    let mut data = Vec::new();
+    thumbnail.write_to(&mut Cursor::new(&mut data), image::ImageOutputFormat::Jpeg(80))
+        .map_err(|e| FileTransferError::IoError(e.to_string()))?;
+
+    Ok(Some(FilePreview {
+        mime_type: "image/jpeg".to_string(),
+        data,
+        truncated: false,
+    }))
+}
+
+/// Begrenzt nachträgliche Vorschau-Anfragen je Peer
+#[derive(Debug, Default)]
+pub struct PreviewRateLimiter {
+    requests: HashMap<String, VecDeque<Instant>>,
+}
+
+impl PreviewRateLimiter {
+    /// Verbucht eine Anfrage; false, wenn der Peer sein Kontingent ausgeschöpft hat
+    pub fn check(&mut self, peer_id: &str, now: Instant) -> bool {
+        let requests = self.requests.entry(peer_id.to_string()).or_default();
+        while requests.front().is_some_and(|at| now.saturating_duration_since(*at) >= PREVIEW_RATE_WINDOW) {
+            requests.pop_front();
+        }
+
+        if requests.len() >= MAX_PREVIEW_REQUESTS {
+            return false;
+        }
+        requests.push_back(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn temp_file(name: &str, data: &[u8]) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("smoldesk-preview-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_text_preview_is_cut_at_a_character_boundary() {
+        // "ä" belegt zwei Bytes und fällt genau auf die Grenze
+        let mut text = "a".repeat(MAX_PREVIEW_SIZE - 1).into_bytes();
+        text.extend("äbc".as_bytes());
+        let path = temp_file("notes.txt", &text);
+
+        let preview = generate_preview(&path, "text/plain").unwrap().unwrap();
+        assert_eq!(preview.data.len(), MAX_PREVIEW_SIZE - 1);
+        assert!(preview.truncated);
+        assert_eq!(preview.mime_type, "text/plain");
+
+        std::fs::write(&path, "short").unwrap();
+        let preview = generate_preview(&path, "text/plain").unwrap().unwrap();
+        assert_eq!(preview.data, b"short");
+        assert!(!preview.truncated);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_image_preview_is_a_bounded_thumbnail() {
+        let pixels = image::RgbaImage::from_fn(1600, 900, |x, y| image::Rgba([(x % 256) as u8, (y % 256) as u8, 128, 255]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(pixels).write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png).unwrap();
+        let path = temp_file("screenshot.png", &png);
+
+        let preview = generate_preview(&path, "image/png").unwrap().unwrap();
+        assert_eq!(preview.mime_type, "image/jpeg");
+        assert!(preview.within_limit());
+        let thumbnail = image::load_from_memory(&preview.data).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (THUMBNAIL_SIZE, 144));
+
+        // Andere Typen haben keine Vorschau
+        assert_eq!(generate_preview(&path, "application/zip").unwrap(), None);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_preview_requests_are_rate_limited_per_peer() {
+        let mut limiter = PreviewRateLimiter::default();
+        let start = Instant::now();
+
+        for _ in 0..MAX_PREVIEW_REQUESTS {
+            assert!(limiter.check("peer-a", start));
+        }
+        assert!(!limiter.check("peer-a", start + Duration::from_secs(59)));
+        assert!(limiter.check("peer-b", start));
+        assert!(limiter.check("peer-a", start + PREVIEW_RATE_WINDOW));
+    }
+}
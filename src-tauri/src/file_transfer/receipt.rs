@@ -0,0 +1,219 @@
+// src-tauri/src/file_transfer/receipt.rs - Signierte Quittungen abgeschlossener Übertragungen
+//
+// Für jede abgeschlossene Übertragung stellt SmolDesk eine Quittung aus: Datei,
+// Größe, SHA-256, Chunk-Anzahl, Beginn und Ende sowie der geprüfte Fingerprint
+// des Peers, signiert mit dem Ed25519-Schlüssel der Geräteidentität. Signiert
+// wird nicht das JSON, in dem die Quittung weitergegeben wird, sondern eine
+// kanonische Form mit fester Feldreihenfolge. Umformatierte oder umsortierte
+// Exporte bleiben so prüfbar, und zum Prüfen genügt der öffentliche Schlüssel.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::connection_security::identity::{self, DeviceIdentity};
+use crate::file_transfer::error::FileTransferError;
+use crate::file_transfer::history::TransferHistoryEntry;
+use crate::file_transfer::types::TransferType;
+
+/// Trennt Quittungssignaturen von allen anderen Verwendungen des Schlüssels
+const RECEIPT_CONTEXT: &str = "smoldesk-transfer-receipt-v1";
+
+pub const RECEIPT_VERSION: u32 = 1;
+
+/// Signiert eine Quittung mit der Geräteidentität und trägt den Fingerprint
+/// des Peers ein; None, solange es keine Identität gibt
+pub type ReceiptSigner = Box<dyn Fn(TransferReceipt) -> Option<TransferReceipt> + Send + Sync>;
+
+/// Nachweis, was übertragen wurde
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferReceipt {
+    pub version: u32,
+    pub transfer_id: String,
+    pub transfer_type: TransferType,
+    pub file_name: String,
+    pub size: u64,
+
+    /// SHA-256 der ganzen Datei (hex)
+    pub sha256: String,
+    pub chunk_count: usize,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub peer_id: String,
+
+    /// Fingerprint der geprüften Identität des Peers; None ohne Identitätsnachweis
+    pub peer_fingerprint: Option<String>,
+
+    /// Öffentlicher Schlüssel des ausstellenden Hosts (Ed25519, Base64)
+    pub host_public_key: String,
+
+    /// Signatur über die kanonische Form (Base64)
+    pub signature: String,
+}
+
+/// Kanonische Form: kompaktes JSON in dieser Feldreihenfolge, Zeitstempel als
+/// RFC 3339 in UTC. Der Kontext steht vorn, damit sich keine andere Signatur
+/// des Schlüssels als Quittung ausgeben lässt.
+#[derive(Serialize)]
+struct CanonicalReceipt<'a> {
+    context: &'static str,
+    version: u32,
+    transfer_id: &'a str,
+    transfer_type: &'a TransferType,
+    file_name: &'a str,
+    size: u64,
+    sha256: &'a str,
+    chunk_count: usize,
+    started_at: String,
+    finished_at: String,
+    peer_id: &'a str,
+    peer_fingerprint: Option<&'a str>,
+    host_public_key: &'a str,
+}
+
+impl TransferReceipt {
+    /// Noch unsignierte Quittung für einen abgeschlossenen Eintrag des Verlaufs
+    pub fn new(entry: &TransferHistoryEntry, sha256: String, chunk_count: usize) -> Self {
+        TransferReceipt {
+            version: RECEIPT_VERSION,
+            transfer_id: entry.transfer_id.clone(),
+            transfer_type: entry.transfer_type.clone(),
+            file_name: entry.file_name.clone(),
+            size: entry.size,
+            sha256,
+            chunk_count,
+            started_at: entry.started_at,
+            finished_at: entry.finished_at,
+            peer_id: entry.peer_id.clone(),
+            peer_fingerprint: None,
+            host_public_key: String::new(),
+            signature: String::new(),
+        }
+    }
+
+    /// Liest eine exportierte Quittung
+    pub fn from_json(json: &str) -> Result<Self, FileTransferError> {
+        serde_json::from_str(json)
+            .map_err(|e| FileTransferError::ReceiptInvalid(format!("Unreadable receipt: {}", e)))
+    }
+
+    pub fn sign(mut self, identity: &DeviceIdentity, peer_fingerprint: Option<String>) -> Self {
+        self.peer_fingerprint = peer_fingerprint;
+        self.host_public_key = identity.public_key();
+        self.signature = identity.sign(&self.canonical_bytes());
+        self
+    }
+
+    /// Prüft die Signatur, mit `public_key` auch, dass dieser Host sie
+    /// ausgestellt hat. Liefert den Fingerprint des Ausstellers.
+    pub fn verify(&self, public_key: Option<&str>) -> Result<String, FileTransferError> {
+        if self.version != RECEIPT_VERSION {
+            return Err(FileTransferError::ReceiptInvalid(format!("Unknown receipt version {}", self.version)));
+        }
+        if public_key.is_some_and(|key| key != self.host_public_key) {
+            return Err(FileTransferError::ReceiptInvalid("Receipt was issued with a different key".to_string()));
+        }
+        identity::verify_signature(&self.host_public_key, &self.canonical_bytes(), &self.signature)
+            .map_err(|e| FileTransferError::ReceiptInvalid(e.to_string()))
+    }
+
+    /// Alle Felder außer der Signatur in kanonischer Form
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let canonical = CanonicalReceipt {
+            context: RECEIPT_CONTEXT,
+            version: self.version,
+            transfer_id: &self.transfer_id,
+            transfer_type: &self.transfer_type,
+            file_name: &self.file_name,
+            size: self.size,
+            sha256: &self.sha256,
+            chunk_count: self.chunk_count,
+            started_at: self.started_at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            finished_at: self.finished_at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            peer_id: &self.peer_id,
+            peer_fingerprint: self.peer_fingerprint.as_deref(),
+            host_public_key: &self.host_public_key,
+        };
+        serde_json::to_vec(&canonical).expect("receipt fields always serialize")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::file_transfer::history::TransferOutcome;
+
+    fn receipt() -> TransferReceipt {
+        let finished_at = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 30).unwrap();
+        let entry = TransferHistoryEntry {
+            transfer_id: "t1".to_string(),
+            transfer_type: TransferType::Download,
+            peer_id: "peer-1".to_string(),
+            file_name: "bericht \"final\".pdf".to_string(),
+            size: 2048,
+            bytes_transferred: 2048,
+            file_hash: None,
+            local_path: None,
+            started_at: finished_at - chrono::Duration::seconds(30),
+            finished_at,
+            duration_ms: 30_000,
+            outcome: TransferOutcome::Completed,
+            reason: None,
+            receipt: None,
+        };
+        TransferReceipt::new(&entry, "ab".repeat(32), 2)
+    }
+
+    #[test]
+    fn test_canonical_form_is_independent_of_the_json_layout() {
+        let identity = DeviceIdentity::generate();
+        let signed = receipt().sign(&identity, Some("SHA256:peer".to_string()));
+
+        let canonical = String::from_utf8(signed.canonical_bytes()).unwrap();
+        assert_eq!(canonical, format!(
+            "{{\"context\":\"smoldesk-transfer-receipt-v1\",\"version\":1,\"transfer_id\":\"t1\",\"transfer_type\":\"Download\",\
+             \"file_name\":\"bericht \\\"final\\\".pdf\",\"size\":2048,\"sha256\":\"{}\",\"chunk_count\":2,\
+             \"started_at\":\"2024-05-01T12:00:00Z\",\"finished_at\":\"2024-05-01T12:00:30Z\",\"peer_id\":\"peer-1\",\
+             \"peer_fingerprint\":\"SHA256:peer\",\"host_public_key\":\"{}\"}}",
+            "ab".repeat(32), identity.public_key()
+        ));
+
+        // Reordered keys, other whitespace and another time zone offset still verify
+        let mut fields: Vec<(String, serde_json::Value)> = serde_json::to_value(&signed).unwrap()
+            .as_object().unwrap().clone().into_iter().collect();
+        fields.reverse();
+        let mut reordered = String::from("{\n");
+        for (index, (key, value)) in fields.iter().enumerate() {
+            let value = match key.as_str() {
+                "started_at" => serde_json::json!("2024-05-01T14:00:00+02:00"),
+                _ => value.clone(),
+            };
+            let separator = if index + 1 < fields.len() { "," } else { "" };
+            reordered.push_str(&format!("  \"{}\" : {}{}\n", key, value, separator));
+        }
+        reordered.push('}');
+
+        let parsed = TransferReceipt::from_json(&reordered).unwrap();
+        assert_eq!(parsed.canonical_bytes(), signed.canonical_bytes());
+        assert_eq!(parsed.verify(Some(&identity.public_key())).unwrap(), identity.fingerprint());
+    }
+
+    #[test]
+    fn test_modified_receipts_fail_verification() {
+        let identity = DeviceIdentity::generate();
+        let json = serde_json::to_string(&receipt().sign(&identity, None)).unwrap();
+        assert!(TransferReceipt::from_json(&json).unwrap().verify(None).is_ok());
+
+        let tampered = json.replace("\"size\":2048", "\"size\":4096");
+        assert_ne!(tampered, json);
+        let tampered = TransferReceipt::from_json(&tampered).unwrap();
+        assert!(matches!(tampered.verify(None), Err(FileTransferError::ReceiptInvalid(_))));
+
+        // A receipt re-signed by another host does not pass for this one
+        let forged = receipt().sign(&DeviceIdentity::generate(), None);
+        assert!(forged.verify(None).is_ok());
+        assert!(forged.verify(Some(&identity.public_key())).is_err());
+
+        assert!(TransferReceipt::from_json("{\"size\": 1}").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+// src-tauri/src/file_transfer/resume.rs - Empfangsstand abgebrochener Downloads neben der Teildatei
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::file_transfer::error::FileTransferError;
+use crate::file_transfer::types::{ChunkStatus, FileHashKind, TransferSession};
+
+/// Endung der Sidecar-Datei neben der Teildatei
+pub const SIDECAR_EXTENSION: &str = "smoldesk-resume";
+
+/// Was nötig ist, um einen Download mit den bereits geschriebenen Chunks fortzusetzen
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResumeSidecar {
+    pub transfer_id: String,
+    pub peer_id: String,
+    pub file_name: String,
+    pub file_size: u64,
+    pub file_hash: Option<String>,
+    pub hash_kind: FileHashKind,
+    pub chunk_size: usize,
+    pub hash_chunk_size: usize,
+
+    /// SHA-256 der geschriebenen Chunks, nach Index sortiert
+    pub chunk_hashes: BTreeMap<usize, String>,
+}
+
+impl ResumeSidecar {
+    /// Empfangsstand eines Downloads; nur vollständig geschriebene Chunks zählen
+    pub fn from_session(session: &TransferSession) -> Self {
+        let chunk_hashes = session.chunk_hashes.iter()
+            .filter(|(index, _)| session.chunks.get(index) == Some(&ChunkStatus::Completed))
+            .map(|(index, hash)| (*index, hash.clone()))
+            .collect();
+
+        ResumeSidecar {
+            transfer_id: session.id.clone(),
+            peer_id: session.peer_id.clone(),
+            file_name: session.file_metadata.name.clone(),
+            file_size: session.file_metadata.size,
+            file_hash: session.file_hash.clone(),
+            hash_kind: session.hash_kind,
+            chunk_size: session.chunk_size,
+            hash_chunk_size: session.hash_chunk_size,
+            chunk_hashes,
+        }
+    }
+
+    /// Pfad der Sidecar-Datei zu einer Teildatei
+    pub fn path_for(partial_file: &Path) -> PathBuf {
+        let mut name = partial_file.as_os_str().to_owned();
+        name.push(".");
+        name.push(SIDECAR_EXTENSION);
+        PathBuf::from(name)
+    }
+
+    /// Schreibt die Sidecar-Datei atomar neben die Teildatei
+    pub fn write(&self, partial_file: &Path) -> Result<PathBuf, FileTransferError> {
+        let path = Self::path_for(partial_file);
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| FileTransferError::IoError(e.to_string()))?;
+
+        let tmp = path.with_extension(format!("{}.tmp", SIDECAR_EXTENSION));
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, &path)?;
+        Ok(path)
+    }
+
+    /// Liest die Sidecar-Datei einer Teildatei
+    pub fn load(partial_file: &Path) -> Result<Self, FileTransferError> {
+        let json = fs::read(Self::path_for(partial_file))?;
+        serde_json::from_slice(&json).map_err(|e| FileTransferError::IoError(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_and_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("smoldesk-resume-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let partial = dir.join("video.mp4");
+        assert_eq!(ResumeSidecar::path_for(&partial), dir.join("video.mp4.smoldesk-resume"));
+
+        let sidecar = ResumeSidecar {
+            transfer_id: "t1".to_string(),
+            peer_id: "peer".to_string(),
+            file_name: "video.mp4".to_string(),
+            file_size: 200_000,
+            file_hash: Some("abc".to_string()),
+            hash_kind: FileHashKind::ChunkList,
+            chunk_size: 65536,
+            hash_chunk_size: 65536,
+            chunk_hashes: [(0, "h0".to_string()), (2, "h2".to_string())].into_iter().collect(),
+        };
+        sidecar.write(&partial).unwrap();
+        assert_eq!(ResumeSidecar::load(&partial).unwrap(), sidecar);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
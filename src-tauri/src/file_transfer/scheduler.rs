@@ -0,0 +1,163 @@
+// src-tauri/src/file_transfer/scheduler.rs - Warteschlange für Übertragungen
+
+use serde::{Deserialize, Serialize};
+
+use crate::file_transfer::error::FileTransferError;
+
+/// Priorität einer Übertragung in der Warteschlange
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum TransferPriority {
+    Low,
+    Normal,
+    High,
+}
+
+impl Default for TransferPriority {
+    fn default() -> Self {
+        TransferPriority::Normal
+    }
+}
+
+/// Ein wartender Eintrag
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedTransfer {
+    pub transfer_id: String,
+    pub priority: TransferPriority,
+}
+
+/// Warteschlange nach Priorität, innerhalb einer Priorität in Ankunftsreihenfolge.
+///
+/// Manuelles Umsortieren per `move_to` hat Vorrang vor der Priorität.
+#[derive(Debug, Default)]
+pub struct TransferQueue {
+    entries: Vec<QueuedTransfer>,
+}
+
+impl TransferQueue {
+    /// Erstellt eine leere Warteschlange
+    pub fn new() -> Self {
+        TransferQueue { entries: Vec::new() }
+    }
+
+    /// Reiht hinter allen Einträgen mit gleicher oder höherer Priorität ein
+    pub fn enqueue(&mut self, transfer_id: &str, priority: TransferPriority) {
+        let position = self.entries.iter()
+            .position(|entry| entry.priority < priority)
+            .unwrap_or(self.entries.len());
+        self.insert(position, transfer_id, priority);
+    }
+
+    /// Reiht vor allen Einträgen gleicher Priorität ein (z.B. fortgesetzte Übertragungen)
+    pub fn enqueue_front(&mut self, transfer_id: &str, priority: TransferPriority) {
+        let position = self.entries.iter()
+            .position(|entry| entry.priority <= priority)
+            .unwrap_or(self.entries.len());
+        self.insert(position, transfer_id, priority);
+    }
+
+    /// Entnimmt den nächsten Eintrag
+    pub fn pop_next(&mut self) -> Option<String> {
+        if self.entries.is_empty() {
+            None
+        } else {
+            Some(self.entries.remove(0).transfer_id)
+        }
+    }
+
+    /// Entfernt einen Eintrag; gibt zurück, ob er vorhanden war
+    pub fn remove(&mut self, transfer_id: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.transfer_id != transfer_id);
+        self.entries.len() != before
+    }
+
+    /// Verschiebt einen Eintrag an eine neue Position (wird auf das Ende begrenzt)
+    pub fn move_to(&mut self, transfer_id: &str, position: usize) -> Result<(), FileTransferError> {
+        let current = self.entries.iter()
+            .position(|entry| entry.transfer_id == transfer_id)
+            .ok_or_else(|| FileTransferError::TransferNotFound(transfer_id.to_string()))?;
+
+        let entry = self.entries.remove(current);
+        let position = position.min(self.entries.len());
+        self.entries.insert(position, entry);
+        Ok(())
+    }
+
+    /// Ob ein Eintrag wartet
+    pub fn contains(&self, transfer_id: &str) -> bool {
+        self.entries.iter().any(|entry| entry.transfer_id == transfer_id)
+    }
+
+    /// Anzahl wartender Einträge
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Ob die Warteschlange leer ist
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Aktuelle Reihenfolge
+    pub fn entries(&self) -> Vec<QueuedTransfer> {
+        self.entries.clone()
+    }
+
+    fn insert(&mut self, position: usize, transfer_id: &str, priority: TransferPriority) {
+        self.remove(transfer_id);
+        let position = position.min(self.entries.len());
+        self.entries.insert(position, QueuedTransfer {
+            transfer_id: transfer_id.to_string(),
+            priority,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(queue: &TransferQueue) -> Vec<String> {
+        queue.entries().into_iter().map(|entry| entry.transfer_id).collect()
+    }
+
+    #[test]
+    fn test_priority_order_is_stable() {
+        let mut queue = TransferQueue::new();
+        queue.enqueue("a", TransferPriority::Normal);
+        queue.enqueue("b", TransferPriority::Low);
+        queue.enqueue("c", TransferPriority::High);
+        queue.enqueue("d", TransferPriority::Normal);
+
+        assert_eq!(ids(&queue), vec!["c", "a", "d", "b"]);
+        assert_eq!(queue.pop_next().as_deref(), Some("c"));
+    }
+
+    #[test]
+    fn test_enqueue_front_within_priority() {
+        let mut queue = TransferQueue::new();
+        queue.enqueue("a", TransferPriority::High);
+        queue.enqueue("b", TransferPriority::Normal);
+        queue.enqueue_front("resumed", TransferPriority::Normal);
+
+        assert_eq!(ids(&queue), vec!["a", "resumed", "b"]);
+    }
+
+    #[test]
+    fn test_move_and_remove() {
+        let mut queue = TransferQueue::new();
+        queue.enqueue("a", TransferPriority::Normal);
+        queue.enqueue("b", TransferPriority::Normal);
+        queue.enqueue("c", TransferPriority::Normal);
+
+        queue.move_to("c", 0).unwrap();
+        assert_eq!(ids(&queue), vec!["c", "a", "b"]);
+
+        queue.move_to("c", 99).unwrap();
+        assert_eq!(ids(&queue), vec!["a", "b", "c"]);
+
+        assert!(queue.remove("b"));
+        assert!(!queue.remove("b"));
+        assert!(matches!(queue.move_to("b", 0), Err(FileTransferError::TransferNotFound(_))));
+    }
+}
@@ -0,0 +1,261 @@
+// src-tauri/src/file_transfer/security.rs - Ende-zu-Ende-Verschlüsselung von Chunks
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use base64::{Engine as _, engine::general_purpose};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey};
+
+use crate::file_transfer::error::FileTransferError;
+
+const NONCE_LEN: usize = 12;
+const KEY_INFO: &[u8] = b"smoldesk file transfer v1";
+
+/// Symmetrischer Schlüssel einer einzelnen Übertragung.
+///
+/// Transfer-ID und Chunk-Index gehen als AAD in jede Verschlüsselung ein,
+/// damit Chunks weder zwischen Übertragungen noch innerhalb einer
+/// Übertragung vertauscht oder wiederholt werden können.
+#[derive(Clone)]
+pub struct TransferCipher {
+    transfer_id: String,
+    cipher: ChaCha20Poly1305,
+}
+
+impl TransferCipher {
+    /// Leitet den Schlüssel per HKDF-SHA256 aus dem ECDH-Geheimnis ab
+    fn derive(transfer_id: &str, shared_secret: &[u8]) -> Self {
+        let hkdf = Hkdf::<Sha256>::new(Some(transfer_id.as_bytes()), shared_secret);
+        let mut key = [0u8; 32];
+        hkdf.expand(KEY_INFO, &mut key)
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+
+        TransferCipher {
+            transfer_id: transfer_id.to_string(),
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+        }
+    }
+
+    /// Verschlüsselt einen Chunk; Ergebnis ist Nonce || Ciphertext
+    pub fn encrypt_chunk(&self, chunk_index: usize, plaintext: &[u8]) -> Result<Vec<u8>, FileTransferError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+
+        let aad = self.aad(chunk_index);
+        let ciphertext = self.cipher
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad: &aad })
+            .map_err(|_| FileTransferError::ChunkError(format!("Failed to encrypt chunk {}", chunk_index)))?;
+
+        let mut payload = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        payload.extend_from_slice(&nonce);
+        payload.extend_from_slice(&ciphertext);
+        Ok(payload)
+    }
+
+    /// Entschlüsselt und authentifiziert einen Chunk
+    pub fn decrypt_chunk(&self, chunk_index: usize, payload: &[u8]) -> Result<Vec<u8>, FileTransferError> {
+        let failed = || FileTransferError::DecryptionFailed {
+            transfer_id: self.transfer_id.clone(),
+            chunk_index,
+        };
+
+        if payload.len() < NONCE_LEN {
+            return Err(failed());
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let aad = self.aad(chunk_index);
+
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: &aad })
+            .map_err(|_| failed())
+    }
+
+    fn aad(&self, chunk_index: usize) -> Vec<u8> {
+        let mut aad = Vec::with_capacity(self.transfer_id.len() + 8);
+        aad.extend_from_slice(self.transfer_id.as_bytes());
+        aad.extend_from_slice(&(chunk_index as u64).to_be_bytes());
+        aad
+    }
+}
+
+/// Verwaltet den Schlüsselaustausch (X25519) und die Schlüssel pro Übertragung.
+///
+/// Die öffentlichen Schlüssel laufen über den bereits authentifizierten
+/// Signalisierungskanal; dieser Typ selbst authentifiziert die Peers nicht.
+pub struct FileTransferSecurity {
+    /// Ob Verschlüsselung aktiviert ist
+    encryption_enabled: bool,
+
+    /// Eigene ephemere Schlüssel, die noch auf den Peer-Schlüssel warten
+    pending_exchanges: Mutex<HashMap<String, EphemeralSecret>>,
+
+    /// Abgeleitete Schlüssel pro Übertragung
+    ciphers: Mutex<HashMap<String, TransferCipher>>,
+}
+
+impl FileTransferSecurity {
+    /// Erstellt einen neuen Sicherheitsmanager
+    pub fn new(encryption_enabled: bool) -> Result<Self, FileTransferError> {
+        Ok(FileTransferSecurity {
+            encryption_enabled,
+            pending_exchanges: Mutex::new(HashMap::new()),
+            ciphers: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Ob Verschlüsselung aktiviert ist
+    pub fn is_encryption_enabled(&self) -> bool {
+        self.encryption_enabled
+    }
+
+    /// Sender: erzeugt ein ephemeres Schlüsselpaar und liefert den öffentlichen Schlüssel
+    pub fn begin_key_exchange(&self, transfer_id: &str) -> String {
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&secret);
+
+        self.pending_exchanges.lock().unwrap().insert(transfer_id.to_string(), secret);
+        general_purpose::STANDARD.encode(public.as_bytes())
+    }
+
+    /// Empfänger: beantwortet den Schlüssel des Senders und leitet den Transfer-Schlüssel ab
+    pub fn respond_key_exchange(&self, transfer_id: &str, peer_public_key: &str) -> Result<String, FileTransferError> {
+        let peer_public = decode_public_key(peer_public_key)?;
+
+        let secret = EphemeralSecret::random_from_rng(OsRng);
+        let public = PublicKey::from(&secret);
+        let shared = secret.diffie_hellman(&peer_public);
+
+        self.ciphers.lock().unwrap().insert(
+            transfer_id.to_string(),
+            TransferCipher::derive(transfer_id, shared.as_bytes()),
+        );
+
+        Ok(general_purpose::STANDARD.encode(public.as_bytes()))
+    }
+
+    /// Sender: schließt den Austausch mit dem Schlüssel des Empfängers ab
+    pub fn complete_key_exchange(&self, transfer_id: &str, peer_public_key: &str) -> Result<(), FileTransferError> {
+        let peer_public = decode_public_key(peer_public_key)?;
+
+        let secret = self.pending_exchanges.lock().unwrap().remove(transfer_id)
+            .ok_or_else(|| FileTransferError::KeyExchangeFailed(
+                format!("No pending key exchange for transfer {}", transfer_id)
+            ))?;
+        let shared = secret.diffie_hellman(&peer_public);
+
+        self.ciphers.lock().unwrap().insert(
+            transfer_id.to_string(),
+            TransferCipher::derive(transfer_id, shared.as_bytes()),
+        );
+
+        Ok(())
+    }
+
+    /// Holt den Schlüssel einer Übertragung
+    pub fn cipher(&self, transfer_id: &str) -> Result<TransferCipher, FileTransferError> {
+        self.ciphers.lock().unwrap().get(transfer_id).cloned()
+            .ok_or_else(|| FileTransferError::KeyExchangeFailed(
+                format!("No key established for transfer {}", transfer_id)
+            ))
+    }
+
+    /// Vergisst alle Schlüssel einer Übertragung
+    pub fn remove_transfer(&self, transfer_id: &str) {
+        self.pending_exchanges.lock().unwrap().remove(transfer_id);
+        self.ciphers.lock().unwrap().remove(transfer_id);
+    }
+}
+
+/// Dekodiert einen Base64-kodierten X25519-Schlüssel
+fn decode_public_key(encoded: &str) -> Result<PublicKey, FileTransferError> {
+    let bytes = general_purpose::STANDARD.decode(encoded)
+        .map_err(|e| FileTransferError::KeyExchangeFailed(format!("Invalid public key encoding: {}", e)))?;
+
+    let bytes: [u8; 32] = bytes.try_into()
+        .map_err(|_| FileTransferError::KeyExchangeFailed("Public key must be 32 bytes".to_string()))?;
+
+    Ok(PublicKey::from(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Führt einen vollständigen Austausch zwischen Sender und Empfänger durch
+    fn exchange(transfer_id: &str) -> (FileTransferSecurity, FileTransferSecurity) {
+        let sender = FileTransferSecurity::new(true).unwrap();
+        let receiver = FileTransferSecurity::new(true).unwrap();
+
+        let sender_public = sender.begin_key_exchange(transfer_id);
+        let receiver_public = receiver.respond_key_exchange(transfer_id, &sender_public).unwrap();
+        sender.complete_key_exchange(transfer_id, &receiver_public).unwrap();
+
+        (sender, receiver)
+    }
+
+    #[test]
+    fn test_chunk_roundtrip() {
+        let (sender, receiver) = exchange("transfer-1");
+
+        let payload = sender.cipher("transfer-1").unwrap().encrypt_chunk(3, b"chunk data").unwrap();
+        let plaintext = receiver.cipher("transfer-1").unwrap().decrypt_chunk(3, &payload).unwrap();
+
+        assert_eq!(plaintext, b"chunk data");
+    }
+
+    #[test]
+    fn test_wrong_key_fails() {
+        let (sender, _) = exchange("transfer-1");
+        let (_, other_receiver) = exchange("transfer-1");
+
+        let payload = sender.cipher("transfer-1").unwrap().encrypt_chunk(0, b"secret").unwrap();
+
+        assert!(matches!(
+            other_receiver.cipher("transfer-1").unwrap().decrypt_chunk(0, &payload),
+            Err(FileTransferError::DecryptionFailed { chunk_index: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_tampered_chunk_fails() {
+        let (sender, receiver) = exchange("transfer-1");
+
+        let mut payload = sender.cipher("transfer-1").unwrap().encrypt_chunk(0, b"secret").unwrap();
+        let last = payload.len() - 1;
+        payload[last] ^= 0x01;
+
+        assert!(matches!(
+            receiver.cipher("transfer-1").unwrap().decrypt_chunk(0, &payload),
+            Err(FileTransferError::DecryptionFailed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_reordered_chunk_fails() {
+        let (sender, receiver) = exchange("transfer-1");
+
+        let payload = sender.cipher("transfer-1").unwrap().encrypt_chunk(1, b"second").unwrap();
+
+        assert!(matches!(
+            receiver.cipher("transfer-1").unwrap().decrypt_chunk(2, &payload),
+            Err(FileTransferError::DecryptionFailed { chunk_index: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_missing_key_exchange() {
+        let security = FileTransferSecurity::new(true).unwrap();
+
+        assert!(matches!(
+            security.cipher("unknown"),
+            Err(FileTransferError::KeyExchangeFailed(_))
+        ));
+    }
+}
@@ -0,0 +1,293 @@
+// src-tauri/src/file_transfer/staging.rs - Quarantäne für empfangene Dateien
+//
+// Downloads werden zuerst in ein eigenes Unterverzeichnis je Übertragung unter
+// dem App-Datenverzeichnis geschrieben und erst nach der Hash-Prüfung an ihr
+// Ziel verschoben. Am Ziel liegt so nie eine halbe oder ungeprüfte Datei.
+// Verzeichnisse abgebrochener Downloads bleiben bis zum Ablauf der
+// Aufbewahrungsdauer liegen (mit `resume_from_disk` samt Empfangsstand) und
+// werden dann von der Wartung entfernt.
+
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use serde::Serialize;
+
+use super::error::FileTransferError;
+
+/// Unterverzeichnis des App-Datenverzeichnisses
+pub const STAGING_DIR: &str = "transfer-staging";
+
+/// Endung der Kopie im Zielverzeichnis, bis sie umbenannt ist
+const PARTIAL_COPY_EXTENSION: &str = "smoldesk-part";
+
+/// Belegter Platz im Staging-Verzeichnis
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageUsage {
+    /// None, solange kein Datenverzeichnis bekannt ist
+    pub staging_dir: Option<PathBuf>,
+    pub staging_bytes: u64,
+    /// Verzeichnisse laufender und abgebrochener Downloads
+    pub staged_transfers: usize,
+    /// Davon gehören zu laufenden Downloads
+    pub active_transfers: usize,
+    /// Chunk-Cache für die Deduplizierung, im Speicher oder im Datenverzeichnis
+    pub chunk_cache_bytes: u64,
+}
+
+/// Ergebnis einer Aufräumrunde
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgeReport {
+    pub removed_transfers: usize,
+    pub freed_bytes: u64,
+}
+
+/// Ein Staging-Verzeichnis einer Übertragung
+struct StagedTransfer {
+    transfer_id: String,
+    path: PathBuf,
+    bytes: u64,
+    /// Letzte Änderung des Verzeichnisses oder einer Datei darin
+    modified: SystemTime,
+}
+
+/// Das Staging-Verzeichnis mit einem Unterverzeichnis je Download
+pub struct StagingArea {
+    root: PathBuf,
+}
+
+impl StagingArea {
+    /// Legt das Staging-Verzeichnis unter `data_dir` an; nur der eigene Benutzer hat Zugriff
+    pub fn open(data_dir: &Path) -> Result<Self, FileTransferError> {
+        let root = data_dir.join(STAGING_DIR);
+        fs::create_dir_all(&root)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&root, fs::Permissions::from_mode(0o700))?;
+        }
+        Ok(StagingArea { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Pfad, unter den der Download `transfer_id` bis zur Prüfung geschrieben wird
+    pub fn prepare(&self, transfer_id: &str, file_name: &OsStr) -> Result<PathBuf, FileTransferError> {
+        let dir = self.transfer_dir(transfer_id)?;
+        fs::create_dir_all(&dir)?;
+        Ok(dir.join(file_name))
+    }
+
+    /// Entfernt das Verzeichnis einer Übertragung samt Inhalt
+    pub fn remove(&self, transfer_id: &str) {
+        if let Ok(dir) = self.transfer_dir(transfer_id) {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+
+    /// Belegter Platz; `active` sind die IDs laufender Übertragungen
+    pub fn usage(&self, active: &HashSet<String>) -> StorageUsage {
+        let staged = self.staged_transfers();
+        StorageUsage {
+            staging_dir: Some(self.root.clone()),
+            staging_bytes: staged.iter().map(|transfer| transfer.bytes).sum(),
+            staged_transfers: staged.len(),
+            active_transfers: staged.iter().filter(|transfer| active.contains(&transfer.transfer_id)).count(),
+            chunk_cache_bytes: 0,
+        }
+    }
+
+    /// Entfernt verwaiste Verzeichnisse, die seit `retention` nicht geändert wurden
+    pub fn cleanup(&self, active: &HashSet<String>, retention: Duration, now: SystemTime) -> PurgeReport {
+        self.remove_orphans(active, |transfer| {
+            now.duration_since(transfer.modified).is_ok_and(|age| age >= retention)
+        })
+    }
+
+    /// Entfernt alle Verzeichnisse ohne laufende Übertragung
+    pub fn purge(&self, active: &HashSet<String>) -> PurgeReport {
+        self.remove_orphans(active, |_| true)
+    }
+
+    fn remove_orphans(&self, active: &HashSet<String>, expired: impl Fn(&StagedTransfer) -> bool) -> PurgeReport {
+        let mut report = PurgeReport::default();
+        for transfer in self.staged_transfers() {
+            if active.contains(&transfer.transfer_id) || !expired(&transfer) {
+                continue;
+            }
+            match fs::remove_dir_all(&transfer.path) {
+                Ok(()) => {
+                    report.removed_transfers += 1;
+                    report.freed_bytes += transfer.bytes;
+                },
+                Err(e) => log::warn!(target: crate::logging::TARGET_TRANSFER,
+                    "Failed to remove staging directory {}: {}", transfer.path.display(), e),
+            }
+        }
+        report
+    }
+
+    fn staged_transfers(&self) -> Vec<StagedTransfer> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+        entries.flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+            .map(|entry| {
+                let path = entry.path();
+                let mut bytes = 0;
+                let mut modified = entry.metadata().and_then(|metadata| metadata.modified()).unwrap_or(SystemTime::UNIX_EPOCH);
+                for file in fs::read_dir(&path).into_iter().flatten().flatten() {
+                    if let Ok(metadata) = file.metadata() {
+                        bytes += metadata.len();
+                        modified = modified.max(metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH));
+                    }
+                }
+                StagedTransfer {
+                    transfer_id: entry.file_name().to_string_lossy().into_owned(),
+                    path,
+                    bytes,
+                    modified,
+                }
+            })
+            .collect()
+    }
+
+    /// Die ID kommt vom Peer und darf nicht aus dem Staging-Verzeichnis herausführen
+    fn transfer_dir(&self, transfer_id: &str) -> Result<PathBuf, FileTransferError> {
+        let valid = !transfer_id.is_empty()
+            && transfer_id.len() <= 128
+            && transfer_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(FileTransferError::InvalidOperation(format!("Invalid transfer ID: {:?}", transfer_id)));
+        }
+        Ok(self.root.join(transfer_id))
+    }
+}
+
+/// Verschiebt `from` nach `to`, sodass `to` entweder fehlt oder vollständig ist.
+/// Über Dateisystemgrenzen wird neben `to` kopiert, die Kopie synchronisiert
+/// und dann umbenannt.
+pub fn move_into_place(from: &Path, to: &Path) -> Result<(), FileTransferError> {
+    move_with(from, to, |from, to| fs::rename(from, to))
+}
+
+/// `move_into_place` mit austauschbarem ersten `rename`, damit sich der Weg
+/// über Dateisystemgrenzen ohne Einhängen prüfen lässt
+fn move_with(from: &Path, to: &Path, rename: impl Fn(&Path, &Path) -> io::Result<()>) -> Result<(), FileTransferError> {
+    match rename(from, to) {
+        Ok(()) => return Ok(sync_parent(to)?),
+        Err(e) if is_cross_device(&e) => {},
+        Err(e) => return Err(e.into()),
+    }
+
+    let partial = partial_copy_path(to);
+    let copied = copy_synced(from, &partial)
+        .and_then(|()| fs::rename(&partial, to))
+        .and_then(|()| sync_parent(to));
+    if let Err(e) = copied {
+        let _ = fs::remove_file(&partial);
+        return Err(e.into());
+    }
+    fs::remove_file(from)?;
+    Ok(())
+}
+
+fn is_cross_device(error: &io::Error) -> bool {
+    error.raw_os_error() == Some(nix::errno::Errno::EXDEV as i32)
+}
+
+fn partial_copy_path(to: &Path) -> PathBuf {
+    let mut name = to.as_os_str().to_owned();
+    name.push(".");
+    name.push(PARTIAL_COPY_EXTENSION);
+    PathBuf::from(name)
+}
+
+fn copy_synced(from: &Path, to: &Path) -> io::Result<()> {
+    fs::copy(from, to)?;
+    OpenOptions::new().write(true).open(to)?.sync_all()
+}
+
+/// Macht die Umbenennung selbst dauerhaft
+fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => File::open(parent)?.sync_all(),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("smoldesk-staging-{}-{}", name, uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_cleanup_removes_only_expired_orphans() {
+        let dir = test_dir("cleanup");
+        let staging = StagingArea::open(&dir).unwrap();
+        for id in ["running", "orphan"] {
+            fs::write(staging.prepare(id, OsStr::new("file.bin")).unwrap(), vec![0; 1000]).unwrap();
+        }
+        assert!(staging.prepare("../escape", OsStr::new("file.bin")).is_err());
+
+        let active: HashSet<String> = ["running".to_string()].into_iter().collect();
+        let usage = staging.usage(&active);
+        assert_eq!((usage.staging_bytes, usage.staged_transfers, usage.active_transfers), (2000, 2, 1));
+
+        let retention = Duration::from_secs(24 * 3600);
+        assert_eq!(staging.cleanup(&active, retention, SystemTime::now()), PurgeReport::default());
+        let later = SystemTime::now() + retention;
+        assert_eq!(staging.cleanup(&active, retention, later), PurgeReport { removed_transfers: 1, freed_bytes: 1000 });
+        assert!(staging.root().join("running").exists());
+        assert!(!staging.root().join("orphan").exists());
+
+        assert_eq!(staging.purge(&HashSet::new()).removed_transfers, 1);
+        assert_eq!(staging.usage(&HashSet::new()).staged_transfers, 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_move_across_filesystems() {
+        let dir = test_dir("cross-device");
+        let cross_device = |_: &Path, _: &Path| Err(io::Error::from_raw_os_error(nix::errno::Errno::EXDEV as i32));
+
+        // rename scheitert mit EXDEV: kopieren, synchronisieren, umbenennen
+        let staged = dir.join("staged.bin");
+        fs::write(&staged, b"moved").unwrap();
+        let destination = dir.join("received.bin");
+        move_with(&staged, &destination, cross_device).unwrap();
+        assert_eq!(fs::read(&destination).unwrap(), b"moved");
+        assert!(!staged.exists());
+        assert!(!partial_copy_path(&destination).exists());
+
+        // Scheitert die Kopie, bleiben Quelle und Ziel unberührt
+        let staged = dir.join("kept.bin");
+        fs::write(&staged, b"kept").unwrap();
+        let unreachable = dir.join("missing").join("kept.bin");
+        assert!(move_with(&staged, &unreachable, cross_device).is_err());
+        assert!(staged.exists());
+        assert!(!unreachable.exists());
+        assert!(!partial_copy_path(&unreachable).exists());
+
+        // Andere Fehler werden nicht durch eine Kopie verdeckt
+        let denied = |_: &Path, _: &Path| Err(io::Error::from(io::ErrorKind::PermissionDenied));
+        assert!(move_with(&staged, &destination, denied).is_err());
+        assert!(staged.exists());
+        assert_eq!(fs::read(&destination).unwrap(), b"moved");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
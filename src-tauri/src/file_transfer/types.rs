@@ -0,0 +1,827 @@
+// src-tauri/src/file_transfer/types.rs - Typen für das Dateiübertragungssystem
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::file_transfer::bandwidth::AutoBandwidthConfig;
+use crate::file_transfer::chunk_sizing::ChunkSizeBounds;
+use crate::file_transfer::preview::FilePreview;
+use crate::file_transfer::scheduler::TransferPriority;
+use crate::protocol::{self, ProtocolError, WireSubsystem};
+
+/// Konfiguration für Dateiübertragungen
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferConfig {
+    /// Größe eines Chunks in Bytes; Vorschlag an den Empfänger beim Senden
+    pub chunk_size: usize,
+
+    /// Kleinste Chunk-Größe, die diese Seite aushandelt
+    #[serde(default = "default_min_chunk_size")]
+    pub min_chunk_size: usize,
+
+    /// Größte Chunk-Größe, die diese Seite aushandelt; begrenzt auch adaptive Batches
+    #[serde(default = "default_max_chunk_size")]
+    pub max_chunk_size: usize,
+
+    /// Zahl unbestätigter Chunks anhand der Bestätigungslatenz anpassen
+    #[serde(default)]
+    pub adaptive_chunk_batching: bool,
+
+    /// Maximale Dateigröße in Bytes
+    pub max_file_size: u64,
+
+    /// Chunks Ende-zu-Ende verschlüsseln
+    pub encryption_enabled: bool,
+
+    /// Maximale Anzahl von Wiederholungen pro Chunk
+    pub max_retries: u32,
+
+    /// Globale Bandbreitengrenze in Bytes/Sekunde (None = unbegrenzt)
+    pub max_bandwidth_bytes_per_sec: Option<u64>,
+
+    /// Automatische Grenze anhand der Video-Bitrate (hat Vorrang vor der festen Grenze)
+    pub auto_bandwidth: Option<AutoBandwidthConfig>,
+
+    /// Maximale Anzahl gleichzeitig laufender Uploads; weitere warten in der Queue
+    pub max_concurrent_transfers: usize,
+
+    /// Maske für übertragene Berechtigungen (Standard entfernt setuid/setgid/sticky)
+    pub permission_mask: u32,
+
+    /// Prüfung eines Downloads nach dem letzten Chunk
+    #[serde(default)]
+    pub verification_mode: VerificationMode,
+
+    /// Aufbewahrungsdauer des Übertragungsverlaufs in Tagen (None = unbegrenzt)
+    #[serde(default = "default_history_retention_days")]
+    pub history_retention_days: Option<u32>,
+
+    /// Sekunden ohne Aktivität, nach denen eine laufende Übertragung als hängend gilt
+    #[serde(default = "default_transfer_timeout_secs")]
+    pub transfer_timeout_secs: u64,
+
+    /// Weitere Sekunden, nach denen eine hängende Übertragung abgebrochen wird
+    #[serde(default = "default_stall_grace_secs")]
+    pub stall_grace_secs: u64,
+
+    /// Teildateien abgebrochener hängender Downloads behalten und den
+    /// Empfangsstand daneben speichern, statt sie zu löschen
+    #[serde(default)]
+    pub resume_from_disk: bool,
+
+    /// Stunden, nach denen Staging-Verzeichnisse abgebrochener Downloads gelöscht werden
+    #[serde(default = "default_staging_retention_hours")]
+    pub staging_retention_hours: u64,
+
+    /// Chunks, die beim Empfänger schon vorliegen, nicht erneut senden
+    #[serde(default = "default_deduplication")]
+    pub deduplication: bool,
+
+    /// Obergrenze des Caches zuletzt empfangener Chunks in Bytes (0 = kein Cache)
+    #[serde(default = "default_chunk_cache_bytes")]
+    pub chunk_cache_bytes: u64,
+
+    /// Chunk-Cache im Datenverzeichnis speichern, damit er Neustarts übersteht
+    #[serde(default)]
+    pub persist_chunk_cache: bool,
+}
+
+fn default_deduplication() -> bool {
+    true
+}
+
+fn default_chunk_cache_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+fn default_staging_retention_hours() -> u64 {
+    24
+}
+
+fn default_transfer_timeout_secs() -> u64 {
+    60
+}
+
+fn default_stall_grace_secs() -> u64 {
+    60
+}
+
+fn default_history_retention_days() -> Option<u32> {
+    Some(90)
+}
+
+fn default_min_chunk_size() -> usize {
+    16 * 1024
+}
+
+fn default_max_chunk_size() -> usize {
+    1024 * 1024
+}
+
+impl Default for TransferConfig {
+    fn default() -> Self {
+        TransferConfig {
+            chunk_size: 64 * 1024,                  // 64 KB
+            min_chunk_size: default_min_chunk_size(),
+            max_chunk_size: default_max_chunk_size(),
+            adaptive_chunk_batching: false,
+            max_file_size: 4 * 1024 * 1024 * 1024,  // 4 GB
+            encryption_enabled: true,
+            max_retries: 3,
+            max_bandwidth_bytes_per_sec: None,
+            auto_bandwidth: None,
+            max_concurrent_transfers: 2,
+            permission_mask: 0o777,
+            verification_mode: VerificationMode::ChunkHashes,
+            history_retention_days: default_history_retention_days(),
+            transfer_timeout_secs: default_transfer_timeout_secs(),
+            stall_grace_secs: default_stall_grace_secs(),
+            resume_from_disk: false,
+            staging_retention_hours: default_staging_retention_hours(),
+            deduplication: default_deduplication(),
+            chunk_cache_bytes: default_chunk_cache_bytes(),
+            persist_chunk_cache: false,
+        }
+    }
+}
+
+/// Wie ein vollständiger Download geprüft wird
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum VerificationMode {
+    /// Datei komplett neu lesen und hashen (erkennt auch Schreibfehler der Platte)
+    FullReread,
+
+    /// Die beim Schreiben berechneten Chunk-Hashes kombinieren, ohne erneutes Lesen
+    #[default]
+    ChunkHashes,
+}
+
+/// Worüber der Datei-Hash einer Übertragungsanfrage gebildet ist
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum FileHashKind {
+    /// SHA-256 über den gesamten Inhalt (ältere Gegenstellen)
+    #[default]
+    WholeFile,
+
+    /// SHA-256 über die Liste der Chunk-Hashes
+    ChunkList,
+}
+
+/// Richtung einer Übertragung
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TransferType {
+    Upload,
+    Download,
+}
+
+/// Zustand einer Übertragung
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TransferStatus {
+    /// Wartet auf Annahme durch den Benutzer
+    Pending,
+
+    /// Wartet in der Queue auf einen freien Platz
+    Queued,
+
+    /// Wird vorbereitet (Hash-Berechnung, Anfrage)
+    Preparing,
+
+    /// Läuft
+    Active,
+
+    /// Pausiert
+    Paused,
+
+    /// Seit `transfer_timeout_secs` ohne Aktivität; wird nach der Karenzzeit abgebrochen
+    Stalled,
+
+    /// Abgeschlossen
+    Completed,
+
+    /// Fehlgeschlagen
+    Failed(String),
+
+    /// Abgebrochen
+    Cancelled,
+}
+
+/// Zustand eines einzelnen Chunks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ChunkStatus {
+    Pending,
+    InProgress,
+    Completed,
+    Failed,
+}
+
+/// Metadaten einer übertragenen Datei
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileMetadata {
+    /// Dateiname (ohne Pfad)
+    pub name: String,
+
+    /// Größe in Bytes
+    pub size: u64,
+
+    /// MIME-Typ
+    pub mime_type: String,
+
+    /// Erstellungszeitpunkt
+    pub created: SystemTime,
+
+    /// Letzte Änderung
+    pub modified: SystemTime,
+
+    /// Unix-Berechtigungen
+    pub permissions: u32,
+
+    /// Zusätzliche Attribute
+    pub attributes: HashMap<String, String>,
+}
+
+/// Fortschritt einer Übertragung
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferProgress {
+    /// Übertragene Bytes
+    pub bytes_transferred: u64,
+
+    /// Gesamtgröße in Bytes
+    pub total_bytes: u64,
+
+    /// Abgeschlossene Chunks
+    pub chunks_completed: usize,
+
+    /// Gesamtzahl der Chunks
+    pub total_chunks: usize,
+
+    /// Übertragungsrate in Bytes/Sekunde
+    pub transfer_rate: f64,
+
+    /// Geschätzte Restzeit in Sekunden
+    pub eta_seconds: Option<f64>,
+
+    /// Davon beim Empfänger schon vorhanden und nicht gesendet
+    #[serde(default)]
+    pub bytes_deduplicated: u64,
+}
+
+/// Interne Sitzung einer Übertragung
+#[derive(Debug, Clone)]
+pub struct TransferSession {
+    pub id: String,
+    pub transfer_type: TransferType,
+    pub peer_id: String,
+    pub status: TransferStatus,
+    pub file_metadata: FileMetadata,
+    pub file_hash: Option<String>,
+    pub source_path: Option<PathBuf>,
+    pub destination_path: Option<PathBuf>,
+
+    /// Schreibziel eines Downloads bis zur Prüfung, falls es ein Staging-Verzeichnis gibt
+    pub staging_path: Option<PathBuf>,
+    pub progress: TransferProgress,
+    pub started_at: Instant,
+    pub last_activity: Instant,
+    pub retry_count: u32,
+    pub chunks: HashMap<usize, ChunkStatus>,
+
+    /// Ob die Chunks dieser Übertragung verschlüsselt sind
+    pub encrypted: bool,
+
+    /// Öffentlicher X25519-Schlüssel des Peers (Base64), sobald bekannt
+    pub peer_public_key: Option<String>,
+
+    /// Priorität in der Queue
+    pub priority: TransferPriority,
+
+    /// Ob die Übertragungsanfrage bereits an den Peer ging
+    pub activated: bool,
+
+    /// Art von `file_hash`
+    pub hash_kind: FileHashKind,
+
+    /// SHA-256 je Chunk: beim Upload vorab berechnet, beim Download beim Schreiben
+    pub chunk_hashes: HashMap<usize, String>,
+
+    /// Ausgehandelte Chunk-Größe der Übertragung
+    pub chunk_size: usize,
+
+    /// Chunk-Größe, über die `file_hash` gebildet ist (Vorschlag des Senders)
+    pub hash_chunk_size: usize,
+
+    /// Erlaubter Bereich: beim Upload der eigene, nach der Aushandlung der gemeinsame
+    pub chunk_size_bounds: ChunkSizeBounds,
+
+    /// Ob der Empfänger jeden Chunk bestätigt
+    pub chunk_acks: bool,
+
+    /// Vorschau der Datei: beim Upload vorab erzeugt, beim Download vom Sender erhalten
+    pub preview: Option<FilePreview>,
+
+    /// Chunk-Hashes des Senders in `hash_chunk_size` für die Deduplizierung;
+    /// nur beim Download, leere Einträge fehlen noch
+    pub manifest: Vec<String>,
+}
+
+impl TransferSession {
+    /// Datei, in die die Chunks eines Downloads geschrieben werden
+    pub fn write_path(&self) -> Option<&PathBuf> {
+        self.staging_path.as_ref().or(self.destination_path.as_ref())
+    }
+}
+
+/// Öffentliche Sicht auf eine Übertragung
+#[derive(Debug, Clone)]
+pub struct TransferInfo {
+    pub id: String,
+    pub transfer_type: TransferType,
+    pub peer_id: String,
+    pub status: TransferStatus,
+    pub file_metadata: FileMetadata,
+    pub progress: TransferProgress,
+    pub started_at: Instant,
+    pub last_activity: Instant,
+    pub retry_count: u32,
+}
+
+/// Übertragungsstatistiken
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransferStats {
+    pub uploads_started: u64,
+    pub uploads_completed: u64,
+    pub downloads_started: u64,
+    pub downloads_completed: u64,
+    pub transfers_failed: u64,
+    pub total_bytes_queued: u64,
+    pub total_bytes_transferred: u64,
+
+    /// Nicht gesendete Bytes, weil der Empfänger sie schon hatte
+    #[serde(default)]
+    pub total_bytes_deduplicated: u64,
+
+    /// Wartende Übertragungen in der Queue
+    pub queued_transfers: usize,
+
+    /// Laufende Übertragungen (vorbereitend oder aktiv)
+    pub active_transfers: usize,
+}
+
+/// Eintrag der Queue für das UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferQueueEntry {
+    pub transfer_id: String,
+    pub file_name: String,
+    pub peer_id: String,
+    pub priority: TransferPriority,
+    pub position: usize,
+}
+
+/// Events für das UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TransferEvent {
+    TransferRequested {
+        transfer_id: String,
+        peer_id: String,
+        file_metadata: FileMetadata,
+        preview: Option<FilePreview>,
+    },
+    /// Nachträglich angeforderte Vorschau; None, wenn der Sender keine liefern kann
+    TransferPreview {
+        transfer_id: String,
+        preview: Option<FilePreview>,
+    },
+    TransferStarted {
+        transfer_id: String,
+        transfer_type: TransferType,
+        file_metadata: FileMetadata,
+        peer_id: String,
+    },
+    TransferQueued {
+        transfer_id: String,
+        position: usize,
+    },
+    TransferAccepted {
+        transfer_id: String,
+    },
+    TransferRejected {
+        transfer_id: String,
+        reason: String,
+    },
+    TransferProgress {
+        transfer_id: String,
+        progress: TransferProgress,
+    },
+    TransferPaused {
+        transfer_id: String,
+    },
+    TransferStalled {
+        transfer_id: String,
+        idle_secs: u64,
+    },
+    TransferResumed {
+        transfer_id: String,
+    },
+    TransferCancelled {
+        transfer_id: String,
+    },
+    TransferCompleted {
+        transfer_id: String,
+    },
+    TransferFailed {
+        transfer_id: String,
+        error: String,
+    },
+}
+
+impl TransferEvent {
+    /// Die betroffene Übertragung
+    pub fn transfer_id(&self) -> &str {
+        match self {
+            TransferEvent::TransferRequested { transfer_id, .. }
+            | TransferEvent::TransferPreview { transfer_id, .. }
+            | TransferEvent::TransferStarted { transfer_id, .. }
+            | TransferEvent::TransferQueued { transfer_id, .. }
+            | TransferEvent::TransferAccepted { transfer_id }
+            | TransferEvent::TransferRejected { transfer_id, .. }
+            | TransferEvent::TransferProgress { transfer_id, .. }
+            | TransferEvent::TransferPaused { transfer_id }
+            | TransferEvent::TransferStalled { transfer_id, .. }
+            | TransferEvent::TransferResumed { transfer_id }
+            | TransferEvent::TransferCancelled { transfer_id }
+            | TransferEvent::TransferCompleted { transfer_id }
+            | TransferEvent::TransferFailed { transfer_id, .. } => transfer_id,
+        }
+    }
+}
+
+/// Anfrage zum Start einer Übertragung
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferRequest {
+    /// Protokollversion des Senders, 0 bei Gegenstellen ohne Versionierung
+    #[serde(default)]
+    pub protocol_version: u32,
+
+    pub transfer_id: String,
+    pub file_metadata: FileMetadata,
+    pub file_hash: String,
+    pub chunk_size: usize,
+    pub total_chunks: usize,
+    pub encryption_enabled: bool,
+
+    /// Ephemerer öffentlicher X25519-Schlüssel des Senders (Base64)
+    pub sender_public_key: Option<String>,
+
+    /// Art von `file_hash`
+    #[serde(default)]
+    pub hash_kind: FileHashKind,
+
+    /// Bereich, in dem der Sender die Chunk-Größe anpassen kann (None = fest)
+    #[serde(default)]
+    pub chunk_size_bounds: Option<ChunkSizeBounds>,
+
+    /// Ob der Sender Bestätigungen je Chunk für adaptive Batches wünscht
+    #[serde(default)]
+    pub chunk_acks: bool,
+
+    /// Vorschau für Text und Bilder, höchstens `MAX_PREVIEW_SIZE` Bytes
+    #[serde(default)]
+    pub preview: Option<FilePreview>,
+
+    /// Ob der Sender Chunks überspringt, die der Empfänger schon hat
+    #[serde(default)]
+    pub dedup: bool,
+
+    /// Chunk-Hashes bis `MANIFEST_PAGE_CHUNKS` Chunks; größere Dateien
+    /// schicken sie auf `ManifestRequest` seitenweise nach
+    #[serde(default)]
+    pub chunk_hashes: Vec<String>,
+}
+
+/// Antwort auf eine Übertragungsanfrage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum TransferResponse {
+    Accept {
+        /// Protokollversion des Empfängers
+        #[serde(default)]
+        protocol_version: u32,
+
+        transfer_id: String,
+        ready: bool,
+
+        /// Ephemerer öffentlicher X25519-Schlüssel des Empfängers (Base64)
+        public_key: Option<String>,
+
+        /// Gegenangebot des Empfängers zur Chunk-Größe
+        #[serde(default)]
+        accepted_chunk_size: Option<usize>,
+
+        /// Gemeinsame Obergrenze beider Seiten für adaptive Batches
+        #[serde(default)]
+        max_chunk_size: Option<usize>,
+
+        /// Ob der Empfänger jeden Chunk bestätigt
+        #[serde(default)]
+        chunk_acks: bool,
+
+        /// Chunks, die der Empfänger schon hat und die der Sender überspringt
+        #[serde(default)]
+        existing_chunks: Vec<usize>,
+    },
+    Reject {
+        transfer_id: String,
+        reason: String,
+    },
+}
+
+/// Daten eines Chunks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkData {
+    pub transfer_id: String,
+    pub chunk_index: usize,
+
+    /// Nutzdaten; bei verschlüsselten Übertragungen Nonce + Ciphertext
+    pub data: Vec<u8>,
+
+    /// SHA-256 der unverschlüsselten Daten
+    pub chunk_hash: Option<String>,
+
+    /// Ob `data` verschlüsselt ist
+    pub encrypted: bool,
+}
+
+/// Anfrage nach einem Chunk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkRequest {
+    pub transfer_id: String,
+    pub chunk_index: usize,
+}
+
+/// Bestätigung eines geschriebenen Chunks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkAck {
+    pub transfer_id: String,
+    pub chunk_index: usize,
+}
+
+/// Anfrage nach der Vorschau einer angebotenen Datei
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewRequest {
+    pub transfer_id: String,
+}
+
+/// Antwort auf eine Vorschau-Anfrage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewResponse {
+    pub transfer_id: String,
+    pub preview: Option<FilePreview>,
+}
+
+/// Anfrage nach den Chunk-Hashes, die nicht in die Übertragungsanfrage passten
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestRequest {
+    pub transfer_id: String,
+}
+
+/// Eine Seite der Chunk-Hashes ab `first_index`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChunkManifest {
+    pub transfer_id: String,
+    pub first_index: usize,
+    pub chunk_hashes: Vec<String>,
+}
+
+/// Steuernachrichten
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase", rename_all_fields = "camelCase")]
+pub enum ControlMessage {
+    Pause { transfer_id: String },
+    Resume { transfer_id: String },
+    Cancel { transfer_id: String },
+}
+
+/// Alle Nachrichten des Übertragungsprotokolls
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "payload", rename_all = "camelCase")]
+pub enum TransferMessage {
+    Request(TransferRequest),
+    Response(TransferResponse),
+    Chunk(ChunkData),
+    ChunkRequest(ChunkRequest),
+    ChunkAck(ChunkAck),
+    Control(ControlMessage),
+    PreviewRequest(PreviewRequest),
+    Preview(PreviewResponse),
+    ManifestRequest(ManifestRequest),
+    Manifest(ChunkManifest),
+}
+
+impl TransferMessage {
+    /// Dekodiert eine Nachricht der Gegenstelle. Anfragen werden vor ihren
+    /// Feldern auf die Protokollversion geprüft, damit eine ältere Gegenstelle
+    /// einen `VersionMismatch` statt eines Schemafehlers bekommt.
+    pub fn decode(json: &str) -> Result<Self, ProtocolError> {
+        let value: Value = serde_json::from_str(json).map_err(|e| ProtocolError::Malformed(e.to_string()))?;
+        if value.get("type").and_then(Value::as_str) == Some("request") {
+            let payload = value.get("payload").unwrap_or(&Value::Null);
+            protocol::negotiate(WireSubsystem::FileTransfer, protocol::version_of(payload))?;
+        }
+        serde_json::from_value(value).map_err(|e| ProtocolError::Malformed(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    use crate::protocol::PROTOCOL_VERSION;
+
+    fn metadata() -> FileMetadata {
+        FileMetadata {
+            name: "a.txt".to_string(),
+            size: 3,
+            mime_type: "text/plain".to_string(),
+            created: SystemTime::UNIX_EPOCH,
+            modified: SystemTime::UNIX_EPOCH,
+            permissions: 0o644,
+            attributes: HashMap::new(),
+        }
+    }
+
+    /// Serialisiert und prüft, dass die Nachricht unverändert zurückkommt
+    fn wire(message: &TransferMessage) -> Value {
+        let value = serde_json::to_value(message).unwrap();
+        let decoded = TransferMessage::decode(&value.to_string()).unwrap();
+        assert_eq!(serde_json::to_value(&decoded).unwrap(), value);
+        value
+    }
+
+    #[test]
+    fn test_request_wire_snapshot() {
+        let request = TransferMessage::Request(TransferRequest {
+            protocol_version: PROTOCOL_VERSION,
+            transfer_id: "t1".to_string(),
+            file_metadata: metadata(),
+            file_hash: "abc".to_string(),
+            chunk_size: 1024,
+            total_chunks: 1,
+            encryption_enabled: false,
+            sender_public_key: None,
+            hash_kind: FileHashKind::ChunkList,
+            chunk_size_bounds: Some(ChunkSizeBounds { min: 512, max: 2048 }),
+            chunk_acks: true,
+            preview: Some(FilePreview { mime_type: "text/plain".to_string(), data: b"abc".to_vec(), truncated: false }),
+            dedup: true,
+            chunk_hashes: vec!["h0".to_string()],
+        });
+        let epoch = json!({ "secs_since_epoch": 0, "nanos_since_epoch": 0 });
+        assert_eq!(wire(&request), json!({
+            "type": "request",
+            "payload": {
+                "protocolVersion": PROTOCOL_VERSION,
+                "transferId": "t1",
+                "fileMetadata": {
+                    "name": "a.txt",
+                    "size": 3,
+                    "mimeType": "text/plain",
+                    "created": epoch,
+                    "modified": epoch,
+                    "permissions": 0o644,
+                    "attributes": {},
+                },
+                "fileHash": "abc",
+                "chunkSize": 1024,
+                "totalChunks": 1,
+                "encryptionEnabled": false,
+                "senderPublicKey": null,
+                "hashKind": "ChunkList",
+                "chunkSizeBounds": { "min": 512, "max": 2048 },
+                "chunkAcks": true,
+                "preview": { "mimeType": "text/plain", "data": [97, 98, 99], "truncated": false },
+                "dedup": true,
+                "chunkHashes": ["h0"],
+            },
+        }));
+    }
+
+    #[test]
+    fn test_response_wire_snapshot() {
+        let accept = TransferMessage::Response(TransferResponse::Accept {
+            protocol_version: PROTOCOL_VERSION,
+            transfer_id: "t1".to_string(),
+            ready: true,
+            public_key: Some("key".to_string()),
+            accepted_chunk_size: Some(1024),
+            max_chunk_size: None,
+            chunk_acks: false,
+            existing_chunks: vec![0, 2],
+        });
+        assert_eq!(wire(&accept), json!({
+            "type": "response",
+            "payload": {
+                "type": "accept",
+                "protocolVersion": PROTOCOL_VERSION,
+                "transferId": "t1",
+                "ready": true,
+                "publicKey": "key",
+                "acceptedChunkSize": 1024,
+                "maxChunkSize": null,
+                "chunkAcks": false,
+                "existingChunks": [0, 2],
+            },
+        }));
+
+        let reject = TransferMessage::Response(TransferResponse::Reject {
+            transfer_id: "t1".to_string(),
+            reason: "no".to_string(),
+        });
+        assert_eq!(wire(&reject), json!({
+            "type": "response",
+            "payload": { "type": "reject", "transferId": "t1", "reason": "no" },
+        }));
+    }
+
+    #[test]
+    fn test_chunk_and_control_wire_snapshots() {
+        let chunk = TransferMessage::Chunk(ChunkData {
+            transfer_id: "t1".to_string(),
+            chunk_index: 2,
+            data: vec![1, 2],
+            chunk_hash: None,
+            encrypted: false,
+        });
+        assert_eq!(wire(&chunk), json!({
+            "type": "chunk",
+            "payload": { "transferId": "t1", "chunkIndex": 2, "data": [1, 2], "chunkHash": null, "encrypted": false },
+        }));
+
+        let request = TransferMessage::ChunkRequest(ChunkRequest { transfer_id: "t1".to_string(), chunk_index: 3 });
+        assert_eq!(wire(&request), json!({ "type": "chunkRequest", "payload": { "transferId": "t1", "chunkIndex": 3 } }));
+
+        let ack = TransferMessage::ChunkAck(ChunkAck { transfer_id: "t1".to_string(), chunk_index: 3 });
+        assert_eq!(wire(&ack), json!({ "type": "chunkAck", "payload": { "transferId": "t1", "chunkIndex": 3 } }));
+
+        let control = TransferMessage::Control(ControlMessage::Pause { transfer_id: "t1".to_string() });
+        assert_eq!(wire(&control), json!({ "type": "control", "payload": { "type": "pause", "transferId": "t1" } }));
+
+        let preview_request = TransferMessage::PreviewRequest(PreviewRequest { transfer_id: "t1".to_string() });
+        assert_eq!(wire(&preview_request), json!({ "type": "previewRequest", "payload": { "transferId": "t1" } }));
+
+        let preview = TransferMessage::Preview(PreviewResponse { transfer_id: "t1".to_string(), preview: None });
+        assert_eq!(wire(&preview), json!({ "type": "preview", "payload": { "transferId": "t1", "preview": null } }));
+
+        let manifest_request = TransferMessage::ManifestRequest(ManifestRequest { transfer_id: "t1".to_string() });
+        assert_eq!(wire(&manifest_request), json!({ "type": "manifestRequest", "payload": { "transferId": "t1" } }));
+
+        let manifest = TransferMessage::Manifest(ChunkManifest {
+            transfer_id: "t1".to_string(),
+            first_index: 512,
+            chunk_hashes: vec!["h512".to_string()],
+        });
+        assert_eq!(wire(&manifest), json!({
+            "type": "manifest",
+            "payload": { "transferId": "t1", "firstIndex": 512, "chunkHashes": ["h512"] },
+        }));
+    }
+
+    #[test]
+    fn test_decode_rejects_unversioned_requests() {
+        let mut request = serde_json::to_value(TransferMessage::Request(TransferRequest {
+            protocol_version: PROTOCOL_VERSION,
+            transfer_id: "t1".to_string(),
+            file_metadata: metadata(),
+            file_hash: "abc".to_string(),
+            chunk_size: 1024,
+            total_chunks: 1,
+            encryption_enabled: false,
+            sender_public_key: None,
+            hash_kind: FileHashKind::WholeFile,
+            chunk_size_bounds: None,
+            chunk_acks: false,
+            preview: None,
+            dedup: false,
+            chunk_hashes: Vec::new(),
+        })).unwrap();
+        request["payload"].as_object_mut().unwrap().remove("protocolVersion");
+
+        assert!(matches!(
+            TransferMessage::decode(&request.to_string()),
+            Err(ProtocolError::VersionMismatch(_))
+        ));
+        assert!(matches!(TransferMessage::decode(r#"{"type":"bogus"}"#), Err(ProtocolError::Malformed(_))));
+    }
+}
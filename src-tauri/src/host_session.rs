@@ -0,0 +1,386 @@
+// src-tauri/src/host_session.rs - Lock screen and display power state of the host
+//
+// Remote input sent while the host is locked or its display is powered down
+// seems to vanish, and the viewer only sees a black stream. The state is
+// checked before input is forwarded: a sleeping display is woken up, a locked
+// session is reported to the frontend. Unlocking is always left to the user.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::command_runner::{CommandRunner, SystemCommandRunner};
+use crate::logging::TARGET_INPUT;
+
+/// How long a queried state is reused; the input path checks on every event
+pub const STATE_CACHE_TTL: Duration = Duration::from_secs(1);
+
+/// Minimum time between two automatic wake attempts
+const WAKE_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Screensaver D-Bus services asked when logind has no lock hint, as (service, object path)
+const SCREENSAVER_SERVICES: [(&str, &str); 2] = [
+    ("org.freedesktop.ScreenSaver", "/org/freedesktop/ScreenSaver"),
+    ("org.gnome.ScreenSaver", "/org/gnome/ScreenSaver"),
+];
+
+/// What the host's session looks like; None where it cannot be determined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct HostSessionState {
+    /// The lock screen (or a locking screensaver) is active
+    pub locked: Option<bool>,
+    /// All outputs are blanked by DPMS
+    pub display_asleep: Option<bool>,
+}
+
+/// What happened before an input event was forwarded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InputPreflight {
+    pub state: HostSessionState,
+    /// The display was asleep and a wake-up was requested
+    pub woke_display: bool,
+    /// The session is locked and this is the first input since it locked
+    pub newly_locked: bool,
+}
+
+pub type StateSource = Box<dyn Fn() -> HostSessionState + Send + Sync>;
+pub type DisplayWaker = Box<dyn Fn() -> Result<(), String> + Send + Sync>;
+
+/// Cached host session state plus the wake-on-input behaviour
+pub struct HostSessionMonitor {
+    source: StateSource,
+    waker: DisplayWaker,
+    ttl: Duration,
+    cached: Mutex<Option<(Instant, HostSessionState)>>,
+    wake_on_input: AtomicBool,
+    last_wake: Mutex<Option<Instant>>,
+    lock_reported: AtomicBool,
+}
+
+impl HostSessionMonitor {
+    pub fn new() -> Self {
+        Self::with_source(Box::new(query_session_state), Box::new(wake_display), STATE_CACHE_TTL)
+    }
+
+    pub fn with_source(source: StateSource, waker: DisplayWaker, ttl: Duration) -> Self {
+        HostSessionMonitor {
+            source,
+            waker,
+            ttl,
+            cached: Mutex::new(None),
+            wake_on_input: AtomicBool::new(true),
+            last_wake: Mutex::new(None),
+            lock_reported: AtomicBool::new(false),
+        }
+    }
+
+    pub fn wake_on_input(&self) -> bool {
+        self.wake_on_input.load(Ordering::SeqCst)
+    }
+
+    pub fn set_wake_on_input(&self, enabled: bool) {
+        self.wake_on_input.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Current state, queried at most once per TTL
+    pub fn state(&self) -> HostSessionState {
+        let mut cached = self.cached.lock().unwrap();
+        if let Some((queried_at, state)) = *cached {
+            if queried_at.elapsed() < self.ttl {
+                return state;
+            }
+        }
+        let state = (self.source)();
+        *cached = Some((Instant::now(), state));
+        state
+    }
+
+    /// Powers the display on and forgets the cached state
+    pub fn wake_display(&self) -> Result<(), String> {
+        *self.last_wake.lock().unwrap() = Some(Instant::now());
+        let result = (self.waker)();
+        *self.cached.lock().unwrap() = None;
+        result
+    }
+
+    /// Runs before an input event is forwarded
+    pub fn before_input(&self) -> InputPreflight {
+        let state = self.state();
+        let mut preflight = InputPreflight { state, ..Default::default() };
+
+        if state.display_asleep == Some(true) && self.wake_on_input() && self.wake_due() {
+            match self.wake_display() {
+                Ok(()) => preflight.woke_display = true,
+                Err(e) => log::warn!(target: TARGET_INPUT, "Failed to wake the display: {}", e),
+            }
+        }
+
+        // Reported once per lock, not for every keystroke that hits the lock screen
+        let locked = state.locked == Some(true);
+        preflight.newly_locked = locked && !self.lock_reported.swap(true, Ordering::SeqCst);
+        if !locked {
+            self.lock_reported.store(false, Ordering::SeqCst);
+        }
+        preflight
+    }
+
+    fn wake_due(&self) -> bool {
+        match *self.last_wake.lock().unwrap() {
+            Some(last) => last.elapsed() >= WAKE_RETRY_INTERVAL,
+            None => true,
+        }
+    }
+}
+
+/// Queries lock and DPMS state from logind, the screensaver and the compositor
+pub fn query_session_state() -> HostSessionState {
+    HostSessionState {
+        locked: query_locked(),
+        display_asleep: query_display_asleep(),
+    }
+}
+
+fn query_locked() -> Option<bool> {
+    let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "self".to_string());
+    let locked_hint = run("loginctl", &["show-session", &session, "-p", "LockedHint", "--value"])
+        .and_then(|output| parse_locked_hint(&output));
+    if locked_hint == Some(true) {
+        return Some(true);
+    }
+
+    // Lockers such as xscreensaver or light-locker do not set LockedHint
+    let screensaver = SCREENSAVER_SERVICES.iter().find_map(|(service, path)| {
+        let method = format!("{}.GetActive", service);
+        let dest = format!("--dest={}", service);
+        run("dbus-send", &["--session", "--print-reply", &dest, path, &method])
+            .and_then(|output| parse_dbus_boolean(&output))
+    });
+    match (locked_hint, screensaver) {
+        (_, Some(true)) => Some(true),
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (None, None) => None,
+    }
+}
+
+fn query_display_asleep() -> Option<bool> {
+    if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        return run("hyprctl", &["monitors", "-j"]).and_then(|output| parse_hyprland_asleep(&output));
+    }
+    if std::env::var_os("SWAYSOCK").is_some() {
+        return run("swaymsg", &["-t", "get_outputs"]).and_then(|output| parse_sway_asleep(&output));
+    }
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        // Other wlroots compositors via wlr-output-power-management
+        return run("wlopm", &[]).and_then(|output| parse_wlopm_asleep(&output));
+    }
+    if std::env::var_os("DISPLAY").is_some() {
+        return run("xset", &["q"]).and_then(|output| parse_xset_asleep(&output));
+    }
+    None
+}
+
+/// Turns the display back on with the compositor's own mechanism
+pub fn wake_display() -> Result<(), String> {
+    if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        return run_checked("hyprctl", &["dispatch", "dpms", "on"]);
+    }
+    if std::env::var_os("SWAYSOCK").is_some() {
+        return run_checked("swaymsg", &["output", "*", "power", "on"]);
+    }
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        return run_checked("wlopm", &["--on", "*"]);
+    }
+    if std::env::var_os("DISPLAY").is_some() {
+        run_checked("xset", &["dpms", "force", "on"])?;
+        // Also ends a blanking screensaver that is not DPMS
+        return run_checked("xset", &["s", "reset"]);
+    }
+    Err("Waking the display is not supported on this desktop".to_string())
+}
+
+/// `loginctl show-session -p LockedHint --value` prints "yes" or "no"
+fn parse_locked_hint(output: &str) -> Option<bool> {
+    match output.trim() {
+        "yes" => Some(true),
+        "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Last line of `dbus-send --print-reply` for a boolean, e.g. "   boolean true"
+fn parse_dbus_boolean(output: &str) -> Option<bool> {
+    let value = output.lines().rev().find_map(|line| line.trim().strip_prefix("boolean "))?;
+    value.trim().parse().ok()
+}
+
+/// `xset q` ends with "DPMS is Enabled" / "Monitor is Off" (or Standby, Suspend)
+fn parse_xset_asleep(output: &str) -> Option<bool> {
+    if output.contains("DPMS is Disabled") {
+        return Some(false);
+    }
+    let state = output.lines().find_map(|line| line.trim().strip_prefix("Monitor is "))?;
+    Some(state.trim() != "On")
+}
+
+/// `hyprctl monitors -j`; asleep when every monitor has `dpmsStatus: false`
+fn parse_hyprland_asleep(output: &str) -> Option<bool> {
+    let monitors: Vec<serde_json::Value> = serde_json::from_str(output).ok()?;
+    all_off(monitors.iter().filter_map(|monitor| monitor.get("dpmsStatus")?.as_bool()))
+}
+
+/// `swaymsg -t get_outputs`; `power` since sway 1.8, `dpms` before
+fn parse_sway_asleep(output: &str) -> Option<bool> {
+    let outputs: Vec<serde_json::Value> = serde_json::from_str(output).ok()?;
+    all_off(outputs.iter()
+        .filter(|output| output.get("active").and_then(|a| a.as_bool()) != Some(false))
+        .filter_map(|output| output.get("power").or_else(|| output.get("dpms"))?.as_bool()))
+}
+
+/// `wlopm` prints one "<output> on|off" line per output
+fn parse_wlopm_asleep(output: &str) -> Option<bool> {
+    all_off(output.lines().filter_map(|line| match line.split_whitespace().nth(1)? {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    }))
+}
+
+/// True if no output is powered on, None if there were no outputs
+fn all_off(mut powered: impl Iterator<Item = bool>) -> Option<bool> {
+    let first = powered.next()?;
+    Some(!first && !powered.any(|on| on))
+}
+
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = SystemCommandRunner.command(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn run_checked(program: &str, args: &[&str]) -> Result<(), String> {
+    let output = SystemCommandRunner.command(program).args(args).output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    fn monitor(state: Arc<Mutex<HostSessionState>>, wakes: Arc<AtomicUsize>) -> HostSessionMonitor {
+        HostSessionMonitor::with_source(
+            Box::new(move || *state.lock().unwrap()),
+            Box::new(move || {
+                wakes.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }),
+            Duration::ZERO,
+        )
+    }
+
+    #[test]
+    fn test_parse_lock_state() {
+        assert_eq!(parse_locked_hint("yes\n"), Some(true));
+        assert_eq!(parse_locked_hint("no\n"), Some(false));
+        assert_eq!(parse_locked_hint(""), None);
+
+        let reply = "method return time=1700000000.1 sender=:1.20 -> destination=:1.99 serial=5 reply_serial=2\n   boolean true\n";
+        assert_eq!(parse_dbus_boolean(reply), Some(true));
+        assert_eq!(parse_dbus_boolean("   boolean false\n"), Some(false));
+        assert_eq!(parse_dbus_boolean("Error org.freedesktop.DBus.Error.ServiceUnknown"), None);
+    }
+
+    #[test]
+    fn test_parse_xset() {
+        let on = "DPMS (Energy Star):\n  Standby: 600    Suspend: 600    Off: 600\n  DPMS is Enabled\n  Monitor is On\n";
+        assert_eq!(parse_xset_asleep(on), Some(false));
+        assert_eq!(parse_xset_asleep(&on.replace("Monitor is On", "Monitor is Off")), Some(true));
+        assert_eq!(parse_xset_asleep(&on.replace("Monitor is On", "Monitor is in Standby")), Some(true));
+        assert_eq!(parse_xset_asleep("DPMS (Energy Star):\n  DPMS is Disabled\n"), Some(false));
+        assert_eq!(parse_xset_asleep("Keyboard Control:\n"), None);
+    }
+
+    #[test]
+    fn test_parse_compositor_power() {
+        assert_eq!(parse_hyprland_asleep(r#"[{"name":"DP-1","dpmsStatus":false},{"name":"DP-2","dpmsStatus":false}]"#), Some(true));
+        assert_eq!(parse_hyprland_asleep(r#"[{"name":"DP-1","dpmsStatus":false},{"name":"DP-2","dpmsStatus":true}]"#), Some(false));
+        assert_eq!(parse_hyprland_asleep("[]"), None);
+
+        // Disabled outputs do not count; older sway reports `dpms`
+        assert_eq!(parse_sway_asleep(r#"[{"name":"eDP-1","active":true,"power":false},{"name":"HDMI-A-1","active":false,"power":true}]"#), Some(true));
+        assert_eq!(parse_sway_asleep(r#"[{"name":"eDP-1","active":true,"dpms":true}]"#), Some(false));
+
+        assert_eq!(parse_wlopm_asleep("eDP-1 off\nDP-1 off\n"), Some(true));
+        assert_eq!(parse_wlopm_asleep("eDP-1 off\nDP-1 on\n"), Some(false));
+        assert_eq!(parse_wlopm_asleep(""), None);
+    }
+
+    #[test]
+    fn test_sleeping_display_is_woken_once() {
+        let state = Arc::new(Mutex::new(HostSessionState { locked: Some(false), display_asleep: Some(true) }));
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let monitor = monitor(state.clone(), wakes.clone());
+
+        assert!(monitor.before_input().woke_display);
+        // The display takes a moment to come back; no second attempt right away
+        assert!(!monitor.before_input().woke_display);
+        assert_eq!(wakes.load(Ordering::SeqCst), 1);
+
+        monitor.set_wake_on_input(false);
+        *monitor.last_wake.lock().unwrap() = None;
+        assert!(!monitor.before_input().woke_display);
+        assert_eq!(wakes.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_lock_is_reported_once_per_lock() {
+        let state = Arc::new(Mutex::new(HostSessionState { locked: Some(true), display_asleep: Some(false) }));
+        let wakes = Arc::new(AtomicUsize::new(0));
+        let monitor = monitor(state.clone(), wakes.clone());
+
+        assert!(monitor.before_input().newly_locked);
+        assert!(!monitor.before_input().newly_locked);
+
+        state.lock().unwrap().locked = Some(false);
+        assert!(!monitor.before_input().newly_locked);
+        state.lock().unwrap().locked = Some(true);
+        assert!(monitor.before_input().newly_locked);
+
+        // Never an unlock attempt, only the display wake-up
+        assert_eq!(wakes.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_state_is_cached() {
+        let queries = Arc::new(AtomicUsize::new(0));
+        let counter = queries.clone();
+        let monitor = HostSessionMonitor::with_source(
+            Box::new(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                HostSessionState::default()
+            }),
+            Box::new(|| Ok(())),
+            Duration::from_secs(60),
+        );
+
+        for _ in 0..5 {
+            monitor.before_input();
+        }
+        assert_eq!(queries.load(Ordering::SeqCst), 1);
+
+        // Waking invalidates the cache so the new state is picked up
+        monitor.wake_display().unwrap();
+        monitor.state();
+        assert_eq!(queries.load(Ordering::SeqCst), 2);
+    }
+}
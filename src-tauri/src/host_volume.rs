@@ -0,0 +1,114 @@
+// src-tauri/src/host_volume.rs - Output volume of the host's default audio sink
+//
+// The volume keys of SpecialCommand step the volume by whatever the desktop
+// chooses; a slider in the viewer needs to set an absolute level instead.
+// PipeWire sessions are driven with wpctl, PulseAudio (and pipewire-pulse
+// without WirePlumber) with pactl.
+
+use crate::command_runner::CommandRunner;
+use crate::logging::TARGET_INPUT;
+
+/// Highest level the remote slider may set; amplification beyond 100% stays local
+pub const MAX_VOLUME_PERCENT: u32 = 100;
+
+/// Sets the default sink to `percent`, clamped to `MAX_VOLUME_PERCENT`.
+/// Returns the level that was applied.
+pub fn set_volume(runner: &dyn CommandRunner, percent: u32) -> Result<u32, String> {
+    let percent = percent.min(MAX_VOLUME_PERCENT);
+    let level = format!("{}%", percent);
+
+    match run(runner, "wpctl", &["set-volume", "@DEFAULT_AUDIO_SINK@", &level]) {
+        Ok(_) => Ok(percent),
+        Err(wpctl_error) => {
+            log::debug!(target: TARGET_INPUT, "wpctl unavailable, trying pactl: {}", wpctl_error);
+            run(runner, "pactl", &["set-sink-volume", "@DEFAULT_SINK@", &level])
+                .map(|_| percent)
+                .map_err(|pactl_error| format!("{}; {}", wpctl_error, pactl_error))
+        },
+    }
+}
+
+/// Current level of the default sink in percent
+pub fn get_volume(runner: &dyn CommandRunner) -> Result<u32, String> {
+    let wpctl_error = match run(runner, "wpctl", &["get-volume", "@DEFAULT_AUDIO_SINK@"]) {
+        Ok(output) => return parse_wpctl_volume(&output)
+            .ok_or_else(|| format!("Unexpected wpctl output: {}", output.trim())),
+        Err(e) => e,
+    };
+
+    let output = run(runner, "pactl", &["get-sink-volume", "@DEFAULT_SINK@"])
+        .map_err(|pactl_error| format!("{}; {}", wpctl_error, pactl_error))?;
+    parse_pactl_volume(&output).ok_or_else(|| format!("Unexpected pactl output: {}", output.trim()))
+}
+
+/// `wpctl get-volume` prints the level as a factor, e.g. "Volume: 0.45 [MUTED]"
+fn parse_wpctl_volume(output: &str) -> Option<u32> {
+    let value = output.trim().strip_prefix("Volume:")?.split_whitespace().next()?;
+    let factor: f64 = value.parse().ok()?;
+    Some((factor * 100.0).round() as u32)
+}
+
+/// `pactl get-sink-volume` prints every channel, e.g.
+/// "Volume: front-left: 29491 /  45% / -20.81 dB,   front-right: ..."; the first one is used
+fn parse_pactl_volume(output: &str) -> Option<u32> {
+    output.split_whitespace()
+        .find_map(|word| word.strip_suffix('%')?.parse().ok())
+}
+
+fn run(runner: &dyn CommandRunner, program: &str, args: &[&str]) -> Result<String, String> {
+    let output = runner.command(program).args(args).output()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(format!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::{call, TestEnv};
+
+    #[test]
+    fn test_parse_volume() {
+        assert_eq!(parse_wpctl_volume("Volume: 0.45\n"), Some(45));
+        assert_eq!(parse_wpctl_volume("Volume: 1.00 [MUTED]\n"), Some(100));
+        assert_eq!(parse_wpctl_volume("Translate ID error"), None);
+
+        let pactl = "Volume: front-left: 29491 /  45% / -20.81 dB,   front-right: 29491 /  45% / -20.81 dB\n        balance 0.00\n";
+        assert_eq!(parse_pactl_volume(pactl), Some(45));
+        assert_eq!(parse_pactl_volume("Failure: No such entity"), None);
+    }
+
+    #[test]
+    fn test_set_volume_prefers_wpctl() {
+        let env = TestEnv::new();
+
+        assert_eq!(set_volume(&*env.runner(), 30), Ok(30));
+        assert_eq!(env.take_invocations(), vec![call("wpctl set-volume @DEFAULT_AUDIO_SINK@ 30%")]);
+
+        // The remote slider cannot amplify beyond 100%
+        assert_eq!(set_volume(&*env.runner(), 150), Ok(100));
+        assert_eq!(env.take_invocations(), vec![call("wpctl set-volume @DEFAULT_AUDIO_SINK@ 100%")]);
+    }
+
+    #[test]
+    fn test_pactl_is_used_without_wireplumber() {
+        let env = TestEnv::new();
+        env.set_exit_code("wpctl", 1);
+        env.set_stdout("pactl", "Volume: front-left: 45875 /  70% / -9.29 dB,   front-right: 45875 /  70% / -9.29 dB\n");
+
+        assert_eq!(set_volume(&*env.runner(), 70), Ok(70));
+        assert_eq!(get_volume(&*env.runner()), Ok(70));
+        assert_eq!(env.take_invocations(), vec![
+            call("wpctl set-volume @DEFAULT_AUDIO_SINK@ 70%"),
+            call("pactl set-sink-volume @DEFAULT_SINK@ 70%"),
+            call("wpctl get-volume @DEFAULT_AUDIO_SINK@"),
+            call("pactl get-sink-volume @DEFAULT_SINK@"),
+        ]);
+
+        env.set_exit_code("pactl", 1);
+        assert!(set_volume(&*env.runner(), 70).is_err());
+    }
+}
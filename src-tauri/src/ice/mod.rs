@@ -0,0 +1,263 @@
+// src-tauri/src/ice/mod.rs - STUN and TURN servers for the WebRTC connections
+//
+// Operators behind strict NATs need their own TURN servers. The server list
+// lives in the `ice` section of the settings; TURN credentials go to the OS
+// keyring under the server's ID and never into settings.toml. A configuration
+// bundle carries them only together with the other secrets.
+
+pub mod probe;
+pub mod url;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::command_runner::CommandRunner;
+use crate::keyring;
+use crate::logging::TARGET_SIGNALING;
+
+pub use url::IceUrl;
+
+const KEYRING_LABEL: &str = "SmolDesk TURN credential";
+
+/// A STUN or TURN server the way RTCPeerConnection takes it
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct IceServer {
+    pub urls: Vec<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub credential: Option<String>,
+}
+
+// The credential must not end up in logs
+impl fmt::Debug for IceServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IceServer")
+            .field("urls", &self.urls)
+            .field("username", &self.username)
+            .field("credential", &self.credential.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+/// A server in the settings; its credential is in the keyring under `id`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredIceServer {
+    pub id: String,
+    pub urls: Vec<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+/// STUN/TURN servers offered to every peer connection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IceConfig {
+    pub servers: Vec<StoredIceServer>,
+}
+
+impl Default for IceConfig {
+    fn default() -> Self {
+        let public_stun = |url: &str| {
+            let urls = vec![url.to_string()];
+            StoredIceServer { id: server_id(&urls, None), urls, username: None }
+        };
+        IceConfig {
+            servers: vec![
+                public_stun("stun:stun.l.google.com:19302"),
+                public_stun("stun:stun1.l.google.com:19302"),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IceError {
+    InvalidUrl { url: String, reason: String },
+    /// A TURN server without username or credential
+    MissingCredential(String),
+    /// The credentials cannot be kept in a keyring
+    KeyringUnavailable(String),
+    /// The probe cannot test this kind of server, e.g. TURN over TLS
+    Unsupported(String),
+}
+
+impl fmt::Display for IceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IceError::InvalidUrl { url, reason } => write!(f, "Invalid ICE server URL {}: {}", url, reason),
+            IceError::MissingCredential(url) => write!(f, "TURN server {} needs a username and a credential", url),
+            IceError::KeyringUnavailable(msg) => write!(f, "Cannot store TURN credentials: {}", msg),
+            IceError::Unsupported(msg) => write!(f, "Cannot test ICE server: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for IceError {}
+
+/// Stable ID of a server, so that saving the same list again keeps the
+/// keyring entries
+pub fn server_id(urls: &[String], username: Option<&str>) -> String {
+    let mut hasher = Sha256::new();
+    for url in urls {
+        hasher.update(url.as_bytes());
+        hasher.update([0]);
+    }
+    hasher.update(username.unwrap_or_default().as_bytes());
+    hasher.finalize().iter().take(8).map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Checks every URL; TURN servers need a username and a credential
+pub fn validate(servers: &[IceServer]) -> Result<(), IceError> {
+    for server in servers {
+        if server.urls.is_empty() {
+            return Err(IceError::InvalidUrl { url: String::new(), reason: "a server needs at least one URL".to_string() });
+        }
+        for url in &server.urls {
+            let parsed = IceUrl::parse(url)?;
+            let has_credential = server.username.as_deref().is_some_and(|username| !username.is_empty())
+                && server.credential.as_deref().is_some_and(|credential| !credential.is_empty());
+            if parsed.is_turn() && !has_credential {
+                return Err(IceError::MissingCredential(url.clone()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Reads and writes the server list together with the keyring
+pub struct IceStore {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl IceStore {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        IceStore { runner }
+    }
+
+    /// The servers of `config` with their credentials. A credential missing
+    /// from the keyring is left out; the server then fails to authenticate.
+    pub fn load(&self, config: &IceConfig) -> Vec<IceServer> {
+        config.servers.iter()
+            .map(|server| {
+                let credential = server.username.as_ref().and_then(|_| self.credential(&server.id));
+                if server.username.is_some() && credential.is_none() {
+                    log::warn!(target: TARGET_SIGNALING, "No credential in the keyring for ICE server {:?}", server.urls);
+                }
+                IceServer { urls: server.urls.clone(), username: server.username.clone(), credential }
+            })
+            .collect()
+    }
+
+    /// Validates `servers`, stores their credentials and returns the
+    /// settings to save. Credentials of servers that are gone are removed.
+    pub fn save(&self, previous: &IceConfig, servers: &[IceServer]) -> Result<IceConfig, IceError> {
+        validate(servers)?;
+
+        let mut stored = Vec::with_capacity(servers.len());
+        for server in servers {
+            let id = server_id(&server.urls, server.username.as_deref());
+            if let (Some(_), Some(credential)) = (&server.username, &server.credential) {
+                keyring::store_secret(&*self.runner, &keyring_name(&id), KEYRING_LABEL, credential)
+                    .map_err(IceError::KeyringUnavailable)?;
+            }
+            stored.push(StoredIceServer { id, urls: server.urls.clone(), username: server.username.clone() });
+        }
+
+        for server in &previous.servers {
+            if server.username.is_some() && !stored.iter().any(|kept| kept.id == server.id) {
+                keyring::delete_secret(&*self.runner, &keyring_name(&server.id));
+            }
+        }
+        Ok(IceConfig { servers: stored })
+    }
+
+    /// The stored server that lists `url`, with its credential
+    pub fn find(&self, config: &IceConfig, url: &str) -> Option<IceServer> {
+        self.load(&IceConfig {
+            servers: config.servers.iter().filter(|server| server.urls.iter().any(|known| known == url)).cloned().collect(),
+        }).into_iter().next()
+    }
+
+    /// Credentials by server ID, for a configuration bundle with secrets
+    pub fn export_credentials(&self, config: &IceConfig) -> BTreeMap<String, String> {
+        config.servers.iter()
+            .filter(|server| server.username.is_some())
+            .filter_map(|server| Some((server.id.clone(), self.credential(&server.id)?)))
+            .collect()
+    }
+
+    /// Stores the credentials of an imported bundle for the servers of `config`
+    pub fn import_credentials(&self, config: &IceConfig, credentials: &BTreeMap<String, String>) -> Result<(), IceError> {
+        for server in &config.servers {
+            if let Some(credential) = credentials.get(&server.id) {
+                keyring::store_secret(&*self.runner, &keyring_name(&server.id), KEYRING_LABEL, credential)
+                    .map_err(IceError::KeyringUnavailable)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn credential(&self, id: &str) -> Option<String> {
+        keyring::lookup_secret(&*self.runner, &keyring_name(id))
+    }
+}
+
+fn keyring_name(id: &str) -> String {
+    format!("ice-server-{}", id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::{call, TestEnv};
+
+    fn turn_server() -> IceServer {
+        IceServer {
+            urls: vec!["turn:turn.example.org:3478".to_string()],
+            username: Some("alice".to_string()),
+            credential: Some("s3cret".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_turn_needs_credentials() {
+        let mut server = turn_server();
+        server.credential = None;
+        assert_eq!(validate(&[server]), Err(IceError::MissingCredential("turn:turn.example.org:3478".to_string())));
+
+        let stun = IceServer { urls: vec!["stun:stun.example.org".to_string()], username: None, credential: None };
+        assert_eq!(validate(&[stun, turn_server()]), Ok(()));
+        assert!(!format!("{:?}", turn_server()).contains("s3cret"));
+    }
+
+    #[test]
+    fn test_credentials_go_to_the_keyring() {
+        let env = TestEnv::new();
+        env.record_stdin("secret-tool");
+        let store = IceStore::new(env.runner());
+
+        let config = store.save(&IceConfig::default(), &[turn_server()]).unwrap();
+        let id = &config.servers[0].id;
+        assert_eq!(config.servers[0].username.as_deref(), Some("alice"));
+        assert!(!serde_json::to_string(&config).unwrap().contains("s3cret"));
+        assert_eq!(env.stdin("secret-tool"), b"s3cret".to_vec());
+        let mut store_call = call(&format!("secret-tool store LABEL application smoldesk secret ice-server-{}", id));
+        store_call[2] = format!("--label={}", KEYRING_LABEL);
+        assert_eq!(env.take_invocations(), vec![store_call]);
+
+        // Saving the same server again keeps its ID; removing it clears the keyring
+        assert_eq!(store.save(&config, &[turn_server()]).unwrap(), config);
+        env.take_invocations();
+        assert_eq!(store.save(&config, &[]).unwrap().servers, vec![]);
+        assert_eq!(env.take_invocations()[0], call(&format!("secret-tool clear application smoldesk secret ice-server-{}", id)));
+
+        env.set_stdout("secret-tool", "s3cret");
+        assert_eq!(store.load(&config), vec![turn_server()]);
+        assert_eq!(store.export_credentials(&config).get(id).map(String::as_str), Some("s3cret"));
+    }
+}
@@ -0,0 +1,183 @@
+// src-tauri/src/ice/probe.rs - Reachability test for a STUN or TURN server
+//
+// Sends a STUN binding request from the host, which shows whether the server
+// answers and under which public address it sees the host. With credentials a
+// TURN server is also asked for a relay allocation, which is released again
+// right away; a wrong password shows up there and not in the binding request.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::net::UdpSocket;
+use turn::client::{Client, ClientConfig};
+use webrtc_util::Conn;
+
+use crate::ice::url::{IceScheme, IceTransport};
+use crate::ice::{IceError, IceUrl};
+
+/// Time a test may take per server
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Outcome of `test_ice_server`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IceProbeResult {
+    pub url: String,
+    /// The server answered the binding request
+    pub reachable: bool,
+    /// The host's address as the server sees it
+    pub mapped_address: Option<String>,
+    /// Relay address allocated on a TURN server, if credentials were given
+    pub relayed_address: Option<String>,
+    /// Round trip of the binding request
+    pub rtt_ms: Option<u64>,
+    /// Why the server is unreachable or the allocation failed
+    pub error: Option<String>,
+}
+
+/// Tests the server at `url`. Fails only if `url` cannot be tested at all;
+/// an unreachable server is a result with `reachable: false`.
+pub async fn probe(url: &str, credentials: Option<(&str, &str)>, timeout: Duration) -> Result<IceProbeResult, IceError> {
+    let ice_url = IceUrl::parse(url)?;
+    if ice_url.scheme == IceScheme::Stuns || ice_url.transport == IceTransport::Tcp {
+        return Err(IceError::Unsupported(format!("{} uses TCP or TLS; only UDP servers can be tested", url)));
+    }
+
+    let mut result = IceProbeResult {
+        url: url.to_string(),
+        reachable: false,
+        mapped_address: None,
+        relayed_address: None,
+        rtt_ms: None,
+        error: None,
+    };
+    let turn_credentials = credentials.filter(|_| ice_url.is_turn());
+    match tokio::time::timeout(timeout, run(&ice_url, turn_credentials, &mut result)).await {
+        Ok(Ok(())) => {},
+        Ok(Err(e)) => result.error = Some(e),
+        Err(_) => result.error = Some(format!("No answer within {} ms", timeout.as_millis())),
+    }
+    Ok(result)
+}
+
+async fn run(url: &IceUrl, credentials: Option<(&str, &str)>, result: &mut IceProbeResult) -> Result<(), String> {
+    let server = resolve(url).await?;
+    let local: SocketAddr = if server.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }.parse().unwrap();
+    let socket = UdpSocket::bind(local).await.map_err(|e| format!("Cannot open a UDP socket: {}", e))?;
+
+    let (username, password) = credentials.unwrap_or_default();
+    let client = Client::new(ClientConfig {
+        stun_serv_addr: server.to_string(),
+        turn_serv_addr: if credentials.is_some() { server.to_string() } else { String::new() },
+        username: username.to_string(),
+        password: password.to_string(),
+        realm: String::new(),
+        software: format!("SmolDesk {}", env!("CARGO_PKG_VERSION")),
+        rto_in_ms: 0,
+        conn: Arc::new(socket),
+        vnet: None,
+    }).await.map_err(|e| e.to_string())?;
+    client.listen().await.map_err(|e| e.to_string())?;
+
+    let outcome = binding_and_allocation(&client, credentials.is_some(), result).await;
+    let _ = client.close().await;
+    outcome
+}
+
+async fn binding_and_allocation(client: &Client, allocate: bool, result: &mut IceProbeResult) -> Result<(), String> {
+    let started = Instant::now();
+    let mapped = client.send_binding_request().await
+        .map_err(|e| format!("Binding request failed: {}", e))?;
+    result.reachable = true;
+    result.rtt_ms = Some(started.elapsed().as_millis() as u64);
+    result.mapped_address = Some(mapped.to_string());
+
+    if allocate {
+        let relay = client.allocate().await
+            .map_err(|e| format!("Allocation failed: {}", e))?;
+        result.relayed_address = relay.local_addr().ok().map(|address| address.to_string());
+        let _ = relay.close().await;
+    }
+    Ok(())
+}
+
+async fn resolve(url: &IceUrl) -> Result<SocketAddr, String> {
+    let mut addresses = tokio::net::lookup_host(url.address()).await
+        .map_err(|e| format!("Cannot resolve {}: {}", url.host, e))?;
+    addresses.next().ok_or_else(|| format!("{} has no address", url.host))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use turn::auth::{generate_auth_key, AuthHandler};
+    use turn::relay::relay_static::RelayAddressGeneratorStatic;
+    use turn::server::config::{ConnConfig, ServerConfig};
+    use turn::server::Server;
+
+    struct FixedCredentials;
+
+    impl AuthHandler for FixedCredentials {
+        fn auth_handle(&self, username: &str, realm: &str, _src_addr: SocketAddr) -> Result<Vec<u8>, turn::Error> {
+            match username {
+                "alice" => Ok(generate_auth_key(username, realm, "s3cret")),
+                _ => Err(turn::Error::ErrNoSuchUser),
+            }
+        }
+    }
+
+    /// TURN server on a loopback port, which also answers binding requests
+    async fn turn_server() -> (Server, String) {
+        let conn = Arc::new(UdpSocket::bind("127.0.0.1:0").await.unwrap());
+        let address = conn.local_addr().unwrap();
+        let server = Server::new(ServerConfig {
+            conn_configs: vec![ConnConfig {
+                conn,
+                relay_addr_generator: Box::new(RelayAddressGeneratorStatic {
+                    relay_address: "127.0.0.1".parse().unwrap(),
+                    address: "0.0.0.0".to_string(),
+                    net: Arc::new(webrtc_util::vnet::net::Net::new(None)),
+                }),
+            }],
+            realm: "smoldesk.test".to_string(),
+            auth_handler: Arc::new(FixedCredentials),
+            channel_bind_timeout: Duration::from_secs(0),
+            alloc_close_notify: None,
+        }).await.unwrap();
+        (server, format!("turn:127.0.0.1:{}", address.port()))
+    }
+
+    #[tokio::test]
+    async fn test_turn_server_reachability_and_allocation() {
+        let (server, url) = turn_server().await;
+
+        let result = probe(&url, Some(("alice", "s3cret")), PROBE_TIMEOUT).await.unwrap();
+        assert!(result.reachable, "{:?}", result.error);
+        assert!(result.mapped_address.as_deref().unwrap().starts_with("127.0.0.1:"));
+        assert!(result.relayed_address.is_some(), "{:?}", result.error);
+        assert!(result.rtt_ms.is_some());
+
+        // A wrong password reaches the server but gets no relay
+        let result = probe(&url, Some(("alice", "wrong")), PROBE_TIMEOUT).await.unwrap();
+        assert!(result.reachable);
+        assert_eq!(result.relayed_address, None);
+        assert!(result.error.unwrap().starts_with("Allocation failed"));
+
+        server.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_silent_server_is_unreachable() {
+        // Bound but never answering
+        let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("stun:127.0.0.1:{}", silent.local_addr().unwrap().port());
+
+        let result = probe(&url, None, Duration::from_millis(300)).await.unwrap();
+        assert!(!result.reachable);
+        assert_eq!(result.error.as_deref(), Some("No answer within 300 ms"));
+
+        assert!(matches!(probe("turns:turn.example.org", None, PROBE_TIMEOUT).await, Err(IceError::Unsupported(_))));
+    }
+}
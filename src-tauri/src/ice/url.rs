@@ -0,0 +1,224 @@
+// src-tauri/src/ice/url.rs - stun:, stuns:, turn: and turns: URLs
+//
+// RFC 7064 and 7065 define the syntax: scheme, host, optional port and, for
+// TURN only, a `transport` parameter. Browsers reject anything else only when
+// the peer connection is created, so the URLs are checked when they are saved
+// and the error names the part that is wrong.
+
+use std::fmt;
+use std::net::Ipv6Addr;
+
+use serde::Serialize;
+
+use crate::ice::IceError;
+
+pub const DEFAULT_PORT: u16 = 3478;
+pub const DEFAULT_TLS_PORT: u16 = 5349;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IceScheme {
+    Stun,
+    Stuns,
+    Turn,
+    Turns,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IceTransport {
+    Udp,
+    Tcp,
+}
+
+/// A parsed STUN or TURN URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IceUrl {
+    pub scheme: IceScheme,
+    /// Host name or IP address, IPv6 without brackets
+    pub host: String,
+    pub port: u16,
+    pub transport: IceTransport,
+}
+
+impl IceUrl {
+    pub fn parse(url: &str) -> Result<Self, IceError> {
+        let invalid = |reason: String| IceError::InvalidUrl { url: url.to_string(), reason };
+
+        let (scheme, rest) = url.split_once(':')
+            .ok_or_else(|| invalid("missing scheme, expected e.g. stun:stun.example.org:3478".to_string()))?;
+        let scheme = match scheme.to_ascii_lowercase().as_str() {
+            "stun" => IceScheme::Stun,
+            "stuns" => IceScheme::Stuns,
+            "turn" => IceScheme::Turn,
+            "turns" => IceScheme::Turns,
+            other => return Err(invalid(format!("unknown scheme '{}' (expected stun, stuns, turn or turns)", other))),
+        };
+        if rest.starts_with("//") {
+            return Err(invalid(format!("'//' does not belong after the scheme, write {}:host:port", scheme.name())));
+        }
+
+        let (authority, query) = match rest.split_once('?') {
+            Some((authority, query)) => (authority, Some(query)),
+            None => (rest, None),
+        };
+        if authority.contains('@') {
+            return Err(invalid("credentials belong in username and credential, not in the URL".to_string()));
+        }
+        if authority.contains('/') {
+            return Err(invalid("a path is not allowed".to_string()));
+        }
+
+        let (host, port) = parse_authority(authority).map_err(invalid)?;
+        let port = port.unwrap_or(match scheme {
+            IceScheme::Stun | IceScheme::Turn => DEFAULT_PORT,
+            IceScheme::Stuns | IceScheme::Turns => DEFAULT_TLS_PORT,
+        });
+
+        let mut transport = match scheme {
+            IceScheme::Stun | IceScheme::Turn => IceTransport::Udp,
+            IceScheme::Stuns | IceScheme::Turns => IceTransport::Tcp,
+        };
+        if let Some(query) = query {
+            if matches!(scheme, IceScheme::Stun | IceScheme::Stuns) {
+                return Err(invalid(format!("{} URLs take no query parameters", scheme.name())));
+            }
+            transport = parse_transport(query).map_err(invalid)?;
+            if scheme == IceScheme::Turns && transport == IceTransport::Udp {
+                return Err(invalid("turns runs over TLS, so transport=udp is not possible".to_string()));
+            }
+        }
+
+        Ok(IceUrl { scheme, host, port, transport })
+    }
+
+    pub fn is_turn(&self) -> bool {
+        matches!(self.scheme, IceScheme::Turn | IceScheme::Turns)
+    }
+
+    /// `host:port` for resolving, with brackets around IPv6 addresses
+    pub fn address(&self) -> String {
+        if self.host.contains(':') {
+            format!("[{}]:{}", self.host, self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+}
+
+impl IceScheme {
+    fn name(&self) -> &'static str {
+        match self {
+            IceScheme::Stun => "stun",
+            IceScheme::Stuns => "stuns",
+            IceScheme::Turn => "turn",
+            IceScheme::Turns => "turns",
+        }
+    }
+}
+
+impl fmt::Display for IceUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.scheme.name(), self.address())?;
+        if self.is_turn() {
+            let transport = match self.transport {
+                IceTransport::Udp => "udp",
+                IceTransport::Tcp => "tcp",
+            };
+            write!(f, "?transport={}", transport)?;
+        }
+        Ok(())
+    }
+}
+
+/// Host and optional port of `host`, `host:port`, `[v6]` or `[v6]:port`
+fn parse_authority(authority: &str) -> Result<(String, Option<u16>), String> {
+    if let Some(bracketed) = authority.strip_prefix('[') {
+        let (address, rest) = bracketed.split_once(']')
+            .ok_or_else(|| "IPv6 address is missing the closing ']'".to_string())?;
+        address.parse::<Ipv6Addr>()
+            .map_err(|_| format!("'{}' is not a valid IPv6 address", address))?;
+        let port = match rest {
+            "" => None,
+            rest => match rest.strip_prefix(':') {
+                Some(port) => Some(parse_port(port)?),
+                None => return Err(format!("unexpected '{}' after the IPv6 address", rest)),
+            },
+        };
+        return Ok((address.to_string(), port));
+    }
+
+    let (host, port) = match authority.split_once(':') {
+        Some((host, port)) => (host, Some(parse_port(port)?)),
+        None => (authority, None),
+    };
+    if host.is_empty() {
+        return Err("host is missing".to_string());
+    }
+    if let Some(invalid) = host.chars().find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '.')) {
+        return Err(format!("invalid character '{}' in host '{}'", invalid, host));
+    }
+    Ok((host.to_string(), port))
+}
+
+fn parse_port(port: &str) -> Result<u16, String> {
+    match port.parse::<u16>() {
+        Ok(port) if port > 0 => Ok(port),
+        _ => Err(format!("port must be a number between 1 and 65535, not '{}'", port)),
+    }
+}
+
+fn parse_transport(query: &str) -> Result<IceTransport, String> {
+    let (key, value) = query.split_once('=').unwrap_or((query, ""));
+    if key != "transport" {
+        return Err(format!("unknown query parameter '{}' (only transport is allowed)", key));
+    }
+    match value {
+        "udp" => Ok(IceTransport::Udp),
+        "tcp" => Ok(IceTransport::Tcp),
+        other => Err(format!("transport must be udp or tcp, not '{}'", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reason(url: &str) -> String {
+        match IceUrl::parse(url) {
+            Err(IceError::InvalidUrl { reason, .. }) => reason,
+            other => panic!("{} parsed as {:?}", url, other),
+        }
+    }
+
+    #[test]
+    fn test_valid_urls() {
+        let url = IceUrl::parse("stun:stun.l.google.com:19302").unwrap();
+        assert_eq!((url.scheme, url.host.as_str(), url.port, url.transport), (IceScheme::Stun, "stun.l.google.com", 19302, IceTransport::Udp));
+
+        let url = IceUrl::parse("turns:turn.example.org").unwrap();
+        assert_eq!((url.port, url.transport), (DEFAULT_TLS_PORT, IceTransport::Tcp));
+
+        let url = IceUrl::parse("turn:[2001:db8::1]:3479?transport=tcp").unwrap();
+        assert_eq!((url.host.as_str(), url.port, url.transport), ("2001:db8::1", 3479, IceTransport::Tcp));
+        assert_eq!(url.address(), "[2001:db8::1]:3479");
+        assert_eq!(url.to_string(), "turn:[2001:db8::1]:3479?transport=tcp");
+    }
+
+    #[test]
+    fn test_malformed_urls_are_rejected_precisely() {
+        assert_eq!(reason("stun.example.org"), "missing scheme, expected e.g. stun:stun.example.org:3478");
+        assert_eq!(reason("http://turn.example.org"), "unknown scheme 'http' (expected stun, stuns, turn or turns)");
+        assert_eq!(reason("turn://turn.example.org"), "'//' does not belong after the scheme, write turn:host:port");
+        assert_eq!(reason("turn:user:pass@turn.example.org"), "credentials belong in username and credential, not in the URL");
+        assert_eq!(reason("stun:"), "host is missing");
+        assert_eq!(reason("stun:stun_server:3478"), "invalid character '_' in host 'stun_server'");
+        assert_eq!(reason("turn:turn.example.org:99999"), "port must be a number between 1 and 65535, not '99999'");
+        assert_eq!(reason("turn:turn.example.org:0"), "port must be a number between 1 and 65535, not '0'");
+        assert_eq!(reason("turn:[2001:db8::1:3478"), "IPv6 address is missing the closing ']'");
+        assert_eq!(reason("stun:stun.example.org?transport=udp"), "stun URLs take no query parameters");
+        assert_eq!(reason("turn:turn.example.org?transport=sctp"), "transport must be udp or tcp, not 'sctp'");
+        assert_eq!(reason("turn:turn.example.org?proto=udp"), "unknown query parameter 'proto' (only transport is allowed)");
+        assert_eq!(reason("turns:turn.example.org?transport=udp"), "turns runs over TLS, so transport=udp is not possible");
+    }
+}
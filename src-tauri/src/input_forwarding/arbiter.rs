@@ -0,0 +1,310 @@
+// arbiter.rs - Decides which connected peer may send input
+//
+// Only the peer holding control has its input forwarded. Control returns to
+// the host (no holder) when the holder releases it, disconnects or stays idle
+// longer than the configured timeout.
+
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// How control is handed out between peers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ControlPolicy {
+    /// The first peer to send input or request control keeps it until released
+    FirstComeHold,
+    /// Peers ask for control and the host grants it
+    HostGrants,
+    /// Every peer's input is forwarded (no arbitration)
+    FreeForAll,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlConfig {
+    pub policy: ControlPolicy,
+    pub idle_timeout_ms: Option<u64>, // Return control to the host after this long without input (None = never)
+}
+
+impl Default for ControlConfig {
+    fn default() -> Self {
+        ControlConfig {
+            policy: ControlPolicy::FreeForAll,
+            idle_timeout_ms: Some(60_000),
+        }
+    }
+}
+
+/// Outcome of a control request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum ControlDecision {
+    Granted,
+    /// Waiting for the host (HostGrants)
+    Pending,
+    /// Another peer holds control
+    Denied { holder: String },
+}
+
+/// Why a peer's input event was dropped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ControlDenied {
+    pub peer_id: Option<String>,
+    pub holder: Option<String>,
+}
+
+/// Current holder and open requests, as shown to the host
+#[derive(Debug, Clone, Serialize)]
+pub struct ControlState {
+    pub policy: ControlPolicy,
+    pub holder: Option<String>,
+    pub pending_requests: Vec<String>,
+}
+
+pub struct InputArbiter {
+    config: ControlConfig,
+    holder: Option<String>,
+    last_input: Instant,
+    pending_requests: BTreeSet<String>,
+}
+
+impl InputArbiter {
+    pub fn new(config: ControlConfig) -> Self {
+        InputArbiter {
+            config,
+            holder: None,
+            last_input: Instant::now(),
+            pending_requests: BTreeSet::new(),
+        }
+    }
+
+    pub fn config(&self) -> &ControlConfig {
+        &self.config
+    }
+
+    /// Applies a new configuration; switching policy returns control to the host
+    pub fn set_config(&mut self, config: ControlConfig) {
+        if config.policy != self.config.policy {
+            self.holder = None;
+            self.pending_requests.clear();
+        }
+        self.config = config;
+    }
+
+    pub fn holder(&self) -> Option<&str> {
+        self.holder.as_deref()
+    }
+
+    pub fn state(&self) -> ControlState {
+        ControlState {
+            policy: self.config.policy,
+            holder: self.holder.clone(),
+            pending_requests: self.pending_requests.iter().cloned().collect(),
+        }
+    }
+
+    /// A peer asks for control
+    pub fn request_control(&mut self, peer_id: &str, now: Instant) -> ControlDecision {
+        self.expire_idle(now);
+
+        if self.holder.as_deref() == Some(peer_id) {
+            return ControlDecision::Granted;
+        }
+        match self.config.policy {
+            ControlPolicy::FreeForAll => ControlDecision::Granted,
+            ControlPolicy::HostGrants => {
+                self.pending_requests.insert(peer_id.to_string());
+                ControlDecision::Pending
+            },
+            ControlPolicy::FirstComeHold => match &self.holder {
+                Some(holder) => ControlDecision::Denied { holder: holder.clone() },
+                None => {
+                    self.take(peer_id, now);
+                    ControlDecision::Granted
+                },
+            },
+        }
+    }
+
+    /// The holder gives control back to the host. Returns false if the peer did not hold it.
+    pub fn release_control(&mut self, peer_id: &str) -> bool {
+        self.pending_requests.remove(peer_id);
+        if self.holder.as_deref() == Some(peer_id) {
+            self.holder = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// The host hands control to a peer, or takes it back with None
+    pub fn grant_control(&mut self, peer_id: Option<&str>, now: Instant) {
+        match peer_id {
+            Some(peer_id) => self.take(peer_id, now),
+            None => self.holder = None,
+        }
+    }
+
+    /// Checks whether an input event from `peer_id` may be forwarded
+    pub fn check_input(&mut self, peer_id: Option<&str>, now: Instant) -> Result<(), ControlDenied> {
+        if self.config.policy == ControlPolicy::FreeForAll {
+            return Ok(());
+        }
+        self.expire_idle(now);
+
+        // Without a peer ID the sender cannot be arbitrated
+        let peer_id = match peer_id {
+            Some(peer_id) => peer_id,
+            None => return Err(ControlDenied { peer_id: None, holder: self.holder.clone() }),
+        };
+
+        match self.holder.as_deref() {
+            Some(holder) if holder == peer_id => {
+                self.last_input = now;
+                Ok(())
+            },
+            None if self.config.policy == ControlPolicy::FirstComeHold => {
+                self.take(peer_id, now);
+                Ok(())
+            },
+            holder => Err(ControlDenied {
+                peer_id: Some(peer_id.to_string()),
+                holder: holder.map(str::to_string),
+            }),
+        }
+    }
+
+    /// Control always returns to the host when its holder disconnects.
+    /// Returns true if the holder changed.
+    pub fn peer_disconnected(&mut self, peer_id: &str) -> bool {
+        self.release_control(peer_id)
+    }
+
+    /// Returns control to the host once the holder was idle too long.
+    /// Returns true if the holder changed.
+    pub fn expire_idle(&mut self, now: Instant) -> bool {
+        let timeout = match self.config.idle_timeout_ms {
+            Some(ms) => Duration::from_millis(ms),
+            None => return false,
+        };
+        if self.holder.is_some() && now.saturating_duration_since(self.last_input) >= timeout {
+            self.holder = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn take(&mut self, peer_id: &str, now: Instant) {
+        self.pending_requests.remove(peer_id);
+        self.holder = Some(peer_id.to_string());
+        self.last_input = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arbiter(policy: ControlPolicy) -> InputArbiter {
+        InputArbiter::new(ControlConfig { policy, idle_timeout_ms: Some(10_000) })
+    }
+
+    #[test]
+    fn test_free_for_all_forwards_everyone() {
+        let mut arbiter = arbiter(ControlPolicy::FreeForAll);
+        let now = Instant::now();
+
+        assert!(arbiter.check_input(Some("a"), now).is_ok());
+        assert!(arbiter.check_input(Some("b"), now).is_ok());
+        assert!(arbiter.check_input(None, now).is_ok());
+        assert_eq!(arbiter.holder(), None);
+    }
+
+    #[test]
+    fn test_first_come_hold_takeover() {
+        let mut arbiter = arbiter(ControlPolicy::FirstComeHold);
+        let now = Instant::now();
+
+        // First input takes control, the second peer is locked out
+        assert!(arbiter.check_input(Some("a"), now).is_ok());
+        assert_eq!(arbiter.holder(), Some("a"));
+        assert_eq!(arbiter.check_input(Some("b"), now), Err(ControlDenied {
+            peer_id: Some("b".to_string()),
+            holder: Some("a".to_string()),
+        }));
+        assert_eq!(arbiter.request_control("b", now), ControlDecision::Denied { holder: "a".to_string() });
+
+        // Only the holder can release
+        assert!(!arbiter.release_control("b"));
+        assert!(arbiter.release_control("a"));
+        assert_eq!(arbiter.request_control("b", now), ControlDecision::Granted);
+        assert!(arbiter.check_input(Some("a"), now).is_err());
+    }
+
+    #[test]
+    fn test_unidentified_input_is_denied() {
+        let mut arbiter = arbiter(ControlPolicy::FirstComeHold);
+        assert!(arbiter.check_input(None, Instant::now()).is_err());
+        assert_eq!(arbiter.holder(), None);
+    }
+
+    #[test]
+    fn test_host_grants() {
+        let mut arbiter = arbiter(ControlPolicy::HostGrants);
+        let now = Instant::now();
+
+        assert!(arbiter.check_input(Some("a"), now).is_err());
+        assert_eq!(arbiter.request_control("a", now), ControlDecision::Pending);
+        assert_eq!(arbiter.request_control("b", now), ControlDecision::Pending);
+        assert_eq!(arbiter.state().pending_requests, vec!["a", "b"]);
+
+        arbiter.grant_control(Some("b"), now);
+        assert_eq!(arbiter.holder(), Some("b"));
+        assert_eq!(arbiter.state().pending_requests, vec!["a"]);
+        assert!(arbiter.check_input(Some("b"), now).is_ok());
+        assert!(arbiter.check_input(Some("a"), now).is_err());
+
+        // Host takes control back
+        arbiter.grant_control(None, now);
+        assert!(arbiter.check_input(Some("b"), now).is_err());
+    }
+
+    #[test]
+    fn test_idle_timeout_returns_control_to_host() {
+        let mut arbiter = arbiter(ControlPolicy::FirstComeHold);
+        let start = Instant::now();
+
+        assert!(arbiter.check_input(Some("a"), start).is_ok());
+        assert!(!arbiter.expire_idle(start + Duration::from_secs(5)));
+        assert!(arbiter.check_input(Some("a"), start + Duration::from_secs(5)).is_ok());
+
+        // Input restarted the timer, so 14s after start is only 9s idle
+        assert!(!arbiter.expire_idle(start + Duration::from_secs(14)));
+        assert!(arbiter.expire_idle(start + Duration::from_secs(15)));
+        assert_eq!(arbiter.holder(), None);
+        assert!(arbiter.check_input(Some("b"), start + Duration::from_secs(16)).is_ok());
+    }
+
+    #[test]
+    fn test_disconnect_returns_control_to_host() {
+        let mut arbiter = arbiter(ControlPolicy::HostGrants);
+        let now = Instant::now();
+
+        arbiter.request_control("b", now);
+        arbiter.grant_control(Some("a"), now);
+        assert!(!arbiter.peer_disconnected("b"));
+        assert!(arbiter.state().pending_requests.is_empty());
+        assert!(arbiter.peer_disconnected("a"));
+        assert_eq!(arbiter.holder(), None);
+    }
+
+    #[test]
+    fn test_policy_change_resets_holder() {
+        let mut arbiter = arbiter(ControlPolicy::FirstComeHold);
+        let now = Instant::now();
+        arbiter.check_input(Some("a"), now).unwrap();
+
+        arbiter.set_config(ControlConfig { policy: ControlPolicy::HostGrants, idle_timeout_ms: None });
+        assert_eq!(arbiter.holder(), None);
+    }
+}
@@ -0,0 +1,279 @@
+// blocklist.rs - Drops remote input aimed at excluded applications
+//
+// Patterns are matched against the focused window's class (X11) or app-id
+// (Wayland compositors with IPC). Where the focused window cannot be queried,
+// e.g. GNOME or KDE on Wayland, input is let through: blocking everything would
+// make the session unusable without protecting anything specific.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::command_runner::{CommandRunner, SystemCommandRunner};
+use crate::input_forwarding::types::{InputEvent, InputEventType};
+use crate::logging::TARGET_INPUT;
+
+/// How long a focused-window lookup is reused before querying again
+pub const FOCUS_CACHE_TTL: Duration = Duration::from_millis(200);
+
+/// Returns the class or app-id of the focused window
+pub type FocusSource = Box<dyn Fn() -> Option<String> + Send + Sync>;
+
+/// Sent with `blocked_input` when an event was dropped
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockedInput {
+    pub app: String,
+    pub pattern: String,
+    pub event_type: InputEventType,
+}
+
+/// Case-insensitive glob match supporting `*` and `?`
+pub fn matches_pattern(pattern: &str, app: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let app: Vec<char> = app.to_lowercase().chars().collect();
+
+    // Iterative matching with backtracking to the last `*`
+    let (mut p, mut a) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while a < app.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == app[a]) {
+            p += 1;
+            a += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, a));
+            p += 1;
+        } else if let Some((star_p, star_a)) = star {
+            p = star_p + 1;
+            a = star_a + 1;
+            star = Some((star_p, star_a + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Whether an event can type or click into the focused window
+///
+/// Releases always pass, otherwise a key pressed before the focus moved to a
+/// blocked app would stay stuck down.
+fn is_blockable(event: &InputEvent) -> bool {
+    match event.event_type {
+        InputEventType::KeyPress | InputEventType::SpecialCommand | InputEventType::TouchDown => true,
+        InputEventType::MouseButton => event.is_pressed.unwrap_or(true),
+        _ => false,
+    }
+}
+
+/// Application patterns that never receive remote keys or clicks
+pub struct InputBlocklist {
+    patterns: Mutex<Vec<String>>,
+    focus_source: FocusSource,
+    ttl: Duration,
+    cached_focus: Mutex<Option<(Instant, Option<String>)>>,
+}
+
+impl InputBlocklist {
+    pub fn new() -> Self {
+        Self::with_focus_source(Box::new(query_focused_app), FOCUS_CACHE_TTL)
+    }
+
+    pub fn with_focus_source(focus_source: FocusSource, ttl: Duration) -> Self {
+        InputBlocklist {
+            patterns: Mutex::new(Vec::new()),
+            focus_source,
+            ttl,
+            cached_focus: Mutex::new(None),
+        }
+    }
+
+    pub fn patterns(&self) -> Vec<String> {
+        self.patterns.lock().unwrap().clone()
+    }
+
+    pub fn set_patterns(&self, patterns: Vec<String>) {
+        let patterns = patterns.into_iter()
+            .map(|pattern| pattern.trim().to_string())
+            .filter(|pattern| !pattern.is_empty())
+            .collect();
+        *self.patterns.lock().unwrap() = patterns;
+    }
+
+    /// Returns why the event must be dropped, or None if it may be forwarded
+    pub fn check(&self, event: &InputEvent) -> Option<BlockedInput> {
+        if !is_blockable(event) {
+            return None;
+        }
+        let patterns = self.patterns.lock().unwrap().clone();
+        if patterns.is_empty() {
+            return None;
+        }
+
+        let app = self.focused_app()?;
+        let pattern = patterns.into_iter().find(|pattern| matches_pattern(pattern, &app))?;
+        log::debug!(target: TARGET_INPUT, "Blocked {:?} for {} (pattern {})", event.event_type, app, pattern);
+        Some(BlockedInput {
+            app,
+            pattern,
+            event_type: event.event_type.clone(),
+        })
+    }
+
+    fn focused_app(&self) -> Option<String> {
+        let mut cached = self.cached_focus.lock().unwrap();
+        if let Some((queried_at, app)) = &*cached {
+            if queried_at.elapsed() < self.ttl {
+                return app.clone();
+            }
+        }
+        let app = (self.focus_source)();
+        *cached = Some((Instant::now(), app.clone()));
+        app
+    }
+}
+
+/// Queries the focused window via compositor IPC or xdotool
+pub fn query_focused_app() -> Option<String> {
+    if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+        let output = run("hyprctl", &["activewindow", "-j"])?;
+        let window: serde_json::Value = serde_json::from_str(&output).ok()?;
+        return non_empty(window.get("class")?.as_str()?);
+    }
+    if std::env::var_os("SWAYSOCK").is_some() {
+        let output = run("swaymsg", &["-t", "get_tree"])?;
+        let tree: serde_json::Value = serde_json::from_str(&output).ok()?;
+        return focused_sway_app(&tree);
+    }
+    if std::env::var_os("DISPLAY").is_some() && std::env::var_os("WAYLAND_DISPLAY").is_none() {
+        return non_empty(run("xdotool", &["getactivewindow", "getwindowclassname"])?.trim());
+    }
+    None
+}
+
+/// App-id (native Wayland) or X11 class (XWayland) of the focused sway node
+fn focused_sway_app(node: &serde_json::Value) -> Option<String> {
+    if node.get("focused").and_then(|f| f.as_bool()) == Some(true) {
+        let app_id = node.get("app_id").and_then(|id| id.as_str());
+        let class = node.pointer("/window_properties/class").and_then(|c| c.as_str());
+        return app_id.or(class).and_then(non_empty);
+    }
+    ["nodes", "floating_nodes"].iter()
+        .filter_map(|key| node.get(key)?.as_array())
+        .flatten()
+        .find_map(focused_sway_app)
+}
+
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = SystemCommandRunner.command(program).args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn event(event_type: InputEventType, is_pressed: Option<bool>) -> InputEvent {
+        InputEvent {
+            event_type,
+            x: None,
+            y: None,
+            button: None,
+            key_code: Some(30),
+            modifiers: None,
+            is_pressed,
+            delta_x: None,
+            delta_y: None,
+            monitor_index: None,
+            gesture: None,
+            gesture_direction: None,
+            gesture_magnitude: None,
+            special_command: None,
+            ping_id: None,
+            client_timestamp: None,
+            touch: None,
+        }
+    }
+
+    fn blocklist(app: &'static str) -> (InputBlocklist, Arc<AtomicUsize>) {
+        let queries = Arc::new(AtomicUsize::new(0));
+        let counter = queries.clone();
+        let blocklist = InputBlocklist::with_focus_source(Box::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            Some(app.to_string())
+        }), Duration::from_secs(60));
+        (blocklist, queries)
+    }
+
+    #[test]
+    fn test_pattern_matching() {
+        assert!(matches_pattern("KeePassXC", "keepassxc"));
+        assert!(matches_pattern("*terminal*", "gnome-terminal-server"));
+        assert!(matches_pattern("org.gnome.*", "org.gnome.Terminal"));
+        assert!(matches_pattern("xterm?", "xterm2"));
+        assert!(matches_pattern("*", "anything"));
+        assert!(matches_pattern("a*b*c", "aXXbYYc"));
+        assert!(!matches_pattern("a*b*c", "aXXbYY"));
+        assert!(!matches_pattern("xterm?", "xterm"));
+        assert!(!matches_pattern("firefox", "firefox-esr"));
+        assert!(!matches_pattern("", "firefox"));
+    }
+
+    #[test]
+    fn test_blocks_keys_and_clicks_only() {
+        let (blocklist, _) = blocklist("org.keepassxc.KeePassXC");
+        blocklist.set_patterns(vec!["*keepass*".to_string()]);
+
+        let blocked = blocklist.check(&event(InputEventType::KeyPress, Some(true))).unwrap();
+        assert_eq!(blocked.app, "org.keepassxc.KeePassXC");
+        assert_eq!(blocked.pattern, "*keepass*");
+        assert!(blocklist.check(&event(InputEventType::MouseButton, Some(true))).is_some());
+
+        assert!(blocklist.check(&event(InputEventType::KeyRelease, Some(false))).is_none());
+        assert!(blocklist.check(&event(InputEventType::MouseButton, Some(false))).is_none());
+        assert!(blocklist.check(&event(InputEventType::MouseMove, None)).is_none());
+    }
+
+    #[test]
+    fn test_empty_blocklist_skips_lookup() {
+        let (blocklist, queries) = blocklist("Alacritty");
+        blocklist.set_patterns(vec!["  ".to_string()]);
+
+        assert!(blocklist.check(&event(InputEventType::KeyPress, Some(true))).is_none());
+        assert_eq!(queries.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_focus_lookup_is_cached() {
+        let (blocklist, queries) = blocklist("firefox");
+        blocklist.set_patterns(vec!["Alacritty".to_string()]);
+
+        for _ in 0..10 {
+            assert!(blocklist.check(&event(InputEventType::KeyPress, Some(true))).is_none());
+        }
+        assert_eq!(queries.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_focused_sway_app() {
+        let tree: serde_json::Value = serde_json::from_str(r#"{
+            "focused": false,
+            "nodes": [{
+                "focused": false,
+                "nodes": [{"focused": false, "app_id": "firefox", "nodes": []}],
+                "floating_nodes": [{"focused": true, "app_id": null,
+                    "window_properties": {"class": "KeePassXC"}, "nodes": []}]
+            }]
+        }"#).unwrap();
+        assert_eq!(focused_sway_app(&tree), Some("KeePassXC".to_string()));
+    }
+}
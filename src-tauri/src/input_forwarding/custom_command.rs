@@ -0,0 +1,256 @@
+// custom_command.rs - Validated SpecialCommand::Custom commands
+//
+// Custom commands arrive as strings from the remote peer. They are never handed
+// to a shell: the string is split into words with shell-like quoting, checked
+// against a small set of subcommands and passed to xdotool/ydotool as argv.
+
+use std::collections::BTreeSet;
+
+use crate::input_forwarding::error::InputForwardingError;
+
+/// Most key combinations a single custom command may press
+const MAX_KEY_COMBINATIONS: usize = 16;
+
+/// Longest text a single custom command may type
+const MAX_TYPE_LENGTH: usize = 1024;
+
+/// A custom command restricted to the allowed subcommands
+#[derive(Debug, Clone, PartialEq)]
+pub enum CustomCommand {
+    /// Press key combinations in sequence, e.g. `key ctrl+alt+t Return`
+    Key(Vec<String>),
+    /// Type literal text, e.g. `type "hello world"`
+    Type(String),
+}
+
+impl CustomCommand {
+    /// Parses and validates a custom command string
+    pub fn parse(command: &str) -> Result<Self, InputForwardingError> {
+        let words = split_words(command)?;
+        let (subcommand, args) = words.split_first()
+            .ok_or_else(|| invalid("empty command"))?;
+
+        match subcommand.as_str() {
+            "key" => {
+                if args.is_empty() || args.len() > MAX_KEY_COMBINATIONS {
+                    return Err(invalid(&format!("key needs 1 to {} key combinations", MAX_KEY_COMBINATIONS)));
+                }
+                for combination in args {
+                    if !is_valid_key_combination(combination) {
+                        return Err(invalid(&format!("invalid key combination '{}'", combination)));
+                    }
+                }
+                Ok(CustomCommand::Key(args.to_vec()))
+            },
+            "type" => {
+                let text = args.join(" ");
+                if text.is_empty() || text.chars().count() > MAX_TYPE_LENGTH {
+                    return Err(invalid(&format!("type needs 1 to {} characters", MAX_TYPE_LENGTH)));
+                }
+                if text.chars().any(|c| c.is_control() && c != '\n' && c != '\t') {
+                    return Err(invalid("type text contains control characters"));
+                }
+                Ok(CustomCommand::Type(text))
+            },
+            other => Err(invalid(&format!("unsupported subcommand '{}'", other))),
+        }
+    }
+
+    /// Arguments for `xdotool` or `ydotool`, which share the `key` / `type` syntax
+    ///
+    /// `--` keeps typed text that starts with a dash from being read as an option.
+    pub fn args(&self) -> Vec<String> {
+        match self {
+            CustomCommand::Key(combinations) => {
+                std::iter::once("key".to_string()).chain(combinations.iter().cloned()).collect()
+            },
+            CustomCommand::Type(text) => vec!["type".to_string(), "--".to_string(), text.clone()],
+        }
+    }
+}
+
+/// The keys of a combination, independent of how they are spelled: case,
+/// order and modifier aliases such as `Control_L` or `Super_R` do not matter,
+/// so `alt+Control_L+Delete` equals `ctrl+alt+delete`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyChord(BTreeSet<String>);
+
+impl KeyChord {
+    pub fn parse(combination: &str) -> Result<Self, InputForwardingError> {
+        if !is_valid_key_combination(combination) {
+            return Err(invalid(&format!("invalid key combination '{}'", combination)));
+        }
+        Ok(KeyChord(combination.split('+').map(canonical_key).collect()))
+    }
+
+    /// Whether pressing this chord presses all keys of `other`, e.g.
+    /// `ctrl+alt+shift+Delete` covers `ctrl+alt+Delete`
+    pub fn covers(&self, other: &KeyChord) -> bool {
+        other.0.is_subset(&self.0)
+    }
+}
+
+/// Lowercased keysym with the left/right variants and aliases of a modifier
+/// folded into one name. X puts Meta on the same modifier as Alt.
+fn canonical_key(key: &str) -> String {
+    let key = key.to_ascii_lowercase();
+    let modifier = match key.as_str() {
+        "ctrl" | "control" | "control_l" | "control_r" => "ctrl",
+        "alt" | "alt_l" | "alt_r" | "meta" | "meta_l" | "meta_r" => "alt",
+        "shift" | "shift_l" | "shift_r" => "shift",
+        "super" | "super_l" | "super_r" | "hyper" | "hyper_l" | "hyper_r" => "super",
+        _ => return key,
+    };
+    modifier.to_string()
+}
+
+fn invalid(reason: &str) -> InputForwardingError {
+    InputForwardingError::UnsupportedEvent(format!("Invalid custom command: {}", reason))
+}
+
+/// Keysym names joined with `+`, e.g. `ctrl+alt+Delete` or `F5`
+fn is_valid_key_combination(combination: &str) -> bool {
+    !combination.is_empty()
+        && combination.split('+').all(|key| {
+            !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+}
+
+/// Splits a string into words like a POSIX shell, without any expansion
+///
+/// Single quotes are literal, double quotes honour `\"` and `\\`, and a
+/// backslash outside quotes escapes the next character.
+fn split_words(input: &str) -> Result<Vec<String>, InputForwardingError> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut in_word = false;
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err(invalid("unterminated single quote")),
+                    }
+                }
+            },
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => current.push(c),
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            },
+                            None => return Err(invalid("unterminated double quote")),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err(invalid("unterminated double quote")),
+                    }
+                }
+            },
+            '\\' => {
+                in_word = true;
+                match chars.next() {
+                    Some(c) => current.push(c),
+                    None => return Err(invalid("trailing backslash")),
+                }
+            },
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            },
+            c => {
+                in_word = true;
+                current.push(c);
+            },
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_words_quoting() {
+        assert_eq!(split_words("type 'a b' \"c \\\"d\\\"\" e\\ f").unwrap(),
+            vec!["type", "a b", "c \"d\"", "e f"]);
+        assert_eq!(split_words("  key   a  ").unwrap(), vec!["key", "a"]);
+        assert_eq!(split_words("type ''").unwrap(), vec!["type", ""]);
+        assert!(split_words("type 'open").is_err());
+        assert!(split_words("type \"open").is_err());
+        assert!(split_words("type open\\").is_err());
+    }
+
+    #[test]
+    fn test_parse_allowed_commands() {
+        assert_eq!(CustomCommand::parse("key ctrl+alt+t Return").unwrap(),
+            CustomCommand::Key(vec!["ctrl+alt+t".to_string(), "Return".to_string()]));
+        assert_eq!(CustomCommand::parse("type \"hello world\"").unwrap(),
+            CustomCommand::Type("hello world".to_string()));
+        assert_eq!(CustomCommand::parse("type hello world").unwrap(),
+            CustomCommand::Type("hello world".to_string()));
+    }
+
+    #[test]
+    fn test_shell_metacharacters_are_rejected_in_keys() {
+        for command in [
+            "key a; rm -rf ~",
+            "key a && reboot",
+            "key a|sh",
+            "key $(id)",
+            "key `id`",
+            "key a>/tmp/x",
+            "key 'a;id'",
+        ] {
+            assert!(CustomCommand::parse(command).is_err(), "accepted: {}", command);
+        }
+    }
+
+    #[test]
+    fn test_shell_metacharacters_stay_literal_in_text() {
+        let command = CustomCommand::parse("type \"$(rm -rf ~); `id` | sh\"").unwrap();
+        assert_eq!(command.args(), vec!["type", "--", "$(rm -rf ~); `id` | sh"]);
+
+        // Text that looks like an option is still typed, not interpreted
+        let command = CustomCommand::parse("type -- --help").unwrap();
+        assert_eq!(command.args(), vec!["type", "--", "-- --help"]);
+    }
+
+    #[test]
+    fn test_other_subcommands_are_rejected() {
+        for command in ["", "exec sh", "mousemove 0 0", "search --name x windowkill", "KEY a"] {
+            assert!(CustomCommand::parse(command).is_err(), "accepted: {}", command);
+        }
+        assert!(CustomCommand::parse("key").is_err());
+        assert!(CustomCommand::parse("type ''").is_err());
+        assert!(CustomCommand::parse("type \"a\u{1b}[2J\"").is_err());
+    }
+
+    #[test]
+    fn test_key_chords_ignore_spelling() {
+        let chord = |combination: &str| KeyChord::parse(combination).unwrap();
+        assert_eq!(chord("ctrl+alt+Delete"), chord("alt+Control_L+delete"));
+        assert_eq!(chord("super+l"), chord("Super_L+L"));
+        assert_eq!(chord("ctrl+alt+BackSpace"), chord("Control_R+Meta_L+BackSpace"));
+        assert_ne!(chord("ctrl+alt+Delete"), chord("ctrl+alt+KP_Delete"));
+
+        assert!(chord("ctrl+alt+shift+Delete").covers(&chord("ctrl+alt+Delete")));
+        assert!(!chord("ctrl+Delete").covers(&chord("ctrl+alt+Delete")));
+        assert!(KeyChord::parse("ctrl++Delete").is_err());
+    }
+}
@@ -11,6 +11,8 @@ pub enum InputForwardingError {
     UnsupportedEvent(String),
     PermissionDenied(String),
     MonitorConfigError(String),
+    MacroError(String),
+    HotkeyError(String),
 }
 
 impl fmt::Display for InputForwardingError {
@@ -21,6 +23,8 @@ impl fmt::Display for InputForwardingError {
             InputForwardingError::UnsupportedEvent(msg) => write!(f, "Unsupported event: {}", msg),
             InputForwardingError::PermissionDenied(msg) => write!(f, "Permission denied: {}", msg),
             InputForwardingError::MonitorConfigError(msg) => write!(f, "Monitor configuration error: {}", msg),
+            InputForwardingError::MacroError(msg) => write!(f, "Input macro error: {}", msg),
+            InputForwardingError::HotkeyError(msg) => write!(f, "Hotkey error: {}", msg),
         }
     }
 }
@@ -7,6 +7,10 @@ use crate::input_forwarding::error::InputForwardingError;
 use crate::input_forwarding::forwarder_trait::ImprovedInputForwarder;
 use crate::input_forwarding::x11::ImprovedX11InputForwarder;
 use crate::input_forwarding::wayland::ImprovedWaylandInputForwarder;
+use crate::input_forwarding::portal::PortalInputForwarder;
+#[cfg(feature = "wayland-support")]
+use crate::input_forwarding::virtual_input::VirtualInputForwarder;
+use crate::logging::TARGET_INPUT;
 
 /// Create the appropriate input forwarder based on display server
 /// 
@@ -20,7 +24,10 @@ use crate::input_forwarding::wayland::ImprovedWaylandInputForwarder;
 /// 
 /// # Returns
 /// 
-/// A boxed trait object implementing `ImprovedInputForwarder`
+/// A boxed trait object implementing `ImprovedInputForwarder`. On Wayland the
+/// RemoteDesktop portal is preferred, then the virtual keyboard and pointer
+/// protocols of wlroots compositors; ydotool is the fallback where neither is
+/// available.
 /// 
 /// # Errors
 /// 
@@ -37,6 +44,15 @@ pub fn create_improved_input_forwarder(
             Ok(Box::new(forwarder))
         },
         DisplayServer::Wayland => {
+            match PortalInputForwarder::new() {
+                Ok(forwarder) => return Ok(Box::new(forwarder)),
+                Err(e) => log::info!(target: TARGET_INPUT, "RemoteDesktop portal unavailable, trying virtual input devices: {}", e),
+            }
+            #[cfg(feature = "wayland-support")]
+            match VirtualInputForwarder::new() {
+                Ok(forwarder) => return Ok(Box::new(forwarder)),
+                Err(e) => log::info!(target: TARGET_INPUT, "Virtual input devices unavailable, falling back to ydotool: {}", e),
+            }
             let forwarder = ImprovedWaylandInputForwarder::new()?;
             Ok(Box::new(forwarder))
         },
@@ -94,12 +110,13 @@ pub fn create_configured_input_forwarder(
     config: &InputForwardingConfig,
     display_server: Option<DisplayServer>
 ) -> Result<Box<dyn ImprovedInputForwarder>, InputForwardingError> {
-    let mut forwarder = create_improved_input_forwarder(display_server)?;
+    let forwarder = create_improved_input_forwarder(display_server)?;
     
     // Apply configuration
     if config.enable_multi_monitor {
         forwarder.configure_monitors(config.monitors.clone())?;
     }
+    forwarder.set_keyboard_layout(&config.keyboard_layout)?;
     
     Ok(forwarder)
 }
@@ -1,7 +1,26 @@
 // forwarder_trait.rs - Common interface for input forwarders
 
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
 use crate::input_forwarding::types::*;
 use crate::input_forwarding::error::InputForwardingError;
+use crate::input_forwarding::shortcuts::ShortcutPolicy;
+use crate::input_forwarding::preview::InputMode;
+use crate::input_forwarding::system_actions::SystemActionGuard;
+use crate::input_forwarding::touchscreen::TouchInput;
+use crate::logging::TARGET_INPUT;
+
+/// Mechanism a forwarder uses to inject input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputBackend {
+    Xdotool,
+    Ydotool,
+    RemoteDesktopPortal,
+    VirtualInput,
+}
 
 /// ImprovedInputForwarder trait defines the common interface for all input forwarders
 /// regardless of the underlying display server or implementation details.
@@ -9,16 +28,27 @@ pub trait ImprovedInputForwarder: Send + Sync {
     /// Forward an input event to the operating system
     fn forward_event(&self, event: &InputEvent) -> Result<(), InputForwardingError>;
     
+    /// Inject events, drop them, or drop them for the app to preview
+    fn set_mode(&self, mode: InputMode);
+    
+    /// How events are currently handled
+    fn mode(&self) -> InputMode;
+    
     /// Enable or disable input forwarding
-    fn set_enabled(&self, enabled: bool);
+    fn set_enabled(&self, enabled: bool) {
+        self.set_mode(if enabled { InputMode::Enabled } else { InputMode::Disabled });
+    }
     
-    /// Check if input forwarding is currently enabled
-    fn is_enabled(&self) -> bool;
+    /// Check if input forwarding is currently enabled; preview does not count
+    fn is_enabled(&self) -> bool {
+        self.mode() == InputMode::Enabled
+    }
     
     /// Configure multi-monitor settings
-    fn configure_monitors(&mut self, monitors: Vec<MonitorConfiguration>) -> Result<(), InputForwardingError>;
+    fn configure_monitors(&self, monitors: Vec<MonitorConfiguration>) -> Result<(), InputForwardingError>;
     
-    /// Handle special system commands like Alt+Tab, Win+D, etc.
+    /// Handle special system commands like Alt+Tab, Win+D, etc. Disruptive
+    /// ones only run once the system action guard admits them.
     fn handle_special_command(&self, command: &SpecialCommand) -> Result<(), InputForwardingError>;
     
     /// Handle touch gestures with optional direction and magnitude
@@ -28,4 +58,51 @@ pub trait ImprovedInputForwarder: Send + Sync {
         direction: Option<&GestureDirection>, 
         magnitude: Option<f32>
     ) -> Result<(), InputForwardingError>;
+    
+    /// Mechanism this forwarder injects input with
+    fn backend(&self) -> InputBackend;
+    
+    /// Release all modifier keys and any keys still recorded as pressed
+    fn reset_input_state(&self) -> Result<(), InputForwardingError>;
+    
+    /// How long keys have been held without further input (None if no key is held)
+    fn held_keys_idle_for(&self) -> Option<Duration>;
+    
+    /// Share the guard deciding on disruptive special commands with the app
+    fn set_system_action_guard(&self, guard: Arc<SystemActionGuard>);
+    
+    /// Replace the rules deciding which key combinations reach the host
+    fn set_shortcut_policy(&self, policy: &ShortcutPolicy) -> Result<(), InputForwardingError>;
+    
+    /// Switch the keyboard layout keys are injected with; only backends that
+    /// bring their own keyboard have one
+    fn set_keyboard_layout(&self, _layout: &str) -> Result<(), InputForwardingError> {
+        Ok(())
+    }
+    
+    /// Called by `set_mode`: once events are no longer injected, keys held at
+    /// this moment would otherwise stay down until input is re-enabled
+    fn release_keys_unless_enabled(&self, mode: InputMode) {
+        if mode != InputMode::Enabled {
+            if let Err(e) = self.reset_input_state() {
+                log::warn!(target: TARGET_INPUT, "Failed to release held keys: {}", e);
+            }
+        }
+    }
+    
+    /// Events every backend handles alike, whatever it injects with
+    fn forward_common(&self, touch: &Mutex<TouchInput>, event: &InputEvent) -> Result<(), InputForwardingError> {
+        match event.event_type {
+            InputEventType::TouchDown | InputEventType::TouchMove | InputEventType::TouchUp => {
+                // Without uinput a single contact comes back as pointer events
+                let pointer_events = touch.lock().unwrap().forward(event)?;
+                pointer_events.iter().try_for_each(|pointer_event| self.forward_event(pointer_event))
+            },
+            // Answered by the app before forwarding, nothing to inject
+            InputEventType::Ping => Ok(()),
+            _ => Err(InputForwardingError::UnsupportedEvent(
+                format!("{:?} events are forwarded by the backend", event.event_type)
+            )),
+        }
+    }
 }
@@ -0,0 +1,163 @@
+// key_state.rs - Keys held down by the remote peer
+//
+// A key release lost with the connection leaves the key stuck on the host.
+// Forwarders record which keys they pressed so they can synthesize the
+// missing releases on reset.
+
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+use crate::input_forwarding::types::{InputEvent, InputEventType};
+
+/// JavaScript keyCodes of Shift, Control, Alt and Meta; always released on reset
+pub const MODIFIER_KEY_CODES: [u32; 4] = [16, 17, 18, 91];
+
+/// Default time after the last input event at which held keys are released
+pub const DEFAULT_STUCK_KEY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// JavaScript keyCode of a modifier name as sent in `InputEvent::modifiers`
+fn modifier_key_code(modifier: &str) -> Option<u32> {
+    match modifier {
+        "shift" => Some(16),
+        "ctrl" => Some(17),
+        "alt" => Some(18),
+        "meta" => Some(91),
+        _ => None,
+    }
+}
+
+/// Pressed keys and the time of the last forwarded event
+#[derive(Debug)]
+pub struct HeldKeys {
+    pressed: BTreeSet<u32>,
+    last_input: Instant,
+}
+
+impl Default for HeldKeys {
+    fn default() -> Self {
+        HeldKeys {
+            pressed: BTreeSet::new(),
+            last_input: Instant::now(),
+        }
+    }
+}
+
+impl HeldKeys {
+    /// Notes that an event of any kind arrived
+    pub fn touch(&mut self) {
+        self.last_input = Instant::now();
+    }
+
+    /// Updates the pressed set after a key event was injected
+    pub fn record_key(&mut self, event: &InputEvent) {
+        let (Some(key_code), Some(is_pressed)) = (event.key_code, event.is_pressed) else {
+            return;
+        };
+        let modifiers = event.modifiers.iter().flatten().filter_map(|m| modifier_key_code(m));
+        if is_pressed {
+            self.pressed.insert(key_code);
+            self.pressed.extend(modifiers);
+        } else {
+            self.pressed.remove(&key_code);
+            for modifier in modifiers {
+                self.pressed.remove(&modifier);
+            }
+        }
+    }
+
+    /// How long keys have been held without further input, None if no key is held
+    pub fn idle_for(&self) -> Option<Duration> {
+        (!self.pressed.is_empty()).then(|| self.last_input.elapsed())
+    }
+
+    /// Release events for all pressed keys followed by all modifiers; clears the set
+    pub fn take_releases(&mut self) -> Vec<InputEvent> {
+        let pressed = std::mem::take(&mut self.pressed);
+        pressed.iter()
+            .copied()
+            .filter(|key| !MODIFIER_KEY_CODES.contains(key))
+            .chain(MODIFIER_KEY_CODES)
+            .map(release_event)
+            .collect()
+    }
+}
+
+fn release_event(key_code: u32) -> InputEvent {
+    InputEvent {
+        event_type: InputEventType::KeyRelease,
+        x: None,
+        y: None,
+        button: None,
+        key_code: Some(key_code),
+        modifiers: None,
+        is_pressed: Some(false),
+        delta_x: None,
+        delta_y: None,
+        monitor_index: None,
+        gesture: None,
+        gesture_direction: None,
+        gesture_magnitude: None,
+        special_command: None,
+        ping_id: None,
+        client_timestamp: None,
+        touch: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key_code: u32, is_pressed: bool, modifiers: &[&str]) -> InputEvent {
+        let mut event = release_event(key_code);
+        event.event_type = if is_pressed { InputEventType::KeyPress } else { InputEventType::KeyRelease };
+        event.is_pressed = Some(is_pressed);
+        event.modifiers = Some(modifiers.iter().map(|m| m.to_string()).collect());
+        event
+    }
+
+    fn released_codes(held: &mut HeldKeys) -> Vec<u32> {
+        held.take_releases().iter()
+            .map(|event| {
+                assert!(matches!(event.event_type, InputEventType::KeyRelease));
+                assert_eq!(event.is_pressed, Some(false));
+                event.key_code.unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_press_without_release_is_released() {
+        let mut held = HeldKeys::default();
+
+        // Ctrl+C pressed, connection drops before any keyup
+        held.record_key(&key(17, true, &[]));
+        held.record_key(&key(67, true, &["ctrl"]));
+        assert!(held.idle_for().is_some());
+
+        assert_eq!(released_codes(&mut held), vec![67, 16, 17, 18, 91]);
+        assert!(held.idle_for().is_none());
+    }
+
+    #[test]
+    fn test_released_keys_are_forgotten() {
+        let mut held = HeldKeys::default();
+        held.record_key(&key(65, true, &["shift"]));
+        held.record_key(&key(65, false, &["shift"]));
+        assert!(held.idle_for().is_none());
+
+        // Modifiers are released even when none are known to be held
+        assert_eq!(released_codes(&mut held), MODIFIER_KEY_CODES.to_vec());
+    }
+
+    #[test]
+    fn test_idle_time_restarts_on_input() {
+        let mut held = HeldKeys::default();
+        held.record_key(&key(16, true, &[]));
+        held.last_input = Instant::now() - Duration::from_secs(10);
+        assert!(held.idle_for().unwrap() >= Duration::from_secs(10));
+
+        held.touch();
+        assert!(held.idle_for().unwrap() < Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,224 @@
+// latency.rs - Queueing and injection latency of forwarded input
+//
+// send_input_event stamps every event when the command arrives, when the
+// forwarder starts injecting it and when injection returns. The tracker keeps
+// the samples of the last minute and reports percentiles per event type, so
+// slow injection through xdotool/ydotool can be told apart from network lag,
+// which the frontend measures with Ping round trips.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::input_forwarding::types::InputEventType;
+
+/// How far back the latency statistics reach
+pub const LATENCY_WINDOW: Duration = Duration::from_secs(60);
+
+/// Upper bound for stored samples; at the default rate limit of 500 events
+/// per second the oldest samples of the window drop out early
+const MAX_SAMPLES: usize = 30_000;
+
+struct LatencySample {
+    at: Instant,
+    event_type: InputEventType,
+    queueing: Duration,
+    injection: Duration,
+}
+
+/// Percentiles of one duration, in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencySummary {
+    /// Summarizes the durations (sorted in place); None if there are none
+    pub fn from_durations(durations: &mut [Duration]) -> Option<Self> {
+        durations.sort_unstable();
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+
+        Some(LatencySummary {
+            p50_ms: ms(percentile(durations, 50.0)?),
+            p95_ms: ms(percentile(durations, 95.0)?),
+            max_ms: ms(*durations.last()?),
+        })
+    }
+}
+
+/// Latency of one event type over the window
+#[derive(Debug, Clone, Serialize)]
+pub struct EventTypeLatency {
+    pub event_type: InputEventType,
+    pub count: usize,
+    /// From receiving the command to the start of injection (locks, checks)
+    pub queueing: LatencySummary,
+    /// Time the forwarder took to inject the event
+    pub injection: LatencySummary,
+}
+
+/// Returned by `get_input_latency_stats`
+#[derive(Debug, Clone, Serialize)]
+pub struct InputLatencyStats {
+    pub window_secs: u64,
+    /// In the order the event types first occurred within the window
+    pub by_event_type: Vec<EventTypeLatency>,
+}
+
+/// Nearest-rank percentile of sorted durations; None if there are none
+pub fn percentile(sorted: &[Duration], percent: f64) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// Latency samples of forwarded input over the last `LATENCY_WINDOW`
+pub struct InputLatencyTracker {
+    samples: Mutex<VecDeque<LatencySample>>,
+}
+
+impl InputLatencyTracker {
+    pub fn new() -> Self {
+        InputLatencyTracker {
+            samples: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Records one injected event: `received` is when the command arrived,
+    /// `started` and `finished` bracket the injection
+    pub fn record(&self, event_type: InputEventType, received: Instant, started: Instant, finished: Instant) {
+        let mut samples = self.samples.lock().unwrap();
+        prune(&mut samples, finished);
+        if samples.len() >= MAX_SAMPLES {
+            samples.pop_front();
+        }
+
+        samples.push_back(LatencySample {
+            at: finished,
+            event_type,
+            queueing: started.saturating_duration_since(received),
+            injection: finished.saturating_duration_since(started),
+        });
+    }
+
+    /// Percentiles per event type over the window ending at `now`
+    pub fn stats(&self, now: Instant) -> InputLatencyStats {
+        let mut samples = self.samples.lock().unwrap();
+        prune(&mut samples, now);
+
+        let mut groups: Vec<(InputEventType, Vec<Duration>, Vec<Duration>)> = Vec::new();
+        for sample in samples.iter() {
+            let index = match groups.iter().position(|(event_type, _, _)| *event_type == sample.event_type) {
+                Some(index) => index,
+                None => {
+                    groups.push((sample.event_type.clone(), Vec::new(), Vec::new()));
+                    groups.len() - 1
+                }
+            };
+            groups[index].1.push(sample.queueing);
+            groups[index].2.push(sample.injection);
+        }
+
+        let by_event_type = groups.into_iter()
+            .filter_map(|(event_type, mut queueing, mut injection)| {
+                Some(EventTypeLatency {
+                    event_type,
+                    count: queueing.len(),
+                    queueing: LatencySummary::from_durations(&mut queueing)?,
+                    injection: LatencySummary::from_durations(&mut injection)?,
+                })
+            })
+            .collect();
+
+        InputLatencyStats {
+            window_secs: LATENCY_WINDOW.as_secs(),
+            by_event_type,
+        }
+    }
+}
+
+impl Default for InputLatencyTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Drops samples that left the window
+fn prune(samples: &mut VecDeque<LatencySample>, now: Instant) {
+    while let Some(oldest) = samples.front() {
+        if now.saturating_duration_since(oldest.at) <= LATENCY_WINDOW {
+            break;
+        }
+        samples.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(values: impl IntoIterator<Item = u64>) -> Vec<Duration> {
+        values.into_iter().map(Duration::from_millis).collect()
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let sorted = millis(1..=100);
+        assert_eq!(percentile(&sorted, 50.0), Some(Duration::from_millis(50)));
+        assert_eq!(percentile(&sorted, 95.0), Some(Duration::from_millis(95)));
+        assert_eq!(percentile(&sorted, 100.0), Some(Duration::from_millis(100)));
+        assert_eq!(percentile(&sorted, 0.0), Some(Duration::from_millis(1)));
+
+        // Nearest rank rounds up: the 95th percentile of 10 values is the 10th
+        let sorted = millis(1..=10);
+        assert_eq!(percentile(&sorted, 50.0), Some(Duration::from_millis(5)));
+        assert_eq!(percentile(&sorted, 95.0), Some(Duration::from_millis(10)));
+
+        assert_eq!(percentile(&millis([7]), 95.0), Some(Duration::from_millis(7)));
+        assert_eq!(percentile(&[], 50.0), None);
+
+        let mut unsorted = millis([30, 10, 20]);
+        assert_eq!(
+            LatencySummary::from_durations(&mut unsorted),
+            Some(LatencySummary { p50_ms: 20.0, p95_ms: 30.0, max_ms: 30.0 })
+        );
+        assert_eq!(LatencySummary::from_durations(&mut []), None);
+    }
+
+    #[test]
+    fn test_stats_per_event_type_within_window() {
+        let tracker = InputLatencyTracker::new();
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+
+        // Only seen before the window
+        tracker.record(InputEventType::KeyPress, at(0), at(1), at(2));
+
+        let later = Duration::from_secs(61).as_millis() as u64;
+        for i in 0..4 {
+            let received = at(later + i * 100);
+            tracker.record(InputEventType::MouseMove, received, received + Duration::from_millis(1), received + Duration::from_millis(1 + 10 * (i + 1)));
+        }
+        let received = at(later + 500);
+        tracker.record(InputEventType::MouseButton, received, received + Duration::from_millis(4), received + Duration::from_millis(34));
+
+        let stats = tracker.stats(at(later + 1000));
+        assert_eq!(stats.window_secs, 60);
+        let types: Vec<(InputEventType, usize)> = stats.by_event_type.iter().map(|t| (t.event_type.clone(), t.count)).collect();
+        assert_eq!(types, vec![(InputEventType::MouseMove, 4), (InputEventType::MouseButton, 1)]);
+
+        let moves = &stats.by_event_type[0];
+        assert_eq!(moves.queueing, LatencySummary { p50_ms: 1.0, p95_ms: 1.0, max_ms: 1.0 });
+        assert_eq!(moves.injection, LatencySummary { p50_ms: 20.0, p95_ms: 40.0, max_ms: 40.0 });
+        assert_eq!(stats.by_event_type[1].injection.max_ms, 30.0);
+
+        assert!(tracker.stats(at(later + 120_000)).by_event_type.is_empty());
+    }
+}
@@ -6,8 +6,22 @@ pub mod error;
 pub mod forwarder_trait;
 pub mod x11;
 pub mod wayland;
+pub mod portal;
+#[cfg(feature = "wayland-support")]
+pub mod virtual_input;
 pub mod factory;
 pub mod utils;
+pub mod recorder;
+pub mod panic_hotkey;
+pub mod blocklist;
+pub mod custom_command;
+pub mod key_state;
+pub mod arbiter;
+pub mod latency;
+pub mod shortcuts;
+pub mod preview;
+pub mod touchscreen;
+pub mod system_actions;
 
 // Re-export public items for easier access
 pub use types::*;
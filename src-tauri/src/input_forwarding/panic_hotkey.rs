@@ -0,0 +1,738 @@
+// panic_hotkey.rs - Local hotkey that cuts off (and restores) remote input
+//
+// The hotkey is read from the physical keyboards, never through the forwarder,
+// so it keeps working while a remote peer floods the desktop with key events.
+// Keyboards are read via evdev where /dev/input is accessible, which covers
+// Wayland compositors; on X11 without evdev access the key is grabbed with
+// XGrabKey instead.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::input_forwarding::error::InputForwardingError;
+use crate::input_forwarding::types::{InputEvent, InputEventType};
+use crate::logging::TARGET_INPUT;
+
+/// Hotkey used unless configured otherwise
+pub const DEFAULT_PANIC_HOTKEY: &str = "Ctrl+Alt+F12";
+
+/// A press of the trigger key this soon after the same key was injected is
+/// attributed to the remote peer (only needed where grabs see injected keys)
+const INJECTED_KEY_WINDOW: Duration = Duration::from_millis(250);
+
+/// How often the listener checks whether it should stop
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Size of `struct input_event` on 64-bit Linux
+const INPUT_EVENT_SIZE: usize = 24;
+
+const EV_KEY: u16 = 0x01;
+
+/// Device names of uinput/XTEST devices used for injecting input
+const VIRTUAL_DEVICE_MARKERS: &[&str] = &["ydotool", "xtest", "uinput", "virtual"];
+
+// Settings of the panic hotkey
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PanicHotkeyConfig {
+    pub enabled: bool,
+    pub hotkey: String,
+    // Also stop sending video while remote input is disabled
+    pub blank_stream: bool,
+}
+
+impl Default for PanicHotkeyConfig {
+    fn default() -> Self {
+        PanicHotkeyConfig {
+            enabled: true,
+            hotkey: DEFAULT_PANIC_HOTKEY.to_string(),
+            blank_stream: false,
+        }
+    }
+}
+
+// Where the hotkey is read from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum HotkeyBackend {
+    Evdev,
+    X11Grab,
+}
+
+// Modifier keys, each matching its left and right key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Modifier {
+    Ctrl,
+    Alt,
+    Shift,
+    Meta,
+}
+
+impl Modifier {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => Some(Modifier::Ctrl),
+            "alt" => Some(Modifier::Alt),
+            "shift" => Some(Modifier::Shift),
+            "meta" | "super" | "win" => Some(Modifier::Meta),
+            _ => None,
+        }
+    }
+
+    fn from_evdev(code: u16) -> Option<Self> {
+        match code {
+            29 | 97 => Some(Modifier::Ctrl),
+            56 | 100 => Some(Modifier::Alt),
+            42 | 54 => Some(Modifier::Shift),
+            125 | 126 => Some(Modifier::Meta),
+            _ => None,
+        }
+    }
+
+    fn from_browser(code: u32) -> Option<Self> {
+        match code {
+            17 => Some(Modifier::Ctrl),
+            18 => Some(Modifier::Alt),
+            16 => Some(Modifier::Shift),
+            91 | 92 => Some(Modifier::Meta),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Modifier::Ctrl => "Ctrl",
+            Modifier::Alt => "Alt",
+            Modifier::Shift => "Shift",
+            Modifier::Meta => "Meta",
+        }
+    }
+}
+
+/// Keys that can trigger the hotkey: name, evdev code, browser key code
+/// (as sent by the frontend) and X keysym name
+const TRIGGER_KEYS: &[(&str, u16, u32, &str)] = &[
+    ("F1", 59, 112, "F1"),
+    ("F2", 60, 113, "F2"),
+    ("F3", 61, 114, "F3"),
+    ("F4", 62, 115, "F4"),
+    ("F5", 63, 116, "F5"),
+    ("F6", 64, 117, "F6"),
+    ("F7", 65, 118, "F7"),
+    ("F8", 66, 119, "F8"),
+    ("F9", 67, 120, "F9"),
+    ("F10", 68, 121, "F10"),
+    ("F11", 87, 122, "F11"),
+    ("F12", 88, 123, "F12"),
+    ("Escape", 1, 27, "Escape"),
+    ("Pause", 119, 19, "Pause"),
+    ("ScrollLock", 70, 145, "Scroll_Lock"),
+    ("Insert", 110, 45, "Insert"),
+    ("Delete", 111, 46, "Delete"),
+    ("Home", 102, 36, "Home"),
+    ("End", 107, 35, "End"),
+];
+
+/// A key combination such as "Ctrl+Alt+F12"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hotkey {
+    modifiers: Vec<Modifier>,
+    // Index into TRIGGER_KEYS
+    key: usize,
+}
+
+impl Hotkey {
+    /// Parses "Modifier+...+Key"; the key must be a function or navigation key
+    pub fn parse(spec: &str) -> Result<Self, InputForwardingError> {
+        let mut parts: Vec<&str> = spec.split('+').map(str::trim).collect();
+        let key_name = parts.pop().filter(|name| !name.is_empty())
+            .ok_or_else(|| InputForwardingError::HotkeyError(format!("Empty hotkey '{}'", spec)))?;
+
+        let key = TRIGGER_KEYS.iter()
+            .position(|(name, ..)| name.eq_ignore_ascii_case(key_name))
+            .ok_or_else(|| InputForwardingError::HotkeyError(format!("Unsupported hotkey key '{}'", key_name)))?;
+
+        let mut modifiers = Vec::new();
+        for part in parts {
+            let modifier = Modifier::parse(part)
+                .ok_or_else(|| InputForwardingError::HotkeyError(format!("Unknown modifier '{}'", part)))?;
+            if !modifiers.contains(&modifier) {
+                modifiers.push(modifier);
+            }
+        }
+
+        // A bare function key is too easy to hit by accident
+        if modifiers.is_empty() {
+            return Err(InputForwardingError::HotkeyError(
+                format!("Hotkey '{}' needs at least one modifier", spec),
+            ));
+        }
+
+        Ok(Hotkey { modifiers, key })
+    }
+
+    fn evdev_code(&self) -> u16 {
+        TRIGGER_KEYS[self.key].1
+    }
+
+    fn browser_code(&self) -> u32 {
+        TRIGGER_KEYS[self.key].2
+    }
+
+    #[cfg_attr(not(feature = "x11-support"), allow(dead_code))]
+    fn keysym_name(&self) -> &'static str {
+        TRIGGER_KEYS[self.key].3
+    }
+}
+
+impl std::fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for modifier in &self.modifiers {
+            write!(f, "{}+", modifier.name())?;
+        }
+        write!(f, "{}", TRIGGER_KEYS[self.key].0)
+    }
+}
+
+/// Decides when the hotkey fires. Physical key events come from the
+/// listener, injected ones from the forwarder; injected events never
+/// count towards the combination.
+#[derive(Debug)]
+pub struct HotkeyMatcher {
+    hotkey: Hotkey,
+    // Physically held modifiers with the codes holding them
+    modifiers: HashMap<Modifier, HashSet<u16>>,
+    // Trigger key is held, auto-repeat must not toggle again
+    latched: bool,
+    // Last time the remote peer pressed the trigger key
+    last_injected_trigger: Option<Instant>,
+}
+
+impl HotkeyMatcher {
+    pub fn new(hotkey: Hotkey) -> Self {
+        HotkeyMatcher {
+            hotkey,
+            modifiers: HashMap::new(),
+            latched: false,
+            last_injected_trigger: None,
+        }
+    }
+
+    /// Feeds a key from a physical keyboard (evdev code); true when the
+    /// hotkey was pressed. Injection devices are never read, so unlike a
+    /// grab this cannot be masked by remote key presses.
+    pub fn physical_key(&mut self, code: u16, pressed: bool) -> bool {
+        if let Some(modifier) = Modifier::from_evdev(code) {
+            let held = self.modifiers.entry(modifier).or_default();
+            if pressed {
+                held.insert(code);
+            } else {
+                held.remove(&code);
+            }
+            return false;
+        }
+
+        if code != self.hotkey.evdev_code() {
+            return false;
+        }
+
+        if !pressed {
+            self.latched = false;
+            return false;
+        }
+
+        let modifiers_held = self.hotkey.modifiers.iter()
+            .all(|modifier| self.modifiers.get(modifier).map_or(false, |held| !held.is_empty()));
+        if !modifiers_held || self.latched {
+            return false;
+        }
+
+        self.latched = true;
+        true
+    }
+
+    /// Called when a key grab reports the combination; the grab has already
+    /// checked the modifiers but cannot tell injected from physical keys
+    pub fn grab_fired(&mut self, now: Instant) -> bool {
+        if self.latched {
+            return false;
+        }
+
+        let injected = self.last_injected_trigger
+            .map_or(false, |at| now.saturating_duration_since(at) <= INJECTED_KEY_WINDOW);
+        if injected {
+            return false;
+        }
+
+        self.latched = true;
+        true
+    }
+
+    /// Releases the latch once a grab reports the key going up
+    pub fn grab_released(&mut self) {
+        self.latched = false;
+    }
+
+    /// Records a key event the forwarder is about to inject
+    pub fn injected_event(&mut self, event: &InputEvent, now: Instant) {
+        let is_key = matches!(event.event_type, InputEventType::KeyPress | InputEventType::KeyRelease);
+        if !is_key || Modifier::from_browser(event.key_code.unwrap_or(0)).is_some() {
+            return;
+        }
+
+        let pressed = event.is_pressed.unwrap_or(matches!(event.event_type, InputEventType::KeyPress));
+        if pressed && event.key_code == Some(self.hotkey.browser_code()) {
+            self.last_injected_trigger = Some(now);
+        }
+    }
+}
+
+/// Callback run on the listener thread whenever the hotkey is pressed
+pub type HotkeyCallback = Arc<dyn Fn() + Send + Sync>;
+
+/// Listener thread for the panic hotkey
+pub struct PanicHotkeyListener {
+    hotkey: Hotkey,
+    backend: HotkeyBackend,
+    matcher: Arc<Mutex<HotkeyMatcher>>,
+    running: Arc<Mutex<bool>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PanicHotkeyListener {
+    /// Starts listening with the first backend that works here
+    pub fn start(hotkey: Hotkey, on_trigger: HotkeyCallback) -> Result<Self, InputForwardingError> {
+        let matcher = Arc::new(Mutex::new(HotkeyMatcher::new(hotkey.clone())));
+        let running = Arc::new(Mutex::new(true));
+
+        let keyboards = open_physical_keyboards(Path::new("/dev/input"));
+        let (backend, thread) = if !keyboards.is_empty() {
+            let (matcher, running) = (matcher.clone(), running.clone());
+            let thread = thread::spawn(move || {
+                evdev_loop(keyboards, matcher, running, on_trigger);
+            });
+            (HotkeyBackend::Evdev, thread)
+        } else {
+            (HotkeyBackend::X11Grab, x11_grab::spawn(&hotkey, matcher.clone(), running.clone(), on_trigger)?)
+        };
+
+        log::info!(target: TARGET_INPUT, "Panic hotkey {} active via {:?}", hotkey, backend);
+
+        Ok(PanicHotkeyListener {
+            hotkey,
+            backend,
+            matcher,
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn hotkey(&self) -> &Hotkey {
+        &self.hotkey
+    }
+
+    pub fn backend(&self) -> HotkeyBackend {
+        self.backend
+    }
+
+    /// Tells the listener about a remote key event before it is injected
+    pub fn note_injected(&self, event: &InputEvent) {
+        self.matcher.lock().unwrap().injected_event(event, Instant::now());
+    }
+
+    pub fn stop(&mut self) {
+        *self.running.lock().unwrap() = false;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for PanicHotkeyListener {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Whether a device name belongs to an input injection device
+fn is_virtual_device(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    VIRTUAL_DEVICE_MARKERS.iter().any(|marker| name.contains(marker))
+}
+
+/// Opens every readable keyboard under `dir`, skipping injection devices
+fn open_physical_keyboards(dir: &Path) -> Vec<File> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut keyboards = Vec::new();
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if !file_name.starts_with("event") {
+            continue;
+        }
+
+        let sys_device = Path::new("/sys/class/input").join(&file_name).join("device");
+        let name = fs::read_to_string(sys_device.join("name")).unwrap_or_default();
+        if is_virtual_device(name.trim()) {
+            log::debug!(target: TARGET_INPUT, "Panic hotkey ignores injection device {}", name.trim());
+            continue;
+        }
+
+        let key_capabilities = fs::read_to_string(sys_device.join("capabilities/key")).unwrap_or_default();
+        if !has_keyboard_keys(&key_capabilities) {
+            continue;
+        }
+
+        match OpenOptions::new()
+            .read(true)
+            .custom_flags(nix::fcntl::OFlag::O_NONBLOCK.bits())
+            .open(entry.path())
+        {
+            Ok(file) => keyboards.push(file),
+            Err(e) => log::debug!(target: TARGET_INPUT, "Cannot read {}: {}", file_name, e),
+        }
+    }
+
+    keyboards
+}
+
+/// Checks the sysfs key capability bitmap for the F12 and left Ctrl keys,
+/// which mice and power buttons do not report
+fn has_keyboard_keys(capabilities: &str) -> bool {
+    // Space separated hex words, most significant first, 64 bits each
+    let words: Vec<u64> = capabilities.split_whitespace().rev()
+        .map(|word| u64::from_str_radix(word, 16).unwrap_or(0))
+        .collect();
+    let has_key = |code: usize| words.get(code / 64).map_or(false, |word| word & (1 << (code % 64)) != 0);
+    has_key(29) && has_key(88)
+}
+
+/// Parses one `struct input_event`: returns (type, code, value)
+fn parse_input_event(record: &[u8]) -> Option<(u16, u16, i32)> {
+    if record.len() < INPUT_EVENT_SIZE {
+        return None;
+    }
+
+    // The first 16 bytes are the timestamp
+    let event_type = u16::from_ne_bytes([record[16], record[17]]);
+    let code = u16::from_ne_bytes([record[18], record[19]]);
+    let value = i32::from_ne_bytes([record[20], record[21], record[22], record[23]]);
+    Some((event_type, code, value))
+}
+
+fn evdev_loop(
+    mut keyboards: Vec<File>,
+    matcher: Arc<Mutex<HotkeyMatcher>>,
+    running: Arc<Mutex<bool>>,
+    on_trigger: HotkeyCallback,
+) {
+    let mut record = [0u8; INPUT_EVENT_SIZE];
+
+    while *running.lock().unwrap() {
+        let mut disconnected = Vec::new();
+
+        for (index, keyboard) in keyboards.iter_mut().enumerate() {
+            loop {
+                match keyboard.read(&mut record) {
+                    Ok(INPUT_EVENT_SIZE) => {
+                        // Value 2 is auto-repeat and never toggles
+                        let (event_type, code, value) = match parse_input_event(&record) {
+                            Some(event) => event,
+                            None => continue,
+                        };
+                        if event_type != EV_KEY || value == 2 {
+                            continue;
+                        }
+
+                        let triggered = matcher.lock().unwrap().physical_key(code, value == 1);
+                        if triggered {
+                            on_trigger();
+                        }
+                    },
+                    Ok(_) => break,
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(e) => {
+                        // Unplugged keyboard
+                        log::debug!(target: TARGET_INPUT, "Keyboard stopped delivering events: {}", e);
+                        disconnected.push(index);
+                        break;
+                    }
+                }
+            }
+        }
+
+        for index in disconnected.into_iter().rev() {
+            keyboards.remove(index);
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(feature = "x11-support")]
+mod x11_grab {
+    use std::ffi::CString;
+    use std::os::raw::c_int;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread::{self, JoinHandle};
+    use std::time::Instant;
+
+    use x11::xlib;
+
+    use super::{Hotkey, HotkeyCallback, HotkeyMatcher, Modifier, POLL_INTERVAL};
+    use crate::input_forwarding::error::InputForwardingError;
+
+    /// Set by the error handler, e.g. when another client owns the grab
+    static GRAB_FAILED: AtomicBool = AtomicBool::new(false);
+
+    unsafe extern "C" fn record_error(_display: *mut xlib::Display, _event: *mut xlib::XErrorEvent) -> c_int {
+        GRAB_FAILED.store(true, Ordering::SeqCst);
+        0
+    }
+
+    /// The display pointer is only touched by the listener thread
+    struct DisplayHandle(*mut xlib::Display);
+
+    unsafe impl Send for DisplayHandle {}
+
+    fn modifier_mask(modifiers: &[Modifier]) -> u32 {
+        modifiers.iter().fold(0, |mask, modifier| mask | match modifier {
+            Modifier::Ctrl => xlib::ControlMask,
+            Modifier::Alt => xlib::Mod1Mask,
+            Modifier::Shift => xlib::ShiftMask,
+            Modifier::Meta => xlib::Mod4Mask,
+        })
+    }
+
+    pub(super) fn spawn(
+        hotkey: &Hotkey,
+        matcher: Arc<Mutex<HotkeyMatcher>>,
+        running: Arc<Mutex<bool>>,
+        on_trigger: HotkeyCallback,
+    ) -> Result<JoinHandle<()>, InputForwardingError> {
+        let keysym_name = CString::new(hotkey.keysym_name()).unwrap();
+        let mask = modifier_mask(&hotkey.modifiers);
+
+        let display = unsafe {
+            let display = xlib::XOpenDisplay(std::ptr::null());
+            if display.is_null() {
+                return Err(InputForwardingError::HotkeyError(
+                    "No readable keyboard in /dev/input and no X display for a key grab".to_string(),
+                ));
+            }
+
+            let keycode = xlib::XKeysymToKeycode(display, xlib::XStringToKeysym(keysym_name.as_ptr()));
+            let root = xlib::XDefaultRootWindow(display);
+
+            GRAB_FAILED.store(false, Ordering::SeqCst);
+            let previous_handler = xlib::XSetErrorHandler(Some(record_error));
+            // Caps Lock and Num Lock must not defeat the grab
+            for extra in [0, xlib::LockMask, xlib::Mod2Mask, xlib::LockMask | xlib::Mod2Mask] {
+                xlib::XGrabKey(display, keycode as c_int, mask | extra, root, xlib::True,
+                    xlib::GrabModeAsync, xlib::GrabModeAsync);
+            }
+            xlib::XSync(display, xlib::False);
+            xlib::XSetErrorHandler(previous_handler);
+
+            if keycode == 0 || GRAB_FAILED.load(Ordering::SeqCst) {
+                xlib::XCloseDisplay(display);
+                return Err(InputForwardingError::HotkeyError(
+                    format!("{} is already grabbed by another application", hotkey),
+                ));
+            }
+
+            // Held keys report one press instead of press/release pairs
+            xlib::XkbSetDetectableAutoRepeat(display, xlib::True, std::ptr::null_mut());
+            DisplayHandle(display)
+        };
+
+        Ok(thread::spawn(move || {
+            let display = display;
+            let mut event: xlib::XEvent = unsafe { std::mem::zeroed() };
+
+            while *running.lock().unwrap() {
+                while unsafe { xlib::XPending(display.0) } > 0 {
+                    unsafe { xlib::XNextEvent(display.0, &mut event) };
+                    match event.get_type() {
+                        xlib::KeyPress => {
+                            let triggered = matcher.lock().unwrap().grab_fired(Instant::now());
+                            if triggered {
+                                on_trigger();
+                            }
+                        },
+                        xlib::KeyRelease => matcher.lock().unwrap().grab_released(),
+                        _ => {},
+                    }
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+
+            // Closing the connection releases the grabs
+            unsafe { xlib::XCloseDisplay(display.0) };
+        }))
+    }
+}
+
+#[cfg(not(feature = "x11-support"))]
+mod x11_grab {
+    use std::sync::{Arc, Mutex};
+    use std::thread::JoinHandle;
+
+    use super::{Hotkey, HotkeyCallback, HotkeyMatcher};
+    use crate::input_forwarding::error::InputForwardingError;
+
+    pub(super) fn spawn(
+        _hotkey: &Hotkey,
+        _matcher: Arc<Mutex<HotkeyMatcher>>,
+        _running: Arc<Mutex<bool>>,
+        _on_trigger: HotkeyCallback,
+    ) -> Result<JoinHandle<()>, InputForwardingError> {
+        Err(InputForwardingError::HotkeyError(
+            "No readable keyboard in /dev/input; add the user to the 'input' group".to_string(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CTRL: u16 = 29;
+    const ALT: u16 = 56;
+    const F12: u16 = 88;
+
+    fn matcher() -> HotkeyMatcher {
+        HotkeyMatcher::new(Hotkey::parse(DEFAULT_PANIC_HOTKEY).unwrap())
+    }
+
+    fn key_event(key_code: u32, pressed: bool) -> InputEvent {
+        InputEvent {
+            event_type: if pressed { InputEventType::KeyPress } else { InputEventType::KeyRelease },
+            x: None,
+            y: None,
+            button: None,
+            key_code: Some(key_code),
+            modifiers: None,
+            is_pressed: Some(pressed),
+            delta_x: None,
+            delta_y: None,
+            monitor_index: None,
+            gesture: None,
+            gesture_direction: None,
+            gesture_magnitude: None,
+            special_command: None,
+            ping_id: None,
+            client_timestamp: None,
+            touch: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_hotkey() {
+        let hotkey = Hotkey::parse("ctrl + alt + f12").unwrap();
+        assert_eq!(hotkey.to_string(), "Ctrl+Alt+F12");
+        assert_eq!(hotkey.evdev_code(), F12);
+
+        assert_eq!(Hotkey::parse("Super+Shift+Pause").unwrap().to_string(), "Meta+Shift+Pause");
+        assert!(Hotkey::parse("F12").is_err());
+        assert!(Hotkey::parse("Ctrl+Hyper+F12").is_err());
+        assert!(Hotkey::parse("Ctrl+Alt+Q").is_err());
+        assert!(Hotkey::parse("").is_err());
+    }
+
+    #[test]
+    fn test_physical_combination_triggers_once_per_press() {
+        let mut matcher = matcher();
+        let now = Instant::now();
+
+        assert!(!matcher.physical_key(F12, true));
+        matcher.physical_key(F12, false);
+
+        matcher.physical_key(97, true); // right Ctrl
+        matcher.physical_key(ALT, true);
+        assert!(matcher.physical_key(F12, true));
+        // Held key
+        assert!(!matcher.physical_key(F12, true));
+
+        // The second press re-enables
+        matcher.physical_key(F12, false);
+        assert!(matcher.physical_key(F12, true));
+
+        matcher.physical_key(ALT, false);
+        matcher.physical_key(F12, false);
+        assert!(!matcher.physical_key(F12, true));
+    }
+
+    #[test]
+    fn test_injected_events_never_trigger() {
+        let mut matcher = matcher();
+        let now = Instant::now();
+
+        // The remote peer types the full combination
+        for (code, pressed) in [(17, true), (18, true), (123, true), (123, false), (18, false), (17, false)] {
+            matcher.injected_event(&key_event(code, pressed), now);
+        }
+        // The grab sees the injected combination as well
+        assert!(!matcher.grab_fired(now + Duration::from_millis(5)));
+
+        // Injected modifiers do not count towards a physical F12
+        assert!(!matcher.physical_key(F12, true));
+    }
+
+    #[test]
+    fn test_physical_press_works_during_remote_typing() {
+        let mut matcher = matcher();
+        let start = Instant::now();
+
+        // Remote keystrokes on other keys do not block the hotkey
+        matcher.injected_event(&key_event(65, true), start);
+        matcher.injected_event(&key_event(17, true), start);
+        matcher.physical_key(CTRL, true);
+        matcher.physical_key(ALT, true);
+        assert!(matcher.physical_key(F12, true));
+
+        // An injected F12 only masks grabs for a short window
+        matcher.grab_released();
+        matcher.injected_event(&key_event(123, true), start);
+        assert!(!matcher.grab_fired(start + Duration::from_millis(100)));
+        assert!(matcher.grab_fired(start + INJECTED_KEY_WINDOW + Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn test_virtual_devices_are_skipped() {
+        assert!(is_virtual_device("ydotoold virtual device"));
+        assert!(is_virtual_device("Virtual core XTEST keyboard"));
+        assert!(!is_virtual_device("AT Translated Set 2 keyboard"));
+        assert!(!is_virtual_device("Logitech USB Keyboard"));
+    }
+
+    #[test]
+    fn test_keyboard_capabilities() {
+        // Typical laptop keyboard
+        assert!(has_keyboard_keys("402000000 3803078f800d001 feffffdfffefffff fffffffffffffffe"));
+        // Power button
+        assert!(!has_keyboard_keys("4000000000000 0"));
+        assert!(!has_keyboard_keys(""));
+    }
+
+    #[test]
+    fn test_parse_input_event() {
+        let mut record = [0u8; INPUT_EVENT_SIZE];
+        record[16..18].copy_from_slice(&EV_KEY.to_ne_bytes());
+        record[18..20].copy_from_slice(&F12.to_ne_bytes());
+        record[20..24].copy_from_slice(&1i32.to_ne_bytes());
+
+        assert_eq!(parse_input_event(&record), Some((EV_KEY, F12, 1)));
+        assert_eq!(parse_input_event(&record[..16]), None);
+    }
+}
@@ -0,0 +1,657 @@
+// portal.rs - Wayland input forwarding through the xdg-desktop-portal RemoteDesktop interface
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use ashpd::desktop::remote_desktop::{Axis, DeviceType, KeyState, RemoteDesktop};
+use ashpd::desktop::screencast::PersistMode;
+use ashpd::desktop::{ResponseError, Session};
+use ashpd::enumflags2::BitFlags;
+use ashpd::WindowIdentifier;
+
+use crate::input_forwarding::types::*;
+use crate::input_forwarding::error::InputForwardingError;
+use crate::input_forwarding::forwarder_trait::{ImprovedInputForwarder, InputBackend};
+use crate::input_forwarding::utils;
+use crate::input_forwarding::key_state::HeldKeys;
+use crate::input_forwarding::preview::InputMode;
+use crate::input_forwarding::shortcuts::{ShortcutFilter, ShortcutOutcome, ShortcutPolicy};
+use crate::input_forwarding::system_actions::SystemActionGuard;
+use crate::input_forwarding::touchscreen::TouchInput;
+
+/// How long to wait for the user to answer the portal's consent dialog
+const CONSENT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Relative motion large enough to push the pointer into the top-left corner
+const HOMING_DISTANCE: f64 = -100_000.0;
+
+// Linux evdev button codes
+pub const BTN_LEFT: i32 = 0x110;
+pub const BTN_RIGHT: i32 = 0x111;
+pub const BTN_MIDDLE: i32 = 0x112;
+pub const BTN_SIDE: i32 = 0x113;
+pub const BTN_EXTRA: i32 = 0x114;
+
+// Linux evdev key codes used by gestures and special commands
+const KEY_LEFTCTRL: i32 = 29;
+const KEY_LEFTALT: i32 = 56;
+const KEY_LEFTMETA: i32 = 125;
+const KEY_TAB: i32 = 15;
+const KEY_D: i32 = 32;
+const KEY_L: i32 = 38;
+const KEY_SYSRQ: i32 = 99;
+const KEY_KPMINUS: i32 = 74;
+const KEY_MUTE: i32 = 113;
+const KEY_VOLUMEDOWN: i32 = 114;
+const KEY_VOLUMEUP: i32 = 115;
+const KEY_NEXTSONG: i32 = 163;
+const KEY_PLAYPAUSE: i32 = 164;
+const KEY_PREVIOUSSONG: i32 = 165;
+const KEY_KPPLUS: i32 = 78;
+const KEY_UP: i32 = 103;
+const KEY_LEFT: i32 = 105;
+const KEY_RIGHT: i32 = 106;
+const KEY_DOWN: i32 = 108;
+
+/// Key combination that triggers a special command
+pub fn special_command_keys(command: &SpecialCommand) -> Result<&'static [i32], InputForwardingError> {
+    let keys: &[i32] = match command {
+        SpecialCommand::AppSwitcher => &[KEY_LEFTALT, KEY_TAB],
+        SpecialCommand::DesktopToggle => &[KEY_LEFTMETA, KEY_D],
+        SpecialCommand::ScreenSnapshot => &[KEY_SYSRQ],
+        SpecialCommand::LockScreen => &[KEY_LEFTMETA, KEY_L],
+        SpecialCommand::VolumeUp => &[KEY_VOLUMEUP],
+        SpecialCommand::VolumeDown => &[KEY_VOLUMEDOWN],
+        SpecialCommand::VolumeMute => &[KEY_MUTE],
+        SpecialCommand::MediaPlayPause => &[KEY_PLAYPAUSE],
+        SpecialCommand::MediaNext => &[KEY_NEXTSONG],
+        SpecialCommand::MediaPrev => &[KEY_PREVIOUSSONG],
+        SpecialCommand::Custom(_) => {
+            return Err(InputForwardingError::UnsupportedEvent(
+                "Custom commands need the ydotool backend".to_string()
+            ));
+        },
+    };
+    Ok(keys)
+}
+
+/// What a touch gesture becomes on a backend that injects evdev codes
+#[derive(Debug, Clone, PartialEq)]
+pub enum GestureAction {
+    /// Discrete wheel steps, positive is down or right
+    Scroll { vertical: bool, steps: i32 },
+    /// Keys pressed in order and released in reverse
+    Keys(Vec<i32>),
+}
+
+pub fn gesture_action(gesture: &TouchGesture, direction: Option<&GestureDirection>, magnitude: Option<f32>) -> Result<GestureAction, InputForwardingError> {
+    match gesture {
+        TouchGesture::TwoFingerScroll => {
+            let direction = direction.ok_or_else(|| InputForwardingError::UnsupportedEvent(
+                "TwoFingerScroll requires a direction".to_string()
+            ))?;
+            let steps = (magnitude.unwrap_or(1.0).abs() as i32).max(1);
+            // Content follows the fingers, so the wheel turns the other way
+            Ok(match direction {
+                GestureDirection::Up => GestureAction::Scroll { vertical: true, steps: -steps },
+                GestureDirection::Down => GestureAction::Scroll { vertical: true, steps },
+                GestureDirection::Left => GestureAction::Scroll { vertical: false, steps: -steps },
+                GestureDirection::Right => GestureAction::Scroll { vertical: false, steps },
+            })
+        },
+        TouchGesture::Pinch => {
+            let zoom_key = if magnitude.unwrap_or(0.0) > 0.0 { KEY_KPPLUS } else { KEY_KPMINUS };
+            Ok(GestureAction::Keys(vec![KEY_LEFTCTRL, zoom_key]))
+        },
+        TouchGesture::ThreeFingerSwipe => {
+            let arrow = match direction {
+                Some(GestureDirection::Left) => KEY_LEFT,
+                Some(GestureDirection::Right) => KEY_RIGHT,
+                Some(GestureDirection::Up) => KEY_UP,
+                Some(GestureDirection::Down) => KEY_DOWN,
+                None => {
+                    return Err(InputForwardingError::UnsupportedEvent(
+                        "ThreeFingerSwipe requires a direction".to_string()
+                    ));
+                },
+            };
+            Ok(GestureAction::Keys(vec![KEY_LEFTCTRL, KEY_LEFTALT, arrow]))
+        },
+        _ => Err(InputForwardingError::UnsupportedEvent(
+            format!("Unsupported gesture without ydotool: {:?}", gesture)
+        )),
+    }
+}
+
+/// Map a JavaScript keyCode to a Linux evdev key code
+pub fn js_key_to_evdev(key_code: u32) -> Option<i32> {
+    // evdev codes of A-Z follow the QWERTY layout
+    const LETTERS: [i32; 26] = [
+        30, 48, 46, 32, 18, 33, 34, 35, 23, 36, 37, 38, 50,
+        49, 24, 25, 16, 19, 31, 20, 22, 47, 17, 45, 21, 44,
+    ];
+    // Numpad 0-9
+    const KEYPAD: [i32; 10] = [82, 79, 80, 81, 75, 76, 77, 71, 72, 73];
+
+    let code = match key_code {
+        48 => 11,                                   // 0
+        49..=57 => key_code as i32 - 47,            // 1-9
+        65..=90 => LETTERS[(key_code - 65) as usize],
+        96..=105 => KEYPAD[(key_code - 96) as usize],
+        112..=121 => key_code as i32 - 53,          // F1-F10
+        122 => 87,                                  // F11
+        123 => 88,                                  // F12
+        8 => 14,                                    // Backspace
+        9 => KEY_TAB,
+        13 => 28,                                   // Enter
+        16 => 42,                                   // Shift
+        17 => KEY_LEFTCTRL,
+        18 => KEY_LEFTALT,
+        19 => 119,                                  // Pause
+        20 => 58,                                   // Caps Lock
+        27 => 1,                                    // Escape
+        32 => 57,                                   // Space
+        33 => 104,                                  // Page Up
+        34 => 109,                                  // Page Down
+        35 => 107,                                  // End
+        36 => 102,                                  // Home
+        37 => KEY_LEFT,
+        38 => KEY_UP,
+        39 => KEY_RIGHT,
+        40 => KEY_DOWN,
+        44 => KEY_SYSRQ,                            // Print Screen
+        45 => 110,                                  // Insert
+        46 => 111,                                  // Delete
+        91 => KEY_LEFTMETA,
+        93 => 127,                                  // Context menu
+        106 => 55,                                  // Numpad *
+        107 => KEY_KPPLUS,
+        109 => KEY_KPMINUS,
+        110 => 83,                                  // Numpad .
+        111 => 98,                                  // Numpad /
+        144 => 69,                                  // Num Lock
+        145 => 70,                                  // Scroll Lock
+        186 => 39,                                  // ;
+        187 => 13,                                  // =
+        188 => 51,                                  // ,
+        189 => 12,                                  // -
+        190 => 52,                                  // .
+        191 => 53,                                  // /
+        192 => 41,                                  // `
+        219 => 26,                                  // [
+        220 => 43,                                  // backslash
+        221 => 27,                                  // ]
+        222 => 40,                                  // '
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// Turns absolute target positions into relative motion.
+///
+/// RemoteDesktop only accepts absolute motion relative to a ScreenCast stream
+/// of the same session, which would mean a second source picker. Instead the
+/// pointer is pushed into the top-left corner once and then moved by deltas.
+/// Local pointer movement is not seen, so it is corrected the next time the
+/// forwarder is re-enabled.
+#[derive(Debug, Default)]
+pub struct PointerTracker {
+    position: Option<(i32, i32)>,
+}
+
+impl PointerTracker {
+    /// Relative motions that bring the pointer to `(x, y)`
+    pub fn motion_to(&mut self, x: i32, y: i32) -> Vec<(f64, f64)> {
+        let mut motions = Vec::new();
+        let (from_x, from_y) = match self.position {
+            Some(position) => position,
+            None => {
+                motions.push((HOMING_DISTANCE, HOMING_DISTANCE));
+                (0, 0)
+            },
+        };
+        if (x, y) != (from_x, from_y) {
+            motions.push(((x - from_x) as f64, (y - from_y) as f64));
+        }
+        self.position = Some((x, y));
+        motions
+    }
+
+    /// The pointer position is unknown again
+    pub fn reset(&mut self) {
+        self.position = None;
+    }
+}
+
+/// A started RemoteDesktop session
+struct PortalSession {
+    proxy: RemoteDesktop<'static>,
+    session: Session<'static>,
+    devices: BitFlags<DeviceType>,
+}
+
+/// Wayland input forwarder that injects through the compositor via the
+/// RemoteDesktop portal, so neither ydotoold nor /dev/uinput access is needed.
+///
+/// The session is opened on the first forwarded event, when the compositor
+/// asks the user for consent. After a denial no dialog is shown again until
+/// input forwarding is re-enabled.
+pub struct PortalInputForwarder {
+    monitors: Arc<Mutex<Vec<MonitorConfiguration>>>,
+    mode: Arc<Mutex<InputMode>>,
+    session: Mutex<Option<PortalSession>>,
+    consent_denied: Mutex<bool>,
+    pointer: Mutex<PointerTracker>,
+    held_keys: Mutex<HeldKeys>,
+    shortcuts: Mutex<ShortcutFilter>,
+    touch: Mutex<TouchInput>,
+    system_actions: Mutex<Arc<SystemActionGuard>>,
+}
+
+impl PortalInputForwarder {
+    pub fn new() -> Result<Self, InputForwardingError> {
+        if !Self::is_available() {
+            return Err(InputForwardingError::InitializationFailed(
+                "The RemoteDesktop portal offers no keyboard or pointer".to_string(),
+            ));
+        }
+
+        Ok(PortalInputForwarder {
+            monitors: Arc::new(Mutex::new(Vec::new())),
+            mode: Arc::new(Mutex::new(InputMode::Enabled)),
+            session: Mutex::new(None),
+            consent_denied: Mutex::new(false),
+            pointer: Mutex::new(PointerTracker::default()),
+            held_keys: Mutex::new(HeldKeys::default()),
+            shortcuts: Mutex::new(ShortcutFilter::default()),
+            touch: Mutex::new(TouchInput::detect()),
+            system_actions: Mutex::new(Arc::new(SystemActionGuard::default())),
+        })
+    }
+
+    /// Whether xdg-desktop-portal implements RemoteDesktop with pointer and keyboard
+    pub fn is_available() -> bool {
+        tauri::async_runtime::block_on(async {
+            let proxy = RemoteDesktop::new().await.ok()?;
+            proxy.available_device_types().await.ok()
+        })
+        .map_or(false, |devices| devices.contains(DeviceType::Pointer | DeviceType::Keyboard))
+    }
+
+    async fn open_session() -> Result<PortalSession, InputForwardingError> {
+        let proxy = RemoteDesktop::new().await.map_err(to_input_error)?;
+        let session = proxy.create_session().await.map_err(to_input_error)?;
+
+        proxy
+            .select_devices(&session, DeviceType::Keyboard | DeviceType::Pointer, None, PersistMode::DoNot)
+            .await
+            .map_err(to_input_error)?;
+
+        let devices = proxy
+            .start(&session, &WindowIdentifier::default())
+            .await
+            .map_err(to_input_error)?
+            .response()
+            .map_err(to_input_error)?
+            .devices();
+
+        if devices.is_empty() {
+            return Err(InputForwardingError::PermissionDenied(
+                "No input devices were granted".to_string()
+            ));
+        }
+
+        Ok(PortalSession { proxy, session, devices })
+    }
+
+    /// Runs `action` on the session, asking for consent first if there is none.
+    /// Fails with PermissionDenied if `device` was not granted.
+    fn with_session<F>(&self, device: DeviceType, action: F) -> Result<(), InputForwardingError>
+    where
+        F: for<'a> FnOnce(&'a PortalSession) -> std::pin::Pin<Box<dyn std::future::Future<Output = ashpd::Result<()>> + 'a>>,
+    {
+        let mut session = self.session.lock().unwrap();
+
+        if session.is_none() {
+            if *self.consent_denied.lock().unwrap() {
+                return Err(InputForwardingError::PermissionDenied(
+                    "Remote control was declined; re-enable input forwarding to ask again".to_string()
+                ));
+            }
+
+            let opened = tauri::async_runtime::block_on(async {
+                tokio::time::timeout(CONSENT_TIMEOUT, Self::open_session())
+                    .await
+                    .map_err(|_| InputForwardingError::PermissionDenied(
+                        "Timed out waiting for remote control consent".to_string()
+                    ))?
+            });
+
+            match opened {
+                Ok(opened) => {
+                    self.pointer.lock().unwrap().reset();
+                    *session = Some(opened);
+                },
+                Err(e) => {
+                    if let InputForwardingError::PermissionDenied(_) = e {
+                        *self.consent_denied.lock().unwrap() = true;
+                    }
+                    return Err(e);
+                },
+            }
+        }
+
+        let active = session.as_ref().unwrap();
+        if !active.devices.contains(device) {
+            return Err(InputForwardingError::PermissionDenied(
+                format!("{:?} access was not granted for remote control", device)
+            ));
+        }
+
+        let result = tauri::async_runtime::block_on(action(active));
+
+        // A session closed by the compositor is reopened on the next event
+        if let Err(ashpd::Error::Zbus(_)) = &result {
+            *session = None;
+        }
+        result.map_err(|e| InputForwardingError::SendEventFailed(e.to_string()))
+    }
+
+    fn notify_key(&self, keycode: i32, pressed: bool) -> Result<(), InputForwardingError> {
+        let state = if pressed { KeyState::Pressed } else { KeyState::Released };
+        self.with_session(DeviceType::Keyboard, move |s| Box::pin(s.proxy.notify_keyboard_keycode(&s.session, keycode, state)))
+    }
+
+    fn notify_button(&self, button: i32, pressed: bool) -> Result<(), InputForwardingError> {
+        let state = if pressed { KeyState::Pressed } else { KeyState::Released };
+        self.with_session(DeviceType::Pointer, move |s| Box::pin(s.proxy.notify_pointer_button(&s.session, button, state)))
+    }
+
+    fn notify_scroll(&self, axis: Axis, steps: i32) -> Result<(), InputForwardingError> {
+        self.with_session(DeviceType::Pointer, move |s| Box::pin(s.proxy.notify_pointer_axis_discrete(&s.session, axis, steps)))
+    }
+
+    /// Injects single key presses and releases, as produced by a shortcut translation
+    fn inject_key_steps(&self, steps: &[(u32, bool)]) -> Result<(), InputForwardingError> {
+        for (key_code, pressed) in steps {
+            let evdev = js_key_to_evdev(*key_code).ok_or_else(|| InputForwardingError::UnsupportedEvent(
+                format!("No evdev mapping for keyCode {}", key_code)
+            ))?;
+            self.notify_key(evdev, *pressed)?;
+        }
+        Ok(())
+    }
+
+    /// Presses the keys in order and releases them in reverse
+    fn press_combination(&self, keys: &[i32]) -> Result<(), InputForwardingError> {
+        for key in keys {
+            self.notify_key(*key, true)?;
+        }
+        for key in keys.iter().rev() {
+            self.notify_key(*key, false)?;
+        }
+        Ok(())
+    }
+
+    fn move_pointer(&self, x: i32, y: i32, monitor_index: Option<usize>) -> Result<(), InputForwardingError> {
+        let (abs_x, abs_y) = {
+            let monitors = self.monitors.lock().unwrap();
+            utils::calculate_absolute_position(x, y, monitor_index, &monitors)
+        };
+
+        let motions = self.pointer.lock().unwrap().motion_to(abs_x, abs_y);
+        for (dx, dy) in motions {
+            self.with_session(DeviceType::Pointer, move |s| Box::pin(s.proxy.notify_pointer_motion(&s.session, dx, dy)))?;
+        }
+        Ok(())
+    }
+
+    fn execute_special_command(&self, command: &SpecialCommand) -> Result<(), InputForwardingError> {
+        self.press_combination(special_command_keys(command)?)
+    }
+
+    fn handle_portal_gesture(&self, gesture: &TouchGesture, direction: Option<&GestureDirection>, magnitude: Option<f32>) -> Result<(), InputForwardingError> {
+        match gesture_action(gesture, direction, magnitude)? {
+            GestureAction::Scroll { vertical: true, steps } => self.notify_scroll(Axis::Vertical, steps),
+            GestureAction::Scroll { vertical: false, steps } => self.notify_scroll(Axis::Horizontal, steps),
+            GestureAction::Keys(keys) => self.press_combination(&keys),
+        }
+    }
+}
+
+impl ImprovedInputForwarder for PortalInputForwarder {
+    fn forward_event(&self, event: &InputEvent) -> Result<(), InputForwardingError> {
+        // Previewed input never opens a session, so no consent dialog either
+        if self.mode() != InputMode::Enabled {
+            return Ok(());
+        }
+        self.held_keys.lock().unwrap().touch();
+
+        match event.event_type {
+            InputEventType::MouseMove => {
+                if let (Some(x), Some(y)) = (event.x, event.y) {
+                    self.move_pointer(x, y, event.monitor_index)
+                } else {
+                    Err(InputForwardingError::UnsupportedEvent(
+                        "Mouse move event missing coordinates".to_string()
+                    ))
+                }
+            },
+            InputEventType::MouseButton => {
+                if let (Some(button), Some(is_pressed)) = (&event.button, event.is_pressed) {
+                    let code = match button {
+                        MouseButton::Left => BTN_LEFT,
+                        MouseButton::Middle => BTN_MIDDLE,
+                        MouseButton::Right => BTN_RIGHT,
+                        MouseButton::Back => BTN_SIDE,
+                        MouseButton::Forward => BTN_EXTRA,
+                        MouseButton::ScrollUp | MouseButton::ScrollDown => {
+                            return Err(InputForwardingError::UnsupportedEvent(
+                                "Scroll events should use MouseScroll type".to_string()
+                            ));
+                        },
+                        MouseButton::TouchTap | MouseButton::TouchDoubleTap => {
+                            if let (Some(x), Some(y)) = (event.x, event.y) {
+                                self.move_pointer(x, y, event.monitor_index)?;
+                            }
+                            let clicks = if matches!(button, MouseButton::TouchTap) { 1 } else { 2 };
+                            for _ in 0..clicks {
+                                self.notify_button(BTN_LEFT, true)?;
+                                self.notify_button(BTN_LEFT, false)?;
+                            }
+                            return Ok(());
+                        },
+                    };
+                    self.notify_button(code, is_pressed)
+                } else {
+                    Err(InputForwardingError::UnsupportedEvent(
+                        "Mouse button event missing button or pressed state".to_string()
+                    ))
+                }
+            },
+            InputEventType::MouseScroll => {
+                if let (Some(delta_x), Some(delta_y)) = (event.delta_x, event.delta_y) {
+                    // One discrete step per unit of delta, like the ydotool backend
+                    if delta_y != 0.0 {
+                        let steps = (delta_y.abs() as i32).max(1);
+                        self.notify_scroll(Axis::Vertical, if delta_y > 0.0 { steps } else { -steps })?;
+                    }
+                    if delta_x != 0.0 {
+                        let steps = (delta_x.abs() as i32).max(1);
+                        self.notify_scroll(Axis::Horizontal, if delta_x > 0.0 { steps } else { -steps })?;
+                    }
+                    Ok(())
+                } else {
+                    Err(InputForwardingError::UnsupportedEvent(
+                        "Mouse scroll event missing delta values".to_string()
+                    ))
+                }
+            },
+            InputEventType::KeyPress | InputEventType::KeyRelease => {
+                // The portal keeps no modifier list, the filter tracks held modifiers itself
+                let event = match self.shortcuts.lock().unwrap().check(event, &[]) {
+                    ShortcutOutcome::Forward(event) => event,
+                    ShortcutOutcome::Drop => return Ok(()),
+                    ShortcutOutcome::Inject(steps) => return self.inject_key_steps(&steps),
+                };
+                let key_code = event.key_code.ok_or_else(|| InputForwardingError::UnsupportedEvent(
+                    "Key event missing keyCode".to_string()
+                ))?;
+                let evdev = js_key_to_evdev(key_code).ok_or_else(|| InputForwardingError::UnsupportedEvent(
+                    format!("No evdev mapping for keyCode {}", key_code)
+                ))?;
+                let pressed = event.is_pressed.unwrap_or(matches!(event.event_type, InputEventType::KeyPress));
+                self.notify_key(evdev, pressed)?;
+                self.held_keys.lock().unwrap().record_key(&event);
+                Ok(())
+            },
+            InputEventType::TouchGesture => {
+                if let Some(gesture) = &event.gesture {
+                    self.handle_portal_gesture(gesture, event.gesture_direction.as_ref(), event.gesture_magnitude)
+                } else {
+                    Err(InputForwardingError::UnsupportedEvent(
+                        "TouchGesture event missing gesture type".to_string()
+                    ))
+                }
+            },
+            InputEventType::SpecialCommand => {
+                if let Some(command) = &event.special_command {
+                    self.handle_special_command(command)
+                } else {
+                    Err(InputForwardingError::UnsupportedEvent(
+                        "SpecialCommand event missing command type".to_string()
+                    ))
+                }
+            },
+            InputEventType::TouchDown | InputEventType::TouchMove | InputEventType::TouchUp | InputEventType::Ping => {
+                self.forward_common(&self.touch, event)
+            },
+        }
+    }
+
+    fn set_mode(&self, mode: InputMode) {
+        *self.mode.lock().unwrap() = mode;
+
+        if mode == InputMode::Enabled {
+            // Re-enabling is an explicit request, so consent may be asked again
+            *self.consent_denied.lock().unwrap() = false;
+            self.pointer.lock().unwrap().reset();
+        }
+        self.release_keys_unless_enabled(mode);
+    }
+
+    fn mode(&self) -> InputMode {
+        *self.mode.lock().unwrap()
+    }
+
+    fn configure_monitors(&self, monitors: Vec<MonitorConfiguration>) -> Result<(), InputForwardingError> {
+        utils::validate_monitor_config(&monitors)?;
+        self.touch.lock().unwrap().configure_monitors(&monitors);
+        *self.monitors.lock().unwrap() = monitors;
+        self.pointer.lock().unwrap().reset();
+        Ok(())
+    }
+
+    fn handle_special_command(&self, command: &SpecialCommand) -> Result<(), InputForwardingError> {
+        let guard = self.system_actions.lock().unwrap().clone();
+        guard.admit(command, Instant::now())?;
+        self.execute_special_command(command)
+    }
+
+    fn handle_gesture(&self, gesture: &TouchGesture, direction: Option<&GestureDirection>, magnitude: Option<f32>) -> Result<(), InputForwardingError> {
+        self.handle_portal_gesture(gesture, direction, magnitude)
+    }
+
+    fn backend(&self) -> InputBackend {
+        InputBackend::RemoteDesktopPortal
+    }
+
+    fn reset_input_state(&self) -> Result<(), InputForwardingError> {
+        let releases = self.held_keys.lock().unwrap().take_releases();
+        self.shortcuts.lock().unwrap().reset();
+        // The touchscreens are not the portal's, lifting contacts needs no session
+        let touch_result = self.touch.lock().unwrap().release_all();
+
+        // Without a session nothing was injected, and releasing must not ask for consent
+        if self.session.lock().unwrap().is_none() {
+            return touch_result;
+        }
+
+        let mut result = touch_result;
+        for key_code in releases.iter().filter_map(|release| release.key_code) {
+            if let Some(evdev) = js_key_to_evdev(key_code) {
+                if let Err(e) = self.notify_key(evdev, false) {
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn held_keys_idle_for(&self) -> Option<Duration> {
+        self.held_keys.lock().unwrap().idle_for()
+    }
+
+    fn set_system_action_guard(&self, guard: Arc<SystemActionGuard>) {
+        *self.system_actions.lock().unwrap() = guard;
+    }
+
+    fn set_shortcut_policy(&self, policy: &ShortcutPolicy) -> Result<(), InputForwardingError> {
+        self.shortcuts.lock().unwrap().set_policy(policy)
+    }
+}
+
+impl Drop for PortalInputForwarder {
+    fn drop(&mut self) {
+        if let Some(session) = self.session.get_mut().unwrap().take() {
+            let _ = tauri::async_runtime::block_on(session.session.close());
+        }
+    }
+}
+
+/// Map portal errors, turning a dismissed consent dialog into PermissionDenied
+fn to_input_error(e: ashpd::Error) -> InputForwardingError {
+    match e {
+        ashpd::Error::Response(ResponseError::Cancelled) => InputForwardingError::PermissionDenied(
+            "Remote control request was cancelled by the user".to_string()
+        ),
+        other => InputForwardingError::InitializationFailed(format!("RemoteDesktop portal: {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_js_keys_map_to_evdev() {
+        assert_eq!(js_key_to_evdev(65), Some(30)); // A
+        assert_eq!(js_key_to_evdev(81), Some(16)); // Q
+        assert_eq!(js_key_to_evdev(48), Some(11)); // 0
+        assert_eq!(js_key_to_evdev(49), Some(2));  // 1
+        assert_eq!(js_key_to_evdev(112), Some(59)); // F1
+        assert_eq!(js_key_to_evdev(123), Some(88)); // F12
+        assert_eq!(js_key_to_evdev(96), Some(82)); // Numpad 0
+        assert_eq!(js_key_to_evdev(105), Some(73)); // Numpad 9
+        assert_eq!(js_key_to_evdev(250), None);
+    }
+
+    #[test]
+    fn test_pointer_tracker_homes_once() {
+        let mut tracker = PointerTracker::default();
+        assert_eq!(tracker.motion_to(100, 50), vec![(HOMING_DISTANCE, HOMING_DISTANCE), (100.0, 50.0)]);
+        assert_eq!(tracker.motion_to(90, 70), vec![(-10.0, 20.0)]);
+        assert!(tracker.motion_to(90, 70).is_empty());
+
+        tracker.reset();
+        assert_eq!(tracker.motion_to(0, 0), vec![(HOMING_DISTANCE, HOMING_DISTANCE)]);
+    }
+
+    #[test]
+    fn test_cancelled_consent_is_permission_denied() {
+        let error = to_input_error(ashpd::Error::Response(ResponseError::Cancelled));
+        assert!(matches!(error, InputForwardingError::PermissionDenied(_)));
+    }
+}
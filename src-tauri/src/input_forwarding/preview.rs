@@ -0,0 +1,495 @@
+// preview.rs - Describing remote input instead of injecting it
+//
+// A support technician can show the customer what they are about to do
+// before they actually do it. In preview mode no event reaches the host;
+// each one is turned into a short description ("click at 400,300 on
+// monitor 1", "type 'ls -la'", "press Ctrl+Alt+T") and the descriptions are
+// sent to the host's UI in batches as `input_preview`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::input_forwarding::types::*;
+
+/// How often collected descriptions are sent as `input_preview`
+pub const PREVIEW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Descriptions per peer and batch; the rest is only counted
+const MAX_ACTIONS_PER_BATCH: usize = 50;
+
+/// How remote input is handled. The variants are ordered from the most to
+/// the least permissive, so the stricter of two modes is their maximum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InputMode {
+    /// Events are injected
+    Enabled,
+    /// Events are described to the host instead of injected
+    Preview,
+    /// Events are dropped
+    Disabled,
+}
+
+/// Sent with `input_preview`: what a peer would have done since the last batch
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputPreviewBatch {
+    pub peer_id: Option<String>,
+    pub actions: Vec<String>,
+    /// Actions left out because the batch was full
+    pub omitted: usize,
+}
+
+#[derive(Default)]
+struct PendingActions {
+    actions: Vec<String>,
+    /// Characters typed since the last other action, shown as one "type '...'"
+    typed: String,
+    /// The last action is a pointer move, which the next move replaces
+    last_was_move: bool,
+    omitted: usize,
+}
+
+impl PendingActions {
+    fn push(&mut self, action: String) {
+        self.flush_typed();
+        if self.actions.len() < MAX_ACTIONS_PER_BATCH {
+            self.actions.push(action);
+        } else {
+            self.omitted += 1;
+        }
+    }
+
+    fn flush_typed(&mut self) {
+        if !self.typed.is_empty() {
+            let typed = std::mem::take(&mut self.typed);
+            self.last_was_move = false;
+            self.push(format!("type '{}'", typed));
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.actions.is_empty() && self.typed.is_empty() && self.omitted == 0
+    }
+}
+
+/// Per-peer input modes and the descriptions waiting to be sent
+pub struct InputPreview {
+    peer_modes: Mutex<HashMap<String, InputMode>>,
+    pending: Mutex<Vec<(Option<String>, PendingActions)>>,
+}
+
+impl InputPreview {
+    pub fn new() -> Self {
+        InputPreview {
+            peer_modes: Mutex::new(HashMap::new()),
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Restricts the input of one peer; `Enabled` drops the restriction.
+    /// The mode outlives a reconnect, so a dropped connection does not lift a preview.
+    pub fn set_peer_mode(&self, peer_id: &str, mode: InputMode) {
+        let mut peer_modes = self.peer_modes.lock().unwrap();
+        if mode == InputMode::Enabled {
+            peer_modes.remove(peer_id);
+        } else {
+            peer_modes.insert(peer_id.to_string(), mode);
+        }
+    }
+
+    /// Mode set for the peer, `Enabled` if none was set
+    pub fn peer_mode(&self, peer_id: Option<&str>) -> InputMode {
+        peer_id
+            .and_then(|peer_id| self.peer_modes.lock().unwrap().get(peer_id).copied())
+            .unwrap_or(InputMode::Enabled)
+    }
+
+    /// Adds the description of an event that was not injected
+    pub fn record(&self, peer_id: Option<&str>, event: &InputEvent) {
+        let mut pending = self.pending.lock().unwrap();
+        let index = match pending.iter().position(|(peer, _)| peer.as_deref() == peer_id) {
+            Some(index) => index,
+            None => {
+                pending.push((peer_id.map(str::to_string), PendingActions::default()));
+                pending.len() - 1
+            }
+        };
+        let actions = &mut pending[index].1;
+
+        if let Some(c) = typed_char(event) {
+            actions.typed.push(c);
+            actions.last_was_move = false;
+            return;
+        }
+
+        let is_move = matches!(event.event_type, InputEventType::MouseMove | InputEventType::TouchMove);
+        if let Some(action) = describe(event) {
+            if is_move && actions.last_was_move && actions.omitted == 0 {
+                actions.actions.pop();
+            }
+            actions.push(action);
+            actions.last_was_move = is_move;
+        }
+    }
+
+    /// Takes the descriptions collected since the last call, one batch per peer
+    pub fn take_batches(&self) -> Vec<InputPreviewBatch> {
+        self.pending.lock().unwrap()
+            .drain(..)
+            .filter(|(_, actions)| !actions.is_empty())
+            .map(|(peer_id, mut actions)| {
+                actions.flush_typed();
+                InputPreviewBatch { peer_id, actions: actions.actions, omitted: actions.omitted }
+            })
+            .collect()
+    }
+}
+
+impl Default for InputPreview {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Human-readable description of an event; None for events that do nothing
+/// on their own, like releases and pings
+pub fn describe(event: &InputEvent) -> Option<String> {
+    match event.event_type {
+        InputEventType::MouseMove => Some(format!("move pointer to {}", position(event)?)),
+        InputEventType::MouseButton => {
+            if event.is_pressed != Some(true) {
+                return None;
+            }
+            let action = match event.button.as_ref()? {
+                MouseButton::Left => "click",
+                MouseButton::Middle => "middle-click",
+                MouseButton::Right => "right-click",
+                MouseButton::Back => "click back",
+                MouseButton::Forward => "click forward",
+                MouseButton::ScrollUp => "scroll up",
+                MouseButton::ScrollDown => "scroll down",
+                MouseButton::TouchTap => "tap",
+                MouseButton::TouchDoubleTap => "double-click",
+            };
+            match position(event) {
+                Some(position) => Some(format!("{} at {}", action, position)),
+                None => Some(action.to_string()),
+            }
+        },
+        InputEventType::MouseScroll => {
+            let mut parts = Vec::new();
+            let delta_y = event.delta_y.unwrap_or(0.0);
+            let delta_x = event.delta_x.unwrap_or(0.0);
+            if delta_y != 0.0 {
+                let direction = if delta_y > 0.0 { "down" } else { "up" };
+                parts.push(format!("scroll {} {}", direction, (delta_y.abs() as i32).max(1)));
+            }
+            if delta_x != 0.0 {
+                let direction = if delta_x > 0.0 { "right" } else { "left" };
+                parts.push(format!("scroll {} {}", direction, (delta_x.abs() as i32).max(1)));
+            }
+            (!parts.is_empty()).then(|| parts.join(", "))
+        },
+        InputEventType::KeyPress => {
+            let key_code = event.key_code?;
+            // Modifiers show up in the combination of the key they modify
+            if is_modifier_key(key_code) {
+                return None;
+            }
+            Some(format!("press {}", key_combo(key_code, event.modifiers.as_deref().unwrap_or_default())))
+        },
+        InputEventType::KeyRelease => None,
+        InputEventType::TouchDown => Some(format!("touch at {}", touch_position(event)?)),
+        InputEventType::TouchMove => Some(format!("drag touch to {}", touch_position(event)?)),
+        InputEventType::TouchUp => None,
+        InputEventType::TouchGesture => {
+            let gesture = match event.gesture.as_ref()? {
+                TouchGesture::Pinch => "pinch",
+                TouchGesture::Rotate => "rotate",
+                TouchGesture::ThreeFingerSwipe => "three-finger swipe",
+                TouchGesture::FourFingerSwipe => "four-finger swipe",
+                TouchGesture::TwoFingerScroll => "two-finger scroll",
+            };
+            let direction = event.gesture_direction.as_ref().map(|direction| match direction {
+                GestureDirection::Left => " left",
+                GestureDirection::Right => " right",
+                GestureDirection::Up => " up",
+                GestureDirection::Down => " down",
+            });
+            Some(format!("{}{}", gesture, direction.unwrap_or_default()))
+        },
+        InputEventType::SpecialCommand => Some(describe_command(event.special_command.as_ref()?)),
+        InputEventType::Ping => None,
+    }
+}
+
+/// What a special command does, e.g. "lock the screen"
+pub fn describe_command(command: &SpecialCommand) -> String {
+    let action = match command {
+        SpecialCommand::AppSwitcher => "switch applications",
+        SpecialCommand::DesktopToggle => "show the desktop",
+        SpecialCommand::ScreenSnapshot => "take a screenshot",
+        SpecialCommand::LockScreen => "lock the screen",
+        SpecialCommand::VolumeUp => "raise the volume",
+        SpecialCommand::VolumeDown => "lower the volume",
+        SpecialCommand::VolumeMute => "mute the volume",
+        SpecialCommand::MediaPlayPause => "play or pause media",
+        SpecialCommand::MediaNext => "skip to the next track",
+        SpecialCommand::MediaPrev => "go back to the previous track",
+        SpecialCommand::Custom(name) => return format!("run custom command '{}'", name),
+    };
+    action.to_string()
+}
+
+/// "400,300 on monitor 1", or just the coordinates without a monitor
+fn position(event: &InputEvent) -> Option<String> {
+    let (x, y) = (event.x?, event.y?);
+    Some(match event.monitor_index {
+        Some(monitor_index) => format!("{},{} on monitor {}", x, y, monitor_index),
+        None => format!("{},{}", x, y),
+    })
+}
+
+/// Touch positions are normalized, shown in percent of the monitor
+fn touch_position(event: &InputEvent) -> Option<String> {
+    let touch = event.touch.as_ref()?;
+    let position = format!("{:.0}%,{:.0}%", touch.x * 100.0, touch.y * 100.0);
+    Some(match event.monitor_index {
+        Some(monitor_index) => format!("{} on monitor {}", position, monitor_index),
+        None => position,
+    })
+}
+
+fn is_modifier_key(key_code: u32) -> bool {
+    matches!(key_code, 16 | 17 | 18 | 91 | 92)
+}
+
+fn has_modifier(modifiers: &[String], names: &[&str]) -> bool {
+    modifiers.iter().any(|modifier| names.contains(&modifier.to_lowercase().as_str()))
+}
+
+/// Character a key press types, if it types one: printable keys without
+/// Ctrl, Alt or Meta
+fn typed_char(event: &InputEvent) -> Option<char> {
+    if event.event_type != InputEventType::KeyPress {
+        return None;
+    }
+    let modifiers = event.modifiers.as_deref().unwrap_or_default();
+    if has_modifier(modifiers, &["ctrl", "control", "alt", "meta", "super", "win"]) {
+        return None;
+    }
+    printable_char(event.key_code?, has_modifier(modifiers, &["shift"]))
+}
+
+/// Character of a JavaScript keyCode on a US layout, like the forwarders' key mapping
+fn printable_char(key_code: u32, shift: bool) -> Option<char> {
+    let (plain, shifted) = match key_code {
+        65..=90 => {
+            let c = char::from_u32(key_code)?;
+            (c.to_ascii_lowercase(), c)
+        },
+        48..=57 => (char::from_u32(key_code)?, ")!@#$%^&*(".chars().nth((key_code - 48) as usize)?),
+        96..=105 => {
+            let c = char::from_u32(key_code - 48)?;
+            (c, c)
+        },
+        32 => (' ', ' '),
+        186 => (';', ':'),
+        187 => ('=', '+'),
+        188 => (',', '<'),
+        189 => ('-', '_'),
+        190 => ('.', '>'),
+        191 => ('/', '?'),
+        192 => ('`', '~'),
+        219 => ('[', '{'),
+        220 => ('\\', '|'),
+        221 => (']', '}'),
+        222 => ('\'', '"'),
+        _ => return None,
+    };
+    Some(if shift { shifted } else { plain })
+}
+
+/// Renders a key with its modifiers, e.g. "Ctrl+Alt+T"
+fn key_combo(key_code: u32, modifiers: &[String]) -> String {
+    let mut names: Vec<String> = [
+        (&["ctrl", "control"][..], "Ctrl"),
+        (&["alt"][..], "Alt"),
+        (&["shift"][..], "Shift"),
+        (&["meta", "super", "win"][..], "Super"),
+    ]
+    .iter()
+    .filter(|(aliases, _)| has_modifier(modifiers, aliases))
+    .map(|(_, name)| name.to_string())
+    .collect();
+    names.push(key_name(key_code));
+    names.join("+")
+}
+
+fn key_name(key_code: u32) -> String {
+    let name = match key_code {
+        48..=57 | 65..=90 => return char::from_u32(key_code).map(String::from).unwrap_or_default(),
+        112..=123 => return format!("F{}", key_code - 111),
+        96..=105 => return format!("Numpad {}", key_code - 96),
+        8 => "Backspace",
+        9 => "Tab",
+        13 => "Enter",
+        19 => "Pause",
+        20 => "CapsLock",
+        27 => "Escape",
+        32 => "Space",
+        33 => "PageUp",
+        34 => "PageDown",
+        35 => "End",
+        36 => "Home",
+        37 => "Left",
+        38 => "Up",
+        39 => "Right",
+        40 => "Down",
+        44 => "PrintScreen",
+        45 => "Insert",
+        46 => "Delete",
+        93 => "Menu",
+        _ => return printable_char(key_code, false)
+            .map(String::from)
+            .unwrap_or_else(|| format!("key {}", key_code)),
+    };
+    name.to_string()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event_type: InputEventType) -> InputEvent {
+        InputEvent {
+            event_type,
+            x: None,
+            y: None,
+            button: None,
+            key_code: None,
+            modifiers: None,
+            is_pressed: None,
+            delta_x: None,
+            delta_y: None,
+            monitor_index: None,
+            gesture: None,
+            gesture_direction: None,
+            gesture_magnitude: None,
+            special_command: None,
+            ping_id: None,
+            client_timestamp: None,
+            touch: None,
+        }
+    }
+
+    fn pointer(event_type: InputEventType, x: i32, y: i32) -> InputEvent {
+        InputEvent { x: Some(x), y: Some(y), monitor_index: Some(1), ..event(event_type) }
+    }
+
+    fn key(key_code: u32, is_pressed: bool, modifiers: &[&str]) -> InputEvent {
+        InputEvent {
+            key_code: Some(key_code),
+            is_pressed: Some(is_pressed),
+            modifiers: Some(modifiers.iter().map(|m| m.to_string()).collect()),
+            ..event(if is_pressed { InputEventType::KeyPress } else { InputEventType::KeyRelease })
+        }
+    }
+
+    #[test]
+    fn test_descriptions() {
+        let click = InputEvent { button: Some(MouseButton::Left), is_pressed: Some(true), ..pointer(InputEventType::MouseButton, 400, 300) };
+        assert_eq!(describe(&click).as_deref(), Some("click at 400,300 on monitor 1"));
+        assert_eq!(describe(&InputEvent { is_pressed: Some(false), ..click }), None);
+
+        assert_eq!(describe(&key(84, true, &["ctrl", "alt"])).as_deref(), Some("press Ctrl+Alt+T"));
+        assert_eq!(describe(&key(46, true, &["control", "alt"])).as_deref(), Some("press Ctrl+Alt+Delete"));
+        assert_eq!(describe(&key(17, true, &["ctrl"])), None);
+
+        let scroll = InputEvent { delta_y: Some(3.0), delta_x: Some(0.0), ..event(InputEventType::MouseScroll) };
+        assert_eq!(describe(&scroll).as_deref(), Some("scroll down 3"));
+
+        let custom = InputEvent { special_command: Some(SpecialCommand::Custom("backup".to_string())), ..event(InputEventType::SpecialCommand) };
+        assert_eq!(describe(&custom).as_deref(), Some("run custom command 'backup'"));
+        assert_eq!(describe(&event(InputEventType::Ping)), None);
+
+        let touch = InputEvent {
+            touch: Some(TouchPoint { id: 1, x: 0.25, y: 0.5, pressure: None }),
+            monitor_index: Some(1),
+            ..event(InputEventType::TouchDown)
+        };
+        assert_eq!(describe(&touch).as_deref(), Some("touch at 25%,50% on monitor 1"));
+        assert_eq!(describe(&InputEvent { event_type: InputEventType::TouchUp, ..touch }), None);
+    }
+
+    #[test]
+    fn test_batches_merge_typing_and_moves() {
+        let preview = InputPreview::new();
+
+        preview.record(Some("peer-1"), &pointer(InputEventType::MouseMove, 10, 10));
+        preview.record(Some("peer-1"), &pointer(InputEventType::MouseMove, 20, 20));
+        // "ls -la", then Enter
+        for (key_code, modifiers) in [(76, &[][..]), (83, &[]), (32, &[]), (189, &[]), (76, &[]), (65, &[])] {
+            preview.record(Some("peer-1"), &key(key_code, true, modifiers));
+            preview.record(Some("peer-1"), &key(key_code, false, modifiers));
+        }
+        preview.record(Some("peer-1"), &key(13, true, &[]));
+        preview.record(Some("peer-2"), &key(67, true, &["ctrl"]));
+        preview.record(Some("peer-2"), &key(49, true, &["shift"]));
+
+        assert_eq!(preview.take_batches(), vec![
+            InputPreviewBatch {
+                peer_id: Some("peer-1".to_string()),
+                actions: vec!["move pointer to 20,20 on monitor 1".to_string(), "type 'ls -la'".to_string(), "press Enter".to_string()],
+                omitted: 0,
+            },
+            InputPreviewBatch {
+                peer_id: Some("peer-2".to_string()),
+                actions: vec!["press Ctrl+C".to_string(), "type '!'".to_string()],
+                omitted: 0,
+            },
+        ]);
+        assert!(preview.take_batches().is_empty());
+
+        // Releases alone make no batch
+        preview.record(None, &key(65, false, &[]));
+        assert!(preview.take_batches().is_empty());
+    }
+
+    #[test]
+    fn test_full_batch_counts_the_rest() {
+        let preview = InputPreview::new();
+        for i in 0..(MAX_ACTIONS_PER_BATCH + 5) {
+            preview.record(None, &key(13, true, &[]));
+            preview.record(None, &pointer(InputEventType::MouseMove, i as i32, 0));
+        }
+
+        let batches = preview.take_batches();
+        assert_eq!(batches[0].actions.len(), MAX_ACTIONS_PER_BATCH);
+        assert_eq!(batches[0].omitted, 60);
+    }
+
+    #[test]
+    fn test_peer_modes() {
+        let preview = InputPreview::new();
+        assert_eq!(preview.peer_mode(Some("peer-1")), InputMode::Enabled);
+
+        preview.set_peer_mode("peer-1", InputMode::Preview);
+        assert_eq!(preview.peer_mode(Some("peer-1")), InputMode::Preview);
+        assert_eq!(preview.peer_mode(Some("peer-2")), InputMode::Enabled);
+        assert_eq!(preview.peer_mode(None), InputMode::Enabled);
+
+        // A peer's mode never lifts a stricter host-wide one
+        assert_eq!(InputMode::Disabled.max(preview.peer_mode(Some("peer-1"))), InputMode::Disabled);
+        assert_eq!(InputMode::Enabled.max(preview.peer_mode(Some("peer-1"))), InputMode::Preview);
+
+        preview.set_peer_mode("peer-1", InputMode::Enabled);
+        assert_eq!(preview.peer_mode(Some("peer-1")), InputMode::Enabled);
+        assert_eq!(serde_json::to_value(InputMode::Preview).unwrap(), serde_json::json!("preview"));
+    }
+}
@@ -0,0 +1,543 @@
+// recorder.rs - Recording and replay of input macros
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::input_forwarding::error::InputForwardingError;
+use crate::input_forwarding::types::*;
+use crate::logging::TARGET_INPUT;
+
+/// Subdirectory of the app data directory holding one JSON file per macro
+const MACRO_DIR: &str = "macros";
+
+/// Longest sleep between checks of the cancel flag during replay
+const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(20);
+
+// One event of a macro with its offset from the start of the recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub offset_ms: u64,
+    pub event: InputEvent,
+}
+
+// A named sequence of input events
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputMacro {
+    pub name: String,
+    pub created_at: String,
+    // Monitor layout at recording time, absolute positions depend on it
+    pub monitors: Vec<MonitorConfiguration>,
+    pub events: Vec<RecordedEvent>,
+}
+
+impl InputMacro {
+    // Duration of the macro at normal speed
+    pub fn duration_ms(&self) -> u64 {
+        self.events.last().map(|event| event.offset_ms).unwrap_or(0)
+    }
+}
+
+// How a replay ended
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayOutcome {
+    pub name: String,
+    pub completed: bool,
+    pub events_sent: usize,
+    pub error: Option<String>,
+}
+
+struct Recording {
+    started: Instant,
+    monitors: Vec<MonitorConfiguration>,
+    events: Vec<RecordedEvent>,
+}
+
+/// Records forwarded events while armed and replays stored macros
+pub struct InputMacroRecorder {
+    storage_dir: Mutex<Option<PathBuf>>,
+    recording: Mutex<Option<Recording>>,
+    replay_cancel: Arc<Mutex<Option<Arc<AtomicBool>>>>,
+}
+
+impl Default for InputMacroRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputMacroRecorder {
+    pub fn new() -> Self {
+        InputMacroRecorder {
+            storage_dir: Mutex::new(None),
+            recording: Mutex::new(None),
+            replay_cancel: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Macros are stored below `data_dir`
+    pub fn set_storage_dir(&self, data_dir: PathBuf) {
+        *self.storage_dir.lock().unwrap() = Some(data_dir.join(MACRO_DIR));
+    }
+
+    /// Arms the recorder; `monitors` is the current layout
+    pub fn start_recording(&self, monitors: Vec<MonitorConfiguration>) -> Result<(), InputForwardingError> {
+        let mut recording = self.recording.lock().unwrap();
+        if recording.is_some() {
+            return Err(InputForwardingError::MacroError("A recording is already running".to_string()));
+        }
+
+        *recording = Some(Recording {
+            started: Instant::now(),
+            monitors,
+            events: Vec::new(),
+        });
+        Ok(())
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.lock().unwrap().is_some()
+    }
+
+    /// Called for every forwarded event; ignored unless recording
+    pub fn record(&self, event: &InputEvent) {
+        if let Some(recording) = &mut *self.recording.lock().unwrap() {
+            recording.events.push(RecordedEvent {
+                offset_ms: recording.started.elapsed().as_millis() as u64,
+                event: event.clone(),
+            });
+        }
+    }
+
+    /// Stops recording and stores the macro under `name`
+    pub fn stop_recording(&self, name: &str) -> Result<InputMacro, InputForwardingError> {
+        validate_name(name)?;
+        let recording = self.recording.lock().unwrap().take()
+            .ok_or_else(|| InputForwardingError::MacroError("No recording is running".to_string()))?;
+
+        let input_macro = InputMacro {
+            name: name.to_string(),
+            created_at: chrono::Utc::now().to_rfc3339(),
+            monitors: recording.monitors,
+            events: recording.events,
+        };
+        self.save_macro(&input_macro)?;
+
+        log::info!(target: TARGET_INPUT, "Recorded input macro '{}' with {} events", name, input_macro.events.len());
+        Ok(input_macro)
+    }
+
+    /// Discards a running recording
+    pub fn discard_recording(&self) {
+        *self.recording.lock().unwrap() = None;
+    }
+
+    pub fn list_macros(&self) -> Result<Vec<String>, InputForwardingError> {
+        let dir = self.macro_dir()?;
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let entries = fs::read_dir(&dir).map_err(|e| io_error("read macro directory", e))?;
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != "json" {
+                    return None;
+                }
+                path.file_stem()?.to_str().map(str::to_string)
+            })
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn load_macro(&self, name: &str) -> Result<InputMacro, InputForwardingError> {
+        let path = self.macro_path(name)?;
+        let json = fs::read(&path)
+            .map_err(|_| InputForwardingError::MacroError(format!("Unknown input macro '{}'", name)))?;
+        serde_json::from_slice(&json)
+            .map_err(|e| InputForwardingError::MacroError(format!("Invalid input macro '{}': {}", name, e)))
+    }
+
+    pub fn delete_macro(&self, name: &str) -> Result<(), InputForwardingError> {
+        let path = self.macro_path(name)?;
+        fs::remove_file(&path)
+            .map_err(|_| InputForwardingError::MacroError(format!("Unknown input macro '{}'", name)))
+    }
+
+    /// Replays a macro on a background thread through `forward`, keeping the
+    /// recorded timing divided by `speed_factor`. Returns warnings, e.g. when
+    /// the monitor layout differs from the one at recording time.
+    pub fn replay<F, D>(
+        &self,
+        input_macro: InputMacro,
+        speed_factor: f32,
+        current_monitors: &[MonitorConfiguration],
+        forward: F,
+        on_finish: D,
+    ) -> Result<Vec<String>, InputForwardingError>
+    where
+        F: Fn(&InputEvent) -> Result<(), InputForwardingError> + Send + 'static,
+        D: FnOnce(ReplayOutcome) + Send + 'static,
+    {
+        if !(speed_factor > 0.0 && speed_factor.is_finite()) {
+            return Err(InputForwardingError::MacroError("Speed factor must be greater than zero".to_string()));
+        }
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        {
+            let mut slot = self.replay_cancel.lock().unwrap();
+            if slot.is_some() {
+                return Err(InputForwardingError::MacroError("Another macro is being replayed".to_string()));
+            }
+            *slot = Some(cancel.clone());
+        }
+
+        let mut warnings = Vec::new();
+        if !same_layout(&input_macro.monitors, current_monitors) {
+            let warning = format!(
+                "Macro '{}' was recorded on a different monitor layout, pointer positions may be off",
+                input_macro.name
+            );
+            log::warn!(target: TARGET_INPUT, "{}", warning);
+            warnings.push(warning);
+        }
+
+        let slot = self.replay_cancel.clone();
+        thread::spawn(move || {
+            let outcome = run_replay(&input_macro, speed_factor, &cancel, &forward);
+            log::info!(target: TARGET_INPUT, "Replay of '{}' ended after {} events (completed: {})",
+                outcome.name, outcome.events_sent, outcome.completed);
+
+            let mut current = slot.lock().unwrap();
+            if current.as_ref().map(|flag| Arc::ptr_eq(flag, &cancel)).unwrap_or(false) {
+                *current = None;
+            }
+            drop(current);
+
+            on_finish(outcome);
+        });
+
+        Ok(warnings)
+    }
+
+    /// Stops a running replay; pressed keys and buttons are released
+    pub fn cancel_replay(&self) -> bool {
+        match &*self.replay_cancel.lock().unwrap() {
+            Some(cancel) => {
+                cancel.store(true, Ordering::SeqCst);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replay_cancel.lock().unwrap().is_some()
+    }
+
+    fn save_macro(&self, input_macro: &InputMacro) -> Result<(), InputForwardingError> {
+        let dir = self.macro_dir()?;
+        fs::create_dir_all(&dir).map_err(|e| io_error("create macro directory", e))?;
+
+        let json = serde_json::to_vec_pretty(input_macro)
+            .map_err(|e| InputForwardingError::MacroError(format!("Failed to serialize macro: {}", e)))?;
+        fs::write(self.macro_path(&input_macro.name)?, json).map_err(|e| io_error("write macro", e))
+    }
+
+    fn macro_dir(&self) -> Result<PathBuf, InputForwardingError> {
+        self.storage_dir.lock().unwrap().clone()
+            .ok_or_else(|| InputForwardingError::MacroError("No storage directory for macros".to_string()))
+    }
+
+    fn macro_path(&self, name: &str) -> Result<PathBuf, InputForwardingError> {
+        validate_name(name)?;
+        Ok(self.macro_dir()?.join(format!("{}.json", name)))
+    }
+}
+
+/// Sends the events in order; on cancel or error everything still held down is released
+fn run_replay<F>(input_macro: &InputMacro, speed_factor: f32, cancel: &AtomicBool, forward: &F) -> ReplayOutcome
+where
+    F: Fn(&InputEvent) -> Result<(), InputForwardingError>,
+{
+    let started = Instant::now();
+    let mut held = HeldInputs::default();
+    let mut events_sent = 0;
+    let mut error = None;
+
+    for recorded in &input_macro.events {
+        let due = Duration::from_secs_f64(recorded.offset_ms as f64 / 1000.0 / speed_factor as f64);
+        while !cancel.load(Ordering::SeqCst) {
+            match due.checked_sub(started.elapsed()) {
+                Some(remaining) if !remaining.is_zero() => thread::sleep(remaining.min(CANCEL_CHECK_INTERVAL)),
+                _ => break,
+            }
+        }
+        if cancel.load(Ordering::SeqCst) {
+            break;
+        }
+
+        if let Err(e) = forward(&recorded.event) {
+            error = Some(e.to_string());
+            break;
+        }
+        held.track(&recorded.event);
+        events_sent += 1;
+    }
+
+    let completed = events_sent == input_macro.events.len();
+    if !completed {
+        // No stuck Ctrl or held mouse button after an aborted replay
+        for release in held.releases() {
+            if let Err(e) = forward(&release) {
+                log::error!(target: TARGET_INPUT, "Failed to release input after aborted replay: {}", e);
+            }
+        }
+    }
+
+    ReplayOutcome {
+        name: input_macro.name.clone(),
+        completed,
+        events_sent,
+        error,
+    }
+}
+
+/// Keys and mouse buttons pressed but not yet released during replay
+#[derive(Default)]
+struct HeldInputs {
+    keys: Vec<u32>,
+    buttons: Vec<MouseButton>,
+}
+
+impl HeldInputs {
+    fn track(&mut self, event: &InputEvent) {
+        match event.event_type {
+            InputEventType::KeyPress => {
+                if let Some(key_code) = event.key_code {
+                    if !self.keys.contains(&key_code) {
+                        self.keys.push(key_code);
+                    }
+                }
+            },
+            InputEventType::KeyRelease => {
+                if let Some(key_code) = event.key_code {
+                    self.keys.retain(|held| *held != key_code);
+                }
+            },
+            InputEventType::MouseButton => {
+                if let Some(button) = &event.button {
+                    let name = format!("{:?}", button);
+                    self.buttons.retain(|held| format!("{:?}", held) != name);
+                    if event.is_pressed.unwrap_or(false) {
+                        self.buttons.push(button.clone());
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Release events, keys in reverse press order so modifiers go last
+    fn releases(&self) -> Vec<InputEvent> {
+        let keys = self.keys.iter().rev().map(|key_code| InputEvent {
+            key_code: Some(*key_code),
+            is_pressed: Some(false),
+            ..empty_event(InputEventType::KeyRelease)
+        });
+        let buttons = self.buttons.iter().map(|button| InputEvent {
+            button: Some(button.clone()),
+            is_pressed: Some(false),
+            ..empty_event(InputEventType::MouseButton)
+        });
+
+        keys.chain(buttons).collect()
+    }
+}
+
+fn empty_event(event_type: InputEventType) -> InputEvent {
+    InputEvent {
+        event_type,
+        x: None,
+        y: None,
+        button: None,
+        key_code: None,
+        modifiers: None,
+        is_pressed: None,
+        delta_x: None,
+        delta_y: None,
+        monitor_index: None,
+        gesture: None,
+        gesture_direction: None,
+        gesture_magnitude: None,
+        special_command: None,
+        ping_id: None,
+        client_timestamp: None,
+        touch: None,
+    }
+}
+
+/// Whether two layouts place monitors at the same positions and sizes
+fn same_layout(recorded: &[MonitorConfiguration], current: &[MonitorConfiguration]) -> bool {
+    let geometry = |monitors: &[MonitorConfiguration]| -> HashSet<(i32, i32, i32, i32, String)> {
+        monitors.iter()
+            .map(|m| (m.x_offset, m.y_offset, m.width, m.height, format!("{:?}", m.rotation)))
+            .collect()
+    };
+
+    geometry(recorded) == geometry(current)
+}
+
+/// Macro names become file names
+fn validate_name(name: &str) -> Result<(), InputForwardingError> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == ' ');
+    if !valid {
+        return Err(InputForwardingError::MacroError(format!(
+            "Invalid macro name '{}': use up to 64 letters, digits, spaces, '-' or '_'", name
+        )));
+    }
+    Ok(())
+}
+
+fn io_error(action: &str, e: std::io::Error) -> InputForwardingError {
+    InputForwardingError::MacroError(format!("Failed to {}: {}", action, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    fn key(event_type: InputEventType, key_code: u32) -> InputEvent {
+        InputEvent {
+            key_code: Some(key_code),
+            ..empty_event(event_type)
+        }
+    }
+
+    fn monitor(x_offset: i32) -> MonitorConfiguration {
+        MonitorConfiguration {
+            index: 0,
+            x_offset,
+            y_offset: 0,
+            width: 1920,
+            height: 1080,
+            scale_factor: 1.0,
+            rotation: MonitorRotation::Normal,
+            is_primary: true,
+        }
+    }
+
+    fn test_macro(events: Vec<(u64, InputEvent)>) -> InputMacro {
+        InputMacro {
+            name: "test".to_string(),
+            created_at: String::new(),
+            monitors: vec![monitor(0)],
+            events: events.into_iter()
+                .map(|(offset_ms, event)| RecordedEvent { offset_ms, event })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_records_only_while_armed() {
+        let dir = std::env::temp_dir().join(format!("smoldesk-macro-{}", uuid::Uuid::new_v4()));
+        let recorder = InputMacroRecorder::new();
+        recorder.set_storage_dir(dir.clone());
+
+        recorder.record(&key(InputEventType::KeyPress, 30));
+        recorder.start_recording(vec![monitor(0)]).unwrap();
+        recorder.record(&key(InputEventType::KeyPress, 31));
+        recorder.record(&key(InputEventType::KeyRelease, 31));
+        let input_macro = recorder.stop_recording("greeting").unwrap();
+        recorder.record(&key(InputEventType::KeyPress, 32));
+
+        assert_eq!(input_macro.events.len(), 2);
+        assert!(input_macro.events[0].offset_ms <= input_macro.events[1].offset_ms);
+        assert_eq!(recorder.list_macros().unwrap(), vec!["greeting"]);
+        assert_eq!(recorder.load_macro("greeting").unwrap().events.len(), 2);
+
+        recorder.delete_macro("greeting").unwrap();
+        assert!(recorder.list_macros().unwrap().is_empty());
+        assert!(recorder.stop_recording("../escape").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cancelled_replay_releases_held_keys() {
+        // Ctrl (29) held, then a long pause before the release
+        let input_macro = test_macro(vec![
+            (0, key(InputEventType::KeyPress, 29)),
+            (0, key(InputEventType::KeyPress, 46)),
+            (0, key(InputEventType::KeyRelease, 46)),
+            (60_000, key(InputEventType::KeyRelease, 29)),
+        ]);
+
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let sent_events = sent.clone();
+        let (done, finished) = mpsc::channel();
+
+        let recorder = InputMacroRecorder::new();
+        recorder.replay(
+            input_macro,
+            1.0,
+            &[monitor(0)],
+            move |event| {
+                sent_events.lock().unwrap().push((format!("{:?}", event.event_type), event.key_code));
+                Ok(())
+            },
+            move |outcome| done.send(outcome).unwrap(),
+        ).unwrap();
+
+        thread::sleep(Duration::from_millis(100));
+        assert!(recorder.is_replaying());
+        assert!(recorder.cancel_replay());
+
+        let outcome = finished.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert!(!outcome.completed);
+        assert_eq!(outcome.events_sent, 3);
+        assert!(!recorder.is_replaying());
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.last().unwrap(), &("KeyRelease".to_string(), Some(29)));
+        assert_eq!(sent.len(), 4);
+    }
+
+    #[test]
+    fn test_speed_factor_and_layout_warning() {
+        let input_macro = test_macro(vec![
+            (0, key(InputEventType::KeyPress, 30)),
+            (400, key(InputEventType::KeyRelease, 30)),
+        ]);
+        let (done, finished) = mpsc::channel();
+
+        let recorder = InputMacroRecorder::new();
+        assert!(recorder.replay(input_macro.clone(), 0.0, &[], |_| Ok(()), |_| {}).is_err());
+
+        let started = Instant::now();
+        let warnings = recorder.replay(
+            input_macro,
+            4.0,
+            &[monitor(0), monitor(1920)],
+            |_| Ok(()),
+            move |outcome| done.send(outcome).unwrap(),
+        ).unwrap();
+
+        assert_eq!(warnings.len(), 1);
+        let outcome = finished.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert!(outcome.completed);
+        assert!(started.elapsed() < Duration::from_millis(400));
+    }
+}
@@ -0,0 +1,470 @@
+// shortcuts.rs - Which key combinations reach the host
+//
+// Some shortcuts are meant for the host, like Alt+Tab; others would wreck the
+// session there, like Ctrl+Alt+F1 switching the host to a text console. Every
+// key press is matched against the policy before injection and forwarded,
+// dropped or replaced by another combination. A key's release follows what
+// happened to its press, even when the modifiers were let go first.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::input_forwarding::error::InputForwardingError;
+use crate::input_forwarding::types::InputEvent;
+
+/// Modifier as named in `InputEvent::modifiers` and in combinations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Modifier {
+    Shift,
+    Ctrl,
+    Alt,
+    Meta,
+}
+
+impl Modifier {
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "shift" => Some(Modifier::Shift),
+            "ctrl" | "control" => Some(Modifier::Ctrl),
+            "alt" => Some(Modifier::Alt),
+            "meta" | "super" | "win" => Some(Modifier::Meta),
+            _ => None,
+        }
+    }
+
+    /// JavaScript keyCode of the modifier key itself
+    fn key_code(self) -> u32 {
+        match self {
+            Modifier::Shift => 16,
+            Modifier::Ctrl => 17,
+            Modifier::Alt => 18,
+            Modifier::Meta => 91,
+        }
+    }
+
+    fn from_key_code(key_code: u32) -> Option<Self> {
+        match key_code {
+            16 => Some(Modifier::Shift),
+            17 => Some(Modifier::Ctrl),
+            18 => Some(Modifier::Alt),
+            91 | 92 => Some(Modifier::Meta),
+            _ => None,
+        }
+    }
+}
+
+/// JavaScript keyCode of a key name, e.g. "F1", "Delete" or "t"
+fn key_code(name: &str) -> Option<u32> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_alphanumeric() {
+            return Some(c.to_ascii_uppercase() as u32);
+        }
+    }
+
+    let lower = name.to_lowercase();
+    if let Some(number) = lower.strip_prefix('f').and_then(|n| n.parse::<u32>().ok()) {
+        return (1..=12).contains(&number).then_some(111 + number);
+    }
+
+    let code = match lower.as_str() {
+        "backspace" => 8,
+        "tab" => 9,
+        "enter" | "return" => 13,
+        "pause" => 19,
+        "capslock" => 20,
+        "escape" | "esc" => 27,
+        "space" => 32,
+        "pageup" => 33,
+        "pagedown" => 34,
+        "end" => 35,
+        "home" => 36,
+        "left" => 37,
+        "up" => 38,
+        "right" => 39,
+        "down" => 40,
+        "insert" => 45,
+        "delete" | "del" => 46,
+        "menu" => 93,
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// A key together with the modifiers held for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyCombo {
+    modifiers: BTreeSet<Modifier>,
+    key_code: u32,
+}
+
+impl KeyCombo {
+    /// Parses "ctrl+alt+F1"; the order of the modifiers does not matter.
+    /// A lone modifier such as "meta" stands for the modifier key itself.
+    pub fn parse(combo: &str) -> Result<Self, InputForwardingError> {
+        let names: Vec<&str> = combo.split('+').map(str::trim).collect();
+        if names.iter().any(|name| name.is_empty()) {
+            return Err(invalid(combo, "empty key name"));
+        }
+
+        if let [name] = names.as_slice() {
+            if let Some(modifier) = Modifier::parse(name) {
+                return Ok(KeyCombo { modifiers: BTreeSet::new(), key_code: modifier.key_code() });
+            }
+        }
+
+        let mut modifiers = BTreeSet::new();
+        let mut key = None;
+        for name in names {
+            if let Some(modifier) = Modifier::parse(name) {
+                modifiers.insert(modifier);
+            } else if key.is_some() {
+                return Err(invalid(combo, "more than one non-modifier key"));
+            } else {
+                key = Some(key_code(name).ok_or_else(|| invalid(combo, &format!("unknown key '{}'", name)))?);
+            }
+        }
+
+        let key_code = key.ok_or_else(|| invalid(combo, "no key besides the modifiers"))?;
+        Ok(KeyCombo { modifiers, key_code })
+    }
+}
+
+fn invalid(combo: &str, reason: &str) -> InputForwardingError {
+    InputForwardingError::UnsupportedEvent(format!("Invalid key combination '{}': {}", combo, reason))
+}
+
+/// What happens to a key combination pressed by the remote peer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ShortcutAction {
+    ForwardToHost,
+    Ignore,
+    /// Press another combination on the host instead, e.g. "ctrl+alt+Left"
+    TranslateTo(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShortcutRule {
+    pub combo: String,
+    pub action: ShortcutAction,
+}
+
+/// Rules for key combinations; combinations without a rule are forwarded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShortcutPolicy {
+    pub rules: Vec<ShortcutRule>,
+}
+
+impl Default for ShortcutPolicy {
+    /// Keeps combinations away from the host that end or leave the graphical session
+    fn default() -> Self {
+        let rule = |combo: &str, action: ShortcutAction| ShortcutRule { combo: combo.to_string(), action };
+
+        // Virtual terminal switches
+        let mut rules: Vec<ShortcutRule> = (1..=12)
+            .map(|n| rule(&format!("ctrl+alt+F{}", n), ShortcutAction::Ignore))
+            .collect();
+        // Logout dialog or reboot, and the X server's zap
+        rules.push(rule("ctrl+alt+Delete", ShortcutAction::Ignore));
+        rules.push(rule("ctrl+alt+Backspace", ShortcutAction::Ignore));
+        // Listed so the viewer knows to capture them instead of its own desktop
+        rules.push(rule("alt+Tab", ShortcutAction::ForwardToHost));
+        rules.push(rule("meta", ShortcutAction::ForwardToHost));
+
+        ShortcutPolicy { rules }
+    }
+}
+
+impl ShortcutPolicy {
+    /// Checks that every combination and translation target can be parsed
+    pub fn validate(&self) -> Result<(), InputForwardingError> {
+        compile(self).map(drop)
+    }
+}
+
+/// What a forwarder injects for a key event
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShortcutOutcome {
+    /// The event, without modifiers whose own key press was not forwarded
+    Forward(InputEvent),
+    Drop,
+    /// Key presses (true) and releases (false) by JavaScript keyCode, in order
+    Inject(Vec<(u32, bool)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Action {
+    Forward,
+    Ignore,
+    Translate(KeyCombo),
+}
+
+fn compile(policy: &ShortcutPolicy) -> Result<Vec<(KeyCombo, Action)>, InputForwardingError> {
+    policy.rules.iter()
+        .map(|rule| {
+            let action = match &rule.action {
+                ShortcutAction::ForwardToHost => Action::Forward,
+                ShortcutAction::Ignore => Action::Ignore,
+                ShortcutAction::TranslateTo(target) => Action::Translate(KeyCombo::parse(target)?),
+            };
+            Ok((KeyCombo::parse(&rule.combo)?, action))
+        })
+        .collect()
+}
+
+/// Applies a policy to the key events of one forwarder
+#[derive(Debug)]
+pub struct ShortcutFilter {
+    rules: Vec<(KeyCombo, Action)>,
+    /// Modifier keys the peer holds, whether or not they reached the host
+    held_modifiers: BTreeSet<Modifier>,
+    /// Keys whose press was dropped or translated; their release is dropped as well
+    swallowed: BTreeSet<u32>,
+}
+
+impl Default for ShortcutFilter {
+    fn default() -> Self {
+        Self::new(&ShortcutPolicy::default()).expect("default shortcut policy is valid")
+    }
+}
+
+impl ShortcutFilter {
+    pub fn new(policy: &ShortcutPolicy) -> Result<Self, InputForwardingError> {
+        Ok(ShortcutFilter {
+            rules: compile(policy)?,
+            held_modifiers: BTreeSet::new(),
+            swallowed: BTreeSet::new(),
+        })
+    }
+
+    /// Replaces the rules; keys already swallowed still have their release dropped
+    pub fn set_policy(&mut self, policy: &ShortcutPolicy) -> Result<(), InputForwardingError> {
+        self.rules = compile(policy)?;
+        Ok(())
+    }
+
+    /// Forgets held keys, e.g. after the forwarder released everything
+    pub fn reset(&mut self) {
+        self.held_modifiers.clear();
+        self.swallowed.clear();
+    }
+
+    /// Decides about a key event. `active_modifiers` are the modifiers the
+    /// forwarder tracks from earlier events, which the event may not repeat.
+    pub fn check(&mut self, event: &InputEvent, active_modifiers: &[String]) -> ShortcutOutcome {
+        let (Some(key), Some(is_pressed)) = (event.key_code, event.is_pressed) else {
+            return ShortcutOutcome::Forward(event.clone());
+        };
+        let own_modifier = Modifier::from_key_code(key);
+
+        if !is_pressed {
+            if let Some(modifier) = own_modifier {
+                self.held_modifiers.remove(&modifier);
+            }
+            if self.swallowed.remove(&key) {
+                return ShortcutOutcome::Drop;
+            }
+            return ShortcutOutcome::Forward(self.strip_swallowed(event));
+        }
+
+        let mut modifiers = self.held_modifiers.clone();
+        modifiers.extend(event.modifiers.iter().flatten().chain(active_modifiers).filter_map(|m| Modifier::parse(m)));
+        if let Some(modifier) = own_modifier {
+            // The browser lists a modifier key among its own modifiers
+            modifiers.remove(&modifier);
+            self.held_modifiers.insert(modifier);
+        }
+
+        let action = self.rules.iter()
+            .find(|(combo, _)| combo.key_code == key && combo.modifiers == modifiers)
+            .map(|(_, action)| action.clone());
+
+        match action {
+            None | Some(Action::Forward) => ShortcutOutcome::Forward(self.strip_swallowed(event)),
+            Some(Action::Ignore) => {
+                self.swallowed.insert(key);
+                ShortcutOutcome::Drop
+            },
+            Some(Action::Translate(target)) => {
+                self.swallowed.insert(key);
+                let on_host: BTreeSet<Modifier> = modifiers.into_iter()
+                    .filter(|modifier| !self.swallowed.contains(&modifier.key_code()))
+                    .collect();
+                ShortcutOutcome::Inject(translation(&on_host, &target))
+            },
+        }
+    }
+
+    fn strip_swallowed(&self, event: &InputEvent) -> InputEvent {
+        let mut event = event.clone();
+        if let Some(modifiers) = &mut event.modifiers {
+            modifiers.retain(|name| {
+                !matches!(Modifier::parse(name), Some(modifier) if self.swallowed.contains(&modifier.key_code()))
+            });
+        }
+        event
+    }
+}
+
+/// Presses `target` with exactly its modifiers and puts the host's modifiers back afterwards
+fn translation(on_host: &BTreeSet<Modifier>, target: &KeyCombo) -> Vec<(u32, bool)> {
+    let lifted: Vec<u32> = on_host.difference(&target.modifiers).map(|m| m.key_code()).collect();
+    let added: Vec<u32> = target.modifiers.difference(on_host).map(|m| m.key_code()).collect();
+
+    let mut steps = Vec::new();
+    steps.extend(lifted.iter().map(|&code| (code, false)));
+    steps.extend(added.iter().map(|&code| (code, true)));
+    steps.push((target.key_code, true));
+    steps.push((target.key_code, false));
+    steps.extend(added.iter().rev().map(|&code| (code, false)));
+    steps.extend(lifted.iter().map(|&code| (code, true)));
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_forwarding::types::InputEventType;
+
+    fn key(key_code: u32, is_pressed: bool, modifiers: &[&str]) -> InputEvent {
+        InputEvent {
+            event_type: if is_pressed { InputEventType::KeyPress } else { InputEventType::KeyRelease },
+            x: None,
+            y: None,
+            button: None,
+            key_code: Some(key_code),
+            modifiers: Some(modifiers.iter().map(|m| m.to_string()).collect()),
+            is_pressed: Some(is_pressed),
+            delta_x: None,
+            delta_y: None,
+            monitor_index: None,
+            gesture: None,
+            gesture_direction: None,
+            gesture_magnitude: None,
+            special_command: None,
+            ping_id: None,
+            client_timestamp: None,
+            touch: None,
+        }
+    }
+
+    fn policy(rules: &[(&str, ShortcutAction)]) -> ShortcutPolicy {
+        ShortcutPolicy {
+            rules: rules.iter().map(|(combo, action)| ShortcutRule { combo: combo.to_string(), action: action.clone() }).collect(),
+        }
+    }
+
+    fn is_forward(outcome: &ShortcutOutcome) -> bool {
+        matches!(outcome, ShortcutOutcome::Forward(_))
+    }
+
+    #[test]
+    fn test_parse_combo() {
+        assert_eq!(KeyCombo::parse("ctrl+alt+F1").unwrap(), KeyCombo::parse("Alt + Control + f1").unwrap());
+        assert_eq!(KeyCombo::parse("ctrl+alt+Delete").unwrap().key_code, 46);
+        assert_eq!(KeyCombo::parse("super+t").unwrap(), KeyCombo::parse("meta+T").unwrap());
+
+        let lone = KeyCombo::parse("meta").unwrap();
+        assert_eq!((lone.modifiers.len(), lone.key_code), (0, 91));
+
+        for bad in ["", "ctrl+", "ctrl+alt", "ctrl+a+b", "ctrl+F13", "hyper+x"] {
+            assert!(KeyCombo::parse(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_default_policy_ignores_console_switch() {
+        let mut filter = ShortcutFilter::default();
+
+        assert!(is_forward(&filter.check(&key(17, true, &["ctrl"]), &[])));
+        assert!(is_forward(&filter.check(&key(18, true, &["ctrl", "alt"]), &[])));
+        assert_eq!(filter.check(&key(114, true, &["ctrl", "alt"]), &[]), ShortcutOutcome::Drop);
+        assert_eq!(filter.check(&key(46, true, &["alt", "ctrl"]), &[]), ShortcutOutcome::Drop);
+        assert_eq!(filter.check(&key(8, true, &["ctrl", "alt"]), &[]), ShortcutOutcome::Drop);
+
+        // Extra modifiers make it a different combination
+        assert!(is_forward(&filter.check(&key(115, true, &["ctrl", "alt", "shift"]), &[])));
+        // Without rule or with ForwardToHost, events pass unchanged
+        assert_eq!(filter.check(&key(84, true, &["ctrl"]), &[]), ShortcutOutcome::Forward(key(84, true, &["ctrl"])));
+        assert!(is_forward(&filter.check(&key(9, true, &["alt"]), &[])));
+        ShortcutPolicy::default().validate().unwrap();
+    }
+
+    #[test]
+    fn test_release_follows_press() {
+        let mut filter = ShortcutFilter::default();
+        assert_eq!(filter.check(&key(112, true, &["ctrl", "alt"]), &[]), ShortcutOutcome::Drop);
+
+        // Modifiers released first: the F1 release no longer matches the combination
+        assert!(is_forward(&filter.check(&key(17, false, &[]), &[])));
+        assert!(is_forward(&filter.check(&key(18, false, &[]), &[])));
+        assert_eq!(filter.check(&key(112, false, &[]), &[]), ShortcutOutcome::Drop);
+
+        // The next F1 on its own reaches the host
+        assert!(is_forward(&filter.check(&key(112, true, &[]), &[])));
+        assert!(is_forward(&filter.check(&key(112, false, &[]), &[])));
+    }
+
+    #[test]
+    fn test_modifiers_from_forwarder_tracking() {
+        let mut filter = ShortcutFilter::default();
+
+        // The event itself lists no modifiers, the forwarder still holds Ctrl and Alt
+        let active = vec!["alt".to_string(), "ctrl".to_string()];
+        assert_eq!(filter.check(&key(113, true, &[]), &active), ShortcutOutcome::Drop);
+
+        // Modifier key events are tracked by the filter itself, e.g. for the portal
+        let mut filter = ShortcutFilter::default();
+        filter.check(&key(17, true, &[]), &[]);
+        filter.check(&key(18, true, &[]), &[]);
+        assert_eq!(filter.check(&key(113, true, &[]), &[]), ShortcutOutcome::Drop);
+        filter.check(&key(18, false, &[]), &[]);
+        assert!(is_forward(&filter.check(&key(113, true, &[]), &[])));
+
+        filter.reset();
+        assert!(is_forward(&filter.check(&key(113, true, &[]), &[])));
+    }
+
+    #[test]
+    fn test_ignored_modifier_is_stripped() {
+        let mut filter = ShortcutFilter::new(&policy(&[("meta", ShortcutAction::Ignore)])).unwrap();
+
+        // The browser reports the Meta keydown with "meta" already set
+        assert_eq!(filter.check(&key(91, true, &["meta"]), &[]), ShortcutOutcome::Drop);
+        // Keys pressed meanwhile reach the host without the swallowed Meta
+        assert_eq!(filter.check(&key(76, true, &["meta"]), &[]), ShortcutOutcome::Forward(key(76, true, &[])));
+        assert_eq!(filter.check(&key(91, false, &[]), &[]), ShortcutOutcome::Drop);
+        assert_eq!(filter.check(&key(76, false, &["meta"]), &[]), ShortcutOutcome::Forward(key(76, false, &["meta"])));
+    }
+
+    #[test]
+    fn test_translation() {
+        let mut filter = ShortcutFilter::new(&policy(&[
+            ("meta+Left", ShortcutAction::TranslateTo("ctrl+alt+Left".to_string())),
+            ("ctrl+q", ShortcutAction::TranslateTo("ctrl+w".to_string())),
+        ])).unwrap();
+
+        // Meta is down on the host and lifted around ctrl+alt+Left
+        filter.check(&key(91, true, &["meta"]), &[]);
+        assert_eq!(filter.check(&key(37, true, &["meta"]), &[]), ShortcutOutcome::Inject(vec![
+            (91, false), (17, true), (18, true), (37, true), (37, false), (18, false), (17, false), (91, true),
+        ]));
+        assert_eq!(filter.check(&key(37, false, &["meta"]), &[]), ShortcutOutcome::Drop);
+        filter.check(&key(91, false, &[]), &[]);
+
+        // Shared modifiers stay pressed
+        filter.check(&key(17, true, &["ctrl"]), &[]);
+        assert_eq!(filter.check(&key(81, true, &["ctrl"]), &[]), ShortcutOutcome::Inject(vec![(87, true), (87, false)]));
+    }
+
+    #[test]
+    fn test_invalid_policy_is_rejected() {
+        let bad = policy(&[("ctrl+alt+F1", ShortcutAction::TranslateTo("ctrl+nope".to_string()))]);
+        assert!(bad.validate().is_err());
+
+        let mut filter = ShortcutFilter::default();
+        assert!(filter.set_policy(&bad).is_err());
+        assert_eq!(filter.check(&key(112, true, &["ctrl", "alt"]), &[]), ShortcutOutcome::Drop);
+    }
+}
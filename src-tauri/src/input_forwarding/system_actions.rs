@@ -0,0 +1,407 @@
+// system_actions.rs - Guards special commands that can lock the technician out
+//
+// Locking the screen or pressing Ctrl+Alt+Del on an unattended machine leaves
+// a remote peer with nobody to unlock it. Such commands are categorized as
+// disruptive by an editable table. A peer runs them only with the
+// `SystemActions` right; for everyone else the configured default decides:
+// allow, deny, or ask the host, who has `ask_timeout_secs` to answer before
+// the request is denied.
+//
+// The app decides who may run what, but the forwarders enforce it: every
+// `handle_special_command` asks the guard to admit the command, and a
+// disruptive command is only admitted with a clearance the app issued for it.
+// A backend cannot run one that was never decided on.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::input_forwarding::custom_command::{CustomCommand, KeyChord};
+use crate::input_forwarding::error::InputForwardingError;
+use crate::input_forwarding::preview::describe_command;
+use crate::input_forwarding::types::SpecialCommand;
+use crate::logging::TARGET_INPUT;
+
+/// How long a clearance waits for the forwarder before it lapses
+pub const CLEARANCE_TTL: Duration = Duration::from_secs(5);
+
+/// Longest the host may be given to answer a request
+const MAX_ASK_TIMEOUT_SECS: u64 = 600;
+
+/// What happens to a disruptive command of a peer without the `SystemActions` right
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SystemActionPolicy {
+    Allow,
+    Deny,
+    /// The host is asked; no answer in time counts as deny
+    Ask,
+}
+
+/// Which special commands are disruptive and how they are handled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SystemActionConfig {
+    pub default_policy: SystemActionPolicy,
+    /// Built-in commands; custom commands are matched by `disruptive_key_combinations`
+    pub disruptive_commands: Vec<SpecialCommand>,
+    /// A custom `key` command is disruptive if it presses one of these, with
+    /// or without further keys. Compared as `KeyChord`s, so the modifiers'
+    /// order, case and aliases do not matter.
+    pub disruptive_key_combinations: Vec<String>,
+    pub ask_timeout_secs: u64,
+}
+
+impl Default for SystemActionConfig {
+    fn default() -> Self {
+        SystemActionConfig {
+            default_policy: SystemActionPolicy::Ask,
+            disruptive_commands: vec![SpecialCommand::LockScreen],
+            disruptive_key_combinations: vec![
+                "ctrl+alt+Delete".to_string(),
+                "ctrl+alt+KP_Delete".to_string(),
+                "ctrl+alt+BackSpace".to_string(),
+                "super+l".to_string(),
+                "XF86PowerOff".to_string(),
+                "XF86LogOff".to_string(),
+                "XF86Sleep".to_string(),
+            ],
+            ask_timeout_secs: 30,
+        }
+    }
+}
+
+impl SystemActionConfig {
+    pub fn validate(&self) -> Result<(), InputForwardingError> {
+        if let Some(custom) = self.disruptive_commands.iter().find(|command| matches!(command, SpecialCommand::Custom(_))) {
+            return Err(InputForwardingError::UnsupportedEvent(format!(
+                "{:?} cannot be listed as a command, add its key combination instead", custom
+            )));
+        }
+        for combination in &self.disruptive_key_combinations {
+            KeyChord::parse(combination.trim())?;
+        }
+        if !(1..=MAX_ASK_TIMEOUT_SECS).contains(&self.ask_timeout_secs) {
+            return Err(InputForwardingError::UnsupportedEvent(format!(
+                "Ask timeout must be 1-{} seconds", MAX_ASK_TIMEOUT_SECS
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn is_disruptive(&self, command: &SpecialCommand) -> bool {
+        match command {
+            // The parsed keys are compared, not the text a peer can spell differently
+            SpecialCommand::Custom(text) => match CustomCommand::parse(text) {
+                Ok(CustomCommand::Key(combinations)) => {
+                    let disruptive: Vec<KeyChord> = self.disruptive_key_combinations.iter()
+                        .filter_map(|combination| KeyChord::parse(combination.trim()).ok())
+                        .collect();
+                    combinations.iter()
+                        .filter_map(|combination| KeyChord::parse(combination).ok())
+                        .any(|chord| disruptive.iter().any(|disruptive| chord.covers(disruptive)))
+                },
+                // Typed text presses no shortcuts, and the forwarders refuse invalid commands
+                _ => false,
+            },
+            command => self.disruptive_commands.contains(command),
+        }
+    }
+}
+
+/// A disruptive command waiting for the host; emitted as `system_action_requested`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemActionRequest {
+    pub request_id: u64,
+    pub peer_id: String,
+    pub command: SpecialCommand,
+    /// E.g. "lock the screen"
+    pub description: String,
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum SystemActionOutcome {
+    Allowed,
+    Denied,
+    /// The host did not answer in time, which denies the command
+    TimedOut,
+}
+
+/// How a request ended; emitted as `system_action_resolved`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemActionResolution {
+    pub request: SystemActionRequest,
+    pub outcome: SystemActionOutcome,
+}
+
+/// What becomes of a special command a peer sent
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActionDecision {
+    /// May be forwarded now
+    Allowed,
+    Denied(String),
+    /// Held back until the host answers
+    Pending(SystemActionRequest),
+}
+
+#[derive(Default)]
+struct GuardState {
+    next_request_id: u64,
+    pending: BTreeMap<u64, (SystemActionRequest, Instant)>,
+    /// Disruptive commands decided on but not yet run, with when they were
+    cleared: Vec<(SpecialCommand, Instant)>,
+}
+
+/// Shared by the app, which decides, and the forwarders, which enforce
+pub struct SystemActionGuard {
+    config: Mutex<SystemActionConfig>,
+    state: Mutex<GuardState>,
+}
+
+impl Default for SystemActionGuard {
+    fn default() -> Self {
+        Self::new(SystemActionConfig::default())
+    }
+}
+
+impl SystemActionGuard {
+    pub fn new(config: SystemActionConfig) -> Self {
+        SystemActionGuard {
+            config: Mutex::new(config),
+            state: Mutex::new(GuardState::default()),
+        }
+    }
+
+    pub fn config(&self) -> SystemActionConfig {
+        self.config.lock().unwrap().clone()
+    }
+
+    /// Applies to commands sent from now on; open requests keep their timeout
+    pub fn set_config(&self, config: SystemActionConfig) {
+        *self.config.lock().unwrap() = config;
+    }
+
+    /// Decides on a special command. `peer_id` is None for the host's own
+    /// frontend, which is never asked; `granted` is whether the peer holds
+    /// the `SystemActions` right.
+    pub fn request(&self, peer_id: Option<&str>, granted: bool, command: &SpecialCommand, now: Instant) -> ActionDecision {
+        let config = self.config();
+        if !config.is_disruptive(command) {
+            return ActionDecision::Allowed;
+        }
+
+        let peer_id = match peer_id {
+            Some(peer_id) if !granted => peer_id,
+            _ => {
+                self.clear(command, now);
+                return ActionDecision::Allowed;
+            },
+        };
+        match config.default_policy {
+            SystemActionPolicy::Allow => {
+                self.clear(command, now);
+                ActionDecision::Allowed
+            },
+            SystemActionPolicy::Deny => ActionDecision::Denied(format!(
+                "The host does not allow peers to {}", describe_command(command)
+            )),
+            SystemActionPolicy::Ask => {
+                let mut state = self.state.lock().unwrap();
+                state.next_request_id += 1;
+                let request = SystemActionRequest {
+                    request_id: state.next_request_id,
+                    peer_id: peer_id.to_string(),
+                    command: command.clone(),
+                    description: describe_command(command),
+                    timeout_secs: config.ask_timeout_secs,
+                };
+                let deadline = now + Duration::from_secs(config.ask_timeout_secs);
+                state.pending.insert(request.request_id, (request.clone(), deadline));
+                ActionDecision::Pending(request)
+            },
+        }
+    }
+
+    /// The host answers a request; None if it is unknown or already resolved.
+    /// An allowed command is cleared and has to be forwarded right away.
+    pub fn answer(&self, request_id: u64, allow: bool, now: Instant) -> Option<SystemActionResolution> {
+        let (request, _) = self.state.lock().unwrap().pending.remove(&request_id)?;
+        let outcome = if allow {
+            self.clear(&request.command, now);
+            SystemActionOutcome::Allowed
+        } else {
+            SystemActionOutcome::Denied
+        };
+        Some(SystemActionResolution { request, outcome })
+    }
+
+    /// Denies the requests the host did not answer in time
+    pub fn expire(&self, now: Instant) -> Vec<SystemActionResolution> {
+        let mut state = self.state.lock().unwrap();
+        let expired: Vec<u64> = state.pending.iter()
+            .filter(|(_, (_, deadline))| now >= *deadline)
+            .map(|(request_id, _)| *request_id)
+            .collect();
+        expired.into_iter()
+            .filter_map(|request_id| state.pending.remove(&request_id))
+            .map(|(request, _)| SystemActionResolution { request, outcome: SystemActionOutcome::TimedOut })
+            .collect()
+    }
+
+    pub fn pending(&self) -> Vec<SystemActionRequest> {
+        self.state.lock().unwrap().pending.values().map(|(request, _)| request.clone()).collect()
+    }
+
+    /// Called by the forwarders before running a special command. A disruptive
+    /// one uses up its clearance; without one it is refused.
+    pub fn admit(&self, command: &SpecialCommand, now: Instant) -> Result<(), InputForwardingError> {
+        let config = self.config();
+        if !config.is_disruptive(command) || config.default_policy == SystemActionPolicy::Allow {
+            return Ok(());
+        }
+
+        let mut state = self.state.lock().unwrap();
+        state.cleared.retain(|(_, cleared_at)| now.saturating_duration_since(*cleared_at) < CLEARANCE_TTL);
+        match state.cleared.iter().position(|(cleared, _)| cleared == command) {
+            Some(index) => {
+                state.cleared.remove(index);
+                Ok(())
+            },
+            None => {
+                log::warn!(target: TARGET_INPUT, "Refused {:?} without clearance", command);
+                Err(InputForwardingError::PermissionDenied(format!(
+                    "Permission to {} was not granted", describe_command(command)
+                )))
+            },
+        }
+    }
+
+    fn clear(&self, command: &SpecialCommand, now: Instant) {
+        self.state.lock().unwrap().cleared.push((command.clone(), now));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom(text: &str) -> SpecialCommand {
+        SpecialCommand::Custom(text.to_string())
+    }
+
+    #[test]
+    fn test_categorization() {
+        let config = SystemActionConfig::default();
+        assert!(config.is_disruptive(&SpecialCommand::LockScreen));
+        assert!(config.is_disruptive(&custom("key ctrl+alt+Delete")));
+        assert!(config.is_disruptive(&custom("key Return CTRL+ALT+BACKSPACE")));
+        assert!(!config.is_disruptive(&SpecialCommand::AppSwitcher));
+        assert!(!config.is_disruptive(&custom("key super+left")));
+        assert!(!config.is_disruptive(&custom("type ctrl+alt")));
+        assert!(!config.is_disruptive(&custom("type ctrl+alt+Delete")));
+
+        assert!(config.validate().is_ok());
+        let listed = SystemActionConfig { disruptive_commands: vec![custom("key a")], ..config.clone() };
+        assert!(listed.validate().is_err());
+        let invalid = SystemActionConfig { disruptive_key_combinations: vec!["ctrl+".to_string()], ..config.clone() };
+        assert!(invalid.validate().is_err());
+        assert!(SystemActionConfig { ask_timeout_secs: 0, ..config }.validate().is_err());
+    }
+
+    #[test]
+    fn test_respelled_combinations_are_still_disruptive() {
+        let config = SystemActionConfig::default();
+        for command in [
+            "key alt+ctrl+Delete",
+            "key 'ctrl'+alt+Delete",
+            "key \"ctrl+alt\"+Delete",
+            "key  super+l",
+            "key Super_L+l",
+            "key Control_L+Alt_R+KP_Delete",
+            "key ctrl+meta+BackSpace",
+            "key ctrl+alt+shift+Delete",
+            "key Return xf86poweroff",
+        ] {
+            assert!(config.is_disruptive(&custom(command)), "not disruptive: {}", command);
+        }
+        for command in ["key super+Left", "key ctrl+l", "key Delete", "key ctrl+alt+Delete;"] {
+            assert!(!config.is_disruptive(&custom(command)), "disruptive: {}", command);
+        }
+    }
+
+    #[test]
+    fn test_routine_and_granted_commands_pass() {
+        let guard = SystemActionGuard::default();
+        let now = Instant::now();
+        assert_eq!(guard.request(Some("peer"), false, &SpecialCommand::VolumeUp, now), ActionDecision::Allowed);
+        assert!(guard.admit(&SpecialCommand::VolumeUp, now).is_ok());
+
+        // The clearance is good for one run only
+        assert_eq!(guard.request(Some("peer"), true, &SpecialCommand::LockScreen, now), ActionDecision::Allowed);
+        assert!(guard.admit(&SpecialCommand::LockScreen, now).is_ok());
+        assert!(matches!(guard.admit(&SpecialCommand::LockScreen, now), Err(InputForwardingError::PermissionDenied(_))));
+
+        // The host's own frontend is never asked, but its clearance lapses too
+        assert_eq!(guard.request(None, false, &SpecialCommand::LockScreen, now), ActionDecision::Allowed);
+        assert!(guard.admit(&SpecialCommand::LockScreen, now + CLEARANCE_TTL).is_err());
+    }
+
+    #[test]
+    fn test_default_policy_for_peers_without_the_right() {
+        let now = Instant::now();
+        let deny = SystemActionGuard::new(SystemActionConfig { default_policy: SystemActionPolicy::Deny, ..SystemActionConfig::default() });
+        assert!(matches!(deny.request(Some("peer"), false, &SpecialCommand::LockScreen, now), ActionDecision::Denied(_)));
+        assert!(deny.admit(&SpecialCommand::LockScreen, now).is_err());
+
+        let allow = SystemActionGuard::new(SystemActionConfig { default_policy: SystemActionPolicy::Allow, ..SystemActionConfig::default() });
+        assert_eq!(allow.request(Some("peer"), false, &custom("key ctrl+alt+Delete"), now), ActionDecision::Allowed);
+        assert!(allow.admit(&custom("key ctrl+alt+Delete"), now).is_ok());
+    }
+
+    #[test]
+    fn test_host_answers_a_request() {
+        let guard = SystemActionGuard::default();
+        let now = Instant::now();
+        let ActionDecision::Pending(request) = guard.request(Some("peer"), false, &SpecialCommand::LockScreen, now) else {
+            panic!("expected a request to the host");
+        };
+        assert_eq!((request.description.as_str(), request.timeout_secs), ("lock the screen", 30));
+        assert!(guard.admit(&SpecialCommand::LockScreen, now).is_err());
+
+        let resolution = guard.answer(request.request_id, true, now).unwrap();
+        assert_eq!(resolution.outcome, SystemActionOutcome::Allowed);
+        assert!(guard.admit(&SpecialCommand::LockScreen, now).is_ok());
+        assert!(guard.answer(request.request_id, true, now).is_none());
+
+        let ActionDecision::Pending(request) = guard.request(Some("peer"), false, &SpecialCommand::LockScreen, now) else {
+            panic!("expected a request to the host");
+        };
+        assert_eq!(guard.answer(request.request_id, false, now).unwrap().outcome, SystemActionOutcome::Denied);
+        assert!(guard.admit(&SpecialCommand::LockScreen, now).is_err());
+    }
+
+    #[test]
+    fn test_unanswered_request_times_out_as_denied() {
+        let guard = SystemActionGuard::default();
+        let now = Instant::now();
+        let ActionDecision::Pending(request) = guard.request(Some("peer"), false, &custom("key ctrl+alt+Delete"), now) else {
+            panic!("expected a request to the host");
+        };
+
+        assert!(guard.expire(now + Duration::from_secs(29)).is_empty());
+        let expired = guard.expire(now + Duration::from_secs(30));
+        assert_eq!(expired, vec![SystemActionResolution { request: request.clone(), outcome: SystemActionOutcome::TimedOut }]);
+        assert!(guard.pending().is_empty());
+
+        // A late answer changes nothing and the command stays refused
+        assert!(guard.answer(request.request_id, true, now + Duration::from_secs(31)).is_none());
+        assert!(matches!(
+            guard.admit(&custom("key ctrl+alt+Delete"), now + Duration::from_secs(31)),
+            Err(InputForwardingError::PermissionDenied(_))
+        ));
+    }
+}
@@ -0,0 +1,612 @@
+// touchscreen.rs - Multitouch input through virtual uinput touchscreens
+//
+// TouchDown, TouchMove and TouchUp carry a contact ID and a position
+// normalized to the monitor. Where /dev/uinput is writable, the first touch on
+// a monitor creates a virtual touchscreen with that monitor's size, and the
+// contacts are multiplexed onto the slots of the multitouch protocol (type B):
+// a contact keeps its slot from TouchDown to TouchUp, so two fingers dragging
+// make two independent tracks. Which output a touchscreen drives is up to the
+// compositor (sway `map_to_output`, `xinput map-to-output` under X11); a
+// device is destroyed when its monitor disappears or changes size and comes
+// back sized anew with the next touch.
+//
+// Without uinput a single contact is injected as the pointer with the left
+// button held. A second finger cannot be expressed that way and is rejected
+// with UnsupportedEvent instead of turning a pinch into a stray drag.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+
+use crate::input_forwarding::types::*;
+use crate::input_forwarding::error::InputForwardingError;
+use crate::logging::TARGET_INPUT;
+
+const UINPUT_DEVICE: &str = "/dev/uinput";
+
+/// Contacts one touchscreen tracks at once
+const MAX_SLOTS: usize = 10;
+
+/// Range of ABS_PRESSURE and ABS_MT_PRESSURE
+const PRESSURE_MAX: i32 = 255;
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_ABS: u16 = 0x03;
+const SYN_REPORT: u16 = 0x00;
+const BTN_TOUCH: u16 = 0x14a;
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_PRESSURE: u16 = 0x18;
+const ABS_MT_SLOT: u16 = 0x2f;
+const ABS_MT_POSITION_X: u16 = 0x35;
+const ABS_MT_POSITION_Y: u16 = 0x36;
+const ABS_MT_TRACKING_ID: u16 = 0x39;
+const ABS_MT_PRESSURE: u16 = 0x3a;
+const INPUT_PROP_DIRECT: u16 = 0x01;
+const BUS_VIRTUAL: u16 = 0x06;
+
+/// One evdev event as written to a touchscreen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EvdevEvent {
+    pub kind: u16,
+    pub code: u16,
+    pub value: i32,
+}
+
+impl EvdevEvent {
+    fn abs(code: u16, value: i32) -> Self {
+        EvdevEvent { kind: EV_ABS, code, value }
+    }
+}
+
+/// A virtual touchscreen
+pub trait TouchDevice: Send {
+    /// Write one frame, which already ends with SYN_REPORT
+    fn write_frame(&mut self, events: &[EvdevEvent]) -> Result<(), InputForwardingError>;
+}
+
+/// Creates the touchscreen of a monitor
+pub type TouchDeviceFactory = Box<dyn Fn(&MonitorConfiguration) -> Result<Box<dyn TouchDevice>, InputForwardingError> + Send>;
+
+/// The touchscreen of one monitor and the contacts on it
+struct Touchscreen {
+    device: Box<dyn TouchDevice>,
+    width: i32,
+    height: i32,
+    /// Contact ID in each slot
+    slots: [Option<u32>; MAX_SLOTS],
+    /// Slot the last ABS_MT_SLOT selected
+    current_slot: Option<usize>,
+    next_tracking_id: i32,
+}
+
+impl Touchscreen {
+    fn new(device: Box<dyn TouchDevice>, monitor: &MonitorConfiguration) -> Self {
+        Touchscreen {
+            device,
+            width: monitor.width,
+            height: monitor.height,
+            slots: [None; MAX_SLOTS],
+            current_slot: None,
+            next_tracking_id: 0,
+        }
+    }
+
+    fn slot_of(&self, id: u32) -> Option<usize> {
+        self.slots.iter().position(|slot| *slot == Some(id))
+    }
+
+    fn select_slot(&mut self, slot: usize, frame: &mut Vec<EvdevEvent>) {
+        if self.current_slot != Some(slot) {
+            frame.push(EvdevEvent::abs(ABS_MT_SLOT, slot as i32));
+            self.current_slot = Some(slot);
+        }
+    }
+
+    /// Position and pressure of a contact; the lowest slot also drives the
+    /// single-touch axes
+    fn report_position(&self, slot: usize, touch: &TouchPoint, frame: &mut Vec<EvdevEvent>) {
+        let x = scale(touch.x, self.width);
+        let y = scale(touch.y, self.height);
+        let pressure = touch.pressure.map_or(PRESSURE_MAX, |pressure| (pressure.clamp(0.0, 1.0) * PRESSURE_MAX as f32).round() as i32);
+        frame.extend([
+            EvdevEvent::abs(ABS_MT_POSITION_X, x),
+            EvdevEvent::abs(ABS_MT_POSITION_Y, y),
+            EvdevEvent::abs(ABS_MT_PRESSURE, pressure),
+        ]);
+        if self.slots.iter().position(Option::is_some) == Some(slot) {
+            frame.extend([
+                EvdevEvent::abs(ABS_X, x),
+                EvdevEvent::abs(ABS_Y, y),
+                EvdevEvent::abs(ABS_PRESSURE, pressure),
+            ]);
+        }
+    }
+
+    fn lift(&mut self, slot: usize, frame: &mut Vec<EvdevEvent>) {
+        self.select_slot(slot, frame);
+        frame.push(EvdevEvent::abs(ABS_MT_TRACKING_ID, -1));
+        self.slots[slot] = None;
+    }
+
+    fn forward(&mut self, event_type: &InputEventType, touch: &TouchPoint) -> Result<(), InputForwardingError> {
+        let mut frame = Vec::new();
+        match (event_type, self.slot_of(touch.id)) {
+            (InputEventType::TouchDown, None) => {
+                let slot = self.slots.iter().position(Option::is_none).ok_or_else(|| InputForwardingError::UnsupportedEvent(
+                    format!("A touchscreen takes at most {} contacts", MAX_SLOTS)
+                ))?;
+                let first = self.slots.iter().all(Option::is_none);
+                self.slots[slot] = Some(touch.id);
+                self.select_slot(slot, &mut frame);
+                frame.push(EvdevEvent::abs(ABS_MT_TRACKING_ID, self.next_tracking_id));
+                self.next_tracking_id = (self.next_tracking_id + 1) & 0xffff;
+                self.report_position(slot, touch, &mut frame);
+                if first {
+                    frame.push(EvdevEvent { kind: EV_KEY, code: BTN_TOUCH, value: 1 });
+                }
+            },
+            // A repeated TouchDown only moves the contact
+            (InputEventType::TouchDown | InputEventType::TouchMove, Some(slot)) => {
+                self.select_slot(slot, &mut frame);
+                self.report_position(slot, touch, &mut frame);
+            },
+            (InputEventType::TouchMove, None) => {
+                return Err(InputForwardingError::UnsupportedEvent(format!("Touch {} moved without TouchDown", touch.id)));
+            },
+            (InputEventType::TouchUp, Some(slot)) => {
+                self.lift(slot, &mut frame);
+                if self.slots.iter().all(Option::is_none) {
+                    frame.push(EvdevEvent { kind: EV_KEY, code: BTN_TOUCH, value: 0 });
+                }
+            },
+            // Lifted already, e.g. by a reset
+            _ => return Ok(()),
+        }
+        frame.push(EvdevEvent { kind: EV_SYN, code: SYN_REPORT, value: 0 });
+        self.device.write_frame(&frame)
+    }
+
+    fn release_all(&mut self) -> Result<(), InputForwardingError> {
+        let held: Vec<usize> = (0..MAX_SLOTS).filter(|slot| self.slots[*slot].is_some()).collect();
+        if held.is_empty() {
+            return Ok(());
+        }
+
+        let mut frame = Vec::new();
+        for slot in held {
+            self.lift(slot, &mut frame);
+        }
+        frame.push(EvdevEvent { kind: EV_KEY, code: BTN_TOUCH, value: 0 });
+        frame.push(EvdevEvent { kind: EV_SYN, code: SYN_REPORT, value: 0 });
+        self.device.write_frame(&frame)
+    }
+}
+
+/// Normalized coordinate to a device position on an axis of `size` pixels
+fn scale(value: f32, size: i32) -> i32 {
+    (value.clamp(0.0, 1.0) * (size - 1).max(0) as f32).round() as i32
+}
+
+/// Where touch events go
+pub struct TouchInput {
+    /// None injects a single contact as the pointer
+    factory: Option<TouchDeviceFactory>,
+    touchscreens: HashMap<usize, Touchscreen>,
+    monitors: Vec<MonitorConfiguration>,
+    /// Contact injected as the pointer
+    pointer_contact: Option<u32>,
+}
+
+impl TouchInput {
+    /// uinput touchscreens where /dev/uinput is writable, the pointer otherwise
+    pub fn detect() -> Self {
+        let writable = OpenOptions::new().read(true).write(true).open(UINPUT_DEVICE).is_ok();
+        if writable {
+            Self::with_devices(Box::new(|monitor: &MonitorConfiguration| {
+                UinputTouchscreen::create(monitor).map(|device| Box::new(device) as Box<dyn TouchDevice>)
+            }))
+        } else {
+            log::info!(target: TARGET_INPUT, "{} is not writable, touch input moves the pointer with a single contact", UINPUT_DEVICE);
+            Self::pointer_only()
+        }
+    }
+
+    pub fn with_devices(factory: TouchDeviceFactory) -> Self {
+        TouchInput {
+            factory: Some(factory),
+            touchscreens: HashMap::new(),
+            monitors: Vec::new(),
+            pointer_contact: None,
+        }
+    }
+
+    pub fn pointer_only() -> Self {
+        TouchInput {
+            factory: None,
+            touchscreens: HashMap::new(),
+            monitors: Vec::new(),
+            pointer_contact: None,
+        }
+    }
+
+    /// Destroys the touchscreens of monitors that are gone or changed size
+    pub fn configure_monitors(&mut self, monitors: &[MonitorConfiguration]) {
+        self.touchscreens.retain(|index, touchscreen| {
+            monitors.iter().any(|monitor| monitor.index == *index && monitor.width == touchscreen.width && monitor.height == touchscreen.height)
+        });
+        self.monitors = monitors.to_vec();
+    }
+
+    /// Forwards a touch event. Returns the pointer events the forwarder has to
+    /// inject itself when there is no touchscreen, nothing otherwise.
+    pub fn forward(&mut self, event: &InputEvent) -> Result<Vec<InputEvent>, InputForwardingError> {
+        let touch = event.touch.as_ref().ok_or_else(|| InputForwardingError::UnsupportedEvent(
+            format!("{:?} event missing touch point", event.event_type)
+        ))?;
+        let monitor = self.monitors.iter()
+            .find(|monitor| event.monitor_index.map_or(monitor.is_primary, |index| monitor.index == index))
+            .or_else(|| self.monitors.first())
+            .cloned()
+            .ok_or_else(|| InputForwardingError::MonitorConfigError("Touch input needs the monitor configuration".to_string()))?;
+
+        let Some(factory) = &self.factory else {
+            return self.pointer_events(&event.event_type, touch, &monitor);
+        };
+        let touchscreen = match self.touchscreens.entry(monitor.index) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            // Contacts that went down before the device was replaced are gone
+            Entry::Vacant(_) if event.event_type != InputEventType::TouchDown => return Ok(Vec::new()),
+            Entry::Vacant(entry) => {
+                let device = factory(&monitor)?;
+                log::info!(target: TARGET_INPUT, "Created touchscreen for monitor {} ({}x{})", monitor.index, monitor.width, monitor.height);
+                entry.insert(Touchscreen::new(device, &monitor))
+            },
+        };
+        touchscreen.forward(&event.event_type, touch)?;
+        Ok(Vec::new())
+    }
+
+    /// Lifts every contact still down on a touchscreen
+    pub fn release_all(&mut self) -> Result<(), InputForwardingError> {
+        self.pointer_contact = None;
+        let mut result = Ok(());
+        for touchscreen in self.touchscreens.values_mut() {
+            if let Err(e) = touchscreen.release_all() {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
+    /// A single contact as pointer moves with the left button held
+    fn pointer_events(&mut self, event_type: &InputEventType, touch: &TouchPoint, monitor: &MonitorConfiguration) -> Result<Vec<InputEvent>, InputForwardingError> {
+        if self.pointer_contact.is_some_and(|contact| contact != touch.id) {
+            if *event_type == InputEventType::TouchUp {
+                return Ok(Vec::new());
+            }
+            return Err(InputForwardingError::UnsupportedEvent(
+                format!("Multitouch needs write access to {}; without it only one contact is injected as the pointer", UINPUT_DEVICE)
+            ));
+        }
+
+        let pointer = InputEvent {
+            x: Some(scale(touch.x, monitor.width)),
+            y: Some(scale(touch.y, monitor.height)),
+            monitor_index: Some(monitor.index),
+            ..pointer_event(InputEventType::MouseMove)
+        };
+        let button = |pressed: bool| InputEvent {
+            event_type: InputEventType::MouseButton,
+            button: Some(MouseButton::Left),
+            is_pressed: Some(pressed),
+            ..pointer.clone()
+        };
+
+        Ok(match (event_type, self.pointer_contact) {
+            (InputEventType::TouchDown, None) => {
+                self.pointer_contact = Some(touch.id);
+                vec![pointer.clone(), button(true)]
+            },
+            (InputEventType::TouchDown | InputEventType::TouchMove, Some(_)) => vec![pointer],
+            (InputEventType::TouchUp, Some(_)) => {
+                self.pointer_contact = None;
+                vec![pointer.clone(), button(false)]
+            },
+            _ => Vec::new(),
+        })
+    }
+}
+
+fn pointer_event(event_type: InputEventType) -> InputEvent {
+    InputEvent {
+        event_type,
+        x: None,
+        y: None,
+        button: None,
+        key_code: None,
+        modifiers: None,
+        is_pressed: None,
+        delta_x: None,
+        delta_y: None,
+        monitor_index: None,
+        gesture: None,
+        gesture_direction: None,
+        gesture_magnitude: None,
+        special_command: None,
+        ping_id: None,
+        client_timestamp: None,
+        touch: None,
+    }
+}
+
+/// struct input_absinfo
+#[repr(C)]
+struct InputAbsinfo {
+    value: i32,
+    minimum: i32,
+    maximum: i32,
+    fuzz: i32,
+    flat: i32,
+    resolution: i32,
+}
+
+/// struct uinput_abs_setup
+#[repr(C)]
+struct UinputAbsSetup {
+    code: u16,
+    absinfo: InputAbsinfo,
+}
+
+/// struct uinput_setup
+#[repr(C)]
+struct UinputSetup {
+    bustype: u16,
+    vendor: u16,
+    product: u16,
+    version: u16,
+    name: [u8; 80],
+    ff_effects_max: u32,
+}
+
+nix::ioctl_none!(ui_dev_create, b'U', 1);
+nix::ioctl_none!(ui_dev_destroy, b'U', 2);
+nix::ioctl_write_ptr!(ui_dev_setup, b'U', 3, UinputSetup);
+nix::ioctl_write_ptr!(ui_abs_setup, b'U', 4, UinputAbsSetup);
+nix::ioctl_write_int!(ui_set_evbit, b'U', 100);
+nix::ioctl_write_int!(ui_set_keybit, b'U', 101);
+nix::ioctl_write_int!(ui_set_absbit, b'U', 103);
+nix::ioctl_write_int!(ui_set_propbit, b'U', 110);
+
+/// A touchscreen created through /dev/uinput, destroyed when dropped
+struct UinputTouchscreen {
+    file: File,
+}
+
+impl UinputTouchscreen {
+    fn create(monitor: &MonitorConfiguration) -> Result<Self, InputForwardingError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(nix::libc::O_NONBLOCK)
+            .open(UINPUT_DEVICE)
+            .map_err(|e| InputForwardingError::PermissionDenied(format!("{}: {}", UINPUT_DEVICE, e)))?;
+        let fd = file.as_raw_fd();
+        let failed = |step: &str, e: nix::Error| InputForwardingError::InitializationFailed(format!("uinput {} failed: {}", step, e));
+
+        let axes = [
+            (ABS_X, monitor.width - 1),
+            (ABS_Y, monitor.height - 1),
+            (ABS_PRESSURE, PRESSURE_MAX),
+            (ABS_MT_SLOT, MAX_SLOTS as i32 - 1),
+            (ABS_MT_TRACKING_ID, 0xffff),
+            (ABS_MT_POSITION_X, monitor.width - 1),
+            (ABS_MT_POSITION_Y, monitor.height - 1),
+            (ABS_MT_PRESSURE, PRESSURE_MAX),
+        ];
+        let mut setup = UinputSetup {
+            bustype: BUS_VIRTUAL,
+            vendor: 0,
+            product: 0,
+            version: 1,
+            name: [0; 80],
+            ff_effects_max: 0,
+        };
+        let name = format!("SmolDesk touchscreen {}", monitor.index);
+        setup.name[..name.len()].copy_from_slice(name.as_bytes());
+
+        // SAFETY: fd is an open uinput device and the structs match the kernel's layout
+        unsafe {
+            ui_set_evbit(fd, EV_KEY as _).map_err(|e| failed("UI_SET_EVBIT", e))?;
+            ui_set_keybit(fd, BTN_TOUCH as _).map_err(|e| failed("UI_SET_KEYBIT", e))?;
+            ui_set_evbit(fd, EV_ABS as _).map_err(|e| failed("UI_SET_EVBIT", e))?;
+            ui_set_propbit(fd, INPUT_PROP_DIRECT as _).map_err(|e| failed("UI_SET_PROPBIT", e))?;
+            for (code, maximum) in axes {
+                ui_set_absbit(fd, code as _).map_err(|e| failed("UI_SET_ABSBIT", e))?;
+                let abs = UinputAbsSetup {
+                    code,
+                    absinfo: InputAbsinfo { value: 0, minimum: 0, maximum, fuzz: 0, flat: 0, resolution: 0 },
+                };
+                ui_abs_setup(fd, &abs).map_err(|e| failed("UI_ABS_SETUP", e))?;
+            }
+            ui_dev_setup(fd, &setup).map_err(|e| failed("UI_DEV_SETUP", e))?;
+            ui_dev_create(fd).map_err(|e| failed("UI_DEV_CREATE", e))?;
+        }
+        Ok(UinputTouchscreen { file })
+    }
+}
+
+impl TouchDevice for UinputTouchscreen {
+    fn write_frame(&mut self, events: &[EvdevEvent]) -> Result<(), InputForwardingError> {
+        let mut bytes = Vec::with_capacity(events.len() * std::mem::size_of::<nix::libc::input_event>());
+        for event in events {
+            // The kernel stamps the time itself
+            // SAFETY: input_event is plain data, all zeroes is a valid value
+            let mut raw: nix::libc::input_event = unsafe { std::mem::zeroed() };
+            raw.type_ = event.kind;
+            raw.code = event.code;
+            raw.value = event.value;
+            // SAFETY: raw lives until the bytes are copied
+            bytes.extend_from_slice(unsafe {
+                std::slice::from_raw_parts(&raw as *const _ as *const u8, std::mem::size_of_val(&raw))
+            });
+        }
+        self.file.write_all(&bytes)
+            .map_err(|e| InputForwardingError::SendEventFailed(format!("Failed to write touch events: {}", e)))
+    }
+}
+
+impl Drop for UinputTouchscreen {
+    fn drop(&mut self) {
+        // SAFETY: the device was created on this fd
+        if let Err(e) = unsafe { ui_dev_destroy(self.file.as_raw_fd()) } {
+            log::warn!(target: TARGET_INPUT, "Failed to destroy touchscreen: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    type Frames = Arc<Mutex<Vec<(usize, Vec<EvdevEvent>)>>>;
+
+    /// Stands in for uinput: records the frames written to each monitor's device
+    struct MockTouchscreen {
+        monitor: usize,
+        frames: Frames,
+    }
+
+    impl TouchDevice for MockTouchscreen {
+        fn write_frame(&mut self, events: &[EvdevEvent]) -> Result<(), InputForwardingError> {
+            self.frames.lock().unwrap().push((self.monitor, events.to_vec()));
+            Ok(())
+        }
+    }
+
+    fn monitor(index: usize, width: i32, height: i32) -> MonitorConfiguration {
+        MonitorConfiguration {
+            index,
+            x_offset: 0,
+            y_offset: 0,
+            width,
+            height,
+            scale_factor: 1.0,
+            rotation: MonitorRotation::Normal,
+            is_primary: index == 0,
+        }
+    }
+
+    fn mock_input() -> (TouchInput, Frames, Arc<Mutex<Vec<usize>>>) {
+        let frames = Frames::default();
+        let created = Arc::new(Mutex::new(Vec::new()));
+        let (device_frames, device_created) = (frames.clone(), created.clone());
+        let mut input = TouchInput::with_devices(Box::new(move |monitor: &MonitorConfiguration| {
+            device_created.lock().unwrap().push(monitor.index);
+            Ok(Box::new(MockTouchscreen { monitor: monitor.index, frames: device_frames.clone() }) as Box<dyn TouchDevice>)
+        }));
+        input.configure_monitors(&[monitor(0, 1001, 501), monitor(1, 101, 101)]);
+        (input, frames, created)
+    }
+
+    fn touch(event_type: InputEventType, id: u32, x: f32, y: f32) -> InputEvent {
+        InputEvent {
+            touch: Some(TouchPoint { id, x, y, pressure: Some(0.5) }),
+            monitor_index: Some(0),
+            ..pointer_event(event_type)
+        }
+    }
+
+    fn abs(code: u16, value: i32) -> EvdevEvent {
+        EvdevEvent::abs(code, value)
+    }
+
+    const SYN: EvdevEvent = EvdevEvent { kind: EV_SYN, code: SYN_REPORT, value: 0 };
+
+    #[test]
+    fn test_two_finger_drag_keeps_each_contact_in_its_slot() {
+        let (mut input, frames, created) = mock_input();
+
+        // Contact IDs are the client's; slots are handed out in order
+        for event in [
+            touch(InputEventType::TouchDown, 7, 0.1, 0.2),
+            touch(InputEventType::TouchDown, 3, 0.5, 0.2),
+            touch(InputEventType::TouchMove, 7, 0.2, 0.4),
+            touch(InputEventType::TouchMove, 3, 0.6, 0.4),
+            touch(InputEventType::TouchUp, 7, 0.2, 0.4),
+            touch(InputEventType::TouchMove, 3, 0.7, 0.6),
+            touch(InputEventType::TouchUp, 3, 0.7, 0.6),
+        ] {
+            assert!(input.forward(&event).unwrap().is_empty());
+        }
+
+        let frames: Vec<Vec<EvdevEvent>> = frames.lock().unwrap().iter().map(|(_, frame)| frame.clone()).collect();
+        let touch_key = |value| EvdevEvent { kind: EV_KEY, code: BTN_TOUCH, value };
+        assert_eq!(frames, vec![
+            vec![abs(ABS_MT_SLOT, 0), abs(ABS_MT_TRACKING_ID, 0), abs(ABS_MT_POSITION_X, 100), abs(ABS_MT_POSITION_Y, 100), abs(ABS_MT_PRESSURE, 128),
+                abs(ABS_X, 100), abs(ABS_Y, 100), abs(ABS_PRESSURE, 128), touch_key(1), SYN],
+            vec![abs(ABS_MT_SLOT, 1), abs(ABS_MT_TRACKING_ID, 1), abs(ABS_MT_POSITION_X, 500), abs(ABS_MT_POSITION_Y, 100), abs(ABS_MT_PRESSURE, 128), SYN],
+            vec![abs(ABS_MT_SLOT, 0), abs(ABS_MT_POSITION_X, 200), abs(ABS_MT_POSITION_Y, 200), abs(ABS_MT_PRESSURE, 128),
+                abs(ABS_X, 200), abs(ABS_Y, 200), abs(ABS_PRESSURE, 128), SYN],
+            vec![abs(ABS_MT_SLOT, 1), abs(ABS_MT_POSITION_X, 600), abs(ABS_MT_POSITION_Y, 200), abs(ABS_MT_PRESSURE, 128), SYN],
+            vec![abs(ABS_MT_SLOT, 0), abs(ABS_MT_TRACKING_ID, -1), SYN],
+            // The remaining contact drives the single-touch axes now
+            vec![abs(ABS_MT_SLOT, 1), abs(ABS_MT_POSITION_X, 700), abs(ABS_MT_POSITION_Y, 300), abs(ABS_MT_PRESSURE, 128),
+                abs(ABS_X, 700), abs(ABS_Y, 300), abs(ABS_PRESSURE, 128), SYN],
+            vec![abs(ABS_MT_TRACKING_ID, -1), touch_key(0), SYN],
+        ]);
+        assert_eq!(*created.lock().unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn test_touchscreens_follow_the_monitor_configuration() {
+        let (mut input, frames, created) = mock_input();
+        input.forward(&touch(InputEventType::TouchDown, 1, 0.5, 0.5)).unwrap();
+        input.forward(&InputEvent { monitor_index: Some(1), ..touch(InputEventType::TouchDown, 2, 1.0, 1.0) }).unwrap();
+        assert_eq!(*created.lock().unwrap(), vec![0, 1]);
+
+        // A reset lifts what is still down
+        frames.lock().unwrap().clear();
+        input.release_all().unwrap();
+        let mut lifted: Vec<usize> = frames.lock().unwrap().iter().map(|(monitor, _)| *monitor).collect();
+        lifted.sort();
+        assert_eq!(lifted, vec![0, 1]);
+
+        // Monitor 0 keeps its device, the resized monitor 1 gets a new one with the next touch
+        input.configure_monitors(&[monitor(0, 1001, 501), monitor(1, 201, 101)]);
+        input.forward(&touch(InputEventType::TouchDown, 1, 0.5, 0.5)).unwrap();
+        input.forward(&InputEvent { monitor_index: Some(1), ..touch(InputEventType::TouchDown, 2, 1.0, 1.0) }).unwrap();
+        assert_eq!(*created.lock().unwrap(), vec![0, 1, 1]);
+        let last = frames.lock().unwrap().last().unwrap().1.clone();
+        assert!(last.contains(&abs(ABS_MT_POSITION_X, 200)));
+    }
+
+    #[test]
+    fn test_without_uinput_a_single_contact_moves_the_pointer() {
+        let mut input = TouchInput::pointer_only();
+        input.configure_monitors(&[monitor(0, 1001, 501)]);
+
+        let down = input.forward(&touch(InputEventType::TouchDown, 7, 0.1, 0.2)).unwrap();
+        let summary = |events: &[InputEvent]| -> Vec<(InputEventType, Option<i32>, Option<bool>)> {
+            events.iter().map(|event| (event.event_type.clone(), event.x, event.is_pressed)).collect()
+        };
+        assert_eq!(summary(&down), vec![(InputEventType::MouseMove, Some(100), None), (InputEventType::MouseButton, Some(100), Some(true))]);
+        assert_eq!(down[1].button, Some(MouseButton::Left));
+
+        let second = input.forward(&touch(InputEventType::TouchDown, 3, 0.5, 0.2));
+        assert!(matches!(second, Err(InputForwardingError::UnsupportedEvent(_))));
+        assert!(input.forward(&touch(InputEventType::TouchUp, 3, 0.5, 0.2)).unwrap().is_empty());
+
+        let moved = input.forward(&touch(InputEventType::TouchMove, 7, 0.2, 0.2)).unwrap();
+        assert_eq!(summary(&moved), vec![(InputEventType::MouseMove, Some(200), None)]);
+        let up = input.forward(&touch(InputEventType::TouchUp, 7, 0.2, 0.2)).unwrap();
+        assert_eq!(summary(&up), vec![(InputEventType::MouseMove, Some(200), None), (InputEventType::MouseButton, Some(200), Some(false))]);
+    }
+}
@@ -2,6 +2,9 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use crate::input_forwarding::shortcuts::ShortcutPolicy;
+use crate::input_forwarding::system_actions::SystemActionConfig;
+use crate::protocol::PROTOCOL_VERSION;
 
 // Display server enum
 #[derive(Debug, Clone, PartialEq)]
@@ -12,7 +15,7 @@ pub enum DisplayServer {
 }
 
 // Improved Input Event Types
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum InputEventType {
     MouseMove,
     MouseButton,
@@ -21,10 +24,14 @@ pub enum InputEventType {
     KeyRelease,
     TouchGesture,  // New type for touch gestures
     SpecialCommand, // New type for special commands (e.g., Win+Tab)
+    Ping,           // Reflected back to the frontend to measure the input round trip
+    TouchDown,      // A contact touches the screen; `touch` says which and where
+    TouchMove,
+    TouchUp,
 }
 
 // Improved Mouse Button Types
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MouseButton {
     Left,
     Middle,
@@ -39,7 +46,7 @@ pub enum MouseButton {
 }
 
 // Touch Gesture Type
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TouchGesture {
     Pinch,
     Rotate,
@@ -48,8 +55,19 @@ pub enum TouchGesture {
     TwoFingerScroll,
 }
 
+// One contact of a touch event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TouchPoint {
+    pub id: u32, // Contact, the same from TouchDown to TouchUp
+    pub x: f32,  // Normalized to the monitor (0.0 - 1.0)
+    pub y: f32,
+    #[serde(default)]
+    pub pressure: Option<f32>, // 0.0 - 1.0, full pressure if the client cannot tell
+}
+
 // Direction for gestures
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum GestureDirection {
     Left,
     Right,
@@ -64,43 +82,82 @@ pub enum SpecialCommand {
     DesktopToggle, // Win+D / Show Desktop
     ScreenSnapshot, // PrintScreen / Win+Shift+S
     LockScreen,   // Win+L / Ctrl+Alt+L
+    VolumeUp,     // XF86AudioRaiseVolume
+    VolumeDown,   // XF86AudioLowerVolume
+    VolumeMute,   // XF86AudioMute
+    MediaPlayPause, // XF86AudioPlay
+    MediaNext,    // XF86AudioNext
+    MediaPrev,    // XF86AudioPrev
     Custom(String), // Custom command
 }
 
 // Improved Input Event Structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InputEvent {
+    // Aliases keep macros recorded before the switch to camelCase readable
+    #[serde(alias = "event_type")]
     pub event_type: InputEventType,
     pub x: Option<i32>,
     pub y: Option<i32>,
     pub button: Option<MouseButton>,
+    #[serde(alias = "key_code")]
     pub key_code: Option<u32>,
     pub modifiers: Option<Vec<String>>,
+    #[serde(alias = "is_pressed")]
     pub is_pressed: Option<bool>,
+    #[serde(alias = "delta_x")]
     pub delta_x: Option<f32>,
+    #[serde(alias = "delta_y")]
     pub delta_y: Option<f32>,
+    #[serde(alias = "monitor_index")]
     pub monitor_index: Option<usize>, // For multi-monitor support
     pub gesture: Option<TouchGesture>, // For touch gestures
+    #[serde(alias = "gesture_direction")]
     pub gesture_direction: Option<GestureDirection>, // For gesture direction
+    #[serde(alias = "gesture_magnitude")]
     pub gesture_magnitude: Option<f32>, // For gesture magnitude
+    #[serde(alias = "special_command")]
     pub special_command: Option<SpecialCommand>, // For special commands
+    #[serde(default, alias = "ping_id")]
+    pub ping_id: Option<u32>, // For pings, echoed in the pong
+    #[serde(default, alias = "client_timestamp")]
+    pub client_timestamp: Option<u64>, // Unix ms on the client, echoed in the pong
+    #[serde(default)]
+    pub touch: Option<TouchPoint>, // For TouchDown, TouchMove and TouchUp
+}
+
+// Rotation of a monitor in the desktop layout
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum MonitorRotation {
+    #[default]
+    Normal,
+    Left,     // 90° counter-clockwise
+    Right,    // 90° clockwise
+    Inverted, // 180°
 }
 
 // Configuration for multi-monitor setups
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MonitorConfiguration {
     pub index: usize,
     pub x_offset: i32,
     pub y_offset: i32,
-    pub width: i32,
+    pub width: i32,  // Size in the desktop layout, i.e. after rotation
     pub height: i32,
     pub scale_factor: f32,
+    #[serde(default)]
+    pub rotation: MonitorRotation,
     pub is_primary: bool,
 }
 
 // Frontend integration interface
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InputForwardingConfig {
+    #[serde(default)]
+    pub protocol_version: u32, // Wire version of the frontend, 0 if it predates versioning
     pub enable_touch_gestures: bool,
     pub enable_special_commands: bool,
     pub enable_multi_monitor: bool,
@@ -108,4 +165,146 @@ pub struct InputForwardingConfig {
     pub monitors: Vec<MonitorConfiguration>,
     pub remap_keys: HashMap<String, String>,
     pub custom_commands: HashMap<String, String>,
+    #[serde(default)]
+    pub input_blocklist: Vec<String>, // Window class / app-id patterns that never receive keys or clicks
+    #[serde(default = "default_stuck_key_timeout_ms")]
+    pub stuck_key_timeout_ms: Option<u64>, // Release held keys after this long without input (None = never)
+    #[serde(default)]
+    pub shortcut_policy: ShortcutPolicy, // Key combinations forwarded, ignored or translated
+    #[serde(default)]
+    pub system_actions: SystemActionConfig, // Special commands that need a grant or the host's approval
+}
+
+fn default_stuck_key_timeout_ms() -> Option<u64> {
+    Some(crate::input_forwarding::key_state::DEFAULT_STUCK_KEY_TIMEOUT.as_millis() as u64)
+}
+
+impl Default for InputForwardingConfig {
+    fn default() -> Self {
+        InputForwardingConfig {
+            protocol_version: PROTOCOL_VERSION,
+            enable_touch_gestures: true,
+            enable_special_commands: true,
+            enable_multi_monitor: true,
+            keyboard_layout: "us".to_string(),
+            monitors: Vec::new(), // Detected at startup
+            remap_keys: HashMap::new(),
+            custom_commands: HashMap::new(),
+            input_blocklist: Vec::new(),
+            stuck_key_timeout_ms: default_stuck_key_timeout_ms(),
+            shortcut_policy: ShortcutPolicy::default(),
+            system_actions: SystemActionConfig::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_input_event_wire_snapshot() {
+        let event = InputEvent {
+            event_type: InputEventType::MouseButton,
+            x: Some(10),
+            y: Some(20),
+            button: Some(MouseButton::Left),
+            key_code: None,
+            modifiers: Some(vec!["ctrl".to_string()]),
+            is_pressed: Some(true),
+            delta_x: None,
+            delta_y: None,
+            monitor_index: Some(1),
+            gesture: None,
+            gesture_direction: None,
+            gesture_magnitude: None,
+            special_command: Some(SpecialCommand::Custom("terminal".to_string())),
+            ping_id: None,
+            client_timestamp: Some(5),
+            touch: None,
+        };
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value, json!({
+            "eventType": "MouseButton",
+            "x": 10,
+            "y": 20,
+            "button": "Left",
+            "keyCode": null,
+            "modifiers": ["ctrl"],
+            "isPressed": true,
+            "deltaX": null,
+            "deltaY": null,
+            "monitorIndex": 1,
+            "gesture": null,
+            "gestureDirection": null,
+            "gestureMagnitude": null,
+            "specialCommand": { "Custom": "terminal" },
+            "pingId": null,
+            "clientTimestamp": 5,
+            "touch": null,
+        }));
+        assert_eq!(serde_json::from_value::<InputEvent>(value).unwrap(), event);
+
+        // Recorded macros may still hold snake_case events
+        let recorded: InputEvent = serde_json::from_value(json!({
+            "event_type": "KeyPress", "x": null, "y": null, "button": null, "key_code": 30,
+            "modifiers": null, "is_pressed": true, "delta_x": null, "delta_y": null, "monitor_index": null,
+            "gesture": null, "gesture_direction": null, "gesture_magnitude": null, "special_command": null,
+        })).unwrap();
+        assert_eq!((recorded.event_type, recorded.key_code), (InputEventType::KeyPress, Some(30)));
+    }
+
+    #[test]
+    fn test_input_config_wire_snapshot() {
+        let config = InputForwardingConfig {
+            monitors: vec![MonitorConfiguration {
+                index: 0,
+                x_offset: 0,
+                y_offset: 0,
+                width: 1920,
+                height: 1080,
+                scale_factor: 1.0,
+                rotation: MonitorRotation::Left,
+                is_primary: true,
+            }],
+            stuck_key_timeout_ms: None,
+            ..Default::default()
+        };
+        assert_eq!(serde_json::to_value(&config).unwrap(), json!({
+            "protocolVersion": crate::protocol::PROTOCOL_VERSION,
+            "enableTouchGestures": true,
+            "enableSpecialCommands": true,
+            "enableMultiMonitor": true,
+            "keyboardLayout": "us",
+            "monitors": [{
+                "index": 0,
+                "xOffset": 0,
+                "yOffset": 0,
+                "width": 1920,
+                "height": 1080,
+                "scaleFactor": 1.0,
+                "rotation": "Left",
+                "isPrimary": true,
+            }],
+            "remapKeys": {},
+            "customCommands": {},
+            "inputBlocklist": [],
+            "stuckKeyTimeoutMs": null,
+            "shortcutPolicy": serde_json::to_value(ShortcutPolicy::default()).unwrap(),
+            "systemActions": serde_json::to_value(SystemActionConfig::default()).unwrap(),
+        }));
+
+        // Without a version the frontend predates versioning
+        let legacy: InputForwardingConfig = serde_json::from_value(json!({
+            "enableTouchGestures": true,
+            "enableSpecialCommands": true,
+            "enableMultiMonitor": false,
+            "keyboardLayout": "us",
+            "monitors": [],
+            "remapKeys": {},
+            "customCommands": {},
+        })).unwrap();
+        assert_eq!(legacy.protocol_version, 0);
+    }
 }
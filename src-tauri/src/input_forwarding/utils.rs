@@ -1,13 +1,20 @@
 // utils.rs - Common utilities for input forwarding
 
 use std::process::Command;
+use crate::command_runner::{CommandRunner, SystemCommandRunner};
+use crate::tool_locator;
 use crate::input_forwarding::types::*;
 use crate::input_forwarding::error::InputForwardingError;
 
 /// Check if a specific command-line tool is installed
 pub fn check_tool_exists(tool_name: &str) -> bool {
-    let cmd = Command::new("which")
-        .arg(tool_name)
+    tool_exists(&SystemCommandRunner, tool_name)
+}
+
+/// Check if a tool is installed in the PATH seen by `runner`
+pub fn tool_exists(runner: &dyn CommandRunner, tool_name: &str) -> bool {
+    let cmd = runner.command("which")
+        .arg(tool_locator::resolve(tool_name))
         .output();
     
     match cmd {
@@ -16,7 +23,11 @@ pub fn check_tool_exists(tool_name: &str) -> bool {
     }
 }
 
-/// Calculate absolute position on screen based on monitor configuration
+/// Calculate absolute position on screen based on monitor configuration.
+///
+/// Client coordinates are in the monitor's own orientation and in logical
+/// pixels: they are scaled to device pixels, rotated into the desktop layout
+/// and then offset by the monitor position.
 pub fn calculate_absolute_position(
     x: i32, 
     y: i32, 
@@ -32,13 +43,31 @@ pub fn calculate_absolute_position(
         _ => monitors.iter().find(|m| m.is_primary).unwrap_or(&monitors[0]),
     };
     
+    let scaled_x = (x as f32 * target_monitor.scale_factor) as i32;
+    let scaled_y = (y as f32 * target_monitor.scale_factor) as i32;
+    let (rotated_x, rotated_y) = rotate_position(scaled_x, scaled_y, target_monitor);
+    
     // Calculate absolute position relative to target monitor
-    let abs_x = target_monitor.x_offset + (x as f32 * target_monitor.scale_factor) as i32;
-    let abs_y = target_monitor.y_offset + (y as f32 * target_monitor.scale_factor) as i32;
+    let abs_x = target_monitor.x_offset + rotated_x;
+    let abs_y = target_monitor.y_offset + rotated_y;
     
     (abs_x, abs_y)
 }
 
+/// Map a point from the monitor's unrotated frame into its layout frame
+fn rotate_position(x: i32, y: i32, monitor: &MonitorConfiguration) -> (i32, i32) {
+    let (width, height) = (monitor.width, monitor.height);
+    
+    match monitor.rotation {
+        MonitorRotation::Normal => (x, y),
+        // The panel's top edge ends up on the left side
+        MonitorRotation::Left => (y, height - 1 - x),
+        // The panel's top edge ends up on the right side
+        MonitorRotation::Right => (width - 1 - y, x),
+        MonitorRotation::Inverted => (width - 1 - x, height - 1 - y),
+    }
+}
+
 /// Validate a monitor configuration
 pub fn validate_monitor_config(
     monitors: &[MonitorConfiguration]
@@ -94,3 +123,78 @@ where
         .map(|i| (i, formatter(i)))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn monitor(index: usize, x_offset: i32, width: i32, height: i32, scale_factor: f32, rotation: MonitorRotation) -> MonitorConfiguration {
+        MonitorConfiguration {
+            index,
+            x_offset,
+            y_offset: 0,
+            width,
+            height,
+            scale_factor,
+            rotation,
+            is_primary: index == 0,
+        }
+    }
+    
+    fn layout() -> Vec<MonitorConfiguration> {
+        vec![
+            monitor(0, 0, 2560, 1440, 1.0, MonitorRotation::Normal),
+            // Portrait monitor: 1920x1080 panel turned on its side
+            monitor(1, 2560, 1080, 1920, 1.0, MonitorRotation::Left),
+            monitor(2, 3640, 1080, 1920, 1.0, MonitorRotation::Right),
+        ]
+    }
+    
+    #[test]
+    fn test_rotation_left_90() {
+        let monitors = layout();
+        
+        // Panel origin is the bottom-left corner of the layout rectangle
+        assert_eq!(calculate_absolute_position(0, 0, Some(1), &monitors), (2560, 1919));
+        assert_eq!(calculate_absolute_position(1919, 1079, Some(1), &monitors), (2560 + 1079, 0));
+        assert_eq!(calculate_absolute_position(100, 200, Some(1), &monitors), (2560 + 200, 1819));
+    }
+    
+    #[test]
+    fn test_rotation_right_270() {
+        let monitors = layout();
+        
+        // Panel origin is the top-right corner of the layout rectangle
+        assert_eq!(calculate_absolute_position(0, 0, Some(2), &monitors), (3640 + 1079, 0));
+        assert_eq!(calculate_absolute_position(100, 200, Some(2), &monitors), (3640 + 879, 100));
+    }
+    
+    #[test]
+    fn test_scale_on_secondary_monitor() {
+        let mut monitors = layout();
+        monitors[1] = monitor(1, 2560, 2880, 1800, 2.0, MonitorRotation::Normal);
+        monitors[2] = monitor(2, 5440, 2400, 1600, 1.5, MonitorRotation::Normal);
+        
+        assert_eq!(calculate_absolute_position(720, 450, Some(1), &monitors), (2560 + 1440, 900));
+        assert_eq!(calculate_absolute_position(100, 100, Some(2), &monitors), (5440 + 150, 150));
+    }
+    
+    #[test]
+    fn test_scale_with_rotation() {
+        let monitors = vec![
+            monitor(0, 0, 1920, 1080, 1.0, MonitorRotation::Normal),
+            monitor(1, 1920, 1600, 2560, 2.0, MonitorRotation::Left),
+        ];
+        
+        // Scaled first (10,20 -> 20,40), then rotated into the layout
+        assert_eq!(calculate_absolute_position(10, 20, Some(1), &monitors), (1920 + 40, 2559 - 20));
+    }
+    
+    #[test]
+    fn test_inverted_and_primary_fallback() {
+        let monitors = vec![monitor(0, 0, 1920, 1080, 1.0, MonitorRotation::Inverted)];
+        
+        assert_eq!(calculate_absolute_position(0, 0, None, &monitors), (1919, 1079));
+        assert_eq!(calculate_absolute_position(0, 0, Some(5), &monitors), (1919, 1079));
+    }
+}
@@ -0,0 +1,956 @@
+// virtual_input.rs - Wayland input forwarding through the virtual keyboard and pointer protocols
+//
+// wlroots compositors (sway, Hyprland, river, ...) let a client create its own
+// keyboard (zwp_virtual_keyboard_v1) and pointer (zwlr_virtual_pointer_v1).
+// Neither root nor the ydotool daemon is needed, and the keyboard carries an
+// xkb keymap for InputForwardingConfig.keyboard_layout, so evdev codes come
+// out as the keys of that layout instead of whatever ydotool's uinput device
+// happens to be mapped to.
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
+use std::fs::File;
+use std::io::Write;
+use std::os::fd::AsFd;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use nix::sys::memfd::{memfd_create, MemFdCreateFlag};
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::{wl_pointer, wl_registry, wl_seat::WlSeat};
+use wayland_client::{delegate_noop, Connection, Dispatch, EventQueue, QueueHandle};
+use wayland_protocols_misc::zwp_virtual_keyboard_v1::client::zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1;
+use wayland_protocols_misc::zwp_virtual_keyboard_v1::client::zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1;
+use wayland_protocols_wlr::virtual_pointer::v1::client::zwlr_virtual_pointer_manager_v1::ZwlrVirtualPointerManagerV1;
+use wayland_protocols_wlr::virtual_pointer::v1::client::zwlr_virtual_pointer_v1::ZwlrVirtualPointerV1;
+use xkbcommon::xkb;
+
+use crate::input_forwarding::types::*;
+use crate::input_forwarding::error::InputForwardingError;
+use crate::input_forwarding::forwarder_trait::{ImprovedInputForwarder, InputBackend};
+use crate::input_forwarding::portal::{
+    gesture_action, js_key_to_evdev, special_command_keys, GestureAction, PointerTracker,
+    BTN_EXTRA, BTN_LEFT, BTN_MIDDLE, BTN_RIGHT, BTN_SIDE,
+};
+use crate::input_forwarding::utils;
+use crate::input_forwarding::key_state::HeldKeys;
+use crate::input_forwarding::preview::InputMode;
+use crate::input_forwarding::shortcuts::{ShortcutFilter, ShortcutOutcome, ShortcutPolicy};
+use crate::input_forwarding::system_actions::SystemActionGuard;
+use crate::input_forwarding::touchscreen::TouchInput;
+use crate::logging::TARGET_INPUT;
+
+/// Layout of the keyboard until the configuration names one
+pub const DEFAULT_KEYBOARD_LAYOUT: &str = "us";
+
+/// wl_keyboard.keymap_format.xkb_v1
+const KEYMAP_FORMAT_XKB_V1: u32 = 1;
+
+/// Scroll distance of one wheel step, as libinput reports it
+const SCROLL_STEP: f64 = 15.0;
+
+/// xkb keycodes are evdev codes shifted by 8
+const EVDEV_OFFSET: u32 = 8;
+
+/// Modifier masks a key sets in its keymap
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct ModifierKey {
+    depressed: u32,
+    locked: u32,
+}
+
+/// An xkb keymap compiled for one layout, e.g. `de` or `de(nodeadkeys)`
+#[derive(Debug, Clone)]
+pub struct VirtualKeymap {
+    layout: String,
+    text: String,
+    /// evdev codes that produce a symbol
+    keys: HashSet<u32>,
+    /// evdev codes of the modifier keys
+    modifiers: HashMap<u32, ModifierKey>,
+}
+
+impl VirtualKeymap {
+    pub fn compile(layout: &str) -> Result<Self, InputForwardingError> {
+        let layout = match layout.trim() {
+            "" => DEFAULT_KEYBOARD_LAYOUT,
+            layout => layout,
+        };
+        let (name, variant) = match layout.split_once('(') {
+            Some((name, variant)) => (name, variant.trim_end_matches(')')),
+            None => (layout, ""),
+        };
+
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        // Empty rules and model select the evdev rules and pc105
+        let keymap = xkb::Keymap::new_from_names(&context, "", "", name, variant, None, xkb::KEYMAP_COMPILE_NO_FLAGS)
+            .ok_or_else(|| InputForwardingError::InitializationFailed(
+                format!("No xkb keymap for keyboard layout {:?}", layout)
+            ))?;
+
+        let mut keys = HashSet::new();
+        let mut modifiers = HashMap::new();
+        for code in keymap.min_keycode().raw()..=keymap.max_keycode().raw() {
+            let keycode = xkb::Keycode::new(code);
+            if keymap.num_layouts_for_key(keycode) == 0 || code < EVDEV_OFFSET {
+                continue;
+            }
+            let evdev = code - EVDEV_OFFSET;
+            keys.insert(evdev);
+
+            // Ask a fresh state what pressing the key alone does
+            let mut state = xkb::State::new(&keymap);
+            state.update_key(keycode, xkb::KeyDirection::Down);
+            let modifier = ModifierKey {
+                depressed: state.serialize_mods(xkb::STATE_MODS_DEPRESSED),
+                locked: state.serialize_mods(xkb::STATE_MODS_LOCKED),
+            };
+            if modifier != ModifierKey::default() {
+                modifiers.insert(evdev, modifier);
+            }
+        }
+
+        Ok(VirtualKeymap {
+            layout: layout.to_string(),
+            text: keymap.get_as_string(xkb::KEYMAP_FORMAT_TEXT_V1),
+            keys,
+            modifiers,
+        })
+    }
+
+    pub fn layout(&self) -> &str {
+        &self.layout
+    }
+
+    pub fn has_key(&self, evdev: u32) -> bool {
+        self.keys.contains(&evdev)
+    }
+
+    /// The keymap as the compositor maps it: an in-memory file ending in NUL
+    fn file(&self) -> Result<(File, u32), InputForwardingError> {
+        let name = CString::new("smoldesk-keymap").unwrap();
+        let fd = memfd_create(&name, MemFdCreateFlag::MFD_CLOEXEC)
+            .map_err(|e| InputForwardingError::InitializationFailed(format!("Failed to create keymap file: {}", e)))?;
+        let mut file = File::from(fd);
+        file.write_all(self.text.as_bytes())
+            .and_then(|()| file.write_all(&[0]))
+            .map_err(|e| InputForwardingError::InitializationFailed(format!("Failed to write keymap: {}", e)))?;
+        Ok((file, self.text.len() as u32 + 1))
+    }
+}
+
+/// Modifiers of the virtual keyboard, tracked from the keys it sent.
+///
+/// The protocol leaves modifier state to the client, so clients that follow
+/// `wl_keyboard.modifiers` rather than their own xkb state see Shift or AltGr
+/// only if they are reported.
+#[derive(Debug, Default)]
+pub struct ModifierState {
+    /// Depressed masks of the held modifier keys
+    held: HashMap<u32, u32>,
+    locked: u32,
+}
+
+impl ModifierState {
+    /// New `(depressed, locked)` masks if the key changes them
+    pub fn update(&mut self, keymap: &VirtualKeymap, evdev: u32, pressed: bool) -> Option<(u32, u32)> {
+        let modifier = keymap.modifiers.get(&evdev)?;
+        let before = (self.depressed(), self.locked);
+        if pressed {
+            // Auto-repeat of a held lock key must not toggle it again
+            if self.held.insert(evdev, modifier.depressed).is_none() {
+                self.locked ^= modifier.locked;
+            }
+        } else {
+            self.held.remove(&evdev);
+        }
+        let after = (self.depressed(), self.locked);
+        (after != before).then_some(after)
+    }
+
+    pub fn depressed(&self) -> u32 {
+        self.held.values().fold(0, |mask, depressed| mask | depressed)
+    }
+}
+
+/// Dispatch state of the connection; the virtual devices send no events
+struct DeviceEvents;
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for DeviceEvents {
+    fn event(_: &mut Self, _: &wl_registry::WlRegistry, _: wl_registry::Event, _: &GlobalListContents, _: &Connection, _: &QueueHandle<Self>) {}
+}
+
+delegate_noop!(DeviceEvents: ignore WlSeat);
+delegate_noop!(DeviceEvents: ZwpVirtualKeyboardManagerV1);
+delegate_noop!(DeviceEvents: ZwpVirtualKeyboardV1);
+delegate_noop!(DeviceEvents: ZwlrVirtualPointerManagerV1);
+delegate_noop!(DeviceEvents: ZwlrVirtualPointerV1);
+
+/// Virtual keyboard and pointer on one compositor connection
+struct VirtualDevices {
+    connection: Connection,
+    queue: EventQueue<DeviceEvents>,
+    seat: WlSeat,
+    keyboard_manager: ZwpVirtualKeyboardManagerV1,
+    keyboard: ZwpVirtualKeyboardV1,
+    keymap: VirtualKeymap,
+    modifiers: ModifierState,
+    pointer: ZwlrVirtualPointerV1,
+    started: Instant,
+}
+
+impl VirtualDevices {
+    /// Binds the globals and creates both devices. Fails if the compositor
+    /// does not advertise them or refuses to create them.
+    fn connect(connection: Connection, keymap: VirtualKeymap) -> Result<Self, InputForwardingError> {
+        let (globals, mut queue) = registry_queue_init::<DeviceEvents>(&connection)
+            .map_err(|e| InputForwardingError::InitializationFailed(format!("Wayland registry: {}", e)))?;
+        let qh = queue.handle();
+        let missing = |e| InputForwardingError::InitializationFailed(
+            format!("Compositor lacks virtual input support: {}", e)
+        );
+
+        let seat: WlSeat = globals.bind(&qh, 1..=7, ()).map_err(missing)?;
+        let keyboard_manager: ZwpVirtualKeyboardManagerV1 = globals.bind(&qh, 1..=1, ()).map_err(missing)?;
+        let pointer_manager: ZwlrVirtualPointerManagerV1 = globals.bind(&qh, 1..=2, ()).map_err(missing)?;
+
+        let pointer = pointer_manager.create_virtual_pointer(Some(&seat), &qh, ());
+        let keyboard = create_keyboard(&keyboard_manager, &seat, &qh, &keymap)?;
+        // Errors such as an unauthorized client arrive with the roundtrip
+        queue.roundtrip(&mut DeviceEvents)
+            .map_err(|e| InputForwardingError::InitializationFailed(format!("Failed to create virtual input devices: {}", e)))?;
+
+        Ok(VirtualDevices {
+            connection,
+            queue,
+            seat,
+            keyboard_manager,
+            keyboard,
+            keymap,
+            modifiers: ModifierState::default(),
+            pointer,
+            started: Instant::now(),
+        })
+    }
+
+    /// Replaces the keyboard with one for `keymap`. Keys still held on the old
+    /// keyboard must have been released.
+    fn replace_keyboard(&mut self, keymap: VirtualKeymap) -> Result<(), InputForwardingError> {
+        self.keyboard.destroy();
+        self.keyboard = create_keyboard(&self.keyboard_manager, &self.seat, &self.queue.handle(), &keymap)?;
+        self.keymap = keymap;
+        self.modifiers = ModifierState::default();
+        self.queue.roundtrip(&mut DeviceEvents)
+            .map(|_| ())
+            .map_err(|e| InputForwardingError::InitializationFailed(format!("Failed to recreate virtual keyboard: {}", e)))
+    }
+
+    /// Milliseconds since the devices were created, the protocols' timestamps
+    fn time(&self) -> u32 {
+        self.started.elapsed().as_millis() as u32
+    }
+
+    fn flush(&self) -> Result<(), InputForwardingError> {
+        self.connection.flush()
+            .map_err(|e| InputForwardingError::SendEventFailed(format!("Wayland connection: {}", e)))
+    }
+
+    fn key(&mut self, evdev: u32, pressed: bool) -> Result<(), InputForwardingError> {
+        if !self.keymap.has_key(evdev) {
+            return Err(InputForwardingError::UnsupportedEvent(
+                format!("Key {} is not in the {} keymap", evdev, self.keymap.layout())
+            ));
+        }
+        self.keyboard.key(self.time(), evdev, u32::from(pressed));
+        if let Some((depressed, locked)) = self.modifiers.update(&self.keymap, evdev, pressed) {
+            self.keyboard.modifiers(depressed, 0, locked, 0);
+        }
+        self.flush()
+    }
+
+    fn motion(&self, dx: f64, dy: f64) -> Result<(), InputForwardingError> {
+        self.pointer.motion(self.time(), dx, dy);
+        self.pointer.frame();
+        self.flush()
+    }
+
+    /// Moves to `(x, y)` within a layout box of `width` x `height`
+    fn motion_absolute(&self, x: u32, y: u32, width: u32, height: u32) -> Result<(), InputForwardingError> {
+        self.pointer.motion_absolute(self.time(), x, y, width, height);
+        self.pointer.frame();
+        self.flush()
+    }
+
+    fn button(&self, button: i32, pressed: bool) -> Result<(), InputForwardingError> {
+        let state = if pressed { wl_pointer::ButtonState::Pressed } else { wl_pointer::ButtonState::Released };
+        self.pointer.button(self.time(), button as u32, state);
+        self.pointer.frame();
+        self.flush()
+    }
+
+    /// Discrete wheel steps, positive is down or right
+    fn scroll(&self, vertical: bool, steps: i32) -> Result<(), InputForwardingError> {
+        let axis = if vertical { wl_pointer::Axis::VerticalScroll } else { wl_pointer::Axis::HorizontalScroll };
+        self.pointer.axis_source(wl_pointer::AxisSource::Wheel);
+        self.pointer.axis_discrete(self.time(), axis, SCROLL_STEP * steps as f64, steps);
+        self.pointer.frame();
+        self.flush()
+    }
+}
+
+impl Drop for VirtualDevices {
+    fn drop(&mut self) {
+        self.keyboard.destroy();
+        self.pointer.destroy();
+        let _ = self.connection.flush();
+    }
+}
+
+fn create_keyboard(
+    manager: &ZwpVirtualKeyboardManagerV1,
+    seat: &WlSeat,
+    qh: &QueueHandle<DeviceEvents>,
+    keymap: &VirtualKeymap,
+) -> Result<ZwpVirtualKeyboardV1, InputForwardingError> {
+    let (file, size) = keymap.file()?;
+    let keyboard = manager.create_virtual_keyboard(seat, qh, ());
+    // The compositor maps the file while handling the request, it can be closed afterwards
+    keyboard.keymap(KEYMAP_FORMAT_XKB_V1, file.as_fd(), size);
+    Ok(keyboard)
+}
+
+/// Bounding box of the monitors in the desktop layout: left, top, width, height
+fn layout_box(monitors: &[MonitorConfiguration]) -> Option<(i32, i32, u32, u32)> {
+    let left = monitors.iter().map(|m| m.x_offset).min()?;
+    let top = monitors.iter().map(|m| m.y_offset).min()?;
+    let right = monitors.iter().map(|m| m.x_offset + m.width).max()?;
+    let bottom = monitors.iter().map(|m| m.y_offset + m.height).max()?;
+    (right > left && bottom > top).then(|| (left, top, (right - left) as u32, (bottom - top) as u32))
+}
+
+/// Wayland input forwarder that brings its own keyboard and pointer, for
+/// compositors that advertise zwp_virtual_keyboard_manager_v1 and
+/// zwlr_virtual_pointer_manager_v1.
+pub struct VirtualInputForwarder {
+    monitors: Mutex<Vec<MonitorConfiguration>>,
+    mode: Mutex<InputMode>,
+    devices: Mutex<VirtualDevices>,
+    /// Relative motion while no monitor layout is known
+    pointer: Mutex<PointerTracker>,
+    held_keys: Mutex<HeldKeys>,
+    shortcuts: Mutex<ShortcutFilter>,
+    touch: Mutex<TouchInput>,
+    system_actions: Mutex<Arc<SystemActionGuard>>,
+}
+
+impl VirtualInputForwarder {
+    /// Connects to the compositor in `WAYLAND_DISPLAY` with the default layout
+    pub fn new() -> Result<Self, InputForwardingError> {
+        let connection = Connection::connect_to_env()
+            .map_err(|e| InputForwardingError::InitializationFailed(format!("Wayland connection: {}", e)))?;
+        Self::with_connection(connection, DEFAULT_KEYBOARD_LAYOUT)
+    }
+
+    pub fn with_connection(connection: Connection, keyboard_layout: &str) -> Result<Self, InputForwardingError> {
+        let keymap = VirtualKeymap::compile(keyboard_layout)?;
+        let devices = VirtualDevices::connect(connection, keymap)?;
+
+        Ok(VirtualInputForwarder {
+            monitors: Mutex::new(Vec::new()),
+            mode: Mutex::new(InputMode::Enabled),
+            devices: Mutex::new(devices),
+            pointer: Mutex::new(PointerTracker::default()),
+            held_keys: Mutex::new(HeldKeys::default()),
+            shortcuts: Mutex::new(ShortcutFilter::default()),
+            touch: Mutex::new(TouchInput::detect()),
+            system_actions: Mutex::new(Arc::new(SystemActionGuard::default())),
+        })
+    }
+
+    fn key(&self, evdev: i32, pressed: bool) -> Result<(), InputForwardingError> {
+        self.devices.lock().unwrap().key(evdev as u32, pressed)
+    }
+
+    /// Injects single key presses and releases, as produced by a shortcut translation
+    fn inject_key_steps(&self, steps: &[(u32, bool)]) -> Result<(), InputForwardingError> {
+        for (key_code, pressed) in steps {
+            self.key(evdev_for(*key_code)?, *pressed)?;
+        }
+        Ok(())
+    }
+
+    /// Presses the keys in order and releases them in reverse
+    fn press_combination(&self, keys: &[i32]) -> Result<(), InputForwardingError> {
+        for key in keys {
+            self.key(*key, true)?;
+        }
+        for key in keys.iter().rev() {
+            self.key(*key, false)?;
+        }
+        Ok(())
+    }
+
+    fn move_pointer(&self, x: i32, y: i32, monitor_index: Option<usize>) -> Result<(), InputForwardingError> {
+        let (abs_x, abs_y, layout) = {
+            let monitors = self.monitors.lock().unwrap();
+            let (abs_x, abs_y) = utils::calculate_absolute_position(x, y, monitor_index, &monitors);
+            (abs_x, abs_y, layout_box(&monitors))
+        };
+
+        let devices = self.devices.lock().unwrap();
+        match layout {
+            Some((left, top, width, height)) => {
+                let x = (abs_x - left).clamp(0, width as i32 - 1) as u32;
+                let y = (abs_y - top).clamp(0, height as i32 - 1) as u32;
+                devices.motion_absolute(x, y, width, height)
+            },
+            None => {
+                for (dx, dy) in self.pointer.lock().unwrap().motion_to(abs_x, abs_y) {
+                    devices.motion(dx, dy)?;
+                }
+                Ok(())
+            },
+        }
+    }
+
+    fn button(&self, button: i32, pressed: bool) -> Result<(), InputForwardingError> {
+        self.devices.lock().unwrap().button(button, pressed)
+    }
+
+    fn scroll(&self, vertical: bool, steps: i32) -> Result<(), InputForwardingError> {
+        self.devices.lock().unwrap().scroll(vertical, steps)
+    }
+}
+
+fn evdev_for(key_code: u32) -> Result<i32, InputForwardingError> {
+    js_key_to_evdev(key_code).ok_or_else(|| InputForwardingError::UnsupportedEvent(
+        format!("No evdev mapping for keyCode {}", key_code)
+    ))
+}
+
+impl ImprovedInputForwarder for VirtualInputForwarder {
+    fn forward_event(&self, event: &InputEvent) -> Result<(), InputForwardingError> {
+        if self.mode() != InputMode::Enabled {
+            return Ok(());
+        }
+        self.held_keys.lock().unwrap().touch();
+
+        match event.event_type {
+            InputEventType::MouseMove => {
+                if let (Some(x), Some(y)) = (event.x, event.y) {
+                    self.move_pointer(x, y, event.monitor_index)
+                } else {
+                    Err(InputForwardingError::UnsupportedEvent(
+                        "Mouse move event missing coordinates".to_string()
+                    ))
+                }
+            },
+            InputEventType::MouseButton => {
+                if let (Some(button), Some(is_pressed)) = (&event.button, event.is_pressed) {
+                    let code = match button {
+                        MouseButton::Left => BTN_LEFT,
+                        MouseButton::Middle => BTN_MIDDLE,
+                        MouseButton::Right => BTN_RIGHT,
+                        MouseButton::Back => BTN_SIDE,
+                        MouseButton::Forward => BTN_EXTRA,
+                        MouseButton::ScrollUp | MouseButton::ScrollDown => {
+                            return Err(InputForwardingError::UnsupportedEvent(
+                                "Scroll events should use MouseScroll type".to_string()
+                            ));
+                        },
+                        MouseButton::TouchTap | MouseButton::TouchDoubleTap => {
+                            if let (Some(x), Some(y)) = (event.x, event.y) {
+                                self.move_pointer(x, y, event.monitor_index)?;
+                            }
+                            let clicks = if matches!(button, MouseButton::TouchTap) { 1 } else { 2 };
+                            for _ in 0..clicks {
+                                self.button(BTN_LEFT, true)?;
+                                self.button(BTN_LEFT, false)?;
+                            }
+                            return Ok(());
+                        },
+                    };
+                    self.button(code, is_pressed)
+                } else {
+                    Err(InputForwardingError::UnsupportedEvent(
+                        "Mouse button event missing button or pressed state".to_string()
+                    ))
+                }
+            },
+            InputEventType::MouseScroll => {
+                if let (Some(delta_x), Some(delta_y)) = (event.delta_x, event.delta_y) {
+                    // One discrete step per unit of delta, like the other backends
+                    if delta_y != 0.0 {
+                        let steps = (delta_y.abs() as i32).max(1);
+                        self.scroll(true, if delta_y > 0.0 { steps } else { -steps })?;
+                    }
+                    if delta_x != 0.0 {
+                        let steps = (delta_x.abs() as i32).max(1);
+                        self.scroll(false, if delta_x > 0.0 { steps } else { -steps })?;
+                    }
+                    Ok(())
+                } else {
+                    Err(InputForwardingError::UnsupportedEvent(
+                        "Mouse scroll event missing delta values".to_string()
+                    ))
+                }
+            },
+            InputEventType::KeyPress | InputEventType::KeyRelease => {
+                let event = match self.shortcuts.lock().unwrap().check(event, &[]) {
+                    ShortcutOutcome::Forward(event) => event,
+                    ShortcutOutcome::Drop => return Ok(()),
+                    ShortcutOutcome::Inject(steps) => return self.inject_key_steps(&steps),
+                };
+                let key_code = event.key_code.ok_or_else(|| InputForwardingError::UnsupportedEvent(
+                    "Key event missing keyCode".to_string()
+                ))?;
+                let pressed = event.is_pressed.unwrap_or(matches!(event.event_type, InputEventType::KeyPress));
+                self.key(evdev_for(key_code)?, pressed)?;
+                self.held_keys.lock().unwrap().record_key(&event);
+                Ok(())
+            },
+            InputEventType::TouchGesture => {
+                if let Some(gesture) = &event.gesture {
+                    self.handle_gesture(gesture, event.gesture_direction.as_ref(), event.gesture_magnitude)
+                } else {
+                    Err(InputForwardingError::UnsupportedEvent(
+                        "TouchGesture event missing gesture type".to_string()
+                    ))
+                }
+            },
+            InputEventType::SpecialCommand => {
+                if let Some(command) = &event.special_command {
+                    self.handle_special_command(command)
+                } else {
+                    Err(InputForwardingError::UnsupportedEvent(
+                        "SpecialCommand event missing command type".to_string()
+                    ))
+                }
+            },
+            InputEventType::TouchDown | InputEventType::TouchMove | InputEventType::TouchUp | InputEventType::Ping => {
+                self.forward_common(&self.touch, event)
+            },
+        }
+    }
+
+    fn set_mode(&self, mode: InputMode) {
+        *self.mode.lock().unwrap() = mode;
+
+        if mode == InputMode::Enabled {
+            self.pointer.lock().unwrap().reset();
+        }
+        self.release_keys_unless_enabled(mode);
+    }
+
+    fn mode(&self) -> InputMode {
+        *self.mode.lock().unwrap()
+    }
+
+    fn configure_monitors(&self, monitors: Vec<MonitorConfiguration>) -> Result<(), InputForwardingError> {
+        utils::validate_monitor_config(&monitors)?;
+        self.touch.lock().unwrap().configure_monitors(&monitors);
+        *self.monitors.lock().unwrap() = monitors;
+        self.pointer.lock().unwrap().reset();
+        Ok(())
+    }
+
+    fn handle_special_command(&self, command: &SpecialCommand) -> Result<(), InputForwardingError> {
+        let guard = self.system_actions.lock().unwrap().clone();
+        guard.admit(command, Instant::now())?;
+        self.press_combination(special_command_keys(command)?)
+    }
+
+    fn handle_gesture(&self, gesture: &TouchGesture, direction: Option<&GestureDirection>, magnitude: Option<f32>) -> Result<(), InputForwardingError> {
+        match gesture_action(gesture, direction, magnitude)? {
+            GestureAction::Scroll { vertical, steps } => self.scroll(vertical, steps),
+            GestureAction::Keys(keys) => self.press_combination(&keys),
+        }
+    }
+
+    fn backend(&self) -> InputBackend {
+        InputBackend::VirtualInput
+    }
+
+    fn reset_input_state(&self) -> Result<(), InputForwardingError> {
+        let releases = self.held_keys.lock().unwrap().take_releases();
+        self.shortcuts.lock().unwrap().reset();
+
+        let mut result = self.touch.lock().unwrap().release_all();
+        for key_code in releases.iter().filter_map(|release| release.key_code) {
+            if let Some(evdev) = js_key_to_evdev(key_code) {
+                if let Err(e) = self.key(evdev, false) {
+                    if result.is_ok() {
+                        result = Err(e);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn held_keys_idle_for(&self) -> Option<Duration> {
+        self.held_keys.lock().unwrap().idle_for()
+    }
+
+    fn set_system_action_guard(&self, guard: Arc<SystemActionGuard>) {
+        *self.system_actions.lock().unwrap() = guard;
+    }
+
+    fn set_shortcut_policy(&self, policy: &ShortcutPolicy) -> Result<(), InputForwardingError> {
+        self.shortcuts.lock().unwrap().set_policy(policy)
+    }
+
+    fn set_keyboard_layout(&self, layout: &str) -> Result<(), InputForwardingError> {
+        if self.devices.lock().unwrap().keymap.layout() == layout {
+            return Ok(());
+        }
+        // A bad layout keeps the current keyboard
+        let keymap = VirtualKeymap::compile(layout)?;
+        // Keys held on the old keyboard would never see their release
+        if let Err(e) = self.reset_input_state() {
+            log::warn!(target: TARGET_INPUT, "Failed to release held keys before switching the keymap: {}", e);
+        }
+        self.devices.lock().unwrap().replace_keyboard(keymap)?;
+        log::info!(target: TARGET_INPUT, "Virtual keyboard now uses the {} layout", layout);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Seek, SeekFrom};
+    use std::os::unix::net::UnixStream;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread::JoinHandle;
+
+    use wayland_protocols_misc::zwp_virtual_keyboard_v1::server::{
+        zwp_virtual_keyboard_manager_v1 as keyboard_manager, zwp_virtual_keyboard_v1 as keyboard,
+    };
+    use wayland_protocols_wlr::virtual_pointer::v1::server::{
+        zwlr_virtual_pointer_manager_v1 as pointer_manager, zwlr_virtual_pointer_v1 as pointer,
+    };
+    use wayland_server::backend::{ClientData, ClientId, DisconnectReason};
+    use wayland_server::protocol::wl_seat;
+    use wayland_server::{Client, DataInit, Dispatch, Display, DisplayHandle, GlobalDispatch, New, WEnum};
+
+    const KEY_LEFTSHIFT: u32 = 42;
+    const KEY_RIGHTALT: u32 = 100;
+    const KEY_CAPSLOCK: u32 = 58;
+    const KEY_Y: u32 = 21;
+
+    /// Compositor side of the protocols; records every request as a line
+    struct Compositor {
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Compositor {
+        fn record(&self, request: String) {
+            self.requests.lock().unwrap().push(request);
+        }
+    }
+
+    struct TestClient;
+
+    impl ClientData for TestClient {
+        fn initialized(&self, _: ClientId) {}
+        fn disconnected(&self, _: ClientId, _: DisconnectReason) {}
+    }
+
+    impl GlobalDispatch<wl_seat::WlSeat, ()> for Compositor {
+        fn bind(_: &mut Self, _: &DisplayHandle, _: &Client, seat: New<wl_seat::WlSeat>, _: &(), data_init: &mut DataInit<'_, Self>) {
+            data_init.init(seat, ());
+        }
+    }
+
+    impl Dispatch<wl_seat::WlSeat, ()> for Compositor {
+        fn request(_: &mut Self, _: &Client, _: &wl_seat::WlSeat, _: wl_seat::Request, _: &(), _: &DisplayHandle, _: &mut DataInit<'_, Self>) {}
+    }
+
+    impl GlobalDispatch<keyboard_manager::ZwpVirtualKeyboardManagerV1, ()> for Compositor {
+        fn bind(_: &mut Self, _: &DisplayHandle, _: &Client, manager: New<keyboard_manager::ZwpVirtualKeyboardManagerV1>, _: &(), data_init: &mut DataInit<'_, Self>) {
+            data_init.init(manager, ());
+        }
+    }
+
+    impl Dispatch<keyboard_manager::ZwpVirtualKeyboardManagerV1, ()> for Compositor {
+        fn request(
+            state: &mut Self,
+            _: &Client,
+            _: &keyboard_manager::ZwpVirtualKeyboardManagerV1,
+            request: keyboard_manager::Request,
+            _: &(),
+            _: &DisplayHandle,
+            data_init: &mut DataInit<'_, Self>,
+        ) {
+            if let keyboard_manager::Request::CreateVirtualKeyboard { id, .. } = request {
+                data_init.init(id, ());
+                state.record("create keyboard".to_string());
+            }
+        }
+    }
+
+    impl Dispatch<keyboard::ZwpVirtualKeyboardV1, ()> for Compositor {
+        fn request(state: &mut Self, _: &Client, _: &keyboard::ZwpVirtualKeyboardV1, request: keyboard::Request, _: &(), _: &DisplayHandle, _: &mut DataInit<'_, Self>) {
+            let line = match request {
+                keyboard::Request::Keymap { format, fd, size } => {
+                    // Compositors map the file; the descriptor shares the offset of the writer
+                    let mut file = File::from(fd);
+                    let mut text = String::new();
+                    file.seek(SeekFrom::Start(0)).unwrap();
+                    file.read_to_string(&mut text).unwrap();
+                    assert_eq!(text.len() as u32, size);
+                    assert!(text.ends_with('\0'));
+                    format!("keymap {} {}", format, key_y_symbol(text))
+                },
+                keyboard::Request::Key { key, state, .. } => format!("key {} {}", key, state),
+                keyboard::Request::Modifiers { mods_depressed, mods_locked, .. } => format!("modifiers {} {}", mods_depressed, mods_locked),
+                keyboard::Request::Destroy => "destroy keyboard".to_string(),
+                _ => return,
+            };
+            state.record(line);
+        }
+    }
+
+    impl GlobalDispatch<pointer_manager::ZwlrVirtualPointerManagerV1, ()> for Compositor {
+        fn bind(_: &mut Self, _: &DisplayHandle, _: &Client, manager: New<pointer_manager::ZwlrVirtualPointerManagerV1>, _: &(), data_init: &mut DataInit<'_, Self>) {
+            data_init.init(manager, ());
+        }
+    }
+
+    impl Dispatch<pointer_manager::ZwlrVirtualPointerManagerV1, ()> for Compositor {
+        fn request(
+            state: &mut Self,
+            _: &Client,
+            _: &pointer_manager::ZwlrVirtualPointerManagerV1,
+            request: pointer_manager::Request,
+            _: &(),
+            _: &DisplayHandle,
+            data_init: &mut DataInit<'_, Self>,
+        ) {
+            if let pointer_manager::Request::CreateVirtualPointer { id, .. } = request {
+                data_init.init(id, ());
+                state.record("create pointer".to_string());
+            }
+        }
+    }
+
+    impl Dispatch<pointer::ZwlrVirtualPointerV1, ()> for Compositor {
+        fn request(state: &mut Self, _: &Client, _: &pointer::ZwlrVirtualPointerV1, request: pointer::Request, _: &(), _: &DisplayHandle, _: &mut DataInit<'_, Self>) {
+            let line = match request {
+                pointer::Request::Motion { dx, dy, .. } => format!("motion {} {}", dx, dy),
+                pointer::Request::MotionAbsolute { x, y, x_extent, y_extent, .. } => format!("motion_absolute {} {} {} {}", x, y, x_extent, y_extent),
+                pointer::Request::Button { button, state: WEnum::Value(pressed), .. } => format!("button {} {:?}", button, pressed),
+                pointer::Request::AxisDiscrete { axis: WEnum::Value(axis), value, discrete, .. } => format!("axis {:?} {} {}", axis, value, discrete),
+                _ => return,
+            };
+            state.record(line);
+        }
+    }
+
+    /// What the key labelled Y on a US keyboard types with the keymap, as a compositor compiles it
+    fn key_y_symbol(mut text: String) -> String {
+        text.pop();
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let keymap = xkb::Keymap::new_from_string(&context, text, xkb::KEYMAP_FORMAT_TEXT_V1, xkb::KEYMAP_COMPILE_NO_FLAGS).unwrap();
+        let state = xkb::State::new(&keymap);
+        xkb::keysym_get_name(state.key_get_one_sym(xkb::Keycode::new(KEY_Y + EVDEV_OFFSET)))
+    }
+
+    /// A compositor on its own thread, reachable through one socket
+    struct MockCompositor {
+        requests: Arc<Mutex<Vec<String>>>,
+        stop: Arc<AtomicBool>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl MockCompositor {
+        /// Starts the compositor; without `virtual_input` it only offers a seat
+        fn start(virtual_input: bool) -> (Self, Connection) {
+            let (client_socket, server_socket) = UnixStream::pair().unwrap();
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let stop = Arc::new(AtomicBool::new(false));
+
+            let mut compositor = Compositor { requests: requests.clone() };
+            let thread_stop = stop.clone();
+            let thread = std::thread::spawn(move || {
+                let mut display: Display<Compositor> = Display::new().unwrap();
+                let mut handle = display.handle();
+                handle.create_global::<Compositor, wl_seat::WlSeat, ()>(7, ());
+                if virtual_input {
+                    handle.create_global::<Compositor, keyboard_manager::ZwpVirtualKeyboardManagerV1, ()>(1, ());
+                    handle.create_global::<Compositor, pointer_manager::ZwlrVirtualPointerManagerV1, ()>(2, ());
+                }
+                handle.insert_client(server_socket, Arc::new(TestClient)).unwrap();
+                while !thread_stop.load(Ordering::Relaxed) {
+                    display.dispatch_clients(&mut compositor).unwrap();
+                    display.flush_clients().unwrap();
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            });
+
+            let connection = Connection::from_socket(client_socket).unwrap();
+            (MockCompositor { requests, stop, thread: Some(thread) }, connection)
+        }
+
+        /// Requests received so far, waiting until `count` have arrived
+        fn take_requests(&self, count: usize) -> Vec<String> {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while self.requests.lock().unwrap().len() < count && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(2));
+            }
+            std::mem::take(&mut *self.requests.lock().unwrap())
+        }
+    }
+
+    impl Drop for MockCompositor {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::Relaxed);
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    fn key_event(key_code: u32, pressed: bool) -> InputEvent {
+        InputEvent {
+            event_type: if pressed { InputEventType::KeyPress } else { InputEventType::KeyRelease },
+            x: None,
+            y: None,
+            button: None,
+            key_code: Some(key_code),
+            modifiers: None,
+            is_pressed: Some(pressed),
+            delta_x: None,
+            delta_y: None,
+            monitor_index: None,
+            gesture: None,
+            gesture_direction: None,
+            gesture_magnitude: None,
+            special_command: None,
+            ping_id: None,
+            client_timestamp: None,
+            touch: None,
+        }
+    }
+
+    fn monitor(index: usize, x_offset: i32, is_primary: bool) -> MonitorConfiguration {
+        MonitorConfiguration {
+            index,
+            x_offset,
+            y_offset: 0,
+            width: 1920,
+            height: 1080,
+            scale_factor: 1.0,
+            rotation: MonitorRotation::Normal,
+            is_primary,
+        }
+    }
+
+    #[test]
+    fn test_keymap_tracks_layout_modifiers() {
+        let keymap = VirtualKeymap::compile("de(nodeadkeys)").unwrap();
+        assert_eq!(keymap.layout(), "de(nodeadkeys)");
+        assert_ne!(keymap.text, VirtualKeymap::compile("de").unwrap().text);
+        assert!(keymap.has_key(30) && !keymap.has_key(0x2ff));
+        assert!(VirtualKeymap::compile("no-such-layout").is_err());
+
+        // AltGr is a modifier of its own in the German layout
+        let mut modifiers = ModifierState::default();
+        let (shift, _) = modifiers.update(&keymap, KEY_LEFTSHIFT, true).unwrap();
+        let (both, _) = modifiers.update(&keymap, KEY_RIGHTALT, true).unwrap();
+        assert!(shift != 0 && both != shift && both & shift == shift);
+        assert_eq!(modifiers.update(&keymap, KEY_LEFTSHIFT, false), Some((both & !shift, 0)));
+        assert_eq!(modifiers.update(&keymap, 30, true), None);
+        // Releasing a modifier that is not held changes nothing
+        assert_eq!(modifiers.update(&keymap, KEY_LEFTSHIFT, false), None);
+
+        // Caps Lock stays locked after its release, and auto-repeat does not toggle it
+        let mut modifiers = ModifierState::default();
+        let (_, locked) = modifiers.update(&keymap, KEY_CAPSLOCK, true).unwrap();
+        assert_ne!(locked, 0);
+        assert_eq!(modifiers.update(&keymap, KEY_CAPSLOCK, true), None);
+        assert_eq!(modifiers.update(&keymap, KEY_CAPSLOCK, false), Some((0, locked)));
+        assert_eq!(modifiers.update(&keymap, KEY_CAPSLOCK, true), Some((locked, 0)));
+    }
+
+    #[test]
+    fn test_events_reach_the_virtual_devices() {
+        let (compositor, connection) = MockCompositor::start(true);
+        let forwarder = VirtualInputForwarder::with_connection(connection, "us").unwrap();
+        let setup = compositor.take_requests(3);
+        assert_eq!(setup[0], "create pointer");
+        assert_eq!(setup[1], "create keyboard");
+        assert_eq!(setup[2], "keymap 1 y");
+
+        // Shift+A with the modifier reported alongside the keys
+        forwarder.forward_event(&key_event(16, true)).unwrap();
+        forwarder.forward_event(&key_event(65, true)).unwrap();
+        forwarder.forward_event(&key_event(65, false)).unwrap();
+        forwarder.forward_event(&key_event(16, false)).unwrap();
+        assert_eq!(compositor.take_requests(6), vec![
+            "key 42 1", "modifiers 1 0", "key 30 1", "key 30 0", "key 42 0", "modifiers 0 0",
+        ]);
+
+        // Absolute motion over the layout box of both monitors
+        forwarder.configure_monitors(vec![monitor(0, -1920, false), monitor(1, 0, true)]).unwrap();
+        let mut event = key_event(0, true);
+        event.event_type = InputEventType::MouseMove;
+        (event.x, event.y, event.monitor_index) = (Some(100), Some(50), Some(1));
+        forwarder.forward_event(&event).unwrap();
+
+        event.event_type = InputEventType::MouseButton;
+        event.button = Some(MouseButton::Right);
+        forwarder.forward_event(&event).unwrap();
+
+        event.event_type = InputEventType::MouseScroll;
+        (event.delta_x, event.delta_y) = (Some(0.0), Some(-2.0));
+        forwarder.forward_event(&event).unwrap();
+        assert_eq!(compositor.take_requests(3), vec![
+            "motion_absolute 2020 50 3840 1080",
+            "button 273 Pressed",
+            "axis VerticalScroll -30 -2",
+        ]);
+
+        // Previewed input never reaches the compositor, leaving live input releases the modifiers
+        forwarder.set_mode(InputMode::Preview);
+        assert_eq!(compositor.take_requests(4), vec!["key 42 0", "key 29 0", "key 56 0", "key 125 0"]);
+        forwarder.forward_event(&key_event(65, true)).unwrap();
+        assert_eq!(forwarder.backend(), InputBackend::VirtualInput);
+        drop(forwarder);
+        assert_eq!(compositor.take_requests(1), vec!["destroy keyboard"]);
+    }
+
+    #[test]
+    fn test_layout_change_recreates_the_keyboard() {
+        let (compositor, connection) = MockCompositor::start(true);
+        let forwarder = VirtualInputForwarder::with_connection(connection, "us").unwrap();
+        compositor.take_requests(3);
+
+        forwarder.forward_event(&key_event(16, true)).unwrap();
+        compositor.take_requests(2);
+        forwarder.set_keyboard_layout("us").unwrap();
+        assert!(compositor.take_requests(0).is_empty());
+
+        // The held Shift and the other modifiers are released on the old keyboard before it goes away
+        forwarder.set_keyboard_layout("de").unwrap();
+        assert_eq!(compositor.take_requests(8), vec![
+            "key 42 0", "modifiers 0 0", "key 29 0", "key 56 0", "key 125 0",
+            "destroy keyboard", "create keyboard", "keymap 1 z",
+        ]);
+
+        // An unknown layout keeps the working keyboard
+        assert!(forwarder.set_keyboard_layout("no-such-layout").is_err());
+        forwarder.forward_event(&key_event(89, true)).unwrap();
+        // Y sits where QWERTY has Z; the keycode stays the physical key
+        assert_eq!(compositor.take_requests(1), vec!["key 21 1"]);
+    }
+
+    #[test]
+    fn test_compositor_without_virtual_input_is_refused() {
+        let (_compositor, connection) = MockCompositor::start(false);
+        let result = VirtualInputForwarder::with_connection(connection, "us");
+        assert!(matches!(result, Err(InputForwardingError::InitializationFailed(_))));
+    }
+}
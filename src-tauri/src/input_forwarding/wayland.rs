@@ -1,27 +1,44 @@
 // wayland.rs - Wayland-specific input forwarding implementation
 
-use std::process::Command;
 use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
+use crate::command_runner::{CommandRunner, SystemCommandRunner};
 use crate::input_forwarding::types::*;
 use crate::input_forwarding::error::InputForwardingError;
-use crate::input_forwarding::forwarder_trait::ImprovedInputForwarder;
+use crate::input_forwarding::forwarder_trait::{ImprovedInputForwarder, InputBackend};
 use crate::input_forwarding::utils;
+use crate::input_forwarding::custom_command::CustomCommand;
+use crate::input_forwarding::key_state::HeldKeys;
+use crate::input_forwarding::preview::InputMode;
+use crate::input_forwarding::shortcuts::{ShortcutFilter, ShortcutOutcome, ShortcutPolicy};
+use crate::input_forwarding::system_actions::SystemActionGuard;
+use crate::input_forwarding::touchscreen::TouchInput;
 
 // Improved Wayland input forwarder implementation
 pub struct ImprovedWaylandInputForwarder {
     monitors: Arc<Mutex<Vec<MonitorConfiguration>>>,
-    enabled: Arc<Mutex<bool>>,
+    mode: Arc<Mutex<InputMode>>,
     key_mapping: HashMap<u32, String>, // JavaScript keyCode to Linux input event code mapping
     active_modifiers: Arc<Mutex<Vec<String>>>, // Active modifiers
+    held_keys: Arc<Mutex<HeldKeys>>, // Keys pressed by the peer, released on reset
+    shortcuts: Arc<Mutex<ShortcutFilter>>, // Combinations ignored or translated before injection
+    touch: Arc<Mutex<TouchInput>>, // Touchscreens for multitouch, or the pointer without uinput
     special_commands: HashMap<SpecialCommand, Vec<String>>, // Key combinations for special commands
+    system_actions: Mutex<Arc<SystemActionGuard>>, // Admits disruptive special commands
+    runner: Arc<dyn CommandRunner>, // Builds the ydotool invocations
 }
 
 impl ImprovedWaylandInputForwarder {
     pub fn new() -> Result<Self, InputForwardingError> {
+        Self::with_runner(Arc::new(SystemCommandRunner))
+    }
+    
+    /// Create a forwarder that runs ydotool through `runner`
+    pub fn with_runner(runner: Arc<dyn CommandRunner>) -> Result<Self, InputForwardingError> {
         // Check if ydotool is installed
-        if !utils::check_tool_exists("ydotool") {
+        if !utils::tool_exists(runner.as_ref(), "ydotool") {
             return Err(InputForwardingError::InitializationFailed(
                 "ydotool is required for Wayland input forwarding".to_string(),
             ));
@@ -78,13 +95,24 @@ impl ImprovedWaylandInputForwarder {
         special_commands.insert(SpecialCommand::DesktopToggle, vec!["KEY_LEFTMETA".to_string(), "KEY_D".to_string()]);
         special_commands.insert(SpecialCommand::ScreenSnapshot, vec!["KEY_PRINT".to_string()]);
         special_commands.insert(SpecialCommand::LockScreen, vec!["KEY_LEFTMETA".to_string(), "KEY_L".to_string()]);
+        special_commands.insert(SpecialCommand::VolumeUp, vec!["KEY_VOLUMEUP".to_string()]);
+        special_commands.insert(SpecialCommand::VolumeDown, vec!["KEY_VOLUMEDOWN".to_string()]);
+        special_commands.insert(SpecialCommand::VolumeMute, vec!["KEY_MUTE".to_string()]);
+        special_commands.insert(SpecialCommand::MediaPlayPause, vec!["KEY_PLAYPAUSE".to_string()]);
+        special_commands.insert(SpecialCommand::MediaNext, vec!["KEY_NEXTSONG".to_string()]);
+        special_commands.insert(SpecialCommand::MediaPrev, vec!["KEY_PREVIOUSSONG".to_string()]);
         
         Ok(ImprovedWaylandInputForwarder {
             monitors: Arc::new(Mutex::new(Vec::new())),
-            enabled: Arc::new(Mutex::new(true)),
+            mode: Arc::new(Mutex::new(InputMode::Enabled)),
             key_mapping,
             active_modifiers: Arc::new(Mutex::new(Vec::new())),
+            held_keys: Arc::new(Mutex::new(HeldKeys::default())),
+            shortcuts: Arc::new(Mutex::new(ShortcutFilter::default())),
+            touch: Arc::new(Mutex::new(TouchInput::detect())),
             special_commands,
+            system_actions: Mutex::new(Arc::new(SystemActionGuard::default())),
+            runner,
         })
     }
     
@@ -113,7 +141,7 @@ impl ImprovedWaylandInputForwarder {
             }
             
             // Create ydotool command
-            let cmd_result = Command::new("ydotool")
+            let cmd_result = self.runner.command("ydotool")
                 .arg("input")
                 .arg("--type").arg("EV_KEY")
                 .arg("--code").arg(&key_code_str)
@@ -123,6 +151,7 @@ impl ImprovedWaylandInputForwarder {
             match cmd_result {
                 Ok(output) => {
                     if output.status.success() {
+                        self.held_keys.lock().unwrap().record_key(event);
                         Ok(())
                     } else {
                         Err(InputForwardingError::SendEventFailed(
@@ -141,6 +170,31 @@ impl ImprovedWaylandInputForwarder {
         }
     }
     
+    // Presses and releases single keys, as produced by a shortcut translation
+    fn inject_key_steps(&self, steps: &[(u32, bool)]) -> Result<(), InputForwardingError> {
+        for (key_code, is_pressed) in steps {
+            let key_code_str = match self.key_mapping.get(key_code) {
+                Some(code) => code.clone(),
+                None => format!("KEY_{}", key_code),
+            };
+            
+            let output = self.runner.command("ydotool")
+                .arg("input")
+                .arg("--type").arg("EV_KEY")
+                .arg("--code").arg(&key_code_str)
+                .arg("--value").arg(if *is_pressed { "1" } else { "0" })
+                .output()
+                .map_err(|e| InputForwardingError::SendEventFailed(format!("Failed to execute ydotool: {}", e)))?;
+            
+            if !output.status.success() {
+                return Err(InputForwardingError::SendEventFailed(
+                    format!("ydotool input failed: {}", String::from_utf8_lossy(&output.stderr))
+                ));
+            }
+        }
+        Ok(())
+    }
+    
     // Implementation of touch gestures for Wayland
     fn handle_wayland_gesture(&self, gesture: &TouchGesture, direction: Option<&GestureDirection>, magnitude: Option<f32>) -> Result<(), InputForwardingError> {
         // Wayland gesture support is similar to X11, but uses ydotool
@@ -167,7 +221,7 @@ impl ImprovedWaylandInputForwarder {
                     let repeats = (mag.abs() as i32).max(1);
                     
                     for _ in 0..repeats {
-                        let cmd_result = Command::new("ydotool")
+                        let cmd_result = self.runner.command("ydotool")
                             .arg("input")
                             .arg("--type").arg("EV_REL")
                             .arg("--code").arg(rel_type)
@@ -234,7 +288,7 @@ impl ImprovedWaylandInputForwarder {
                 
                 // Press all keys
                 for key in &key_sequence {
-                    let cmd_result = Command::new("ydotool")
+                    let cmd_result = self.runner.command("ydotool")
                         .arg("input")
                         .arg("--type").arg("EV_KEY")
                         .arg("--code").arg(key)
@@ -257,7 +311,7 @@ impl ImprovedWaylandInputForwarder {
                 
                 // Release all keys in reverse order
                 for key in key_sequence.iter().rev() {
-                    let cmd_result = Command::new("ydotool")
+                    let cmd_result = self.runner.command("ydotool")
                         .arg("input")
                         .arg("--type").arg("EV_KEY")
                         .arg("--code").arg(key)
@@ -293,12 +347,12 @@ impl ImprovedWaylandInputForwarder {
         let key_sequence = match self.special_commands.get(command) {
             Some(keys) => keys,
             None => {
-                // For custom commands, use direct string
+                // Custom commands are parsed and validated instead of mapped
                 if let SpecialCommand::Custom(cmd_str) = command {
-                    // Execute direct ydotool command
-                    let output = Command::new("sh")
-                        .arg("-c")
-                        .arg(format!("ydotool {}", cmd_str))
+                    // Run the validated command directly, never through a shell
+                    let custom = CustomCommand::parse(cmd_str)?;
+                    let output = self.runner.command("ydotool")
+                        .args(custom.args())
                         .output()
                         .map_err(|e| {
                             InputForwardingError::SendEventFailed(
@@ -323,7 +377,7 @@ impl ImprovedWaylandInputForwarder {
         
         // Press all keys
         for key in key_sequence {
-            let cmd_result = Command::new("ydotool")
+            let cmd_result = self.runner.command("ydotool")
                 .arg("input")
                 .arg("--type").arg("EV_KEY")
                 .arg("--code").arg(key)
@@ -346,7 +400,7 @@ impl ImprovedWaylandInputForwarder {
         
         // Release all keys in reverse order
         for key in key_sequence.iter().rev() {
-            let cmd_result = Command::new("ydotool")
+            let cmd_result = self.runner.command("ydotool")
                 .arg("input")
                 .arg("--type").arg("EV_KEY")
                 .arg("--code").arg(key)
@@ -374,9 +428,11 @@ impl ImprovedWaylandInputForwarder {
 // Implementation of ImprovedInputForwarder trait for Wayland
 impl ImprovedInputForwarder for ImprovedWaylandInputForwarder {
     fn forward_event(&self, event: &InputEvent) -> Result<(), InputForwardingError> {
-        if !self.is_enabled() {
+        // Previewed input is described by the app instead
+        if self.mode() != InputMode::Enabled {
             return Ok(());
         }
+        self.held_keys.lock().unwrap().touch();
         
         match event.event_type {
             InputEventType::MouseMove => {
@@ -386,7 +442,7 @@ impl ImprovedInputForwarder for ImprovedWaylandInputForwarder {
                     let (abs_x, abs_y) = utils::calculate_absolute_position(x, y, event.monitor_index, &monitors);
                     
                     // Execute ydotool
-                    let cmd_result = Command::new("ydotool")
+                    let cmd_result = self.runner.command("ydotool")
                         .arg("mousemove")
                         .arg("--absolute")  // Use absolute coordinates
                         .arg(abs_x.to_string())
@@ -407,6 +463,112 @@ impl ImprovedInputForwarder for ImprovedWaylandInputForwarder {
                             format!("Failed to execute ydotool: {}", e)
                         )),
                     }
+                } else {
+                    Err(InputForwardingError::UnsupportedEvent(
+                        "Mouse move event missing coordinates".to_string()
+                    ))
+                }
+            },
+            InputEventType::MouseButton => {
+                if let (Some(button), Some(is_pressed)) = (&event.button, event.is_pressed) {
+                    // For Wayland we use Linux button codes
+                    let button_arg = match button {
+                        MouseButton::Left => "BTN_LEFT",
+                        MouseButton::Middle => "BTN_MIDDLE",
+                        MouseButton::Right => "BTN_RIGHT",
+                        MouseButton::Back => "BTN_SIDE",
+                        MouseButton::Forward => "BTN_EXTRA",
+                        MouseButton::ScrollUp | MouseButton::ScrollDown => {
+                            return Err(InputForwardingError::UnsupportedEvent(
+                                "Scroll events should use MouseScroll type".to_string()
+                            ));
+                        },
+                        MouseButton::TouchTap => {
+                            // Simulate left click for touch tap
+                            let tap_event = InputEvent {
+                                event_type: InputEventType::MouseButton,
+                                button: Some(MouseButton::Left),
+                                is_pressed: Some(true),
+                                x: event.x, y: event.y,
+                                key_code: None, modifiers: None, delta_x: None, delta_y: None,
+                                monitor_index: event.monitor_index, gesture: None, 
+                                gesture_direction: None, gesture_magnitude: None, special_command: None, ping_id: None, client_timestamp: None, touch: None,
+                            };
+                            self.forward_event(&tap_event)?;
+                            
+                            // Release after short delay
+                            let release_event = InputEvent {
+                                event_type: InputEventType::MouseButton,
+                                button: Some(MouseButton::Left),
+                                is_pressed: Some(false),
+                                x: event.x, y: event.y,
+                                key_code: None, modifiers: None, delta_x: None, delta_y: None,
+                                monitor_index: event.monitor_index, gesture: None, 
+                                gesture_direction: None, gesture_magnitude: None, special_command: None, ping_id: None, client_timestamp: None, touch: None,
+                            };
+                            self.forward_event(&release_event)?;
+                            return Ok(());
+                        },
+                        MouseButton::TouchDoubleTap => {
+                            // Simulate double-click by pressing and releasing twice
+                            for _ in 0..2 {
+                                // Press
+                                let cmd_result = self.runner.command("ydotool")
+                                    .arg("input")
+                                    .arg("--type").arg("EV_KEY")
+                                    .arg("--code").arg("BTN_LEFT")
+                                    .arg("--value").arg("1")
+                                    .output();
+                                
+                                if let Err(e) = cmd_result {
+                                    return Err(InputForwardingError::SendEventFailed(
+                                        format!("Failed to execute ydotool: {}", e)
+                                    ));
+                                }
+                                
+                                // Release
+                                let cmd_result = self.runner.command("ydotool")
+                                    .arg("input")
+                                    .arg("--type").arg("EV_KEY")
+                                    .arg("--code").arg("BTN_LEFT")
+                                    .arg("--value").arg("0")
+                                    .output();
+                                
+                                if let Err(e) = cmd_result {
+                                    return Err(InputForwardingError::SendEventFailed(
+                                        format!("Failed to execute ydotool: {}", e)
+                                    ));
+                                }
+                            }
+                            
+                            return Ok(());
+                        },
+                    };
+                    
+                    let value = if is_pressed { "1" } else { "0" };
+                    
+                    // Execute ydotool command
+                    let cmd_result = self.runner.command("ydotool")
+                        .arg("input")
+                        .arg("--type").arg("EV_KEY")
+                        .arg("--code").arg(button_arg)
+                        .arg("--value").arg(value)
+                        .output();
+                    
+                    match cmd_result {
+                        Ok(output) => {
+                            if output.status.success() {
+                                Ok(())
+                            } else {
+                                Err(InputForwardingError::SendEventFailed(
+                                    format!("ydotool button event failed: {}", String::from_utf8_lossy(&output.stderr))
+                                ))
+                            }
+                        }
+                        Err(e) => Err(InputForwardingError::SendEventFailed(
+                            format!("Failed to execute ydotool: {}", e)
+                        )),
+                    }
                 } else {
                     Err(InputForwardingError::UnsupportedEvent(
                         "Mouse button event missing button or pressed state".to_string()
@@ -421,7 +583,7 @@ impl ImprovedInputForwarder for ImprovedWaylandInputForwarder {
                         let repeats = (delta_y.abs() as i32).max(1);
                         
                         for _ in 0..repeats {
-                            let cmd_result = Command::new("ydotool")
+                            let cmd_result = self.runner.command("ydotool")
                                 .arg("input")
                                 .arg("--type").arg("EV_REL")
                                 .arg("--code").arg("REL_WHEEL")
@@ -449,7 +611,7 @@ impl ImprovedInputForwarder for ImprovedWaylandInputForwarder {
                         let repeats = (delta_x.abs() as i32).max(1);
                         
                         for _ in 0..repeats {
-                            let cmd_result = Command::new("ydotool")
+                            let cmd_result = self.runner.command("ydotool")
                                 .arg("input")
                                 .arg("--type").arg("EV_REL")
                                 .arg("--code").arg("REL_HWHEEL")
@@ -479,7 +641,13 @@ impl ImprovedInputForwarder for ImprovedWaylandInputForwarder {
                 }
             },
             InputEventType::KeyPress | InputEventType::KeyRelease => {
-                self.forward_improved_key_event(event)
+                let active_modifiers = self.active_modifiers.lock().unwrap().clone();
+                let outcome = self.shortcuts.lock().unwrap().check(event, &active_modifiers);
+                match outcome {
+                    ShortcutOutcome::Forward(event) => self.forward_improved_key_event(&event),
+                    ShortcutOutcome::Drop => Ok(()),
+                    ShortcutOutcome::Inject(steps) => self.inject_key_steps(&steps),
+                }
             },
             InputEventType::TouchGesture => {
                 if let Some(gesture) = &event.gesture {
@@ -492,27 +660,31 @@ impl ImprovedInputForwarder for ImprovedWaylandInputForwarder {
             },
             InputEventType::SpecialCommand => {
                 if let Some(command) = &event.special_command {
-                    self.execute_special_command(command)
+                    self.handle_special_command(command)
                 } else {
                     Err(InputForwardingError::UnsupportedEvent(
                         "SpecialCommand event missing command type".to_string()
                     ))
                 }
             },
+            InputEventType::TouchDown | InputEventType::TouchMove | InputEventType::TouchUp | InputEventType::Ping => {
+                self.forward_common(&self.touch, event)
+            },
         }
     }
 
-    fn set_enabled(&self, enabled: bool) {
-        let mut state = self.enabled.lock().unwrap();
-        *state = enabled;
+    fn set_mode(&self, mode: InputMode) {
+        *self.mode.lock().unwrap() = mode;
+        self.release_keys_unless_enabled(mode);
     }
 
-    fn is_enabled(&self) -> bool {
-        *self.enabled.lock().unwrap()
+    fn mode(&self) -> InputMode {
+        *self.mode.lock().unwrap()
     }
 
-    fn configure_monitors(&mut self, monitors: Vec<MonitorConfiguration>) -> Result<(), InputForwardingError> {
+    fn configure_monitors(&self, monitors: Vec<MonitorConfiguration>) -> Result<(), InputForwardingError> {
         utils::validate_monitor_config(&monitors)?;
+        self.touch.lock().unwrap().configure_monitors(&monitors);
         
         let mut monitor_config = self.monitors.lock().unwrap();
         *monitor_config = monitors;
@@ -521,116 +693,322 @@ impl ImprovedInputForwarder for ImprovedWaylandInputForwarder {
     }
 
     fn handle_special_command(&self, command: &SpecialCommand) -> Result<(), InputForwardingError> {
+        let guard = self.system_actions.lock().unwrap().clone();
+        guard.admit(command, Instant::now())?;
         self.execute_special_command(command)
     }
 
     fn handle_gesture(&self, gesture: &TouchGesture, direction: Option<&GestureDirection>, magnitude: Option<f32>) -> Result<(), InputForwardingError> {
         self.handle_wayland_gesture(gesture, direction, magnitude)
     }
-}
-                    format!("ydotool mousemove failed: {}", String::from_utf8_lossy(&output.stderr))
-                                ))
-                            }
-                        }
-                        Err(e) => Err(InputForwardingError::SendEventFailed(
-                            format!("Failed to execute ydotool: {}", e)
-                        )),
-                    }
-                } else {
-                    Err(InputForwardingError::UnsupportedEvent(
-                        "Mouse move event missing coordinates".to_string()
-                    ))
+
+    fn reset_input_state(&self) -> Result<(), InputForwardingError> {
+        let releases = self.held_keys.lock().unwrap().take_releases();
+        self.active_modifiers.lock().unwrap().clear();
+        self.shortcuts.lock().unwrap().reset();
+        
+        // Release everything even if one key fails, report the first failure
+        let mut result = self.touch.lock().unwrap().release_all();
+        for release in &releases {
+            if let Err(e) = self.forward_improved_key_event(release) {
+                if result.is_ok() {
+                    result = Err(e);
                 }
-            },
-            InputEventType::MouseButton => {
-                if let (Some(button), Some(is_pressed)) = (&event.button, event.is_pressed) {
-                    // For Wayland we use Linux button codes
-                    let button_arg = match button {
-                        MouseButton::Left => "BTN_LEFT",
-                        MouseButton::Middle => "BTN_MIDDLE",
-                        MouseButton::Right => "BTN_RIGHT",
-                        MouseButton::Back => "BTN_SIDE",
-                        MouseButton::Forward => "BTN_EXTRA",
-                        MouseButton::ScrollUp | MouseButton::ScrollDown => {
-                            return Err(InputForwardingError::UnsupportedEvent(
-                                "Scroll events should use MouseScroll type".to_string()
-                            ));
-                        },
-                        MouseButton::TouchTap => {
-                            // Simulate left click for touch tap
-                            let tap_event = InputEvent {
-                                event_type: InputEventType::MouseButton,
-                                button: Some(MouseButton::Left),
-                                is_pressed: Some(true),
-                                x: event.x, y: event.y,
-                                key_code: None, modifiers: None, delta_x: None, delta_y: None,
-                                monitor_index: event.monitor_index, gesture: None, 
-                                gesture_direction: None, gesture_magnitude: None, special_command: None,
-                            };
-                            self.forward_event(&tap_event)?;
-                            
-                            // Release after short delay
-                            let release_event = InputEvent {
-                                event_type: InputEventType::MouseButton,
-                                button: Some(MouseButton::Left),
-                                is_pressed: Some(false),
-                                x: event.x, y: event.y,
-                                key_code: None, modifiers: None, delta_x: None, delta_y: None,
-                                monitor_index: event.monitor_index, gesture: None, 
-                                gesture_direction: None, gesture_magnitude: None, special_command: None,
-                            };
-                            self.forward_event(&release_event)?;
-                            return Ok(());
-                        },
-                        MouseButton::TouchDoubleTap => {
-                            // Simulate double-click by pressing and releasing twice
-                            for _ in 0..2 {
-                                // Press
-                                let cmd_result = Command::new("ydotool")
-                                    .arg("input")
-                                    .arg("--type").arg("EV_KEY")
-                                    .arg("--code").arg("BTN_LEFT")
-                                    .arg("--value").arg("1")
-                                    .output();
-                                
-                                if let Err(e) = cmd_result {
-                                    return Err(InputForwardingError::SendEventFailed(
-                                        format!("Failed to execute ydotool: {}", e)
-                                    ));
-                                }
-                                
-                                // Release
-                                let cmd_result = Command::new("ydotool")
-                                    .arg("input")
-                                    .arg("--type").arg("EV_KEY")
-                                    .arg("--code").arg("BTN_LEFT")
-                                    .arg("--value").arg("0")
-                                    .output();
-                                
-                                if let Err(e) = cmd_result {
-                                    return Err(InputForwardingError::SendEventFailed(
-                                        format!("Failed to execute ydotool: {}", e)
-                                    ));
-                                }
-                            }
-                            
-                            return Ok(());
-                        },
-                    };
-                    
-                    let value = if is_pressed { "1" } else { "0" };
-                    
-                    // Execute ydotool command
-                    let cmd_result = Command::new("ydotool")
-                        .arg("input")
-                        .arg("--type").arg("EV_KEY")
-                        .arg("--code").arg(button_arg)
-                        .arg("--value").arg(value)
-                        .output();
-                    
-                    match cmd_result {
-                        Ok(output) => {
-                            if output.status.success() {
-                                Ok(())
-                            } else {
-                                Err(InputForwardingError::SendEventFailed(
+            }
+        }
+        result
+    }
+    
+    fn held_keys_idle_for(&self) -> Option<Duration> {
+        self.held_keys.lock().unwrap().idle_for()
+    }
+    
+    fn set_system_action_guard(&self, guard: Arc<SystemActionGuard>) {
+        *self.system_actions.lock().unwrap() = guard;
+    }
+    
+    fn set_shortcut_policy(&self, policy: &ShortcutPolicy) -> Result<(), InputForwardingError> {
+        self.shortcuts.lock().unwrap().set_policy(policy)
+    }
+    
+    fn backend(&self) -> InputBackend {
+        InputBackend::Ydotool
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_forwarding::shortcuts::{ShortcutAction, ShortcutRule};
+    use crate::input_forwarding::system_actions::{SystemActionConfig, SystemActionPolicy};
+    use crate::test_env::{call, TestEnv};
+
+    fn event(event_type: InputEventType) -> InputEvent {
+        InputEvent {
+            event_type,
+            x: None,
+            y: None,
+            button: None,
+            key_code: None,
+            modifiers: None,
+            is_pressed: None,
+            delta_x: None,
+            delta_y: None,
+            monitor_index: None,
+            gesture: None,
+            gesture_direction: None,
+            gesture_magnitude: None,
+            special_command: None,
+            ping_id: None,
+            client_timestamp: None,
+            touch: None,
+        }
+    }
+
+    fn button(button: MouseButton, is_pressed: bool) -> InputEvent {
+        InputEvent { button: Some(button), is_pressed: Some(is_pressed), ..event(InputEventType::MouseButton) }
+    }
+
+    fn key(key_code: u32, is_pressed: bool, modifiers: &[&str]) -> InputEvent {
+        InputEvent {
+            key_code: Some(key_code),
+            is_pressed: Some(is_pressed),
+            modifiers: Some(modifiers.iter().map(|m| m.to_string()).collect()),
+            ..event(if is_pressed { InputEventType::KeyPress } else { InputEventType::KeyRelease })
+        }
+    }
+
+    fn special(command: SpecialCommand) -> InputEvent {
+        InputEvent { special_command: Some(command), ..event(InputEventType::SpecialCommand) }
+    }
+
+    /// ydotool call injecting a single evdev event
+    fn input(event_type: &str, code: &str, value: &str) -> String {
+        format!("ydotool input --type {} --code {} --value {}", event_type, code, value)
+    }
+
+    fn forwarder(env: &TestEnv) -> ImprovedWaylandInputForwarder {
+        let forwarder = ImprovedWaylandInputForwarder::with_runner(env.runner()).unwrap();
+        // Whether uinput is writable depends on the machine running the tests
+        *forwarder.touch.lock().unwrap() = TouchInput::pointer_only();
+        forwarder.configure_monitors(vec![MonitorConfiguration {
+            index: 0,
+            x_offset: 1920,
+            y_offset: 0,
+            width: 1920,
+            height: 1080,
+            scale_factor: 1.0,
+            rotation: MonitorRotation::Normal,
+            is_primary: true,
+        }]).unwrap();
+        forwarder
+    }
+
+    #[test]
+    fn test_input_event_matrix() {
+        let env = TestEnv::new();
+        let forwarder = forwarder(&env);
+        // Disruptive commands run without asking; the guard has its own test
+        forwarder.set_system_action_guard(Arc::new(SystemActionGuard::new(SystemActionConfig {
+            default_policy: SystemActionPolicy::Allow,
+            ..SystemActionConfig::default()
+        })));
+
+        let cases: Vec<(InputEvent, Vec<String>)> = vec![
+            (InputEvent { x: Some(100), y: Some(200), ..event(InputEventType::MouseMove) }, vec!["ydotool mousemove --absolute 2020 200".to_string()]),
+            (button(MouseButton::Left, true), vec![input("EV_KEY", "BTN_LEFT", "1")]),
+            (button(MouseButton::Left, false), vec![input("EV_KEY", "BTN_LEFT", "0")]),
+            (button(MouseButton::Middle, true), vec![input("EV_KEY", "BTN_MIDDLE", "1")]),
+            (button(MouseButton::Right, false), vec![input("EV_KEY", "BTN_RIGHT", "0")]),
+            (button(MouseButton::Back, true), vec![input("EV_KEY", "BTN_SIDE", "1")]),
+            (button(MouseButton::Forward, true), vec![input("EV_KEY", "BTN_EXTRA", "1")]),
+            (button(MouseButton::TouchTap, true), vec![input("EV_KEY", "BTN_LEFT", "1"), input("EV_KEY", "BTN_LEFT", "0")]),
+            (
+                button(MouseButton::TouchDoubleTap, true),
+                vec![
+                    input("EV_KEY", "BTN_LEFT", "1"), input("EV_KEY", "BTN_LEFT", "0"),
+                    input("EV_KEY", "BTN_LEFT", "1"), input("EV_KEY", "BTN_LEFT", "0"),
+                ],
+            ),
+            (
+                InputEvent { delta_x: Some(0.0), delta_y: Some(2.0), ..event(InputEventType::MouseScroll) },
+                vec![input("EV_REL", "REL_WHEEL", "-1"), input("EV_REL", "REL_WHEEL", "-1")],
+            ),
+            (
+                InputEvent { delta_x: Some(-1.0), delta_y: Some(-1.0), ..event(InputEventType::MouseScroll) },
+                vec![input("EV_REL", "REL_WHEEL", "1"), input("EV_REL", "REL_HWHEEL", "1")],
+            ),
+            (key(65, true, &["ctrl"]), vec![input("EV_KEY", "KEY_A", "1")]),
+            (key(65, false, &["ctrl"]), vec![input("EV_KEY", "KEY_A", "0")]),
+            (key(112, true, &[]), vec![input("EV_KEY", "KEY_F1", "1")]),
+            (key(250, false, &[]), vec![input("EV_KEY", "KEY_250", "0")]),
+            (
+                InputEvent {
+                    gesture: Some(TouchGesture::TwoFingerScroll),
+                    gesture_direction: Some(GestureDirection::Left),
+                    ..event(InputEventType::TouchGesture)
+                },
+                vec![input("EV_REL", "REL_HWHEEL", "-1")],
+            ),
+            (
+                InputEvent { gesture: Some(TouchGesture::Pinch), gesture_magnitude: Some(1.0), ..event(InputEventType::TouchGesture) },
+                vec![
+                    input("EV_KEY", "KEY_LEFTCTRL", "1"), input("EV_KEY", "KEY_KPPLUS", "1"),
+                    input("EV_KEY", "KEY_KPPLUS", "0"), input("EV_KEY", "KEY_LEFTCTRL", "0"),
+                ],
+            ),
+            (
+                special(SpecialCommand::AppSwitcher),
+                vec![
+                    input("EV_KEY", "KEY_LEFTALT", "1"), input("EV_KEY", "KEY_TAB", "1"),
+                    input("EV_KEY", "KEY_TAB", "0"), input("EV_KEY", "KEY_LEFTALT", "0"),
+                ],
+            ),
+            (special(SpecialCommand::VolumeUp), vec![input("EV_KEY", "KEY_VOLUMEUP", "1"), input("EV_KEY", "KEY_VOLUMEUP", "0")]),
+            (special(SpecialCommand::VolumeDown), vec![input("EV_KEY", "KEY_VOLUMEDOWN", "1"), input("EV_KEY", "KEY_VOLUMEDOWN", "0")]),
+            (special(SpecialCommand::VolumeMute), vec![input("EV_KEY", "KEY_MUTE", "1"), input("EV_KEY", "KEY_MUTE", "0")]),
+            (special(SpecialCommand::MediaPlayPause), vec![input("EV_KEY", "KEY_PLAYPAUSE", "1"), input("EV_KEY", "KEY_PLAYPAUSE", "0")]),
+            (special(SpecialCommand::MediaNext), vec![input("EV_KEY", "KEY_NEXTSONG", "1"), input("EV_KEY", "KEY_NEXTSONG", "0")]),
+            (special(SpecialCommand::MediaPrev), vec![input("EV_KEY", "KEY_PREVIOUSSONG", "1"), input("EV_KEY", "KEY_PREVIOUSSONG", "0")]),
+            (special(SpecialCommand::Custom("key ctrl+alt+Delete".to_string())), vec!["ydotool key ctrl+alt+Delete".to_string()]),
+            (event(InputEventType::Ping), vec![]),
+        ];
+
+        for (event, expected) in cases {
+            forwarder.forward_event(&event).unwrap_or_else(|e| panic!("{:?} failed: {}", event, e));
+            let expected: Vec<Vec<String>> = expected.iter().map(|line| call(line)).collect();
+            assert_eq!(env.take_invocations(), expected, "{:?}", event);
+        }
+    }
+
+    #[test]
+    fn test_invalid_events_run_nothing() {
+        let env = TestEnv::new();
+        let forwarder = forwarder(&env);
+
+        let invalid = [
+            event(InputEventType::MouseMove),
+            button(MouseButton::ScrollDown, false),
+            event(InputEventType::KeyRelease),
+            InputEvent { gesture: Some(TouchGesture::Rotate), ..event(InputEventType::TouchGesture) },
+            special(SpecialCommand::Custom("exec rm -rf /".to_string())),
+        ];
+        for event in &invalid {
+            assert!(matches!(forwarder.forward_event(event), Err(InputForwardingError::UnsupportedEvent(_))), "{:?}", event);
+        }
+        assert!(env.invocations().is_empty());
+    }
+
+    #[test]
+    fn test_disruptive_commands_need_clearance() {
+        let env = TestEnv::new();
+        let forwarder = forwarder(&env);
+        let guard = Arc::new(SystemActionGuard::default());
+        forwarder.set_system_action_guard(guard.clone());
+
+        let reboot = SpecialCommand::Custom("key ctrl+alt+Delete".to_string());
+        assert!(matches!(forwarder.forward_event(&special(reboot.clone())), Err(InputForwardingError::PermissionDenied(_))));
+        assert!(forwarder.handle_special_command(&SpecialCommand::LockScreen).is_err());
+        assert!(env.invocations().is_empty());
+
+        guard.request(None, false, &reboot, Instant::now());
+        forwarder.handle_special_command(&reboot).unwrap();
+        assert_eq!(env.take_invocations(), vec![call("ydotool key ctrl+alt+Delete")]);
+        assert!(forwarder.handle_special_command(&reboot).is_err());
+    }
+
+    #[test]
+    fn test_tool_failure_is_reported() {
+        let env = TestEnv::new();
+        let forwarder = forwarder(&env);
+        env.set_exit_code("ydotool", 2);
+        env.set_stderr("ydotool", "failed to connect socket `/tmp/.ydotool_socket'");
+
+        match forwarder.forward_event(&key(65, true, &[])) {
+            Err(InputForwardingError::SendEventFailed(message)) => assert!(message.contains("ydotool_socket"), "{}", message),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reset_releases_held_keys() {
+        let env = TestEnv::new();
+        let forwarder = forwarder(&env);
+        forwarder.forward_event(&key(65, true, &[])).unwrap();
+        env.take_invocations();
+
+        forwarder.reset_input_state().unwrap();
+        assert_eq!(env.take_invocations(), vec![
+            call(&input("EV_KEY", "KEY_A", "0")),
+            call(&input("EV_KEY", "KEY_LEFTSHIFT", "0")),
+            call(&input("EV_KEY", "KEY_LEFTCTRL", "0")),
+            call(&input("EV_KEY", "KEY_LEFTALT", "0")),
+            call(&input("EV_KEY", "KEY_LEFTMETA", "0")),
+        ]);
+
+        // Disabled forwarders inject nothing
+        forwarder.set_enabled(false);
+        env.take_invocations();
+        forwarder.forward_event(&button(MouseButton::Left, true)).unwrap();
+        assert!(env.invocations().is_empty());
+    }
+
+    #[test]
+    fn test_preview_mode_runs_nothing() {
+        let env = TestEnv::new();
+        let forwarder = forwarder(&env);
+        forwarder.forward_event(&key(65, true, &[])).unwrap();
+
+        // Switching releases the held key, and from then on nothing is spawned
+        forwarder.set_mode(InputMode::Preview);
+        assert!(!forwarder.is_enabled());
+        env.take_invocations();
+
+        let events = [
+            InputEvent { x: Some(400), y: Some(300), monitor_index: Some(0), ..event(InputEventType::MouseMove) },
+            button(MouseButton::Left, true),
+            button(MouseButton::TouchTap, true),
+            key(84, true, &["ctrl", "alt"]),
+            InputEvent { delta_x: Some(0.0), delta_y: Some(3.0), ..event(InputEventType::MouseScroll) },
+            special(SpecialCommand::LockScreen),
+        ];
+        for event in &events {
+            forwarder.forward_event(event).unwrap();
+        }
+        assert!(env.invocations().is_empty());
+
+        forwarder.set_enabled(true);
+        assert_eq!(forwarder.mode(), InputMode::Enabled);
+    }
+
+    #[test]
+    fn test_shortcut_policy() {
+        let env = TestEnv::new();
+        let forwarder = forwarder(&env);
+
+        // The default policy keeps the host on its graphical session
+        forwarder.forward_event(&key(112, true, &["ctrl", "alt"])).unwrap();
+        forwarder.forward_event(&key(112, false, &["ctrl", "alt"])).unwrap();
+        assert!(env.take_invocations().is_empty());
+
+        forwarder.set_shortcut_policy(&ShortcutPolicy {
+            rules: vec![ShortcutRule {
+                combo: "ctrl+Left".to_string(),
+                action: ShortcutAction::TranslateTo("alt+Left".to_string()),
+            }],
+        }).unwrap();
+        forwarder.forward_event(&key(37, true, &["ctrl"])).unwrap();
+        assert_eq!(env.take_invocations(), vec![
+            call(&input("EV_KEY", "KEY_LEFTCTRL", "0")),
+            call(&input("EV_KEY", "KEY_LEFTALT", "1")),
+            call(&input("EV_KEY", "KEY_LEFT", "1")),
+            call(&input("EV_KEY", "KEY_LEFT", "0")),
+            call(&input("EV_KEY", "KEY_LEFTALT", "0")),
+            call(&input("EV_KEY", "KEY_LEFTCTRL", "1")),
+        ]);
+
+        // The release belongs to the translated press
+        forwarder.forward_event(&key(37, false, &["ctrl"])).unwrap();
+        assert!(env.take_invocations().is_empty());
+    }
+}
@@ -1,28 +1,45 @@
 // x11.rs - X11-specific input forwarding implementation
 
-use std::process::Command;
 use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
+use crate::command_runner::{CommandRunner, SystemCommandRunner};
 use crate::input_forwarding::types::*;
 use crate::input_forwarding::error::InputForwardingError;
-use crate::input_forwarding::forwarder_trait::ImprovedInputForwarder;
+use crate::input_forwarding::forwarder_trait::{ImprovedInputForwarder, InputBackend};
 use crate::input_forwarding::utils;
+use crate::input_forwarding::custom_command::CustomCommand;
+use crate::input_forwarding::key_state::HeldKeys;
+use crate::input_forwarding::preview::InputMode;
+use crate::input_forwarding::shortcuts::{ShortcutFilter, ShortcutOutcome, ShortcutPolicy};
+use crate::input_forwarding::system_actions::SystemActionGuard;
+use crate::input_forwarding::touchscreen::TouchInput;
 
 // Improved X11 input forwarder implementation
 pub struct ImprovedX11InputForwarder {
     monitors: Arc<Mutex<Vec<MonitorConfiguration>>>,
-    enabled: Arc<Mutex<bool>>,
+    mode: Arc<Mutex<InputMode>>,
     key_mapping: HashMap<u32, String>, // JavaScript keyCode to X11 keysym mapping
     active_modifiers: Arc<Mutex<Vec<String>>>, // Active modifiers
+    held_keys: Arc<Mutex<HeldKeys>>, // Keys pressed by the peer, released on reset
+    shortcuts: Arc<Mutex<ShortcutFilter>>, // Combinations ignored or translated before injection
+    touch: Arc<Mutex<TouchInput>>, // Touchscreens for multitouch, or the pointer without uinput
     // Key combinations for special commands
     special_commands: HashMap<SpecialCommand, Vec<String>>,
+    system_actions: Mutex<Arc<SystemActionGuard>>, // Admits disruptive special commands
+    runner: Arc<dyn CommandRunner>, // Builds the xdotool invocations
 }
 
 impl ImprovedX11InputForwarder {
     pub fn new() -> Result<Self, InputForwardingError> {
+        Self::with_runner(Arc::new(SystemCommandRunner))
+    }
+    
+    /// Create a forwarder that runs xdotool through `runner`
+    pub fn with_runner(runner: Arc<dyn CommandRunner>) -> Result<Self, InputForwardingError> {
         // Check if xdotool is installed
-        if !utils::check_tool_exists("xdotool") {
+        if !utils::tool_exists(runner.as_ref(), "xdotool") {
             return Err(InputForwardingError::InitializationFailed(
                 "xdotool is required for X11 input forwarding".to_string(),
             ));
@@ -76,13 +93,24 @@ impl ImprovedX11InputForwarder {
         special_commands.insert(SpecialCommand::DesktopToggle, vec!["super".to_string(), "d".to_string()]);
         special_commands.insert(SpecialCommand::ScreenSnapshot, vec!["Print".to_string()]);
         special_commands.insert(SpecialCommand::LockScreen, vec!["super".to_string(), "l".to_string()]);
+        special_commands.insert(SpecialCommand::VolumeUp, vec!["XF86AudioRaiseVolume".to_string()]);
+        special_commands.insert(SpecialCommand::VolumeDown, vec!["XF86AudioLowerVolume".to_string()]);
+        special_commands.insert(SpecialCommand::VolumeMute, vec!["XF86AudioMute".to_string()]);
+        special_commands.insert(SpecialCommand::MediaPlayPause, vec!["XF86AudioPlay".to_string()]);
+        special_commands.insert(SpecialCommand::MediaNext, vec!["XF86AudioNext".to_string()]);
+        special_commands.insert(SpecialCommand::MediaPrev, vec!["XF86AudioPrev".to_string()]);
         
         Ok(ImprovedX11InputForwarder {
             monitors: Arc::new(Mutex::new(Vec::new())),
-            enabled: Arc::new(Mutex::new(true)),
+            mode: Arc::new(Mutex::new(InputMode::Enabled)),
             key_mapping,
             active_modifiers: Arc::new(Mutex::new(Vec::new())),
+            held_keys: Arc::new(Mutex::new(HeldKeys::default())),
+            shortcuts: Arc::new(Mutex::new(ShortcutFilter::default())),
+            touch: Arc::new(Mutex::new(TouchInput::detect())),
             special_commands,
+            system_actions: Mutex::new(Arc::new(SystemActionGuard::default())),
+            runner,
         })
     }
     
@@ -111,7 +139,7 @@ impl ImprovedX11InputForwarder {
             }
             
             // Create xdotool command
-            let mut cmd = Command::new("xdotool");
+            let mut cmd = self.runner.command("xdotool");
             cmd.arg(action);
             
             // Add active modifiers
@@ -139,12 +167,37 @@ impl ImprovedX11InputForwarder {
                 ));
             }
             
+            self.held_keys.lock().unwrap().record_key(event);
             Ok(())
         } else {
             Err(InputForwardingError::UnsupportedEvent("Key event missing keyCode or pressed state".to_string()))
         }
     }
     
+    // Presses and releases single keys, as produced by a shortcut translation
+    fn inject_key_steps(&self, steps: &[(u32, bool)]) -> Result<(), InputForwardingError> {
+        for (key_code, is_pressed) in steps {
+            let key_sym = match self.key_mapping.get(key_code) {
+                Some(sym) => sym.clone(),
+                None => format!("0x{:X}", key_code),
+            };
+            let action = if *is_pressed { "keydown" } else { "keyup" };
+            
+            let output = self.runner.command("xdotool")
+                .arg(action)
+                .arg(&key_sym)
+                .output()
+                .map_err(|e| InputForwardingError::SendEventFailed(format!("Error executing xdotool: {}", e)))?;
+            
+            if !output.status.success() {
+                return Err(InputForwardingError::SendEventFailed(
+                    format!("xdotool {} failed: {}", action, String::from_utf8_lossy(&output.stderr))
+                ));
+            }
+        }
+        Ok(())
+    }
+    
     // Implementation of touch gestures
     fn handle_x11_gesture(&self, gesture: &TouchGesture, direction: Option<&GestureDirection>, magnitude: Option<f32>) -> Result<(), InputForwardingError> {
         match gesture {
@@ -174,6 +227,9 @@ impl ImprovedX11InputForwarder {
                         gesture_direction: None,
                         gesture_magnitude: None,
                         special_command: None,
+                        ping_id: None,
+                        client_timestamp: None,
+                        touch: None,
                     };
                     
                     return self.forward_event(&scroll_event);
@@ -192,7 +248,7 @@ impl ImprovedX11InputForwarder {
                         modifiers: Some(vec!["ctrl".to_string()]),
                         x: None, y: None, button: None, delta_x: None, delta_y: None,
                         monitor_index: None, gesture: None, gesture_direction: None,
-                        gesture_magnitude: None, special_command: None,
+                        gesture_magnitude: None, special_command: None, ping_id: None, client_timestamp: None, touch: None,
                     };
                     
                     // Press Plus/Minus key depending on zoom direction
@@ -203,7 +259,7 @@ impl ImprovedX11InputForwarder {
                         modifiers: Some(vec!["ctrl".to_string()]),
                         x: None, y: None, button: None, delta_x: None, delta_y: None,
                         monitor_index: None, gesture: None, gesture_direction: None,
-                        gesture_magnitude: None, special_command: None,
+                        gesture_magnitude: None, special_command: None, ping_id: None, client_timestamp: None, touch: None,
                     };
                     
                     // Release Plus/Minus key
@@ -214,7 +270,7 @@ impl ImprovedX11InputForwarder {
                         modifiers: Some(vec!["ctrl".to_string()]),
                         x: None, y: None, button: None, delta_x: None, delta_y: None,
                         monitor_index: None, gesture: None, gesture_direction: None,
-                        gesture_magnitude: None, special_command: None,
+                        gesture_magnitude: None, special_command: None, ping_id: None, client_timestamp: None, touch: None,
                     };
                     
                     // Release Ctrl key
@@ -225,7 +281,7 @@ impl ImprovedX11InputForwarder {
                         modifiers: None,
                         x: None, y: None, button: None, delta_x: None, delta_y: None,
                         monitor_index: None, gesture: None, gesture_direction: None,
-                        gesture_magnitude: None, special_command: None,
+                        gesture_magnitude: None, special_command: None, ping_id: None, client_timestamp: None, touch: None,
                     };
                     
                     // Execute events in sequence
@@ -259,12 +315,12 @@ impl ImprovedX11InputForwarder {
         let key_sequence = match self.special_commands.get(command) {
             Some(keys) => keys,
             None => {
-                // For custom commands, use direct string
+                // Custom commands are parsed and validated instead of mapped
                 if let SpecialCommand::Custom(cmd_str) = command {
-                    // Execute direct xdotool command
-                    let output = Command::new("sh")
-                        .arg("-c")
-                        .arg(format!("xdotool {}", cmd_str))
+                    // Run the validated command directly, never through a shell
+                    let custom = CustomCommand::parse(cmd_str)?;
+                    let output = self.runner.command("xdotool")
+                        .args(custom.args())
                         .output()
                         .map_err(|e| {
                             InputForwardingError::SendEventFailed(
@@ -301,7 +357,7 @@ impl ImprovedX11InputForwarder {
         cmd_args.push(&key_string);
         
         // Execute command
-        let output = Command::new("xdotool")
+        let output = self.runner.command("xdotool")
             .args(cmd_args)
             .output()
             .map_err(|e| {
@@ -321,9 +377,11 @@ impl ImprovedX11InputForwarder {
 // Implementation of ImprovedInputForwarder trait for X11
 impl ImprovedInputForwarder for ImprovedX11InputForwarder {
     fn forward_event(&self, event: &InputEvent) -> Result<(), InputForwardingError> {
-        if !self.is_enabled() {
+        // Previewed input is described by the app instead
+        if self.mode() != InputMode::Enabled {
             return Ok(());
         }
+        self.held_keys.lock().unwrap().touch();
         
         match event.event_type {
             InputEventType::MouseMove => {
@@ -333,7 +391,7 @@ impl ImprovedInputForwarder for ImprovedX11InputForwarder {
                     let (abs_x, abs_y) = utils::calculate_absolute_position(x, y, event.monitor_index, &monitors);
                     
                     // Execute xdotool
-                    let cmd_result = Command::new("xdotool")
+                    let cmd_result = self.runner.command("xdotool")
                         .arg("mousemove")
                         .arg(abs_x.to_string())
                         .arg(abs_y.to_string())
@@ -381,7 +439,7 @@ impl ImprovedInputForwarder for ImprovedX11InputForwarder {
                                 x: event.x, y: event.y,
                                 key_code: None, modifiers: None, delta_x: None, delta_y: None,
                                 monitor_index: event.monitor_index, gesture: None, 
-                                gesture_direction: None, gesture_magnitude: None, special_command: None,
+                                gesture_direction: None, gesture_magnitude: None, special_command: None, ping_id: None, client_timestamp: None, touch: None,
                             };
                             self.forward_event(&tap_event)?;
                             
@@ -393,14 +451,14 @@ impl ImprovedInputForwarder for ImprovedX11InputForwarder {
                                 x: event.x, y: event.y,
                                 key_code: None, modifiers: None, delta_x: None, delta_y: None,
                                 monitor_index: event.monitor_index, gesture: None, 
-                                gesture_direction: None, gesture_magnitude: None, special_command: None,
+                                gesture_direction: None, gesture_magnitude: None, special_command: None, ping_id: None, client_timestamp: None, touch: None,
                             };
                             self.forward_event(&release_event)?;
                             return Ok(());
                         },
                         MouseButton::TouchDoubleTap => {
                             // Use xdotool click to do a double-click
-                            let cmd_result = Command::new("xdotool")
+                            let cmd_result = self.runner.command("xdotool")
                                 .arg("click")
                                 .arg("--repeat")
                                 .arg("2")
@@ -430,7 +488,7 @@ impl ImprovedInputForwarder for ImprovedX11InputForwarder {
                     };
                     
                     // Execute xdotool command
-                    let cmd_result = Command::new("xdotool")
+                    let cmd_result = self.runner.command("xdotool")
                         .arg(action)
                         .arg(button_arg)
                         .output();
@@ -482,7 +540,7 @@ impl ImprovedInputForwarder for ImprovedX11InputForwarder {
                     // Execute all scroll commands
                     for cmd_str in commands {
                         let parts: Vec<&str> = cmd_str.split_whitespace().collect();
-                        let mut cmd = Command::new(parts[0]);
+                        let mut cmd = self.runner.command(parts[0]);
                         for arg in &parts[1..] {
                             cmd.arg(arg);
                         }
@@ -510,7 +568,13 @@ impl ImprovedInputForwarder for ImprovedX11InputForwarder {
                 }
             },
             InputEventType::KeyPress | InputEventType::KeyRelease => {
-                self.forward_improved_key_event(event)
+                let active_modifiers = self.active_modifiers.lock().unwrap().clone();
+                let outcome = self.shortcuts.lock().unwrap().check(event, &active_modifiers);
+                match outcome {
+                    ShortcutOutcome::Forward(event) => self.forward_improved_key_event(&event),
+                    ShortcutOutcome::Drop => Ok(()),
+                    ShortcutOutcome::Inject(steps) => self.inject_key_steps(&steps),
+                }
             },
             InputEventType::TouchGesture => {
                 if let Some(gesture) = &event.gesture {
@@ -523,27 +587,31 @@ impl ImprovedInputForwarder for ImprovedX11InputForwarder {
             },
             InputEventType::SpecialCommand => {
                 if let Some(command) = &event.special_command {
-                    self.execute_special_command(command)
+                    self.handle_special_command(command)
                 } else {
                     Err(InputForwardingError::UnsupportedEvent(
                         "SpecialCommand event missing command type".to_string()
                     ))
                 }
             },
+            InputEventType::TouchDown | InputEventType::TouchMove | InputEventType::TouchUp | InputEventType::Ping => {
+                self.forward_common(&self.touch, event)
+            },
         }
     }
 
-    fn set_enabled(&self, enabled: bool) {
-        let mut state = self.enabled.lock().unwrap();
-        *state = enabled;
+    fn set_mode(&self, mode: InputMode) {
+        *self.mode.lock().unwrap() = mode;
+        self.release_keys_unless_enabled(mode);
     }
 
-    fn is_enabled(&self) -> bool {
-        *self.enabled.lock().unwrap()
+    fn mode(&self) -> InputMode {
+        *self.mode.lock().unwrap()
     }
 
-    fn configure_monitors(&mut self, monitors: Vec<MonitorConfiguration>) -> Result<(), InputForwardingError> {
+    fn configure_monitors(&self, monitors: Vec<MonitorConfiguration>) -> Result<(), InputForwardingError> {
         utils::validate_monitor_config(&monitors)?;
+        self.touch.lock().unwrap().configure_monitors(&monitors);
         
         let mut monitor_config = self.monitors.lock().unwrap();
         *monitor_config = monitors;
@@ -552,10 +620,320 @@ impl ImprovedInputForwarder for ImprovedX11InputForwarder {
     }
 
     fn handle_special_command(&self, command: &SpecialCommand) -> Result<(), InputForwardingError> {
+        let guard = self.system_actions.lock().unwrap().clone();
+        guard.admit(command, Instant::now())?;
         self.execute_special_command(command)
     }
 
     fn handle_gesture(&self, gesture: &TouchGesture, direction: Option<&GestureDirection>, magnitude: Option<f32>) -> Result<(), InputForwardingError> {
         self.handle_x11_gesture(gesture, direction, magnitude)
     }
+
+    fn reset_input_state(&self) -> Result<(), InputForwardingError> {
+        let releases = self.held_keys.lock().unwrap().take_releases();
+        self.active_modifiers.lock().unwrap().clear();
+        self.shortcuts.lock().unwrap().reset();
+        
+        // Release everything even if one key fails, report the first failure
+        let mut result = self.touch.lock().unwrap().release_all();
+        for release in &releases {
+            if let Err(e) = self.forward_improved_key_event(release) {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+    
+    fn held_keys_idle_for(&self) -> Option<Duration> {
+        self.held_keys.lock().unwrap().idle_for()
+    }
+    
+    fn set_system_action_guard(&self, guard: Arc<SystemActionGuard>) {
+        *self.system_actions.lock().unwrap() = guard;
+    }
+    
+    fn set_shortcut_policy(&self, policy: &ShortcutPolicy) -> Result<(), InputForwardingError> {
+        self.shortcuts.lock().unwrap().set_policy(policy)
+    }
+    
+    fn backend(&self) -> InputBackend {
+        InputBackend::Xdotool
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_forwarding::shortcuts::{ShortcutAction, ShortcutRule};
+    use crate::input_forwarding::system_actions::{SystemActionConfig, SystemActionPolicy};
+    use crate::test_env::{call, TestEnv};
+
+    fn event(event_type: InputEventType) -> InputEvent {
+        InputEvent {
+            event_type,
+            x: None,
+            y: None,
+            button: None,
+            key_code: None,
+            modifiers: None,
+            is_pressed: None,
+            delta_x: None,
+            delta_y: None,
+            monitor_index: None,
+            gesture: None,
+            gesture_direction: None,
+            gesture_magnitude: None,
+            special_command: None,
+            ping_id: None,
+            client_timestamp: None,
+            touch: None,
+        }
+    }
+
+    fn button(button: MouseButton, is_pressed: bool) -> InputEvent {
+        InputEvent { button: Some(button), is_pressed: Some(is_pressed), ..event(InputEventType::MouseButton) }
+    }
+
+    fn key(key_code: u32, is_pressed: bool, modifiers: &[&str]) -> InputEvent {
+        InputEvent {
+            key_code: Some(key_code),
+            is_pressed: Some(is_pressed),
+            modifiers: Some(modifiers.iter().map(|m| m.to_string()).collect()),
+            ..event(if is_pressed { InputEventType::KeyPress } else { InputEventType::KeyRelease })
+        }
+    }
+
+    fn special(command: SpecialCommand) -> InputEvent {
+        InputEvent { special_command: Some(command), ..event(InputEventType::SpecialCommand) }
+    }
+
+    fn forwarder(env: &TestEnv) -> ImprovedX11InputForwarder {
+        let forwarder = ImprovedX11InputForwarder::with_runner(env.runner()).unwrap();
+        // Whether uinput is writable depends on the machine running the tests
+        *forwarder.touch.lock().unwrap() = TouchInput::pointer_only();
+        forwarder.configure_monitors(vec![MonitorConfiguration {
+            index: 0,
+            x_offset: 1920,
+            y_offset: 0,
+            width: 1920,
+            height: 1080,
+            scale_factor: 1.0,
+            rotation: MonitorRotation::Normal,
+            is_primary: true,
+        }]).unwrap();
+        forwarder
+    }
+
+    #[test]
+    fn test_input_event_matrix() {
+        let env = TestEnv::new();
+        let forwarder = forwarder(&env);
+        // Disruptive commands run without asking; the guard has its own test
+        forwarder.set_system_action_guard(Arc::new(SystemActionGuard::new(SystemActionConfig {
+            default_policy: SystemActionPolicy::Allow,
+            ..SystemActionConfig::default()
+        })));
+
+        let cases: Vec<(InputEvent, Vec<&str>)> = vec![
+            (InputEvent { x: Some(100), y: Some(200), ..event(InputEventType::MouseMove) }, vec!["xdotool mousemove 2020 200"]),
+            (button(MouseButton::Left, true), vec!["xdotool mousedown 1"]),
+            (button(MouseButton::Left, false), vec!["xdotool mouseup 1"]),
+            (button(MouseButton::Middle, true), vec!["xdotool mousedown 2"]),
+            (button(MouseButton::Right, false), vec!["xdotool mouseup 3"]),
+            (button(MouseButton::Back, true), vec!["xdotool mousedown 8"]),
+            (button(MouseButton::Forward, true), vec!["xdotool mousedown 9"]),
+            (button(MouseButton::TouchTap, true), vec!["xdotool mousedown 1", "xdotool mouseup 1"]),
+            (button(MouseButton::TouchDoubleTap, true), vec!["xdotool click --repeat 2 1"]),
+            (
+                InputEvent { delta_x: Some(0.0), delta_y: Some(2.0), ..event(InputEventType::MouseScroll) },
+                vec!["xdotool click --repeat 1 5", "xdotool click --repeat 1 5"],
+            ),
+            (
+                InputEvent { delta_x: Some(-1.0), delta_y: Some(-1.0), ..event(InputEventType::MouseScroll) },
+                vec!["xdotool click --repeat 1 4", "xdotool click --repeat 1 6"],
+            ),
+            (key(65, true, &["ctrl"]), vec!["xdotool keydown ctrl a"]),
+            (key(65, false, &["ctrl"]), vec!["xdotool keyup a"]),
+            (key(112, true, &[]), vec!["xdotool keydown F1"]),
+            (key(250, false, &[]), vec!["xdotool keyup 0xFA"]),
+            (
+                InputEvent { gesture: Some(TouchGesture::Pinch), gesture_magnitude: Some(1.0), ..event(InputEventType::TouchGesture) },
+                vec!["xdotool keydown ctrl Control_L", "xdotool keydown ctrl KP_Add", "xdotool keyup KP_Add", "xdotool keyup Control_L"],
+            ),
+            (special(SpecialCommand::AppSwitcher), vec!["xdotool key alt+Tab"]),
+            (special(SpecialCommand::LockScreen), vec!["xdotool key super+l"]),
+            (special(SpecialCommand::VolumeUp), vec!["xdotool key XF86AudioRaiseVolume"]),
+            (special(SpecialCommand::VolumeDown), vec!["xdotool key XF86AudioLowerVolume"]),
+            (special(SpecialCommand::VolumeMute), vec!["xdotool key XF86AudioMute"]),
+            (special(SpecialCommand::MediaPlayPause), vec!["xdotool key XF86AudioPlay"]),
+            (special(SpecialCommand::MediaNext), vec!["xdotool key XF86AudioNext"]),
+            (special(SpecialCommand::MediaPrev), vec!["xdotool key XF86AudioPrev"]),
+            (special(SpecialCommand::Custom("key ctrl+alt+Delete".to_string())), vec!["xdotool key ctrl+alt+Delete"]),
+            (event(InputEventType::Ping), vec![]),
+        ];
+
+        for (event, expected) in cases {
+            forwarder.forward_event(&event).unwrap_or_else(|e| panic!("{:?} failed: {}", event, e));
+            let expected: Vec<Vec<String>> = expected.into_iter().map(call).collect();
+            assert_eq!(env.take_invocations(), expected, "{:?}", event);
+        }
+    }
+
+    #[test]
+    fn test_invalid_events_run_nothing() {
+        let env = TestEnv::new();
+        let forwarder = forwarder(&env);
+
+        let invalid = [
+            event(InputEventType::MouseMove),
+            button(MouseButton::ScrollUp, true),
+            event(InputEventType::KeyPress),
+            special(SpecialCommand::Custom("exec rm -rf /".to_string())),
+        ];
+        for event in &invalid {
+            assert!(matches!(forwarder.forward_event(event), Err(InputForwardingError::UnsupportedEvent(_))), "{:?}", event);
+        }
+        assert!(env.invocations().is_empty());
+    }
+
+    #[test]
+    fn test_touch_without_uinput_moves_the_pointer() {
+        let env = TestEnv::new();
+        let forwarder = forwarder(&env);
+        let touch = |event_type, id, x| InputEvent {
+            touch: Some(TouchPoint { id, x, y: 0.5, pressure: None }),
+            ..event(event_type)
+        };
+
+        forwarder.forward_event(&touch(InputEventType::TouchDown, 1, 0.25)).unwrap();
+        assert_eq!(env.take_invocations(), vec![call("xdotool mousemove 2400 540"), call("xdotool mousedown 1")]);
+
+        // A second finger cannot be injected through the pointer
+        let second = forwarder.forward_event(&touch(InputEventType::TouchDown, 2, 0.75));
+        assert!(matches!(second, Err(InputForwardingError::UnsupportedEvent(_))));
+        assert!(env.invocations().is_empty());
+
+        forwarder.forward_event(&touch(InputEventType::TouchUp, 1, 0.5)).unwrap();
+        assert_eq!(env.take_invocations(), vec![call("xdotool mousemove 2880 540"), call("xdotool mouseup 1")]);
+    }
+
+    #[test]
+    fn test_disruptive_commands_need_clearance() {
+        let env = TestEnv::new();
+        let forwarder = forwarder(&env);
+        let guard = Arc::new(SystemActionGuard::default());
+        forwarder.set_system_action_guard(guard.clone());
+
+        // Neither the event path nor a direct call gets past the guard
+        let lock = special(SpecialCommand::LockScreen);
+        assert!(matches!(forwarder.forward_event(&lock), Err(InputForwardingError::PermissionDenied(_))));
+        assert!(forwarder.handle_special_command(&SpecialCommand::LockScreen).is_err());
+        assert!(env.invocations().is_empty());
+
+        guard.request(Some("peer"), true, &SpecialCommand::LockScreen, Instant::now());
+        forwarder.forward_event(&lock).unwrap();
+        assert_eq!(env.take_invocations(), vec![call("xdotool key super+l")]);
+        assert!(forwarder.forward_event(&lock).is_err());
+
+        // Routine commands need nothing
+        forwarder.forward_event(&special(SpecialCommand::VolumeMute)).unwrap();
+        assert_eq!(env.take_invocations(), vec![call("xdotool key XF86AudioMute")]);
+    }
+
+    #[test]
+    fn test_tool_failure_is_reported() {
+        let env = TestEnv::new();
+        let forwarder = forwarder(&env);
+        env.set_exit_code("xdotool", 1);
+        env.set_stderr("xdotool", "Can't open display");
+
+        match forwarder.forward_event(&button(MouseButton::Left, true)) {
+            Err(InputForwardingError::SendEventFailed(message)) => assert!(message.contains("Can't open display"), "{}", message),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_reset_releases_held_keys() {
+        let env = TestEnv::new();
+        let forwarder = forwarder(&env);
+        forwarder.forward_event(&key(65, true, &[])).unwrap();
+        env.take_invocations();
+
+        forwarder.reset_input_state().unwrap();
+        assert_eq!(env.take_invocations(), vec![
+            call("xdotool keyup a"),
+            call("xdotool keyup Shift_L"),
+            call("xdotool keyup Control_L"),
+            call("xdotool keyup Alt_L"),
+            call("xdotool keyup Super_L"),
+        ]);
+
+        // Disabled forwarders inject nothing
+        forwarder.set_enabled(false);
+        env.take_invocations();
+        forwarder.forward_event(&button(MouseButton::Left, true)).unwrap();
+        assert!(env.invocations().is_empty());
+    }
+
+    #[test]
+    fn test_preview_mode_runs_nothing() {
+        let env = TestEnv::new();
+        let forwarder = forwarder(&env);
+        forwarder.forward_event(&key(65, true, &[])).unwrap();
+
+        // Switching releases the held key, and from then on nothing is spawned
+        forwarder.set_mode(InputMode::Preview);
+        assert!(!forwarder.is_enabled());
+        env.take_invocations();
+
+        let events = [
+            InputEvent { x: Some(400), y: Some(300), monitor_index: Some(0), ..event(InputEventType::MouseMove) },
+            button(MouseButton::Left, true),
+            button(MouseButton::TouchTap, true),
+            key(84, true, &["ctrl", "alt"]),
+            InputEvent { delta_x: Some(0.0), delta_y: Some(3.0), ..event(InputEventType::MouseScroll) },
+            special(SpecialCommand::LockScreen),
+        ];
+        for event in &events {
+            forwarder.forward_event(event).unwrap();
+        }
+        assert!(env.invocations().is_empty());
+
+        forwarder.set_enabled(true);
+        assert_eq!(forwarder.mode(), InputMode::Enabled);
+    }
+
+    #[test]
+    fn test_shortcut_policy() {
+        let env = TestEnv::new();
+        let forwarder = forwarder(&env);
+
+        // The default policy keeps the host on its graphical session
+        forwarder.forward_event(&key(112, true, &["ctrl", "alt"])).unwrap();
+        forwarder.forward_event(&key(112, false, &["ctrl", "alt"])).unwrap();
+        assert!(env.take_invocations().is_empty());
+
+        forwarder.set_shortcut_policy(&ShortcutPolicy {
+            rules: vec![ShortcutRule {
+                combo: "ctrl+Left".to_string(),
+                action: ShortcutAction::TranslateTo("alt+Left".to_string()),
+            }],
+        }).unwrap();
+        forwarder.forward_event(&key(37, true, &["ctrl"])).unwrap();
+        assert_eq!(env.take_invocations(), vec![
+            call("xdotool keyup Control_L"),
+            call("xdotool keydown Alt_L"),
+            call("xdotool keydown Left"),
+            call("xdotool keyup Left"),
+            call("xdotool keyup Alt_L"),
+            call("xdotool keydown Control_L"),
+        ]);
+
+        // The release belongs to the translated press
+        forwarder.forward_event(&key(37, false, &["ctrl"])).unwrap();
+        assert!(env.take_invocations().is_empty());
+    }
 }
@@ -0,0 +1,221 @@
+// src-tauri/src/keyring.rs - Secrets kept in the user's OS keyring
+//
+// Keys that protect data at rest must not sit next to that data. They go to
+// the Secret Service (GNOME Keyring, KWallet) through secret-tool, or, on
+// sessions without one, to the kernel's user keyring through keyctl. Keys in
+// the kernel keyring only live until the user's last session ends, so data
+// encrypted with them becomes unreadable after a reboot.
+
+use std::io::Write;
+use std::process::Stdio;
+
+use base64::{engine::general_purpose, Engine as _};
+use rand::RngCore;
+
+use crate::command_runner::CommandRunner;
+
+/// Attribute that groups all SmolDesk secrets in the Secret Service
+const APPLICATION: &str = "smoldesk";
+
+/// Length of the symmetric keys handed out
+pub const KEY_LEN: usize = 32;
+
+/// Returns the key stored under `name`, or stores a new random one.
+/// Fails if neither secret-tool nor keyctl can keep it; the caller must not
+/// fall back to a key on disk then.
+pub fn load_or_create_key(runner: &dyn CommandRunner, name: &str, label: &str) -> Result<[u8; KEY_LEN], String> {
+    let secret_service_error = match secret_service_key(runner, name, label) {
+        Ok(key) => return Ok(key),
+        Err(e) => e,
+    };
+
+    kernel_keyring_key(runner, name).map_err(|keyctl_error| {
+        format!("No keyring available ({}; {})", secret_service_error, keyctl_error)
+    })
+}
+
+fn secret_service_key(runner: &dyn CommandRunner, name: &str, label: &str) -> Result<[u8; KEY_LEN], String> {
+    let attributes = ["application", APPLICATION, "secret", name];
+
+    let output = runner.command("secret-tool").arg("lookup").args(attributes).output()
+        .map_err(|e| format!("Failed to run secret-tool: {}", e))?;
+    // A missing secret exits 1 without a message; anything on stderr means the
+    // service is unreachable or locked, and a new key would orphan the old data
+    if !output.status.success() && !output.stderr.is_empty() {
+        return Err(format!("secret-tool failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    if output.status.success() && !output.stdout.is_empty() {
+        return decode_key(&output.stdout);
+    }
+
+    let key = new_key();
+    let mut args = vec!["store".to_string(), format!("--label={}", label)];
+    args.extend(attributes.iter().map(|arg| arg.to_string()));
+    run_with_input(runner, "secret-tool", &args, &encode_key(&key))?;
+    Ok(key)
+}
+
+fn kernel_keyring_key(runner: &dyn CommandRunner, name: &str) -> Result<[u8; KEY_LEN], String> {
+    let description = format!("{}:{}", APPLICATION, name);
+
+    if let Ok(serial) = run_with_input(runner, "keyctl", &["search", "@u", "user", &description], "") {
+        let payload = run_with_input(runner, "keyctl", &["pipe", serial.trim()], "")?;
+        return decode_key(payload.as_bytes());
+    }
+
+    let key = new_key();
+    run_with_input(runner, "keyctl", &["padd", "user", &description, "@u"], &encode_key(&key))?;
+    Ok(key)
+}
+
+/// Stores `value` under `name`, replacing what was stored there before.
+/// Fails if neither secret-tool nor keyctl can keep it.
+pub fn store_secret(runner: &dyn CommandRunner, name: &str, label: &str, value: &str) -> Result<(), String> {
+    let mut args = vec!["store".to_string(), format!("--label={}", label)];
+    args.extend(["application", APPLICATION, "secret", name].iter().map(|arg| arg.to_string()));
+    let secret_service_error = match run_with_input(runner, "secret-tool", &args, value) {
+        Ok(_) => return Ok(()),
+        Err(e) => e,
+    };
+
+    let description = format!("{}:{}", APPLICATION, name);
+    run_with_input(runner, "keyctl", &["padd", "user", &description, "@u"], value)
+        .map(|_| ())
+        .map_err(|keyctl_error| format!("No keyring available ({}; {})", secret_service_error, keyctl_error))
+}
+
+/// Value stored under `name` by `store_secret`, from whichever keyring has it
+pub fn lookup_secret(runner: &dyn CommandRunner, name: &str) -> Option<String> {
+    let output = runner.command("secret-tool")
+        .args(["lookup", "application", APPLICATION, "secret", name])
+        .output();
+    if let Ok(output) = output {
+        if output.status.success() && !output.stdout.is_empty() {
+            return Some(String::from_utf8_lossy(&output.stdout).into_owned());
+        }
+    }
+
+    let description = format!("{}:{}", APPLICATION, name);
+    let serial = run_with_input(runner, "keyctl", &["search", "@u", "user", &description], "").ok()?;
+    run_with_input(runner, "keyctl", &["pipe", serial.trim()], "").ok()
+}
+
+/// Removes the value stored under `name` from both keyrings
+pub fn delete_secret(runner: &dyn CommandRunner, name: &str) {
+    // Either may not hold it; there is nothing to report then
+    let _ = runner.command("secret-tool")
+        .args(["clear", "application", APPLICATION, "secret", name])
+        .output();
+
+    let description = format!("{}:{}", APPLICATION, name);
+    if let Ok(serial) = run_with_input(runner, "keyctl", &["search", "@u", "user", &description], "") {
+        let _ = run_with_input(runner, "keyctl", &["unlink", serial.trim(), "@u"], "");
+    }
+}
+
+/// Runs `program` with `input` on stdin and returns its stdout
+fn run_with_input<S: AsRef<str>>(runner: &dyn CommandRunner, program: &str, args: &[S], input: &str) -> Result<String, String> {
+    let mut child = runner.command(program)
+        .args(args.iter().map(AsRef::as_ref))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())
+            .map_err(|e| format!("Failed to write to {}: {}", program, e))?;
+    }
+
+    let output = child.wait_with_output()
+        .map_err(|e| format!("Failed to wait for {}: {}", program, e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(format!("{} failed: {}", program, String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+fn new_key() -> [u8; KEY_LEN] {
+    let mut key = [0u8; KEY_LEN];
+    rand::thread_rng().fill_bytes(&mut key);
+    key
+}
+
+/// Keys are stored as base64 so both tools can pass them as text
+fn encode_key(key: &[u8; KEY_LEN]) -> String {
+    general_purpose::STANDARD.encode(key)
+}
+
+fn decode_key(stored: &[u8]) -> Result<[u8; KEY_LEN], String> {
+    let text = String::from_utf8_lossy(stored);
+    let bytes = general_purpose::STANDARD.decode(text.trim())
+        .map_err(|e| format!("Stored key is not valid base64: {}", e))?;
+    bytes.try_into().map_err(|_| "Stored key has the wrong length".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::{call, TestEnv};
+
+    #[test]
+    fn test_existing_secret_service_key_is_used() {
+        let env = TestEnv::new();
+        let key = [7u8; KEY_LEN];
+        env.set_stdout("secret-tool", encode_key(&key));
+
+        assert_eq!(load_or_create_key(&*env.runner(), "clipboard-history", "SmolDesk"), Ok(key));
+        assert_eq!(env.take_invocations(), vec![
+            call("secret-tool lookup application smoldesk secret clipboard-history"),
+        ]);
+    }
+
+    #[test]
+    fn test_missing_key_is_created_in_secret_service() {
+        let env = TestEnv::new();
+        env.record_stdin("secret-tool");
+
+        let key = load_or_create_key(&*env.runner(), "clipboard-history", "SmolDesk").unwrap();
+        assert_eq!(env.stdin("secret-tool"), encode_key(&key).into_bytes());
+        assert_eq!(env.take_invocations(), vec![
+            call("secret-tool lookup application smoldesk secret clipboard-history"),
+            call("secret-tool store --label=SmolDesk application smoldesk secret clipboard-history"),
+        ]);
+    }
+
+    #[test]
+    fn test_secret_falls_back_to_kernel_keyring() {
+        let env = TestEnv::new();
+        env.set_exit_code("secret-tool", 1);
+        env.set_stderr("secret-tool", "Cannot autolaunch D-Bus without X11 $DISPLAY");
+        env.record_stdin("keyctl");
+
+        store_secret(&*env.runner(), "ice-server-1", "SmolDesk", "s3cret").unwrap();
+        assert_eq!(env.stdin("keyctl"), b"s3cret".to_vec());
+        assert_eq!(env.take_invocations(), vec![
+            call("secret-tool store --label=SmolDesk application smoldesk secret ice-server-1"),
+            call("keyctl padd user smoldesk:ice-server-1 @u"),
+        ]);
+
+        env.set_stdout("keyctl", "s3cret");
+        assert_eq!(lookup_secret(&*env.runner(), "ice-server-1").as_deref(), Some("s3cret"));
+    }
+
+    #[test]
+    fn test_locked_secret_service_is_not_overwritten() {
+        let env = TestEnv::new();
+        env.set_exit_code("secret-tool", 1);
+        env.set_stderr("secret-tool", "Cannot autolaunch D-Bus without X11 $DISPLAY");
+        env.set_exit_code("keyctl", 1);
+
+        let error = load_or_create_key(&*env.runner(), "clipboard-history", "SmolDesk").unwrap_err();
+        assert!(error.contains("D-Bus"), "{}", error);
+        assert_eq!(env.take_invocations(), vec![
+            call("secret-tool lookup application smoldesk secret clipboard-history"),
+            call("keyctl search @u user smoldesk:clipboard-history"),
+            call("keyctl padd user smoldesk:clipboard-history @u"),
+        ]);
+    }
+}
@@ -0,0 +1,349 @@
+// src-tauri/src/logging.rs - Structured logging and diagnostics bundles
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt as tracing_fmt, reload, Registry};
+
+use crate::input_forwarding::factory::detect_display_server;
+use crate::input_forwarding::utils::check_tool_exists;
+use crate::screen_capture::utils::check_ffmpeg;
+
+/// Log targets used by the backend modules
+pub const TARGET_SCREEN_CAPTURE: &str = "screen_capture";
+pub const TARGET_INPUT: &str = "input";
+pub const TARGET_CLIPBOARD: &str = "clipboard";
+pub const TARGET_TRANSFER: &str = "transfer";
+pub const TARGET_SECURITY: &str = "security";
+pub const TARGET_SIGNALING: &str = "signaling";
+pub const TARGET_CHAT: &str = "chat";
+
+pub const TARGETS: [&str; 7] = [
+    TARGET_SCREEN_CAPTURE,
+    TARGET_INPUT,
+    TARGET_CLIPBOARD,
+    TARGET_TRANSFER,
+    TARGET_SECURITY,
+    TARGET_SIGNALING,
+    TARGET_CHAT,
+];
+
+const LOG_FILE_PREFIX: &str = "smoldesk";
+const LOG_FILE_SUFFIX: &str = "log";
+const MAX_LOG_FILES: usize = 7;
+const DIAGNOSTIC_LOG_FILES: usize = 3;
+const DEFAULT_LEVEL: &str = "info";
+
+/// Minimum interval between repeated messages on hot paths
+pub const HOT_PATH_LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// External tools whose availability is recorded in diagnostics bundles
+const DIAGNOSTIC_TOOLS: [&str; 8] = [
+    "ffmpeg", "xdotool", "ydotool", "xclip", "wl-copy", "wl-paste", "pipewire", "xrandr",
+];
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Error type for logging setup and diagnostics
+#[derive(Debug)]
+pub enum LoggingError {
+    AlreadyInitialized,
+    NotInitialized,
+    InvalidLevel(String),
+    UnknownTarget(String),
+    IoError(String),
+    InitializationFailed(String),
+}
+
+impl fmt::Display for LoggingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoggingError::AlreadyInitialized => write!(f, "Logging already initialized"),
+            LoggingError::NotInitialized => write!(f, "Logging not initialized"),
+            LoggingError::InvalidLevel(level) => write!(f, "Invalid log level: {}", level),
+            LoggingError::UnknownTarget(target) => write!(f, "Unknown log target: {}", target),
+            LoggingError::IoError(msg) => write!(f, "I/O error: {}", msg),
+            LoggingError::InitializationFailed(msg) => write!(f, "Failed to initialize logging: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for LoggingError {}
+
+impl From<std::io::Error> for LoggingError {
+    fn from(error: std::io::Error) -> Self {
+        LoggingError::IoError(error.to_string())
+    }
+}
+
+/// Global level plus per-target overrides, rendered as an `EnvFilter` directive
+#[derive(Debug, Clone)]
+struct FilterDirectives {
+    global: LevelFilter,
+    targets: BTreeMap<String, LevelFilter>,
+}
+
+impl FilterDirectives {
+    fn new(global: LevelFilter) -> Self {
+        FilterDirectives {
+            global,
+            targets: BTreeMap::new(),
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut directive = self.global.to_string().to_lowercase();
+        for (target, level) in &self.targets {
+            directive.push_str(&format!(",{}={}", target, level.to_string().to_lowercase()));
+        }
+        directive
+    }
+}
+
+struct Logger {
+    log_dir: PathBuf,
+    directives: Mutex<FilterDirectives>,
+    reload_handle: reload::Handle<EnvFilter, Registry>,
+}
+
+/// Directory for log files: `$XDG_STATE_HOME/smoldesk/logs`, falling back to
+/// `~/.local/state/smoldesk/logs`
+pub fn default_log_dir() -> PathBuf {
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))
+        .unwrap_or_else(std::env::temp_dir);
+
+    state_home.join("smoldesk").join("logs")
+}
+
+/// Installs the global subscriber: daily-rotating file output plus stderr.
+///
+/// `log` records from dependencies and from our own modules are bridged into
+/// `tracing`. `RUST_LOG` overrides the default level if set.
+pub fn init(log_dir: &Path) -> Result<(), LoggingError> {
+    if LOGGER.get().is_some() {
+        return Err(LoggingError::AlreadyInitialized);
+    }
+
+    std::fs::create_dir_all(log_dir)?;
+
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix(LOG_FILE_SUFFIX)
+        .max_log_files(MAX_LOG_FILES)
+        .build(log_dir)
+        .map_err(|e| LoggingError::InitializationFailed(e.to_string()))?;
+
+    let global = std::env::var("RUST_LOG").ok()
+        .and_then(|level| LevelFilter::from_str(&level).ok())
+        .unwrap_or_else(|| LevelFilter::from_str(DEFAULT_LEVEL).unwrap());
+    let directives = FilterDirectives::new(global);
+
+    let (filter, reload_handle) = reload::Layer::new(EnvFilter::new(directives.render()));
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_fmt::layer().with_writer(appender).with_ansi(false))
+        .with(tracing_fmt::layer().with_writer(std::io::stderr))
+        .try_init()
+        .map_err(|e| LoggingError::InitializationFailed(e.to_string()))?;
+
+    LOGGER.set(Logger {
+        log_dir: log_dir.to_path_buf(),
+        directives: Mutex::new(directives),
+        reload_handle,
+    }).map_err(|_| LoggingError::AlreadyInitialized)
+}
+
+/// Changes the log level globally or for one of the module targets
+pub fn set_log_level(level: &str, target: Option<&str>) -> Result<(), LoggingError> {
+    let logger = LOGGER.get().ok_or(LoggingError::NotInitialized)?;
+    let level = LevelFilter::from_str(level)
+        .map_err(|_| LoggingError::InvalidLevel(level.to_string()))?;
+
+    let mut directives = logger.directives.lock().unwrap();
+    match target {
+        Some(target) => {
+            if !TARGETS.contains(&target) {
+                return Err(LoggingError::UnknownTarget(target.to_string()));
+            }
+            directives.targets.insert(target.to_string(), level);
+        },
+        None => {
+            directives.global = level;
+            directives.targets.clear();
+        },
+    }
+
+    logger.reload_handle
+        .reload(EnvFilter::new(directives.render()))
+        .map_err(|e| LoggingError::InitializationFailed(e.to_string()))
+}
+
+/// Directory the log files are written to, if logging is initialized
+pub fn log_dir() -> Option<PathBuf> {
+    LOGGER.get().map(|logger| logger.log_dir.clone())
+}
+
+/// Writes a zip bundle with the most recent log files and an environment
+/// report to `output_path`
+pub fn collect_diagnostics(output_path: &Path) -> Result<PathBuf, LoggingError> {
+    let log_dir = log_dir().ok_or(LoggingError::NotInitialized)?;
+
+    let file = File::create(output_path)?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
+
+    let map_zip_err = |e: zip::result::ZipError| LoggingError::IoError(e.to_string());
+
+    zip.start_file("environment.txt", options).map_err(map_zip_err)?;
+    zip.write_all(environment_report().as_bytes())?;
+
+    for path in recent_log_files(&log_dir, DIAGNOSTIC_LOG_FILES)? {
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => format!("logs/{}", name),
+            None => continue,
+        };
+        let contents = std::fs::read(&path)?;
+        zip.start_file(name, options).map_err(map_zip_err)?;
+        zip.write_all(&contents)?;
+    }
+
+    zip.finish().map_err(map_zip_err)?;
+
+    Ok(output_path.to_path_buf())
+}
+
+/// Newest log files first
+fn recent_log_files(log_dir: &Path, limit: usize) -> Result<Vec<PathBuf>, LoggingError> {
+    let mut files: Vec<(std::time::SystemTime, PathBuf)> = std::fs::read_dir(log_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry.file_name().to_str()
+                .map(|name| name.starts_with(LOG_FILE_PREFIX))
+                .unwrap_or(false)
+        })
+        .filter_map(|entry| {
+            let modified = entry.metadata().and_then(|metadata| metadata.modified()).ok()?;
+            Some((modified, entry.path()))
+        })
+        .collect();
+
+    files.sort_by(|a, b| b.0.cmp(&a.0));
+    Ok(files.into_iter().take(limit).map(|(_, path)| path).collect())
+}
+
+/// Plain-text summary of the runtime environment for bug reports
+pub fn environment_report() -> String {
+    let mut report = String::new();
+
+    report.push_str(&format!("SmolDesk version: {}\n", env!("CARGO_PKG_VERSION")));
+    report.push_str(&format!("Generated at: {}\n", chrono::Utc::now().to_rfc3339()));
+    report.push_str(&format!("OS: {}\n", os_name()));
+    report.push_str(&format!("Display server: {:?}\n", detect_display_server()));
+
+    for variable in ["XDG_SESSION_TYPE", "XDG_CURRENT_DESKTOP", "WAYLAND_DISPLAY", "DISPLAY"] {
+        let value = std::env::var(variable).unwrap_or_else(|_| "<unset>".to_string());
+        report.push_str(&format!("{}: {}\n", variable, value));
+    }
+
+    report.push_str("\nTools:\n");
+    for tool in DIAGNOSTIC_TOOLS {
+        let status = if check_tool_exists(tool) { "available" } else { "missing" };
+        report.push_str(&format!("  {}: {}\n", tool, status));
+    }
+
+    let ffmpeg = check_ffmpeg().unwrap_or_else(|e| format!("unavailable ({})", e));
+    report.push_str(&format!("\nFFmpeg: {}\n", ffmpeg));
+
+    report
+}
+
+fn os_name() -> String {
+    std::fs::read_to_string("/etc/os-release").ok()
+        .and_then(|contents| {
+            contents.lines()
+                .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+                .map(|name| name.trim_matches('"').to_string())
+        })
+        .unwrap_or_else(|| std::env::consts::OS.to_string())
+}
+
+/// Suppresses repeated log messages on hot paths (e.g. per-frame errors in
+/// the capture loop) so a persistent failure cannot flood the log files.
+#[derive(Debug)]
+pub struct RateLimitedLog {
+    interval: Duration,
+    last_emit: Option<Instant>,
+    suppressed: u64,
+}
+
+impl RateLimitedLog {
+    pub fn new(interval: Duration) -> Self {
+        RateLimitedLog {
+            interval,
+            last_emit: None,
+            suppressed: 0,
+        }
+    }
+
+    /// Returns `Some(suppressed)` if the message should be logged now, where
+    /// `suppressed` is the number of occurrences dropped since the last one
+    pub fn check(&mut self) -> Option<u64> {
+        self.check_at(Instant::now())
+    }
+
+    fn check_at(&mut self, now: Instant) -> Option<u64> {
+        match self.last_emit {
+            Some(last) if now.duration_since(last) < self.interval => {
+                self.suppressed += 1;
+                None
+            },
+            _ => {
+                self.last_emit = Some(now);
+                Some(std::mem::take(&mut self.suppressed))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limited_log_counts_suppressed() {
+        let mut limiter = RateLimitedLog::new(Duration::from_secs(5));
+        let start = Instant::now();
+
+        assert_eq!(limiter.check_at(start), Some(0));
+        assert_eq!(limiter.check_at(start + Duration::from_secs(1)), None);
+        assert_eq!(limiter.check_at(start + Duration::from_secs(2)), None);
+        assert_eq!(limiter.check_at(start + Duration::from_secs(6)), Some(2));
+        assert_eq!(limiter.check_at(start + Duration::from_secs(7)), None);
+    }
+
+    #[test]
+    fn test_filter_directives_render() {
+        let mut directives = FilterDirectives::new(LevelFilter::INFO);
+        assert_eq!(directives.render(), "info");
+
+        directives.targets.insert(TARGET_SCREEN_CAPTURE.to_string(), LevelFilter::DEBUG);
+        directives.targets.insert(TARGET_CLIPBOARD.to_string(), LevelFilter::WARN);
+        assert_eq!(directives.render(), "info,clipboard=warn,screen_capture=debug");
+    }
+}
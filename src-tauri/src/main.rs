@@ -1,300 +1,4430 @@
-// src-tauri/src/main.rs
-
-#![cfg_attr(
-    all(not(debug_assertions), target_os = "windows"),
-    windows_subsystem = "windows"
-)]
-
-mod screen_capture;
-mod input_forwarding;
-mod clipboard;
-mod connection_security;
-mod file_transfer;
-
-use std::sync::{Arc, Mutex};
-use tauri::{Manager, Window};
-use serde::{Deserialize, Serialize};
-
-use screen_capture::{ScreenCaptureManager, ScreenCaptureConfig, MonitorInfo};
-use input_forwarding::{
-    InputEvent, 
-    forwarder_trait::ImprovedInputForwarder, 
-    factory::{detect_display_server, create_improved_input_forwarder},
-    types::{InputForwardingConfig, MonitorConfiguration},
-    error::InputForwardingError
-};
-use clipboard::ClipboardManager;
-use connection_security::ConnectionSecurityManager;
-
-// Application state
-struct AppState {
-    screen_capture: Arc<Mutex<Option<ScreenCaptureManager>>>,
-    input_forwarder: Arc<Mutex<Option<Box<dyn ImprovedInputForwarder>>>>,
-    clipboard_manager: Arc<Mutex<Option<ClipboardManager>>>,
-    security_manager: Arc<Mutex<Option<ConnectionSecurityManager>>>,
-}
-
-// Commands
-
-#[tauri::command]
-fn get_display_server() -> String {
-    match detect_display_server() {
-        input_forwarding::types::DisplayServer::X11 => "X11".to_string(),
-        input_forwarding::types::DisplayServer::Wayland => "Wayland".to_string(),
-        input_forwarding::types::DisplayServer::Unknown => "Unknown".to_string(),
-    }
-}
-
-#[tauri::command]
-fn get_monitors(state: tauri::State<'_, AppState>) -> Result<Vec<MonitorInfo>, String> {
-    let screen_capture = state.screen_capture.lock().unwrap();
-    
-    if let Some(capture_manager) = &*screen_capture {
-        Ok(capture_manager.get_monitors())
-    } else {
-        Err("Screen capture manager not initialized".to_string())
-    }
-}
-
-#[tauri::command]
-fn start_capture(
-    window: Window,
-    monitor_index: usize,
-    config: ScreenCaptureConfig,
-    state: tauri::State<'_, AppState>,
-) -> Result<(), String> {
-    let mut screen_capture = state.screen_capture.lock().unwrap();
-    
-    if let Some(capture_manager) = &mut *screen_capture {
-        // Update config with the selected monitor
-        let mut updated_config = config;
-        updated_config.monitor_index = monitor_index;
-        
-        capture_manager.update_config(updated_config)
-            .map_err(|e| e.to_string())?;
-        
-        // Start capture
-        capture_manager.start_capture(window)
-            .map_err(|e| e.to_string())?;
-        
-        Ok(())
-    } else {
-        Err("Screen capture manager not initialized".to_string())
-    }
-}
-
-#[tauri::command]
-fn stop_capture(state: tauri::State<'_, AppState>) -> Result<(), String> {
-    let mut screen_capture = state.screen_capture.lock().unwrap();
-    
-    if let Some(capture_manager) = &mut *screen_capture {
-        capture_manager.stop_capture()
-            .map_err(|e| e.to_string())?;
-        
-        Ok(())
-    } else {
-        Err("Screen capture manager not initialized".to_string())
-    }
-}
-
-#[tauri::command]
-fn send_input_event(event: InputEvent, state: tauri::State<'_, AppState>) -> Result<(), String> {
-    let input_forwarder = state.input_forwarder.lock().unwrap();
-    
-    if let Some(forwarder) = &*input_forwarder {
-        let new_event: input_forwarding::types::InputEvent = event.into();
-        forwarder.forward_event(&new_event)
-            .map_err(|e| e.to_string())?;
-        
-        Ok(())
-    } else {
-        Err("Input forwarder not initialized".to_string())
-    }
-}
-
-#[tauri::command]
-fn set_input_enabled(enabled: bool, state: tauri::State<'_, AppState>) -> Result<(), String> {
-    let input_forwarder = state.input_forwarder.lock().unwrap();
-    
-    if let Some(forwarder) = &*input_forwarder {
-        forwarder.set_enabled(enabled);
-        Ok(())
-    } else {
-        Err("Input forwarder not initialized".to_string())
-    }
-}
-
-#[tauri::command]
-fn configure_input_forwarding(config: InputForwardingConfig, state: tauri::State<'_, AppState>) -> Result<(), String> {
-    let mut input_forwarder = state.input_forwarder.lock().unwrap();
-    
-    if let Some(forwarder) = &mut *input_forwarder {
-        // Update multi-monitor configuration if enabled
-        if config.enable_multi_monitor {
-            forwarder.configure_monitors(config.monitors)
-                .map_err(|e| e.to_string())?;
-        }
-        
-        Ok(())
-    } else {
-        Err("Input forwarder not initialized".to_string())
-    }
-}
-
-#[tauri::command]
-fn get_video_codecs() -> Vec<String> {
-    vec![
-        "H264".to_string(),
-        "VP8".to_string(),
-        "VP9".to_string(),
-        "AV1".to_string(),
-    ]
-}
-
-#[tauri::command]
-fn get_hardware_acceleration_options() -> Vec<String> {
-    vec![
-        "None".to_string(),
-        "VAAPI".to_string(),
-        "NVENC".to_string(),
-        "QuickSync".to_string(),
-    ]
-}
-
-#[tauri::command]
-fn get_clipboard_text(state: tauri::State<'_, AppState>) -> Result<String, String> {
-    let mut clipboard = state.clipboard_manager.lock().unwrap();
-    
-    if let Some(clipboard_manager) = &mut *clipboard {
-        clipboard_manager.get_text()
-            .map_err(|e| e.to_string())
-    } else {
-        Err("Clipboard manager not initialized".to_string())
-    }
-}
-
-#[tauri::command]
-fn set_clipboard_text(text: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
-    let mut clipboard = state.clipboard_manager.lock().unwrap();
-    
-    if let Some(clipboard_manager) = &mut *clipboard {
-        clipboard_manager.set_text(&text)
-            .map_err(|e| e.to_string())
-    } else {
-        Err("Clipboard manager not initialized".to_string())
-    }
-}
-
-#[tauri::command]
-fn initialize_security(secret_key: String, state: tauri::State<'_, AppState>) -> Result<(), String> {
-    let security_config = connection_security::ConnectionSecurityConfig::default();
-    let security_manager = ConnectionSecurityManager::new(&secret_key, security_config);
-    
-    let mut app_security = state.security_manager.lock().unwrap();
-    *app_security = Some(security_manager);
-    
-    Ok(())
-}
-
-fn main() {
-    tauri::Builder::default()
-        .setup(|app| {
-            // Initialize the screen capture manager
-            let screen_capture_manager = match ScreenCaptureManager::new() {
-                Ok(manager) => Some(manager),
-                Err(e) => {
-                    eprintln!("Failed to initialize screen capture manager: {}", e);
-                    None
-                }
-            };
-            
-            // Get monitor information for input forwarder
-            let monitors = if let Some(manager) = &screen_capture_manager {
-                manager.get_monitors()
-            } else {
-                vec![]
-            };
-            
-            // Convert screen_capture MonitorInfo to input_forwarding MonitorConfiguration
-            let input_monitors: Vec<MonitorConfiguration> = monitors.iter().enumerate()
-                .map(|(idx, monitor)| MonitorConfiguration {
-                    index: idx,
-                    x_offset: monitor.x_offset,
-                    y_offset: monitor.y_offset,
-                    width: monitor.width as i32,
-                    height: monitor.height as i32,
-                    scale_factor: 1.0, // Default scale factor
-                    is_primary: idx == 0, // Assume first monitor is primary
-                })
-                .collect();
-
-            // Initialize input forwarder with automatic display server detection
-            let input_forwarder = match create_improved_input_forwarder(None) {
-                Ok(mut forwarder) => {
-                    // Configure with monitors if available
-                    if !input_monitors.is_empty() {
-                        if let Err(e) = forwarder.configure_monitors(input_monitors) {
-                            eprintln!("Failed to configure monitors for input forwarder: {}", e);
-                        }
-                    }
-                    Some(forwarder)
-                },
-                Err(e) => {
-                    eprintln!("Failed to initialize input forwarder: {}", e);
-                    None
-                }
-            };
-
-            // Initialize clipboard manager
-            let clipboard_manager = match detect_display_server() {
-                input_forwarding::types::DisplayServer::X11 => {
-                    match ClipboardManager::new(screen_capture::types::DisplayServer::X11) {
-                        Ok(manager) => Some(manager),
-                        Err(e) => {
-                            eprintln!("Failed to initialize clipboard manager: {}", e);
-                            None
-                        }
-                    }
-                },
-                input_forwarding::types::DisplayServer::Wayland => {
-                    match ClipboardManager::new(screen_capture::types::DisplayServer::Wayland) {
-                        Ok(manager) => Some(manager),
-                        Err(e) => {
-                            eprintln!("Failed to initialize clipboard manager: {}", e);
-                            None
-                        }
-                    }
-                },
-                _ => None,
-            };
-            
-            // Create app state
-            let state = AppState {
-                screen_capture: Arc::new(Mutex::new(screen_capture_manager)),
-                input_forwarder: Arc::new(Mutex::new(input_forwarder)),
-                clipboard_manager: Arc::new(Mutex::new(clipboard_manager)),
-                security_manager: Arc::new(Mutex::new(None)),
-            };
-            
-            // Manage state
-            app.manage(state);
-            
-            Ok(())
-        })
-        .invoke_handler(tauri::generate_handler![
-            get_display_server,
-            get_monitors,
-            start_capture,
-            stop_capture,
-            send_input_event,
-            set_input_enabled,
-            configure_input_forwarding,
-            get_video_codecs,
-            get_hardware_acceleration_options,
-            get_clipboard_text,
-            set_clipboard_text,
-            initialize_security,
-        ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
-}
+// src-tauri/src/main.rs
+
+#![cfg_attr(
+    all(not(debug_assertions), target_os = "windows"),
+    windows_subsystem = "windows"
+)]
+
+mod screen_capture;
+mod input_forwarding;
+mod clipboard;
+mod connection_security;
+mod file_transfer;
+mod chat;
+mod logging;
+mod capabilities;
+mod signaling;
+mod stats;
+mod settings;
+mod config_bundle;
+mod host_session;
+mod host_volume;
+mod do_not_disturb;
+mod sharing_indicator;
+mod notifications;
+mod metrics;
+mod keyring;
+mod control_socket;
+mod power;
+mod cli;
+mod command_error;
+mod constant_time;
+mod subsystems;
+mod command_runner;
+mod tool_locator;
+mod protocol;
+mod accessibility;
+mod ice;
+mod safe_mode;
+mod peers;
+mod shutdown;
+#[cfg(test)]
+mod test_env;
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{Manager, Window};
+use serde::{Deserialize, Serialize};
+
+use screen_capture::{
+    ScreenCaptureManager, ScreenCaptureConfig, ScreenCaptureError, MonitorInfo,
+    types::{CaptureErrorReport, CaptureRegion, CaptureStats, MonitorRotation},
+    buffer::{BufferConfig, BufferStats},
+    config::{builtin_profiles, capture_profiles, find_capture_profile, CaptureProfile, OutputResolution},
+    encoders::SupportedOptions,
+    pipewire::{self, PipeWireSource},
+    pointer_feed::PointerFeed,
+    privacy::{PrivacyMask, PrivacyMasks},
+    recording_cursor::{self, CursorTrack, RecordingCursorConfig},
+    resume::SuspendedCapture,
+    stats_history::{StatsHistoryRange, StatsSample},
+    thumbnail::{self, MonitorThumbnail, ThumbnailStream},
+    validation::ConfigIssue,
+    viewport::ViewerViewport,
+    virtual_display::VirtualDisplay
+};
+use input_forwarding::{
+    InputEvent, 
+    forwarder_trait::{ImprovedInputForwarder, InputBackend}, 
+    factory::{detect_display_server, create_improved_input_forwarder},
+    types::{InputForwardingConfig, MonitorConfiguration},
+    utils::calculate_absolute_position,
+    error::InputForwardingError,
+    recorder::{InputMacro, InputMacroRecorder},
+    panic_hotkey::{Hotkey, HotkeyBackend, PanicHotkeyConfig, PanicHotkeyListener},
+    blocklist::InputBlocklist,
+    arbiter::{ControlConfig, ControlDecision, ControlState, InputArbiter},
+    latency::{InputLatencyStats, InputLatencyTracker},
+    preview::{InputMode, InputPreview, PREVIEW_INTERVAL},
+    shortcuts::ShortcutPolicy,
+    system_actions::{ActionDecision, SystemActionConfig, SystemActionGuard, SystemActionRequest, SystemActionResolution}
+};
+use clipboard::{
+    ClipboardManager,
+    error::ClipboardError,
+    offload::{ImageOffload, ImageReference},
+    delta::DeltaSync,
+    shared::SharedClipboard,
+    poll::{ClipboardPoller, ClipboardStats},
+    types::{ClipboardConfig, ClipboardConnectionMode, ClipboardContentType, ClipboardEntry},
+    sync_policy::{ClipboardSyncPolicy, ClipboardSyncStatus, RemoteSyncOutcome}
+};
+use capabilities::{CapabilityReport, Feature};
+use command_error::{CommandError, ErrorKind};
+use subsystems::{lock_or_retry, RetryGate, Subsystem, RETRY_INTERVAL};
+use safe_mode::{RemoteControl, SafeMode};
+use command_runner::SystemCommandRunner;
+use tool_locator::ToolLocator;
+use connection_security::{ConnectionSecurityManager, PeerInfo, AccessTokenClaims, AccessRight, UserRole, UNIDENTIFIED_PEER};
+use connection_security::identity::{DeviceIdentity, IdentityChallenge, IdentityProof, TrustedPeer, VerifiedPeer};
+use connection_security::rate_limit::{RateLimitDecision, RateLimitedCommand};
+use connection_security::clipboard_crypto::SealedSyncEntry;
+use connection_security::audit::{
+    AuditConfig, AuditEvent, AuditEventKind, AuditExportFormat, AuditLog, AuditSink, AuditVerification
+};
+use signaling::{ClientMessage, SignalingClient, SignalingConfig, SignalingEvent, SignalingStatus};
+use stats::{ConnectionQualityReport, FrameLatencyTracker, LatencyBreakdown, NetworkReport, QualityMonitor};
+use settings::{AppSettings, LoadedSettings, SettingsStore};
+use config_bundle::{BundleError, BundleManifest, ConfigImportReport, SecretsExport};
+use host_session::{HostSessionMonitor, HostSessionState};
+use notifications::{Notification, NotificationCategory, Notifier};
+use do_not_disturb::{DndChange, DndConfig, DndController, DndStatus};
+use sharing_indicator::{IndicatorConfig, SharingIndicator};
+use peers::{ConnectedPeer, PeerChange, PeerRegistry, PeerSource};
+use metrics::{MetricsConfig, MetricsServer, MetricsSnapshot};
+use control_socket::{ControlCommand, ControlServer};
+use accessibility::{AccessibilityTree, AccessibleText, ScreenRect};
+use ice::{IceServer, IceStore};
+use ice::probe::IceProbeResult;
+use power::{PowerSource, PowerSourceWatcher, SleepEvent, SleepWatcher};
+use shutdown::ShutdownSequence;
+use file_transfer::{
+    FileTransferManager,
+    types::{TransferEvent, TransferPriority, TransferQueueEntry},
+    bandwidth::AutoBandwidthConfig,
+    drop::{DropConfig, DropEvent, DropPosition},
+    history::{TransferHistoryPage, TransferHistoryQuery},
+    receipt::TransferReceipt,
+    staging::{PurgeReport, StorageUsage}
+};
+use chat::{
+    ChatError, ChatManager, ChatTransport,
+    types::{ChatEvent, ChatFrame, ChatMessage}
+};
+
+/// Label of the window from tauri.conf.json; closing it quits the app
+const MAIN_WINDOW_LABEL: &str = "main";
+
+/// Lifetime of the access token presented to the signaling server
+const SIGNALING_TOKEN_TTL: std::time::Duration = std::time::Duration::from_secs(300);
+
+// Application state
+struct AppState {
+    screen_capture: Arc<Mutex<Option<ScreenCaptureManager>>>,
+    /// Where input reports pointer positions and clicks, so it never waits for `screen_capture`
+    pointer_feed: PointerFeed,
+    input_forwarder: Arc<Mutex<Option<Box<dyn ImprovedInputForwarder>>>>,
+    input_recorder: Arc<InputMacroRecorder>,
+    input_blocklist: Arc<InputBlocklist>,
+    /// Decides on disruptive special commands; every forwarder enforces its decisions
+    system_actions: Arc<SystemActionGuard>,
+    input_latency: Arc<InputLatencyTracker>,
+    input_preview: Arc<InputPreview>,
+    stuck_key_timeout: Arc<Mutex<Option<std::time::Duration>>>,
+    input_arbiter: Arc<Mutex<InputArbiter>>,
+    host_session: Arc<HostSessionMonitor>,
+    panic_hotkey: Arc<Mutex<Option<PanicHotkeyListener>>>,
+    panic_hotkey_config: Arc<Mutex<PanicHotkeyConfig>>,
+    clipboard_manager: Arc<Mutex<Option<ClipboardManager>>>,
+    /// Outlives the clipboard manager, so forwarded input always reaches it
+    clipboard_poller: Arc<ClipboardPoller>,
+    security_manager: Arc<Mutex<Option<ConnectionSecurityManager>>>,
+    /// Fully set up before the state is managed, so no lock: async calls on it
+    /// never hold a guard
+    file_transfer: Option<Arc<FileTransferManager>>,
+    /// Conversations with the connected peers, kept across frontend reloads
+    chat: Arc<ChatManager>,
+    capabilities: Arc<Mutex<Option<CapabilityReport>>>,
+    audit_log: Arc<AuditLog>,
+    signaling: Arc<Mutex<SignalingClient>>,
+    connection_quality: Arc<Mutex<QualityMonitor>>,
+    frame_latency: Arc<Mutex<FrameLatencyTracker>>,
+    settings: Arc<Mutex<AppSettings>>,
+    settings_store: Option<Arc<SettingsStore>>,
+    notifier: Arc<Mutex<Notifier>>,
+    do_not_disturb: Arc<Mutex<DndController>>,
+    sharing_indicator: Arc<Mutex<SharingIndicator>>,
+    /// Connected peers; the one source of `peer_connected` and `peer_disconnected`
+    peers: Arc<PeerRegistry>,
+    metrics_server: Arc<Mutex<Option<MetricsServer>>>,
+    control_server: Arc<Mutex<Option<ControlServer>>>,
+    sleep_watcher: Arc<Mutex<Option<SleepWatcher>>>,
+    power_watcher: Arc<Mutex<Option<PowerSourceWatcher>>>,
+    /// Some while the frontend is subscribed to `monitor_thumbnails`
+    thumbnail_stream: Arc<Mutex<Option<ThumbnailStream>>>,
+    accessibility: Arc<dyn AccessibilityTree>,
+    // Some while the host sleeps: the captures to bring back on resume
+    suspended_captures: Arc<Mutex<Option<Vec<SuspendedCapture>>>>,
+    init_retries: RetryGate,
+    /// Decided at startup; leaving safe mode takes a restart
+    safe_mode: SafeMode,
+    /// Set once the shutdown sequence has begun
+    shutting_down: AtomicBool,
+    app_handle: tauri::AppHandle,
+}
+
+impl AppState {
+    // Prefer the capability report's diagnosis over a generic "not initialized"
+    fn feature_error(&self, feature: Feature, fallback: &str) -> CommandError {
+        let control = match feature {
+            Feature::InputForwarding => Some(RemoteControl::Input),
+            Feature::Clipboard => Some(RemoteControl::Clipboard),
+            Feature::ScreenCapture | Feature::VirtualDisplay => None,
+        };
+        if let Some(Err(e)) = control.map(|control| self.safe_mode.check(control)) {
+            return e.into();
+        }
+        
+        let kind = match feature {
+            Feature::ScreenCapture => ErrorKind::CaptureNotInitialized,
+            Feature::InputForwarding => ErrorKind::InputNotInitialized,
+            Feature::Clipboard => ErrorKind::ClipboardNotInitialized,
+            Feature::VirtualDisplay => ErrorKind::CaptureVirtualDisplay,
+        };
+        let capabilities = self.capabilities.lock().unwrap();
+        let diagnosis = capabilities.as_ref().and_then(|report| report.diagnose(feature));
+        CommandError::not_initialized(kind, fallback, diagnosis)
+    }
+    
+    /// The file transfer manager for a command; see `SafeMode::reach`
+    fn transfer_manager(&self) -> Result<&Arc<FileTransferManager>, CommandError> {
+        self.safe_mode.reach(RemoteControl::FileTransfer, &self.file_transfer)?
+            .ok_or_else(|| CommandError::new(ErrorKind::TransferNotInitialized, "File transfer manager not initialized"))
+    }
+}
+
+/// Convert capture monitors to the input forwarder's monitor layout
+fn input_monitor_configurations(monitors: &[MonitorInfo]) -> Vec<MonitorConfiguration> {
+    monitors.iter().enumerate()
+        .map(|(idx, monitor)| MonitorConfiguration {
+            index: idx,
+            x_offset: monitor.x_offset,
+            y_offset: monitor.y_offset,
+            width: monitor.width as i32,
+            height: monitor.height as i32,
+            scale_factor: monitor.scale_factor as f32,
+            rotation: match monitor.rotation {
+                MonitorRotation::Normal => input_forwarding::MonitorRotation::Normal,
+                MonitorRotation::Left => input_forwarding::MonitorRotation::Left,
+                MonitorRotation::Right => input_forwarding::MonitorRotation::Right,
+                MonitorRotation::Inverted => input_forwarding::MonitorRotation::Inverted,
+            },
+            is_primary: idx == 0, // Assume first monitor is primary
+        })
+        .collect()
+}
+
+/// Push the capture geometry (including a capture region) to the input forwarder
+fn sync_input_monitors(state: &AppState, monitors: &[MonitorInfo]) {
+    if monitors.is_empty() {
+        return;
+    }
+    
+    if let Some(forwarder) = &*state.input_forwarder.lock().unwrap() {
+        if let Err(e) = forwarder.configure_monitors(input_monitor_configurations(monitors)) {
+            log::error!(target: logging::TARGET_INPUT, "Failed to configure monitors for input forwarder: {}", e);
+        }
+    }
+}
+
+/// Create the screen capture manager and remove virtual displays a crashed session left behind
+fn create_screen_capture_manager(
+    app_handle: &tauri::AppHandle,
+    host_session: &Arc<HostSessionMonitor>,
+    frame_latency: &Arc<Mutex<FrameLatencyTracker>>,
+    pointer_feed: &PointerFeed,
+) -> Result<ScreenCaptureManager, ScreenCaptureError> {
+    let mut manager = ScreenCaptureManager::new()?;
+    manager.set_host_session_monitor(host_session.clone());
+    manager.set_pointer_feed(pointer_feed.clone());
+    manager.set_frame_latency(frame_latency.clone());
+    manager.set_power_source(power::read_power_source(std::path::Path::new(power::POWER_SUPPLY_DIR)));
+    if let Some(data_dir) = app_handle.path_resolver().app_data_dir() {
+        if let Err(e) = manager.set_state_dir(&data_dir) {
+            log::error!(target: logging::TARGET_SCREEN_CAPTURE, "Failed to reconcile virtual displays: {}", e);
+        }
+    }
+    Ok(manager)
+}
+
+/// Create the input forwarder with automatic display server detection
+fn create_input_forwarder(
+    monitors: &[MonitorInfo],
+    config: &InputForwardingConfig,
+    system_actions: &Arc<SystemActionGuard>,
+) -> Result<Box<dyn ImprovedInputForwarder>, InputForwardingError> {
+    let forwarder = create_improved_input_forwarder(None)?;
+    forwarder.set_system_action_guard(system_actions.clone());
+    let input_monitors = input_monitor_configurations(monitors);
+    if !input_monitors.is_empty() {
+        if let Err(e) = forwarder.configure_monitors(input_monitors) {
+            log::error!(target: logging::TARGET_INPUT, "Failed to configure monitors for input forwarder: {}", e);
+        }
+    }
+    if let Err(e) = forwarder.set_shortcut_policy(&config.shortcut_policy) {
+        log::error!(target: logging::TARGET_INPUT, "Failed to apply shortcut policy, keeping the default: {}", e);
+    }
+    if let Err(e) = forwarder.set_keyboard_layout(&config.keyboard_layout) {
+        log::error!(target: logging::TARGET_INPUT, "Failed to apply keyboard layout, keeping the default: {}", e);
+    }
+    Ok(forwarder)
+}
+
+/// Create the clipboard manager with its persisted history; local changes go to the frontend
+fn create_clipboard_manager(app_handle: &tauri::AppHandle, config: ClipboardConfig, audit_log: Arc<AuditLog>, poller: Arc<ClipboardPoller>) -> Result<ClipboardManager, ClipboardError> {
+    let display_server = match detect_display_server() {
+        input_forwarding::types::DisplayServer::X11 => screen_capture::types::DisplayServer::X11,
+        input_forwarding::types::DisplayServer::Wayland => screen_capture::types::DisplayServer::Wayland,
+        input_forwarding::types::DisplayServer::Unknown => screen_capture::types::DisplayServer::Unknown,
+    };
+    let mut manager = ClipboardManager::new(display_server, config)?;
+    manager.set_poller(poller);
+    
+    if let Some(data_dir) = app_handle.path_resolver().app_data_dir() {
+        let app_handle = app_handle.clone();
+        manager.set_storage_dir(&data_dir, Arc::new(SystemCommandRunner), move |result| {
+            let warning = match result {
+                Ok(0) => return,
+                Ok(skipped_records) => ClipboardHistoryWarning {
+                    message: format!("Skipped {} unreadable clipboard history records", skipped_records),
+                    memory_only: false,
+                    skipped_records,
+                },
+                // Without a key the history is never written, not even as plaintext
+                Err(e) => ClipboardHistoryWarning {
+                    message: format!("Clipboard history is kept in memory only: {}", e),
+                    memory_only: true,
+                    skipped_records: 0,
+                },
+            };
+            log::warn!(target: logging::TARGET_CLIPBOARD, "{}", warning.message);
+            if let Err(e) = app_handle.emit_all("clipboard_history_warning", warning) {
+                log::error!(target: logging::TARGET_CLIPBOARD, "Failed to emit clipboard_history_warning: {}", e);
+            }
+        });
+    }
+    
+    let app_handle = app_handle.clone();
+    let image_offload = manager.image_offload();
+    let delta_sync = manager.delta_sync();
+    let shared_clipboard = manager.shared_clipboard();
+    manager.add_change_callback(move |entry| {
+        let sent_event = || AuditEvent::new(AuditEventKind::ClipboardSent, None)
+            .with_detail(entry.metadata.mime_type.clone())
+            .with_size(entry.metadata.size as u64)
+            .with_content(entry.data.clone());
+        
+        // Large images go to each peer as a file transfer instead of inline
+        if image_offload.should_offload(entry) && offer_clipboard_image(&app_handle, &image_offload, &shared_clipboard, entry) {
+            audit_log.record(sent_event());
+            return;
+        }
+        
+        // Once a peer has a clipboard key, entries only leave sealed
+        if emit_sealed_clipboard_entries(&app_handle, &delta_sync, &shared_clipboard, entry) {
+            audit_log.record(sent_event());
+            return;
+        }
+        
+        // A plain entry reaches every peer, so one that only uses the shared
+        // clipboard holds it back for all
+        if !shared_clipboard.allows_system_for_all(&connected_peers(&app_handle)) {
+            log::debug!(target: logging::TARGET_CLIPBOARD, "Clipboard change not sent: a peer only uses the shared clipboard");
+            return;
+        }
+        
+        match delta_sync.encode_for(None, entry) {
+            Ok(payload) => {
+                if let Err(e) = app_handle.emit_all("clipboard_changed", payload) {
+                    log::error!(target: logging::TARGET_CLIPBOARD, "Failed to emit clipboard change: {}", e);
+                }
+                audit_log.record(sent_event());
+            },
+            Err(e) => log::error!(target: logging::TARGET_CLIPBOARD, "Failed to encode clipboard entry: {}", e),
+        }
+    });
+    
+    Ok(manager)
+}
+
+/// An `ImageReference` entry for one peer, in the format of `clipboard_changed`
+#[derive(Clone, Serialize)]
+struct ClipboardImageOffer {
+    peer_id: String,
+    payload: String,
+}
+
+/// A `clipboard_changed` payload sealed for one peer
+#[derive(Clone, Serialize)]
+struct SealedClipboardEntry {
+    peer_id: String,
+    envelope: SealedSyncEntry,
+}
+
+/// A chat frame sealed for one peer, emitted as `chat_outgoing`
+#[derive(Clone, Serialize)]
+struct SealedChatFrame {
+    peer_id: String,
+    envelope: SealedSyncEntry,
+}
+
+/// Seals chat frames with the peer's clipboard key and hands them to the
+/// frontend, which sends them over the peer's data channel
+struct SealedChatTransport {
+    app_handle: tauri::AppHandle,
+    security_manager: Arc<Mutex<Option<ConnectionSecurityManager>>>,
+}
+
+impl ChatTransport for SealedChatTransport {
+    fn send(&self, peer_id: &str, frame: &ChatFrame) -> Result<(), ChatError> {
+        let payload = serde_json::to_string(frame)
+            .map_err(|e| ChatError::Protocol(protocol::ProtocolError::Malformed(e.to_string())))?;
+        let envelope = match &*self.security_manager.lock().unwrap() {
+            Some(security_manager) if security_manager.has_clipboard_key(peer_id) => security_manager
+                .encrypt_sync_entry(peer_id, &payload)
+                .map_err(|e| ChatError::NotConnected(e.to_string()))?,
+            Some(_) => return Err(ChatError::NotConnected(format!("no key negotiated with peer {}", peer_id))),
+            None => return Err(ChatError::NotConnected("security manager not initialized".to_string())),
+        };
+        
+        self.app_handle.emit_all("chat_outgoing", SealedChatFrame { peer_id: peer_id.to_string(), envelope })
+            .map_err(|e| ChatError::NotConnected(e.to_string()))
+    }
+}
+
+/// A change of one peer's shared clipboard, emitted as `shared_clipboard_changed`.
+/// Local changes carry the entry for the peer in the format of
+/// `clipboard_changed`, sealed once the peer has a clipboard key.
+#[derive(Clone, Serialize)]
+struct SharedClipboardChange {
+    peer_id: String,
+    key: String,
+    /// `local` or `remote`
+    origin: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    envelope: Option<SealedSyncEntry>,
+}
+
+/// Emitted as `shared_clipboard_cleared` when a peer's shared clipboard is dropped
+#[derive(Clone, Serialize)]
+struct SharedClipboardCleared {
+    peer_id: String,
+}
+
+/// Seal a sync entry for one peer and emit it as `clipboard_sealed`.
+/// Returns false if the peer has no clipboard key.
+fn emit_sealed_clipboard_entry(app_handle: &tauri::AppHandle, security_manager: &ConnectionSecurityManager, peer_id: &str, payload: &str) -> bool {
+    if !security_manager.has_clipboard_key(peer_id) {
+        return false;
+    }
+    
+    match security_manager.encrypt_sync_entry(peer_id, payload) {
+        Ok(envelope) => {
+            let sealed = SealedClipboardEntry { peer_id: peer_id.to_string(), envelope };
+            if let Err(e) = app_handle.emit_all("clipboard_sealed", sealed) {
+                log::error!(target: logging::TARGET_CLIPBOARD, "Failed to emit sealed clipboard entry: {}", e);
+            }
+        },
+        Err(e) => log::error!(target: logging::TARGET_CLIPBOARD, "Failed to seal clipboard entry for {}: {}", peer_id, e),
+    }
+    true
+}
+
+/// Emit an entry sealed for every peer with a clipboard key, as a delta where
+/// the peer has a similar text; peers in `SharedOnly` mode are skipped.
+/// Returns false if no peer has a key, so the caller emits it in plain text.
+fn emit_sealed_clipboard_entries(app_handle: &tauri::AppHandle, delta_sync: &DeltaSync, shared_clipboard: &SharedClipboard, entry: &ClipboardEntry) -> bool {
+    let state = match app_handle.try_state::<AppState>() {
+        Some(state) => state,
+        None => return false,
+    };
+    let peers = match &*state.security_manager.lock().unwrap() {
+        Some(manager) => manager.clipboard_key_peers(),
+        None => return false,
+    };
+    
+    // Encoding a large entry takes a while; input checks the same lock for rate limits
+    for peer_id in &peers {
+        if !shared_clipboard.mode_for(Some(peer_id)).allows_system() {
+            continue;
+        }
+        match delta_sync.encode_for(Some(peer_id), entry) {
+            Ok(payload) => {
+                if let Some(security_manager) = &*state.security_manager.lock().unwrap() {
+                    emit_sealed_clipboard_entry(app_handle, security_manager, peer_id, &payload);
+                }
+            },
+            Err(e) => log::error!(target: logging::TARGET_CLIPBOARD, "Failed to encode clipboard entry for {}: {}", peer_id, e),
+        }
+    }
+    !peers.is_empty()
+}
+
+/// Start a file transfer of a clipboard image to every connected peer that
+/// shares the system clipboard and emit `clipboard_image_offered` per peer.
+/// Returns false without a file transfer transport or such peers, so the
+/// caller syncs the image inline instead.
+fn offer_clipboard_image(app_handle: &tauri::AppHandle, image_offload: &ImageOffload, shared_clipboard: &SharedClipboard, entry: &ClipboardEntry) -> bool {
+    let state = match app_handle.try_state::<AppState>() {
+        Some(state) => state,
+        None => return false,
+    };
+    let peers: Vec<String> = state.signaling.lock().unwrap().status().peers.into_iter()
+        .filter(|peer_id| shared_clipboard.mode_for(Some(peer_id)).allows_system())
+        .collect();
+    let manager = match &state.file_transfer {
+        Some(manager) if manager.has_transport() && !peers.is_empty() => manager,
+        _ => return false,
+    };
+    
+    let mut offers = Vec::new();
+    for peer_id in peers {
+        match tauri::async_runtime::block_on(image_offload.offer(manager, entry, &peer_id)) {
+            Ok(reference) => offers.push((peer_id, reference)),
+            Err(e) => {
+                // All peers get the same inline entry instead
+                log::warn!(target: logging::TARGET_CLIPBOARD, "Failed to offer clipboard image to {}, sending it inline: {}", peer_id, e);
+                for (_, reference) in offers {
+                    if let Ok(reference) = ImageReference::from_entry(&reference) {
+                        let _ = tauri::async_runtime::block_on(manager.cancel_transfer(&reference.transfer_id));
+                    }
+                }
+                return false;
+            }
+        }
+    }
+    
+    let security = state.security_manager.lock().unwrap();
+    for (peer_id, reference) in offers {
+        match clipboard::encode_sync_entry(&reference) {
+            Ok(payload) => {
+                if let Some(security_manager) = &*security {
+                    if emit_sealed_clipboard_entry(app_handle, security_manager, &peer_id, &payload) {
+                        continue;
+                    }
+                }
+                if let Err(e) = app_handle.emit_all("clipboard_image_offered", ClipboardImageOffer { peer_id, payload }) {
+                    log::error!(target: logging::TARGET_CLIPBOARD, "Failed to emit clipboard image offer: {}", e);
+                }
+            },
+            Err(e) => log::error!(target: logging::TARGET_CLIPBOARD, "Failed to encode clipboard image offer: {}", e),
+        }
+    }
+    true
+}
+
+/// Peers currently connected, whoever reported them
+fn connected_peers(app_handle: &tauri::AppHandle) -> Vec<String> {
+    app_handle.try_state::<AppState>()
+        .map(|state| state.peers.list().into_iter().map(|peer| peer.peer_id).collect())
+        .unwrap_or_default()
+}
+
+/// Drop the shared clipboard of a disconnected peer and emit `shared_clipboard_cleared`
+fn clear_shared_clipboard(app_handle: &tauri::AppHandle, state: &AppState, peer_id: &str) {
+    let shared_clipboard = match &*lock_clipboard(state) {
+        Some(clipboard_manager) => clipboard_manager.shared_clipboard(),
+        None => return,
+    };
+    if !shared_clipboard.remove_peer(peer_id) {
+        return;
+    }
+    
+    log::info!(target: logging::TARGET_CLIPBOARD, "Cleared the shared clipboard of {}", peer_id);
+    if let Err(e) = app_handle.emit_all("shared_clipboard_cleared", SharedClipboardCleared { peer_id: peer_id.to_string() }) {
+        log::error!(target: logging::TARGET_CLIPBOARD, "Failed to emit shared_clipboard_cleared: {}", e);
+    }
+}
+
+/// Feed a transfer event to the clipboard image offload and paste images that
+/// finished downloading. Returns true if the transfer carried a clipboard image.
+fn bridge_clipboard_image(app_handle: &tauri::AppHandle, event: &TransferEvent) -> bool {
+    let state = app_handle.state::<AppState>();
+    let image_offload = match &*state.clipboard_manager.lock().unwrap() {
+        Some(manager) => manager.image_offload(),
+        None => return false,
+    };
+    if !image_offload.owns(event.transfer_id()) {
+        return false;
+    }
+    
+    let (peer_id, image) = match &state.file_transfer {
+        Some(manager) => (
+            manager.get_transfer_info(event.transfer_id()).map(|info| info.peer_id),
+            tauri::async_runtime::block_on(image_offload.handle_transfer_event(manager, event)),
+        ),
+        None => (None, None),
+    };
+    
+    if let Some(image) = image {
+        let audit_event = AuditEvent::new(AuditEventKind::ClipboardReceived, peer_id.as_deref())
+            .with_detail(image.metadata.mime_type.clone())
+            .with_size(image.metadata.size as u64)
+            .with_content(image.data.clone());
+        
+        let mut clipboard = state.clipboard_manager.lock().unwrap();
+        if let Some(clipboard_manager) = &mut *clipboard {
+            match clipboard_manager.sync_remote_entry(peer_id.as_deref(), image) {
+                Ok(RemoteSyncOutcome::Applied) => {
+                    state.audit_log.record(audit_event);
+                    notify_host(app_handle, Notification::clipboard_synced());
+                },
+                Ok(outcome) => log::debug!(target: logging::TARGET_CLIPBOARD, "Transferred clipboard image not applied: {:?}", outcome),
+                Err(e) => log::warn!(target: logging::TARGET_CLIPBOARD, "Failed to apply transferred clipboard image: {}", e),
+            }
+        }
+    }
+    true
+}
+
+fn lock_screen_capture(state: &AppState) -> MutexGuard<'_, Option<ScreenCaptureManager>> {
+    lock_or_retry(&state.init_retries, &state.screen_capture, Subsystem::ScreenCapture, || {
+        create_screen_capture_manager(&state.app_handle, &state.host_session, &state.frame_latency, &state.pointer_feed)
+    })
+}
+
+fn lock_input_forwarder(state: &AppState) -> MutexGuard<'_, Option<Box<dyn ImprovedInputForwarder>>> {
+    // Never created in safe mode
+    if state.safe_mode.is_active() {
+        return state.input_forwarder.lock().unwrap();
+    }
+    lock_or_retry(&state.init_retries, &state.input_forwarder, Subsystem::InputForwarding, || {
+        let monitors = state.pointer_feed.input_monitors();
+        let config = state.settings.lock().unwrap().input.clone();
+        create_input_forwarder(&monitors, &config, &state.system_actions)
+    })
+}
+
+fn lock_clipboard(state: &AppState) -> MutexGuard<'_, Option<ClipboardManager>> {
+    if state.safe_mode.is_active() {
+        return state.clipboard_manager.lock().unwrap();
+    }
+    lock_or_retry(&state.init_retries, &state.clipboard_manager, Subsystem::Clipboard, || {
+        let config = state.settings.lock().unwrap().clipboard.clone();
+        create_clipboard_manager(&state.app_handle, config, state.audit_log.clone(), state.clipboard_poller.clone())
+    })
+}
+
+/// Retries missing managers in the background, so e.g. clipboard changes are
+/// picked up without a command touching the clipboard first
+fn start_subsystem_monitor(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(RETRY_INTERVAL);
+        
+        let state = app_handle.state::<AppState>();
+        for subsystem in Subsystem::ALL {
+            match subsystem {
+                Subsystem::ScreenCapture => drop(lock_screen_capture(&state)),
+                Subsystem::InputForwarding => drop(lock_input_forwarder(&state)),
+                Subsystem::Clipboard => drop(lock_clipboard(&state)),
+            }
+        }
+    });
+}
+
+// Commands
+
+#[tauri::command]
+fn get_display_server() -> String {
+    match detect_display_server() {
+        input_forwarding::types::DisplayServer::X11 => "X11".to_string(),
+        input_forwarding::types::DisplayServer::Wayland => "Wayland".to_string(),
+        input_forwarding::types::DisplayServer::Unknown => "Unknown".to_string(),
+    }
+}
+
+#[tauri::command]
+fn get_monitors(state: tauri::State<'_, AppState>) -> Result<Vec<MonitorInfo>, CommandError> {
+    let screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &*screen_capture {
+        Ok(capture_manager.get_monitors())
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+/// Still image of one monitor for the monitor picker, without a capture session
+#[tauri::command]
+fn get_monitor_thumbnail(
+    monitor_index: usize,
+    max_width: Option<u32>,
+    force: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<MonitorThumbnail, CommandError> {
+    // The grab runs without the manager lock
+    let (targets, grabber) = {
+        let screen_capture = lock_screen_capture(&state);
+        let capture_manager = screen_capture.as_ref()
+            .ok_or_else(|| state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))?;
+        (capture_manager.thumbnail_targets(Some(monitor_index), force.unwrap_or(false))?, capture_manager.thumbnail_grabber())
+    };
+    
+    grabber.grab(&targets.monitors, thumbnail::thumbnail_width(max_width))?
+        .pop()
+        .ok_or_else(|| CommandError::new(ErrorKind::CaptureFailed, "No thumbnail was grabbed"))
+}
+
+/// Emits `monitor_thumbnails` for all monitors every `interval_ms` until
+/// unsubscribed; a new subscription replaces the previous one
+#[tauri::command]
+fn subscribe_thumbnails(
+    interval_ms: Option<u64>,
+    max_width: Option<u32>,
+    force: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), CommandError> {
+    let grabber = {
+        let screen_capture = lock_screen_capture(&state);
+        let capture_manager = screen_capture.as_ref()
+            .ok_or_else(|| state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))?;
+        capture_manager.thumbnail_grabber()
+    };
+    let interval = interval_ms
+        .map_or(thumbnail::DEFAULT_THUMBNAIL_INTERVAL, std::time::Duration::from_millis)
+        .max(thumbnail::MIN_THUMBNAIL_INTERVAL);
+    let force = force.unwrap_or(false);
+    
+    let targets_handle = state.app_handle.clone();
+    let app_handle = state.app_handle.clone();
+    let stream = ThumbnailStream::start(
+        grabber,
+        interval,
+        thumbnail::thumbnail_width(max_width),
+        // Monitors are looked up every tick, captures may have started meanwhile
+        move || {
+            let state = targets_handle.state::<AppState>();
+            let screen_capture = state.screen_capture.lock().unwrap();
+            screen_capture.as_ref()?.thumbnail_targets(None, force).ok()
+        },
+        move |batch| {
+            let _ = app_handle.emit_all("monitor_thumbnails", batch);
+        },
+    );
+    
+    let previous = state.thumbnail_stream.lock().unwrap().replace(stream);
+    if let Some(mut previous) = previous {
+        previous.stop();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn unsubscribe_thumbnails(state: tauri::State<'_, AppState>) {
+    let stream = state.thumbnail_stream.lock().unwrap().take();
+    if let Some(mut stream) = stream {
+        stream.stop();
+    }
+}
+
+#[tauri::command]
+fn start_capture(
+    window: Window,
+    monitor_index: usize,
+    config: ScreenCaptureConfig,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), CommandError> {
+    let mut screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &mut *screen_capture {
+        // Update config with the selected monitor
+        let mut updated_config = config;
+        updated_config.monitor_index = monitor_index;
+        
+        // x11grab would record the frame; if asked, it goes around the captured area instead
+        let indicator = state.sharing_indicator.lock().unwrap().config().clone();
+        if indicator.enabled && indicator.narrow_capture && capture_manager.get_display_server() == screen_capture::types::DisplayServer::X11 {
+            if let Some(monitor) = capture_manager.get_monitors().into_iter().find(|m| m.index == monitor_index) {
+                let region = updated_config.effective_region(&monitor);
+                updated_config.capture_region = Some(sharing_indicator::inset_region(&monitor, &region, &indicator).ok_or_else(|| {
+                    CommandError::new(ErrorKind::CaptureInvalidConfig, "Capture region leaves no room for the sharing indicator")
+                })?);
+            }
+        }
+        
+        // Start capture, restarting a running capture of the same monitor
+        capture_manager.start_capture(updated_config, window)?;
+        
+        let input_monitors = capture_manager.get_input_monitors();
+        drop(screen_capture);
+        sync_input_monitors(&state, &input_monitors);
+        sync_do_not_disturb(&state);
+        sync_sharing_indicator(&state);
+        
+        Ok(())
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn stop_capture(monitor_index: Option<usize>, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let mut screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &mut *screen_capture {
+        capture_manager.stop_capture(monitor_index)?;
+        drop(screen_capture);
+        sync_do_not_disturb(&state);
+        sync_sharing_indicator(&state);
+        
+        Ok(())
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+/// Switches do-not-disturb on or off when sharing started or stopped
+fn sync_do_not_disturb(state: &AppState) {
+    let sharing = match &*state.screen_capture.lock().unwrap() {
+        Some(capture_manager) => !capture_manager.active_captures().is_empty(),
+        None => false,
+    };
+    let change = state.do_not_disturb.lock().unwrap().update(sharing);
+    emit_dnd_changed(&state.app_handle, change);
+}
+
+fn emit_dnd_changed(app_handle: &tauri::AppHandle, change: Option<DndChange>) {
+    if let Some(change) = change {
+        if let Err(e) = app_handle.emit_all("dnd_changed", change) {
+            log::error!(target: logging::TARGET_SCREEN_CAPTURE, "Failed to emit dnd_changed: {}", e);
+        }
+    }
+}
+
+/// Catches captures that ended without `stop_capture` and do-not-disturb
+/// switched by the user while sharing
+fn start_dnd_watcher(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        
+        let state = app_handle.state::<AppState>();
+        sync_do_not_disturb(&state);
+        sync_sharing_indicator(&state);
+    });
+}
+
+/// Applies the limits of the new power profile to running captures and
+/// tells the frontend which captures changed and why
+fn handle_power_source_change(app_handle: &tauri::AppHandle, source: PowerSource) {
+    let state = app_handle.state::<AppState>();
+    
+    let changes = lock_screen_capture(&state).as_mut()
+        .map(|manager| manager.set_power_source(source))
+        .unwrap_or_default();
+    log::info!(target: logging::TARGET_SCREEN_CAPTURE, "Power source changed: {:?}", source);
+    if changes.is_empty() {
+        return;
+    }
+    
+    let event = PowerProfileChanged { source, captures: changes };
+    if let Err(e) = app_handle.emit_all("power_profile_changed", event) {
+        log::error!(target: logging::TARGET_SCREEN_CAPTURE, "Failed to emit power_profile_changed: {}", e);
+    }
+}
+
+/// Stops all captures before a suspend and restarts them after the resume.
+/// Monitors may come back renumbered or not at all, see `screen_capture::resume`.
+fn handle_sleep_event(app_handle: &tauri::AppHandle, event: SleepEvent) {
+    let state = app_handle.state::<AppState>();
+    
+    match event {
+        SleepEvent::Suspending => {
+            let suspended = lock_screen_capture(&state).as_mut()
+                .map(|manager| manager.suspend_captures())
+                .unwrap_or_default();
+            log::info!(target: logging::TARGET_SCREEN_CAPTURE, "Host is suspending, stopped {} capture(s)", suspended.len());
+            *state.suspended_captures.lock().unwrap() = Some(suspended);
+            
+            sync_do_not_disturb(&state);
+            sync_sharing_indicator(&state);
+        },
+        SleepEvent::Resumed => {
+            let suspended = state.suspended_captures.lock().unwrap().take().unwrap_or_default();
+            
+            // Outputs are still being re-enabled right after the wake up
+            std::thread::sleep(power::RESUME_SETTLE_DELAY);
+            
+            let mut screen_capture = lock_screen_capture(&state);
+            let capture_manager = match &mut *screen_capture {
+                Some(capture_manager) => capture_manager,
+                None => return,
+            };
+            let report = capture_manager.resume_captures(suspended);
+            let input_monitors = capture_manager.get_input_monitors();
+            drop(screen_capture);
+            
+            sync_input_monitors(&state, &input_monitors);
+            sync_do_not_disturb(&state);
+            sync_sharing_indicator(&state);
+            
+            for change in &report.changes {
+                log::warn!(target: logging::TARGET_SCREEN_CAPTURE, "Capture changed after resume: {:?}", change);
+            }
+            log::info!(target: logging::TARGET_SCREEN_CAPTURE, "Host resumed, restarted {} capture(s)", report.restarted.len());
+            
+            if let Err(e) = app_handle.emit_all("session_resumed", report) {
+                log::error!(target: logging::TARGET_SCREEN_CAPTURE, "Failed to emit session_resumed: {}", e);
+            }
+        },
+    }
+}
+
+/// Frames every running capture, and only those. Under Wayland the
+/// compositor's screencast indicator takes this role.
+fn sync_sharing_indicator(state: &AppState) {
+    let mut frames = std::collections::BTreeMap::new();
+    let config = state.sharing_indicator.lock().unwrap().config().clone();
+    if let Some(capture_manager) = &*state.screen_capture.lock().unwrap() {
+        if capture_manager.get_display_server() == screen_capture::types::DisplayServer::X11 {
+            let monitors = capture_manager.get_monitors();
+            for index in capture_manager.active_captures() {
+                let (Some(monitor), Some(capture_config)) = (monitors.iter().find(|m| m.index == index), capture_manager.get_config(Some(index))) else {
+                    continue;
+                };
+                let region = capture_config.effective_region(monitor);
+                frames.insert(index, sharing_indicator::frame_strips(monitor, &region, &config));
+            }
+        }
+    }
+    
+    let mut indicator = state.sharing_indicator.lock().unwrap();
+    indicator.sync(&state.app_handle, frames);
+    indicator.close_orphans(&state.app_handle);
+}
+
+/// Frame drawn around shared screens
+#[tauri::command]
+fn get_sharing_indicator(state: tauri::State<'_, AppState>) -> IndicatorConfig {
+    state.sharing_indicator.lock().unwrap().config().clone()
+}
+
+#[tauri::command]
+fn set_sharing_indicator(config: IndicatorConfig, state: tauri::State<'_, AppState>) -> Result<IndicatorConfig, CommandError> {
+    if config.thickness == 0 || config.thickness > sharing_indicator::MAX_THICKNESS {
+        return Err(CommandError::new(
+            ErrorKind::CaptureInvalidConfig,
+            format!("Indicator thickness must be between 1 and {} pixels", sharing_indicator::MAX_THICKNESS),
+        ));
+    }
+    
+    let mut settings = state.settings.lock().unwrap().clone();
+    settings.sharing_indicator = config;
+    store_settings(&state, settings)?;
+    
+    // Running captures keep their area; only the frame follows
+    sync_sharing_indicator(&state);
+    Ok(state.sharing_indicator.lock().unwrap().config().clone())
+}
+
+/// Whether sharing switches on do-not-disturb, and which notification daemon it uses
+#[tauri::command]
+fn get_dnd_integration(state: tauri::State<'_, AppState>) -> DndStatus {
+    state.do_not_disturb.lock().unwrap().status()
+}
+
+#[tauri::command]
+fn set_dnd_integration(enabled: bool, state: tauri::State<'_, AppState>) -> Result<DndStatus, CommandError> {
+    let mut settings = state.settings.lock().unwrap().clone();
+    settings.do_not_disturb = DndConfig { enabled };
+    store_settings(&state, settings)?;
+    
+    // A capture that is already running gets do-not-disturb right away
+    sync_do_not_disturb(&state);
+    Ok(state.do_not_disturb.lock().unwrap().status())
+}
+
+#[tauri::command]
+fn get_capture_sessions(state: tauri::State<'_, AppState>) -> Result<Vec<CaptureStats>, CommandError> {
+    let screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &*screen_capture {
+        Ok(capture_manager.get_all_stats())
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+/// Per-second capture stats of the last 15 minutes
+#[tauri::command]
+fn get_stats_history(range: Option<StatsHistoryRange>, state: tauri::State<'_, AppState>) -> Result<Vec<StatsSample>, CommandError> {
+    let screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &*screen_capture {
+        Ok(capture_manager.get_stats_history(&range.unwrap_or_default()))
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn set_max_simultaneous_captures(limit: usize, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let mut screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &mut *screen_capture {
+        capture_manager.set_max_simultaneous_captures(limit)
+            .map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn get_last_capture_error(monitor_index: Option<usize>, state: tauri::State<'_, AppState>) -> Result<Option<CaptureErrorReport>, CommandError> {
+    let screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &*screen_capture {
+        Ok(capture_manager.get_last_error(monitor_index))
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn request_keyframe(monitor_index: Option<usize>, state: tauri::State<'_, AppState>) -> Result<u64, CommandError> {
+    let screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &*screen_capture {
+        capture_manager.request_keyframe(monitor_index)
+            .map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn get_frame_stream_url(monitor_index: Option<usize>, state: tauri::State<'_, AppState>) -> Result<Option<String>, CommandError> {
+    let screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &*screen_capture {
+        Ok(capture_manager.get_frame_stream_url(monitor_index))
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+/// Resize the encoded stream; input coordinates stay in captured pixels
+#[tauri::command]
+fn set_output_resolution(
+    resolution: Option<OutputResolution>,
+    monitor_index: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> Result<u64, CommandError> {
+    let mut screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &mut *screen_capture {
+        capture_manager.set_output_resolution(resolution, monitor_index)
+            .map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+/// Size of the element a viewer shows the stream in, in CSS pixels. Captures
+/// are fitted to the largest viewer once the sizes stopped changing and
+/// `quality_changed` reports the new size. False for unknown peers.
+#[tauri::command]
+fn set_viewer_viewport(
+    peer_id: String,
+    width: u32,
+    height: u32,
+    device_pixel_ratio: f64,
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, CommandError> {
+    if !state.peers.list().iter().any(|peer| peer.peer_id == peer_id) {
+        log::debug!(target: logging::TARGET_SCREEN_CAPTURE, "Viewport of unknown peer {}", peer_id);
+        return Ok(false);
+    }
+    
+    let mut screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &mut *screen_capture {
+        capture_manager.set_viewer_viewport(&peer_id, ViewerViewport { width, height, device_pixel_ratio })
+            .map_err(CommandError::from)?;
+        Ok(true)
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+/// Start sampling the cursor of a captured monitor for a recording's overlay
+#[tauri::command]
+fn start_recording_cursor_track(
+    monitor_index: usize,
+    config: Option<RecordingCursorConfig>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), CommandError> {
+    let mut screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &mut *screen_capture {
+        capture_manager.start_cursor_track(monitor_index, config.unwrap_or_default())
+            .map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+/// Stop sampling and hand the track to the frontend, which passes it to
+/// `burn_recording_cursor` once the recording is written
+#[tauri::command]
+fn stop_recording_cursor_track(monitor_index: usize, state: tauri::State<'_, AppState>) -> Result<CursorTrack, CommandError> {
+    let mut screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &mut *screen_capture {
+        capture_manager.stop_cursor_track(monitor_index)
+            .map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+/// Sent with `recording_cursor_rendered` when the cursor is burned in
+#[derive(Debug, Clone, Serialize)]
+struct RecordingCursorRendered {
+    output: std::path::PathBuf,
+    /// Set if ffmpeg failed; the recording itself is left as it was
+    error: Option<String>,
+}
+
+/// Render the cursor track into a copy of a recorded file. Encoding takes
+/// a while, so the result arrives as `recording_cursor_rendered`.
+#[tauri::command]
+fn burn_recording_cursor(
+    video_path: std::path::PathBuf,
+    output_path: std::path::PathBuf,
+    track: CursorTrack,
+    app_handle: tauri::AppHandle,
+) {
+    std::thread::spawn(move || {
+        let error = recording_cursor::burn_cursor(&SystemCommandRunner, &video_path, &output_path, &track)
+            .err()
+            .map(|e| e.to_string());
+        if let Some(error) = &error {
+            log::error!(target: logging::TARGET_SCREEN_CAPTURE, "Failed to burn the cursor into {}: {}", video_path.display(), error);
+        }
+        
+        let rendered = RecordingCursorRendered { output: output_path, error };
+        if let Err(e) = app_handle.emit_all("recording_cursor_rendered", rendered) {
+            log::error!(target: logging::TARGET_SCREEN_CAPTURE, "Failed to emit recording_cursor_rendered: {}", e);
+        }
+    });
+}
+
+/// Sent with `profile_applied` once running captures switched to a profile
+#[derive(Debug, Clone, Serialize)]
+struct ProfileApplied {
+    name: String,
+    /// Delay until the restarted encoders deliver the new stream
+    restart_delay_ms: u64,
+}
+
+/// Built-in capture profiles followed by the user's saved ones
+#[tauri::command]
+fn get_capture_profiles(state: tauri::State<'_, AppState>) -> Vec<CaptureProfile> {
+    capture_profiles(&state.settings.lock().unwrap().capture_profiles)
+}
+
+/// Switch to a capture profile: it becomes the configuration for new
+/// captures, and running captures restart their encoders with it
+#[tauri::command]
+fn apply_capture_profile(window: Window, name: String, state: tauri::State<'_, AppState>) -> Result<u64, CommandError> {
+    let mut settings = state.settings.lock().unwrap().clone();
+    let profile = find_capture_profile(&name, &settings.capture_profiles)
+        .ok_or_else(|| CommandError::new(ErrorKind::CaptureUnknownProfile, format!("No capture profile named {:?}", name)))?;
+    
+    let restart_delay_ms = match &mut *lock_screen_capture(&state) {
+        Some(capture_manager) => capture_manager.apply_capture_profile(&profile)?,
+        None => 0,
+    };
+    
+    settings.capture = settings.capture.with_profile(&profile);
+    store_settings(&state, settings)?;
+    
+    log::info!(target: logging::TARGET_SCREEN_CAPTURE, "Applied capture profile {:?}", name);
+    window.emit("profile_applied", ProfileApplied { name, restart_delay_ms })?;
+    Ok(restart_delay_ms)
+}
+
+/// Save the encoding parameters of `config` as a user profile; built-in
+/// profiles cannot be overwritten
+#[tauri::command]
+fn save_capture_profile(name: String, config: ScreenCaptureConfig, state: tauri::State<'_, AppState>) -> Result<Vec<CaptureProfile>, CommandError> {
+    let name = name.trim().to_string();
+    if name.is_empty() || builtin_profiles().iter().any(|profile| profile.name == name) {
+        return Err(CommandError::new(
+            ErrorKind::CaptureInvalidConfig,
+            format!("{:?} is not available as a profile name, choose another", name),
+        ));
+    }
+    
+    let mut settings = state.settings.lock().unwrap().clone();
+    settings.capture_profiles.insert(name, config);
+    let settings = store_settings(&state, settings)?;
+    Ok(capture_profiles(&settings.capture_profiles))
+}
+
+/// Hide a monitor-relative area from viewers; returns the mask's id
+#[tauri::command]
+fn add_privacy_mask(
+    monitor_index: usize,
+    mask: PrivacyMask,
+    state: tauri::State<'_, AppState>,
+) -> Result<u32, CommandError> {
+    let mut screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &mut *screen_capture {
+        capture_manager.add_privacy_mask(monitor_index, mask)
+            .map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn remove_privacy_mask(monitor_index: usize, id: u32, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let mut screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &mut *screen_capture {
+        capture_manager.remove_privacy_mask(monitor_index, id)
+            .map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn list_privacy_masks(monitor_index: usize, state: tauri::State<'_, AppState>) -> Result<PrivacyMasks, CommandError> {
+    let screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &*screen_capture {
+        capture_manager.list_privacy_masks(monitor_index)
+            .map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn set_capture_region(
+    window: Window,
+    monitor_index: usize,
+    region: Option<CaptureRegion>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), CommandError> {
+    let mut screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &mut *screen_capture {
+        capture_manager.set_capture_region(monitor_index, region, window)?;
+        
+        // Remote clicks at client (0,0) must land at the region origin
+        let input_monitors = capture_manager.get_input_monitors();
+        drop(screen_capture);
+        sync_input_monitors(&state, &input_monitors);
+        
+        Ok(())
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn set_buffer_config(config: BufferConfig, monitor_index: Option<usize>, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let mut screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &mut *screen_capture {
+        capture_manager.set_buffer_config(config, monitor_index)
+            .map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn get_buffer_stats(monitor_index: Option<usize>, state: tauri::State<'_, AppState>) -> Result<Option<BufferStats>, CommandError> {
+    let screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &*screen_capture {
+        Ok(capture_manager.get_buffer_stats(monitor_index))
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn create_virtual_display(
+    window: Window,
+    width: u32,
+    height: u32,
+    refresh_rate: u32,
+    state: tauri::State<'_, AppState>,
+) -> Result<MonitorInfo, CommandError> {
+    let mut screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &mut *screen_capture {
+        let monitor = capture_manager.create_virtual_display(width, height, refresh_rate)?;
+        
+        let monitors = capture_manager.get_monitors();
+        let input_monitors = capture_manager.get_input_monitors();
+        drop(screen_capture);
+        sync_input_monitors(&state, &input_monitors);
+        let _ = window.emit("monitors_changed", monitors);
+        
+        Ok(monitor)
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn destroy_virtual_display(window: Window, id: String, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let mut screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &mut *screen_capture {
+        capture_manager.destroy_virtual_display(&id)?;
+        
+        let monitors = capture_manager.get_monitors();
+        let input_monitors = capture_manager.get_input_monitors();
+        drop(screen_capture);
+        sync_input_monitors(&state, &input_monitors);
+        let _ = window.emit("monitors_changed", monitors);
+        
+        Ok(())
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn get_virtual_displays(state: tauri::State<'_, AppState>) -> Result<Vec<VirtualDisplay>, CommandError> {
+    let screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &*screen_capture {
+        Ok(capture_manager.get_virtual_displays())
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn send_input_event(
+    event: InputEvent,
+    peer_id: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>
+) -> Result<(), CommandError> {
+    let received = std::time::Instant::now();
+    
+    // Pings only measure the round trip and never reach the forwarder
+    if let input_forwarding::InputEventType::Ping = event.event_type {
+        let payload = InputPong {
+            ping_id: event.ping_id,
+            client_timestamp: event.client_timestamp,
+            host_timestamp: stats::now_ms(),
+        };
+        return app_handle.emit_all("input_pong", payload).map_err(CommandError::from);
+    }
+    
+    if let Some(peer_id) = &peer_id {
+        state.peers.touch(peer_id, chrono::Utc::now());
+    }
+    
+    // Not even previewed: in safe mode a viewer's input goes nowhere
+    state.safe_mode.check(RemoteControl::Input)?;
+    
+    if !check_input_control(&app_handle, &state, peer_id.as_deref())? {
+        return Ok(());
+    }
+    
+    if let Some(input_forwarding::SpecialCommand::Custom(_)) = &event.special_command {
+        if !custom_commands_allowed(&state) {
+            return Err(CommandError::new(ErrorKind::InputPermissionDenied, "Custom commands are not allowed for remote peers"));
+        }
+    }
+    
+    match input_mode_for(&state, peer_id.as_deref())? {
+        InputMode::Enabled => {},
+        // Shown to the host with `input_preview`; nothing is injected and the display stays asleep
+        InputMode::Preview => {
+            state.input_preview.record(peer_id.as_deref(), &event);
+            return Ok(());
+        },
+        InputMode::Disabled => return Ok(()),
+    }
+    
+    // Wake a sleeping display; a locked session is only reported, never unlocked
+    let preflight = state.host_session.before_input();
+    if preflight.woke_display {
+        log::info!(target: logging::TARGET_INPUT, "Woke the host display for remote input");
+    }
+    if preflight.newly_locked {
+        app_handle.emit_all("host_locked", preflight.state)?;
+    }
+    
+    let input_forwarder = lock_input_forwarder(&state);
+    
+    if let Some(forwarder) = state.safe_mode.reach(RemoteControl::Input, &*input_forwarder)? {
+        let new_event: input_forwarding::types::InputEvent = event.into();
+        
+        // Keys and clicks aimed at an excluded application are dropped silently for the peer
+        if let Some(blocked) = state.input_blocklist.check(&new_event) {
+            return app_handle.emit_all("blocked_input", blocked).map_err(CommandError::from);
+        }
+        
+        if let (input_forwarding::InputEventType::SpecialCommand, Some(command)) = (&new_event.event_type, &new_event.special_command) {
+            if !authorize_system_action(&app_handle, &state, peer_id.as_deref(), command)? {
+                return Ok(());
+            }
+        }
+        
+        note_injected_event(&state, &new_event);
+        let started = std::time::Instant::now();
+        forwarder.forward_event(&new_event)?;
+        state.input_latency.record(new_event.event_type.clone(), received, started, std::time::Instant::now());
+        drop(input_forwarder);
+        state.input_recorder.record(&new_event);
+        // A paste by the peer often follows a copy the poller has not seen yet
+        state.clipboard_poller.input_activity();
+        
+        // Feed the client-side cursor where the pointer cannot be queried
+        if let (input_forwarding::InputEventType::MouseMove, Some(x), Some(y)) = (&new_event.event_type, new_event.x, new_event.y) {
+            state.pointer_feed.report_position(x, y, new_event.monitor_index);
+        }
+        
+        // Mark presses on cursor tracks of recordings
+        if new_event.event_type == input_forwarding::InputEventType::MouseButton && new_event.is_pressed != Some(false) {
+            state.pointer_feed.report_click(new_event.x.zip(new_event.y), new_event.monitor_index);
+        }
+        
+        Ok(())
+    } else {
+        Err(state.feature_error(Feature::InputForwarding, "Input forwarder not initialized"))
+    }
+}
+
+/// Disruptive special commands run with the peer's `SystemActions` right or
+/// as the configured default says. Returns false while the host is asked;
+/// `system_action_requested` has been emitted then.
+fn authorize_system_action(
+    app_handle: &tauri::AppHandle,
+    state: &AppState,
+    peer_id: Option<&str>,
+    command: &input_forwarding::SpecialCommand,
+) -> Result<bool, CommandError> {
+    let granted = match (peer_id, &*state.security_manager.lock().unwrap()) {
+        (Some(peer_id), Some(security_manager)) => security_manager.get_peer_access_rights(peer_id).contains(&AccessRight::SystemActions),
+        _ => false,
+    };
+    
+    match state.system_actions.request(peer_id, granted, command, std::time::Instant::now()) {
+        ActionDecision::Allowed => Ok(true),
+        ActionDecision::Denied(reason) => Err(CommandError::new(ErrorKind::InputPermissionDenied, reason)),
+        ActionDecision::Pending(request) => {
+            app_handle.emit_all("system_action_requested", &request)?;
+            notify_host(app_handle, Notification::system_action_requested(&request.peer_id, &request.description));
+            Ok(false)
+        },
+    }
+}
+
+/// Tells the frontend how a request for a system action ended
+fn emit_system_action_resolved(app_handle: &tauri::AppHandle, resolution: &SystemActionResolution) {
+    if let Err(e) = app_handle.emit_all("system_action_resolved", resolution) {
+        log::error!(target: logging::TARGET_INPUT, "Failed to emit system_action_resolved: {}", e);
+    }
+}
+
+/// The host allows or denies a system action a peer asked for; an allowed
+/// one runs right away. Returns false if the request was already resolved.
+#[tauri::command]
+fn answer_system_action(
+    request_id: u64,
+    allow: bool,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>
+) -> Result<bool, CommandError> {
+    let Some(resolution) = state.system_actions.answer(request_id, allow, std::time::Instant::now()) else {
+        return Ok(false);
+    };
+    emit_system_action_resolved(&app_handle, &resolution);
+    log::info!(target: logging::TARGET_INPUT, "Host {} {} for {}",
+        if allow { "allowed" } else { "denied" }, resolution.request.description, resolution.request.peer_id);
+    
+    if allow {
+        match &*lock_input_forwarder(&state) {
+            Some(forwarder) => forwarder.handle_special_command(&resolution.request.command)?,
+            None => return Err(state.feature_error(Feature::InputForwarding, "Input forwarder not initialized")),
+        }
+    }
+    Ok(true)
+}
+
+/// Requests for system actions the host has not answered yet
+#[tauri::command]
+fn get_pending_system_actions(state: tauri::State<'_, AppState>) -> Vec<SystemActionRequest> {
+    state.system_actions.pending()
+}
+
+#[tauri::command]
+fn get_system_action_config(state: tauri::State<'_, AppState>) -> SystemActionConfig {
+    state.settings.lock().unwrap().input.system_actions.clone()
+}
+
+/// Which special commands are disruptive and what happens to them for peers without the `SystemActions` right
+#[tauri::command]
+fn set_system_action_config(config: SystemActionConfig, state: tauri::State<'_, AppState>) -> Result<SystemActionConfig, CommandError> {
+    config.validate()
+        .map_err(|e| CommandError::new(ErrorKind::InputInvalidConfig, e.to_string()))?;
+    
+    let mut settings = state.settings.lock().unwrap().clone();
+    settings.input.system_actions = config;
+    Ok(store_settings(&state, settings)?.input.system_actions)
+}
+
+/// Denies requests for system actions the host left unanswered
+fn start_system_action_timer(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        
+        let state = app_handle.state::<AppState>();
+        for resolution in state.system_actions.expire(std::time::Instant::now()) {
+            log::info!(target: logging::TARGET_INPUT, "No answer to {} for {}, denied",
+                resolution.request.description, resolution.request.peer_id);
+            emit_system_action_resolved(&app_handle, &resolution);
+        }
+    });
+}
+
+/// How input from `peer_id` is handled: the host-wide mode, restricted further by the peer's own
+fn input_mode_for(state: &AppState, peer_id: Option<&str>) -> Result<InputMode, CommandError> {
+    let host_mode = match &*lock_input_forwarder(state) {
+        Some(forwarder) => forwarder.mode(),
+        None => return Err(state.feature_error(Feature::InputForwarding, "Input forwarder not initialized")),
+    };
+    Ok(host_mode.max(state.input_preview.peer_mode(peer_id)))
+}
+
+/// Rate limit and control arbitration shared by everything that counts as remote input.
+/// Returns false if another peer holds control; `control_denied` has been emitted then.
+fn check_input_control(app_handle: &tauri::AppHandle, state: &AppState, peer_id: Option<&str>) -> Result<bool, CommandError> {
+    check_rate_limit(app_handle, state, peer_id, RateLimitedCommand::Input)?;
+    
+    // Only the peer holding control gets its input forwarded
+    let (checked, holder_changed) = {
+        let mut arbiter = state.input_arbiter.lock().unwrap();
+        let previous = arbiter.holder().map(str::to_string);
+        let checked = arbiter.check_input(peer_id, std::time::Instant::now());
+        (checked, previous.as_deref() != arbiter.holder())
+    };
+    if holder_changed {
+        emit_control_changed(app_handle, state);
+    }
+    if let Err(denied) = checked {
+        app_handle.emit_all("control_denied", denied)?;
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Sets the host's output volume; a remote peer needs the same permission as for input.
+/// Returns the applied level, which is clamped to 100%.
+#[tauri::command]
+fn set_host_volume(
+    percent: u32,
+    peer_id: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>
+) -> Result<u32, CommandError> {
+    if peer_id.is_some() {
+        // A preview cannot show a volume change without making it
+        if input_mode_for(&state, peer_id.as_deref())? != InputMode::Enabled {
+            return Err(CommandError::new(ErrorKind::InputPermissionDenied, "Remote input is disabled on the host"));
+        }
+        if !check_input_control(&app_handle, &state, peer_id.as_deref())? {
+            return Err(CommandError::new(ErrorKind::InputPermissionDenied, "Another peer has control of the host"));
+        }
+    }
+    
+    host_volume::set_volume(&SystemCommandRunner, percent)
+        .map_err(|e| CommandError::new(ErrorKind::HostVolumeFailed, e))
+}
+
+/// Output volume of the host in percent, e.g. to position the volume slider
+#[tauri::command]
+fn get_host_volume() -> Result<u32, CommandError> {
+    host_volume::get_volume(&SystemCommandRunner)
+        .map_err(|e| CommandError::new(ErrorKind::HostVolumeFailed, e))
+}
+
+/// Peers read on-screen text only with the `AccessibilityText` right, which
+/// no role grants by default; the local frontend always may
+fn check_accessibility_access(state: &AppState, peer_id: Option<&str>) -> Result<(), CommandError> {
+    let Some(peer_id) = peer_id else { return Ok(()) };
+    
+    let security = state.security_manager.lock().unwrap();
+    let security_manager = security.as_ref()
+        .ok_or_else(|| CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized"))?;
+    if !security_manager.get_peer_access_rights(peer_id).contains(&AccessRight::AccessibilityText) {
+        return Err(CommandError::new(ErrorKind::SecurityPermissionDenied, "The host does not allow this peer to read on-screen text"));
+    }
+    Ok(())
+}
+
+/// Reads by peers are audited with what was read, but not the text itself
+fn record_accessibility_read(state: &AppState, peer_id: Option<&str>, detail: String, objects: &[AccessibleText]) {
+    if peer_id.is_none() {
+        return;
+    }
+    let chars: usize = objects.iter()
+        .filter_map(|object| object.text.as_deref())
+        .map(|text| text.chars().count())
+        .sum();
+    state.audit_log.record(AuditEvent::new(AuditEventKind::AccessibilityTextRead, peer_id)
+        .with_detail(detail)
+        .with_size(chars as u64));
+}
+
+/// Text of the accessible objects intersecting a rectangle of a monitor, in
+/// reading order, for viewers with screen readers. Coordinates are relative
+/// to the monitor.
+#[tauri::command]
+fn get_text_at_region(
+    monitor_index: usize,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    peer_id: Option<String>,
+    state: tauri::State<'_, AppState>
+) -> Result<Vec<AccessibleText>, CommandError> {
+    check_accessibility_access(&state, peer_id.as_deref())?;
+    
+    let monitor = match &*lock_screen_capture(&state) {
+        Some(capture_manager) => capture_manager.get_monitors().into_iter().find(|monitor| monitor.index == monitor_index),
+        None => return Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized")),
+    };
+    let monitor = monitor
+        .ok_or_else(|| CommandError::new(ErrorKind::CaptureInvalidMonitor, format!("Monitor {} not found", monitor_index)))?;
+    let region = ScreenRect::on_monitor(&monitor, x, y, width, height)
+        .ok_or_else(|| CommandError::new(
+            ErrorKind::CaptureInvalidRegion,
+            format!("{}x{} at {},{} is empty or outside monitor {}", width, height, x, y, monitor_index),
+        ))?;
+    
+    let objects = tauri::async_runtime::block_on(
+        accessibility::text_at_region(state.accessibility.as_ref(), region, accessibility::QUERY_TIMEOUT)
+    )?;
+    record_accessibility_read(
+        &state,
+        peer_id.as_deref(),
+        format!("monitor {} {}x{} at {},{}", monitor_index, width, height, x, y),
+        &objects,
+    );
+    Ok(objects)
+}
+
+/// Text or value of the widget with keyboard focus on the host
+#[tauri::command]
+fn get_focused_text(
+    peer_id: Option<String>,
+    state: tauri::State<'_, AppState>
+) -> Result<Option<AccessibleText>, CommandError> {
+    check_accessibility_access(&state, peer_id.as_deref())?;
+    
+    let focused = tauri::async_runtime::block_on(
+        accessibility::focused_text(state.accessibility.as_ref(), accessibility::QUERY_TIMEOUT)
+    )?;
+    record_accessibility_read(&state, peer_id.as_deref(), "focused widget".to_string(), focused.as_slice());
+    Ok(focused)
+}
+
+/// Which mechanism injects remote input, e.g. the RemoteDesktop portal or ydotool
+#[tauri::command]
+fn get_input_backend(state: tauri::State<'_, AppState>) -> Result<InputBackend, CommandError> {
+    match &*lock_input_forwarder(&state) {
+        Some(forwarder) => Ok(forwarder.backend()),
+        None => Err(state.feature_error(Feature::InputForwarding, "Input forwarder not initialized")),
+    }
+}
+
+/// Queueing and injection latency of forwarded input over the last minute, per event type
+#[tauri::command]
+fn get_input_latency_stats(state: tauri::State<'_, AppState>) -> InputLatencyStats {
+    state.input_latency.stats(std::time::Instant::now())
+}
+
+#[tauri::command]
+fn set_input_enabled(enabled: bool, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let input_forwarder = lock_input_forwarder(&state);
+    
+    if let Some(forwarder) = &*input_forwarder {
+        forwarder.set_enabled(enabled);
+        let kind = if enabled { AuditEventKind::InputEnabled } else { AuditEventKind::InputDisabled };
+        state.audit_log.record(AuditEvent::new(kind, None));
+        Ok(())
+    } else {
+        Err(state.feature_error(Feature::InputForwarding, "Input forwarder not initialized"))
+    }
+}
+
+/// Switches remote input between injecting, previewing and dropping events, either for
+/// the whole host or, with `peer_id`, for one peer. A peer's mode can only restrict the host's.
+#[tauri::command]
+fn set_input_mode(mode: InputMode, peer_id: Option<String>, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    match &peer_id {
+        Some(peer_id) => state.input_preview.set_peer_mode(peer_id, mode),
+        None => match &*lock_input_forwarder(&state) {
+            Some(forwarder) => forwarder.set_mode(mode),
+            None => return Err(state.feature_error(Feature::InputForwarding, "Input forwarder not initialized")),
+        },
+    }
+    
+    let kind = match mode {
+        InputMode::Enabled => AuditEventKind::InputEnabled,
+        InputMode::Preview => AuditEventKind::InputPreview,
+        InputMode::Disabled => AuditEventKind::InputDisabled,
+    };
+    state.audit_log.record(AuditEvent::new(kind, peer_id.as_deref()));
+    Ok(())
+}
+
+/// Lock and display power state of the host session
+#[tauri::command]
+fn get_host_session_state(state: tauri::State<'_, AppState>) -> HostSessionState {
+    state.host_session.state()
+}
+
+#[tauri::command]
+fn wake_display(state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    state.host_session.wake_display()
+        .map_err(|e| CommandError::new(ErrorKind::HostWakeFailed, e))
+}
+
+/// Whether the first remote input wakes a display blanked by DPMS
+#[tauri::command]
+fn set_wake_display_on_input(enabled: bool, state: tauri::State<'_, AppState>) {
+    state.host_session.set_wake_on_input(enabled);
+}
+
+/// Enforces the per-peer command limits; drops and suspensions are reported with `rate_limited`.
+/// Commands without a peer ID are counted together under `UNIDENTIFIED_PEER`.
+fn check_rate_limit(app_handle: &tauri::AppHandle, state: &AppState, peer_id: Option<&str>, command: RateLimitedCommand) -> Result<(), CommandError> {
+    let decision = match &*state.security_manager.lock().unwrap() {
+        Some(manager) => manager.check_rate_limit(peer_id, command),
+        None => return Ok(()),
+    };
+    
+    if let Some(report) = decision.report() {
+        if let Err(e) = app_handle.emit_all("rate_limited", report) {
+            log::error!(target: logging::TARGET_SECURITY, "Failed to emit rate_limited: {}", e);
+        }
+    }
+    
+    let peer_id = peer_id.unwrap_or(UNIDENTIFIED_PEER);
+    match decision {
+        RateLimitDecision::Allowed => Ok(()),
+        RateLimitDecision::Dropped(_) => Err(CommandError::new(ErrorKind::PeerRateLimited, format!("Rate limit exceeded for peer {}", peer_id))),
+        RateLimitDecision::Suspended(_) => Err(CommandError::new(ErrorKind::PeerSuspended, format!("Peer {} is suspended for exceeding rate limits", peer_id))),
+    }
+}
+
+/// Custom commands run host tools with peer-supplied arguments and need an explicit opt-in
+fn custom_commands_allowed(state: &AppState) -> bool {
+    state.security_manager.lock().unwrap().as_ref()
+        .map(|manager| manager.custom_commands_allowed())
+        .unwrap_or(false)
+}
+
+/// Lets the panic hotkey tell remote key presses from local ones
+fn note_injected_event(state: &AppState, event: &input_forwarding::types::InputEvent) {
+    if let Some(listener) = &*state.panic_hotkey.lock().unwrap() {
+        listener.note_injected(event);
+    }
+}
+
+/// Release modifiers and keys the peer left pressed
+#[tauri::command]
+fn reset_input_state(app_handle: tauri::AppHandle, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    if lock_input_forwarder(&state).is_none() {
+        return Err(state.feature_error(Feature::InputForwarding, "Input forwarder not initialized"));
+    }
+    release_held_keys(&app_handle, "requested");
+    Ok(())
+}
+
+/// Force-releases held keys and tells the frontend why
+fn release_held_keys(app_handle: &tauri::AppHandle, reason: &str) {
+    let state = match app_handle.try_state::<AppState>() {
+        Some(state) => state,
+        None => return,
+    };
+    
+    if let Some(forwarder) = &*state.input_forwarder.lock().unwrap() {
+        if let Err(e) = forwarder.reset_input_state() {
+            log::error!(target: logging::TARGET_INPUT, "Failed to reset input state: {}", e);
+        }
+    }
+    
+    let payload = InputStateReset { reason: reason.to_string() };
+    if let Err(e) = app_handle.emit_all("input_state_reset", payload) {
+        log::error!(target: logging::TARGET_INPUT, "Failed to emit input_state_reset: {}", e);
+    }
+}
+
+/// Releases keys that stay held while no input arrives, e.g. after a lost keyup
+fn start_stuck_key_watchdog(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        
+        let state = app_handle.state::<AppState>();
+        let timeout = match *state.stuck_key_timeout.lock().unwrap() {
+            Some(timeout) => timeout,
+            None => continue,
+        };
+        let idle = state.input_forwarder.lock().unwrap().as_ref()
+            .and_then(|forwarder| forwarder.held_keys_idle_for());
+        
+        if let Some(idle) = idle.filter(|idle| *idle >= timeout) {
+            log::warn!(target: logging::TARGET_INPUT, "Releasing keys held for {:?} without input", idle);
+            release_held_keys(&app_handle, "watchdog");
+        }
+    });
+}
+
+/// Tells the frontend who holds input control now
+fn emit_control_changed(app_handle: &tauri::AppHandle, state: &AppState) {
+    let control = state.input_arbiter.lock().unwrap().state();
+    if let Err(e) = app_handle.emit_all("control_changed", control) {
+        log::error!(target: logging::TARGET_INPUT, "Failed to emit control_changed: {}", e);
+    }
+}
+
+#[tauri::command]
+fn request_control(peer_id: String, app_handle: tauri::AppHandle, state: tauri::State<'_, AppState>) -> ControlDecision {
+    let decision = state.input_arbiter.lock().unwrap().request_control(&peer_id, std::time::Instant::now());
+    match decision {
+        ControlDecision::Granted => emit_control_changed(&app_handle, &state),
+        ControlDecision::Pending => {
+            if let Err(e) = app_handle.emit_all("control_requested", &peer_id) {
+                log::error!(target: logging::TARGET_INPUT, "Failed to emit control_requested: {}", e);
+            }
+            notify_host(&app_handle, Notification::control_requested(&peer_id));
+        },
+        ControlDecision::Denied { .. } => {},
+    }
+    decision
+}
+
+#[tauri::command]
+fn release_control(peer_id: String, app_handle: tauri::AppHandle, state: tauri::State<'_, AppState>) -> bool {
+    let released = state.input_arbiter.lock().unwrap().release_control(&peer_id);
+    if released {
+        emit_control_changed(&app_handle, &state);
+    }
+    released
+}
+
+/// Host hands control to a peer, or takes it back when `peer_id` is omitted
+#[tauri::command]
+fn grant_control(peer_id: Option<String>, app_handle: tauri::AppHandle, state: tauri::State<'_, AppState>) {
+    state.input_arbiter.lock().unwrap().grant_control(peer_id.as_deref(), std::time::Instant::now());
+    emit_control_changed(&app_handle, &state);
+}
+
+#[tauri::command]
+fn get_control_state(state: tauri::State<'_, AppState>) -> ControlState {
+    state.input_arbiter.lock().unwrap().state()
+}
+
+#[tauri::command]
+fn set_control_config(config: ControlConfig, app_handle: tauri::AppHandle, state: tauri::State<'_, AppState>) {
+    state.input_arbiter.lock().unwrap().set_config(config);
+    emit_control_changed(&app_handle, &state);
+}
+
+#[tauri::command]
+fn get_control_config(state: tauri::State<'_, AppState>) -> ControlConfig {
+    state.input_arbiter.lock().unwrap().config().clone()
+}
+
+/// Returns control to the host once its holder stopped sending input
+fn start_control_idle_timer(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        
+        let state = app_handle.state::<AppState>();
+        let expired = state.input_arbiter.lock().unwrap().expire_idle(std::time::Instant::now());
+        if expired {
+            emit_control_changed(&app_handle, &state);
+        }
+    });
+}
+
+/// Sent with `input_state_reset`; reason is `requested`, `peer_disconnected` or `watchdog`
+#[derive(Debug, Clone, Serialize)]
+struct InputStateReset {
+    reason: String,
+}
+
+/// Answer to a Ping input event; both timestamps are Unix milliseconds
+#[derive(Debug, Clone, Serialize)]
+struct InputPong {
+    ping_id: Option<u32>,
+    client_timestamp: Option<u64>,
+    host_timestamp: u64,
+}
+
+/// Sent with `input_forwarding_disabled` and `input_forwarding_enabled`
+#[derive(Debug, Clone, Serialize)]
+struct InputForwardingToggle {
+    enabled: bool,
+    hotkey: String,
+    stream_blanked: bool,
+}
+
+/// Panic hotkey action: cut off remote input, or restore it on the second press
+fn toggle_remote_input(app_handle: &tauri::AppHandle) {
+    let state = match app_handle.try_state::<AppState>() {
+        Some(state) => state,
+        None => return,
+    };
+    
+    let enabled = match &*state.input_forwarder.lock().unwrap() {
+        Some(forwarder) => {
+            // A preview counts as on, so the first press cuts it off instead of lifting it
+            let enabled = forwarder.mode() == InputMode::Disabled;
+            forwarder.set_enabled(enabled);
+            enabled
+        },
+        None => return,
+    };
+    
+    // A running macro would keep typing otherwise
+    if !enabled {
+        state.input_recorder.cancel_replay();
+    }
+    
+    let config = state.panic_hotkey_config.lock().unwrap().clone();
+    let mut stream_blanked = false;
+    if let Some(capture_manager) = &mut *state.screen_capture.lock().unwrap() {
+        if config.blank_stream || capture_manager.is_output_blanked() {
+            capture_manager.set_output_blanked(!enabled && config.blank_stream);
+        }
+        stream_blanked = capture_manager.is_output_blanked();
+    }
+    
+    log::warn!(target: logging::TARGET_INPUT, "Remote input {} by panic hotkey", if enabled { "re-enabled" } else { "disabled" });
+    let kind = if enabled { AuditEventKind::InputEnabled } else { AuditEventKind::InputDisabled };
+    state.audit_log.record(AuditEvent::new(kind, None).with_detail("panic hotkey"));
+    
+    let event = if enabled { "input_forwarding_enabled" } else { "input_forwarding_disabled" };
+    let payload = InputForwardingToggle { enabled, hotkey: config.hotkey, stream_blanked };
+    if let Err(e) = app_handle.emit_all(event, payload) {
+        log::error!(target: logging::TARGET_INPUT, "Failed to emit {}: {}", event, e);
+    }
+}
+
+/// Starts the hotkey listener for `config`, None if the hotkey is disabled
+fn start_panic_hotkey(app_handle: tauri::AppHandle, config: &PanicHotkeyConfig) -> Result<Option<PanicHotkeyListener>, InputForwardingError> {
+    if !config.enabled {
+        return Ok(None);
+    }
+    
+    let hotkey = Hotkey::parse(&config.hotkey)?;
+    let listener = PanicHotkeyListener::start(hotkey, Arc::new(move || toggle_remote_input(&app_handle)))?;
+    Ok(Some(listener))
+}
+
+#[tauri::command]
+fn configure_panic_hotkey(
+    app_handle: tauri::AppHandle,
+    config: PanicHotkeyConfig,
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<HotkeyBackend>, CommandError> {
+    // Validate before tearing down the working listener
+    if config.enabled {
+        Hotkey::parse(&config.hotkey)?;
+    }
+    
+    // Not stopped under the lock: the listener may be waiting for a toggle
+    // that in turn waits for send_input_event
+    let previous = state.panic_hotkey.lock().unwrap().take();
+    if let Some(mut listener) = previous {
+        listener.stop();
+    }
+    
+    *state.panic_hotkey_config.lock().unwrap() = config.clone();
+    let listener = start_panic_hotkey(app_handle, &config)?;
+    let backend = listener.as_ref().map(|listener| listener.backend());
+    *state.panic_hotkey.lock().unwrap() = listener;
+    
+    Ok(backend)
+}
+
+#[tauri::command]
+fn get_panic_hotkey_config(state: tauri::State<'_, AppState>) -> PanicHotkeyConfig {
+    state.panic_hotkey_config.lock().unwrap().clone()
+}
+
+/// Current monitor layout as seen by the input forwarder
+fn current_input_monitors(state: &AppState) -> Vec<MonitorConfiguration> {
+    input_monitor_configurations(&state.pointer_feed.input_monitors())
+}
+
+#[tauri::command]
+fn start_input_recording(state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let monitors = current_input_monitors(&state);
+    state.input_recorder.start_recording(monitors)?;
+    state.audit_log.record(AuditEvent::new(AuditEventKind::RecordingStarted, None).with_detail("input macro"));
+    Ok(())
+}
+
+#[tauri::command]
+fn stop_input_recording(name: String, state: tauri::State<'_, AppState>) -> Result<InputMacro, CommandError> {
+    state.input_recorder.stop_recording(&name)
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn list_input_macros(state: tauri::State<'_, AppState>) -> Result<Vec<String>, CommandError> {
+    state.input_recorder.list_macros()
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn delete_input_macro(name: String, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    state.input_recorder.delete_macro(&name)
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn replay_input_macro(
+    window: Window,
+    name: String,
+    speed_factor: Option<f32>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<String>, CommandError> {
+    if lock_input_forwarder(&state).is_none() {
+        return Err(state.feature_error(Feature::InputForwarding, "Input forwarder not initialized"));
+    }
+    
+    let input_macro = state.input_recorder.load_macro(&name)?;
+    let monitors = current_input_monitors(&state);
+    
+    // Replayed events bypass send_input_event, so they are never re-recorded
+    let input_forwarder = state.input_forwarder.clone();
+    let panic_hotkey = state.panic_hotkey.clone();
+    let system_actions = state.system_actions.clone();
+    let forward = move |event: &input_forwarding::types::InputEvent| {
+        if let Some(listener) = &*panic_hotkey.lock().unwrap() {
+            listener.note_injected(event);
+        }
+        // The host replays its own macros, so their system actions are not asked about
+        if let (input_forwarding::InputEventType::SpecialCommand, Some(command)) = (&event.event_type, &event.special_command) {
+            system_actions.request(None, false, command, std::time::Instant::now());
+        }
+        match &*input_forwarder.lock().unwrap() {
+            Some(forwarder) => forwarder.forward_event(event),
+            None => Err(InputForwardingError::InitializationFailed("Input forwarder not initialized".to_string())),
+        }
+    };
+    
+    state.input_recorder.replay(input_macro, speed_factor.unwrap_or(1.0), &monitors, forward, move |outcome| {
+        let _ = window.emit("input_replay_finished", outcome);
+    })
+    .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn cancel_input_replay(state: tauri::State<'_, AppState>) -> bool {
+    state.input_recorder.cancel_replay()
+}
+
+#[tauri::command]
+fn configure_input_forwarding(config: InputForwardingConfig, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    // A frontend that predates versioning sends a layout this build cannot read reliably
+    protocol::negotiate(protocol::WireSubsystem::InputConfig, config.protocol_version)?;
+    
+    let input_forwarder = lock_input_forwarder(&state);
+    
+    if let Some(forwarder) = &*input_forwarder {
+        // Update multi-monitor configuration if enabled
+        if config.enable_multi_monitor {
+            forwarder.configure_monitors(config.monitors)?;
+        }
+        
+        forwarder.set_shortcut_policy(&config.shortcut_policy)
+            .map_err(|e| CommandError::new(ErrorKind::InputInvalidConfig, e.to_string()))?;
+        forwarder.set_keyboard_layout(&config.keyboard_layout)
+            .map_err(|e| CommandError::new(ErrorKind::InputInvalidConfig, e.to_string()))?;
+        state.input_blocklist.set_patterns(config.input_blocklist);
+        *state.stuck_key_timeout.lock().unwrap() = config.stuck_key_timeout_ms.map(std::time::Duration::from_millis);
+        Ok(())
+    } else {
+        Err(state.feature_error(Feature::InputForwarding, "Input forwarder not initialized"))
+    }
+}
+
+/// Window class / app-id patterns (`*` and `?` wildcards) that never receive remote keys or clicks
+#[tauri::command]
+fn set_input_blocklist(patterns: Vec<String>, state: tauri::State<'_, AppState>) {
+    state.input_blocklist.set_patterns(patterns);
+}
+
+#[tauri::command]
+fn get_input_blocklist(state: tauri::State<'_, AppState>) -> Vec<String> {
+    state.input_blocklist.patterns()
+}
+
+#[tauri::command]
+fn get_shortcut_policy(state: tauri::State<'_, AppState>) -> ShortcutPolicy {
+    state.settings.lock().unwrap().input.shortcut_policy.clone()
+}
+
+/// Decide per key combination whether it reaches the host, is ignored or is translated
+#[tauri::command]
+fn set_shortcut_policy(policy: ShortcutPolicy, state: tauri::State<'_, AppState>) -> Result<ShortcutPolicy, CommandError> {
+    policy.validate()
+        .map_err(|e| CommandError::new(ErrorKind::InputInvalidConfig, e.to_string()))?;
+    
+    let mut settings = state.settings.lock().unwrap().clone();
+    settings.input.shortcut_policy = policy;
+    Ok(store_settings(&state, settings)?.input.shortcut_policy)
+}
+
+#[tauri::command]
+fn get_video_codecs(state: tauri::State<'_, AppState>) -> Result<SupportedOptions, CommandError> {
+    let screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &*screen_capture {
+        Ok(capture_manager.get_encoder_capabilities().codec_options())
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+/// Every problem with a configuration; an empty list means it can be started
+#[tauri::command]
+fn validate_capture_config(config: ScreenCaptureConfig, state: tauri::State<'_, AppState>) -> Result<Vec<ConfigIssue>, CommandError> {
+    let screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &*screen_capture {
+        Ok(capture_manager.validate_capture_config(&config))
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn get_hardware_acceleration_options(state: tauri::State<'_, AppState>) -> Result<SupportedOptions, CommandError> {
+    let screen_capture = lock_screen_capture(&state);
+    
+    if let Some(capture_manager) = &*screen_capture {
+        Ok(capture_manager.get_encoder_capabilities().acceleration_options())
+    } else {
+        Err(state.feature_error(Feature::ScreenCapture, "Screen capture manager not initialized"))
+    }
+}
+
+/// PipeWire screens and windows that can be set as `pipewire_node` of a capture
+#[tauri::command]
+fn get_wayland_capture_sources() -> Result<Vec<PipeWireSource>, CommandError> {
+    pipewire::list_sources(&SystemCommandRunner).map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_clipboard_text(state: tauri::State<'_, AppState>) -> Result<String, CommandError> {
+    let mut clipboard = lock_clipboard(&state);
+    
+    if let Some(clipboard_manager) = &mut *clipboard {
+        clipboard_manager.get_text()
+            .map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::Clipboard, "Clipboard manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn set_clipboard_text(
+    text: String,
+    source: Option<String>,
+    peer_id: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>
+) -> Result<(), CommandError> {
+    if source.as_deref() == Some("remote") {
+        check_rate_limit(&app_handle, &state, peer_id.as_deref(), RateLimitedCommand::Clipboard)?;
+    }
+    
+    let mut clipboard = lock_clipboard(&state);
+    
+    if let Some(clipboard_manager) = &mut *clipboard {
+        // Text received from a peer must not be reported back as a local change
+        let result = if source.as_deref() == Some("remote") {
+            check_system_clipboard_allowed(clipboard_manager, peer_id.as_deref())?;
+            let result = clipboard_manager.set_remote_text(&text);
+            if result.is_ok() {
+                state.audit_log.record(AuditEvent::new(AuditEventKind::ClipboardReceived, peer_id.as_deref())
+                    .with_size(text.len() as u64)
+                    .with_content(text.clone()));
+            }
+            result
+        } else {
+            clipboard_manager.set_text(&text)
+        };
+        
+        result.map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::Clipboard, "Clipboard manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn start_clipboard_monitoring(state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let mut clipboard = lock_clipboard(&state);
+    
+    if let Some(clipboard_manager) = &mut *clipboard {
+        clipboard_manager.start_monitoring()
+            .map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::Clipboard, "Clipboard manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn stop_clipboard_monitoring(state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let mut clipboard = lock_clipboard(&state);
+    
+    if let Some(clipboard_manager) = &mut *clipboard {
+        clipboard_manager.stop_monitoring();
+        Ok(())
+    } else {
+        Err(state.feature_error(Feature::Clipboard, "Clipboard manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn configure_clipboard(config: ClipboardConfig, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let mut clipboard = lock_clipboard(&state);
+    
+    if let Some(clipboard_manager) = &mut *clipboard {
+        clipboard_manager.update_config(config)
+            .map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::Clipboard, "Clipboard manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn set_clipboard_sync_policy(policy: ClipboardSyncPolicy, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let clipboard = lock_clipboard(&state);
+    
+    if let Some(clipboard_manager) = &*clipboard {
+        clipboard_manager.set_sync_policy(policy);
+        Ok(())
+    } else {
+        Err(state.feature_error(Feature::Clipboard, "Clipboard manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn get_clipboard_sync_status(state: tauri::State<'_, AppState>) -> Result<ClipboardSyncStatus, CommandError> {
+    let clipboard = lock_clipboard(&state);
+    
+    if let Some(clipboard_manager) = &*clipboard {
+        Ok(clipboard_manager.get_sync_status())
+    } else {
+        Err(state.feature_error(Feature::Clipboard, "Clipboard manager not initialized"))
+    }
+}
+
+/// How the clipboard monitor detects changes and its current poll interval
+#[tauri::command]
+fn get_clipboard_stats(state: tauri::State<'_, AppState>) -> Result<ClipboardStats, CommandError> {
+    let clipboard = lock_clipboard(&state);
+    
+    if let Some(clipboard_manager) = &*clipboard {
+        Ok(clipboard_manager.stats())
+    } else {
+        Err(state.feature_error(Feature::Clipboard, "Clipboard manager not initialized"))
+    }
+}
+
+/// Apply an entry received from the peer, in the format of `clipboard_changed`.
+/// Peers with a clipboard key must use `sync_sealed_clipboard_entry` instead.
+#[tauri::command]
+fn sync_remote_clipboard_entry(
+    payload: String,
+    peer_id: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>
+) -> Result<RemoteSyncOutcome, CommandError> {
+    check_rate_limit(&app_handle, &state, peer_id.as_deref(), RateLimitedCommand::Clipboard)?;
+    
+    if let (Some(peer_id), Some(security_manager)) = (&peer_id, &*state.security_manager.lock().unwrap()) {
+        if security_manager.has_clipboard_key(peer_id) {
+            return Err(ClipboardError::AuthenticationFailed(
+                format!("Peer {} must send clipboard entries encrypted", peer_id)
+            ).into());
+        }
+    }
+    
+    let entry = clipboard::decode_sync_entry(&payload)?;
+    apply_remote_clipboard_entry(entry, None, peer_id.as_deref(), &app_handle, &state)
+}
+
+/// Decrypt and apply an entry the peer sealed with its clipboard key
+#[tauri::command]
+fn sync_sealed_clipboard_entry(
+    envelope: SealedSyncEntry,
+    peer_id: String,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>
+) -> Result<RemoteSyncOutcome, CommandError> {
+    check_rate_limit(&app_handle, &state, Some(&peer_id), RateLimitedCommand::Clipboard)?;
+    
+    let (payload, sent_at) = match &*state.security_manager.lock().unwrap() {
+        Some(security_manager) => security_manager.decrypt_sync_entry(&peer_id, &envelope)
+            .map_err(|e| {
+                log::warn!(target: logging::TARGET_CLIPBOARD, "Rejected sealed clipboard entry from {}: {}", peer_id, e);
+                ClipboardError::AuthenticationFailed(e.to_string())
+            })?,
+        None => return Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized")),
+    };
+    
+    let entry = clipboard::decode_sync_entry(&payload)?;
+    apply_remote_clipboard_entry(entry, Some(sent_at), Some(&peer_id), &app_handle, &state)
+}
+
+/// Send an entry in full to a peer that answered a delta with `NeedsFullEntry`:
+/// sealed if the peer has a clipboard key, otherwise as `clipboard_changed`
+#[tauri::command]
+fn resend_clipboard_entry(
+    entry_id: String,
+    peer_id: Option<String>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>
+) -> Result<(), CommandError> {
+    check_rate_limit(&app_handle, &state, peer_id.as_deref(), RateLimitedCommand::Clipboard)?;
+    
+    let payload = match &*lock_clipboard(&state) {
+        Some(clipboard_manager) => {
+            check_system_clipboard_allowed(clipboard_manager, peer_id.as_deref())?;
+            clipboard_manager.create_full_sync_entry(peer_id.as_deref(), &entry_id)?
+        },
+        None => return Err(state.feature_error(Feature::Clipboard, "Clipboard manager not initialized")),
+    };
+    
+    if let (Some(peer_id), Some(security_manager)) = (&peer_id, &*state.security_manager.lock().unwrap()) {
+        if emit_sealed_clipboard_entry(&app_handle, security_manager, peer_id, &payload) {
+            return Ok(());
+        }
+    }
+    app_handle.emit_all("clipboard_changed", payload).map_err(CommandError::from)
+}
+
+/// Apply a decoded remote entry; sealed entries also pass the freshness check
+fn apply_remote_clipboard_entry(
+    entry: ClipboardEntry,
+    sent_at: Option<chrono::DateTime<chrono::Utc>>,
+    peer_id: Option<&str>,
+    app_handle: &tauri::AppHandle,
+    state: &AppState
+) -> Result<RemoteSyncOutcome, CommandError> {
+    let mut clipboard = lock_clipboard(state);
+    
+    if let Some(clipboard_manager) = &mut *clipboard {
+        check_system_clipboard_allowed(clipboard_manager, peer_id)?;
+        
+        // Deltas are rebuilt first so the audit log and conflict checks see the text
+        let entry = match clipboard_manager.resolve_remote_entry(peer_id, entry) {
+            Ok(entry) => entry,
+            Err(ClipboardError::DeltaMismatch(reason)) => {
+                log::info!(target: logging::TARGET_CLIPBOARD, "Asking for the full clipboard entry: {}", reason);
+                return Ok(RemoteSyncOutcome::NeedsFullEntry);
+            },
+            Err(e) => return Err(e.into()),
+        };
+        let audit_event = AuditEvent::new(AuditEventKind::ClipboardReceived, peer_id)
+            .with_detail(entry.metadata.mime_type.clone())
+            .with_size(entry.metadata.size as u64)
+            .with_content(entry.data.clone());
+        let reference = (entry.content_type == ClipboardContentType::ImageReference).then(|| entry.clone());
+        
+        let outcome = match sent_at {
+            Some(sent_at) => clipboard_manager.sync_sealed_entry(peer_id, entry, sent_at)?,
+            None => clipboard_manager.sync_remote_entry(peer_id, entry)?,
+        };
+        match (outcome, reference) {
+            (RemoteSyncOutcome::Applied, _) => {
+                state.audit_log.record(audit_event);
+                notify_host(app_handle, Notification::clipboard_synced());
+            },
+            // The image follows as a file transfer and is pasted once it completes
+            (RemoteSyncOutcome::Pending, Some(reference)) => {
+                let image_offload = clipboard_manager.image_offload();
+                // Clipboard commands need not wait for the transfer to be set up
+                drop(clipboard);
+                let manager = state.transfer_manager()?;
+                tauri::async_runtime::block_on(
+                    image_offload.expect(manager, peer_id.unwrap_or(UNIDENTIFIED_PEER), reference)
+                )?;
+            },
+            _ => {},
+        }
+        Ok(outcome)
+    } else {
+        Err(state.feature_error(Feature::Clipboard, "Clipboard manager not initialized"))
+    }
+}
+
+/// Peers in `SharedOnly` mode never touch the system clipboard
+fn check_system_clipboard_allowed(clipboard_manager: &ClipboardManager, peer_id: Option<&str>) -> Result<(), ClipboardError> {
+    if clipboard_manager.shared_clipboard().mode_for(peer_id).allows_system() {
+        Ok(())
+    } else {
+        Err(ClipboardError::ContentBlocked(format!("{} only uses the shared clipboard", peer_id.unwrap_or("peer"))))
+    }
+}
+
+/// The shared clipboard, for use outside the clipboard manager lock
+fn shared_clipboard(state: &AppState) -> Result<Arc<SharedClipboard>, CommandError> {
+    match &*lock_clipboard(state) {
+        Some(clipboard_manager) => Ok(clipboard_manager.shared_clipboard()),
+        None => Err(state.feature_error(Feature::Clipboard, "Clipboard manager not initialized")),
+    }
+}
+
+/// Put a text into a peer's shared clipboard and emit it for the peer as
+/// `shared_clipboard_changed`; the system clipboard and history stay untouched
+#[tauri::command]
+fn set_shared_clipboard(
+    peer_id: String,
+    key: String,
+    text: String,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>
+) -> Result<ClipboardEntry, CommandError> {
+    let entry = shared_clipboard(&state)?.set(&peer_id, &key, &text)?;
+    let payload = clipboard::encode_sync_entry(&entry)?;
+    
+    let mut change = SharedClipboardChange { peer_id: peer_id.clone(), key, origin: "local", payload: None, envelope: None };
+    match &*state.security_manager.lock().unwrap() {
+        Some(security_manager) if security_manager.has_clipboard_key(&peer_id) => {
+            change.envelope = Some(security_manager.encrypt_sync_entry(&peer_id, &payload)?);
+        },
+        _ => change.payload = Some(payload),
+    }
+    
+    state.audit_log.record(AuditEvent::new(AuditEventKind::ClipboardSent, Some(&peer_id))
+        .with_detail(entry.metadata.mime_type.clone())
+        .with_size(entry.metadata.size as u64)
+        .with_content(text));
+    app_handle.emit_all("shared_clipboard_changed", change)?;
+    Ok(entry)
+}
+
+#[tauri::command]
+fn get_shared_clipboard(peer_id: String, key: String, state: tauri::State<'_, AppState>) -> Result<ClipboardEntry, CommandError> {
+    shared_clipboard(&state)?.get(&peer_id, &key)
+        .ok_or_else(|| ClipboardError::EntryNotFound(key).into())
+}
+
+/// All entries of a peer's shared clipboard, sorted by key
+#[tauri::command]
+fn list_shared_clipboard(peer_id: String, state: tauri::State<'_, AppState>) -> Result<Vec<ClipboardEntry>, CommandError> {
+    Ok(shared_clipboard(&state)?.list(&peer_id))
+}
+
+/// Apply a shared clipboard entry from the peer: `envelope` if the peer has a
+/// clipboard key, otherwise `payload` in the format of `clipboard_changed`.
+/// Returns false if a newer entry under the same key is already there.
+#[tauri::command]
+fn sync_shared_clipboard_entry(
+    peer_id: String,
+    payload: Option<String>,
+    envelope: Option<SealedSyncEntry>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>
+) -> Result<bool, CommandError> {
+    check_rate_limit(&app_handle, &state, Some(&peer_id), RateLimitedCommand::Clipboard)?;
+    
+    let payload = {
+        let security = state.security_manager.lock().unwrap();
+        let has_key = security.as_ref().is_some_and(|manager| manager.has_clipboard_key(&peer_id));
+        match (envelope, payload) {
+            (Some(envelope), _) => match &*security {
+                Some(security_manager) => security_manager.decrypt_sync_entry(&peer_id, &envelope)
+                    .map(|(payload, _)| payload)
+                    .map_err(|e| {
+                        log::warn!(target: logging::TARGET_CLIPBOARD, "Rejected sealed shared clipboard entry from {}: {}", peer_id, e);
+                        ClipboardError::AuthenticationFailed(e.to_string())
+                    })?,
+                None => return Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized")),
+            },
+            (None, Some(_)) if has_key => return Err(ClipboardError::AuthenticationFailed(
+                format!("Peer {} must send clipboard entries encrypted", peer_id)
+            ).into()),
+            (None, Some(payload)) => payload,
+            (None, None) => return Err(ClipboardError::InvalidFormat("Neither payload nor envelope given".to_string()).into()),
+        }
+    };
+    
+    let entry = clipboard::decode_sync_entry(&payload)?;
+    let key = entry.id.clone();
+    let audit_event = AuditEvent::new(AuditEventKind::ClipboardReceived, Some(&peer_id))
+        .with_detail(entry.metadata.mime_type.clone())
+        .with_size(entry.metadata.size as u64)
+        .with_content(entry.data.clone());
+    
+    let applied = shared_clipboard(&state)?.apply_remote(&peer_id, entry)?;
+    if applied {
+        state.audit_log.record(audit_event);
+        app_handle.emit_all("shared_clipboard_changed", SharedClipboardChange { peer_id, key, origin: "remote", payload: None, envelope: None })?;
+    }
+    Ok(applied)
+}
+
+/// Choose which clipboard a peer uses; without `peer_id` the default for
+/// peers without their own mode. The mode outlives a disconnect.
+#[tauri::command]
+fn set_clipboard_connection_mode(mode: ClipboardConnectionMode, peer_id: Option<String>, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    log::info!(target: logging::TARGET_CLIPBOARD, "Clipboard mode for {} set to {:?}", peer_id.as_deref().unwrap_or("all peers"), mode);
+    shared_clipboard(&state)?.set_mode(peer_id.as_deref(), mode);
+    Ok(())
+}
+
+#[tauri::command]
+fn get_clipboard_connection_mode(peer_id: Option<String>, state: tauri::State<'_, AppState>) -> Result<ClipboardConnectionMode, CommandError> {
+    Ok(shared_clipboard(&state)?.mode_for(peer_id.as_deref()))
+}
+
+/// Start the clipboard key exchange with a peer; returns our public key (base64).
+/// Called again on every reconnect, which replaces the previous key.
+#[tauri::command]
+fn begin_clipboard_key_exchange(peer_id: String, state: tauri::State<'_, AppState>) -> Result<String, CommandError> {
+    match &*state.security_manager.lock().unwrap() {
+        Some(security_manager) => Ok(security_manager.begin_clipboard_key_exchange(&peer_id)),
+        None => Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized")),
+    }
+}
+
+/// Finish the clipboard key exchange with the peer's public key
+#[tauri::command]
+fn complete_clipboard_key_exchange(peer_id: String, public_key: String, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    match &*state.security_manager.lock().unwrap() {
+        Some(security_manager) => security_manager.complete_clipboard_key_exchange(&peer_id, &public_key)
+            .map_err(CommandError::from),
+        None => Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized")),
+    }
+}
+
+/// Send a chat message; it leaves sealed as `chat_outgoing`
+#[tauri::command]
+fn send_chat_message(peer_id: String, text: String, state: tauri::State<'_, AppState>) -> Result<ChatMessage, CommandError> {
+    state.chat.send_message(&peer_id, &text)
+        .map_err(CommandError::from)
+}
+
+/// The last `limit` messages with a connected peer, oldest first
+#[tauri::command]
+fn get_chat_history(peer_id: String, limit: Option<usize>, state: tauri::State<'_, AppState>) -> Vec<ChatMessage> {
+    state.chat.history(&peer_id, limit)
+}
+
+/// Tell the peer the user started or stopped typing; false if the notice
+/// was held back by the rate limit
+#[tauri::command]
+fn send_chat_typing(peer_id: String, typing: bool, state: tauri::State<'_, AppState>) -> Result<bool, CommandError> {
+    state.chat.send_typing(&peer_id, typing)
+        .map_err(CommandError::from)
+}
+
+/// Open and handle a chat frame the peer sealed with its clipboard key
+#[tauri::command]
+fn receive_chat_frame(envelope: SealedSyncEntry, peer_id: String, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let (payload, sent_at) = match &*state.security_manager.lock().unwrap() {
+        Some(security_manager) => security_manager.decrypt_sync_entry(&peer_id, &envelope)
+            .map_err(|e| {
+                log::warn!(target: logging::TARGET_CHAT, "Rejected chat frame from {}: {}", peer_id, e);
+                CommandError::from(e)
+            })?,
+        None => return Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized")),
+    };
+    
+    state.chat.receive(&peer_id, &payload, sent_at)
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn pin_clipboard_entry(entry_id: String, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let clipboard = lock_clipboard(&state);
+    
+    if let Some(clipboard_manager) = &*clipboard {
+        clipboard_manager.pin_entry(&entry_id)
+            .map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::Clipboard, "Clipboard manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn unpin_clipboard_entry(entry_id: String, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let clipboard = lock_clipboard(&state);
+    
+    if let Some(clipboard_manager) = &*clipboard {
+        clipboard_manager.unpin_entry(&entry_id)
+            .map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::Clipboard, "Clipboard manager not initialized"))
+    }
+}
+
+/// Clear the clipboard history in memory and overwrite it on disk. Pinned
+/// entries are wiped too unless `keep_pinned` is set.
+#[tauri::command]
+fn wipe_clipboard_history(keep_pinned: Option<bool>, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let clipboard = lock_clipboard(&state);
+    
+    if let Some(clipboard_manager) = &*clipboard {
+        clipboard_manager.wipe_history(keep_pinned.unwrap_or(false))
+            .map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::Clipboard, "Clipboard manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn search_clipboard_history(
+    query: String,
+    content_type: Option<ClipboardContentType>,
+    state: tauri::State<'_, AppState>
+) -> Result<Vec<ClipboardEntry>, CommandError> {
+    let clipboard = lock_clipboard(&state);
+    
+    if let Some(clipboard_manager) = &*clipboard {
+        Ok(clipboard_manager.search_history(&query, content_type))
+    } else {
+        Err(state.feature_error(Feature::Clipboard, "Clipboard manager not initialized"))
+    }
+}
+
+/// Put an older history entry back on the live clipboard
+#[tauri::command]
+fn select_clipboard_history_entry(entry_id: String, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let mut clipboard = lock_clipboard(&state);
+    
+    if let Some(clipboard_manager) = &mut *clipboard {
+        clipboard_manager.select_history_entry(&entry_id)
+            .map_err(CommandError::from)
+    } else {
+        Err(state.feature_error(Feature::Clipboard, "Clipboard manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn set_transfer_bandwidth_limit(
+    transfer_id: Option<String>,
+    bytes_per_sec: Option<u64>,
+    state: tauri::State<'_, AppState>
+) -> Result<(), CommandError> {
+    let manager = state.transfer_manager()?;
+    manager.set_bandwidth_limit(transfer_id.as_deref(), bytes_per_sec)
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn set_transfer_bandwidth_auto(
+    link_capacity_bytes_per_sec: u64,
+    headroom_percent: u32,
+    state: tauri::State<'_, AppState>
+) -> Result<(), CommandError> {
+    let manager = state.transfer_manager()?;
+    manager.set_auto_bandwidth(AutoBandwidthConfig {
+        link_capacity_bytes_per_sec,
+        headroom_percent,
+    });
+    Ok(())
+}
+
+#[tauri::command]
+fn get_transfer_queue(state: tauri::State<'_, AppState>) -> Result<Vec<TransferQueueEntry>, CommandError> {
+    let manager = state.transfer_manager()?;
+    Ok(manager.get_transfer_queue())
+}
+
+#[tauri::command]
+fn reorder_transfer_queue(transfer_id: String, position: usize, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let manager = state.transfer_manager()?;
+    manager.reorder_transfer_queue(&transfer_id, position)
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_transfer_history(query: Option<TransferHistoryQuery>, state: tauri::State<'_, AppState>) -> Result<TransferHistoryPage, CommandError> {
+    let manager = state.transfer_manager()?;
+    Ok(manager.get_transfer_history(&query.unwrap_or_default()))
+}
+
+/// Signed receipt of a completed transfer
+#[tauri::command]
+fn get_transfer_receipt(transfer_id: String, state: tauri::State<'_, AppState>) -> Result<TransferReceipt, CommandError> {
+    let manager = state.transfer_manager()?;
+    manager.get_transfer_receipt(&transfer_id).map_err(CommandError::from)
+}
+
+/// Checks an exported receipt, with `public_key` also who issued it; returns
+/// the issuer's fingerprint. Needs no transfer manager.
+#[tauri::command]
+fn verify_transfer_receipt(receipt: String, public_key: Option<String>) -> Result<String, CommandError> {
+    TransferReceipt::from_json(&receipt)?
+        .verify(public_key.as_deref())
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn clear_transfer_history(older_than_days: Option<u32>, state: tauri::State<'_, AppState>) -> Result<usize, CommandError> {
+    let manager = state.transfer_manager()?;
+    Ok(manager.clear_history(older_than_days))
+}
+
+#[tauri::command]
+fn get_storage_usage(state: tauri::State<'_, AppState>) -> Result<StorageUsage, CommandError> {
+    let manager = state.transfer_manager()?;
+    Ok(manager.get_storage_usage())
+}
+
+#[tauri::command]
+fn purge_staging(state: tauri::State<'_, AppState>) -> Result<PurgeReport, CommandError> {
+    let manager = state.transfer_manager()?;
+    Ok(manager.purge_staging())
+}
+
+#[tauri::command]
+fn handle_remote_file_drop(
+    peer_id: String,
+    transfer_id: String,
+    x: i32,
+    y: i32,
+    monitor_index: Option<usize>,
+    state: tauri::State<'_, AppState>
+) -> Result<String, CommandError> {
+    // Viewer coordinates are monitor-relative, like pointer input events
+    let (x, y) = calculate_absolute_position(x, y, monitor_index, &current_input_monitors(&state));
+    let manager = state.transfer_manager()?;
+    let file_drop = tauri::async_runtime::block_on(
+        manager.handle_remote_file_drop(&peer_id, &transfer_id, DropPosition { x, y })
+    )?;
+    Ok(file_drop.drop_id)
+}
+
+/// Ask the sender for a preview of an offered file; it arrives as a `transfer_preview` event
+#[tauri::command]
+fn request_transfer_preview(transfer_id: String, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let manager = state.transfer_manager()?;
+    tauri::async_runtime::block_on(manager.request_transfer_preview(&transfer_id))
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_file_drop_config(state: tauri::State<'_, AppState>) -> Result<DropConfig, CommandError> {
+    let manager = state.transfer_manager()?;
+    Ok(manager.get_drop_config())
+}
+
+#[tauri::command]
+fn set_file_drop_config(config: DropConfig, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let manager = state.transfer_manager()?;
+    manager.set_drop_config(config);
+    Ok(())
+}
+
+#[tauri::command]
+fn initialize_security(secret_key: String, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let security_config = state.settings.lock().unwrap().security.clone();
+    let mut security_manager = ConnectionSecurityManager::new(&secret_key, security_config);
+    security_manager.set_audit_sink(state.audit_log.clone());
+    if let Some(data_dir) = state.app_handle.path_resolver().app_data_dir() {
+        match DeviceIdentity::load_or_create(&data_dir) {
+            Ok(identity) => security_manager.set_identity(identity),
+            Err(e) => log::error!(target: logging::TARGET_SECURITY, "Failed to load device identity: {}", e),
+        }
+    }
+    
+    let mut app_security = state.security_manager.lock().unwrap();
+    *app_security = Some(security_manager);
+    
+    Ok(())
+}
+
+#[tauri::command]
+fn generate_access_token(peer_info: PeerInfo, ttl_seconds: u64, state: tauri::State<'_, AppState>) -> Result<String, CommandError> {
+    let security = state.security_manager.lock().unwrap();
+    
+    if let Some(security_manager) = &*security {
+        security_manager.generate_access_token(peer_info, std::time::Duration::from_secs(ttl_seconds))
+            .map_err(CommandError::from)
+    } else {
+        Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn validate_access_token(token: String, state: tauri::State<'_, AppState>) -> Result<AccessTokenClaims, CommandError> {
+    let security = state.security_manager.lock().unwrap();
+    
+    if let Some(security_manager) = &*security {
+        security_manager.validate_access_token(&token)
+            .map_err(CommandError::from)
+    } else {
+        Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn revoke_token(token: String, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let security = state.security_manager.lock().unwrap();
+    
+    if let Some(security_manager) = &*security {
+        security_manager.revoke_token(&token)
+            .map_err(CommandError::from)
+    } else {
+        Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn rotate_token_key(grace_period_seconds: u64, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let security = state.security_manager.lock().unwrap();
+    
+    if let Some(security_manager) = &*security {
+        security_manager.rotate_token_key(std::time::Duration::from_secs(grace_period_seconds))
+            .map_err(CommandError::from)
+    } else {
+        Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn set_room_password(password: String, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let security = state.security_manager.lock().unwrap();
+    
+    if let Some(security_manager) = &*security {
+        security_manager.set_room_password(&password)
+            .map_err(CommandError::from)
+    } else {
+        Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn set_custom_commands_allowed(allowed: bool, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let security = state.security_manager.lock().unwrap();
+    
+    if let Some(security_manager) = &*security {
+        security_manager.set_custom_commands_allowed(allowed);
+        Ok(())
+    } else {
+        Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn clear_room_password(state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    let security = state.security_manager.lock().unwrap();
+    
+    if let Some(security_manager) = &*security {
+        security_manager.clear_room_password();
+        Ok(())
+    } else {
+        Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized"))
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct RoomPasswordFailedPayload {
+    peer_id: String,
+    failed_attempts: u32,
+    locked_for_seconds: Option<u64>,
+}
+
+#[tauri::command]
+fn verify_room_password(
+    peer_id: String,
+    attempt: String,
+    window: Window,
+    state: tauri::State<'_, AppState>
+) -> Result<bool, CommandError> {
+    let security = state.security_manager.lock().unwrap();
+    
+    if let Some(security_manager) = &*security {
+        let result = security_manager.verify_room_password(&peer_id, &attempt);
+        
+        // Let the host see brute-force activity
+        let locked_for_seconds = match &result {
+            Ok(false) => Some(None),
+            Err(connection_security::SecurityError::TooManyAttempts(secs)) => Some(Some(*secs)),
+            _ => None,
+        };
+        if let Ok(true) = result {
+            let rights = security_manager.get_peer_access_rights(&peer_id);
+            state.audit_log.record(AuditEvent::new(AuditEventKind::PermissionGranted, Some(&peer_id))
+                .with_detail(format!("{:?}", rights)));
+        }
+        if let Some(locked_for_seconds) = locked_for_seconds {
+            let _ = window.emit("room_password_failed", RoomPasswordFailedPayload {
+                peer_id: peer_id.clone(),
+                failed_attempts: security_manager.failed_room_password_attempts(&peer_id),
+                locked_for_seconds,
+            });
+        }
+        
+        result.map_err(CommandError::from)
+    } else {
+        Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized"))
+    }
+}
+
+/// The frontend owns the WebRTC connection and reports peers joining or leaving
+/// Persist the trusted peer list without re-applying the other settings,
+/// which would reset state such as the room password
+fn store_trusted_peers(state: &AppState, trusted_peers: Vec<TrustedPeer>) -> Result<Vec<TrustedPeer>, CommandError> {
+    let mut settings = state.settings.lock().unwrap().clone();
+    settings.security.trusted_peers = trusted_peers.clone();
+    if let Some(store) = &state.settings_store {
+        store.save(&settings)?;
+    }
+    *state.settings.lock().unwrap() = settings;
+    Ok(trusted_peers)
+}
+
+#[tauri::command]
+fn get_identity_fingerprint(state: tauri::State<'_, AppState>) -> Result<String, CommandError> {
+    let security = state.security_manager.lock().unwrap();
+    
+    if let Some(security_manager) = &*security {
+        security_manager.get_identity_fingerprint()
+            .map_err(CommandError::from)
+    } else {
+        Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn create_identity_challenge(peer_id: String, state: tauri::State<'_, AppState>) -> Result<IdentityChallenge, CommandError> {
+    let security = state.security_manager.lock().unwrap();
+    
+    if let Some(security_manager) = &*security {
+        security_manager.create_identity_challenge(&peer_id)
+            .map_err(CommandError::from)
+    } else {
+        Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized"))
+    }
+}
+
+/// Sign a remote host's challenge with this device's identity key
+#[tauri::command]
+fn answer_identity_challenge(nonce: String, state: tauri::State<'_, AppState>) -> Result<IdentityProof, CommandError> {
+    let security = state.security_manager.lock().unwrap();
+    
+    if let Some(security_manager) = &*security {
+        security_manager.answer_identity_challenge(&nonce, &connection_security::identity::device_name())
+            .map_err(CommandError::from)
+    } else {
+        Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn verify_peer_identity(peer_id: String, proof: IdentityProof, state: tauri::State<'_, AppState>) -> Result<VerifiedPeer, CommandError> {
+    let security = state.security_manager.lock().unwrap();
+    
+    if let Some(security_manager) = &*security {
+        security_manager.verify_identity_proof(&peer_id, &proof)
+            .map_err(CommandError::from)
+    } else {
+        Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized"))
+    }
+}
+
+#[tauri::command]
+fn list_trusted_peers(state: tauri::State<'_, AppState>) -> Result<Vec<TrustedPeer>, CommandError> {
+    let security = state.security_manager.lock().unwrap();
+    
+    if let Some(security_manager) = &*security {
+        Ok(security_manager.trusted_peers())
+    } else {
+        Err(CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized"))
+    }
+}
+
+/// Remember a verified peer so later sessions skip the room password
+#[tauri::command]
+fn trust_peer(
+    peer_id: String,
+    nickname: Option<String>,
+    access_rights: Option<Vec<AccessRight>>,
+    state: tauri::State<'_, AppState>
+) -> Result<TrustedPeer, CommandError> {
+    let (trusted, trusted_peers) = {
+        let security = state.security_manager.lock().unwrap();
+        let security_manager = security.as_ref()
+            .ok_or_else(|| CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized"))?;
+        let trusted = security_manager.trust_peer(&peer_id, nickname, access_rights.unwrap_or_default())?;
+        (trusted, security_manager.trusted_peers())
+    };
+    
+    store_trusted_peers(&state, trusted_peers)?;
+    state.audit_log.record(AuditEvent::new(AuditEventKind::PeerTrusted, Some(&peer_id))
+        .with_detail(trusted.fingerprint.clone()));
+    Ok(trusted)
+}
+
+#[tauri::command]
+fn revoke_peer(fingerprint: String, state: tauri::State<'_, AppState>) -> Result<Vec<TrustedPeer>, CommandError> {
+    let trusted_peers = {
+        let security = state.security_manager.lock().unwrap();
+        let security_manager = security.as_ref()
+            .ok_or_else(|| CommandError::new(ErrorKind::SecurityNotInitialized, "Security manager not initialized"))?;
+        security_manager.revoke_peer(&fingerprint)?;
+        security_manager.trusted_peers()
+    };
+    
+    state.audit_log.record(AuditEvent::new(AuditEventKind::PeerUntrusted, None)
+        .with_detail(fingerprint.clone()));
+    store_trusted_peers(&state, trusted_peers)
+}
+
+/// A peer the frontend connected without the backend signaling client,
+/// which registers its own peers
+#[tauri::command]
+fn register_peer(peer_id: String, name: Option<String>, app_handle: tauri::AppHandle, state: tauri::State<'_, AppState>) {
+    let change = state.peers.register(&peer_id, name, PeerSource::Frontend, chrono::Utc::now());
+    apply_peer_changes(&app_handle, &state, change.into_iter().collect());
+}
+
+#[tauri::command]
+fn unregister_peer(peer_id: String, app_handle: tauri::AppHandle, state: tauri::State<'_, AppState>) {
+    let change = state.peers.unregister(&peer_id);
+    apply_peer_changes(&app_handle, &state, change.into_iter().collect());
+}
+
+/// Connected peers with their access rights and last reported stream statistics
+#[tauri::command]
+fn get_connected_peers(state: tauri::State<'_, AppState>) -> Vec<ConnectedPeer> {
+    state.peers.list().into_iter()
+        .map(|peer| with_access_rights(&state, peer))
+        .collect()
+}
+
+fn with_access_rights(state: &AppState, mut peer: ConnectedPeer) -> ConnectedPeer {
+    if let Some(security_manager) = &*state.security_manager.lock().unwrap() {
+        peer.access_rights = security_manager.get_peer_access_rights(&peer.peer_id);
+    }
+    peer
+}
+
+/// Everything that follows a peer joining or leaving: the frontend, the
+/// audit log, notifications, input control, the clipboard and the frame's
+/// viewer count
+fn apply_peer_changes(app_handle: &tauri::AppHandle, state: &AppState, changes: Vec<PeerChange>) {
+    if changes.is_empty() {
+        return;
+    }
+    
+    for change in changes {
+        match change {
+            PeerChange::Connected(peer) => {
+                state.audit_log.record(AuditEvent::new(AuditEventKind::PeerConnected, Some(&peer.peer_id)));
+                notify_host(app_handle, Notification::peer_connected(&peer.peer_id));
+                emit_peer_change(app_handle, "peer_connected", with_access_rights(state, peer));
+            },
+            PeerChange::Disconnected(peer) => {
+                state.audit_log.record(AuditEvent::new(AuditEventKind::PeerDisconnected, Some(&peer.peer_id)));
+                
+                // A peer that drops mid-keystroke never sends its key releases, and control returns to the host
+                release_held_keys(app_handle, "peer_disconnected");
+                if state.input_arbiter.lock().unwrap().peer_disconnected(&peer.peer_id) {
+                    emit_control_changed(app_handle, state);
+                }
+                
+                // Keys belong to one connection; a reconnecting peer negotiates a new one.
+                // The shared clipboard lives only as long as the connection, too.
+                if let Some(security_manager) = &*state.security_manager.lock().unwrap() {
+                    security_manager.forget_clipboard_key(&peer.peer_id);
+                }
+                clear_shared_clipboard(app_handle, state, &peer.peer_id);
+                state.chat.end_session(&peer.peer_id);
+                if let Some(capture_manager) = lock_screen_capture(state).as_mut() {
+                    capture_manager.remove_viewer_viewport(&peer.peer_id);
+                }
+                emit_peer_change(app_handle, "peer_disconnected", peer);
+            },
+        }
+    }
+    
+    // The frame's badge counts the viewers
+    if state.sharing_indicator.lock().unwrap().set_viewers(state.peers.count()) {
+        sync_sharing_indicator(state);
+    }
+}
+
+fn emit_peer_change(app_handle: &tauri::AppHandle, event: &str, peer: ConnectedPeer) {
+    if let Err(e) = app_handle.emit_all(event, peer) {
+        log::error!(target: logging::TARGET_SIGNALING, "Failed to emit {}: {}", event, e);
+    }
+}
+
+/// Without ICE servers in `config` the session uses the stored ones
+#[tauri::command]
+fn connect_signaling(mut config: SignalingConfig, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    if config.ice_servers.is_empty() {
+        config.ice_servers = ice_store().load(&state.settings.lock().unwrap().ice);
+    } else {
+        ice::validate(&config.ice_servers)?;
+    }
+    state.signaling.lock().unwrap().connect(config)
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn disconnect_signaling(state: tauri::State<'_, AppState>) {
+    state.signaling.lock().unwrap().disconnect();
+}
+
+#[tauri::command]
+fn send_signaling_message(message: ClientMessage, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    state.signaling.lock().unwrap().send(message)
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn get_signaling_status(state: tauri::State<'_, AppState>) -> SignalingStatus {
+    state.signaling.lock().unwrap().status()
+}
+
+fn ice_store() -> IceStore {
+    IceStore::new(Arc::new(SystemCommandRunner))
+}
+
+/// STUN/TURN servers with their credentials from the keyring
+#[tauri::command]
+fn get_ice_servers(state: tauri::State<'_, AppState>) -> Vec<IceServer> {
+    ice_store().load(&state.settings.lock().unwrap().ice)
+}
+
+/// Replace the STUN/TURN servers; credentials go to the keyring, the rest to the settings
+#[tauri::command]
+fn set_ice_servers(servers: Vec<IceServer>, state: tauri::State<'_, AppState>) -> Result<Vec<IceServer>, CommandError> {
+    let mut settings = state.settings.lock().unwrap().clone();
+    settings.ice = ice_store().save(&settings.ice, &servers)?;
+    if let Some(store) = &state.settings_store {
+        store.save(&settings)?;
+    }
+    *state.settings.lock().unwrap() = settings;
+    log::info!(target: logging::TARGET_SIGNALING, "Stored {} ICE servers", servers.len());
+    Ok(servers)
+}
+
+/// Binding request against `url`, plus a TURN allocation with credentials.
+/// Without `username` and `credential` those of the stored server are used.
+#[tauri::command]
+fn test_ice_server(
+    url: String,
+    username: Option<String>,
+    credential: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<IceProbeResult, CommandError> {
+    let (username, credential) = match (username, credential) {
+        (Some(username), Some(credential)) => (Some(username), Some(credential)),
+        _ => match ice_store().find(&state.settings.lock().unwrap().ice, &url) {
+            Some(server) => (server.username, server.credential),
+            None => (None, None),
+        },
+    };
+    let credentials = username.as_deref().zip(credential.as_deref());
+    
+    let result = tauri::async_runtime::block_on(ice::probe::probe(&url, credentials, ice::probe::PROBE_TIMEOUT))?;
+    log::info!(target: logging::TARGET_SIGNALING, "ICE server {} reachable: {}", url, result.reachable);
+    Ok(result)
+}
+
+#[tauri::command]
+fn get_connection_quality(state: tauri::State<'_, AppState>) -> ConnectionQualityReport {
+    state.connection_quality.lock().unwrap().report()
+}
+
+/// WebRTC round trip and loss plus the measured input round trip, from the
+/// frontend; with `peer_id` also kept as that peer's stream statistics
+#[tauri::command]
+fn report_network_stats(report: NetworkReport, peer_id: Option<String>, state: tauri::State<'_, AppState>) {
+    if let Some(peer_id) = &peer_id {
+        if !state.peers.report_network(peer_id, &report, chrono::Utc::now()) {
+            log::debug!(target: logging::TARGET_SIGNALING, "Network stats for unknown peer {}", peer_id);
+        }
+    }
+    state.connection_quality.lock().unwrap().report_network(report, stats::now_ms());
+}
+
+/// The frontend read `clientTimestamp` (ms on its clock) when `clock_sync` `syncId` arrived
+#[tauri::command]
+fn report_clock_sync(sync_id: u64, client_timestamp: f64, state: tauri::State<'_, AppState>) -> bool {
+    state.frame_latency.lock().unwrap().complete_clock_sync(sync_id, client_timestamp, screen_capture::clock::monotonic_us())
+}
+
+/// The frontend rendered a frame of the frame stream at `clientTimestamp` (ms on its clock)
+#[tauri::command]
+fn report_frame_rendered(frame_id: u64, client_timestamp: f64, state: tauri::State<'_, AppState>) -> bool {
+    state.frame_latency.lock().unwrap().frame_rendered(frame_id, client_timestamp, screen_capture::clock::monotonic_us())
+}
+
+#[tauri::command]
+fn get_latency_breakdown(state: tauri::State<'_, AppState>) -> LatencyBreakdown {
+    state.frame_latency.lock().unwrap().breakdown(screen_capture::clock::monotonic_us())
+}
+
+/// Emits `clock_sync` right away and then once per interval, so the offset
+/// of the frontend's clock follows its drift
+fn start_clock_sync(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        let state = app_handle.state::<AppState>();
+        let sync = state.frame_latency.lock().unwrap()
+            .begin_clock_sync(screen_capture::clock::monotonic_us(), stats::now_ms());
+        if let Err(e) = app_handle.emit_all("clock_sync", sync) {
+            log::error!("Failed to emit clock sync: {}", e);
+        }
+        std::thread::sleep(stats::CLOCK_SYNC_INTERVAL);
+    });
+}
+
+/// Samples the connection quality once per interval and emits `connection_quality`
+fn start_quality_sampler(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(stats::SAMPLE_INTERVAL);
+        
+        let state = app_handle.state::<AppState>();
+        let captures: Vec<_> = match &mut *state.screen_capture.lock().unwrap() {
+            Some(capture_manager) => {
+                capture_manager.record_stats_sample(stats::now_ms());
+                capture_manager.get_all_stats().into_iter()
+                .filter_map(|stats| {
+                    let config = capture_manager.get_config(Some(stats.monitor_index))?;
+                    Some((stats, config))
+                })
+                .collect()
+            },
+            None => Vec::new(),
+        };
+        
+        let quality = state.connection_quality.lock().unwrap().sample(&captures, stats::now_ms());
+        if let Some(quality) = quality {
+            if let Err(e) = app_handle.emit_all("connection_quality", quality) {
+                log::error!("Failed to emit connection quality: {}", e);
+            }
+        }
+    });
+}
+
+/// Fits the captures to the viewers' viewports once they settled and emits
+/// `quality_changed` for every capture whose size changed
+fn start_viewport_fit(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        
+        let state = app_handle.state::<AppState>();
+        let changes = state.screen_capture.lock().unwrap().as_mut()
+            .map(|manager| manager.apply_viewer_viewports(std::time::Instant::now()))
+            .unwrap_or_default();
+        for change in changes {
+            if let Err(e) = app_handle.emit_all("quality_changed", change) {
+                log::error!(target: logging::TARGET_SCREEN_CAPTURE, "Failed to emit quality_changed: {}", e);
+            }
+        }
+    });
+}
+
+/// Shows a desktop notification for remote activity, subject to the notification settings
+fn notify_host(app_handle: &tauri::AppHandle, notification: Notification) {
+    let state = match app_handle.try_state::<AppState>() {
+        Some(state) => state,
+        None => return,
+    };
+    let shown = state.notifier.lock().unwrap()
+        .notify(notification, std::time::Instant::now(), chrono::Local::now().time());
+    show_notifications(app_handle, &shown);
+}
+
+fn show_notifications(app_handle: &tauri::AppHandle, notifications: &[Notification]) {
+    let identifier = &app_handle.config().tauri.bundle.identifier;
+    for notification in notifications {
+        if let Err(e) = notifications::show(identifier, notification) {
+            log::warn!("Failed to show notification: {}", e);
+        }
+    }
+}
+
+/// Shows the summary of a notification flood once its debounce window has ended
+fn start_notification_flush(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        
+        let state = app_handle.state::<AppState>();
+        let shown = state.notifier.lock().unwrap()
+            .flush(std::time::Instant::now(), chrono::Local::now().time());
+        show_notifications(&app_handle, &shown);
+    });
+}
+
+/// Sends what previewed peers would have done, batched so typing does not flood the UI
+fn start_input_preview_flush(app_handle: tauri::AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(PREVIEW_INTERVAL);
+        
+        let state = app_handle.state::<AppState>();
+        for batch in state.input_preview.take_batches() {
+            if let Err(e) = app_handle.emit_all("input_preview", batch) {
+                log::error!(target: logging::TARGET_INPUT, "Failed to emit input_preview: {}", e);
+            }
+        }
+    });
+}
+
+/// Turns file transfer events into notifications about incoming and finished files
+/// and pastes clipboard images a peer sent as a file transfer. Runs on its own
+/// thread, as the clipboard bridge blocks on the file transfer manager.
+fn start_transfer_notifications(app_handle: tauri::AppHandle, mut events: tokio::sync::mpsc::UnboundedReceiver<TransferEvent>) {
+    std::thread::spawn(move || {
+        let mut file_names = std::collections::HashMap::new();
+        while let Some(event) = events.blocking_recv() {
+            // Clipboard images are no files the host needs to hear about
+            if bridge_clipboard_image(&app_handle, &event) {
+                continue;
+            }
+            
+            // Offers and their previews let the frontend decide about accepting
+            let ui_event = match &event {
+                TransferEvent::TransferRequested { .. } => Some("transfer_requested"),
+                TransferEvent::TransferPreview { .. } => Some("transfer_preview"),
+                _ => None,
+            };
+            if let Some(name) = ui_event {
+                if let Err(e) = app_handle.emit_all(name, &event) {
+                    log::error!(target: logging::TARGET_TRANSFER, "Failed to emit {}: {}", name, e);
+                }
+            }
+            
+            match event {
+                TransferEvent::TransferRequested { transfer_id, peer_id, file_metadata, .. } => {
+                    notify_host(&app_handle, Notification::transfer_requested(&peer_id, &file_metadata.name));
+                    file_names.insert(transfer_id, file_metadata.name);
+                },
+                TransferEvent::TransferStarted { transfer_id, file_metadata, .. } => {
+                    file_names.insert(transfer_id, file_metadata.name);
+                },
+                TransferEvent::TransferCompleted { transfer_id } => {
+                    if let Some(name) = file_names.remove(&transfer_id) {
+                        notify_host(&app_handle, Notification::transfer_completed(&name));
+                    }
+                },
+                TransferEvent::TransferRejected { transfer_id, .. }
+                | TransferEvent::TransferCancelled { transfer_id }
+                | TransferEvent::TransferFailed { transfer_id, .. } => {
+                    file_names.remove(&transfer_id);
+                },
+                _ => {},
+            }
+        }
+    });
+}
+
+/// Starts, restarts or stops the metrics endpoint to match `config`
+fn configure_metrics(state: &AppState, config: &MetricsConfig) -> Result<(), CommandError> {
+    let mut metrics_server = state.metrics_server.lock().unwrap();
+    
+    let unchanged = match &*metrics_server {
+        Some(server) => config.enabled && server.config() == config,
+        None => !config.enabled,
+    };
+    if unchanged {
+        return Ok(());
+    }
+    
+    if let Some(mut server) = metrics_server.take() {
+        server.stop();
+    }
+    if config.enabled {
+        // Gathered from the managers on each scrape
+        let screen_capture = state.screen_capture.clone();
+        let file_transfer = state.file_transfer.clone();
+        let signaling = state.signaling.clone();
+        let source = Arc::new(move || MetricsSnapshot {
+            captures: screen_capture.lock().unwrap().as_ref()
+                .map(|capture_manager| capture_manager.get_all_stats())
+                .unwrap_or_default(),
+            active_transfers: file_transfer.as_ref()
+                .map(|file_transfer| file_transfer.get_stats().active_transfers)
+                .unwrap_or(0),
+            connected_peers: signaling.lock().unwrap().status().peers.len(),
+        });
+        let server = MetricsServer::start(config, source)
+            .map_err(|e| CommandError::new(
+                ErrorKind::MetricsBindFailed,
+                format!("Failed to start metrics endpoint on {}:{}: {}", config.bind_address, config.port, e),
+            ))?;
+        *metrics_server = Some(server);
+    }
+    Ok(())
+}
+
+/// Connection and capture state printed by `smoldesk status`
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InstanceStatus {
+    signaling: SignalingStatus,
+    captures: Vec<CaptureStats>,
+    active_transfers: usize,
+}
+
+/// Runs a control socket command against the managers, like the matching Tauri command
+fn handle_control_command(app_handle: &tauri::AppHandle, command: ControlCommand) -> Result<serde_json::Value, CommandError> {
+    let state = app_handle.state::<AppState>();
+    match command {
+        ControlCommand::SendFile { path, peer_id } => {
+            let manager = state.transfer_manager()?;
+            let transfer_id = tauri::async_runtime::block_on(
+                manager.start_upload(&path, &peer_id, None, TransferPriority::default())
+            )?;
+            Ok(serde_json::json!({ "transferId": transfer_id }))
+        },
+        ControlCommand::SetClipboardText { text } => {
+            let mut clipboard = lock_clipboard(&state);
+            let clipboard_manager = clipboard.as_mut()
+                .ok_or_else(|| state.feature_error(Feature::Clipboard, "Clipboard manager not initialized"))?;
+            clipboard_manager.set_text(&text)?;
+            Ok(serde_json::Value::Null)
+        },
+        ControlCommand::Status => {
+            let status = InstanceStatus {
+                signaling: state.signaling.lock().unwrap().status(),
+                captures: lock_screen_capture(&state).as_ref()
+                    .map(|capture_manager| capture_manager.get_all_stats())
+                    .unwrap_or_default(),
+                active_transfers: state.file_transfer.as_ref()
+                    .map(|manager| manager.get_stats().active_transfers)
+                    .unwrap_or(0),
+            };
+            serde_json::to_value(status).map_err(|e| CommandError::new(ErrorKind::Internal, e.to_string()))
+        },
+        ControlCommand::ListTransfers => {
+            let manager = state.transfer_manager()?;
+            serde_json::to_value(manager.get_transfer_queue()).map_err(|e| CommandError::new(ErrorKind::Internal, e.to_string()))
+        },
+    }
+}
+
+/// Turns the Prometheus endpoint on or off and returns its URL while it runs
+#[tauri::command]
+fn enable_metrics_endpoint(enabled: bool, port: Option<u16>, state: tauri::State<'_, AppState>) -> Result<Option<String>, CommandError> {
+    let mut settings = state.settings.lock().unwrap().clone();
+    settings.metrics.enabled = enabled;
+    if let Some(port) = port {
+        settings.metrics.port = port;
+    }
+    store_settings(&state, settings)?;
+    
+    let metrics_server = state.metrics_server.lock().unwrap();
+    Ok(metrics_server.as_ref().map(|server| format!("http://{}/metrics", server.local_addr())))
+}
+
+/// Shows a sample notification regardless of the notification settings
+#[tauri::command]
+fn test_notification(app_handle: tauri::AppHandle) -> Result<(), CommandError> {
+    let notification = Notification::new(
+        NotificationCategory::PeerConnected,
+        "SmolDesk notifications",
+        "Remote activity on this host will be shown like this",
+    );
+    notifications::show(&app_handle.config().tauri.bundle.identifier, &notification)
+        .map_err(|e| CommandError::new(ErrorKind::NotificationFailed, e))
+}
+
+#[tauri::command]
+fn configure_audit_log(config: AuditConfig, state: tauri::State<'_, AppState>) {
+    state.audit_log.set_config(config);
+}
+
+#[tauri::command]
+fn get_audit_config(state: tauri::State<'_, AppState>) -> AuditConfig {
+    state.audit_log.config()
+}
+
+#[tauri::command]
+fn export_audit_log(format: AuditExportFormat, state: tauri::State<'_, AppState>) -> Result<String, CommandError> {
+    state.audit_log.export(format)
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn verify_audit_log(state: tauri::State<'_, AppState>) -> Result<AuditVerification, CommandError> {
+    state.audit_log.verify()
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn set_log_level(level: String, target: Option<String>) -> Result<(), CommandError> {
+    logging::set_log_level(&level, target.as_deref())
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn collect_diagnostics(output_path: Option<String>) -> Result<String, CommandError> {
+    let output_path = match output_path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => {
+            let log_dir = logging::log_dir()
+                .ok_or_else(|| CommandError::new(ErrorKind::LoggingNotInitialized, "Logging not initialized"))?;
+            let file_name = format!("smoldesk-diagnostics-{}.zip", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+            log_dir.parent().unwrap_or(&log_dir).join(file_name)
+        }
+    };
+    
+    logging::collect_diagnostics(&output_path)
+        .map(|path| path.to_string_lossy().into_owned())
+        .map_err(CommandError::from)
+}
+
+#[tauri::command]
+fn check_system_capabilities(state: tauri::State<'_, AppState>) -> CapabilityReport {
+    let report = capabilities::check_system_capabilities().with_safe_mode(state.safe_mode.source());
+    *state.capabilities.lock().unwrap() = Some(report.clone());
+    report
+}
+
+/// Tear down a manager and create it again, e.g. after ydotoold was started or
+/// the session switched from X11 to Wayland. A failed attempt leaves the
+/// subsystem uninitialized, so commands keep retrying it.
+#[tauri::command]
+fn reinitialize_subsystem(window: Window, name: Subsystem, state: tauri::State<'_, AppState>) -> Result<(), CommandError> {
+    match name {
+        Subsystem::InputForwarding => state.safe_mode.check(RemoteControl::Input)?,
+        Subsystem::Clipboard => state.safe_mode.check(RemoteControl::Clipboard)?,
+        Subsystem::ScreenCapture => {},
+    }
+    
+    // Later errors should explain the system as it is now
+    *state.capabilities.lock().unwrap() = Some(capabilities::check_system_capabilities().with_safe_mode(state.safe_mode.source()));
+    
+    match name {
+        Subsystem::ScreenCapture => {
+            // Taken out of the slot first, so stopping does not block other commands
+            let previous = state.screen_capture.lock().unwrap().take();
+            if let Some(mut capture_manager) = previous {
+                capture_manager.stop_capture(None).ok();
+                capture_manager.destroy_virtual_displays();
+            }
+            
+            let capture_manager = create_screen_capture_manager(&state.app_handle, &state.host_session, &state.frame_latency, &state.pointer_feed)?;
+            let monitors = capture_manager.get_monitors();
+            let input_monitors = capture_manager.get_input_monitors();
+            *state.screen_capture.lock().unwrap() = Some(capture_manager);
+            sync_input_monitors(&state, &input_monitors);
+            window.emit("monitors_changed", monitors)?;
+        },
+        Subsystem::InputForwarding => {
+            // A macro would keep typing into the old forwarder
+            state.input_recorder.cancel_replay();
+            
+            let previous = state.input_forwarder.lock().unwrap().take();
+            let mode = match previous {
+                Some(forwarder) => {
+                    if let Err(e) = forwarder.reset_input_state() {
+                        log::warn!(target: logging::TARGET_INPUT, "Failed to release keys before reinitializing: {}", e);
+                    }
+                    forwarder.mode()
+                },
+                None => InputMode::Enabled,
+            };
+            
+            let monitors = state.pointer_feed.input_monitors();
+            let config = state.settings.lock().unwrap().input.clone();
+            let forwarder = create_input_forwarder(&monitors, &config, &state.system_actions)?;
+            // Input cut off by the panic hotkey stays off, a preview stays a preview
+            forwarder.set_mode(mode);
+            *state.input_forwarder.lock().unwrap() = Some(forwarder);
+        },
+        Subsystem::Clipboard => {
+            let previous = state.clipboard_manager.lock().unwrap().take();
+            // Dropping the old manager joins its monitor thread
+            let (monitoring, policy) = match previous {
+                Some(manager) => (manager.is_monitoring(), Some(manager.get_sync_status().policy)),
+                None => (false, None),
+            };
+            
+            let config = state.settings.lock().unwrap().clipboard.clone();
+            let mut clipboard_manager = create_clipboard_manager(&state.app_handle, config, state.audit_log.clone(), state.clipboard_poller.clone())?;
+            if let Some(policy) = policy {
+                clipboard_manager.set_sync_policy(policy);
+            }
+            if monitoring {
+                clipboard_manager.start_monitoring()?;
+            }
+            *state.clipboard_manager.lock().unwrap() = Some(clipboard_manager);
+        },
+    }
+    
+    log::info!("{:?} reinitialized", name);
+    Ok(())
+}
+
+/// Sent with `clipboard_history_warning` when the stored clipboard history
+/// could not be (fully) read or the history cannot be stored at all
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ClipboardHistoryWarning {
+    message: String,
+    memory_only: bool,
+    skipped_records: usize,
+}
+
+/// Sent with `settings_warning` when the stored settings could not be used
+#[derive(Debug, Clone, Serialize)]
+struct SettingsWarning {
+    message: String,
+}
+
+/// Sent with `power_profile_changed` for the captures whose limits changed
+#[derive(Debug, Clone, Serialize)]
+struct PowerProfileChanged {
+    source: PowerSource,
+    captures: Vec<screen_capture::power_profile::PowerProfileChange>,
+}
+
+/// Push settings to the running managers. Capture settings apply to the next
+/// `start_capture`, transfer settings after a restart.
+fn apply_settings(state: &AppState, settings: &AppSettings) -> Result<(), CommandError> {
+    if let Some(clipboard_manager) = &mut *state.clipboard_manager.lock().unwrap() {
+        clipboard_manager.update_config(settings.clipboard.clone())?;
+    }
+    if let Some(security_manager) = &*state.security_manager.lock().unwrap() {
+        security_manager.update_config(settings.security.clone());
+    }
+    state.input_blocklist.set_patterns(settings.input.input_blocklist.clone());
+    state.system_actions.set_config(settings.input.system_actions.clone());
+    *state.stuck_key_timeout.lock().unwrap() = settings.input.stuck_key_timeout_ms.map(std::time::Duration::from_millis);
+    if let Some(forwarder) = &*state.input_forwarder.lock().unwrap() {
+        forwarder.set_shortcut_policy(&settings.input.shortcut_policy)
+            .map_err(|e| CommandError::new(ErrorKind::InputInvalidConfig, e.to_string()))?;
+    }
+    state.notifier.lock().unwrap().set_config(settings.notifications.clone());
+    let dnd_change = state.do_not_disturb.lock().unwrap().set_config(settings.do_not_disturb.clone());
+    emit_dnd_changed(&state.app_handle, dnd_change);
+    state.sharing_indicator.lock().unwrap().set_config(settings.sharing_indicator.clone());
+    state.chat.set_config(settings.chat.clone());
+    configure_metrics(state, &settings.metrics)?;
+    
+    // New tool paths change what the capability report has to say
+    if tool_locator::current().config() != &settings.tools {
+        tool_locator::install(ToolLocator::new(settings.tools.clone()));
+        *state.capabilities.lock().unwrap() = Some(capabilities::check_system_capabilities().with_safe_mode(state.safe_mode.source()));
+    }
+    Ok(())
+}
+
+/// Apply and persist new settings
+fn store_settings(state: &AppState, settings: AppSettings) -> Result<AppSettings, CommandError> {
+    apply_settings(state, &settings)?;
+    if let Some(store) = &state.settings_store {
+        store.save(&settings)?;
+    }
+    *state.settings.lock().unwrap() = settings.clone();
+    Ok(settings)
+}
+
+#[tauri::command]
+fn get_settings(state: tauri::State<'_, AppState>) -> AppSettings {
+    state.settings.lock().unwrap().clone()
+}
+
+/// Merge a partial update, e.g. `{ "capture": { "fps": 60 } }`, into the stored settings
+#[tauri::command]
+fn update_settings(patch: serde_json::Value, state: tauri::State<'_, AppState>) -> Result<AppSettings, CommandError> {
+    let current = state.settings.lock().unwrap().clone();
+    let settings = settings::merge(&current, patch)?;
+    store_settings(&state, settings)
+}
+
+#[tauri::command]
+fn reset_settings(state: tauri::State<'_, AppState>) -> Result<AppSettings, CommandError> {
+    store_settings(&state, AppSettings::default())
+}
+
+/// Settings as they are in effect; passwords set at runtime are only known
+/// to the security manager
+fn effective_settings(state: &AppState) -> AppSettings {
+    let mut settings = state.settings.lock().unwrap().clone();
+    if let Some(security_manager) = &*state.security_manager.lock().unwrap() {
+        settings.security = security_manager.get_config();
+    }
+    settings
+}
+
+/// Write all settings, capture profiles, the shortcut policy and trusted peers
+/// to a bundle; with `include_secrets` also the identity key and password
+/// hashes, encrypted with `passphrase`
+#[tauri::command]
+fn export_config_bundle(
+    path: String,
+    include_secrets: bool,
+    passphrase: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<BundleManifest, CommandError> {
+    let settings = effective_settings(&state);
+    let security = state.security_manager.lock().unwrap();
+    let secrets = match (include_secrets, passphrase.as_deref()) {
+        (false, _) => None,
+        (true, None) => return Err(BundleError::PassphraseRequired.into()),
+        (true, Some(passphrase)) => Some(SecretsExport {
+            passphrase,
+            identity: security.as_ref().and_then(|security_manager| security_manager.identity()),
+            ice_credentials: ice_store().export_credentials(&settings.ice),
+        }),
+    };
+    
+    let manifest = config_bundle::write_bundle(std::path::Path::new(&path), &settings, secrets)?;
+    log::info!("Exported configuration bundle to {} (secrets: {})", path, manifest.includes_secrets);
+    Ok(manifest)
+}
+
+/// Replace the configuration with a bundle's; reports every setting that changed
+#[tauri::command]
+fn import_config_bundle(
+    path: String,
+    passphrase: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ConfigImportReport, CommandError> {
+    ensure_no_active_sessions(&state)?;
+    
+    let bundle = config_bundle::read_bundle(std::path::Path::new(&path), passphrase.as_deref())?;
+    let current = effective_settings(&state);
+    let settings = bundle.settings_for(&current);
+    let changes = config_bundle::diff_settings(&current, &settings);
+    
+    // apply_settings updates one manager after the other, so a failure
+    // puts the previous settings back everywhere
+    if let Err(e) = store_settings(&state, settings) {
+        if let Err(rollback) = apply_settings(&state, &current) {
+            log::error!("Failed to restore settings after a failed import: {}", rollback);
+        }
+        return Err(e);
+    }
+    
+    // Imported TURN servers keep their IDs, so their credentials find them
+    if let Err(e) = ice_store().import_credentials(&bundle.settings.ice, &bundle.ice_credentials) {
+        log::warn!("TURN credentials from the bundle were not stored: {}", e);
+    }
+    
+    let identity_replaced = bundle.identity.is_some();
+    if let Some(identity) = bundle.identity {
+        let data_dir = state.app_handle.path_resolver().app_data_dir()
+            .ok_or_else(|| CommandError::new(ErrorKind::SettingsFailed, "No data directory for the device identity"))?;
+        identity.store(&data_dir)?;
+        if let Some(security_manager) = &mut *state.security_manager.lock().unwrap() {
+            security_manager.set_identity(identity);
+        }
+    }
+    
+    log::info!("Imported configuration bundle from {} ({} changes)", path, changes.len());
+    Ok(ConfigImportReport { manifest: bundle.manifest, changes, identity_replaced })
+}
+
+/// Replacing the configuration under a running capture or transfer would
+/// pull it out from under the peer
+fn ensure_no_active_sessions(state: &AppState) -> Result<(), CommandError> {
+    if let Some(capture_manager) = &*state.screen_capture.lock().unwrap() {
+        if !capture_manager.active_captures().is_empty() {
+            return Err(CommandError::new(ErrorKind::SettingsBusy, "Stop screen capture before importing a configuration"));
+        }
+    }
+    if let Some(manager) = &state.file_transfer {
+        let stats = manager.get_stats();
+        if stats.active_transfers > 0 || stats.queued_transfers > 0 {
+            return Err(CommandError::new(ErrorKind::SettingsBusy, "Wait for file transfers to finish before importing a configuration"));
+        }
+    }
+    Ok(())
+}
+
+/// Quits once the shutdown sequence is through; later requests, e.g. a
+/// second click on the close button, are ignored
+fn quit(app_handle: &tauri::AppHandle) {
+    if app_handle.state::<AppState>().shutting_down.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+        shutdown(&app_handle);
+        app_handle.exit(0);
+    });
+}
+
+/// Runs the shutdown steps within the deadline, then kills whatever child
+/// processes are left
+fn shutdown(app_handle: &tauri::AppHandle) {
+    log::info!("Shutting down");
+    let report = shutdown_sequence(app_handle).run(shutdown::SHUTDOWN_DEADLINE);
+    match report.timed_out {
+        Some(step) => log::warn!(
+            "Shutdown step {} did not finish within {}s, skipping the rest (done: {})",
+            step, shutdown::SHUTDOWN_DEADLINE.as_secs(), report.completed.join(", ")
+        ),
+        None => log::info!("Shut down in {} ms", report.elapsed.as_millis()),
+    }
+    
+    let killed = shutdown::kill_processes(shutdown::child_processes(std::process::id()));
+    for process in &killed {
+        log::warn!("Killed {} ({}), which outlived the shutdown", process.name, process.pid);
+    }
+    log::logger().flush();
+}
+
+/// Everything that has to happen before the process goes away, in order
+fn shutdown_sequence(app_handle: &tauri::AppHandle) -> ShutdownSequence {
+    let handle = app_handle.clone();
+    let step = move |run: fn(&AppState)| {
+        let handle = handle.clone();
+        move || run(&handle.state::<AppState>())
+    };
+    
+    ShutdownSequence::new()
+        .step("control", step(|state| {
+            // Scripts must not reach a half torn down app; also removes the socket file
+            let control_server = state.control_server.lock().unwrap().take();
+            if let Some(mut server) = control_server {
+                server.stop();
+            }
+            
+            // Free the metrics port before the process goes away
+            let metrics_server = state.metrics_server.lock().unwrap().take();
+            if let Some(mut server) = metrics_server {
+                server.stop();
+            }
+        }))
+        .step("capture", step(|state| {
+            let thumbnail_stream = state.thumbnail_stream.lock().unwrap().take();
+            if let Some(mut stream) = thumbnail_stream {
+                stream.stop();
+            }
+            
+            // Stopping waits for the encoders to exit; virtual outputs must not outlive the app
+            if let Some(capture_manager) = &mut *state.screen_capture.lock().unwrap() {
+                capture_manager.stop_capture(None).ok();
+                capture_manager.destroy_virtual_displays();
+            }
+        }))
+        .step("input", step(|state| {
+            // An aborted replay releases the keys it still holds, and so do remote peers
+            state.input_recorder.cancel_replay();
+            release_held_keys(&state.app_handle, "shutdown");
+            
+            // Closing the listener also releases an X11 key grab
+            let panic_hotkey = state.panic_hotkey.lock().unwrap().take();
+            if let Some(mut listener) = panic_hotkey {
+                listener.stop();
+            }
+        }))
+        .step("transfers", step(|state| {
+            if let Some(manager) = &state.file_transfer {
+                let closed = tauri::async_runtime::block_on(manager.shutdown());
+                if closed > 0 {
+                    log::info!(target: logging::TARGET_TRANSFER, "Closed {} transfers on shutdown", closed);
+                }
+            }
+        }))
+        .step("clipboard", step(|state| {
+            if let Some(clipboard_manager) = &mut *state.clipboard_manager.lock().unwrap() {
+                clipboard_manager.stop_monitoring();
+            }
+        }))
+        .step("settings", step(|state| {
+            if let Some(store) = &state.settings_store {
+                let settings = state.settings.lock().unwrap().clone();
+                if let Err(e) = store.save(&settings) {
+                    log::error!("Failed to save settings on shutdown: {}", e);
+                }
+            }
+        }))
+        .step("desktop", step(|state| {
+            // Also releases the suspend delay lock
+            let sleep_watcher = state.sleep_watcher.lock().unwrap().take();
+            if let Some(mut watcher) = sleep_watcher {
+                watcher.stop();
+            }
+            
+            let power_watcher = state.power_watcher.lock().unwrap().take();
+            if let Some(mut watcher) = power_watcher {
+                watcher.stop();
+            }
+            
+            // Sharing is over, so the desktop gets its notifications back
+            state.do_not_disturb.lock().unwrap().update(false);
+            state.sharing_indicator.lock().unwrap().sync(&state.app_handle, Default::default());
+        }))
+}
+
+fn main() {
+    // `smoldesk <subcommand>` talks to a running instance instead of starting one
+    let safe_mode_flag = match cli::run() {
+        cli::Invocation::Exit(exit_code) => std::process::exit(exit_code),
+        cli::Invocation::Start { safe_mode } => safe_mode,
+    };
+    
+    if let Err(e) = logging::init(&logging::default_log_dir()) {
+        eprintln!("Failed to initialize logging: {}", e);
+    }
+    
+    tauri::Builder::default()
+        .setup(move |app| {
+            // Stored settings configure the managers below
+            let settings_store = app.path_resolver().app_config_dir().map(|dir| Arc::new(SettingsStore::new(&dir)));
+            let LoadedSettings { settings, warning: settings_warning } = match &settings_store {
+                Some(store) => store.load_or_default(),
+                None => LoadedSettings { settings: AppSettings::default(), warning: None },
+            };
+            
+            // Every tool spawned from here on uses the configured binaries
+            tool_locator::install(ToolLocator::new(settings.tools.clone()));
+            
+            // Input, clipboard and file transfer managers are not even created in safe mode
+            let safe_mode = SafeMode::detect(safe_mode_flag, settings.safe_mode);
+            if let Some(source) = safe_mode.source() {
+                log::warn!(target: logging::TARGET_SECURITY, "Safe mode ({:?}): remote input, clipboard sync and file transfer are disabled", source);
+            }
+            
+            // Probe external tools once so later errors can name the missing piece
+            let capability_report = capabilities::check_system_capabilities().with_safe_mode(safe_mode.source());
+            for feature in &capability_report.features {
+                if feature.status != capabilities::CapabilityStatus::Available {
+                    log::warn!("{:?}: {}", feature.feature, feature.message);
+                }
+            }
+            
+            // Initialize the screen capture manager
+            let host_session = Arc::new(HostSessionMonitor::new());
+            let frame_latency = Arc::new(Mutex::new(FrameLatencyTracker::new()));
+            let pointer_feed = PointerFeed::new();
+            let screen_capture_manager = match create_screen_capture_manager(&app.handle(), &host_session, &frame_latency, &pointer_feed) {
+                Ok(manager) => Some(manager),
+                Err(e) => {
+                    log::error!(target: logging::TARGET_SCREEN_CAPTURE, "Failed to initialize screen capture manager: {}", e);
+                    None
+                }
+            };
+            
+            // Get monitor information for input forwarder
+            let monitors = if let Some(manager) = &screen_capture_manager {
+                manager.get_monitors()
+            } else {
+                vec![]
+            };
+            
+            // Initialize input forwarder with automatic display server detection
+            let system_actions = Arc::new(SystemActionGuard::new(settings.input.system_actions.clone()));
+            let input_forwarder = match safe_mode.create(RemoteControl::Input, || create_input_forwarder(&monitors, &settings.input, &system_actions)) {
+                Some(Ok(forwarder)) => Some(forwarder),
+                None => None,
+                Some(Err(e)) => {
+                    log::error!(target: logging::TARGET_INPUT, "Failed to initialize input forwarder: {}", e);
+                    None
+                }
+            };
+
+            // Input macros live in the app data directory
+            let input_recorder = Arc::new(InputMacroRecorder::new());
+            if let Some(data_dir) = app.path_resolver().app_data_dir() {
+                input_recorder.set_storage_dir(data_dir);
+            }
+            
+            // Tamper-evident audit log of sensitive actions
+            let audit_log = Arc::new(match app.path_resolver().app_data_dir() {
+                Some(data_dir) => AuditLog::open(&data_dir, AuditConfig::default()).unwrap_or_else(|e| {
+                    log::error!(target: logging::TARGET_SECURITY, "Failed to open audit log, keeping it in memory: {}", e);
+                    AuditLog::in_memory(AuditConfig::default())
+                }),
+                None => AuditLog::in_memory(AuditConfig::default()),
+            });
+            
+            // Initialize clipboard manager; local changes are forwarded to the frontend
+            let clipboard_poller = Arc::new(ClipboardPoller::new(settings.clipboard.max_poll_interval_ms));
+            let clipboard_manager = match safe_mode.create(RemoteControl::Clipboard, || create_clipboard_manager(&app.handle(), settings.clipboard.clone(), audit_log.clone(), clipboard_poller.clone())) {
+                Some(Ok(manager)) => Some(manager),
+                None => None,
+                Some(Err(e)) => {
+                    log::error!(target: logging::TARGET_CLIPBOARD, "Failed to initialize clipboard manager: {}", e);
+                    None
+                }
+            };
+            
+            let screen_capture = Arc::new(Mutex::new(screen_capture_manager));
+            let security_manager: Arc<Mutex<Option<ConnectionSecurityManager>>> = Arc::new(Mutex::new(None));
+            
+            // Initialize the file transfer manager
+            let file_transfer_manager = match safe_mode.create(RemoteControl::FileTransfer, || FileTransferManager::new(settings.transfer.clone())) {
+                Some(Ok(mut manager)) => {
+                    manager.set_audit_sink(audit_log.clone());
+                    
+                    // Receipts are signed with the device identity once security is initialized
+                    let security = security_manager.clone();
+                    manager.set_receipt_signer(Box::new(move |receipt: TransferReceipt| {
+                        let security = security.lock().unwrap();
+                        let security = security.as_ref()?;
+                        let peer_fingerprint = security.get_peer_fingerprint(&receipt.peer_id);
+                        Some(receipt.sign(security.identity()?, peer_fingerprint))
+                    }));
+                    
+                    // Incoming and finished files are announced on the desktop
+                    let (event_sender, events) = tokio::sync::mpsc::unbounded_channel();
+                    manager.set_event_sender(event_sender);
+                    start_transfer_notifications(app.handle(), events);
+                    
+                    // Auto bandwidth mode follows the current video bitrate
+                    let capture = screen_capture.clone();
+                    manager.set_video_bitrate_source(Box::new(move || {
+                        capture.lock().unwrap().as_ref()
+                            .map(|manager| manager.get_total_bitrate())
+                            .unwrap_or(0)
+                    }));
+                    
+                    // Drop gestures report progress and their final location to the frontend
+                    let app_handle = app.handle();
+                    manager.set_drop_listener(Box::new(move |event: &DropEvent| {
+                        if let Err(e) = app_handle.emit_all(event.event_name(), event) {
+                            log::error!(target: logging::TARGET_TRANSFER, "Failed to emit file drop event: {}", e);
+                        }
+                    }));
+                    
+                    // Load the persisted transfer history and clear out staging left over from
+                    // earlier runs; without a data directory downloads go straight to their target
+                    if let Some(data_dir) = app.path_resolver().app_data_dir() {
+                        if let Err(e) = manager.set_history_dir(&data_dir) {
+                            log::error!(target: logging::TARGET_TRANSFER, "Failed to open transfer history: {}", e);
+                        }
+                        if let Err(e) = manager.set_staging_dir(&data_dir) {
+                            log::error!(target: logging::TARGET_TRANSFER, "Failed to open transfer staging directory: {}", e);
+                        }
+                        // Otherwise the chunk cache only lives in memory
+                        if settings.transfer.persist_chunk_cache {
+                            if let Err(e) = manager.set_chunk_cache_dir(&data_dir) {
+                                log::error!(target: logging::TARGET_TRANSFER, "Failed to open chunk cache: {}", e);
+                            }
+                        }
+                    }
+
+                    // Stalled transfer detection runs on the async runtime, which tokio::spawn needs as context
+                    tauri::async_runtime::block_on(async { manager.start_maintenance() });
+                    Some(manager)
+                },
+                None => None,
+                Some(Err(e)) => {
+                    log::error!(target: logging::TARGET_TRANSFER, "Failed to initialize file transfer manager: {}", e);
+                    None
+                }
+            };
+            
+            // Backend signaling client; its events go to the frontend as they are
+            let mut signaling = SignalingClient::new();
+            {
+                let app_handle = app.handle();
+                signaling.subscribe(Arc::new(move |event: &SignalingEvent| {
+                    if let Err(e) = app_handle.emit_all(event.event_name(), event) {
+                        log::error!(target: logging::TARGET_SIGNALING, "Failed to emit signaling event: {}", e);
+                    }
+                }));
+                
+                // Peers go through the registry, which reports each one joining and leaving once
+                let app_handle = app.handle();
+                signaling.subscribe(Arc::new(move |event: &SignalingEvent| {
+                    if let Some(state) = app_handle.try_state::<AppState>() {
+                        let changes = state.peers.handle_signaling_event(event, chrono::Utc::now());
+                        apply_peer_changes(&app_handle, &state, changes);
+                    }
+                }));
+                
+                // Authenticate with a fresh token once security is initialized
+                let security = security_manager.clone();
+                signaling.set_token_provider(Arc::new(move |peer_id: &str| {
+                    let security = security.lock().unwrap();
+                    let peer_info = PeerInfo {
+                        peer_id: peer_id.to_string(),
+                        display_name: None,
+                        access_rights: ConnectionSecurityManager::assign_default_rights_by_role(&UserRole::Owner),
+                    };
+                    match security.as_ref()?.generate_access_token(peer_info, SIGNALING_TOKEN_TTL) {
+                        Ok(token) => Some(token),
+                        Err(e) => {
+                            log::error!(target: logging::TARGET_SIGNALING, "Failed to issue signaling token: {}", e);
+                            None
+                        }
+                    }
+                }));
+            }
+            
+            // Chat frames are sealed with the clipboard keys and leave through the frontend
+            let mut chat = ChatManager::new(settings.chat.clone());
+            chat.set_transport(Arc::new(SealedChatTransport {
+                app_handle: app.handle(),
+                security_manager: security_manager.clone(),
+            }));
+            let app_handle = app.handle();
+            chat.set_listener(Box::new(move |event: &ChatEvent| {
+                if let Err(e) = app_handle.emit_all(event.event_name(), event) {
+                    log::error!(target: logging::TARGET_CHAT, "Failed to emit chat event: {}", e);
+                }
+            }));
+            // The disk history belongs to the verified device, not the connection
+            let security = security_manager.clone();
+            chat.set_fingerprint_source(Box::new(move |peer_id: &str| {
+                security.lock().unwrap().as_ref()?.get_peer_fingerprint(peer_id)
+            }));
+            if let Some(data_dir) = app.path_resolver().app_data_dir() {
+                if let Err(e) = chat.set_history_dir(&data_dir) {
+                    log::error!(target: logging::TARGET_CHAT, "Failed to open chat history: {}", e);
+                }
+            }
+            
+            let input_blocklist = Arc::new(InputBlocklist::new());
+            input_blocklist.set_patterns(settings.input.input_blocklist.clone());
+            let stuck_key_timeout = settings.input.stuck_key_timeout_ms.map(std::time::Duration::from_millis);
+            let notifier = Notifier::new(settings.notifications.clone());
+            
+            // Restores do-not-disturb a crashed sharing session left switched on
+            let mut do_not_disturb = DndController::new(Arc::new(SystemCommandRunner), settings.do_not_disturb.clone());
+            let mut dnd_recovered = None;
+            if let Some(data_dir) = app.path_resolver().app_data_dir() {
+                match do_not_disturb.set_state_dir(&data_dir) {
+                    Ok(change) => dnd_recovered = change,
+                    Err(e) => log::error!(target: logging::TARGET_SCREEN_CAPTURE, "Failed to restore do-not-disturb: {}", e),
+                }
+            }
+            
+            let sharing_indicator = SharingIndicator::new(settings.sharing_indicator.clone(), safe_mode.is_active());
+            
+            // Create app state
+            let state = AppState {
+                screen_capture,
+                pointer_feed,
+                input_forwarder: Arc::new(Mutex::new(input_forwarder)),
+                input_recorder,
+                input_blocklist,
+                system_actions,
+                input_latency: Arc::new(InputLatencyTracker::new()),
+                input_preview: Arc::new(InputPreview::new()),
+                panic_hotkey: Arc::new(Mutex::new(None)),
+                panic_hotkey_config: Arc::new(Mutex::new(PanicHotkeyConfig::default())),
+                stuck_key_timeout: Arc::new(Mutex::new(stuck_key_timeout)),
+                input_arbiter: Arc::new(Mutex::new(InputArbiter::new(ControlConfig::default()))),
+                host_session,
+                clipboard_manager: Arc::new(Mutex::new(clipboard_manager)),
+                clipboard_poller,
+                security_manager,
+                file_transfer: file_transfer_manager.map(Arc::new),
+                chat: Arc::new(chat),
+                capabilities: Arc::new(Mutex::new(Some(capability_report))),
+                audit_log,
+                signaling: Arc::new(Mutex::new(signaling)),
+                connection_quality: Arc::new(Mutex::new(QualityMonitor::new())),
+                frame_latency,
+                settings: Arc::new(Mutex::new(settings)),
+                settings_store,
+                notifier: Arc::new(Mutex::new(notifier)),
+                do_not_disturb: Arc::new(Mutex::new(do_not_disturb)),
+                sharing_indicator: Arc::new(Mutex::new(sharing_indicator)),
+                peers: Arc::new(PeerRegistry::new()),
+                metrics_server: Arc::new(Mutex::new(None)),
+                control_server: Arc::new(Mutex::new(None)),
+                sleep_watcher: Arc::new(Mutex::new(None)),
+                power_watcher: Arc::new(Mutex::new(None)),
+                thumbnail_stream: Arc::new(Mutex::new(None)),
+                accessibility: Arc::from(accessibility::system_tree()),
+                suspended_captures: Arc::new(Mutex::new(None)),
+                init_retries: RetryGate::new(RETRY_INTERVAL),
+                safe_mode,
+                shutting_down: AtomicBool::new(false),
+                app_handle: app.handle(),
+            };
+            
+            // Manage state
+            app.manage(state);
+            
+            if let Some(message) = settings_warning {
+                if let Err(e) = app.emit_all("settings_warning", SettingsWarning { message }) {
+                    log::error!("Failed to emit settings_warning: {}", e);
+                }
+            }
+            
+            // The hotkey callback needs the managed state, so it starts last
+            let state = app.state::<AppState>();
+            let config = state.panic_hotkey_config.lock().unwrap().clone();
+            match start_panic_hotkey(app.handle(), &config) {
+                Ok(listener) => *state.panic_hotkey.lock().unwrap() = listener,
+                Err(e) => log::warn!(target: logging::TARGET_INPUT, "Panic hotkey unavailable: {}", e),
+            }
+            
+            start_quality_sampler(app.handle());
+            start_viewport_fit(app.handle());
+            start_clock_sync(app.handle());
+            start_stuck_key_watchdog(app.handle());
+            start_control_idle_timer(app.handle());
+            start_system_action_timer(app.handle());
+            start_notification_flush(app.handle());
+            start_input_preview_flush(app.handle());
+            start_subsystem_monitor(app.handle());
+            start_dnd_watcher(app.handle());
+            emit_dnd_changed(&app.handle(), dnd_recovered);
+            
+            // Captures are stopped before the host sleeps and brought back after
+            let sleep_handle = app.handle();
+            let watcher = SleepWatcher::start(Arc::new(SystemCommandRunner), move |event| handle_sleep_event(&sleep_handle, event));
+            *state.sleep_watcher.lock().unwrap() = Some(watcher);
+            
+            // Running captures are limited while the host is on battery
+            let power_handle = app.handle();
+            let watcher = PowerSourceWatcher::start(std::path::PathBuf::from(power::POWER_SUPPLY_DIR), move |source| handle_power_source_change(&power_handle, source));
+            *state.power_watcher.lock().unwrap() = Some(watcher);
+            
+            let metrics_config = state.settings.lock().unwrap().metrics.clone();
+            if let Err(e) = configure_metrics(&state, &metrics_config) {
+                log::warn!("{}", e);
+            }
+            
+            // Scripts reach this instance through `smoldesk <subcommand>`
+            let app_handle = app.handle();
+            let handler = Arc::new(move |command| handle_control_command(&app_handle, command));
+            match ControlServer::start(&control_socket::default_dir(), handler) {
+                Ok(server) => *state.control_server.lock().unwrap() = Some(server),
+                Err(e) => log::warn!("Control socket unavailable: {}", e),
+            }
+            
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            get_display_server,
+            get_monitors,
+            get_monitor_thumbnail,
+            subscribe_thumbnails,
+            unsubscribe_thumbnails,
+            start_capture,
+            stop_capture,
+            get_capture_sessions,
+            set_max_simultaneous_captures,
+            get_last_capture_error,
+            request_keyframe,
+            get_frame_stream_url,
+            start_recording_cursor_track,
+            stop_recording_cursor_track,
+            burn_recording_cursor,
+            set_capture_region,
+            set_output_resolution,
+            set_viewer_viewport,
+            get_capture_profiles,
+            apply_capture_profile,
+            save_capture_profile,
+            add_privacy_mask,
+            remove_privacy_mask,
+            list_privacy_masks,
+            set_buffer_config,
+            get_buffer_stats,
+            create_virtual_display,
+            destroy_virtual_display,
+            get_virtual_displays,
+            send_input_event,
+            get_input_backend,
+            get_input_latency_stats,
+            set_input_enabled,
+            set_input_mode,
+            configure_input_forwarding,
+            set_input_blocklist,
+            reset_input_state,
+            get_host_session_state,
+            wake_display,
+            set_wake_display_on_input,
+            get_dnd_integration,
+            set_dnd_integration,
+            get_sharing_indicator,
+            set_sharing_indicator,
+            set_host_volume,
+            get_host_volume,
+            get_text_at_region,
+            get_focused_text,
+            request_control,
+            release_control,
+            grant_control,
+            get_control_state,
+            set_control_config,
+            get_control_config,
+            get_input_blocklist,
+            get_shortcut_policy,
+            set_shortcut_policy,
+            get_system_action_config,
+            set_system_action_config,
+            get_pending_system_actions,
+            answer_system_action,
+            start_input_recording,
+            stop_input_recording,
+            list_input_macros,
+            delete_input_macro,
+            replay_input_macro,
+            cancel_input_replay,
+            configure_panic_hotkey,
+            get_panic_hotkey_config,
+            get_video_codecs,
+            get_hardware_acceleration_options,
+            validate_capture_config,
+            get_wayland_capture_sources,
+            get_clipboard_text,
+            set_clipboard_text,
+            start_clipboard_monitoring,
+            stop_clipboard_monitoring,
+            configure_clipboard,
+            set_clipboard_sync_policy,
+            get_clipboard_sync_status,
+            get_clipboard_stats,
+            sync_remote_clipboard_entry,
+            sync_sealed_clipboard_entry,
+            resend_clipboard_entry,
+            begin_clipboard_key_exchange,
+            complete_clipboard_key_exchange,
+            send_chat_message,
+            get_chat_history,
+            send_chat_typing,
+            receive_chat_frame,
+            set_shared_clipboard,
+            get_shared_clipboard,
+            list_shared_clipboard,
+            sync_shared_clipboard_entry,
+            set_clipboard_connection_mode,
+            get_clipboard_connection_mode,
+            pin_clipboard_entry,
+            unpin_clipboard_entry,
+            wipe_clipboard_history,
+            search_clipboard_history,
+            select_clipboard_history_entry,
+            set_transfer_bandwidth_limit,
+            set_transfer_bandwidth_auto,
+            get_transfer_queue,
+            reorder_transfer_queue,
+            get_transfer_history,
+            get_transfer_receipt,
+            verify_transfer_receipt,
+            clear_transfer_history,
+            get_storage_usage,
+            purge_staging,
+            handle_remote_file_drop,
+            request_transfer_preview,
+            get_file_drop_config,
+            set_file_drop_config,
+            initialize_security,
+            generate_access_token,
+            validate_access_token,
+            revoke_token,
+            rotate_token_key,
+            set_room_password,
+            clear_room_password,
+            set_custom_commands_allowed,
+            verify_room_password,
+            get_identity_fingerprint,
+            create_identity_challenge,
+            answer_identity_challenge,
+            verify_peer_identity,
+            list_trusted_peers,
+            trust_peer,
+            revoke_peer,
+            register_peer,
+            unregister_peer,
+            get_connected_peers,
+            configure_audit_log,
+            get_audit_config,
+            export_audit_log,
+            verify_audit_log,
+            connect_signaling,
+            disconnect_signaling,
+            send_signaling_message,
+            get_signaling_status,
+            get_ice_servers,
+            set_ice_servers,
+            test_ice_server,
+            get_connection_quality,
+            report_clock_sync,
+            report_frame_rendered,
+            get_latency_breakdown,
+            report_network_stats,
+            set_log_level,
+            collect_diagnostics,
+            check_system_capabilities,
+            reinitialize_subsystem,
+            get_settings,
+            update_settings,
+            reset_settings,
+            export_config_bundle,
+            import_config_bundle,
+            test_notification,
+            get_stats_history,
+            enable_metrics_endpoint,
+        ])
+        .on_window_event(|event| {
+            // Closing the main window quits, even while the sharing indicator's frame is open
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event.event() {
+                if event.window().label() == MAIN_WINDOW_LABEL {
+                    // The window goes away right away, the app once everything is cleaned up
+                    api.prevent_close();
+                    let _ = event.window().hide();
+                    quit(&event.window().app_handle());
+                }
+            }
+        })
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| match event {
+            tauri::RunEvent::ExitRequested { api, .. } => {
+                api.prevent_exit();
+                quit(app_handle);
+            },
+            // The event loop ended some other way; clean up as far as possible
+            tauri::RunEvent::Exit => {
+                if !app_handle.state::<AppState>().shutting_down.swap(true, Ordering::SeqCst) {
+                    shutdown(app_handle);
+                }
+            },
+            _ => {},
+        });
+}
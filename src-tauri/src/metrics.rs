@@ -0,0 +1,319 @@
+// src-tauri/src/metrics.rs - Prometheus metrics endpoint for streaming health
+//
+// A minimal HTTP listener that answers `GET /metrics` with the current capture,
+// transfer and peer figures in the Prometheus text exposition format. It is
+// off by default and binds to localhost unless configured otherwise.
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::screen_capture::gpu_stats::GpuUsage;
+use crate::screen_capture::types::CaptureStats;
+
+/// Content type of the text exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// How long the accept loop sleeps when no scrape is waiting
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A slow or stuck client must not block the next scrape
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Upper bound for the request head; scrapes are a single short GET
+const MAX_REQUEST_LEN: usize = 8 * 1024;
+
+/// Where the metrics endpoint listens
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    /// Use 0.0.0.0 only on trusted networks; the endpoint has no authentication
+    pub bind_address: String,
+    pub port: u16,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        MetricsConfig {
+            enabled: false,
+            bind_address: "127.0.0.1".to_string(),
+            port: 9464,
+        }
+    }
+}
+
+/// Figures exported on every scrape
+#[derive(Debug, Clone, Default)]
+pub struct MetricsSnapshot {
+    pub captures: Vec<CaptureStats>,
+    pub active_transfers: usize,
+    pub connected_peers: usize,
+}
+
+/// Collects a snapshot when a scrape arrives
+pub type MetricsSource = Arc<dyn Fn() -> MetricsSnapshot + Send + Sync>;
+
+/// One GPU figure of a capture, None if its source does not report it
+type GpuFigure = fn(&GpuUsage) -> Option<String>;
+
+/// Renders a snapshot in the Prometheus text exposition format
+pub fn render(snapshot: &MetricsSnapshot) -> String {
+    let mut out = String::new();
+
+    let per_capture: [(&str, &str, &str, fn(&CaptureStats) -> String); 4] = [
+        ("smoldesk_capture_fps", "gauge", "Frames encoded per second", |stats| stats.fps.to_string()),
+        ("smoldesk_capture_bitrate_bits_per_second", "gauge", "Video bitrate in bits per second", |stats| stats.bitrate.to_string()),
+        ("smoldesk_capture_dropped_frames_total", "counter", "Frames dropped since the capture started", |stats| stats.dropped_frames.to_string()),
+        ("smoldesk_capture_buffer_level", "gauge", "Frames waiting in the stream buffer", |stats| stats.buffer_level.to_string()),
+    ];
+    for (name, kind, help, value) in per_capture {
+        write_header(&mut out, name, kind, help);
+        for stats in &snapshot.captures {
+            out.push_str(&format!("{}{{monitor=\"{}\"}} {}\n", name, stats.monitor_index, value(stats)));
+        }
+    }
+
+    // Only captures with hardware encoding and a readable GPU have these
+    let per_gpu: [(&str, &str, GpuFigure); 4] = [
+        ("smoldesk_capture_gpu_encoder_utilization_percent", "Busy percentage of the GPU's video encode engine", |gpu| gpu.encoder_utilization.map(|value| value.to_string())),
+        ("smoldesk_capture_gpu_utilization_percent", "Busy percentage of the GPU's 3D/compute engine", |gpu| gpu.gpu_utilization.map(|value| value.to_string())),
+        ("smoldesk_capture_gpu_memory_used_bytes", "Video memory in use", |gpu| gpu.memory_used_bytes.map(|value| value.to_string())),
+        ("smoldesk_capture_gpu_memory_total_bytes", "Video memory of the GPU", |gpu| gpu.memory_total_bytes.map(|value| value.to_string())),
+    ];
+    for (name, help, value) in per_gpu {
+        write_header(&mut out, name, "gauge", help);
+        for stats in &snapshot.captures {
+            if let Some(value) = stats.gpu.as_ref().and_then(value) {
+                out.push_str(&format!("{}{{monitor=\"{}\"}} {}\n", name, stats.monitor_index, value));
+            }
+        }
+    }
+
+    write_header(&mut out, "smoldesk_file_transfers_active", "gauge", "File transfers preparing or running");
+    out.push_str(&format!("smoldesk_file_transfers_active {}\n", snapshot.active_transfers));
+
+    write_header(&mut out, "smoldesk_peers_connected", "gauge", "Peers in the signaling room");
+    out.push_str(&format!("smoldesk_peers_connected {}\n", snapshot.connected_peers));
+
+    out
+}
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+}
+
+/// HTTP listener serving `GET /metrics`
+pub struct MetricsServer {
+    config: MetricsConfig,
+    local_addr: SocketAddr,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MetricsServer {
+    /// Binds the configured address and starts the accept thread
+    pub fn start(config: &MetricsConfig, source: MetricsSource) -> io::Result<Self> {
+        let listener = TcpListener::bind((config.bind_address.as_str(), config.port))?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = running.clone();
+        let thread = thread::spawn(move || Self::accept_loop(listener, thread_running, source));
+
+        log::info!("Metrics endpoint listening on http://{}/metrics", local_addr);
+
+        Ok(MetricsServer {
+            config: config.clone(),
+            local_addr,
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    /// The configuration the server was started with
+    pub fn config(&self) -> &MetricsConfig {
+        &self.config
+    }
+
+    /// Bound address; differs from the config when port 0 was requested
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stops accepting scrapes and waits for the accept thread
+    pub fn stop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    fn accept_loop(listener: TcpListener, running: Arc<AtomicBool>, source: MetricsSource) {
+        while running.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(e) = Self::serve(stream, &source) {
+                        log::debug!("Metrics scrape failed: {}", e);
+                    }
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL_INTERVAL),
+                Err(e) => {
+                    log::warn!("Metrics endpoint accept failed: {}", e);
+                    thread::sleep(ACCEPT_POLL_INTERVAL);
+                },
+            }
+        }
+    }
+
+    fn serve(mut stream: TcpStream, source: &MetricsSource) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+        stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+
+        let head = read_request_head(&mut stream)?;
+        let request_line = head.lines().next().unwrap_or_default();
+        let mut parts = request_line.split_whitespace();
+        let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+
+        let (status, content_type, body) = match (method, path.split('?').next().unwrap_or_default()) {
+            ("GET", "/metrics") => ("200 OK", CONTENT_TYPE, render(&source())),
+            ("GET", _) => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+            _ => ("405 Method Not Allowed", "text/plain", "Method not allowed\n".to_string()),
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status, content_type, body.len(), body
+        )?;
+        stream.flush()
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Reads up to the blank line that ends the request head
+fn read_request_head(stream: &mut TcpStream) -> io::Result<String> {
+    let mut head = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST_LEN {
+            return Err(io::Error::new(ErrorKind::InvalidData, "request head too large"));
+        }
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+    Ok(String::from_utf8_lossy(&head).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen_capture::gpu_stats::GpuStatsSource;
+
+    fn snapshot() -> MetricsSnapshot {
+        MetricsSnapshot {
+            captures: vec![
+                CaptureStats { monitor_index: 0, fps: 29.5, bitrate: 4_000_000, dropped_frames: 3, buffer_level: 2, ..Default::default() },
+                CaptureStats {
+                    monitor_index: 1,
+                    fps: 15.0,
+                    bitrate: 1_000_000,
+                    gpu: Some(GpuUsage {
+                        source: GpuStatsSource::NvidiaSmi,
+                        encoder_utilization: Some(23.0),
+                        gpu_utilization: Some(11.0),
+                        memory_used_bytes: Some(1_244_659_712),
+                        memory_total_bytes: None,
+                    }),
+                    ..Default::default()
+                },
+            ],
+            active_transfers: 1,
+            connected_peers: 2,
+        }
+    }
+
+    fn scrape(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    fn test_config() -> MetricsConfig {
+        MetricsConfig { enabled: true, port: 0, ..Default::default() }
+    }
+
+    #[test]
+    fn test_default_binds_localhost_and_is_off() {
+        let config = MetricsConfig::default();
+        assert!(!config.enabled);
+        assert_eq!(config.bind_address, "127.0.0.1");
+    }
+
+    #[test]
+    fn test_scrape_returns_exposition_format() {
+        let mut server = MetricsServer::start(&test_config(), Arc::new(snapshot)).unwrap();
+        assert!(server.local_addr().ip().is_loopback());
+
+        let response = scrape(server.local_addr(), "/metrics");
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert!(head.contains(&format!("Content-Type: {}", CONTENT_TYPE)));
+        assert!(head.contains(&format!("Content-Length: {}", body.len())));
+
+        assert!(body.contains("# TYPE smoldesk_capture_fps gauge\n"));
+        assert!(body.contains("smoldesk_capture_fps{monitor=\"0\"} 29.5\n"));
+        assert!(body.contains("smoldesk_capture_bitrate_bits_per_second{monitor=\"1\"} 1000000\n"));
+        assert!(body.contains("# TYPE smoldesk_capture_dropped_frames_total counter\n"));
+        assert!(body.contains("smoldesk_capture_buffer_level{monitor=\"0\"} 2\n"));
+        assert!(body.contains("smoldesk_file_transfers_active 1\n"));
+        assert!(body.contains("smoldesk_peers_connected 2\n"));
+
+        // GPU figures only for the capture and fields that have them
+        assert!(body.contains("smoldesk_capture_gpu_encoder_utilization_percent{monitor=\"1\"} 23\n"));
+        assert!(body.contains("smoldesk_capture_gpu_memory_used_bytes{monitor=\"1\"} 1244659712\n"));
+        assert!(!body.contains("smoldesk_capture_gpu_utilization_percent{monitor=\"0\"}"));
+        assert!(!body.contains("smoldesk_capture_gpu_memory_total_bytes{"));
+
+        // Every sample line belongs to a declared metric family
+        for line in body.lines().filter(|line| !line.starts_with('#')) {
+            let name = line.split(['{', ' ']).next().unwrap();
+            assert!(body.contains(&format!("# TYPE {} ", name)), "undeclared metric: {}", line);
+        }
+
+        server.stop();
+    }
+
+    #[test]
+    fn test_unknown_path_is_not_found() {
+        let server = MetricsServer::start(&test_config(), Arc::new(MetricsSnapshot::default)).unwrap();
+        assert!(scrape(server.local_addr(), "/").starts_with("HTTP/1.1 404 Not Found"));
+    }
+
+    #[test]
+    fn test_stop_releases_the_port() {
+        let mut server = MetricsServer::start(&test_config(), Arc::new(MetricsSnapshot::default)).unwrap();
+        let addr = server.local_addr();
+        server.stop();
+
+        let config = MetricsConfig { port: addr.port(), ..test_config() };
+        assert!(MetricsServer::start(&config, Arc::new(MetricsSnapshot::default)).is_ok());
+    }
+}
@@ -0,0 +1,324 @@
+// src-tauri/src/notifications.rs - Desktop notifications for remote activity
+//
+// The host should notice when someone connects, asks for control, or sends a
+// file, even while the SmolDesk window is hidden. Events are turned into
+// desktop notifications here; each category can be switched off, quiet hours
+// hold back everything that does not wait for the host's answer, and floods
+// of one category collapse into a summary.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+
+/// Events of one category within this window after the first are summarized
+pub const DEBOUNCE_WINDOW: Duration = Duration::from_secs(60);
+
+/// What a notification is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NotificationCategory {
+    PeerConnected,
+    ControlRequested,
+    TransferRequested,
+    TransferCompleted,
+    ClipboardSynced,
+}
+
+impl NotificationCategory {
+    /// Categories that wait for the host to allow or deny something
+    pub fn needs_approval(&self) -> bool {
+        matches!(self, NotificationCategory::ControlRequested | NotificationCategory::TransferRequested)
+    }
+
+    /// Summary of `count` suppressed notifications
+    fn summary(&self, count: usize) -> (String, String) {
+        let (title, one, many) = match self {
+            NotificationCategory::PeerConnected => ("Peers connected", "peer connection", "peer connections"),
+            NotificationCategory::ControlRequested => ("Control requested", "control request", "control requests"),
+            NotificationCategory::TransferRequested => ("Incoming files", "file transfer request", "file transfer requests"),
+            NotificationCategory::TransferCompleted => ("File transfers completed", "completed file transfer", "completed file transfers"),
+            NotificationCategory::ClipboardSynced => ("Clipboard synced", "clipboard sync", "clipboard syncs"),
+        };
+        let what = if count == 1 { one } else { many };
+        (title.to_string(), format!("{} more {} in the last minute", count, what))
+    }
+}
+
+/// A time-of-day range; `start` after `end` wraps around midnight
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    /// Local time as "HH:MM"
+    pub start: String,
+    pub end: String,
+}
+
+impl QuietHours {
+    /// Whether `time` falls into the range; unparsable times never match
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        let (Some(start), Some(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+            return false;
+        };
+        if start <= end {
+            start <= time && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M").ok()
+}
+
+/// Which notifications are shown
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub enabled: bool,
+    pub peer_connected: bool,
+    pub control_requested: bool,
+    pub transfer_requested: bool,
+    pub transfer_completed: bool,
+    pub clipboard_synced: bool,
+    /// Holds back everything except approval requests
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        NotificationConfig {
+            enabled: true,
+            peer_connected: true,
+            control_requested: true,
+            transfer_requested: true,
+            transfer_completed: true,
+            clipboard_synced: false,
+            quiet_hours: None,
+        }
+    }
+}
+
+impl NotificationConfig {
+    pub fn category_enabled(&self, category: NotificationCategory) -> bool {
+        self.enabled && match category {
+            NotificationCategory::PeerConnected => self.peer_connected,
+            NotificationCategory::ControlRequested => self.control_requested,
+            NotificationCategory::TransferRequested => self.transfer_requested,
+            NotificationCategory::TransferCompleted => self.transfer_completed,
+            NotificationCategory::ClipboardSynced => self.clipboard_synced,
+        }
+    }
+}
+
+/// One desktop notification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Notification {
+    pub category: NotificationCategory,
+    pub title: String,
+    pub body: String,
+    /// What the host can do about it, shown below the body
+    pub action_hint: Option<String>,
+}
+
+impl Notification {
+    pub fn new(category: NotificationCategory, title: impl Into<String>, body: impl Into<String>) -> Self {
+        let action_hint = category.needs_approval()
+            .then(|| "Open SmolDesk to allow or deny".to_string());
+        Notification { category, title: title.into(), body: body.into(), action_hint }
+    }
+
+    pub fn peer_connected(peer_id: &str) -> Self {
+        Notification::new(NotificationCategory::PeerConnected, "Peer connected", format!("{} is viewing this screen", peer_id))
+    }
+
+    pub fn control_requested(peer_id: &str) -> Self {
+        Notification::new(NotificationCategory::ControlRequested, "Control requested", format!("{} wants to control keyboard and mouse", peer_id))
+    }
+
+    /// Counts as a control request: the host allows or denies it the same way
+    pub fn system_action_requested(peer_id: &str, action: &str) -> Self {
+        Notification::new(NotificationCategory::ControlRequested, "System action requested", format!("{} wants to {}", peer_id, action))
+    }
+
+    pub fn transfer_requested(peer_id: &str, file_name: &str) -> Self {
+        Notification::new(NotificationCategory::TransferRequested, "Incoming file", format!("{} wants to send {}", peer_id, file_name))
+    }
+
+    pub fn transfer_completed(file_name: &str) -> Self {
+        Notification::new(NotificationCategory::TransferCompleted, "File transfer completed", file_name.to_string())
+    }
+
+    pub fn clipboard_synced() -> Self {
+        Notification::new(NotificationCategory::ClipboardSynced, "Clipboard synced", "The remote clipboard was copied to this host")
+    }
+
+    /// Body with the action hint on its own line
+    pub fn full_body(&self) -> String {
+        match &self.action_hint {
+            Some(hint) => format!("{}\n{}", self.body, hint),
+            None => self.body.clone(),
+        }
+    }
+
+    fn summary(category: NotificationCategory, count: usize) -> Self {
+        let (title, body) = category.summary(count);
+        Notification::new(category, title, body)
+    }
+}
+
+/// Debounce state of one category
+#[derive(Debug)]
+struct Burst {
+    started: Instant,
+    suppressed: usize,
+}
+
+/// Decides which notifications are shown. The first notification of a
+/// category is shown at once; further ones within `DEBOUNCE_WINDOW` are
+/// counted and shown as one summary when the window ends.
+#[derive(Debug)]
+pub struct Notifier {
+    config: NotificationConfig,
+    bursts: HashMap<NotificationCategory, Burst>,
+}
+
+impl Notifier {
+    pub fn new(config: NotificationConfig) -> Self {
+        Notifier { config, bursts: HashMap::new() }
+    }
+
+    pub fn set_config(&mut self, config: NotificationConfig) {
+        self.config = config;
+    }
+
+    /// Returns the notifications to show now for a new event
+    pub fn notify(&mut self, notification: Notification, now: Instant, local_time: NaiveTime) -> Vec<Notification> {
+        let category = notification.category;
+        if !self.config.category_enabled(category) {
+            return Vec::new();
+        }
+
+        let mut shown: Vec<Notification> = self.flush_category(category, now, local_time).into_iter().collect();
+        match self.bursts.get_mut(&category) {
+            Some(burst) => burst.suppressed += 1,
+            None => {
+                self.bursts.insert(category, Burst { started: now, suppressed: 0 });
+                if self.allowed_now(category, local_time) {
+                    shown.push(notification);
+                }
+            },
+        }
+        shown
+    }
+
+    /// Returns summaries of windows that have ended; call periodically
+    pub fn flush(&mut self, now: Instant, local_time: NaiveTime) -> Vec<Notification> {
+        let categories: Vec<NotificationCategory> = self.bursts.keys().copied().collect();
+        categories.into_iter()
+            .flat_map(|category| self.flush_category(category, now, local_time))
+            .collect()
+    }
+
+    fn flush_category(&mut self, category: NotificationCategory, now: Instant, local_time: NaiveTime) -> Option<Notification> {
+        let burst = self.bursts.get(&category)?;
+        if now.duration_since(burst.started) < DEBOUNCE_WINDOW {
+            return None;
+        }
+
+        let suppressed = burst.suppressed;
+        self.bursts.remove(&category);
+        (suppressed > 0 && self.config.category_enabled(category) && self.allowed_now(category, local_time))
+            .then(|| Notification::summary(category, suppressed))
+    }
+
+    fn allowed_now(&self, category: NotificationCategory, local_time: NaiveTime) -> bool {
+        category.needs_approval() || !self.config.quiet_hours.as_ref()
+            .map(|quiet| quiet.contains(local_time))
+            .unwrap_or(false)
+    }
+}
+
+/// Shows a notification on the desktop
+pub fn show(identifier: &str, notification: &Notification) -> Result<(), String> {
+    tauri::api::notification::Notification::new(identifier)
+        .title(&notification.title)
+        .body(notification.full_body())
+        .show()
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noon() -> NaiveTime {
+        NaiveTime::from_hms_opt(12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_clipboard_flood_collapses_into_summary() {
+        let mut notifier = Notifier::new(NotificationConfig { clipboard_synced: true, ..Default::default() });
+        let start = Instant::now();
+
+        let mut shown = Vec::new();
+        for second in 0..50 {
+            shown.extend(notifier.notify(Notification::clipboard_synced(), start + Duration::from_secs(second), noon()));
+        }
+        assert_eq!(shown.len(), 1);
+        assert!(notifier.flush(start + Duration::from_secs(59), noon()).is_empty());
+
+        let summary = notifier.flush(start + DEBOUNCE_WINDOW, noon());
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].body, "49 more clipboard syncs in the last minute");
+        assert!(notifier.flush(start + DEBOUNCE_WINDOW * 2, noon()).is_empty());
+    }
+
+    #[test]
+    fn test_event_after_window_flushes_summary_first() {
+        let mut notifier = Notifier::new(NotificationConfig::default());
+        let start = Instant::now();
+        notifier.notify(Notification::peer_connected("a"), start, noon());
+        notifier.notify(Notification::peer_connected("b"), start, noon());
+
+        let shown = notifier.notify(Notification::peer_connected("c"), start + DEBOUNCE_WINDOW, noon());
+        assert_eq!(shown.len(), 2);
+        assert_eq!(shown[0].body, "1 more peer connection in the last minute");
+        assert_eq!(shown[1], Notification::peer_connected("c"));
+    }
+
+    #[test]
+    fn test_disabled_category_is_not_shown() {
+        let mut notifier = Notifier::new(NotificationConfig { transfer_completed: false, ..Default::default() });
+        assert!(notifier.notify(Notification::transfer_completed("a.txt"), Instant::now(), noon()).is_empty());
+
+        notifier.set_config(NotificationConfig { enabled: false, ..Default::default() });
+        assert!(notifier.notify(Notification::peer_connected("a"), Instant::now(), noon()).is_empty());
+    }
+
+    #[test]
+    fn test_quiet_hours_only_let_approvals_through() {
+        let quiet_hours = QuietHours { start: "22:00".to_string(), end: "07:00".to_string() };
+        let mut notifier = Notifier::new(NotificationConfig { quiet_hours: Some(quiet_hours), ..Default::default() });
+        let night = NaiveTime::from_hms_opt(23, 30, 0).unwrap();
+
+        assert!(notifier.notify(Notification::peer_connected("a"), Instant::now(), night).is_empty());
+        let shown = notifier.notify(Notification::control_requested("a"), Instant::now(), night);
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].action_hint.as_deref(), Some("Open SmolDesk to allow or deny"));
+    }
+
+    #[test]
+    fn test_quiet_hours_wrap_around_midnight() {
+        let overnight = QuietHours { start: "22:00".to_string(), end: "07:00".to_string() };
+        assert!(overnight.contains(NaiveTime::from_hms_opt(3, 0, 0).unwrap()));
+        assert!(!overnight.contains(noon()));
+
+        let daytime = QuietHours { start: "09:00".to_string(), end: "17:00".to_string() };
+        assert!(daytime.contains(noon()));
+        assert!(!daytime.contains(NaiveTime::from_hms_opt(17, 0, 0).unwrap()));
+
+        let invalid = QuietHours { start: "late".to_string(), end: "07:00".to_string() };
+        assert!(!invalid.contains(noon()));
+    }
+}
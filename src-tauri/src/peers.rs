@@ -0,0 +1,221 @@
+// src-tauri/src/peers.rs - Registry of the connected peers
+//
+// Peers are announced by the backend signaling client or, where the frontend
+// runs the signaling itself, reported with `register_peer`. Either way they
+// end up here, and only a change of the registry makes the host emit
+// `peer_connected` or `peer_disconnected`; the audit log, notifications and
+// the viewer count of the sharing indicator follow those, so a peer reported
+// twice is neither logged nor counted twice. Besides who is connected and
+// since when, the registry keeps the stream statistics the frontend reports
+// per peer connection.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::connection_security::AccessRight;
+use crate::signaling::SignalingEvent;
+use crate::stats::NetworkReport;
+
+/// Who announced a peer; leaving the backend's room only drops its own peers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum PeerSource {
+    Signaling,
+    Frontend,
+}
+
+/// Last WebRTC statistics reported for the connection to one peer
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PeerStreamStats {
+    /// Frames sent on the peer connection so far
+    pub frames_delivered: Option<u64>,
+    pub rtt_ms: Option<f64>,
+    /// Fraction of lost packets (0.0 - 1.0)
+    pub packet_loss: Option<f64>,
+    pub reported_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConnectedPeer {
+    pub peer_id: String,
+    /// Display name, if the frontend knows one
+    pub name: Option<String>,
+    pub source: PeerSource,
+    /// Filled in from the security manager when the list is requested
+    pub access_rights: Vec<AccessRight>,
+    pub connected_at: DateTime<Utc>,
+    /// Last input or stats report from the peer
+    pub last_activity: DateTime<Utc>,
+    pub stream: PeerStreamStats,
+}
+
+/// A peer joined or left
+#[derive(Debug, Clone, PartialEq)]
+pub enum PeerChange {
+    Connected(ConnectedPeer),
+    Disconnected(ConnectedPeer),
+}
+
+#[derive(Default)]
+pub struct PeerRegistry {
+    peers: Mutex<BTreeMap<String, ConnectedPeer>>,
+}
+
+impl PeerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a peer; None if it is registered already. A name given for a
+    /// known peer replaces the old one.
+    pub fn register(&self, peer_id: &str, name: Option<String>, source: PeerSource, now: DateTime<Utc>) -> Option<PeerChange> {
+        let mut peers = self.peers.lock().unwrap();
+        if let Some(peer) = peers.get_mut(peer_id) {
+            if name.is_some() {
+                peer.name = name;
+            }
+            return None;
+        }
+
+        let peer = ConnectedPeer {
+            peer_id: peer_id.to_string(),
+            name,
+            source,
+            access_rights: Vec::new(),
+            connected_at: now,
+            last_activity: now,
+            stream: PeerStreamStats::default(),
+        };
+        peers.insert(peer_id.to_string(), peer.clone());
+        Some(PeerChange::Connected(peer))
+    }
+
+    /// Removes a peer; None if it was not registered
+    pub fn unregister(&self, peer_id: &str) -> Option<PeerChange> {
+        self.peers.lock().unwrap().remove(peer_id).map(PeerChange::Disconnected)
+    }
+
+    /// Follows the backend signaling client
+    pub fn handle_signaling_event(&self, event: &SignalingEvent, now: DateTime<Utc>) -> Vec<PeerChange> {
+        match event {
+            SignalingEvent::RoomJoined { peers, .. } => peers.iter()
+                .filter_map(|peer_id| self.register(peer_id, None, PeerSource::Signaling, now))
+                .collect(),
+            SignalingEvent::PeerConnected { peer_id } => {
+                self.register(peer_id, None, PeerSource::Signaling, now).into_iter().collect()
+            },
+            SignalingEvent::PeerDisconnected { peer_id } => self.unregister(peer_id).into_iter().collect(),
+            SignalingEvent::RoomLeft { .. } => {
+                let mut peers = self.peers.lock().unwrap();
+                let left: Vec<String> = peers.values()
+                    .filter(|peer| peer.source == PeerSource::Signaling)
+                    .map(|peer| peer.peer_id.clone())
+                    .collect();
+                left.iter()
+                    .filter_map(|peer_id| peers.remove(peer_id))
+                    .map(PeerChange::Disconnected)
+                    .collect()
+            },
+            _ => Vec::new(),
+        }
+    }
+
+    /// Notes input or another sign of life from a peer
+    pub fn touch(&self, peer_id: &str, now: DateTime<Utc>) {
+        if let Some(peer) = self.peers.lock().unwrap().get_mut(peer_id) {
+            peer.last_activity = now;
+        }
+    }
+
+    /// Keeps the latest WebRTC statistics of a peer; values missing from the
+    /// report keep their previous value. False for unknown peers.
+    pub fn report_network(&self, peer_id: &str, report: &NetworkReport, now: DateTime<Utc>) -> bool {
+        let mut peers = self.peers.lock().unwrap();
+        let peer = match peers.get_mut(peer_id) {
+            Some(peer) => peer,
+            None => return false,
+        };
+
+        let stream = &mut peer.stream;
+        stream.frames_delivered = report.frames_sent.or(stream.frames_delivered);
+        stream.rtt_ms = report.rtt_ms.or(stream.rtt_ms);
+        stream.packet_loss = report.packet_loss.or(stream.packet_loss);
+        stream.reported_at = Some(now);
+        peer.last_activity = now;
+        true
+    }
+
+    /// Connected peers, ordered by ID
+    pub fn list(&self) -> Vec<ConnectedPeer> {
+        self.peers.lock().unwrap().values().cloned().collect()
+    }
+
+    pub fn count(&self) -> usize {
+        self.peers.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(changes: &[PeerChange]) -> Vec<String> {
+        changes.iter()
+            .map(|change| match change {
+                PeerChange::Connected(peer) => format!("+{}", peer.peer_id),
+                PeerChange::Disconnected(peer) => format!("-{}", peer.peer_id),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_each_peer_changes_once_whoever_reports_it() {
+        let registry = PeerRegistry::new();
+        let now = Utc::now();
+
+        let joined = SignalingEvent::RoomJoined { room_id: "room".to_string(), peers: vec!["a".to_string(), "b".to_string()] };
+        assert_eq!(ids(&registry.handle_signaling_event(&joined, now)), vec!["+a", "+b"]);
+
+        // The frontend reporting a known peer only adds its name
+        assert!(registry.register("a", Some("Alice".to_string()), PeerSource::Frontend, now).is_none());
+        assert!(registry.register("c", None, PeerSource::Frontend, now).is_some());
+        assert!(registry.handle_signaling_event(&SignalingEvent::PeerConnected { peer_id: "c".to_string() }, now).is_empty());
+
+        let left = SignalingEvent::PeerDisconnected { peer_id: "b".to_string() };
+        assert_eq!(ids(&registry.handle_signaling_event(&left, now)), vec!["-b"]);
+        assert!(registry.handle_signaling_event(&left, now).is_empty());
+        assert!(registry.unregister("b").is_none());
+
+        // Leaving the backend's room keeps peers the frontend reported
+        let room_left = SignalingEvent::RoomLeft { room_id: "room".to_string() };
+        assert_eq!(ids(&registry.handle_signaling_event(&room_left, now)), vec!["-a"]);
+        assert_eq!(registry.list().iter().map(|peer| peer.peer_id.as_str()).collect::<Vec<_>>(), vec!["c"]);
+    }
+
+    #[test]
+    fn test_network_reports_are_kept_per_peer() {
+        let registry = PeerRegistry::new();
+        let connected = Utc::now();
+        registry.register("a", None, PeerSource::Frontend, connected);
+        registry.register("b", None, PeerSource::Frontend, connected);
+
+        let later = connected + chrono::Duration::seconds(5);
+        let report = NetworkReport { rtt_ms: Some(42.0), packet_loss: Some(0.01), frames_sent: Some(300), ..NetworkReport::default() };
+        assert!(registry.report_network("a", &report, later));
+        // A partial report keeps the other values
+        assert!(registry.report_network("a", &NetworkReport { rtt_ms: Some(50.0), ..NetworkReport::default() }, later));
+        assert!(!registry.report_network("unknown", &report, later));
+
+        let peers = registry.list();
+        assert_eq!(peers[0].stream, PeerStreamStats {
+            frames_delivered: Some(300),
+            rtt_ms: Some(50.0),
+            packet_loss: Some(0.01),
+            reported_at: Some(later),
+        });
+        assert_eq!((peers[0].connected_at, peers[0].last_activity), (connected, later));
+        assert_eq!(peers[1].stream, PeerStreamStats::default());
+    }
+}
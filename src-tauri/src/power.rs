@@ -0,0 +1,411 @@
+// src-tauri/src/power.rs - Suspend, resume and power source of the host
+//
+// A suspend kills the capture process and can renumber monitors on wake, so
+// the app listens for logind's PrepareForSleep signal. Like the other D-Bus
+// queries in `host_session`, this goes through the command line tools:
+// dbus-monitor reports the signal, and systemd-inhibit holds a delay lock so
+// the captures can be stopped cleanly before the host actually sleeps.
+//
+// Whether the host runs on battery is read from /sys/class/power_supply,
+// which needs no D-Bus service and is polled like the battery applets do.
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::command_runner::CommandRunner;
+use crate::logging::TARGET_SCREEN_CAPTURE;
+
+/// Match rule for logind's PrepareForSleep signal on the system bus
+const PREPARE_FOR_SLEEP_MATCH: &str = "type='signal',sender='org.freedesktop.login1',\
+interface='org.freedesktop.login1.Manager',member='PrepareForSleep'";
+
+/// Pause before dbus-monitor is started again after it ended
+const RESPAWN_DELAY: Duration = Duration::from_secs(5);
+
+/// dbus-monitor failing to start this often in a row gives up on sleep events
+const MAX_RESPAWN_FAILURES: u32 = 3;
+
+/// Time the displays get after a resume before monitors are detected again
+pub const RESUME_SETTLE_DELAY: Duration = Duration::from_secs(2);
+
+/// Where the kernel lists AC adapters and batteries
+pub const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// How often the power supplies are read
+const POWER_SOURCE_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Transition reported by logind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SleepEvent {
+    /// The host is about to suspend or hibernate
+    Suspending,
+    /// The host woke up again
+    Resumed,
+}
+
+/// Runs a handler for every suspend and resume until stopped
+pub struct SleepWatcher {
+    stopped: Arc<AtomicBool>,
+    monitor: Arc<Mutex<Option<Child>>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl SleepWatcher {
+    /// Start watching. On `Suspending` the delay lock is released only after
+    /// `handler` returned; on `Resumed` it is taken again before `handler` runs.
+    pub fn start<F>(runner: Arc<dyn CommandRunner>, handler: F) -> Self
+    where
+        F: Fn(SleepEvent) + Send + 'static,
+    {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let monitor = Arc::new(Mutex::new(None));
+
+        let thread_stopped = stopped.clone();
+        let thread_monitor = monitor.clone();
+        let thread = thread::spawn(move || watch(runner, handler, thread_monitor, thread_stopped));
+
+        SleepWatcher { stopped, monitor, thread: Some(thread) }
+    }
+
+    /// Stop watching and release the delay lock
+    pub fn stop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(child) = &mut *self.monitor.lock().unwrap() {
+            let _ = child.kill();
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for SleepWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn watch<F: Fn(SleepEvent)>(
+    runner: Arc<dyn CommandRunner>,
+    handler: F,
+    monitor: Arc<Mutex<Option<Child>>>,
+    stopped: Arc<AtomicBool>,
+) {
+    let mut inhibitor = take_delay_lock(runner.as_ref());
+    let mut failures = 0;
+
+    while !stopped.load(Ordering::SeqCst) {
+        let spawned = runner.command("dbus-monitor")
+            .args(["--system", PREPARE_FOR_SLEEP_MATCH])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(e) => {
+                failures += 1;
+                if failures >= MAX_RESPAWN_FAILURES {
+                    log::warn!(target: TARGET_SCREEN_CAPTURE, "Not watching for suspend, dbus-monitor is unavailable: {}", e);
+                    break;
+                }
+                sleep_unless_stopped(&stopped, RESPAWN_DELAY);
+                continue;
+            },
+        };
+        failures = 0;
+
+        let stdout = child.stdout.take();
+        *monitor.lock().unwrap() = Some(child);
+
+        // stop() may have run between spawn and registration
+        if stopped.load(Ordering::SeqCst) {
+            break;
+        }
+
+        if let Some(stdout) = stdout {
+            let mut parser = SignalParser::default();
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                match parser.feed(&line) {
+                    Some(SleepEvent::Suspending) => {
+                        handler(SleepEvent::Suspending);
+                        release_delay_lock(&mut inhibitor);
+                    },
+                    Some(SleepEvent::Resumed) => {
+                        if inhibitor.is_none() {
+                            inhibitor = take_delay_lock(runner.as_ref());
+                        }
+                        handler(SleepEvent::Resumed);
+                    },
+                    None => {},
+                }
+            }
+        }
+
+        if let Some(mut child) = monitor.lock().unwrap().take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        sleep_unless_stopped(&stopped, RESPAWN_DELAY);
+    }
+
+    if let Some(mut child) = monitor.lock().unwrap().take() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+    release_delay_lock(&mut inhibitor);
+}
+
+/// Whether the host runs on battery, and its charge
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerSource {
+    pub on_battery: bool,
+
+    /// Charge of the emptiest system battery (None without a battery)
+    pub battery_percent: Option<u8>,
+}
+
+/// Reads the power supplies below `dir`. The host counts as on battery when
+/// no AC adapter is online and a battery is discharging; batteries of mice
+/// and other devices (scope "Device") are ignored.
+pub fn read_power_source(dir: &Path) -> PowerSource {
+    let read = |supply: &Path, attribute: &str| -> Option<String> {
+        fs::read_to_string(supply.join(attribute)).ok().map(|value| value.trim().to_string())
+    };
+
+    let mut ac_online = false;
+    let mut discharging = false;
+    let mut battery_percent: Option<u8> = None;
+
+    let supplies = fs::read_dir(dir).into_iter().flatten().map_while(Result::ok).map(|entry| entry.path());
+    for supply in supplies {
+        match read(&supply, "type").as_deref() {
+            Some("Mains") | Some("USB") => ac_online |= read(&supply, "online").as_deref() == Some("1"),
+            Some("Battery") if read(&supply, "scope").as_deref() != Some("Device") => {
+                discharging |= read(&supply, "status").as_deref() == Some("Discharging");
+                if let Some(capacity) = read(&supply, "capacity").and_then(|value| value.parse::<u8>().ok()) {
+                    battery_percent = Some(battery_percent.map_or(capacity, |percent| percent.min(capacity)));
+                }
+            },
+            _ => {},
+        }
+    }
+
+    PowerSource { on_battery: discharging && !ac_online, battery_percent }
+}
+
+/// Reports changes of the power source until stopped
+pub struct PowerSourceWatcher {
+    stopped: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl PowerSourceWatcher {
+    /// Start polling `dir`. `handler` is called with the first reading if the
+    /// host is on battery, and after that on every change.
+    pub fn start<F>(dir: PathBuf, handler: F) -> Self
+    where
+        F: Fn(PowerSource) + Send + 'static,
+    {
+        Self::start_with_interval(dir, POWER_SOURCE_POLL_INTERVAL, handler)
+    }
+
+    fn start_with_interval<F>(dir: PathBuf, interval: Duration, handler: F) -> Self
+    where
+        F: Fn(PowerSource) + Send + 'static,
+    {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+        let thread = thread::spawn(move || {
+            let mut last = PowerSource::default();
+            while !thread_stopped.load(Ordering::SeqCst) {
+                let source = read_power_source(&dir);
+                if source != last {
+                    handler(source);
+                    last = source;
+                }
+                sleep_unless_stopped(&thread_stopped, interval);
+            }
+        });
+
+        PowerSourceWatcher { stopped, thread: Some(thread) }
+    }
+
+    pub fn stop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for PowerSourceWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Picks PrepareForSleep out of dbus-monitor's output: a header line naming
+/// the member, followed by the argument, e.g. "   boolean true"
+#[derive(Default)]
+struct SignalParser {
+    in_prepare_for_sleep: bool,
+}
+
+impl SignalParser {
+    fn feed(&mut self, line: &str) -> Option<SleepEvent> {
+        if line.starts_with("signal ") {
+            self.in_prepare_for_sleep = line.contains("member=PrepareForSleep");
+            return None;
+        }
+        if !self.in_prepare_for_sleep {
+            return None;
+        }
+
+        self.in_prepare_for_sleep = false;
+        match line.trim() {
+            "boolean true" => Some(SleepEvent::Suspending),
+            "boolean false" => Some(SleepEvent::Resumed),
+            _ => None,
+        }
+    }
+}
+
+/// Delays suspend until the lock is released (logind caps the delay at
+/// InhibitDelayMaxSec). None if systemd-inhibit is not available.
+fn take_delay_lock(runner: &dyn CommandRunner) -> Option<Child> {
+    let spawned = runner.command("systemd-inhibit")
+        .args([
+            "--what=sleep",
+            "--mode=delay",
+            "--who=SmolDesk",
+            "--why=Stopping screen capture",
+            "sleep",
+            "infinity",
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match spawned {
+        Ok(child) => Some(child),
+        Err(e) => {
+            log::debug!(target: TARGET_SCREEN_CAPTURE, "No suspend delay lock, captures may be cut off: {}", e);
+            None
+        },
+    }
+}
+
+fn release_delay_lock(inhibitor: &mut Option<Child>) {
+    if let Some(mut child) = inhibitor.take() {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+/// Sleeps in short steps so stop() does not wait for a whole respawn delay
+fn sleep_unless_stopped(stopped: &AtomicBool, duration: Duration) {
+    let step = Duration::from_millis(100);
+    let mut slept = Duration::ZERO;
+    while slept < duration && !stopped.load(Ordering::SeqCst) {
+        thread::sleep(step);
+        slept += step;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::TestEnv;
+    use std::sync::mpsc;
+
+    /// dbus-monitor output around a suspend, recorded on a systemd host
+    const SUSPEND_AND_RESUME: &str = "\
+signal time=1718000000.101 sender=org.freedesktop.DBus -> destination=:1.210 serial=2 path=/org/freedesktop/DBus; interface=org.freedesktop.DBus; member=NameAcquired
+   string \":1.210\"
+signal time=1718000000.102 sender=org.freedesktop.DBus -> destination=:1.210 serial=4 path=/org/freedesktop/DBus; interface=org.freedesktop.DBus; member=NameLost
+   string \":1.210\"
+signal time=1718000042.417 sender=:1.3 -> destination=(null destination) serial=1571 path=/org/freedesktop/login1; interface=org.freedesktop.login1.Manager; member=PrepareForSleep
+   boolean true
+signal time=1718003600.902 sender=:1.3 -> destination=(null destination) serial=1580 path=/org/freedesktop/login1; interface=org.freedesktop.login1.Manager; member=PrepareForSleep
+   boolean false
+";
+
+    #[test]
+    fn test_parser_ignores_other_signals() {
+        let mut parser = SignalParser::default();
+        let events: Vec<SleepEvent> = SUSPEND_AND_RESUME.lines().filter_map(|line| parser.feed(line)).collect();
+        assert_eq!(events, vec![SleepEvent::Suspending, SleepEvent::Resumed]);
+
+        // An argument without a PrepareForSleep header is not a sleep event
+        let mut parser = SignalParser::default();
+        assert_eq!(parser.feed("   boolean true"), None);
+    }
+
+    #[test]
+    fn test_watcher_reports_simulated_signals() {
+        let env = TestEnv::new();
+        env.set_stdout("dbus-monitor", SUSPEND_AND_RESUME);
+
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = SleepWatcher::start(env.runner(), move |event| {
+            let _ = sender.send(event);
+        });
+
+        let timeout = Duration::from_secs(5);
+        assert_eq!(receiver.recv_timeout(timeout), Ok(SleepEvent::Suspending));
+        assert_eq!(receiver.recv_timeout(timeout), Ok(SleepEvent::Resumed));
+        watcher.stop();
+
+        assert!(env.invocations().contains(&vec![
+            "dbus-monitor".to_string(),
+            "--system".to_string(),
+            PREPARE_FOR_SLEEP_MATCH.to_string(),
+        ]));
+    }
+
+    fn write_supply(dir: &Path, name: &str, attributes: &[(&str, &str)]) {
+        let supply = dir.join(name);
+        fs::create_dir_all(&supply).unwrap();
+        for (attribute, value) in attributes {
+            fs::write(supply.join(attribute), format!("{}\n", value)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_power_source_from_sysfs() {
+        let dir = std::env::temp_dir().join(format!("smoldesk-power-{}", uuid::Uuid::new_v4()));
+        write_supply(&dir, "AC", &[("type", "Mains"), ("online", "1")]);
+        write_supply(&dir, "BAT0", &[("type", "Battery"), ("status", "Charging"), ("capacity", "41")]);
+        write_supply(&dir, "hidpp_battery_0", &[("type", "Battery"), ("scope", "Device"), ("status", "Discharging"), ("capacity", "5")]);
+        assert_eq!(read_power_source(&dir), PowerSource { on_battery: false, battery_percent: Some(41) });
+
+        // Unplugged: the mouse battery neither counts as discharging nor as the charge
+        write_supply(&dir, "AC", &[("online", "0")]);
+        write_supply(&dir, "BAT0", &[("status", "Discharging"), ("capacity", "40")]);
+        assert_eq!(read_power_source(&dir), PowerSource { on_battery: true, battery_percent: Some(40) });
+
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = PowerSourceWatcher::start_with_interval(dir.clone(), Duration::from_millis(10), move |source| {
+            let _ = sender.send(source);
+        });
+        let timeout = Duration::from_secs(5);
+        assert_eq!(receiver.recv_timeout(timeout).map(|source| source.on_battery), Ok(true));
+        write_supply(&dir, "AC", &[("online", "1")]);
+        assert_eq!(receiver.recv_timeout(timeout).map(|source| source.on_battery), Ok(false));
+        watcher.stop();
+
+        // A desktop without batteries is always on AC
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(read_power_source(&dir), PowerSource::default());
+    }
+}
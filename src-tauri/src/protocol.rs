@@ -0,0 +1,192 @@
+// src-tauri/src/protocol.rs - Versioning of the wire formats shared with frontends and peers
+//
+// Serialization conventions for every wire-facing type (docs/api/protocols.md):
+// - struct fields are camelCase
+// - message enums are internally tagged with `type`; variant names and their
+//   fields are camelCase
+// - plain value enums (codecs, statuses, buttons) keep their Rust variant names
+//
+// The first message of each subsystem carries `protocolVersion`: the transfer
+// request, the clipboard sync entry, the input configuration, each chat
+// message and each control socket request. Messages without it come from a frontend or peer older than
+// versioning and are rejected with a `VersionMismatch` before their fields are
+// decoded.
+
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Version spoken by this build
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Oldest version this build still understands
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Name of the version field in the first message of a subsystem
+pub const VERSION_FIELD: &str = "protocolVersion";
+
+/// Subsystems with their own versioned message stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WireSubsystem {
+    FileTransfer,
+    ClipboardSync,
+    InputConfig,
+    Chat,
+    ControlSocket,
+}
+
+impl fmt::Display for WireSubsystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WireSubsystem::FileTransfer => write!(f, "file transfer"),
+            WireSubsystem::ClipboardSync => write!(f, "clipboard sync"),
+            WireSubsystem::InputConfig => write!(f, "input configuration"),
+            WireSubsystem::Chat => write!(f, "chat"),
+            WireSubsystem::ControlSocket => write!(f, "control socket"),
+        }
+    }
+}
+
+/// The other side speaks a version outside `MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionMismatch {
+    pub subsystem: WireSubsystem,
+    /// 0 if the message carried no version at all
+    pub received: u32,
+    pub min_supported: u32,
+    pub max_supported: u32,
+}
+
+impl fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.received == 0 {
+            write!(f, "Unversioned {} message; protocol {}..={} required", self.subsystem, self.min_supported, self.max_supported)
+        } else {
+            write!(
+                f, "Unsupported {} protocol version {} (supported: {}..={})",
+                self.subsystem, self.received, self.min_supported, self.max_supported
+            )
+        }
+    }
+}
+
+impl std::error::Error for VersionMismatch {}
+
+/// A wire message that could not be accepted
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProtocolError {
+    VersionMismatch(VersionMismatch),
+    /// The version is fine but the message does not match the schema
+    Malformed(String),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::VersionMismatch(mismatch) => mismatch.fmt(f),
+            ProtocolError::Malformed(msg) => write!(f, "Malformed message: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+impl From<VersionMismatch> for ProtocolError {
+    fn from(mismatch: VersionMismatch) -> Self {
+        ProtocolError::VersionMismatch(mismatch)
+    }
+}
+
+/// Version both sides speak: the lower of the two, as long as this build still
+/// supports it. A newer peer falls back to the version in our reply.
+pub fn negotiate(subsystem: WireSubsystem, received: u32) -> Result<u32, VersionMismatch> {
+    if received < MIN_PROTOCOL_VERSION {
+        return Err(VersionMismatch {
+            subsystem,
+            received,
+            min_supported: MIN_PROTOCOL_VERSION,
+            max_supported: PROTOCOL_VERSION,
+        });
+    }
+    Ok(received.min(PROTOCOL_VERSION))
+}
+
+/// Version field of a raw message; 0 if it is missing or not a number
+pub fn version_of(message: &Value) -> u32 {
+    message.get(VERSION_FIELD)
+        .and_then(Value::as_u64)
+        .and_then(|v| u32::try_from(v).ok())
+        .unwrap_or(0)
+}
+
+/// Decodes the first message of a subsystem, checking its version before the fields
+pub fn decode_versioned<T: DeserializeOwned>(subsystem: WireSubsystem, json: &str) -> Result<(T, u32), ProtocolError> {
+    let value: Value = serde_json::from_str(json).map_err(|e| ProtocolError::Malformed(e.to_string()))?;
+    let version = negotiate(subsystem, version_of(&value))?;
+    let message = serde_json::from_value(value).map_err(|e| ProtocolError::Malformed(e.to_string()))?;
+    Ok((message, version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Hello {
+        #[allow(dead_code)]
+        protocol_version: u32,
+        peer_name: String,
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(negotiate(WireSubsystem::FileTransfer, PROTOCOL_VERSION), Ok(PROTOCOL_VERSION));
+        assert_eq!(negotiate(WireSubsystem::FileTransfer, PROTOCOL_VERSION + 5), Ok(PROTOCOL_VERSION));
+
+        let mismatch = negotiate(WireSubsystem::ClipboardSync, 0).unwrap_err();
+        assert_eq!(mismatch.received, 0);
+        assert_eq!(mismatch.subsystem, WireSubsystem::ClipboardSync);
+        assert!(mismatch.to_string().starts_with("Unversioned clipboard sync message"));
+    }
+
+    #[test]
+    fn test_decode_checks_version_before_fields() {
+        let (hello, version) = decode_versioned::<Hello>(
+            WireSubsystem::InputConfig,
+            r#"{"protocolVersion":1,"peerName":"laptop"}"#,
+        ).unwrap();
+        assert_eq!(hello.peer_name, "laptop");
+        assert_eq!(version, 1);
+
+        // An old snake_case message fails on the missing version, not on its field names
+        assert!(matches!(
+            decode_versioned::<Hello>(WireSubsystem::InputConfig, r#"{"peer_name":"laptop"}"#),
+            Err(ProtocolError::VersionMismatch(VersionMismatch { received: 0, .. }))
+        ));
+        assert!(matches!(
+            decode_versioned::<Hello>(WireSubsystem::InputConfig, r#"{"protocolVersion":1}"#),
+            Err(ProtocolError::Malformed(_))
+        ));
+        assert!(matches!(
+            decode_versioned::<Hello>(WireSubsystem::InputConfig, "not json"),
+            Err(ProtocolError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_version_mismatch_shape() {
+        let mismatch = negotiate(WireSubsystem::FileTransfer, 0).unwrap_err();
+        assert_eq!(serde_json::to_value(&mismatch).unwrap(), json!({
+            "subsystem": "fileTransfer",
+            "received": 0,
+            "minSupported": MIN_PROTOCOL_VERSION,
+            "maxSupported": PROTOCOL_VERSION,
+        }));
+    }
+}
@@ -0,0 +1,226 @@
+// src-tauri/src/safe_mode.rs - Start without any remote control
+//
+// In safe mode viewers can watch the screen but not act on it. The input
+// forwarder, clipboard sync and file transfer manager are never created, so
+// neither a command nor a peer message finds anything to reach, and their
+// slots in the app state stay empty for the whole run. The mode is decided
+// once at startup; leaving it takes a restart, also when it came from the
+// settings.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Environment variable that starts the app in safe mode
+pub const SAFE_MODE_ENV: &str = "SMOLDESK_SAFE_MODE";
+
+/// What switched safe mode on, in order of precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SafeModeSource {
+    CommandLine,
+    Environment,
+    Settings,
+}
+
+/// Features that safe mode keeps switched off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteControl {
+    Input,
+    Clipboard,
+    FileTransfer,
+}
+
+impl RemoteControl {
+    fn name(self) -> &'static str {
+        match self {
+            RemoteControl::Input => "Input forwarding",
+            RemoteControl::Clipboard => "Clipboard sync",
+            RemoteControl::FileTransfer => "File transfer",
+        }
+    }
+}
+
+/// Whether this run is in safe mode; fixed once the app has started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SafeMode {
+    source: Option<SafeModeSource>,
+}
+
+impl SafeMode {
+    /// Safe mode from the `--safe-mode` flag, the value of `SMOLDESK_SAFE_MODE`
+    /// and the settings toggle. Any value of the variable other than an empty
+    /// one, `0`, `false`, `no` or `off` switches it on.
+    pub fn resolve(flag: bool, env: Option<&str>, setting: bool) -> Self {
+        let env_set = env.is_some_and(|value| {
+            !matches!(value.trim().to_ascii_lowercase().as_str(), "" | "0" | "false" | "no" | "off")
+        });
+        let source = if flag {
+            Some(SafeModeSource::CommandLine)
+        } else if env_set {
+            Some(SafeModeSource::Environment)
+        } else if setting {
+            Some(SafeModeSource::Settings)
+        } else {
+            None
+        };
+        SafeMode { source }
+    }
+
+    /// `resolve` with the variable from the process environment
+    pub fn detect(flag: bool, setting: bool) -> Self {
+        SafeMode::resolve(flag, std::env::var(SAFE_MODE_ENV).ok().as_deref(), setting)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.source.is_some()
+    }
+
+    pub fn source(&self) -> Option<SafeModeSource> {
+        self.source
+    }
+
+    /// Fails if safe mode switched `control` off
+    pub fn check(&self, control: RemoteControl) -> Result<(), SafeModeError> {
+        match self.source {
+            Some(source) => Err(SafeModeError { control, source }),
+            None => Ok(()),
+        }
+    }
+
+    /// The manager in `slot` for a command or peer message of `control`, None
+    /// if the slot is empty. In safe mode the slot is not even looked at, so a
+    /// manager that got there some other way is still out of reach.
+    pub fn reach<'a, T>(&self, control: RemoteControl, slot: &'a Option<T>) -> Result<Option<&'a T>, SafeModeError> {
+        self.check(control)?;
+        Ok(slot.as_ref())
+    }
+
+    /// Runs `create` only outside safe mode. In safe mode the manager for
+    /// `control` is never constructed and its slot stays empty.
+    pub fn create<T, E>(&self, control: RemoteControl, create: impl FnOnce() -> Result<T, E>) -> Option<Result<T, E>> {
+        self.check(control).ok()?;
+        Some(create())
+    }
+}
+
+/// A remote control feature was used while safe mode is on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SafeModeError {
+    pub control: RemoteControl,
+    pub source: SafeModeSource,
+}
+
+impl fmt::Display for SafeModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let by = match self.source {
+            SafeModeSource::CommandLine => "--safe-mode",
+            SafeModeSource::Environment => SAFE_MODE_ENV,
+            SafeModeSource::Settings => "the safe mode setting",
+        };
+        write!(f, "{} is disabled by safe mode (switched on by {})", self.control.name(), by)
+    }
+}
+
+impl std::error::Error for SafeModeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+    use crate::file_transfer::scheduler::TransferPriority;
+    use crate::file_transfer::types::{TransferConfig, TransferStatus};
+    use crate::file_transfer::FileTransferManager;
+    use crate::input_forwarding::x11::ImprovedX11InputForwarder;
+    use crate::input_forwarding::{ImprovedInputForwarder, InputEvent};
+    use crate::test_env::TestEnv;
+
+    #[test]
+    fn test_sources_in_order_of_precedence() {
+        assert_eq!(SafeMode::resolve(false, None, false).source(), None);
+        assert_eq!(SafeMode::resolve(true, Some("1"), true).source(), Some(SafeModeSource::CommandLine));
+        assert_eq!(SafeMode::resolve(false, Some("yes"), true).source(), Some(SafeModeSource::Environment));
+        assert_eq!(SafeMode::resolve(false, Some("Off"), true).source(), Some(SafeModeSource::Settings));
+        assert!(!SafeMode::resolve(false, Some(" 0 "), false).is_active());
+        assert!(!SafeMode::resolve(false, Some(""), false).is_active());
+    }
+
+    #[test]
+    fn test_input_cannot_reach_the_forwarder_in_safe_mode() {
+        let env = TestEnv::new();
+        let safe_mode = SafeMode::resolve(true, None, false);
+        let x11_forwarder = || ImprovedX11InputForwarder::with_runner(env.runner())
+            .map(|forwarder| Box::new(forwarder) as Box<dyn ImprovedInputForwarder>);
+
+        let mut constructed = false;
+        let slot = safe_mode.create(RemoteControl::Input, || {
+            constructed = true;
+            x11_forwarder()
+        }).and_then(Result::ok);
+        assert!(!constructed && slot.is_none());
+        let Err(error) = safe_mode.reach(RemoteControl::Input, &slot) else {
+            panic!("reached the input forwarder in safe mode");
+        };
+        assert_eq!(error.to_string(), "Input forwarding is disabled by safe mode (switched on by --safe-mode)");
+
+        // Even a forwarder put into the slot behind its back injects nothing,
+        // through the same gate as send_input_event
+        let slot = Some(x11_forwarder().unwrap());
+        let event: InputEvent = serde_json::from_value(serde_json::json!({
+            "eventType": "KeyPress", "keyCode": 65, "isPressed": true,
+        })).unwrap();
+        env.take_invocations();
+        let forwarded = safe_mode.reach(RemoteControl::Input, &slot)
+            .map(|forwarder| forwarder.map(|forwarder| forwarder.forward_event(&event)));
+        assert!(forwarded.is_err());
+        assert_eq!(env.take_invocations(), Vec::<Vec<String>>::new());
+
+        // Outside safe mode the same event reaches xdotool
+        let forwarder = SafeMode::default().reach(RemoteControl::Input, &slot).unwrap().unwrap();
+        forwarder.forward_event(&event).unwrap();
+        assert!(env.take_invocations().iter().any(|invocation| invocation[0] == "xdotool"));
+    }
+
+    #[tokio::test]
+    async fn test_transfer_cannot_be_accepted_in_safe_mode() {
+        let safe_mode = SafeMode::resolve(false, Some("1"), false);
+        let slot = safe_mode.create(RemoteControl::FileTransfer, || {
+            FileTransferManager::new(TransferConfig::default()).map(Arc::new)
+        }).and_then(Result::ok);
+        assert!(slot.is_none());
+        assert!(safe_mode.reach(RemoteControl::FileTransfer, &slot).is_err());
+
+        // A manager with a pending offer, put into the slot behind its back
+        let dir = std::env::temp_dir().join(format!("smoldesk-safe-mode-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("offer.txt");
+        std::fs::write(&source, b"offered").unwrap();
+
+        let mut sender = FileTransferManager::new(TransferConfig::default()).unwrap();
+        let (sender_messages, mut sent) = mpsc::unbounded_channel();
+        sender.set_message_sender(sender_messages);
+        let mut receiver = FileTransferManager::new(TransferConfig::default()).unwrap();
+        let (receiver_messages, mut replies) = mpsc::unbounded_channel();
+        receiver.set_message_sender(receiver_messages);
+
+        let transfer_id = sender.start_upload(&source, "receiver", None, TransferPriority::default()).await.unwrap();
+        let (_, offer) = sent.try_recv().unwrap();
+        receiver.handle_transfer_message("sender", offer).await.unwrap();
+        while replies.try_recv().is_ok() {}
+        let slot = Some(Arc::new(receiver));
+
+        // accept_transfer is behind the gate and never runs
+        assert!(safe_mode.reach(RemoteControl::FileTransfer, &slot).is_err());
+        let manager = slot.as_ref().unwrap();
+        assert_eq!(manager.get_transfer_info(&transfer_id).unwrap().status, TransferStatus::Pending);
+        assert!(replies.try_recv().is_err());
+
+        // Outside safe mode the offer is accepted and the sender told so
+        let manager = SafeMode::default().reach(RemoteControl::FileTransfer, &slot).unwrap().unwrap();
+        manager.accept_transfer(&transfer_id, &dir.join("received.txt")).await.unwrap();
+        assert!(replies.try_recv().is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
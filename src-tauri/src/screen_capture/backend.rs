@@ -0,0 +1,389 @@
+// screen_capture/backend.rs - Encoder pipelines behind a common interface
+
+use std::collections::HashSet;
+use std::io::{ErrorKind, Read};
+use std::os::fd::RawFd;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::screen_capture::config::ScreenCaptureConfig;
+use crate::screen_capture::error::{ScreenCaptureError, to_capture_error, to_ffmpeg_error};
+use crate::screen_capture::privacy;
+use crate::screen_capture::scale;
+use crate::screen_capture::supervisor::StderrCollector;
+use crate::screen_capture::types::{HardwareAcceleration, MonitorInfo, VideoCodec};
+
+/// Size of the reads from an encoder process's stdout
+const READ_CHUNK_SIZE: usize = 65536;
+
+/// How often an encoder process is polled after it closed its stdout
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Elements every GStreamer capture pipeline needs besides the encoder
+pub const GSTREAMER_BASE_ELEMENTS: [&str; 5] = ["pipewiresrc", "videorate", "videoconvert", "matroskamux", "appsink"];
+
+/// Sink of in-process pipelines; the backend pulls the stream from it by name
+pub const GSTREAMER_APPSINK: [&str; 3] = ["appsink", "name=sink", "sync=false"];
+
+/// Sink of gst-launch pipelines, the stream goes to stdout like ffmpeg's
+pub const GSTREAMER_FDSINK: [&str; 2] = ["fdsink", "fd=1"];
+
+/// Output of a running encoder
+#[derive(Debug)]
+pub enum EncoderOutput {
+    /// Next piece of the matroska stream
+    Data(Vec<u8>),
+    /// The encoder stopped by itself; the reason ends up in the restart report
+    Ended(String),
+}
+
+/// An encoder pipeline that turns the captured screen into a matroska stream.
+///
+/// All backends emit the same container, so the capture loop demuxes,
+/// buffers and counts frames the same way whichever backend is active.
+pub trait EncoderBackend: Send {
+    /// Name used in logs and restart reasons
+    fn name(&self) -> &'static str;
+
+    /// Prepares the pipeline; errors in its description surface here, before any frame flows
+    fn build(&mut self) -> Result<(), ScreenCaptureError>;
+
+    /// Starts encoding. The stream arrives on the returned channel, which is
+    /// closed once the backend has stopped.
+    fn start(&mut self) -> Result<Receiver<EncoderOutput>, ScreenCaptureError>;
+
+    /// Error output of the encoder, classified by the supervisor after a crash
+    fn diagnostics(&self) -> StderrCollector;
+
+    /// Stops encoding and releases the pipeline
+    fn stop(&mut self);
+}
+
+/// Where a GStreamer pipeline reads the screen from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PipeWireSource {
+    /// Stream of an xdg-desktop-portal session, reached through its remote fd
+    Portal { node_id: u32, fd: RawFd },
+    /// Node of the user's PipeWire daemon; None for its default source
+    Node(Option<u32>),
+}
+
+/// Encoder running as an external process that writes matroska to stdout
+pub struct ProcessBackend {
+    name: &'static str,
+    command: Command,
+    spawn_error: fn(std::io::Error) -> ScreenCaptureError,
+    process: Arc<Mutex<Option<Child>>>,
+    stderr: StderrCollector,
+}
+
+impl ProcessBackend {
+    /// ffmpeg reading a PipeWire node
+    pub fn ffmpeg(command: Command) -> Self {
+        Self::new("FFmpeg", command, |e| to_ffmpeg_error(e, "Failed to start FFmpeg process with PipeWire"))
+    }
+
+    /// gst-launch reading a portal stream; ffmpeg has no input that accepts the portal's fd
+    pub fn gst_launch(command: Command) -> Self {
+        Self::new("gst-launch", command, |e| ScreenCaptureError::PipeWireError(
+            format!("Failed to start GStreamer pipeline for portal stream: {}", e)
+        ))
+    }
+
+    fn new(name: &'static str, command: Command, spawn_error: fn(std::io::Error) -> ScreenCaptureError) -> Self {
+        ProcessBackend {
+            name,
+            command,
+            spawn_error,
+            process: Arc::new(Mutex::new(None)),
+            stderr: StderrCollector::default(),
+        }
+    }
+
+    /// Waits for the process after it closed stdout; None if it was stopped
+    /// on request. The lock is released between polls so `stop` can kill it.
+    fn exit_reason(name: &str, process: &Mutex<Option<Child>>) -> Option<String> {
+        loop {
+            match process.lock().unwrap().as_mut()?.try_wait() {
+                Ok(Some(status)) => return Some(format!("{} exited with {}", name, status)),
+                Ok(None) => {},
+                Err(e) => return Some(format!("Error checking {} process: {}", name, e)),
+            }
+            thread::sleep(EXIT_POLL_INTERVAL);
+        }
+    }
+}
+
+impl EncoderBackend for ProcessBackend {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn build(&mut self) -> Result<(), ScreenCaptureError> {
+        // stdout carries the stream, stderr is kept so crashes can be diagnosed
+        self.command.stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        Ok(())
+    }
+
+    fn start(&mut self) -> Result<Receiver<EncoderOutput>, ScreenCaptureError> {
+        let mut process = self.command.spawn().map_err(self.spawn_error)?;
+        self.stderr = StderrCollector::spawn(process.stderr.take());
+        let mut stdout = process.stdout.take()
+            .ok_or_else(|| to_capture_error("stdout is not piped", &format!("Failed to read from {}", self.name)))?;
+        *self.process.lock().unwrap() = Some(process);
+
+        let (sender, receiver) = mpsc::channel();
+        let name = self.name;
+        let process = self.process.clone();
+        thread::spawn(move || {
+            let mut buffer = vec![0u8; READ_CHUNK_SIZE];
+            let read_error = loop {
+                match stdout.read(&mut buffer) {
+                    Ok(0) => break None,
+                    Ok(n) => {
+                        // The capture loop is gone, nobody reads the stream anymore
+                        if sender.send(EncoderOutput::Data(buffer[..n].to_vec())).is_err() {
+                            return;
+                        }
+                    },
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {},
+                    Err(e) => break Some(format!("Error reading from {}: {}", name, e)),
+                }
+            };
+
+            let reason = match read_error {
+                Some(reason) => reason,
+                None => match Self::exit_reason(name, &process) {
+                    Some(reason) => reason,
+                    None => return,
+                },
+            };
+            let _ = sender.send(EncoderOutput::Ended(reason));
+        });
+
+        Ok(receiver)
+    }
+
+    fn diagnostics(&self) -> StderrCollector {
+        self.stderr.clone()
+    }
+
+    fn stop(&mut self) {
+        // Killing the process closes stdout, which ends the reader thread
+        if let Some(mut process) = self.process.lock().unwrap().take() {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+    }
+}
+
+impl Drop for ProcessBackend {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// GStreamer encoder element for a combination; None if GStreamer has none
+pub fn gstreamer_encoder(codec: &VideoCodec, acceleration: &HardwareAcceleration) -> Option<&'static str> {
+    match (acceleration, codec) {
+        (HardwareAcceleration::None, VideoCodec::H264) => Some("x264enc"),
+        (HardwareAcceleration::None, VideoCodec::VP8) => Some("vp8enc"),
+        (HardwareAcceleration::None, VideoCodec::VP9) => Some("vp9enc"),
+        (HardwareAcceleration::None, VideoCodec::AV1) => Some("av1enc"),
+        (HardwareAcceleration::VAAPI, VideoCodec::H264) => Some("vaapih264enc"),
+        (HardwareAcceleration::NVENC, VideoCodec::H264) => Some("nvh264enc"),
+        (HardwareAcceleration::QuickSync, VideoCodec::H264) => Some("qsvh264enc"),
+        _ => None,
+    }
+}
+
+/// GStreamer elements usable for capture pipelines, out of the base elements
+/// and `encoders`. Empty if a base element is missing or the binary was built
+/// without the `gstreamer-backend` feature.
+pub fn gstreamer_elements(encoders: &[&str]) -> HashSet<String> {
+    #[cfg(feature = "gstreamer-backend")]
+    {
+        crate::screen_capture::gst_pipeline::available_elements(&GSTREAMER_BASE_ELEMENTS, encoders)
+    }
+    #[cfg(not(feature = "gstreamer-backend"))]
+    {
+        let _ = encoders;
+        HashSet::new()
+    }
+}
+
+/// Capture pipeline from `source` through the encoder into the matroska
+/// muxer, followed by `sink`. The tokens are gst-launch arguments; joined
+/// with spaces they are also the description of an in-process pipeline.
+pub fn gstreamer_pipeline(
+    config: &ScreenCaptureConfig,
+    monitor: &MonitorInfo,
+    output_scale_percent: u32,
+    source: &PipeWireSource,
+    sink: &[&str],
+) -> Vec<String> {
+    let mut elements = vec!["pipewiresrc".to_string()];
+    match source {
+        PipeWireSource::Portal { node_id, fd } => {
+            elements.push(format!("fd={}", fd));
+            elements.push(format!("path={}", node_id));
+        },
+        PipeWireSource::Node(Some(node_id)) => elements.push(format!("path={}", node_id)),
+        PipeWireSource::Node(None) => {},
+    }
+    elements.extend([
+        "do-timestamp=true".to_string(),
+        "!".to_string(),
+        "videorate".to_string(),
+        "!".to_string(),
+        format!("video/x-raw,framerate={}/1", config.fps),
+        "!".to_string(),
+        "videoconvert".to_string(),
+        "!".to_string(),
+    ]);
+
+    // videocrop takes the margins to remove on each side
+    let region = config.effective_region(monitor);
+    if config.capture_region.is_some() {
+        elements.extend([
+            "videocrop".to_string(),
+            format!("left={}", region.x),
+            format!("top={}", region.y),
+            format!("right={}", monitor.width.saturating_sub(region.x + region.width)),
+            format!("bottom={}", monitor.height.saturating_sub(region.y + region.height)),
+            "!".to_string(),
+        ]);
+    }
+
+    // Black boxes over the masks; the compositor cannot pixelate
+    let (mask_elements, mask_sources) = privacy::gstreamer_mask_elements(&config.privacy, &region, config.fps);
+    elements.extend(mask_elements);
+
+    let (output_width, output_height) = config.output_size(monitor, output_scale_percent);
+    if (output_width, output_height) != (region.width, region.height) {
+        elements.extend(scale::gstreamer_scale_elements(output_width, output_height));
+    }
+
+    // Accelerators without an element for the codec encode in software
+    let keyframe_interval = config.keyframe_interval;
+    let bitrate = config.bitrate.unwrap_or(4000);
+    let encoder = gstreamer_encoder(&config.codec, &config.hardware_acceleration)
+        .or_else(|| gstreamer_encoder(&config.codec, &HardwareAcceleration::None))
+        .unwrap_or("x264enc");
+    elements.push(encoder.to_string());
+    let properties = match encoder {
+        "vaapih264enc" => vec![format!("keyframe-period={}", keyframe_interval), format!("bitrate={}", bitrate)],
+        "nvh264enc" => vec![
+            "preset=low-latency-hp".to_string(),
+            format!("gop-size={}", keyframe_interval),
+            format!("bitrate={}", bitrate),
+        ],
+        "qsvh264enc" => vec![
+            "low-latency=true".to_string(),
+            format!("gop-size={}", keyframe_interval),
+            format!("bitrate={}", bitrate),
+        ],
+        "x264enc" => vec![
+            "tune=zerolatency".to_string(),
+            "speed-preset=ultrafast".to_string(),
+            format!("key-int-max={}", keyframe_interval),
+            format!("bitrate={}", bitrate),
+        ],
+        "av1enc" => vec![
+            "usage-profile=realtime".to_string(),
+            "cpu-used=8".to_string(),
+            format!("keyframe-max-dist={}", keyframe_interval),
+        ],
+        // vp8enc and vp9enc
+        _ => vec![
+            "deadline=1".to_string(),
+            "cpu-used=8".to_string(),
+            format!("keyframe-max-dist={}", keyframe_interval),
+        ],
+    };
+    elements.extend(properties);
+    if config.codec == VideoCodec::H264 {
+        elements.extend(["!".to_string(), "h264parse".to_string()]);
+    }
+
+    // Same matroska container as the ffmpeg path
+    elements.extend([
+        "!".to_string(),
+        "matroskamux".to_string(),
+        "streamable=true".to_string(),
+        "!".to_string(),
+    ]);
+    elements.extend(sink.iter().map(|element| element.to_string()));
+
+    // The mask sources feed the compositor as separate branches
+    elements.extend(mask_sources);
+
+    elements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+    use crate::test_env::monitor;
+
+    #[test]
+    fn test_gstreamer_pipeline_uses_hardware_encoder() {
+        let config = ScreenCaptureConfig {
+            hardware_acceleration: HardwareAcceleration::VAAPI,
+            ..ScreenCaptureConfig::default()
+        };
+
+        let pipeline = gstreamer_pipeline(&config, &monitor(), 100, &PipeWireSource::Node(Some(42)), &GSTREAMER_APPSINK).join(" ");
+        assert!(pipeline.starts_with("pipewiresrc path=42 do-timestamp=true ! videorate ! video/x-raw,framerate=30/1 ! videoconvert ! vaapih264enc "), "{}", pipeline);
+        assert!(pipeline.ends_with(" ! h264parse ! matroskamux streamable=true ! appsink name=sink sync=false"), "{}", pipeline);
+
+        let portal = gstreamer_pipeline(&config, &monitor(), 100, &PipeWireSource::Portal { node_id: 7, fd: 12 }, &GSTREAMER_FDSINK).join(" ");
+        assert!(portal.starts_with("pipewiresrc fd=12 path=7 "), "{}", portal);
+        assert!(portal.ends_with(" ! fdsink fd=1"), "{}", portal);
+    }
+
+    #[test]
+    fn test_gstreamer_pipeline_falls_back_to_software_encoder() {
+        // NVENC has no VP9 element
+        let config = ScreenCaptureConfig {
+            codec: VideoCodec::VP9,
+            hardware_acceleration: HardwareAcceleration::NVENC,
+            ..ScreenCaptureConfig::default()
+        };
+        assert_eq!(gstreamer_encoder(&config.codec, &config.hardware_acceleration), None);
+
+        let pipeline = gstreamer_pipeline(&config, &monitor(), 100, &PipeWireSource::Node(None), &GSTREAMER_APPSINK);
+        assert!(pipeline.contains(&"vp9enc".to_string()));
+        assert!(!pipeline.contains(&"h264parse".to_string()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_process_backend_streams_until_exit() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "printf stream; echo 'No such device' >&2; exit 3"]);
+        let mut backend = ProcessBackend::ffmpeg(command);
+        backend.build().unwrap();
+        let output = backend.start().unwrap();
+
+        let mut data = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let reason = loop {
+            assert!(Instant::now() < deadline, "no exit reported");
+            match output.recv_timeout(Duration::from_secs(5)).unwrap() {
+                EncoderOutput::Data(chunk) => data.extend(chunk),
+                EncoderOutput::Ended(reason) => break reason,
+            }
+        };
+
+        assert_eq!(data, b"stream");
+        assert!(reason.starts_with("FFmpeg exited with"), "{}", reason);
+        assert!(backend.diagnostics().wait_closed(Duration::from_secs(2)));
+        assert_eq!(backend.diagnostics().last_line().as_deref(), Some("No such device"));
+    }
+}
@@ -2,13 +2,14 @@
 
 use std::collections::VecDeque;
 use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
 use crate::screen_capture::types::FrameData;
 use crate::screen_capture::error::ScreenCaptureError;
 
 /// Stream buffer for managing continuous video streams
 pub struct StreamBuffer {
-    /// Queue of video frame chunks
-    chunks: VecDeque<FrameData>,
+    /// Queue of video frame chunks with their arrival time
+    chunks: VecDeque<BufferedFrame>,
     
     /// Maximum number of chunks to store
     max_size: usize,
@@ -19,44 +20,92 @@ pub struct StreamBuffer {
     /// Maximum buffer size in bytes
     max_bytes: usize,
     
-    /// Whether to drop old frames when buffer is full
+    /// Which frames to drop when the buffer is full
     drop_mode: DropMode,
     
+    /// Frames waiting longer than this are discarded (None = no budget)
+    max_latency: Option<Duration>,
+    
     /// Time at which the newest frame was added
     latest_timestamp: Option<Instant>,
     
     /// Estimated frame duration based on configured FPS
     frame_duration: Duration,
     
+    /// While set, no frames reach the reader
+    blanked: bool,
+    
+    /// After unblanking, frames are held back until the next keyframe
+    awaiting_keyframe: bool,
+    
     /// Stats about the buffer
     stats: BufferStats,
 }
 
-/// Mode for handling buffer overflow
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// A frame together with the time it entered the buffer
+struct BufferedFrame {
+    frame: FrameData,
+    received: Instant,
+}
+
+/// Strategy for making room when the buffer is full
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum DropMode {
-    /// Drop oldest frames when buffer is full
+    /// Evict the oldest frame; the stream stays as current as possible
     DropOldest,
     
-    /// Drop newest frames when buffer is full (better for maintaining temporal consistency)
+    /// Reject the incoming frame; what is buffered stays intact
     DropNewest,
     
-    /// Drop alternating frames to maintain some temporal consistency
-    DropAlternating,
+    /// Evict the oldest non-keyframe, and the oldest frame if only keyframes are left
+    DropNonKeyframesFirst,
+}
+
+/// Runtime-tunable buffer settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BufferConfig {
+    pub max_frames: usize,
+    pub max_bytes_mb: usize,
+    pub drop_mode: DropMode,
+    /// Discard frames older than this many milliseconds (None = unlimited)
+    pub max_latency_ms: Option<u32>,
+}
+
+/// Frames dropped, by reason
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DropCounters {
+    /// Oldest frame evicted because the buffer was full
+    pub oldest_evicted: u64,
+    
+    /// Incoming frame rejected because the buffer was full
+    pub newest_rejected: u64,
+    
+    /// Non-keyframe evicted because the buffer was full
+    pub non_keyframe_evicted: u64,
     
-    /// Drop non-keyframes first, then oldest keyframes
-    DropNonKeyframes,
+    /// Frame waited longer than the latency budget
+    pub latency_exceeded: u64,
+    
+    /// Frame trimmed after the buffer was shrunk
+    pub resized: u64,
+    
+    /// Frame withheld while the output was blanked
+    #[serde(default)]
+    pub blanked: u64,
 }
 
 /// Statistics about the buffer
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BufferStats {
     /// Number of frames added
     pub frames_added: u64,
     
-    /// Number of frames dropped due to buffer overflow
+    /// Number of frames dropped, for any reason
     pub frames_dropped: u64,
     
+    /// Dropped frames broken down by reason
+    pub dropped_by_reason: DropCounters,
+    
     /// Number of frames read from the buffer
     pub frames_read: u64,
     
@@ -66,8 +115,22 @@ pub struct BufferStats {
     /// Current number of frames in buffer
     pub frame_count: usize,
     
-    /// Estimated buffer latency in milliseconds
+    /// How long the oldest buffered frame has been waiting, in milliseconds
     pub latency_ms: f64,
+    
+    /// Current bytes in the buffer
+    pub bytes: usize,
+}
+
+/// Why a frame left the buffer without being read
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DropReason {
+    OldestEvicted,
+    NewestRejected,
+    NonKeyframeEvicted,
+    LatencyExceeded,
+    Resized,
+    Blanked,
 }
 
 impl StreamBuffer {
@@ -82,26 +145,49 @@ impl StreamBuffer {
             total_bytes: 0,
             max_bytes,
             drop_mode,
+            max_latency: None,
             latest_timestamp: None,
             frame_duration,
+            blanked: false,
+            awaiting_keyframe: false,
             stats: BufferStats {
                 frames_added: 0,
                 frames_dropped: 0,
+                dropped_by_reason: DropCounters::default(),
                 frames_read: 0,
                 fill_ratio: 0.0,
                 frame_count: 0,
                 latency_ms: 0.0,
+                bytes: 0,
             },
         }
     }
     
     /// Push a new frame to the buffer
     pub fn push_frame(&mut self, frame: FrameData) -> Result<(), ScreenCaptureError> {
+        self.push_frame_at(frame, Instant::now())
+    }
+    
+    fn push_frame_at(&mut self, frame: FrameData, now: Instant) -> Result<(), ScreenCaptureError> {
         let frame_size = frame.data.len();
         
         // Update statistics
         self.stats.frames_added += 1;
-        self.latest_timestamp = Some(Instant::now());
+        self.latest_timestamp = Some(now);
+        
+        // A decoder cannot resume from a delta frame, so after blanking the
+        // stream restarts at a keyframe
+        if self.awaiting_keyframe && frame.keyframe {
+            self.awaiting_keyframe = false;
+        }
+        if self.blanked || self.awaiting_keyframe {
+            self.record_drop(DropReason::Blanked);
+            self.update_stats(now);
+            return Ok(());
+        }
+        
+        // Stale frames go first, they may already make enough room
+        self.expire_frames(now);
         
         // Check if buffer is full (by frames or bytes)
         let is_buffer_full = self.chunks.len() >= self.max_size || 
@@ -110,94 +196,57 @@ impl StreamBuffer {
         if is_buffer_full {
             match self.drop_mode {
                 DropMode::DropOldest => {
-                    // Drop oldest frame to make room
-                    if let Some(old_frame) = self.chunks.pop_front() {
-                        self.total_bytes -= old_frame.data.len();
-                        self.stats.frames_dropped += 1;
-                    }
+                    self.drop_front(DropReason::OldestEvicted);
                 },
                 DropMode::DropNewest => {
                     // Don't add the new frame
-                    self.stats.frames_dropped += 1;
+                    self.record_drop(DropReason::NewestRejected);
+                    self.update_stats(now);
                     return Ok(());
                 },
-                DropMode::DropAlternating => {
-                    // Drop every other frame, starting from the oldest
-                    let mut dropped = false;
-                    for i in 0..self.chunks.len() {
-                        if i % 2 == 0 {
-                            if let Some(removed_frame) = self.chunks.remove(i) {
-                                self.total_bytes -= removed_frame.data.len();
-                                self.stats.frames_dropped += 1;
-                                dropped = true;
-                                break;
-                            }
-                        }
-                    }
-                    
-                    // If we couldn't drop any alternating frames, drop the oldest
-                    if !dropped {
-                        if let Some(old_frame) = self.chunks.pop_front() {
-                            self.total_bytes -= old_frame.data.len();
-                            self.stats.frames_dropped += 1;
-                        }
-                    }
-                },
-                DropMode::DropNonKeyframes => {
-                    // First try to drop non-keyframes
-                    let mut dropped = false;
-                    
+                DropMode::DropNonKeyframesFirst => {
                     // Find the oldest non-keyframe
-                    for i in 0..self.chunks.len() {
-                        if !self.chunks[i].keyframe {
-                            if let Some(removed_frame) = self.chunks.remove(i) {
-                                self.total_bytes -= removed_frame.data.len();
-                                self.stats.frames_dropped += 1;
-                                dropped = true;
-                                break;
+                    match self.chunks.iter().position(|buffered| !buffered.frame.keyframe) {
+                        Some(index) => {
+                            if let Some(removed) = self.chunks.remove(index) {
+                                self.total_bytes -= removed.frame.data.len();
+                                self.record_drop(DropReason::NonKeyframeEvicted);
                             }
-                        }
-                    }
-                    
-                    // If we couldn't drop any non-keyframes, drop the oldest frame
-                    if !dropped {
-                        if let Some(old_frame) = self.chunks.pop_front() {
-                            self.total_bytes -= old_frame.data.len();
-                            self.stats.frames_dropped += 1;
-                        }
+                        },
+                        // Only keyframes left, drop the oldest one
+                        None => self.drop_front(DropReason::OldestEvicted),
                     }
                 },
             }
         }
         
         // Add the new frame
-        self.chunks.push_back(frame);
+        self.chunks.push_back(BufferedFrame { frame, received: now });
         self.total_bytes += frame_size;
         
         // Update buffer statistics
-        self.update_stats();
+        self.update_stats(now);
         
         Ok(())
     }
     
     /// Get the next frame from the buffer
     pub fn get_next_frame(&mut self) -> Option<FrameData> {
-        if self.chunks.is_empty() {
-            return None;
-        }
+        let now = Instant::now();
+        self.expire_frames(now);
         
-        let frame = self.chunks.pop_front()?;
-        self.total_bytes -= frame.data.len();
+        let buffered = self.chunks.pop_front()?;
+        self.total_bytes -= buffered.frame.data.len();
         
         self.stats.frames_read += 1;
-        self.update_stats();
+        self.update_stats(now);
         
-        Some(frame)
+        Some(buffered.frame)
     }
     
     /// Peek at the next frame without removing it
     pub fn peek_next_frame(&self) -> Option<&FrameData> {
-        self.chunks.front()
+        self.chunks.front().map(|buffered| &buffered.frame)
     }
     
     /// Get the number of frames in the buffer
@@ -215,12 +264,14 @@ impl StreamBuffer {
         self.chunks.clear();
         self.total_bytes = 0;
         self.latest_timestamp = None;
-        self.update_stats();
+        self.update_stats(Instant::now());
     }
     
-    /// Get the buffer statistics
-    pub fn get_stats(&self) -> &BufferStats {
-        &self.stats
+    /// Get the buffer statistics, with the latency measured now
+    pub fn get_stats(&self) -> BufferStats {
+        let mut stats = self.stats.clone();
+        stats.latency_ms = self.latency_at(Instant::now());
+        stats
     }
     
     /// Get buffer fill ratio (0.0 - 1.0)
@@ -231,10 +282,9 @@ impl StreamBuffer {
         self.chunks.len() as f32 / self.max_size as f32
     }
     
-    /// Get estimated latency in milliseconds
+    /// Get the latency in milliseconds: how long the oldest frame has waited
     pub fn get_latency_ms(&self) -> f64 {
-        // Latency is roughly the time it would take to play all frames in the buffer
-        (self.chunks.len() as f64) * self.frame_duration.as_secs_f64() * 1000.0
+        self.latency_at(Instant::now())
     }
     
     /// Get the current buffer size in bytes
@@ -247,11 +297,70 @@ impl StreamBuffer {
         self.max_bytes
     }
     
+    /// Current settings
+    pub fn get_config(&self) -> BufferConfig {
+        BufferConfig {
+            max_frames: self.max_size,
+            max_bytes_mb: self.max_bytes / (1024 * 1024),
+            drop_mode: self.drop_mode,
+            max_latency_ms: self.max_latency.map(|latency| latency.as_millis() as u32),
+        }
+    }
+    
+    /// Apply new settings; frames that no longer fit are dropped right away
+    pub fn configure(&mut self, config: &BufferConfig) {
+        self.drop_mode = config.drop_mode;
+        self.max_latency = config.max_latency_ms.map(|ms| Duration::from_millis(ms as u64));
+        self.resize(config.max_frames, config.max_bytes_mb);
+    }
+    
+    fn latency_at(&self, now: Instant) -> f64 {
+        self.chunks.front()
+            .map(|buffered| now.saturating_duration_since(buffered.received).as_secs_f64() * 1000.0)
+            .unwrap_or(0.0)
+    }
+    
+    /// Discard frames that waited longer than the latency budget
+    fn expire_frames(&mut self, now: Instant) {
+        let max_latency = match self.max_latency {
+            Some(latency) => latency,
+            None => return,
+        };
+        
+        while let Some(oldest) = self.chunks.front() {
+            if now.saturating_duration_since(oldest.received) <= max_latency {
+                break;
+            }
+            self.drop_front(DropReason::LatencyExceeded);
+        }
+    }
+    
+    fn drop_front(&mut self, reason: DropReason) {
+        if let Some(old_frame) = self.chunks.pop_front() {
+            self.total_bytes -= old_frame.frame.data.len();
+            self.record_drop(reason);
+        }
+    }
+    
+    fn record_drop(&mut self, reason: DropReason) {
+        self.stats.frames_dropped += 1;
+        let counters = &mut self.stats.dropped_by_reason;
+        match reason {
+            DropReason::OldestEvicted => counters.oldest_evicted += 1,
+            DropReason::NewestRejected => counters.newest_rejected += 1,
+            DropReason::NonKeyframeEvicted => counters.non_keyframe_evicted += 1,
+            DropReason::LatencyExceeded => counters.latency_exceeded += 1,
+            DropReason::Resized => counters.resized += 1,
+            DropReason::Blanked => counters.blanked += 1,
+        }
+    }
+    
     /// Update buffer statistics
-    fn update_stats(&mut self) {
+    fn update_stats(&mut self, now: Instant) {
         self.stats.fill_ratio = self.get_fill_ratio();
         self.stats.frame_count = self.chunks.len();
-        self.stats.latency_ms = self.get_latency_ms();
+        self.stats.latency_ms = self.latency_at(now);
+        self.stats.bytes = self.total_bytes;
     }
     
     /// Adjust the buffer size
@@ -261,19 +370,20 @@ impl StreamBuffer {
         
         // Trim the buffer if it's now over the new max size
         while self.chunks.len() > self.max_size || self.total_bytes > self.max_bytes {
-            if let Some(old_frame) = self.chunks.pop_front() {
-                self.total_bytes -= old_frame.data.len();
-                self.stats.frames_dropped += 1;
-            }
+            self.drop_front(DropReason::Resized);
         }
         
-        self.update_stats();
+        self.update_stats(Instant::now());
     }
     
-    /// Set a new frame rate for latency calculation
+    /// Set a new frame rate
     pub fn set_fps(&mut self, fps: u32) {
         self.frame_duration = Duration::from_secs_f64(1.0 / fps as f64);
-        self.update_stats();
+    }
+    
+    /// Nominal duration of one frame at the configured frame rate
+    pub fn frame_duration(&self) -> Duration {
+        self.frame_duration
     }
     
     /// Get all frames, draining the buffer
@@ -291,6 +401,29 @@ impl StreamBuffer {
     pub fn set_drop_mode(&mut self, mode: DropMode) {
         self.drop_mode = mode;
     }
+    
+    /// Withhold all frames, e.g. while the host has locally cut off the
+    /// remote peer. Buffered frames are discarded right away.
+    pub fn set_blanked(&mut self, blanked: bool) {
+        if blanked == self.blanked {
+            return;
+        }
+        
+        self.blanked = blanked;
+        if blanked {
+            while !self.chunks.is_empty() {
+                self.drop_front(DropReason::Blanked);
+            }
+            self.update_stats(Instant::now());
+        } else {
+            self.awaiting_keyframe = true;
+        }
+    }
+    
+    /// Whether frames are currently withheld
+    pub fn is_blanked(&self) -> bool {
+        self.blanked
+    }
 }
 
 #[cfg(test)]
@@ -309,6 +442,9 @@ mod tests {
             width: 640,
             height: 480,
             format: "h264".to_string(),
+            frame_id: 0,
+            captured_us: 0,
+            encoded_us: 0,
         };
         
         let frame2 = FrameData {
@@ -318,6 +454,9 @@ mod tests {
             width: 640,
             height: 480,
             format: "h264".to_string(),
+            frame_id: 0,
+            captured_us: 0,
+            encoded_us: 0,
         };
         
         // Test push and get
@@ -351,6 +490,9 @@ mod tests {
                 width: 640,
                 height: 480,
                 format: "h264".to_string(),
+                frame_id: 0,
+                captured_us: 0,
+                encoded_us: 0,
             };
             
             buffer.push_frame(frame).unwrap();
@@ -363,4 +505,132 @@ mod tests {
         let next_frame = buffer.get_next_frame().unwrap();
         assert_eq!(next_frame.timestamp, 3);
     }
+    
+    fn synthetic_frames(keyframes: &[bool]) -> Vec<FrameData> {
+        keyframes.iter().enumerate()
+            .map(|(i, &keyframe)| FrameData {
+                data: vec![0; 100],
+                timestamp: i as u64 + 1,
+                keyframe,
+                width: 640,
+                height: 480,
+                format: "h264".to_string(),
+                frame_id: 0,
+                captured_us: 0,
+                encoded_us: 0,
+            })
+            .collect()
+    }
+    
+    fn buffered_timestamps(buffer: &mut StreamBuffer) -> Vec<u64> {
+        buffer.drain().iter().map(|frame| frame.timestamp).collect()
+    }
+    
+    #[test]
+    fn test_drop_newest_keeps_buffered_frames() {
+        let mut buffer = StreamBuffer::new(3, 10, 30, DropMode::DropNewest);
+        for frame in synthetic_frames(&[true, false, false, false, false]) {
+            buffer.push_frame(frame).unwrap();
+        }
+        
+        let stats = buffer.get_stats();
+        assert_eq!(stats.frames_dropped, 2);
+        assert_eq!(stats.dropped_by_reason.newest_rejected, 2);
+        assert_eq!(buffered_timestamps(&mut buffer), vec![1, 2, 3]);
+    }
+    
+    #[test]
+    fn test_drop_oldest_counts_evictions() {
+        let mut buffer = StreamBuffer::new(2, 10, 30, DropMode::DropOldest);
+        for frame in synthetic_frames(&[true, false, false, false]) {
+            buffer.push_frame(frame).unwrap();
+        }
+        
+        assert_eq!(buffer.get_stats().dropped_by_reason.oldest_evicted, 2);
+        assert_eq!(buffered_timestamps(&mut buffer), vec![3, 4]);
+    }
+    
+    #[test]
+    fn test_drop_non_keyframes_first() {
+        let mut buffer = StreamBuffer::new(3, 10, 30, DropMode::DropNonKeyframesFirst);
+        // Each overflow evicts the oldest delta frame
+        for frame in synthetic_frames(&[true, false, true, false, true]) {
+            buffer.push_frame(frame).unwrap();
+        }
+        
+        let stats = buffer.get_stats();
+        assert_eq!(stats.dropped_by_reason.non_keyframe_evicted, 2);
+        assert_eq!(stats.dropped_by_reason.oldest_evicted, 0);
+        assert_eq!(buffered_timestamps(&mut buffer), vec![1, 3, 5]);
+        
+        // Only keyframes buffered: the oldest keyframe goes
+        let mut buffer = StreamBuffer::new(2, 10, 30, DropMode::DropNonKeyframesFirst);
+        for frame in synthetic_frames(&[true, true, true]) {
+            buffer.push_frame(frame).unwrap();
+        }
+        assert_eq!(buffer.get_stats().dropped_by_reason.oldest_evicted, 1);
+        assert_eq!(buffered_timestamps(&mut buffer), vec![2, 3]);
+    }
+    
+    #[test]
+    fn test_latency_budget_discards_stale_frames() {
+        let mut buffer = StreamBuffer::new(10, 10, 30, DropMode::DropOldest);
+        buffer.configure(&BufferConfig {
+            max_frames: 10,
+            max_bytes_mb: 10,
+            drop_mode: DropMode::DropOldest,
+            max_latency_ms: Some(100),
+        });
+        
+        let now = Instant::now();
+        let mut frames = synthetic_frames(&[true, false, false]).into_iter();
+        buffer.push_frame_at(frames.next().unwrap(), now - Duration::from_millis(500)).unwrap();
+        buffer.push_frame_at(frames.next().unwrap(), now - Duration::from_millis(50)).unwrap();
+        buffer.push_frame_at(frames.next().unwrap(), now).unwrap();
+        
+        let stats = buffer.get_stats();
+        assert_eq!(stats.dropped_by_reason.latency_exceeded, 1);
+        assert!(stats.latency_ms >= 50.0 && stats.latency_ms < 100.0);
+        assert_eq!(buffered_timestamps(&mut buffer), vec![2, 3]);
+    }
+    
+    #[test]
+    fn test_configure_shrinks_buffer() {
+        let mut buffer = StreamBuffer::new(5, 10, 30, DropMode::DropOldest);
+        for frame in synthetic_frames(&[true, false, false, false]) {
+            buffer.push_frame(frame).unwrap();
+        }
+        
+        buffer.configure(&BufferConfig {
+            max_frames: 2,
+            max_bytes_mb: 10,
+            drop_mode: DropMode::DropNewest,
+            max_latency_ms: None,
+        });
+        
+        assert_eq!(buffer.get_config().drop_mode, DropMode::DropNewest);
+        assert_eq!(buffer.get_stats().dropped_by_reason.resized, 2);
+        assert_eq!(buffered_timestamps(&mut buffer), vec![3, 4]);
+    }
+    
+    #[test]
+    fn test_blanking_withholds_frames_until_next_keyframe() {
+        let mut buffer = StreamBuffer::new(10, 10, 30, DropMode::DropOldest);
+        let mut frames = synthetic_frames(&[true, false, false, false, true, false]).into_iter();
+        buffer.push_frame(frames.next().unwrap()).unwrap();
+        
+        buffer.set_blanked(true);
+        assert!(buffer.is_empty());
+        buffer.push_frame(frames.next().unwrap()).unwrap();
+        assert!(buffer.is_empty());
+        
+        // Delta frames after unblanking cannot be decoded on their own
+        buffer.set_blanked(false);
+        for frame in frames {
+            buffer.push_frame(frame).unwrap();
+        }
+        
+        assert_eq!(buffer.get_stats().dropped_by_reason.blanked, 4);
+        assert_eq!(buffered_timestamps(&mut buffer), vec![5, 6]);
+    }
 }
@@ -0,0 +1,104 @@
+// screen_capture/clock.rs - Monotonic clock for frame timestamps
+//
+// Frames are stamped in microseconds since one epoch for the whole process,
+// so the stamps of all captures and of the frame stream compare directly.
+// The frontend relates this clock to its own through `clock_sync` events.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::screen_capture::types::FrameData;
+
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+/// Frame IDs are unique across captures, so a render report needs no monitor
+static NEXT_FRAME_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Microseconds from the epoch to `at`; instants before the epoch give 0
+pub fn instant_us(at: Instant) -> u64 {
+    let epoch = *EPOCH.get_or_init(Instant::now);
+    at.saturating_duration_since(epoch).as_micros() as u64
+}
+
+/// Current time on the frame clock
+pub fn monotonic_us() -> u64 {
+    instant_us(Instant::now())
+}
+
+/// Stamps the frames of one encoder process.
+///
+/// The encoder's timestamps start at 0 with the process and say nothing about
+/// when the screen was grabbed. The frame that arrived fastest anchors them on
+/// the frame clock, so the estimated capture time leaves out the encoder's
+/// minimum delay and only shows what comes on top of it.
+#[derive(Debug, Default)]
+pub struct FrameClock {
+    /// Smallest gap between encoder timestamp and read time so far
+    offset_us: Option<u64>,
+}
+
+impl FrameClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assigns the next frame ID, the read time `read_at` and the estimated capture time
+    pub fn stamp(&mut self, frame: &mut FrameData, read_at: Instant) {
+        let encoded_us = instant_us(read_at);
+        let pts_us = frame.timestamp.saturating_mul(1000);
+
+        let offset_us = encoded_us.saturating_sub(pts_us);
+        let offset_us = self.offset_us.map_or(offset_us, |anchor| anchor.min(offset_us));
+        self.offset_us = Some(offset_us);
+
+        frame.frame_id = NEXT_FRAME_ID.fetch_add(1, Ordering::Relaxed);
+        frame.captured_us = (pts_us + offset_us).min(encoded_us);
+        frame.encoded_us = encoded_us;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn frame(timestamp: u64) -> FrameData {
+        FrameData {
+            data: Vec::new(),
+            timestamp,
+            keyframe: false,
+            width: 1920,
+            height: 1080,
+            format: "h264".to_string(),
+            frame_id: 0,
+            captured_us: 0,
+            encoded_us: 0,
+        }
+    }
+
+    #[test]
+    fn test_fastest_frame_anchors_capture_times() {
+        let started = Instant::now() + Duration::from_secs(1);
+        let mut clock = FrameClock::new();
+
+        // Read 40 ms after the process started: the anchor
+        let mut first = frame(0);
+        clock.stamp(&mut first, started + Duration::from_millis(40));
+        assert_eq!(first.captured_us, first.encoded_us);
+
+        // 33 ms of stream time later, but read 60 ms later: 27 ms on top
+        let mut second = frame(33);
+        clock.stamp(&mut second, started + Duration::from_millis(100));
+        assert_eq!(second.encoded_us - second.captured_us, 27_000);
+        assert!(second.frame_id > first.frame_id);
+
+        // A faster frame moves the anchor for the ones after it
+        let mut third = frame(66);
+        clock.stamp(&mut third, started + Duration::from_millis(96));
+        assert_eq!(third.captured_us, third.encoded_us);
+        let mut fourth = frame(100);
+        clock.stamp(&mut fourth, started + Duration::from_millis(140));
+        assert_eq!(fourth.encoded_us - fourth.captured_us, 10_000);
+    }
+}
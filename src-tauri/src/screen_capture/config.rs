@@ -1,7 +1,13 @@
 // screen_capture/config.rs - Configuration structures
 
+use std::collections::BTreeMap;
 use serde::{Deserialize, Serialize};
-use crate::screen_capture::types::{VideoCodec, HardwareAcceleration, LatencyMode};
+use crate::screen_capture::error::ScreenCaptureError;
+use crate::screen_capture::types::{VideoCodec, HardwareAcceleration, EncoderBackendKind, LatencyMode, FrameTransport, CursorMode, CaptureRegion, MonitorInfo};
+use crate::screen_capture::privacy::PrivacyConfig;
+
+/// Smallest output scale; below it text on the remote screen is unreadable
+pub const MIN_OUTPUT_SCALE_PERCENT: u32 = 10;
 
 /// Screen capture configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,9 +27,21 @@ pub struct ScreenCaptureConfig {
     /// Hardware acceleration method
     pub hardware_acceleration: HardwareAcceleration,
     
-    /// Whether to capture the cursor
+    /// Encoder pipeline; `Auto` picks FFmpeg or GStreamer from the capability probe
+    #[serde(default)]
+    pub encoder_backend: EncoderBackendKind,
+    
+    /// Whether to capture the cursor; only used when `cursor_mode` is not set
     pub capture_cursor: bool,
     
+    /// How the cursor is shown (embedded, hidden, or rendered by the client)
+    #[serde(default)]
+    pub cursor_mode: Option<CursorMode>,
+    
+    /// Part of the monitor to capture (None = whole monitor)
+    #[serde(default)]
+    pub capture_region: Option<CaptureRegion>,
+    
     /// Whether to capture audio
     pub capture_audio: bool,
     
@@ -38,6 +56,389 @@ pub struct ScreenCaptureConfig {
     
     /// Advanced FFmpeg options (optional)
     pub advanced_options: Option<AdvancedEncodingOptions>,
+    
+    /// How often a crashed capture process is restarted before giving up
+    #[serde(default = "default_max_restart_attempts")]
+    pub max_restart_attempts: u32,
+    
+    /// An encoder that delivers no frames for this long is restarted; 0 disables the check
+    #[serde(default = "default_freeze_timeout_ms")]
+    pub freeze_timeout_ms: u64,
+    
+    /// How frames reach the frontend; `Event` keeps the base64 IPC path
+    #[serde(default)]
+    pub frame_transport: FrameTransport,
+    
+    /// Frame rate throttling while the screen is static
+    #[serde(default)]
+    pub idle: IdleConfig,
+    
+    /// X11 damage tracking: only changed screen areas are grabbed and encoded
+    #[serde(default)]
+    pub damage: DamageConfig,
+    
+    /// PipeWire node captured on wlroots compositors instead of the monitor's stream;
+    /// portal sessions capture the source picked in the consent dialog
+    #[serde(default)]
+    pub pipewire_node: Option<PipeWireNodeSelection>,
+    
+    /// Size of the encoded stream (None = size of the captured area).
+    /// Input coordinates stay in the captured area's pixels either way.
+    #[serde(default)]
+    pub output_resolution: Option<OutputResolution>,
+    
+    /// Areas and windows blacked out or pixelated in the stream
+    #[serde(default)]
+    pub privacy: PrivacyConfig,
+    
+    /// Whether a failing hardware encoder may be replaced by software
+    /// encoding; a power profile can forbid it on battery
+    #[serde(default = "default_true")]
+    pub software_fallback: bool,
+    
+    /// Limits applied while the host runs on battery
+    #[serde(default)]
+    pub power_profiles: PowerProfilesConfig,
+    
+    /// Keep the configured settings on battery
+    #[serde(default)]
+    pub ignore_power_profile: bool,
+    
+    /// Shrinking the stream to the largest viewer's window
+    #[serde(default)]
+    pub viewport_fit: ViewportFitConfig,
+}
+
+/// Size the captured area is scaled to before encoding. The aspect ratio is
+/// kept and the stream is never scaled up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum OutputResolution {
+    /// Fit into a box, e.g. 1920x1080 for a viewer on a 1080p laptop
+    Size { width: u32, height: u32 },
+    
+    /// Percentage of the captured area
+    Scale { percent: u32 },
+}
+
+impl OutputResolution {
+    /// Checks that the resolution yields an encodable stream
+    pub fn validate(&self) -> Result<(), ScreenCaptureError> {
+        match *self {
+            OutputResolution::Size { width, height } if width < 2 || height < 2 => {
+                Err(ScreenCaptureError::InvalidConfig(format!(
+                    "Output resolution {}x{} is too small, at least 2x2 pixels are required", width, height
+                )))
+            },
+            OutputResolution::Scale { percent } if !(MIN_OUTPUT_SCALE_PERCENT..=100).contains(&percent) => {
+                Err(ScreenCaptureError::InvalidConfig(format!(
+                    "Output scale {}% is outside {}-100%", percent, MIN_OUTPUT_SCALE_PERCENT
+                )))
+            },
+            _ => Ok(()),
+        }
+    }
+    
+    /// Factor applied to a captured area of `width`x`height`, at most 1
+    fn factor(&self, width: u32, height: u32) -> f64 {
+        let factor = match *self {
+            OutputResolution::Size { width: max_width, height: max_height } => {
+                (max_width as f64 / width.max(1) as f64).min(max_height as f64 / height.max(1) as f64)
+            },
+            OutputResolution::Scale { percent } => percent as f64 / 100.0,
+        };
+        factor.min(1.0)
+    }
+}
+
+/// A PipeWire capture source chosen from `get_wayland_capture_sources`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipeWireNodeSelection {
+    /// Node id at the time of selection
+    pub node_id: u32,
+    
+    /// Node name, used to find the node again when the id has become stale
+    pub name: String,
+}
+
+/// Throttling of the encoder while nothing on screen changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IdleConfig {
+    /// Whether static frames are dropped down to `idle_fps`
+    pub enabled: bool,
+    
+    /// Frame rate encoded while the screen is static
+    pub idle_fps: u32,
+    
+    /// Time without changes before the capture counts as idle
+    pub idle_after_ms: u64,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        IdleConfig {
+            enabled: true,
+            idle_fps: 2,
+            idle_after_ms: 2000,
+        }
+    }
+}
+
+/// Damage tracking on X11 (XDamage)
+///
+/// Instead of grabbing the whole screen every frame, only the areas the X
+/// server reports as changed are read, and no frame is encoded while nothing
+/// changed. Needs a client-side cursor; with an embedded cursor the capture
+/// falls back to grabbing full frames.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DamageConfig {
+    /// Whether damage tracking is used when the X server supports it
+    pub enabled: bool,
+    
+    /// Changes smaller than this fraction of the captured area are held back,
+    /// e.g. 0.0001 for a blinking caret. 0 encodes every change at once.
+    pub skip_below_fraction: f64,
+    
+    /// Longest time a held back change waits before it is encoded anyway
+    pub max_delay_ms: u64,
+}
+
+impl Default for DamageConfig {
+    fn default() -> Self {
+        DamageConfig {
+            enabled: true,
+            skip_below_fraction: 0.0,
+            max_delay_ms: 250,
+        }
+    }
+}
+
+/// Ceilings for one power profile; unset limits leave the configuration as is
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerProfileLimits {
+    /// Highest frame rate encoded
+    pub max_fps: Option<u32>,
+    
+    /// Highest video bitrate in Kbps, also for the adaptive quality controller
+    pub max_bitrate_kbps: Option<u32>,
+    
+    /// Stop the capture instead of encoding in software when the hardware
+    /// encoder fails
+    pub forbid_software_fallback: bool,
+}
+
+/// Limits per power profile (see `power_profile`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerProfilesConfig {
+    /// On battery
+    pub battery: PowerProfileLimits,
+    
+    /// On battery at or below `low_battery_percent`, on top of `battery`
+    pub low_battery: PowerProfileLimits,
+    
+    /// Charge in percent from which the battery counts as low
+    pub low_battery_percent: u8,
+}
+
+impl Default for PowerProfilesConfig {
+    fn default() -> Self {
+        PowerProfilesConfig {
+            battery: PowerProfileLimits {
+                max_fps: Some(30),
+                ..PowerProfileLimits::default()
+            },
+            low_battery: PowerProfileLimits {
+                max_fps: Some(15),
+                max_bitrate_kbps: Some(2000),
+                forbid_software_fallback: true,
+            },
+            low_battery_percent: 20,
+        }
+    }
+}
+
+/// Fitting the stream to the viewers' windows (see `viewport`)
+///
+/// The bounds are physical pixels and apply to the box the stream is fitted
+/// into, not to the stream itself: the aspect ratio of the captured area is
+/// kept and the stream is never scaled up.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewportFitConfig {
+    /// Whether viewports reported by viewers shrink the stream
+    pub enabled: bool,
+    
+    /// Smallest box the stream is fitted into, however small the viewer's window
+    pub min_width: u32,
+    pub min_height: u32,
+    
+    /// Largest box the stream is fitted into (None = the configured output size)
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+}
+
+impl Default for ViewportFitConfig {
+    fn default() -> Self {
+        ViewportFitConfig {
+            enabled: true,
+            min_width: 640,
+            min_height: 360,
+            max_width: None,
+            max_height: None,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_restart_attempts() -> u32 {
+    5
+}
+
+fn default_freeze_timeout_ms() -> u64 {
+    5000
+}
+
+impl ScreenCaptureConfig {
+    /// Effective cursor mode, falling back to the legacy `capture_cursor` switch
+    pub fn cursor_mode(&self) -> CursorMode {
+        match self.cursor_mode {
+            Some(mode) => mode,
+            None if self.capture_cursor => CursorMode::Embedded,
+            None => CursorMode::Hidden,
+        }
+    }
+    
+    /// Whether the capture backend should draw the cursor into the video
+    pub fn draws_cursor(&self) -> bool {
+        self.cursor_mode() == CursorMode::Embedded
+    }
+    
+    /// Area of the monitor that is encoded, with even dimensions
+    pub fn effective_region(&self, monitor: &MonitorInfo) -> CaptureRegion {
+        self.capture_region
+            .unwrap_or_else(|| CaptureRegion::full(monitor))
+            .even()
+    }
+    
+    /// Size of the encoded video: the effective region scaled by
+    /// `output_resolution` and by the quality controller's
+    /// `adaptive_scale_percent`, with even dimensions
+    pub fn output_size(&self, monitor: &MonitorInfo, adaptive_scale_percent: u32) -> (u32, u32) {
+        let region = self.effective_region(monitor);
+        let configured = self.output_resolution
+            .map(|resolution| resolution.factor(region.width, region.height))
+            .unwrap_or(1.0);
+        let factor = configured * adaptive_scale_percent.min(100) as f64 / 100.0;
+        
+        let scale = |size: u32| ((size as f64 * factor).round() as u32 & !1).max(2);
+        (scale(region.width), scale(region.height))
+    }
+}
+
+/// Built-in profile for links below 2 Mbps
+pub const PROFILE_LOW_BANDWIDTH: &str = "Low bandwidth (<2 Mbps)";
+
+/// Built-in profile matching the default settings
+pub const PROFILE_BALANCED: &str = "Balanced";
+
+/// Built-in profile for fast local networks
+pub const PROFILE_HIGH_QUALITY_LAN: &str = "High quality LAN";
+
+/// Built-in profile for slides and documents, tuned for sharp text
+pub const PROFILE_PRESENTATION: &str = "Presentation (text-sharp)";
+
+/// A named set of encoding parameters that can be switched to in one call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureProfile {
+    pub name: String,
+    
+    /// Built-in profiles ship with SmolDesk and cannot be overwritten
+    pub builtin: bool,
+    
+    pub config: ScreenCaptureConfig,
+}
+
+/// The profiles that ship with SmolDesk
+pub fn builtin_profiles() -> Vec<CaptureProfile> {
+    let builtin = |name: &str, config: ScreenCaptureConfig| CaptureProfile {
+        name: name.to_string(),
+        builtin: true,
+        config,
+    };
+    
+    vec![
+        builtin(PROFILE_LOW_BANDWIDTH, ScreenCaptureConfigBuilder::new()
+            .fps(15)
+            .quality(40)
+            .keyframe_interval(60)
+            .bitrate(Some(1500))
+            .build()),
+        builtin(PROFILE_BALANCED, ScreenCaptureConfig::default()),
+        builtin(PROFILE_HIGH_QUALITY_LAN, ScreenCaptureConfigBuilder::new()
+            .fps(60)
+            .quality(95)
+            .keyframe_interval(120)
+            .bitrate(Some(20000))
+            .build()),
+        // Few frames, each close to lossless; stillimage keeps text edges crisp
+        builtin(PROFILE_PRESENTATION, ScreenCaptureConfigBuilder::new()
+            .fps(5)
+            .quality(95)
+            .keyframe_interval(10)
+            .latency_mode(LatencyMode::Quality)
+            .advanced_options(AdvancedEncodingOptions {
+                preset: None,
+                tune: Some("stillimage".to_string()),
+                profile: None,
+                rate_control: RateControlMode::CRF(18),
+                ..AdvancedEncodingOptions::default()
+            })
+            .build()),
+    ]
+}
+
+/// Built-in and user-defined profiles; user profiles are sorted by name
+pub fn capture_profiles(user_profiles: &BTreeMap<String, ScreenCaptureConfig>) -> Vec<CaptureProfile> {
+    let mut profiles = builtin_profiles();
+    profiles.extend(user_profiles.iter().map(|(name, config)| CaptureProfile {
+        name: name.clone(),
+        builtin: false,
+        config: config.clone(),
+    }));
+    profiles
+}
+
+/// Looks up a profile by name, built-in profiles first
+pub fn find_capture_profile(name: &str, user_profiles: &BTreeMap<String, ScreenCaptureConfig>) -> Option<ScreenCaptureConfig> {
+    builtin_profiles().into_iter()
+        .find(|profile| profile.name == name)
+        .map(|profile| profile.config)
+        .or_else(|| user_profiles.get(name).cloned())
+}
+
+impl ScreenCaptureConfig {
+    /// This configuration with the encoding parameters of `profile`. What is
+    /// captured (monitor, region, PipeWire node, privacy masks) and how
+    /// (acceleration, cursor, transport) is kept.
+    pub fn with_profile(&self, profile: &ScreenCaptureConfig) -> ScreenCaptureConfig {
+        ScreenCaptureConfig {
+            fps: profile.fps,
+            quality: profile.quality,
+            codec: profile.codec.clone(),
+            keyframe_interval: profile.keyframe_interval,
+            bitrate: profile.bitrate,
+            latency_mode: profile.latency_mode.clone(),
+            advanced_options: profile.advanced_options.clone(),
+            ..self.clone()
+        }
+    }
 }
 
 /// Advanced encoding options for FFmpeg
@@ -86,12 +487,27 @@ impl Default for ScreenCaptureConfig {
             quality: 80,
             codec: VideoCodec::H264,
             hardware_acceleration: HardwareAcceleration::None,
+            encoder_backend: EncoderBackendKind::Auto,
             capture_cursor: true,
+            cursor_mode: None,
+            capture_region: None,
             capture_audio: false,
             keyframe_interval: 30,   // One keyframe per second at 30 FPS
             bitrate: None,           // Auto bitrate based on quality
             latency_mode: LatencyMode::Balanced,
             advanced_options: None,
+            max_restart_attempts: default_max_restart_attempts(),
+            freeze_timeout_ms: default_freeze_timeout_ms(),
+            frame_transport: FrameTransport::default(),
+            idle: IdleConfig::default(),
+            damage: DamageConfig::default(),
+            pipewire_node: None,
+            output_resolution: None,
+            privacy: PrivacyConfig::default(),
+            software_fallback: true,
+            power_profiles: PowerProfilesConfig::default(),
+            ignore_power_profile: false,
+            viewport_fit: ViewportFitConfig::default(),
         }
     }
 }
@@ -146,11 +562,26 @@ impl ScreenCaptureConfigBuilder {
         self
     }
     
+    pub fn encoder_backend(mut self, backend: EncoderBackendKind) -> Self {
+        self.config.encoder_backend = backend;
+        self
+    }
+    
     pub fn capture_cursor(mut self, capture: bool) -> Self {
         self.config.capture_cursor = capture;
         self
     }
     
+    pub fn cursor_mode(mut self, mode: CursorMode) -> Self {
+        self.config.cursor_mode = Some(mode);
+        self
+    }
+    
+    pub fn capture_region(mut self, region: Option<CaptureRegion>) -> Self {
+        self.config.capture_region = region;
+        self
+    }
+    
     pub fn capture_audio(mut self, capture: bool) -> Self {
         self.config.capture_audio = capture;
         self
@@ -176,7 +607,167 @@ impl ScreenCaptureConfigBuilder {
         self
     }
     
+    pub fn max_restart_attempts(mut self, attempts: u32) -> Self {
+        self.config.max_restart_attempts = attempts;
+        self
+    }
+    
+    pub fn frame_transport(mut self, transport: FrameTransport) -> Self {
+        self.config.frame_transport = transport;
+        self
+    }
+    
+    pub fn idle(mut self, idle: IdleConfig) -> Self {
+        self.config.idle = idle;
+        self
+    }
+    
+    pub fn damage(mut self, damage: DamageConfig) -> Self {
+        self.config.damage = damage;
+        self
+    }
+    
+    pub fn pipewire_node(mut self, node: Option<PipeWireNodeSelection>) -> Self {
+        self.config.pipewire_node = node;
+        self
+    }
+    
+    pub fn output_resolution(mut self, resolution: Option<OutputResolution>) -> Self {
+        self.config.output_resolution = resolution;
+        self
+    }
+    
+    pub fn privacy(mut self, privacy: PrivacyConfig) -> Self {
+        self.config.privacy = privacy;
+        self
+    }
+    
+    pub fn software_fallback(mut self, allowed: bool) -> Self {
+        self.config.software_fallback = allowed;
+        self
+    }
+    
+    pub fn power_profiles(mut self, profiles: PowerProfilesConfig) -> Self {
+        self.config.power_profiles = profiles;
+        self
+    }
+    
+    pub fn ignore_power_profile(mut self, ignore: bool) -> Self {
+        self.config.ignore_power_profile = ignore;
+        self
+    }
+    
     pub fn build(self) -> ScreenCaptureConfig {
         self.config
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::monitor;
+    
+    #[test]
+    fn test_region_validation() {
+        let quadrant = CaptureRegion { x: 960, y: 540, width: 960, height: 540 };
+        assert!(quadrant.validate(&monitor()).is_ok());
+        assert_eq!(quadrant.origin(&monitor()), (3520, 540));
+        
+        let too_wide = CaptureRegion { x: 961, ..quadrant };
+        assert!(matches!(too_wide.validate(&monitor()), Err(ScreenCaptureError::InvalidRegion(_))));
+        
+        let empty = CaptureRegion { x: 0, y: 0, width: 0, height: 100 };
+        assert!(empty.validate(&monitor()).is_err());
+    }
+    
+    #[test]
+    fn test_effective_region_is_even() {
+        let config = ScreenCaptureConfigBuilder::new()
+            .capture_region(Some(CaptureRegion { x: 10, y: 10, width: 801, height: 601 }))
+            .build();
+        assert_eq!(config.effective_region(&monitor()), CaptureRegion { x: 10, y: 10, width: 800, height: 600 });
+        
+        let full = ScreenCaptureConfig::default().effective_region(&monitor());
+        assert_eq!(full, CaptureRegion { x: 0, y: 0, width: 1920, height: 1080 });
+    }
+    
+    #[test]
+    fn test_output_size() {
+        let with = |resolution| ScreenCaptureConfigBuilder::new().output_resolution(resolution).build();
+        
+        assert_eq!(with(None).output_size(&monitor(), 100), (1920, 1080));
+        assert_eq!(with(Some(OutputResolution::Size { width: 1280, height: 720 })).output_size(&monitor(), 100), (1280, 720));
+        assert_eq!(with(Some(OutputResolution::Scale { percent: 50 })).output_size(&monitor(), 100), (960, 540));
+        
+        // The aspect ratio is kept and the stream is never scaled up
+        assert_eq!(with(Some(OutputResolution::Size { width: 1280, height: 800 })).output_size(&monitor(), 100), (1280, 720));
+        assert_eq!(with(Some(OutputResolution::Size { width: 3840, height: 2160 })).output_size(&monitor(), 100), (1920, 1080));
+        
+        // The quality controller scales on top of the configured size
+        assert_eq!(with(None).output_size(&monitor(), 75), (1440, 810));
+        assert_eq!(with(Some(OutputResolution::Scale { percent: 50 })).output_size(&monitor(), 50), (480, 270));
+        
+        // The region is scaled, not the monitor
+        let region = ScreenCaptureConfigBuilder::new()
+            .capture_region(Some(CaptureRegion { x: 0, y: 0, width: 1001, height: 500 }))
+            .output_resolution(Some(OutputResolution::Scale { percent: 50 }))
+            .build();
+        assert_eq!(region.output_size(&monitor(), 100), (500, 250));
+    }
+    
+    #[test]
+    fn test_output_resolution_validation() {
+        assert!(OutputResolution::Size { width: 1280, height: 720 }.validate().is_ok());
+        assert!(OutputResolution::Size { width: 0, height: 720 }.validate().is_err());
+        assert!(OutputResolution::Scale { percent: 10 }.validate().is_ok());
+        assert!(OutputResolution::Scale { percent: 100 }.validate().is_ok());
+        assert!(matches!(OutputResolution::Scale { percent: 5 }.validate(), Err(ScreenCaptureError::InvalidConfig(_))));
+        assert!(OutputResolution::Scale { percent: 150 }.validate().is_err());
+        
+        let json = serde_json::to_value(OutputResolution::Scale { percent: 50 }).unwrap();
+        assert_eq!(json, serde_json::json!({ "mode": "scale", "percent": 50 }));
+    }
+    
+    #[test]
+    fn test_capture_profiles() {
+        let profiles = builtin_profiles();
+        assert_eq!(
+            profiles.iter().map(|profile| profile.name.as_str()).collect::<Vec<_>>(),
+            vec![PROFILE_LOW_BANDWIDTH, PROFILE_BALANCED, PROFILE_HIGH_QUALITY_LAN, PROFILE_PRESENTATION]
+        );
+        for profile in &profiles {
+            assert!(profile.config.validate(&[monitor()]).is_empty(), "{}", profile.name);
+        }
+        assert!(find_capture_profile(PROFILE_LOW_BANDWIDTH, &BTreeMap::new()).unwrap().bitrate.unwrap() < 2000);
+        
+        // User profiles come after the built-ins, which they cannot shadow
+        let user = BTreeMap::from([
+            ("Gaming".to_string(), ScreenCaptureConfigBuilder::new().fps(120).build()),
+            (PROFILE_BALANCED.to_string(), ScreenCaptureConfigBuilder::new().fps(1).build()),
+        ]);
+        assert_eq!(find_capture_profile("Gaming", &user).unwrap().fps, 120);
+        assert_eq!(find_capture_profile(PROFILE_BALANCED, &user).unwrap().fps, 30);
+        assert!(find_capture_profile("Missing", &user).is_none());
+        assert!(!capture_profiles(&user)[4].builtin);
+    }
+    
+    #[test]
+    fn test_profile_keeps_what_is_captured() {
+        let region = CaptureRegion { x: 0, y: 0, width: 1280, height: 720 };
+        let current = ScreenCaptureConfigBuilder::new()
+            .monitor_index(1)
+            .capture_region(Some(region))
+            .hardware_acceleration(HardwareAcceleration::VAAPI)
+            .output_resolution(Some(OutputResolution::Scale { percent: 50 }))
+            .build();
+        let presentation = find_capture_profile(PROFILE_PRESENTATION, &BTreeMap::new()).unwrap();
+        
+        let applied = current.with_profile(&presentation);
+        assert_eq!((applied.fps, applied.quality, applied.keyframe_interval), (5, 95, 10));
+        assert_eq!(applied.advanced_options.unwrap().tune.as_deref(), Some("stillimage"));
+        assert_eq!(applied.monitor_index, 1);
+        assert_eq!(applied.capture_region, Some(region));
+        assert_eq!(applied.hardware_acceleration, HardwareAcceleration::VAAPI);
+        assert_eq!(applied.output_resolution, Some(OutputResolution::Scale { percent: 50 }));
+    }
+}
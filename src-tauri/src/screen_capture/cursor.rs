@@ -0,0 +1,255 @@
+// screen_capture/cursor.rs - Cursor position polling for client-side cursor rendering
+
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::screen_capture::types::MonitorInfo;
+
+/// Upper bound for cursor_position events
+pub const CURSOR_POLL_INTERVAL: Duration = Duration::from_micros(1_000_000 / 60);
+
+/// Shape name reported when the source cannot tell
+pub const DEFAULT_CURSOR_SHAPE: &str = "default";
+
+/// Cursor state sent to the frontend as `cursor_position`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CursorPosition {
+    /// Position relative to the captured monitor
+    pub x: i32,
+    pub y: i32,
+    /// Whether the pointer is on the captured monitor
+    pub visible: bool,
+    /// Cursor shape name, e.g. "left_ptr" or "xterm" on X11
+    pub shape: String,
+}
+
+/// Receiver for cursor updates, e.g. forwarding them to the frontend
+pub type CursorSink = Arc<dyn Fn(CursorPosition) + Send + Sync>;
+
+/// Where the pointer position comes from
+pub trait CursorSource: Send {
+    /// Pointer position in desktop coordinates and the current shape name
+    fn query(&mut self) -> Option<(i32, i32, String)>;
+}
+
+/// Positions derived from forwarded input events, for desktops where the
+/// pointer cannot be queried (Wayland)
+#[derive(Clone, Default)]
+pub struct ReportedCursorSource {
+    position: Arc<Mutex<Option<(i32, i32)>>>,
+}
+
+impl ReportedCursorSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a pointer position in desktop coordinates
+    pub fn report(&self, x: i32, y: i32) {
+        *self.position.lock().unwrap() = Some((x, y));
+    }
+}
+
+impl CursorSource for ReportedCursorSource {
+    fn query(&mut self) -> Option<(i32, i32, String)> {
+        self.position.lock().unwrap().map(|(x, y)| (x, y, DEFAULT_CURSOR_SHAPE.to_string()))
+    }
+}
+
+/// XQueryPointer for the position and XFixes for the cursor name
+#[cfg(feature = "x11-support")]
+pub struct X11CursorSource {
+    display: *mut x11::xlib::Display,
+    root: x11::xlib::Window,
+    has_xfixes: bool,
+}
+
+// The display connection is owned by the tracker thread and never shared
+#[cfg(feature = "x11-support")]
+unsafe impl Send for X11CursorSource {}
+
+#[cfg(feature = "x11-support")]
+impl X11CursorSource {
+    /// Opens a dedicated connection to the X server in $DISPLAY
+    pub fn open() -> Option<Self> {
+        use x11::{xfixes, xlib};
+
+        unsafe {
+            let display = xlib::XOpenDisplay(std::ptr::null());
+            if display.is_null() {
+                return None;
+            }
+
+            let root = xlib::XDefaultRootWindow(display);
+            let (mut event_base, mut error_base) = (0, 0);
+            let has_xfixes = xfixes::XFixesQueryExtension(display, &mut event_base, &mut error_base) != 0;
+
+            Some(X11CursorSource { display, root, has_xfixes })
+        }
+    }
+
+    fn cursor_name(&self) -> Option<String> {
+        use x11::{xfixes, xlib};
+
+        if !self.has_xfixes {
+            return None;
+        }
+
+        unsafe {
+            let image = xfixes::XFixesGetCursorImage(self.display);
+            if image.is_null() {
+                return None;
+            }
+
+            let name = (*image).name;
+            let shape = if name.is_null() {
+                None
+            } else {
+                Some(std::ffi::CStr::from_ptr(name).to_string_lossy().into_owned())
+            };
+
+            xlib::XFree(image as *mut _);
+            shape.filter(|shape| !shape.is_empty())
+        }
+    }
+}
+
+#[cfg(feature = "x11-support")]
+impl CursorSource for X11CursorSource {
+    fn query(&mut self) -> Option<(i32, i32, String)> {
+        let (mut root_return, mut child_return) = (0, 0);
+        let (mut root_x, mut root_y, mut win_x, mut win_y) = (0, 0, 0, 0);
+        let mut mask = 0;
+
+        let on_screen = unsafe {
+            x11::xlib::XQueryPointer(
+                self.display, self.root,
+                &mut root_return, &mut child_return,
+                &mut root_x, &mut root_y, &mut win_x, &mut win_y,
+                &mut mask,
+            )
+        };
+        if on_screen == 0 {
+            return None;
+        }
+
+        let shape = self.cursor_name().unwrap_or_else(|| DEFAULT_CURSOR_SHAPE.to_string());
+        Some((root_x, root_y, shape))
+    }
+}
+
+#[cfg(feature = "x11-support")]
+impl Drop for X11CursorSource {
+    fn drop(&mut self) {
+        unsafe {
+            x11::xlib::XCloseDisplay(self.display);
+        }
+    }
+}
+
+/// Converts desktop coordinates to coordinates on the captured monitor
+pub fn to_monitor_position(x: i32, y: i32, shape: String, monitor: &MonitorInfo) -> CursorPosition {
+    let relative_x = x - monitor.x_offset;
+    let relative_y = y - monitor.y_offset;
+    let visible = relative_x >= 0 && relative_y >= 0
+        && relative_x < monitor.width as i32 && relative_y < monitor.height as i32;
+
+    CursorPosition {
+        x: relative_x,
+        y: relative_y,
+        visible,
+        shape,
+    }
+}
+
+/// Polls a cursor source and reports changes, at most 60 times per second
+pub struct CursorTracker {
+    running: Arc<Mutex<bool>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl CursorTracker {
+    pub fn start(mut source: Box<dyn CursorSource>, monitor: MonitorInfo, sink: CursorSink) -> Self {
+        let running = Arc::new(Mutex::new(true));
+        let thread_running = running.clone();
+
+        let thread = thread::spawn(move || {
+            let mut last_position: Option<CursorPosition> = None;
+
+            while *thread_running.lock().unwrap() {
+                let poll_started = Instant::now();
+
+                if let Some((x, y, shape)) = source.query() {
+                    let position = to_monitor_position(x, y, shape, &monitor);
+                    // Only changes are sent, a resting pointer costs nothing
+                    if last_position.as_ref() != Some(&position) {
+                        sink(position.clone());
+                        last_position = Some(position);
+                    }
+                }
+
+                if let Some(remaining) = CURSOR_POLL_INTERVAL.checked_sub(poll_started.elapsed()) {
+                    thread::sleep(remaining);
+                }
+            }
+        });
+
+        CursorTracker {
+            running,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        *self.running.lock().unwrap() = false;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for CursorTracker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::monitor;
+
+    #[test]
+    fn test_positions_are_monitor_relative() {
+        let position = to_monitor_position(2660, 50, "xterm".to_string(), &monitor());
+        assert_eq!(position, CursorPosition { x: 100, y: 50, visible: true, shape: "xterm".to_string() });
+
+        // On the monitor to the left
+        let position = to_monitor_position(100, 50, DEFAULT_CURSOR_SHAPE.to_string(), &monitor());
+        assert!(!position.visible);
+        assert_eq!(position.x, -2460);
+    }
+
+    #[test]
+    fn test_tracker_only_reports_changes() {
+        let source = ReportedCursorSource::new();
+        source.report(2600, 10);
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let recorded = received.clone();
+        let sink: CursorSink = Arc::new(move |position| recorded.lock().unwrap().push(position));
+
+        let mut tracker = CursorTracker::start(Box::new(source.clone()), monitor(), sink);
+        thread::sleep(CURSOR_POLL_INTERVAL * 4);
+        source.report(2610, 20);
+        thread::sleep(CURSOR_POLL_INTERVAL * 4);
+        tracker.stop();
+
+        let received = received.lock().unwrap();
+        let positions: Vec<(i32, i32)> = received.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(positions, vec![(40, 10), (50, 20)]);
+    }
+}
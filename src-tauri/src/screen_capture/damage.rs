@@ -0,0 +1,463 @@
+// screen_capture/damage.rs - X11 damage tracking for partial screen grabs
+//
+// x11grab reads and compares the whole screen every frame, even when only a
+// blinking caret changed. With damage tracking the X server reports changed
+// rectangles (XDamage); they are accumulated between frames, only those areas
+// are read back into a retained frame, and the frame is handed to FFmpeg as
+// raw video only when something changed. While nothing changes, keepalive
+// frames are written at the idle frame rate, so the stream looks exactly like
+// one throttled by mpdecimate.
+
+use std::time::{Duration, Instant};
+
+use crate::screen_capture::config::ScreenCaptureConfig;
+
+pub use grabber::{DamageGrabber, DamageSource};
+
+/// Bytes per pixel of the retained frame (BGRX, FFmpeg's `bgr0`)
+pub const BYTES_PER_PIXEL: usize = 4;
+
+/// More dirty rectangles than this are merged into their bounding box
+const MAX_DIRTY_RECTS: usize = 64;
+
+/// Rectangle in capture coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirtyRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl DirtyRect {
+    pub fn area(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+
+    fn bounding_box(&self, other: &DirtyRect) -> DirtyRect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        DirtyRect { x, y, width: right - x, height: bottom - y }
+    }
+}
+
+/// What to write to the encoder on a frame tick
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameDecision {
+    /// Nothing to write; the viewer keeps showing the last frame
+    Skip,
+    /// Write the retained frame unchanged to keep the stream alive
+    Keepalive,
+    /// Read these areas into the retained frame, then write it
+    Grab(Vec<DirtyRect>),
+}
+
+/// Accumulates damage between frames and decides when a frame is written
+#[derive(Debug)]
+pub struct DamagePacer {
+    /// Top left corner of the captured area in root window coordinates
+    origin: (i32, i32),
+    width: u32,
+    height: u32,
+
+    skip_below_fraction: f64,
+    max_delay: Duration,
+    keepalive: Duration,
+
+    dirty: Vec<DirtyRect>,
+    dirty_since: Option<Instant>,
+    last_written: Option<Instant>,
+}
+
+impl DamagePacer {
+    /// Starts with the whole area dirty, a new stream needs a complete image
+    pub fn new(config: &ScreenCaptureConfig, origin: (i32, i32), width: u32, height: u32) -> Self {
+        let keepalive_fps = if config.idle.enabled && config.idle.idle_fps > 0 {
+            config.idle.idle_fps.min(config.fps.max(1))
+        } else {
+            config.fps.max(1)
+        };
+
+        DamagePacer {
+            origin,
+            width,
+            height,
+            skip_below_fraction: config.damage.skip_below_fraction,
+            max_delay: Duration::from_millis(config.damage.max_delay_ms),
+            keepalive: Duration::from_secs(1) / keepalive_fps,
+            dirty: vec![DirtyRect { x: 0, y: 0, width, height }],
+            dirty_since: None,
+            last_written: None,
+        }
+    }
+
+    /// Adds a damaged rectangle in root window coordinates; parts outside the
+    /// captured area are ignored
+    pub fn add_damage(&mut self, x: i32, y: i32, width: u32, height: u32, now: Instant) {
+        let left = (x - self.origin.0).max(0) as i64;
+        let top = (y - self.origin.1).max(0) as i64;
+        let right = (x as i64 - self.origin.0 as i64 + width as i64).min(self.width as i64);
+        let bottom = (y as i64 - self.origin.1 as i64 + height as i64).min(self.height as i64);
+        if right <= left || bottom <= top {
+            return;
+        }
+
+        self.dirty.push(DirtyRect {
+            x: left as u32,
+            y: top as u32,
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+        });
+        if self.dirty.len() > MAX_DIRTY_RECTS {
+            let merged = self.dirty.iter().skip(1).fold(self.dirty[0], |acc, rect| acc.bounding_box(rect));
+            self.dirty = vec![merged];
+        }
+        self.dirty_since.get_or_insert(now);
+    }
+
+    /// Share of the captured area that changed since the last written frame;
+    /// overlapping rectangles are counted twice, so this errs towards grabbing
+    pub fn dirty_fraction(&self) -> f64 {
+        let total = self.width as u64 * self.height as u64;
+        if total == 0 {
+            return 0.0;
+        }
+        let dirty: u64 = self.dirty.iter().map(DirtyRect::area).sum();
+        (dirty as f64 / total as f64).min(1.0)
+    }
+
+    /// Decides what to write on the frame tick at `now`
+    pub fn decide(&mut self, now: Instant) -> FrameDecision {
+        let keepalive_due = match self.last_written {
+            Some(last) => now.duration_since(last) >= self.keepalive,
+            None => true,
+        };
+
+        if !self.dirty.is_empty() {
+            let waited = self.dirty_since
+                .map(|since| now.duration_since(since))
+                .unwrap_or(Duration::ZERO);
+            if keepalive_due || waited >= self.max_delay || self.dirty_fraction() >= self.skip_below_fraction {
+                self.last_written = Some(now);
+                self.dirty_since = None;
+                return FrameDecision::Grab(std::mem::take(&mut self.dirty));
+            }
+        }
+
+        if keepalive_due {
+            self.last_written = Some(now);
+            return FrameDecision::Keepalive;
+        }
+        FrameDecision::Skip
+    }
+}
+
+/// Copies a grabbed rectangle of BGRX pixels into the retained frame
+pub fn blit(frame: &mut [u8], frame_width: u32, rect: &DirtyRect, pixels: &[u8]) {
+    let row_len = rect.width as usize * BYTES_PER_PIXEL;
+    let stride = frame_width as usize * BYTES_PER_PIXEL;
+
+    for (row, source) in pixels.chunks_exact(row_len).take(rect.height as usize).enumerate() {
+        let start = (rect.y as usize + row) * stride + rect.x as usize * BYTES_PER_PIXEL;
+        if let Some(target) = frame.get_mut(start..start + row_len) {
+            target.copy_from_slice(source);
+        }
+    }
+}
+
+#[cfg(feature = "x11-support")]
+mod grabber {
+    use std::io::Write;
+    use std::process::ChildStdin;
+    use std::sync::mpsc::Sender;
+    use std::sync::{Arc, Mutex};
+    use std::thread::{self, JoinHandle};
+    use std::time::{Duration, Instant};
+
+    use x11rb::connection::Connection;
+    use x11rb::protocol::damage::{self, ConnectionExt as _};
+    use x11rb::protocol::xproto::{self, ConnectionExt as _, ImageFormat};
+    use x11rb::protocol::Event;
+    use x11rb::rust_connection::RustConnection;
+
+    use super::{blit, DamagePacer, DirtyRect, FrameDecision, BYTES_PER_PIXEL};
+    use crate::logging::TARGET_SCREEN_CAPTURE;
+    use crate::screen_capture::config::ScreenCaptureConfig;
+    use crate::screen_capture::types::CaptureStats;
+
+    /// Connection to the X server with damage reporting on the root window
+    pub struct DamageSource {
+        conn: RustConnection,
+        root: xproto::Window,
+        origin: (i32, i32),
+        width: u32,
+        height: u32,
+    }
+
+    impl DamageSource {
+        /// Subscribes to damage of the area at `origin` in root window coordinates
+        pub fn connect(origin: (i32, i32), width: u32, height: u32) -> Result<Self, String> {
+            let (conn, screen_num) = x11rb::connect(None)
+                .map_err(|e| format!("Cannot connect to the X server: {}", e))?;
+            let root = conn.setup().roots[screen_num].root;
+
+            if conn.extension_information(damage::X11_EXTENSION_NAME).map_err(|e| e.to_string())?.is_none() {
+                return Err("The X server has no DAMAGE extension".to_string());
+            }
+            conn.damage_query_version(1, 1).map_err(|e| e.to_string())?
+                .reply().map_err(|e| e.to_string())?;
+
+            let damage = conn.generate_id().map_err(|e| e.to_string())?;
+            conn.damage_create(damage, root, damage::ReportLevel::RAW_RECTANGLES).map_err(|e| e.to_string())?;
+            conn.flush().map_err(|e| e.to_string())?;
+
+            Ok(DamageSource { conn, root, origin, width, height })
+        }
+
+        /// Feeds pending damage events to the pacer
+        fn poll(&self, pacer: &mut DamagePacer) -> Result<(), String> {
+            while let Some(event) = self.conn.poll_for_event().map_err(|e| e.to_string())? {
+                if let Event::DamageNotify(event) = event {
+                    let area = event.area;
+                    pacer.add_damage(area.x as i32, area.y as i32, area.width as u32, area.height as u32, Instant::now());
+                }
+            }
+            Ok(())
+        }
+
+        /// Reads a rectangle of the captured area as BGRX pixels
+        fn grab(&self, rect: &DirtyRect) -> Result<Vec<u8>, String> {
+            let image = self.conn.get_image(
+                ImageFormat::Z_PIXMAP,
+                self.root,
+                (self.origin.0 + rect.x as i32) as i16,
+                (self.origin.1 + rect.y as i32) as i16,
+                rect.width as u16,
+                rect.height as u16,
+                !0,
+            ).map_err(|e| e.to_string())?
+                .reply().map_err(|e| e.to_string())?;
+
+            if image.data.len() != rect.area() as usize * BYTES_PER_PIXEL {
+                return Err(format!("Unsupported image format with depth {}", image.depth));
+            }
+            Ok(image.data)
+        }
+    }
+
+    /// Writes frames of a damage source into FFmpeg's stdin at up to `fps`
+    pub struct DamageGrabber {
+        running: Arc<Mutex<bool>>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl DamageGrabber {
+        /// `changes` receives for every written frame whether it carries a
+        /// change, in the order the encoder will emit them
+        pub fn start(
+            source: DamageSource,
+            config: &ScreenCaptureConfig,
+            mut stdin: ChildStdin,
+            changes: Sender<bool>,
+            stats: Arc<Mutex<CaptureStats>>,
+        ) -> Self {
+            let mut pacer = DamagePacer::new(config, source.origin, source.width, source.height);
+            let frame_interval = Duration::from_secs(1) / config.fps.max(1);
+            let running = Arc::new(Mutex::new(true));
+            let thread_running = running.clone();
+
+            let thread = thread::spawn(move || {
+                let mut frame = vec![0u8; source.width as usize * source.height as usize * BYTES_PER_PIXEL];
+
+                while *thread_running.lock().unwrap() {
+                    let tick = Instant::now();
+
+                    if let Err(e) = source.poll(&mut pacer) {
+                        log::error!(target: TARGET_SCREEN_CAPTURE, "Damage tracking failed: {}", e);
+                        break;
+                    }
+
+                    let changed = match pacer.decide(tick) {
+                        FrameDecision::Skip => {
+                            stats.lock().unwrap().skipped_frames += 1;
+                            None
+                        },
+                        FrameDecision::Keepalive => Some(false),
+                        FrameDecision::Grab(rects) => {
+                            for rect in &rects {
+                                match source.grab(rect) {
+                                    Ok(pixels) => blit(&mut frame, source.width, rect, &pixels),
+                                    Err(e) => log::warn!(target: TARGET_SCREEN_CAPTURE, "Failed to grab damaged area: {}", e),
+                                }
+                            }
+                            Some(true)
+                        },
+                    };
+
+                    if let Some(changed) = changed {
+                        let _ = changes.send(changed);
+                        // FFmpeg is gone, e.g. restarted for a keyframe
+                        if stdin.write_all(&frame).is_err() {
+                            break;
+                        }
+                    }
+
+                    if let Some(remaining) = frame_interval.checked_sub(tick.elapsed()) {
+                        thread::sleep(remaining);
+                    }
+                }
+            });
+
+            DamageGrabber { running, thread: Some(thread) }
+        }
+
+        pub fn stop(&mut self) {
+            *self.running.lock().unwrap() = false;
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    impl Drop for DamageGrabber {
+        fn drop(&mut self) {
+            self.stop();
+        }
+    }
+}
+
+/// Without X11 support there is no damage source; capture grabs full frames
+#[cfg(not(feature = "x11-support"))]
+mod grabber {
+    use std::process::ChildStdin;
+    use std::sync::mpsc::Sender;
+    use std::sync::{Arc, Mutex};
+
+    use crate::screen_capture::config::ScreenCaptureConfig;
+    use crate::screen_capture::types::CaptureStats;
+
+    pub enum DamageSource {}
+
+    impl DamageSource {
+        pub fn connect(_origin: (i32, i32), _width: u32, _height: u32) -> Result<Self, String> {
+            Err("Built without X11 support".to_string())
+        }
+    }
+
+    pub enum DamageGrabber {}
+
+    impl DamageGrabber {
+        pub fn start(
+            source: DamageSource,
+            _config: &ScreenCaptureConfig,
+            _stdin: ChildStdin,
+            _changes: Sender<bool>,
+            _stats: Arc<Mutex<CaptureStats>>,
+        ) -> Self {
+            match source {}
+        }
+
+        pub fn stop(&mut self) {
+            match *self {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen_capture::config::{DamageConfig, IdleConfig, ScreenCaptureConfigBuilder};
+
+    fn config(skip_below_fraction: f64) -> ScreenCaptureConfig {
+        ScreenCaptureConfigBuilder::new()
+            .fps(30)
+            .idle(IdleConfig { enabled: true, idle_fps: 2, idle_after_ms: 2000 })
+            .damage(DamageConfig { enabled: true, skip_below_fraction, max_delay_ms: 250 })
+            .build()
+    }
+
+    fn full_hd(skip_below_fraction: f64) -> DamagePacer {
+        DamagePacer::new(&config(skip_below_fraction), (1920, 0), 1920, 1080)
+    }
+
+    fn ms(start: Instant, millis: u64) -> Instant {
+        start + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_first_frame_grabs_everything() {
+        let mut pacer = full_hd(0.0);
+        let start = Instant::now();
+        assert_eq!(pacer.decide(start), FrameDecision::Grab(vec![DirtyRect { x: 0, y: 0, width: 1920, height: 1080 }]));
+    }
+
+    #[test]
+    fn test_static_screen_only_writes_keepalives() {
+        let mut pacer = full_hd(0.0);
+        let start = Instant::now();
+        pacer.decide(start);
+
+        // 30 fps ticks for one second; after the grab at 0 ms, one keepalive at 500 ms
+        let written = (1..=30)
+            .map(|tick| pacer.decide(ms(start, tick * 33 + 1)))
+            .filter(|decision| *decision != FrameDecision::Skip)
+            .collect::<Vec<_>>();
+        assert_eq!(written, vec![FrameDecision::Keepalive]);
+    }
+
+    #[test]
+    fn test_damage_is_clipped_to_capture() {
+        let mut pacer = full_hd(0.0);
+        let start = Instant::now();
+        pacer.decide(start);
+
+        // Left monitor only, then straddling the edge
+        pacer.add_damage(100, 100, 50, 50, start);
+        pacer.add_damage(1900, 10, 40, 20, start);
+        assert_eq!(pacer.decide(ms(start, 33)), FrameDecision::Grab(vec![DirtyRect { x: 0, y: 10, width: 20, height: 20 }]));
+    }
+
+    #[test]
+    fn test_small_change_is_held_back_until_max_delay() {
+        let mut pacer = full_hd(0.0001);
+        let start = Instant::now();
+        pacer.decide(start);
+
+        // A 2x20 caret is far below 0.01% of the screen
+        pacer.add_damage(1920 + 500, 300, 2, 20, ms(start, 10));
+        assert!(pacer.dirty_fraction() < 0.0001);
+        assert_eq!(pacer.decide(ms(start, 33)), FrameDecision::Skip);
+        assert_eq!(pacer.decide(ms(start, 66)), FrameDecision::Skip);
+        assert!(matches!(pacer.decide(ms(start, 260)), FrameDecision::Grab(_)));
+
+        // A large change goes out at once
+        pacer.add_damage(1920, 0, 800, 600, ms(start, 270));
+        assert!(matches!(pacer.decide(ms(start, 293)), FrameDecision::Grab(_)));
+    }
+
+    #[test]
+    fn test_many_rects_merge_into_bounding_box() {
+        let mut pacer = full_hd(0.0);
+        let start = Instant::now();
+        pacer.decide(start);
+
+        for i in 0..=MAX_DIRTY_RECTS as i32 {
+            pacer.add_damage(1920 + i * 10, 100, 5, 5, start);
+        }
+        let expected = DirtyRect { x: 0, y: 100, width: MAX_DIRTY_RECTS as u32 * 10 + 5, height: 5 };
+        assert_eq!(pacer.decide(ms(start, 33)), FrameDecision::Grab(vec![expected]));
+    }
+
+    #[test]
+    fn test_blit_copies_rows_at_offset() {
+        let mut frame = vec![0u8; 4 * 3 * BYTES_PER_PIXEL];
+        let rect = DirtyRect { x: 1, y: 1, width: 2, height: 2 };
+        blit(&mut frame, 4, &rect, &[1u8; 2 * 2 * BYTES_PER_PIXEL]);
+
+        let pixel = |x: usize, y: usize| frame[(y * 4 + x) * BYTES_PER_PIXEL];
+        assert_eq!((pixel(0, 1), pixel(1, 1), pixel(2, 1), pixel(3, 1)), (0, 1, 1, 0));
+        assert_eq!((pixel(1, 0), pixel(1, 2)), (0, 1));
+    }
+}
@@ -0,0 +1,547 @@
+// screen_capture/encoders.rs - Probing which codec and hardware acceleration combinations work
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use serde::{Deserialize, Serialize};
+
+use crate::command_runner::{CommandRunner, SystemCommandRunner};
+use crate::logging;
+use crate::screen_capture::backend::{self, gstreamer_encoder};
+use crate::screen_capture::config::ScreenCaptureConfig;
+use crate::screen_capture::error::ScreenCaptureError;
+use crate::screen_capture::types::{DisplayServer, EncoderBackendKind, HardwareAcceleration, VideoCodec};
+
+const CODECS: [VideoCodec; 4] = [VideoCodec::H264, VideoCodec::VP8, VideoCodec::VP9, VideoCodec::AV1];
+const ACCELERATIONS: [HardwareAcceleration; 4] = [
+    HardwareAcceleration::None,
+    HardwareAcceleration::VAAPI,
+    HardwareAcceleration::NVENC,
+    HardwareAcceleration::QuickSync,
+];
+
+/// Places where the CUDA driver library is installed by common distributions
+const LIBCUDA_DIRS: [&str; 5] = [
+    "/usr/lib",
+    "/usr/lib64",
+    "/usr/lib/x86_64-linux-gnu",
+    "/usr/lib/aarch64-linux-gnu",
+    "/usr/local/cuda/lib64",
+];
+
+/// FFmpeg encoder that implements a combination; None if there is none
+pub fn encoder_name(codec: &VideoCodec, acceleration: &HardwareAcceleration) -> Option<&'static str> {
+    match (acceleration, codec) {
+        (HardwareAcceleration::None, VideoCodec::H264) => Some("libx264"),
+        (HardwareAcceleration::None, VideoCodec::VP8) => Some("libvpx"),
+        (HardwareAcceleration::None, VideoCodec::VP9) => Some("libvpx-vp9"),
+        (HardwareAcceleration::None, VideoCodec::AV1) => Some("libaom-av1"),
+        (HardwareAcceleration::VAAPI, VideoCodec::H264) => Some("h264_vaapi"),
+        (HardwareAcceleration::VAAPI, VideoCodec::VP8) => Some("vp8_vaapi"),
+        (HardwareAcceleration::VAAPI, VideoCodec::VP9) => Some("vp9_vaapi"),
+        (HardwareAcceleration::VAAPI, VideoCodec::AV1) => Some("av1_vaapi"),
+        (HardwareAcceleration::NVENC, VideoCodec::H264) => Some("h264_nvenc"),
+        (HardwareAcceleration::NVENC, VideoCodec::AV1) => Some("av1_nvenc"),
+        (HardwareAcceleration::NVENC, _) => None,
+        (HardwareAcceleration::QuickSync, VideoCodec::H264) => Some("h264_qsv"),
+        (HardwareAcceleration::QuickSync, VideoCodec::VP9) => Some("vp9_qsv"),
+        (HardwareAcceleration::QuickSync, VideoCodec::AV1) => Some("av1_qsv"),
+        (HardwareAcceleration::QuickSync, _) => None,
+    }
+}
+
+/// Outcome of probing one codec × acceleration combination
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncoderSupport {
+    pub codec: VideoCodec,
+    pub acceleration: HardwareAcceleration,
+    pub encoder: Option<String>,
+    /// Why the combination cannot be used; None if a test encode succeeded
+    pub unavailable_reason: Option<String>,
+}
+
+impl EncoderSupport {
+    pub fn is_supported(&self) -> bool {
+        self.unavailable_reason.is_none()
+    }
+}
+
+/// Options the UI may offer, plus why the others are missing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupportedOptions {
+    pub supported: Vec<String>,
+    pub unavailable: BTreeMap<String, String>,
+}
+
+/// What the system offers to the encoders, probed once
+#[derive(Debug, Clone, Default)]
+pub struct SystemProbe {
+    /// Encoders listed by `ffmpeg -encoders`
+    pub ffmpeg_encoders: HashSet<String>,
+    /// DRM render nodes (/dev/dri/renderD*)
+    pub render_nodes: Vec<PathBuf>,
+    /// `vainfo` output; None if vainfo is not installed or failed
+    pub vainfo: Option<String>,
+    /// nvidia-smi ran successfully or libcuda is installed
+    pub nvidia_driver: bool,
+    /// GStreamer elements for capture pipelines; empty if GStreamer is unusable
+    pub gstreamer_elements: HashSet<String>,
+}
+
+impl SystemProbe {
+    pub fn run() -> Self {
+        let ffmpeg_encoders = SystemCommandRunner.command("ffmpeg")
+            .args(["-hide_banner", "-encoders"])
+            .output()
+            .map(|output| parse_ffmpeg_encoders(&String::from_utf8_lossy(&output.stdout)))
+            .unwrap_or_default();
+
+        let vainfo = SystemCommandRunner.command("vainfo")
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned());
+
+        let nvidia_smi = SystemCommandRunner.command("nvidia-smi")
+            .arg("-L")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false);
+        let libcuda = LIBCUDA_DIRS.iter().any(|dir| Path::new(dir).join("libcuda.so.1").exists());
+
+        let gstreamer_encoders: Vec<&str> = ACCELERATIONS.iter()
+            .flat_map(|acceleration| CODECS.iter().filter_map(move |codec| gstreamer_encoder(codec, acceleration)))
+            .collect();
+
+        SystemProbe {
+            ffmpeg_encoders,
+            render_nodes: find_render_nodes(Path::new("/dev/dri")),
+            vainfo,
+            nvidia_driver: nvidia_smi || libcuda,
+            gstreamer_elements: backend::gstreamer_elements(&gstreamer_encoders),
+        }
+    }
+
+    /// Reason the combination can be ruled out without a test encode
+    fn rule_out(&self, codec: &VideoCodec, acceleration: &HardwareAcceleration, encoder: &str) -> Option<String> {
+        if !self.ffmpeg_encoders.contains(encoder) {
+            return Some(format!("FFmpeg was built without the {} encoder", encoder));
+        }
+
+        match acceleration {
+            HardwareAcceleration::None => None,
+            HardwareAcceleration::VAAPI => {
+                if self.render_nodes.is_empty() {
+                    return Some("No GPU render node found in /dev/dri".to_string());
+                }
+                // Without vainfo the test encode has the final word
+                match &self.vainfo {
+                    Some(vainfo) if !vainfo_can_encode(vainfo, codec) => {
+                        Some(format!("The VAAPI driver cannot encode {:?}", codec))
+                    },
+                    _ => None,
+                }
+            },
+            HardwareAcceleration::NVENC => {
+                if self.nvidia_driver {
+                    None
+                } else {
+                    Some("No NVIDIA driver found (nvidia-smi and libcuda are missing)".to_string())
+                }
+            },
+            HardwareAcceleration::QuickSync => {
+                if self.render_nodes.is_empty() {
+                    Some("No GPU render node found in /dev/dri".to_string())
+                } else {
+                    None
+                }
+            },
+        }
+    }
+}
+
+/// Codec × acceleration combinations verified by a one-frame test encode
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CapabilityMatrix {
+    pub entries: Vec<EncoderSupport>,
+    /// GStreamer elements found by the probe, sorted; empty if GStreamer is unusable
+    #[serde(default)]
+    pub gstreamer_elements: Vec<String>,
+}
+
+impl CapabilityMatrix {
+    /// Probes the system and test-encodes every remaining combination
+    pub fn probe() -> Self {
+        let system = SystemProbe::run();
+        let render_node = system.render_nodes.first().cloned();
+        Self::build(&system, |acceleration, encoder| test_encode(acceleration, encoder, render_node.as_deref()))
+    }
+
+    /// Builds the matrix; `test_encode` returns the failure of a test encode
+    pub fn build<F>(system: &SystemProbe, mut test_encode: F) -> Self
+    where
+        F: FnMut(&HardwareAcceleration, &str) -> Result<(), String>,
+    {
+        let mut entries = Vec::new();
+        for acceleration in ACCELERATIONS {
+            for codec in CODECS {
+                let encoder = encoder_name(&codec, &acceleration);
+                let unavailable_reason = match encoder {
+                    None => Some(format!("{:?} has no {:?} encoder", acceleration, codec)),
+                    Some(encoder) => system.rule_out(&codec, &acceleration, encoder)
+                        .or_else(|| test_encode(&acceleration, encoder).err()),
+                };
+                if let Some(reason) = &unavailable_reason {
+                    log::debug!(target: logging::TARGET_SCREEN_CAPTURE, "{:?} with {:?} unavailable: {}", codec, acceleration, reason);
+                }
+                entries.push(EncoderSupport {
+                    codec,
+                    acceleration,
+                    encoder: encoder.map(str::to_string),
+                    unavailable_reason,
+                });
+            }
+        }
+        let mut gstreamer_elements: Vec<String> = system.gstreamer_elements.iter().cloned().collect();
+        gstreamer_elements.sort();
+        CapabilityMatrix { entries, gstreamer_elements }
+    }
+
+    fn entry(&self, codec: &VideoCodec, acceleration: &HardwareAcceleration) -> Option<&EncoderSupport> {
+        self.entries.iter().find(|entry| entry.codec == *codec && entry.acceleration == *acceleration)
+    }
+
+    pub fn is_supported(&self, codec: &VideoCodec, acceleration: &HardwareAcceleration) -> bool {
+        self.entry(codec, acceleration).map_or(false, EncoderSupport::is_supported)
+    }
+
+    /// Whether a GStreamer pipeline can encode the combination
+    fn gstreamer_supports(&self, codec: &VideoCodec, acceleration: &HardwareAcceleration) -> bool {
+        gstreamer_encoder(codec, acceleration)
+            .map_or(false, |encoder| self.gstreamer_elements.iter().any(|element| element == encoder))
+    }
+
+    /// Encoder backend for a capture. X11 always uses FFmpeg (x11grab and
+    /// damage input); on Wayland `Auto` takes GStreamer only where the FFmpeg
+    /// encoder failed the probe and GStreamer has an element for the combination.
+    pub fn select_backend(&self, config: &ScreenCaptureConfig, display_server: &DisplayServer) -> EncoderBackendKind {
+        if *display_server != DisplayServer::Wayland {
+            if config.encoder_backend == EncoderBackendKind::GStreamer {
+                log::warn!(target: logging::TARGET_SCREEN_CAPTURE, "The GStreamer backend needs a PipeWire source, encoding with FFmpeg on {:?}", display_server);
+            }
+            return EncoderBackendKind::FFmpeg;
+        }
+
+        match config.encoder_backend {
+            EncoderBackendKind::FFmpeg => EncoderBackendKind::FFmpeg,
+            EncoderBackendKind::GStreamer if self.gstreamer_elements.is_empty() => {
+                log::warn!(target: logging::TARGET_SCREEN_CAPTURE, "GStreamer is not available, encoding with FFmpeg");
+                EncoderBackendKind::FFmpeg
+            },
+            EncoderBackendKind::GStreamer => EncoderBackendKind::GStreamer,
+            EncoderBackendKind::Auto => {
+                let (codec, acceleration) = (&config.codec, &config.hardware_acceleration);
+                if !self.is_supported(codec, acceleration) && self.gstreamer_supports(codec, acceleration) {
+                    EncoderBackendKind::GStreamer
+                } else {
+                    EncoderBackendKind::FFmpeg
+                }
+            },
+        }
+    }
+
+    /// Codecs usable with at least one acceleration
+    pub fn codec_options(&self) -> SupportedOptions {
+        let mut options = SupportedOptions { supported: Vec::new(), unavailable: BTreeMap::new() };
+        for codec in CODECS {
+            let entries: Vec<_> = self.entries.iter().filter(|entry| entry.codec == codec).collect();
+            // Software encoding comes first; its reason explains a missing codec best
+            options.add(format!("{:?}", codec), &entries);
+        }
+        options
+    }
+
+    /// Accelerations usable with at least one codec
+    pub fn acceleration_options(&self) -> SupportedOptions {
+        let mut options = SupportedOptions { supported: Vec::new(), unavailable: BTreeMap::new() };
+        for acceleration in ACCELERATIONS {
+            let entries: Vec<_> = self.entries.iter().filter(|entry| entry.acceleration == acceleration).collect();
+            // H264 is the first codec, so its reason comes first
+            options.add(format!("{:?}", acceleration), &entries);
+        }
+        options
+    }
+
+    /// Like `check`, for the encoder elements of `backend`
+    pub fn check_backend(&self, config: &ScreenCaptureConfig, backend: &EncoderBackendKind) -> Result<(), ScreenCaptureError> {
+        if *backend != EncoderBackendKind::GStreamer {
+            return self.check(config);
+        }
+
+        let (codec, acceleration) = (&config.codec, &config.hardware_acceleration);
+        let software_fallback = gstreamer_encoder(codec, acceleration).is_none()
+            && self.gstreamer_supports(codec, &HardwareAcceleration::None);
+        if self.gstreamer_supports(codec, acceleration) || software_fallback {
+            return Ok(());
+        }
+        Err(ScreenCaptureError::UnsupportedEncoder(format!(
+            "{:?} with {:?} acceleration: GStreamer has no {} element",
+            codec,
+            acceleration,
+            gstreamer_encoder(codec, acceleration).unwrap_or("matching encoder")
+        )))
+    }
+
+    /// Rejects configurations whose combination did not pass the probe
+    pub fn check(&self, config: &ScreenCaptureConfig) -> Result<(), ScreenCaptureError> {
+        match self.entry(&config.codec, &config.hardware_acceleration) {
+            Some(entry) if entry.is_supported() => Ok(()),
+            // The capture backends encode in software when the accelerator lacks the codec
+            Some(entry) if entry.encoder.is_none() && self.is_supported(&config.codec, &HardwareAcceleration::None) => Ok(()),
+            Some(entry) => Err(ScreenCaptureError::UnsupportedEncoder(format!(
+                "{:?} with {:?} acceleration: {}",
+                config.codec,
+                config.hardware_acceleration,
+                entry.unavailable_reason.as_deref().unwrap_or("not supported")
+            ))),
+            None => Err(ScreenCaptureError::UnsupportedEncoder(format!(
+                "{:?} with {:?} acceleration was not probed",
+                config.codec, config.hardware_acceleration
+            ))),
+        }
+    }
+}
+
+impl SupportedOptions {
+    fn add(&mut self, name: String, entries: &[&EncoderSupport]) {
+        if entries.iter().any(|entry| entry.is_supported()) {
+            self.supported.push(name);
+        } else if let Some(reason) = entries.iter().find_map(|entry| entry.unavailable_reason.clone()) {
+            self.unavailable.insert(name, reason);
+        }
+    }
+}
+
+/// Encoder names from `ffmpeg -encoders` (" V....D libx264   libx264 H.264 ...")
+pub fn parse_ffmpeg_encoders(output: &str) -> HashSet<String> {
+    output.lines()
+        .skip_while(|line| !line.trim_start().starts_with("------"))
+        .skip(1)
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let flags = fields.next()?;
+            if !flags.starts_with('V') {
+                return None;
+            }
+            fields.next().map(str::to_string)
+        })
+        .collect()
+}
+
+/// Whether vainfo lists an encode entrypoint for the codec
+pub fn vainfo_can_encode(vainfo: &str, codec: &VideoCodec) -> bool {
+    let profile = match codec {
+        VideoCodec::H264 => "VAProfileH264",
+        VideoCodec::VP8 => "VAProfileVP8",
+        VideoCodec::VP9 => "VAProfileVP9",
+        VideoCodec::AV1 => "VAProfileAV1",
+    };
+    vainfo.lines().any(|line| {
+        let line = line.trim();
+        line.starts_with(profile) && (line.contains("VAEntrypointEncSlice") || line.contains("VAEntrypointEncSliceLP"))
+    })
+}
+
+fn find_render_nodes(dri_dir: &Path) -> Vec<PathBuf> {
+    let mut nodes: Vec<PathBuf> = std::fs::read_dir(dri_dir)
+        .map(|entries| {
+            entries.filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.file_name()
+                    .and_then(|name| name.to_str())
+                    .map_or(false, |name| name.starts_with("renderD")))
+                .collect()
+        })
+        .unwrap_or_default();
+    nodes.sort();
+    nodes
+}
+
+/// Encodes one synthetic frame; returns FFmpeg's error output on failure
+fn test_encode(acceleration: &HardwareAcceleration, encoder: &str, render_node: Option<&Path>) -> Result<(), String> {
+    let mut cmd = SystemCommandRunner.command("ffmpeg");
+    cmd.args(["-hide_banner", "-loglevel", "error"]);
+
+    if let (HardwareAcceleration::VAAPI, Some(node)) = (acceleration, render_node) {
+        cmd.arg("-vaapi_device").arg(node);
+    }
+
+    cmd.args(["-f", "lavfi", "-i", "color=black:size=256x256:rate=1", "-frames:v", "1"]);
+
+    match acceleration {
+        HardwareAcceleration::VAAPI => {
+            cmd.args(["-vf", "format=nv12,hwupload"]);
+        },
+        HardwareAcceleration::QuickSync => {
+            cmd.args(["-vf", "format=nv12"]);
+        },
+        HardwareAcceleration::NVENC | HardwareAcceleration::None => {
+            cmd.args(["-pix_fmt", "yuv420p"]);
+        },
+    }
+
+    let output = cmd.args(["-c:v", encoder, "-f", "null", "-"])
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("unknown error");
+        Err(format!("Test encode with {} failed: {}", encoder, message.trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENCODERS: &str = "Encoders:
+ V..... = Video
+ A..... = Audio
+ ------
+ V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC (codec h264)
+ V....D h264_nvenc           NVIDIA NVENC H.264 encoder (codec h264)
+ V....D h264_vaapi           H.264/AVC (VAAPI) (codec h264)
+ V....D libvpx               libvpx VP8 (codec vp8)
+ A....D aac                  AAC (Advanced Audio Coding)
+";
+
+    const VAINFO: &str = "vainfo: Driver version: Intel iHD driver
+      VAProfileH264Main               :\tVAEntrypointVLD
+      VAProfileH264Main               :\tVAEntrypointEncSliceLP
+      VAProfileVP9Profile0            :\tVAEntrypointVLD
+";
+
+    fn system() -> SystemProbe {
+        SystemProbe {
+            ffmpeg_encoders: parse_ffmpeg_encoders(ENCODERS),
+            render_nodes: vec![PathBuf::from("/dev/dri/renderD128")],
+            vainfo: Some(VAINFO.to_string()),
+            nvidia_driver: false,
+            gstreamer_elements: HashSet::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_ffmpeg_encoders() {
+        let encoders = parse_ffmpeg_encoders(ENCODERS);
+        assert!(encoders.contains("libx264"));
+        assert!(encoders.contains("h264_vaapi"));
+        assert!(!encoders.contains("aac"));
+        assert!(!encoders.contains("Video"));
+    }
+
+    #[test]
+    fn test_vainfo_requires_encode_entrypoint() {
+        assert!(vainfo_can_encode(VAINFO, &VideoCodec::H264));
+        assert!(!vainfo_can_encode(VAINFO, &VideoCodec::VP9));
+        assert!(!vainfo_can_encode(VAINFO, &VideoCodec::AV1));
+    }
+
+    #[test]
+    fn test_matrix_only_offers_verified_combinations() {
+        let mut tested = Vec::new();
+        let matrix = CapabilityMatrix::build(&system(), |_, encoder| {
+            tested.push(encoder.to_string());
+            if encoder == "libvpx" { Err("broken".to_string()) } else { Ok(()) }
+        });
+
+        // NVENC is ruled out by the missing driver before any test encode
+        assert_eq!(tested, vec!["libx264", "libvpx", "h264_vaapi"]);
+        assert!(matrix.is_supported(&VideoCodec::H264, &HardwareAcceleration::VAAPI));
+        assert!(!matrix.is_supported(&VideoCodec::H264, &HardwareAcceleration::NVENC));
+        assert!(!matrix.is_supported(&VideoCodec::VP8, &HardwareAcceleration::None));
+
+        let accelerations = matrix.acceleration_options();
+        assert_eq!(accelerations.supported, vec!["None", "VAAPI"]);
+        assert!(accelerations.unavailable["NVENC"].contains("NVIDIA driver"));
+        assert!(accelerations.unavailable.contains_key("QuickSync"));
+
+        let codecs = matrix.codec_options();
+        assert_eq!(codecs.supported, vec!["H264"]);
+        assert_eq!(codecs.unavailable["VP8"], "broken");
+    }
+
+    #[test]
+    fn test_check_rejects_unverified_combination() {
+        let matrix = CapabilityMatrix::build(&system(), |_, _| Ok(()));
+
+        let config = ScreenCaptureConfig {
+            codec: VideoCodec::H264,
+            hardware_acceleration: HardwareAcceleration::VAAPI,
+            ..ScreenCaptureConfig::default()
+        };
+        assert!(matrix.check(&config).is_ok());
+
+        let config = ScreenCaptureConfig { hardware_acceleration: HardwareAcceleration::NVENC, ..config };
+        let error = matrix.check(&config).unwrap_err();
+        assert_eq!(error.kind(), "UnsupportedEncoder");
+        assert!(error.to_string().contains("NVIDIA driver"));
+    }
+
+    #[test]
+    fn test_check_allows_software_fallback() {
+        let matrix = CapabilityMatrix::build(&system(), |_, _| Ok(()));
+
+        // NVENC has no VP8 encoder, so libvpx is used instead
+        let config = ScreenCaptureConfig {
+            codec: VideoCodec::VP8,
+            hardware_acceleration: HardwareAcceleration::NVENC,
+            ..ScreenCaptureConfig::default()
+        };
+        assert!(matrix.check(&config).is_ok());
+
+        // ...but only if the software encoder is available (no libvpx-vp9 here)
+        let config = ScreenCaptureConfig { codec: VideoCodec::VP9, ..config };
+        assert!(matrix.check(&config).is_err());
+    }
+
+    #[test]
+    fn test_backend_selection_follows_probe() {
+        // FFmpeg lacks av1_vaapi and the VAAPI driver cannot encode VP9
+        let mut system = system();
+        system.gstreamer_elements = ["pipewiresrc", "matroskamux", "appsink", "vaapih264enc", "x264enc"]
+            .iter().map(|name| name.to_string()).collect();
+        let matrix = CapabilityMatrix::build(&system, |_, _| Ok(()));
+
+        let config = ScreenCaptureConfig {
+            codec: VideoCodec::H264,
+            hardware_acceleration: HardwareAcceleration::VAAPI,
+            ..ScreenCaptureConfig::default()
+        };
+        assert_eq!(matrix.select_backend(&config, &DisplayServer::Wayland), EncoderBackendKind::FFmpeg);
+
+        // Only GStreamer has a working VAAPI H.264 encoder
+        system.ffmpeg_encoders.remove("h264_vaapi");
+        let matrix = CapabilityMatrix::build(&system, |_, _| Ok(()));
+        assert!(matrix.check(&config).is_err());
+        assert_eq!(matrix.select_backend(&config, &DisplayServer::Wayland), EncoderBackendKind::GStreamer);
+        assert!(matrix.check_backend(&config, &EncoderBackendKind::GStreamer).is_ok());
+
+        // X11 and an explicit override always get FFmpeg
+        assert_eq!(matrix.select_backend(&config, &DisplayServer::X11), EncoderBackendKind::FFmpeg);
+        let ffmpeg = ScreenCaptureConfig { encoder_backend: EncoderBackendKind::FFmpeg, ..config.clone() };
+        assert_eq!(matrix.select_backend(&ffmpeg, &DisplayServer::Wayland), EncoderBackendKind::FFmpeg);
+
+        // GStreamer without a NVENC or VP9 element
+        let nvenc = ScreenCaptureConfig { hardware_acceleration: HardwareAcceleration::NVENC, ..config.clone() };
+        assert!(matrix.check_backend(&nvenc, &EncoderBackendKind::GStreamer).unwrap_err().to_string().contains("nvh264enc"));
+        let vp9 = ScreenCaptureConfig { codec: VideoCodec::VP9, ..config };
+        assert!(matrix.check_backend(&vp9, &EncoderBackendKind::GStreamer).is_err());
+    }
+}
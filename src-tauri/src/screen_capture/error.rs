@@ -21,6 +21,18 @@ pub enum ScreenCaptureError {
     /// Error when trying to capture from an invalid monitor
     InvalidMonitor(String),
     
+    /// The capture region does not fit the monitor
+    InvalidRegion(String),
+    
+    /// The limit of simultaneous capture sessions is reached
+    TooManyCaptures(String),
+    
+    /// A running capture of the monitor would compete with the request
+    MonitorBusy(String),
+    
+    /// Creating or removing a virtual output failed
+    VirtualDisplayError(String),
+    
     /// Error related to stream buffer operations
     StreamBufferError(String),
     
@@ -32,6 +44,30 @@ pub enum ScreenCaptureError {
     
     /// Error with PipeWire process (Wayland)
     PipeWireError(String),
+    
+    /// Error talking to xdg-desktop-portal (GNOME/KDE Wayland)
+    PortalError(String),
+    
+    /// The user dismissed the screen sharing consent dialog
+    ConsentDenied(String),
+    
+    /// The encoder's device (e.g. a VAAPI render node) does not exist
+    EncoderDeviceNotFound(String),
+    
+    /// The CUDA driver library could not be loaded for NVENC
+    CudaUnavailable(String),
+    
+    /// FFmpeg rejected an option, usually because it was built without it
+    UnsupportedOption(String),
+    
+    /// The user may not open the GPU render node
+    DevicePermissionDenied(String),
+    
+    /// The codec and hardware acceleration combination did not pass the probe
+    UnsupportedEncoder(String),
+    
+    /// A configuration value is out of range or contradicts another one
+    InvalidConfig(String),
 }
 
 impl fmt::Display for ScreenCaptureError {
@@ -42,16 +78,67 @@ impl fmt::Display for ScreenCaptureError {
             ScreenCaptureError::EncodingError(msg) => write!(f, "Encoding error: {}", msg),
             ScreenCaptureError::DisplayServerError(msg) => write!(f, "Display server error: {}", msg),
             ScreenCaptureError::InvalidMonitor(msg) => write!(f, "Invalid monitor: {}", msg),
+            ScreenCaptureError::InvalidRegion(msg) => write!(f, "Invalid capture region: {}", msg),
+            ScreenCaptureError::TooManyCaptures(msg) => write!(f, "Too many simultaneous captures: {}", msg),
+            ScreenCaptureError::MonitorBusy(msg) => write!(f, "Monitor busy: {}", msg),
+            ScreenCaptureError::VirtualDisplayError(msg) => write!(f, "Virtual display error: {}", msg),
             ScreenCaptureError::StreamBufferError(msg) => write!(f, "Stream buffer error: {}", msg),
             ScreenCaptureError::HardwareAccelerationError(msg) => write!(f, "Hardware acceleration error: {}", msg),
             ScreenCaptureError::FFmpegError(msg) => write!(f, "FFmpeg error: {}", msg),
             ScreenCaptureError::PipeWireError(msg) => write!(f, "PipeWire error: {}", msg),
+            ScreenCaptureError::PortalError(msg) => write!(f, "Portal error: {}", msg),
+            ScreenCaptureError::ConsentDenied(msg) => write!(f, "Consent denied: {}", msg),
+            ScreenCaptureError::EncoderDeviceNotFound(msg) => write!(
+                f, "Encoder device not found: {}. Check that the GPU driver is loaded or disable hardware acceleration", msg
+            ),
+            ScreenCaptureError::CudaUnavailable(msg) => write!(
+                f, "CUDA is not available: {}. Install the NVIDIA driver or choose VAAPI/software encoding", msg
+            ),
+            ScreenCaptureError::UnsupportedOption(msg) => write!(
+                f, "FFmpeg does not support an option: {}. Your FFmpeg build may lack this encoder or feature", msg
+            ),
+            ScreenCaptureError::DevicePermissionDenied(msg) => write!(
+                f, "Permission denied on GPU device: {}. Add your user to the 'render' and 'video' groups", msg
+            ),
+            ScreenCaptureError::UnsupportedEncoder(msg) => write!(
+                f, "Unsupported encoder: {}. Choose one of the offered codecs and acceleration options", msg
+            ),
+            ScreenCaptureError::InvalidConfig(msg) => write!(f, "Invalid capture configuration: {}", msg),
         }
     }
 }
 
 impl Error for ScreenCaptureError {}
 
+impl ScreenCaptureError {
+    /// Stable name of the error variant, used by the frontend to pick a hint
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ScreenCaptureError::InitializationFailed(_) => "InitializationFailed",
+            ScreenCaptureError::CaptureError(_) => "CaptureError",
+            ScreenCaptureError::EncodingError(_) => "EncodingError",
+            ScreenCaptureError::DisplayServerError(_) => "DisplayServerError",
+            ScreenCaptureError::InvalidMonitor(_) => "InvalidMonitor",
+            ScreenCaptureError::InvalidRegion(_) => "InvalidRegion",
+            ScreenCaptureError::TooManyCaptures(_) => "TooManyCaptures",
+            ScreenCaptureError::MonitorBusy(_) => "MonitorBusy",
+            ScreenCaptureError::VirtualDisplayError(_) => "VirtualDisplayError",
+            ScreenCaptureError::StreamBufferError(_) => "StreamBufferError",
+            ScreenCaptureError::HardwareAccelerationError(_) => "HardwareAccelerationError",
+            ScreenCaptureError::FFmpegError(_) => "FFmpegError",
+            ScreenCaptureError::PipeWireError(_) => "PipeWireError",
+            ScreenCaptureError::PortalError(_) => "PortalError",
+            ScreenCaptureError::ConsentDenied(_) => "ConsentDenied",
+            ScreenCaptureError::EncoderDeviceNotFound(_) => "EncoderDeviceNotFound",
+            ScreenCaptureError::CudaUnavailable(_) => "CudaUnavailable",
+            ScreenCaptureError::UnsupportedOption(_) => "UnsupportedOption",
+            ScreenCaptureError::DevicePermissionDenied(_) => "DevicePermissionDenied",
+            ScreenCaptureError::UnsupportedEncoder(_) => "UnsupportedEncoder",
+            ScreenCaptureError::InvalidConfig(_) => "InvalidConfig",
+        }
+    }
+}
+
 // Helper conversion traits for working with Result
 pub trait ScreenCaptureErrorExt<T> {
     fn with_context<C>(self, context: C) -> Result<T, ScreenCaptureError> 
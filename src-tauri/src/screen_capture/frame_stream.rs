@@ -0,0 +1,391 @@
+// screen_capture/frame_stream.rs - Binary frame transport to the frontend
+//
+// Frames are sent as binary WebSocket messages on a localhost port instead of
+// base64 strings through the Tauri IPC bridge. The URL path carries a random
+// token so other local processes cannot attach to the stream. Each frame
+// carries its frame clock timestamps, so the frontend can report when it was
+// rendered and the latency can be split into segments.
+
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::{Message, WebSocket};
+
+use crate::constant_time::constant_time_eq;
+use crate::logging::TARGET_SCREEN_CAPTURE;
+use crate::screen_capture::buffer::StreamBuffer;
+use crate::screen_capture::clock;
+use crate::screen_capture::error::ScreenCaptureError;
+use crate::screen_capture::types::{CaptureStats, FrameData};
+use crate::stats::{FrameLatencyTracker, SentFrame};
+
+/// Size of the header in front of every frame payload
+pub const FRAME_HEADER_LEN: usize = 45;
+
+/// Header flag for keyframes
+pub const FRAME_FLAG_KEYFRAME: u8 = 0x01;
+
+/// How long the sender sleeps when the stream buffer is empty
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// A stalled client must not block the sender forever
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A client that connects and sends nothing must not hold up the accept
+/// loop, and with it the frontend's stream
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Serializes a frame as one binary message, stamped with the time `sent_us`
+/// on the frame clock. All fields are big-endian:
+///
+/// | payload length u32 | timestamp ms u64 | flags u8 | width u32 | height u32 |
+/// | frame id u64 | encoded µs u64 | sent µs u64 | payload |
+pub fn encode_frame_message(frame: &FrameData, sent_us: u64) -> Vec<u8> {
+    let mut message = Vec::with_capacity(FRAME_HEADER_LEN + frame.data.len());
+    message.extend_from_slice(&(frame.data.len() as u32).to_be_bytes());
+    message.extend_from_slice(&frame.timestamp.to_be_bytes());
+    message.push(if frame.keyframe { FRAME_FLAG_KEYFRAME } else { 0 });
+    message.extend_from_slice(&frame.width.to_be_bytes());
+    message.extend_from_slice(&frame.height.to_be_bytes());
+    message.extend_from_slice(&frame.frame_id.to_be_bytes());
+    message.extend_from_slice(&frame.encoded_us.to_be_bytes());
+    message.extend_from_slice(&sent_us.to_be_bytes());
+    message.extend_from_slice(&frame.data);
+    message
+}
+
+/// Measures delivered bytes per second over windows of one second
+#[derive(Debug)]
+pub struct ThroughputMeter {
+    window_start: Instant,
+    window_bytes: u64,
+}
+
+impl Default for ThroughputMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ThroughputMeter {
+    pub fn new() -> Self {
+        ThroughputMeter {
+            window_start: Instant::now(),
+            window_bytes: 0,
+        }
+    }
+
+    /// Records sent bytes; returns the rate whenever a window completes
+    pub fn record(&mut self, bytes: usize) -> Option<u64> {
+        self.window_bytes += bytes as u64;
+
+        let elapsed = self.window_start.elapsed();
+        if elapsed < Duration::from_secs(1) {
+            return None;
+        }
+
+        let rate = (self.window_bytes as f64 / elapsed.as_secs_f64()) as u64;
+        self.window_start = Instant::now();
+        self.window_bytes = 0;
+        Some(rate)
+    }
+}
+
+/// Localhost WebSocket server streaming frames out of the StreamBuffer.
+///
+/// Only one client is served at a time since sending consumes the frames;
+/// the next client is accepted once the current one disconnects.
+pub struct FrameStreamServer {
+    port: u16,
+    token: String,
+    running: Arc<Mutex<bool>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FrameStreamServer {
+    /// Binds an ephemeral port on 127.0.0.1 and starts the sender thread.
+    /// Sent frames are recorded in `latency`.
+    pub fn start(
+        stream_buffer: Arc<Mutex<StreamBuffer>>,
+        stats: Arc<Mutex<CaptureStats>>,
+        latency: Arc<Mutex<FrameLatencyTracker>>,
+    ) -> Result<Self, ScreenCaptureError> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .map_err(|e| ScreenCaptureError::InitializationFailed(format!("Failed to bind frame stream socket: {}", e)))?;
+        listener.set_nonblocking(true)
+            .map_err(|e| ScreenCaptureError::InitializationFailed(format!("Failed to configure frame stream socket: {}", e)))?;
+        let port = listener.local_addr()
+            .map_err(|e| ScreenCaptureError::InitializationFailed(format!("Failed to read frame stream address: {}", e)))?
+            .port();
+
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+
+        let running = Arc::new(Mutex::new(true));
+        let thread_running = running.clone();
+        let path = format!("/{}", token);
+
+        let thread = thread::spawn(move || {
+            Self::accept_loop(listener, path, thread_running, stream_buffer, stats, latency);
+        });
+
+        log::info!(target: TARGET_SCREEN_CAPTURE, "Frame stream listening on 127.0.0.1:{}", port);
+
+        Ok(FrameStreamServer {
+            port,
+            token,
+            running,
+            thread: Some(thread),
+        })
+    }
+
+    /// WebSocket URL the frontend connects to
+    pub fn url(&self) -> String {
+        format!("ws://127.0.0.1:{}/{}", self.port, self.token)
+    }
+
+    /// Stops the server and waits for the sender thread
+    pub fn stop(&mut self) {
+        *self.running.lock().unwrap() = false;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+
+    fn accept_loop(
+        listener: TcpListener,
+        path: String,
+        running: Arc<Mutex<bool>>,
+        stream_buffer: Arc<Mutex<StreamBuffer>>,
+        stats: Arc<Mutex<CaptureStats>>,
+        latency: Arc<Mutex<FrameLatencyTracker>>,
+    ) {
+        while *running.lock().unwrap() {
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(50));
+                    continue;
+                },
+                Err(e) => {
+                    log::warn!(target: TARGET_SCREEN_CAPTURE, "Frame stream accept failed: {}", e);
+                    thread::sleep(Duration::from_millis(50));
+                    continue;
+                }
+            };
+
+            match Self::handshake(stream, &path) {
+                Ok(socket) => {
+                    log::info!(target: TARGET_SCREEN_CAPTURE, "Frame stream client connected");
+                    Self::serve_client(socket, &running, &stream_buffer, &stats, &latency);
+                    stats.lock().unwrap().transport_throughput = 0;
+                    log::info!(target: TARGET_SCREEN_CAPTURE, "Frame stream client disconnected");
+                },
+                Err(e) => log::warn!(target: TARGET_SCREEN_CAPTURE, "Rejected frame stream client: {}", e),
+            }
+        }
+    }
+
+    // The callback signature is tungstenite's, its error is an HTTP response
+    #[allow(clippy::result_large_err)]
+    fn handshake(stream: TcpStream, path: &str) -> Result<WebSocket<TcpStream>, String> {
+        stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+        stream.set_nodelay(true).map_err(|e| e.to_string())?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT)).map_err(|e| e.to_string())?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT)).map_err(|e| e.to_string())?;
+
+        let check_token = |request: &Request, response: Response| -> Result<Response, ErrorResponse> {
+            if constant_time_eq(request.uri().path().as_bytes(), path.as_bytes()) {
+                return Ok(response);
+            }
+            let mut rejection = ErrorResponse::new(Some("Invalid frame stream token".to_string()));
+            *rejection.status_mut() = StatusCode::FORBIDDEN;
+            Err(rejection)
+        };
+
+        let socket = tungstenite::accept_hdr(stream, check_token).map_err(|e| e.to_string())?;
+        // The sender only writes; frames must not be cut off by a read deadline
+        socket.get_ref().set_read_timeout(None).map_err(|e| e.to_string())?;
+        Ok(socket)
+    }
+
+    fn serve_client(
+        mut socket: WebSocket<TcpStream>,
+        running: &Arc<Mutex<bool>>,
+        stream_buffer: &Arc<Mutex<StreamBuffer>>,
+        stats: &Arc<Mutex<CaptureStats>>,
+        latency: &Arc<Mutex<FrameLatencyTracker>>,
+    ) {
+        let mut meter = ThroughputMeter::new();
+
+        while *running.lock().unwrap() {
+            let frame = stream_buffer.lock().unwrap().get_next_frame();
+            let frame = match frame {
+                Some(frame) => frame,
+                None => {
+                    thread::sleep(IDLE_POLL_INTERVAL);
+                    continue;
+                }
+            };
+
+            let sent_us = clock::monotonic_us();
+            let message = encode_frame_message(&frame, sent_us);
+            let len = message.len();
+            if let Err(e) = socket.send(Message::Binary(message)) {
+                log::debug!(target: TARGET_SCREEN_CAPTURE, "Frame stream send failed: {}", e);
+                return;
+            }
+            latency.lock().unwrap().frame_sent(SentFrame {
+                frame_id: frame.frame_id,
+                captured_us: frame.captured_us,
+                encoded_us: frame.encoded_us,
+                sent_us,
+            });
+
+            if let Some(rate) = meter.record(len) {
+                stats.lock().unwrap().transport_throughput = rate;
+            }
+        }
+
+        let _ = socket.close(None);
+    }
+}
+
+impl Drop for FrameStreamServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen_capture::buffer::DropMode;
+    use crate::screen_capture::types::CaptureActivity;
+
+    fn test_frame(data: &[u8], keyframe: bool) -> FrameData {
+        FrameData {
+            data: data.to_vec(),
+            timestamp: 1234,
+            keyframe,
+            width: 1920,
+            height: 1080,
+            format: "h264".to_string(),
+            frame_id: 7,
+            captured_us: 900,
+            encoded_us: 1000,
+        }
+    }
+
+    fn empty_stats() -> CaptureStats {
+        CaptureStats {
+            monitor_index: 0,
+            fps: 0.0,
+            bitrate: 0,
+            encode_time: 0.0,
+            frame_size: 0,
+            frame_count: 0,
+            dropped_frames: 0,
+            buffer_level: 0,
+            latency_estimate: 0.0,
+            restart_count: 0,
+            hardware_acceleration_fallback: None,
+            transport_throughput: 0,
+            activity: CaptureActivity::Active,
+            time_since_keyframe_ms: None,
+            host_session: None,
+            skipped_frames: 0,
+            freeze_recoveries: 0,
+            gpu: None,
+        }
+    }
+
+    #[test]
+    fn test_frame_message_layout() {
+        let message = encode_frame_message(&test_frame(b"payload", true), 1500);
+
+        assert_eq!(message.len(), FRAME_HEADER_LEN + 7);
+        assert_eq!(&message[0..4], &7u32.to_be_bytes());
+        assert_eq!(&message[4..12], &1234u64.to_be_bytes());
+        assert_eq!(message[12], FRAME_FLAG_KEYFRAME);
+        assert_eq!(&message[13..17], &1920u32.to_be_bytes());
+        assert_eq!(&message[17..21], &1080u32.to_be_bytes());
+        assert_eq!(&message[21..29], &7u64.to_be_bytes());
+        assert_eq!(&message[29..37], &1000u64.to_be_bytes());
+        assert_eq!(&message[37..45], &1500u64.to_be_bytes());
+        assert_eq!(&message[FRAME_HEADER_LEN..], b"payload");
+
+        let delta = encode_frame_message(&test_frame(b"", false), 1500);
+        assert_eq!(delta[12], 0);
+    }
+
+    #[test]
+    fn test_throughput_meter_reports_per_window() {
+        let mut meter = ThroughputMeter::new();
+        assert_eq!(meter.record(1000), None);
+
+        meter.window_start -= Duration::from_secs(2);
+        let rate = meter.record(1000).unwrap();
+        assert!((900..=1000).contains(&rate));
+        assert_eq!(meter.window_bytes, 0);
+    }
+
+    #[test]
+    fn test_streams_frames_to_client_with_token() {
+        let buffer = Arc::new(Mutex::new(StreamBuffer::new(10, 10, 30, DropMode::DropOldest)));
+        buffer.lock().unwrap().push_frame(test_frame(b"frame-1", true)).unwrap();
+        let stats = Arc::new(Mutex::new(empty_stats()));
+
+        let latency = Arc::new(Mutex::new(FrameLatencyTracker::new()));
+        let mut server = FrameStreamServer::start(buffer.clone(), stats, latency.clone()).unwrap();
+
+        let wrong_url = format!("ws://127.0.0.1:{}/wrong-token", server.port);
+        assert!(tungstenite::connect(wrong_url).is_err());
+
+        let (mut client, _) = tungstenite::connect(server.url()).unwrap();
+        match client.read().unwrap() {
+            Message::Binary(message) => {
+                assert_eq!(message[12], FRAME_FLAG_KEYFRAME);
+                assert_eq!(&message[FRAME_HEADER_LEN..], b"frame-1");
+            },
+            other => panic!("unexpected message {:?}", other),
+        }
+        assert!(buffer.lock().unwrap().is_empty());
+
+        server.stop();
+        assert_eq!(latency.lock().unwrap().breakdown(clock::monotonic_us()).frames, 1);
+    }
+
+    #[test]
+    fn test_silent_client_does_not_block_the_frontend() {
+        let buffer = Arc::new(Mutex::new(StreamBuffer::new(10, 10, 30, DropMode::DropOldest)));
+        buffer.lock().unwrap().push_frame(test_frame(b"frame-1", true)).unwrap();
+        let stats = Arc::new(Mutex::new(empty_stats()));
+        let latency = Arc::new(Mutex::new(FrameLatencyTracker::new()));
+        let mut server = FrameStreamServer::start(buffer, stats, latency).unwrap();
+
+        // Connects first and never sends a handshake, but stays open
+        let _silent = TcpStream::connect(("127.0.0.1", server.port)).unwrap();
+        thread::sleep(Duration::from_millis(100));
+
+        let started = Instant::now();
+        let (mut client, _) = tungstenite::connect(server.url()).unwrap();
+        match client.read().unwrap() {
+            Message::Binary(message) => assert_eq!(&message[FRAME_HEADER_LEN..], b"frame-1"),
+            other => panic!("unexpected message {:?}", other),
+        }
+        assert!(started.elapsed() < HANDSHAKE_TIMEOUT + Duration::from_secs(2));
+
+        server.stop();
+    }
+}
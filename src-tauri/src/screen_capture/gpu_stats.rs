@@ -0,0 +1,403 @@
+// screen_capture/gpu_stats.rs - Encoder load and memory of the GPU
+//
+// With VAAPI or NVENC the CPU figures alone do not tell whether the encoder
+// or the copy path limits the stream. A `GpuMonitor` samples the GPU on its
+// own thread, at most once per `GPU_SAMPLE_INTERVAL`, and the capture loop
+// only copies the cached reading into `CaptureStats`.
+//
+// Like the D-Bus queries, the readings come from the tools that ship with the
+// drivers instead of linked libraries: `nvidia-smi dmon` for NVIDIA,
+// `intel_gpu_top -J` for Intel and the amdgpu files in /sys/class/drm for
+// AMD. The i915 engine directories in sysfs carry no busy counters, so Intel
+// needs intel_gpu_top, which in turn usually needs CAP_PERFMON. Every source
+// is optional: a missing tool, a failing one or unreadable output only leaves
+// fields at None, and a tool that failed is not started again.
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::command_runner::CommandRunner;
+use crate::logging::TARGET_SCREEN_CAPTURE;
+use crate::screen_capture::types::HardwareAcceleration;
+
+/// Shortest time between two samples of the GPU
+pub const GPU_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where the kernel lists the DRM devices
+pub const DRM_DIR: &str = "/sys/class/drm";
+
+/// A tool taking longer than this is killed and not asked again
+const TOOL_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Where a GPU reading came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GpuStatsSource {
+    NvidiaSmi,
+    IntelGpuTop,
+    AmdgpuSysfs,
+}
+
+/// Load and memory of the GPU the encoder runs on; fields the source does
+/// not report are None
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GpuUsage {
+    pub source: GpuStatsSource,
+    #[serde(default)]
+    pub encoder_utilization: Option<f64>, // Busy percentage of the video encode engine
+    #[serde(default)]
+    pub gpu_utilization: Option<f64>,     // Busy percentage of the 3D/compute engine
+    #[serde(default)]
+    pub memory_used_bytes: Option<u64>,   // Dedicated video memory in use
+    #[serde(default)]
+    pub memory_total_bytes: Option<u64>,
+}
+
+impl GpuUsage {
+    fn new(source: GpuStatsSource) -> Self {
+        GpuUsage {
+            source,
+            encoder_utilization: None,
+            gpu_utilization: None,
+            memory_used_bytes: None,
+            memory_total_bytes: None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.encoder_utilization.is_none()
+            && self.gpu_utilization.is_none()
+            && self.memory_used_bytes.is_none()
+            && self.memory_total_bytes.is_none()
+    }
+}
+
+/// Samples the GPU in the background until stopped
+pub struct GpuMonitor {
+    latest: Arc<Mutex<Option<GpuUsage>>>,
+    stopped: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl GpuMonitor {
+    /// Starts sampling for a capture encoding with `acceleration`; None for
+    /// software encoding, which leaves the GPU alone
+    pub fn start(runner: Arc<dyn CommandRunner>, acceleration: &HardwareAcceleration) -> Option<Self> {
+        let sampler = GpuSampler::new(runner, acceleration, PathBuf::from(DRM_DIR))?;
+        Some(Self::start_with_interval(sampler, GPU_SAMPLE_INTERVAL))
+    }
+
+    fn start_with_interval(mut sampler: GpuSampler, interval: Duration) -> Self {
+        let latest = Arc::new(Mutex::new(None));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let thread_latest = latest.clone();
+        let thread_stopped = stopped.clone();
+        let thread = thread::spawn(move || {
+            while !thread_stopped.load(Ordering::SeqCst) && sampler.has_sources() {
+                *thread_latest.lock().unwrap() = sampler.sample();
+                sleep_unless_stopped(&thread_stopped, interval);
+            }
+        });
+
+        GpuMonitor { latest, stopped, thread: Some(thread) }
+    }
+
+    /// The last reading; None before the first one or without any source
+    pub fn latest(&self) -> Option<GpuUsage> {
+        self.latest.lock().unwrap().clone()
+    }
+
+    pub fn stop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for GpuMonitor {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Sources of GPU readings, in the order they are tried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Source {
+    NvidiaSmi,
+    AmdgpuSysfs,
+    IntelGpuTop,
+}
+
+/// Reads the GPU through the sources that fit the hardware encoder
+struct GpuSampler {
+    runner: Arc<dyn CommandRunner>,
+    drm_dir: PathBuf,
+    sources: Vec<Source>,
+}
+
+impl GpuSampler {
+    fn new(runner: Arc<dyn CommandRunner>, acceleration: &HardwareAcceleration, drm_dir: PathBuf) -> Option<Self> {
+        let sources = match acceleration {
+            HardwareAcceleration::None => return None,
+            HardwareAcceleration::NVENC => vec![Source::NvidiaSmi],
+            // VAAPI runs on AMD and Intel alike; the sysfs files are cheaper to try
+            HardwareAcceleration::VAAPI => vec![Source::AmdgpuSysfs, Source::IntelGpuTop],
+            HardwareAcceleration::QuickSync => vec![Source::IntelGpuTop],
+        };
+        Some(GpuSampler { runner, drm_dir, sources })
+    }
+
+    fn has_sources(&self) -> bool {
+        !self.sources.is_empty()
+    }
+
+    /// Reading of the first source that delivers one. Tools that fail are
+    /// dropped; the sysfs files are read again, a GPU may appear later.
+    fn sample(&mut self) -> Option<GpuUsage> {
+        let mut failed = Vec::new();
+        let mut usage = None;
+        for source in self.sources.clone() {
+            let reading = match source {
+                Source::NvidiaSmi => self.run_tool("nvidia-smi", &["dmon", "-c", "1", "-s", "um"])
+                    .map(|output| parse_nvidia_smi_dmon(&output)),
+                Source::IntelGpuTop => self.run_tool("intel_gpu_top", &["-J", "-s", "500", "-n", "1"])
+                    .map(|output| parse_intel_gpu_top(&output)),
+                Source::AmdgpuSysfs => Some(read_amdgpu_sysfs(&self.drm_dir)),
+            };
+            match reading {
+                Some(Some(reading)) => {
+                    usage = Some(reading);
+                    break;
+                },
+                Some(None) => {},
+                None => failed.push(source),
+            }
+        }
+        self.sources.retain(|source| !failed.contains(source));
+        usage
+    }
+
+    /// Stdout of a tool that exited successfully within `TOOL_TIMEOUT`
+    fn run_tool(&self, program: &str, args: &[&str]) -> Option<Vec<u8>> {
+        let spawned = self.runner.command(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(e) => {
+                log::debug!(target: TARGET_SCREEN_CAPTURE, "No GPU statistics from {}: {}", program, e);
+                return None;
+            },
+        };
+
+        let started = Instant::now();
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break Some(status),
+                Ok(None) if started.elapsed() < TOOL_TIMEOUT => thread::sleep(Duration::from_millis(20)),
+                _ => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    break None;
+                },
+            }
+        };
+        match status {
+            Some(status) if status.success() => {
+                let mut output = Vec::new();
+                child.stdout.take()?.read_to_end(&mut output).ok()?;
+                Some(output)
+            },
+            status => {
+                log::debug!(target: TARGET_SCREEN_CAPTURE, "No GPU statistics from {}: {}", program,
+                    status.map_or("timed out".to_string(), |status| status.to_string()));
+                None
+            },
+        }
+    }
+}
+
+/// First GPU of `nvidia-smi dmon -s um`. The columns differ between driver
+/// versions, so they are looked up by the names in the first header line;
+/// "-" marks a figure the GPU does not report.
+fn parse_nvidia_smi_dmon(output: &[u8]) -> Option<GpuUsage> {
+    let output = String::from_utf8_lossy(output);
+    let mut lines = output.lines();
+    let columns: Vec<&str> = lines.next()?.strip_prefix('#')?.split_whitespace().collect();
+    let values: Vec<&str> = lines.find(|line| !line.starts_with('#') && !line.trim().is_empty())?
+        .split_whitespace()
+        .collect();
+    let value = |name: &str| {
+        let index = columns.iter().position(|column| *column == name)?;
+        values.get(index)?.parse::<f64>().ok()
+    };
+
+    let mut usage = GpuUsage::new(GpuStatsSource::NvidiaSmi);
+    usage.encoder_utilization = value("enc");
+    usage.gpu_utilization = value("sm");
+    usage.memory_used_bytes = value("fb").map(|mib| (mib * 1024.0 * 1024.0) as u64);
+    (!usage.is_empty()).then_some(usage)
+}
+
+/// Last sample of `intel_gpu_top -J`. Its output is a JSON array that is
+/// left open when the tool is cut off, and engines are named by class
+/// ("Video") or, with -p, per instance ("Video/0"). Memory is shared with
+/// the CPU, so it is not reported.
+fn parse_intel_gpu_top(output: &[u8]) -> Option<GpuUsage> {
+    let text = String::from_utf8_lossy(output);
+    let text = text.trim().trim_end_matches(',');
+    let json: serde_json::Value = serde_json::from_str(text)
+        .or_else(|_| serde_json::from_str(&format!("{}]", text)))
+        .ok()?;
+    let sample = match &json {
+        serde_json::Value::Array(samples) => samples.last()?,
+        sample => sample,
+    };
+
+    let engines = sample.get("engines")?.as_object()?;
+    let busiest = |class: &str| {
+        engines.iter()
+            .filter(|(name, _)| name.as_str() == class || name.strip_prefix(class).map_or(false, |rest| rest.starts_with('/')))
+            .filter_map(|(_, engine)| engine.get("busy")?.as_f64())
+            .reduce(f64::max)
+    };
+
+    let mut usage = GpuUsage::new(GpuStatsSource::IntelGpuTop);
+    usage.encoder_utilization = busiest("Video");
+    usage.gpu_utilization = busiest("Render/3D");
+    (!usage.is_empty()).then_some(usage)
+}
+
+/// Load and video memory of the first amdgpu card in `drm_dir`. amdgpu has
+/// no busy figure for its encode engine, only for the GPU as a whole.
+fn read_amdgpu_sysfs(drm_dir: &Path) -> Option<GpuUsage> {
+    let mut cards: Vec<PathBuf> = fs::read_dir(drm_dir).ok()?
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.strip_prefix("card").map_or(false, |number| number.chars().all(|c| c.is_ascii_digit()))
+        })
+        .map(|entry| entry.path().join("device"))
+        .collect();
+    cards.sort();
+
+    cards.iter().find_map(|device| {
+        let read = |attribute: &str| fs::read_to_string(device.join(attribute)).ok()?.trim().parse::<u64>().ok();
+        let mut usage = GpuUsage::new(GpuStatsSource::AmdgpuSysfs);
+        usage.gpu_utilization = read("gpu_busy_percent").map(|percent| percent as f64);
+        usage.memory_used_bytes = read("mem_info_vram_used");
+        usage.memory_total_bytes = read("mem_info_vram_total");
+        (!usage.is_empty()).then_some(usage)
+    })
+}
+
+/// Sleeps in short steps so stop() does not wait for a whole interval
+fn sleep_unless_stopped(stopped: &AtomicBool, duration: Duration) {
+    let step = Duration::from_millis(50);
+    let mut slept = Duration::ZERO;
+    while slept < duration && !stopped.load(Ordering::SeqCst) {
+        thread::sleep(step);
+        slept += step;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::{call, fixture, TestEnv};
+
+    #[test]
+    fn test_parse_nvidia_smi_dmon() {
+        let usage = parse_nvidia_smi_dmon(&fixture("nvidia-smi-dmon.txt")).unwrap();
+        assert_eq!(usage.source, GpuStatsSource::NvidiaSmi);
+        assert_eq!(usage.encoder_utilization, Some(23.0));
+        assert_eq!(usage.gpu_utilization, Some(11.0));
+        assert_eq!(usage.memory_used_bytes, Some(1187 * 1024 * 1024));
+        assert_eq!(usage.memory_total_bytes, None);
+
+        // Older drivers have fewer columns, GPUs without an encoder report "-"
+        let old = b"# gpu   sm  mem  enc  dec   fb bar1\n# Idx    %    %    %    %   MB   MB\n    0    4    2    -    0  301    3\n";
+        let usage = parse_nvidia_smi_dmon(old).unwrap();
+        assert_eq!((usage.encoder_utilization, usage.gpu_utilization), (None, Some(4.0)));
+        assert_eq!(parse_nvidia_smi_dmon(b"NVIDIA-SMI has failed because it couldn't communicate with the NVIDIA driver."), None);
+        assert_eq!(parse_nvidia_smi_dmon(b""), None);
+    }
+
+    #[test]
+    fn test_parse_intel_gpu_top() {
+        let usage = parse_intel_gpu_top(&fixture("intel_gpu_top.json")).unwrap();
+        assert_eq!(usage.source, GpuStatsSource::IntelGpuTop);
+        assert_eq!(usage.encoder_utilization, Some(37.5));
+        assert_eq!(usage.gpu_utilization, Some(4.2));
+        assert_eq!(usage.memory_used_bytes, None);
+
+        // Cut off before the array was closed, engines listed per instance
+        let open = br#"[
+{"period": {"duration": 500.1, "unit": "ms"}, "engines": {"Render/3D/0": {"busy": 1.0, "unit": "%"}, "Video/0": {"busy": 12.0, "unit": "%"}, "Video/1": {"busy": 30.5, "unit": "%"}, "VideoEnhance/0": {"busy": 90.0, "unit": "%"}}},
+"#;
+        let usage = parse_intel_gpu_top(open).unwrap();
+        assert_eq!((usage.encoder_utilization, usage.gpu_utilization), (Some(30.5), Some(1.0)));
+        assert_eq!(parse_intel_gpu_top(b"Failed to initialize PMU! (Permission denied)"), None);
+    }
+
+    #[test]
+    fn test_read_amdgpu_sysfs() {
+        let dir = std::env::temp_dir().join(format!("smoldesk-drm-{}", uuid::Uuid::new_v4()));
+        assert_eq!(read_amdgpu_sysfs(&dir), None);
+
+        // The Intel card has no amdgpu files, connectors are not cards
+        fs::create_dir_all(dir.join("card0/device")).unwrap();
+        fs::create_dir_all(dir.join("card1-DP-1")).unwrap();
+        let device = dir.join("card1/device");
+        fs::create_dir_all(&device).unwrap();
+        fs::write(device.join("gpu_busy_percent"), "17\n").unwrap();
+        fs::write(device.join("mem_info_vram_used"), "536870912\n").unwrap();
+        fs::write(device.join("mem_info_vram_total"), "8589934592\n").unwrap();
+
+        let usage = read_amdgpu_sysfs(&dir).unwrap();
+        assert_eq!(usage.source, GpuStatsSource::AmdgpuSysfs);
+        assert_eq!((usage.encoder_utilization, usage.gpu_utilization), (None, Some(17.0)));
+        assert_eq!((usage.memory_used_bytes, usage.memory_total_bytes), (Some(536870912), Some(8589934592)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_monitor_caches_samples_and_drops_failing_tools() {
+        let env = TestEnv::new();
+        env.set_stdout("nvidia-smi", fixture("nvidia-smi-dmon.txt"));
+        assert!(GpuMonitor::start(env.runner(), &HardwareAcceleration::None).is_none());
+
+        let sampler = GpuSampler::new(env.runner(), &HardwareAcceleration::NVENC, env.dir().join("drm")).unwrap();
+        let mut monitor = GpuMonitor::start_with_interval(sampler, Duration::from_secs(60));
+        let started = Instant::now();
+        while monitor.latest().is_none() && started.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(monitor.latest().and_then(|usage| usage.encoder_utilization), Some(23.0));
+        monitor.stop();
+
+        // Sampled once, the reading is cached until the interval is up
+        assert_eq!(env.take_invocations(), vec![call("nvidia-smi dmon -c 1 -s um")]);
+
+        // Without the permission for intel_gpu_top nothing is reported, and it is not tried again
+        env.set_exit_code("intel_gpu_top", 1);
+        let mut sampler = GpuSampler::new(env.runner(), &HardwareAcceleration::VAAPI, env.dir().join("drm")).unwrap();
+        assert_eq!(sampler.sample(), None);
+        assert_eq!(sampler.sample(), None);
+        assert_eq!(env.take_invocations(), vec![call("intel_gpu_top -J -s 500 -n 1")]);
+        assert!(sampler.has_sources());
+    }
+}
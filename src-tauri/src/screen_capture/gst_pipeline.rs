@@ -0,0 +1,198 @@
+// screen_capture/gst_pipeline.rs - In-process GStreamer encoder backend
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread;
+
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use gstreamer_app as gst_app;
+
+use crate::logging::TARGET_SCREEN_CAPTURE;
+use crate::screen_capture::backend::{EncoderBackend, EncoderOutput};
+use crate::screen_capture::error::{ScreenCaptureError, to_encoding_error, to_init_error};
+use crate::screen_capture::supervisor::StderrCollector;
+
+/// How often the bus thread checks whether the pipeline was stopped
+const BUS_POLL_INTERVAL_MS: u64 = 100;
+
+/// Name of the appsink in `backend::GSTREAMER_APPSINK`
+const APPSINK_NAME: &str = "sink";
+
+/// Encoder pipeline running inside the SmolDesk process.
+///
+/// pipewiresrc hands the frames straight to the encoder, without a pipe to
+/// an external process in between; the matroska stream is pulled from an appsink.
+pub struct GStreamerBackend {
+    description: String,
+    pipeline: Option<gst::Pipeline>,
+    stderr: StderrCollector,
+    stopped: Arc<AtomicBool>,
+    bus_thread: Option<thread::JoinHandle<()>>,
+}
+
+impl GStreamerBackend {
+    /// Backend for a pipeline from `backend::gstreamer_pipeline` ending in the appsink
+    pub fn new(elements: Vec<String>) -> Self {
+        GStreamerBackend {
+            description: elements.join(" "),
+            pipeline: None,
+            stderr: StderrCollector::default(),
+            stopped: Arc::new(AtomicBool::new(false)),
+            bus_thread: None,
+        }
+    }
+
+    /// Copies the errors a failed state change left on the bus into the diagnostics
+    fn drain_errors(&self, bus: &gst::Bus) {
+        while let Some(message) = bus.pop_filtered(&[gst::MessageType::Error]) {
+            if let gst::MessageView::Error(error) = message.view() {
+                self.stderr.push(format_error(&error));
+            }
+        }
+        self.stderr.close();
+    }
+}
+
+impl EncoderBackend for GStreamerBackend {
+    fn name(&self) -> &'static str {
+        "GStreamer"
+    }
+
+    fn build(&mut self) -> Result<(), ScreenCaptureError> {
+        gst::init().map_err(|e| to_init_error(e, "Failed to initialize GStreamer"))?;
+
+        let pipeline = gst::parse::launch(&self.description)
+            .map_err(|e| to_encoding_error(e, "Invalid GStreamer pipeline"))?
+            .downcast::<gst::Pipeline>()
+            .map_err(|_| ScreenCaptureError::EncodingError("GStreamer description is not a pipeline".to_string()))?;
+        self.pipeline = Some(pipeline);
+        Ok(())
+    }
+
+    fn start(&mut self) -> Result<Receiver<EncoderOutput>, ScreenCaptureError> {
+        let pipeline = self.pipeline.clone()
+            .ok_or_else(|| ScreenCaptureError::EncodingError("GStreamer pipeline was not built".to_string()))?;
+        let appsink = pipeline.by_name(APPSINK_NAME)
+            .and_then(|element| element.downcast::<gst_app::AppSink>().ok())
+            .ok_or_else(|| ScreenCaptureError::EncodingError("GStreamer pipeline has no appsink".to_string()))?;
+        let bus = pipeline.bus()
+            .ok_or_else(|| ScreenCaptureError::EncodingError("GStreamer pipeline has no bus".to_string()))?;
+
+        let (sender, receiver) = mpsc::channel();
+        let sample_sender = sender.clone();
+        appsink.set_callbacks(
+            gst_app::AppSinkCallbacks::builder()
+                .new_sample(move |sink| {
+                    let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                    let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
+                    let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+                    // The capture loop is gone, nobody reads the stream anymore
+                    sample_sender.send(EncoderOutput::Data(map.as_slice().to_vec()))
+                        .map_err(|_| gst::FlowError::Flushing)?;
+                    Ok(gst::FlowSuccess::Ok)
+                })
+                .build(),
+        );
+
+        self.stderr = StderrCollector::default();
+        if let Err(e) = pipeline.set_state(gst::State::Playing) {
+            self.drain_errors(&bus);
+            let _ = pipeline.set_state(gst::State::Null);
+            return Err(to_encoding_error(e, "Failed to start GStreamer pipeline"));
+        }
+
+        // Bus errors take the place of an encoder process's stderr
+        self.stopped.store(false, Ordering::SeqCst);
+        let stopped = self.stopped.clone();
+        let stderr = self.stderr.clone();
+        self.bus_thread = Some(thread::spawn(move || {
+            while !stopped.load(Ordering::SeqCst) {
+                let message = match bus.timed_pop(gst::ClockTime::from_mseconds(BUS_POLL_INTERVAL_MS)) {
+                    Some(message) => message,
+                    None => continue,
+                };
+                match message.view() {
+                    gst::MessageView::Error(error) => {
+                        stderr.push(format_error(&error));
+                        let _ = sender.send(EncoderOutput::Ended(format!("GStreamer pipeline failed: {}", error.error())));
+                        break;
+                    },
+                    gst::MessageView::Warning(warning) => {
+                        log::warn!(target: TARGET_SCREEN_CAPTURE, "GStreamer warning: {}", warning.error());
+                        stderr.push(format!("Warning: {}", warning.error()));
+                    },
+                    gst::MessageView::Eos(_) => {
+                        let _ = sender.send(EncoderOutput::Ended("GStreamer pipeline reached end of stream".to_string()));
+                        break;
+                    },
+                    _ => {},
+                }
+            }
+            stderr.close();
+        }));
+
+        Ok(receiver)
+    }
+
+    fn diagnostics(&self) -> StderrCollector {
+        self.stderr.clone()
+    }
+
+    fn stop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+
+        // Dropping the pipeline releases the appsink callback, which closes the channel
+        if let Some(pipeline) = self.pipeline.take() {
+            if let Err(e) = pipeline.set_state(gst::State::Null) {
+                log::error!(target: TARGET_SCREEN_CAPTURE, "Error stopping GStreamer pipeline: {}", e);
+            }
+        }
+        if let Some(handle) = self.bus_thread.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for GStreamerBackend {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Error message with the failing element, e.g. "/GstPipeline:pipeline0/GstVaapiEncodeH264:vaapih264enc0: ..."
+fn format_error(error: &gst::message::Error) -> String {
+    let source = error.src()
+        .map(|source| source.path_string().to_string())
+        .unwrap_or_else(|| "pipeline".to_string());
+    match error.debug() {
+        Some(debug) => format!("{}: {} ({})", source, error.error(), debug),
+        None => format!("{}: {}", source, error.error()),
+    }
+}
+
+/// Names out of `base` and `encoders` that GStreamer can create. Empty if
+/// GStreamer cannot be initialized or one of the `base` elements is missing.
+pub fn available_elements(base: &[&str], encoders: &[&str]) -> HashSet<String> {
+    if let Err(e) = gst::init() {
+        log::debug!(target: TARGET_SCREEN_CAPTURE, "GStreamer backend unavailable: {}", e);
+        return HashSet::new();
+    }
+
+    let missing: Vec<&str> = base.iter()
+        .copied()
+        .filter(|name| gst::ElementFactory::find(name).is_none())
+        .collect();
+    if !missing.is_empty() {
+        log::debug!(target: TARGET_SCREEN_CAPTURE, "GStreamer backend unavailable, missing elements: {}", missing.join(", "));
+        return HashSet::new();
+    }
+
+    base.iter()
+        .chain(encoders)
+        .filter(|name| gst::ElementFactory::find(name).is_some())
+        .map(|name| name.to_string())
+        .collect()
+}
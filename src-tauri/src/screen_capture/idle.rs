@@ -0,0 +1,245 @@
+// screen_capture/idle.rs - Idle detection for static screens
+
+use crate::screen_capture::config::{IdleConfig, ScreenCaptureConfig};
+use crate::screen_capture::types::CaptureActivity;
+
+/// ffmpeg filter that drops frames identical to the last kept one.
+///
+/// At most `fps / idle_fps - 1` frames in a row are dropped, so a static
+/// screen is still encoded at `idle_fps` and the stream stays alive. The first
+/// frame that differs is passed on at once, which is what makes the ramp back
+/// up to the full frame rate take a single frame. Needs variable frame rate
+/// output (`-vsync vfr`), otherwise ffmpeg duplicates the dropped frames again.
+///
+/// Returns None when idle throttling is disabled or would not lower the rate.
+pub fn decimate_filter(config: &ScreenCaptureConfig) -> Option<String> {
+    let max_dropped = max_dropped_frames(&config.idle, config.fps);
+    if max_dropped == 0 {
+        return None;
+    }
+    Some(format!("mpdecimate=max={}", max_dropped))
+}
+
+fn max_dropped_frames(idle: &IdleConfig, fps: u32) -> u32 {
+    if !idle.enabled || idle.idle_fps == 0 {
+        return 0;
+    }
+    (fps / idle.idle_fps).saturating_sub(1)
+}
+
+/// Tracks whether the captured screen is changing, based on the timing of the
+/// frames that leave the decimating encoder.
+///
+/// While the screen changes, frames arrive at the full frame rate. Once it is
+/// static, only keepalive frames arrive, one per `fps / idle_fps` frames. A frame
+/// that arrives noticeably earlier than the keepalive interval therefore carries
+/// a change.
+///
+/// Active -> Idle: no changed frame for `idle_after_ms`.
+/// Idle -> Active: the first changed frame.
+///
+/// With X11 damage tracking the grabber knows whether a frame carries a
+/// change and reports it directly through `on_change_signal`.
+///
+/// While idle the adaptive quality controller is paused: the low frame rate
+/// and bitrate are caused by the throttling, not by the network, and must not
+/// lower the quality that is used once the screen changes again.
+pub struct IdleDetector {
+    /// False when the pipeline does not drop static frames
+    enabled: bool,
+
+    /// Frames closer together than this carry a change
+    change_threshold_ms: u64,
+
+    /// Time without changes after which the screen counts as idle
+    idle_after_ms: u64,
+
+    state: CaptureActivity,
+    last_frame_ms: Option<u64>,
+    last_change_ms: Option<u64>,
+}
+
+impl IdleDetector {
+    pub fn new(config: &ScreenCaptureConfig) -> Self {
+        let fps = config.fps.max(1) as u64;
+        let max_dropped = max_dropped_frames(&config.idle, config.fps) as u64;
+        let frame_interval_ms = 1000 / fps;
+        let keepalive_interval_ms = (max_dropped + 1) * 1000 / fps;
+
+        IdleDetector {
+            enabled: max_dropped > 0,
+            change_threshold_ms: keepalive_interval_ms.saturating_sub(frame_interval_ms / 2),
+            idle_after_ms: config.idle.idle_after_ms,
+            state: CaptureActivity::Active,
+            last_frame_ms: None,
+            last_change_ms: None,
+        }
+    }
+
+    /// Detector for pipelines without decimation; always reports Active
+    pub fn disabled() -> Self {
+        IdleDetector {
+            enabled: false,
+            change_threshold_ms: 0,
+            idle_after_ms: 0,
+            state: CaptureActivity::Active,
+            last_frame_ms: None,
+            last_change_ms: None,
+        }
+    }
+
+    pub fn state(&self) -> CaptureActivity {
+        self.state
+    }
+
+    /// Whether the adaptive quality controller may act on current metrics
+    pub fn quality_control_enabled(&self) -> bool {
+        self.state == CaptureActivity::Active
+    }
+
+    /// Feeds the timestamp (ms) of an encoded frame; returns the new state on
+    /// a transition.
+    pub fn on_frame(&mut self, timestamp_ms: u64) -> Option<CaptureActivity> {
+        if !self.enabled {
+            return None;
+        }
+
+        // A timestamp going backwards means a restarted process: count as a change
+        let changed = match self.last_frame_ms {
+            Some(last) => match timestamp_ms.checked_sub(last) {
+                Some(gap) => gap < self.change_threshold_ms,
+                None => true,
+            },
+            None => true,
+        };
+        self.last_frame_ms = Some(timestamp_ms);
+
+        self.on_change_signal(timestamp_ms, changed)
+    }
+
+    /// Feeds an encoded frame whose source already knows whether the screen
+    /// changed, e.g. from X11 damage tracking; returns the new state on a
+    /// transition.
+    pub fn on_change_signal(&mut self, timestamp_ms: u64, changed: bool) -> Option<CaptureActivity> {
+        if !self.enabled {
+            return None;
+        }
+
+        if changed {
+            self.last_change_ms = Some(timestamp_ms);
+            return self.transition(CaptureActivity::Active);
+        }
+
+        let since_change = self.last_change_ms
+            .map(|last| timestamp_ms.saturating_sub(last))
+            .unwrap_or(0);
+        if since_change >= self.idle_after_ms {
+            return self.transition(CaptureActivity::Idle);
+        }
+        None
+    }
+
+    fn transition(&mut self, state: CaptureActivity) -> Option<CaptureActivity> {
+        if self.state == state {
+            return None;
+        }
+        self.state = state;
+        Some(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen_capture::config::ScreenCaptureConfigBuilder;
+
+    fn config(fps: u32, idle_fps: u32) -> ScreenCaptureConfig {
+        ScreenCaptureConfigBuilder::new()
+            .fps(fps)
+            .idle(IdleConfig { enabled: true, idle_fps, idle_after_ms: 2000 })
+            .build()
+    }
+
+    #[test]
+    fn test_decimate_filter() {
+        assert_eq!(decimate_filter(&config(30, 2)).as_deref(), Some("mpdecimate=max=14"));
+        assert_eq!(decimate_filter(&config(30, 30)), None);
+        assert_eq!(decimate_filter(&config(30, 0)), None);
+
+        let mut disabled = config(30, 2);
+        disabled.idle.enabled = false;
+        assert_eq!(decimate_filter(&disabled), None);
+    }
+
+    #[test]
+    fn test_state_transitions() {
+        // 30 fps, keepalive every 500 ms
+        let mut detector = IdleDetector::new(&config(30, 2));
+        assert_eq!(detector.state(), CaptureActivity::Active);
+
+        // Changing screen at full rate
+        let mut t = 0;
+        for _ in 0..30 {
+            assert_eq!(detector.on_frame(t), None);
+            t += 33;
+        }
+
+        // Static screen: keepalives only, idle once 2 s passed without a change
+        let last_change = t - 33;
+        let mut became_idle_at = None;
+        while became_idle_at.is_none() {
+            t += 500;
+            if detector.on_frame(t) == Some(CaptureActivity::Idle) {
+                became_idle_at = Some(t);
+            }
+        }
+        assert!(became_idle_at.unwrap() - last_change >= 2000);
+        assert!(!detector.quality_control_enabled());
+
+        // More keepalives keep it idle
+        t += 500;
+        assert_eq!(detector.on_frame(t), None);
+
+        // The first changed frame ramps back up
+        t += 120;
+        assert_eq!(detector.on_frame(t), Some(CaptureActivity::Active));
+        assert!(detector.quality_control_enabled());
+        t += 33;
+        assert_eq!(detector.on_frame(t), None);
+    }
+
+    #[test]
+    fn test_restart_counts_as_change() {
+        let mut detector = IdleDetector::new(&config(30, 2));
+        let mut t = 0;
+        while detector.state() == CaptureActivity::Active {
+            t += 500;
+            detector.on_frame(t);
+        }
+        assert_eq!(detector.on_frame(0), Some(CaptureActivity::Active));
+    }
+
+    #[test]
+    fn test_change_signal_drives_transitions() {
+        let mut detector = IdleDetector::new(&config(30, 2));
+        assert_eq!(detector.on_change_signal(0, true), None);
+
+        // Keepalives at the full rate still count as unchanged
+        let mut t = 0;
+        while detector.state() == CaptureActivity::Active {
+            t += 33;
+            detector.on_change_signal(t, false);
+        }
+        assert!(t >= 2000);
+        assert_eq!(detector.on_change_signal(t + 33, true), Some(CaptureActivity::Active));
+    }
+
+    #[test]
+    fn test_disabled_stays_active() {
+        let mut detector = IdleDetector::new(&config(30, 30));
+        for t in (0..10_000).step_by(500) {
+            assert_eq!(detector.on_frame(t), None);
+        }
+        assert_eq!(detector.state(), CaptureActivity::Active);
+    }
+}
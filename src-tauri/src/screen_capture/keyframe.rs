@@ -0,0 +1,123 @@
+// screen_capture/keyframe.rs - Keyframes forced on request, e.g. when a viewer joins
+
+use std::time::{Duration, Instant};
+
+/// Minimum time between two forced keyframes. Every forced keyframe restarts
+/// the encoder, so a client requesting them in a loop must not be able to
+/// ruin compression for everyone.
+pub const MIN_FORCED_KEYFRAME_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Schedules forced keyframes for a capture loop.
+///
+/// ffmpeg cannot be told to emit an IDR frame while running, but every new
+/// process starts its stream with one. A request is therefore served by
+/// restarting the encoder, at most once per `MIN_FORCED_KEYFRAME_INTERVAL`.
+/// Requests inside that window are deferred, not dropped, and a keyframe the
+/// encoder emits on its own in the meantime satisfies them.
+#[derive(Debug, Default)]
+pub struct KeyframeScheduler {
+    /// A keyframe was requested and none was emitted since
+    pending: bool,
+
+    /// Start of the last encoder process (each one begins with a keyframe)
+    last_forced: Option<Instant>,
+
+    /// Last keyframe seen in the stream
+    last_keyframe: Option<Instant>,
+}
+
+impl KeyframeScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a request; returns how long until the keyframe is forced
+    pub fn request(&mut self, now: Instant) -> Duration {
+        self.pending = true;
+        self.remaining_wait(now)
+    }
+
+    /// Whether the capture loop should restart the encoder now
+    pub fn take_due(&mut self, now: Instant) -> bool {
+        if !self.pending || !self.remaining_wait(now).is_zero() {
+            return false;
+        }
+        self.pending = false;
+        true
+    }
+
+    /// A new encoder process was started, on request or after a crash
+    pub fn encoder_started(&mut self, now: Instant) {
+        self.pending = false;
+        self.last_forced = Some(now);
+    }
+
+    /// A keyframe left the encoder
+    pub fn keyframe_emitted(&mut self, now: Instant) {
+        self.pending = false;
+        self.last_keyframe = Some(now);
+    }
+
+    /// Time since the last keyframe; None before the first one
+    pub fn since_last_keyframe(&self, now: Instant) -> Option<Duration> {
+        self.last_keyframe.map(|last| now.saturating_duration_since(last))
+    }
+
+    fn remaining_wait(&self, now: Instant) -> Duration {
+        match self.last_forced {
+            Some(last) => MIN_FORCED_KEYFRAME_INTERVAL.saturating_sub(now.saturating_duration_since(last)),
+            None => Duration::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forced_keyframes_are_rate_limited() {
+        let start = Instant::now();
+        let mut scheduler = KeyframeScheduler::new();
+        scheduler.encoder_started(start);
+
+        // Right after the start the request has to wait for the rest of the second
+        let wait = scheduler.request(start + Duration::from_millis(200));
+        assert_eq!(wait, Duration::from_millis(800));
+        assert!(!scheduler.take_due(start + Duration::from_millis(500)));
+
+        // Repeated requests do not queue up more restarts
+        scheduler.request(start + Duration::from_millis(600));
+        assert!(scheduler.take_due(start + Duration::from_secs(1)));
+        assert!(!scheduler.take_due(start + Duration::from_secs(1)));
+
+        scheduler.encoder_started(start + Duration::from_secs(1));
+        scheduler.request(start + Duration::from_millis(1100));
+        assert!(!scheduler.take_due(start + Duration::from_millis(1900)));
+        assert!(scheduler.take_due(start + Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_emitted_keyframe_satisfies_request() {
+        let start = Instant::now();
+        let mut scheduler = KeyframeScheduler::new();
+        assert_eq!(scheduler.since_last_keyframe(start), None);
+
+        scheduler.encoder_started(start);
+        scheduler.request(start + Duration::from_millis(100));
+        scheduler.keyframe_emitted(start + Duration::from_millis(300));
+        assert!(!scheduler.take_due(start + Duration::from_secs(2)));
+        assert_eq!(
+            scheduler.since_last_keyframe(start + Duration::from_millis(500)),
+            Some(Duration::from_millis(200))
+        );
+    }
+
+    #[test]
+    fn test_first_request_is_immediate() {
+        let now = Instant::now();
+        let mut scheduler = KeyframeScheduler::new();
+        assert_eq!(scheduler.request(now), Duration::ZERO);
+        assert!(scheduler.take_due(now));
+    }
+}
@@ -1,33 +1,78 @@
 // screen_capture/manager.rs - Screen capture manager implementation
 
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::thread;
+use serde::Serialize;
 use tauri::Window;
 
-use crate::screen_capture::types::{DisplayServer, CaptureStats, MonitorInfo, FrameData, ScreenCapturer, MonitorDetector};
+use crate::screen_capture::types::{DisplayServer, CaptureStats, CaptureActivity, CaptureErrorReport, CaptureEvent, CaptureEventSink, CaptureRegion, CursorMode, MonitorInfo, FrameData, FrameTransport, ScreenCapturer, MonitorDetector};
 use crate::screen_capture::error::ScreenCaptureError;
-use crate::screen_capture::config::ScreenCaptureConfig;
-use crate::screen_capture::buffer::{StreamBuffer, DropMode};
+use crate::screen_capture::config::{OutputResolution, ScreenCaptureConfig};
+use crate::screen_capture::validation::ConfigIssue;
+use crate::screen_capture::encoders::CapabilityMatrix;
+use crate::screen_capture::buffer::{StreamBuffer, DropMode, BufferConfig, BufferStats};
+use crate::screen_capture::stats_history::{StatsHistory, StatsHistoryRange, StatsSample};
 use crate::screen_capture::quality::AdaptiveQualityController;
+use crate::screen_capture::keyframe::KeyframeScheduler;
+use crate::screen_capture::frame_stream::{FrameStreamServer, ThroughputMeter};
+use crate::screen_capture::cursor::{CursorSink, CursorSource, CursorTracker};
+use crate::screen_capture::pointer_feed::PointerFeed;
+use crate::screen_capture::recording_cursor::{CursorTrack, CursorTrackRecording, RecordingCursorConfig};
+use crate::screen_capture::privacy::{
+    HyprlandWindowSource, PrivacyMask, PrivacyMaskEntry, PrivacyMasks, WindowMaskWatcher, WindowSource, X11WindowSource,
+};
 use crate::screen_capture::x11::{X11ScreenCapturer, X11MonitorDetector, get_x11_monitors};
-use crate::screen_capture::wayland::{WaylandScreenCapturer, WaylandMonitorDetector, get_wayland_monitors};
+use crate::screen_capture::wayland::{
+    WaylandScreenCapturer, WaylandMonitorDetector, get_wayland_monitors,
+    WaylandCompositor, detect_wayland_compositor, select_capture_backend,
+};
+use crate::screen_capture::virtual_display::{VirtualDisplay, VirtualDisplayManager};
+use crate::screen_capture::resume::{self, ResumeChange, ResumeReport, SuspendedCapture};
+use crate::screen_capture::power_profile::{self, PowerProfile, PowerProfileChange};
+use crate::screen_capture::viewport::{QualityChange, ViewerViewport, ViewerViewports};
+use crate::screen_capture::thumbnail::{self, ThumbnailGrabber, ThumbnailTargets};
 use crate::screen_capture::utils;
+use crate::command_runner::SystemCommandRunner;
+use crate::host_session::HostSessionMonitor;
+use crate::power::PowerSource;
+use crate::stats::FrameLatencyTracker;
+use crate::logging::TARGET_SCREEN_CAPTURE;
 
-/// Screen capture manager
-pub struct ScreenCaptureManager {
-    /// Current display server type
-    display_server: DisplayServer,
-    
-    /// Screen capture configuration
+/// Default limit of monitors captured at the same time
+pub const DEFAULT_MAX_SIMULTANEOUS_CAPTURES: usize = 2;
+
+/// Payload of per-monitor events, so the frontend can route them to the right track
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorEvent<T: Serialize> {
+    pub monitor_index: usize,
+    #[serde(flatten)]
+    pub payload: T,
+}
+
+/// Base64 frame for the `frame_data` event fallback
+#[derive(Debug, Clone, Serialize)]
+pub struct FrameEventData {
+    pub data: String,
+}
+
+/// WebSocket endpoint announced with `frame_stream_ready`
+#[derive(Debug, Clone, Serialize)]
+pub struct FrameStreamInfo {
+    pub url: String,
+}
+
+/// One running capture of a monitor with its own buffer, quality
+/// controller and statistics
+struct CaptureSession {
+    /// Configuration of this capture
     config: Arc<Mutex<ScreenCaptureConfig>>,
     
-    /// Available monitors
-    monitors: Vec<MonitorInfo>,
-    
     /// Statistics
     stats: Arc<Mutex<CaptureStats>>,
     
-    /// Whether capture is running
+    /// Whether the event fallback sender should keep running
     running: Arc<Mutex<bool>>,
     
     /// Stream buffer
@@ -36,8 +81,117 @@ pub struct ScreenCaptureManager {
     /// Quality controller
     quality_controller: Arc<Mutex<AdaptiveQualityController>>,
     
+    /// Forced keyframes for viewers joining mid-stream
+    keyframes: Arc<Mutex<KeyframeScheduler>>,
+    
     /// The actual screen capturer implementation
-    capturer: Option<Box<dyn ScreenCapturer>>,
+    capturer: Box<dyn ScreenCapturer>,
+    
+    /// Last error reported by the capture process
+    last_error: Arc<Mutex<Option<CaptureErrorReport>>>,
+    
+    /// Binary frame transport, if enabled and started
+    frame_stream: Option<FrameStreamServer>,
+    
+    /// Cursor poller for the client-side cursor mode
+    cursor_tracker: Option<CursorTracker>,
+    
+    /// Geometry poller for windows masked by class
+    window_masks: Option<WindowMaskWatcher>,
+    
+    /// Window the capture events go to, kept to restart the capture after a
+    /// suspend (None for sessions built in tests)
+    window: Option<Window>,
+    
+    /// Power profile whose limits the config carries
+    power_profile: PowerProfile,
+}
+
+impl CaptureSession {
+    fn stop(&mut self) -> Result<(), ScreenCaptureError> {
+        *self.running.lock().unwrap() = false;
+        
+        // Stop sending cursor positions
+        if let Some(mut tracker) = self.cursor_tracker.take() {
+            tracker.stop();
+        }
+        
+        // Stop following masked windows
+        if let Some(mut watcher) = self.window_masks.take() {
+            watcher.stop();
+        }
+        
+        // Disconnect the frontend from the binary frame stream
+        if let Some(mut frame_stream) = self.frame_stream.take() {
+            frame_stream.stop();
+        }
+        
+        self.capturer.stop_capture()
+    }
+}
+
+/// A manager that is dropped, e.g. when it is reinitialized, must not leave
+/// capture processes and their threads behind
+impl Drop for CaptureSession {
+    fn drop(&mut self) {
+        if !*self.running.lock().unwrap() {
+            return;
+        }
+        if let Err(e) = self.stop() {
+            log::warn!(target: TARGET_SCREEN_CAPTURE, "Failed to stop capture on drop: {}", e);
+        }
+    }
+}
+
+/// Screen capture manager: one capture session per shared monitor
+pub struct ScreenCaptureManager {
+    /// Current display server type
+    display_server: DisplayServer,
+    
+    /// Available monitors
+    monitors: Vec<MonitorInfo>,
+    
+    /// Last configuration per monitor, kept across stop/start
+    configs: HashMap<usize, ScreenCaptureConfig>,
+    
+    /// Running captures by monitor index
+    sessions: HashMap<usize, CaptureSession>,
+    
+    /// Upper bound for `sessions`
+    max_simultaneous_captures: usize,
+    
+    /// Buffer settings applied to new sessions (None = defaults from the config)
+    buffer_config: Option<BufferConfig>,
+    
+    /// Forwarded pointer input, shared with the input path
+    pointer_feed: PointerFeed,
+    
+    /// Virtual outputs for extend-display use
+    virtual_displays: VirtualDisplayManager,
+    
+    /// Frames are withheld from every capture while set
+    output_blanked: bool,
+    
+    /// Codec and hardware acceleration combinations that passed a test encode
+    encoders: CapabilityMatrix,
+    
+    /// Lock and DPMS state reported with the stats
+    host_session: Option<Arc<HostSessionMonitor>>,
+    
+    /// Per-second stats of all captures over the last 15 minutes
+    stats_history: StatsHistory,
+    
+    /// AC or battery; captures run with the limits of the matching profile
+    power_source: PowerSource,
+    
+    /// Timestamps of the frames sent on frame streams
+    frame_latency: Arc<Mutex<FrameLatencyTracker>>,
+    
+    /// Window sizes reported by the viewers; captures are fitted to the largest
+    viewports: ViewerViewports,
+    
+    /// Still images for the monitor picker, shared with a thumbnail subscription
+    thumbnails: ThumbnailGrabber,
 }
 
 impl ScreenCaptureManager {
@@ -57,39 +211,32 @@ impl ScreenCaptureManager {
             }
         }?;
         
-        // Create default configuration
-        let default_config = ScreenCaptureConfig::default();
-        
-        // Create quality controller with default configuration
-        let quality_controller = AdaptiveQualityController::new(default_config.quality, None);
-        
-        // Create stream buffer
-        // Buffer size based on FPS and latency target (e.g., 3 seconds of frames)
-        let buffer_size = (default_config.fps * 3) as usize;
-        let stream_buffer = StreamBuffer::new(buffer_size, 10, default_config.fps, DropMode::DropOldest);
+        let virtual_displays = VirtualDisplayManager::new(display_server.clone());
+        let thumbnails = ThumbnailGrabber::new(Arc::new(SystemCommandRunner), display_server.clone());
         
-        // Create default stats
-        let stats = CaptureStats {
-            fps: 0.0,
-            bitrate: 0,
-            encode_time: 0.0,
-            frame_size: 0,
-            frame_count: 0,
-            dropped_frames: 0,
-            buffer_level: 0,
-            latency_estimate: 0.0,
-        };
+        // Probe the encoders once; offering untested ones breaks the stream later
+        let encoders = CapabilityMatrix::probe();
         
-        Ok(ScreenCaptureManager {
+        let manager = ScreenCaptureManager {
             display_server,
-            config: Arc::new(Mutex::new(default_config)),
             monitors,
-            stats: Arc::new(Mutex::new(stats)),
-            running: Arc::new(Mutex::new(false)),
-            stream_buffer: Arc::new(Mutex::new(stream_buffer)),
-            quality_controller: Arc::new(Mutex::new(quality_controller)),
-            capturer: None,
-        })
+            configs: HashMap::new(),
+            sessions: HashMap::new(),
+            max_simultaneous_captures: DEFAULT_MAX_SIMULTANEOUS_CAPTURES,
+            buffer_config: None,
+            pointer_feed: PointerFeed::new(),
+            virtual_displays,
+            output_blanked: false,
+            encoders,
+            host_session: None,
+            stats_history: StatsHistory::new(),
+            power_source: PowerSource::default(),
+            frame_latency: Arc::new(Mutex::new(FrameLatencyTracker::new())),
+            viewports: ViewerViewports::new(),
+            thumbnails,
+        };
+        manager.update_pointer_feed();
+        Ok(manager)
     }
     
     /// Get detected display server
@@ -97,6 +244,11 @@ impl ScreenCaptureManager {
         self.display_server.clone()
     }
     
+    /// Codec and hardware acceleration combinations usable on this system
+    pub fn get_encoder_capabilities(&self) -> &CapabilityMatrix {
+        &self.encoders
+    }
+    
     /// Get available monitors
     pub fn get_monitors(&self) -> Vec<MonitorInfo> {
         self.monitors.clone()
@@ -114,120 +266,272 @@ impl ScreenCaptureManager {
             }
         }?;
         
+        self.update_pointer_feed();
         Ok(())
     }
     
-    /// Update capture configuration
-    pub fn update_config(&self, config: ScreenCaptureConfig) -> Result<(), ScreenCaptureError> {
+    /// Report the host's lock and DPMS state in the capture stats
+    pub fn set_host_session_monitor(&mut self, monitor: Arc<HostSessionMonitor>) {
+        self.host_session = Some(monitor);
+    }
+    
+    /// Take forwarded pointer input from `feed`, which outlives a
+    /// reinitialized manager
+    pub fn set_pointer_feed(&mut self, feed: PointerFeed) {
+        self.pointer_feed = feed;
+        self.update_pointer_feed();
+    }
+    
+    /// Record the frames sent on frame streams in `tracker`
+    pub fn set_frame_latency(&mut self, tracker: Arc<Mutex<FrameLatencyTracker>>) {
+        self.frame_latency = tracker;
+    }
+    
+    /// Remember virtual outputs in `data_dir` and remove leftovers of a crashed session
+    pub fn set_state_dir(&mut self, data_dir: &Path) -> Result<(), ScreenCaptureError> {
+        self.virtual_displays.set_state_dir(data_dir)?;
+        self.refresh_monitors()
+    }
+    
+    /// Add a virtual output and return it as a capturable monitor
+    pub fn create_virtual_display(&mut self, width: u32, height: u32, refresh_rate: u32) -> Result<MonitorInfo, ScreenCaptureError> {
+        let display = self.virtual_displays.create(width, height, refresh_rate)?;
+        self.refresh_monitors()?;
+        
+        self.monitors.iter()
+            .find(|monitor| monitor.name == display.output)
+            .cloned()
+            .ok_or_else(|| ScreenCaptureError::VirtualDisplayError(format!(
+                "Virtual display {} does not show up in the monitor list", display.output
+            )))
+    }
+    
+    /// Stop capturing a virtual output and remove it
+    pub fn destroy_virtual_display(&mut self, id: &str) -> Result<(), ScreenCaptureError> {
+        let output = self.virtual_displays.list().into_iter()
+            .find(|display| display.id == id)
+            .map(|display| display.output);
+        
+        if let Some(index) = output.and_then(|name| self.monitors.iter().position(|monitor| monitor.name == name)) {
+            self.stop_capture(Some(index))?;
+            self.configs.remove(&index);
+        }
+        
+        self.virtual_displays.destroy(id)?;
+        self.refresh_monitors()
+    }
+    
+    /// Virtual outputs created in this session
+    pub fn get_virtual_displays(&self) -> Vec<VirtualDisplay> {
+        self.virtual_displays.list()
+    }
+    
+    /// Remove all virtual outputs, e.g. on exit
+    pub fn destroy_virtual_displays(&mut self) {
+        self.virtual_displays.destroy_all();
+    }
+    
+    /// Limit how many monitors may be captured at once
+    pub fn set_max_simultaneous_captures(&mut self, limit: usize) -> Result<(), ScreenCaptureError> {
+        if limit == 0 {
+            return Err(ScreenCaptureError::TooManyCaptures(
+                "The limit must allow at least one capture".to_string(),
+            ));
+        }
+        self.max_simultaneous_captures = limit;
+        Ok(())
+    }
+    
+    /// Monitor indices that are being captured
+    pub fn active_captures(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = self.sessions.keys().copied().collect();
+        indices.sort_unstable();
+        indices
+    }
+    
+    /// Monitors a thumbnail request may grab: `monitor_index` or all of them.
+    /// Captured monitors are refused or skipped unless `force` is set.
+    pub fn thumbnail_targets(&self, monitor_index: Option<usize>, force: bool) -> Result<ThumbnailTargets, ScreenCaptureError> {
+        thumbnail::select_targets(&self.monitors, &self.active_captures(), monitor_index, force)
+    }
+    
+    /// Grabber for thumbnails; grab with it after releasing the manager
+    pub fn thumbnail_grabber(&self) -> ThumbnailGrabber {
+        self.thumbnails.clone()
+    }
+    
+    /// Check a configuration against the monitor list
+    fn validate_config(&self, config: &ScreenCaptureConfig) -> Result<(), ScreenCaptureError> {
         // Validate monitor index
         if config.monitor_index >= self.monitors.len() {
             return Err(ScreenCaptureError::InvalidMonitor(format!(
                 "Monitor index {} out of bounds (0-{})",
                 config.monitor_index,
-                self.monitors.len() - 1
+                self.monitors.len().saturating_sub(1)
             )));
         }
         
-        // Update buffer size if FPS changed
-        {
-            let mut current_config = self.config.lock().unwrap();
-            let old_fps = current_config.fps;
-            
-            if old_fps != config.fps {
-                let mut buffer = self.stream_buffer.lock().unwrap();
-                buffer.set_fps(config.fps);
-            }
-            
-            *current_config = config;
+        // The region must fit the selected monitor
+        if let Some(region) = &config.capture_region {
+            region.validate(&self.monitors[config.monitor_index])?;
         }
         
-        // If already running, restart capture with new config
-        let is_running = *self.running.lock().unwrap();
-        if is_running {
-            self.restart_capture()?;
+        let errors: Vec<String> = config.validate(&self.monitors).iter()
+            .filter(|issue| issue.is_error())
+            .map(ToString::to_string)
+            .collect();
+        if !errors.is_empty() {
+            return Err(ScreenCaptureError::InvalidConfig(errors.join("; ")));
         }
         
-        Ok(())
+        let backend = self.encoders.select_backend(config, &self.display_server);
+        self.encoders.check_backend(config, &backend)
     }
     
-    /// Restart the capture with new configuration
-    fn restart_capture(&self) -> Result<(), ScreenCaptureError> {
-        // This is a simplified implementation - in a real app, you'd want to preserve
-        // the window handle and restart more gracefully
-        
-        // Stop existing capture
-        if let Some(capturer) = &self.capturer {
-            capturer.stop_capture()?;
+    /// All problems with a configuration, including encoders that did not pass the probe
+    pub fn validate_capture_config(&self, config: &ScreenCaptureConfig) -> Vec<ConfigIssue> {
+        let mut issues = config.validate(&self.monitors);
+        let backend = self.encoders.select_backend(config, &self.display_server);
+        if let Err(e) = self.encoders.check_backend(config, &backend) {
+            issues.push(ConfigIssue::unsupported_encoder(config, &e));
         }
+        issues
+    }
+    
+    /// Start capturing `config.monitor_index`. A running capture of the
+    /// same monitor is restarted with the new configuration.
+    pub fn start_capture(&mut self, config: ScreenCaptureConfig, window: Window) -> Result<(), ScreenCaptureError> {
+        self.validate_config(&config)?;
+        let monitor_index = config.monitor_index;
         
-        // Note: In a fully implemented version, you'd recreate the capturer with the 
-        // new configuration and restart it. Since we don't have access to the window
-        // handle at this point in the code, we'll let the caller handle restart.
-        
-        // Set running to false to indicate we need a full restart
-        {
-            let mut running = self.running.lock().unwrap();
-            *running = false;
+        // Restart instead of running two captures of one monitor; a cursor
+        // track being recorded carries over to the new capture
+        if let Some(mut session) = self.sessions.remove(&monitor_index) {
+            session.stop()?;
+        } else if self.sessions.len() >= self.max_simultaneous_captures {
+            return Err(ScreenCaptureError::TooManyCaptures(format!(
+                "{} of {} captures are running, stop one before sharing monitor {}",
+                self.sessions.len(), self.max_simultaneous_captures, monitor_index
+            )));
         }
         
+        self.configs.insert(monitor_index, config.clone());
+        let session = self.create_session(config, window)?;
+        self.sessions.insert(monitor_index, session);
+        
+        self.update_pointer_feed();
         Ok(())
     }
     
-    /// Start screen capture
-    pub fn start_capture(&mut self, window: Window) -> Result<(), ScreenCaptureError> {
-        // Check if already running
-        {
-            let mut running = self.running.lock().unwrap();
-            if *running {
-                return Ok(());
+    fn create_session(&self, config: ScreenCaptureConfig, window: Window) -> Result<CaptureSession, ScreenCaptureError> {
+        let monitor_index = config.monitor_index;
+        
+        // Get the monitor to capture
+        let monitor = self.monitors[monitor_index].clone();
+        
+        // The capture runs within the limits of the current power profile
+        let power_profile = PowerProfile::for_source(&self.power_source, &config.power_profiles);
+        let limits = power_profile::limits_for(&config, power_profile);
+        let mut config = config;
+        power_profile::apply_limits(&config.clone(), &limits, &mut config);
+        
+        // Create quality controller for this capture
+        let quality_controller = Arc::new(Mutex::new(AdaptiveQualityController::new(config.quality, None)));
+        quality_controller.lock().unwrap().set_bitrate_ceiling(limits.max_bitrate_kbps);
+        
+        // Create stream buffer
+        // Buffer size based on FPS and latency target (e.g., 3 seconds of frames)
+        let buffer_size = (config.fps * 3) as usize;
+        let mut stream_buffer = StreamBuffer::new(buffer_size, 10, config.fps, DropMode::DropOldest);
+        if let Some(buffer_config) = &self.buffer_config {
+            stream_buffer.configure(buffer_config);
+            if let Some(max_latency_ms) = buffer_config.max_latency_ms {
+                quality_controller.lock().unwrap().set_target_latency(max_latency_ms);
             }
-            *running = true;
         }
+        stream_buffer.set_blanked(self.output_blanked);
+        let stream_buffer = Arc::new(Mutex::new(stream_buffer));
         
-        // Get current configuration
-        let config = self.config.clone();
-        let config_guard = config.lock().unwrap();
-        let monitor_index = config_guard.monitor_index;
-        drop(config_guard);
+        // Create default stats
+        let stats = Arc::new(Mutex::new(CaptureStats {
+            monitor_index,
+            fps: 0.0,
+            bitrate: 0,
+            encode_time: 0.0,
+            frame_size: 0,
+            frame_count: 0,
+            dropped_frames: 0,
+            buffer_level: 0,
+            latency_estimate: 0.0,
+            restart_count: 0,
+            hardware_acceleration_fallback: None,
+            transport_throughput: 0,
+            activity: CaptureActivity::Active,
+            time_since_keyframe_ms: None,
+            host_session: None,
+            skipped_frames: 0,
+            freeze_recoveries: 0,
+            gpu: None,
+        }));
         
-        // Check if monitor index is valid
-        if monitor_index >= self.monitors.len() {
-            return Err(ScreenCaptureError::InvalidMonitor(format!(
-                "Monitor index {} out of bounds (0-{})",
-                monitor_index,
-                self.monitors.len() - 1
-            )));
-        }
+        let keyframes = Arc::new(Mutex::new(KeyframeScheduler::new()));
         
-        // Get the monitor to capture
-        let monitor = self.monitors[monitor_index].clone();
+        let cursor_mode = config.cursor_mode();
+        let transport = config.frame_transport;
+        let encoder_backend = self.encoders.select_backend(&config, &self.display_server);
+        let window_patterns = config.privacy.window_patterns()?;
+        let config = Arc::new(Mutex::new(config));
         
-        // Clear stream buffer
-        {
-            let mut buffer = self.stream_buffer.lock().unwrap();
-            buffer.clear();
-        }
+        // Forward supervisor events of the capture process to the frontend
+        let last_error = Arc::new(Mutex::new(None));
+        let event_last_error = last_error.clone();
+        let event_window = window.clone();
+        let event_sink: CaptureEventSink = Arc::new(move |event: CaptureEvent| {
+            let name = match &event {
+                CaptureEvent::Interrupted { .. } => "capture_interrupted",
+                CaptureEvent::Resumed { .. } => "capture_resumed",
+                CaptureEvent::HardwareAccelerationDisabled { .. } => "capture_hardware_acceleration_disabled",
+                CaptureEvent::Error(report) => {
+                    *event_last_error.lock().unwrap() = Some(report.clone());
+                    "capture_error"
+                },
+                CaptureEvent::Failed { .. } => "capture_failed",
+                CaptureEvent::ActivityChanged { .. } => "capture_activity_changed",
+                CaptureEvent::Frozen { .. } => "capture_frozen",
+            };
+            let _ = event_window.emit(name, MonitorEvent { monitor_index, payload: event });
+        });
         
         // Create capturer based on display server
-        let capturer: Box<dyn ScreenCapturer> = match self.display_server {
+        let mut capturer: Box<dyn ScreenCapturer> = match self.display_server {
             DisplayServer::X11 => {
                 let x11_capturer = X11ScreenCapturer::new(
-                    self.config.clone(),
+                    config.clone(),
                     monitor,
-                    self.stream_buffer.clone(),
-                    self.quality_controller.clone(),
-                    self.stats.clone()
+                    stream_buffer.clone(),
+                    quality_controller.clone(),
+                    stats.clone(),
+                    keyframes.clone(),
+                    event_sink.clone()
                 )?;
                 
                 Box::new(x11_capturer)
             },
             DisplayServer::Wayland => {
+                // GNOME/KDE go through the ScreenCast portal, wlroots through ffmpeg
+                let backend = select_capture_backend(&detect_wayland_compositor());
+                log::info!(target: TARGET_SCREEN_CAPTURE, "Capturing monitor {} via {:?}, encoding with {:?}", monitor_index, backend, encoder_backend);
+                
                 let wayland_capturer = WaylandScreenCapturer::new(
-                    self.config.clone(),
+                    config.clone(),
                     monitor,
-                    self.stream_buffer.clone(),
-                    self.quality_controller.clone(),
-                    self.stats.clone()
+                    stream_buffer.clone(),
+                    quality_controller.clone(),
+                    stats.clone(),
+                    keyframes.clone(),
+                    backend,
+                    encoder_backend,
+                    event_sink.clone()
                 )?;
                 
                 Box::new(wayland_capturer)
@@ -242,19 +546,79 @@ impl ScreenCaptureManager {
         // Start the capture
         capturer.start_capture()?;
         
-        // Store the capturer
-        self.capturer = Some(capturer);
+        let mut session = CaptureSession {
+            config,
+            stats,
+            running: Arc::new(Mutex::new(true)),
+            stream_buffer,
+            quality_controller,
+            keyframes,
+            capturer,
+            last_error,
+            frame_stream: None,
+            cursor_tracker: None,
+            window_masks: None,
+            window: Some(window.clone()),
+            power_profile,
+        };
+        
+        // Mask windows by class wherever they are moved
+        if !window_patterns.is_empty() {
+            match self.window_source() {
+                Some(source) => {
+                    session.window_masks = Some(WindowMaskWatcher::start(
+                        source,
+                        window_patterns,
+                        self.monitors[monitor_index].clone(),
+                        session.config.clone(),
+                        session.keyframes.clone(),
+                    ));
+                },
+                None => {
+                    log::warn!(target: TARGET_SCREEN_CAPTURE, "Window masks are not supported on this compositor, only fixed masks apply");
+                }
+            }
+        }
+        
+        // Client-side cursor: the video has none, positions go out as events
+        if cursor_mode == CursorMode::ClientSide {
+            let cursor_window = window.clone();
+            let sink: CursorSink = Arc::new(move |position| {
+                let _ = cursor_window.emit("cursor_position", MonitorEvent { monitor_index, payload: position });
+            });
+            // Positions are reported relative to the captured region
+            let monitor = self.get_input_monitors()[monitor_index].clone();
+            session.cursor_tracker = Some(CursorTracker::start(self.cursor_source(), monitor, sink));
+        }
+        
+        // Prefer the binary WebSocket transport; base64 events are the fallback
+        if transport == FrameTransport::WebSocket {
+            match FrameStreamServer::start(session.stream_buffer.clone(), session.stats.clone(), self.frame_latency.clone()) {
+                Ok(server) => {
+                    let info = FrameStreamInfo { url: server.url() };
+                    let _ = window.emit("frame_stream_ready", MonitorEvent { monitor_index, payload: info });
+                    session.frame_stream = Some(server);
+                    return Ok(session);
+                },
+                Err(e) => {
+                    log::warn!(target: TARGET_SCREEN_CAPTURE, "Frame stream unavailable, falling back to frame_data events: {}", e);
+                }
+            }
+        }
         
         // Create a listener for frontend frame requests
-        let stream_buffer = self.stream_buffer.clone();
+        let stream_buffer = session.stream_buffer.clone();
+        let running = session.running.clone();
+        let stats = session.stats.clone();
         let _window = window.clone();
         
         // Optionally set up a thread to periodically send frames to the UI
         // This is only needed if the UI needs regular updates without explicit requests
         let _frame_sender_thread = thread::spawn(move || {
             let mut last_frame_time = std::time::Instant::now();
+            let mut meter = ThroughputMeter::new();
             
-            while _window.is_visible().unwrap_or(false) {
+            while *running.lock().unwrap() && _window.is_visible().unwrap_or(false) {
                 // Rate limit to avoid overwhelming the UI
                 let elapsed = last_frame_time.elapsed();
                 if elapsed < std::time::Duration::from_millis(33) {  // ~30 FPS for UI updates
@@ -269,47 +633,588 @@ impl ScreenCaptureManager {
                 
                 // Send to UI
                 if let Some(frame_data) = frame_preview {
-                    let _ = _window.emit("frame_data", utils::frame_to_base64(&frame_data));
+                    let data = utils::frame_to_base64(&frame_data);
+                    let len = data.len();
+                    let payload = MonitorEvent { monitor_index, payload: FrameEventData { data } };
+                    if _window.emit("frame_data", payload).is_ok() {
+                        if let Some(rate) = meter.record(len) {
+                            stats.lock().unwrap().transport_throughput = rate;
+                        }
+                    }
                 }
                 
                 last_frame_time = std::time::Instant::now();
             }
         });
         
+        Ok(session)
+    }
+    
+    /// Stop capturing one monitor, or all of them
+    pub fn stop_capture(&mut self, monitor_index: Option<usize>) -> Result<(), ScreenCaptureError> {
+        let indices = match monitor_index {
+            Some(index) => vec![index],
+            None => self.active_captures(),
+        };
+        
+        for index in indices {
+            // A cursor track ends with its capture
+            self.pointer_feed.finish_track(index);
+            if let Some(mut session) = self.sessions.remove(&index) {
+                session.stop()?;
+            }
+        }
+        
+        self.update_pointer_feed();
+        Ok(())
+    }
+    
+    /// Stop every capture before the host suspends. The returned captures
+    /// are started again by `resume_captures` once the host is awake.
+    pub fn suspend_captures(&mut self) -> Vec<SuspendedCapture> {
+        let mut suspended = Vec::new();
+        
+        for index in self.active_captures() {
+            self.pointer_feed.finish_track(index);
+            if let Some(mut session) = self.sessions.remove(&index) {
+                // The capture process dies with the suspend anyway
+                if let Err(e) = session.stop() {
+                    log::warn!(target: TARGET_SCREEN_CAPTURE, "Failed to stop capture of monitor {} before suspend: {}", index, e);
+                }
+                // The configuration as set, without the limits of the power profile
+                let config = self.configs.get(&index).cloned()
+                    .unwrap_or_else(|| session.config.lock().unwrap().clone());
+                suspended.push(SuspendedCapture {
+                    monitor_name: self.monitors.get(index).map(|monitor| monitor.name.clone()).unwrap_or_default(),
+                    config,
+                    window: session.window.clone(),
+                });
+            }
+        }
+        
+        self.update_pointer_feed();
+        suspended
+    }
+    
+    /// Detect the monitors again after a resume and restart the suspended
+    /// captures on the monitors with the same names (see `resume`)
+    pub fn resume_captures(&mut self, suspended: Vec<SuspendedCapture>) -> ResumeReport {
+        let previous_monitors = self.monitors.clone();
+        if let Err(e) = self.refresh_monitors() {
+            log::error!(target: TARGET_SCREEN_CAPTURE, "Failed to detect monitors after resume, keeping the old list: {}", e);
+        }
+        
+        let monitors_changed = resume::monitors_changed(&previous_monitors, &self.monitors);
+        if monitors_changed {
+            // Regions and settings of idle monitors refer to the old numbering
+            self.configs.clear();
+        }
+        
+        let captures: Vec<(String, ScreenCaptureConfig)> = suspended.iter()
+            .map(|capture| (capture.monitor_name.clone(), capture.config.clone()))
+            .collect();
+        let plan = resume::plan_resume(&captures, &self.monitors);
+        
+        let mut report = ResumeReport { monitors_changed, restarted: Vec::new(), changes: plan.changes };
+        for (position, config) in plan.restarts {
+            let capture = &suspended[position];
+            let monitor_index = config.monitor_index;
+            let result = match &capture.window {
+                Some(window) => self.start_capture(config, window.clone()).map_err(|e| e.to_string()),
+                None => Err("No window to send the capture to".to_string()),
+            };
+            match result {
+                Ok(()) => report.restarted.push(monitor_index),
+                Err(reason) => report.changes.push(ResumeChange::NotRestarted {
+                    monitor_name: capture.monitor_name.clone(),
+                    from: capture.config.monitor_index,
+                    reason,
+                }),
+            }
+        }
+        
+        report
+    }
+    
+    /// The session for `monitor_index`; without an index the capture with
+    /// the lowest monitor index, which is the only one in single-monitor use
+    fn session(&self, monitor_index: Option<usize>) -> Option<&CaptureSession> {
+        match monitor_index {
+            Some(index) => self.sessions.get(&index),
+            None => self.active_captures().first().and_then(|index| self.sessions.get(index)),
+        }
+    }
+    
+    /// Get a frame from the capturer of a monitor
+    pub fn get_next_frame(&mut self, monitor_index: usize) -> Option<FrameData> {
+        self.sessions.get_mut(&monitor_index)
+            .and_then(|session| session.capturer.get_next_frame())
+    }
+    
+    /// Change the captured region of a monitor; a running capture of it is
+    /// restarted with the new region
+    pub fn set_capture_region(
+        &mut self,
+        monitor_index: usize,
+        region: Option<CaptureRegion>,
+        window: Window,
+    ) -> Result<(), ScreenCaptureError> {
+        let mut config = self.configs.get(&monitor_index).cloned()
+            .unwrap_or_else(|| ScreenCaptureConfig { monitor_index, ..ScreenCaptureConfig::default() });
+        config.capture_region = region;
+        self.validate_config(&config)?;
+        
+        if self.sessions.contains_key(&monitor_index) {
+            self.start_capture(config, window)
+        } else {
+            self.configs.insert(monitor_index, config);
+            self.update_pointer_feed();
+            Ok(())
+        }
+    }
+    
+    /// Change the size of the encoded stream of one monitor, or of all running
+    /// captures. A running encoder is restarted with the new size while the
+    /// session, its buffer and the viewers' connections stay up; the new
+    /// stream starts with a keyframe. Returns the delay until then in ms.
+    pub fn set_output_resolution(
+        &mut self,
+        resolution: Option<OutputResolution>,
+        monitor_index: Option<usize>,
+    ) -> Result<u64, ScreenCaptureError> {
+        if let Some(resolution) = &resolution {
+            resolution.validate()?;
+        }
+        
+        let indices = match monitor_index {
+            Some(index) => {
+                self.check_monitor_index(index)?;
+                vec![index]
+            },
+            None => self.active_captures(),
+        };
+        
+        let wait = indices.into_iter()
+            .map(|index| self.update_encoder_config(index, |config| config.output_resolution = resolution))
+            .max()
+            .unwrap_or(0);
+        
+        Ok(wait)
+    }
+    
+    /// Switch all running captures to the encoding parameters of a profile.
+    /// Encoders are restarted like on `set_output_resolution`, with the
+    /// profile's quality as the new starting point; what is captured stays
+    /// the same. Returns the delay until the restarts in ms.
+    pub fn apply_capture_profile(&mut self, profile: &ScreenCaptureConfig) -> Result<u64, ScreenCaptureError> {
+        let indices = self.active_captures();
+        
+        // A profile is applied to every capture or to none
+        for index in &indices {
+            let config = self.configs.get(index).cloned()
+                .unwrap_or_else(|| ScreenCaptureConfig { monitor_index: *index, ..ScreenCaptureConfig::default() });
+            self.validate_config(&config.with_profile(profile))?;
+        }
+        
+        let wait = indices.into_iter()
+            .map(|index| {
+                let wait = self.update_encoder_config(index, |config| *config = config.with_profile(profile));
+                if let Some(session) = self.sessions.get(&index) {
+                    session.quality_controller.lock().unwrap().reset_quality(profile.quality);
+                    // The profile's frame rate may be capped on battery
+                    let fps = session.config.lock().unwrap().fps;
+                    session.stream_buffer.lock().unwrap().set_fps(fps);
+                }
+                wait
+            })
+            .max()
+            .unwrap_or(0);
+        
+        Ok(wait)
+    }
+    
+    /// Applies `update` to the stored config of a monitor and, if it is
+    /// captured, to the running session, whose encoder is then restarted.
+    /// Returns the delay until the restart in ms.
+    fn update_encoder_config(&mut self, monitor_index: usize, update: impl Fn(&mut ScreenCaptureConfig)) -> u64 {
+        let config = self.configs.entry(monitor_index)
+            .or_insert_with(|| ScreenCaptureConfig { monitor_index, ..ScreenCaptureConfig::default() });
+        update(config);
+        
+        // The capture loop reads the config on every encoder start
+        match self.sessions.get(&monitor_index) {
+            Some(session) => {
+                let limits = power_profile::limits_for(config, session.power_profile);
+                let mut session_config = session.config.lock().unwrap();
+                update(&mut session_config);
+                power_profile::apply_limits(config, &limits, &mut session_config);
+                session.keyframes.lock().unwrap().request(std::time::Instant::now()).as_millis() as u64
+            },
+            None => 0,
+        }
+    }
+    
+    /// Apply the power profile matching `source` to every capture. Captures
+    /// whose profile changed get the new limits: the frame rate and the
+    /// software fallback through an encoder restart, the bitrate ceiling
+    /// right away in the quality controller. Returns those captures.
+    pub fn set_power_source(&mut self, source: PowerSource) -> Vec<PowerProfileChange> {
+        self.power_source = source;
+        
+        let mut changes = Vec::new();
+        for index in self.active_captures() {
+            let Some(session) = self.sessions.get_mut(&index) else { continue };
+            let user = self.configs.get(&index).cloned()
+                .unwrap_or_else(|| session.config.lock().unwrap().clone());
+            let profile = PowerProfile::for_source(&source, &user.power_profiles);
+            if profile == session.power_profile {
+                continue;
+            }
+            
+            let limits = power_profile::limits_for(&user, profile);
+            let change = PowerProfileChange::new(index, session.power_profile, profile, &source, &user, &limits);
+            log::info!(target: TARGET_SCREEN_CAPTURE, "Monitor {}: {}", index, change.explanation);
+            
+            let restart = {
+                let mut config = session.config.lock().unwrap();
+                let before = (config.fps, config.bitrate, config.software_fallback);
+                power_profile::apply_limits(&user, &limits, &mut config);
+                session.stream_buffer.lock().unwrap().set_fps(config.fps);
+                before != (config.fps, config.bitrate, config.software_fallback)
+            };
+            session.quality_controller.lock().unwrap().set_bitrate_ceiling(limits.max_bitrate_kbps);
+            if restart {
+                session.keyframes.lock().unwrap().request(std::time::Instant::now());
+            }
+            
+            session.power_profile = profile;
+            changes.push(change);
+        }
+        
+        changes
+    }
+    
+    /// AC or battery, as last reported
+    pub fn get_power_source(&self) -> PowerSource {
+        self.power_source
+    }
+    
+    /// Note the size of the window a viewer shows the stream in. Captures
+    /// are refitted by `apply_viewer_viewports` once the viewports settled.
+    pub fn set_viewer_viewport(&mut self, peer_id: &str, viewport: ViewerViewport) -> Result<(), ScreenCaptureError> {
+        viewport.validate()?;
+        self.viewports.set(peer_id, viewport, std::time::Instant::now());
         Ok(())
     }
     
-    /// Stop screen capture
-    pub fn stop_capture(&mut self) -> Result<(), ScreenCaptureError> {
-        // Set running flag to false
+    /// Forget the viewport of a viewer that left
+    pub fn remove_viewer_viewport(&mut self, peer_id: &str) {
+        self.viewports.remove(peer_id, std::time::Instant::now());
+    }
+    
+    /// Fit every capture to the largest viewer viewport, once no viewport
+    /// changed for the debounce time. Captures whose size changed have their
+    /// encoder restarted like on `set_output_resolution`; the adaptive
+    /// quality controller keeps scaling on top. Returns those captures.
+    pub fn apply_viewer_viewports(&mut self, now: std::time::Instant) -> Vec<QualityChange> {
+        if !self.viewports.settled(now) {
+            return Vec::new();
+        }
+        
+        let mut changes = Vec::new();
+        for index in self.active_captures() {
+            let (Some(session), Some(monitor)) = (self.sessions.get(&index), self.monitors.get(index)) else { continue };
+            let config = session.config.lock().unwrap().clone();
+            let decision = if config.viewport_fit.enabled {
+                self.viewports.largest(config.output_size(monitor, 100), &config.viewport_fit)
+            } else {
+                None
+            };
+            let percent = decision.as_ref().map_or(100, |decision| decision.scale_percent);
+            
+            let size = {
+                let mut controller = session.quality_controller.lock().unwrap();
+                if controller.viewport_scale_percent() == percent {
+                    continue;
+                }
+                controller.set_viewport_scale_percent(percent);
+                config.output_size(monitor, controller.output_scale_percent())
+            };
+            session.keyframes.lock().unwrap().request(now);
+            
+            let change = QualityChange::viewport(index, decision, size, self.viewports.len());
+            log::info!(target: TARGET_SCREEN_CAPTURE, "Monitor {}: {}", index, change.explanation);
+            changes.push(change);
+        }
+        
+        changes
+    }
+    
+    fn check_monitor_index(&self, monitor_index: usize) -> Result<&MonitorInfo, ScreenCaptureError> {
+        self.monitors.get(monitor_index).ok_or_else(|| ScreenCaptureError::InvalidMonitor(format!(
+            "Monitor index {} out of bounds (0-{})", monitor_index, self.monitors.len().saturating_sub(1)
+        )))
+    }
+    
+    /// Hide an area of a monitor from viewers. A running encoder is restarted
+    /// with the mask like on `set_output_resolution`. Returns the mask's id.
+    pub fn add_privacy_mask(&mut self, monitor_index: usize, mask: PrivacyMask) -> Result<u32, ScreenCaptureError> {
+        mask.validate(self.check_monitor_index(monitor_index)?)?;
+        
+        let id = self.configs.get(&monitor_index)
+            .map(|config| config.privacy.next_id())
+            .unwrap_or(1);
+        self.update_encoder_config(monitor_index, |config| config.privacy.masks.push(PrivacyMaskEntry { id, mask }));
+        
+        Ok(id)
+    }
+    
+    /// Remove a mask added with `add_privacy_mask`
+    pub fn remove_privacy_mask(&mut self, monitor_index: usize, id: u32) -> Result<(), ScreenCaptureError> {
+        self.check_monitor_index(monitor_index)?;
+        
+        let exists = self.configs.get(&monitor_index)
+            .is_some_and(|config| config.privacy.masks.iter().any(|entry| entry.id == id));
+        if !exists {
+            return Err(ScreenCaptureError::InvalidConfig(format!(
+                "Monitor {} has no privacy mask with id {}", monitor_index, id
+            )));
+        }
+        
+        self.update_encoder_config(monitor_index, |config| config.privacy.masks.retain(|entry| entry.id != id));
+        Ok(())
+    }
+    
+    /// Fixed masks of a monitor and the masks over its matched windows
+    pub fn list_privacy_masks(&self, monitor_index: usize) -> Result<PrivacyMasks, ScreenCaptureError> {
+        self.check_monitor_index(monitor_index)?;
+        
+        let masks = self.configs.get(&monitor_index)
+            .map(|config| config.privacy.masks.clone())
+            .unwrap_or_default();
+        // Only the running session knows where the windows are
+        let window_masks = self.sessions.get(&monitor_index)
+            .map(|session| session.config.lock().unwrap().privacy.window_masks.clone())
+            .unwrap_or_default();
+        
+        Ok(PrivacyMasks { masks, window_masks })
+    }
+    
+    /// Monitors as the input forwarder should see them: captured monitors
+    /// are narrowed to their capture region, so client (0,0) is the region origin.
+    /// The output resolution plays no part: input coordinates are pixels of
+    /// the captured area, whatever size the stream is encoded at.
+    pub fn get_input_monitors(&self) -> Vec<MonitorInfo> {
+        let mut monitors = self.monitors.clone();
+        
+        for (index, config) in &self.configs {
+            if config.capture_region.is_none() {
+                continue;
+            }
+            if let Some(monitor) = monitors.get_mut(*index) {
+                let region = config.effective_region(monitor);
+                let (x, y) = region.origin(monitor);
+                monitor.x_offset = x;
+                monitor.y_offset = y;
+                monitor.width = region.width;
+                monitor.height = region.height;
+            }
+        }
+        
+        monitors
+    }
+    
+    /// Forwarded pointer input; the input path reports to it without
+    /// taking this manager's lock
+    pub fn pointer_feed(&self) -> PointerFeed {
+        self.pointer_feed.clone()
+    }
+    
+    /// Push the input monitors and the default monitor to the pointer feed
+    fn update_pointer_feed(&self) {
+        let default_index = self.active_captures().first().copied().unwrap_or(0);
+        self.pointer_feed.set_layout(self.get_input_monitors(), default_index);
+    }
+    
+    /// Start sampling the cursor of a captured monitor for a recording's
+    /// overlay. A track already being recorded is started over.
+    pub fn start_cursor_track(&mut self, monitor_index: usize, config: RecordingCursorConfig) -> Result<(), ScreenCaptureError> {
+        if !self.sessions.contains_key(&monitor_index) {
+            return Err(ScreenCaptureError::InvalidMonitor(format!("Monitor {} is not being captured", monitor_index)));
+        }
+        let monitor = self.get_input_monitors().get(monitor_index).cloned().ok_or_else(|| {
+            ScreenCaptureError::InvalidMonitor(format!("Monitor {} not found", monitor_index))
+        })?;
+        
+        let recording = CursorTrackRecording::start(self.cursor_source(), monitor, config);
+        self.pointer_feed.start_track(monitor_index, recording);
+        Ok(())
+    }
+    
+    /// Stop sampling the cursor of a monitor and return the track
+    pub fn stop_cursor_track(&mut self, monitor_index: usize) -> Result<CursorTrack, ScreenCaptureError> {
+        self.pointer_feed.finish_track(monitor_index)
+            .ok_or_else(|| ScreenCaptureError::InvalidMonitor(
+                format!("No cursor track is being recorded for monitor {}", monitor_index)
+            ))
+    }
+    
+    /// Pointer source for the client-side cursor: XQueryPointer on X11,
+    /// forwarded input events elsewhere
+    fn cursor_source(&self) -> Box<dyn CursorSource> {
+        #[cfg(feature = "x11-support")]
         {
-            let mut running = self.running.lock().unwrap();
-            *running = false;
+            if self.display_server == DisplayServer::X11 {
+                if let Some(source) = crate::screen_capture::cursor::X11CursorSource::open() {
+                    return Box::new(source);
+                }
+                log::warn!(target: TARGET_SCREEN_CAPTURE, "Could not open X display for cursor polling, using forwarded input positions");
+            }
+        }
+        
+        Box::new(self.pointer_feed.cursor_source())
+    }
+    
+    /// Lists windows for masking by class: xdotool on X11, hyprctl on Hyprland
+    fn window_source(&self) -> Option<Box<dyn WindowSource>> {
+        match self.display_server {
+            DisplayServer::X11 => Some(Box::new(X11WindowSource::new(Arc::new(SystemCommandRunner)))),
+            DisplayServer::Wayland if detect_wayland_compositor() == WaylandCompositor::Hyprland => {
+                Some(Box::new(HyprlandWindowSource::new(Arc::new(SystemCommandRunner))))
+            },
+            _ => None,
         }
+    }
+    
+    /// WebSocket URL of a monitor's binary frame stream, if that transport is active
+    pub fn get_frame_stream_url(&self, monitor_index: Option<usize>) -> Option<String> {
+        self.session(monitor_index)
+            .and_then(|session| session.frame_stream.as_ref())
+            .map(|server| server.url())
+    }
+    
+    /// Change buffer size, drop strategy and latency budget at runtime, for
+    /// one monitor or for all running and future captures
+    pub fn set_buffer_config(&mut self, config: BufferConfig, monitor_index: Option<usize>) -> Result<(), ScreenCaptureError> {
+        if config.max_frames == 0 || config.max_bytes_mb == 0 {
+            return Err(ScreenCaptureError::StreamBufferError(
+                "Buffer limits must be greater than zero".to_string(),
+            ));
+        }
+        
+        let sessions: Vec<&CaptureSession> = match monitor_index {
+            Some(index) => {
+                let session = self.sessions.get(&index).ok_or_else(|| ScreenCaptureError::InvalidMonitor(
+                    format!("Monitor {} is not being captured", index)
+                ))?;
+                vec![session]
+            },
+            None => self.sessions.values().collect(),
+        };
         
-        // Stop the capturer if it exists
-        if let Some(capturer) = &mut self.capturer {
-            capturer.stop_capture()?;
+        for session in sessions {
+            // The quality controller aims for the same latency the buffer enforces
+            if let Some(max_latency_ms) = config.max_latency_ms {
+                session.quality_controller.lock().unwrap().set_target_latency(max_latency_ms);
+            }
+            session.stream_buffer.lock().unwrap().configure(&config);
         }
         
-        // Remove the capturer
-        self.capturer = None;
+        if monitor_index.is_none() {
+            self.buffer_config = Some(config);
+        }
         
         Ok(())
     }
     
-    /// Get a frame from the capturer
-    pub fn get_next_frame(&mut self) -> Option<FrameData> {
-        if let Some(capturer) = &mut self.capturer {
-            capturer.get_next_frame()
-        } else {
-            None
+    /// Force a keyframe so a viewer joining mid-stream can start decoding.
+    /// Returns how long until it is produced in ms; requests are limited to
+    /// one forced keyframe per second and capture.
+    pub fn request_keyframe(&self, monitor_index: Option<usize>) -> Result<u64, ScreenCaptureError> {
+        let sessions: Vec<&CaptureSession> = match monitor_index {
+            Some(index) => {
+                let session = self.sessions.get(&index).ok_or_else(|| ScreenCaptureError::InvalidMonitor(
+                    format!("Monitor {} is not being captured", index)
+                ))?;
+                vec![session]
+            },
+            None => self.sessions.values().collect(),
+        };
+        
+        let now = std::time::Instant::now();
+        let wait = sessions.iter()
+            .map(|session| session.keyframes.lock().unwrap().request(now))
+            .max()
+            .unwrap_or_default();
+        
+        Ok(wait.as_millis() as u64)
+    }
+    
+    /// Stop delivering frames of all running and future captures, or resume.
+    /// Capturing continues, so the stream picks up at the next keyframe.
+    pub fn set_output_blanked(&mut self, blanked: bool) {
+        self.output_blanked = blanked;
+        for session in self.sessions.values() {
+            session.stream_buffer.lock().unwrap().set_blanked(blanked);
         }
+        
+        log::info!(target: TARGET_SCREEN_CAPTURE, "Outgoing stream {}", if blanked { "blanked" } else { "resumed" });
+    }
+    
+    /// Whether frames are currently withheld
+    pub fn is_output_blanked(&self) -> bool {
+        self.output_blanked
+    }
+    
+    /// Get stream buffer statistics, including drops by reason
+    pub fn get_buffer_stats(&self, monitor_index: Option<usize>) -> Option<BufferStats> {
+        self.session(monitor_index)
+            .map(|session| session.stream_buffer.lock().unwrap().get_stats())
+    }
+    
+    /// Get capture statistics of a monitor
+    pub fn get_stats(&self, monitor_index: Option<usize>) -> Option<CaptureStats> {
+        let mut stats = self.session(monitor_index)
+            .map(|session| session.stats.lock().unwrap().clone())?;
+        stats.host_session = self.host_session.as_ref().map(|monitor| monitor.state());
+        Some(stats)
+    }
+    
+    /// Statistics of every running capture, ordered by monitor index
+    pub fn get_all_stats(&self) -> Vec<CaptureStats> {
+        self.active_captures().iter()
+            .filter_map(|index| self.get_stats(Some(*index)))
+            .collect()
+    }
+    
+    /// Adds the current stats of every running capture to the history; called once per second
+    pub fn record_stats_sample(&mut self, now_ms: u64) {
+        let stats = self.get_all_stats();
+        self.stats_history.record(stats, now_ms);
+    }
+    
+    /// Recorded stats samples within `range`, oldest first
+    pub fn get_stats_history(&self, range: &StatsHistoryRange) -> Vec<StatsSample> {
+        self.stats_history.query(range)
+    }
+    
+    /// Combined video bitrate of all captures in bits per second
+    pub fn get_total_bitrate(&self) -> u64 {
+        self.sessions.values()
+            .map(|session| session.stats.lock().unwrap().bitrate)
+            .sum()
     }
     
-    /// Get capture statistics
-    pub fn get_stats(&self) -> CaptureStats {
-        self.stats.lock().unwrap().clone()
+    /// Configuration of a running capture
+    pub fn get_config(&self, monitor_index: Option<usize>) -> Option<ScreenCaptureConfig> {
+        self.session(monitor_index)
+            .map(|session| session.config.lock().unwrap().clone())
+    }
+    
+    /// Get the last error reported by a capture process, with its stderr
+    pub fn get_last_error(&self, monitor_index: Option<usize>) -> Option<CaptureErrorReport> {
+        self.session(monitor_index)
+            .and_then(|session| session.last_error.lock().unwrap().clone())
     }
 }
 
@@ -334,3 +1239,411 @@ pub fn detect_display_server() -> Result<DisplayServer, ScreenCaptureError> {
         "No supported display server detected".to_string(),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Capturer that only counts how often it was stopped
+    struct CountingCapturer {
+        stops: Arc<AtomicUsize>,
+    }
+
+    impl ScreenCapturer for CountingCapturer {
+        fn start_capture(&mut self) -> Result<(), ScreenCaptureError> {
+            Ok(())
+        }
+
+        fn stop_capture(&mut self) -> Result<(), ScreenCaptureError> {
+            self.stops.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn get_next_frame(&mut self) -> Option<FrameData> {
+            None
+        }
+
+        fn get_stats(&self) -> CaptureStats {
+            CaptureStats::default()
+        }
+    }
+
+    fn session(stops: &Arc<AtomicUsize>) -> CaptureSession {
+        CaptureSession {
+            config: Arc::new(Mutex::new(ScreenCaptureConfig::default())),
+            stats: Arc::new(Mutex::new(CaptureStats::default())),
+            running: Arc::new(Mutex::new(true)),
+            stream_buffer: Arc::new(Mutex::new(StreamBuffer::new(10, 10, 30, DropMode::DropOldest))),
+            quality_controller: Arc::new(Mutex::new(AdaptiveQualityController::new(80, None))),
+            keyframes: Arc::new(Mutex::new(KeyframeScheduler::new())),
+            capturer: Box::new(CountingCapturer { stops: stops.clone() }),
+            last_error: Arc::new(Mutex::new(None)),
+            frame_stream: None,
+            cursor_tracker: None,
+            window_masks: None,
+            window: None,
+            power_profile: PowerProfile::Ac,
+        }
+    }
+
+    fn manager() -> ScreenCaptureManager {
+        ScreenCaptureManager {
+            display_server: DisplayServer::X11,
+            monitors: Vec::new(),
+            configs: HashMap::new(),
+            sessions: HashMap::new(),
+            max_simultaneous_captures: DEFAULT_MAX_SIMULTANEOUS_CAPTURES,
+            buffer_config: None,
+            pointer_feed: PointerFeed::new(),
+            virtual_displays: VirtualDisplayManager::new(DisplayServer::X11),
+            output_blanked: false,
+            encoders: CapabilityMatrix::default(),
+            host_session: None,
+            stats_history: StatsHistory::new(),
+            power_source: PowerSource::default(),
+            frame_latency: Arc::new(Mutex::new(FrameLatencyTracker::new())),
+            viewports: ViewerViewports::new(),
+            thumbnails: ThumbnailGrabber::new(Arc::new(SystemCommandRunner), DisplayServer::X11),
+        }
+    }
+
+    #[test]
+    fn test_drop_stops_running_captures() {
+        let stops = Arc::new(AtomicUsize::new(0));
+        let mut manager = manager();
+        let first = session(&stops);
+        let running = first.running.clone();
+        manager.sessions.insert(0, first);
+        manager.sessions.insert(1, session(&stops));
+
+        drop(manager);
+        assert_eq!(stops.load(Ordering::SeqCst), 2);
+        assert!(!*running.lock().unwrap());
+    }
+
+    #[test]
+    fn test_suspend_stops_captures_and_remembers_monitor_names() {
+        let stops = Arc::new(AtomicUsize::new(0));
+        let mut manager = manager();
+        manager.monitors = vec![
+            MonitorInfo { index: 0, name: "eDP-1".to_string(), width: 1920, height: 1080, refresh_rate: None, primary: true, x_offset: 0, y_offset: 0, scale_factor: 1.0, rotation: Default::default() },
+            MonitorInfo { index: 1, name: "DP-1".to_string(), width: 2560, height: 1440, refresh_rate: None, primary: false, x_offset: 1920, y_offset: 0, scale_factor: 1.0, rotation: Default::default() },
+        ];
+        let second = session(&stops);
+        second.config.lock().unwrap().monitor_index = 1;
+        manager.sessions.insert(1, second);
+        
+        let suspended = manager.suspend_captures();
+        assert!(manager.active_captures().is_empty());
+        assert_eq!(stops.load(Ordering::SeqCst), 1);
+        assert_eq!(suspended.len(), 1);
+        assert_eq!(suspended[0].monitor_name, "DP-1");
+        assert_eq!(suspended[0].config.monitor_index, 1);
+    }
+
+    #[test]
+    fn test_output_resolution_restarts_encoder_and_keeps_input_space() {
+        let stops = Arc::new(AtomicUsize::new(0));
+        let mut manager = manager();
+        manager.monitors = vec![MonitorInfo {
+            index: 0,
+            name: "DP-1".to_string(),
+            width: 3840,
+            height: 2160,
+            refresh_rate: Some(60.0),
+            primary: true,
+            x_offset: 0,
+            y_offset: 0,
+            scale_factor: 1.0,
+            rotation: crate::screen_capture::types::MonitorRotation::Normal,
+        }];
+        let region = CaptureRegion { x: 1920, y: 0, width: 1920, height: 1080 };
+        manager.configs.insert(0, ScreenCaptureConfig { capture_region: Some(region), ..ScreenCaptureConfig::default() });
+        let session = session(&stops);
+        *session.config.lock().unwrap() = manager.configs[&0].clone();
+        let config = session.config.clone();
+        let keyframes = session.keyframes.clone();
+        manager.sessions.insert(0, session);
+        
+        let before = manager.get_input_monitors();
+        let half = OutputResolution::Scale { percent: 50 };
+        assert_eq!(manager.set_output_resolution(Some(half), None).unwrap(), 0);
+        
+        // The running encoder picks up the new size on its next start, which is due now
+        assert_eq!(config.lock().unwrap().output_resolution, Some(half));
+        assert_eq!(config.lock().unwrap().output_size(&manager.monitors[0], 100), (960, 540));
+        assert!(keyframes.lock().unwrap().take_due(std::time::Instant::now()));
+        keyframes.lock().unwrap().encoder_started(std::time::Instant::now());
+        assert_eq!(stops.load(Ordering::SeqCst), 0);
+        
+        // A click in the middle of the half-size stream is sent as the middle
+        // of the captured region and lands there on the desktop
+        let monitors = manager.get_input_monitors();
+        assert_eq!((monitors[0].x_offset, monitors[0].width, monitors[0].height), (before[0].x_offset, 1920, 1080));
+        let (stream_x, stream_y) = (480, 270);
+        let (x, y) = (stream_x * 1920 / 960, stream_y * 1080 / 540);
+        assert_eq!((monitors[0].x_offset + x, monitors[0].y_offset + y), (2880, 540));
+        
+        assert!(manager.set_output_resolution(Some(OutputResolution::Scale { percent: 0 }), Some(0)).is_err());
+        assert!(matches!(manager.set_output_resolution(None, Some(3)), Err(ScreenCaptureError::InvalidMonitor(_))));
+        // Restarts are rate limited like forced keyframes
+        assert!(manager.set_output_resolution(None, Some(0)).unwrap() > 0);
+        assert_eq!(manager.configs[&0].output_resolution, None);
+    }
+
+    #[test]
+    fn test_viewport_downscale_keeps_clicks_on_target() {
+        use crate::screen_capture::viewport::VIEWPORT_DEBOUNCE;
+        
+        let stops = Arc::new(AtomicUsize::new(0));
+        let mut manager = manager();
+        manager.monitors = vec![MonitorInfo {
+            index: 0,
+            name: "DP-1".to_string(),
+            width: 3840,
+            height: 2160,
+            refresh_rate: Some(60.0),
+            primary: true,
+            x_offset: 1920,
+            y_offset: 0,
+            scale_factor: 1.0,
+            rotation: crate::screen_capture::types::MonitorRotation::Normal,
+        }];
+        let session = session(&stops);
+        let config = session.config.clone();
+        let controller = session.quality_controller.clone();
+        let keyframes = session.keyframes.clone();
+        manager.sessions.insert(0, session);
+        
+        let laptop = ViewerViewport { width: 1280, height: 720, device_pixel_ratio: 1.5 };
+        let phone = ViewerViewport { width: 390, height: 844, device_pixel_ratio: 3.0 };
+        manager.set_viewer_viewport("laptop", laptop).unwrap();
+        manager.set_viewer_viewport("phone", phone).unwrap();
+        assert!(manager.set_viewer_viewport("phone", ViewerViewport { width: 0, ..phone }).is_err());
+        
+        // Nothing happens while the viewports may still change
+        let start = std::time::Instant::now();
+        assert!(manager.apply_viewer_viewports(start).is_empty());
+        
+        let settled = start + VIEWPORT_DEBOUNCE;
+        let changes = manager.apply_viewer_viewports(settled);
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].peer_id.as_deref(), changes[0].scale_percent, changes[0].viewers), (Some("laptop"), 50, 2));
+        assert_eq!((changes[0].width, changes[0].height), (1920, 1080));
+        assert!(keyframes.lock().unwrap().take_due(settled));
+        assert!(manager.apply_viewer_viewports(settled).is_empty());
+        
+        // The encoder restarts at the fitted size; the adaptive scale applies on top
+        let stream = config.lock().unwrap().output_size(&manager.monitors[0], controller.lock().unwrap().output_scale_percent());
+        assert_eq!(stream, (1920, 1080));
+        assert_eq!(stops.load(Ordering::SeqCst), 0);
+        
+        // Clicks are still sent in source pixels: the corner and the middle
+        // of the half-size stream land on the corner and middle of the monitor
+        let monitors = manager.get_input_monitors();
+        assert_eq!((monitors[0].width, monitors[0].height), (3840, 2160));
+        for ((stream_x, stream_y), target) in [((0, 0), (1920, 0)), ((960, 540), (3840, 1080)), ((1919, 1079), (5758, 2158))] {
+            let (x, y) = (stream_x * monitors[0].width as i32 / stream.0 as i32, stream_y * monitors[0].height as i32 / stream.1 as i32);
+            assert_eq!((monitors[0].x_offset + x, monitors[0].y_offset + y), target);
+        }
+        
+        // Once the laptop leaves, the stream is fitted to the phone
+        manager.remove_viewer_viewport("laptop");
+        let changes = manager.apply_viewer_viewports(std::time::Instant::now() + VIEWPORT_DEBOUNCE);
+        assert_eq!((changes[0].peer_id.as_deref(), changes[0].scale_percent), (Some("phone"), 31));
+        
+        // Disabled fitting restores the configured size
+        config.lock().unwrap().viewport_fit.enabled = false;
+        let changes = manager.apply_viewer_viewports(std::time::Instant::now() + VIEWPORT_DEBOUNCE);
+        assert_eq!((changes[0].peer_id.as_deref(), changes[0].width, changes[0].height), (None, 3840, 2160));
+    }
+
+    #[test]
+    fn test_privacy_masks_restart_encoder() {
+        let stops = Arc::new(AtomicUsize::new(0));
+        let mut manager = manager();
+        manager.monitors = vec![MonitorInfo {
+            index: 0,
+            name: "DP-1".to_string(),
+            width: 1920,
+            height: 1080,
+            refresh_rate: Some(60.0),
+            primary: true,
+            x_offset: 0,
+            y_offset: 0,
+            scale_factor: 1.0,
+            rotation: crate::screen_capture::types::MonitorRotation::Normal,
+        }];
+        let session = session(&stops);
+        let config = session.config.clone();
+        let keyframes = session.keyframes.clone();
+        manager.sessions.insert(0, session);
+        
+        let mask = PrivacyMask { x: 1800, y: -20, width: 300, height: 100, style: Default::default() };
+        assert_eq!(manager.add_privacy_mask(0, mask).unwrap(), 1);
+        assert_eq!(manager.add_privacy_mask(0, PrivacyMask { x: 0, ..mask }).unwrap(), 2);
+        
+        // The running encoder is restarted with the masks
+        assert_eq!(config.lock().unwrap().privacy.masks.len(), 2);
+        assert!(keyframes.lock().unwrap().take_due(std::time::Instant::now()));
+        
+        config.lock().unwrap().privacy.window_masks = vec![mask];
+        let listed = manager.list_privacy_masks(0).unwrap();
+        assert_eq!(listed.masks.iter().map(|entry| entry.id).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(listed.window_masks, vec![mask]);
+        
+        manager.remove_privacy_mask(0, 1).unwrap();
+        assert_eq!(config.lock().unwrap().privacy.masks, vec![PrivacyMaskEntry { id: 2, mask: PrivacyMask { x: 0, ..mask } }]);
+        assert_eq!(manager.add_privacy_mask(0, mask).unwrap(), 3);
+        
+        assert!(manager.remove_privacy_mask(0, 1).is_err());
+        assert!(matches!(manager.add_privacy_mask(0, PrivacyMask { x: 1920, ..mask }), Err(ScreenCaptureError::InvalidRegion(_))));
+        assert!(matches!(manager.list_privacy_masks(1), Err(ScreenCaptureError::InvalidMonitor(_))));
+        assert_eq!(stops.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_capture_profile_restarts_encoders() {
+        use crate::screen_capture::config::{find_capture_profile, PROFILE_PRESENTATION};
+        use crate::screen_capture::encoders::SystemProbe;
+        use crate::screen_capture::types::VideoCodec;
+        
+        let stops = Arc::new(AtomicUsize::new(0));
+        let mut manager = manager();
+        manager.monitors = vec![MonitorInfo {
+            index: 0,
+            name: "DP-1".to_string(),
+            width: 1920,
+            height: 1080,
+            refresh_rate: Some(60.0),
+            primary: true,
+            x_offset: 0,
+            y_offset: 0,
+            scale_factor: 1.0,
+            rotation: crate::screen_capture::types::MonitorRotation::Normal,
+        }];
+        let system = SystemProbe { ffmpeg_encoders: ["libx264".to_string()].into(), ..Default::default() };
+        manager.encoders = CapabilityMatrix::build(&system, |_, _| Ok(()));
+        
+        let region = CaptureRegion { x: 0, y: 0, width: 1280, height: 720 };
+        manager.configs.insert(0, ScreenCaptureConfig { capture_region: Some(region), ..ScreenCaptureConfig::default() });
+        let session = session(&stops);
+        *session.config.lock().unwrap() = manager.configs[&0].clone();
+        let config = session.config.clone();
+        let keyframes = session.keyframes.clone();
+        let quality_controller = session.quality_controller.clone();
+        manager.sessions.insert(0, session);
+        
+        let presentation = find_capture_profile(PROFILE_PRESENTATION, &Default::default()).unwrap();
+        assert_eq!(manager.apply_capture_profile(&presentation).unwrap(), 0);
+        
+        // The session stays up and its encoder restarts with the profile
+        let applied = config.lock().unwrap().clone();
+        assert_eq!((applied.fps, applied.keyframe_interval), (5, 10));
+        assert_eq!(applied.capture_region, Some(region));
+        assert_eq!(manager.configs[&0].fps, 5);
+        assert_eq!(quality_controller.lock().unwrap().get_quality(), 95);
+        assert!(keyframes.lock().unwrap().take_due(std::time::Instant::now()));
+        assert_eq!(stops.load(Ordering::SeqCst), 0);
+        
+        // Profiles the encoders cannot run change nothing
+        let vp9 = ScreenCaptureConfig { codec: VideoCodec::VP9, fps: 60, ..presentation };
+        assert!(matches!(manager.apply_capture_profile(&vp9), Err(ScreenCaptureError::UnsupportedEncoder(_))));
+        assert_eq!(config.lock().unwrap().fps, 5);
+    }
+
+    #[test]
+    fn test_power_profile_limits_running_captures() {
+        use crate::screen_capture::config::{find_capture_profile, PROFILE_HIGH_QUALITY_LAN};
+        use crate::screen_capture::encoders::SystemProbe;
+        
+        let stops = Arc::new(AtomicUsize::new(0));
+        let mut manager = manager();
+        manager.monitors = vec![MonitorInfo { index: 0, name: "eDP-1".to_string(), width: 1920, height: 1080, refresh_rate: None, primary: true, x_offset: 0, y_offset: 0, scale_factor: 1.0, rotation: Default::default() }];
+        let system = SystemProbe { ffmpeg_encoders: ["libx264".to_string()].into(), ..Default::default() };
+        manager.encoders = CapabilityMatrix::build(&system, |_, _| Ok(()));
+        manager.configs.insert(0, ScreenCaptureConfig { fps: 60, ..ScreenCaptureConfig::default() });
+        let session = session(&stops);
+        *session.config.lock().unwrap() = manager.configs[&0].clone();
+        let config = session.config.clone();
+        let keyframes = session.keyframes.clone();
+        manager.sessions.insert(0, session);
+        
+        let battery = PowerSource { on_battery: true, battery_percent: Some(70) };
+        let changes = manager.set_power_source(battery);
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].profile, changes[0].fps, changes[0].configured_fps), (PowerProfile::Battery, 30, 60));
+        assert_eq!(config.lock().unwrap().fps, 30);
+        assert!(keyframes.lock().unwrap().take_due(std::time::Instant::now()));
+        keyframes.lock().unwrap().encoder_started(std::time::Instant::now());
+        
+        // Only a change of profile is reported, not every percent
+        assert!(manager.set_power_source(PowerSource { battery_percent: Some(69), ..battery }).is_empty());
+        
+        // A profile switched to on battery stays capped, the stored config is not
+        let lan = find_capture_profile(PROFILE_HIGH_QUALITY_LAN, &Default::default()).unwrap();
+        manager.apply_capture_profile(&lan).unwrap();
+        assert_eq!((config.lock().unwrap().fps, manager.configs[&0].fps), (30, 60));
+        assert_eq!(manager.suspend_captures()[0].config.fps, 60);
+        
+        // Back on AC the configured rate returns
+        let mut session = session(&stops);
+        *session.config.lock().unwrap() = ScreenCaptureConfig { fps: 30, ..manager.configs[&0].clone() };
+        session.power_profile = PowerProfile::Battery;
+        let config = session.config.clone();
+        manager.sessions.insert(0, session);
+        let changes = manager.set_power_source(PowerSource::default());
+        assert_eq!(changes[0].profile, PowerProfile::Ac);
+        assert_eq!(config.lock().unwrap().fps, 60);
+    }
+    
+    #[test]
+    fn test_stopped_capture_is_not_stopped_again_on_drop() {
+        let stops = Arc::new(AtomicUsize::new(0));
+        let mut manager = manager();
+        manager.sessions.insert(0, session(&stops));
+
+        manager.stop_capture(Some(0)).unwrap();
+        drop(manager);
+        assert_eq!(stops.load(Ordering::SeqCst), 1);
+    }
+    
+    #[test]
+    fn test_pointer_feed_does_not_wait_for_a_busy_manager() {
+        let stops = Arc::new(AtomicUsize::new(0));
+        let mut manager = manager();
+        manager.monitors = vec![
+            MonitorInfo { index: 0, name: "eDP-1".to_string(), width: 1920, height: 1080, refresh_rate: None, primary: true, x_offset: 0, y_offset: 0, scale_factor: 1.0, rotation: Default::default() },
+            MonitorInfo { index: 1, name: "DP-1".to_string(), width: 2560, height: 1440, refresh_rate: None, primary: false, x_offset: 1920, y_offset: 0, scale_factor: 1.0, rotation: Default::default() },
+        ];
+        let session = session(&stops);
+        session.config.lock().unwrap().monitor_index = 1;
+        manager.sessions.insert(1, session);
+        manager.update_pointer_feed();
+        manager.start_cursor_track(1, RecordingCursorConfig::default()).unwrap();
+        let feed = manager.pointer_feed();
+        
+        // Starting a capture holds the manager for as long as ffmpeg takes to come up
+        let manager = Arc::new(Mutex::new(manager));
+        let busy = manager.clone();
+        let (locked_sender, locked) = std::sync::mpsc::channel();
+        let holder = std::thread::spawn(move || {
+            let _manager = busy.lock().unwrap();
+            locked_sender.send(()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(500));
+        });
+        locked.recv().unwrap();
+        
+        let started = std::time::Instant::now();
+        feed.report_position(100, 50, None);
+        feed.report_click(Some((100, 50)), None);
+        assert!(started.elapsed() < std::time::Duration::from_millis(100));
+        holder.join().unwrap();
+        
+        // Without an index the first captured monitor is meant
+        assert_eq!(feed.cursor_source().query().map(|(x, y, _)| (x, y)), Some((2020, 50)));
+        let track = manager.lock().unwrap().stop_cursor_track(1).unwrap();
+        assert_eq!(track.clicks.iter().map(|click| (click.x, click.y)).collect::<Vec<_>>(), vec![(100, 50)]);
+    }
+}
@@ -0,0 +1,565 @@
+// screen_capture/matroska.rs - Minimal streaming Matroska/EBML demuxer
+//
+// Only what the capture loop needs: track geometry and codec from the Tracks
+// element, cluster timestamps, and the frames in SimpleBlock/Block elements.
+// Everything else is skipped.
+
+use std::collections::HashMap;
+
+use crate::screen_capture::types::FrameData;
+
+// Element IDs (with the VINT marker bit, as they appear in the stream)
+const ID_EBML: u64 = 0x1A45DFA3;
+const ID_SEGMENT: u64 = 0x18538067;
+const ID_INFO: u64 = 0x1549A966;
+const ID_TIMECODE_SCALE: u64 = 0x2AD7B1;
+const ID_TRACKS: u64 = 0x1654AE6B;
+const ID_TRACK_ENTRY: u64 = 0xAE;
+const ID_TRACK_NUMBER: u64 = 0xD7;
+const ID_TRACK_TYPE: u64 = 0x83;
+const ID_CODEC_ID: u64 = 0x86;
+const ID_VIDEO: u64 = 0xE0;
+const ID_PIXEL_WIDTH: u64 = 0xB0;
+const ID_PIXEL_HEIGHT: u64 = 0xBA;
+const ID_CLUSTER: u64 = 0x1F43B675;
+const ID_CLUSTER_TIMECODE: u64 = 0xE7;
+const ID_SIMPLE_BLOCK: u64 = 0xA3;
+const ID_BLOCK_GROUP: u64 = 0xA0;
+const ID_BLOCK: u64 = 0xA1;
+const ID_REFERENCE_BLOCK: u64 = 0xFB;
+
+/// Byte pattern of a Cluster element ID, used to resynchronize
+const CLUSTER_SYNC: [u8; 4] = [0x1F, 0x43, 0xB6, 0x75];
+
+/// Elements larger than this are treated as corruption rather than waited for
+const MAX_ELEMENT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Marker for elements of unknown size (live streams)
+const UNKNOWN_SIZE: u64 = u64::MAX;
+
+const TRACK_TYPE_VIDEO: u64 = 1;
+
+/// Track information from the Tracks element
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MatroskaTrack {
+    pub number: u64,
+    pub track_type: u64,
+    pub codec_id: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl MatroskaTrack {
+    /// Short codec name for the frontend, e.g. "h264" for V_MPEG4/ISO/AVC
+    pub fn codec_name(&self) -> String {
+        match self.codec_id.as_str() {
+            "V_MPEG4/ISO/AVC" => "h264".to_string(),
+            "V_VP8" => "vp8".to_string(),
+            "V_VP9" => "vp9".to_string(),
+            "V_AV1" => "av1".to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+/// A frame extracted from a SimpleBlock or Block
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatroskaFrame {
+    pub track_number: u64,
+    /// Presentation timestamp in milliseconds
+    pub timestamp_ms: u64,
+    pub keyframe: bool,
+    pub data: Vec<u8>,
+}
+
+/// A Block inside a BlockGroup; its keyframe flag is only known once the
+/// group ends (no ReferenceBlock means keyframe)
+#[derive(Debug)]
+struct PendingBlock {
+    frame: MatroskaFrame,
+    referenced: bool,
+}
+
+enum VintError {
+    /// More bytes are needed
+    Incomplete,
+    /// Not a valid VINT at this position
+    Invalid,
+}
+
+/// Incremental demuxer: feed bytes as they arrive, get frames back
+#[derive(Debug)]
+pub struct MatroskaDemuxer {
+    buffer: Vec<u8>,
+    /// Absolute stream offset of `buffer[0]`
+    buffer_offset: u64,
+    /// Open master elements of known size: (id, absolute end offset)
+    open_elements: Vec<(u64, u64)>,
+    tracks: HashMap<u64, MatroskaTrack>,
+    current_track: Option<MatroskaTrack>,
+    pending_block: Option<PendingBlock>,
+    timecode_scale: u64,
+    cluster_timecode: u64,
+    resync_count: u64,
+}
+
+impl Default for MatroskaDemuxer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MatroskaDemuxer {
+    pub fn new() -> Self {
+        MatroskaDemuxer {
+            buffer: Vec::new(),
+            buffer_offset: 0,
+            open_elements: Vec::new(),
+            tracks: HashMap::new(),
+            current_track: None,
+            pending_block: None,
+            timecode_scale: 1_000_000, // Matroska default: 1 ms
+            cluster_timecode: 0,
+            resync_count: 0,
+        }
+    }
+
+    /// Tracks seen so far
+    pub fn tracks(&self) -> &HashMap<u64, MatroskaTrack> {
+        &self.tracks
+    }
+
+    /// Track information for a frame
+    pub fn track(&self, number: u64) -> Option<&MatroskaTrack> {
+        self.tracks.get(&number)
+    }
+
+    /// First video track, if the Tracks element has been parsed
+    pub fn video_track(&self) -> Option<&MatroskaTrack> {
+        self.tracks.values()
+            .filter(|track| track.track_type == TRACK_TYPE_VIDEO)
+            .min_by_key(|track| track.number)
+    }
+
+    /// How often the parser had to skip to the next cluster
+    pub fn resync_count(&self) -> u64 {
+        self.resync_count
+    }
+
+    /// Bytes buffered while waiting for the rest of an element
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Converts a demuxed frame for the stream buffer. Frames of non-video
+    /// tracks yield `None`; until the Tracks element has been seen the
+    /// monitor geometry is used instead of the track's pixel size.
+    pub fn to_frame_data(&self, frame: MatroskaFrame, fallback_width: u32, fallback_height: u32) -> Option<FrameData> {
+        let track = self.track(frame.track_number);
+        if let Some(track) = track {
+            if track.track_type != TRACK_TYPE_VIDEO {
+                return None;
+            }
+        }
+
+        Some(FrameData {
+            data: frame.data,
+            timestamp: frame.timestamp_ms,
+            keyframe: frame.keyframe,
+            width: track.and_then(|track| track.width).unwrap_or(fallback_width),
+            height: track.and_then(|track| track.height).unwrap_or(fallback_height),
+            format: track.map(|track| track.codec_name()).unwrap_or_else(|| "matroska".to_string()),
+            frame_id: 0,
+            captured_us: 0,
+            encoded_us: 0,
+        })
+    }
+
+    /// Appends stream data and returns all frames completed by it
+    pub fn push(&mut self, data: &[u8]) -> Vec<MatroskaFrame> {
+        self.buffer.extend_from_slice(data);
+
+        let mut frames = Vec::new();
+        let mut pos = 0;
+
+        loop {
+            self.close_finished_elements(self.buffer_offset + pos as u64, &mut frames);
+
+            if pos >= self.buffer.len() {
+                break;
+            }
+
+            let (id, id_len) = match read_vint(&self.buffer[pos..], true, 4) {
+                Ok(vint) => vint,
+                Err(VintError::Incomplete) => break,
+                Err(VintError::Invalid) => {
+                    pos = self.resync(pos);
+                    continue;
+                }
+            };
+
+            let (size, size_len) = match read_vint(&self.buffer[pos + id_len..], false, 8) {
+                Ok(vint) => vint,
+                Err(VintError::Incomplete) => break,
+                Err(VintError::Invalid) => {
+                    pos = self.resync(pos);
+                    continue;
+                }
+            };
+
+            let header_len = id_len + size_len;
+            let element_start = self.buffer_offset + pos as u64;
+
+            if is_master(id) {
+                // Descend into masters without waiting for their whole body
+                if size != UNKNOWN_SIZE {
+                    self.open_elements.push((id, element_start + header_len as u64 + size));
+                }
+                self.on_master_start(id);
+                pos += header_len;
+                continue;
+            }
+
+            if size == UNKNOWN_SIZE || size > MAX_ELEMENT_SIZE {
+                pos = self.resync(pos);
+                continue;
+            }
+
+            let size = size as usize;
+            if self.buffer.len() - pos < header_len + size {
+                break;
+            }
+
+            let body_start = pos + header_len;
+            let body = self.buffer[body_start..body_start + size].to_vec();
+            self.on_leaf(id, &body, &mut frames);
+            pos = body_start + size;
+        }
+
+        self.buffer.drain(..pos);
+        self.buffer_offset += pos as u64;
+
+        frames
+    }
+
+    /// Skips ahead to the next Cluster after a parse error. Tracks stay
+    /// known, so frames from the next cluster are usable right away.
+    fn resync(&mut self, pos: usize) -> usize {
+        self.resync_count += 1;
+        self.open_elements.clear();
+        self.pending_block = None;
+        self.current_track = None;
+
+        let search_start = pos + 1;
+        match self.buffer[search_start.min(self.buffer.len())..]
+            .windows(CLUSTER_SYNC.len())
+            .position(|window| window == CLUSTER_SYNC)
+        {
+            Some(offset) => search_start + offset,
+            // Keep a possible partial sync pattern at the end
+            None => self.buffer.len().saturating_sub(CLUSTER_SYNC.len() - 1).max(pos + 1).min(self.buffer.len()),
+        }
+    }
+
+    fn close_finished_elements(&mut self, offset: u64, frames: &mut Vec<MatroskaFrame>) {
+        while let Some(&(id, end)) = self.open_elements.last() {
+            if end > offset {
+                break;
+            }
+            self.open_elements.pop();
+            self.on_master_end(id, frames);
+        }
+    }
+
+    fn on_master_start(&mut self, id: u64) {
+        match id {
+            ID_TRACK_ENTRY => self.current_track = Some(MatroskaTrack::default()),
+            ID_BLOCK_GROUP => self.pending_block = None,
+            ID_CLUSTER => self.cluster_timecode = 0,
+            _ => {}
+        }
+    }
+
+    fn on_master_end(&mut self, id: u64, frames: &mut Vec<MatroskaFrame>) {
+        match id {
+            ID_TRACK_ENTRY => {
+                if let Some(track) = self.current_track.take() {
+                    self.tracks.insert(track.number, track);
+                }
+            },
+            ID_BLOCK_GROUP => {
+                if let Some(pending) = self.pending_block.take() {
+                    let mut frame = pending.frame;
+                    frame.keyframe = !pending.referenced;
+                    frames.push(frame);
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn on_leaf(&mut self, id: u64, body: &[u8], frames: &mut Vec<MatroskaFrame>) {
+        match id {
+            ID_TIMECODE_SCALE => {
+                let scale = read_uint(body);
+                if scale > 0 {
+                    self.timecode_scale = scale;
+                }
+            },
+            ID_CLUSTER_TIMECODE => self.cluster_timecode = read_uint(body),
+            ID_TRACK_NUMBER => self.with_track(|track| track.number = read_uint(body)),
+            ID_TRACK_TYPE => self.with_track(|track| track.track_type = read_uint(body)),
+            ID_CODEC_ID => {
+                let codec_id = String::from_utf8_lossy(body).trim_end_matches('\0').to_string();
+                self.with_track(|track| track.codec_id = codec_id);
+            },
+            ID_PIXEL_WIDTH => self.with_track(|track| track.width = Some(read_uint(body) as u32)),
+            ID_PIXEL_HEIGHT => self.with_track(|track| track.height = Some(read_uint(body) as u32)),
+            ID_SIMPLE_BLOCK => {
+                if let Some((frame, flags)) = self.parse_block(body) {
+                    frames.push(MatroskaFrame { keyframe: flags & 0x80 != 0, ..frame });
+                }
+            },
+            ID_BLOCK => {
+                if let Some((frame, _)) = self.parse_block(body) {
+                    self.pending_block = Some(PendingBlock { frame, referenced: false });
+                }
+            },
+            ID_REFERENCE_BLOCK => {
+                if let Some(pending) = &mut self.pending_block {
+                    pending.referenced = true;
+                }
+            },
+            // EBML header, SeekHead, Cues, Tags, Void, ... are skipped
+            _ => {}
+        }
+    }
+
+    fn with_track<F: FnOnce(&mut MatroskaTrack)>(&mut self, update: F) {
+        if let Some(track) = &mut self.current_track {
+            update(track);
+        }
+    }
+
+    /// Parses the block header: track number, relative timecode, flags.
+    /// Laced blocks are returned as a single frame with the raw payload.
+    fn parse_block(&self, body: &[u8]) -> Option<(MatroskaFrame, u8)> {
+        let (track_number, track_len) = read_vint(body, false, 8).ok()?;
+        if body.len() < track_len + 3 {
+            return None;
+        }
+
+        let relative = i16::from_be_bytes([body[track_len], body[track_len + 1]]) as i64;
+        let flags = body[track_len + 2];
+        let timecode = (self.cluster_timecode as i64 + relative).max(0) as u64;
+        let timestamp_ms = timecode.saturating_mul(self.timecode_scale) / 1_000_000;
+
+        Some((MatroskaFrame {
+            track_number,
+            timestamp_ms,
+            keyframe: false,
+            data: body[track_len + 3..].to_vec(),
+        }, flags))
+    }
+}
+
+fn is_master(id: u64) -> bool {
+    matches!(
+        id,
+        ID_SEGMENT | ID_INFO | ID_TRACKS | ID_TRACK_ENTRY | ID_VIDEO | ID_CLUSTER | ID_BLOCK_GROUP
+    )
+}
+
+/// Reads an EBML variable-length integer. IDs keep their marker bit, sizes
+/// don't; an all-ones size means "unknown".
+fn read_vint(data: &[u8], keep_marker: bool, max_len: usize) -> Result<(u64, usize), VintError> {
+    let first = *data.first().ok_or(VintError::Incomplete)?;
+    if first == 0 {
+        return Err(VintError::Invalid);
+    }
+
+    let len = first.leading_zeros() as usize + 1;
+    if len > max_len {
+        return Err(VintError::Invalid);
+    }
+    if data.len() < len {
+        return Err(VintError::Incomplete);
+    }
+
+    let mask = if keep_marker { 0xFF } else { 0xFFu8 >> len };
+    let mut value = (first & mask) as u64;
+    let mut all_ones = first & (0xFFu8 >> len) == 0xFFu8 >> len;
+    for &byte in &data[1..len] {
+        value = (value << 8) | byte as u64;
+        all_ones &= byte == 0xFF;
+    }
+
+    if !keep_marker && all_ones {
+        return Ok((UNKNOWN_SIZE, len));
+    }
+
+    Ok((value, len))
+}
+
+fn read_uint(data: &[u8]) -> u64 {
+    data.iter().take(8).fold(0, |value, &byte| (value << 8) | byte as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_id(id: u64) -> Vec<u8> {
+        let bytes = id.to_be_bytes();
+        let first = bytes.iter().position(|&b| b != 0).unwrap();
+        bytes[first..].to_vec()
+    }
+
+    fn encode_size(size: usize) -> Vec<u8> {
+        // Always 8 bytes, like many muxers do for patched sizes
+        let mut bytes = (size as u64).to_be_bytes();
+        bytes[0] = 0x01;
+        bytes.to_vec()
+    }
+
+    fn element(id: u64, body: &[u8]) -> Vec<u8> {
+        let mut out = encode_id(id);
+        out.extend(encode_size(body.len()));
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn uint_element(id: u64, value: u64) -> Vec<u8> {
+        element(id, &value.to_be_bytes())
+    }
+
+    fn simple_block(track: u8, relative: i16, keyframe: bool, payload: &[u8]) -> Vec<u8> {
+        let mut body = vec![0x80 | track];
+        body.extend_from_slice(&relative.to_be_bytes());
+        body.push(if keyframe { 0x80 } else { 0x00 });
+        body.extend_from_slice(payload);
+        element(ID_SIMPLE_BLOCK, &body)
+    }
+
+    fn cluster(timecode: u64, blocks: &[Vec<u8>]) -> Vec<u8> {
+        let mut body = uint_element(ID_CLUSTER_TIMECODE, timecode);
+        for block in blocks {
+            body.extend_from_slice(block);
+        }
+        element(ID_CLUSTER, &body)
+    }
+
+    /// A small stream shaped like ffmpeg's live matroska output:
+    /// unknown-size Segment, one H.264 video track, two clusters
+    fn recorded_stream() -> Vec<u8> {
+        let mut stream = element(ID_EBML, &element(0x4282, b"matroska"));
+
+        stream.extend(encode_id(ID_SEGMENT));
+        stream.extend([0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+
+        stream.extend(element(ID_INFO, &uint_element(ID_TIMECODE_SCALE, 1_000_000)));
+
+        let mut video = uint_element(ID_PIXEL_WIDTH, 1920);
+        video.extend(uint_element(ID_PIXEL_HEIGHT, 1080));
+        let mut entry = uint_element(ID_TRACK_NUMBER, 1);
+        entry.extend(uint_element(ID_TRACK_TYPE, TRACK_TYPE_VIDEO));
+        entry.extend(element(ID_CODEC_ID, b"V_MPEG4/ISO/AVC"));
+        entry.extend(element(ID_VIDEO, &video));
+        stream.extend(element(ID_TRACKS, &element(ID_TRACK_ENTRY, &entry)));
+
+        stream.extend(cluster(0, &[
+            simple_block(1, 0, true, b"idr-frame"),
+            simple_block(1, 33, false, b"p-frame-1"),
+        ]));
+        stream.extend(cluster(1000, &[
+            simple_block(1, 0, true, b"idr-frame-2"),
+        ]));
+
+        stream
+    }
+
+    #[test]
+    fn test_parses_tracks_and_simple_blocks() {
+        let mut demuxer = MatroskaDemuxer::new();
+        let frames = demuxer.push(&recorded_stream());
+
+        let track = demuxer.video_track().unwrap();
+        assert_eq!(track.width, Some(1920));
+        assert_eq!(track.height, Some(1080));
+        assert_eq!(track.codec_name(), "h264");
+
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0], MatroskaFrame { track_number: 1, timestamp_ms: 0, keyframe: true, data: b"idr-frame".to_vec() });
+        assert_eq!((frames[1].timestamp_ms, frames[1].keyframe), (33, false));
+        assert_eq!((frames[2].timestamp_ms, frames[2].keyframe), (1000, true));
+        assert_eq!(demuxer.buffered_len(), 0);
+    }
+
+    #[test]
+    fn test_frame_data_uses_track_geometry() {
+        let mut demuxer = MatroskaDemuxer::new();
+        let frames = demuxer.push(&recorded_stream());
+
+        let frame = demuxer.to_frame_data(frames[1].clone(), 800, 600).unwrap();
+        assert_eq!((frame.width, frame.height), (1920, 1080));
+        assert_eq!(frame.format, "h264");
+        assert_eq!(frame.timestamp, 33);
+        assert!(!frame.keyframe);
+
+        let audio = MatroskaTrack { number: 2, track_type: 2, ..Default::default() };
+        demuxer.tracks.insert(2, audio);
+        let audio_frame = MatroskaFrame { track_number: 2, timestamp_ms: 0, keyframe: true, data: Vec::new() };
+        assert!(demuxer.to_frame_data(audio_frame, 800, 600).is_none());
+    }
+
+    #[test]
+    fn test_byte_by_byte_input_yields_same_frames() {
+        let stream = recorded_stream();
+        let mut demuxer = MatroskaDemuxer::new();
+
+        let frames: Vec<MatroskaFrame> = stream.chunks(1)
+            .flat_map(|byte| demuxer.push(byte))
+            .collect();
+
+        assert_eq!(frames, MatroskaDemuxer::new().push(&stream));
+    }
+
+    #[test]
+    fn test_block_group_keyframe_from_reference_block() {
+        let mut block_body = vec![0x81];
+        block_body.extend_from_slice(&10i16.to_be_bytes());
+        block_body.push(0x00);
+        block_body.extend_from_slice(b"frame");
+
+        let mut referenced = element(ID_BLOCK, &block_body);
+        referenced.extend(element(ID_REFERENCE_BLOCK, &[0xF0]));
+
+        let stream = cluster(500, &[
+            element(ID_BLOCK_GROUP, &element(ID_BLOCK, &block_body)),
+            element(ID_BLOCK_GROUP, &referenced),
+        ]);
+
+        let frames = MatroskaDemuxer::new().push(&stream);
+        assert_eq!(frames.len(), 2);
+        assert!(frames[0].keyframe);
+        assert!(!frames[1].keyframe);
+        assert_eq!(frames[0].timestamp_ms, 510);
+    }
+
+    #[test]
+    fn test_resyncs_on_next_cluster_after_garbage() {
+        let stream = recorded_stream();
+        let mut demuxer = MatroskaDemuxer::new();
+        demuxer.push(&stream);
+
+        // Corrupt data, then a valid cluster
+        let mut corrupted = vec![0x00, 0x00, 0x13, 0x37];
+        corrupted.extend(cluster(2000, &[simple_block(1, 0, true, b"after-resync")]));
+
+        let frames = demuxer.push(&corrupted);
+        assert_eq!(demuxer.resync_count(), 1);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].data, b"after-resync".to_vec());
+        assert_eq!(frames[0].timestamp_ms, 2000);
+    }
+}
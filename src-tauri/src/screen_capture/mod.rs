@@ -3,11 +3,38 @@
 pub mod types;
 pub mod error;
 pub mod config;
+pub mod validation;
 pub mod manager;
 pub mod buffer;
+pub mod stats_history;
+pub mod matroska;
+pub mod frame_stream;
+pub mod clock;
+pub mod cursor;
+pub mod recording_cursor;
+pub mod pointer_feed;
+pub mod encoders;
+pub mod backend;
+#[cfg(feature = "gstreamer-backend")]
+pub mod gst_pipeline;
 pub mod quality;
+pub mod idle;
+pub mod scale;
+pub mod viewport;
+pub mod thumbnail;
+pub mod privacy;
+pub mod damage;
+pub mod keyframe;
+pub mod gpu_stats;
 pub mod x11;
 pub mod wayland;
+pub mod portal;
+pub mod pipewire;
+pub mod supervisor;
+pub mod watchdog;
+pub mod resume;
+pub mod power_profile;
+pub mod virtual_display;
 pub mod utils;
 
 // Re-export the main components for easier access
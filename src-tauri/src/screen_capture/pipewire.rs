@@ -0,0 +1,172 @@
+// screen_capture/pipewire.rs - PipeWire capture source enumeration for Wayland
+
+use serde::{Deserialize, Serialize};
+
+use crate::command_runner::CommandRunner;
+use crate::screen_capture::config::PipeWireNodeSelection;
+use crate::screen_capture::error::ScreenCaptureError;
+
+/// What a PipeWire video source shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipeWireSourceKind {
+    Monitor,
+    Window,
+}
+
+/// A PipeWire video node that can be captured
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipeWireSource {
+    /// Node id passed to ffmpeg; changes when the compositor restarts
+    pub node_id: u32,
+
+    /// Stable node name, used to find the node again after a restart
+    pub name: String,
+
+    /// Human readable description, if the node has one
+    pub description: Option<String>,
+
+    pub kind: PipeWireSourceKind,
+}
+
+/// List the capturable video sources of the running PipeWire daemon
+pub fn list_sources(runner: &dyn CommandRunner) -> Result<Vec<PipeWireSource>, ScreenCaptureError> {
+    let output = runner.command("pw-dump")
+        .output()
+        .map_err(|e| ScreenCaptureError::PipeWireError(format!("Failed to run pw-dump: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(ScreenCaptureError::PipeWireError(format!(
+            "pw-dump failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    parse_pw_dump(&output.stdout)
+}
+
+/// Extract the screen and window sources from pw-dump's JSON output.
+/// Cameras are video sources as well and are skipped.
+pub fn parse_pw_dump(output: &[u8]) -> Result<Vec<PipeWireSource>, ScreenCaptureError> {
+    let objects: Vec<serde_json::Value> = serde_json::from_slice(output)
+        .map_err(|e| ScreenCaptureError::PipeWireError(format!("Failed to parse pw-dump output: {}", e)))?;
+
+    let mut sources = Vec::new();
+    for object in &objects {
+        if object.get("type").and_then(|v| v.as_str()) != Some("PipeWire:Interface:Node") {
+            continue;
+        }
+
+        let node_id = match object.get("id").and_then(|v| v.as_u64()) {
+            Some(id) => id as u32,
+            None => continue,
+        };
+
+        let props = match object.pointer("/info/props").and_then(|v| v.as_object()) {
+            Some(props) => props,
+            None => continue,
+        };
+        let prop = |key: &str| props.get(key).and_then(|v| v.as_str());
+
+        if prop("media.class") != Some("Video/Source") {
+            continue;
+        }
+        if prop("device.api").is_some() || prop("media.role") == Some("Camera") {
+            continue;
+        }
+
+        let name = match prop("node.name") {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let description = prop("node.description")
+            .or_else(|| prop("media.name"))
+            .map(str::to_string);
+
+        // PipeWire has no property for the source type; portals and compositors
+        // name window streams accordingly
+        let is_window = [Some(name.as_str()), description.as_deref(), prop("media.name")]
+            .iter()
+            .flatten()
+            .any(|value| value.to_lowercase().contains("window"));
+
+        sources.push(PipeWireSource {
+            node_id,
+            name,
+            description,
+            kind: if is_window { PipeWireSourceKind::Window } else { PipeWireSourceKind::Monitor },
+        });
+    }
+
+    Ok(sources)
+}
+
+/// Node id to capture for a selection. A node id that no longer belongs to the
+/// selected node, e.g. after a compositor restart, is looked up again by name.
+pub fn resolve_node(selection: &PipeWireNodeSelection, sources: &[PipeWireSource]) -> Result<u32, ScreenCaptureError> {
+    if sources.iter().any(|s| s.node_id == selection.node_id && s.name == selection.name) {
+        return Ok(selection.node_id);
+    }
+
+    sources.iter()
+        .find(|s| s.name == selection.name)
+        .map(|s| s.node_id)
+        .ok_or_else(|| ScreenCaptureError::PipeWireError(format!(
+            "PipeWire source '{}' (node {}) is no longer available",
+            selection.name, selection.node_id
+        )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PW_DUMP: &str = r#"[
+        { "id": 0, "type": "PipeWire:Interface:Core", "info": { "props": {} } },
+        { "id": 42, "type": "PipeWire:Interface:Node", "info": { "props": {
+            "media.class": "Video/Source", "node.name": "xdpw-stream-DP-1", "node.description": "DP-1" } } },
+        { "id": 57, "type": "PipeWire:Interface:Node", "info": { "props": {
+            "media.class": "Video/Source", "node.name": "kwin-window-firefox", "media.name": "Window: Firefox" } } },
+        { "id": 60, "type": "PipeWire:Interface:Node", "info": { "props": {
+            "media.class": "Video/Source", "node.name": "v4l2_input.webcam", "device.api": "v4l2" } } },
+        { "id": 61, "type": "PipeWire:Interface:Node", "info": { "props": {
+            "media.class": "Audio/Sink", "node.name": "alsa_output.speakers" } } }
+    ]"#;
+
+    #[test]
+    fn test_parse_pw_dump() {
+        let sources = parse_pw_dump(PW_DUMP.as_bytes()).unwrap();
+
+        assert_eq!(sources, vec![
+            PipeWireSource {
+                node_id: 42,
+                name: "xdpw-stream-DP-1".to_string(),
+                description: Some("DP-1".to_string()),
+                kind: PipeWireSourceKind::Monitor,
+            },
+            PipeWireSource {
+                node_id: 57,
+                name: "kwin-window-firefox".to_string(),
+                description: Some("Window: Firefox".to_string()),
+                kind: PipeWireSourceKind::Window,
+            },
+        ]);
+
+        assert!(parse_pw_dump(b"not json").is_err());
+    }
+
+    #[test]
+    fn test_stale_node_is_resolved_by_name() {
+        let sources = parse_pw_dump(PW_DUMP.as_bytes()).unwrap();
+
+        let current = PipeWireNodeSelection { node_id: 42, name: "xdpw-stream-DP-1".to_string() };
+        assert_eq!(resolve_node(&current, &sources).unwrap(), 42);
+
+        // After a compositor restart the id belongs to another node or to none
+        let stale = PipeWireNodeSelection { node_id: 57, name: "xdpw-stream-DP-1".to_string() };
+        assert_eq!(resolve_node(&stale, &sources).unwrap(), 42);
+
+        let gone = PipeWireNodeSelection { node_id: 42, name: "xdpw-stream-HDMI-1".to_string() };
+        assert!(matches!(resolve_node(&gone, &sources), Err(ScreenCaptureError::PipeWireError(_))));
+    }
+}
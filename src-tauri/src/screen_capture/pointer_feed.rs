@@ -0,0 +1,92 @@
+// screen_capture/pointer_feed.rs - Forwarded pointer input for the capture side
+//
+// Every forwarded pointer position feeds the client-side cursor, and every
+// click marks the cursor tracks of recordings. Going through the capture
+// manager for that would make input wait whenever a capture is started,
+// restarted or reconfigured, which takes as long as ffmpeg needs to come up.
+// The feed is shared instead: the manager keeps the monitor layout and the
+// running cursor tracks in it up to date, and the input path only ever takes
+// the feed's own short locks.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::screen_capture::cursor::ReportedCursorSource;
+use crate::screen_capture::recording_cursor::{CursorTrack, CursorTrackRecording};
+use crate::screen_capture::types::MonitorInfo;
+
+/// Monitors as the input forwarder sees them
+#[derive(Debug, Clone, Default)]
+struct PointerLayout {
+    /// Input monitors, narrowed to their capture regions
+    monitors: Vec<MonitorInfo>,
+
+    /// Monitor of events without an index: the first captured one
+    default_index: usize,
+}
+
+/// Shared between the capture manager and the input path
+#[derive(Clone, Default)]
+pub struct PointerFeed {
+    cursor: ReportedCursorSource,
+    layout: Arc<Mutex<PointerLayout>>,
+    tracks: Arc<Mutex<HashMap<usize, CursorTrackRecording>>>,
+}
+
+impl PointerFeed {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the layout after monitors or captures changed
+    pub fn set_layout(&self, monitors: Vec<MonitorInfo>, default_index: usize) {
+        *self.layout.lock().unwrap() = PointerLayout { monitors, default_index };
+    }
+
+    /// Input monitors as last set by the capture manager
+    pub fn input_monitors(&self) -> Vec<MonitorInfo> {
+        self.layout.lock().unwrap().monitors.clone()
+    }
+
+    /// Positions for the client-side cursor where the pointer cannot be queried
+    pub fn cursor_source(&self) -> ReportedCursorSource {
+        self.cursor.clone()
+    }
+
+    /// Record a forwarded pointer position (relative to `monitor_index`, or
+    /// the first captured monitor)
+    pub fn report_position(&self, x: i32, y: i32, monitor_index: Option<usize>) {
+        if let Some((x, y)) = self.to_desktop(x, y, monitor_index) {
+            self.cursor.report(x, y);
+        }
+    }
+
+    /// Mark a forwarded click on every cursor track being recorded. Without
+    /// a position the click lands where the cursor was last seen.
+    pub fn report_click(&self, position: Option<(i32, i32)>, monitor_index: Option<usize>) {
+        let desktop_position = position.and_then(|(x, y)| self.to_desktop(x, y, monitor_index));
+        for track in self.tracks.lock().unwrap().values() {
+            track.record_click(desktop_position);
+        }
+    }
+
+    /// Keep a started cursor track of a monitor; one already running there is dropped
+    pub fn start_track(&self, monitor_index: usize, recording: CursorTrackRecording) {
+        let previous = self.tracks.lock().unwrap().insert(monitor_index, recording);
+        if let Some(previous) = previous {
+            previous.finish();
+        }
+    }
+
+    /// Stop the cursor track of a monitor and return it
+    pub fn finish_track(&self, monitor_index: usize) -> Option<CursorTrack> {
+        let recording = self.tracks.lock().unwrap().remove(&monitor_index);
+        recording.map(CursorTrackRecording::finish)
+    }
+
+    fn to_desktop(&self, x: i32, y: i32, monitor_index: Option<usize>) -> Option<(i32, i32)> {
+        let layout = self.layout.lock().unwrap();
+        let index = monitor_index.unwrap_or(layout.default_index);
+        layout.monitors.get(index).map(|monitor| (x + monitor.x_offset, y + monitor.y_offset))
+    }
+}
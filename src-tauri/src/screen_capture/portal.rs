@@ -0,0 +1,200 @@
+// screen_capture/portal.rs - xdg-desktop-portal ScreenCast integration for GNOME/KDE
+
+use std::os::fd::{AsRawFd, OwnedFd, RawFd};
+use std::time::Duration;
+
+use ashpd::desktop::screencast::{CursorMode as PortalCursorMode, PersistMode, Screencast, SourceType};
+use ashpd::desktop::{ResponseError, Session};
+use ashpd::WindowIdentifier;
+
+use crate::screen_capture::error::ScreenCaptureError;
+use crate::screen_capture::types::{CursorMode, MonitorInfo, MonitorRotation};
+
+/// How long to wait for the user to answer the portal's consent dialog
+const CONSENT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Name prefix used for monitors that are resolved through the portal
+pub const PORTAL_MONITOR_PREFIX: &str = "portal:";
+
+/// A single PipeWire stream granted by the portal
+#[derive(Debug, Clone)]
+pub struct PortalStream {
+    /// PipeWire node id of the stream
+    pub node_id: u32,
+
+    /// Position of the source in the compositor's logical space, if known
+    pub position: Option<(i32, i32)>,
+
+    /// Size of the source, if known
+    pub size: Option<(i32, i32)>,
+}
+
+/// An active ScreenCast session. Dropping it closes the session on the portal side.
+pub struct PortalSession {
+    proxy: Screencast<'static>,
+    session: Session<'static>,
+    pipewire_fd: OwnedFd,
+    streams: Vec<PortalStream>,
+}
+
+impl PortalSession {
+    /// Ask the portal for a screen source. This shows the compositor's consent dialog
+    /// and blocks until the user picks a source, cancels, or the timeout expires.
+    pub fn open(cursor_mode: CursorMode) -> Result<Self, ScreenCaptureError> {
+        tauri::async_runtime::block_on(async {
+            tokio::time::timeout(CONSENT_TIMEOUT, Self::open_async(cursor_mode))
+                .await
+                .map_err(|_| ScreenCaptureError::PortalError(
+                    "Timed out waiting for screen sharing consent".to_string()
+                ))?
+        })
+    }
+
+    async fn open_async(cursor_mode: CursorMode) -> Result<Self, ScreenCaptureError> {
+        let proxy = Screencast::new().await.map_err(to_portal_error)?;
+        let session = proxy.create_session().await.map_err(to_portal_error)?;
+
+        // A client-side cursor is drawn by the frontend, so the stream has none
+        let cursor_mode = match cursor_mode {
+            CursorMode::Embedded => PortalCursorMode::Embedded,
+            CursorMode::Hidden | CursorMode::ClientSide => PortalCursorMode::Hidden,
+        };
+
+        proxy
+            .select_sources(
+                &session,
+                cursor_mode,
+                SourceType::Monitor | SourceType::Window,
+                false,
+                None,
+                PersistMode::DoNot,
+            )
+            .await
+            .map_err(to_portal_error)?;
+
+        let response = proxy
+            .start(&session, &WindowIdentifier::default())
+            .await
+            .map_err(to_portal_error)?
+            .response()
+            .map_err(to_portal_error)?;
+
+        let streams: Vec<PortalStream> = response
+            .streams()
+            .iter()
+            .map(|stream| PortalStream {
+                node_id: stream.pipe_wire_node_id(),
+                position: stream.position(),
+                size: stream.size(),
+            })
+            .collect();
+
+        if streams.is_empty() {
+            return Err(ScreenCaptureError::PortalError(
+                "Portal returned no streams".to_string()
+            ));
+        }
+
+        let pipewire_fd = proxy
+            .open_pipe_wire_remote(&session)
+            .await
+            .map_err(to_portal_error)?;
+
+        Ok(PortalSession {
+            proxy,
+            session,
+            pipewire_fd,
+            streams,
+        })
+    }
+
+    /// Streams granted by the user
+    pub fn streams(&self) -> &[PortalStream] {
+        &self.streams
+    }
+
+    /// File descriptor of the PipeWire remote. It stays close-on-exec; a child
+    /// process that needs it (gst-launch) gets its own copy when it is spawned.
+    pub fn pipewire_fd(&self) -> RawFd {
+        self.pipewire_fd.as_raw_fd()
+    }
+
+    /// Close the session explicitly
+    pub fn close(self) {
+        let _ = tauri::async_runtime::block_on(self.session.close());
+        drop(self.proxy);
+    }
+}
+
+/// Enumerate sources for portal-based desktops.
+///
+/// The portal does not reveal individual outputs before the user has consented, so
+/// every available source type becomes one selectable entry; the real geometry is
+/// filled in from the stream once a session has been started.
+pub fn get_portal_monitors() -> Result<Vec<MonitorInfo>, ScreenCaptureError> {
+    let source_types = tauri::async_runtime::block_on(async {
+        let proxy = Screencast::new().await.map_err(to_portal_error)?;
+        proxy.available_source_types().await.map_err(to_portal_error)
+    })?;
+
+    let mut monitors = Vec::new();
+
+    if source_types.contains(SourceType::Monitor) {
+        monitors.push(portal_monitor(monitors.len(), "monitor", true));
+    }
+
+    if source_types.contains(SourceType::Window) {
+        monitors.push(portal_monitor(monitors.len(), "window", false));
+    }
+
+    if monitors.is_empty() {
+        return Err(ScreenCaptureError::PortalError(
+            "Portal offers no screen sources".to_string()
+        ));
+    }
+
+    Ok(monitors)
+}
+
+fn portal_monitor(index: usize, kind: &str, primary: bool) -> MonitorInfo {
+    MonitorInfo {
+        index,
+        name: format!("{}{}", PORTAL_MONITOR_PREFIX, kind),
+        width: 1920,  // Replaced by the stream size after consent
+        height: 1080, // Replaced by the stream size after consent
+        refresh_rate: None,
+        primary,
+        x_offset: 0,
+        y_offset: 0,
+        scale_factor: 1.0,
+        rotation: MonitorRotation::Normal,
+    }
+}
+
+/// Map portal errors, turning a dismissed consent dialog into its own variant
+fn to_portal_error(e: ashpd::Error) -> ScreenCaptureError {
+    match e {
+        ashpd::Error::Response(ResponseError::Cancelled) => ScreenCaptureError::ConsentDenied(
+            "Screen sharing request was cancelled by the user".to_string()
+        ),
+        other => ScreenCaptureError::PortalError(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_a_cancelled_dialog_is_consent_denied() {
+        assert!(matches!(
+            to_portal_error(ashpd::Error::Response(ResponseError::Cancelled)),
+            ScreenCaptureError::ConsentDenied(_)
+        ));
+        assert!(matches!(
+            to_portal_error(ashpd::Error::Response(ResponseError::Other)),
+            ScreenCaptureError::PortalError(_)
+        ));
+        assert!(matches!(to_portal_error(ashpd::Error::NoResponse), ScreenCaptureError::PortalError(_)));
+    }
+}
@@ -0,0 +1,259 @@
+// screen_capture/power_profile.rs - Capture limits while the host runs on battery
+//
+// Three layers decide what a capture encodes. The user's configuration is the
+// starting point. The limits of the current power profile are ceilings on top
+// of it: they lower the frame rate and bitrate but never raise them, and they
+// are lifted again on AC. The adaptive quality controller only moves below
+// those ceilings. `ignore_power_profile` drops the middle layer.
+
+use serde::Serialize;
+
+use crate::power::PowerSource;
+use crate::screen_capture::config::{PowerProfileLimits, PowerProfilesConfig, ScreenCaptureConfig};
+
+/// Power state a capture is limited for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PowerProfile {
+    #[default]
+    Ac,
+    Battery,
+    LowBattery,
+}
+
+impl PowerProfile {
+    pub fn for_source(source: &PowerSource, profiles: &PowerProfilesConfig) -> Self {
+        if !source.on_battery {
+            return PowerProfile::Ac;
+        }
+        match source.battery_percent {
+            Some(percent) if percent <= profiles.low_battery_percent => PowerProfile::LowBattery,
+            _ => PowerProfile::Battery,
+        }
+    }
+}
+
+/// Limits of `profile` for a capture; none on AC or when the capture ignores
+/// power profiles. On a low battery the stricter of both profiles applies.
+pub fn limits_for(config: &ScreenCaptureConfig, profile: PowerProfile) -> PowerProfileLimits {
+    if config.ignore_power_profile {
+        return PowerProfileLimits::default();
+    }
+    
+    let profiles = &config.power_profiles;
+    match profile {
+        PowerProfile::Ac => PowerProfileLimits::default(),
+        PowerProfile::Battery => profiles.battery.clone(),
+        PowerProfile::LowBattery => PowerProfileLimits {
+            max_fps: min_limit(profiles.battery.max_fps, profiles.low_battery.max_fps),
+            max_bitrate_kbps: min_limit(profiles.battery.max_bitrate_kbps, profiles.low_battery.max_bitrate_kbps),
+            forbid_software_fallback: profiles.battery.forbid_software_fallback || profiles.low_battery.forbid_software_fallback,
+        },
+    }
+}
+
+/// Sets the limited settings of `effective` from the user's configuration
+/// capped by `limits`. Everything else in `effective` is left alone, e.g. a
+/// hardware accelerator the supervisor disabled after a failure.
+pub fn apply_limits(user: &ScreenCaptureConfig, limits: &PowerProfileLimits, effective: &mut ScreenCaptureConfig) {
+    effective.fps = limits.max_fps.map_or(user.fps, |max_fps| user.fps.min(max_fps.max(1)));
+    effective.bitrate = match (user.bitrate, limits.max_bitrate_kbps) {
+        (Some(bitrate), Some(max_bitrate)) => Some(bitrate.min(max_bitrate)),
+        (bitrate, _) => bitrate,
+    };
+    effective.software_fallback = user.software_fallback && !limits.forbid_software_fallback;
+}
+
+/// How one capture changed with the power profile, sent with
+/// `power_profile_changed`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerProfileChange {
+    pub monitor_index: usize,
+    pub profile: PowerProfile,
+    pub previous: PowerProfile,
+
+    /// Frame rate encoded from now on
+    pub fps: u32,
+
+    /// Frame rate in the capture's configuration
+    pub configured_fps: u32,
+
+    /// Ceiling for the bitrate, including the adaptive quality controller
+    pub max_bitrate_kbps: Option<u32>,
+
+    /// Whether a failing hardware encoder may still fall back to software
+    pub software_fallback: bool,
+
+    /// The capture keeps its settings (`ignore_power_profile`)
+    pub ignored: bool,
+
+    /// Human-readable summary for notifications and logs
+    pub explanation: String,
+}
+
+impl PowerProfileChange {
+    pub fn new(
+        monitor_index: usize,
+        previous: PowerProfile,
+        profile: PowerProfile,
+        source: &PowerSource,
+        user: &ScreenCaptureConfig,
+        limits: &PowerProfileLimits,
+    ) -> Self {
+        let mut effective = user.clone();
+        apply_limits(user, limits, &mut effective);
+
+        let state = match (profile, source.battery_percent) {
+            (PowerProfile::Ac, _) => "On AC power".to_string(),
+            (_, Some(percent)) => format!("On battery at {}%", percent),
+            (_, None) => "On battery".to_string(),
+        };
+        let mut adjustments = Vec::new();
+        if effective.fps < user.fps {
+            adjustments.push(format!("capped at {} fps (configured {})", effective.fps, user.fps));
+        }
+        if let Some(max_bitrate) = limits.max_bitrate_kbps {
+            adjustments.push(format!("bitrate at most {} Kbps", max_bitrate));
+        }
+        if user.software_fallback && !effective.software_fallback {
+            adjustments.push("no software encoding fallback".to_string());
+        }
+        let explanation = if profile != PowerProfile::Ac && user.ignore_power_profile {
+            format!("{}: power profile ignored, configured settings kept", state)
+        } else if adjustments.is_empty() {
+            format!("{}: configured settings apply", state)
+        } else {
+            format!("{}: {}", state, adjustments.join(", "))
+        };
+
+        PowerProfileChange {
+            monitor_index,
+            profile,
+            previous,
+            fps: effective.fps,
+            configured_fps: user.fps,
+            max_bitrate_kbps: limits.max_bitrate_kbps,
+            software_fallback: effective.software_fallback,
+            ignored: user.ignore_power_profile,
+            explanation,
+        }
+    }
+}
+
+fn min_limit(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen_capture::quality::{AdaptiveQualityController, QualityAdapterConfig};
+
+    const AC: PowerSource = PowerSource { on_battery: false, battery_percent: Some(100) };
+    const BATTERY: PowerSource = PowerSource { on_battery: true, battery_percent: Some(60) };
+    const LOW_BATTERY: PowerSource = PowerSource { on_battery: true, battery_percent: Some(12) };
+
+    fn user_config() -> ScreenCaptureConfig {
+        ScreenCaptureConfig { fps: 60, bitrate: Some(8000), ..ScreenCaptureConfig::default() }
+    }
+
+    fn effective(user: &ScreenCaptureConfig, source: &PowerSource) -> ScreenCaptureConfig {
+        let profile = PowerProfile::for_source(source, &user.power_profiles);
+        let mut effective = user.clone();
+        apply_limits(user, &limits_for(user, profile), &mut effective);
+        effective
+    }
+
+    #[test]
+    fn test_profile_caps_user_config_and_is_lifted_on_ac() {
+        let user = user_config();
+        let profiles = &user.power_profiles;
+        assert_eq!(PowerProfile::for_source(&AC, profiles), PowerProfile::Ac);
+        assert_eq!(PowerProfile::for_source(&BATTERY, profiles), PowerProfile::Battery);
+        assert_eq!(PowerProfile::for_source(&LOW_BATTERY, profiles), PowerProfile::LowBattery);
+        assert_eq!(PowerProfile::for_source(&PowerSource { on_battery: true, battery_percent: None }, profiles), PowerProfile::Battery);
+
+        let on_battery = effective(&user, &BATTERY);
+        assert_eq!((on_battery.fps, on_battery.bitrate, on_battery.software_fallback), (30, Some(8000), true));
+
+        // The low battery profile adds to the battery profile
+        let low = effective(&user, &LOW_BATTERY);
+        assert_eq!((low.fps, low.bitrate, low.software_fallback), (15, Some(2000), false));
+
+        // Back on AC the user's values return, not the limited ones
+        let mut restored = low.clone();
+        apply_limits(&user, &limits_for(&user, PowerProfile::Ac), &mut restored);
+        assert_eq!((restored.fps, restored.bitrate, restored.software_fallback), (60, Some(8000), true));
+    }
+
+    #[test]
+    fn test_limits_never_raise_user_settings() {
+        let user = ScreenCaptureConfig {
+            fps: 10,
+            bitrate: Some(800),
+            software_fallback: false,
+            ..ScreenCaptureConfig::default()
+        };
+        let low = effective(&user, &LOW_BATTERY);
+        assert_eq!((low.fps, low.bitrate, low.software_fallback), (10, Some(800), false));
+
+        // Without a configured bitrate the quality controller gets the ceiling
+        let crf = ScreenCaptureConfig { bitrate: None, ..user_config() };
+        assert_eq!(effective(&crf, &LOW_BATTERY).bitrate, None);
+        assert_eq!(limits_for(&crf, PowerProfile::LowBattery).max_bitrate_kbps, Some(2000));
+    }
+
+    #[test]
+    fn test_ignore_power_profile_keeps_settings() {
+        let user = ScreenCaptureConfig { ignore_power_profile: true, ..user_config() };
+        let low = effective(&user, &LOW_BATTERY);
+        assert_eq!((low.fps, low.bitrate, low.software_fallback), (60, Some(8000), true));
+
+        let change = PowerProfileChange::new(0, PowerProfile::Ac, PowerProfile::LowBattery, &LOW_BATTERY, &user, &limits_for(&user, PowerProfile::LowBattery));
+        assert!(change.ignored);
+        assert_eq!(change.fps, 60);
+        assert_eq!(change.explanation, "On battery at 12%: power profile ignored, configured settings kept");
+    }
+
+    #[test]
+    fn test_change_explains_adjustment() {
+        let user = user_config();
+        let limits = limits_for(&user, PowerProfile::LowBattery);
+        let change = PowerProfileChange::new(1, PowerProfile::Battery, PowerProfile::LowBattery, &LOW_BATTERY, &user, &limits);
+        assert_eq!((change.fps, change.configured_fps, change.max_bitrate_kbps), (15, 60, Some(2000)));
+        assert_eq!(
+            change.explanation,
+            "On battery at 12%: capped at 15 fps (configured 60), bitrate at most 2000 Kbps, no software encoding fallback"
+        );
+
+        let change = PowerProfileChange::new(1, PowerProfile::LowBattery, PowerProfile::Ac, &AC, &user, &limits_for(&user, PowerProfile::Ac));
+        assert_eq!(change.explanation, "On AC power: configured settings apply");
+        assert_eq!(serde_json::to_value(&change).unwrap()["previous"], "lowBattery");
+    }
+
+    #[test]
+    fn test_adaptive_controller_stays_below_ceiling() {
+        let config = QualityAdapterConfig { min_adjustment_interval_ms: 0, ..Default::default() };
+        let mut controller = AdaptiveQualityController::new(90, Some(config));
+        controller.set_bitrate_ceiling(limits_for(&user_config(), PowerProfile::LowBattery).max_bitrate_kbps);
+
+        // Plenty of headroom still does not lift the bitrate over the ceiling
+        controller.update_metrics(10.0, 50_000, 0.0, 50);
+        controller.adjust_quality();
+        assert_eq!(controller.get_bitrate_for_resolution(1920, 1080), 2000);
+
+        let user = user_config();
+        let params = controller.generate_ffmpeg_params(&effective(&user, &LOW_BATTERY));
+        let value_of = |flag: &str| params.iter().position(|param| param == flag).map(|index| params[index + 1].clone());
+        assert_eq!(value_of("-b:v").as_deref(), Some("2000k"));
+        assert_eq!(value_of("-maxrate").as_deref(), Some("2000k"));
+
+        controller.set_bitrate_ceiling(None);
+        let params = controller.generate_ffmpeg_params(&user);
+        assert!(!params.contains(&"-maxrate".to_string()));
+    }
+}
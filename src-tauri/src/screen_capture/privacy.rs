@@ -0,0 +1,600 @@
+// screen_capture/privacy.rs - Privacy masks blacked out or pixelated in the outgoing stream
+//
+// Masks are monitor-relative rectangles. They are drawn into the captured
+// frames before any scaling, so they shrink with the stream and nothing under
+// them ever reaches the encoder. Windows can be masked by class as well: a
+// watcher polls their geometry and restarts the encoder when one moves.
+
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::command_runner::CommandRunner;
+use crate::screen_capture::config::ScreenCaptureConfig;
+use crate::screen_capture::error::ScreenCaptureError;
+use crate::screen_capture::keyframe::KeyframeScheduler;
+use crate::screen_capture::types::{CaptureRegion, MonitorInfo};
+use crate::logging::TARGET_SCREEN_CAPTURE;
+
+/// Edge length of a pixelation block in the encoded stream
+pub const PIXELATE_BLOCK_SIZE: u32 = 16;
+
+/// How often the geometry of masked windows is checked
+const WINDOW_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How a masked area is rendered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskStyle {
+    /// Solid black box
+    #[default]
+    Black,
+
+    /// Coarse blocks of `PIXELATE_BLOCK_SIZE` stream pixels; rendered black
+    /// on the GStreamer portal pipeline
+    Pixelate,
+}
+
+/// Rectangle hidden from viewers, relative to the monitor's top-left corner.
+/// Parts outside the monitor or the capture region are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrivacyMask {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub style: MaskStyle,
+}
+
+/// A mask added with `add_privacy_mask`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrivacyMaskEntry {
+    /// Id `remove_privacy_mask` takes, unique per monitor
+    pub id: u32,
+    #[serde(flatten)]
+    pub mask: PrivacyMask,
+}
+
+/// Privacy masks of one capture
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrivacyConfig {
+    /// Fixed masks
+    pub masks: Vec<PrivacyMaskEntry>,
+
+    /// Regular expressions; visible windows whose class matches one are masked
+    /// wherever they are moved. X11 and Hyprland only.
+    pub window_classes: Vec<String>,
+
+    /// How matched windows are rendered
+    pub window_style: MaskStyle,
+
+    /// Masks of the matched windows, kept up to date by the window watcher
+    #[serde(skip)]
+    pub window_masks: Vec<PrivacyMask>,
+}
+
+/// Returned by `list_privacy_masks`
+#[derive(Debug, Clone, Serialize)]
+pub struct PrivacyMasks {
+    pub masks: Vec<PrivacyMaskEntry>,
+    /// Masks currently covering windows matched by `window_classes`
+    pub window_masks: Vec<PrivacyMask>,
+}
+
+impl PrivacyMask {
+    /// Checks that the mask has an area and covers part of the monitor
+    pub fn validate(&self, monitor: &MonitorInfo) -> Result<(), ScreenCaptureError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(ScreenCaptureError::InvalidRegion(format!(
+                "Privacy mask {}x{} is empty", self.width, self.height
+            )));
+        }
+
+        if self.clamp(&CaptureRegion::full(monitor)).is_none() {
+            return Err(ScreenCaptureError::InvalidRegion(format!(
+                "Privacy mask {}x{}{:+}{:+} lies outside monitor {} ({}x{})",
+                self.width, self.height, self.x, self.y, monitor.name, monitor.width, monitor.height
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// The part of the mask inside `region`, relative to the region's
+    /// top-left corner; None if they do not overlap
+    pub fn clamp(&self, region: &CaptureRegion) -> Option<CaptureRegion> {
+        let left = (self.x as i64).max(region.x as i64);
+        let top = (self.y as i64).max(region.y as i64);
+        let right = (self.x as i64 + self.width as i64).min(region.x as i64 + region.width as i64);
+        let bottom = (self.y as i64 + self.height as i64).min(region.y as i64 + region.height as i64);
+
+        if right <= left || bottom <= top {
+            return None;
+        }
+
+        Some(CaptureRegion {
+            x: (left - region.x as i64) as u32,
+            y: (top - region.y as i64) as u32,
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+        })
+    }
+}
+
+impl PrivacyConfig {
+    /// Fixed and window masks, clamped to `region` and relative to it
+    fn clamped(&self, region: &CaptureRegion) -> Vec<(CaptureRegion, MaskStyle)> {
+        self.masks.iter().map(|entry| &entry.mask)
+            .chain(self.window_masks.iter())
+            .filter_map(|mask| mask.clamp(region).map(|area| (area, mask.style)))
+            .collect()
+    }
+
+    /// Id for the next added mask
+    pub fn next_id(&self) -> u32 {
+        self.masks.iter().map(|entry| entry.id + 1).max().unwrap_or(1)
+    }
+
+    /// The window class patterns, compiled
+    pub fn window_patterns(&self) -> Result<Vec<Regex>, ScreenCaptureError> {
+        self.window_classes.iter()
+            .map(|pattern| Regex::new(pattern).map_err(|e| ScreenCaptureError::InvalidConfig(format!(
+                "Invalid window class pattern {:?}: {}", pattern, e
+            ))))
+            .collect()
+    }
+}
+
+/// ffmpeg filters that draw the masks into frames of the captured `region`.
+/// They need software frames and go before the scale filter; `output_width`
+/// is the encoded width, so pixelation blocks keep their size in the stream.
+pub fn ffmpeg_mask_filters(privacy: &PrivacyConfig, region: &CaptureRegion, output_width: u32) -> Vec<String> {
+    let block = (PIXELATE_BLOCK_SIZE as u64 * region.width as u64).div_ceil(output_width.max(1) as u64).max(1) as u32;
+
+    privacy.clamped(region).into_iter().enumerate()
+        .map(|(i, (area, style))| match style {
+            MaskStyle::Black => format!(
+                "drawbox=x={}:y={}:w={}:h={}:color=black:t=fill",
+                area.x, area.y, area.width, area.height
+            ),
+            // Shrink a copy of the area and blow it up again without interpolation
+            MaskStyle::Pixelate => format!(
+                "split[pm{i}a][pm{i}b];[pm{i}b]crop={w}:{h}:{x}:{y},scale={sw}:{sh}:flags=neighbor,scale={w}:{h}:flags=neighbor[pm{i}p];[pm{i}a][pm{i}p]overlay={x}:{y}",
+                i = i, x = area.x, y = area.y, w = area.width, h = area.height,
+                sw = area.width.div_ceil(block), sh = area.height.div_ceil(block),
+            ),
+        })
+        .collect()
+}
+
+/// GStreamer elements that black out the masks in frames of the captured
+/// `region`: a compositor inserted into the pipeline, and one black source
+/// per mask that has to be appended after the pipeline's sink
+pub fn gstreamer_mask_elements(privacy: &PrivacyConfig, region: &CaptureRegion, fps: u32) -> (Vec<String>, Vec<String>) {
+    let areas = privacy.clamped(region);
+    if areas.is_empty() {
+        return (Vec::new(), Vec::new());
+    }
+
+    // The captured stream is linked first and becomes sink_0
+    let mut inline = vec!["compositor".to_string(), "name=privacy".to_string()];
+    let mut sources = Vec::new();
+    for (i, (area, _)) in areas.iter().enumerate() {
+        inline.push(format!("sink_{}::xpos={}", i + 1, area.x));
+        inline.push(format!("sink_{}::ypos={}", i + 1, area.y));
+
+        sources.extend([
+            "videotestsrc".to_string(),
+            "pattern=black".to_string(),
+            "is-live=true".to_string(),
+            "!".to_string(),
+            format!("video/x-raw,width={},height={},framerate={}/1", area.width, area.height, fps),
+            "!".to_string(),
+            "privacy.".to_string(),
+        ]);
+    }
+    inline.push("!".to_string());
+
+    (inline, sources)
+}
+
+/// A window in desktop coordinates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Lists the visible windows whose class matches a pattern
+pub trait WindowSource: Send {
+    /// None if the windows could not be queried; the last masks stay then
+    fn matching_windows(&mut self, patterns: &[Regex]) -> Option<Vec<WindowGeometry>>;
+
+    /// Whether positions are in the compositor's scaled layout rather than pixels
+    fn logical_coordinates(&self) -> bool {
+        false
+    }
+}
+
+/// Windows on X11, found with `xdotool search --class`
+pub struct X11WindowSource {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl X11WindowSource {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        X11WindowSource { runner }
+    }
+}
+
+impl WindowSource for X11WindowSource {
+    fn matching_windows(&mut self, patterns: &[Regex]) -> Option<Vec<WindowGeometry>> {
+        let mut windows = Vec::new();
+
+        for pattern in patterns {
+            // Search and geometry in one call; %@ stands for all found windows
+            let output = self.runner.command("xdotool")
+                .args(["search", "--onlyvisible", "--class", pattern.as_str(), "getwindowgeometry", "--shell", "%@"])
+                .output();
+
+            match output {
+                Ok(output) if output.status.success() => {
+                    windows.extend(parse_xdotool_geometry(&String::from_utf8_lossy(&output.stdout)));
+                },
+                // xdotool fails when no window matches
+                Ok(output) if output.stdout.is_empty() && output.stderr.is_empty() => {},
+                Ok(output) => {
+                    log::debug!(target: TARGET_SCREEN_CAPTURE, "xdotool search failed: {}", String::from_utf8_lossy(&output.stderr));
+                    return None;
+                },
+                Err(e) => {
+                    log::debug!(target: TARGET_SCREEN_CAPTURE, "Failed to run xdotool: {}", e);
+                    return None;
+                },
+            }
+        }
+
+        Some(windows)
+    }
+}
+
+/// Windows on Hyprland, from `hyprctl clients -j`
+pub struct HyprlandWindowSource {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl HyprlandWindowSource {
+    pub fn new(runner: Arc<dyn CommandRunner>) -> Self {
+        HyprlandWindowSource { runner }
+    }
+}
+
+impl WindowSource for HyprlandWindowSource {
+    fn matching_windows(&mut self, patterns: &[Regex]) -> Option<Vec<WindowGeometry>> {
+        let output = match self.runner.command("hyprctl").args(["clients", "-j"]).output() {
+            Ok(output) if output.status.success() => output,
+            Ok(output) => {
+                log::debug!(target: TARGET_SCREEN_CAPTURE, "hyprctl exited with {}", output.status);
+                return None;
+            },
+            Err(e) => {
+                log::debug!(target: TARGET_SCREEN_CAPTURE, "Failed to run hyprctl: {}", e);
+                return None;
+            },
+        };
+
+        match parse_hyprctl_clients(&output.stdout, patterns) {
+            Ok(windows) => Some(windows),
+            Err(e) => {
+                log::warn!(target: TARGET_SCREEN_CAPTURE, "Ignoring hyprctl output: {}", e);
+                None
+            },
+        }
+    }
+
+    fn logical_coordinates(&self) -> bool {
+        true
+    }
+}
+
+/// Parse `xdotool getwindowgeometry --shell` output, one block per window
+fn parse_xdotool_geometry(output: &str) -> Vec<WindowGeometry> {
+    let mut windows = Vec::new();
+    let mut current: Option<WindowGeometry> = None;
+
+    for line in output.lines() {
+        let (key, value) = match line.split_once('=') {
+            Some(pair) => pair,
+            None => continue,
+        };
+
+        if key == "WINDOW" {
+            windows.extend(current.take());
+            current = Some(WindowGeometry { x: 0, y: 0, width: 0, height: 0 });
+            continue;
+        }
+
+        if let Some(window) = current.as_mut() {
+            match key {
+                "X" => window.x = value.parse().unwrap_or(0),
+                "Y" => window.y = value.parse().unwrap_or(0),
+                "WIDTH" => window.width = value.parse().unwrap_or(0),
+                "HEIGHT" => window.height = value.parse().unwrap_or(0),
+                _ => {},
+            }
+        }
+    }
+    windows.extend(current);
+
+    windows.retain(|window| window.width > 0 && window.height > 0);
+    windows
+}
+
+/// Parse `hyprctl clients -j`, keeping mapped windows whose class matches.
+/// Windows on hidden workspaces are kept too: masking too much is harmless.
+fn parse_hyprctl_clients(output: &[u8], patterns: &[Regex]) -> Result<Vec<WindowGeometry>, ScreenCaptureError> {
+    let json: serde_json::Value = serde_json::from_slice(output)
+        .map_err(|e| ScreenCaptureError::DisplayServerError(
+            format!("Failed to parse hyprctl clients: {}", e)
+        ))?;
+
+    let pair = |client: &serde_json::Value, key: &str| -> Option<(i64, i64)> {
+        let values = client.get(key)?.as_array()?;
+        Some((values.first()?.as_i64()?, values.get(1)?.as_i64()?))
+    };
+
+    let mut windows = Vec::new();
+    for client in json.as_array().into_iter().flatten() {
+        let class = client.get("class").and_then(|v| v.as_str()).unwrap_or("");
+        if !patterns.iter().any(|pattern| pattern.is_match(class)) {
+            continue;
+        }
+
+        let mapped = client.get("mapped").and_then(|v| v.as_bool()).unwrap_or(true);
+        let hidden = client.get("hidden").and_then(|v| v.as_bool()).unwrap_or(false);
+        if !mapped || hidden {
+            continue;
+        }
+
+        if let (Some((x, y)), Some((width, height))) = (pair(client, "at"), pair(client, "size")) {
+            if width > 0 && height > 0 {
+                windows.push(WindowGeometry { x: x as i32, y: y as i32, width: width as u32, height: height as u32 });
+            }
+        }
+    }
+
+    Ok(windows)
+}
+
+/// Masks covering `windows` (desktop coordinates) on `monitor`
+fn window_masks(windows: &[WindowGeometry], monitor: &MonitorInfo, logical: bool, style: MaskStyle) -> Vec<PrivacyMask> {
+    let scale = if logical { monitor.scale_factor } else { 1.0 };
+
+    windows.iter()
+        .map(|window| PrivacyMask {
+            x: ((window.x - monitor.x_offset) as f64 * scale).floor() as i32,
+            y: ((window.y - monitor.y_offset) as f64 * scale).floor() as i32,
+            width: (window.width as f64 * scale).ceil() as u32,
+            height: (window.height as f64 * scale).ceil() as u32,
+            style,
+        })
+        .filter(|mask| mask.clamp(&CaptureRegion::full(monitor)).is_some())
+        .collect()
+}
+
+/// Keeps the window masks of a capture up to date. When a matched window
+/// appears, moves or closes, the encoder is restarted with the new masks.
+pub struct WindowMaskWatcher {
+    running: Arc<Mutex<bool>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WindowMaskWatcher {
+    pub fn start(
+        mut source: Box<dyn WindowSource>,
+        patterns: Vec<Regex>,
+        monitor: MonitorInfo,
+        config: Arc<Mutex<ScreenCaptureConfig>>,
+        keyframes: Arc<Mutex<KeyframeScheduler>>,
+    ) -> Self {
+        let running = Arc::new(Mutex::new(true));
+        let thread_running = running.clone();
+
+        let thread = thread::spawn(move || {
+            while *thread_running.lock().unwrap() {
+                let poll_started = Instant::now();
+
+                if let Some(windows) = source.matching_windows(&patterns) {
+                    let mut config = config.lock().unwrap();
+                    let masks = window_masks(&windows, &monitor, source.logical_coordinates(), config.privacy.window_style);
+                    if masks != config.privacy.window_masks {
+                        log::debug!(target: TARGET_SCREEN_CAPTURE, "{} window(s) masked on monitor {}", masks.len(), monitor.index);
+                        config.privacy.window_masks = masks;
+                        // The capture loop reads the config on every encoder start
+                        keyframes.lock().unwrap().request(Instant::now());
+                    }
+                }
+
+                if let Some(remaining) = WINDOW_POLL_INTERVAL.checked_sub(poll_started.elapsed()) {
+                    thread::sleep(remaining);
+                }
+            }
+        });
+
+        WindowMaskWatcher {
+            running,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        *self.running.lock().unwrap() = false;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for WindowMaskWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::{call, fixture, monitor, TestEnv};
+
+    fn mask(x: i32, y: i32, width: u32, height: u32, style: MaskStyle) -> PrivacyMask {
+        PrivacyMask { x, y, width, height, style }
+    }
+
+    fn privacy(masks: &[PrivacyMask]) -> PrivacyConfig {
+        PrivacyConfig {
+            masks: masks.iter().enumerate().map(|(i, mask)| PrivacyMaskEntry { id: i as u32 + 1, mask: *mask }).collect(),
+            ..PrivacyConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_masks_are_clamped_to_the_captured_region() {
+        let region = CaptureRegion { x: 100, y: 100, width: 800, height: 600 };
+
+        assert_eq!(
+            mask(150, 200, 100, 50, MaskStyle::Black).clamp(&region),
+            Some(CaptureRegion { x: 50, y: 100, width: 100, height: 50 })
+        );
+        // Sticking out at the top-left and bottom-right
+        assert_eq!(
+            mask(-20, 50, 200, 100, MaskStyle::Black).clamp(&region),
+            Some(CaptureRegion { x: 0, y: 0, width: 80, height: 50 })
+        );
+        assert_eq!(
+            mask(850, 650, 500, 500, MaskStyle::Black).clamp(&region),
+            Some(CaptureRegion { x: 750, y: 550, width: 50, height: 50 })
+        );
+        assert_eq!(mask(0, 0, 100, 100, MaskStyle::Black).clamp(&region), None);
+
+        assert!(mask(1900, 1000, 100, 100, MaskStyle::Black).validate(&monitor()).is_ok());
+        assert!(mask(1920, 0, 100, 100, MaskStyle::Black).validate(&monitor()).is_err());
+        assert!(mask(0, 0, 0, 100, MaskStyle::Black).validate(&monitor()).is_err());
+    }
+
+    #[test]
+    fn test_ffmpeg_mask_filters() {
+        let region = CaptureRegion { x: 0, y: 0, width: 1920, height: 1080 };
+        let config = privacy(&[
+            mask(10, 20, 300, 200, MaskStyle::Black),
+            mask(1800, 1000, 200, 200, MaskStyle::Pixelate),
+            mask(-500, 0, 100, 100, MaskStyle::Black),
+        ]);
+
+        let filters = ffmpeg_mask_filters(&config, &region, 1920);
+        assert_eq!(filters, vec![
+            "drawbox=x=10:y=20:w=300:h=200:color=black:t=fill".to_string(),
+            "split[pm1a][pm1b];[pm1b]crop=120:80:1800:1000,scale=8:5:flags=neighbor,scale=120:80:flags=neighbor[pm1p];[pm1a][pm1p]overlay=1800:1000".to_string(),
+        ]);
+
+        // Streamed at half size the blocks are twice as large in captured pixels
+        let filters = ffmpeg_mask_filters(&config, &region, 960);
+        assert!(filters[1].contains("scale=4:3:flags=neighbor"));
+
+        assert!(ffmpeg_mask_filters(&PrivacyConfig::default(), &region, 1920).is_empty());
+    }
+
+    #[test]
+    fn test_gstreamer_masks_are_composited_black() {
+        let region = CaptureRegion { x: 0, y: 0, width: 1920, height: 1080 };
+        let config = privacy(&[mask(10, 20, 300, 200, MaskStyle::Pixelate)]);
+
+        let (inline, sources) = gstreamer_mask_elements(&config, &region, 30);
+        assert_eq!(inline.join(" "), "compositor name=privacy sink_1::xpos=10 sink_1::ypos=20 !");
+        assert_eq!(
+            sources.join(" "),
+            "videotestsrc pattern=black is-live=true ! video/x-raw,width=300,height=200,framerate=30/1 ! privacy."
+        );
+
+        assert_eq!(gstreamer_mask_elements(&PrivacyConfig::default(), &region, 30), (Vec::new(), Vec::new()));
+    }
+
+    #[test]
+    fn test_x11_windows_are_found_by_class() {
+        let env = TestEnv::new();
+        env.set_stdout("xdotool", "WINDOW=62914566\nX=2600\nY=100\nWIDTH=800\nHEIGHT=600\nSCREEN=0\nWINDOW=62914570\nX=0\nY=0\nWIDTH=1\nHEIGHT=0\nSCREEN=0\n");
+        let mut source = X11WindowSource::new(env.runner());
+
+        let patterns = vec![Regex::new("^(Signal|KeePassXC)$").unwrap()];
+        let windows = source.matching_windows(&patterns).unwrap();
+        assert_eq!(windows, vec![WindowGeometry { x: 2600, y: 100, width: 800, height: 600 }]);
+        assert_eq!(
+            env.take_invocations(),
+            vec![call("xdotool search --onlyvisible --class ^(Signal|KeePassXC)$ getwindowgeometry --shell %@")]
+        );
+
+        // On the second monitor, partly beyond its right edge
+        assert_eq!(
+            window_masks(&windows, &monitor(), false, MaskStyle::Pixelate),
+            vec![mask(40, 100, 800, 600, MaskStyle::Pixelate)]
+        );
+        let other = MonitorInfo { x_offset: 0, ..monitor() };
+        assert!(window_masks(&[WindowGeometry { x: 2600, y: 100, width: 800, height: 600 }], &other, false, MaskStyle::Black).is_empty());
+    }
+
+    #[test]
+    fn test_parse_hyprctl_clients() {
+        let patterns = vec![Regex::new("(?i)signal").unwrap()];
+        let windows = parse_hyprctl_clients(&fixture("hyprctl-clients.json"), &patterns).unwrap();
+        assert_eq!(windows, vec![WindowGeometry { x: 2570, y: 50, width: 900, height: 700 }]);
+
+        // Hyprland positions are in the scaled layout
+        let scaled = MonitorInfo { scale_factor: 1.5, ..monitor() };
+        assert_eq!(window_masks(&windows, &scaled, true, MaskStyle::Black), vec![mask(15, 75, 1350, 1050, MaskStyle::Black)]);
+    }
+
+    #[test]
+    fn test_watcher_restarts_encoder_when_a_window_moves() {
+        struct MovingWindow(Arc<Mutex<Vec<WindowGeometry>>>);
+
+        impl WindowSource for MovingWindow {
+            fn matching_windows(&mut self, _patterns: &[Regex]) -> Option<Vec<WindowGeometry>> {
+                Some(self.0.lock().unwrap().clone())
+            }
+        }
+
+        let windows = Arc::new(Mutex::new(vec![WindowGeometry { x: 2660, y: 0, width: 100, height: 100 }]));
+        let config = Arc::new(Mutex::new(ScreenCaptureConfig::default()));
+        let keyframes = Arc::new(Mutex::new(KeyframeScheduler::new()));
+        let mut watcher = WindowMaskWatcher::start(
+            Box::new(MovingWindow(windows.clone())),
+            Vec::new(),
+            monitor(),
+            config.clone(),
+            keyframes.clone(),
+        );
+
+        let wait_for = |expected: Vec<PrivacyMask>| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while config.lock().unwrap().privacy.window_masks != expected && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
+            }
+            assert_eq!(config.lock().unwrap().privacy.window_masks, expected);
+        };
+
+        wait_for(vec![mask(100, 0, 100, 100, MaskStyle::Black)]);
+        assert!(keyframes.lock().unwrap().take_due(Instant::now()));
+        keyframes.lock().unwrap().encoder_started(Instant::now());
+
+        windows.lock().unwrap()[0].y = 500;
+        wait_for(vec![mask(100, 500, 100, 100, MaskStyle::Black)]);
+
+        watcher.stop();
+    }
+}
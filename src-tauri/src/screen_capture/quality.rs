@@ -3,6 +3,9 @@
 use std::time::{Duration, Instant};
 use crate::screen_capture::config::{RateControlMode, ScreenCaptureConfig};
 
+/// Percentage points by which the output scale changes per adjustment
+const OUTPUT_SCALE_STEP: u32 = 25;
+
 /// Adaptive quality controller for dynamically adjusting encoding parameters
 pub struct AdaptiveQualityController {
     /// Current quality setting (0-100)
@@ -37,6 +40,15 @@ pub struct AdaptiveQualityController {
     
     /// Actual measured latency in milliseconds
     measured_latency_ms: u32,
+    
+    /// Percentage of the configured output size that is encoded (100 = full)
+    output_scale_percent: u32,
+    
+    /// Percentage the viewers' viewports leave of the configured output size
+    viewport_scale_percent: u32,
+    
+    /// Bitrate the stream must not exceed, e.g. from a power profile
+    bitrate_ceiling_kbps: Option<u32>,
 }
 
 /// Configuration for the quality adapter
@@ -67,6 +79,15 @@ pub struct QualityAdapterConfig {
     
     /// Whether to prioritize latency over quality
     pub prioritize_latency: bool,
+    
+    /// Lowest output scale in percent; 100 keeps the resolution fixed
+    pub min_output_scale: u32,
+    
+    /// Quality below which the stream is downscaled instead of degraded further
+    pub scale_down_below_quality: u32,
+    
+    /// Quality from which a downscaled stream is enlarged again
+    pub scale_up_from_quality: u32,
 }
 
 impl Default for QualityAdapterConfig {
@@ -81,6 +102,9 @@ impl Default for QualityAdapterConfig {
             frame_drop_threshold: 0.05,
             history_size: 5,
             prioritize_latency: true,
+            min_output_scale: 50,
+            scale_down_below_quality: 40,
+            scale_up_from_quality: 85,
         }
     }
 }
@@ -103,6 +127,9 @@ impl AdaptiveQualityController {
             config,
             target_latency_ms: 200, // Default target latency
             measured_latency_ms: 0,
+            output_scale_percent: 100,
+            viewport_scale_percent: 100,
+            bitrate_ceiling_kbps: None,
         }
     }
     
@@ -157,10 +184,22 @@ impl AdaptiveQualityController {
         adjustment = (adjustment as f32 * self.config.adjustment_factor) as i32;
         
         // Apply adjustment
-        let new_quality = (self.current_quality as i32 + adjustment)
+        let mut new_quality = (self.current_quality as i32 + adjustment)
             .max(self.config.min_quality as i32)
             .min(self.config.max_quality as i32) as u32;
         
+        // Fewer pixels buy back picture quality: a low quality is traded for
+        // a smaller stream, and the size is restored once there is headroom.
+        // Either way the quality restarts at the downscale threshold.
+        let min_scale = self.config.min_output_scale.min(100);
+        if new_quality < self.config.scale_down_below_quality && self.output_scale_percent > min_scale {
+            self.output_scale_percent = self.output_scale_percent.saturating_sub(OUTPUT_SCALE_STEP).max(min_scale);
+            new_quality = self.config.scale_down_below_quality.min(self.config.max_quality);
+        } else if adjustment > 0 && new_quality >= self.config.scale_up_from_quality && self.output_scale_percent < 100 {
+            self.output_scale_percent = (self.output_scale_percent + OUTPUT_SCALE_STEP).min(100);
+            new_quality = self.config.scale_down_below_quality.max(self.config.min_quality);
+        }
+        
         // Update quality
         self.current_quality = new_quality;
         self.quality_history.push(new_quality);
@@ -172,11 +211,40 @@ impl AdaptiveQualityController {
         new_quality
     }
     
+    /// Start over from `quality`, e.g. after a profile switch
+    pub fn reset_quality(&mut self, quality: u32) {
+        let quality = quality.min(self.config.max_quality).max(self.config.min_quality);
+        self.current_quality = quality;
+        self.quality_history = vec![quality; self.config.history_size];
+    }
+    
+    /// Hard upper bound for the bitrate; quality adjustments stay below it
+    /// and the encoder is told to cap its rate (None lifts the ceiling)
+    pub fn set_bitrate_ceiling(&mut self, ceiling_kbps: Option<u32>) {
+        self.bitrate_ceiling_kbps = ceiling_kbps;
+    }
+    
     /// Get the current quality setting
     pub fn get_quality(&self) -> u32 {
         self.current_quality
     }
     
+    /// Percentage of the configured output size the encoder should produce,
+    /// the adaptive scale applied on top of the viewport scale; a change
+    /// takes effect when the encoder is restarted
+    pub fn output_scale_percent(&self) -> u32 {
+        (self.output_scale_percent * self.viewport_scale_percent).div_ceil(100)
+    }
+    
+    /// Shrink the stream to fit the viewers' viewports (100 = no limit)
+    pub fn set_viewport_scale_percent(&mut self, percent: u32) {
+        self.viewport_scale_percent = percent.clamp(1, 100);
+    }
+    
+    pub fn viewport_scale_percent(&self) -> u32 {
+        self.viewport_scale_percent
+    }
+    
     /// Get a smoothed quality value based on recent history
     pub fn get_smoothed_quality(&self) -> u32 {
         if self.quality_history.is_empty() {
@@ -208,7 +276,7 @@ impl AdaptiveQualityController {
         let network_cap = (avg_bandwidth as f32 * 0.8) as u32;
         
         let bitrate = (base_bitrate as f32 * quality_factor) as u32;
-        bitrate.min(network_cap)
+        bitrate.min(network_cap).min(self.bitrate_ceiling_kbps.unwrap_or(u32::MAX))
     }
     
     /// Get average bandwidth from history
@@ -245,7 +313,7 @@ impl AdaptiveQualityController {
         match config.codec {
             crate::screen_capture::types::VideoCodec::H264 => {
                 // CRF mode (lower = better quality)
-                let crf = 51 - (self.current_quality / 2);
+                let crf = fixed_crf(config).unwrap_or(51 - (self.current_quality / 2));
                 params.push("-crf".to_string());
                 params.push(crf.to_string());
                 
@@ -263,9 +331,12 @@ impl AdaptiveQualityController {
                 params.push("-preset".to_string());
                 params.push(preset.to_string());
                 
-                // Use zerolatency tuning for remote desktop
+                // Use zerolatency tuning for remote desktop unless a profile asks for another
+                let tune = config.advanced_options.as_ref()
+                    .and_then(|options| options.tune.as_deref())
+                    .unwrap_or("zerolatency");
                 params.push("-tune".to_string());
-                params.push("zerolatency".to_string());
+                params.push(tune.to_string());
             },
             crate::screen_capture::types::VideoCodec::VP8 => {
                 // VP8 quality (lower = better quality)
@@ -277,7 +348,7 @@ impl AdaptiveQualityController {
             },
             crate::screen_capture::types::VideoCodec::VP9 => {
                 // VP9 quality (lower = better quality)
-                let crf = 63 - (self.current_quality * 63 / 100);
+                let crf = fixed_crf(config).unwrap_or(63 - (self.current_quality * 63 / 100));
                 params.push("-crf".to_string());
                 params.push(crf.to_string());
                 
@@ -314,9 +385,18 @@ impl AdaptiveQualityController {
         }
         
         // If we have a specific bitrate preference, use it
+        let ceiling = self.bitrate_ceiling_kbps;
         if let Some(target_bitrate) = config.bitrate {
             params.push("-b:v".to_string());
-            params.push(format!("{}k", target_bitrate));
+            params.push(format!("{}k", target_bitrate.min(ceiling.unwrap_or(u32::MAX))));
+        }
+        
+        // A ceiling also holds in CRF mode, where the quality sets the rate
+        if let Some(ceiling) = ceiling {
+            params.push("-maxrate".to_string());
+            params.push(format!("{}k", ceiling));
+            params.push("-bufsize".to_string());
+            params.push(format!("{}k", ceiling * 2));
         }
         
         // Keyframe interval
@@ -327,6 +407,14 @@ impl AdaptiveQualityController {
     }
 }
 
+/// CRF set in `advanced_options`, which replaces the adaptive value
+fn fixed_crf(config: &ScreenCaptureConfig) -> Option<u32> {
+    match config.advanced_options.as_ref().map(|options| &options.rate_control) {
+        Some(RateControlMode::CRF(crf)) => Some(*crf),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -353,6 +441,36 @@ mod tests {
         assert!(new_quality > 50);
     }
     
+    #[test]
+    fn test_low_quality_is_traded_for_resolution() {
+        let config = QualityAdapterConfig { min_adjustment_interval_ms: 0, ..Default::default() };
+        let mut controller = AdaptiveQualityController::new(45, Some(config));
+        
+        // Sustained frame drops push the quality below the threshold
+        controller.update_metrics(50.0, 5000, 0.2, 150);
+        assert_eq!(controller.adjust_quality(), 40);
+        assert_eq!(controller.output_scale_percent(), 75);
+        assert_eq!(controller.adjust_quality(), 40);
+        assert_eq!(controller.output_scale_percent(), 50);
+        
+        // At the minimum scale only the quality drops
+        assert_eq!(controller.adjust_quality(), 30);
+        assert_eq!(controller.output_scale_percent(), 50);
+        
+        // With headroom the quality recovers first, then the resolution
+        controller.update_metrics(30.0, 10000, 0.0, 100);
+        controller.current_quality = 84;
+        assert_eq!(controller.adjust_quality(), 40);
+        assert_eq!(controller.output_scale_percent(), 75);
+        
+        // Fixed resolution: the quality has to give
+        let config = QualityAdapterConfig { min_adjustment_interval_ms: 0, min_output_scale: 100, ..Default::default() };
+        let mut controller = AdaptiveQualityController::new(45, Some(config));
+        controller.update_metrics(50.0, 5000, 0.2, 150);
+        assert_eq!(controller.adjust_quality(), 35);
+        assert_eq!(controller.output_scale_percent(), 100);
+    }
+    
     #[test]
     fn test_bitrate_calculation() {
         let mut controller = AdaptiveQualityController::new(50, None);
@@ -374,4 +492,42 @@ mod tests {
         // Higher quality should have higher bitrate
         assert!(bitrate_high_quality > bitrate_low_quality);
     }
+    
+    #[test]
+    fn test_presentation_profile_args() {
+        use crate::screen_capture::config::{find_capture_profile, PROFILE_BALANCED, PROFILE_PRESENTATION};
+        use crate::screen_capture::types::VideoCodec;
+        
+        let profile = |name| find_capture_profile(name, &Default::default()).unwrap();
+        let value_of = |params: &[String], flag: &str| {
+            params.iter().position(|param| param == flag).map(|index| params[index + 1].clone())
+        };
+        
+        let presentation = profile(PROFILE_PRESENTATION);
+        let params = AdaptiveQualityController::new(presentation.quality, None).generate_ffmpeg_params(&presentation);
+        assert_eq!(value_of(&params, "-tune").as_deref(), Some("stillimage"));
+        assert_eq!(value_of(&params, "-crf").as_deref(), Some("18"));
+        assert_eq!(value_of(&params, "-g").as_deref(), Some("10"));
+        
+        // Without a fixed CRF the quality controller picks it
+        let balanced = profile(PROFILE_BALANCED);
+        let params = AdaptiveQualityController::new(balanced.quality, None).generate_ffmpeg_params(&balanced);
+        assert_eq!(value_of(&params, "-tune").as_deref(), Some("zerolatency"));
+        assert_eq!(value_of(&params, "-crf").as_deref(), Some("11"));
+        
+        // A user profile can keep text sharp on VP9 with its own CRF
+        let vp9 = ScreenCaptureConfig { codec: VideoCodec::VP9, ..presentation };
+        let params = AdaptiveQualityController::new(95, None).generate_ffmpeg_params(&vp9);
+        assert_eq!(value_of(&params, "-crf").as_deref(), Some("18"));
+    }
+    
+    #[test]
+    fn test_reset_quality() {
+        let mut controller = AdaptiveQualityController::new(80, None);
+        controller.reset_quality(40);
+        assert_eq!(controller.get_quality(), 40);
+        assert_eq!(controller.get_smoothed_quality(), 40);
+        controller.reset_quality(0);
+        assert_eq!(controller.get_quality(), 10);
+    }
 }
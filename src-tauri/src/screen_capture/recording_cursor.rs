@@ -0,0 +1,492 @@
+// screen_capture/recording_cursor.rs - Synthetic cursor burned into recordings
+//
+// Some capture paths deliver frames without the pointer, which makes recorded
+// tutorials hard to follow. While a recording runs, a cursor track samples the
+// pointer on the captured monitor, plus click markers from forwarded input,
+// on a clock that starts with the track. The finalize step renders a cursor
+// sprite (and a ring per click) into a copy of the recorded file with
+// ffmpeg's overlay filter, moved by a `sendcmd` script built from the track.
+// The live stream never sees any of this.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::command_runner::CommandRunner;
+use crate::logging::TARGET_SCREEN_CAPTURE;
+use crate::screen_capture::cursor::{CursorPosition, CursorSink, CursorSource, CursorTracker};
+use crate::screen_capture::error::ScreenCaptureError;
+use crate::screen_capture::types::MonitorInfo;
+
+/// How long the ring around a click stays visible
+pub const CLICK_HIGHLIGHT_MS: u64 = 300;
+
+/// Samples kept per track, about 4.5 hours of constant movement at 60 Hz
+const MAX_TRACK_SAMPLES: usize = 1_000_000;
+
+/// Overlay position that puts a sprite outside any frame
+const HIDDEN: i32 = -10_000;
+
+/// Outer radius and line width of the click ring
+const RING_RADIUS: u32 = 18;
+const RING_WIDTH: u32 = 3;
+
+/// Classic arrow with the hotspot at the tip: `#` outline, `.` fill
+const ARROW: [&str; 19] = [
+    "#",
+    "##",
+    "#.#",
+    "#..#",
+    "#...#",
+    "#....#",
+    "#.....#",
+    "#......#",
+    "#.......#",
+    "#........#",
+    "#.........#",
+    "#......#####",
+    "#...#..#",
+    "#..##..#",
+    "#.#  #..#",
+    "##   #..#",
+    "#     #..#",
+    "      #..#",
+    "       ##",
+];
+
+/// Sprite drawn at the pointer position
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub enum CursorSprite {
+    /// Black and white arrow, built into the binary
+    #[default]
+    Arrow,
+
+    /// Translucent yellow dot centered on the pointer, built into the binary
+    Dot,
+
+    /// PNG file; the hotspot is its top-left corner
+    File(PathBuf),
+}
+
+/// Cursor overlay options of one recording
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordingCursorConfig {
+    #[serde(default)]
+    pub sprite: CursorSprite,
+
+    /// Draw a ring around the pointer for `CLICK_HIGHLIGHT_MS` after each click
+    #[serde(default = "default_true")]
+    pub highlight_clicks: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for RecordingCursorConfig {
+    fn default() -> Self {
+        RecordingCursorConfig {
+            sprite: CursorSprite::Arrow,
+            highlight_clicks: true,
+        }
+    }
+}
+
+/// Pointer position relative to the captured monitor, `t_ms` after the track started
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CursorSample {
+    pub t_ms: u64,
+    pub x: i32,
+    pub y: i32,
+    pub visible: bool,
+}
+
+/// A mouse button press at a position relative to the captured monitor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClickMarker {
+    pub t_ms: u64,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Everything the finalize step needs to draw the cursor into a recording
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CursorTrack {
+    pub config: RecordingCursorConfig,
+    pub monitor_index: usize,
+    pub samples: Vec<CursorSample>,
+    pub clicks: Vec<ClickMarker>,
+}
+
+/// Collects samples and clicks with timestamps relative to its creation
+#[derive(Clone)]
+struct CursorTrackRecorder {
+    started: Instant,
+    track: Arc<Mutex<CursorTrack>>,
+}
+
+impl CursorTrackRecorder {
+    fn new(config: RecordingCursorConfig, monitor_index: usize) -> Self {
+        CursorTrackRecorder {
+            started: Instant::now(),
+            track: Arc::new(Mutex::new(CursorTrack {
+                config,
+                monitor_index,
+                samples: Vec::new(),
+                clicks: Vec::new(),
+            })),
+        }
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    fn record_position(&self, position: &CursorPosition) {
+        let t_ms = self.elapsed_ms();
+        let mut track = self.track.lock().unwrap();
+        if track.samples.len() < MAX_TRACK_SAMPLES {
+            track.samples.push(CursorSample { t_ms, x: position.x, y: position.y, visible: position.visible });
+        }
+    }
+
+    /// A click at `position`, or where the pointer was last seen
+    fn record_click(&self, position: Option<(i32, i32)>) {
+        let t_ms = self.elapsed_ms();
+        let mut track = self.track.lock().unwrap();
+        let position = position.or_else(|| {
+            track.samples.last()
+                .filter(|sample| sample.visible)
+                .map(|sample| (sample.x, sample.y))
+        });
+        if let Some((x, y)) = position {
+            track.clicks.push(ClickMarker { t_ms, x, y });
+        }
+    }
+
+    fn snapshot(&self) -> CursorTrack {
+        self.track.lock().unwrap().clone()
+    }
+}
+
+/// Cursor track of a running recording: polls the pointer of one monitor
+/// until it is finished
+pub struct CursorTrackRecording {
+    tracker: CursorTracker,
+    recorder: CursorTrackRecorder,
+    monitor: MonitorInfo,
+}
+
+impl CursorTrackRecording {
+    pub fn start(source: Box<dyn CursorSource>, monitor: MonitorInfo, config: RecordingCursorConfig) -> Self {
+        let recorder = CursorTrackRecorder::new(config, monitor.index);
+        let sink_recorder = recorder.clone();
+        let sink: CursorSink = Arc::new(move |position| sink_recorder.record_position(&position));
+
+        CursorTrackRecording {
+            tracker: CursorTracker::start(source, monitor.clone(), sink),
+            recorder,
+            monitor,
+        }
+    }
+
+    /// Record a click given in desktop coordinates; clicks on other monitors
+    /// are ignored. Without a position the last sampled one is used.
+    pub fn record_click(&self, desktop_position: Option<(i32, i32)>) {
+        let position = match desktop_position {
+            Some((x, y)) => {
+                let position = crate::screen_capture::cursor::to_monitor_position(x, y, String::new(), &self.monitor);
+                if !position.visible {
+                    return;
+                }
+                Some((position.x, position.y))
+            },
+            None => None,
+        };
+        self.recorder.record_click(position);
+    }
+
+    /// Stop polling and return the track
+    pub fn finish(mut self) -> CursorTrack {
+        self.tracker.stop();
+        self.recorder.snapshot()
+    }
+}
+
+/// A sprite image with its hotspot
+struct Sprite {
+    image: RgbaImage,
+    hotspot: (i32, i32),
+}
+
+fn arrow_sprite() -> Sprite {
+    let width = ARROW.iter().map(|row| row.len()).max().unwrap_or(0) as u32;
+    let mut image = RgbaImage::new(width, ARROW.len() as u32);
+    for (y, row) in ARROW.iter().enumerate() {
+        for (x, pixel) in row.bytes().enumerate() {
+            let color = match pixel {
+                b'#' => Rgba([0, 0, 0, 255]),
+                b'.' => Rgba([255, 255, 255, 255]),
+                _ => continue,
+            };
+            image.put_pixel(x as u32, y as u32, color);
+        }
+    }
+    Sprite { image, hotspot: (0, 0) }
+}
+
+/// A filled circle, or a ring of `width` pixels, centered in a square image
+fn circle_sprite(radius: u32, width: Option<u32>, color: Rgba<u8>) -> Sprite {
+    let size = radius * 2 + 1;
+    let mut image = RgbaImage::new(size, size);
+    let outer = (radius * radius) as i64;
+    let inner = width.map(|width| (radius.saturating_sub(width) as i64).pow(2));
+    for (x, y, pixel) in image.enumerate_pixels_mut() {
+        let dx = x as i64 - radius as i64;
+        let dy = y as i64 - radius as i64;
+        let distance = dx * dx + dy * dy;
+        if distance <= outer && !inner.is_some_and(|inner| distance <= inner) {
+            *pixel = color;
+        }
+    }
+    Sprite { image, hotspot: (radius as i32, radius as i32) }
+}
+
+fn ring_sprite() -> Sprite {
+    circle_sprite(RING_RADIUS, Some(RING_WIDTH), Rgba([255, 200, 0, 230]))
+}
+
+/// Sprite for the pointer; files are checked and read here
+fn cursor_sprite(sprite: &CursorSprite) -> Result<Sprite, ScreenCaptureError> {
+    match sprite {
+        CursorSprite::Arrow => Ok(arrow_sprite()),
+        CursorSprite::Dot => Ok(circle_sprite(8, None, Rgba([255, 220, 0, 160]))),
+        CursorSprite::File(path) => {
+            let image = image::open(path)
+                .map_err(|e| ScreenCaptureError::InvalidConfig(format!("Cursor sprite {}: {}", path.display(), e)))?
+                .to_rgba8();
+            Ok(Sprite { image, hotspot: (0, 0) })
+        },
+    }
+}
+
+/// `sendcmd` script that moves the cursor overlay (`overlay@cursor`) along
+/// the track and shows the click ring (`overlay@click`) after each click
+fn sendcmd_script(track: &CursorTrack, cursor_hotspot: (i32, i32), ring_hotspot: (i32, i32)) -> String {
+    let mut commands: Vec<(u64, String)> = Vec::new();
+
+    for sample in &track.samples {
+        let (x, y) = if sample.visible {
+            (sample.x - cursor_hotspot.0, sample.y - cursor_hotspot.1)
+        } else {
+            (HIDDEN, HIDDEN)
+        };
+        commands.push((sample.t_ms, format!("overlay@cursor x {}, overlay@cursor y {}", x, y)));
+    }
+
+    if track.config.highlight_clicks {
+        for (index, click) in track.clicks.iter().enumerate() {
+            commands.push((click.t_ms, format!(
+                "overlay@click x {}, overlay@click y {}", click.x - ring_hotspot.0, click.y - ring_hotspot.1
+            )));
+            // A click within the highlight moves the ring instead of hiding it
+            let hide_at = click.t_ms + CLICK_HIGHLIGHT_MS;
+            if !track.clicks.get(index + 1).is_some_and(|next| next.t_ms < hide_at) {
+                commands.push((hide_at, format!("overlay@click x {}, overlay@click y {}", HIDDEN, HIDDEN)));
+            }
+        }
+    }
+
+    commands.sort_by_key(|(t_ms, _)| *t_ms);
+    commands.iter()
+        .map(|(t_ms, command)| format!("{}.{:03} {};\n", t_ms / 1000, t_ms % 1000, command))
+        .collect()
+}
+
+/// Video encoder arguments matching the output container
+fn video_codec_args(output: &Path) -> Vec<&'static str> {
+    let webm = output.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("webm"));
+    if webm {
+        vec!["-c:v", "libvpx-vp9", "-b:v", "0", "-crf", "32", "-row-mt", "1"]
+    } else {
+        vec!["-c:v", "libx264", "-preset", "veryfast", "-crf", "18", "-pix_fmt", "yuv420p"]
+    }
+}
+
+/// ffmpeg arguments for the finalize step; `cursor.cmd`, `cursor.png` and
+/// `click.png` are read from the working directory
+fn ffmpeg_args(video: &Path, output: &Path, highlight_clicks: bool) -> Vec<String> {
+    let mut args: Vec<String> = ["-y", "-hide_banner", "-loglevel", "error", "-i"].iter().map(|arg| arg.to_string()).collect();
+    args.push(video.display().to_string());
+    args.extend(["-i", "cursor.png"].iter().map(|arg| arg.to_string()));
+
+    let hidden = format!("x={}:y={}", HIDDEN, HIDDEN);
+    let graph = if highlight_clicks {
+        args.extend(["-i", "click.png"].iter().map(|arg| arg.to_string()));
+        format!(
+            "[0:v]sendcmd=f=cursor.cmd[timed];[timed][2:v]overlay@click={hidden}[clicked];[clicked][1:v]overlay@cursor={hidden}[out]",
+            hidden = hidden
+        )
+    } else {
+        format!("[0:v]sendcmd=f=cursor.cmd[timed];[timed][1:v]overlay@cursor={}[out]", hidden)
+    };
+
+    args.extend(["-filter_complex".to_string(), graph]);
+    args.extend(["-map", "[out]", "-map", "0:a?", "-c:a", "copy"].iter().map(|arg| arg.to_string()));
+    args.extend(video_codec_args(output).iter().map(|arg| arg.to_string()));
+    args.push(output.display().to_string());
+    args
+}
+
+/// Render the cursor of `track` into a copy of the recording at `video`,
+/// written to `output`. Blocks until ffmpeg is done.
+pub fn burn_cursor(runner: &dyn CommandRunner, video: &Path, output: &Path, track: &CursorTrack) -> Result<(), ScreenCaptureError> {
+    // ffmpeg runs in a scratch directory, so relative paths would point there
+    if !video.is_absolute() || !output.is_absolute() {
+        return Err(ScreenCaptureError::InvalidConfig("Recording paths must be absolute".to_string()));
+    }
+    if video == output {
+        return Err(ScreenCaptureError::InvalidConfig("The cursor is rendered into a copy, not the recording itself".to_string()));
+    }
+    if !video.is_file() {
+        return Err(ScreenCaptureError::InvalidConfig(format!("Recording {} does not exist", video.display())));
+    }
+
+    let cursor = cursor_sprite(&track.config.sprite)?;
+    let ring = ring_sprite();
+
+    let work_dir = std::env::temp_dir().join(format!("smoldesk-cursor-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&work_dir).map_err(|e| ScreenCaptureError::EncodingError(format!("Failed to create {}: {}", work_dir.display(), e)))?;
+    let result = render_in(&work_dir, runner, video, output, track, &cursor, &ring);
+    let _ = fs::remove_dir_all(&work_dir);
+    result
+}
+
+/// Write the script and sprites to `work_dir` and run ffmpeg there
+fn render_in(
+    work_dir: &Path,
+    runner: &dyn CommandRunner,
+    video: &Path,
+    output: &Path,
+    track: &CursorTrack,
+    cursor: &Sprite,
+    ring: &Sprite,
+) -> Result<(), ScreenCaptureError> {
+    let prepare_error = |e: &dyn std::fmt::Display| ScreenCaptureError::EncodingError(format!("Failed to prepare the cursor overlay: {}", e));
+    fs::write(work_dir.join("cursor.cmd"), sendcmd_script(track, cursor.hotspot, ring.hotspot)).map_err(|e| prepare_error(&e))?;
+    cursor.image.save(work_dir.join("cursor.png")).map_err(|e| prepare_error(&e))?;
+    if track.config.highlight_clicks {
+        ring.image.save(work_dir.join("click.png")).map_err(|e| prepare_error(&e))?;
+    }
+
+    log::info!(target: TARGET_SCREEN_CAPTURE, "Rendering the cursor of {} samples and {} clicks into {}", track.samples.len(), track.clicks.len(), output.display());
+    let result = runner.command("ffmpeg")
+        .args(ffmpeg_args(video, output, track.config.highlight_clicks))
+        .current_dir(work_dir)
+        .output()
+        .map_err(|e| ScreenCaptureError::FFmpegError(format!("Failed to run ffmpeg: {}", e)))?;
+    if !result.status.success() {
+        return Err(ScreenCaptureError::FFmpegError(format!(
+            "Rendering the cursor failed: {}", String::from_utf8_lossy(&result.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::TestEnv;
+
+    fn track(highlight_clicks: bool) -> CursorTrack {
+        CursorTrack {
+            config: RecordingCursorConfig { sprite: CursorSprite::Dot, highlight_clicks },
+            monitor_index: 0,
+            samples: vec![
+                CursorSample { t_ms: 0, x: 100, y: 50, visible: true },
+                CursorSample { t_ms: 1250, x: 110, y: 60, visible: true },
+                CursorSample { t_ms: 2000, x: -5, y: 60, visible: false },
+            ],
+            clicks: vec![
+                ClickMarker { t_ms: 1300, x: 110, y: 60 },
+                ClickMarker { t_ms: 1400, x: 110, y: 60 },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_sendcmd_script_follows_the_track() {
+        let script = sendcmd_script(&track(true), (8, 8), (18, 18));
+        let lines: Vec<&str> = script.lines().collect();
+        assert_eq!(lines, vec![
+            "0.000 overlay@cursor x 92, overlay@cursor y 42;",
+            "1.250 overlay@cursor x 102, overlay@cursor y 52;",
+            "1.300 overlay@click x 92, overlay@click y 42;",
+            // The double click keeps the ring, only the last one hides it
+            "1.400 overlay@click x 92, overlay@click y 42;",
+            "1.700 overlay@click x -10000, overlay@click y -10000;",
+            "2.000 overlay@cursor x -10000, overlay@cursor y -10000;",
+        ]);
+
+        let without_clicks = sendcmd_script(&track(false), (0, 0), (18, 18));
+        assert!(!without_clicks.contains("overlay@click"));
+    }
+
+    #[test]
+    fn test_clicks_without_position_use_the_last_sample() {
+        let recorder = CursorTrackRecorder::new(RecordingCursorConfig::default(), 0);
+        recorder.record_click(None);
+        recorder.record_position(&CursorPosition { x: 40, y: 30, visible: true, shape: "default".to_string() });
+        recorder.record_click(None);
+        recorder.record_click(Some((7, 9)));
+
+        let clicks: Vec<(i32, i32)> = recorder.snapshot().clicks.iter().map(|click| (click.x, click.y)).collect();
+        assert_eq!(clicks, vec![(40, 30), (7, 9)]);
+    }
+
+    #[test]
+    fn test_built_in_sprites() {
+        let arrow = arrow_sprite();
+        assert_eq!((arrow.image.width(), arrow.image.height()), (12, 19));
+        assert_eq!(arrow.image.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(arrow.image.get_pixel(11, 0).0[3], 0);
+
+        let ring = ring_sprite();
+        assert_eq!(ring.hotspot, (RING_RADIUS as i32, RING_RADIUS as i32));
+        assert_eq!(ring.image.get_pixel(RING_RADIUS, RING_RADIUS).0[3], 0);
+        assert_eq!(ring.image.get_pixel(RING_RADIUS, 0).0[3], 230);
+    }
+
+    #[test]
+    fn test_burn_cursor_runs_ffmpeg_on_a_copy() {
+        let env = TestEnv::new();
+        let dir = std::env::temp_dir().join(format!("smoldesk-recording-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let video = dir.join("session.mkv");
+        fs::write(&video, b"recording").unwrap();
+        let output = dir.join("session-cursor.webm");
+
+        burn_cursor(env.runner().as_ref(), &video, &output, &track(true)).unwrap();
+        let invocations = env.invocations();
+        assert_eq!(invocations.len(), 1);
+        let args = &invocations[0];
+        assert_eq!(args[0], "ffmpeg");
+        assert!(args.contains(&video.display().to_string()));
+        assert!(args.iter().any(|arg| arg.contains("overlay@click") && arg.contains("sendcmd=f=cursor.cmd")));
+        assert!(args.contains(&"libvpx-vp9".to_string()));
+        assert_eq!(args.last().unwrap(), &output.display().to_string());
+
+        // The recording itself is never overwritten
+        assert!(matches!(burn_cursor(env.runner().as_ref(), &video, &video, &track(true)), Err(ScreenCaptureError::InvalidConfig(_))));
+
+        env.set_exit_code("ffmpeg", 1);
+        assert!(matches!(burn_cursor(env.runner().as_ref(), &video, &output, &track(false)), Err(ScreenCaptureError::FFmpegError(_))));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
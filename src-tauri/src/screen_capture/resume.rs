@@ -0,0 +1,217 @@
+// screen_capture/resume.rs - Restarting captures after the host resumed from suspend
+//
+// Monitor indices are positions in the detected list and can change while
+// the host sleeps (a dock was unplugged, outputs came back in another order).
+// Captures are therefore matched to monitors by name; a capture whose monitor
+// is gone moves to the primary monitor.
+
+use std::collections::HashSet;
+
+use serde::Serialize;
+use tauri::Window;
+
+use crate::screen_capture::config::ScreenCaptureConfig;
+use crate::screen_capture::types::MonitorInfo;
+
+/// A capture stopped before a suspend
+pub struct SuspendedCapture {
+    /// Name of the captured monitor, which survives renumbering
+    pub monitor_name: String,
+
+    /// Configuration the capture ran with
+    pub config: ScreenCaptureConfig,
+
+    /// Window the capture events went to
+    pub window: Option<Window>,
+}
+
+/// What differs for one capture after the resume
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ResumeChange {
+    /// The monitor is still there under another index
+    #[serde(rename_all = "camelCase")]
+    Renumbered { monitor_name: String, from: usize, to: usize },
+
+    /// The monitor is gone; the primary monitor is captured instead, without
+    /// the capture region of the old monitor
+    #[serde(rename_all = "camelCase")]
+    FellBackToPrimary { monitor_name: String, from: usize, to: usize, primary_name: String },
+
+    /// The capture was not restarted
+    #[serde(rename_all = "camelCase")]
+    NotRestarted { monitor_name: String, from: usize, reason: String },
+}
+
+/// Sent with `session_resumed`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResumeReport {
+    /// The detected monitors differ from before the suspend
+    pub monitors_changed: bool,
+
+    /// Monitor indices captured again
+    pub restarted: Vec<usize>,
+
+    /// Captures that moved or could not be restarted
+    pub changes: Vec<ResumeChange>,
+}
+
+/// Captures to start, as (position in the suspended list, configuration)
+#[derive(Debug, Default)]
+pub struct ResumePlan {
+    pub restarts: Vec<(usize, ScreenCaptureConfig)>,
+    pub changes: Vec<ResumeChange>,
+}
+
+/// Maps each suspended capture, given as (monitor name, configuration), to a
+/// monitor of the new list. Monitors that kept their name are claimed first,
+/// so a fallback never takes a monitor that is still captured under its name.
+pub fn plan_resume(suspended: &[(String, ScreenCaptureConfig)], monitors: &[MonitorInfo]) -> ResumePlan {
+    let mut plan = ResumePlan::default();
+    let mut claimed = HashSet::new();
+    let mut missing = Vec::new();
+
+    for (position, (name, config)) in suspended.iter().enumerate() {
+        let from = config.monitor_index;
+        match monitors.iter().find(|monitor| &monitor.name == name) {
+            Some(monitor) if claimed.insert(monitor.index) => {
+                if monitor.index != from {
+                    plan.changes.push(ResumeChange::Renumbered { monitor_name: name.clone(), from, to: monitor.index });
+                }
+                plan.restarts.push((position, ScreenCaptureConfig { monitor_index: monitor.index, ..config.clone() }));
+            },
+            _ => missing.push(position),
+        }
+    }
+
+    let primary = monitors.iter().find(|monitor| monitor.primary).or_else(|| monitors.first());
+    for position in missing {
+        let (name, config) = &suspended[position];
+        let from = config.monitor_index;
+        let reason = match primary {
+            Some(primary) if claimed.insert(primary.index) => {
+                plan.changes.push(ResumeChange::FellBackToPrimary {
+                    monitor_name: name.clone(),
+                    from,
+                    to: primary.index,
+                    primary_name: primary.name.clone(),
+                });
+                plan.restarts.push((position, ScreenCaptureConfig {
+                    monitor_index: primary.index,
+                    capture_region: None,
+                    ..config.clone()
+                }));
+                continue;
+            },
+            Some(primary) => format!("Monitor is gone and the primary monitor {} is already captured", primary.name),
+            None => "No monitors detected after resume".to_string(),
+        };
+        plan.changes.push(ResumeChange::NotRestarted { monitor_name: name.clone(), from, reason });
+    }
+
+    plan
+}
+
+/// Whether two monitor lists describe the same outputs at the same places
+pub fn monitors_changed(before: &[MonitorInfo], after: &[MonitorInfo]) -> bool {
+    let layout = |monitors: &[MonitorInfo]| -> Vec<(String, u32, u32, i32, i32)> {
+        monitors.iter()
+            .map(|monitor| (monitor.name.clone(), monitor.width, monitor.height, monitor.x_offset, monitor.y_offset))
+            .collect()
+    };
+    layout(before) != layout(after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen_capture::types::CaptureRegion;
+
+    fn monitor(index: usize, name: &str, primary: bool) -> MonitorInfo {
+        MonitorInfo {
+            index,
+            name: name.to_string(),
+            width: 1920,
+            height: 1080,
+            refresh_rate: Some(60.0),
+            primary,
+            x_offset: 1920 * index as i32,
+            y_offset: 0,
+            scale_factor: 1.0,
+            rotation: Default::default(),
+        }
+    }
+
+    fn suspended(name: &str, monitor_index: usize) -> (String, ScreenCaptureConfig) {
+        let config = ScreenCaptureConfig {
+            monitor_index,
+            capture_region: Some(CaptureRegion { x: 0, y: 0, width: 800, height: 600 }),
+            ..ScreenCaptureConfig::default()
+        };
+        (name.to_string(), config)
+    }
+
+    #[test]
+    fn test_captures_follow_their_monitor_by_name() {
+        // The dock's outputs came back in the opposite order
+        let before = vec![monitor(0, "eDP-1", true), monitor(1, "DP-1", false), monitor(2, "DP-2", false)];
+        let after = vec![monitor(0, "eDP-1", true), monitor(1, "DP-2", false), monitor(2, "DP-1", false)];
+        assert!(monitors_changed(&before, &after));
+        assert!(!monitors_changed(&before, &before));
+
+        let plan = plan_resume(&[suspended("DP-1", 1), suspended("eDP-1", 0)], &after);
+        let restarts: Vec<(usize, usize)> = plan.restarts.iter().map(|(position, config)| (*position, config.monitor_index)).collect();
+        assert_eq!(restarts, vec![(0, 2), (1, 0)]);
+        assert!(plan.restarts[0].1.capture_region.is_some(), "same monitor keeps its region");
+        assert_eq!(plan.changes, vec![ResumeChange::Renumbered { monitor_name: "DP-1".to_string(), from: 1, to: 2 }]);
+    }
+
+    #[test]
+    fn test_missing_monitor_falls_back_to_primary_once() {
+        let after = vec![monitor(0, "DP-2", false), monitor(1, "eDP-1", true)];
+
+        let plan = plan_resume(&[suspended("HDMI-1", 2)], &after);
+        assert_eq!(plan.restarts.len(), 1);
+        assert_eq!(plan.restarts[0].1.monitor_index, 1);
+        assert_eq!(plan.restarts[0].1.capture_region, None);
+        assert_eq!(plan.changes, vec![ResumeChange::FellBackToPrimary {
+            monitor_name: "HDMI-1".to_string(),
+            from: 2,
+            to: 1,
+            primary_name: "eDP-1".to_string(),
+        }]);
+
+        // The primary monitor is still captured under its own name
+        let plan = plan_resume(&[suspended("HDMI-1", 2), suspended("eDP-1", 0)], &after);
+        let restarts: Vec<(usize, usize)> = plan.restarts.iter().map(|(position, config)| (*position, config.monitor_index)).collect();
+        assert_eq!(restarts, vec![(1, 1)]);
+        assert!(matches!(&plan.changes[..], [
+            ResumeChange::Renumbered { .. },
+            ResumeChange::NotRestarted { from: 2, .. },
+        ]));
+
+        let plan = plan_resume(&[suspended("HDMI-1", 0)], &[]);
+        assert!(plan.restarts.is_empty());
+        assert_eq!(plan.changes.len(), 1);
+    }
+
+    #[test]
+    fn test_report_wire_format() {
+        let report = ResumeReport {
+            monitors_changed: true,
+            restarted: vec![1],
+            changes: vec![ResumeChange::FellBackToPrimary {
+                monitor_name: "HDMI-1".to_string(),
+                from: 2,
+                to: 1,
+                primary_name: "eDP-1".to_string(),
+            }],
+        };
+        assert_eq!(serde_json::to_value(&report).unwrap(), serde_json::json!({
+            "monitorsChanged": true,
+            "restarted": [1],
+            "changes": [{ "kind": "fellBackToPrimary", "monitorName": "HDMI-1", "from": 2, "to": 1, "primaryName": "eDP-1" }],
+        }));
+    }
+}
@@ -0,0 +1,77 @@
+// screen_capture/scale.rs - Downscaling of the encoded stream
+
+use crate::screen_capture::config::ScreenCaptureConfig;
+use crate::screen_capture::types::{HardwareAcceleration, VideoCodec};
+
+/// VAAPI device the scaler uploads frames to; the encoders use the same one
+const VAAPI_DEVICE: &str = "/dev/dri/renderD128";
+
+/// GPU that encodes the stream, if the capture pipelines use a hardware
+/// encoder for the configured codec
+fn gpu_encoder(config: &ScreenCaptureConfig) -> Option<HardwareAcceleration> {
+    match (&config.hardware_acceleration, &config.codec) {
+        (HardwareAcceleration::VAAPI, VideoCodec::H264 | VideoCodec::VP8 | VideoCodec::VP9) => Some(HardwareAcceleration::VAAPI),
+        (HardwareAcceleration::NVENC, VideoCodec::H264 | VideoCodec::AV1) => Some(HardwareAcceleration::NVENC),
+        _ => None,
+    }
+}
+
+/// ffmpeg filter that scales the captured frames to `width`x`height`.
+///
+/// With a VAAPI or NVENC encoder the frames are uploaded first and scaled on
+/// the GPU, so the encoder takes them from video memory instead of having
+/// them copied back. QuickSync and software encoders get a software scaler.
+/// Must come after software-only filters such as crop and mpdecimate.
+pub fn ffmpeg_scale_filter(config: &ScreenCaptureConfig, width: u32, height: u32) -> String {
+    match gpu_encoder(config) {
+        Some(HardwareAcceleration::VAAPI) => format!("format=nv12,hwupload,scale_vaapi=w={}:h={}", width, height),
+        Some(HardwareAcceleration::NVENC) => format!("hwupload_cuda,scale_cuda={}:{}", width, height),
+        _ => format!("scale={}:{}:flags=bicubic", width, height),
+    }
+}
+
+/// Global ffmpeg options the scale filter needs, e.g. the device `hwupload` uses
+pub fn ffmpeg_scale_device_args(config: &ScreenCaptureConfig) -> Vec<String> {
+    match gpu_encoder(config) {
+        Some(HardwareAcceleration::VAAPI) => vec!["-vaapi_device".to_string(), VAAPI_DEVICE.to_string()],
+        _ => Vec::new(),
+    }
+}
+
+/// GStreamer elements that scale the portal stream to `width`x`height`
+pub fn gstreamer_scale_elements(width: u32, height: u32) -> Vec<String> {
+    vec![
+        "videoscale".to_string(),
+        "!".to_string(),
+        format!("video/x-raw,width={},height={}", width, height),
+        "!".to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(hardware_acceleration: HardwareAcceleration, codec: VideoCodec) -> ScreenCaptureConfig {
+        ScreenCaptureConfig { hardware_acceleration, codec, ..Default::default() }
+    }
+
+    #[test]
+    fn test_scaler_follows_the_encoder() {
+        let vaapi = config(HardwareAcceleration::VAAPI, VideoCodec::H264);
+        assert_eq!(ffmpeg_scale_filter(&vaapi, 1920, 1080), "format=nv12,hwupload,scale_vaapi=w=1920:h=1080");
+        assert_eq!(ffmpeg_scale_device_args(&vaapi), vec!["-vaapi_device", "/dev/dri/renderD128"]);
+
+        let nvenc = config(HardwareAcceleration::NVENC, VideoCodec::H264);
+        assert_eq!(ffmpeg_scale_filter(&nvenc, 1280, 720), "hwupload_cuda,scale_cuda=1280:720");
+        assert!(ffmpeg_scale_device_args(&nvenc).is_empty());
+
+        // NVENC has no VP8 encoder, the pipeline falls back to libvpx
+        let fallback = config(HardwareAcceleration::NVENC, VideoCodec::VP8);
+        assert_eq!(ffmpeg_scale_filter(&fallback, 1280, 720), "scale=1280:720:flags=bicubic");
+
+        let software = config(HardwareAcceleration::None, VideoCodec::H264);
+        assert_eq!(ffmpeg_scale_filter(&software, 960, 540), "scale=960:540:flags=bicubic");
+        assert!(ffmpeg_scale_device_args(&software).is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+// screen_capture/stats_history.rs - Per-second history of capture statistics
+
+use std::collections::VecDeque;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::screen_capture::types::CaptureStats;
+
+/// How far back the history reaches
+pub const STATS_HISTORY_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// Statistics of one capture at one point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsSample {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub stats: CaptureStats,
+}
+
+/// Which part of the history to return; all fields are optional
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatsHistoryRange {
+    /// Oldest sample (inclusive), in milliseconds since the Unix epoch
+    pub from_ms: Option<u64>,
+    /// Newest sample (inclusive)
+    pub to_ms: Option<u64>,
+    /// Only samples of this monitor
+    pub monitor_index: Option<usize>,
+}
+
+/// Ring buffer of stats samples covering `STATS_HISTORY_WINDOW`
+#[derive(Debug, Default)]
+pub struct StatsHistory {
+    samples: VecDeque<StatsSample>,
+}
+
+impl StatsHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the stats of every running capture and drops samples that left the window
+    pub fn record(&mut self, stats: Vec<CaptureStats>, now_ms: u64) {
+        self.samples.extend(stats.into_iter().map(|stats| StatsSample { timestamp_ms: now_ms, stats }));
+
+        let oldest = now_ms.saturating_sub(STATS_HISTORY_WINDOW.as_millis() as u64);
+        while self.samples.front().is_some_and(|sample| sample.timestamp_ms < oldest) {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Samples within `range`, oldest first
+    pub fn query(&self, range: &StatsHistoryRange) -> Vec<StatsSample> {
+        self.samples.iter()
+            .filter(|sample| match range.from_ms {
+                Some(from) => sample.timestamp_ms >= from,
+                None => true,
+            })
+            .filter(|sample| match range.to_ms {
+                Some(to) => sample.timestamp_ms <= to,
+                None => true,
+            })
+            .filter(|sample| match range.monitor_index {
+                Some(index) => sample.stats.monitor_index == index,
+                None => true,
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(monitor_index: usize, fps: f64) -> CaptureStats {
+        CaptureStats {
+            monitor_index,
+            fps,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_history_keeps_fifteen_minutes() {
+        let mut history = StatsHistory::new();
+        for second in 0..20 * 60 {
+            history.record(vec![stats(0, 30.0), stats(1, 15.0)], second * 1000);
+        }
+
+        // 15 minutes plus the sample on the window's edge, for two monitors
+        let all = history.query(&StatsHistoryRange::default());
+        assert_eq!(all.len(), 2 * (15 * 60 + 1));
+        assert_eq!(all.first().unwrap().timestamp_ms, 5 * 60 * 1000 - 1000);
+        assert_eq!(all.last().unwrap().timestamp_ms, 20 * 60 * 1000 - 1000);
+    }
+
+    #[test]
+    fn test_query_filters_range_and_monitor() {
+        let mut history = StatsHistory::new();
+        for second in 0..10 {
+            history.record(vec![stats(0, second as f64), stats(1, 0.0)], second * 1000);
+        }
+
+        let range = StatsHistoryRange { from_ms: Some(3000), to_ms: Some(5000), monitor_index: Some(0) };
+        let fps: Vec<f64> = history.query(&range).iter().map(|sample| sample.stats.fps).collect();
+        assert_eq!(fps, vec![3.0, 4.0, 5.0]);
+    }
+}
@@ -0,0 +1,444 @@
+// screen_capture/supervisor.rs - Restart policy and stderr collection for capture processes
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::logging::TARGET_SCREEN_CAPTURE;
+use crate::screen_capture::config::ScreenCaptureConfig;
+use crate::screen_capture::error::ScreenCaptureError;
+use crate::screen_capture::types::{CaptureErrorReport, CaptureEvent, CaptureEventSink, CaptureStats, HardwareAcceleration};
+
+/// Number of stderr lines kept per capture process
+const STDERR_HISTORY_LINES: usize = 50;
+
+/// How long to wait for the last stderr lines after the process exited
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A process that ran at least this long is considered healthy again,
+/// so its restart budget is refilled
+pub const STABLE_RUN_DURATION: Duration = Duration::from_secs(30);
+
+/// stderr fragments (lowercase) that indicate the hardware encoder or device
+/// failed. Only error messages are listed: stream mapping lines name the
+/// hardware encoder on healthy runs too.
+const HARDWARE_FAILURE_SIGNATURES: &[&str] = &[
+    "failed to initialise vaapi",
+    "no va display",
+    "/dev/dri",
+    "cannot load libcuda",
+    "no nvenc capable devices",
+    "openencodesessionex failed",
+    "cuda_error",
+    "error initializing an internal mfx session",
+    "error creating a mfx session",
+    "failed to create a qsv device",
+    "device creation failed",
+];
+
+/// Exponential backoff for restarting a crashed capture process
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RestartPolicy {
+    pub fn new(max_attempts: u32) -> Self {
+        RestartPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+        }
+    }
+
+    /// Delay before the given restart attempt (1-based)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        self.initial_backoff
+            .saturating_mul(1u32 << exponent)
+            .min(self.max_backoff)
+    }
+}
+
+/// Sleeps for `duration` in short slices, returning early (with `false`) if
+/// `running` is cleared in the meantime
+pub fn sleep_while_running(running: &Arc<Mutex<bool>>, duration: Duration) -> bool {
+    let deadline = Instant::now() + duration;
+
+    while Instant::now() < deadline {
+        if !*running.lock().unwrap() {
+            return false;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        thread::sleep(remaining.min(Duration::from_millis(50)));
+    }
+
+    *running.lock().unwrap()
+}
+
+/// Keeps the last lines a capture process wrote to stderr.
+///
+/// stderr must be drained continuously, otherwise the process blocks once
+/// the pipe buffer is full.
+#[derive(Debug, Clone, Default)]
+pub struct StderrCollector {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    closed: Arc<AtomicBool>,
+}
+
+impl StderrCollector {
+    /// Starts a reader thread over the given stderr pipe
+    pub fn spawn<R: Read + Send + 'static>(stderr: Option<R>) -> Self {
+        let collector = StderrCollector::default();
+
+        if let Some(stderr) = stderr {
+            let lines = collector.lines.clone();
+            let closed = collector.closed.clone();
+            thread::spawn(move || {
+                for line in BufReader::new(stderr).lines() {
+                    let line = match line {
+                        Ok(line) => line,
+                        Err(_) => break,
+                    };
+                    let mut lines = lines.lock().unwrap();
+                    if lines.len() >= STDERR_HISTORY_LINES {
+                        lines.pop_front();
+                    }
+                    lines.push_back(line);
+                }
+                closed.store(true, Ordering::SeqCst);
+            });
+        } else {
+            collector.closed.store(true, Ordering::SeqCst);
+        }
+
+        collector
+    }
+
+    /// Records a line from an in-process pipeline, e.g. a GStreamer bus error
+    pub fn push(&self, line: String) {
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() >= STDERR_HISTORY_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// Marks the output as complete, so `wait_closed` returns right away
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+
+    /// Waits until the pipe is closed (the process exited) or the timeout
+    /// elapses, so the final error lines are not missed
+    pub fn wait_closed(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while !self.closed.load(Ordering::SeqCst) {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        true
+    }
+
+    /// Snapshot of the collected lines, oldest first
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Last non-empty line, usually ffmpeg's final error message
+    pub fn last_line(&self) -> Option<String> {
+        self.lines.lock().unwrap().iter()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .cloned()
+    }
+}
+
+/// Whether the stderr output points at the hardware encoder as the culprit
+pub fn is_hardware_acceleration_failure(lines: &[String]) -> bool {
+    lines.iter().any(|line| {
+        let line = line.to_lowercase();
+        HARDWARE_FAILURE_SIGNATURES.iter().any(|signature| line.contains(signature))
+    })
+}
+
+/// Maps well-known FFmpeg failure messages to typed errors
+pub fn classify_stderr(lines: &[String]) -> Option<ScreenCaptureError> {
+    for line in lines {
+        let lower = line.to_lowercase();
+        let message = line.trim().to_string();
+
+        if lower.contains("/dev/dri") && lower.contains("permission denied") {
+            return Some(ScreenCaptureError::DevicePermissionDenied(message));
+        }
+        if lower.contains("cannot load libcuda") {
+            return Some(ScreenCaptureError::CudaUnavailable(message));
+        }
+        if lower.contains("no such device") || lower.contains("failed to initialise vaapi") {
+            return Some(ScreenCaptureError::EncoderDeviceNotFound(message));
+        }
+        if lower.contains("unrecognized option") || lower.contains("option not found") {
+            return Some(ScreenCaptureError::UnsupportedOption(message));
+        }
+    }
+
+    None
+}
+
+/// Records a crash, reports a classified error, downgrades hardware
+/// acceleration if it was the cause and emits the matching events.
+/// Returns false if restarting cannot help or the restart budget is spent.
+pub fn prepare_restart(
+    config: &Arc<Mutex<ScreenCaptureConfig>>,
+    stats: &Arc<Mutex<CaptureStats>>,
+    event_sink: &CaptureEventSink,
+    policy: &RestartPolicy,
+    restart_attempt: &mut u32,
+    reason: String,
+    stderr: &StderrCollector,
+    spawn_error: Option<ScreenCaptureError>,
+) -> bool {
+    stderr.wait_closed(STDERR_DRAIN_TIMEOUT);
+    let lines = stderr.lines();
+
+    let reason = match stderr.last_line() {
+        Some(line) => format!("{}: {}", reason, line),
+        None => reason,
+    };
+
+    // Spawn failures carry their own error, everything else is read from stderr
+    let error = spawn_error.or_else(|| classify_stderr(&lines));
+    let unsupported_option = matches!(error, Some(ScreenCaptureError::UnsupportedOption(_)));
+    if let Some(error) = &error {
+        event_sink(CaptureEvent::Error(CaptureErrorReport {
+            kind: error.kind().to_string(),
+            message: error.to_string(),
+            stderr: lines.clone(),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+        }));
+    }
+
+    // The same command line fails the same way, so don't retry it
+    if unsupported_option {
+        log::error!(target: TARGET_SCREEN_CAPTURE, "Not restarting FFmpeg, the command line is not supported: {}", reason);
+        event_sink(CaptureEvent::Failed { reason });
+        return false;
+    }
+
+    if *restart_attempt >= policy.max_attempts {
+        log::error!(target: TARGET_SCREEN_CAPTURE, "Giving up on FFmpeg after {} restarts: {}", restart_attempt, reason);
+        event_sink(CaptureEvent::Failed { reason });
+        return false;
+    }
+
+    // Retry in software if the hardware encoder was the failing component
+    let hardware_error = matches!(
+        error,
+        Some(ScreenCaptureError::EncoderDeviceNotFound(_))
+            | Some(ScreenCaptureError::CudaUnavailable(_))
+            | Some(ScreenCaptureError::DevicePermissionDenied(_))
+    );
+    if hardware_error || is_hardware_acceleration_failure(&lines) {
+        let mut config_guard = config.lock().unwrap();
+        // Software encoding drains the battery faster than no stream at all
+        if config_guard.hardware_acceleration != HardwareAcceleration::None && !config_guard.software_fallback {
+            let reason = format!("Hardware acceleration {:?} failed and software encoding is not allowed: {}", config_guard.hardware_acceleration, reason);
+            log::error!(target: TARGET_SCREEN_CAPTURE, "Not restarting FFmpeg: {}", reason);
+            event_sink(CaptureEvent::Failed { reason });
+            return false;
+        }
+        if config_guard.hardware_acceleration != HardwareAcceleration::None {
+            let previous = std::mem::replace(&mut config_guard.hardware_acceleration, HardwareAcceleration::None);
+            log::warn!(target: TARGET_SCREEN_CAPTURE, "Hardware acceleration {:?} failed, falling back to software encoding", previous);
+            stats.lock().unwrap().hardware_acceleration_fallback = Some(previous.clone());
+            event_sink(CaptureEvent::HardwareAccelerationDisabled { previous });
+        }
+    }
+
+    *restart_attempt += 1;
+    stats.lock().unwrap().restart_count += 1;
+
+    let backoff = policy.backoff(*restart_attempt);
+    log::warn!(target: TARGET_SCREEN_CAPTURE, "Capture interrupted ({}), restart {}/{} in {:?}", reason, restart_attempt, policy.max_attempts, backoff);
+    event_sink(CaptureEvent::Interrupted {
+        reason,
+        attempt: *restart_attempt,
+        max_attempts: policy.max_attempts,
+        retry_in_ms: backoff.as_millis() as u64,
+    });
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen_capture::types::CaptureActivity;
+
+    #[test]
+    fn test_backoff_is_exponential_and_capped() {
+        let policy = RestartPolicy::new(5);
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3), Duration::from_secs(2));
+        assert_eq!(policy.backoff(10), Duration::from_secs(8));
+    }
+
+    #[test]
+    fn test_hardware_failure_detection() {
+        let vaapi = vec!["[AVHWDeviceContext @ 0x5] Failed to initialise VAAPI connection: -1 (unknown libva error).".to_string()];
+        let cuda = vec!["Cannot load libcuda.so.1".to_string()];
+        let pipe = vec!["av_interleaved_write_frame(): Broken pipe".to_string()];
+
+        assert!(is_hardware_acceleration_failure(&vaapi));
+        assert!(is_hardware_acceleration_failure(&cuda));
+        assert!(!is_hardware_acceleration_failure(&pipe));
+    }
+
+    #[test]
+    fn test_classify_known_ffmpeg_errors() {
+        let classify = |line: &str| classify_stderr(&[line.to_string()]).map(|e| e.kind());
+
+        assert_eq!(classify("[AVHWDeviceContext @ 0x1] Failed to open /dev/dri/renderD128: Permission denied"), Some("DevicePermissionDenied"));
+        assert_eq!(classify("Cannot load libcuda.so.1"), Some("CudaUnavailable"));
+        assert_eq!(classify("[h264_vaapi @ 0x2] No such device"), Some("EncoderDeviceNotFound"));
+        assert_eq!(classify("Unrecognized option 'zerolatency'."), Some("UnsupportedOption"));
+        assert_eq!(classify("frame=  100 fps= 30 q=23.0 size=512kB"), None);
+    }
+
+    /// Writes an executable shell script standing in for ffmpeg
+    #[cfg(unix)]
+    fn fake_ffmpeg(name: &str, stderr_output: &str) -> std::path::PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("smoldesk-fake-ffmpeg-{}-{}", name, std::process::id()));
+        let script = format!("#!/bin/sh\nprintf '%s\\n' \"{}\" >&2\nexit 1\n", stderr_output);
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[cfg(unix)]
+    fn run_fake(name: &str, stderr_output: &str) -> StderrCollector {
+        let binary = fake_ffmpeg(name, stderr_output);
+        let mut child = std::process::Command::new(&binary)
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let collector = StderrCollector::spawn(child.stderr.take());
+        child.wait().unwrap();
+        assert!(collector.wait_closed(Duration::from_secs(2)));
+        let _ = std::fs::remove_file(binary);
+        collector
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fake_binary_errors_are_classified() {
+        let cuda = run_fake("cuda", "Cannot load libcuda.so.1");
+        assert!(matches!(classify_stderr(&cuda.lines()), Some(ScreenCaptureError::CudaUnavailable(_))));
+
+        let option = run_fake("option", "Unrecognized option 'low_power'.");
+        assert!(matches!(classify_stderr(&option.lines()), Some(ScreenCaptureError::UnsupportedOption(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unsupported_option_stops_restarts() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let sink: CaptureEventSink = Arc::new(move |event| recorded.lock().unwrap().push(event));
+
+        let config = Arc::new(Mutex::new(ScreenCaptureConfig::default()));
+        let stats = Arc::new(Mutex::new(CaptureStats {
+            monitor_index: 0,
+            fps: 0.0,
+            bitrate: 0,
+            encode_time: 0.0,
+            frame_size: 0,
+            frame_count: 0,
+            dropped_frames: 0,
+            buffer_level: 0,
+            latency_estimate: 0.0,
+            restart_count: 0,
+            hardware_acceleration_fallback: None,
+            transport_throughput: 0,
+            activity: CaptureActivity::Active,
+            time_since_keyframe_ms: None,
+            host_session: None,
+            skipped_frames: 0,
+            freeze_recoveries: 0,
+            gpu: None,
+        }));
+        let stderr = run_fake("restart", "Unrecognized option 'low_power'.");
+        let mut attempt = 0;
+
+        let restart = prepare_restart(&config, &stats, &sink, &RestartPolicy::new(5), &mut attempt, "exited".to_string(), &stderr, None);
+
+        assert!(!restart);
+        let events = events.lock().unwrap();
+        match &events[0] {
+            CaptureEvent::Error(report) => {
+                assert_eq!(report.kind, "UnsupportedOption");
+                assert_eq!(report.stderr, vec!["Unrecognized option 'low_power'.".to_string()]);
+            },
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert!(matches!(events[1], CaptureEvent::Failed { .. }));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_forbidden_software_fallback_stops_restarts() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let sink: CaptureEventSink = Arc::new(move |event| recorded.lock().unwrap().push(event));
+
+        // As set by the low battery power profile
+        let config = Arc::new(Mutex::new(ScreenCaptureConfig {
+            hardware_acceleration: HardwareAcceleration::VAAPI,
+            software_fallback: false,
+            ..ScreenCaptureConfig::default()
+        }));
+        let stats = Arc::new(Mutex::new(CaptureStats::default()));
+        let stderr = run_fake("no-fallback", "Cannot load libcuda.so.1");
+        let mut attempt = 0;
+
+        let restart = prepare_restart(&config, &stats, &sink, &RestartPolicy::new(5), &mut attempt, "exited".to_string(), &stderr, None);
+
+        assert!(!restart);
+        assert_eq!(config.lock().unwrap().hardware_acceleration, HardwareAcceleration::VAAPI);
+        assert_eq!(stats.lock().unwrap().hardware_acceleration_fallback, None);
+        assert!(matches!(events.lock().unwrap().last(), Some(CaptureEvent::Failed { .. })));
+    }
+
+    #[test]
+    fn test_stderr_collector_keeps_last_lines() {
+        let input: String = (0..60).map(|i| format!("line {}\n", i)).collect();
+        let collector = StderrCollector::spawn(Some(std::io::Cursor::new(input.into_bytes())));
+
+        // Reader thread finishes as soon as the cursor is exhausted
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while collector.last_line().as_deref() != Some("line 59") && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let lines = collector.lines();
+        assert_eq!(lines.len(), STDERR_HISTORY_LINES);
+        assert_eq!(lines[0], "line 10");
+        assert_eq!(collector.last_line().as_deref(), Some("line 59"));
+    }
+}
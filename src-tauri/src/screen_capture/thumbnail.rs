@@ -0,0 +1,446 @@
+// screen_capture/thumbnail.rs - Small still images of the monitors for the monitor picker
+//
+// A thumbnail needs no capture session: one grab of the desktop area covering
+// all requested monitors, x11grab on X11 and grim on wlroots compositors, is
+// cut into the monitors in-process and encoded as JPEG. The grab is already
+// downscaled to what the widest thumbnail needs, so a tick costs one short
+// tool invocation and a few hundred kilobytes, however many monitors there
+// are. Grabs are serialized: a one-off request waits for a running tick.
+//
+// Monitors with a running capture are left alone unless forced; a second
+// x11grab or screencopy competes with the encoder for the same frames.
+
+use std::io::Cursor;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::command_runner::CommandRunner;
+use crate::logging::TARGET_SCREEN_CAPTURE;
+use crate::screen_capture::error::ScreenCaptureError;
+use crate::screen_capture::types::{DisplayServer, MonitorInfo};
+use crate::screen_capture::utils;
+
+/// Width of thumbnails when the frontend asks for none
+pub const DEFAULT_THUMBNAIL_WIDTH: u32 = 320;
+
+/// Widest thumbnail served; anything larger is a screenshot
+pub const MAX_THUMBNAIL_WIDTH: u32 = 1280;
+
+/// Interval of thumbnail events when the frontend asks for none
+pub const DEFAULT_THUMBNAIL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Shortest interval between two thumbnail events
+pub const MIN_THUMBNAIL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Enough to recognize a screen, small enough to send every second
+const JPEG_QUALITY: u8 = 60;
+
+/// A still image of one monitor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorThumbnail {
+    pub monitor_index: usize,
+    pub width: u32,
+    pub height: u32,
+
+    /// Base64 JPEG
+    pub data: String,
+
+    /// Milliseconds since the Unix epoch
+    pub captured_at: u64,
+}
+
+/// One tick of a thumbnail subscription; emitted as `monitor_thumbnails`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailBatch {
+    pub thumbnails: Vec<MonitorThumbnail>,
+
+    /// Monitors left out because they are being captured
+    pub skipped: Vec<usize>,
+}
+
+/// Monitors to grab for a thumbnail request
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThumbnailTargets {
+    pub monitors: Vec<MonitorInfo>,
+    pub skipped: Vec<usize>,
+}
+
+/// Picks the monitors to grab: `monitor_index`, or all monitors if None.
+/// A single monitor with a running capture is refused, from all monitors
+/// the captured ones are skipped; `force` grabs them anyway.
+pub fn select_targets(
+    monitors: &[MonitorInfo],
+    active_captures: &[usize],
+    monitor_index: Option<usize>,
+    force: bool,
+) -> Result<ThumbnailTargets, ScreenCaptureError> {
+    let busy = |monitor: &MonitorInfo| !force && active_captures.contains(&monitor.index);
+
+    match monitor_index {
+        Some(index) => {
+            let monitor = monitors.iter().find(|monitor| monitor.index == index).ok_or_else(|| {
+                ScreenCaptureError::InvalidMonitor(format!("Monitor index {} out of bounds (0-{})", index, monitors.len().saturating_sub(1)))
+            })?;
+            if busy(monitor) {
+                return Err(ScreenCaptureError::MonitorBusy(format!(
+                    "Monitor {} is being captured, a thumbnail would compete with its encoder", index
+                )));
+            }
+            Ok(ThumbnailTargets { monitors: vec![monitor.clone()], skipped: Vec::new() })
+        },
+        None => {
+            let (skipped, monitors): (Vec<&MonitorInfo>, Vec<&MonitorInfo>) = monitors.iter().partition(|monitor| busy(monitor));
+            Ok(ThumbnailTargets {
+                monitors: monitors.into_iter().cloned().collect(),
+                skipped: skipped.into_iter().map(|monitor| monitor.index).collect(),
+            })
+        },
+    }
+}
+
+/// Clamps a requested thumbnail width to 16-`MAX_THUMBNAIL_WIDTH`
+pub fn thumbnail_width(max_width: Option<u32>) -> u32 {
+    max_width.unwrap_or(DEFAULT_THUMBNAIL_WIDTH).clamp(16, MAX_THUMBNAIL_WIDTH)
+}
+
+/// Monitor area in the coordinates the grab tool takes: pixels on X11,
+/// the compositor's logical layout on Wayland
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LayoutRect {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+impl LayoutRect {
+    fn of(monitor: &MonitorInfo, display_server: &DisplayServer) -> Self {
+        let scale = match display_server {
+            DisplayServer::Wayland if monitor.scale_factor > 0.0 => monitor.scale_factor,
+            _ => 1.0,
+        };
+        LayoutRect {
+            x: monitor.x_offset,
+            y: monitor.y_offset,
+            width: ((monitor.width as f64 / scale).round() as u32).max(1),
+            height: ((monitor.height as f64 / scale).round() as u32).max(1),
+        }
+    }
+
+    /// Smallest rectangle containing all of `rects`
+    fn bounding(rects: &[LayoutRect]) -> Option<Self> {
+        let left = rects.iter().map(|rect| rect.x).min()?;
+        let top = rects.iter().map(|rect| rect.y).min()?;
+        let right = rects.iter().map(|rect| rect.x + rect.width as i32).max()?;
+        let bottom = rects.iter().map(|rect| rect.y + rect.height as i32).max()?;
+        Some(LayoutRect { x: left, y: top, width: (right - left) as u32, height: (bottom - top) as u32 })
+    }
+}
+
+/// Grabs the desktop once per call and cuts it into monitor thumbnails
+#[derive(Clone)]
+pub struct ThumbnailGrabber {
+    runner: Arc<dyn CommandRunner>,
+    display_server: DisplayServer,
+
+    /// Held for the duration of a grab
+    gate: Arc<Mutex<()>>,
+}
+
+impl ThumbnailGrabber {
+    pub fn new(runner: Arc<dyn CommandRunner>, display_server: DisplayServer) -> Self {
+        ThumbnailGrabber { runner, display_server, gate: Arc::new(Mutex::new(())) }
+    }
+
+    /// Thumbnails of `monitors`, none wider than `max_width`
+    pub fn grab(&self, monitors: &[MonitorInfo], max_width: u32) -> Result<Vec<MonitorThumbnail>, ScreenCaptureError> {
+        let _gate = self.gate.lock().unwrap();
+
+        let rects: Vec<LayoutRect> = monitors.iter().map(|monitor| LayoutRect::of(monitor, &self.display_server)).collect();
+        let area = match LayoutRect::bounding(&rects) {
+            Some(area) => area,
+            None => return Ok(Vec::new()),
+        };
+
+        // Grabbed no larger than the monitor that needs the most pixels
+        let scale = rects.iter()
+            .map(|rect| max_width as f64 / rect.width as f64)
+            .fold(0.0, f64::max)
+            .min(1.0);
+        let desktop = self.grab_area(area, scale)?;
+        if desktop.width() == 0 || desktop.height() == 0 {
+            return Err(self.grab_error("The grabbed image is empty".to_string()));
+        }
+
+        let captured_at = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+        let (scale_x, scale_y) = (desktop.width() as f64 / area.width as f64, desktop.height() as f64 / area.height as f64);
+        monitors.iter().zip(&rects)
+            .map(|(monitor, rect)| {
+                let x = (((rect.x - area.x) as f64 * scale_x) as u32).min(desktop.width() - 1);
+                let y = (((rect.y - area.y) as f64 * scale_y) as u32).min(desktop.height() - 1);
+                let width = ((rect.width as f64 * scale_x).round() as u32).clamp(1, desktop.width() - x);
+                let height = ((rect.height as f64 * scale_y).round() as u32).clamp(1, desktop.height() - y);
+
+                let mut image = desktop.crop_imm(x, y, width, height);
+                if image.width() > max_width {
+                    image = image.resize(max_width, u32::MAX, image::imageops::FilterType::Triangle);
+                }
+                // JPEG has no alpha channel
+                let image = image::DynamicImage::ImageRgb8(image.to_rgb8());
+                let mut jpeg = Vec::new();
+                image.write_to(&mut Cursor::new(&mut jpeg), image::ImageOutputFormat::Jpeg(JPEG_QUALITY))
+                    .map_err(|e| ScreenCaptureError::EncodingError(format!("Cannot encode thumbnail: {}", e)))?;
+
+                Ok(MonitorThumbnail {
+                    monitor_index: monitor.index,
+                    width: image.width(),
+                    height: image.height(),
+                    data: utils::frame_to_base64(&jpeg),
+                    captured_at,
+                })
+            })
+            .collect()
+    }
+
+    /// One frame of `area`, scaled by `scale`, as decoded image
+    fn grab_area(&self, area: LayoutRect, scale: f64) -> Result<image::DynamicImage, ScreenCaptureError> {
+        let mut cmd = match self.display_server {
+            DisplayServer::X11 => {
+                let width = ((area.width as f64 * scale).round() as u32).max(1);
+                let height = ((area.height as f64 * scale).round() as u32).max(1);
+                let mut cmd = self.runner.command("ffmpeg");
+                cmd.args(["-hide_banner", "-loglevel", "error", "-nostdin", "-threads", "1"])
+                    .args(["-f", "x11grab", "-draw_mouse", "0"])
+                    .arg("-video_size").arg(format!("{}x{}", area.width, area.height))
+                    .arg("-i").arg(format!(":0.0+{},{}", area.x, area.y))
+                    .args(["-frames:v", "1"])
+                    .arg("-vf").arg(format!("scale={}:{}:flags=fast_bilinear", width, height))
+                    .args(["-f", "image2pipe", "-vcodec", "ppm", "-"]);
+                cmd
+            },
+            // screencopy of the layout region, rendered at the thumbnail scale
+            DisplayServer::Wayland => {
+                let mut cmd = self.runner.command("grim");
+                cmd.arg("-s").arg(format!("{:.3}", scale))
+                    .arg("-g").arg(format!("{},{} {}x{}", area.x, area.y, area.width, area.height))
+                    .args(["-t", "ppm", "-"]);
+                cmd
+            },
+            DisplayServer::Unknown => {
+                return Err(ScreenCaptureError::DisplayServerError("Unsupported display server".to_string()));
+            },
+        };
+
+        let tool = if self.display_server == DisplayServer::X11 { "ffmpeg" } else { "grim" };
+        let output = cmd.stdin(Stdio::null()).output().map_err(|e| self.grab_error(format!("Cannot run {}: {}", tool, e)))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(self.grab_error(format!("{} exited with {}: {}", tool, output.status, stderr.lines().last().unwrap_or_default())));
+        }
+
+        image::load_from_memory_with_format(&output.stdout, image::ImageFormat::Pnm)
+            .map_err(|e| self.grab_error(format!("Cannot decode the {} image: {}", tool, e)))
+    }
+
+    fn grab_error(&self, message: String) -> ScreenCaptureError {
+        match self.display_server {
+            DisplayServer::X11 => ScreenCaptureError::FFmpegError(message),
+            // grim needs wlr-screencopy; GNOME and KDE only share through the portal
+            _ => ScreenCaptureError::DisplayServerError(format!("{}. Thumbnails on Wayland need grim and a wlroots compositor", message)),
+        }
+    }
+}
+
+/// Sends thumbnails of all monitors at a fixed interval until stopped
+pub struct ThumbnailStream {
+    stopped: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ThumbnailStream {
+    /// Every `interval`, asks `targets` which monitors to grab, grabs them
+    /// and hands the batch to `handler`. A failing grab is logged and
+    /// retried on the next tick; a tick without monitors grabs nothing.
+    pub fn start<T, F>(grabber: ThumbnailGrabber, interval: Duration, max_width: u32, targets: T, handler: F) -> Self
+    where
+        T: Fn() -> Option<ThumbnailTargets> + Send + 'static,
+        F: Fn(ThumbnailBatch) + Send + 'static,
+    {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = stopped.clone();
+        let thread = thread::spawn(move || {
+            while !thread_stopped.load(Ordering::SeqCst) {
+                if let Some(ThumbnailTargets { monitors, skipped }) = targets() {
+                    match grabber.grab(&monitors, max_width) {
+                        Ok(thumbnails) => handler(ThumbnailBatch { thumbnails, skipped }),
+                        Err(e) => log::debug!(target: TARGET_SCREEN_CAPTURE, "Thumbnail grab failed: {}", e),
+                    }
+                }
+                sleep_unless_stopped(&thread_stopped, interval);
+            }
+        });
+
+        ThumbnailStream { stopped, thread: Some(thread) }
+    }
+
+    pub fn stop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for ThumbnailStream {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Sleeps in short steps so stop() does not wait for a whole interval
+fn sleep_unless_stopped(stopped: &AtomicBool, duration: Duration) {
+    let step = Duration::from_millis(50);
+    let mut slept = Duration::ZERO;
+    while slept < duration && !stopped.load(Ordering::SeqCst) {
+        thread::sleep(step);
+        slept += step;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use crate::screen_capture::types::MonitorRotation;
+    use crate::test_env::{call, TestEnv};
+    use base64::{Engine as _, engine::general_purpose};
+
+    fn monitor(index: usize, x_offset: i32, width: u32, height: u32, scale_factor: f64) -> MonitorInfo {
+        MonitorInfo {
+            index,
+            name: format!("DP-{}", index + 1),
+            width,
+            height,
+            refresh_rate: None,
+            primary: index == 0,
+            x_offset,
+            y_offset: 0,
+            scale_factor,
+            rotation: MonitorRotation::Normal,
+        }
+    }
+
+    /// PPM of a desktop whose left `split` columns are red and the rest blue
+    fn desktop_ppm(width: u32, height: u32, split: u32) -> Vec<u8> {
+        let image = image::RgbImage::from_fn(width, height, |x, _| {
+            if x < split { image::Rgb([255, 0, 0]) } else { image::Rgb([0, 0, 255]) }
+        });
+        let mut ppm = Vec::new();
+        image::DynamicImage::ImageRgb8(image)
+            .write_to(&mut Cursor::new(&mut ppm), image::ImageOutputFormat::Pnm(image::codecs::pnm::PnmSubtype::Pixmap(image::codecs::pnm::SampleEncoding::Binary)))
+            .unwrap();
+        ppm
+    }
+
+    fn center_pixel(thumbnail: &MonitorThumbnail) -> [u8; 3] {
+        let jpeg = general_purpose::STANDARD.decode(&thumbnail.data).unwrap();
+        let image = image::load_from_memory_with_format(&jpeg, image::ImageFormat::Jpeg).unwrap().to_rgb8();
+        assert_eq!((image.width(), image.height()), (thumbnail.width, thumbnail.height));
+        image.get_pixel(image.width() / 2, image.height() / 2).0
+    }
+
+    #[test]
+    fn test_one_grab_is_cut_into_all_monitors() {
+        let env = TestEnv::new();
+        // The 1280 wide monitor needs a quarter of the desktop's pixels for 320
+        env.set_stdout("ffmpeg", desktop_ppm(800, 270, 480));
+        let grabber = ThumbnailGrabber::new(env.runner(), DisplayServer::X11);
+        let monitors = vec![monitor(0, 0, 1920, 1080, 1.0), monitor(1, 1920, 1280, 1024, 1.0)];
+
+        let thumbnails = grabber.grab(&monitors, 320).unwrap();
+        assert_eq!(env.take_invocations(), vec![call(
+            "ffmpeg -hide_banner -loglevel error -nostdin -threads 1 -f x11grab -draw_mouse 0 -video_size 3200x1080 -i :0.0+0,0 \
+             -frames:v 1 -vf scale=800:270:flags=fast_bilinear -f image2pipe -vcodec ppm -"
+        )]);
+
+        assert_eq!(thumbnails.len(), 2);
+        assert_eq!((thumbnails[0].monitor_index, thumbnails[0].width, thumbnails[0].height), (0, 320, 180));
+        assert_eq!((thumbnails[1].monitor_index, thumbnails[1].width, thumbnails[1].height), (1, 320, 256));
+        let [red, _, blue] = center_pixel(&thumbnails[0]);
+        assert!(red > 200 && blue < 50);
+        let [red, _, blue] = center_pixel(&thumbnails[1]);
+        assert!(red < 50 && blue > 200);
+    }
+
+    #[test]
+    fn test_wayland_grabs_the_logical_layout_with_grim() {
+        let env = TestEnv::new();
+        env.set_stdout("grim", desktop_ppm(320, 180, 320));
+        let grabber = ThumbnailGrabber::new(env.runner(), DisplayServer::Wayland);
+
+        // A 4K panel at 200% covers 1920x1080 of the layout
+        let thumbnails = grabber.grab(&[monitor(0, 1504, 3840, 2160, 2.0)], 320).unwrap();
+        assert_eq!(env.take_invocations(), vec![
+            vec!["grim".to_string(), "-s".to_string(), "0.167".to_string(), "-g".to_string(), "1504,0 1920x1080".to_string(), "-t".to_string(), "ppm".to_string(), "-".to_string()],
+        ]);
+        assert_eq!((thumbnails[0].width, thumbnails[0].height), (320, 180));
+
+        env.set_exit_code("grim", 1);
+        env.set_stderr("grim", "compositor doesn't support wlr-screencopy-unstable-v1\n");
+        let error = grabber.grab(&[monitor(0, 0, 1920, 1080, 1.0)], 320).unwrap_err();
+        assert!(matches!(error, ScreenCaptureError::DisplayServerError(ref message) if message.contains("wlr-screencopy")), "{}", error);
+    }
+
+    #[test]
+    fn test_captured_monitors_are_refused_unless_forced() {
+        let monitors = vec![monitor(0, 0, 1920, 1080, 1.0), monitor(1, 1920, 1920, 1080, 1.0)];
+
+        let error = select_targets(&monitors, &[1], Some(1), false).unwrap_err();
+        assert_eq!(error.kind(), "MonitorBusy");
+        assert_eq!(select_targets(&monitors, &[1], Some(1), true).unwrap().monitors, vec![monitors[1].clone()]);
+        assert_eq!(select_targets(&monitors, &[1], Some(0), false).unwrap().monitors, vec![monitors[0].clone()]);
+        assert!(matches!(select_targets(&monitors, &[], Some(2), false), Err(ScreenCaptureError::InvalidMonitor(_))));
+
+        let all = select_targets(&monitors, &[1], None, false).unwrap();
+        assert_eq!((all.monitors, all.skipped), (vec![monitors[0].clone()], vec![1]));
+        assert_eq!(select_targets(&monitors, &[1], None, true).unwrap().monitors.len(), 2);
+
+        assert_eq!(thumbnail_width(None), DEFAULT_THUMBNAIL_WIDTH);
+        assert_eq!(thumbnail_width(Some(10_000)), MAX_THUMBNAIL_WIDTH);
+    }
+
+    #[test]
+    fn test_stream_grabs_once_per_tick() {
+        let env = TestEnv::new();
+        env.set_stdout("ffmpeg", desktop_ppm(640, 180, 320));
+        let grabber = ThumbnailGrabber::new(env.runner(), DisplayServer::X11);
+        let monitors = vec![monitor(0, 0, 1920, 1080, 1.0), monitor(1, 1920, 1920, 1080, 1.0)];
+
+        let (sender, receiver) = mpsc::channel();
+        let mut stream = ThumbnailStream::start(
+            grabber,
+            Duration::from_millis(50),
+            DEFAULT_THUMBNAIL_WIDTH,
+            move || Some(ThumbnailTargets { monitors: monitors.clone(), skipped: vec![2] }),
+            move |batch| {
+                let _ = sender.send(batch);
+            },
+        );
+        for _ in 0..2 {
+            let batch = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+            assert_eq!(batch.thumbnails.iter().map(|thumbnail| thumbnail.monitor_index).collect::<Vec<_>>(), vec![0, 1]);
+            assert_eq!(batch.skipped, vec![2]);
+        }
+        stream.stop();
+
+        let batches = 2 + receiver.try_iter().count();
+        assert_eq!(env.invocations().len(), batches);
+    }
+}
@@ -2,6 +2,10 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::host_session::HostSessionState;
+use crate::screen_capture::gpu_stats::GpuUsage;
+use crate::screen_capture::watchdog::FreezeKind;
+
 /// Display server type
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum DisplayServer {
@@ -11,7 +15,7 @@ pub enum DisplayServer {
 }
 
 /// Video codec options
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum VideoCodec {
     H264,
     VP8,
@@ -20,7 +24,7 @@ pub enum VideoCodec {
 }
 
 /// Hardware acceleration options
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum HardwareAcceleration {
     None,
     VAAPI,
@@ -36,6 +40,59 @@ pub enum LatencyMode {
     Quality,   // Higher quality, possibly at the expense of latency
 }
 
+/// How encoded frames are delivered to the frontend
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum FrameTransport {
+    #[default]
+    WebSocket, // Binary messages over a localhost WebSocket
+    Event,     // Base64 in `frame_data` window events (fallback)
+}
+
+/// Pipeline that encodes the captured frames
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum EncoderBackendKind {
+    #[default]
+    Auto,      // Picked from the encoder capability probe
+    FFmpeg,    // External ffmpeg process (gst-launch for portal streams)
+    GStreamer, // In-process GStreamer pipeline, Wayland only
+}
+
+/// How the mouse pointer appears in the stream
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CursorMode {
+    Embedded,   // Drawn into the video by the capture backend
+    Hidden,     // Not shown at all
+    ClientSide, // Left out of the video; positions are sent as cursor_position events
+}
+
+/// Monitor rotation, named like xrandr's --rotate values
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum MonitorRotation {
+    #[default]
+    Normal,
+    Left,     // 90° counter-clockwise
+    Right,    // 90° clockwise
+    Inverted, // 180°
+}
+
+impl MonitorRotation {
+    /// Parses xrandr rotation names ("left") and Wayland output transforms ("90")
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "normal" | "0" => Some(MonitorRotation::Normal),
+            // wl_output transforms count counter-clockwise
+            "left" | "90" => Some(MonitorRotation::Left),
+            "right" | "270" => Some(MonitorRotation::Right),
+            "inverted" | "180" => Some(MonitorRotation::Inverted),
+            _ => None,
+        }
+    }
+}
+
+fn default_scale_factor() -> f64 {
+    1.0
+}
+
 /// Monitor information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MonitorInfo {
@@ -47,11 +104,65 @@ pub struct MonitorInfo {
     pub primary: bool,
     pub x_offset: i32,
     pub y_offset: i32,
+    #[serde(default = "default_scale_factor")]
+    pub scale_factor: f64,
+    #[serde(default)]
+    pub rotation: MonitorRotation,
+}
+
+/// Rectangle to capture, relative to the monitor's top-left corner
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CaptureRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CaptureRegion {
+    /// The whole monitor
+    pub fn full(monitor: &MonitorInfo) -> Self {
+        CaptureRegion { x: 0, y: 0, width: monitor.width, height: monitor.height }
+    }
+    
+    /// Checks that the region lies within the monitor and can be encoded
+    pub fn validate(&self, monitor: &MonitorInfo) -> Result<(), crate::screen_capture::error::ScreenCaptureError> {
+        use crate::screen_capture::error::ScreenCaptureError;
+        
+        if self.width < 2 || self.height < 2 {
+            return Err(ScreenCaptureError::InvalidRegion(format!(
+                "{}x{} is too small, at least 2x2 pixels are required", self.width, self.height
+            )));
+        }
+        
+        let right = self.x as u64 + self.width as u64;
+        let bottom = self.y as u64 + self.height as u64;
+        if right > monitor.width as u64 || bottom > monitor.height as u64 {
+            return Err(ScreenCaptureError::InvalidRegion(format!(
+                "{}x{}+{}+{} exceeds monitor {} ({}x{})",
+                self.width, self.height, self.x, self.y, monitor.name, monitor.width, monitor.height
+            )));
+        }
+        
+        Ok(())
+    }
+    
+    /// Same region with even width and height, as yuv420p encoders require
+    pub fn even(&self) -> Self {
+        CaptureRegion { width: self.width & !1, height: self.height & !1, ..*self }
+    }
+    
+    /// Desktop coordinates of the region's top-left corner
+    pub fn origin(&self, monitor: &MonitorInfo) -> (i32, i32) {
+        (monitor.x_offset + self.x as i32, monitor.y_offset + self.y as i32)
+    }
 }
 
 /// Statistics for screen capturing
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CaptureStats {
+    #[serde(default)]
+    pub monitor_index: usize,   // Monitor this capture session belongs to
     pub fps: f64,
     pub bitrate: u64,
     pub encode_time: f64,
@@ -60,17 +171,97 @@ pub struct CaptureStats {
     pub dropped_frames: u64,
     pub buffer_level: usize,    // Buffer fill level
     pub latency_estimate: f64,  // Estimated latency in ms
+    pub restart_count: u32,     // Capture process restarts after crashes
+    pub hardware_acceleration_fallback: Option<HardwareAcceleration>, // Accelerator disabled after a failure
+    #[serde(default)]
+    pub transport_throughput: u64, // Bytes per second delivered to the frontend
+    #[serde(default)]
+    pub activity: CaptureActivity, // Whether the screen is changing or throttled as idle
+    #[serde(default)]
+    pub time_since_keyframe_ms: Option<u64>, // None until the first keyframe
+    #[serde(default)]
+    pub host_session: Option<HostSessionState>, // Lock and DPMS state, explains a black stream
+    #[serde(default)]
+    pub skipped_frames: u64,    // Frames not grabbed because damage tracking saw no change
+    #[serde(default)]
+    pub freeze_recoveries: u32, // Encoder restarts because no frames arrived
+    #[serde(default)]
+    pub gpu: Option<GpuUsage>,  // Encoder load and memory with hardware encoding, None without a source
+}
+
+/// Whether the captured screen is changing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CaptureActivity {
+    /// Frames are encoded at the configured frame rate
+    #[default]
+    Active,
+    /// Nothing changed for a while; frames are encoded at the idle frame rate
+    Idle,
 }
 
+/// Lifecycle events of the supervised capture process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum CaptureEvent {
+    /// The capture process exited unexpectedly and a restart is scheduled
+    Interrupted {
+        reason: String,
+        attempt: u32,
+        max_attempts: u32,
+        retry_in_ms: u64,
+    },
+    /// A restarted process is delivering frames again
+    Resumed {
+        attempt: u32,
+    },
+    /// Hardware acceleration failed and capture continues in software
+    HardwareAccelerationDisabled {
+        previous: HardwareAcceleration,
+    },
+    /// The capture process reported an error, classified from its stderr
+    Error(CaptureErrorReport),
+    /// All restart attempts are exhausted
+    Failed {
+        reason: String,
+    },
+    /// The screen became static or started changing again
+    ActivityChanged {
+        activity: CaptureActivity,
+    },
+    /// The encoder delivered no frames for too long and is restarted
+    Frozen {
+        kind: FreezeKind,
+        stalled_ms: u64,
+    },
+}
+
+/// Last error of the capture process with the stderr context it came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureErrorReport {
+    pub kind: String,
+    pub message: String,
+    pub stderr: Vec<String>,
+    pub timestamp: u64, // Unix time in milliseconds
+}
+
+/// Receiver for capture events, e.g. forwarding them to the frontend
+pub type CaptureEventSink = std::sync::Arc<dyn Fn(CaptureEvent) + Send + Sync>;
+
 /// Frame data containing video frame and metadata
 #[derive(Debug, Clone)]
 pub struct FrameData {
     pub data: Vec<u8>,
-    pub timestamp: u64,
+    pub timestamp: u64, // Encoder timestamp in milliseconds since the process started
     pub keyframe: bool,
     pub width: u32,
     pub height: u32,
     pub format: String, // e.g., "h264", "vp8"
+    /// Unique across captures; the frontend reports it back once rendered
+    pub frame_id: u64,
+    /// Estimated time the screen was grabbed, in µs on the frame clock
+    pub captured_us: u64,
+    /// Time the frame was read from the encoder, in µs on the frame clock
+    pub encoded_us: u64,
 }
 
 /// Monitor detection interface
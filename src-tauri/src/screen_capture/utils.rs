@@ -1,42 +1,87 @@
 // screen_capture/utils.rs - Helper functions for screen capture
 
-use std::process::Command;
 use std::io::{Error as IoError, ErrorKind};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::command_runner::{CommandRunner, SystemCommandRunner};
 use crate::screen_capture::error::ScreenCaptureError;
 
-/// Get current CPU usage
+/// How long a CPU sample is reused; all capture loops within this window
+/// see the same value, so their quality controllers react to one reading
+const CPU_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Delta-based CPU usage from /proc/stat, shared by all capture sessions
+struct CpuSampler {
+    /// (total, idle) jiffies of the previous reading
+    last_times: Option<(u64, u64)>,
+    last_sample: Option<Instant>,
+    usage: f32,
+}
+
+static CPU_SAMPLER: Mutex<CpuSampler> = Mutex::new(CpuSampler {
+    last_times: None,
+    last_sample: None,
+    usage: 0.0,
+});
+
+impl CpuSampler {
+    /// Usage between the previous and the current reading, in percent
+    fn update(&mut self, times: (u64, u64), now: Instant) -> f32 {
+        if let Some((last_total, last_idle)) = self.last_times {
+            let total = times.0.saturating_sub(last_total);
+            let idle = times.1.saturating_sub(last_idle);
+            if total > 0 {
+                self.usage = 100.0 * (1.0 - idle as f32 / total as f32);
+            }
+        }
+        self.last_times = Some(times);
+        self.last_sample = Some(now);
+        self.usage
+    }
+}
+
+/// Parses the aggregate "cpu" line of /proc/stat into (total, idle) jiffies
+fn parse_cpu_times(stat: &str) -> Option<(u64, u64)> {
+    let line = stat.lines().next()?;
+    let mut values = line.split_whitespace();
+    if values.next()? != "cpu" {
+        return None;
+    }
+    
+    let times: Vec<u64> = values.filter_map(|v| v.parse().ok()).collect();
+    if times.len() < 4 {
+        return None;
+    }
+    
+    // idle + iowait count as idle time
+    let idle = times[3] + times.get(4).copied().unwrap_or(0);
+    Some((times.iter().sum(), idle))
+}
+
+/// Get current CPU usage in percent.
+///
+/// Readings are shared: callers within the same sample interval get the
+/// cached value instead of racing each other for a fresh delta.
 pub fn get_cpu_usage() -> Result<f32, ScreenCaptureError> {
     #[cfg(target_os = "linux")]
     {
+        let mut sampler = CPU_SAMPLER.lock().unwrap();
+        let now = Instant::now();
+        
+        if let Some(last_sample) = sampler.last_sample {
+            if now.duration_since(last_sample) < CPU_SAMPLE_INTERVAL {
+                return Ok(sampler.usage);
+            }
+        }
+        
         // Read /proc/stat to get CPU information
         let output = std::fs::read_to_string("/proc/stat")
             .map_err(|e| ScreenCaptureError::CaptureError(format!("Failed to read /proc/stat: {}", e)))?;
         
-        // Parse CPU line (first line)
-        if let Some(line) = output.lines().next() {
-            let mut values = line.split_whitespace();
-            
-            // Skip "cpu" prefix
-            values.next();
-            
-            // Read CPU time values
-            let user = values.next().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
-            let nice = values.next().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
-            let system = values.next().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
-            let idle = values.next().and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
-            
-            // Calculate total and idle time
-            let total = user + nice + system + idle;
-            
-            // Calculate usage percentage
-            if total > 0 {
-                let usage = 100.0 * (1.0 - (idle as f32 / total as f32));
-                return Ok(usage);
-            }
-        }
+        let times = parse_cpu_times(&output)
+            .ok_or_else(|| ScreenCaptureError::CaptureError("Unexpected /proc/stat format".to_string()))?;
         
-        // Default if we couldn't calculate
-        Ok(50.0)
+        Ok(sampler.update(times, now))
     }
     
     #[cfg(not(target_os = "linux"))]
@@ -48,7 +93,7 @@ pub fn get_cpu_usage() -> Result<f32, ScreenCaptureError> {
 
 /// Check if FFmpeg is installed and get its version
 pub fn check_ffmpeg() -> Result<String, ScreenCaptureError> {
-    let output = Command::new("ffmpeg")
+    let output = SystemCommandRunner.command("ffmpeg")
         .arg("-version")
         .output()
         .map_err(|e| ScreenCaptureError::InitializationFailed(
@@ -85,7 +130,7 @@ fn check_vaapi() -> Result<bool, ScreenCaptureError> {
     // Check if the VAAPI device exists
     if std::path::Path::new("/dev/dri/renderD128").exists() {
         // Check if FFmpeg supports VAAPI
-        let output = Command::new("ffmpeg")
+        let output = SystemCommandRunner.command("ffmpeg")
             .arg("-hide_banner")
             .arg("-hwaccels")
             .output()
@@ -101,7 +146,7 @@ fn check_vaapi() -> Result<bool, ScreenCaptureError> {
 /// Check if NVENC (NVIDIA encoder) is available
 fn check_nvenc() -> Result<bool, ScreenCaptureError> {
     // Check if FFmpeg supports NVENC
-    let output = Command::new("ffmpeg")
+    let output = SystemCommandRunner.command("ffmpeg")
         .arg("-hide_banner")
         .arg("-encoders")
         .output()
@@ -114,7 +159,7 @@ fn check_nvenc() -> Result<bool, ScreenCaptureError> {
 /// Check if QuickSync is available
 fn check_quicksync() -> Result<bool, ScreenCaptureError> {
     // Check if FFmpeg supports QuickSync
-    let output = Command::new("ffmpeg")
+    let output = SystemCommandRunner.command("ffmpeg")
         .arg("-hide_banner")
         .arg("-encoders")
         .output()
@@ -174,7 +219,7 @@ pub fn frame_to_base64(data: &[u8]) -> String {
 
 /// Get available video codecs supported by current FFmpeg installation
 pub fn get_available_codecs() -> Result<Vec<String>, ScreenCaptureError> {
-    let output = Command::new("ffmpeg")
+    let output = SystemCommandRunner.command("ffmpeg")
         .arg("-hide_banner")
         .arg("-encoders")
         .output()
@@ -231,9 +276,7 @@ pub fn get_available_hardware_acceleration() -> Result<Vec<String>, ScreenCaptur
 pub fn kill_process(pid: u32) -> Result<(), IoError> {
     #[cfg(target_family = "unix")]
     {
-        use std::process::Command;
-        
-        let status = Command::new("kill")
+        let status = SystemCommandRunner.command("kill")
             .arg("-9")
             .arg(pid.to_string())
             .status()?;
@@ -247,9 +290,7 @@ pub fn kill_process(pid: u32) -> Result<(), IoError> {
     
     #[cfg(target_family = "windows")]
     {
-        use std::process::Command;
-        
-        let status = Command::new("taskkill")
+        let status = SystemCommandRunner.command("taskkill")
             .arg("/F")
             .arg("/PID")
             .arg(pid.to_string())
@@ -262,3 +303,26 @@ pub fn kill_process(pid: u32) -> Result<(), IoError> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_cpu_times() {
+        let stat = "cpu  100 0 50 800 50 0 0 0 0 0\ncpu0 50 0 25 400 25 0 0 0 0 0\n";
+        assert_eq!(parse_cpu_times(stat), Some((1000, 850)));
+        assert_eq!(parse_cpu_times("intr 1 2 3"), None);
+    }
+    
+    #[test]
+    fn test_cpu_usage_is_delta_between_samples() {
+        let mut sampler = CpuSampler { last_times: None, last_sample: None, usage: 0.0 };
+        let now = Instant::now();
+        
+        assert_eq!(sampler.update((1000, 900), now), 0.0);
+        // 100 jiffies passed, 25 of them idle
+        let usage = sampler.update((1100, 925), now);
+        assert!((usage - 75.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,516 @@
+// screen_capture/validation.rs - Range and consistency checks for capture settings
+
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+use crate::screen_capture::config::{AdvancedEncodingOptions, OutputResolution, RateControlMode, ScreenCaptureConfig, MIN_OUTPUT_SCALE_PERCENT};
+use crate::screen_capture::encoders::encoder_name;
+use crate::screen_capture::error::ScreenCaptureError;
+use crate::screen_capture::types::{LatencyMode, MonitorInfo};
+
+pub const MIN_FPS: u32 = 1;
+pub const MAX_FPS: u32 = 240;
+pub const MAX_KEYFRAME_INTERVAL: u32 = 600;
+/// Below this FFmpeg produces unusable video or rejects the rate outright
+pub const MIN_BITRATE_KBPS: u32 = 50;
+pub const MAX_BITRATE_KBPS: u32 = 100_000;
+pub const MAX_CRF: u32 = 63;
+
+/// x264/x265 presets that keep encode time low enough for UltraLow latency
+const FAST_PRESETS: [&str; 3] = ["ultrafast", "superfast", "veryfast"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IssueSeverity {
+    /// The capture cannot start with this value
+    Error,
+    /// The capture starts, but not the way the settings suggest
+    Warning,
+}
+
+/// One problem found in a capture configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigIssue {
+    pub severity: IssueSeverity,
+    pub field: String,
+    pub value: String,
+    pub allowed: String,
+    pub suggestion: String,
+}
+
+impl ConfigIssue {
+    fn error(field: &str, value: impl ToString, allowed: impl ToString, suggestion: impl ToString) -> Self {
+        ConfigIssue {
+            severity: IssueSeverity::Error,
+            field: field.to_string(),
+            value: value.to_string(),
+            allowed: allowed.to_string(),
+            suggestion: suggestion.to_string(),
+        }
+    }
+
+    fn warning(field: &str, value: impl ToString, allowed: impl ToString, suggestion: impl ToString) -> Self {
+        ConfigIssue { severity: IssueSeverity::Warning, ..Self::error(field, value, allowed, suggestion) }
+    }
+
+    /// The encoder probe ruled out the codec and acceleration combination
+    pub fn unsupported_encoder(config: &ScreenCaptureConfig, error: &ScreenCaptureError) -> Self {
+        Self::error(
+            "hardware_acceleration",
+            format!("{:?} with {:?}", config.hardware_acceleration, config.codec),
+            "a codec and acceleration offered by the encoder probe",
+            error,
+        )
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == IssueSeverity::Error
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} = {} (allowed: {}); {}", self.field, self.value, self.allowed, self.suggestion)
+    }
+}
+
+impl ScreenCaptureConfig {
+    /// Checks every setting against its allowed range and against each other
+    pub fn validate(&self, monitors: &[MonitorInfo]) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+
+        match monitors.get(self.monitor_index) {
+            Some(monitor) => {
+                if let Some(region) = &self.capture_region {
+                    if let Err(e) = region.validate(monitor) {
+                        issues.push(ConfigIssue::error(
+                            "capture_region",
+                            format!("{}x{}+{}+{}", region.width, region.height, region.x, region.y),
+                            format!("at least 2x2 within {}x{}", monitor.width, monitor.height),
+                            format!("{}; leave empty to capture the whole monitor", e),
+                        ));
+                    }
+                }
+            },
+            None => issues.push(ConfigIssue::error(
+                "monitor_index",
+                self.monitor_index,
+                if monitors.is_empty() { "no monitors detected".to_string() } else { format!("0-{}", monitors.len() - 1) },
+                "Select one of the detected monitors",
+            )),
+        }
+
+        if !(MIN_FPS..=MAX_FPS).contains(&self.fps) {
+            issues.push(ConfigIssue::error("fps", self.fps, format!("{}-{}", MIN_FPS, MAX_FPS), "Use 30 for desktop work or 60 for video"));
+        }
+
+        if !(1..=100).contains(&self.quality) {
+            issues.push(ConfigIssue::error("quality", self.quality, "1-100", "Use 80 for a balanced picture"));
+        }
+
+        if !(1..=MAX_KEYFRAME_INTERVAL).contains(&self.keyframe_interval) {
+            issues.push(ConfigIssue::error(
+                "keyframe_interval",
+                self.keyframe_interval,
+                format!("1-{}", MAX_KEYFRAME_INTERVAL),
+                format!("Use {} for one keyframe per second", self.fps.clamp(MIN_FPS, MAX_FPS)),
+            ));
+        }
+
+        if let Some(bitrate) = self.bitrate {
+            if !(MIN_BITRATE_KBPS..=MAX_BITRATE_KBPS).contains(&bitrate) {
+                issues.push(ConfigIssue::error(
+                    "bitrate",
+                    format!("{} kbps", bitrate),
+                    format!("{}-{} kbps", MIN_BITRATE_KBPS, MAX_BITRATE_KBPS),
+                    "Leave empty to derive the bitrate from quality",
+                ));
+            }
+        }
+
+        if self.idle.enabled && (self.idle.idle_fps == 0 || self.idle.idle_fps > self.fps) {
+            issues.push(ConfigIssue::error(
+                "idle.idle_fps",
+                self.idle.idle_fps,
+                format!("1-{}", self.fps.max(1)),
+                "Use 2 or disable idle throttling",
+            ));
+        }
+
+        if self.damage.enabled && !(0.0..=1.0).contains(&self.damage.skip_below_fraction) {
+            issues.push(ConfigIssue::error(
+                "damage.skip_below_fraction",
+                self.damage.skip_below_fraction,
+                "0-1",
+                "Use 0 to encode every change at once",
+            ));
+        }
+
+        if let Some(resolution) = &self.output_resolution {
+            if resolution.validate().is_err() {
+                let (value, allowed) = match resolution {
+                    OutputResolution::Size { width, height } => (format!("{}x{}", width, height), "at least 2x2".to_string()),
+                    OutputResolution::Scale { percent } => (format!("{}%", percent), format!("{}-100%", MIN_OUTPUT_SCALE_PERCENT)),
+                };
+                issues.push(ConfigIssue::error(
+                    "output_resolution",
+                    value,
+                    allowed,
+                    "Leave empty to stream at the captured size",
+                ));
+            }
+        }
+
+        if let Some(monitor) = monitors.get(self.monitor_index) {
+            for entry in &self.privacy.masks {
+                if let Err(e) = entry.mask.validate(monitor) {
+                    let mask = &entry.mask;
+                    issues.push(ConfigIssue::error(
+                        "privacy.masks",
+                        format!("{}x{}{:+}{:+}", mask.width, mask.height, mask.x, mask.y),
+                        format!("a non-empty area overlapping {}x{}", monitor.width, monitor.height),
+                        format!("{}; remove the mask", e),
+                    ));
+                }
+            }
+        }
+
+        for pattern in &self.privacy.window_classes {
+            if let Err(e) = regex::Regex::new(pattern) {
+                issues.push(ConfigIssue::error(
+                    "privacy.window_classes",
+                    format!("{:?}", pattern),
+                    "a regular expression",
+                    format!("{}; escape characters such as ( or . with a backslash", e.to_string().lines().last().unwrap_or("")),
+                ));
+            }
+        }
+
+        if let Some(options) = &self.advanced_options {
+            self.validate_advanced(options, &mut issues);
+        }
+
+        // The capture backends encode in software when the accelerator lacks the codec
+        if encoder_name(&self.codec, &self.hardware_acceleration).is_none() {
+            issues.push(ConfigIssue::warning(
+                "hardware_acceleration",
+                format!("{:?} with {:?}", self.hardware_acceleration, self.codec),
+                format!("a codec {:?} can encode", self.hardware_acceleration),
+                format!("{:?} will fall back to software encoding; choose H264 to keep acceleration", self.codec),
+            ));
+        }
+
+        if let LatencyMode::UltraLow = self.latency_mode {
+            self.validate_ultra_low_latency(&mut issues);
+        }
+
+        issues
+    }
+
+    fn validate_advanced(&self, options: &AdvancedEncodingOptions, issues: &mut Vec<ConfigIssue>) {
+        if options.pixel_format.trim().is_empty() {
+            issues.push(ConfigIssue::error("advanced_options.pixel_format", "\"\"", "an FFmpeg pixel format", "Use yuv420p"));
+        }
+
+        match &options.rate_control {
+            RateControlMode::CRF(crf) if *crf > MAX_CRF => {
+                issues.push(ConfigIssue::error(
+                    "advanced_options.rate_control",
+                    format!("CRF {}", crf),
+                    format!("CRF 0-{}", MAX_CRF),
+                    "Use CRF 23; lower values mean higher quality",
+                ));
+            },
+            RateControlMode::VBR { target_bitrate, max_bitrate } => {
+                if !(MIN_BITRATE_KBPS..=MAX_BITRATE_KBPS).contains(target_bitrate) {
+                    issues.push(ConfigIssue::error(
+                        "advanced_options.rate_control",
+                        format!("VBR target {} kbps", target_bitrate),
+                        format!("{}-{} kbps", MIN_BITRATE_KBPS, MAX_BITRATE_KBPS),
+                        "Use a target of at least 1000 kbps",
+                    ));
+                }
+                if max_bitrate < target_bitrate {
+                    issues.push(ConfigIssue::error(
+                        "advanced_options.rate_control",
+                        format!("VBR max {} kbps below target {} kbps", max_bitrate, target_bitrate),
+                        "max_bitrate >= target_bitrate",
+                        format!("Set max_bitrate to at least {}", target_bitrate),
+                    ));
+                }
+            },
+            RateControlMode::CBR(bitrate) if !(MIN_BITRATE_KBPS..=MAX_BITRATE_KBPS).contains(bitrate) => {
+                issues.push(ConfigIssue::error(
+                    "advanced_options.rate_control",
+                    format!("CBR {} kbps", bitrate),
+                    format!("{}-{} kbps", MIN_BITRATE_KBPS, MAX_BITRATE_KBPS),
+                    "Use at least 1000 kbps",
+                ));
+            },
+            _ => {},
+        }
+    }
+
+    /// Quality-oriented settings defeat the point of UltraLow latency
+    fn validate_ultra_low_latency(&self, issues: &mut Vec<ConfigIssue>) {
+        if self.quality > 90 {
+            issues.push(ConfigIssue::warning(
+                "quality",
+                self.quality,
+                "up to 90 with UltraLow latency",
+                "High quality raises encode time; lower it or use the Balanced latency mode",
+            ));
+        }
+
+        let Some(options) = &self.advanced_options else {
+            return;
+        };
+        if let Some(preset) = &options.preset {
+            if !FAST_PRESETS.contains(&preset.as_str()) {
+                issues.push(ConfigIssue::warning(
+                    "advanced_options.preset",
+                    preset,
+                    FAST_PRESETS.join(", "),
+                    "Slower presets add encode latency; use ultrafast",
+                ));
+            }
+        }
+        if let Some(tune) = &options.tune {
+            if tune != "zerolatency" {
+                issues.push(ConfigIssue::warning(
+                    "advanced_options.tune",
+                    tune,
+                    "zerolatency",
+                    "Other tunings buffer frames; use zerolatency",
+                ));
+            }
+        }
+        if let RateControlMode::VBR { .. } = options.rate_control {
+            issues.push(ConfigIssue::warning(
+                "advanced_options.rate_control",
+                "VBR",
+                "CRF or CBR with UltraLow latency",
+                "VBR lets frame sizes spike; use CBR for steady latency",
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen_capture::config::{DamageConfig, IdleConfig};
+    use crate::screen_capture::privacy::{MaskStyle, PrivacyConfig, PrivacyMask, PrivacyMaskEntry};
+    use crate::screen_capture::types::{CaptureRegion, HardwareAcceleration, MonitorRotation, VideoCodec};
+
+    fn monitors() -> Vec<MonitorInfo> {
+        (0..2).map(|index| MonitorInfo {
+            index,
+            name: format!("DP-{}", index + 1),
+            width: 1920,
+            height: 1080,
+            refresh_rate: Some(60.0),
+            primary: index == 0,
+            x_offset: 1920 * index as i32,
+            y_offset: 0,
+            scale_factor: 1.0,
+            rotation: MonitorRotation::Normal,
+        }).collect()
+    }
+
+    fn fields(config: &ScreenCaptureConfig, severity: IssueSeverity) -> Vec<String> {
+        config.validate(&monitors()).into_iter()
+            .filter(|issue| issue.severity == severity)
+            .map(|issue| issue.field)
+            .collect()
+    }
+
+    fn errors(config: &ScreenCaptureConfig) -> Vec<String> {
+        fields(config, IssueSeverity::Error)
+    }
+
+    fn warnings(config: &ScreenCaptureConfig) -> Vec<String> {
+        fields(config, IssueSeverity::Warning)
+    }
+
+    #[test]
+    fn test_default_config_is_valid() {
+        assert!(ScreenCaptureConfig::default().validate(&monitors()).is_empty());
+    }
+
+    #[test]
+    fn test_monitor_index() {
+        let config = ScreenCaptureConfig { monitor_index: 1, ..Default::default() };
+        assert!(errors(&config).is_empty());
+
+        let config = ScreenCaptureConfig { monitor_index: 2, ..Default::default() };
+        let issues = config.validate(&monitors());
+        assert_eq!(issues[0].field, "monitor_index");
+        assert_eq!(issues[0].value, "2");
+        assert_eq!(issues[0].allowed, "0-1");
+
+        assert_eq!(ScreenCaptureConfig::default().validate(&[])[0].allowed, "no monitors detected");
+    }
+
+    #[test]
+    fn test_capture_region() {
+        let inside = CaptureRegion { x: 0, y: 0, width: 1920, height: 1080 };
+        let config = ScreenCaptureConfig { capture_region: Some(inside), ..Default::default() };
+        assert!(errors(&config).is_empty());
+
+        let outside = CaptureRegion { x: 1, ..inside };
+        let config = ScreenCaptureConfig { capture_region: Some(outside), ..Default::default() };
+        assert_eq!(errors(&config), vec!["capture_region"]);
+    }
+
+    #[test]
+    fn test_fps_range() {
+        for (fps, valid) in [(0, false), (1, true), (60, true), (240, true), (241, false)] {
+            let config = ScreenCaptureConfig { fps, idle: IdleConfig { enabled: false, ..Default::default() }, ..Default::default() };
+            assert_eq!(errors(&config).is_empty(), valid, "fps {}", fps);
+        }
+    }
+
+    #[test]
+    fn test_quality_range() {
+        for (quality, valid) in [(0, false), (1, true), (100, true), (101, false)] {
+            let config = ScreenCaptureConfig { quality, ..Default::default() };
+            assert_eq!(errors(&config).is_empty(), valid, "quality {}", quality);
+        }
+    }
+
+    #[test]
+    fn test_keyframe_interval_range() {
+        for (interval, valid) in [(0, false), (1, true), (600, true), (601, false)] {
+            let config = ScreenCaptureConfig { keyframe_interval: interval, ..Default::default() };
+            assert_eq!(errors(&config).is_empty(), valid, "keyframe_interval {}", interval);
+        }
+        let config = ScreenCaptureConfig { keyframe_interval: 0, fps: 60, ..Default::default() };
+        assert!(config.validate(&monitors())[0].suggestion.contains("60"));
+    }
+
+    #[test]
+    fn test_bitrate_range() {
+        for (bitrate, valid) in [(None, true), (Some(49), false), (Some(50), true), (Some(100_000), true), (Some(100_001), false)] {
+            let config = ScreenCaptureConfig { bitrate, ..Default::default() };
+            assert_eq!(errors(&config).is_empty(), valid, "bitrate {:?}", bitrate);
+        }
+    }
+
+    #[test]
+    fn test_idle_fps() {
+        let idle = |idle_fps| IdleConfig { enabled: true, idle_fps, idle_after_ms: 2000 };
+        for (idle_fps, valid) in [(0, false), (1, true), (30, true), (31, false)] {
+            let config = ScreenCaptureConfig { fps: 30, idle: idle(idle_fps), ..Default::default() };
+            assert_eq!(errors(&config).is_empty(), valid, "idle_fps {}", idle_fps);
+        }
+
+        // Irrelevant while throttling is off
+        let config = ScreenCaptureConfig { idle: IdleConfig { enabled: false, ..idle(0) }, ..Default::default() };
+        assert!(errors(&config).is_empty());
+    }
+
+    #[test]
+    fn test_damage_skip_fraction() {
+        for (fraction, valid) in [(-0.1, false), (0.0, true), (0.0001, true), (1.0, true), (1.5, false)] {
+            let damage = DamageConfig { skip_below_fraction: fraction, ..Default::default() };
+            let config = ScreenCaptureConfig { damage, ..Default::default() };
+            assert_eq!(errors(&config).is_empty(), valid, "skip_below_fraction {}", fraction);
+        }
+    }
+
+    #[test]
+    fn test_output_resolution() {
+        let with = |resolution| ScreenCaptureConfig { output_resolution: Some(resolution), ..Default::default() };
+        assert!(errors(&with(OutputResolution::Size { width: 1280, height: 720 })).is_empty());
+        assert!(errors(&with(OutputResolution::Scale { percent: 50 })).is_empty());
+        assert_eq!(errors(&with(OutputResolution::Size { width: 1, height: 720 })), vec!["output_resolution"]);
+
+        let issues = with(OutputResolution::Scale { percent: 0 }).validate(&monitors());
+        assert_eq!((issues[0].value.as_str(), issues[0].allowed.as_str()), ("0%", "10-100%"));
+    }
+
+    #[test]
+    fn test_privacy_masks() {
+        let mask = |x, width| PrivacyMaskEntry { id: 1, mask: PrivacyMask { x, y: 0, width, height: 100, style: MaskStyle::Black } };
+        let with = |masks, window_classes: &[&str]| ScreenCaptureConfig {
+            privacy: PrivacyConfig {
+                masks,
+                window_classes: window_classes.iter().map(ToString::to_string).collect(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(errors(&with(vec![mask(-50, 100)], &["^Signal$"])).is_empty());
+        assert_eq!(errors(&with(vec![mask(0, 0)], &[])), vec!["privacy.masks"]);
+        assert_eq!(errors(&with(vec![mask(-100, 100)], &[])), vec!["privacy.masks"]);
+        assert_eq!(errors(&with(Vec::new(), &["Signal("])), vec!["privacy.window_classes"]);
+    }
+
+    #[test]
+    fn test_rate_control() {
+        let with = |rate_control| ScreenCaptureConfig {
+            advanced_options: Some(AdvancedEncodingOptions { rate_control, ..Default::default() }),
+            ..Default::default()
+        };
+        assert!(errors(&with(RateControlMode::CRF(23))).is_empty());
+        assert!(errors(&with(RateControlMode::CRF(63))).is_empty());
+        assert_eq!(errors(&with(RateControlMode::CRF(64))).len(), 1);
+        assert!(errors(&with(RateControlMode::CBR(2500))).is_empty());
+        assert_eq!(errors(&with(RateControlMode::CBR(10))).len(), 1);
+        assert!(errors(&with(RateControlMode::VBR { target_bitrate: 2000, max_bitrate: 4000 })).is_empty());
+        assert_eq!(errors(&with(RateControlMode::VBR { target_bitrate: 4000, max_bitrate: 2000 })).len(), 1);
+        assert_eq!(errors(&with(RateControlMode::VBR { target_bitrate: 10, max_bitrate: 5 })).len(), 2);
+
+        let config = ScreenCaptureConfig {
+            advanced_options: Some(AdvancedEncodingOptions { pixel_format: " ".to_string(), ..Default::default() }),
+            ..Default::default()
+        };
+        assert_eq!(errors(&config), vec!["advanced_options.pixel_format"]);
+    }
+
+    #[test]
+    fn test_missing_hardware_encoder_warns_about_fallback() {
+        for (codec, acceleration, fallback) in [
+            (VideoCodec::VP8, HardwareAcceleration::NVENC, true),
+            (VideoCodec::VP9, HardwareAcceleration::NVENC, true),
+            (VideoCodec::VP8, HardwareAcceleration::QuickSync, true),
+            (VideoCodec::H264, HardwareAcceleration::NVENC, false),
+            (VideoCodec::VP8, HardwareAcceleration::VAAPI, false),
+            (VideoCodec::VP8, HardwareAcceleration::None, false),
+        ] {
+            let config = ScreenCaptureConfig { codec: codec.clone(), hardware_acceleration: acceleration.clone(), ..Default::default() };
+            assert!(errors(&config).is_empty());
+            assert_eq!(warnings(&config) == vec!["hardware_acceleration"], fallback, "{:?} with {:?}", codec, acceleration);
+        }
+    }
+
+    #[test]
+    fn test_ultra_low_latency_conflicts() {
+        let ultra_low = ScreenCaptureConfig { latency_mode: LatencyMode::UltraLow, ..Default::default() };
+        assert!(warnings(&ultra_low).is_empty());
+        assert!(warnings(&ScreenCaptureConfig { advanced_options: Some(AdvancedEncodingOptions::default()), ..ultra_low.clone() }).is_empty());
+
+        let config = ScreenCaptureConfig { quality: 95, ..ultra_low.clone() };
+        assert_eq!(warnings(&config), vec!["quality"]);
+
+        let config = ScreenCaptureConfig {
+            advanced_options: Some(AdvancedEncodingOptions {
+                preset: Some("slow".to_string()),
+                tune: Some("film".to_string()),
+                rate_control: RateControlMode::VBR { target_bitrate: 2000, max_bitrate: 4000 },
+                ..Default::default()
+            }),
+            ..ultra_low.clone()
+        };
+        assert_eq!(warnings(&config), vec![
+            "advanced_options.preset",
+            "advanced_options.tune",
+            "advanced_options.rate_control",
+        ]);
+        assert!(errors(&config).is_empty());
+
+        // The same settings are fine when latency is not the priority
+        let config = ScreenCaptureConfig { latency_mode: LatencyMode::Quality, quality: 95, ..config };
+        assert!(warnings(&config).is_empty());
+    }
+}
@@ -0,0 +1,260 @@
+// screen_capture/viewport.rs - Fitting the stream to the viewers' windows
+//
+// Viewers report the size of the element the stream is shown in. A viewer in
+// a small window gets no sharper picture from a 4K stream, so the encoder is
+// shrunk to the largest reported viewport: the stream is sized for the viewer
+// who needs the most pixels, the others scale it down in the browser as
+// before. The viewport scale sits below the adaptive quality controller's
+// own scale, which keeps trading resolution for quality on top of it.
+//
+// Resizing a window reports many sizes in a row, and every change restarts
+// the encoder, so viewports only take effect once none has changed for
+// `VIEWPORT_DEBOUNCE`. Input is unaffected: clients send coordinates in the
+// captured area's pixels whatever size the stream has.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::screen_capture::config::{ViewportFitConfig, MIN_OUTPUT_SCALE_PERCENT};
+use crate::screen_capture::error::ScreenCaptureError;
+
+/// Time without viewport changes before the stream is refitted
+pub const VIEWPORT_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Largest viewport edge accepted, in CSS pixels
+const MAX_VIEWPORT_EDGE: u32 = 16384;
+
+/// Largest device pixel ratio accepted
+const MAX_DEVICE_PIXEL_RATIO: f64 = 8.0;
+
+/// Size of the element a viewer shows the stream in
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewerViewport {
+    /// CSS pixels
+    pub width: u32,
+    pub height: u32,
+
+    /// Physical pixels per CSS pixel on the viewer's screen
+    pub device_pixel_ratio: f64,
+}
+
+impl ViewerViewport {
+    pub fn validate(&self) -> Result<(), ScreenCaptureError> {
+        if !(1..=MAX_VIEWPORT_EDGE).contains(&self.width) || !(1..=MAX_VIEWPORT_EDGE).contains(&self.height) {
+            return Err(ScreenCaptureError::InvalidConfig(format!(
+                "Viewport {}x{} is outside 1-{} pixels", self.width, self.height, MAX_VIEWPORT_EDGE
+            )));
+        }
+        if !(self.device_pixel_ratio > 0.0 && self.device_pixel_ratio <= MAX_DEVICE_PIXEL_RATIO) {
+            return Err(ScreenCaptureError::InvalidConfig(format!(
+                "Device pixel ratio {} is outside 0-{}", self.device_pixel_ratio, MAX_DEVICE_PIXEL_RATIO
+            )));
+        }
+        Ok(())
+    }
+
+    /// Size in the viewer's physical pixels
+    pub fn physical_size(&self) -> (u32, u32) {
+        let scale = |size: u32| ((size as f64 * self.device_pixel_ratio).round() as u32).max(1);
+        (scale(self.width), scale(self.height))
+    }
+
+    /// Percentage of a stream of `output` size that fills this viewport,
+    /// with the viewport bounded by `fit`
+    pub fn scale_percent(&self, output: (u32, u32), fit: &ViewportFitConfig) -> u32 {
+        let (width, height) = self.physical_size();
+        let bound = |size: u32, min: u32, max: Option<u32>| {
+            let size = size.max(min);
+            max.map_or(size, |max| size.min(max)).max(1)
+        };
+        let width = bound(width, fit.min_width, fit.max_width);
+        let height = bound(height, fit.min_height, fit.max_height);
+
+        let factor = (width as f64 / output.0.max(1) as f64).min(height as f64 / output.1.max(1) as f64);
+        ((factor * 100.0).ceil() as u32).clamp(MIN_OUTPUT_SCALE_PERCENT, 100)
+    }
+}
+
+/// Viewport of the viewer a stream was fitted to
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewportDecision {
+    pub peer_id: String,
+    pub viewport: ViewerViewport,
+    pub scale_percent: u32,
+}
+
+/// Last viewport of every viewer
+#[derive(Debug, Default)]
+pub struct ViewerViewports {
+    viewports: BTreeMap<String, ViewerViewport>,
+
+    /// Last change not yet applied
+    changed_at: Option<Instant>,
+}
+
+impl ViewerViewports {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps the viewport of a viewer; false if it did not change
+    pub fn set(&mut self, peer_id: &str, viewport: ViewerViewport, now: Instant) -> bool {
+        if self.viewports.get(peer_id) == Some(&viewport) {
+            return false;
+        }
+        self.viewports.insert(peer_id.to_string(), viewport);
+        self.changed_at = Some(now);
+        true
+    }
+
+    /// Forgets a viewer, e.g. after it disconnected; false if it had no viewport
+    pub fn remove(&mut self, peer_id: &str, now: Instant) -> bool {
+        if self.viewports.remove(peer_id).is_none() {
+            return false;
+        }
+        self.changed_at = Some(now);
+        true
+    }
+
+    /// Whether no viewport changed during the last `VIEWPORT_DEBOUNCE`
+    pub fn settled(&self, now: Instant) -> bool {
+        self.changed_at.map_or(true, |changed_at| now.saturating_duration_since(changed_at) >= VIEWPORT_DEBOUNCE)
+    }
+
+    /// The viewer needing the largest part of a stream of `output` size;
+    /// None without viewports
+    pub fn largest(&self, output: (u32, u32), fit: &ViewportFitConfig) -> Option<ViewportDecision> {
+        self.viewports.iter()
+            .map(|(peer_id, viewport)| ViewportDecision {
+                peer_id: peer_id.clone(),
+                viewport: *viewport,
+                scale_percent: viewport.scale_percent(output, fit),
+            })
+            .max_by_key(|decision| decision.scale_percent)
+    }
+
+    pub fn len(&self) -> usize {
+        self.viewports.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.viewports.is_empty()
+    }
+}
+
+/// What made the host change the size of a stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QualityChangeReason {
+    Viewport,
+}
+
+/// A capture was refitted; emitted to the frontend as `quality_changed`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QualityChange {
+    pub monitor_index: usize,
+    pub reason: QualityChangeReason,
+
+    /// Percentage of the configured output size left by the viewports
+    pub scale_percent: u32,
+
+    /// Size encoded from the next encoder start on
+    pub width: u32,
+    pub height: u32,
+
+    /// Viewer whose viewport decided; None once no viewer reports one
+    pub peer_id: Option<String>,
+    pub viewport: Option<ViewerViewport>,
+
+    /// Viewers with a reported viewport
+    pub viewers: usize,
+
+    /// Human-readable summary for notifications and logs
+    pub explanation: String,
+}
+
+impl QualityChange {
+    pub fn viewport(monitor_index: usize, decision: Option<ViewportDecision>, size: (u32, u32), viewers: usize) -> Self {
+        let explanation = match &decision {
+            Some(decision) => {
+                let (width, height) = decision.viewport.physical_size();
+                let others = match viewers {
+                    0 | 1 => String::new(),
+                    viewers => format!(", the largest of {} viewers", viewers),
+                };
+                format!("Fitted to the {}x{} viewport of {}{}: {}x{} stream", width, height, decision.peer_id, others, size.0, size.1)
+            },
+            None => format!("No viewer viewport limits the stream: {}x{} stream", size.0, size.1),
+        };
+
+        QualityChange {
+            monitor_index,
+            reason: QualityChangeReason::Viewport,
+            scale_percent: decision.as_ref().map_or(100, |decision| decision.scale_percent),
+            width: size.0,
+            height: size.1,
+            peer_id: decision.as_ref().map(|decision| decision.peer_id.clone()),
+            viewport: decision.map(|decision| decision.viewport),
+            viewers,
+            explanation,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewport(width: u32, height: u32, device_pixel_ratio: f64) -> ViewerViewport {
+        ViewerViewport { width, height, device_pixel_ratio }
+    }
+
+    #[test]
+    fn test_largest_viewport_decides_within_bounds() {
+        let fit = ViewportFitConfig::default();
+        let output = (3840, 2160);
+        let now = Instant::now();
+        let mut viewports = ViewerViewports::new();
+        assert!(viewports.largest(output, &fit).is_none());
+
+        // A 1280x720 window on a HiDPI screen needs half of a 4K stream
+        assert!(viewports.set("laptop", viewport(1280, 720, 1.5), now));
+        assert!(viewports.set("phone", viewport(390, 844, 3.0), now));
+        let decision = viewports.largest(output, &fit).unwrap();
+        assert_eq!((decision.peer_id.as_str(), decision.scale_percent), ("laptop", 50));
+
+        // A tiny window is raised to the floor, a huge one capped by the ceiling
+        assert_eq!(viewport(320, 180, 1.0).scale_percent(output, &fit), 17);
+        let capped = ViewportFitConfig { max_width: Some(1920), ..ViewportFitConfig::default() };
+        assert_eq!(viewport(3840, 2160, 2.0).scale_percent(output, &capped), 50);
+        assert_eq!(viewport(3840, 2160, 2.0).scale_percent(output, &fit), 100);
+
+        assert!(viewport(0, 720, 1.0).validate().is_err());
+        assert!(viewport(1280, 720, f64::NAN).validate().is_err());
+        assert!(viewport(1280, 720, 2.0).validate().is_ok());
+    }
+
+    #[test]
+    fn test_changes_settle_after_the_debounce() {
+        let now = Instant::now();
+        let mut viewports = ViewerViewports::new();
+        assert!(viewports.settled(now));
+
+        viewports.set("a", viewport(800, 600, 1.0), now);
+        let resized = now + Duration::from_millis(500);
+        viewports.set("a", viewport(900, 600, 1.0), resized);
+        assert!(!viewports.settled(now + VIEWPORT_DEBOUNCE));
+        assert!(viewports.settled(resized + VIEWPORT_DEBOUNCE));
+
+        // Reporting the same size again does not restart the wait
+        assert!(!viewports.set("a", viewport(900, 600, 1.0), resized + VIEWPORT_DEBOUNCE));
+        assert!(viewports.settled(resized + VIEWPORT_DEBOUNCE));
+        assert!(viewports.remove("a", resized + VIEWPORT_DEBOUNCE));
+        assert!(!viewports.remove("a", resized + VIEWPORT_DEBOUNCE));
+        assert!(viewports.is_empty());
+    }
+}
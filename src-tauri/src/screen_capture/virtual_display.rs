@@ -0,0 +1,382 @@
+// screen_capture/virtual_display.rs - Virtual outputs for extending the desktop
+//
+// A virtual display is an extra output without a physical screen, e.g. for a
+// tablet used as a second monitor. On X11 a disconnected VIRTUAL output of the
+// modesetting/intel driver gets a custom mode; on wlroots compositors a headless
+// output is created. Created outputs are recorded in a state file so that a
+// crashed session does not leave them behind: the next start removes them.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::command_runner::{CommandRunner, SystemCommandRunner};
+use crate::logging::TARGET_SCREEN_CAPTURE;
+use crate::screen_capture::error::ScreenCaptureError;
+use crate::screen_capture::types::DisplayServer;
+use crate::screen_capture::wayland::{detect_wayland_compositor, WaylandCompositor};
+
+/// File in the app data directory listing outputs created by SmolDesk
+const STATE_FILE: &str = "virtual_displays.json";
+
+/// Prefix of the xrandr modes created for virtual outputs
+const MODE_PREFIX: &str = "smoldesk_";
+
+/// How virtual outputs can be created in this session
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum VirtualDisplayBackend {
+    /// A disconnected VIRTUAL output configured with xrandr
+    XrandrVirtualOutput,
+    /// A wlroots headless output created with swaymsg
+    WlrootsHeadless,
+}
+
+/// A virtual output created by SmolDesk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VirtualDisplay {
+    pub id: String,
+    /// Output name, also the name of the monitor in get_monitors
+    pub output: String,
+    /// xrandr mode added for this output (X11 only)
+    pub mode: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub refresh_rate: u32,
+    pub backend: VirtualDisplayBackend,
+}
+
+/// Detects the virtual output backend of the running session
+pub fn detect_backend(display_server: &DisplayServer) -> Result<VirtualDisplayBackend, ScreenCaptureError> {
+    match display_server {
+        DisplayServer::X11 => {
+            let output = run("xrandr", &["--query"])?;
+            if free_virtual_outputs(&output, &[]).is_empty() {
+                return Err(ScreenCaptureError::VirtualDisplayError(
+                    "The X server has no VIRTUAL outputs (enable VirtualHeads in the intel driver or use the modesetting driver)".to_string()
+                ));
+            }
+            Ok(VirtualDisplayBackend::XrandrVirtualOutput)
+        },
+        DisplayServer::Wayland => match detect_wayland_compositor() {
+            WaylandCompositor::Wlroots => Ok(VirtualDisplayBackend::WlrootsHeadless),
+            other => Err(ScreenCaptureError::VirtualDisplayError(format!(
+                "Virtual outputs are not supported on the {:?} compositor", other
+            ))),
+        },
+        DisplayServer::Unknown => Err(ScreenCaptureError::DisplayServerError(
+            "Unsupported display server".to_string(),
+        )),
+    }
+}
+
+/// Creates and removes virtual outputs and keeps the state file current
+pub struct VirtualDisplayManager {
+    display_server: DisplayServer,
+    state_path: Option<PathBuf>,
+    displays: Vec<VirtualDisplay>,
+}
+
+impl VirtualDisplayManager {
+    pub fn new(display_server: DisplayServer) -> Self {
+        VirtualDisplayManager {
+            display_server,
+            state_path: None,
+            displays: Vec::new(),
+        }
+    }
+
+    /// Sets the state directory and removes outputs left over by a previous run
+    pub fn set_state_dir(&mut self, data_dir: &Path) -> Result<(), ScreenCaptureError> {
+        fs::create_dir_all(data_dir).map_err(|e| ScreenCaptureError::VirtualDisplayError(
+            format!("Failed to create {}: {}", data_dir.display(), e)
+        ))?;
+        let path = data_dir.join(STATE_FILE);
+
+        for display in load_state(&path) {
+            log::warn!(target: TARGET_SCREEN_CAPTURE, "Removing virtual display {} left over from a previous session", display.output);
+            if let Err(e) = remove_output(&display) {
+                log::error!(target: TARGET_SCREEN_CAPTURE, "Failed to remove leftover virtual display {}: {}", display.output, e);
+            }
+        }
+
+        self.state_path = Some(path);
+        self.save();
+        Ok(())
+    }
+
+    /// Virtual outputs created in this session
+    pub fn list(&self) -> Vec<VirtualDisplay> {
+        self.displays.clone()
+    }
+
+    /// Whether a monitor is one of our virtual outputs
+    pub fn is_virtual(&self, output: &str) -> bool {
+        self.displays.iter().any(|display| display.output == output)
+    }
+
+    pub fn create(&mut self, width: u32, height: u32, refresh_rate: u32) -> Result<VirtualDisplay, ScreenCaptureError> {
+        if width == 0 || height == 0 || refresh_rate == 0 {
+            return Err(ScreenCaptureError::VirtualDisplayError(
+                "Width, height and refresh rate must be greater than zero".to_string()
+            ));
+        }
+
+        let backend = detect_backend(&self.display_server)?;
+        let display = match backend {
+            VirtualDisplayBackend::XrandrVirtualOutput => self.create_xrandr_output(width, height, refresh_rate)?,
+            VirtualDisplayBackend::WlrootsHeadless => create_headless_output(width, height, refresh_rate)?,
+        };
+
+        log::info!(target: TARGET_SCREEN_CAPTURE, "Created virtual display {} ({}x{}@{})", display.output, width, height, refresh_rate);
+
+        self.displays.push(display.clone());
+        self.save();
+        Ok(display)
+    }
+
+    pub fn destroy(&mut self, id: &str) -> Result<VirtualDisplay, ScreenCaptureError> {
+        let position = self.displays.iter().position(|display| display.id == id)
+            .ok_or_else(|| ScreenCaptureError::VirtualDisplayError(format!("Unknown virtual display {}", id)))?;
+
+        remove_output(&self.displays[position])?;
+        let display = self.displays.remove(position);
+        self.save();
+
+        log::info!(target: TARGET_SCREEN_CAPTURE, "Removed virtual display {}", display.output);
+        Ok(display)
+    }
+
+    /// Removes all virtual outputs, e.g. on exit
+    pub fn destroy_all(&mut self) {
+        for display in std::mem::take(&mut self.displays) {
+            if let Err(e) = remove_output(&display) {
+                log::error!(target: TARGET_SCREEN_CAPTURE, "Failed to remove virtual display {}: {}", display.output, e);
+                // Keep it in the state file so the next start retries
+                self.displays.push(display);
+            }
+        }
+        self.save();
+    }
+
+    fn create_xrandr_output(&self, width: u32, height: u32, refresh_rate: u32) -> Result<VirtualDisplay, ScreenCaptureError> {
+        let query = run("xrandr", &["--query"])?;
+        let used: Vec<&str> = self.displays.iter().map(|display| display.output.as_str()).collect();
+        let output = free_virtual_outputs(&query, &used).into_iter().next()
+            .ok_or_else(|| ScreenCaptureError::VirtualDisplayError("All VIRTUAL outputs are in use".to_string()))?;
+
+        let modeline = run("cvt", &[&width.to_string(), &height.to_string(), &refresh_rate.to_string()])?;
+        let timings = parse_cvt_modeline(&modeline)
+            .ok_or_else(|| ScreenCaptureError::VirtualDisplayError("Could not parse cvt output".to_string()))?;
+        let mode = format!("{}{}x{}_{}", MODE_PREFIX, width, height, refresh_rate);
+
+        let mut new_mode = vec!["--newmode", mode.as_str()];
+        new_mode.extend(timings.iter().map(String::as_str));
+        run("xrandr", &new_mode)?;
+
+        let display = VirtualDisplay {
+            id: uuid::Uuid::new_v4().to_string(),
+            output: output.clone(),
+            mode: Some(mode.clone()),
+            width,
+            height,
+            refresh_rate,
+            backend: VirtualDisplayBackend::XrandrVirtualOutput,
+        };
+
+        let enabled = run("xrandr", &["--addmode", &output, &mode])
+            .and_then(|_| match primary_output(&query) {
+                Some(primary) => run("xrandr", &["--output", &output, "--mode", &mode, "--right-of", &primary]),
+                None => run("xrandr", &["--output", &output, "--mode", &mode]),
+            });
+        if let Err(e) = enabled {
+            let _ = remove_output(&display);
+            return Err(e);
+        }
+
+        Ok(display)
+    }
+
+    fn save(&self) {
+        let Some(path) = &self.state_path else {
+            return;
+        };
+
+        let result = serde_json::to_vec_pretty(&self.displays)
+            .map_err(|e| e.to_string())
+            .and_then(|json| fs::write(path, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            log::error!(target: TARGET_SCREEN_CAPTURE, "Failed to save virtual display state: {}", e);
+        }
+    }
+}
+
+fn load_state(path: &Path) -> Vec<VirtualDisplay> {
+    fs::read(path)
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .unwrap_or_default()
+}
+
+fn create_headless_output(width: u32, height: u32, refresh_rate: u32) -> Result<VirtualDisplay, ScreenCaptureError> {
+    let before = sway_output_names()?;
+    run("swaymsg", &["create_output"])?;
+    let output = sway_output_names()?.into_iter()
+        .find(|name| !before.contains(name))
+        .ok_or_else(|| ScreenCaptureError::VirtualDisplayError("The compositor did not create a headless output".to_string()))?;
+
+    let display = VirtualDisplay {
+        id: uuid::Uuid::new_v4().to_string(),
+        output: output.clone(),
+        mode: None,
+        width,
+        height,
+        refresh_rate,
+        backend: VirtualDisplayBackend::WlrootsHeadless,
+    };
+
+    let mode = format!("{}x{}@{}Hz", width, height, refresh_rate);
+    if let Err(e) = run("swaymsg", &["output", &output, "mode", &mode]) {
+        let _ = remove_output(&display);
+        return Err(e);
+    }
+
+    Ok(display)
+}
+
+fn remove_output(display: &VirtualDisplay) -> Result<(), ScreenCaptureError> {
+    match display.backend {
+        VirtualDisplayBackend::XrandrVirtualOutput => {
+            run("xrandr", &["--output", &display.output, "--off"])?;
+            if let Some(mode) = &display.mode {
+                // The mode may already be gone after a crash during creation
+                let _ = run("xrandr", &["--delmode", &display.output, mode]);
+                let _ = run("xrandr", &["--rmmode", mode]);
+            }
+            Ok(())
+        },
+        VirtualDisplayBackend::WlrootsHeadless => {
+            run("swaymsg", &["output", &display.output, "unplug"]).map(|_| ())
+        },
+    }
+}
+
+fn sway_output_names() -> Result<Vec<String>, ScreenCaptureError> {
+    let json = run("swaymsg", &["-t", "get_outputs", "--raw"])?;
+    let outputs: serde_json::Value = serde_json::from_str(&json)
+        .map_err(|e| ScreenCaptureError::VirtualDisplayError(format!("Failed to parse swaymsg output: {}", e)))?;
+
+    Ok(outputs.as_array()
+        .map(|outputs| outputs.iter()
+            .filter_map(|output| output.get("name").and_then(|name| name.as_str()))
+            .map(str::to_string)
+            .collect())
+        .unwrap_or_default())
+}
+
+/// Disconnected VIRTUAL outputs from `xrandr --query`, minus the ones in use
+fn free_virtual_outputs(query: &str, used: &[&str]) -> Vec<String> {
+    query.lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let name = parts.next()?;
+            let state = parts.next()?;
+            (name.starts_with("VIRTUAL") && state == "disconnected" && !used.contains(&name))
+                .then(|| name.to_string())
+        })
+        .collect()
+}
+
+fn primary_output(query: &str) -> Option<String> {
+    query.lines()
+        .find(|line| line.contains(" connected primary"))
+        .and_then(|line| line.split_whitespace().next())
+        .map(str::to_string)
+}
+
+/// Timings of a cvt modeline, without the quoted mode name:
+/// `Modeline "1920x1080_60.00"  173.00  1920 2048 2248 2576  1080 1083 1088 1120 -hsync +vsync`
+fn parse_cvt_modeline(output: &str) -> Option<Vec<String>> {
+    let line = output.lines().find(|line| line.trim_start().starts_with("Modeline"))?;
+    let after_name = line.splitn(3, '"').nth(2)?;
+    let timings: Vec<String> = after_name.split_whitespace().map(str::to_string).collect();
+    // Clock, 8 timings and at least the sync flags
+    (timings.len() >= 9).then_some(timings)
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String, ScreenCaptureError> {
+    let output = SystemCommandRunner.command(program)
+        .args(args)
+        .output()
+        .map_err(|e| ScreenCaptureError::VirtualDisplayError(format!("Failed to execute {}: {}", program, e)))?;
+
+    if !output.status.success() {
+        return Err(ScreenCaptureError::VirtualDisplayError(format!(
+            "{} {} failed: {}", program, args.join(" "), String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XRANDR_QUERY: &str = "\
+Screen 0: minimum 8 x 8, current 1920 x 1080, maximum 32767 x 32767
+eDP-1 connected primary 1920x1080+0+0 (normal left inverted right x axis y axis) 344mm x 193mm
+   1920x1080     60.01*+
+HDMI-1 disconnected (normal left inverted right x axis y axis)
+VIRTUAL1 disconnected (normal left inverted right x axis y axis)
+VIRTUAL2 disconnected (normal left inverted right x axis y axis)
+";
+
+    #[test]
+    fn test_free_virtual_outputs() {
+        assert_eq!(free_virtual_outputs(XRANDR_QUERY, &[]), vec!["VIRTUAL1", "VIRTUAL2"]);
+        assert_eq!(free_virtual_outputs(XRANDR_QUERY, &["VIRTUAL1"]), vec!["VIRTUAL2"]);
+        assert_eq!(primary_output(XRANDR_QUERY), Some("eDP-1".to_string()));
+    }
+
+    #[test]
+    fn test_parse_cvt_modeline() {
+        let output = "# 1920x1080 59.96 Hz (CVT 2.07M9) hsync: 67.16 kHz; pclk: 173.00 MHz\n\
+            Modeline \"1920x1080_60.00\"  173.00  1920 2048 2248 2576  1080 1083 1088 1120 -hsync +vsync\n";
+
+        let timings = parse_cvt_modeline(output).unwrap();
+        assert_eq!(timings[0], "173.00");
+        assert_eq!(timings.len(), 11);
+        assert_eq!(timings.last().unwrap(), "+vsync");
+
+        assert!(parse_cvt_modeline("cvt: cannot generate mode").is_none());
+    }
+
+    #[test]
+    fn test_state_survives_restart() {
+        let dir = std::env::temp_dir().join(format!("smoldesk-vd-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(STATE_FILE);
+
+        let display = VirtualDisplay {
+            id: "a".to_string(),
+            output: "VIRTUAL1".to_string(),
+            mode: Some("smoldesk_1280x800_60".to_string()),
+            width: 1280,
+            height: 800,
+            refresh_rate: 60,
+            backend: VirtualDisplayBackend::XrandrVirtualOutput,
+        };
+        let manager = VirtualDisplayManager {
+            display_server: DisplayServer::X11,
+            state_path: Some(path.clone()),
+            displays: vec![display.clone()],
+        };
+        manager.save();
+
+        assert_eq!(load_state(&path), vec![display]);
+        assert!(load_state(&dir.join("missing.json")).is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,116 @@
+// screen_capture/watchdog.rs - Detection of capture pipelines that stopped delivering frames
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::screen_capture::config::ScreenCaptureConfig;
+use crate::screen_capture::types::CaptureActivity;
+
+/// Why a pipeline counts as frozen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FreezeKind {
+    /// The encoder is alive but wrote nothing at all
+    DeadPipe,
+    /// Data arrived, but no frame could be taken from it
+    NoFrames,
+}
+
+/// Notices an encoder that keeps running but no longer delivers frames,
+/// e.g. after a PipeWire stream stalled.
+///
+/// A static screen is not frozen: the idle throttling still sends keepalive
+/// frames, and while the idle detector reports `Idle` data without frames
+/// is accepted as well. Only a pipe that stays silent counts as dead.
+pub struct FreezeWatchdog {
+    /// None when the watchdog is disabled
+    timeout: Option<Duration>,
+    last_data: Instant,
+    last_frame: Instant,
+}
+
+impl FreezeWatchdog {
+    /// Watchdog for an encoder started at `now`; the first frame gets the same time
+    pub fn new(config: &ScreenCaptureConfig, now: Instant) -> Self {
+        FreezeWatchdog {
+            timeout: (config.freeze_timeout_ms > 0).then(|| Duration::from_millis(config.freeze_timeout_ms)),
+            last_data: now,
+            last_frame: now,
+        }
+    }
+
+    pub fn data_received(&mut self, now: Instant) {
+        self.last_data = now;
+    }
+
+    pub fn frame_pushed(&mut self, now: Instant) {
+        self.last_frame = now;
+    }
+
+    /// Time since the last frame
+    pub fn stalled_for(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.last_frame)
+    }
+
+    /// Some if the encoder should be restarted
+    pub fn check(&self, now: Instant, activity: CaptureActivity) -> Option<FreezeKind> {
+        let timeout = self.timeout?;
+        if self.stalled_for(now) < timeout {
+            return None;
+        }
+
+        if now.saturating_duration_since(self.last_data) >= timeout {
+            Some(FreezeKind::DeadPipe)
+        } else if activity == CaptureActivity::Idle {
+            // Unchanged but alive
+            None
+        } else {
+            Some(FreezeKind::NoFrames)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watchdog(timeout_ms: u64, start: Instant) -> FreezeWatchdog {
+        let config = ScreenCaptureConfig { freeze_timeout_ms: timeout_ms, ..ScreenCaptureConfig::default() };
+        FreezeWatchdog::new(&config, start)
+    }
+
+    #[test]
+    fn test_silent_pipe_is_frozen() {
+        let start = Instant::now();
+        let mut watchdog = watchdog(5000, start);
+
+        assert_eq!(watchdog.check(start + Duration::from_secs(4), CaptureActivity::Active), None);
+        watchdog.data_received(start + Duration::from_secs(1));
+        watchdog.frame_pushed(start + Duration::from_secs(1));
+        assert_eq!(watchdog.check(start + Duration::from_secs(5), CaptureActivity::Active), None);
+
+        // A dead pipe is frozen even while the screen counted as static
+        assert_eq!(watchdog.check(start + Duration::from_secs(6), CaptureActivity::Idle), Some(FreezeKind::DeadPipe));
+        assert_eq!(watchdog.stalled_for(start + Duration::from_secs(6)), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_data_without_frames_is_frozen_unless_idle() {
+        let start = Instant::now();
+        let mut watchdog = watchdog(5000, start);
+        watchdog.data_received(start + Duration::from_secs(5));
+
+        let later = start + Duration::from_secs(6);
+        assert_eq!(watchdog.check(later, CaptureActivity::Active), Some(FreezeKind::NoFrames));
+        assert_eq!(watchdog.check(later, CaptureActivity::Idle), None);
+    }
+
+    #[test]
+    fn test_zero_timeout_disables_the_watchdog() {
+        let start = Instant::now();
+        let watchdog = watchdog(0, start);
+
+        assert_eq!(watchdog.check(start + Duration::from_secs(3600), CaptureActivity::Active), None);
+    }
+}
@@ -2,17 +2,138 @@
 
 use std::process::{Command, Stdio, Child};
 use std::sync::{Arc, Mutex};
+use std::sync::mpsc::RecvTimeoutError;
 use std::thread;
 use std::time::{Duration, Instant};
-use std::io::{Read, Write};
+use std::io::Write;
+use std::os::fd::RawFd;
+use std::os::unix::process::CommandExt;
 use tauri::Window;
 
-use crate::screen_capture::types::{MonitorInfo, CaptureStats, ScreenCapturer, MonitorDetector, FrameData, DisplayServer, VideoCodec, HardwareAcceleration};
+use crate::command_runner::{CommandRunner, SystemCommandRunner};
+use crate::tool_locator;
+use crate::screen_capture::types::{MonitorInfo, MonitorRotation, CaptureStats, CaptureEvent, CaptureEventSink, ScreenCapturer, MonitorDetector, FrameData, DisplayServer, EncoderBackendKind, VideoCodec, HardwareAcceleration};
 use crate::screen_capture::error::{ScreenCaptureError, to_capture_error, to_ffmpeg_error};
 use crate::screen_capture::config::ScreenCaptureConfig;
+use crate::screen_capture::matroska::MatroskaDemuxer;
+use crate::screen_capture::clock::FrameClock;
 use crate::screen_capture::buffer::{StreamBuffer, DropMode};
 use crate::screen_capture::quality::AdaptiveQualityController;
+use crate::screen_capture::idle::{self, IdleDetector};
+use crate::screen_capture::watchdog::FreezeWatchdog;
+use crate::screen_capture::scale;
+use crate::screen_capture::privacy;
+use crate::screen_capture::keyframe::KeyframeScheduler;
+use crate::screen_capture::gpu_stats::GpuMonitor;
+use crate::screen_capture::portal::{self, PortalSession};
+use crate::screen_capture::pipewire;
+use crate::screen_capture::backend::{
+    EncoderBackend, EncoderOutput, PipeWireSource, ProcessBackend, GSTREAMER_FDSINK,
+    gstreamer_pipeline,
+};
+#[cfg(feature = "gstreamer-backend")]
+use crate::screen_capture::backend::GSTREAMER_APPSINK;
+#[cfg(feature = "gstreamer-backend")]
+use crate::screen_capture::gst_pipeline::GStreamerBackend;
+use crate::screen_capture::supervisor::{
+    RestartPolicy, STABLE_RUN_DURATION,
+    prepare_restart, sleep_while_running,
+};
 use crate::screen_capture::utils;
+use crate::logging::{RateLimitedLog, HOT_PATH_LOG_INTERVAL, TARGET_SCREEN_CAPTURE};
+
+/// Descriptor number under which gst-launch receives the portal's PipeWire remote
+const PORTAL_CHILD_FD: RawFd = 3;
+
+/// Wayland compositor family, used to pick capture and monitor detection paths
+#[derive(Debug, Clone, PartialEq)]
+pub enum WaylandCompositor {
+    /// wlroots-based compositors (Sway, river, ...) that ffmpeg can read directly
+    Wlroots,
+    /// Hyprland, captured like wlroots but with monitors from hyprctl
+    Hyprland,
+    /// GNOME Shell (Mutter)
+    Gnome,
+    /// KDE Plasma (KWin)
+    Kde,
+    /// Anything else
+    Unknown,
+}
+
+/// Capture path used for a Wayland session
+#[derive(Debug, Clone, PartialEq)]
+pub enum WaylandCaptureBackend {
+    /// ffmpeg's pipewire input, monitors from hyprctl/wlr-randr/swaymsg
+    Wlroots,
+    /// xdg-desktop-portal ScreenCast session with user consent
+    Portal,
+}
+
+/// Detect the running Wayland compositor from the session environment
+pub fn detect_wayland_compositor() -> WaylandCompositor {
+    compositor_from_env(|name| std::env::var(name).ok())
+}
+
+/// Classify the compositor from session variables looked up through `var`.
+/// The compositor's own socket variables win over the desktop name, which
+/// is often inherited from a parent session.
+fn compositor_from_env(var: impl Fn(&str) -> Option<String>) -> WaylandCompositor {
+    let is_set = |name: &str| var(name).map(|v| !v.is_empty()).unwrap_or(false);
+    
+    if is_set("HYPRLAND_INSTANCE_SIGNATURE") {
+        return WaylandCompositor::Hyprland;
+    }
+    if is_set("SWAYSOCK") {
+        return WaylandCompositor::Wlroots;
+    }
+    
+    let desktop = var("XDG_CURRENT_DESKTOP")
+        .or_else(|| var("XDG_SESSION_DESKTOP"))
+        .unwrap_or_default()
+        .to_lowercase();
+    
+    if desktop.contains("hyprland") {
+        WaylandCompositor::Hyprland
+    } else if desktop.contains("gnome") || desktop.contains("unity") || desktop.contains("pantheon") {
+        WaylandCompositor::Gnome
+    } else if desktop.contains("kde") || desktop.contains("plasma") {
+        WaylandCompositor::Kde
+    } else if desktop.contains("sway") || desktop.contains("wlroots") || desktop.contains("river") {
+        WaylandCompositor::Wlroots
+    } else {
+        WaylandCompositor::Unknown
+    }
+}
+
+/// Pick the capture backend for a compositor. GNOME and KDE do not expose their
+/// outputs to ffmpeg and require a portal session.
+pub fn select_capture_backend(compositor: &WaylandCompositor) -> WaylandCaptureBackend {
+    capture_backend_for(compositor, || tool_available("wlr-randr") || tool_available("swaymsg"))
+}
+
+/// Backend choice with the wlroots tool probe passed in; it only runs for unknown compositors
+fn capture_backend_for(compositor: &WaylandCompositor, wlroots_tools_installed: impl FnOnce() -> bool) -> WaylandCaptureBackend {
+    match compositor {
+        WaylandCompositor::Gnome | WaylandCompositor::Kde => WaylandCaptureBackend::Portal,
+        WaylandCompositor::Wlroots | WaylandCompositor::Hyprland => WaylandCaptureBackend::Wlroots,
+        WaylandCompositor::Unknown => {
+            // Prefer wlroots tooling when it is installed, otherwise rely on the portal
+            if wlroots_tools_installed() {
+                WaylandCaptureBackend::Wlroots
+            } else {
+                WaylandCaptureBackend::Portal
+            }
+        }
+    }
+}
+
+fn tool_available(tool: &str) -> bool {
+    SystemCommandRunner.command("which")
+        .arg(tool_locator::resolve(tool))
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
 
 /// Wayland-specific monitor detector implementation
 pub struct WaylandMonitorDetector;
@@ -31,9 +152,6 @@ pub struct WaylandScreenCapturer {
     // Capture state
     running: Arc<Mutex<bool>>,
     
-    // FFmpeg process
-    capture_process: Arc<Mutex<Option<Child>>>,
-    
     // Monitor info
     monitor: MonitorInfo,
     
@@ -46,8 +164,26 @@ pub struct WaylandScreenCapturer {
     // Stats
     stats: Arc<Mutex<CaptureStats>>,
     
+    // Keyframes requested for joining viewers
+    keyframes: Arc<Mutex<KeyframeScheduler>>,
+    
     // Capture thread
     capture_thread: Option<thread::JoinHandle<()>>,
+    
+    // Capture backend (wlroots or portal)
+    backend: WaylandCaptureBackend,
+    
+    // Encoder pipeline, already resolved from `Auto`
+    encoder_backend: EncoderBackendKind,
+    
+    // Portal session, kept alive while capturing
+    portal_session: Option<PortalSession>,
+    
+    // Receiver for restart/failure events of the capture process
+    event_sink: CaptureEventSink,
+    
+    // Builds the ffmpeg/GStreamer and pw-dump invocations
+    runner: Arc<dyn CommandRunner>,
 }
 
 impl WaylandScreenCapturer {
@@ -57,34 +193,50 @@ impl WaylandScreenCapturer {
         monitor: MonitorInfo,
         stream_buffer: Arc<Mutex<StreamBuffer>>,
         quality_controller: Arc<Mutex<AdaptiveQualityController>>,
-        stats: Arc<Mutex<CaptureStats>>
+        stats: Arc<Mutex<CaptureStats>>,
+        keyframes: Arc<Mutex<KeyframeScheduler>>,
+        backend: WaylandCaptureBackend,
+        encoder_backend: EncoderBackendKind,
+        event_sink: CaptureEventSink
     ) -> Result<Self, ScreenCaptureError> {
         Ok(WaylandScreenCapturer {
             config,
             running: Arc::new(Mutex::new(false)),
-            capture_process: Arc::new(Mutex::new(None)),
             monitor,
             stream_buffer,
             quality_controller,
             stats,
+            keyframes,
             capture_thread: None,
+            backend,
+            encoder_backend,
+            portal_session: None,
+            event_sink,
+            runner: Arc::new(SystemCommandRunner),
         })
     }
+    
+    /// Get the capture backend in use
+    pub fn backend(&self) -> &WaylandCaptureBackend {
+        &self.backend
+    }
 
     /// Start PipeWire process for Wayland screen capture
     fn start_pipewire_process(&self) -> Result<Child, ScreenCaptureError> {
         let config_guard = self.config.lock().unwrap();
         
         // Create FFmpeg command for continuous stream using PipeWire
-        let mut cmd = Command::new("ffmpeg");
+        let mut cmd = self.runner.command("ffmpeg");
         
         // Input configuration
         // Use pipewire to capture Wayland screens
         cmd.arg("-f").arg("pipewire")
            .arg("-framerate").arg(config_guard.fps.to_string());
            
-        // Select specific monitor if needed
-        if self.monitor.name != "Wayland-0" {
+        // Select the chosen PipeWire node, otherwise the monitor's stream
+        if let Some(node) = &config_guard.pipewire_node {
+            cmd.arg("-i").arg(node.node_id.to_string());
+        } else if self.monitor.name != "Wayland-0" {
             cmd.arg("-i").arg(format!("{}:{}", "pipewire", self.monitor.index));
         } else {
             cmd.arg("-i").arg("0"); // Default screen
@@ -234,16 +386,25 @@ impl WaylandScreenCapturer {
         Ok(process)
     }
     
-    /// Wayland capture loop
+    /// Wayland capture loop.
+    ///
+    /// Supervises the encoder backend the same way as the X11 loop supervises
+    /// FFmpeg: unexpected exits are restarted with exponential backoff, and a
+    /// failing hardware encoder is replaced by software encoding. Every backend
+    /// delivers matroska, so frames and stats are handled the same for all.
     fn capture_loop(
         config: Arc<Mutex<ScreenCaptureConfig>>,
         running: Arc<Mutex<bool>>,
         stats: Arc<Mutex<CaptureStats>>,
+        keyframes: Arc<Mutex<KeyframeScheduler>>,
         window: Option<Window>,
         monitor: MonitorInfo,
         stream_buffer: Arc<Mutex<StreamBuffer>>,
         quality_controller: Arc<Mutex<AdaptiveQualityController>>,
-        capture_process: Arc<Mutex<Option<Child>>>,
+        portal_source: Option<(u32, RawFd)>,
+        encoder_backend: EncoderBackendKind,
+        event_sink: CaptureEventSink,
+        runner: Arc<dyn CommandRunner>,
     ) {
         // Get initial CPU usage
         let initial_cpu_usage = utils::get_cpu_usage().unwrap_or(0.0);
@@ -251,222 +412,376 @@ impl WaylandScreenCapturer {
         let mut last_frame_time = Instant::now();
         let mut frame_count: u64 = 0;
         let mut dropped_frames: u64 = 0;
+        let mut bytes_received: u64 = 0;
         let start_time = Instant::now();
         
-        // Start the PipeWire process for continuous capture
-        let mut process = match Self::start_pipewire_process_static(&config, &monitor, &quality_controller) {
-            Ok(process) => process,
-            Err(e) => {
-                eprintln!("Failed to start PipeWire process: {}", e);
-                return;
-            }
-        };
-        
-        // Store the process in shared variable
-        {
-            let mut process_guard = capture_process.lock().unwrap();
-            *process_guard = Some(process.try_clone().unwrap_or(process));
-        }
+        // Per-frame error paths are rate limited so a broken pipe cannot flood the logs
+        let mut buffer_error_log = RateLimitedLog::new(HOT_PATH_LOG_INTERVAL);
+        let mut resync_log = RateLimitedLog::new(HOT_PATH_LOG_INTERVAL);
+        let mut read_error_log = RateLimitedLog::new(HOT_PATH_LOG_INTERVAL);
         
-        // Get stdout for reading video data
-        let mut stdout = process.stdout.take().expect("Failed to take stdout from FFmpeg process");
+        let policy = RestartPolicy::new(config.lock().unwrap().max_restart_attempts);
+        let mut restart_attempt: u32 = 0;
         
-        // Buffer for reading output
-        let mut buffer = Vec::new();
-        let mut read_buffer = vec![0u8; 65536]; // 64KB buffer for reading
+        // Only the ffmpeg pipeline drops static frames; GStreamer keeps the full rate
+        let mut idle_detector = match (portal_source, encoder_backend) {
+            (None, EncoderBackendKind::FFmpeg) => IdleDetector::new(&config.lock().unwrap()),
+            _ => IdleDetector::disabled(),
+        };
         
-        // Main loop for capturing and processing frames
-        let mut last_stats_update = Instant::now();
+        // Sampled on its own thread, the loop only picks up the last reading
+        let gpu_monitor = GpuMonitor::start(runner.clone(), &config.lock().unwrap().hardware_acceleration);
         
         while *running.lock().unwrap() {
-            let now = Instant::now();
-            
-            // Check if the process is still running
-            match process.try_wait() {
-                Ok(Some(status)) => {
-                    eprintln!("FFmpeg/PipeWire process exited with status: {}", status);
-                    break;
-                }
-                Ok(None) => {},
-                Err(e) => {
-                    eprintln!("Error checking FFmpeg/PipeWire process: {}", e);
-                    break;
-                }
-            }
+            // Build and start the encoder for continuous capture
+            let mut encoder = None;
+            let start_result = match Self::create_encoder(runner.as_ref(), &config, &monitor, &quality_controller, portal_source, encoder_backend) {
+                Ok(backend) => {
+                    let backend = encoder.insert(backend);
+                    backend.build().and_then(|_| backend.start())
+                },
+                Err(e) => Err(e),
+            };
+            let process_started = Instant::now();
+            let mut keyframe_restart = false;
+            let stderr = encoder.as_ref().map(|encoder| encoder.diagnostics()).unwrap_or_default();
             
-            // Read data from the FFmpeg process
-            match stdout.read(&mut read_buffer) {
-                Ok(n) if n > 0 => {
-                    // Data was read, add it to the buffer
-                    buffer.extend_from_slice(&read_buffer[0..n]);
+            let (exit_reason, spawn_error) = match start_result {
+                Ok(output) => {
+                    let encoder_name = encoder.as_ref().map_or("encoder", |encoder| encoder.name());
+                    keyframes.lock().unwrap().encoder_started(process_started);
+                    
+                    // Every pipeline starts a new matroska stream with its own header
+                    let mut demuxer = MatroskaDemuxer::new();
+                    let mut frame_clock = FrameClock::new();
                     
-                    // For matroska/webm streams, we need to detect frame boundaries
-                    // Here's a simple heuristic: look for keyframe markers (0x87)
-                    // A more robust approach would involve parsing the matroska container
+                    // Main loop for capturing and processing frames
+                    let mut last_stats_update = Instant::now();
+                    let mut exit_reason = None;
+                    let mut resumed_reported = restart_attempt == 0;
+                    let mut watchdog = FreezeWatchdog::new(&config.lock().unwrap(), process_started);
                     
-                    let mut frame_start_index = 0;
-                    for i in 0..buffer.len().saturating_sub(4) {
-                        // Look for likely keyframe marker (simple heuristic)
-                        if buffer[i] == 0x87 && buffer[i+1] == 0x00 {
-                            // Found a potential keyframe, treat everything before this as one frame
-                            if i > frame_start_index {
-                                // Extract the frame data
-                                let frame_data = buffer[frame_start_index..i].to_vec();
+                    while *running.lock().unwrap() {
+                        let now = Instant::now();
+                        
+                        // A new pipeline starts its stream with a keyframe
+                        if keyframes.lock().unwrap().take_due(now) {
+                            log::info!(target: TARGET_SCREEN_CAPTURE, "Restarting {} to force a keyframe", encoder_name);
+                            keyframe_restart = true;
+                            break;
+                        }
+                        
+                        // A stalled PipeWire stream leaves the encoder running without output
+                        if let Some(kind) = watchdog.check(now, idle_detector.state()) {
+                            let stalled_ms = watchdog.stalled_for(now).as_millis() as u64;
+                            log::warn!(target: TARGET_SCREEN_CAPTURE, "{} delivered no frames for {} ms ({:?}), restarting it", encoder_name, stalled_ms, kind);
+                            stats.lock().unwrap().freeze_recoveries += 1;
+                            event_sink(CaptureEvent::Frozen { kind, stalled_ms });
+                            exit_reason = Some(format!("{} delivered no frames for {} ms", encoder_name, stalled_ms));
+                            break;
+                        }
+                        
+                        // Wait briefly for data so a stop request is noticed
+                        match output.recv_timeout(Duration::from_millis(100)) {
+                            Ok(EncoderOutput::Data(data)) => {
+                                let read_at = Instant::now();
+                                if !resumed_reported {
+                                    resumed_reported = true;
+                                    event_sink(CaptureEvent::Resumed { attempt: restart_attempt });
+                                }
+                                
+                                bytes_received += data.len() as u64;
+                                watchdog.data_received(now);
                                 
-                                if !frame_data.is_empty() {
-                                    // Create frame data
-                                    let frame = FrameData {
-                                        data: frame_data,
-                                        timestamp: now.elapsed().as_millis() as u64,
-                                        keyframe: true, // Assuming keyframes for simplicity
-                                        width: monitor.width,
-                                        height: monitor.height,
-                                        format: "matroska".to_string(),
+                                // Demux the matroska stream into frames of the video track
+                                let resyncs_before = demuxer.resync_count();
+                                for frame in demuxer.push(&data) {
+                                    let mut frame = match demuxer.to_frame_data(frame, monitor.width, monitor.height) {
+                                        Some(frame) => frame,
+                                        None => continue,
                                     };
+                                    frame_clock.stamp(&mut frame, read_at);
+                                    
+                                    // The first changed frame after an idle phase ends it
+                                    if let Some(activity) = idle_detector.on_frame(frame.timestamp) {
+                                        log::debug!(target: TARGET_SCREEN_CAPTURE, "Capture activity changed to {:?}", activity);
+                                        stats.lock().unwrap().activity = activity;
+                                        event_sink(CaptureEvent::ActivityChanged { activity });
+                                    }
+                                    
+                                    if frame.keyframe {
+                                        keyframes.lock().unwrap().keyframe_emitted(now);
+                                    }
                                     
                                     // Add to buffer
                                     {
                                         let mut stream_buf = stream_buffer.lock().unwrap();
                                         if let Err(e) = stream_buf.push_frame(frame) {
-                                            eprintln!("Error adding frame to buffer: {}", e);
+                                            if let Some(suppressed) = buffer_error_log.check() {
+                                                log::warn!(target: TARGET_SCREEN_CAPTURE, "Error adding frame to buffer: {} ({} similar messages suppressed)", e, suppressed);
+                                            }
                                             dropped_frames += 1;
                                         }
                                     }
                                     
+                                    // A full buffer means a slow viewer, not a frozen encoder
+                                    watchdog.frame_pushed(now);
                                     frame_count += 1;
                                 }
                                 
-                                frame_start_index = i;
-                            }
-                        }
-                    }
-                    
-                    // Remove processed data from buffer, keeping potential partial frame
-                    if frame_start_index > 0 {
-                        buffer.drain(0..frame_start_index);
-                    }
-                    
-                    // If buffer is too big, reset it (something went wrong)
-                    if buffer.len() > 10 * 1024 * 1024 { // 10MB limit
-                        buffer.clear();
-                        eprintln!("Buffer overflow, clearing");
-                    }
-                    
-                    // Send frame data to frontend if window is provided
-                    if let Some(ref window) = window {
-                        // Get the first frame from buffer without removing it
-                        let frame_preview = {
-                            let stream_buf = stream_buffer.lock().unwrap();
-                            stream_buf.peek_next_frame().map(|f| f.data.clone())
-                        };
-                        
-                        if let Some(frame_data) = frame_preview {
-                            // Send as binary data or base64 depending on frontend needs
-                            let _ = window.emit("frame_data", utils::frame_to_base64(&frame_data));
-                        }
-                    }
-                    
-                    // Update stats periodically
-                    if now.duration_since(last_stats_update) > Duration::from_millis(500) {
-                        last_stats_update = now;
-                        
-                        // Capture current statistics
-                        let current_cpu_usage = utils::get_cpu_usage().unwrap_or(initial_cpu_usage);
-                        let buffer_stats = stream_buffer.lock().unwrap().get_stats();
-                        let buffer_fill_ratio = buffer_stats.fill_ratio;
-                        
-                        // Calculate frame rate and bitrate
-                        let elapsed_secs = start_time.elapsed().as_secs_f64();
-                        let fps = if elapsed_secs > 0.0 { frame_count as f64 / elapsed_secs } else { 0.0 };
-                        let bitrate = if elapsed_secs > 0.0 { 
-                            (buffer.len() as f64 * 8.0 / elapsed_secs) as u64
-                        } else { 
-                            0 
-                        };
-                        
-                        // Update quality controller with new metrics
-                        {
-                            let mut quality_ctrl = quality_controller.lock().unwrap();
-                            quality_ctrl.update_metrics(
-                                current_cpu_usage,
-                                (bitrate / 1000) as u32, // kbps
-                                if frame_count > 0 { dropped_frames as f32 / frame_count as f32 } else { 0.0 },
-                                buffer_stats.latency_ms as u32
-                            );
-                            
-                            // Apply quality adjustments if needed
-                            let _ = quality_ctrl.adjust_quality();
-                        }
-                        
-                        // Update capture statistics
-                        {
-                            let mut stats_guard = stats.lock().unwrap();
-                            stats_guard.fps = fps;
-                            stats_guard.bitrate = bitrate;
-                            stats_guard.frame_count = frame_count;
-                            stats_guard.dropped_frames = dropped_frames;
-                            stats_guard.buffer_level = buffer_stats.frame_count;
-                            stats_guard.latency_estimate = buffer_stats.latency_ms;
-                            
-                            // Send stats to frontend
-                            if let Some(ref window) = window {
-                                let _ = window.emit("capture_stats", stats_guard.clone());
+                                // Corrupt data is skipped up to the next cluster by the demuxer
+                                if demuxer.resync_count() > resyncs_before {
+                                    if let Some(suppressed) = resync_log.check() {
+                                        log::warn!(target: TARGET_SCREEN_CAPTURE, "Invalid matroska data, resynchronized on next cluster ({} similar messages suppressed)", suppressed);
+                                    }
+                                }
+                                
+                                // Send frame data to frontend if window is provided
+                                if let Some(ref window) = window {
+                                    // Get the first frame from buffer without removing it
+                                    let frame_preview = {
+                                        let stream_buf = stream_buffer.lock().unwrap();
+                                        stream_buf.peek_next_frame().map(|f| f.data.clone())
+                                    };
+                                    
+                                    if let Some(frame_data) = frame_preview {
+                                        // Send as binary data or base64 depending on frontend needs
+                                        let _ = window.emit("frame_data", utils::frame_to_base64(&frame_data));
+                                    }
+                                }
+                                
+                                // Update stats periodically
+                                if now.duration_since(last_stats_update) > Duration::from_millis(500) {
+                                    last_stats_update = now;
+                                    
+                                    // Capture current statistics
+                                    let current_cpu_usage = utils::get_cpu_usage().unwrap_or(initial_cpu_usage);
+                                    let buffer_stats = stream_buffer.lock().unwrap().get_stats();
+                                    let buffer_fill_ratio = buffer_stats.fill_ratio;
+                                    
+                                    // Calculate frame rate and bitrate
+                                    let elapsed_secs = start_time.elapsed().as_secs_f64();
+                                    let fps = if elapsed_secs > 0.0 { frame_count as f64 / elapsed_secs } else { 0.0 };
+                                    let bitrate = if elapsed_secs > 0.0 { 
+                                        (bytes_received as f64 * 8.0 / elapsed_secs) as u64
+                                    } else { 
+                                        0 
+                                    };
+                                    
+                                    // Update quality controller with new metrics. Paused while
+                                    // idle: the throttled rate says nothing about the network.
+                                    if idle_detector.quality_control_enabled() {
+                                        let mut quality_ctrl = quality_controller.lock().unwrap();
+                                        quality_ctrl.update_metrics(
+                                            current_cpu_usage,
+                                            (bitrate / 1000) as u32, // kbps
+                                            if frame_count > 0 { dropped_frames as f32 / frame_count as f32 } else { 0.0 },
+                                            buffer_stats.latency_ms as u32
+                                        );
+                                        
+                                        // Apply quality adjustments if needed
+                                        let scale_before = quality_ctrl.output_scale_percent();
+                                        let _ = quality_ctrl.adjust_quality();
+                                        
+                                        // A new output size takes a new encoder process
+                                        if quality_ctrl.output_scale_percent() != scale_before {
+                                            log::info!(target: TARGET_SCREEN_CAPTURE, "Output scale changed to {}%, restarting encoder", quality_ctrl.output_scale_percent());
+                                            keyframes.lock().unwrap().request(now);
+                                        }
+                                    }
+                                    
+                                    // Update capture statistics
+                                    {
+                                        let mut stats_guard = stats.lock().unwrap();
+                                        stats_guard.fps = fps;
+                                        stats_guard.bitrate = bitrate;
+                                        stats_guard.frame_count = frame_count;
+                                        stats_guard.dropped_frames = dropped_frames;
+                                        stats_guard.buffer_level = buffer_stats.frame_count;
+                                        stats_guard.latency_estimate = buffer_stats.latency_ms;
+                                        stats_guard.time_since_keyframe_ms = keyframes.lock().unwrap()
+                                            .since_last_keyframe(now)
+                                            .map(|elapsed| elapsed.as_millis() as u64);
+                                        stats_guard.gpu = gpu_monitor.as_ref().and_then(GpuMonitor::latest);
+                                        
+                                        // Send stats to frontend
+                                        if let Some(ref window) = window {
+                                            let _ = window.emit("capture_stats", stats_guard.clone());
+                                        }
+                                    }
+                                }
+                                
+                                last_frame_time = now;
+                            },
+                            Ok(EncoderOutput::Ended(reason)) => {
+                                log::warn!(target: TARGET_SCREEN_CAPTURE, "{}", reason);
+                                exit_reason = Some(reason);
+                                break;
+                            },
+                            Err(RecvTimeoutError::Timeout) => {},
+                            Err(RecvTimeoutError::Disconnected) => {
+                                if let Some(suppressed) = read_error_log.check() {
+                                    log::error!(target: TARGET_SCREEN_CAPTURE, "{} output closed unexpectedly ({} similar messages suppressed)", encoder_name, suppressed);
+                                }
+                                exit_reason = Some(format!("{} output closed unexpectedly", encoder_name));
+                                break;
                             }
                         }
                     }
                     
-                    last_frame_time = now;
-                },
-                Ok(_) => {
-                    // No data available, wait a bit
-                    thread::sleep(Duration::from_millis(1));
+                    (exit_reason, None)
                 },
                 Err(e) => {
-                    eprintln!("Error reading from FFmpeg/PipeWire: {}", e);
-                    dropped_frames += 1;
-                    
-                    // Update stats
-                    let mut stats_guard = stats.lock().unwrap();
-                    stats_guard.dropped_frames = dropped_frames;
-                    
-                    // Check if process is still alive
-                    if let Err(e) = process.try_wait() {
-                        eprintln!("Error checking FFmpeg/PipeWire process: {}", e);
-                        break;
-                    }
+                    log::error!(target: TARGET_SCREEN_CAPTURE, "Failed to start encoder: {}", e);
+                    (Some(e.to_string()), Some(e))
                 }
+            };
+            
+            // Clean up the encoder before deciding whether to restart
+            if let Some(mut encoder) = encoder {
+                encoder.stop();
+            }
+            
+            // Restarted on purpose, not a failure
+            if keyframe_restart && *running.lock().unwrap() {
+                continue;
+            }
+            
+            // Stopped on request
+            let reason = match exit_reason {
+                Some(reason) if *running.lock().unwrap() => reason,
+                _ => break,
+            };
+            
+            // A long healthy run refills the restart budget
+            if process_started.elapsed() >= STABLE_RUN_DURATION {
+                restart_attempt = 0;
+            }
+            
+            if !prepare_restart(&config, &stats, &event_sink, &policy, &mut restart_attempt, reason, &stderr, spawn_error) {
+                break;
+            }
+            
+            if !sleep_while_running(&running, policy.backoff(restart_attempt)) {
+                break;
             }
         }
+    }
+    
+    /// Encoder for the next run of the capture loop. Portal streams are read
+    /// by GStreamer either way, through gst-launch unless the in-process
+    /// backend was selected.
+    fn create_encoder(
+        runner: &dyn CommandRunner,
+        config: &Arc<Mutex<ScreenCaptureConfig>>,
+        monitor: &MonitorInfo,
+        quality_controller: &Arc<Mutex<AdaptiveQualityController>>,
+        portal_source: Option<(u32, RawFd)>,
+        encoder_backend: EncoderBackendKind
+    ) -> Result<Box<dyn EncoderBackend>, ScreenCaptureError> {
+        #[cfg(feature = "gstreamer-backend")]
+        if encoder_backend == EncoderBackendKind::GStreamer {
+            let source = match portal_source {
+                Some((node_id, fd)) => PipeWireSource::Portal { node_id, fd },
+                None => PipeWireSource::Node(Self::resolve_pipewire_node(runner, config)?),
+            };
+            let output_scale = quality_controller.lock().unwrap().output_scale_percent();
+            let elements = gstreamer_pipeline(&config.lock().unwrap(), monitor, output_scale, &source, &GSTREAMER_APPSINK);
+            return Ok(Box::new(GStreamerBackend::new(elements)));
+        }
+        #[cfg(not(feature = "gstreamer-backend"))]
+        let _ = encoder_backend;
+        
+        match portal_source {
+            Some((node_id, fd)) => Ok(Box::new(ProcessBackend::gst_launch(
+                Self::portal_command_static(runner, config, monitor, quality_controller, node_id, fd)
+            ))),
+            None => Ok(Box::new(ProcessBackend::ffmpeg(
+                Self::pipewire_command_static(runner, config, monitor, quality_controller)?
+            ))),
+        }
+    }
+    
+    /// Node id of the configured PipeWire source. A stale id is looked up again
+    /// by node name and replaced in the config.
+    fn resolve_pipewire_node(runner: &dyn CommandRunner, config: &Arc<Mutex<ScreenCaptureConfig>>) -> Result<Option<u32>, ScreenCaptureError> {
+        let selection = match config.lock().unwrap().pipewire_node.clone() {
+            Some(selection) => selection,
+            None => return Ok(None),
+        };
         
-        // Clean up when the loop ends
-        if let Err(e) = process.kill() {
-            eprintln!("Error killing FFmpeg/PipeWire process: {}", e);
+        let node_id = pipewire::resolve_node(&selection, &pipewire::list_sources(runner)?)?;
+        if node_id != selection.node_id {
+            log::info!(
+                target: TARGET_SCREEN_CAPTURE,
+                "PipeWire node {} of '{}' is stale, capturing node {}",
+                selection.node_id, selection.name, node_id
+            );
+            if let Some(node) = &mut config.lock().unwrap().pipewire_node {
+                node.node_id = node_id;
+            }
         }
+        
+        Ok(Some(node_id))
     }
     
-    /// Static version of start_pipewire_process for use in capture_loop
-    fn start_pipewire_process_static(
+    /// ffmpeg command reading the PipeWire stream, for the FFmpeg backend
+    fn pipewire_command_static(
+        runner: &dyn CommandRunner,
         config: &Arc<Mutex<ScreenCaptureConfig>>,
         monitor: &MonitorInfo,
         quality_controller: &Arc<Mutex<AdaptiveQualityController>>
-    ) -> Result<Child, ScreenCaptureError> {
+    ) -> Result<Command, ScreenCaptureError> {
+        // Node ids change when the compositor restarts, so resolve them on every start
+        let node_id = Self::resolve_pipewire_node(runner, config)?;
         let config_guard = config.lock().unwrap();
         
         // Create FFmpeg command for continuous stream using PipeWire
-        let mut cmd = Command::new("ffmpeg");
+        let mut cmd = runner.command("ffmpeg");
         
         // Input configuration
         // Use pipewire to capture Wayland screens
         cmd.arg("-f").arg("pipewire")
            .arg("-framerate").arg(config_guard.fps.to_string());
            
-        // Select specific monitor if needed
-        if monitor.name != "Wayland-0" {
+        // Select the chosen PipeWire node, otherwise the monitor's stream
+        if let Some(node_id) = node_id {
+            cmd.arg("-i").arg(node_id.to_string());
+        } else if monitor.name != "Wayland-0" {
             cmd.arg("-i").arg(format!("{}:{}", "pipewire", monitor.index));
         } else {
             cmd.arg("-i").arg("0"); // Default screen
         }
         
+        // Crop to the configured region; PipeWire always delivers the whole output
+        let mut filters = Vec::new();
+        let region = config_guard.effective_region(monitor);
+        if config_guard.capture_region.is_some() {
+            filters.push(format!("crop={}:{}:{}:{}", region.width, region.height, region.x, region.y));
+        }
+        
+        // Masks are drawn before decimating, so changes under them do not count as activity
+        let output_scale = quality_controller.lock().unwrap().output_scale_percent();
+        let (output_width, output_height) = config_guard.output_size(monitor, output_scale);
+        filters.extend(privacy::ffmpeg_mask_filters(&config_guard.privacy, &region, output_width));
+        
+        // Drop unchanged frames down to the idle frame rate
+        let decimate = idle::decimate_filter(&config_guard);
+        filters.extend(decimate.clone());
+        
+        // Scale to the output resolution last, the other filters work on raw frames
+        if (output_width, output_height) != (region.width, region.height) {
+            cmd.args(scale::ffmpeg_scale_device_args(&config_guard));
+            filters.push(scale::ffmpeg_scale_filter(&config_guard, output_width, output_height));
+        }
+        
+        if !filters.is_empty() {
+            cmd.arg("-vf").arg(filters.join(","));
+        }
+        if decimate.is_some() {
+            cmd.arg("-vsync").arg("vfr");
+        }
+        
         // Hardware acceleration
         match config_guard.hardware_acceleration {
             HardwareAcceleration::VAAPI => {
@@ -585,15 +900,40 @@ impl WaylandScreenCapturer {
            .arg("-movflags").arg("faststart")  // Fast start for streaming
            .arg("-");  // Output to stdout
         
-        // Redirect stderr and make stdout available for reading
-        cmd.stderr(Stdio::null())
-           .stdout(Stdio::piped());
+        Ok(cmd)
+    }
+    
+    /// gst-launch command that reads the portal's PipeWire node.
+    /// ffmpeg has no input that accepts a portal remote fd, pipewiresrc does.
+    fn portal_command_static(
+        runner: &dyn CommandRunner,
+        config: &Arc<Mutex<ScreenCaptureConfig>>,
+        monitor: &MonitorInfo,
+        quality_controller: &Arc<Mutex<AdaptiveQualityController>>,
+        node_id: u32,
+        pipewire_fd: RawFd
+    ) -> Command {
+        let output_scale = quality_controller.lock().unwrap().output_scale_percent();
+        let source = PipeWireSource::Portal { node_id, fd: PORTAL_CHILD_FD };
         
-        // Start the ffmpeg process
-        let process = cmd.spawn()
-            .map_err(|e| to_ffmpeg_error(e, "Failed to start FFmpeg process with PipeWire"))?;
+        let mut cmd = runner.command("gst-launch-1.0");
+        cmd.arg("-q"); // Keep stdout clean for the video stream
+        cmd.args(gstreamer_pipeline(&config.lock().unwrap(), monitor, output_scale, &source, &GSTREAMER_FDSINK));
         
-        Ok(process)
+        // The remote fd is close-on-exec in this process, so only gst-launch
+        // gets a copy. dup2 is async-signal-safe and the copy it creates is
+        // inherited across exec.
+        unsafe {
+            cmd.pre_exec(move || {
+                if pipewire_fd == PORTAL_CHILD_FD {
+                    nix::fcntl::fcntl(pipewire_fd, nix::fcntl::FcntlArg::F_SETFD(nix::fcntl::FdFlag::empty()))?;
+                } else {
+                    nix::unistd::dup2(pipewire_fd, PORTAL_CHILD_FD)?;
+                }
+                Ok(())
+            });
+        }
+        cmd
     }
 }
 
@@ -614,14 +954,53 @@ impl ScreenCapturer for WaylandScreenCapturer {
             buffer.clear();
         }
 
+        // Portal desktops need user consent before a stream exists
+        let portal_source = if self.backend == WaylandCaptureBackend::Portal {
+            let cursor_mode = self.config.lock().unwrap().cursor_mode();
+            
+            let session = match PortalSession::open(cursor_mode) {
+                Ok(session) => session,
+                Err(e) => {
+                    *self.running.lock().unwrap() = false;
+                    return Err(e);
+                }
+            };
+            
+            let stream = session.streams()[0].clone();
+            let fd = session.pipewire_fd();
+            
+            // The chosen source defines the real capture geometry
+            if let Some((width, height)) = stream.size {
+                self.monitor.width = width as u32;
+                self.monitor.height = height as u32;
+            }
+            if let Some((x, y)) = stream.position {
+                self.monitor.x_offset = x;
+                self.monitor.y_offset = y;
+            }
+            
+            self.portal_session = Some(session);
+            Some((stream.node_id, fd))
+        } else {
+            // Report a vanished PipeWire source right away instead of from the capture thread
+            if let Err(e) = Self::resolve_pipewire_node(self.runner.as_ref(), &self.config) {
+                *self.running.lock().unwrap() = false;
+                return Err(e);
+            }
+            None
+        };
+
         // Clone necessary data for the capture thread
         let config = self.config.clone();
         let running = self.running.clone();
         let stats = self.stats.clone();
+        let keyframes = self.keyframes.clone();
         let monitor = self.monitor.clone();
         let stream_buffer = self.stream_buffer.clone();
         let quality_controller = self.quality_controller.clone();
-        let capture_process = self.capture_process.clone();
+        let encoder_backend = self.encoder_backend;
+        let event_sink = self.event_sink.clone();
+        let runner = self.runner.clone();
 
         // Create the capture thread
         self.capture_thread = Some(thread::spawn(move || {
@@ -629,11 +1008,15 @@ impl ScreenCapturer for WaylandScreenCapturer {
                 config,
                 running,
                 stats,
+                keyframes,
                 None, // No window for direct UI updates in the module
                 monitor,
                 stream_buffer,
                 quality_controller,
-                capture_process
+                portal_source,
+                encoder_backend,
+                event_sink,
+                runner
             );
         }));
 
@@ -647,25 +1030,21 @@ impl ScreenCapturer for WaylandScreenCapturer {
             *running = false;
         }
 
-        // Kill the FFmpeg process if it's running
-        {
-            let mut process = self.capture_process.lock().unwrap();
-            if let Some(ref mut child) = *process {
-                let _ = child.kill();
-            }
-            *process = None;
-        }
-
-        // Wait for the capture thread to finish
+        // Wait for the capture thread to finish; it stops the encoder
         if let Some(handle) = self.capture_thread.take() {
             match handle.join() {
                 Ok(_) => {}
                 Err(e) => {
-                    eprintln!("Error joining capture thread: {:?}", e);
+                    log::error!(target: TARGET_SCREEN_CAPTURE, "Error joining capture thread: {:?}", e);
                 }
             }
         }
 
+        // Release the portal session so the compositor stops its sharing indicator
+        if let Some(session) = self.portal_session.take() {
+            session.close();
+        }
+
         Ok(())
     }
 
@@ -681,11 +1060,33 @@ impl ScreenCapturer for WaylandScreenCapturer {
 
 /// Get monitor information for Wayland
 pub fn get_wayland_monitors() -> Result<Vec<MonitorInfo>, ScreenCaptureError> {
+    let compositor = detect_wayland_compositor();
+    
+    // Compositor-specific tools know the full layout; if they are missing or
+    // fail, fall through to the generic detection below
+    let native = match compositor {
+        WaylandCompositor::Hyprland => {
+            query_monitors("hyprctl", &["monitors", "all", "-j"], parse_hyprctl_output)
+        },
+        WaylandCompositor::Kde => {
+            query_monitors("kscreen-doctor", &["-j"], parse_kscreen_doctor_output)
+        },
+        _ => None,
+    };
+    if let Some(monitors) = native {
+        return Ok(monitors);
+    }
+    
+    // GNOME, and KDE without kscreen-doctor, only expose their sources through the portal
+    if select_capture_backend(&compositor) == WaylandCaptureBackend::Portal {
+        return portal::get_portal_monitors();
+    }
+    
     // For Wayland, we can use wlr-randr for wlroots-based compositors
     // or try to use swaymsg for Sway
     
     // First try wlr-randr
-    let output = Command::new("wlr-randr")
+    let output = SystemCommandRunner.command("wlr-randr")
         .output();
     
     if let Ok(output) = output {
@@ -695,7 +1096,7 @@ pub fn get_wayland_monitors() -> Result<Vec<MonitorInfo>, ScreenCaptureError> {
     }
     
     // Fallback to swaymsg for Sway
-    let output = Command::new("swaymsg")
+    let output = SystemCommandRunner.command("swaymsg")
         .arg("-t")
         .arg("get_outputs")
         .output();
@@ -706,29 +1107,24 @@ pub fn get_wayland_monitors() -> Result<Vec<MonitorInfo>, ScreenCaptureError> {
         }
     }
     
-    // If both fail, try to use most basic detection with Wayland-specific tools
-    let output = Command::new("sh")
-        .arg("-c")
-        .arg("echo $WAYLAND_DISPLAY")
-        .output();
-    
-    // If we're running on Wayland, at least provide a generic monitor
-    if let Ok(output) = output {
-        if output.status.success() && !String::from_utf8_lossy(&output.stdout).trim().is_empty() {
-            // For now, just return a single monitor that represents the whole screen
-            let monitors = vec![MonitorInfo {
-                index: 0,
-                name: "Wayland-0".to_string(),
-                width: 1920, // Default assumption
-                height: 1080, // Default assumption
-                refresh_rate: Some(60.0), // Default assumption
-                primary: true,
-                x_offset: 0,
-                y_offset: 0,
-            }];
-            
-            return Ok(monitors);
-        }
+    // If both fail, at least provide a generic monitor when running on Wayland
+    let wayland_display = std::env::var_os("WAYLAND_DISPLAY").unwrap_or_default();
+    if !wayland_display.is_empty() {
+        // For now, just return a single monitor that represents the whole screen
+        let monitors = vec![MonitorInfo {
+            index: 0,
+            name: "Wayland-0".to_string(),
+            width: 1920, // Default assumption
+            height: 1080, // Default assumption
+            refresh_rate: Some(60.0), // Default assumption
+            primary: true,
+            x_offset: 0,
+            y_offset: 0,
+            scale_factor: 1.0,
+            rotation: MonitorRotation::Normal,
+        }];
+        
+        return Ok(monitors);
     }
     
     Err(ScreenCaptureError::DisplayServerError(
@@ -736,6 +1132,185 @@ pub fn get_wayland_monitors() -> Result<Vec<MonitorInfo>, ScreenCaptureError> {
     ))
 }
 
+/// Run a monitor listing tool and parse its output, `None` if either fails
+fn query_monitors(
+    program: &str,
+    args: &[&str],
+    parse: fn(&[u8]) -> Result<Vec<MonitorInfo>, ScreenCaptureError>,
+) -> Option<Vec<MonitorInfo>> {
+    let output = match SystemCommandRunner.command(program).args(args).output() {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            log::debug!(target: TARGET_SCREEN_CAPTURE, "{} exited with {}", program, output.status);
+            return None;
+        },
+        Err(e) => {
+            log::debug!(target: TARGET_SCREEN_CAPTURE, "Failed to run {}: {}", program, e);
+            return None;
+        },
+    };
+    
+    match parse(&output.stdout) {
+        Ok(monitors) => Some(monitors),
+        Err(e) => {
+            log::warn!(target: TARGET_SCREEN_CAPTURE, "Ignoring {} output: {}", program, e);
+            None
+        },
+    }
+}
+
+/// Rotation of a numeric wl_output transform; the flipped variants 4-7
+/// rotate like 0-3
+fn rotation_from_transform(transform: u64) -> MonitorRotation {
+    match transform % 4 {
+        1 => MonitorRotation::Left,
+        2 => MonitorRotation::Inverted,
+        3 => MonitorRotation::Right,
+        _ => MonitorRotation::Normal,
+    }
+}
+
+/// Parse `hyprctl monitors all -j` output (JSON)
+fn parse_hyprctl_output(output: &[u8]) -> Result<Vec<MonitorInfo>, ScreenCaptureError> {
+    let json: serde_json::Value = serde_json::from_slice(output)
+        .map_err(|e| ScreenCaptureError::DisplayServerError(
+            format!("Failed to parse hyprctl output: {}", e)
+        ))?;
+    
+    let mut monitors = Vec::new();
+    
+    for output in json.as_array().into_iter().flatten() {
+        let name = match output.get("name").and_then(|v| v.as_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+        
+        // "monitors all" also lists disabled outputs
+        if output.get("disabled").and_then(|v| v.as_bool()).unwrap_or(false) {
+            continue;
+        }
+        
+        // width/height are the mode's pixels, x/y are in the scaled layout
+        let width = output.get("width").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+        let height = output.get("height").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+        if width == 0 || height == 0 {
+            continue;
+        }
+        
+        let refresh_rate = output.get("refreshRate")
+            .and_then(|v| v.as_f64())
+            .filter(|rate| *rate > 0.0);
+        
+        monitors.push(MonitorInfo {
+            index: monitors.len(),
+            name: name.to_string(),
+            width,
+            height,
+            refresh_rate,
+            // Hyprland has no primary output
+            primary: false,
+            x_offset: output.get("x").and_then(|v| v.as_i64()).unwrap_or(0) as i32,
+            y_offset: output.get("y").and_then(|v| v.as_i64()).unwrap_or(0) as i32,
+            scale_factor: output.get("scale").and_then(|v| v.as_f64()).unwrap_or(1.0),
+            rotation: rotation_from_transform(output.get("transform").and_then(|v| v.as_u64()).unwrap_or(0)),
+        });
+    }
+    
+    if monitors.is_empty() {
+        return Err(ScreenCaptureError::DisplayServerError(
+            "No active monitors detected from hyprctl".to_string(),
+        ));
+    }
+    
+    Ok(monitors)
+}
+
+/// Parse `kscreen-doctor -j` output (JSON)
+fn parse_kscreen_doctor_output(output: &[u8]) -> Result<Vec<MonitorInfo>, ScreenCaptureError> {
+    let json: serde_json::Value = serde_json::from_slice(output)
+        .map_err(|e| ScreenCaptureError::DisplayServerError(
+            format!("Failed to parse kscreen-doctor output: {}", e)
+        ))?;
+    
+    let mut monitors = Vec::new();
+    
+    let outputs = json.get("outputs").and_then(|v| v.as_array()).into_iter().flatten();
+    for output in outputs {
+        let name = match output.get("name").and_then(|v| v.as_str()) {
+            Some(name) => name,
+            None => continue,
+        };
+        
+        let flag = |key: &str| output.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+        if !flag("connected") || !flag("enabled") {
+            continue;
+        }
+        
+        // The current mode gives the refresh rate, and the size on versions
+        // that do not report it per output
+        let current_mode_id = output.get("currentModeId").and_then(|v| v.as_str()).unwrap_or_default();
+        let current_mode = output.get("modes")
+            .and_then(|v| v.as_array())
+            .and_then(|modes| modes.iter().find(|mode| {
+                mode.get("id").and_then(|v| v.as_str()) == Some(current_mode_id)
+            }));
+        
+        let mode_size = |value: Option<&serde_json::Value>| -> Option<(u32, u32)> {
+            let size = value?.get("size")?;
+            let width = u32::try_from(size.get("width")?.as_i64()?).ok()?;
+            let height = u32::try_from(size.get("height")?.as_i64()?).ok()?;
+            Some((width, height)).filter(|(width, height)| *width > 0 && *height > 0)
+        };
+        let (width, height) = match mode_size(Some(output)).or_else(|| mode_size(current_mode)) {
+            Some(size) => size,
+            None => continue,
+        };
+        
+        let refresh_rate = current_mode
+            .and_then(|mode| mode.get("refreshRate"))
+            .and_then(|v| v.as_f64());
+        
+        // Plasma 5.24 replaced the primary flag with a priority, 1 being primary
+        let primary = output.get("primary").and_then(|v| v.as_bool())
+            .unwrap_or_else(|| output.get("priority").and_then(|v| v.as_u64()) == Some(1));
+        
+        let position = output.get("pos");
+        let coordinate = |key: &str| position
+            .and_then(|pos| pos.get(key))
+            .and_then(|v| v.as_i64())
+            .unwrap_or(0) as i32;
+        
+        // KScreen rotations are flags: 1 none, 2 left, 4 inverted, 8 right
+        let rotation = match output.get("rotation").and_then(|v| v.as_u64()) {
+            Some(2) => MonitorRotation::Left,
+            Some(4) => MonitorRotation::Inverted,
+            Some(8) => MonitorRotation::Right,
+            _ => MonitorRotation::Normal,
+        };
+        
+        monitors.push(MonitorInfo {
+            index: monitors.len(),
+            name: name.to_string(),
+            width,
+            height,
+            refresh_rate,
+            primary,
+            x_offset: coordinate("x"),
+            y_offset: coordinate("y"),
+            scale_factor: output.get("scale").and_then(|v| v.as_f64()).unwrap_or(1.0),
+            rotation,
+        });
+    }
+    
+    if monitors.is_empty() {
+        return Err(ScreenCaptureError::DisplayServerError(
+            "No active monitors detected from kscreen-doctor".to_string(),
+        ));
+    }
+    
+    Ok(monitors)
+}
+
 /// Parse wlr-randr output to get monitor information
 fn parse_wlr_randr_output(output: &[u8]) -> Result<Vec<MonitorInfo>, ScreenCaptureError> {
     let output_str = String::from_utf8_lossy(output);
@@ -744,13 +1319,18 @@ fn parse_wlr_randr_output(output: &[u8]) -> Result<Vec<MonitorInfo>, ScreenCaptu
     let mut index = 0;
     
     for line in output_str.lines() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        
         if !line.starts_with(' ') {
             // This is a monitor name line
             if let Some(monitor) = current_monitor.take() {
                 monitors.push(monitor);
             }
             
-            let name = line.trim().to_string();
+            // e.g. "DP-1 \"Dell Inc. DELL U2719D (DP-1)\""
+            let name = line.split_whitespace().next().unwrap_or_default().to_string();
             current_monitor = Some(MonitorInfo {
                 index,
                 name,
@@ -760,47 +1340,45 @@ fn parse_wlr_randr_output(output: &[u8]) -> Result<Vec<MonitorInfo>, ScreenCaptu
                 primary: false,
                 x_offset: 0,
                 y_offset: 0,
+                scale_factor: 1.0,
+                rotation: MonitorRotation::Normal,
             });
             
             index += 1;
-        } else if line.contains("current") {
-            // This line contains resolution
+        } else if line.trim_start().starts_with("Transform:") {
+            // e.g. "  Transform: 90"
             if let Some(ref mut monitor) = current_monitor {
-                let parts: Vec<&str> = line.trim().split_whitespace().collect();
-                if parts.len() >= 2 && parts[0] == "current" {
-                    let resolution = parts[1];
-                    let dimensions: Vec<&str> = resolution.split('x').collect();
-                    if dimensions.len() >= 2 {
-                        if let (Ok(width), Ok(height)) = (
-                            dimensions[0].parse::<u32>(),
-                            dimensions[1].parse::<u32>(),
-                        ) {
-                            monitor.width = width;
-                            monitor.height = height;
-                        }
-                    }
+                if let Some(rotation) = MonitorRotation::parse(&line.trim()["Transform:".len()..]) {
+                    monitor.rotation = rotation;
                 }
             }
-        } else if line.contains("Hz") {
-            // This line might contain refresh rate
+        } else if line.trim_start().starts_with("Scale:") {
+            // e.g. "  Scale: 2.000000"
             if let Some(ref mut monitor) = current_monitor {
-                if let Some(hz_part) = line.split_whitespace().find(|p| p.ends_with("Hz")) {
-                    if let Ok(rate) = hz_part.trim_end_matches("Hz").parse::<f64>() {
-                        monitor.refresh_rate = Some(rate);
-                    }
+                if let Ok(scale) = line.trim()["Scale:".len()..].trim().parse::<f64>() {
+                    monitor.scale_factor = scale;
                 }
             }
-        } else if line.contains("position") {
-            // This line contains position information
+        } else if line.contains("current") {
+            // The active mode, e.g. "    2560x1440 px, 59.951000 Hz (preferred, current)"
             if let Some(ref mut monitor) = current_monitor {
-                let parts: Vec<&str> = line.trim().split_whitespace().collect();
-                if parts.len() >= 2 && parts[0] == "position" {
-                    let position = parts[1];
-                    let pos_parts: Vec<&str> = position.split(',').collect();
-                    if pos_parts.len() >= 2 {
-                        monitor.x_offset = pos_parts[0].parse().unwrap_or(0);
-                        monitor.y_offset = pos_parts[1].parse().unwrap_or(0);
-                    }
+                let (resolution, refresh_rate) = parse_wlr_randr_mode(line);
+                if let Some((width, height)) = resolution {
+                    monitor.width = width;
+                    monitor.height = height;
+                }
+                if refresh_rate.is_some() {
+                    monitor.refresh_rate = refresh_rate;
+                }
+            }
+        } else if line.trim_start().to_lowercase().starts_with("position") {
+            // e.g. "  Position: 2560,0"
+            if let Some(ref mut monitor) = current_monitor {
+                let position = line.trim()["position".len()..].trim_start_matches(':').trim();
+                let pos_parts: Vec<&str> = position.split(',').collect();
+                if pos_parts.len() >= 2 {
+                    monitor.x_offset = pos_parts[0].trim().parse().unwrap_or(0);
+                    monitor.y_offset = pos_parts[1].trim().parse().unwrap_or(0);
                 }
             }
         } else if line.contains("primary") {
@@ -825,6 +1403,30 @@ fn parse_wlr_randr_output(output: &[u8]) -> Result<Vec<MonitorInfo>, ScreenCaptu
     Ok(monitors)
 }
 
+/// Resolution and refresh rate of a wlr-randr mode line. Accepts both
+/// "1920x1080 px, 60.000000 Hz" and the older "current 1920x1080 60Hz".
+fn parse_wlr_randr_mode(line: &str) -> (Option<(u32, u32)>, Option<f64>) {
+    let tokens: Vec<&str> = line
+        .split(|c: char| c.is_whitespace() || c == ',' || c == '(' || c == ')')
+        .filter(|token| !token.is_empty())
+        .collect();
+    
+    let resolution = tokens.iter().find_map(|token| {
+        let (width, height) = token.split_once('x')?;
+        Some((width.parse::<u32>().ok()?, height.parse::<u32>().ok()?))
+    });
+    
+    let refresh_rate = tokens.iter().enumerate().find_map(|(i, token)| {
+        if *token == "Hz" {
+            tokens.get(i.checked_sub(1)?)?.parse::<f64>().ok()
+        } else {
+            token.strip_suffix("Hz")?.parse::<f64>().ok()
+        }
+    });
+    
+    (resolution, refresh_rate)
+}
+
 /// Parse swaymsg output (JSON)
 fn parse_swaymsg_output(output: &[u8]) -> Result<Vec<MonitorInfo>, ScreenCaptureError> {
     let output_str = String::from_utf8_lossy(output);
@@ -867,8 +1469,21 @@ fn parse_swaymsg_output(output: &[u8]) -> Result<Vec<MonitorInfo>, ScreenCapture
                                 .and_then(|v| v.as_i64())
                                 .unwrap_or(0) as i32;
                             
-                            let refresh_rate = output.get("refresh")
-                                .and_then(|v| v.as_f64());
+                            // sway reports the active mode's refresh rate in mHz
+                            let refresh_rate = output.get("current_mode")
+                                .and_then(|mode| mode.get("refresh"))
+                                .and_then(|v| v.as_f64())
+                                .map(|millihertz| millihertz / 1000.0)
+                                .or_else(|| output.get("refresh").and_then(|v| v.as_f64()));
+                            
+                            let scale_factor = output.get("scale")
+                                .and_then(|v| v.as_f64())
+                                .unwrap_or(1.0);
+                            
+                            let rotation = output.get("transform")
+                                .and_then(|v| v.as_str())
+                                .and_then(MonitorRotation::parse)
+                                .unwrap_or(MonitorRotation::Normal);
                             
                             monitors.push(MonitorInfo {
                                 index,
@@ -879,6 +1494,8 @@ fn parse_swaymsg_output(output: &[u8]) -> Result<Vec<MonitorInfo>, ScreenCapture
                                 primary,
                                 x_offset,
                                 y_offset,
+                                scale_factor,
+                                rotation,
                             });
                         }
                     }
@@ -900,3 +1517,232 @@ fn parse_swaymsg_output(output: &[u8]) -> Result<Vec<MonitorInfo>, ScreenCapture
     
     Ok(monitors)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::{fixture, TestEnv};
+
+    #[test]
+    fn test_parse_wlr_randr_output() {
+        let monitors = parse_wlr_randr_output(&fixture("wlr-randr.txt")).unwrap();
+        assert_eq!(monitors.len(), 2);
+
+        let dp = &monitors[0];
+        assert_eq!((dp.index, dp.name.as_str()), (0, "DP-1"));
+        assert_eq!((dp.width, dp.height, dp.refresh_rate), (2560, 1440, Some(59.951)));
+        assert_eq!((dp.x_offset, dp.y_offset, dp.scale_factor, dp.rotation), (0, 0, 1.0, MonitorRotation::Normal));
+
+        // The current mode is the first one listed, the transform comes after it
+        let hdmi = &monitors[1];
+        assert_eq!((hdmi.index, hdmi.name.as_str()), (1, "HDMI-A-1"));
+        assert_eq!((hdmi.width, hdmi.height, hdmi.refresh_rate), (1920, 1080, Some(60.0)));
+        assert_eq!((hdmi.x_offset, hdmi.y_offset, hdmi.scale_factor, hdmi.rotation), (2560, 0, 1.5, MonitorRotation::Left));
+
+        assert_eq!(parse_wlr_randr_mode("current 1920x1080 60Hz"), (Some((1920, 1080)), Some(60.0)));
+        assert!(parse_wlr_randr_output(b"").is_err());
+    }
+
+    #[test]
+    fn test_parse_swaymsg_output() {
+        let monitors = parse_swaymsg_output(&fixture("swaymsg-get-outputs.json")).unwrap();
+
+        // The disabled laptop panel is skipped
+        let names: Vec<&str> = monitors.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["DP-1", "HDMI-A-1"]);
+
+        let dp = &monitors[0];
+        assert_eq!((dp.width, dp.height, dp.refresh_rate), (2560, 1440, Some(59.951)));
+        assert_eq!((dp.x_offset, dp.scale_factor, dp.rotation), (0, 1.0, MonitorRotation::Normal));
+
+        // sway reports the logical, rotated and scaled size in rect
+        let hdmi = &monitors[1];
+        assert_eq!((hdmi.width, hdmi.height, hdmi.refresh_rate), (720, 1280, Some(60.0)));
+        assert_eq!((hdmi.x_offset, hdmi.y_offset, hdmi.scale_factor, hdmi.rotation), (2560, 0, 1.5, MonitorRotation::Left));
+
+        assert!(parse_swaymsg_output(b"[]").is_err());
+    }
+
+    #[test]
+    fn test_parse_hyprctl_output() {
+        let monitors = parse_hyprctl_output(&fixture("hyprctl-monitors-all.json")).unwrap();
+
+        // The disabled DP-2 is skipped and the indices stay contiguous
+        let names: Vec<(usize, &str)> = monitors.iter().map(|m| (m.index, m.name.as_str())).collect();
+        assert_eq!(names, vec![(0, "DP-1"), (1, "eDP-1"), (2, "HDMI-A-1")]);
+        assert!(monitors.iter().all(|m| !m.primary));
+
+        let dp = &monitors[0];
+        assert_eq!((dp.width, dp.height, dp.refresh_rate), (2560, 1440, Some(59.951)));
+        assert_eq!((dp.x_offset, dp.y_offset, dp.scale_factor, dp.rotation), (0, 0, 1.0, MonitorRotation::Normal));
+
+        // Fractional scaling keeps the mode size, the layout position is logical
+        let edp = &monitors[1];
+        assert_eq!((edp.width, edp.height, edp.refresh_rate), (2256, 1504, Some(59.999)));
+        assert_eq!((edp.x_offset, edp.scale_factor), (2560, 1.25));
+
+        let hdmi = &monitors[2];
+        assert_eq!((hdmi.x_offset, hdmi.rotation), (4365, MonitorRotation::Left));
+
+        assert_eq!(rotation_from_transform(7), MonitorRotation::Right);
+        assert!(parse_hyprctl_output(b"[]").is_err());
+        assert!(parse_hyprctl_output(b"invalid").is_err());
+    }
+
+    #[test]
+    fn test_parse_kscreen_doctor_output() {
+        let monitors = parse_kscreen_doctor_output(&fixture("kscreen-doctor.json")).unwrap();
+
+        // The disabled HDMI-A-1 and the disconnected DP-2 are skipped
+        let names: Vec<(usize, &str)> = monitors.iter().map(|m| (m.index, m.name.as_str())).collect();
+        assert_eq!(names, vec![(0, "DP-1"), (1, "eDP-1")]);
+
+        let dp = &monitors[0];
+        assert_eq!((dp.width, dp.height, dp.refresh_rate), (2560, 1440, Some(59.95100021362305)));
+        assert_eq!((dp.x_offset, dp.y_offset, dp.scale_factor, dp.rotation), (1504, 0, 1.0, MonitorRotation::Normal));
+        assert!(!dp.primary);
+
+        let edp = &monitors[1];
+        assert_eq!((edp.width, edp.height, edp.refresh_rate), (2256, 1504, Some(59.999000549316406)));
+        assert_eq!((edp.x_offset, edp.scale_factor, edp.rotation), (0, 1.5, MonitorRotation::Left));
+        assert!(edp.primary);
+
+        // Older versions report neither the output size nor a priority
+        let legacy = br#"{"outputs": [{"name": "VGA-1", "connected": true, "enabled": true, "primary": true,
+            "currentModeId": "3", "modes": [{"id": "3", "refreshRate": 75, "size": {"width": 1280, "height": 1024}}]}]}"#;
+        let monitors = parse_kscreen_doctor_output(legacy).unwrap();
+        assert_eq!((monitors[0].width, monitors[0].height, monitors[0].refresh_rate), (1280, 1024, Some(75.0)));
+        assert!(monitors[0].primary);
+
+        assert!(parse_kscreen_doctor_output(br#"{"outputs": []}"#).is_err());
+    }
+
+    #[test]
+    fn test_compositor_from_env() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| vars.iter().find(|(key, _)| *key == name).map(|(_, value)| value.to_string())
+        };
+
+        // Session variables, detected compositor, backend with and without wlroots tools installed
+        type Case = (&'static [(&'static str, &'static str)], WaylandCompositor, WaylandCaptureBackend, WaylandCaptureBackend);
+        let cases: &[Case] = &[
+            (&[("HYPRLAND_INSTANCE_SIGNATURE", "v0.41_1718000000")], WaylandCompositor::Hyprland, WaylandCaptureBackend::Wlroots, WaylandCaptureBackend::Wlroots),
+            (&[("XDG_CURRENT_DESKTOP", "Hyprland")], WaylandCompositor::Hyprland, WaylandCaptureBackend::Wlroots, WaylandCaptureBackend::Wlroots),
+            (&[("SWAYSOCK", "/run/user/1000/sway-ipc.sock")], WaylandCompositor::Wlroots, WaylandCaptureBackend::Wlroots, WaylandCaptureBackend::Wlroots),
+            (&[("XDG_CURRENT_DESKTOP", "sway")], WaylandCompositor::Wlroots, WaylandCaptureBackend::Wlroots, WaylandCaptureBackend::Wlroots),
+            (&[("XDG_CURRENT_DESKTOP", "river")], WaylandCompositor::Wlroots, WaylandCaptureBackend::Wlroots, WaylandCaptureBackend::Wlroots),
+            (&[("XDG_CURRENT_DESKTOP", "ubuntu:GNOME")], WaylandCompositor::Gnome, WaylandCaptureBackend::Portal, WaylandCaptureBackend::Portal),
+            (&[("XDG_CURRENT_DESKTOP", "Pantheon")], WaylandCompositor::Gnome, WaylandCaptureBackend::Portal, WaylandCaptureBackend::Portal),
+            (&[("XDG_CURRENT_DESKTOP", "KDE")], WaylandCompositor::Kde, WaylandCaptureBackend::Portal, WaylandCaptureBackend::Portal),
+            (&[("XDG_SESSION_DESKTOP", "plasmawayland")], WaylandCompositor::Kde, WaylandCaptureBackend::Portal, WaylandCaptureBackend::Portal),
+            // A socket of the running compositor beats an inherited desktop name
+            (&[("XDG_CURRENT_DESKTOP", "KDE"), ("HYPRLAND_INSTANCE_SIGNATURE", "abc")], WaylandCompositor::Hyprland, WaylandCaptureBackend::Wlroots, WaylandCaptureBackend::Wlroots),
+            (&[("XDG_CURRENT_DESKTOP", "GNOME"), ("SWAYSOCK", "/run/user/1000/sway-ipc.sock")], WaylandCompositor::Wlroots, WaylandCaptureBackend::Wlroots, WaylandCaptureBackend::Wlroots),
+            // Unknown compositors use wlroots tooling when it is there
+            (&[("HYPRLAND_INSTANCE_SIGNATURE", "")], WaylandCompositor::Unknown, WaylandCaptureBackend::Wlroots, WaylandCaptureBackend::Portal),
+            (&[("XDG_CURRENT_DESKTOP", "niri")], WaylandCompositor::Unknown, WaylandCaptureBackend::Wlroots, WaylandCaptureBackend::Portal),
+            (&[], WaylandCompositor::Unknown, WaylandCaptureBackend::Wlroots, WaylandCaptureBackend::Portal),
+        ];
+
+        for (vars, compositor, with_tools, without_tools) in cases {
+            let detected = compositor_from_env(env(vars));
+            assert_eq!(&detected, compositor, "{:?}", vars);
+            assert_eq!(&capture_backend_for(&detected, || true), with_tools, "{:?}", vars);
+            assert_eq!(&capture_backend_for(&detected, || false), without_tools, "{:?}", vars);
+        }
+
+        // Known compositors never probe for tools
+        capture_backend_for(&WaylandCompositor::Gnome, || panic!("probed tools for GNOME"));
+    }
+
+    #[test]
+    fn test_capture_loop_demuxes_ffmpeg_stream() {
+        let env = TestEnv::new();
+        env.set_stdout("ffmpeg", fixture("pipewire-capture.mkv"));
+        env.keep_running("ffmpeg", Duration::from_secs(1));
+
+        let monitor = parse_wlr_randr_output(&fixture("wlr-randr.txt")).unwrap().remove(0);
+        let stream_buffer = Arc::new(Mutex::new(StreamBuffer::new(10, 10, 30, DropMode::DropOldest)));
+        let mut capturer = WaylandScreenCapturer::new(
+            Arc::new(Mutex::new(ScreenCaptureConfig::default())),
+            monitor,
+            stream_buffer,
+            Arc::new(Mutex::new(AdaptiveQualityController::new(80, None))),
+            Arc::new(Mutex::new(CaptureStats::default())),
+            Arc::new(Mutex::new(KeyframeScheduler::new())),
+            WaylandCaptureBackend::Wlroots,
+            EncoderBackendKind::FFmpeg,
+            Arc::new(|_| {}),
+        ).unwrap();
+        capturer.runner = env.runner();
+
+        capturer.start_capture().unwrap();
+        let mut frames = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while frames.len() < 3 && Instant::now() < deadline {
+            match capturer.get_next_frame() {
+                Some(frame) => frames.push(frame),
+                None => thread::sleep(Duration::from_millis(10)),
+            }
+        }
+        capturer.stop_capture().unwrap();
+
+        let data: Vec<&[u8]> = frames.iter().map(|f| f.data.as_slice()).collect();
+        assert_eq!(data, vec![&b"idr-frame"[..], b"p-frame-1", b"idr-frame-2"]);
+        assert_eq!(frames.iter().map(|f| f.keyframe).collect::<Vec<_>>(), vec![true, false, true]);
+        assert!(frames.iter().all(|f| (f.width, f.height, f.format.as_str()) == (1920, 1080, "h264")));
+
+        // Restarts after the fake exits may add further calls
+        let invocations = env.invocations();
+        let ffmpeg = invocations.first().unwrap().join(" ");
+        assert!(ffmpeg.starts_with("ffmpeg -f pipewire -framerate 30 -i pipewire:0 "), "{}", ffmpeg);
+        assert!(ffmpeg.ends_with(" -f matroska -movflags faststart -"), "{}", ffmpeg);
+    }
+
+    #[test]
+    fn test_frozen_ffmpeg_is_restarted() {
+        // Writes its stream once, then stays alive without another byte
+        let env = TestEnv::new();
+        let stream = fixture("pipewire-capture.mkv");
+        env.set_stdout("ffmpeg", &stream[..stream.len() / 2]);
+        env.keep_running("ffmpeg", Duration::from_secs(10));
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let stats = Arc::new(Mutex::new(CaptureStats::default()));
+        let config = ScreenCaptureConfig { freeze_timeout_ms: 300, ..ScreenCaptureConfig::default() };
+
+        let monitor = parse_wlr_randr_output(&fixture("wlr-randr.txt")).unwrap().remove(0);
+        let mut capturer = WaylandScreenCapturer::new(
+            Arc::new(Mutex::new(config)),
+            monitor,
+            Arc::new(Mutex::new(StreamBuffer::new(10, 10, 30, DropMode::DropOldest))),
+            Arc::new(Mutex::new(AdaptiveQualityController::new(80, None))),
+            stats.clone(),
+            Arc::new(Mutex::new(KeyframeScheduler::new())),
+            WaylandCaptureBackend::Wlroots,
+            EncoderBackendKind::FFmpeg,
+            Arc::new(move |event| recorded.lock().unwrap().push(event)),
+        ).unwrap();
+        capturer.runner = env.runner();
+
+        capturer.start_capture().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while env.invocations().len() < 2 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        capturer.stop_capture().unwrap();
+
+        assert!(env.invocations().len() >= 2, "the frozen ffmpeg was not restarted");
+        assert!(stats.lock().unwrap().freeze_recoveries >= 1);
+        let events = events.lock().unwrap();
+        let frozen = events.iter().find_map(|event| match event {
+            CaptureEvent::Frozen { kind, stalled_ms } => Some((*kind, *stalled_ms)),
+            _ => None,
+        });
+        let (kind, stalled_ms) = frozen.expect("no capture_frozen event");
+        assert_eq!(kind, crate::screen_capture::watchdog::FreezeKind::DeadPipe);
+        assert!(stalled_ms >= 300);
+        assert!(events.iter().any(|event| matches!(event, CaptureEvent::Interrupted { .. })));
+    }
+}
@@ -1,17 +1,33 @@
 // screen_capture/x11.rs - X11-specific screen capture implementation
 
-use std::process::{Command, Stdio, Child};
+use std::collections::HashMap;
+use std::process::{Stdio, Child};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 use std::io::Read;
+use std::sync::mpsc;
 
-use crate::screen_capture::types::{MonitorInfo, CaptureStats, ScreenCapturer, MonitorDetector, FrameData, VideoCodec, HardwareAcceleration};
+use crate::command_runner::{CommandRunner, SystemCommandRunner};
+use crate::screen_capture::types::{MonitorInfo, MonitorRotation, CaptureStats, CaptureEvent, CaptureEventSink, ScreenCapturer, MonitorDetector, FrameData, VideoCodec, HardwareAcceleration};
 use crate::screen_capture::error::{ScreenCaptureError, to_capture_error, to_ffmpeg_error};
 use crate::screen_capture::config::ScreenCaptureConfig;
+use crate::screen_capture::matroska::MatroskaDemuxer;
+use crate::screen_capture::clock::FrameClock;
 use crate::screen_capture::buffer::StreamBuffer;
 use crate::screen_capture::quality::AdaptiveQualityController;
+use crate::screen_capture::idle::{self, IdleDetector};
+use crate::screen_capture::scale;
+use crate::screen_capture::privacy;
+use crate::screen_capture::damage::{DamageGrabber, DamageSource};
+use crate::screen_capture::keyframe::KeyframeScheduler;
+use crate::screen_capture::gpu_stats::GpuMonitor;
+use crate::screen_capture::supervisor::{
+    RestartPolicy, StderrCollector, STABLE_RUN_DURATION,
+    prepare_restart, sleep_while_running,
+};
 use crate::screen_capture::utils;
+use crate::logging::{RateLimitedLog, HOT_PATH_LOG_INTERVAL, TARGET_SCREEN_CAPTURE};
 
 /// X11-specific monitor detector implementation
 pub struct X11MonitorDetector;
@@ -45,8 +61,14 @@ pub struct X11ScreenCapturer {
     // Stats
     stats: Arc<Mutex<CaptureStats>>,
     
+    // Keyframes requested for joining viewers
+    keyframes: Arc<Mutex<KeyframeScheduler>>,
+    
     // Capture thread
     capture_thread: Option<thread::JoinHandle<()>>,
+    
+    // Receiver for restart/failure events of the capture process
+    event_sink: CaptureEventSink,
 }
 
 impl X11ScreenCapturer {
@@ -56,7 +78,9 @@ impl X11ScreenCapturer {
         monitor: MonitorInfo,
         stream_buffer: Arc<Mutex<StreamBuffer>>,
         quality_controller: Arc<Mutex<AdaptiveQualityController>>,
-        stats: Arc<Mutex<CaptureStats>>
+        stats: Arc<Mutex<CaptureStats>>,
+        keyframes: Arc<Mutex<KeyframeScheduler>>,
+        event_sink: CaptureEventSink
     ) -> Result<Self, ScreenCaptureError> {
         Ok(X11ScreenCapturer {
             config,
@@ -66,34 +90,52 @@ impl X11ScreenCapturer {
             stream_buffer,
             quality_controller,
             stats,
+            keyframes,
             capture_thread: None,
+            event_sink,
         })
     }
 
     /// Start FFmpeg process for X11 screen capture
+    ///
+    /// With `damage_input` the frames come as raw video from stdin, written by
+    /// a `DamageGrabber` only when the screen changed.
     fn start_ffmpeg_process_static(
         config: &Arc<Mutex<ScreenCaptureConfig>>,
         monitor: &MonitorInfo,
-        quality_controller: &Arc<Mutex<AdaptiveQualityController>>
+        quality_controller: &Arc<Mutex<AdaptiveQualityController>>,
+        damage_input: bool
     ) -> Result<Child, ScreenCaptureError> {
         let config_guard = config.lock().unwrap();
         
         // Create FFmpeg command for continuous stream
-        let mut cmd = Command::new("ffmpeg");
-        
-        // Input configuration
-        cmd.arg("-f").arg("x11grab")
-           .arg("-video_size").arg(format!("{}x{}", monitor.width, monitor.height))
-           .arg("-i").arg(format!(":0.0+{},{}", monitor.x_offset, monitor.y_offset));
+        let mut cmd = SystemCommandRunner.command("ffmpeg");
         
-        // Framerate
-        cmd.arg("-framerate").arg(config_guard.fps.to_string());
-        
-        // Mouse cursor capture
-        if config_guard.capture_cursor {
-            cmd.arg("-draw_mouse").arg("1");
+        // Input configuration: the whole monitor or the configured region of it
+        let region = config_guard.effective_region(monitor);
+        if damage_input {
+            // Frames arrive irregularly, so they are timestamped on arrival
+            cmd.arg("-f").arg("rawvideo")
+               .arg("-pixel_format").arg("bgr0")
+               .arg("-video_size").arg(format!("{}x{}", region.width, region.height))
+               .arg("-use_wallclock_as_timestamps").arg("1")
+               .arg("-i").arg("-");
+            cmd.stdin(Stdio::piped());
         } else {
-            cmd.arg("-draw_mouse").arg("0");
+            let (origin_x, origin_y) = region.origin(monitor);
+            cmd.arg("-f").arg("x11grab")
+               .arg("-video_size").arg(format!("{}x{}", region.width, region.height))
+               .arg("-i").arg(format!(":0.0+{},{}", origin_x, origin_y));
+            
+            // Framerate
+            cmd.arg("-framerate").arg(config_guard.fps.to_string());
+            
+            // Mouse cursor capture (client-side cursors are left out of the video)
+            if config_guard.draws_cursor() {
+                cmd.arg("-draw_mouse").arg("1");
+            } else {
+                cmd.arg("-draw_mouse").arg("0");
+            }
         }
         
         // Hardware acceleration
@@ -199,13 +241,37 @@ impl X11ScreenCapturer {
         // Keyframe interval
         cmd.arg("-g").arg(config_guard.keyframe_interval.to_string());
         
+        // Drop unchanged frames down to the idle frame rate; the damage
+        // grabber already leaves them out
+        let mut filters = Vec::new();
+        let decimate = if damage_input { None } else { idle::decimate_filter(&config_guard) };
+        let variable_rate = damage_input || decimate.is_some();
+        
+        // Masks are drawn first, so changes under them do not count as activity
+        let (output_width, output_height) = config_guard.output_size(monitor, quality_controller_guard.output_scale_percent());
+        filters.extend(privacy::ffmpeg_mask_filters(&config_guard.privacy, &region, output_width));
+        filters.extend(decimate);
+        
+        // Scale to the output resolution after decimating, which compares raw frames
+        if (output_width, output_height) != (region.width, region.height) {
+            cmd.args(scale::ffmpeg_scale_device_args(&config_guard));
+            filters.push(scale::ffmpeg_scale_filter(&config_guard, output_width, output_height));
+        }
+        
+        if !filters.is_empty() {
+            cmd.arg("-vf").arg(filters.join(","));
+        }
+        if variable_rate {
+            cmd.arg("-vsync").arg("vfr");
+        }
+        
         // Output format for streaming - use matroska for container
         cmd.arg("-f").arg("matroska")
            .arg("-movflags").arg("faststart")
            .arg("-");
         
-        // Redirect stderr and make stdout available for reading
-        cmd.stderr(Stdio::null())
+        // Pipe stderr so crashes can be diagnosed; stdout carries the stream
+        cmd.stderr(Stdio::piped())
            .stdout(Stdio::piped());
         
         // Start the ffmpeg process
@@ -215,93 +281,171 @@ impl X11ScreenCapturer {
         Ok(process)
     }
     
-    /// X11 capture loop
+    /// Subscribes to X11 damage for the captured area, or None to grab full frames
+    fn open_damage_source(config: &Arc<Mutex<ScreenCaptureConfig>>, monitor: &MonitorInfo) -> Option<DamageSource> {
+        let config_guard = config.lock().unwrap();
+        
+        // The grabbed image has no cursor, x11grab draws it
+        if !config_guard.damage.enabled || config_guard.draws_cursor() {
+            return None;
+        }
+        
+        let region = config_guard.effective_region(monitor);
+        let (origin_x, origin_y) = region.origin(monitor);
+        match DamageSource::connect((origin_x, origin_y), region.width, region.height) {
+            Ok(source) => Some(source),
+            Err(e) => {
+                log::warn!(target: TARGET_SCREEN_CAPTURE, "Damage tracking unavailable, grabbing full frames: {}", e);
+                None
+            }
+        }
+    }
+    
+    /// X11 capture loop.
+    ///
+    /// Supervises the FFmpeg process: when it exits unexpectedly the process is
+    /// restarted with exponential backoff and frames keep flowing into the same
+    /// stream buffer. If stderr points at the hardware encoder, the restart
+    /// falls back to software encoding.
+    ///
+    /// With damage tracking, each process is fed by its own `DamageGrabber`,
+    /// which also tells the idle detector which frames carry a change.
     fn capture_loop(
         config: Arc<Mutex<ScreenCaptureConfig>>,
         running: Arc<Mutex<bool>>,
         stats: Arc<Mutex<CaptureStats>>,
+        keyframes: Arc<Mutex<KeyframeScheduler>>,
         monitor: MonitorInfo,
         stream_buffer: Arc<Mutex<StreamBuffer>>,
         quality_controller: Arc<Mutex<AdaptiveQualityController>>,
         capture_process: Arc<Mutex<Option<Child>>>,
+        event_sink: CaptureEventSink,
     ) {
         let mut frame_count: u64 = 0;
         let mut dropped_frames: u64 = 0;
+        let mut bytes_received: u64 = 0;
         let start_time = Instant::now();
         
-        // Start the FFmpeg process for continuous capture
-        let mut process = match Self::start_ffmpeg_process_static(&config, &monitor, &quality_controller) {
-            Ok(process) => process,
-            Err(e) => {
-                eprintln!("Failed to start FFmpeg process: {}", e);
-                return;
-            }
-        };
-        
-        // Store the process in shared variable
-        {
-            let mut process_guard = capture_process.lock().unwrap();
-            *process_guard = Some(process.try_clone().unwrap_or(process));
-        }
-        
-        // Get stdout for reading video data
-        let mut stdout = process.stdout.take().expect("Failed to take stdout from FFmpeg process");
+        // Per-frame error paths are rate limited so a broken pipe cannot flood the logs
+        let mut buffer_error_log = RateLimitedLog::new(HOT_PATH_LOG_INTERVAL);
+        let mut resync_log = RateLimitedLog::new(HOT_PATH_LOG_INTERVAL);
+        let mut read_error_log = RateLimitedLog::new(HOT_PATH_LOG_INTERVAL);
         
-        // Buffer for reading output
-        let mut buffer = Vec::new();
-        let mut read_buffer = vec![0u8; 65536]; // 64KB buffer for reading
+        let policy = RestartPolicy::new(config.lock().unwrap().max_restart_attempts);
+        let mut restart_attempt: u32 = 0;
+        let mut idle_detector = IdleDetector::new(&config.lock().unwrap());
         
-        // Main loop for capturing and processing frames
-        let mut last_stats_update = Instant::now();
+        // Sampled on its own thread, the loop only picks up the last reading
+        let gpu_monitor = GpuMonitor::start(Arc::new(SystemCommandRunner), &config.lock().unwrap().hardware_acceleration);
         
         while *running.lock().unwrap() {
-            let now = Instant::now();
-            
-            // Check if the process is still running
-            match process.try_wait() {
-                Ok(Some(status)) => {
-                    eprintln!("FFmpeg process exited with status: {}", status);
-                    break;
-                }
-                Ok(None) => {},
-                Err(e) => {
-                    eprintln!("Error checking FFmpeg process: {}", e);
-                    break;
-                }
-            }
+            // Start the FFmpeg process for continuous capture
+            let damage_source = Self::open_damage_source(&config, &monitor);
+            let spawn_result = Self::start_ffmpeg_process_static(&config, &monitor, &quality_controller, damage_source.is_some());
+            let process_started = Instant::now();
+            let mut keyframe_restart = false;
             
-            // Read data from the FFmpeg process
-            match stdout.read(&mut read_buffer) {
-                Ok(n) if n > 0 => {
-                    // Data was read, add it to the buffer
-                    buffer.extend_from_slice(&read_buffer[0..n]);
+            let (exit_reason, stderr, spawn_error) = match spawn_result {
+                Ok(mut process) => {
+                    let stderr = StderrCollector::spawn(process.stderr.take());
+                    keyframes.lock().unwrap().encoder_started(process_started);
+                    
+                    // Raw frames for FFmpeg, and per frame whether it carries a change
+                    let mut damage_grabber = None;
+                    let mut damage_changes = None;
+                    if let (Some(source), Some(stdin)) = (damage_source, process.stdin.take()) {
+                        let (changes_tx, changes_rx) = mpsc::channel();
+                        let config_guard = config.lock().unwrap();
+                        damage_grabber = Some(DamageGrabber::start(source, &config_guard, stdin, changes_tx, stats.clone()));
+                        damage_changes = Some(changes_rx);
+                    }
                     
-                    // For matroska/webm streams, we need to detect frame boundaries
-                    let mut frame_start_index = 0;
-                    for i in 0..buffer.len().saturating_sub(4) {
-                        // Look for likely keyframe marker (simple heuristic)
-                        if buffer[i] == 0x87 && buffer[i+1] == 0x00 {
-                            // Found a potential keyframe, treat everything before this as one frame
-                            if i > frame_start_index {
-                                // Extract the frame data
-                                let frame_data = buffer[frame_start_index..i].to_vec();
+                    // Store the process in shared variable
+                    {
+                        let mut process_guard = capture_process.lock().unwrap();
+                        *process_guard = Some(process.try_clone().unwrap_or(process));
+                    }
+                    
+                    // Get stdout for reading video data
+                    let mut stdout = process.stdout.take().expect("Failed to take stdout from FFmpeg process");
+                    
+                    // Every process starts a new matroska stream with its own header
+                    let mut demuxer = MatroskaDemuxer::new();
+                    let mut frame_clock = FrameClock::new();
+                    let mut read_buffer = vec![0u8; 65536]; // 64KB buffer for reading
+                    
+                    // Main loop for capturing and processing frames
+                    let mut last_stats_update = Instant::now();
+                    let mut exit_reason = None;
+                    let mut resumed_reported = restart_attempt == 0;
+                    
+                    while *running.lock().unwrap() {
+                        let now = Instant::now();
+                        
+                        // A new process starts its stream with a keyframe
+                        if keyframes.lock().unwrap().take_due(now) {
+                            log::info!(target: TARGET_SCREEN_CAPTURE, "Restarting capture process to force a keyframe");
+                            keyframe_restart = true;
+                            break;
+                        }
+                        
+                        // Check if the process is still running
+                        match process.try_wait() {
+                            Ok(Some(status)) => {
+                                log::warn!(target: TARGET_SCREEN_CAPTURE, "FFmpeg process exited with status: {}", status);
+                                exit_reason = Some(format!("FFmpeg exited with {}", status));
+                                break;
+                            }
+                            Ok(None) => {},
+                            Err(e) => {
+                                log::error!(target: TARGET_SCREEN_CAPTURE, "Error checking FFmpeg process: {}", e);
+                                exit_reason = Some(format!("Error checking FFmpeg process: {}", e));
+                                break;
+                            }
+                        }
+                        
+                        // Read data from the FFmpeg process
+                        match stdout.read(&mut read_buffer) {
+                            Ok(n) if n > 0 => {
+                                let read_at = Instant::now();
+                                if !resumed_reported {
+                                    resumed_reported = true;
+                                    event_sink(CaptureEvent::Resumed { attempt: restart_attempt });
+                                }
+                                
+                                bytes_received += n as u64;
                                 
-                                if !frame_data.is_empty() {
-                                    // Create frame data
-                                    let frame = FrameData {
-                                        data: frame_data,
-                                        timestamp: now.elapsed().as_millis() as u64,
-                                        keyframe: true,
-                                        width: monitor.width,
-                                        height: monitor.height,
-                                        format: "matroska".to_string(),
+                                // Demux the matroska stream into frames of the video track
+                                let resyncs_before = demuxer.resync_count();
+                                for frame in demuxer.push(&read_buffer[0..n]) {
+                                    let mut frame = match demuxer.to_frame_data(frame, monitor.width, monitor.height) {
+                                        Some(frame) => frame,
+                                        None => continue,
                                     };
+                                    frame_clock.stamp(&mut frame, read_at);
+                                    
+                                    // The first changed frame after an idle phase ends it
+                                    let activity = match &damage_changes {
+                                        Some(changes) => idle_detector.on_change_signal(frame.timestamp, changes.try_recv().unwrap_or(true)),
+                                        None => idle_detector.on_frame(frame.timestamp),
+                                    };
+                                    if let Some(activity) = activity {
+                                        log::debug!(target: TARGET_SCREEN_CAPTURE, "Capture activity changed to {:?}", activity);
+                                        stats.lock().unwrap().activity = activity;
+                                        event_sink(CaptureEvent::ActivityChanged { activity });
+                                    }
+                                    
+                                    if frame.keyframe {
+                                        keyframes.lock().unwrap().keyframe_emitted(now);
+                                    }
                                     
                                     // Add to buffer
                                     {
                                         let mut stream_buf = stream_buffer.lock().unwrap();
                                         if let Err(e) = stream_buf.push_frame(frame) {
-                                            eprintln!("Error adding frame to buffer: {}", e);
+                                            if let Some(suppressed) = buffer_error_log.check() {
+                                                log::warn!(target: TARGET_SCREEN_CAPTURE, "Error adding frame to buffer: {} ({} similar messages suppressed)", e, suppressed);
+                                            }
                                             dropped_frames += 1;
                                         }
                                     }
@@ -309,89 +453,136 @@ impl X11ScreenCapturer {
                                     frame_count += 1;
                                 }
                                 
-                                frame_start_index = i;
+                                // Corrupt data is skipped up to the next cluster by the demuxer
+                                if demuxer.resync_count() > resyncs_before {
+                                    if let Some(suppressed) = resync_log.check() {
+                                        log::warn!(target: TARGET_SCREEN_CAPTURE, "Invalid matroska data, resynchronized on next cluster ({} similar messages suppressed)", suppressed);
+                                    }
+                                }
+                                
+                                // Update stats periodically
+                                if now.duration_since(last_stats_update) > Duration::from_millis(500) {
+                                    last_stats_update = now;
+                                    
+                                    // Capture current statistics
+                                    let current_cpu_usage = utils::get_cpu_usage().unwrap_or(0.0);
+                                    let buffer_stats = stream_buffer.lock().unwrap().get_stats();
+                                    
+                                    // Calculate frame rate and bitrate
+                                    let elapsed_secs = start_time.elapsed().as_secs_f64();
+                                    let fps = if elapsed_secs > 0.0 { frame_count as f64 / elapsed_secs } else { 0.0 };
+                                    let bitrate = if elapsed_secs > 0.0 { 
+                                        (bytes_received as f64 * 8.0 / elapsed_secs) as u64
+                                    } else { 
+                                        0 
+                                    };
+                                    
+                                    // Update quality controller with new metrics. Paused while
+                                    // idle: the throttled rate says nothing about the network.
+                                    if idle_detector.quality_control_enabled() {
+                                        let mut quality_ctrl = quality_controller.lock().unwrap();
+                                        quality_ctrl.update_metrics(
+                                            current_cpu_usage,
+                                            (bitrate / 1000) as u32,
+                                            if frame_count > 0 { dropped_frames as f32 / frame_count as f32 } else { 0.0 },
+                                            buffer_stats.latency_ms as u32
+                                        );
+                                        
+                                        let scale_before = quality_ctrl.output_scale_percent();
+                                        let _ = quality_ctrl.adjust_quality();
+                                        
+                                        // A new output size takes a new encoder process
+                                        if quality_ctrl.output_scale_percent() != scale_before {
+                                            log::info!(target: TARGET_SCREEN_CAPTURE, "Output scale changed to {}%, restarting encoder", quality_ctrl.output_scale_percent());
+                                            keyframes.lock().unwrap().request(now);
+                                        }
+                                    }
+                                    
+                                    // Update capture statistics
+                                    {
+                                        let mut stats_guard = stats.lock().unwrap();
+                                        stats_guard.fps = fps;
+                                        stats_guard.bitrate = bitrate;
+                                        stats_guard.frame_count = frame_count;
+                                        stats_guard.dropped_frames = dropped_frames;
+                                        stats_guard.buffer_level = buffer_stats.frame_count;
+                                        stats_guard.latency_estimate = buffer_stats.latency_ms;
+                                        stats_guard.time_since_keyframe_ms = keyframes.lock().unwrap()
+                                            .since_last_keyframe(now)
+                                            .map(|elapsed| elapsed.as_millis() as u64);
+                                        stats_guard.gpu = gpu_monitor.as_ref().and_then(GpuMonitor::latest);
+                                    }
+                                }
+                            },
+                            Ok(_) => {
+                                // No data available, wait a bit
+                                thread::sleep(Duration::from_millis(1));
+                            },
+                            Err(e) => {
+                                if let Some(suppressed) = read_error_log.check() {
+                                    log::error!(target: TARGET_SCREEN_CAPTURE, "Error reading from FFmpeg: {} ({} similar messages suppressed)", e, suppressed);
+                                }
+                                dropped_frames += 1;
+                                
+                                // Update stats
+                                let mut stats_guard = stats.lock().unwrap();
+                                stats_guard.dropped_frames = dropped_frames;
+                                
+                                // Check if process is still alive
+                                if let Err(e) = process.try_wait() {
+                                    log::error!(target: TARGET_SCREEN_CAPTURE, "Error checking FFmpeg process: {}", e);
+                                    exit_reason = Some(format!("Error checking FFmpeg process: {}", e));
+                                    break;
+                                }
                             }
                         }
                     }
                     
-                    // Remove processed data from buffer, keeping potential partial frame
-                    if frame_start_index > 0 {
-                        buffer.drain(0..frame_start_index);
+                    // Clean up the process before deciding whether to restart
+                    if let Err(e) = process.kill() {
+                        log::error!(target: TARGET_SCREEN_CAPTURE, "Error killing FFmpeg process: {}", e);
                     }
+                    let _ = process.wait();
                     
-                    // If buffer is too big, reset it (something went wrong)
-                    if buffer.len() > 10 * 1024 * 1024 { // 10MB limit
-                        buffer.clear();
-                        eprintln!("Buffer overflow, clearing");
+                    // Its writes fail once FFmpeg is gone
+                    if let Some(mut grabber) = damage_grabber {
+                        grabber.stop();
                     }
                     
-                    // Update stats periodically
-                    if now.duration_since(last_stats_update) > Duration::from_millis(500) {
-                        last_stats_update = now;
-                        
-                        // Capture current statistics
-                        let current_cpu_usage = utils::get_cpu_usage().unwrap_or(0.0);
-                        let buffer_stats = stream_buffer.lock().unwrap().get_stats();
-                        
-                        // Calculate frame rate and bitrate
-                        let elapsed_secs = start_time.elapsed().as_secs_f64();
-                        let fps = if elapsed_secs > 0.0 { frame_count as f64 / elapsed_secs } else { 0.0 };
-                        let bitrate = if elapsed_secs > 0.0 { 
-                            (buffer.len() as f64 * 8.0 / elapsed_secs) as u64
-                        } else { 
-                            0 
-                        };
-                        
-                        // Update quality controller with new metrics
-                        {
-                            let mut quality_ctrl = quality_controller.lock().unwrap();
-                            quality_ctrl.update_metrics(
-                                current_cpu_usage,
-                                (bitrate / 1000) as u32,
-                                if frame_count > 0 { dropped_frames as f32 / frame_count as f32 } else { 0.0 },
-                                buffer_stats.latency_ms as u32
-                            );
-                            
-                            let _ = quality_ctrl.adjust_quality();
-                        }
-                        
-                        // Update capture statistics
-                        {
-                            let mut stats_guard = stats.lock().unwrap();
-                            stats_guard.fps = fps;
-                            stats_guard.bitrate = bitrate;
-                            stats_guard.frame_count = frame_count;
-                            stats_guard.dropped_frames = dropped_frames;
-                            stats_guard.buffer_level = buffer_stats.frame_count;
-                            stats_guard.latency_estimate = buffer_stats.latency_ms;
-                        }
-                    }
-                },
-                Ok(_) => {
-                    // No data available, wait a bit
-                    thread::sleep(Duration::from_millis(1));
+                    (exit_reason, stderr, None)
                 },
                 Err(e) => {
-                    eprintln!("Error reading from FFmpeg: {}", e);
-                    dropped_frames += 1;
-                    
-                    // Update stats
-                    let mut stats_guard = stats.lock().unwrap();
-                    stats_guard.dropped_frames = dropped_frames;
-                    
-                    // Check if process is still alive
-                    if let Err(e) = process.try_wait() {
-                        eprintln!("Error checking FFmpeg process: {}", e);
-                        break;
-                    }
+                    log::error!(target: TARGET_SCREEN_CAPTURE, "Failed to start FFmpeg process: {}", e);
+                    (Some(e.to_string()), StderrCollector::default(), Some(e))
                 }
+            };
+            
+            // Restarted on purpose, not a failure
+            if keyframe_restart && *running.lock().unwrap() {
+                continue;
+            }
+            
+            // Stopped on request
+            let reason = match exit_reason {
+                Some(reason) if *running.lock().unwrap() => reason,
+                _ => break,
+            };
+            
+            // A long healthy run refills the restart budget
+            if process_started.elapsed() >= STABLE_RUN_DURATION {
+                restart_attempt = 0;
+            }
+            
+            if !prepare_restart(&config, &stats, &event_sink, &policy, &mut restart_attempt, reason, &stderr, spawn_error) {
+                break;
+            }
+            
+            if !sleep_while_running(&running, policy.backoff(restart_attempt)) {
+                break;
             }
         }
         
-        // Clean up when the loop ends
-        if let Err(e) = process.kill() {
-            eprintln!("Error killing FFmpeg process: {}", e);
-        }
+        capture_process.lock().unwrap().take();
     }
 }
 
@@ -416,10 +607,12 @@ impl ScreenCapturer for X11ScreenCapturer {
         let config = self.config.clone();
         let running = self.running.clone();
         let stats = self.stats.clone();
+        let keyframes = self.keyframes.clone();
         let monitor = self.monitor.clone();
         let stream_buffer = self.stream_buffer.clone();
         let quality_controller = self.quality_controller.clone();
         let capture_process = self.capture_process.clone();
+        let event_sink = self.event_sink.clone();
 
         // Create the capture thread
         self.capture_thread = Some(thread::spawn(move || {
@@ -427,10 +620,12 @@ impl ScreenCapturer for X11ScreenCapturer {
                 config,
                 running,
                 stats,
+                keyframes,
                 monitor,
                 stream_buffer,
                 quality_controller,
-                capture_process
+                capture_process,
+                event_sink
             );
         }));
 
@@ -458,7 +653,7 @@ impl ScreenCapturer for X11ScreenCapturer {
             match handle.join() {
                 Ok(_) => {}
                 Err(e) => {
-                    eprintln!("Error joining capture thread: {:?}", e);
+                    log::error!(target: TARGET_SCREEN_CAPTURE, "Error joining capture thread: {:?}", e);
                 }
             }
         }
@@ -479,7 +674,7 @@ impl ScreenCapturer for X11ScreenCapturer {
 /// Get monitor information for X11
 pub fn get_x11_monitors() -> Result<Vec<MonitorInfo>, ScreenCaptureError> {
     // Use xrandr to get monitor information
-    let output = Command::new("xrandr")
+    let output = SystemCommandRunner.command("xrandr")
         .arg("--listmonitors")
         .output()
         .map_err(|e| {
@@ -542,6 +737,8 @@ pub fn get_x11_monitors() -> Result<Vec<MonitorInfo>, ScreenCaptureError> {
                             primary: line.contains("primary"),
                             x_offset,
                             y_offset,
+                            scale_factor: 1.0,
+                            rotation: MonitorRotation::Normal,
                         });
                     }
                 }
@@ -549,6 +746,23 @@ pub fn get_x11_monitors() -> Result<Vec<MonitorInfo>, ScreenCaptureError> {
         }
     }
     
+    // --listmonitors has neither rotation nor scaling
+    let rotations = SystemCommandRunner.command("xrandr")
+        .arg("--query")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| parse_xrandr_rotations(&String::from_utf8_lossy(&output.stdout)))
+        .unwrap_or_default();
+    let scale_factor = x11_scale_factor();
+    
+    for monitor in &mut monitors {
+        monitor.scale_factor = scale_factor;
+        if let Some(rotation) = rotations.get(&monitor.name) {
+            monitor.rotation = *rotation;
+        }
+    }
+    
     // If no monitors found, provide a default one
     if monitors.is_empty() {
         monitors.push(MonitorInfo {
@@ -560,8 +774,61 @@ pub fn get_x11_monitors() -> Result<Vec<MonitorInfo>, ScreenCaptureError> {
             primary: true,
             x_offset: 0,
             y_offset: 0,
+            scale_factor: 1.0,
+            rotation: MonitorRotation::Normal,
         });
     }
     
     Ok(monitors)
 }
+
+/// Rotation per output from `xrandr --query`, e.g.
+/// "HDMI-1 connected 1080x1920+1920+0 left (normal left inverted right x axis y axis) ..."
+fn parse_xrandr_rotations(output: &str) -> HashMap<String, MonitorRotation> {
+    let mut rotations = HashMap::new();
+    
+    for line in output.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 3 || parts[1] != "connected" {
+            continue;
+        }
+        
+        // The rotation follows the geometry and precedes the list of supported rotations
+        let rotation = parts.iter()
+            .skip(2)
+            .take_while(|part| !part.starts_with('('))
+            .skip_while(|part| !part.contains('+'))
+            .skip(1)
+            .find_map(|part| MonitorRotation::parse(part))
+            .unwrap_or(MonitorRotation::Normal);
+        
+        rotations.insert(parts[0].to_string(), rotation);
+    }
+    
+    rotations
+}
+
+/// X11 has one scale for all monitors: GDK_SCALE if set, otherwise Xft.dpi / 96
+fn x11_scale_factor() -> f64 {
+    if let Some(scale) = std::env::var("GDK_SCALE").ok().and_then(|v| v.parse::<f64>().ok()) {
+        if scale > 0.0 {
+            return scale;
+        }
+    }
+    
+    let dpi = SystemCommandRunner.command("xrdb")
+        .arg("-query")
+        .output()
+        .ok()
+        .and_then(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .find(|line| line.starts_with("Xft.dpi:"))
+                .and_then(|line| line["Xft.dpi:".len()..].trim().parse::<f64>().ok())
+        });
+    
+    match dpi {
+        Some(dpi) if dpi > 0.0 => dpi / 96.0,
+        _ => 1.0,
+    }
+}
@@ -0,0 +1,554 @@
+// src-tauri/src/settings.rs - Persistent application settings
+//
+// All user-facing configuration lives in one TOML file in the XDG config
+// directory. The file carries a schema version; older files are migrated on
+// load, unreadable ones are set aside and replaced by defaults.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::chat::types::ChatConfig;
+use crate::clipboard::types::ClipboardConfig;
+use crate::connection_security::ConnectionSecurityConfig;
+use crate::do_not_disturb::DndConfig;
+use crate::file_transfer::types::TransferConfig;
+use crate::ice::IceConfig;
+use crate::input_forwarding::types::InputForwardingConfig;
+use crate::metrics::MetricsConfig;
+use crate::notifications::NotificationConfig;
+use crate::screen_capture::config::ScreenCaptureConfig;
+use crate::sharing_indicator::IndicatorConfig;
+use crate::tool_locator::ToolPathConfig;
+
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+/// Current schema version, written to every saved file
+pub const SETTINGS_VERSION: u32 = 2;
+
+/// Everything that survives a restart
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub version: u32,
+    pub capture: ScreenCaptureConfig,
+    /// User-defined capture profiles by name, next to the built-in ones
+    pub capture_profiles: BTreeMap<String, ScreenCaptureConfig>,
+    pub input: InputForwardingConfig,
+    pub clipboard: ClipboardConfig,
+    pub transfer: TransferConfig,
+    pub chat: ChatConfig,
+    pub security: ConnectionSecurityConfig,
+    pub notifications: NotificationConfig,
+    /// Do-not-disturb on the host while the screen is shared
+    pub do_not_disturb: DndConfig,
+    /// Frame around the shared area of the host's screen
+    pub sharing_indicator: IndicatorConfig,
+    pub metrics: MetricsConfig,
+    /// STUN/TURN servers; TURN credentials are in the keyring
+    pub ice: IceConfig,
+    /// Paths of external tools such as ffmpeg for installs without them on PATH
+    pub tools: ToolPathConfig,
+    /// Start without input, clipboard sync and file transfer; applies at the next start
+    pub safe_mode: bool,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings {
+            version: SETTINGS_VERSION,
+            capture: ScreenCaptureConfig::default(),
+            capture_profiles: BTreeMap::new(),
+            input: InputForwardingConfig::default(),
+            clipboard: ClipboardConfig::default(),
+            transfer: TransferConfig::default(),
+            chat: ChatConfig::default(),
+            security: ConnectionSecurityConfig::default(),
+            notifications: NotificationConfig::default(),
+            do_not_disturb: DndConfig::default(),
+            sharing_indicator: IndicatorConfig::default(),
+            metrics: MetricsConfig::default(),
+            ice: IceConfig::default(),
+            tools: ToolPathConfig::default(),
+            safe_mode: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(io::Error),
+    /// The file is not valid TOML or does not match the schema
+    Parse(String),
+    /// A partial update does not produce valid settings
+    InvalidUpdate(String),
+    /// The file was written by a newer version of SmolDesk
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(e) => write!(f, "Settings I/O error: {}", e),
+            SettingsError::Parse(msg) => write!(f, "Invalid settings file: {}", msg),
+            SettingsError::InvalidUpdate(msg) => write!(f, "Invalid settings update: {}", msg),
+            SettingsError::UnsupportedVersion(version) => write!(
+                f, "Settings version {} is newer than the supported version {}", version, SETTINGS_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<io::Error> for SettingsError {
+    fn from(e: io::Error) -> Self {
+        SettingsError::Io(e)
+    }
+}
+
+/// Result of loading at startup
+pub struct LoadedSettings {
+    pub settings: AppSettings,
+    /// Why the stored file was not used; sent to the frontend as `settings_warning`
+    pub warning: Option<String>,
+}
+
+/// The settings file in the config directory
+pub struct SettingsStore {
+    path: PathBuf,
+}
+
+impl SettingsStore {
+    pub fn new(config_dir: &Path) -> Self {
+        SettingsStore { path: config_dir.join(SETTINGS_FILE) }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the stored settings; a missing file yields the defaults
+    pub fn load(&self) -> Result<AppSettings, SettingsError> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => parse(&content),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(AppSettings::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Like `load`, but falls back to the defaults if the file cannot be used.
+    /// An unusable file is renamed to `settings.toml.invalid` so the next save
+    /// does not destroy it.
+    pub fn load_or_default(&self) -> LoadedSettings {
+        match self.load() {
+            Ok(settings) => LoadedSettings { settings, warning: None },
+            Err(e) => {
+                let backup = self.path.with_extension("toml.invalid");
+                let warning = match fs::rename(&self.path, &backup) {
+                    Ok(()) => format!("{}; using defaults, the old file was moved to {}", e, backup.display()),
+                    Err(_) => format!("{}; using defaults", e),
+                };
+                log::warn!("{}", warning);
+                LoadedSettings { settings: AppSettings::default(), warning: Some(warning) }
+            },
+        }
+    }
+
+    /// Writes the settings atomically (temporary file + rename)
+    pub fn save(&self, settings: &AppSettings) -> Result<(), SettingsError> {
+        let mut settings = settings.clone();
+        settings.version = SETTINGS_VERSION;
+        let content = toml::to_string_pretty(&settings)
+            .map_err(|e| SettingsError::Parse(e.to_string()))?;
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temp_path = self.path.with_extension("toml.tmp");
+        {
+            let mut file = create_private_file(&temp_path)?;
+            file.write_all(content.as_bytes())?;
+            file.sync_all()?;
+        }
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Parses a settings file, migrating older schema versions
+pub fn parse(content: &str) -> Result<AppSettings, SettingsError> {
+    let mut table: toml::Table = toml::from_str(content)
+        .map_err(|e| SettingsError::Parse(e.to_string()))?;
+    migrate(&mut table)?;
+    table.try_into().map_err(|e: toml::de::Error| SettingsError::Parse(e.to_string()))
+}
+
+/// Brings a stored table up to `SETTINGS_VERSION`, one version at a time
+fn migrate(table: &mut toml::Table) -> Result<(), SettingsError> {
+    // Files written before versioning have no `version` key
+    let mut version = match table.get("version") {
+        None => 0,
+        Some(value) => value.as_integer()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| SettingsError::Parse("version must be a non-negative integer".to_string()))?,
+    };
+    if version > SETTINGS_VERSION {
+        return Err(SettingsError::UnsupportedVersion(version));
+    }
+
+    while version < SETTINGS_VERSION {
+        match version {
+            // Unversioned files already use the version 1 layout
+            0 => {},
+            // The input section became wire-facing and switched to camelCase keys
+            1 => {
+                if let Some(toml::Value::Table(input)) = table.get_mut("input") {
+                    camel_case_keys(input);
+                    input.insert(
+                        crate::protocol::VERSION_FIELD.to_string(),
+                        toml::Value::Integer(crate::protocol::PROTOCOL_VERSION.into()),
+                    );
+                    if let Some(toml::Value::Array(monitors)) = input.get_mut("monitors") {
+                        for monitor in monitors.iter_mut().filter_map(toml::Value::as_table_mut) {
+                            camel_case_keys(monitor);
+                        }
+                    }
+                }
+            },
+            _ => unreachable!("no migration from settings version {}", version),
+        }
+        version += 1;
+    }
+    table.insert("version".to_string(), toml::Value::Integer(SETTINGS_VERSION.into()));
+    Ok(())
+}
+
+/// Renames the snake_case keys of a table to camelCase, e.g. `x_offset` to `xOffset`
+fn camel_case_keys(table: &mut toml::Table) {
+    let snake_keys: Vec<String> = table.keys().filter(|key| key.contains('_')).cloned().collect();
+    for key in snake_keys {
+        let value = table.remove(&key).expect("key was just listed");
+        let mut parts = key.split('_');
+        let mut camel = parts.next().unwrap_or_default().to_string();
+        for part in parts {
+            let mut chars = part.chars();
+            if let Some(first) = chars.next() {
+                camel.extend(first.to_uppercase());
+                camel.push_str(chars.as_str());
+            }
+        }
+        table.insert(camel, value);
+    }
+}
+
+/// Applies a partial update. Sections are merged field by field; a field's
+/// value replaces the old one as a whole, so e.g. switching `rate_control`
+/// from CRF to VBR does not leave both variants behind.
+pub fn merge(settings: &AppSettings, patch: serde_json::Value) -> Result<AppSettings, SettingsError> {
+    let mut merged = serde_json::to_value(settings)
+        .map_err(|e| SettingsError::InvalidUpdate(e.to_string()))?;
+    let (Some(sections), serde_json::Value::Object(patch)) = (merged.as_object_mut(), patch) else {
+        return Err(SettingsError::InvalidUpdate("expected an object of settings sections".to_string()));
+    };
+
+    for (section, fields) in patch {
+        match (sections.get_mut(&section).and_then(|s| s.as_object_mut()), fields) {
+            (Some(current), serde_json::Value::Object(fields)) => current.extend(fields),
+            (_, value) => {
+                sections.insert(section, value);
+            },
+        }
+    }
+
+    let mut settings: AppSettings = serde_json::from_value(merged)
+        .map_err(|e| SettingsError::InvalidUpdate(e.to_string()))?;
+    settings.version = SETTINGS_VERSION;
+    Ok(settings)
+}
+
+pub(crate) fn create_private_file(path: &Path) -> io::Result<fs::File> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    // The security section holds password hashes
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use serde::de::DeserializeOwned;
+
+    use crate::connection_security::{AccessRight, ConnectionMode};
+    use crate::connection_security::identity::TrustedPeer;
+    use crate::connection_security::rate_limit::RateLimitConfig;
+    use crate::file_transfer::bandwidth::AutoBandwidthConfig;
+    use crate::input_forwarding::shortcuts::{ShortcutAction, ShortcutPolicy, ShortcutRule};
+    use crate::input_forwarding::system_actions::{SystemActionConfig, SystemActionPolicy};
+    use crate::input_forwarding::types::{MonitorConfiguration, SpecialCommand};
+    use crate::notifications::QuietHours;
+    use crate::screen_capture::config::{AdvancedEncodingOptions, PipeWireNodeSelection, RateControlMode};
+    use crate::screen_capture::types::{CaptureRegion, HardwareAcceleration, LatencyMode, VideoCodec};
+
+    /// Serializes to TOML and back; the JSON forms must be identical
+    fn assert_round_trip<T: Serialize + DeserializeOwned>(value: &T) {
+        let content = toml::to_string_pretty(value).unwrap();
+        let parsed: T = toml::from_str(&content).unwrap_or_else(|e| panic!("{}\n{}", e, content));
+        assert_eq!(serde_json::to_value(value).unwrap(), serde_json::to_value(&parsed).unwrap());
+    }
+
+    fn temp_store() -> SettingsStore {
+        let dir = std::env::temp_dir().join(format!("smoldesk-settings-{}", uuid::Uuid::new_v4()));
+        SettingsStore::new(&dir)
+    }
+
+    #[test]
+    fn test_capture_config_round_trip() {
+        assert_round_trip(&ScreenCaptureConfig::default());
+        assert_round_trip(&ScreenCaptureConfig {
+            codec: VideoCodec::VP9,
+            hardware_acceleration: HardwareAcceleration::VAAPI,
+            capture_region: Some(CaptureRegion { x: 10, y: 20, width: 800, height: 600 }),
+            bitrate: Some(4000),
+            latency_mode: LatencyMode::UltraLow,
+            advanced_options: Some(AdvancedEncodingOptions {
+                rate_control: RateControlMode::VBR { target_bitrate: 2000, max_bitrate: 4000 },
+                extra_params: vec![("g".to_string(), "60".to_string())],
+                ..Default::default()
+            }),
+            pipewire_node: Some(PipeWireNodeSelection { node_id: 42, name: "xdpw-stream-DP-1".to_string() }),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_input_config_round_trip() {
+        assert_round_trip(&InputForwardingConfig::default());
+        assert_round_trip(&InputForwardingConfig {
+            monitors: vec![MonitorConfiguration {
+                index: 0,
+                x_offset: -1920,
+                y_offset: 0,
+                width: 1920,
+                height: 1080,
+                scale_factor: 1.25,
+                rotation: Default::default(),
+                is_primary: true,
+            }],
+            remap_keys: HashMap::from([("CapsLock".to_string(), "Escape".to_string())]),
+            custom_commands: HashMap::from([("terminal".to_string(), "key ctrl+alt+t".to_string())]),
+            input_blocklist: vec!["*keepass*".to_string()],
+            stuck_key_timeout_ms: None,
+            shortcut_policy: ShortcutPolicy {
+                rules: vec![ShortcutRule {
+                    combo: "meta+Left".to_string(),
+                    action: ShortcutAction::TranslateTo("ctrl+alt+Left".to_string()),
+                }],
+            },
+            system_actions: SystemActionConfig {
+                default_policy: SystemActionPolicy::Deny,
+                disruptive_commands: vec![SpecialCommand::LockScreen, SpecialCommand::DesktopToggle],
+                disruptive_key_combinations: vec!["ctrl+alt+Delete".to_string()],
+                ask_timeout_secs: 10,
+            },
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_clipboard_config_round_trip() {
+        assert_round_trip(&ClipboardConfig::default());
+        assert_round_trip(&ClipboardConfig {
+            ignore_patterns: vec![r"^\d{16}$".to_string()],
+            persist_to_disk: true,
+            max_poll_interval_ms: 5000,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_transfer_config_round_trip() {
+        assert_round_trip(&TransferConfig::default());
+        assert_round_trip(&TransferConfig {
+            max_bandwidth_bytes_per_sec: Some(1_000_000),
+            auto_bandwidth: Some(AutoBandwidthConfig { link_capacity_bytes_per_sec: 12_500_000, headroom_percent: 20 }),
+            history_retention_days: None,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_notification_config_round_trip() {
+        assert_round_trip(&NotificationConfig::default());
+        assert_round_trip(&NotificationConfig {
+            clipboard_synced: true,
+            quiet_hours: Some(QuietHours { start: "22:00".to_string(), end: "07:00".to_string() }),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_tool_path_config_round_trip() {
+        assert_round_trip(&ToolPathConfig::default());
+        assert_round_trip(&ToolPathConfig {
+            ffmpeg_path: Some(PathBuf::from("/opt/ffmpeg/bin/ffmpeg")),
+            wl_clipboard_path: Some(PathBuf::from("/app/bin")),
+            other: BTreeMap::from([("xrandr".to_string(), PathBuf::from("/app/bin/xrandr"))]),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_chat_config_round_trip() {
+        assert_round_trip(&ChatConfig::default());
+        assert_round_trip(&ChatConfig { persist_history: true });
+    }
+
+    #[test]
+    fn test_metrics_config_round_trip() {
+        assert_round_trip(&MetricsConfig::default());
+        assert_round_trip(&MetricsConfig { enabled: true, bind_address: "0.0.0.0".to_string(), port: 9100 });
+    }
+
+    #[test]
+    fn test_security_config_round_trip() {
+        assert_round_trip(&ConnectionSecurityConfig::default());
+        assert_round_trip(&ConnectionSecurityConfig {
+            mode: ConnectionMode::Public,
+            allowed_users: Some(vec!["alice".to_string()]),
+            default_access_rights: vec![AccessRight::ViewOnly, AccessRight::ControlInput],
+            room_password_hash: Some("$argon2id$v=19$m=19456,t=2,p=1$c2FsdA$aGFzaA".to_string()),
+            allow_custom_commands: true,
+            rate_limits: RateLimitConfig { input_events_per_sec: 200, suspend_factor: 0, ..Default::default() },
+            trusted_peers: vec![TrustedPeer {
+                fingerprint: "SHA256:47DEQpj8HBSa+/TImW+5JCeuQeRkm5NMpJWZG3hSuFU".to_string(),
+                device_name: "laptop".to_string(),
+                nickname: Some("Büro".to_string()),
+                access_rights: vec![AccessRight::ControlInput],
+                trusted_at: 1_700_000_000,
+            }],
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_app_settings_round_trip() {
+        assert_round_trip(&AppSettings::default());
+
+        let mut settings = AppSettings::default();
+        settings.capture_profiles.insert("Slides (VP9)".to_string(), ScreenCaptureConfig {
+            codec: VideoCodec::VP9,
+            fps: 5,
+            advanced_options: Some(AdvancedEncodingOptions { rate_control: RateControlMode::CRF(40), ..Default::default() }),
+            ..Default::default()
+        });
+        assert_round_trip(&settings);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let store = temp_store();
+        assert_eq!(store.load().unwrap().capture.fps, 30);
+
+        let mut settings = AppSettings::default();
+        settings.capture.fps = 60;
+        settings.transfer.max_concurrent_transfers = 4;
+        store.save(&settings).unwrap();
+
+        let loaded = store.load().unwrap();
+        assert_eq!(loaded.capture.fps, 60);
+        assert_eq!(loaded.transfer.max_concurrent_transfers, 4);
+        assert!(!store.path().with_extension("toml.tmp").exists());
+
+        fs::remove_dir_all(store.path().parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_unversioned_and_partial_files_are_migrated() {
+        let settings = parse("[capture]\nmonitor_index = 0\nfps = 15\nquality = 70\ncodec = \"VP8\"\n\
+            hardware_acceleration = \"None\"\ncapture_cursor = false\ncapture_audio = false\n\
+            keyframe_interval = 15\nlatency_mode = \"Balanced\"\n").unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.capture.fps, 15);
+        // Sections missing from the file keep their defaults
+        assert_eq!(settings.clipboard.max_history_size, ClipboardConfig::default().max_history_size);
+    }
+
+    #[test]
+    fn test_version_1_input_keys_are_migrated() {
+        let settings = parse("version = 1\n[input]\nenable_touch_gestures = false\nenable_special_commands = true\n\
+            enable_multi_monitor = true\nkeyboard_layout = \"de\"\nremap_keys = {}\ncustom_commands = {}\n\
+            input_blocklist = [\"*keepass*\"]\n\
+            [[input.monitors]]\nindex = 0\nx_offset = -1920\ny_offset = 0\nwidth = 1920\nheight = 1080\n\
+            scale_factor = 1.0\nis_primary = true\n").unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert!(!settings.input.enable_touch_gestures);
+        assert_eq!(settings.input.keyboard_layout, "de");
+        assert_eq!(settings.input.input_blocklist, vec!["*keepass*"]);
+        assert_eq!(settings.input.monitors[0].x_offset, -1920);
+        assert!(settings.input.monitors[0].is_primary);
+        assert_eq!(settings.input.protocol_version, crate::protocol::PROTOCOL_VERSION);
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() {
+        let content = format!("version = {}\n", SETTINGS_VERSION + 1);
+        assert!(matches!(parse(&content), Err(SettingsError::UnsupportedVersion(_))));
+        assert!(matches!(parse("version = -1\n"), Err(SettingsError::Parse(_))));
+    }
+
+    #[test]
+    fn test_invalid_file_falls_back_to_defaults() {
+        let store = temp_store();
+        fs::create_dir_all(store.path().parent().unwrap()).unwrap();
+        fs::write(store.path(), "[capture]\nfps = \"fast\"\n").unwrap();
+
+        let loaded = store.load_or_default();
+        assert!(loaded.warning.unwrap().contains("Invalid settings file"));
+        assert_eq!(loaded.settings.capture.fps, 30);
+        assert!(!store.path().exists());
+        assert!(store.path().with_extension("toml.invalid").exists());
+
+        fs::remove_dir_all(store.path().parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_partial_update_merges() {
+        let mut settings = AppSettings::default();
+        settings.input.input_blocklist = vec!["KeePassXC".to_string()];
+
+        let merged = merge(&settings, serde_json::json!({
+            "capture": { "fps": 60, "bitrate": 8000 },
+            "input": { "stuckKeyTimeoutMs": null },
+        })).unwrap();
+        assert_eq!(merged.capture.fps, 60);
+        assert_eq!(merged.capture.bitrate, Some(8000));
+        assert_eq!(merged.capture.quality, settings.capture.quality);
+        assert_eq!(merged.input.stuck_key_timeout_ms, None);
+        assert_eq!(merged.input.input_blocklist, vec!["KeePassXC"]);
+
+        // Nested values are replaced as a whole
+        let merged = merge(&merged, serde_json::json!({
+            "capture": { "advanced_options": { "pixel_format": "yuv420p", "rate_control": { "CBR": 3000 }, "extra_params": [] } },
+        })).unwrap();
+        assert!(matches!(merged.capture.advanced_options.unwrap().rate_control, RateControlMode::CBR(3000)));
+
+        assert!(merge(&settings, serde_json::json!([])).is_err());
+        assert!(matches!(
+            merge(&settings, serde_json::json!({ "capture": { "fps": "fast" } })),
+            Err(SettingsError::InvalidUpdate(_))
+        ));
+    }
+}
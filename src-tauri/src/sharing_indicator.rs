@@ -0,0 +1,335 @@
+// src-tauri/src/sharing_indicator.rs - Frame around the shared area of the host's screen
+//
+// Whoever sits at the host must be able to tell that the screen is shared.
+// While a capture runs, four thin always-on-top windows frame the captured
+// area and the top one shows how many viewers are connected, and whether they
+// can only watch because the app runs in safe mode. x11grab cannot leave
+// windows out, so under X11 the frame lies just outside the captured area
+// where there is room. Where the area reaches the edge of the monitor the
+// frame overlaps it and shows up in the stream, unless `narrow_capture` is
+// set: then the capture is narrowed by the frame instead.
+// Wayland compositors give clients no say over window positions and the
+// portal stream would show the frame, so there it is left to the
+// compositor's own screencast indicator.
+//
+// The windows live in this process and end with it; the watcher reconciles
+// them with the running captures, e.g. after an encoder crashed.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, WindowBuilder, WindowUrl};
+
+use crate::logging::TARGET_SCREEN_CAPTURE;
+use crate::screen_capture::types::{CaptureRegion, MonitorInfo};
+
+/// Labels of all indicator windows start with this
+pub const WINDOW_LABEL_PREFIX: &str = "sharing-indicator";
+
+/// Height of the top edge, which carries the viewer count
+pub const BADGE_HEIGHT: u32 = 20;
+
+/// Thickest frame the settings accept
+pub const MAX_THICKNESS: u32 = 32;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndicatorConfig {
+    pub enabled: bool,
+    /// CSS color of the frame
+    pub color: String,
+    /// Width of the left, right and bottom edge in pixels
+    pub thickness: u32,
+    /// Under X11, keep the frame out of the stream by narrowing captures that
+    /// reach the monitor's edge. Off, the frame overlaps such captures and is
+    /// visible to viewers.
+    pub narrow_capture: bool,
+}
+
+impl Default for IndicatorConfig {
+    fn default() -> Self {
+        IndicatorConfig {
+            enabled: true,
+            color: "#e01b24".to_string(),
+            thickness: 4,
+            narrow_capture: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Edge {
+    fn name(self) -> &'static str {
+        match self {
+            Edge::Top => "top",
+            Edge::Bottom => "bottom",
+            Edge::Left => "left",
+            Edge::Right => "right",
+        }
+    }
+}
+
+/// One edge of the frame in desktop coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Strip {
+    pub edge: Edge,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The area to capture so the frame fits around it on the monitor. Sides
+/// that reach the monitor's edge are moved inwards by the frame. None if
+/// the region is too small to keep anything.
+pub fn inset_region(monitor: &MonitorInfo, region: &CaptureRegion, config: &IndicatorConfig) -> Option<CaptureRegion> {
+    let (side, top_height) = edge_sizes(config);
+    let mut left = region.x;
+    let mut top = region.y;
+    let mut right = region.x + region.width;
+    let mut bottom = region.y + region.height;
+
+    if left < side {
+        left = side;
+    }
+    if monitor.width.saturating_sub(right) < side {
+        right = monitor.width.saturating_sub(side);
+    }
+    if top < top_height {
+        top = top_height;
+    }
+    if monitor.height.saturating_sub(bottom) < side {
+        bottom = monitor.height.saturating_sub(side);
+    }
+
+    if right < left + 2 || bottom < top + 2 {
+        return None;
+    }
+    Some(CaptureRegion { x: left, y: top, width: right - left, height: bottom - top }.even())
+}
+
+/// The frame around `capture`. Edges without room outside the capture are
+/// kept on the monitor and overlap it, e.g. when the frame was switched on
+/// during a capture.
+pub fn frame_strips(monitor: &MonitorInfo, capture: &CaptureRegion, config: &IndicatorConfig) -> Vec<Strip> {
+    let (side, top_height) = edge_sizes(config);
+    let left = capture.x.saturating_sub(side);
+    let top = capture.y.saturating_sub(top_height);
+    let right = (capture.x + capture.width + side).min(monitor.width);
+    let bottom = (capture.y + capture.height + side).min(monitor.height);
+    let inner_top = top + top_height;
+    let inner_bottom = bottom.saturating_sub(side).max(inner_top);
+
+    let strip = |edge, x: u32, y: u32, width: u32, height: u32| Strip {
+        edge,
+        x: monitor.x_offset + x as i32,
+        y: monitor.y_offset + y as i32,
+        width,
+        height,
+    };
+    vec![
+        strip(Edge::Top, left, top, right - left, top_height),
+        strip(Edge::Bottom, left, inner_bottom, right - left, side),
+        strip(Edge::Left, left, inner_top, side, inner_bottom - inner_top),
+        strip(Edge::Right, right.saturating_sub(side), inner_top, side, inner_bottom - inner_top),
+    ]
+}
+
+fn edge_sizes(config: &IndicatorConfig) -> (u32, u32) {
+    let side = config.thickness.clamp(1, MAX_THICKNESS);
+    (side, BADGE_HEIGHT.max(side))
+}
+
+/// The indicator windows and the viewers they count
+pub struct SharingIndicator {
+    config: IndicatorConfig,
+    /// Connected peers, as counted by the peer registry
+    viewers: usize,
+    /// Frames on screen by monitor index, and the settings they were drawn with
+    shown: BTreeMap<usize, Vec<Strip>>,
+    shown_config: IndicatorConfig,
+    shown_viewers: usize,
+    /// Viewers can only watch; fixed for the run like safe mode itself
+    view_only: bool,
+}
+
+impl SharingIndicator {
+    pub fn new(config: IndicatorConfig, view_only: bool) -> Self {
+        SharingIndicator {
+            shown_config: config.clone(),
+            config,
+            viewers: 0,
+            shown: BTreeMap::new(),
+            shown_viewers: 0,
+            view_only,
+        }
+    }
+
+    pub fn config(&self) -> &IndicatorConfig {
+        &self.config
+    }
+
+    /// Takes effect with the next `sync`
+    pub fn set_config(&mut self, config: IndicatorConfig) {
+        self.config = config;
+    }
+
+    /// Takes effect with the next `sync`; true if the count changed
+    pub fn set_viewers(&mut self, viewers: usize) -> bool {
+        let changed = viewers != self.viewers;
+        self.viewers = viewers;
+        changed
+    }
+
+    pub fn sync(&mut self, app_handle: &AppHandle, frames: BTreeMap<usize, Vec<Strip>>) {
+        let frames = if self.config.enabled { frames } else { BTreeMap::new() };
+        let restyled = self.shown_config != self.config;
+
+        let stale: Vec<usize> = self.shown.iter()
+            .filter(|(index, strips)| restyled || frames.get(index) != Some(strips))
+            .map(|(index, _)| *index)
+            .collect();
+        for index in stale {
+            for strip in self.shown.remove(&index).unwrap_or_default() {
+                if let Some(window) = app_handle.get_window(&window_label(index, strip.edge)) {
+                    if let Err(e) = window.close() {
+                        log::warn!(target: TARGET_SCREEN_CAPTURE, "Failed to close sharing indicator: {}", e);
+                    }
+                }
+            }
+        }
+        self.shown_config = self.config.clone();
+
+        let viewers = self.viewers;
+        for (index, strips) in frames {
+            if self.shown.contains_key(&index) {
+                continue;
+            }
+            for strip in &strips {
+                if let Err(e) = self.open_window(app_handle, index, strip, viewers) {
+                    log::error!(target: TARGET_SCREEN_CAPTURE, "Failed to show sharing indicator on monitor {}: {}", index, e);
+                }
+            }
+            self.shown.insert(index, strips);
+        }
+
+        // New windows start with the current count
+        if viewers != self.shown_viewers {
+            for index in self.shown.keys() {
+                if let Some(window) = app_handle.get_window(&window_label(*index, Edge::Top)) {
+                    let _ = window.eval(&format!("window.setViewers({})", viewers));
+                }
+            }
+            self.shown_viewers = viewers;
+        }
+    }
+
+    fn open_window(&self, app_handle: &AppHandle, index: usize, strip: &Strip, viewers: usize) -> tauri::Result<()> {
+        let settings = serde_json::json!({
+            "color": self.config.color,
+            "badge": strip.edge == Edge::Top,
+            "viewers": viewers,
+            "viewOnly": self.view_only,
+        });
+        let window = WindowBuilder::new(app_handle, window_label(index, strip.edge), WindowUrl::App("indicator.html".into()))
+            .title("SmolDesk screen sharing")
+            .initialization_script(&format!("window.__SMOLDESK_INDICATOR__ = {};", settings))
+            .decorations(false)
+            .always_on_top(true)
+            .skip_taskbar(true)
+            .resizable(false)
+            .focused(false)
+            .visible(false)
+            .build()?;
+
+        // Physical pixels, as the monitor layout is
+        window.set_size(PhysicalSize::new(strip.width, strip.height))?;
+        window.set_position(PhysicalPosition::new(strip.x, strip.y))?;
+        window.set_ignore_cursor_events(true)?;
+        window.show()
+    }
+
+    /// Closes indicator windows this instance does not know about, e.g. when
+    /// the state was reset on startup
+    pub fn close_orphans(&self, app_handle: &AppHandle) {
+        let known: BTreeSet<String> = self.shown.iter()
+            .flat_map(|(index, strips)| strips.iter().map(|strip| window_label(*index, strip.edge)))
+            .collect();
+        for (label, window) in app_handle.windows() {
+            if label.starts_with(WINDOW_LABEL_PREFIX) && !known.contains(&label) {
+                log::info!(target: TARGET_SCREEN_CAPTURE, "Closing orphaned sharing indicator {}", label);
+                let _ = window.close();
+            }
+        }
+    }
+}
+
+fn window_label(index: usize, edge: Edge) -> String {
+    format!("{}-{}-{}", WINDOW_LABEL_PREFIX, index, edge.name())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::monitor;
+
+    #[test]
+    fn test_full_monitor_is_narrowed_on_request() {
+        let monitor = monitor();
+        let config = IndicatorConfig { narrow_capture: true, ..Default::default() };
+
+        let capture = inset_region(&monitor, &CaptureRegion::full(&monitor), &config).unwrap();
+        assert_eq!(capture, CaptureRegion { x: 4, y: 20, width: 1912, height: 1056 });
+
+        // The frame covers exactly the pixels left out of the capture
+        assert_eq!(frame_strips(&monitor, &capture, &config), vec![
+            Strip { edge: Edge::Top, x: 2560, y: 0, width: 1920, height: 20 },
+            Strip { edge: Edge::Bottom, x: 2560, y: 1076, width: 1920, height: 4 },
+            Strip { edge: Edge::Left, x: 2560, y: 20, width: 4, height: 1056 },
+            Strip { edge: Edge::Right, x: 4476, y: 20, width: 4, height: 1056 },
+        ]);
+    }
+
+    #[test]
+    fn test_full_monitor_is_overlapped_by_default() {
+        let monitor = monitor();
+        let config = IndicatorConfig::default();
+        assert!(!config.narrow_capture);
+
+        let strips = frame_strips(&monitor, &CaptureRegion::full(&monitor), &config);
+        assert_eq!(strips[0], Strip { edge: Edge::Top, x: 2560, y: 0, width: 1920, height: 20 });
+        assert_eq!(strips[3], Strip { edge: Edge::Right, x: 4476, y: 20, width: 4, height: 1056 });
+    }
+
+    #[test]
+    fn test_region_with_room_keeps_its_size() {
+        let monitor = monitor();
+        let config = IndicatorConfig::default();
+        let region = CaptureRegion { x: 100, y: 100, width: 1280, height: 720 };
+
+        let capture = inset_region(&monitor, &region, &config).unwrap();
+        assert_eq!(capture, region);
+        assert_eq!(frame_strips(&monitor, &capture, &config)[0], Strip { edge: Edge::Top, x: 2656, y: 80, width: 1288, height: 20 });
+
+        // Too small to keep anything once the frame is taken off
+        let tiny = CaptureRegion { x: 0, y: 0, width: 6, height: 6 };
+        assert_eq!(inset_region(&monitor, &tiny, &config), None);
+    }
+
+    #[test]
+    fn test_viewer_count_reports_changes() {
+        let mut indicator = SharingIndicator::new(IndicatorConfig::default(), false);
+
+        assert!(!indicator.set_viewers(0));
+        assert!(indicator.set_viewers(2));
+        assert!(!indicator.set_viewers(2));
+        assert!(indicator.set_viewers(0));
+    }
+}
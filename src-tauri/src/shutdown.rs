@@ -0,0 +1,236 @@
+// src-tauri/src/shutdown.rs - Orderly shutdown when the app quits
+//
+// Quitting used to tear down only what was cheap to stop: encoders could
+// outlive the app, a ydotool sequence could leave keys pressed and running
+// transfers vanished without a trace. The shutdown now runs as a sequence of
+// named steps in a fixed order on a worker thread. The caller waits for the
+// sequence at most `SHUTDOWN_DEADLINE`; a step that hangs, e.g. on an
+// encoder that does not die, is abandoned together with the steps after it.
+// Either way every child process that is still around afterwards gets
+// SIGKILL, so nothing the app started survives it.
+
+use std::collections::HashMap;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+
+/// Longest the app waits for its shutdown steps before it exits anyway
+pub const SHUTDOWN_DEADLINE: Duration = Duration::from_secs(5);
+
+/// Helper processes of the webview (names as the kernel truncates them);
+/// the toolkit ends them itself when the window goes away
+const WEBVIEW_PROCESSES: [&str; 3] = ["WebKitWebProces", "WebKitNetworkPr", "WebKitGPUProces"];
+
+type Step = Box<dyn FnOnce() + Send>;
+
+/// Steps run in the order they were added
+#[derive(Default)]
+pub struct ShutdownSequence {
+    steps: Vec<(&'static str, Step)>,
+}
+
+/// What became of a shutdown sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Steps that ran to the end, in order
+    pub completed: Vec<&'static str>,
+
+    /// Step still running at the deadline; it and all later steps were abandoned
+    pub timed_out: Option<&'static str>,
+
+    pub elapsed: Duration,
+}
+
+impl ShutdownSequence {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn step(mut self, name: &'static str, run: impl FnOnce() + Send + 'static) -> Self {
+        self.steps.push((name, Box::new(run)));
+        self
+    }
+
+    /// Runs the steps and returns once all of them finished or `deadline`
+    /// passed. A panicking step is logged and the next one runs.
+    pub fn run(self, deadline: Duration) -> ShutdownReport {
+        let started = Instant::now();
+        let names: Vec<&'static str> = self.steps.iter().map(|(name, _)| *name).collect();
+
+        let (finished, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for (name, run) in self.steps {
+                if panic::catch_unwind(AssertUnwindSafe(run)).is_err() {
+                    log::error!("Shutdown step {} panicked", name);
+                }
+                if finished.send(name).is_err() {
+                    return;
+                }
+            }
+        });
+
+        let mut completed = Vec::new();
+        let timed_out = loop {
+            let remaining = deadline.saturating_sub(started.elapsed());
+            match receiver.recv_timeout(remaining) {
+                Ok(name) => completed.push(name),
+                Err(RecvTimeoutError::Disconnected) => break None,
+                Err(RecvTimeoutError::Timeout) => break names.get(completed.len()).copied(),
+            }
+        };
+
+        ShutdownReport { completed, timed_out, elapsed: started.elapsed() }
+    }
+}
+
+/// A process started by the app, directly or through one of its children
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChildProcess {
+    pub pid: u32,
+    pub name: String,
+}
+
+/// Living descendants of process `pid`, parents before their children.
+/// Zombies are left out, they are gone already; so are the webview's helpers.
+pub fn child_processes(pid: u32) -> Vec<ChildProcess> {
+    let mut children: HashMap<u32, Vec<ChildProcess>> = HashMap::new();
+    let entries = match fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::warn!("Cannot list processes: {}", e);
+            return Vec::new();
+        },
+    };
+    for entry in entries.flatten() {
+        let Some(child) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else { continue };
+        // Processes may end while the list is read
+        let Ok(stat) = fs::read_to_string(entry.path().join("stat")) else { continue };
+        if let Some((name, state, parent)) = parse_stat(&stat) {
+            if state != 'Z' && !WEBVIEW_PROCESSES.contains(&name.as_str()) {
+                children.entry(parent).or_default().push(ChildProcess { pid: child, name });
+            }
+        }
+    }
+
+    let mut descendants = Vec::new();
+    let mut parents = vec![pid];
+    while let Some(parent) = parents.pop() {
+        for child in children.remove(&parent).unwrap_or_default() {
+            parents.push(child.pid);
+            descendants.push(child);
+        }
+    }
+    descendants
+}
+
+/// Name, state and parent pid from /proc/<pid>/stat. The name is in
+/// parentheses and may itself contain spaces and parentheses.
+fn parse_stat(stat: &str) -> Option<(String, char, u32)> {
+    let name = &stat[stat.find('(')? + 1..stat.rfind(')')?];
+    let mut fields = stat[stat.rfind(')')? + 1..].split_whitespace();
+    let state = fields.next()?.chars().next()?;
+    let parent = fields.next()?.parse().ok()?;
+    Some((name.to_string(), state, parent))
+}
+
+/// Sends SIGKILL to `processes`; returns those that received it
+pub fn kill_processes(processes: Vec<ChildProcess>) -> Vec<ChildProcess> {
+    processes.into_iter()
+        .filter(|process| match kill(Pid::from_raw(process.pid as i32), Signal::SIGKILL) {
+            Ok(()) => true,
+            Err(e) => {
+                log::debug!("Could not kill {} ({}): {}", process.name, process.pid, e);
+                false
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use crate::screen_capture::backend::{EncoderBackend, ProcessBackend};
+    use crate::test_env::{fixture, TestEnv};
+
+    /// Fake tools started from `env`; other tests run their own at the same time
+    fn fakes_of(env: &TestEnv) -> Vec<ChildProcess> {
+        let marker = format!("SMOLDESK_MOCK_DIR={}", env.dir().display());
+        child_processes(std::process::id()).into_iter()
+            .filter(|process| {
+                fs::read(format!("/proc/{}/environ", process.pid))
+                    .map(|environ| environ.split(|byte| *byte == 0).any(|var| var == marker.as_bytes()))
+                    .unwrap_or(false)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_normal_quit_leaves_no_encoder_behind() {
+        let env = TestEnv::new();
+        env.set_stdout("ffmpeg", fixture("pipewire-capture.mkv"));
+        env.keep_running("ffmpeg", Duration::from_secs(30));
+
+        let mut encoder = ProcessBackend::ffmpeg(env.runner().command("ffmpeg"));
+        encoder.build().unwrap();
+        let _stream = encoder.start().unwrap();
+        assert_eq!(fakes_of(&env).len(), 1);
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let (first, second) = (order.clone(), order.clone());
+        let report = ShutdownSequence::new()
+            .step("capture", move || {
+                encoder.stop();
+                first.lock().unwrap().push("capture");
+            })
+            .step("settings", move || second.lock().unwrap().push("settings"))
+            .run(SHUTDOWN_DEADLINE);
+
+        assert_eq!(report.completed, vec!["capture", "settings"]);
+        assert_eq!(report.timed_out, None);
+        assert_eq!(*order.lock().unwrap(), vec!["capture", "settings"]);
+        assert!(fakes_of(&env).is_empty(), "encoder survived the quit");
+    }
+
+    #[test]
+    fn test_stuck_step_is_abandoned_and_children_killed() {
+        let env = TestEnv::new();
+        env.keep_running("ffmpeg", Duration::from_secs(30));
+        let mut orphan = env.runner().command("ffmpeg").spawn().unwrap();
+
+        let report = ShutdownSequence::new()
+            .step("input", || {})
+            .step("transfers", || thread::sleep(Duration::from_secs(30)))
+            .step("clipboard", || panic!("never reached"))
+            .run(Duration::from_millis(200));
+        assert_eq!(report.completed, vec!["input"]);
+        assert_eq!(report.timed_out, Some("transfers"));
+        assert!(report.elapsed < Duration::from_secs(2));
+
+        let killed = kill_processes(fakes_of(&env));
+        assert_eq!(killed.iter().map(|process| process.pid).collect::<Vec<_>>(), vec![orphan.id()]);
+        assert!(!orphan.wait().unwrap().success());
+        assert!(fakes_of(&env).is_empty());
+    }
+
+    #[test]
+    fn test_panicking_step_does_not_stop_the_sequence() {
+        let report = ShutdownSequence::new()
+            .step("capture", || panic!("encoder lock poisoned"))
+            .step("settings", || {})
+            .run(SHUTDOWN_DEADLINE);
+        assert_eq!(report.completed, vec!["capture", "settings"]);
+    }
+
+    #[test]
+    fn test_parse_stat() {
+        assert_eq!(parse_stat("4242 (ffmpeg) S 4200 4242 4200 0"), Some(("ffmpeg".to_string(), 'S', 4200)));
+        assert_eq!(parse_stat("17 (a (b) c) Z 1 17"), Some(("a (b) c".to_string(), 'Z', 1)));
+        assert_eq!(parse_stat("garbage"), None);
+    }
+}
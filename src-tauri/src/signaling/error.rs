@@ -0,0 +1,33 @@
+// signaling/error.rs - Error types for the signaling client
+
+use std::error::Error;
+use std::fmt;
+
+/// Error types for the signaling client
+#[derive(Debug)]
+pub enum SignalingError {
+    /// The server URL is not a ws:// or wss:// URL
+    InvalidUrl(String),
+
+    /// No connection to the signaling server is established
+    NotConnected,
+
+    /// The message needs a room, but the client has not joined one
+    NotInRoom,
+
+    /// The connection task is gone
+    ChannelClosed,
+}
+
+impl fmt::Display for SignalingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignalingError::InvalidUrl(url) => write!(f, "Invalid signaling server URL: {}", url),
+            SignalingError::NotConnected => write!(f, "Not connected to the signaling server"),
+            SignalingError::NotInRoom => write!(f, "Not in a signaling room"),
+            SignalingError::ChannelClosed => write!(f, "Signaling connection task has stopped"),
+        }
+    }
+}
+
+impl Error for SignalingError {}
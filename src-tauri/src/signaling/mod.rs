@@ -0,0 +1,514 @@
+// signaling/mod.rs - Client for the SmolDesk signaling server
+//
+// Connects the backend to the signaling server so connections can be set up
+// without the frontend (headless host mode). The WebRTC offer/answer/ICE
+// payloads are passed through as JSON; peer lifecycle is reported to
+// subscribers, e.g. the frontend and the audit log.
+
+pub mod error;
+pub mod protocol;
+pub mod session;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::{Sink, SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, watch};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+use crate::ice::IceServer;
+use crate::logging::TARGET_SIGNALING;
+
+pub use error::SignalingError;
+pub use protocol::{ClientMessage, ServerMessage};
+pub use session::{SignalingEvent, SignalingSession, SignalingState};
+
+/// Keepalive interval; the server drops clients that are silent for 2 minutes
+const PING_INTERVAL: Duration = Duration::from_secs(25);
+
+/// First and maximum delay between reconnection attempts
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Error the server sends when `create-room` names an existing room
+const ROOM_EXISTS_ERROR: &str = "Room already exists";
+
+/// Receiver for signaling events
+pub type SignalingEventSink = Arc<dyn Fn(&SignalingEvent) + Send + Sync>;
+
+/// Issues a fresh access token for the given local peer ID. Access tokens
+/// are single-use, so every (re)connection asks for a new one.
+pub type TokenProvider = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// Room to create or join after connecting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RoomRequest {
+    /// Host a room; the server picks an ID if none is given
+    Create {
+        #[serde(default)]
+        room_id: Option<String>,
+        #[serde(default)]
+        settings: Option<Value>,
+    },
+    /// Join an existing room
+    Join {
+        room_id: String,
+    },
+}
+
+fn default_max_reconnect_attempts() -> u32 {
+    10
+}
+
+/// Signaling client configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalingConfig {
+    /// ws:// or wss:// URL of the signaling server
+    pub server_url: String,
+
+    /// Room to enter on every (re)connection
+    #[serde(default)]
+    pub room: Option<RoomRequest>,
+
+    /// Access token; ignored when a token provider is set
+    #[serde(default)]
+    pub access_token: Option<String>,
+
+    /// Reconnection attempts before giving up (0 = unlimited)
+    #[serde(default = "default_max_reconnect_attempts")]
+    pub max_reconnect_attempts: u32,
+
+    /// STUN/TURN servers for the peer connections; empty means the stored ones
+    #[serde(default)]
+    pub ice_servers: Vec<IceServer>,
+}
+
+/// Snapshot of the client for the frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct SignalingStatus {
+    pub state: SignalingState,
+    pub client_id: Option<String>,
+    pub room_id: Option<String>,
+    pub peers: Vec<String>,
+    /// STUN/TURN servers the peer connections of this session use
+    pub ice_servers: Vec<IceServer>,
+}
+
+/// Delay before reconnection attempt `attempt` (starting at 1)
+pub fn reconnect_delay(attempt: u32) -> Duration {
+    let factor = 1u32 << attempt.saturating_sub(1).min(16);
+    RECONNECT_BASE_DELAY.saturating_mul(factor).min(RECONNECT_MAX_DELAY)
+}
+
+/// Adds the access token as `token` query parameter
+fn connect_url(server_url: &str, token: Option<&str>) -> String {
+    match token {
+        Some(token) => {
+            let separator = if server_url.contains('?') { '&' } else { '?' };
+            format!("{}{}token={}", server_url, separator, urlencoding::encode(token))
+        },
+        None => server_url.to_string(),
+    }
+}
+
+/// State of one connect() call, shared between the client and its task
+struct Connection {
+    state: Mutex<SignalingState>,
+    ice_servers: Vec<IceServer>,
+    session: Mutex<SignalingSession>,
+    sinks: Arc<Mutex<Vec<SignalingEventSink>>>,
+}
+
+impl Connection {
+    fn emit(&self, event: SignalingEvent) {
+        let sinks = self.sinks.lock().unwrap().clone();
+        for sink in sinks {
+            sink(&event);
+        }
+    }
+
+    fn set_state(&self, state: SignalingState) {
+        *self.state.lock().unwrap() = state.clone();
+        self.emit(SignalingEvent::StateChanged { state });
+    }
+
+    fn apply(&self, message: ServerMessage) {
+        let events = self.session.lock().unwrap().handle(message);
+        for event in events {
+            self.emit(event);
+        }
+    }
+
+    fn connection_lost(&self) {
+        let events = self.session.lock().unwrap().connection_lost();
+        for event in events {
+            self.emit(event);
+        }
+    }
+}
+
+/// How a single WebSocket connection ended
+enum ConnectionEnd {
+    /// disconnect() was called or the client was dropped
+    Shutdown,
+    /// The connection failed; `welcomed` tells whether it got as far as the welcome
+    Lost { reason: String, welcomed: bool },
+}
+
+/// Everything the connection task needs
+struct Runner {
+    config: SignalingConfig,
+    local_peer_id: String,
+    token_provider: Option<TokenProvider>,
+    connection: Arc<Connection>,
+    /// Room ID assigned on the first connection, re-entered after reconnects
+    room_id: Option<String>,
+}
+
+impl Runner {
+    async fn run(mut self, mut outgoing: mpsc::UnboundedReceiver<ClientMessage>, mut shutdown: watch::Receiver<bool>) {
+        let mut attempt: u32 = 0;
+
+        loop {
+            let token = self.next_token();
+            let url = connect_url(&self.config.server_url, token.as_deref());
+
+            let connected = tokio::select! {
+                result = connect_async(url.as_str()) => result,
+                _ = shutdown.changed() => return,
+            };
+
+            let reason = match connected {
+                Ok((socket, _)) => match self.run_connection(socket, token, &mut outgoing, &mut shutdown).await {
+                    ConnectionEnd::Shutdown => return,
+                    ConnectionEnd::Lost { reason, welcomed } => {
+                        if welcomed {
+                            attempt = 0;
+                        }
+                        reason
+                    },
+                },
+                Err(e) => e.to_string(),
+            };
+            log::warn!(target: TARGET_SIGNALING, "Signaling connection lost: {}", reason);
+            self.connection.connection_lost();
+
+            attempt += 1;
+            if self.config.max_reconnect_attempts > 0 && attempt > self.config.max_reconnect_attempts {
+                log::error!(target: TARGET_SIGNALING, "Giving up on the signaling server after {} attempts", attempt - 1);
+                self.connection.set_state(SignalingState::Failed { reason });
+                return;
+            }
+
+            let delay = reconnect_delay(attempt);
+            self.connection.set_state(SignalingState::Reconnecting {
+                attempt,
+                retry_in_ms: delay.as_millis() as u64,
+            });
+
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {},
+                _ = shutdown.changed() => return,
+            }
+            self.connection.set_state(SignalingState::Connecting);
+        }
+    }
+
+    async fn run_connection(
+        &mut self,
+        socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+        token: Option<String>,
+        outgoing: &mut mpsc::UnboundedReceiver<ClientMessage>,
+        shutdown: &mut watch::Receiver<bool>,
+    ) -> ConnectionEnd {
+        let (mut sink, mut stream) = socket.split();
+        let mut ping = tokio::time::interval(PING_INTERVAL);
+        ping.tick().await;
+        let mut welcomed = false;
+
+        macro_rules! send_or_lose {
+            ($message:expr) => {
+                if let Err(e) = send(&mut sink, $message).await {
+                    return ConnectionEnd::Lost { reason: e.to_string(), welcomed };
+                }
+            };
+        }
+
+        loop {
+            tokio::select! {
+                incoming = stream.next() => match incoming {
+                    Some(Ok(Message::Text(text))) => {
+                        let message = match serde_json::from_str::<ServerMessage>(&text) {
+                            Ok(message) => message,
+                            Err(e) => {
+                                log::debug!(target: TARGET_SIGNALING, "Ignoring signaling message: {}", e);
+                                continue;
+                            },
+                        };
+
+                        match &message {
+                            ServerMessage::Welcome { client_id, .. } => {
+                                welcomed = true;
+                                self.connection.set_state(SignalingState::Connected { client_id: client_id.clone() });
+
+                                // Enter the room again on every connection
+                                if let Some(request) = self.room_message(token.clone()) {
+                                    send_or_lose!(&request);
+                                }
+                            },
+                            ServerMessage::RoomCreated { room_id } | ServerMessage::RoomJoined { room_id, .. } => {
+                                self.room_id = Some(room_id.clone());
+                            },
+                            // After a reconnect the viewers may have kept the room alive
+                            ServerMessage::Error { message } if message == ROOM_EXISTS_ERROR => {
+                                if let (Some(RoomRequest::Create { .. }), Some(room_id)) = (&self.config.room, &self.room_id) {
+                                    send_or_lose!(&ClientMessage::JoinRoom { room_id: room_id.clone(), token: token.clone() });
+                                    continue;
+                                }
+                            },
+                            _ => {},
+                        }
+
+                        self.connection.apply(message);
+                    },
+                    Some(Ok(Message::Close(_))) | None => {
+                        return ConnectionEnd::Lost { reason: "closed by server".to_string(), welcomed };
+                    },
+                    // Protocol-level pings are answered by tungstenite
+                    Some(Ok(_)) => {},
+                    Some(Err(e)) => {
+                        return ConnectionEnd::Lost { reason: e.to_string(), welcomed };
+                    },
+                },
+                message = outgoing.recv() => match message {
+                    Some(message) => send_or_lose!(&message),
+                    None => return ConnectionEnd::Shutdown,
+                },
+                _ = ping.tick() => send_or_lose!(&ClientMessage::Ping),
+                _ = shutdown.changed() => {
+                    if self.connection.session.lock().unwrap().room_id().is_some() {
+                        let _ = send(&mut sink, &ClientMessage::LeaveRoom).await;
+                    }
+                    let _ = sink.close().await;
+                    self.connection.connection_lost();
+                    return ConnectionEnd::Shutdown;
+                },
+            }
+        }
+    }
+
+    fn next_token(&self) -> Option<String> {
+        match &self.token_provider {
+            Some(provider) => provider(&self.local_peer_id),
+            None => self.config.access_token.clone(),
+        }
+    }
+
+    fn room_message(&self, token: Option<String>) -> Option<ClientMessage> {
+        match self.config.room.as_ref()? {
+            RoomRequest::Create { room_id, settings } => Some(ClientMessage::CreateRoom {
+                room_id: self.room_id.clone().or_else(|| room_id.clone()),
+                settings: settings.clone(),
+                token,
+            }),
+            RoomRequest::Join { room_id } => Some(ClientMessage::JoinRoom {
+                room_id: room_id.clone(),
+                token,
+            }),
+        }
+    }
+}
+
+async fn send<S>(sink: &mut S, message: &ClientMessage) -> Result<(), tokio_tungstenite::tungstenite::Error>
+where
+    S: Sink<Message, Error = tokio_tungstenite::tungstenite::Error> + Unpin,
+{
+    let text = serde_json::to_string(message).expect("client messages always serialize");
+    sink.send(Message::Text(text)).await
+}
+
+/// Signaling client with automatic reconnection
+pub struct SignalingClient {
+    /// ID under which access tokens are issued for this host
+    local_peer_id: String,
+
+    /// Subscribers, kept across connections
+    sinks: Arc<Mutex<Vec<SignalingEventSink>>>,
+
+    token_provider: Option<TokenProvider>,
+
+    /// State of the current connection
+    connection: Option<Arc<Connection>>,
+
+    /// Messages for the connection task
+    outgoing: Option<mpsc::UnboundedSender<ClientMessage>>,
+
+    /// Tells the connection task to leave the room and close
+    shutdown: Option<watch::Sender<bool>>,
+}
+
+impl SignalingClient {
+    pub fn new() -> Self {
+        SignalingClient {
+            local_peer_id: uuid::Uuid::new_v4().to_string(),
+            sinks: Arc::new(Mutex::new(Vec::new())),
+            token_provider: None,
+            connection: None,
+            outgoing: None,
+            shutdown: None,
+        }
+    }
+
+    /// Registers a receiver for all future events
+    pub fn subscribe(&self, sink: SignalingEventSink) {
+        self.sinks.lock().unwrap().push(sink);
+    }
+
+    /// Issues access tokens for each connection attempt
+    pub fn set_token_provider(&mut self, provider: TokenProvider) {
+        self.token_provider = Some(provider);
+    }
+
+    /// Connects to the signaling server, replacing an existing connection.
+    /// Returns immediately; progress is reported as events.
+    pub fn connect(&mut self, config: SignalingConfig) -> Result<(), SignalingError> {
+        if !(config.server_url.starts_with("ws://") || config.server_url.starts_with("wss://")) {
+            return Err(SignalingError::InvalidUrl(config.server_url));
+        }
+
+        self.disconnect();
+
+        let connection = Arc::new(Connection {
+            state: Mutex::new(SignalingState::Disconnected),
+            ice_servers: config.ice_servers.clone(),
+            session: Mutex::new(SignalingSession::new()),
+            sinks: self.sinks.clone(),
+        });
+        connection.set_state(SignalingState::Connecting);
+
+        let (outgoing_tx, outgoing_rx) = mpsc::unbounded_channel();
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+        let runner = Runner {
+            config,
+            local_peer_id: self.local_peer_id.clone(),
+            token_provider: self.token_provider.clone(),
+            connection: connection.clone(),
+            room_id: None,
+        };
+        tauri::async_runtime::spawn(runner.run(outgoing_rx, shutdown_rx));
+
+        self.connection = Some(connection);
+        self.outgoing = Some(outgoing_tx);
+        self.shutdown = Some(shutdown_tx);
+        Ok(())
+    }
+
+    /// Leaves the room and closes the connection
+    pub fn disconnect(&mut self) {
+        self.outgoing = None;
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(true);
+        }
+        if let Some(connection) = self.connection.take() {
+            log::info!(target: TARGET_SIGNALING, "Disconnecting from the signaling server");
+            connection.set_state(SignalingState::Disconnected);
+        }
+    }
+
+    /// Sends a message to the server, e.g. an offer for a peer
+    pub fn send(&self, message: ClientMessage) -> Result<(), SignalingError> {
+        let connection = self.connection.as_ref().ok_or(SignalingError::NotConnected)?;
+        if !matches!(*connection.state.lock().unwrap(), SignalingState::Connected { .. }) {
+            return Err(SignalingError::NotConnected);
+        }
+        if message.needs_room() && connection.session.lock().unwrap().room_id().is_none() {
+            return Err(SignalingError::NotInRoom);
+        }
+
+        self.outgoing.as_ref()
+            .ok_or(SignalingError::NotConnected)?
+            .send(message)
+            .map_err(|_| SignalingError::ChannelClosed)
+    }
+
+    pub fn status(&self) -> SignalingStatus {
+        match &self.connection {
+            Some(connection) => {
+                let session = connection.session.lock().unwrap();
+                SignalingStatus {
+                    state: connection.state.lock().unwrap().clone(),
+                    client_id: session.client_id().map(str::to_string),
+                    room_id: session.room_id().map(str::to_string),
+                    peers: session.peers().to_vec(),
+                    ice_servers: connection.ice_servers.clone(),
+                }
+            },
+            None => SignalingStatus {
+                state: SignalingState::Disconnected,
+                client_id: None,
+                room_id: None,
+                peers: Vec::new(),
+                ice_servers: Vec::new(),
+            },
+        }
+    }
+}
+
+impl Default for SignalingClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SignalingClient {
+    fn drop(&mut self) {
+        self.disconnect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconnect_delay_backs_off_and_caps() {
+        assert_eq!(reconnect_delay(1), Duration::from_secs(1));
+        assert_eq!(reconnect_delay(2), Duration::from_secs(2));
+        assert_eq!(reconnect_delay(4), Duration::from_secs(8));
+        assert_eq!(reconnect_delay(6), Duration::from_secs(30));
+        assert_eq!(reconnect_delay(1000), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_connect_url_adds_token() {
+        assert_eq!(connect_url("wss://signal.example/ws", None), "wss://signal.example/ws");
+        assert_eq!(connect_url("wss://signal.example/ws", Some("a.b+c")), "wss://signal.example/ws?token=a.b%2Bc");
+        assert_eq!(connect_url("ws://localhost:3000/?v=1", Some("t")), "ws://localhost:3000/?v=1&token=t");
+    }
+
+    #[test]
+    fn test_send_requires_connection() {
+        let client = SignalingClient::new();
+        assert!(matches!(client.send(ClientMessage::Ping), Err(SignalingError::NotConnected)));
+        assert_eq!(client.status().state, SignalingState::Disconnected);
+    }
+
+    #[test]
+    fn test_connect_rejects_non_websocket_urls() {
+        let mut client = SignalingClient::new();
+        let config = SignalingConfig {
+            server_url: "https://signal.example".to_string(),
+            room: None,
+            access_token: None,
+            max_reconnect_attempts: 1,
+            ice_servers: Vec::new(),
+        };
+        assert!(matches!(client.connect(config), Err(SignalingError::InvalidUrl(_))));
+    }
+}
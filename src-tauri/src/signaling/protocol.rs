@@ -0,0 +1,139 @@
+// signaling/protocol.rs - Messages of the SmolDesk signaling server (signaling-server/index.js)
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Messages sent to the signaling server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case", rename_all_fields = "camelCase")]
+pub enum ClientMessage {
+    CreateRoom {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        room_id: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        settings: Option<Value>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+    },
+    JoinRoom {
+        room_id: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+    },
+    LeaveRoom,
+    Offer {
+        target_id: String,
+        offer: Value,
+    },
+    Answer {
+        target_id: String,
+        answer: Value,
+    },
+    IceCandidate {
+        target_id: String,
+        candidate: Value,
+    },
+    Ping,
+}
+
+impl ClientMessage {
+    /// Whether the message is only meaningful while in a room
+    pub fn needs_room(&self) -> bool {
+        matches!(self, ClientMessage::Offer { .. } | ClientMessage::Answer { .. } | ClientMessage::IceCandidate { .. })
+    }
+}
+
+/// Messages received from the signaling server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case", rename_all_fields = "camelCase")]
+pub enum ServerMessage {
+    Welcome {
+        client_id: String,
+        token: String,
+    },
+    RoomCreated {
+        room_id: String,
+    },
+    RoomJoined {
+        room_id: String,
+        #[serde(default)]
+        peers: Vec<String>,
+        #[serde(default)]
+        settings: Value,
+    },
+    RoomLeft {
+        room_id: String,
+    },
+    PeerJoined {
+        peer_id: String,
+    },
+    PeerLeft {
+        peer_id: String,
+    },
+    PeerDisconnected {
+        peer_id: String,
+    },
+    Offer {
+        peer_id: String,
+        offer: Value,
+    },
+    Answer {
+        peer_id: String,
+        answer: Value,
+    },
+    IceCandidate {
+        peer_id: String,
+        candidate: Value,
+    },
+    Error {
+        message: String,
+    },
+    Pong,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_client_messages_match_server_protocol() {
+        let join = ClientMessage::JoinRoom { room_id: "room-1".to_string(), token: None };
+        assert_eq!(serde_json::to_value(&join).unwrap(), json!({ "type": "join-room", "roomId": "room-1" }));
+
+        let candidate = ClientMessage::IceCandidate {
+            target_id: "peer-2".to_string(),
+            candidate: json!({ "candidate": "candidate:1 1 UDP 1 10.0.0.1 5000 typ host" }),
+        };
+        assert_eq!(
+            serde_json::to_value(&candidate).unwrap(),
+            json!({
+                "type": "ice-candidate",
+                "targetId": "peer-2",
+                "candidate": { "candidate": "candidate:1 1 UDP 1 10.0.0.1 5000 typ host" },
+            })
+        );
+
+        assert_eq!(serde_json::to_value(ClientMessage::LeaveRoom).unwrap(), json!({ "type": "leave-room" }));
+    }
+
+    #[test]
+    fn test_server_messages_parse() {
+        let joined: ServerMessage = serde_json::from_str(
+            r#"{"type":"room-joined","roomId":"room-1","peers":["a","b"],"settings":{}}"#
+        ).unwrap();
+        assert_eq!(joined, ServerMessage::RoomJoined {
+            room_id: "room-1".to_string(),
+            peers: vec!["a".to_string(), "b".to_string()],
+            settings: json!({}),
+        });
+
+        let offer: ServerMessage = serde_json::from_str(
+            r#"{"type":"offer","offer":{"type":"offer","sdp":"v=0"},"peerId":"a"}"#
+        ).unwrap();
+        assert!(matches!(offer, ServerMessage::Offer { ref peer_id, .. } if peer_id == "a"));
+
+        assert_eq!(serde_json::from_str::<ServerMessage>(r#"{"type":"pong"}"#).unwrap(), ServerMessage::Pong);
+        assert!(serde_json::from_str::<ServerMessage>(r#"{"type":"unknown"}"#).is_err());
+    }
+}
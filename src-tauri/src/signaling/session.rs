@@ -0,0 +1,221 @@
+// signaling/session.rs - Room and peer bookkeeping of one signaling connection
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::signaling::protocol::ServerMessage;
+
+/// Connection state of the signaling client
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state")]
+pub enum SignalingState {
+    Disconnected,
+    Connecting,
+    /// Welcomed by the server, which assigned `client_id`
+    Connected {
+        client_id: String,
+    },
+    /// The connection was lost; the next attempt starts in `retry_in_ms`
+    Reconnecting {
+        attempt: u32,
+        retry_in_ms: u64,
+    },
+    /// All reconnection attempts are exhausted
+    Failed {
+        reason: String,
+    },
+}
+
+/// Events of the signaling client, for the frontend and other modules
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum SignalingEvent {
+    StateChanged {
+        state: SignalingState,
+    },
+    RoomJoined {
+        room_id: String,
+        peers: Vec<String>,
+    },
+    RoomLeft {
+        room_id: String,
+    },
+    PeerConnected {
+        peer_id: String,
+    },
+    PeerDisconnected {
+        peer_id: String,
+    },
+    Offer {
+        peer_id: String,
+        offer: Value,
+    },
+    Answer {
+        peer_id: String,
+        answer: Value,
+    },
+    IceCandidate {
+        peer_id: String,
+        candidate: Value,
+    },
+    /// The server rejected a request, e.g. an unknown room
+    ServerError {
+        message: String,
+    },
+}
+
+impl SignalingEvent {
+    /// Name of the Tauri event the frontend listens for
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            SignalingEvent::StateChanged { .. } => "signaling_state_changed",
+            SignalingEvent::RoomJoined { .. } => "signaling_room_joined",
+            SignalingEvent::RoomLeft { .. } => "signaling_room_left",
+            SignalingEvent::PeerConnected { .. } => "signaling_peer_connected",
+            SignalingEvent::PeerDisconnected { .. } => "signaling_peer_disconnected",
+            SignalingEvent::Offer { .. } => "signaling_offer",
+            SignalingEvent::Answer { .. } => "signaling_answer",
+            SignalingEvent::IceCandidate { .. } => "signaling_ice_candidate",
+            SignalingEvent::ServerError { .. } => "signaling_error",
+        }
+    }
+}
+
+/// Client ID, room and peers as reported by the server
+#[derive(Debug, Default)]
+pub struct SignalingSession {
+    client_id: Option<String>,
+    room_id: Option<String>,
+    peers: Vec<String>,
+}
+
+impl SignalingSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn client_id(&self) -> Option<&str> {
+        self.client_id.as_deref()
+    }
+
+    pub fn room_id(&self) -> Option<&str> {
+        self.room_id.as_deref()
+    }
+
+    pub fn peers(&self) -> &[String] {
+        &self.peers
+    }
+
+    /// Applies a server message and returns the resulting events
+    pub fn handle(&mut self, message: ServerMessage) -> Vec<SignalingEvent> {
+        match message {
+            ServerMessage::Welcome { client_id, .. } => {
+                self.client_id = Some(client_id);
+                Vec::new()
+            },
+            ServerMessage::RoomCreated { room_id } => {
+                self.room_id = Some(room_id.clone());
+                vec![SignalingEvent::RoomJoined { room_id, peers: Vec::new() }]
+            },
+            ServerMessage::RoomJoined { room_id, peers, .. } => {
+                self.room_id = Some(room_id.clone());
+                let mut events = vec![SignalingEvent::RoomJoined { room_id, peers: peers.clone() }];
+                for peer_id in peers {
+                    events.extend(self.add_peer(peer_id));
+                }
+                events
+            },
+            ServerMessage::RoomLeft { room_id } => {
+                let mut events = self.remove_all_peers();
+                self.room_id = None;
+                events.push(SignalingEvent::RoomLeft { room_id });
+                events
+            },
+            ServerMessage::PeerJoined { peer_id } => self.add_peer(peer_id).into_iter().collect(),
+            ServerMessage::PeerLeft { peer_id } | ServerMessage::PeerDisconnected { peer_id } => {
+                self.remove_peer(&peer_id).into_iter().collect()
+            },
+            ServerMessage::Offer { peer_id, offer } => vec![SignalingEvent::Offer { peer_id, offer }],
+            ServerMessage::Answer { peer_id, answer } => vec![SignalingEvent::Answer { peer_id, answer }],
+            ServerMessage::IceCandidate { peer_id, candidate } => vec![SignalingEvent::IceCandidate { peer_id, candidate }],
+            ServerMessage::Error { message } => vec![SignalingEvent::ServerError { message }],
+            ServerMessage::Pong => Vec::new(),
+        }
+    }
+
+    /// The connection is gone: every peer counts as disconnected. The server
+    /// assigns a new client ID on the next connection.
+    pub fn connection_lost(&mut self) -> Vec<SignalingEvent> {
+        let events = self.remove_all_peers();
+        self.client_id = None;
+        self.room_id = None;
+        events
+    }
+
+    fn add_peer(&mut self, peer_id: String) -> Option<SignalingEvent> {
+        if self.peers.contains(&peer_id) {
+            return None;
+        }
+        self.peers.push(peer_id.clone());
+        Some(SignalingEvent::PeerConnected { peer_id })
+    }
+
+    fn remove_peer(&mut self, peer_id: &str) -> Option<SignalingEvent> {
+        let index = self.peers.iter().position(|peer| peer == peer_id)?;
+        let peer_id = self.peers.remove(index);
+        Some(SignalingEvent::PeerDisconnected { peer_id })
+    }
+
+    fn remove_all_peers(&mut self) -> Vec<SignalingEvent> {
+        self.peers.drain(..)
+            .map(|peer_id| SignalingEvent::PeerDisconnected { peer_id })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer_connected(peer_id: &str) -> SignalingEvent {
+        SignalingEvent::PeerConnected { peer_id: peer_id.to_string() }
+    }
+
+    fn peer_disconnected(peer_id: &str) -> SignalingEvent {
+        SignalingEvent::PeerDisconnected { peer_id: peer_id.to_string() }
+    }
+
+    #[test]
+    fn test_peer_lifecycle() {
+        let mut session = SignalingSession::new();
+        session.handle(ServerMessage::Welcome { client_id: "me".to_string(), token: "t".to_string() });
+        assert_eq!(session.client_id(), Some("me"));
+
+        let events = session.handle(ServerMessage::RoomJoined {
+            room_id: "room".to_string(),
+            peers: vec!["a".to_string()],
+            settings: Value::Null,
+        });
+        assert_eq!(events[1..], [peer_connected("a")]);
+
+        assert_eq!(session.handle(ServerMessage::PeerJoined { peer_id: "b".to_string() }), vec![peer_connected("b")]);
+        assert!(session.handle(ServerMessage::PeerJoined { peer_id: "b".to_string() }).is_empty());
+
+        assert_eq!(session.handle(ServerMessage::PeerLeft { peer_id: "a".to_string() }), vec![peer_disconnected("a")]);
+        assert!(session.handle(ServerMessage::PeerDisconnected { peer_id: "a".to_string() }).is_empty());
+        assert_eq!(session.peers(), ["b".to_string()]);
+    }
+
+    #[test]
+    fn test_connection_lost_disconnects_all_peers() {
+        let mut session = SignalingSession::new();
+        session.handle(ServerMessage::RoomCreated { room_id: "room".to_string() });
+        session.handle(ServerMessage::PeerJoined { peer_id: "a".to_string() });
+        session.handle(ServerMessage::PeerJoined { peer_id: "b".to_string() });
+
+        assert_eq!(session.connection_lost(), vec![peer_disconnected("a"), peer_disconnected("b")]);
+        assert!(session.peers().is_empty());
+        assert_eq!(session.room_id(), None);
+        assert!(session.connection_lost().is_empty());
+    }
+}
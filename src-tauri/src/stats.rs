@@ -0,0 +1,658 @@
+// src-tauri/src/stats.rs - Connection quality aggregated from capture, buffer and network stats,
+// and the glass-to-glass latency of streamed frames
+
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+use crate::input_forwarding::latency::LatencySummary;
+use crate::screen_capture::config::ScreenCaptureConfig;
+use crate::screen_capture::types::{CaptureActivity, CaptureStats};
+
+/// How often the quality is sampled and the `connection_quality` event is emitted
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How far back the sample history reaches
+pub const HISTORY_WINDOW: Duration = Duration::from_secs(5 * 60);
+
+/// Network reports older than this are no longer taken into account
+const NETWORK_REPORT_MAX_AGE_MS: u64 = 10_000;
+
+/// Overall quality of the connection, from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum QualityScore {
+    Excellent,
+    Good,
+    Poor,
+    Bad,
+}
+
+/// Metric that determines the overall score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LimitingFactor {
+    FrameRate,
+    BufferLatency,
+    DroppedFrames,
+    InputLatency,
+    NetworkLatency,
+    PacketLoss,
+}
+
+/// Network measurements only the frontend can take (WebRTC stats, input pings)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NetworkReport {
+    /// Round-trip time of the peer connection
+    #[serde(default)]
+    pub rtt_ms: Option<f64>,
+    /// Fraction of lost packets (0.0 - 1.0)
+    #[serde(default)]
+    pub packet_loss: Option<f64>,
+    /// Round trip of a Ping input event through the backend
+    #[serde(default)]
+    pub input_rtt_ms: Option<f64>,
+    /// Frames sent on the peer connection so far
+    #[serde(default)]
+    pub frames_sent: Option<u64>,
+}
+
+/// Raw metrics of one sample; None where nothing was measured
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QualityMetrics {
+    pub encoder_fps: Option<f64>,
+    pub target_fps: Option<f64>,
+    pub buffer_latency_ms: Option<f64>,
+    pub dropped_frame_ratio: Option<f64>,
+    pub input_rtt_ms: Option<f64>,
+    pub network_rtt_ms: Option<f64>,
+    pub packet_loss: Option<f64>,
+}
+
+/// One point of the connection quality history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionQuality {
+    /// Unix time in milliseconds
+    pub timestamp: u64,
+    pub metrics: QualityMetrics,
+    pub score: QualityScore,
+    /// None while everything is excellent
+    pub limiting_factor: Option<LimitingFactor>,
+}
+
+/// Current quality plus the history for sparklines
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionQualityReport {
+    pub current: Option<ConnectionQuality>,
+    pub history: Vec<ConnectionQuality>,
+}
+
+/// Upper bounds (exclusive) for Excellent, Good and Poor; anything above is Bad
+struct Thresholds([f64; 3]);
+
+impl Thresholds {
+    fn score(&self, value: f64) -> QualityScore {
+        if value < self.0[0] {
+            QualityScore::Excellent
+        } else if value < self.0[1] {
+            QualityScore::Good
+        } else if value < self.0[2] {
+            QualityScore::Poor
+        } else {
+            QualityScore::Bad
+        }
+    }
+}
+
+// Share of the target frame rate that is missing
+const FRAME_RATE_DEFICIT: Thresholds = Thresholds([0.1, 0.25, 0.5]);
+const BUFFER_LATENCY_MS: Thresholds = Thresholds([50.0, 100.0, 250.0]);
+const DROPPED_FRAME_RATIO: Thresholds = Thresholds([0.01, 0.05, 0.15]);
+const INPUT_RTT_MS: Thresholds = Thresholds([30.0, 80.0, 200.0]);
+const NETWORK_RTT_MS: Thresholds = Thresholds([50.0, 150.0, 300.0]);
+const PACKET_LOSS: Thresholds = Thresholds([0.01, 0.03, 0.08]);
+
+/// Scores every measured metric; the worst one determines the overall score.
+/// On a tie the factor listed first wins.
+pub fn score_metrics(metrics: &QualityMetrics) -> (QualityScore, Option<LimitingFactor>) {
+    let frame_rate_deficit = match (metrics.encoder_fps, metrics.target_fps) {
+        (Some(fps), Some(target)) if target > 0.0 => Some((1.0 - fps / target).max(0.0)),
+        _ => None,
+    };
+
+    let scored = [
+        (LimitingFactor::FrameRate, frame_rate_deficit.map(|deficit| FRAME_RATE_DEFICIT.score(deficit))),
+        (LimitingFactor::BufferLatency, metrics.buffer_latency_ms.map(|ms| BUFFER_LATENCY_MS.score(ms))),
+        (LimitingFactor::DroppedFrames, metrics.dropped_frame_ratio.map(|ratio| DROPPED_FRAME_RATIO.score(ratio))),
+        (LimitingFactor::InputLatency, metrics.input_rtt_ms.map(|ms| INPUT_RTT_MS.score(ms))),
+        (LimitingFactor::NetworkLatency, metrics.network_rtt_ms.map(|ms| NETWORK_RTT_MS.score(ms))),
+        (LimitingFactor::PacketLoss, metrics.packet_loss.map(|loss| PACKET_LOSS.score(loss))),
+    ];
+
+    let mut worst = (QualityScore::Excellent, None);
+    for (factor, score) in scored {
+        if let Some(score) = score {
+            if score > worst.0 {
+                worst = (score, Some(factor));
+            }
+        }
+    }
+    worst
+}
+
+/// Collects the metrics, scores them and keeps the last five minutes of samples
+pub struct QualityMonitor {
+    history: VecDeque<ConnectionQuality>,
+    network: Option<(u64, NetworkReport)>,
+    /// Frame and drop counters of the previous sample, per monitor
+    last_counters: Vec<(usize, u64, u64)>,
+}
+
+impl QualityMonitor {
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::with_capacity(Self::capacity()),
+            network: None,
+            last_counters: Vec::new(),
+        }
+    }
+
+    fn capacity() -> usize {
+        (HISTORY_WINDOW.as_millis() / SAMPLE_INTERVAL.as_millis()) as usize
+    }
+
+    /// Stores measurements reported by the frontend; missing values keep their last report
+    pub fn report_network(&mut self, report: NetworkReport, now_ms: u64) {
+        let merged = match self.network.take() {
+            Some((_, previous)) => NetworkReport {
+                rtt_ms: report.rtt_ms.or(previous.rtt_ms),
+                packet_loss: report.packet_loss.or(previous.packet_loss),
+                input_rtt_ms: report.input_rtt_ms.or(previous.input_rtt_ms),
+                frames_sent: report.frames_sent.or(previous.frames_sent),
+            },
+            None => report,
+        };
+        self.network = Some((now_ms, merged));
+    }
+
+    /// Aggregates the running captures with their configs and records a sample.
+    /// Returns None when nothing is being measured.
+    pub fn sample(&mut self, captures: &[(CaptureStats, ScreenCaptureConfig)], now_ms: u64) -> Option<ConnectionQuality> {
+        let mut metrics = QualityMetrics::default();
+
+        // The capture furthest below its target frame rate counts
+        for (stats, config) in captures {
+            let target = match stats.activity {
+                CaptureActivity::Idle if config.idle.enabled => config.idle.idle_fps,
+                _ => config.fps,
+            } as f64;
+            let is_worse = match (metrics.encoder_fps, metrics.target_fps) {
+                (Some(fps), Some(worst_target)) => stats.fps / target < fps / worst_target,
+                _ => true,
+            };
+            if target > 0.0 && is_worse {
+                metrics.encoder_fps = Some(stats.fps);
+                metrics.target_fps = Some(target);
+            }
+
+            let latency = metrics.buffer_latency_ms.unwrap_or(0.0).max(stats.latency_estimate);
+            metrics.buffer_latency_ms = Some(latency);
+        }
+        metrics.dropped_frame_ratio = self.dropped_frame_ratio(captures.iter().map(|(stats, _)| stats));
+
+        if let Some((reported_at, report)) = &self.network {
+            if now_ms.saturating_sub(*reported_at) <= NETWORK_REPORT_MAX_AGE_MS {
+                metrics.network_rtt_ms = report.rtt_ms;
+                metrics.packet_loss = report.packet_loss;
+                metrics.input_rtt_ms = report.input_rtt_ms;
+            }
+        }
+
+        if metrics == QualityMetrics::default() {
+            return None;
+        }
+
+        let (score, limiting_factor) = score_metrics(&metrics);
+        let quality = ConnectionQuality { timestamp: now_ms, metrics, score, limiting_factor };
+
+        let window_start = now_ms.saturating_sub(HISTORY_WINDOW.as_millis() as u64);
+        while self.history.front().map_or(false, |oldest| oldest.timestamp < window_start)
+            || self.history.len() >= Self::capacity()
+        {
+            self.history.pop_front();
+        }
+        self.history.push_back(quality.clone());
+
+        Some(quality)
+    }
+
+    /// Share of frames dropped since the previous sample, over all captures
+    fn dropped_frame_ratio<'a>(&mut self, captures: impl Iterator<Item = &'a CaptureStats>) -> Option<f64> {
+        let mut frames = 0;
+        let mut dropped = 0;
+        let mut counters = Vec::new();
+
+        for stats in captures {
+            let previous = self.last_counters.iter()
+                .find(|(index, _, _)| *index == stats.monitor_index)
+                .map(|(_, frames, dropped)| (*frames, *dropped));
+
+            // Counters start over when a capture is restarted
+            match previous {
+                Some((last_frames, last_dropped)) if stats.frame_count >= last_frames && stats.dropped_frames >= last_dropped => {
+                    frames += stats.frame_count - last_frames;
+                    dropped += stats.dropped_frames - last_dropped;
+                },
+                _ => {
+                    frames += stats.frame_count;
+                    dropped += stats.dropped_frames;
+                },
+            }
+            counters.push((stats.monitor_index, stats.frame_count, stats.dropped_frames));
+        }
+        self.last_counters = counters;
+
+        if frames == 0 {
+            return None;
+        }
+        Some(dropped as f64 / frames as f64)
+    }
+
+    pub fn latest(&self) -> Option<&ConnectionQuality> {
+        self.history.back()
+    }
+
+    pub fn report(&self) -> ConnectionQualityReport {
+        ConnectionQualityReport {
+            current: self.latest().cloned(),
+            history: self.history.iter().cloned().collect(),
+        }
+    }
+}
+
+impl Default for QualityMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How far back the frame latency breakdown reaches
+pub const FRAME_LATENCY_WINDOW: Duration = Duration::from_secs(60);
+
+/// How often `clock_sync` re-estimates the offset of the frontend's clock
+pub const CLOCK_SYNC_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Upper bound for stored frames, a minute of 60 fps on a few monitors
+const MAX_FRAME_SAMPLES: usize = 20_000;
+
+/// Answers to older syncs no longer count, so a drifting clock is followed
+const CLOCK_SYNC_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// Syncs still waiting for an answer
+const MAX_PENDING_SYNCS: usize = 8;
+
+/// Sent with `clock_sync`; the frontend answers with `report_clock_sync`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClockSync {
+    pub sync_id: u64,
+    /// Frame clock in microseconds, the clock of the frame stream's timestamps
+    pub host_monotonic_us: u64,
+    /// Unix time in milliseconds at the same moment
+    pub wall_clock_ms: u64,
+}
+
+/// Host timestamps of a frame sent on the frame stream, in µs on the frame clock
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SentFrame {
+    pub frame_id: u64,
+    pub captured_us: u64,
+    pub encoded_us: u64,
+    pub sent_us: u64,
+}
+
+/// Returned by `get_latency_breakdown`
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyBreakdown {
+    pub window_secs: u64,
+    /// Frames sent within the window
+    pub frames: usize,
+    /// Frames the frontend reported as rendered
+    pub rendered_frames: usize,
+    pub capture_to_encode: Option<LatencySummary>,
+    pub encode_to_transport: Option<LatencySummary>,
+    /// Needs a clock offset from `report_clock_sync`
+    pub transport_to_render: Option<LatencySummary>,
+    /// Capture to render, for rendered frames only
+    pub glass_to_glass: Option<LatencySummary>,
+    /// Frontend clock minus host clock; None until a sync was answered
+    pub clock_offset_ms: Option<f64>,
+    /// Round trip of the sync the offset is based on
+    pub clock_sync_rtt_ms: Option<f64>,
+}
+
+struct FrameSample {
+    frame: SentFrame,
+    /// Render time converted to the frame clock
+    rendered_us: Option<u64>,
+}
+
+/// One answered sync: the offset and how precise it is
+struct OffsetSample {
+    at_us: u64,
+    rtt_us: u64,
+    offset_us: i64,
+}
+
+/// Glass-to-glass latency of streamed frames, split into the segments
+/// capture→encode, encode→transport and transport→render.
+///
+/// The render times come from the frontend's clock. Every sync estimates the
+/// offset between the clocks from one round trip, as NTP does; the answer
+/// with the shortest round trip within the last minute is the most precise.
+pub struct FrameLatencyTracker {
+    samples: VecDeque<FrameSample>,
+    pending_syncs: VecDeque<(u64, u64)>,
+    offsets: VecDeque<OffsetSample>,
+    next_sync_id: u64,
+}
+
+impl FrameLatencyTracker {
+    pub fn new() -> Self {
+        Self {
+            samples: VecDeque::new(),
+            pending_syncs: VecDeque::new(),
+            offsets: VecDeque::new(),
+            next_sync_id: 1,
+        }
+    }
+
+    /// Records a frame as it leaves for the transport
+    pub fn frame_sent(&mut self, frame: SentFrame) {
+        self.prune(frame.sent_us);
+        if self.samples.len() >= MAX_FRAME_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(FrameSample { frame, rendered_us: None });
+    }
+
+    /// Starts a sync at `now_us` on the frame clock
+    pub fn begin_clock_sync(&mut self, now_us: u64, wall_clock_ms: u64) -> ClockSync {
+        let sync_id = self.next_sync_id;
+        self.next_sync_id += 1;
+
+        if self.pending_syncs.len() >= MAX_PENDING_SYNCS {
+            self.pending_syncs.pop_front();
+        }
+        self.pending_syncs.push_back((sync_id, now_us));
+        ClockSync { sync_id, host_monotonic_us: now_us, wall_clock_ms }
+    }
+
+    /// The frontend read `client_ms` on its clock when the sync arrived.
+    /// False if the sync is unknown or was answered already.
+    pub fn complete_clock_sync(&mut self, sync_id: u64, client_ms: f64, now_us: u64) -> bool {
+        let sent_us = match self.pending_syncs.iter().position(|(id, _)| *id == sync_id) {
+            Some(index) => self.pending_syncs.remove(index).map(|(_, sent_us)| sent_us).unwrap_or(now_us),
+            None => return false,
+        };
+
+        // The answer left the frontend halfway through the round trip
+        let rtt_us = now_us.saturating_sub(sent_us);
+        let host_us = sent_us + rtt_us / 2;
+        let client_us = (client_ms * 1000.0) as i64;
+        self.offsets.push_back(OffsetSample { at_us: now_us, rtt_us, offset_us: client_us - host_us as i64 });
+        self.prune(now_us);
+        true
+    }
+
+    /// The frontend rendered `frame_id` at `client_ms` on its clock. False if
+    /// the frame is unknown, e.g. outside the window, or no sync was answered yet.
+    pub fn frame_rendered(&mut self, frame_id: u64, client_ms: f64, now_us: u64) -> bool {
+        self.prune(now_us);
+        let offset_us = match self.best_offset() {
+            Some(offset) => offset.offset_us,
+            None => return false,
+        };
+        let sample = match self.samples.iter_mut().rev().find(|sample| sample.frame.frame_id == frame_id) {
+            Some(sample) => sample,
+            None => return false,
+        };
+
+        let rendered_us = (client_ms * 1000.0) as i64 - offset_us;
+        // An offset estimated a little off must not put the render before the send
+        sample.rendered_us = Some((rendered_us.max(0) as u64).max(sample.frame.sent_us));
+        true
+    }
+
+    /// Percentiles of every segment over the window ending at `now_us`
+    pub fn breakdown(&mut self, now_us: u64) -> LatencyBreakdown {
+        self.prune(now_us);
+
+        let span = |from: u64, to: u64| Duration::from_micros(to.saturating_sub(from));
+        let mut capture_to_encode = Vec::with_capacity(self.samples.len());
+        let mut encode_to_transport = Vec::with_capacity(self.samples.len());
+        let mut transport_to_render = Vec::new();
+        let mut glass_to_glass = Vec::new();
+        for sample in &self.samples {
+            let frame = &sample.frame;
+            capture_to_encode.push(span(frame.captured_us, frame.encoded_us));
+            encode_to_transport.push(span(frame.encoded_us, frame.sent_us));
+            if let Some(rendered_us) = sample.rendered_us {
+                transport_to_render.push(span(frame.sent_us, rendered_us));
+                glass_to_glass.push(span(frame.captured_us, rendered_us));
+            }
+        }
+
+        let offset = self.best_offset();
+        LatencyBreakdown {
+            window_secs: FRAME_LATENCY_WINDOW.as_secs(),
+            frames: self.samples.len(),
+            rendered_frames: glass_to_glass.len(),
+            capture_to_encode: LatencySummary::from_durations(&mut capture_to_encode),
+            encode_to_transport: LatencySummary::from_durations(&mut encode_to_transport),
+            transport_to_render: LatencySummary::from_durations(&mut transport_to_render),
+            glass_to_glass: LatencySummary::from_durations(&mut glass_to_glass),
+            clock_offset_ms: offset.map(|offset| offset.offset_us as f64 / 1000.0),
+            clock_sync_rtt_ms: offset.map(|offset| offset.rtt_us as f64 / 1000.0),
+        }
+    }
+
+    fn best_offset(&self) -> Option<&OffsetSample> {
+        self.offsets.iter().min_by_key(|offset| offset.rtt_us)
+    }
+
+    fn prune(&mut self, now_us: u64) {
+        let frames_from = now_us.saturating_sub(FRAME_LATENCY_WINDOW.as_micros() as u64);
+        while self.samples.front().is_some_and(|oldest| oldest.frame.sent_us < frames_from) {
+            self.samples.pop_front();
+        }
+        // The newest answer stays even if the frontend stopped answering
+        let offsets_from = now_us.saturating_sub(CLOCK_SYNC_MAX_AGE.as_micros() as u64);
+        while self.offsets.len() > 1 && self.offsets.front().is_some_and(|oldest| oldest.at_us < offsets_from) {
+            self.offsets.pop_front();
+        }
+    }
+}
+
+impl Default for FrameLatencyTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Current Unix time in milliseconds
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture(monitor_index: usize, fps: f64, frame_count: u64, dropped_frames: u64) -> CaptureStats {
+        CaptureStats {
+            monitor_index,
+            fps,
+            frame_count,
+            dropped_frames,
+            latency_estimate: 20.0,
+            ..CaptureStats::default()
+        }
+    }
+
+    fn config(fps: u32) -> ScreenCaptureConfig {
+        ScreenCaptureConfig { fps, ..ScreenCaptureConfig::default() }
+    }
+
+    #[test]
+    fn test_score_is_determined_by_worst_metric() {
+        let metrics = QualityMetrics {
+            encoder_fps: Some(29.0),
+            target_fps: Some(30.0),
+            buffer_latency_ms: Some(120.0),
+            network_rtt_ms: Some(60.0),
+            ..QualityMetrics::default()
+        };
+        assert_eq!(score_metrics(&metrics), (QualityScore::Poor, Some(LimitingFactor::BufferLatency)));
+
+        let metrics = QualityMetrics { packet_loss: Some(0.2), ..metrics };
+        assert_eq!(score_metrics(&metrics), (QualityScore::Bad, Some(LimitingFactor::PacketLoss)));
+    }
+
+    #[test]
+    fn test_excellent_has_no_limiting_factor() {
+        let metrics = QualityMetrics {
+            encoder_fps: Some(30.0),
+            target_fps: Some(30.0),
+            dropped_frame_ratio: Some(0.0),
+            input_rtt_ms: Some(12.0),
+            ..QualityMetrics::default()
+        };
+        assert_eq!(score_metrics(&metrics), (QualityScore::Excellent, None));
+        assert_eq!(score_metrics(&QualityMetrics::default()), (QualityScore::Excellent, None));
+    }
+
+    #[test]
+    fn test_frame_rate_scored_against_target() {
+        let metrics = |fps| QualityMetrics { encoder_fps: Some(fps), target_fps: Some(60.0), ..QualityMetrics::default() };
+        assert_eq!(score_metrics(&metrics(70.0)).0, QualityScore::Excellent);
+        assert_eq!(score_metrics(&metrics(50.0)), (QualityScore::Good, Some(LimitingFactor::FrameRate)));
+        assert_eq!(score_metrics(&metrics(20.0)).0, QualityScore::Bad);
+    }
+
+    #[test]
+    fn test_ties_keep_first_factor() {
+        let metrics = QualityMetrics {
+            buffer_latency_ms: Some(80.0),
+            network_rtt_ms: Some(100.0),
+            ..QualityMetrics::default()
+        };
+        assert_eq!(score_metrics(&metrics), (QualityScore::Good, Some(LimitingFactor::BufferLatency)));
+    }
+
+    #[test]
+    fn test_idle_capture_is_measured_against_idle_frame_rate() {
+        let mut monitor = QualityMonitor::new();
+        let stats = CaptureStats { activity: CaptureActivity::Idle, ..capture(0, 2.0, 10, 0) };
+        let quality = monitor.sample(&[(stats, config(30))], 1_000).unwrap();
+        assert_eq!(quality.metrics.target_fps, Some(2.0));
+        assert_eq!(quality.score, QualityScore::Excellent);
+    }
+
+    #[test]
+    fn test_dropped_frames_counted_since_last_sample() {
+        let mut monitor = QualityMonitor::new();
+        monitor.sample(&[(capture(0, 30.0, 100, 50), config(30))], 1_000);
+
+        let quality = monitor.sample(&[(capture(0, 30.0, 200, 51), config(30))], 2_000).unwrap();
+        assert_eq!(quality.metrics.dropped_frame_ratio, Some(0.01));
+
+        // A restarted capture begins counting at zero again
+        let quality = monitor.sample(&[(capture(0, 30.0, 10, 5), config(30))], 3_000).unwrap();
+        assert_eq!(quality.metrics.dropped_frame_ratio, Some(0.5));
+    }
+
+    #[test]
+    fn test_stale_network_report_is_ignored() {
+        let mut monitor = QualityMonitor::new();
+        monitor.report_network(NetworkReport { rtt_ms: Some(400.0), ..NetworkReport::default() }, 0);
+        monitor.report_network(NetworkReport { input_rtt_ms: Some(10.0), ..NetworkReport::default() }, 1_000);
+
+        let quality = monitor.sample(&[], 2_000).unwrap();
+        assert_eq!(quality.metrics.network_rtt_ms, Some(400.0));
+        assert_eq!(quality.limiting_factor, Some(LimitingFactor::NetworkLatency));
+
+        assert!(monitor.sample(&[], 1_000 + NETWORK_REPORT_MAX_AGE_MS + 1).is_none());
+    }
+
+    #[test]
+    fn test_history_covers_five_minutes() {
+        let mut monitor = QualityMonitor::new();
+        let interval = SAMPLE_INTERVAL.as_millis() as u64;
+        for i in 0..400 {
+            monitor.sample(&[(capture(0, 30.0, i * 30, 0), config(30))], i * interval);
+        }
+
+        let report = monitor.report();
+        assert_eq!(report.history.len(), QualityMonitor::capacity());
+        let oldest = report.history.first().unwrap().timestamp;
+        let newest = report.current.unwrap().timestamp;
+        assert!(newest - oldest < HISTORY_WINDOW.as_millis() as u64);
+    }
+
+    fn sent_frame(frame_id: u64, sent_us: u64) -> SentFrame {
+        SentFrame { frame_id, captured_us: sent_us - 30_000, encoded_us: sent_us - 5_000, sent_us }
+    }
+
+    #[test]
+    fn test_frame_latency_segments() {
+        let mut tracker = FrameLatencyTracker::new();
+        let start = 10_000_000;
+        tracker.frame_sent(sent_frame(1, start));
+        // Without a clock offset the render cannot be placed
+        assert!(!tracker.frame_rendered(1, 0.0, start));
+
+        // The frontend's clock is 1000 s ahead; the sync takes a 4 ms round trip
+        let client_ms = |host_us: u64| host_us as f64 / 1000.0 + 1_000_000.0;
+        let sync = tracker.begin_clock_sync(start, 0);
+        assert!(tracker.complete_clock_sync(sync.sync_id, client_ms(start + 2_000), start + 4_000));
+        assert!(!tracker.complete_clock_sync(sync.sync_id, client_ms(start + 2_000), start + 4_000));
+
+        assert!(tracker.frame_rendered(1, client_ms(start + 12_000), start + 20_000));
+        assert!(!tracker.frame_rendered(2, client_ms(start + 12_000), start + 20_000));
+
+        let breakdown = tracker.breakdown(start + 20_000);
+        assert_eq!((breakdown.frames, breakdown.rendered_frames), (1, 1));
+        assert_eq!(breakdown.capture_to_encode.unwrap().p50_ms, 25.0);
+        assert_eq!(breakdown.encode_to_transport.unwrap().p50_ms, 5.0);
+        assert_eq!(breakdown.transport_to_render.unwrap().p50_ms, 12.0);
+        assert_eq!(breakdown.glass_to_glass.unwrap().p50_ms, 42.0);
+        assert_eq!(breakdown.clock_offset_ms, Some(1_000_000.0));
+        assert_eq!(breakdown.clock_sync_rtt_ms, Some(4.0));
+
+        // Frames leave the window after a minute
+        let later = tracker.breakdown(start + FRAME_LATENCY_WINDOW.as_micros() as u64 + 1);
+        assert_eq!(later.frames, 0);
+        assert!(later.capture_to_encode.is_none());
+    }
+
+    #[test]
+    fn test_clock_offset_follows_drift() {
+        let mut tracker = FrameLatencyTracker::new();
+
+        // A precise answer with the clocks in step
+        let sync = tracker.begin_clock_sync(1_000_000, 0);
+        tracker.complete_clock_sync(sync.sync_id, 1000.5, 1_001_000);
+        // A slower answer does not replace it while it is recent
+        let sync = tracker.begin_clock_sync(2_000_000, 0);
+        tracker.complete_clock_sync(sync.sync_id, 2055.0, 2_010_000);
+        assert_eq!(tracker.breakdown(2_010_000).clock_offset_ms, Some(0.0));
+
+        // A minute later the frontend's clock has drifted by 50 ms
+        let breakdown = tracker.breakdown(62_000_000);
+        assert_eq!(breakdown.clock_offset_ms, Some(50.0));
+        assert_eq!(breakdown.clock_sync_rtt_ms, Some(10.0));
+        // The last answer is kept even when the frontend stops answering
+        assert_eq!(tracker.breakdown(600_000_000).clock_offset_ms, Some(50.0));
+    }
+}
@@ -0,0 +1,160 @@
+// src-tauri/src/subsystems.rs - Re-initialization of managers that failed at startup
+//
+// A manager whose external tool (xdotool, ydotool, wl-clipboard, ffmpeg, ...)
+// is missing at startup stays uninitialized. Commands and a background monitor
+// retry the initialization, at most once per RETRY_INTERVAL and subsystem, so
+// installing the tool later does not require restarting SmolDesk.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Minimum time between two automatic initialization attempts of a subsystem
+pub const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Managers that can be recreated at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    ScreenCapture,
+    InputForwarding,
+    Clipboard,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 3] = [Subsystem::ScreenCapture, Subsystem::InputForwarding, Subsystem::Clipboard];
+}
+
+/// Rate limits automatic initialization attempts per subsystem
+pub struct RetryGate {
+    interval: Duration,
+    last_attempt: Mutex<HashMap<Subsystem, Instant>>,
+}
+
+impl RetryGate {
+    pub fn new(interval: Duration) -> Self {
+        RetryGate {
+            interval,
+            last_attempt: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether an attempt for `subsystem` may run at `now`; a granted attempt is recorded
+    pub fn try_acquire(&self, subsystem: Subsystem, now: Instant) -> bool {
+        let mut last_attempt = self.last_attempt.lock().unwrap();
+        let due = match last_attempt.get(&subsystem) {
+            Some(last) => now.saturating_duration_since(*last) >= self.interval,
+            None => true,
+        };
+        if due {
+            last_attempt.insert(subsystem, now);
+        }
+        due
+    }
+}
+
+/// Lock a manager slot; an empty slot is re-initialized first, at most once per `RETRY_INTERVAL`.
+/// The manager is created outside the lock, so creating one may lock other slots.
+pub fn lock_or_retry<'a, T, E: std::fmt::Display>(
+    retries: &RetryGate,
+    slot: &'a Mutex<Option<T>>,
+    subsystem: Subsystem,
+    create: impl FnOnce() -> Result<T, E>,
+) -> MutexGuard<'a, Option<T>> {
+    if slot.lock().unwrap().is_some() || !retries.try_acquire(subsystem, Instant::now()) {
+        return slot.lock().unwrap();
+    }
+
+    let created = create();
+    let mut guard = slot.lock().unwrap();
+    match created {
+        Ok(manager) if guard.is_none() => {
+            log::info!("{:?} initialized after an earlier failure", subsystem);
+            *guard = Some(manager);
+        },
+        Ok(_) => {},
+        Err(e) => log::debug!("{:?} still unavailable: {}", subsystem, e),
+    }
+    guard
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attempts_are_rate_limited_per_subsystem() {
+        let gate = RetryGate::new(RETRY_INTERVAL);
+        let start = Instant::now();
+
+        assert!(gate.try_acquire(Subsystem::InputForwarding, start));
+        assert!(!gate.try_acquire(Subsystem::InputForwarding, start + Duration::from_secs(9)));
+        assert!(gate.try_acquire(Subsystem::Clipboard, start + Duration::from_secs(9)));
+
+        // The denied attempt does not push the next one back
+        assert!(gate.try_acquire(Subsystem::InputForwarding, start + RETRY_INTERVAL));
+    }
+
+    #[test]
+    fn test_subsystem_names() {
+        assert_eq!(serde_json::to_value(Subsystem::InputForwarding).unwrap(), "input_forwarding");
+        assert_eq!(serde_json::from_str::<Subsystem>("\"screen_capture\"").unwrap(), Subsystem::ScreenCapture);
+    }
+
+    #[test]
+    fn test_blocked_clipboard_does_not_delay_input() {
+        use std::sync::{mpsc, Arc};
+        use std::thread;
+
+        // Far above the few microseconds two uncontended locks take
+        const INPUT_DEADLINE: Duration = Duration::from_millis(200);
+
+        let retries = Arc::new(RetryGate::new(RETRY_INTERVAL));
+        let clipboard: Arc<Mutex<Option<&str>>> = Arc::new(Mutex::new(None));
+        let input: Mutex<Option<&str>> = Mutex::new(None);
+        let (started_tx, started) = mpsc::channel();
+
+        // The background monitor retries the clipboard and its tool probe hangs
+        let (release_probe, probe) = mpsc::channel::<()>();
+        let retry = {
+            let (retries, clipboard, started_tx) = (retries.clone(), clipboard.clone(), started_tx.clone());
+            thread::spawn(move || {
+                drop(lock_or_retry(&retries, &clipboard, Subsystem::Clipboard, || {
+                    started_tx.send(()).unwrap();
+                    probe.recv().map(|_| "clipboard").map_err(|e| e.to_string())
+                }));
+            })
+        };
+        started.recv().unwrap();
+
+        // Meanwhile a clipboard command waits on the provider while holding the slot
+        let (release_call, call) = mpsc::channel::<()>();
+        let command = {
+            let (retries, clipboard) = (retries.clone(), clipboard.clone());
+            thread::spawn(move || {
+                let _clipboard = lock_or_retry(&retries, &clipboard, Subsystem::Clipboard, || Err::<&str, _>("not retried"));
+                started_tx.send(()).unwrap();
+                call.recv().unwrap();
+            })
+        };
+        started.recv().unwrap();
+
+        // Input creates its forwarder and then finds it in place, like two send_input_event calls
+        let begin = Instant::now();
+        for _ in 0..2 {
+            let forwarder = lock_or_retry(&retries, &input, Subsystem::InputForwarding, || Ok::<_, String>("forwarder"));
+            assert_eq!(*forwarder, Some("forwarder"));
+        }
+        let elapsed = begin.elapsed();
+
+        release_call.send(()).unwrap();
+        command.join().unwrap();
+        release_probe.send(()).unwrap();
+        retry.join().unwrap();
+
+        assert!(elapsed < INPUT_DEADLINE, "input waited {:?} for the clipboard", elapsed);
+        assert_eq!(*clipboard.lock().unwrap(), Some("clipboard"));
+    }
+}
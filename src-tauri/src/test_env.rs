@@ -0,0 +1,167 @@
+// src-tauri/src/test_env.rs - Test harness for code that runs external tools
+//
+// Commands built through a TestEnv's runner find the fake tools from
+// `mock-tools` first in their PATH. The fakes record every invocation and
+// replay the output configured here, so forwarders and capturers can be tested
+// without xdotool, ydotool, ffmpeg, audio tools or a display server. Fixtures
+// shared by several test modules live here as well.
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use crate::command_runner::CommandRunner;
+use crate::screen_capture::types::{MonitorInfo, MonitorRotation};
+
+/// Must match `MOCK_DIR_VAR` and `INVOCATION_LOG` in mock-tools
+const MOCK_DIR_VAR: &str = "SMOLDESK_MOCK_DIR";
+const INVOCATION_LOG: &str = "invocations.log";
+
+/// Recorded fixture in `src-tauri/test-fixtures`
+pub fn fixture(name: &str) -> Vec<u8> {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("test-fixtures").join(name);
+    fs::read(&path).unwrap_or_else(|e| panic!("missing fixture {}: {}", path.display(), e))
+}
+
+/// A secondary 1920x1080 monitor right of a 2560 pixel wide primary one, so
+/// desktop and monitor coordinates differ
+pub fn monitor() -> MonitorInfo {
+    MonitorInfo {
+        index: 1,
+        name: "HDMI-1".to_string(),
+        width: 1920,
+        height: 1080,
+        refresh_rate: Some(60.0),
+        primary: false,
+        x_offset: 2560,
+        y_offset: 0,
+        scale_factor: 1.0,
+        rotation: MonitorRotation::Normal,
+    }
+}
+
+/// Directory with the fake tools, built once per test run
+fn mock_tools_dir() -> &'static Path {
+    static DIR: OnceLock<PathBuf> = OnceLock::new();
+    DIR.get_or_init(|| {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let target_dir = root.join("target").join("mock-tools");
+        let status = Command::new(env!("CARGO"))
+            .args(["build", "--quiet", "--manifest-path"])
+            .arg(root.join("mock-tools").join("Cargo.toml"))
+            .arg("--target-dir")
+            .arg(&target_dir)
+            .status()
+            .expect("failed to run cargo for mock-tools");
+        assert!(status.success(), "building mock-tools failed");
+        target_dir.join("debug")
+    })
+}
+
+/// Scratch directory with the canned output and invocation log of one test
+pub struct TestEnv {
+    dir: PathBuf,
+    path: OsString,
+}
+
+impl TestEnv {
+    pub fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!("smoldesk-mock-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+
+        // The fakes shadow installed tools; everything else, e.g. `which`, stays reachable
+        let mut paths = vec![mock_tools_dir().to_path_buf()];
+        if let Some(path) = std::env::var_os("PATH") {
+            paths.extend(std::env::split_paths(&path));
+        }
+        let path = std::env::join_paths(paths).unwrap();
+
+        TestEnv { dir, path }
+    }
+
+    /// Scratch directory; the fakes started from this environment carry it in
+    /// their environment
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Runner whose commands use the fake tools of this environment
+    pub fn runner(&self) -> Arc<dyn CommandRunner> {
+        Arc::new(MockCommandRunner {
+            dir: self.dir.clone(),
+            path: self.path.clone(),
+        })
+    }
+
+    /// Bytes `tool` writes to stdout on every call
+    pub fn set_stdout(&self, tool: &str, data: impl AsRef<[u8]>) {
+        fs::write(self.dir.join(format!("{}.stdout", tool)), data).unwrap();
+    }
+
+    pub fn set_stderr(&self, tool: &str, data: impl AsRef<[u8]>) {
+        fs::write(self.dir.join(format!("{}.stderr", tool)), data).unwrap();
+    }
+
+    pub fn set_exit_code(&self, tool: &str, code: i32) {
+        fs::write(self.dir.join(format!("{}.exit", tool)), code.to_string()).unwrap();
+    }
+
+    /// Keep `tool` running after its output, like a streaming encoder
+    pub fn keep_running(&self, tool: &str, duration: Duration) {
+        fs::write(self.dir.join(format!("{}.sleep_ms", tool)), duration.as_millis().to_string()).unwrap();
+    }
+
+    /// Let `tool` read its stdin; the data is returned by `stdin`
+    pub fn record_stdin(&self, tool: &str) {
+        fs::write(self.dir.join(format!("{}.read_stdin", tool)), "").unwrap();
+    }
+
+    /// Everything `tool` read from stdin so far
+    pub fn stdin(&self, tool: &str) -> Vec<u8> {
+        fs::read(self.dir.join(format!("{}.stdin", tool))).unwrap_or_default()
+    }
+
+    /// Calls of all fake tools in order, each as tool name followed by the arguments
+    pub fn invocations(&self) -> Vec<Vec<String>> {
+        fs::read_to_string(self.dir.join(INVOCATION_LOG))
+            .unwrap_or_default()
+            .lines()
+            .map(|line| line.split('\t').map(str::to_string).collect())
+            .collect()
+    }
+
+    /// Calls recorded since the last `take_invocations`
+    pub fn take_invocations(&self) -> Vec<Vec<String>> {
+        let invocations = self.invocations();
+        let _ = fs::remove_file(self.dir.join(INVOCATION_LOG));
+        invocations
+    }
+}
+
+impl Drop for TestEnv {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+struct MockCommandRunner {
+    dir: PathBuf,
+    path: OsString,
+}
+
+impl CommandRunner for MockCommandRunner {
+    fn command(&self, program: &str) -> Command {
+        // The program is looked up in the child's PATH, so the fakes win
+        let mut cmd = Command::new(program);
+        cmd.env("PATH", &self.path).env(MOCK_DIR_VAR, &self.dir);
+        cmd
+    }
+}
+
+/// Expected invocation, written like a command line
+pub fn call(command_line: &str) -> Vec<String> {
+    command_line.split_whitespace().map(str::to_string).collect()
+}
@@ -0,0 +1,288 @@
+// src-tauri/src/tool_locator.rs - Where external tools are found and how they run
+//
+// Flatpak and other containerized installs cannot rely on ffmpeg, xdotool or
+// wl-clipboard being on PATH, and some users point SmolDesk at their own
+// ffmpeg build. The `tools` settings section names binaries explicitly; every
+// tool spawned through `SystemCommandRunner` is resolved here and started with
+// a restricted environment, so secrets in the app's environment do not leak
+// into child processes.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::RwLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// A version probe taking longer than this counts as not answering
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Variables passed on to child processes; everything else is cleared.
+/// Besides the display and runtime directory, tools need PATH to find their
+/// helpers, XAUTHORITY to connect to X, the session bus and compositor sockets
+/// (swaymsg, hyprctl, ydotool) and the locale.
+const INHERITED_ENV: [&str; 15] = [
+    "PATH",
+    "HOME",
+    "DISPLAY",
+    "XAUTHORITY",
+    "WAYLAND_DISPLAY",
+    "XDG_RUNTIME_DIR",
+    "XDG_SESSION_TYPE",
+    "XDG_CURRENT_DESKTOP",
+    "DBUS_SESSION_BUS_ADDRESS",
+    "SWAYSOCK",
+    "HYPRLAND_INSTANCE_SIGNATURE",
+    "YDOTOOL_SOCKET",
+    "PIPEWIRE_REMOTE",
+    "LANG",
+    "LANGUAGE",
+];
+
+/// Paths of external tools; unset tools are looked up in PATH
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolPathConfig {
+    pub ffmpeg_path: Option<PathBuf>,
+    pub xdotool_path: Option<PathBuf>,
+    pub ydotool_path: Option<PathBuf>,
+    /// Directory containing `wl-copy` and `wl-paste`
+    pub wl_clipboard_path: Option<PathBuf>,
+    pub xclip_path: Option<PathBuf>,
+    pub grim_path: Option<PathBuf>,
+    /// Further tools by program name, e.g. `xrandr` or `wlr-randr`
+    pub other: BTreeMap<String, PathBuf>,
+}
+
+/// A configured path that cannot be used
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolPathProblem {
+    /// Program name, e.g. `ffmpeg`
+    pub tool: String,
+    /// Settings key, e.g. `ffmpeg_path` or `other.xrandr`
+    pub setting: String,
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// Resolves program names to the configured binaries
+#[derive(Debug, Clone, Default)]
+pub struct ToolLocator {
+    config: ToolPathConfig,
+}
+
+impl ToolLocator {
+    pub fn new(config: ToolPathConfig) -> Self {
+        ToolLocator { config }
+    }
+
+    pub fn config(&self) -> &ToolPathConfig {
+        &self.config
+    }
+
+    /// Configured binary for `program`, with the settings key it came from
+    pub fn configured(&self, program: &str) -> Option<(String, PathBuf)> {
+        let config = &self.config;
+        let named = match program {
+            "ffmpeg" => config.ffmpeg_path.clone().map(|path| ("ffmpeg_path", path)),
+            "xdotool" => config.xdotool_path.clone().map(|path| ("xdotool_path", path)),
+            "ydotool" => config.ydotool_path.clone().map(|path| ("ydotool_path", path)),
+            "wl-copy" | "wl-paste" => config.wl_clipboard_path.as_ref().map(|dir| ("wl_clipboard_path", dir.join(program))),
+            "xclip" => config.xclip_path.clone().map(|path| ("xclip_path", path)),
+            "grim" => config.grim_path.clone().map(|path| ("grim_path", path)),
+            _ => None,
+        };
+
+        match named {
+            Some((setting, path)) => Some((setting.to_string(), path)),
+            None => config.other.get(program).map(|path| (format!("other.{}", program), path.clone())),
+        }
+    }
+
+    /// What to start for `program`: the configured binary or the bare name
+    pub fn resolve(&self, program: &str) -> OsString {
+        match self.configured(program) {
+            Some((_, path)) => path.into_os_string(),
+            None => OsString::from(program),
+        }
+    }
+
+    /// Checks every configured binary: it must exist, be executable and
+    /// answer a version probe
+    pub fn validate(&self) -> Vec<ToolPathProblem> {
+        let mut programs = vec!["ffmpeg", "xdotool", "ydotool", "wl-copy", "wl-paste", "xclip", "grim"];
+        programs.extend(self.config.other.keys().map(String::as_str));
+
+        programs.into_iter()
+            .filter_map(|program| {
+                let (setting, path) = self.configured(program)?;
+                let reason = check_binary(&path, version_args(program)).err()?;
+                Some(ToolPathProblem { tool: program.to_string(), setting, path, reason })
+            })
+            .collect()
+    }
+}
+
+static LOCATOR: RwLock<Option<ToolLocator>> = RwLock::new(None);
+
+/// Makes `locator` the one used for all tools spawned from now on
+pub fn install(locator: ToolLocator) {
+    *LOCATOR.write().unwrap() = Some(locator);
+}
+
+/// The locator in effect; without `install` every tool comes from PATH
+pub fn current() -> ToolLocator {
+    LOCATOR.read().unwrap().clone().unwrap_or_default()
+}
+
+/// What to start for `program` with the installed locator
+pub fn resolve(program: &str) -> OsString {
+    match &*LOCATOR.read().unwrap() {
+        Some(locator) => locator.resolve(program),
+        None => OsString::from(program),
+    }
+}
+
+/// Clears the environment of `cmd` except for `INHERITED_ENV` and `LC_*`
+pub fn restrict_env(cmd: &mut Command) {
+    cmd.env_clear();
+    cmd.envs(std::env::vars_os().filter(|(name, _)| {
+        let name = name.to_string_lossy();
+        INHERITED_ENV.contains(&name.as_ref()) || name.starts_with("LC_")
+    }));
+}
+
+/// Arguments that make `program` print its version and exit
+fn version_args(program: &str) -> &'static [&'static str] {
+    match program {
+        "ffmpeg" => &["-version"],
+        "xdotool" => &["version"],
+        "ydotool" => &["--help"],
+        "xclip" => &["-version"],
+        "grim" => &["-h"],
+        _ => &["--version"],
+    }
+}
+
+/// Whether `path` is an executable file that prints something for `args`
+/// within `PROBE_TIMEOUT`. Only the output counts, since some tools exit
+/// with an error after printing their usage.
+fn check_binary(path: &Path, args: &[&str]) -> Result<(), String> {
+    let metadata = std::fs::metadata(path).map_err(|e| e.to_string())?;
+    if !metadata.is_file() {
+        return Err("not a file".to_string());
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 == 0 {
+            return Err("not executable".to_string());
+        }
+    }
+
+    let mut cmd = Command::new(path);
+    restrict_env(&mut cmd);
+    let mut child = cmd.args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("cannot be started: {}", e))?;
+
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if started.elapsed() < PROBE_TIMEOUT => thread::sleep(Duration::from_millis(20)),
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("did not answer `{}` within {} s", args.join(" "), PROBE_TIMEOUT.as_secs()));
+            },
+        }
+    }
+
+    // Some tools (xclip) print their version to stderr
+    let mut output = Vec::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        let _ = stdout.read_to_end(&mut output);
+    }
+    if let Some(mut stderr) = child.stderr.take() {
+        let _ = stderr.read_to_end(&mut output);
+    }
+    if output.iter().all(u8::is_ascii_whitespace) {
+        return Err(format!("printed nothing for `{}`", args.join(" ")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_configured_tools_are_resolved() {
+        let locator = ToolLocator::new(ToolPathConfig {
+            ffmpeg_path: Some(PathBuf::from("/opt/ffmpeg/bin/ffmpeg")),
+            wl_clipboard_path: Some(PathBuf::from("/app/bin")),
+            other: BTreeMap::from([("xrandr".to_string(), PathBuf::from("/app/bin/xrandr"))]),
+            ..ToolPathConfig::default()
+        });
+
+        assert_eq!(locator.resolve("ffmpeg"), OsString::from("/opt/ffmpeg/bin/ffmpeg"));
+        assert_eq!(locator.resolve("wl-paste"), OsString::from("/app/bin/wl-paste"));
+        assert_eq!(locator.configured("xrandr"), Some(("other.xrandr".to_string(), PathBuf::from("/app/bin/xrandr"))));
+        assert_eq!(locator.resolve("xdotool"), OsString::from("xdotool"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_validation_names_unusable_paths() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("smoldesk-tools-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = |name: &str, body: &str, mode: u32| {
+            let path = dir.join(name);
+            std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+            path
+        };
+
+        let locator = ToolLocator::new(ToolPathConfig {
+            ffmpeg_path: Some(script("ffmpeg", "echo 'ffmpeg version 6.1'", 0o755)),
+            xdotool_path: Some(script("xdotool", "echo 'xdotool version 3'", 0o644)),
+            ydotool_path: Some(dir.join("missing")),
+            xclip_path: Some(script("xclip", "exit 0", 0o755)),
+            ..ToolPathConfig::default()
+        });
+
+        let problems: Vec<(String, String)> = locator.validate().into_iter()
+            .map(|problem| (problem.setting, problem.reason))
+            .collect();
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert_eq!(problems[0], ("xdotool_path".to_string(), "not executable".to_string()));
+        assert_eq!(problems[1].0, "ydotool_path");
+        assert_eq!(problems[2], ("xclip_path".to_string(), "printed nothing for `-version`".to_string()));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_child_environment_is_restricted() {
+        std::env::set_var("SMOLDESK_TEST_SECRET", "hunter2");
+        let mut cmd = Command::new("env");
+        restrict_env(&mut cmd);
+
+        let output = String::from_utf8(cmd.output().unwrap().stdout).unwrap();
+        assert!(!output.contains("SMOLDESK_TEST_SECRET"));
+        assert!(output.lines().all(|line| {
+            let name = line.split('=').next().unwrap_or_default();
+            INHERITED_ENV.contains(&name) || name.starts_with("LC_")
+        }), "{}", output);
+    }
+}
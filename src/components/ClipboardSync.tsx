@@ -8,12 +8,13 @@ import { WebRTCConnection } from '../utils/webrtc';
 // Typdefinitionen
 interface ClipboardEntry {
   id: string;
-  content_type: 'Text' | 'Image' | 'Html' | 'Files';
+  contentType: 'Text' | 'Image' | 'Html' | 'Files' | 'ImageReference';
   data: string;
   metadata: {
     size: number;
-    mime_type: string;
+    mimeType: string;
     source: string;
+    truncated?: boolean;
   };
   timestamp: string;
 }
@@ -262,10 +263,10 @@ const ClipboardSync: React.FC<ClipboardSyncProps> = ({
     if (!config.enabled) return false;
     
     // Größenprüfung
-    if (entry.metadata.size > config.max_content_size) return false;
+    if (entry.metadata.truncated || entry.metadata.size > config.max_content_size) return false;
     
     // Typ-spezifische Prüfungen
-    switch (entry.content_type) {
+    switch (entry.contentType) {
       case 'Image':
         return config.sync_images;
       case 'Html':
@@ -288,7 +289,7 @@ const ClipboardSync: React.FC<ClipboardSyncProps> = ({
   // Eintrag in die Zwischenablage setzen
   const setClipboardEntry = async (entry: ClipboardEntry) => {
     try {
-      switch (entry.content_type) {
+      switch (entry.contentType) {
         case 'Text':
         case 'Html':
           await invoke('set_clipboard_text', { text: entry.data });
@@ -296,11 +297,11 @@ const ClipboardSync: React.FC<ClipboardSyncProps> = ({
         case 'Image':
           await invoke('set_clipboard_image', { 
             imageData: entry.data, 
-            format: entry.metadata.mime_type.split('/')[1] || 'png' 
+            format: entry.metadata.mimeType.split('/')[1] || 'png' 
           });
           break;
         default:
-          throw new Error(`Unsupported content type: ${entry.content_type}`);
+          throw new Error(`Unsupported content type: ${entry.contentType}`);
       }
       
       setCurrentEntry(entry);
@@ -335,7 +336,7 @@ const ClipboardSync: React.FC<ClipboardSyncProps> = ({
     }
     
     // Typfilter
-    if (filterType !== 'all' && entry.content_type.toLowerCase() !== filterType.toLowerCase()) {
+    if (filterType !== 'all' && entry.contentType.toLowerCase() !== filterType.toLowerCase()) {
       return false;
     }
     
@@ -344,14 +345,19 @@ const ClipboardSync: React.FC<ClipboardSyncProps> = ({
 
   // Formatierte Anzeige für Einträge
   const formatEntryPreview = (entry: ClipboardEntry): string => {
-    switch (entry.content_type) {
+    // Zu große Inhalte sind nur als Vorschau bekannt und werden nicht synchronisiert
+    if (entry.metadata.truncated) {
+      return `${entry.data.substring(0, 100)}... (${formatBytes(entry.metadata.size)}, not synced)`;
+    }
+    
+    switch (entry.contentType) {
       case 'Text':
         return entry.data.substring(0, 100) + (entry.data.length > 100 ? '...' : '');
       case 'Html':
         const textContent = entry.data.replace(/<[^>]+>/g, '');
         return textContent.substring(0, 100) + (textContent.length > 100 ? '...' : '');
       case 'Image':
-        return `Image (${entry.metadata.mime_type}, ${formatBytes(entry.metadata.size)})`;
+        return `Image (${entry.metadata.mimeType}, ${formatBytes(entry.metadata.size)})`;
       case 'Files':
         const files = entry.data.split('\n').filter(f => f.trim());
         return `${files.length} file(s): ${files[0]} ${files.length > 1 ? '...' : ''}`;
@@ -430,7 +436,7 @@ const ClipboardSync: React.FC<ClipboardSyncProps> = ({
         <div className="current-entry">
           <h4>Current Clipboard</h4>
           <div className="entry-preview">
-            <div className="entry-type">{currentEntry.content_type}</div>
+            <div className="entry-type">{currentEntry.contentType}</div>
             <div className="entry-content">{formatEntryPreview(currentEntry)}</div>
             <div className="entry-meta">
               {formatBytes(currentEntry.metadata.size)} • {formatTime(currentEntry.timestamp)}
@@ -523,7 +529,7 @@ const ClipboardSync: React.FC<ClipboardSyncProps> = ({
                 onDoubleClick={() => setClipboardEntry(entry)}
               >
                 <div className="entry-header">
-                  <span className="entry-type">{entry.content_type}</span>
+                  <span className="entry-type">{entry.contentType}</span>
                   <span className="entry-time">{formatTime(entry.timestamp)}</span>
                   <span className="entry-source">{entry.metadata.source}</span>
                 </div>
@@ -135,7 +135,13 @@ export interface User {
   id: string;
   username: string;
   role: 'Guest' | 'Member' | 'Moderator' | 'Admin' | 'Owner';
-  access_rights: Array<'ViewOnly' | 'ControlInput' | 'FileTransfer' | 'AudioAccess' | 'FullAccess'>;
+  access_rights: Array<'ViewOnly' | 'ControlInput' | 'FileTransfer' | 'AudioAccess' | 'FullAccess' | 'AccessibilityText' | 'SystemActions'>;
+}
+
+// Von get_video_codecs / get_hardware_acceleration_options: nur getestete Optionen
+export interface SupportedOptions {
+  supported: string[];
+  unavailable: Record<string, string>;
 }
 
 export interface SmolDeskConfig {
@@ -254,15 +260,15 @@ export function useSmolDesk(config?: Partial<SmolDeskConfig>): SmolDeskHook {
         }
         
         try {
-          const codecs = await invoke<string[]>('get_video_codecs');
-          setAvailableCodecs(codecs);
+          const codecs = await invoke<SupportedOptions>('get_video_codecs');
+          setAvailableCodecs(codecs.supported);
         } catch {
           setAvailableCodecs(['H264', 'VP8', 'VP9']);
         }
         
         try {
-          const hwAccel = await invoke<string[]>('get_hardware_acceleration_options');
-          setAvailableHwAccel(hwAccel);
+          const hwAccel = await invoke<SupportedOptions>('get_hardware_acceleration_options');
+          setAvailableHwAccel(hwAccel.supported);
         } catch {
           setAvailableHwAccel(['None', 'VAAPI', 'NVENC']);
         }
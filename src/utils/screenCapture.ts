@@ -213,7 +213,7 @@ export class ScreenCaptureManager {
       if (!this.captureActive || !this.decoder) return;
       
       try {
-        const data = event.payload as string;
+        const { data } = event.payload as { monitor_index: number; data: string };
         
         // Decode base64 data
         const binaryData = this.base64ToArrayBuffer(data);
@@ -291,7 +291,7 @@ export class ScreenCaptureManager {
       if (!this.captureActive) return;
       
       try {
-        const data = event.payload as string;
+        const { data } = event.payload as { monitor_index: number; data: string };
         
         // Create a data URL from the base64 string
         const dataUrl = `data:image/png;base64,${data}`;
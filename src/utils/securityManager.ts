@@ -30,7 +30,7 @@ export interface User {
   id: string;
   username: string;
   role: 'Guest' | 'Member' | 'Moderator' | 'Admin' | 'Owner';
-  access_rights: Array<'ViewOnly' | 'ControlInput' | 'FileTransfer' | 'AudioAccess' | 'FullAccess'>;
+  access_rights: Array<'ViewOnly' | 'ControlInput' | 'FileTransfer' | 'AudioAccess' | 'FullAccess' | 'AccessibilityText' | 'SystemActions'>;
 }
 
 /**
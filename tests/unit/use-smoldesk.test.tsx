@@ -28,9 +28,9 @@ describe.skip('useSmolDesk', () => {
             { index: 0, name: 'Monitor 1', width: 1920, height: 1080, primary: true }
           ]);
         case 'get_video_codecs':
-          return Promise.resolve(['H264', 'VP8', 'VP9']);
+          return Promise.resolve({ supported: ['H264', 'VP8', 'VP9'], unavailable: { AV1: 'FFmpeg was built without the libaom-av1 encoder' } });
         case 'get_hardware_acceleration_options':
-          return Promise.resolve(['None', 'VAAPI', 'NVENC']);
+          return Promise.resolve({ supported: ['None', 'VAAPI', 'NVENC'], unavailable: {} });
         default:
           return Promise.resolve(true);
       }
@@ -128,8 +128,8 @@ echo ""
 log_info "Prüfe Rust Backend-Dateien..."
 
 check_file "src-tauri/src/main.rs" "Rust Main" || ((errors++))
-check_file "src-tauri/src/screen_capture.rs" "Screen Capture Modul" || ((errors++))
-check_file "src-tauri/src/input_forwarding.rs" "Input Forwarding Modul" || ((errors++))
+check_file "src-tauri/src/screen_capture/mod.rs" "Screen Capture Modul" || ((errors++))
+check_file "src-tauri/src/input_forwarding/mod.rs" "Input Forwarding Modul" || ((errors++))
 check_file "src-tauri/build.rs" "Rust Build Script" || ((warnings++))
 
 echo ""